@@ -0,0 +1,147 @@
+//! Admin Commands
+//!
+//! Operator commands for inspecting the cluster.
+
+use crate::symbols;
+use anyhow::Result;
+use console::style;
+use cyxcloud_client::{GatewayClient, ScanSummary};
+use std::path::PathBuf;
+
+/// Configuration for topology export
+pub struct TopologyConfig {
+    /// Emit Graphviz DOT instead of JSON
+    pub dot: bool,
+    /// Write to a file instead of stdout
+    pub output: Option<PathBuf>,
+}
+
+/// Configuration for the scan history report
+pub struct ScansConfig {
+    /// Window in hours
+    pub hours: u32,
+}
+
+/// Rows in the under-replication chart
+const CHART_ROWS: usize = 24;
+/// Width of the longest bar
+const CHART_WIDTH: usize = 40;
+
+/// Export the cluster topology
+pub async fn topology(client: &GatewayClient, config: TopologyConfig) -> Result<()> {
+    let format = if config.dot { "dot" } else { "json" };
+    let raw = client.get_topology(format).await?;
+
+    // Pretty-print JSON; DOT is passed through as-is
+    let document = if config.dot {
+        raw
+    } else {
+        let value: serde_json::Value = serde_json::from_str(&raw)?;
+        serde_json::to_string_pretty(&value)?
+    };
+
+    match config.output {
+        Some(path) => {
+            std::fs::write(&path, document)?;
+            eprintln!(
+                "{} Topology written to {}",
+                style(symbols::CHECK).green(),
+                style(path.display()).cyan()
+            );
+        }
+        None => println!("{}", document),
+    }
+
+    Ok(())
+}
+
+/// Show rebalancer scan history and the under-replication trend
+pub async fn scans(client: &GatewayClient, config: ScansConfig) -> Result<()> {
+    let history = client.get_scan_history(config.hours).await?;
+
+    println!(
+        "{}",
+        style(format!("Rebalancer Scans (last {}h)", history.hours))
+            .bold()
+            .underlined()
+    );
+    println!();
+
+    if history.scans.is_empty() {
+        println!("  No scans recorded in this window");
+        return Ok(());
+    }
+
+    let latest = &history.scans[history.scans.len() - 1];
+    println!("  Scans:            {}", style(history.scans.len()).cyan());
+    println!(
+        "  Under-replicated: {} (peak {})",
+        style(latest.under_replicated).cyan(),
+        history.trend.peak_under_replicated
+    );
+    println!("  Critical:         {}", style(latest.critical).cyan());
+    println!(
+        "  Bytes at risk:    {}",
+        style(format_bytes(latest.bytes_at_risk)).cyan()
+    );
+
+    let trend = format!(
+        "{} ({:+} chunks, {:+} bytes)",
+        history.trend.direction,
+        history.trend.under_replicated_change,
+        history.trend.bytes_at_risk_change
+    );
+    let marker = match history.trend.direction.as_str() {
+        "converging" => style(symbols::CHECK).green(),
+        "diverging" => style(symbols::WARN).yellow(),
+        _ => style(symbols::INFO).dim(),
+    };
+    println!("  Trend:            {} {}", marker, trend);
+
+    println!();
+    println!("  {}", style("Under-replicated chunks").bold());
+    let peak = history.trend.peak_under_replicated.max(1);
+    for scan in chart_samples(&history.scans) {
+        let width = (scan.under_replicated * CHART_WIDTH as i64 / peak) as usize;
+        println!(
+            "  {:<20} {:>8} {}",
+            scan.scanned_at.get(..19).unwrap_or(&scan.scanned_at),
+            scan.under_replicated,
+            style("#".repeat(width)).cyan()
+        );
+    }
+
+    Ok(())
+}
+
+/// Evenly spaced scans for the chart, always including the latest
+fn chart_samples(scans: &[ScanSummary]) -> Vec<&ScanSummary> {
+    if scans.len() <= CHART_ROWS {
+        return scans.iter().collect();
+    }
+    let step = scans.len().div_ceil(CHART_ROWS);
+    let mut samples: Vec<_> = scans.iter().step_by(step).collect();
+    if let Some(last) = scans.last() {
+        if !std::ptr::eq(samples[samples.len() - 1], last) {
+            samples.push(last);
+        }
+    }
+    samples
+}
+
+/// Format bytes as human-readable string
+fn format_bytes(bytes: i64) -> String {
+    const KB: i64 = 1024;
+    const MB: i64 = KB * 1024;
+    const GB: i64 = MB * 1024;
+
+    if bytes >= GB {
+        format!("{:.2} GB", bytes as f64 / GB as f64)
+    } else if bytes >= MB {
+        format!("{:.2} MB", bytes as f64 / MB as f64)
+    } else if bytes >= KB {
+        format!("{:.2} KB", bytes as f64 / KB as f64)
+    } else {
+        format!("{} bytes", bytes)
+    }
+}
@@ -6,8 +6,8 @@ use anyhow::{Context, Result};
 use console::style;
 
 use crate::config::{self, Credentials};
-use crate::cyxwiz_client::CyxWizClient;
 use crate::symbols;
+use cyxcloud_client::CyxWizClient;
 
 /// Login configuration
 pub struct LoginConfig {
@@ -0,0 +1,80 @@
+//! Backup Command
+//!
+//! Saves a bucket (or the keys under a prefix) as a single tar archive,
+//! streamed from the gateway's bucket export.
+
+use crate::output::{OutputFormat, TransferEvent};
+use crate::symbols;
+use anyhow::{Context, Result};
+use console::style;
+use cyxcloud_client::GatewayClient;
+use indicatif::{ProgressBar, ProgressStyle};
+use std::path::PathBuf;
+
+/// Backup configuration
+pub struct BackupConfig {
+    pub bucket: String,
+    pub prefix: Option<String>,
+    /// Archive path (default: `<bucket>.tar`)
+    pub output: Option<PathBuf>,
+    pub format: OutputFormat,
+}
+
+/// Run backup command
+pub async fn run(client: &GatewayClient, config: BackupConfig) -> Result<()> {
+    let path = config
+        .output
+        .unwrap_or_else(|| PathBuf::from(format!("{}.tar", config.bucket)));
+
+    // The archive size is not known up front, so show bytes and rate only
+    let bar = if config.format.is_table() {
+        let bar = ProgressBar::new_spinner();
+        bar.set_style(
+            ProgressStyle::default_spinner()
+                .template("{spinner:.green} [{elapsed_precise}] {bytes} ({bytes_per_sec})")
+                .unwrap(),
+        );
+        bar
+    } else {
+        ProgressBar::hidden()
+    };
+
+    let result = client
+        .export_bucket_to_file(&config.bucket, config.prefix.as_deref(), &path, |written| {
+            bar.set_position(written)
+        })
+        .await
+        .with_context(|| format!("Failed to back up bucket '{}'", config.bucket));
+    let size = match result {
+        Ok(size) => size,
+        Err(e) => {
+            bar.abandon();
+            return Err(e);
+        }
+    };
+    bar.finish_and_clear();
+
+    config.format.print_json(&TransferEvent::Complete {
+        key: &config.bucket,
+        bytes: size,
+        etag: None,
+        path: Some(&path),
+    });
+    if !config.format.is_table() {
+        return Ok(());
+    }
+
+    println!(
+        "{} Backed up {} to {} ({} bytes)",
+        style(symbols::CHECK).green(),
+        config.bucket,
+        path.display(),
+        size
+    );
+    println!(
+        "  Objects are under {}/ and listed with their hashes in manifest.json",
+        config.bucket
+    );
+
+    Ok(())
+}
@@ -8,10 +8,10 @@
 //! - share: Share a dataset with another user
 //! - info: Show detailed dataset information
 
-use crate::client::GatewayClient;
 use crate::symbols;
 use anyhow::Result;
 use console::{style, Term};
+use cyxcloud_client::GatewayClient;
 
 /// Configuration for listing datasets
 pub struct ListConfig {
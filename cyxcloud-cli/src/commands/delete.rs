@@ -2,10 +2,10 @@
 //!
 //! Deletes files from CyxCloud storage.
 
-use crate::client::GatewayClient;
 use crate::symbols;
 use anyhow::{Context, Result};
 use console::style;
+use cyxcloud_client::GatewayClient;
 
 /// Delete configuration
 pub struct DeleteConfig {
@@ -32,14 +32,11 @@ pub async fn run(client: &GatewayClient, config: DeleteConfig) -> Result<()> {
                 return Ok(());
             }
         }
-        Err(crate::client::ClientError::NotFound(_)) => {
-            println!(
-                "{} Object not found: {}/{}",
-                style("Error:").red(),
-                config.bucket,
-                config.key
-            );
-            return Ok(());
+        Err(e @ cyxcloud_client::ClientError::NotFound(_)) => {
+            return Err(e).context(format!(
+                "Object not found: {}/{}",
+                config.bucket, config.key
+            ));
         }
         Err(e) => {
             return Err(e).context("Failed to check object");
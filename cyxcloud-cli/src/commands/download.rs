@@ -2,10 +2,11 @@
 //!
 //! Downloads files or directories from CyxCloud storage.
 
-use crate::client::GatewayClient;
+use crate::output::{OutputFormat, TransferEvent, TransferProgress};
 use crate::symbols;
 use anyhow::{Context, Result};
 use console::style;
+use cyxcloud_client::GatewayClient;
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
 use std::path::Path;
 use tokio::fs;
@@ -16,6 +17,7 @@ pub struct DownloadConfig {
     pub key: Option<String>,
     pub output: String,
     pub prefix: Option<String>,
+    pub format: OutputFormat,
 }
 
 /// Run download command
@@ -24,7 +26,7 @@ pub async fn run(client: &GatewayClient, config: DownloadConfig) -> Result<()> {
 
     // If a specific key is provided, download single file
     if let Some(key) = &config.key {
-        download_single_file(client, &config.bucket, key, output_path).await?;
+        download_single_file(client, &config.bucket, key, output_path, config.format).await?;
     } else {
         // Download all objects with prefix
         download_prefix(
@@ -32,6 +34,7 @@ pub async fn run(client: &GatewayClient, config: DownloadConfig) -> Result<()> {
             &config.bucket,
             config.prefix.as_deref(),
             output_path,
+            config.format,
         )
         .await?;
     }
@@ -45,6 +48,7 @@ async fn download_single_file(
     bucket: &str,
     key: &str,
     output_path: &Path,
+    output: OutputFormat,
 ) -> Result<()> {
     // Get object metadata first
     let metadata = client
@@ -66,29 +70,29 @@ async fn download_single_file(
         fs::create_dir_all(parent).await?;
     }
 
-    // Create progress bar
-    let pb = ProgressBar::new(metadata.size);
-    pb.set_style(
-        ProgressStyle::default_bar()
-            .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({eta})")
-            .unwrap()
-            .progress_chars("#>-"),
-    );
-
-    pb.set_message(format!("Downloading {}", key));
-
-    // Download file
-    let size = client
-        .download_to_file(bucket, key, &file_path)
+    // Download file, reporting progress as it streams
+    let progress = TransferProgress::start(output, key, metadata.size, true);
+    let result = client
+        .download_to_file_with_progress(bucket, key, &file_path, |written| progress.update(written))
         .await
-        .context("Failed to download file")?;
+        .context("Failed to download file");
+    let size = match result {
+        Ok(size) => size,
+        Err(e) => {
+            progress.fail(&e);
+            return Err(e);
+        }
+    };
+    progress.complete(size, None, Some(&file_path));
 
-    pb.finish_with_message(format!(
-        "{} Downloaded {} ({} bytes)",
-        style(symbols::CHECK).green(),
-        key,
-        size
-    ));
+    output.print_json(&TransferEvent::Summary {
+        succeeded: 1,
+        failed: 0,
+        bytes: size,
+    });
+    if !output.is_table() {
+        return Ok(());
+    }
 
     println!(
         "\n{} {}\n  Size: {} bytes\n  Saved to: {}",
@@ -107,6 +111,7 @@ async fn download_prefix(
     bucket: &str,
     prefix: Option<&str>,
     output_dir: &Path,
+    output: OutputFormat,
 ) -> Result<()> {
     // List objects with prefix
     let response = client
@@ -115,26 +120,39 @@ async fn download_prefix(
         .context("Failed to list objects")?;
 
     if response.objects.is_empty() {
-        println!(
-            "{} No objects found with prefix: {}",
-            style("Warning:").yellow(),
-            prefix.unwrap_or("(none)")
-        );
+        if output.is_table() {
+            println!(
+                "{} No objects found with prefix: {}",
+                style("Warning:").yellow(),
+                prefix.unwrap_or("(none)")
+            );
+        }
+        output.print_json(&TransferEvent::Summary {
+            succeeded: 0,
+            failed: 0,
+            bytes: 0,
+        });
         return Ok(());
     }
 
-    println!(
-        "{} {} objects to download",
-        style("Found").cyan(),
-        response.objects.len()
-    );
+    if output.is_table() {
+        println!(
+            "{} {} objects to download",
+            style("Found").cyan(),
+            response.objects.len()
+        );
+    }
 
     // Ensure output directory exists
     fs::create_dir_all(output_dir).await?;
 
     // Create multi-progress bar
     let multi = MultiProgress::new();
-    let overall_pb = multi.add(ProgressBar::new(response.objects.len() as u64));
+    let overall_pb = if output.is_table() {
+        multi.add(ProgressBar::new(response.objects.len() as u64))
+    } else {
+        ProgressBar::hidden()
+    };
     overall_pb.set_style(
         ProgressStyle::default_bar()
             .template(
@@ -145,8 +163,8 @@ async fn download_prefix(
     );
 
     let mut total_bytes: u64 = 0;
-    let mut success_count = 0;
-    let mut error_count = 0;
+    let mut success_count: u64 = 0;
+    let mut error_count: u64 = 0;
 
     for obj in &response.objects {
         // Calculate local file path
@@ -160,34 +178,47 @@ async fn download_prefix(
         };
 
         let file_path = output_dir.join(relative_path);
+        let progress = TransferProgress::start(output, &obj.key, obj.size, false);
 
         // Ensure parent directory exists
         if let Some(parent) = file_path.parent() {
             if let Err(e) = fs::create_dir_all(parent).await {
-                eprintln!(
-                    "{} Failed to create directory {}: {}",
-                    style(symbols::CROSS).red(),
-                    parent.display(),
-                    e
-                );
+                if output.is_table() {
+                    eprintln!(
+                        "{} Failed to create directory {}: {}",
+                        style(symbols::CROSS).red(),
+                        parent.display(),
+                        e
+                    );
+                }
+                progress.fail(&e.into());
                 error_count += 1;
                 overall_pb.inc(1);
                 continue;
             }
         }
 
-        match client.download_to_file(bucket, &obj.key, &file_path).await {
+        match client
+            .download_to_file_with_progress(bucket, &obj.key, &file_path, |written| {
+                progress.update(written)
+            })
+            .await
+        {
             Ok(size) => {
+                progress.complete(size, None, Some(&file_path));
                 total_bytes += size;
                 success_count += 1;
             }
             Err(e) => {
-                eprintln!(
-                    "{} Failed to download {}: {}",
-                    style(symbols::CROSS).red(),
-                    obj.key,
-                    e
-                );
+                if output.is_table() {
+                    eprintln!(
+                        "{} Failed to download {}: {}",
+                        style(symbols::CROSS).red(),
+                        obj.key,
+                        e
+                    );
+                }
+                progress.fail(&e.into());
                 error_count += 1;
             }
         }
@@ -198,17 +229,31 @@ async fn download_prefix(
     overall_pb.finish_with_message("Download complete");
 
     // Print summary
-    println!("\n{}", style("Download Summary:").bold());
-    println!(
-        "  {} files downloaded successfully",
-        style(success_count).green()
-    );
-    if error_count > 0 {
-        println!("  {} files failed", style(error_count).red());
+    output.print_json(&TransferEvent::Summary {
+        succeeded: success_count,
+        failed: error_count,
+        bytes: total_bytes,
+    });
+    if output.is_table() {
+        println!("\n{}", style("Download Summary:").bold());
+        println!(
+            "  {} files downloaded successfully",
+            style(success_count).green()
+        );
+        if error_count > 0 {
+            println!("  {} files failed", style(error_count).red());
+        }
+        println!("  {} total bytes transferred", format_bytes(total_bytes));
+        println!("  Saved to: {}", output_dir.display());
     }
-    println!("  {} total bytes transferred", format_bytes(total_bytes));
-    println!("  Saved to: {}", output_dir.display());
 
+    if error_count > 0 {
+        anyhow::bail!(
+            "{} of {} objects failed to download",
+            error_count,
+            response.objects.len()
+        );
+    }
     Ok(())
 }
 
@@ -0,0 +1,134 @@
+//! Fsck Command
+//!
+//! Checks that the objects in a bucket are still fully stored and, with
+//! `--repair`, queues repairs for the degraded ones.
+
+use crate::symbols;
+use anyhow::{bail, Context, Result};
+use console::style;
+use cyxcloud_client::{FsckRequest, GatewayClient};
+
+/// Fsck configuration
+pub struct FsckConfig {
+    /// `s3://bucket[/prefix]` or `bucket[/prefix]`
+    pub target: String,
+    pub repair: bool,
+    pub verbose: bool,
+}
+
+/// Split `s3://bucket[/prefix]` into the bucket and the key prefix
+fn parse_target(target: &str) -> Result<(String, Option<String>)> {
+    let path = target.strip_prefix("s3://").unwrap_or(target);
+    let (bucket, prefix) = match path.split_once('/') {
+        Some((bucket, prefix)) => (bucket, Some(prefix).filter(|p| !p.is_empty())),
+        None => (path, None),
+    };
+    if bucket.is_empty() {
+        bail!("Expected s3://bucket[/prefix], got '{}'", target);
+    }
+    Ok((bucket.to_string(), prefix.map(str::to_string)))
+}
+
+/// Run fsck command
+pub async fn run(client: &GatewayClient, config: FsckConfig) -> Result<()> {
+    let (bucket, prefix) = parse_target(&config.target)?;
+
+    println!(
+        "{} Checking s3://{}/{} ...",
+        style(symbols::LOOKING_GLASS).cyan(),
+        bucket,
+        prefix.as_deref().unwrap_or("")
+    );
+
+    let request = FsckRequest {
+        prefix,
+        repair: config.repair,
+        verbose: config.verbose,
+    };
+    let report = client
+        .fsck(&bucket, &request)
+        .await
+        .context("Failed to check objects")?;
+
+    if !report.objects.is_empty() {
+        println!();
+        println!(
+            "{:<10} {:>8} {:>8} {:>11} {:>8} {}",
+            style("HEALTH").bold(),
+            style("DAMAGED").bold(),
+            style("CORRUPT").bold(),
+            style("UNAVAILABLE").bold(),
+            style("REPAIRS").bold(),
+            style("KEY").bold()
+        );
+        for object in &report.objects {
+            let health = match object.health.as_str() {
+                "healthy" => style(object.health.as_str()).green(),
+                "degraded" => style(object.health.as_str()).yellow(),
+                _ => style(object.health.as_str()).red(),
+            };
+            println!(
+                "{:<10} {:>8} {:>8} {:>11} {:>8} {}",
+                health,
+                format!("{}/{}", object.damaged_shards, object.shards),
+                object.corrupt_copies,
+                object.unavailable_copies,
+                object.repairs_queued,
+                object.key
+            );
+        }
+    }
+
+    println!();
+    println!("  Checked:  {}", style(report.checked).cyan());
+    println!("  Healthy:  {}", style(report.healthy).green());
+    println!("  Degraded: {}", style(report.degraded).yellow());
+    println!("  Lost:     {}", style(report.lost).red());
+    if report.truncated {
+        println!(
+            "  {}",
+            style("(stopped after 10,000 objects; narrow the prefix to check the rest)").dim()
+        );
+    }
+
+    if config.repair {
+        println!(
+            "\n{} Queued {} repair jobs, dropped {} corrupt copies",
+            style(symbols::CHECK).green(),
+            report.repairs_queued,
+            report.copies_dropped
+        );
+    } else if report.degraded > 0 {
+        println!(
+            "\n{} Run with --repair to restore the degraded objects",
+            style(symbols::INFO).cyan()
+        );
+    }
+
+    if report.lost > 0 {
+        bail!("{} objects can no longer be read", report.lost);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_target() {
+        assert_eq!(
+            parse_target("s3://photos/2024/").unwrap(),
+            ("photos".to_string(), Some("2024/".to_string()))
+        );
+        assert_eq!(
+            parse_target("s3://photos").unwrap(),
+            ("photos".to_string(), None)
+        );
+        assert_eq!(
+            parse_target("photos/").unwrap(),
+            ("photos".to_string(), None)
+        );
+        assert!(parse_target("s3://").is_err());
+    }
+}
@@ -0,0 +1,558 @@
+//! Import Command
+//!
+//! Copies an existing S3/MinIO bucket into CyxCloud. Objects are streamed
+//! from the source straight into the gateway's erasure-coded upload path,
+//! keeping their keys, content type and user metadata.
+//!
+//! Every imported object is appended to a journal file, so an interrupted
+//! import can be re-run and only copies what is missing or changed. Each
+//! upload is checked against the hashes computed while streaming, and a
+//! final listing of the destination produces the verification report.
+
+use crate::symbols;
+use anyhow::{bail, Context, Result};
+use bytes::Bytes;
+use console::style;
+use cyxcloud_client::{GatewayClient, ObjectInfo, S3Client, S3Credentials};
+use futures::{Stream, StreamExt, TryStreamExt};
+use indicatif::{ProgressBar, ProgressStyle};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+
+/// Attempts per object before it is reported as failed
+const MAX_ATTEMPTS: u32 = 3;
+
+/// Content type for objects the source reports none for
+const DEFAULT_CONTENT_TYPE: &str = "application/octet-stream";
+
+/// Import configuration
+pub struct ImportConfig {
+    /// Source S3 endpoint, e.g. `http://minio:9000`
+    pub endpoint: String,
+    pub region: String,
+    /// Source credentials (`None` for public buckets)
+    pub credentials: Option<S3Credentials>,
+    pub source_bucket: String,
+    /// Only import keys starting with this prefix
+    pub prefix: Option<String>,
+    pub dest_bucket: String,
+    /// Prepended to every key in the destination
+    pub dest_prefix: Option<String>,
+    /// Objects copied in parallel
+    pub jobs: usize,
+    /// Journal of completed objects, used to resume
+    pub journal: PathBuf,
+    /// Write the verification report as JSON to this file
+    pub report: Option<PathBuf>,
+    /// Only list what would be imported
+    pub dry_run: bool,
+}
+
+/// Journal line for an imported object
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+struct JournalEntry {
+    key: String,
+    size: u64,
+    /// ETag reported by the source listing
+    etag: String,
+}
+
+/// Hashes of an object computed while it streams through
+struct StreamDigest {
+    md5: md5::Context,
+    blake3: blake3::Hasher,
+    bytes: u64,
+}
+
+/// Outcome of a finished import
+#[derive(Debug, Default, Serialize)]
+pub struct ImportReport {
+    pub source: String,
+    pub destination: String,
+    pub total_objects: usize,
+    pub total_bytes: u64,
+    pub imported: usize,
+    pub imported_bytes: u64,
+    /// Already in the journal from a previous run
+    pub skipped: usize,
+    pub failed: Vec<FailedObject>,
+    /// Source objects absent from the destination listing
+    pub missing: Vec<String>,
+    /// Destination objects whose size differs from the source
+    pub size_mismatches: Vec<String>,
+    pub verified: usize,
+}
+
+/// Object that could not be imported
+#[derive(Debug, Serialize)]
+pub struct FailedObject {
+    pub key: String,
+    pub error: String,
+}
+
+impl ImportReport {
+    /// Whether every source object made it across intact
+    pub fn is_complete(&self) -> bool {
+        self.failed.is_empty() && self.missing.is_empty() && self.size_mismatches.is_empty()
+    }
+}
+
+/// Run import command
+pub async fn run(client: Arc<GatewayClient>, config: ImportConfig) -> Result<()> {
+    let source = S3Client::new(&config.endpoint, &config.region, config.credentials.clone())
+        .context("Invalid source endpoint")?;
+
+    println!(
+        "{} s3://{}/{} at {}",
+        style("Listing").cyan(),
+        config.source_bucket,
+        config.prefix.as_deref().unwrap_or(""),
+        config.endpoint
+    );
+    let objects = list_source(&source, &config.source_bucket, config.prefix.as_deref()).await?;
+    let journal = load_journal(&config.journal).await?;
+
+    let pending: Vec<ObjectInfo> = objects
+        .iter()
+        .filter(|o| !is_journaled(&journal, o))
+        .cloned()
+        .collect();
+
+    let mut report = ImportReport {
+        source: format!("{}/{}", config.endpoint, config.source_bucket),
+        destination: config.dest_bucket.clone(),
+        total_objects: objects.len(),
+        total_bytes: objects.iter().map(|o| o.size).sum(),
+        skipped: objects.len() - pending.len(),
+        ..Default::default()
+    };
+    let pending_bytes: u64 = pending.iter().map(|o| o.size).sum();
+
+    println!(
+        "{} {} objects ({} bytes), {} already imported, {} to copy",
+        style("Found").cyan(),
+        report.total_objects,
+        report.total_bytes,
+        report.skipped,
+        pending.len()
+    );
+
+    if config.dry_run {
+        for object in &pending {
+            println!("  {} ({} bytes)", object.key, object.size);
+        }
+        return Ok(());
+    }
+
+    client
+        .create_bucket(&config.dest_bucket)
+        .await
+        .context("Failed to create destination bucket")?;
+
+    let pb = ProgressBar::new(pending_bytes);
+    pb.set_style(
+        ProgressStyle::default_bar()
+            .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({eta}) {msg}")
+            .unwrap()
+            .progress_chars("#>-"),
+    );
+
+    let mut journal_file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&config.journal)
+        .await
+        .with_context(|| format!("Failed to open journal {}", config.journal.display()))?;
+
+    let config = Arc::new(config);
+    let mut results = futures::stream::iter(pending)
+        .map(|object| {
+            let source = source.clone();
+            let client = client.clone();
+            let config = config.clone();
+            let pb = pb.clone();
+            async move {
+                let result = import_with_retry(&source, &client, &config, &object, &pb).await;
+                (object, result)
+            }
+        })
+        .buffer_unordered(config.jobs.max(1));
+
+    while let Some((object, result)) = results.next().await {
+        match result {
+            Ok(()) => {
+                report.imported += 1;
+                report.imported_bytes += object.size;
+                let entry = JournalEntry {
+                    key: object.key.clone(),
+                    size: object.size,
+                    etag: object.etag.clone(),
+                };
+                let mut line = serde_json::to_string(&entry)?;
+                line.push('\n');
+                journal_file.write_all(line.as_bytes()).await?;
+            }
+            Err(e) => {
+                pb.println(format!(
+                    "{} {}: {:#}",
+                    style(symbols::CROSS).red(),
+                    object.key,
+                    e
+                ));
+                report.failed.push(FailedObject {
+                    key: object.key.clone(),
+                    error: format!("{:#}", e),
+                });
+            }
+        }
+        pb.set_message(format!("{} imported", report.imported));
+    }
+    journal_file.flush().await?;
+    pb.finish_and_clear();
+
+    verify_destination(&client, &config, &objects, &mut report).await?;
+    print_report(&report);
+
+    if let Some(path) = &config.report {
+        tokio::fs::write(path, serde_json::to_vec_pretty(&report)?)
+            .await
+            .with_context(|| format!("Failed to write report {}", path.display()))?;
+        println!("Report written to {}", path.display());
+    }
+
+    if !report.is_complete() {
+        bail!("Import incomplete; re-run the same command to retry");
+    }
+    Ok(())
+}
+
+/// List every object under the source prefix
+async fn list_source(
+    source: &S3Client,
+    bucket: &str,
+    prefix: Option<&str>,
+) -> Result<Vec<ObjectInfo>> {
+    let mut objects = Vec::new();
+    let mut token: Option<String> = None;
+
+    loop {
+        let page = source
+            .list_objects_page(bucket, prefix, token.as_deref())
+            .await
+            .context("Failed to list source bucket")?;
+        objects.extend(page.objects);
+
+        match page.next_token {
+            Some(next) if page.is_truncated => token = Some(next),
+            _ => return Ok(objects),
+        }
+    }
+}
+
+/// Load the journal of a previous run (missing file = fresh import)
+async fn load_journal(path: &PathBuf) -> Result<HashMap<String, JournalEntry>> {
+    let text = match tokio::fs::read_to_string(path).await {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(HashMap::new()),
+        Err(e) => return Err(e).context("Failed to read journal"),
+    };
+
+    Ok(parse_journal(&text))
+}
+
+/// Parse journal lines; a torn last line from a crash is ignored
+fn parse_journal(text: &str) -> HashMap<String, JournalEntry> {
+    text.lines()
+        .filter_map(|line| serde_json::from_str::<JournalEntry>(line).ok())
+        .map(|entry| (entry.key.clone(), entry))
+        .collect()
+}
+
+/// Whether an object was imported by a previous run and has not changed since
+fn is_journaled(journal: &HashMap<String, JournalEntry>, object: &ObjectInfo) -> bool {
+    journal
+        .get(&object.key)
+        .is_some_and(|e| e.size == object.size && e.etag == object.etag)
+}
+
+/// Destination key of a source key
+fn dest_key(config: &ImportConfig, key: &str) -> String {
+    match &config.dest_prefix {
+        Some(prefix) => format!("{}{}", prefix, key),
+        None => key.to_string(),
+    }
+}
+
+/// Import one object, retrying transient failures
+async fn import_with_retry(
+    source: &S3Client,
+    client: &GatewayClient,
+    config: &ImportConfig,
+    object: &ObjectInfo,
+    pb: &ProgressBar,
+) -> Result<()> {
+    let mut attempt = 1;
+    loop {
+        match import_object(source, client, config, object, pb).await {
+            Ok(()) => return Ok(()),
+            Err(e) if attempt < MAX_ATTEMPTS => {
+                tracing::warn!(key = %object.key, attempt, error = %e, "Retrying import");
+                tokio::time::sleep(Duration::from_secs(1 << attempt)).await;
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Stream one object from the source into CyxCloud and check what arrived
+async fn import_object(
+    source: &S3Client,
+    client: &GatewayClient,
+    config: &ImportConfig,
+    object: &ObjectInfo,
+    pb: &ProgressBar,
+) -> Result<()> {
+    let fetched = source
+        .get_object(&config.source_bucket, &object.key)
+        .await
+        .context("Source GET failed")?;
+    let size = fetched.size.unwrap_or(object.size);
+    let content_type = fetched
+        .content_type
+        .clone()
+        .unwrap_or_else(|| DEFAULT_CONTENT_TYPE.to_string());
+    let source_etag = fetched.etag.clone();
+    let metadata = fetched.metadata.clone();
+
+    let digest = Arc::new(Mutex::new(StreamDigest {
+        md5: md5::Context::new(),
+        blake3: blake3::Hasher::new(),
+        bytes: 0,
+    }));
+    let body = hash_stream(fetched.into_stream(), digest.clone(), pb.clone());
+
+    let etag = client
+        .upload_stream_with_metadata(
+            &config.dest_bucket,
+            &dest_key(config, &object.key),
+            body,
+            Some(size),
+            &content_type,
+            &metadata,
+        )
+        .await
+        .context("Upload failed")?;
+
+    let digest = digest.lock().unwrap();
+    check_upload(&digest, size, source_etag.as_deref(), &etag)
+}
+
+/// Pass a body through while hashing it and advancing the progress bar
+fn hash_stream(
+    body: impl Stream<Item = std::io::Result<Bytes>> + Send + Sync + 'static,
+    digest: Arc<Mutex<StreamDigest>>,
+    pb: ProgressBar,
+) -> impl Stream<Item = std::io::Result<Bytes>> + Send + Sync + 'static {
+    body.inspect_ok(move |chunk| {
+        let mut digest = digest.lock().unwrap();
+        digest.md5.consume(chunk);
+        digest.blake3.update(chunk);
+        digest.bytes += chunk.len() as u64;
+        pb.inc(chunk.len() as u64);
+    })
+}
+
+/// Compare the streamed bytes with the source and destination ETags
+fn check_upload(
+    digest: &StreamDigest,
+    expected_size: u64,
+    source_etag: Option<&str>,
+    dest_etag: &str,
+) -> Result<()> {
+    if digest.bytes != expected_size {
+        bail!(
+            "Read {} bytes from source, expected {}",
+            digest.bytes,
+            expected_size
+        );
+    }
+
+    let md5 = format!("{:x}", digest.md5.clone().compute());
+    // Multipart ETags ("<hash>-<parts>") are not content hashes
+    if let Some(etag) = source_etag.filter(|e| e.len() == 32 && !e.contains('-')) {
+        if !etag.eq_ignore_ascii_case(&md5) {
+            bail!("Source data does not match its ETag {}", etag);
+        }
+    }
+
+    // The gateway reports the BLAKE3 content hash (MD5 in memory mode)
+    let dest_etag = dest_etag.trim_matches('"');
+    let expected = match dest_etag.len() {
+        64 => digest.blake3.finalize().to_hex().to_string(),
+        32 => md5,
+        _ => return Ok(()),
+    };
+    if !dest_etag.eq_ignore_ascii_case(&expected) {
+        bail!("Gateway ETag {} does not match uploaded data", dest_etag);
+    }
+
+    Ok(())
+}
+
+/// Check every source object against a listing of the destination
+async fn verify_destination(
+    client: &GatewayClient,
+    config: &ImportConfig,
+    objects: &[ObjectInfo],
+    report: &mut ImportReport,
+) -> Result<()> {
+    let listing_prefix = match (&config.dest_prefix, &config.prefix) {
+        (Some(dest), Some(src)) => Some(format!("{}{}", dest, src)),
+        (Some(dest), None) => Some(dest.clone()),
+        (None, src) => src.clone(),
+    };
+    let dest: HashMap<String, u64> = client
+        .list_all_objects(&config.dest_bucket, listing_prefix.as_deref())
+        .await
+        .context("Failed to list destination bucket")?
+        .into_iter()
+        .map(|o| (o.key, o.size))
+        .collect();
+
+    let failed: std::collections::HashSet<&str> =
+        report.failed.iter().map(|f| f.key.as_str()).collect();
+    let mut missing = Vec::new();
+    let mut mismatches = Vec::new();
+    let mut verified = 0;
+
+    for object in objects {
+        if failed.contains(object.key.as_str()) {
+            continue;
+        }
+        match dest.get(&dest_key(config, &object.key)) {
+            Some(&size) if size == object.size => verified += 1,
+            Some(_) => mismatches.push(object.key.clone()),
+            None => missing.push(object.key.clone()),
+        }
+    }
+
+    report.verified = verified;
+    report.missing = missing;
+    report.size_mismatches = mismatches;
+    Ok(())
+}
+
+fn print_report(report: &ImportReport) {
+    println!();
+    println!("{}", style("Import Report").bold());
+    println!("  Source:        {}", report.source);
+    println!("  Destination:   {}", report.destination);
+    println!(
+        "  Objects:       {} ({} bytes)",
+        report.total_objects, report.total_bytes
+    );
+    println!(
+        "  Imported:      {} ({} bytes)",
+        report.imported, report.imported_bytes
+    );
+    println!("  Skipped:       {} (already imported)", report.skipped);
+    println!("  Verified:      {}", report.verified);
+
+    let problems = [
+        (
+            "Failed",
+            report.failed.iter().map(|f| &f.key).collect::<Vec<_>>(),
+        ),
+        ("Missing", report.missing.iter().collect()),
+        ("Size mismatch", report.size_mismatches.iter().collect()),
+    ];
+    for (label, keys) in problems {
+        if keys.is_empty() {
+            continue;
+        }
+        println!(
+            "  {} {}: {}",
+            style(symbols::CROSS).red(),
+            label,
+            keys.len()
+        );
+        for key in keys.iter().take(10) {
+            println!("      {}", key);
+        }
+        if keys.len() > 10 {
+            println!("      ... and {} more", keys.len() - 10);
+        }
+    }
+
+    if report.is_complete() {
+        println!(
+            "\n{} All {} objects present in the destination",
+            style(symbols::CHECK).green(),
+            report.verified
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn object(key: &str, size: u64, etag: &str) -> ObjectInfo {
+        ObjectInfo {
+            key: key.to_string(),
+            size,
+            last_modified: String::new(),
+            etag: etag.to_string(),
+            metadata: Default::default(),
+        }
+    }
+
+    fn digest_of(data: &[u8]) -> StreamDigest {
+        let mut digest = StreamDigest {
+            md5: md5::Context::new(),
+            blake3: blake3::Hasher::new(),
+            bytes: 0,
+        };
+        digest.md5.consume(data);
+        digest.blake3.update(data);
+        digest.bytes = data.len() as u64;
+        digest
+    }
+
+    #[test]
+    fn test_journal_resume_skips_unchanged_objects() {
+        let text = concat!(
+            r#"{"key":"a.txt","size":3,"etag":"e1"}"#,
+            "\n",
+            r#"{"key":"b.txt","size":5,"etag":"e2"}"#,
+            "\n",
+            r#"{"key":"c.tx"#, // torn write from a crash
+        );
+        let journal = parse_journal(text);
+        assert_eq!(journal.len(), 2);
+
+        assert!(is_journaled(&journal, &object("a.txt", 3, "e1")));
+        // Changed at the source since the last run
+        assert!(!is_journaled(&journal, &object("b.txt", 5, "e3")));
+        assert!(!is_journaled(&journal, &object("c.txt", 1, "e4")));
+    }
+
+    #[test]
+    fn test_check_upload_compares_hashes() {
+        let data = b"hello world";
+        let md5 = format!("{:x}", md5::compute(data));
+        let blake3 = blake3::hash(data).to_hex().to_string();
+
+        let digest = digest_of(data);
+        assert!(check_upload(&digest, 11, Some(&md5), &blake3).is_ok());
+        assert!(check_upload(&digest, 11, Some("abc-2"), &md5).is_ok());
+        assert!(check_upload(&digest, 12, None, &blake3).is_err());
+        assert!(check_upload(&digest, 11, Some(&"0".repeat(32)), &blake3).is_err());
+        assert!(check_upload(&digest, 11, None, &"0".repeat(64)).is_err());
+    }
+}
@@ -1,10 +1,22 @@
 //! List Command
 //!
 //! Lists objects in CyxCloud storage buckets.
+//!
+//! By default a listing shows one level of the key hierarchy, like a
+//! directory: keys continuing past the next `/` after the prefix are grouped
+//! into common prefixes (`data/`, `images/`). `--recursive` lists every key.
+//! `--summarize` adds the object count and size of each prefix and the
+//! totals of the listing.
 
-use crate::client::GatewayClient;
+use crate::output::OutputFormat;
 use anyhow::{Context, Result};
 use console::style;
+use cyxcloud_client::{GatewayClient, ObjectInfo};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Separator between the levels of the key hierarchy
+pub const DELIMITER: char = '/';
 
 /// List configuration
 pub struct ListConfig {
@@ -13,15 +25,135 @@ pub struct ListConfig {
     pub long_format: bool,
     pub human_readable: bool,
     pub max_keys: Option<i32>,
+    /// List every key instead of one level of the hierarchy
+    pub recursive: bool,
+    /// Print object counts and sizes per prefix and in total
+    pub summarize: bool,
+    pub format: OutputFormat,
+}
+
+/// Listing printed in json mode
+#[derive(Serialize)]
+struct ListOutput<'a> {
+    bucket: &'a str,
+    prefix: Option<&'a str>,
+    /// Prefixes grouping deeper keys (omitted for recursive listings)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    common_prefixes: Option<&'a [PrefixSummary]>,
+    objects: &'a [ObjectInfo],
+    /// Objects under the prefix, including those in common prefixes
+    object_count: u64,
+    total_bytes: u64,
+    is_truncated: bool,
+    next_token: Option<&'a str>,
+}
+
+/// Keys grouped under one common prefix
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PrefixSummary {
+    pub prefix: String,
+    pub object_count: u64,
+    pub total_bytes: u64,
+}
+
+/// One level of the key hierarchy below a prefix
+#[derive(Debug, Default)]
+struct Level {
+    prefixes: Vec<PrefixSummary>,
+    objects: Vec<ObjectInfo>,
+    is_truncated: bool,
+}
+
+impl Level {
+    /// Group objects into the prefixes and objects directly below `prefix`
+    fn group(prefix: &str, objects: Vec<ObjectInfo>) -> Self {
+        let mut prefixes: BTreeMap<String, PrefixSummary> = BTreeMap::new();
+        let mut direct = Vec::new();
+
+        for obj in objects {
+            let Some(rest) = obj.key.strip_prefix(prefix) else {
+                continue;
+            };
+            match rest.find(DELIMITER) {
+                Some(end) => {
+                    let common = obj.key[..prefix.len() + end + 1].to_string();
+                    let summary = prefixes
+                        .entry(common.clone())
+                        .or_insert_with(|| PrefixSummary {
+                            prefix: common,
+                            object_count: 0,
+                            total_bytes: 0,
+                        });
+                    summary.object_count += 1;
+                    summary.total_bytes += obj.size;
+                }
+                None => direct.push(obj),
+            }
+        }
+
+        Self {
+            prefixes: prefixes.into_values().collect(),
+            objects: direct,
+            is_truncated: false,
+        }
+    }
+
+    /// Keep the first `max` entries, prefixes first
+    fn truncate(&mut self, max: usize) {
+        if self.prefixes.len() + self.objects.len() <= max {
+            return;
+        }
+        self.prefixes.truncate(max);
+        self.objects.truncate(max - self.prefixes.len());
+        self.is_truncated = true;
+    }
+
+    /// Objects under the level, including those in its prefixes
+    fn object_count(&self) -> u64 {
+        self.prefixes.iter().map(|p| p.object_count).sum::<u64>() + self.objects.len() as u64
+    }
+
+    /// Bytes under the level, including those in its prefixes
+    fn total_bytes(&self) -> u64 {
+        self.prefixes.iter().map(|p| p.total_bytes).sum::<u64>()
+            + self.objects.iter().map(|obj| obj.size).sum::<u64>()
+    }
+}
+
+/// Part of a key shown below `prefix`
+///
+/// Names are relative to the last `/` of the prefix, so listing `data/ra`
+/// shows `raw/` rather than the whole key.
+pub fn display_name<'a>(prefix: &str, key: &'a str) -> &'a str {
+    let base = prefix.rfind(DELIMITER).map_or(0, |i| i + 1);
+    key.get(base..).unwrap_or(key)
 }
 
 /// Run list command
 pub async fn run(client: &GatewayClient, config: ListConfig) -> Result<()> {
+    if !config.recursive {
+        return run_level(client, config).await;
+    }
+
     let response = client
         .list_objects(&config.bucket, config.prefix.as_deref(), config.max_keys)
         .await
         .context("Failed to list objects")?;
 
+    if !config.format.is_table() {
+        config.format.print_json(&ListOutput {
+            bucket: &config.bucket,
+            prefix: config.prefix.as_deref(),
+            common_prefixes: None,
+            objects: &response.objects,
+            object_count: response.objects.len() as u64,
+            total_bytes: response.objects.iter().map(|obj| obj.size).sum(),
+            is_truncated: response.is_truncated,
+            next_token: response.next_token.as_deref(),
+        });
+        return Ok(());
+    }
+
     if response.objects.is_empty() {
         println!(
             "{} No objects found in bucket '{}' with prefix '{}'",
@@ -69,7 +201,7 @@ pub async fn run(client: &GatewayClient, config: ListConfig) -> Result<()> {
     }
 
     // Print summary
-    if config.long_format {
+    if config.long_format || config.summarize {
         println!("{}", "-".repeat(100));
         println!(
             "{} objects, {} total",
@@ -92,6 +224,135 @@ pub async fn run(client: &GatewayClient, config: ListConfig) -> Result<()> {
     Ok(())
 }
 
+/// List one level of the hierarchy below the prefix
+async fn run_level(client: &GatewayClient, config: ListConfig) -> Result<()> {
+    let prefix = config.prefix.clone().unwrap_or_default();
+    let objects = client
+        .list_all_objects(&config.bucket, config.prefix.as_deref())
+        .await
+        .context("Failed to list objects")?;
+
+    let mut level = Level::group(&prefix, objects);
+    let object_count = level.object_count();
+    let total_bytes = level.total_bytes();
+    if let Some(max) = config.max_keys {
+        level.truncate(max.max(0) as usize);
+    }
+
+    if !config.format.is_table() {
+        config.format.print_json(&ListOutput {
+            bucket: &config.bucket,
+            prefix: config.prefix.as_deref(),
+            common_prefixes: Some(&level.prefixes),
+            objects: &level.objects,
+            object_count,
+            total_bytes,
+            is_truncated: level.is_truncated,
+            next_token: None,
+        });
+        return Ok(());
+    }
+
+    if level.prefixes.is_empty() && level.objects.is_empty() {
+        println!(
+            "{} No objects found in bucket '{}' with prefix '{}'",
+            style("Info:").cyan(),
+            config.bucket,
+            config.prefix.as_deref().unwrap_or("(none)")
+        );
+        return Ok(());
+    }
+
+    let size = |bytes: u64| {
+        if config.human_readable {
+            format_bytes(bytes)
+        } else {
+            bytes.to_string()
+        }
+    };
+
+    if config.long_format {
+        println!(
+            "{:<40} {:>12} {:>24} {}",
+            style("NAME").bold(),
+            style("SIZE").bold(),
+            style("LAST MODIFIED").bold(),
+            style("ETAG").bold()
+        );
+        println!("{}", "-".repeat(100));
+    }
+
+    for entry in &level.prefixes {
+        let name = display_name(&prefix, &entry.prefix);
+        if config.long_format {
+            let (size_str, count) = if config.summarize {
+                (
+                    size(entry.total_bytes),
+                    format!("{} objects", entry.object_count),
+                )
+            } else {
+                ("PRE".to_string(), String::new())
+            };
+            println!(
+                "{:<40} {:>12} {:>24} {}",
+                style(truncate_key(name, 40)).blue().bold(),
+                size_str,
+                "",
+                count
+            );
+        } else if config.summarize {
+            println!(
+                "{}  ({} objects, {})",
+                style(name).blue().bold(),
+                entry.object_count,
+                size(entry.total_bytes)
+            );
+        } else {
+            println!("{}", style(name).blue().bold());
+        }
+    }
+
+    for obj in &level.objects {
+        let name = display_name(&prefix, &obj.key);
+        if config.long_format {
+            println!(
+                "{:<40} {:>12} {:>24} {}",
+                truncate_key(name, 40),
+                size(obj.size),
+                truncate_string(&obj.last_modified, 24),
+                truncate_string(&obj.etag, 16)
+            );
+        } else {
+            println!("{}", name);
+        }
+    }
+
+    if config.long_format || config.summarize {
+        if config.long_format {
+            println!("{}", "-".repeat(100));
+        }
+        println!(
+            "{} prefixes, {} objects, {} total",
+            style(level.prefixes.len()).green(),
+            style(object_count).green(),
+            if config.human_readable {
+                format_bytes(total_bytes)
+            } else {
+                format!("{} bytes", total_bytes)
+            }
+        );
+    }
+
+    if level.is_truncated {
+        println!(
+            "{}",
+            style("(results truncated, use --max-keys to retrieve more)").yellow()
+        );
+    }
+
+    Ok(())
+}
+
 /// Truncate a key for display
 fn truncate_key(key: &str, max_len: usize) -> String {
     if key.len() <= max_len {
@@ -111,7 +372,7 @@ fn truncate_string(s: &str, max_len: usize) -> String {
 }
 
 /// Format bytes as human-readable string
-fn format_bytes(bytes: u64) -> String {
+pub fn format_bytes(bytes: u64) -> String {
     const KB: u64 = 1024;
     const MB: u64 = KB * 1024;
     const GB: u64 = MB * 1024;
@@ -134,6 +395,96 @@ fn format_bytes(bytes: u64) -> String {
 mod tests {
     use super::*;
 
+    fn object(key: &str, size: u64) -> ObjectInfo {
+        ObjectInfo {
+            key: key.to_string(),
+            size,
+            last_modified: String::new(),
+            etag: String::new(),
+            metadata: Default::default(),
+        }
+    }
+
+    fn keys(objects: &[ObjectInfo]) -> Vec<&str> {
+        objects.iter().map(|obj| obj.key.as_str()).collect()
+    }
+
+    #[test]
+    fn test_group_level() {
+        let objects = vec![
+            object("data/a.csv", 10),
+            object("data/raw/b.bin", 20),
+            object("data/raw/c.bin", 30),
+            object("images/d.jpg", 40),
+            object("readme.md", 5),
+        ];
+
+        let level = Level::group("", objects.clone());
+        assert_eq!(
+            level.prefixes,
+            vec![
+                PrefixSummary {
+                    prefix: "data/".to_string(),
+                    object_count: 3,
+                    total_bytes: 60,
+                },
+                PrefixSummary {
+                    prefix: "images/".to_string(),
+                    object_count: 1,
+                    total_bytes: 40,
+                },
+            ]
+        );
+        assert_eq!(keys(&level.objects), vec!["readme.md"]);
+        assert_eq!(level.object_count(), 5);
+        assert_eq!(level.total_bytes(), 105);
+
+        // One level further down
+        let level = Level::group("data/", objects.clone());
+        assert_eq!(level.prefixes.len(), 1);
+        assert_eq!(level.prefixes[0].prefix, "data/raw/");
+        assert_eq!(keys(&level.objects), vec!["data/a.csv"]);
+        assert_eq!(level.object_count(), 3);
+
+        // A prefix that ends mid-name groups at the next delimiter
+        let level = Level::group("data/r", objects);
+        assert_eq!(level.prefixes[0].prefix, "data/raw/");
+        assert!(level.objects.is_empty());
+    }
+
+    #[test]
+    fn test_level_truncate() {
+        let objects = vec![
+            object("a/1", 1),
+            object("b/2", 2),
+            object("c", 3),
+            object("d", 4),
+        ];
+
+        let mut level = Level::group("", objects.clone());
+        level.truncate(4);
+        assert!(!level.is_truncated);
+
+        // Prefixes come first
+        level.truncate(3);
+        assert!(level.is_truncated);
+        assert_eq!(level.prefixes.len(), 2);
+        assert_eq!(keys(&level.objects), vec!["c"]);
+
+        let mut level = Level::group("", objects);
+        level.truncate(1);
+        assert_eq!(level.prefixes.len(), 1);
+        assert!(level.objects.is_empty());
+    }
+
+    #[test]
+    fn test_display_name() {
+        assert_eq!(display_name("", "data/a.csv"), "data/a.csv");
+        assert_eq!(display_name("data/", "data/raw/"), "raw/");
+        assert_eq!(display_name("data/ra", "data/raw/"), "raw/");
+        assert_eq!(display_name("da", "data/"), "data/");
+    }
+
     #[test]
     fn test_truncate_key() {
         assert_eq!(truncate_key("short", 40), "short");
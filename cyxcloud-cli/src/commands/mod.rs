@@ -1,11 +1,19 @@
 //! CLI Commands
 
+pub mod admin;
 pub mod auth;
+pub mod backup;
 pub mod dataset;
 pub mod delete;
 pub mod download;
+pub mod fsck;
+pub mod import;
 pub mod list;
+pub mod mount;
+pub mod share;
 pub mod status;
+pub mod trash;
+pub mod tree;
 pub mod upload;
 
 pub use delete::run as delete;
@@ -0,0 +1,75 @@
+//! Mount Command
+//!
+//! Mounts a bucket as a local filesystem until interrupted.
+
+use crate::mount::MountOptions;
+use anyhow::Result;
+use cyxcloud_client::GatewayClient;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Mount configuration
+pub struct MountConfig {
+    pub mountpoint: PathBuf,
+    pub options: MountOptions,
+    /// Let other users access the mount (needs `user_allow_other`)
+    pub allow_other: bool,
+}
+
+/// Run mount command
+#[cfg(feature = "fuse")]
+pub async fn run(client: Arc<GatewayClient>, config: MountConfig) -> Result<()> {
+    use crate::mount::fs::CyxFs;
+    use crate::symbols;
+    use anyhow::Context;
+    use console::style;
+    use fuser::MountOption;
+
+    // Fail early on a missing bucket instead of serving an empty mount
+    client
+        .list_objects(&config.options.bucket, None, Some(1))
+        .await
+        .with_context(|| format!("Cannot access bucket '{}'", config.options.bucket))?;
+
+    let mut mount_options = vec![
+        MountOption::FSName(format!("cyxcloud:{}", config.options.bucket)),
+        MountOption::Subtype("cyxcloud".to_string()),
+        MountOption::DefaultPermissions,
+        MountOption::AutoUnmount,
+    ];
+    if config.options.read_only {
+        mount_options.push(MountOption::RO);
+    }
+    if config.allow_other {
+        mount_options.push(MountOption::AllowOther);
+    }
+
+    let bucket = config.options.bucket.clone();
+    let read_only = config.options.read_only;
+    let fs = CyxFs::new(client, tokio::runtime::Handle::current(), config.options);
+    let session = fuser::spawn_mount2(fs, &config.mountpoint, &mount_options)
+        .with_context(|| format!("Failed to mount at {}", config.mountpoint.display()))?;
+
+    println!(
+        "{} Mounted {} at {} ({})",
+        style(symbols::CHECK).green(),
+        style(&bucket).cyan(),
+        config.mountpoint.display(),
+        if read_only { "read-only" } else { "read-write" }
+    );
+    println!("Press Ctrl-C to unmount");
+
+    tokio::signal::ctrl_c().await?;
+    // Dropping the session unmounts; it joins the FUSE thread, so do it
+    // off the runtime
+    tokio::task::spawn_blocking(move || drop(session)).await?;
+    println!("Unmounted {}", config.mountpoint.display());
+
+    Ok(())
+}
+
+/// Run mount command
+#[cfg(not(feature = "fuse"))]
+pub async fn run(_client: Arc<GatewayClient>, _config: MountConfig) -> Result<()> {
+    anyhow::bail!("This build has no FUSE support; rebuild with `--features fuse`")
+}
@@ -0,0 +1,116 @@
+//! Share Command
+//!
+//! Makes an object (or every object of a bucket) public-read and prints the
+//! link anyone can download it from without logging in. Downloads through
+//! the link count against the owner's bandwidth. `--revoke` makes it
+//! private again.
+
+use crate::symbols;
+use anyhow::{bail, Context, Result};
+use console::style;
+use cyxcloud_client::GatewayClient;
+
+/// Tenant of users whose gateway does not report one
+const DEFAULT_TENANT: &str = "default";
+
+/// Share configuration
+pub struct ShareConfig {
+    /// `s3://bucket/key`, or `s3://bucket` to share the whole bucket
+    pub target: String,
+    /// Make the object or bucket private again
+    pub revoke: bool,
+}
+
+/// Split `s3://bucket[/key]` into the bucket and the key
+fn parse_target(target: &str) -> Result<(String, Option<String>)> {
+    let path = target.strip_prefix("s3://").unwrap_or(target);
+    let (bucket, key) = match path.split_once('/') {
+        Some((bucket, key)) => (bucket, Some(key).filter(|k| !k.is_empty())),
+        None => (path, None),
+    };
+    if bucket.is_empty() {
+        bail!("Expected s3://bucket/key or s3://bucket, got '{}'", target);
+    }
+    Ok((bucket.to_string(), key.map(str::to_string)))
+}
+
+/// Percent-encode a key for a URL, keeping its `/` separators
+fn encode_key(key: &str) -> String {
+    let mut encoded = String::with_capacity(key.len());
+    for byte in key.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// Run share command
+pub async fn run(client: &GatewayClient, config: ShareConfig) -> Result<()> {
+    let (bucket, key) = parse_target(&config.target)?;
+    let public = !config.revoke;
+
+    match key {
+        Some(ref key) => client.set_object_public(&bucket, key, public).await,
+        None => client.set_bucket_public(&bucket, public).await,
+    }
+    .context("Failed to change access")?;
+
+    let target = format!("s3://{}/{}", bucket, key.as_deref().unwrap_or(""));
+    if config.revoke {
+        println!(
+            "{} {} is private again",
+            style(symbols::CHECK).green(),
+            target
+        );
+        return Ok(());
+    }
+
+    let tenant = client
+        .whoami()
+        .await
+        .ok()
+        .and_then(|user| user.tenant)
+        .unwrap_or_else(|| DEFAULT_TENANT.to_string());
+    let url = client.public_url(&tenant, &bucket, &encode_key(key.as_deref().unwrap_or("")));
+
+    println!("{} {} is public", style(symbols::CHECK).green(), target);
+    if key.is_some() {
+        println!("  {}", style(&url).cyan());
+        println!(
+            "  {}",
+            style("(a new upload to the key is private until shared again)").dim()
+        );
+    } else {
+        println!("  {}{}", style(&url).cyan(), style("<key>").dim());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_target() {
+        assert_eq!(
+            parse_target("s3://photos/2026/cat.jpg").unwrap(),
+            ("photos".to_string(), Some("2026/cat.jpg".to_string()))
+        );
+        assert_eq!(
+            parse_target("photos/").unwrap(),
+            ("photos".to_string(), None)
+        );
+        assert!(parse_target("s3:///cat.jpg").is_err());
+    }
+
+    #[test]
+    fn test_encode_key() {
+        assert_eq!(encode_key("2026/cat pic.jpg"), "2026/cat%20pic.jpg");
+        assert_eq!(encode_key("a+b&c"), "a%2Bb%26c");
+        assert_eq!(encode_key("ü"), "%C3%BC");
+    }
+}
@@ -2,14 +2,30 @@
 //!
 //! Shows storage status and health information.
 
-use crate::client::GatewayClient;
-use anyhow::Result;
+use crate::output::{CliError, ExitKind, OutputFormat};
+use anyhow::{Context, Result};
 use console::style;
+use cyxcloud_client::{BucketStats, GatewayClient, ObjectInfo, StorageStats};
+use serde::Serialize;
 
 /// Status configuration
 pub struct StatusConfig {
     pub bucket: Option<String>,
     pub verbose: bool,
+    pub format: OutputFormat,
+}
+
+/// Status printed in json mode
+#[derive(Serialize)]
+struct StatusOutput {
+    gateway_online: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    storage: Option<StorageStats>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bucket: Option<BucketStats>,
+    /// First objects of the bucket (verbose only)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    objects: Vec<ObjectInfo>,
 }
 
 /// Run status command
@@ -17,6 +33,10 @@ pub async fn run(client: &GatewayClient, config: StatusConfig) -> Result<()> {
     // Check gateway health
     let healthy = client.health().await.unwrap_or(false);
 
+    if !config.format.is_table() {
+        return report_status(client, &config, healthy).await;
+    }
+
     println!("{}", style("CyxCloud Storage Status").bold().underlined());
     println!();
 
@@ -39,46 +59,87 @@ pub async fn run(client: &GatewayClient, config: StatusConfig) -> Result<()> {
             "{}",
             style("Cannot retrieve storage status: gateway is offline").yellow()
         );
-        return Ok(());
+        return Err(gateway_offline());
     }
 
     // If a specific bucket is requested, show its details
     if let Some(bucket) = &config.bucket {
         show_bucket_status(client, bucket, config.verbose).await?;
     } else {
-        show_overall_status(config.verbose);
+        show_overall_status(client, config.verbose).await?;
     }
 
     Ok(())
 }
 
+fn gateway_offline() -> anyhow::Error {
+    CliError::new(ExitKind::Transport, "Gateway is offline").into()
+}
+
+/// Print the status as JSON (or nothing in quiet mode)
+async fn report_status(client: &GatewayClient, config: &StatusConfig, healthy: bool) -> Result<()> {
+    let mut status = StatusOutput {
+        gateway_online: healthy,
+        storage: None,
+        bucket: None,
+        objects: Vec::new(),
+    };
+    if !healthy {
+        config.format.print_json(&status);
+        return Err(gateway_offline());
+    }
+
+    if let Some(bucket) = &config.bucket {
+        status.bucket = Some(
+            client
+                .bucket_stats(bucket)
+                .await
+                .context("Failed to get bucket info")?,
+        );
+        if config.verbose {
+            status.objects = client
+                .list_objects(bucket, None, Some(5))
+                .await
+                .context("Failed to list objects")?
+                .objects;
+        }
+    } else {
+        status.storage = Some(
+            client
+                .storage_stats()
+                .await
+                .context("Failed to get storage stats")?,
+        );
+    }
+
+    config.format.print_json(&status);
+    Ok(())
+}
+
 /// Show status for a specific bucket
 async fn show_bucket_status(client: &GatewayClient, bucket: &str, verbose: bool) -> Result<()> {
     println!("{}", style(format!("Bucket: {}", bucket)).bold());
     println!();
 
-    // List objects to get stats
-    let response = client.list_objects(bucket, None, Some(1000)).await;
-
-    match response {
-        Ok(list) => {
-            let total_size: u64 = list.objects.iter().map(|o| o.size).sum();
-            let object_count = list.objects.len();
-
-            println!("  Objects:      {}", style(object_count).cyan());
-            println!("  Total Size:   {}", style(format_bytes(total_size)).cyan());
-
-            if list.is_truncated {
-                println!("  {}", style("(showing first 1000 objects)").dim());
-            }
+    match client.bucket_stats(bucket).await {
+        Ok(stats) => {
+            println!("  Objects:      {}", style(stats.object_count).cyan());
+            println!(
+                "  Total Size:   {}",
+                style(format_bytes(stats.total_bytes.max(0) as u64)).cyan()
+            );
+        }
+        Err(e) => return Err(e).context("Failed to get bucket info"),
+    }
 
-            if verbose && !list.objects.is_empty() {
+    if verbose {
+        // Show the first few objects
+        if let Ok(list) = client.list_objects(bucket, None, Some(6)).await {
+            if !list.objects.is_empty() {
                 println!();
                 println!("  {}", style("Recent Objects:").bold());
 
-                // Show last 5 objects
-                let recent: Vec<_> = list.objects.iter().take(5).collect();
-                for obj in recent {
+                for obj in list.objects.iter().take(5) {
                     println!(
                         "    {} ({}) - {}",
                         obj.key,
@@ -91,41 +152,53 @@ async fn show_bucket_status(client: &GatewayClient, bucket: &str, verbose: bool)
                     );
                 }
 
-                if list.objects.len() > 5 {
-                    println!(
-                        "    {} more objects...",
-                        style(format!("... and {}", list.objects.len() - 5)).dim()
-                    );
+                if list.objects.len() > 5 || list.is_truncated {
+                    println!("    {}", style("... more objects").dim());
                 }
             }
         }
-        Err(e) => {
-            println!(
-                "  {} Failed to get bucket info: {}",
-                style("Error:").red(),
-                e
-            );
-        }
     }
 
     Ok(())
 }
 
 /// Show overall storage status
-fn show_overall_status(verbose: bool) {
+async fn show_overall_status(client: &GatewayClient, verbose: bool) -> Result<()> {
     println!("{}", style("Storage Overview").bold());
     println!();
-    println!("  Use 'cyxcloud status --bucket <name>' to view bucket details");
-    println!("  Use 'cyxcloud list <bucket>' to list objects");
 
-    if verbose {
-        println!();
-        println!("{}", style("Available Commands:").bold());
-        println!("  upload    Upload files to storage");
-        println!("  download  Download files from storage");
-        println!("  list      List objects in a bucket");
-        println!("  status    Show storage status (this command)");
+    match client.storage_stats().await {
+        Ok(stats) => {
+            println!("  Buckets:      {}", style(stats.bucket_count).cyan());
+            println!("  Objects:      {}", style(stats.object_count).cyan());
+            println!(
+                "  Total Size:   {}",
+                style(format_bytes(stats.total_bytes.max(0) as u64)).cyan()
+            );
+
+            if verbose && !stats.buckets.is_empty() {
+                println!();
+                println!("  {}", style("Buckets:").bold());
+                for bucket in &stats.buckets {
+                    println!(
+                        "    {:<32} {:>10} objects  {:>12}",
+                        bucket.bucket,
+                        bucket.object_count,
+                        format_bytes(bucket.total_bytes.max(0) as u64)
+                    );
+                }
+            }
+        }
+        Err(e) => {
+            println!("  Use 'cyxcloud login' to view your storage usage");
+            return Err(e).context("Failed to get storage stats");
+        }
     }
+
+    println!();
+    println!("  Use 'cyxcloud status --bucket <name>' to view bucket details");
+    println!("  Use 'cyxcloud list <bucket>' to list objects");
+    Ok(())
 }
 
 /// Format bytes as human-readable string
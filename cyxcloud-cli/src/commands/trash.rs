@@ -0,0 +1,117 @@
+//! Trash Commands
+//!
+//! Lists deleted objects that are still within the retention window and
+//! restores them.
+
+use crate::symbols;
+use anyhow::{Context, Result};
+use console::style;
+use cyxcloud_client::{ClientError, GatewayClient};
+
+/// Configuration for listing a bucket's trash
+pub struct TrashListConfig {
+    pub bucket: String,
+    pub prefix: Option<String>,
+    pub max_keys: Option<i32>,
+}
+
+/// Configuration for restoring a deleted object
+pub struct RestoreConfig {
+    pub bucket: String,
+    pub key: String,
+    /// Deleted version to restore (default: the most recently deleted)
+    pub version_id: Option<String>,
+}
+
+/// List restorable deleted objects
+pub async fn list(client: &GatewayClient, config: TrashListConfig) -> Result<()> {
+    let response = client
+        .list_trash(&config.bucket, config.prefix.as_deref(), config.max_keys)
+        .await
+        .context("Failed to list deleted objects")?;
+
+    if response.objects.is_empty() {
+        println!(
+            "{} No restorable objects in bucket '{}'",
+            style("Info:").cyan(),
+            config.bucket
+        );
+        return Ok(());
+    }
+
+    println!(
+        "{:<40} {:>12} {:<26} {}",
+        style("KEY").bold(),
+        style("SIZE").bold(),
+        style("DELETED AT").bold(),
+        style("VERSION").bold()
+    );
+    println!("{}", "-".repeat(116));
+
+    for obj in &response.objects {
+        println!(
+            "{:<40} {:>12} {:<26} {}",
+            obj.key,
+            format_bytes(obj.size),
+            obj.deleted_at,
+            obj.version_id
+        );
+    }
+
+    println!("{}", "-".repeat(116));
+    println!("{} deleted objects", style(response.objects.len()).green());
+    if response.is_truncated {
+        println!(
+            "{}",
+            style("(results truncated, use --max-keys to retrieve more)").yellow()
+        );
+    }
+
+    Ok(())
+}
+
+/// Restore a deleted object
+pub async fn restore(client: &GatewayClient, config: RestoreConfig) -> Result<()> {
+    match client
+        .restore_object(&config.bucket, &config.key, config.version_id.as_deref())
+        .await
+    {
+        Ok(etag) => {
+            println!(
+                "{} Restored: {}/{}",
+                style(symbols::CHECK).green(),
+                config.bucket,
+                config.key
+            );
+            if !etag.is_empty() {
+                println!("  ETag: {}", etag);
+            }
+            Ok(())
+        }
+        Err(e @ ClientError::NotFound(_)) => Err(e).context(format!(
+            "No restorable version of {}/{} (deleted too long ago?)",
+            config.bucket, config.key
+        )),
+        Err(e) => Err(e).context("Failed to restore object"),
+    }
+}
+
+/// Format bytes as human-readable string
+fn format_bytes(bytes: u64) -> String {
+    const KB: u64 = 1024;
+    const MB: u64 = KB * 1024;
+    const GB: u64 = MB * 1024;
+    const TB: u64 = GB * 1024;
+
+    if bytes >= TB {
+        format!("{:.2} TB", bytes as f64 / TB as f64)
+    } else if bytes >= GB {
+        format!("{:.2} GB", bytes as f64 / GB as f64)
+    } else if bytes >= MB {
+        format!("{:.2} MB", bytes as f64 / MB as f64)
+    } else if bytes >= KB {
+        format!("{:.2} KB", bytes as f64 / KB as f64)
+    } else {
+        format!("{} B", bytes)
+    }
+}
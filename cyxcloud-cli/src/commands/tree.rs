@@ -0,0 +1,267 @@
+//! Tree Command
+//!
+//! Renders the prefix hierarchy of a bucket, splitting keys at `/`, with the
+//! object count and size of every prefix.
+
+use super::list::{display_name, format_bytes, DELIMITER};
+use crate::output::OutputFormat;
+use anyhow::{Context, Result};
+use console::style;
+use cyxcloud_client::GatewayClient;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Tree configuration
+pub struct TreeConfig {
+    pub bucket: String,
+    pub prefix: Option<String>,
+    pub human_readable: bool,
+    /// Levels of prefixes to expand (None = all)
+    pub max_depth: Option<usize>,
+    pub format: OutputFormat,
+}
+
+/// Tree printed in json mode
+#[derive(Serialize)]
+struct TreeOutput<'a> {
+    bucket: &'a str,
+    prefix: Option<&'a str>,
+    tree: &'a PrefixNode,
+}
+
+/// A prefix and everything below it
+#[derive(Debug, Default, Serialize)]
+struct PrefixNode {
+    object_count: u64,
+    total_bytes: u64,
+    /// Child prefixes by name (ending in `/`)
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    prefixes: BTreeMap<String, PrefixNode>,
+    /// Sizes of the objects directly in this prefix by name
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    objects: BTreeMap<String, u64>,
+}
+
+impl PrefixNode {
+    /// Add an object by its path below this prefix
+    fn insert(&mut self, path: &str, size: u64) {
+        self.object_count += 1;
+        self.total_bytes += size;
+
+        match path.split_once(DELIMITER) {
+            Some((name, rest)) => self
+                .prefixes
+                .entry(format!("{}{}", name, DELIMITER))
+                .or_default()
+                .insert(rest, size),
+            // Directory markers (`data/`) count but have no name of their own
+            None if path.is_empty() => {}
+            None => {
+                self.objects.insert(path.to_string(), size);
+            }
+        }
+    }
+
+    /// Render the children of this prefix, one line each
+    fn render(&self, indent: &str, depth: usize, options: &RenderOptions, lines: &mut Vec<String>) {
+        let entries = self.prefixes.len() + self.objects.len();
+        let mut index = 0;
+        let mut branch = || {
+            index += 1;
+            let last = index == entries;
+            let branch = if last { "└── " } else { "├── " };
+            let child_indent = format!("{}{}", indent, if last { "    " } else { "│   " });
+            (branch, child_indent)
+        };
+
+        for (name, child) in &self.prefixes {
+            let (branch, child_indent) = branch();
+            lines.push(format!(
+                "{}{}{} ({})",
+                indent,
+                branch,
+                name,
+                options.totals(child)
+            ));
+            if options.max_depth.map_or(true, |max| depth < max) {
+                child.render(&child_indent, depth + 1, options, lines);
+            }
+        }
+
+        for (name, size) in &self.objects {
+            let (branch, _) = branch();
+            lines.push(format!(
+                "{}{}{} ({})",
+                indent,
+                branch,
+                name,
+                options.size(*size)
+            ));
+        }
+    }
+}
+
+/// How the tree is rendered
+struct RenderOptions {
+    human_readable: bool,
+    max_depth: Option<usize>,
+}
+
+impl RenderOptions {
+    fn size(&self, bytes: u64) -> String {
+        if self.human_readable {
+            format_bytes(bytes)
+        } else {
+            format!("{} bytes", bytes)
+        }
+    }
+
+    fn totals(&self, node: &PrefixNode) -> String {
+        format!(
+            "{} objects, {}",
+            node.object_count,
+            self.size(node.total_bytes)
+        )
+    }
+}
+
+/// Build the tree of the objects below the last `/` of `prefix`
+fn build(prefix: &str, objects: impl IntoIterator<Item = (String, u64)>) -> PrefixNode {
+    let mut root = PrefixNode::default();
+    for (key, size) in objects {
+        if key.starts_with(prefix) {
+            root.insert(display_name(prefix, &key), size);
+        }
+    }
+    root
+}
+
+/// Run tree command
+pub async fn run(client: &GatewayClient, config: TreeConfig) -> Result<()> {
+    let prefix = config.prefix.clone().unwrap_or_default();
+    let objects = client
+        .list_all_objects(&config.bucket, config.prefix.as_deref())
+        .await
+        .context("Failed to list objects")?;
+
+    let root = build(&prefix, objects.into_iter().map(|obj| (obj.key, obj.size)));
+
+    if !config.format.is_table() {
+        config.format.print_json(&TreeOutput {
+            bucket: &config.bucket,
+            prefix: config.prefix.as_deref(),
+            tree: &root,
+        });
+        return Ok(());
+    }
+
+    if root.object_count == 0 {
+        println!(
+            "{} No objects found in bucket '{}' with prefix '{}'",
+            style("Info:").cyan(),
+            config.bucket,
+            config.prefix.as_deref().unwrap_or("(none)")
+        );
+        return Ok(());
+    }
+
+    let options = RenderOptions {
+        human_readable: config.human_readable,
+        max_depth: config.max_depth,
+    };
+    let base = &prefix[..prefix.rfind(DELIMITER).map_or(0, |i| i + 1)];
+
+    println!(
+        "{} ({})",
+        style(format!("{}/{}", config.bucket, base)).blue().bold(),
+        options.totals(&root)
+    );
+    let mut lines = Vec::new();
+    root.render("", 1, &options, &mut lines);
+    for line in lines {
+        println!("{}", line);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tree(prefix: &str) -> PrefixNode {
+        build(
+            prefix,
+            [
+                ("data/a.csv", 10),
+                ("data/raw/b.bin", 20),
+                ("data/raw/c.bin", 30),
+                ("images/", 0),
+                ("images/d.jpg", 40),
+                ("readme.md", 5),
+            ]
+            .into_iter()
+            .map(|(key, size)| (key.to_string(), size)),
+        )
+    }
+
+    fn render(root: &PrefixNode, max_depth: Option<usize>) -> Vec<String> {
+        let options = RenderOptions {
+            human_readable: false,
+            max_depth,
+        };
+        let mut lines = Vec::new();
+        root.render("", 1, &options, &mut lines);
+        lines
+    }
+
+    #[test]
+    fn test_build_tree() {
+        let root = tree("");
+        assert_eq!(root.object_count, 6);
+        assert_eq!(root.total_bytes, 105);
+        assert_eq!(root.objects.keys().collect::<Vec<_>>(), vec!["readme.md"]);
+
+        let data = &root.prefixes["data/"];
+        assert_eq!(data.object_count, 3);
+        assert_eq!(data.total_bytes, 60);
+        assert_eq!(data.prefixes["raw/"].objects.len(), 2);
+
+        // The directory marker counts without becoming an object
+        let images = &root.prefixes["images/"];
+        assert_eq!(images.object_count, 2);
+        assert_eq!(images.objects.keys().collect::<Vec<_>>(), vec!["d.jpg"]);
+
+        // Below a prefix, names start after its last `/`
+        let root = tree("data/r");
+        assert_eq!(root.object_count, 2);
+        assert_eq!(root.prefixes["raw/"].total_bytes, 50);
+    }
+
+    #[test]
+    fn test_render_tree() {
+        assert_eq!(
+            render(&tree(""), None),
+            vec![
+                "├── data/ (3 objects, 60 bytes)",
+                "│   ├── raw/ (2 objects, 50 bytes)",
+                "│   │   ├── b.bin (20 bytes)",
+                "│   │   └── c.bin (30 bytes)",
+                "│   └── a.csv (10 bytes)",
+                "├── images/ (2 objects, 40 bytes)",
+                "│   └── d.jpg (40 bytes)",
+                "└── readme.md (5 bytes)",
+            ]
+        );
+
+        // Prefixes below the depth limit keep their totals but are not expanded
+        assert_eq!(
+            render(&tree(""), Some(1)),
+            vec![
+                "├── data/ (3 objects, 60 bytes)",
+                "├── images/ (2 objects, 40 bytes)",
+                "└── readme.md (5 bytes)",
+            ]
+        );
+    }
+}
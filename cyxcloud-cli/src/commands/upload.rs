@@ -2,10 +2,11 @@
 //!
 //! Uploads files or directories to CyxCloud storage.
 
-use crate::client::GatewayClient;
+use crate::output::{CliError, ExitKind, OutputFormat, TransferEvent, TransferProgress};
 use crate::symbols;
 use anyhow::{Context, Result};
 use console::style;
+use cyxcloud_client::GatewayClient;
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
 use std::path::Path;
 use tokio::fs;
@@ -16,6 +17,7 @@ pub struct UploadConfig {
     pub bucket: String,
     pub prefix: Option<String>,
     pub encrypt: bool,
+    pub format: OutputFormat,
 }
 
 /// Run upload command
@@ -23,7 +25,11 @@ pub async fn run(client: &GatewayClient, config: UploadConfig) -> Result<()> {
     let path = Path::new(&config.path);
 
     if !path.exists() {
-        anyhow::bail!("Path does not exist: {}", config.path);
+        return Err(CliError::new(
+            ExitKind::NotFound,
+            format!("Path does not exist: {}", config.path),
+        )
+        .into());
     }
 
     // Ensure bucket exists
@@ -33,9 +39,23 @@ pub async fn run(client: &GatewayClient, config: UploadConfig) -> Result<()> {
         .context("Failed to create bucket")?;
 
     if path.is_file() {
-        upload_single_file(client, &config.bucket, path, config.prefix.as_deref()).await?;
+        upload_single_file(
+            client,
+            &config.bucket,
+            path,
+            config.prefix.as_deref(),
+            config.format,
+        )
+        .await?;
     } else if path.is_dir() {
-        upload_directory(client, &config.bucket, path, config.prefix.as_deref()).await?;
+        upload_directory(
+            client,
+            &config.bucket,
+            path,
+            config.prefix.as_deref(),
+            config.format,
+        )
+        .await?;
     } else {
         anyhow::bail!("Path is neither a file nor directory: {}", config.path);
     }
@@ -49,6 +69,7 @@ async fn upload_single_file(
     bucket: &str,
     path: &Path,
     prefix: Option<&str>,
+    output: OutputFormat,
 ) -> Result<()> {
     let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("file");
 
@@ -60,29 +81,30 @@ async fn upload_single_file(
     let metadata = fs::metadata(path).await?;
     let size = metadata.len();
 
-    // Create progress bar
-    let pb = ProgressBar::new(size);
-    pb.set_style(
-        ProgressStyle::default_bar()
-            .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({eta})")
-            .unwrap()
-            .progress_chars("#>-"),
-    );
-
-    pb.set_message(format!("Uploading {}", file_name));
-
-    // Upload file
-    let (etag, uploaded_size) = client
-        .upload_local_file(bucket, &key, path)
+    // Upload file, reporting progress as it streams
+    let progress = TransferProgress::start(output, &key, size, true);
+    let reporter = progress.clone();
+    let result = client
+        .upload_local_file_with_progress(bucket, &key, path, move |sent| reporter.update(sent))
         .await
-        .context("Failed to upload file")?;
-
-    pb.finish_with_message(format!(
-        "{} Uploaded {} ({} bytes)",
-        style(symbols::CHECK).green(),
-        key,
-        uploaded_size
-    ));
+        .context("Failed to upload file");
+    let (etag, uploaded_size) = match result {
+        Ok(uploaded) => uploaded,
+        Err(e) => {
+            progress.fail(&e);
+            return Err(e);
+        }
+    };
+    progress.complete(uploaded_size, Some(&etag), None);
+
+    output.print_json(&TransferEvent::Summary {
+        succeeded: 1,
+        failed: 0,
+        bytes: uploaded_size,
+    });
+    if !output.is_table() {
+        return Ok(());
+    }
 
     println!(
         "\n{} {}/{}\n  ETag: {}\n  Size: {} bytes",
@@ -102,20 +124,34 @@ async fn upload_directory(
     bucket: &str,
     dir_path: &Path,
     prefix: Option<&str>,
+    output: OutputFormat,
 ) -> Result<()> {
     // Collect all files first
     let files = collect_files(dir_path).await?;
 
     if files.is_empty() {
-        println!("{}", style("No files to upload").yellow());
+        if output.is_table() {
+            println!("{}", style("No files to upload").yellow());
+        }
+        output.print_json(&TransferEvent::Summary {
+            succeeded: 0,
+            failed: 0,
+            bytes: 0,
+        });
         return Ok(());
     }
 
-    println!("{} {} files to upload", style("Found").cyan(), files.len());
+    if output.is_table() {
+        println!("{} {} files to upload", style("Found").cyan(), files.len());
+    }
 
     // Create multi-progress bar
     let multi = MultiProgress::new();
-    let overall_pb = multi.add(ProgressBar::new(files.len() as u64));
+    let overall_pb = if output.is_table() {
+        multi.add(ProgressBar::new(files.len() as u64))
+    } else {
+        ProgressBar::hidden()
+    };
     overall_pb.set_style(
         ProgressStyle::default_bar()
             .template(
@@ -126,8 +162,8 @@ async fn upload_directory(
     );
 
     let mut total_bytes: u64 = 0;
-    let mut success_count = 0;
-    let mut error_count = 0;
+    let mut success_count: u64 = 0;
+    let mut error_count: u64 = 0;
 
     for file_path in &files {
         // Calculate key from relative path
@@ -141,18 +177,30 @@ async fn upload_directory(
         // Replace backslashes with forward slashes for S3 compatibility
         let key = key.replace('\\', "/");
 
-        match client.upload_local_file(bucket, &key, file_path).await {
-            Ok((_, size)) => {
+        let size = fs::metadata(file_path).await.map(|m| m.len()).unwrap_or(0);
+        let progress = TransferProgress::start(output, &key, size, false);
+        let reporter = progress.clone();
+        match client
+            .upload_local_file_with_progress(bucket, &key, file_path, move |sent| {
+                reporter.update(sent)
+            })
+            .await
+        {
+            Ok((etag, size)) => {
+                progress.complete(size, Some(&etag), None);
                 total_bytes += size;
                 success_count += 1;
             }
             Err(e) => {
-                eprintln!(
-                    "{} Failed to upload {}: {}",
-                    style(symbols::CROSS).red(),
-                    file_path.display(),
-                    e
-                );
+                if output.is_table() {
+                    eprintln!(
+                        "{} Failed to upload {}: {}",
+                        style(symbols::CROSS).red(),
+                        file_path.display(),
+                        e
+                    );
+                }
+                progress.fail(&e.into());
                 error_count += 1;
             }
         }
@@ -163,16 +211,26 @@ async fn upload_directory(
     overall_pb.finish_with_message("Upload complete");
 
     // Print summary
-    println!("\n{}", style("Upload Summary:").bold());
-    println!(
-        "  {} files uploaded successfully",
-        style(success_count).green()
-    );
-    if error_count > 0 {
-        println!("  {} files failed", style(error_count).red());
+    output.print_json(&TransferEvent::Summary {
+        succeeded: success_count,
+        failed: error_count,
+        bytes: total_bytes,
+    });
+    if output.is_table() {
+        println!("\n{}", style("Upload Summary:").bold());
+        println!(
+            "  {} files uploaded successfully",
+            style(success_count).green()
+        );
+        if error_count > 0 {
+            println!("  {} files failed", style(error_count).red());
+        }
+        println!("  {} total bytes transferred", format_bytes(total_bytes));
     }
-    println!("  {} total bytes transferred", format_bytes(total_bytes));
 
+    if error_count > 0 {
+        anyhow::bail!("{} of {} files failed to upload", error_count, files.len());
+    }
     Ok(())
 }
 
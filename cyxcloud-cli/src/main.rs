@@ -9,10 +9,23 @@
 //! - `register` - Register a new account
 //! - `upload` - Upload a file or directory
 //! - `download` - Download a file or directory
-//! - `list` - List stored files
+//! - `backup` - Save a bucket as a tar archive
+//! - `list` - List stored files (one prefix level, or `--recursive`)
+//! - `tree` - Show the prefix hierarchy of a bucket with sizes
 //! - `delete` - Delete a file from storage
+//! - `trash` - List and restore deleted objects
+//! - `share` - Make an object public and print its link
+//! - `fsck` - Check (and repair) the objects in a bucket
+//! - `mount` - Mount a bucket as a local filesystem (`fuse` feature)
+//! - `import-s3` - Import a bucket from S3/MinIO
 //! - `status` - Show storage status
 //! - `config` - Show or edit configuration
+//! - `admin` - Cluster administration (topology export)
+//!
+//! # Output
+//! `--output table|json|quiet` selects human-readable text, JSON for scripts
+//! or errors only. Exit codes: 1 failure, 2 usage, 3 not found, 4 not
+//! logged in or permission denied, 5 gateway unreachable.
 //!
 //! # Configuration
 //! Config file: ~/.cyxcloud/config.toml
@@ -20,19 +33,22 @@
 
 #![allow(dead_code)]
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use clap::{Parser, Subcommand};
 use std::path::PathBuf;
 
-mod client;
 mod commands;
 mod config;
-mod cyxwiz_client;
+mod mount;
+mod output;
 mod symbols;
 
-use client::{GatewayClient, TlsConfig};
-use commands::{auth, dataset, delete, download, list, status, upload};
-use cyxwiz_client::CyxWizClient;
+use commands::{
+    admin, auth, backup, dataset, delete, download, fsck, import, list, share, status, trash, tree,
+    upload,
+};
+use cyxcloud_client::{CyxWizClient, GatewayClient, S3Credentials, TlsConfig};
+use output::{CliError, ExitKind, OutputFormat};
 
 #[derive(Parser)]
 #[command(name = "cyxcloud")]
@@ -64,6 +80,10 @@ struct Cli {
     #[arg(long, global = true, default_value = "false")]
     insecure: bool,
 
+    /// Output format: table, json (NDJSON events for transfers) or quiet
+    #[arg(long, global = true, value_enum, default_value = "table")]
+    output: OutputFormat,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -130,6 +150,20 @@ enum Commands {
         output: String,
     },
 
+    /// Save a bucket as a tar archive with a manifest of keys and hashes
+    Backup {
+        /// Bucket name
+        bucket: String,
+
+        /// Only back up keys starting with this prefix
+        #[arg(short, long)]
+        prefix: Option<String>,
+
+        /// Archive path (default: <bucket>.tar)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
     /// List objects in a bucket
     List {
         /// Bucket name
@@ -150,6 +184,32 @@ enum Commands {
         /// Maximum number of keys to return
         #[arg(long)]
         max_keys: Option<i32>,
+
+        /// List every key instead of grouping keys below the next `/`
+        #[arg(short, long)]
+        recursive: bool,
+
+        /// Show object count and size per prefix and in total
+        #[arg(short, long)]
+        summarize: bool,
+    },
+
+    /// Show the prefix hierarchy of a bucket with sizes
+    Tree {
+        /// Bucket name
+        bucket: String,
+
+        /// Only show keys starting with this prefix
+        #[arg(short, long)]
+        prefix: Option<String>,
+
+        /// Human-readable sizes
+        #[arg(short = 'H', long)]
+        human_readable: bool,
+
+        /// Levels of prefixes to expand (default: all)
+        #[arg(short = 'L', long)]
+        max_depth: Option<usize>,
     },
 
     /// Show storage status
@@ -176,6 +236,114 @@ enum Commands {
         force: bool,
     },
 
+    /// Make an object (or a whole bucket) public and print its link
+    Share {
+        /// s3://bucket/key, or s3://bucket for all of its objects
+        target: String,
+
+        /// Make it private again
+        #[arg(long)]
+        revoke: bool,
+    },
+
+    /// Check that the objects in a bucket are fully stored
+    Fsck {
+        /// s3://bucket[/prefix]
+        target: String,
+
+        /// Queue repairs for degraded objects
+        #[arg(long)]
+        repair: bool,
+
+        /// List healthy objects too
+        #[arg(short, long)]
+        verbose: bool,
+    },
+
+    /// Mount a bucket as a local filesystem (requires the `fuse` feature)
+    Mount {
+        /// Bucket name
+        bucket: String,
+
+        /// Directory to mount on
+        mountpoint: PathBuf,
+
+        /// Allow creating, modifying and deleting files
+        #[arg(short, long)]
+        writable: bool,
+
+        /// Seconds to cache attributes and directory listings
+        #[arg(long, default_value = "30")]
+        cache_ttl: u64,
+
+        /// Read cache size in MiB
+        #[arg(long, default_value = "256")]
+        cache_mb: u64,
+
+        /// Largest file (MiB) that can be written back
+        #[arg(long, default_value = "64")]
+        max_write_mb: u64,
+
+        /// Let other users access the mount
+        #[arg(long)]
+        allow_other: bool,
+    },
+
+    /// Import a bucket from an S3-compatible service (AWS S3, MinIO, ...)
+    ImportS3 {
+        /// Source S3 endpoint (e.g. http://minio:9000)
+        #[arg(long)]
+        endpoint: String,
+
+        /// Source bucket
+        #[arg(long)]
+        bucket: String,
+
+        /// Only import keys starting with this prefix
+        #[arg(short, long)]
+        prefix: Option<String>,
+
+        /// Destination bucket (defaults to the source bucket name)
+        #[arg(long)]
+        dest_bucket: Option<String>,
+
+        /// Prefix prepended to every imported key
+        #[arg(long)]
+        dest_prefix: Option<String>,
+
+        /// Source region used for request signing
+        #[arg(long, env = "AWS_REGION", default_value = "us-east-1")]
+        region: String,
+
+        /// Source access key ID (omit for public buckets)
+        #[arg(long, env = "AWS_ACCESS_KEY_ID")]
+        access_key_id: Option<String>,
+
+        /// Source secret access key
+        #[arg(long, env = "AWS_SECRET_ACCESS_KEY", hide_env_values = true)]
+        secret_access_key: Option<String>,
+
+        /// Source session token for temporary credentials
+        #[arg(long, env = "AWS_SESSION_TOKEN", hide_env_values = true)]
+        session_token: Option<String>,
+
+        /// Objects copied in parallel
+        #[arg(short, long, default_value = "4")]
+        jobs: usize,
+
+        /// Journal file used to resume (default: .cyxcloud-import-<bucket>.jsonl)
+        #[arg(long)]
+        journal: Option<PathBuf>,
+
+        /// Write the verification report as JSON
+        #[arg(long)]
+        report: Option<PathBuf>,
+
+        /// List what would be imported without copying
+        #[arg(long)]
+        dry_run: bool,
+    },
+
     /// Show or initialize configuration
     Config {
         #[command(subcommand)]
@@ -187,6 +355,69 @@ enum Commands {
         #[command(subcommand)]
         command: DatasetCommands,
     },
+
+    /// Cluster administration commands
+    Admin {
+        #[command(subcommand)]
+        command: AdminCommands,
+    },
+
+    /// List and restore deleted objects
+    Trash {
+        #[command(subcommand)]
+        command: TrashCommands,
+    },
+}
+
+#[derive(Subcommand)]
+enum TrashCommands {
+    /// List deleted objects that can still be restored
+    List {
+        /// Bucket name
+        bucket: String,
+
+        /// Only list keys starting with this prefix
+        #[arg(short, long)]
+        prefix: Option<String>,
+
+        /// Maximum number of objects to list
+        #[arg(long)]
+        max_keys: Option<i32>,
+    },
+
+    /// Restore a deleted object
+    Restore {
+        /// Bucket name
+        bucket: String,
+
+        /// Object key
+        key: String,
+
+        /// Deleted version to restore (from `trash list`; default: latest)
+        #[arg(long)]
+        version_id: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum AdminCommands {
+    /// Export the cluster topology (regions, datacenters, nodes)
+    Topology {
+        /// Output Graphviz DOT instead of JSON
+        #[arg(long)]
+        dot: bool,
+
+        /// Write to a file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Show rebalancer scan history and the under-replication trend
+    Scans {
+        /// Window in hours
+        #[arg(long, default_value = "24")]
+        hours: u32,
+    },
 }
 
 #[derive(Subcommand)]
@@ -287,7 +518,7 @@ enum ConfigCommands {
 }
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() {
     // Initialize logging
     tracing_subscriber::fmt()
         .with_env_filter(
@@ -297,6 +528,17 @@ async fn main() -> Result<()> {
         .init();
 
     let cli = Cli::parse();
+    let format = cli.output;
+
+    if let Err(e) = run(cli).await {
+        output::report_error(format, &e);
+        std::process::exit(ExitKind::of(&e).code());
+    }
+}
+
+/// Run the parsed command
+async fn run(cli: Cli) -> Result<()> {
+    let format = cli.output;
 
     // Load configuration from ~/.cyxcloud/config.toml
     let cfg = config::load_config();
@@ -311,20 +553,20 @@ async fn main() -> Result<()> {
     // Get auth token for gateway commands
     let auth_token = config::get_valid_credentials().map(|c| c.access_token);
 
-    // Build TLS configuration from CLI args
-    let tls_config = if cli.ca_cert.is_some() || cli.client_cert.is_some() || cli.insecure {
-        Some(TlsConfig {
+    // Create gateway client with auth token and optional TLS from CLI args
+    let mut builder = GatewayClient::builder(&gateway_url);
+    if let Some(ref token) = auth_token {
+        builder = builder.access_token(token);
+    }
+    if cli.ca_cert.is_some() || cli.client_cert.is_some() || cli.insecure {
+        builder = builder.tls(TlsConfig {
             ca_cert: cli.ca_cert,
             client_cert: cli.client_cert,
             client_key: cli.client_key,
             danger_accept_invalid_certs: cli.insecure,
-        })
-    } else {
-        None
-    };
-
-    // Create gateway client with auth token and optional TLS
-    let client = GatewayClient::with_tls(&gateway_url, auth_token.clone(), tls_config);
+        });
+    }
+    let client = builder.build().context("Failed to create gateway client")?;
 
     match cli.command {
         // Auth commands
@@ -359,6 +601,7 @@ async fn main() -> Result<()> {
                 bucket,
                 prefix,
                 encrypt,
+                format,
             };
             upload::run(&client, config).await?;
         }
@@ -375,16 +618,34 @@ async fn main() -> Result<()> {
                 key,
                 output,
                 prefix,
+                format,
             };
             download::run(&client, config).await?;
         }
 
+        Commands::Backup {
+            bucket,
+            prefix,
+            output,
+        } => {
+            require_auth(&auth_token)?;
+            let config = backup::BackupConfig {
+                bucket,
+                prefix,
+                output,
+                format,
+            };
+            backup::run(&client, config).await?;
+        }
+
         Commands::List {
             bucket,
             prefix,
             long,
             human_readable,
             max_keys,
+            recursive,
+            summarize,
         } => {
             require_auth(&auth_token)?;
             let config = list::ListConfig {
@@ -393,13 +654,37 @@ async fn main() -> Result<()> {
                 long_format: long,
                 human_readable,
                 max_keys,
+                recursive,
+                summarize,
+                format,
             };
             list::run(&client, config).await?;
         }
 
+        Commands::Tree {
+            bucket,
+            prefix,
+            human_readable,
+            max_depth,
+        } => {
+            require_auth(&auth_token)?;
+            let config = tree::TreeConfig {
+                bucket,
+                prefix,
+                human_readable,
+                max_depth,
+                format,
+            };
+            tree::run(&client, config).await?;
+        }
+
         Commands::Status { bucket, verbose } => {
             // Status doesn't require auth (health check)
-            let config = status::StatusConfig { bucket, verbose };
+            let config = status::StatusConfig {
+                bucket,
+                verbose,
+                format,
+            };
             status::run(&client, config).await?;
         }
 
@@ -409,6 +694,95 @@ async fn main() -> Result<()> {
             delete::run(&client, config).await?;
         }
 
+        Commands::Share { target, revoke } => {
+            require_auth(&auth_token)?;
+            let config = share::ShareConfig { target, revoke };
+            share::run(&client, config).await?;
+        }
+
+        Commands::Fsck {
+            target,
+            repair,
+            verbose,
+        } => {
+            require_auth(&auth_token)?;
+            let config = fsck::FsckConfig {
+                target,
+                repair,
+                verbose,
+            };
+            fsck::run(&client, config).await?;
+        }
+
+        Commands::Mount {
+            bucket,
+            mountpoint,
+            writable,
+            cache_ttl,
+            cache_mb,
+            max_write_mb,
+            allow_other,
+        } => {
+            require_auth(&auth_token)?;
+            let defaults = mount::MountOptions::default();
+            let options = mount::MountOptions {
+                bucket,
+                read_only: !writable,
+                metadata_ttl: std::time::Duration::from_secs(cache_ttl),
+                cache_blocks: (cache_mb * 1024 * 1024 / defaults.block_size) as usize,
+                write_back_limit: max_write_mb * 1024 * 1024,
+                ..defaults
+            };
+            let config = commands::mount::MountConfig {
+                mountpoint,
+                options,
+                allow_other,
+            };
+            commands::mount::run(std::sync::Arc::new(client), config).await?;
+        }
+
+        Commands::ImportS3 {
+            endpoint,
+            bucket,
+            prefix,
+            dest_bucket,
+            dest_prefix,
+            region,
+            access_key_id,
+            secret_access_key,
+            session_token,
+            jobs,
+            journal,
+            report,
+            dry_run,
+        } => {
+            require_auth(&auth_token)?;
+            let credentials = match (access_key_id, secret_access_key) {
+                (Some(access_key_id), Some(secret_access_key)) => Some(S3Credentials {
+                    access_key_id,
+                    secret_access_key,
+                    session_token,
+                }),
+                (None, None) => None,
+                _ => anyhow::bail!("--access-key-id and --secret-access-key must be set together"),
+            };
+            let config = import::ImportConfig {
+                endpoint,
+                region,
+                credentials,
+                journal: journal
+                    .unwrap_or_else(|| PathBuf::from(format!(".cyxcloud-import-{}.jsonl", bucket))),
+                dest_bucket: dest_bucket.unwrap_or_else(|| bucket.clone()),
+                source_bucket: bucket,
+                prefix,
+                dest_prefix,
+                jobs,
+                report,
+                dry_run,
+            };
+            import::run(std::sync::Arc::new(client), config).await?;
+        }
+
         Commands::Config { command } => {
             handle_config_command(command)?;
         }
@@ -474,6 +848,50 @@ async fn main() -> Result<()> {
                 }
             }
         }
+
+        Commands::Admin { command } => {
+            require_auth(&auth_token)?;
+            match command {
+                AdminCommands::Topology { dot, output } => {
+                    let config = admin::TopologyConfig { dot, output };
+                    admin::topology(&client, config).await?;
+                }
+                AdminCommands::Scans { hours } => {
+                    let config = admin::ScansConfig { hours };
+                    admin::scans(&client, config).await?;
+                }
+            }
+        }
+
+        Commands::Trash { command } => {
+            require_auth(&auth_token)?;
+            match command {
+                TrashCommands::List {
+                    bucket,
+                    prefix,
+                    max_keys,
+                } => {
+                    let config = trash::TrashListConfig {
+                        bucket,
+                        prefix,
+                        max_keys,
+                    };
+                    trash::list(&client, config).await?;
+                }
+                TrashCommands::Restore {
+                    bucket,
+                    key,
+                    version_id,
+                } => {
+                    let config = trash::RestoreConfig {
+                        bucket,
+                        key,
+                        version_id,
+                    };
+                    trash::restore(&client, config).await?;
+                }
+            }
+        }
     }
 
     Ok(())
@@ -573,7 +991,11 @@ fn handle_config_command(command: Option<ConfigCommands>) -> Result<()> {
 /// Check if user is authenticated, return error if not
 fn require_auth(token: &Option<String>) -> Result<()> {
     if token.is_none() {
-        anyhow::bail!("Not logged in. Run 'cyxcloud login' first.");
+        return Err(CliError::new(
+            ExitKind::PermissionDenied,
+            "Not logged in. Run 'cyxcloud login' first.",
+        )
+        .into());
     }
     Ok(())
 }
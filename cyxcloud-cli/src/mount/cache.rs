@@ -0,0 +1,105 @@
+//! Block Cache
+//!
+//! FUSE reads arrive in small pieces (typically 128 KiB), while the gateway
+//! decodes whole chunks for every range GET. Reads are therefore aligned to
+//! larger blocks and the most recently used blocks are kept in memory.
+
+use bytes::Bytes;
+use std::collections::{HashMap, VecDeque};
+
+/// LRU cache of file blocks keyed by `(inode, block index)`
+#[derive(Debug)]
+pub struct BlockCache {
+    block_size: u64,
+    capacity: usize,
+    blocks: HashMap<(u64, u64), Bytes>,
+    order: VecDeque<(u64, u64)>,
+}
+
+impl BlockCache {
+    /// Create a cache holding at most `capacity` blocks of `block_size` bytes
+    pub fn new(block_size: u64, capacity: usize) -> Self {
+        Self {
+            block_size: block_size.max(1),
+            capacity,
+            blocks: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    pub fn block_size(&self) -> u64 {
+        self.block_size
+    }
+
+    /// Indices of the blocks covering `size` bytes at `offset`
+    pub fn blocks_for(&self, offset: u64, size: u64) -> std::ops::RangeInclusive<u64> {
+        let last = offset + size.max(1) - 1;
+        offset / self.block_size..=last / self.block_size
+    }
+
+    pub fn get(&mut self, ino: u64, block: u64) -> Option<Bytes> {
+        let data = self.blocks.get(&(ino, block))?.clone();
+        self.touch((ino, block));
+        Some(data)
+    }
+
+    pub fn insert(&mut self, ino: u64, block: u64, data: Bytes) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.blocks.insert((ino, block), data).is_some() {
+            self.touch((ino, block));
+            return;
+        }
+        self.order.push_back((ino, block));
+        while self.order.len() > self.capacity {
+            if let Some(evicted) = self.order.pop_front() {
+                self.blocks.remove(&evicted);
+            }
+        }
+    }
+
+    /// Drop all cached blocks of a file
+    pub fn invalidate(&mut self, ino: u64) {
+        self.blocks.retain(|(i, _), _| *i != ino);
+        self.order.retain(|(i, _)| *i != ino);
+    }
+
+    fn touch(&mut self, key: (u64, u64)) {
+        if let Some(pos) = self.order.iter().position(|k| *k == key) {
+            self.order.remove(pos);
+            self.order.push_back(key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blocks_for_range() {
+        let cache = BlockCache::new(100, 4);
+        assert_eq!(cache.blocks_for(0, 100), 0..=0);
+        assert_eq!(cache.blocks_for(50, 100), 0..=1);
+        assert_eq!(cache.blocks_for(250, 1), 2..=2);
+    }
+
+    #[test]
+    fn test_lru_eviction_and_invalidate() {
+        let mut cache = BlockCache::new(4, 2);
+        cache.insert(1, 0, Bytes::from_static(b"aaaa"));
+        cache.insert(1, 1, Bytes::from_static(b"bbbb"));
+        // Touch block 0 so block 1 is evicted next
+        assert!(cache.get(1, 0).is_some());
+        cache.insert(2, 0, Bytes::from_static(b"cccc"));
+
+        assert!(cache.get(1, 1).is_none());
+        assert!(cache.get(1, 0).is_some());
+        assert!(cache.get(2, 0).is_some());
+
+        cache.invalidate(1);
+        assert!(cache.get(1, 0).is_none());
+        assert!(cache.get(2, 0).is_some());
+    }
+}
@@ -0,0 +1,597 @@
+//! FUSE Filesystem
+//!
+//! Implements `fuser::Filesystem` on top of the gateway client. Callbacks
+//! run on the FUSE session thread and block on the tokio runtime for each
+//! gateway request.
+
+use super::cache::BlockCache;
+use super::tree::{InodeTree, Node, NodeKind};
+use super::MountOptions;
+use bytes::Bytes;
+use cyxcloud_client::{ClientError, GatewayClient};
+use fuser::{
+    FileAttr, FileType, Filesystem, ReplyAttr, ReplyCreate, ReplyData, ReplyDirectory, ReplyEmpty,
+    ReplyEntry, ReplyOpen, ReplyWrite, Request, TimeOrNow,
+};
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::sync::Arc;
+use std::time::SystemTime;
+use tokio::runtime::Handle;
+use tracing::{debug, warn};
+
+/// Pending contents of a file opened for writing
+struct WriteBuffer {
+    data: Vec<u8>,
+    dirty: bool,
+    handles: u32,
+}
+
+/// Bucket mounted as a filesystem
+pub struct CyxFs {
+    client: Arc<GatewayClient>,
+    runtime: Handle,
+    options: MountOptions,
+    tree: InodeTree,
+    cache: BlockCache,
+    /// Write-back buffers by inode
+    buffers: HashMap<u64, WriteBuffer>,
+    next_fh: u64,
+    uid: u32,
+    gid: u32,
+}
+
+impl CyxFs {
+    pub fn new(client: Arc<GatewayClient>, runtime: Handle, options: MountOptions) -> Self {
+        let cache = BlockCache::new(options.block_size, options.cache_blocks);
+        Self {
+            client,
+            runtime,
+            options,
+            tree: InodeTree::new(),
+            cache,
+            buffers: HashMap::new(),
+            next_fh: 1,
+            // SAFETY: getuid/getgid cannot fail
+            uid: unsafe { libc::getuid() },
+            gid: unsafe { libc::getgid() },
+        }
+    }
+
+    fn attr(&self, node: &Node) -> FileAttr {
+        let (kind, size, mtime, perm) = match node.kind {
+            NodeKind::Dir { .. } => (FileType::Directory, 0, SystemTime::UNIX_EPOCH, 0o755),
+            NodeKind::File { size, mtime } => {
+                // Unflushed writes take precedence over the listed size
+                let size = self
+                    .buffers
+                    .get(&node.ino)
+                    .map_or(size, |b| b.data.len() as u64);
+                let perm = if self.options.read_only { 0o444 } else { 0o644 };
+                (FileType::RegularFile, size, mtime, perm)
+            }
+        };
+
+        FileAttr {
+            ino: node.ino,
+            size,
+            blocks: size.div_ceil(512),
+            atime: mtime,
+            mtime,
+            ctime: mtime,
+            crtime: mtime,
+            kind,
+            perm: if node.is_dir() && self.options.read_only {
+                0o555
+            } else {
+                perm
+            },
+            nlink: if node.is_dir() { 2 } else { 1 },
+            uid: self.uid,
+            gid: self.gid,
+            rdev: 0,
+            blksize: self.options.block_size as u32,
+            flags: 0,
+        }
+    }
+
+    /// Refresh a directory's contents if its cached listing expired
+    fn ensure_listed(&mut self, ino: u64) -> Result<(), i32> {
+        if !self.tree.needs_listing(ino, self.options.metadata_ttl) {
+            return Ok(());
+        }
+        let prefix = self.tree.get(ino).ok_or(libc::ENOENT)?.list_prefix();
+        let prefix = (!prefix.is_empty()).then_some(prefix);
+
+        debug!(bucket = %self.options.bucket, ?prefix, "Listing directory");
+        let objects = self
+            .runtime
+            .block_on(
+                self.client
+                    .list_all_objects(&self.options.bucket, prefix.as_deref()),
+            )
+            .map_err(errno)?;
+
+        let buffers = &self.buffers;
+        self.tree
+            .apply_listing(ino, &objects, |child| buffers.contains_key(&child));
+        Ok(())
+    }
+
+    /// Read `size` bytes at `offset` through the block cache
+    fn read_remote(&mut self, node: &Node, offset: u64, size: u64) -> Result<Bytes, i32> {
+        let NodeKind::File {
+            size: file_size, ..
+        } = node.kind
+        else {
+            return Err(libc::EISDIR);
+        };
+        if offset >= file_size || size == 0 {
+            return Ok(Bytes::new());
+        }
+        let size = size.min(file_size - offset);
+        let block_size = self.cache.block_size();
+
+        let mut data = Vec::with_capacity(size as usize);
+        for block in self.cache.blocks_for(offset, size) {
+            let bytes = match self.cache.get(node.ino, block) {
+                Some(bytes) => bytes,
+                None => {
+                    let start = block * block_size;
+                    let end = (start + block_size).min(file_size) - 1;
+                    let bytes = self
+                        .runtime
+                        .block_on(self.client.download_range(
+                            &self.options.bucket,
+                            &node.path,
+                            start,
+                            end,
+                        ))
+                        .map_err(errno)?;
+                    self.cache.insert(node.ino, block, bytes.clone());
+                    bytes
+                }
+            };
+            data.extend_from_slice(&bytes);
+        }
+
+        let skip = (offset % block_size) as usize;
+        let end = (skip + size as usize).min(data.len());
+        Ok(Bytes::from(data).slice(skip.min(end)..end))
+    }
+
+    /// Set up a write-back buffer for `ino`, loading the current contents
+    /// unless the file is being truncated
+    fn open_buffer(&mut self, ino: u64, truncate: bool) -> Result<(), i32> {
+        if self.options.read_only {
+            return Err(libc::EROFS);
+        }
+        if let Some(buffer) = self.buffers.get_mut(&ino) {
+            buffer.handles += 1;
+            if truncate {
+                buffer.data.clear();
+                buffer.dirty = true;
+            }
+            return Ok(());
+        }
+
+        let node = self.tree.get(ino).ok_or(libc::ENOENT)?.clone();
+        let NodeKind::File { size, .. } = node.kind else {
+            return Err(libc::EISDIR);
+        };
+        let data = if truncate || size == 0 {
+            Vec::new()
+        } else if size > self.options.write_back_limit {
+            return Err(libc::EFBIG);
+        } else {
+            self.runtime
+                .block_on(self.client.download_file(&self.options.bucket, &node.path))
+                .map_err(errno)?
+                .to_vec()
+        };
+
+        self.buffers.insert(
+            ino,
+            WriteBuffer {
+                data,
+                dirty: truncate,
+                handles: 1,
+            },
+        );
+        Ok(())
+    }
+
+    /// Upload a dirty write-back buffer
+    fn flush_buffer(&mut self, ino: u64) -> Result<(), i32> {
+        let Some(buffer) = self.buffers.get(&ino).filter(|b| b.dirty) else {
+            return Ok(());
+        };
+        let path = self.tree.get(ino).ok_or(libc::ENOENT)?.path.clone();
+        let data = Bytes::from(buffer.data.clone());
+        let size = data.len() as u64;
+
+        debug!(bucket = %self.options.bucket, key = %path, size, "Writing back file");
+        self.runtime
+            .block_on(self.client.upload_file(
+                &self.options.bucket,
+                &path,
+                data,
+                "application/octet-stream",
+            ))
+            .map_err(errno)?;
+
+        if let Some(buffer) = self.buffers.get_mut(&ino) {
+            buffer.dirty = false;
+        }
+        self.tree.set_file_attrs(ino, size, SystemTime::now());
+        self.cache.invalidate(ino);
+        Ok(())
+    }
+
+    fn next_fh(&mut self) -> u64 {
+        let fh = self.next_fh;
+        self.next_fh += 1;
+        fh
+    }
+
+    /// Look up a child, listing the parent first if needed
+    fn lookup_child(&mut self, parent: u64, name: &OsStr) -> Result<Node, i32> {
+        let name = name.to_str().ok_or(libc::EINVAL)?;
+        self.ensure_listed(parent)?;
+        self.tree.child(parent, name).cloned().ok_or(libc::ENOENT)
+    }
+}
+
+impl Filesystem for CyxFs {
+    fn lookup(&mut self, _req: &Request<'_>, parent: u64, name: &OsStr, reply: ReplyEntry) {
+        match self.lookup_child(parent, name) {
+            Ok(node) => reply.entry(&self.options.metadata_ttl, &self.attr(&node), 0),
+            Err(e) => reply.error(e),
+        }
+    }
+
+    fn getattr(&mut self, _req: &Request<'_>, ino: u64, reply: ReplyAttr) {
+        match self.tree.get(ino) {
+            Some(node) => reply.attr(&self.options.metadata_ttl, &self.attr(node)),
+            None => reply.error(libc::ENOENT),
+        }
+    }
+
+    fn setattr(
+        &mut self,
+        _req: &Request<'_>,
+        ino: u64,
+        _mode: Option<u32>,
+        _uid: Option<u32>,
+        _gid: Option<u32>,
+        size: Option<u64>,
+        _atime: Option<TimeOrNow>,
+        _mtime: Option<TimeOrNow>,
+        _ctime: Option<SystemTime>,
+        _fh: Option<u64>,
+        _crtime: Option<SystemTime>,
+        _chgtime: Option<SystemTime>,
+        _bkuptime: Option<SystemTime>,
+        _flags: Option<u32>,
+        reply: ReplyAttr,
+    ) {
+        // Only truncation is supported; other attributes are not stored
+        if let Some(size) = size {
+            if size > self.options.write_back_limit {
+                return reply.error(libc::EFBIG);
+            }
+            let result = match self.buffers.get_mut(&ino) {
+                Some(buffer) => {
+                    buffer.data.resize(size as usize, 0);
+                    buffer.dirty = true;
+                    Ok(())
+                }
+                None => self.open_buffer(ino, size == 0).and_then(|_| {
+                    let buffer = self.buffers.get_mut(&ino).expect("buffer opened");
+                    buffer.data.resize(size as usize, 0);
+                    buffer.dirty = true;
+                    buffer.handles -= 1;
+                    // No handle holds the buffer, so write it back now
+                    let result = self.flush_buffer(ino);
+                    self.buffers.remove(&ino);
+                    result
+                }),
+            };
+            if let Err(e) = result {
+                return reply.error(e);
+            }
+        }
+
+        match self.tree.get(ino) {
+            Some(node) => reply.attr(&self.options.metadata_ttl, &self.attr(node)),
+            None => reply.error(libc::ENOENT),
+        }
+    }
+
+    fn mkdir(
+        &mut self,
+        _req: &Request<'_>,
+        parent: u64,
+        name: &OsStr,
+        _mode: u32,
+        _umask: u32,
+        reply: ReplyEntry,
+    ) {
+        if self.options.read_only {
+            return reply.error(libc::EROFS);
+        }
+        let Some(name) = name.to_str() else {
+            return reply.error(libc::EINVAL);
+        };
+        let Some(prefix) = self.tree.get(parent).map(Node::list_prefix) else {
+            return reply.error(libc::ENOENT);
+        };
+
+        // Directories only exist through their keys; store an empty marker
+        let marker = format!("{}{}/", prefix, name);
+        if let Err(e) = self.runtime.block_on(self.client.upload_file(
+            &self.options.bucket,
+            &marker,
+            Bytes::new(),
+            "application/x-directory",
+        )) {
+            return reply.error(errno(e));
+        }
+
+        let ino = self
+            .tree
+            .upsert(parent, name, NodeKind::Dir { listed_at: None });
+        let node = self.tree.get(ino).expect("inserted").clone();
+        reply.entry(&self.options.metadata_ttl, &self.attr(&node), 0)
+    }
+
+    fn unlink(&mut self, _req: &Request<'_>, parent: u64, name: &OsStr, reply: ReplyEmpty) {
+        if self.options.read_only {
+            return reply.error(libc::EROFS);
+        }
+        let node = match self.lookup_child(parent, name) {
+            Ok(node) if node.is_dir() => return reply.error(libc::EISDIR),
+            Ok(node) => node,
+            Err(e) => return reply.error(e),
+        };
+
+        match self
+            .runtime
+            .block_on(self.client.delete_object(&self.options.bucket, &node.path))
+        {
+            Ok(()) | Err(ClientError::NotFound(_)) => {
+                self.tree.remove(node.ino);
+                self.cache.invalidate(node.ino);
+                self.buffers.remove(&node.ino);
+                reply.ok()
+            }
+            Err(e) => reply.error(errno(e)),
+        }
+    }
+
+    fn rmdir(&mut self, _req: &Request<'_>, parent: u64, name: &OsStr, reply: ReplyEmpty) {
+        if self.options.read_only {
+            return reply.error(libc::EROFS);
+        }
+        let node = match self.lookup_child(parent, name) {
+            Ok(node) if !node.is_dir() => return reply.error(libc::ENOTDIR),
+            Ok(node) => node,
+            Err(e) => return reply.error(e),
+        };
+        if let Err(e) = self.ensure_listed(node.ino) {
+            return reply.error(e);
+        }
+        if !self.tree.children(node.ino).is_empty() {
+            return reply.error(libc::ENOTEMPTY);
+        }
+
+        let marker = node.list_prefix();
+        match self
+            .runtime
+            .block_on(self.client.delete_object(&self.options.bucket, &marker))
+        {
+            Ok(()) | Err(ClientError::NotFound(_)) => {
+                self.tree.remove(node.ino);
+                reply.ok()
+            }
+            Err(e) => reply.error(errno(e)),
+        }
+    }
+
+    fn open(&mut self, _req: &Request<'_>, ino: u64, flags: i32, reply: ReplyOpen) {
+        if flags & libc::O_ACCMODE != libc::O_RDONLY {
+            if let Err(e) = self.open_buffer(ino, flags & libc::O_TRUNC != 0) {
+                return reply.error(e);
+            }
+        }
+        let fh = self.next_fh();
+        reply.opened(fh, 0)
+    }
+
+    fn create(
+        &mut self,
+        _req: &Request<'_>,
+        parent: u64,
+        name: &OsStr,
+        _mode: u32,
+        _umask: u32,
+        _flags: i32,
+        reply: ReplyCreate,
+    ) {
+        if self.options.read_only {
+            return reply.error(libc::EROFS);
+        }
+        let Some(name) = name.to_str() else {
+            return reply.error(libc::EINVAL);
+        };
+        if self.tree.get(parent).map_or(true, |p| !p.is_dir()) {
+            return reply.error(libc::ENOENT);
+        }
+
+        let ino = self.tree.upsert(
+            parent,
+            name,
+            NodeKind::File {
+                size: 0,
+                mtime: SystemTime::now(),
+            },
+        );
+        self.cache.invalidate(ino);
+        // The object only appears in the bucket once the file is flushed
+        self.buffers.insert(
+            ino,
+            WriteBuffer {
+                data: Vec::new(),
+                dirty: true,
+                handles: 1,
+            },
+        );
+
+        let node = self.tree.get(ino).expect("inserted").clone();
+        let fh = self.next_fh();
+        reply.created(&self.options.metadata_ttl, &self.attr(&node), 0, fh, 0)
+    }
+
+    fn read(
+        &mut self,
+        _req: &Request<'_>,
+        ino: u64,
+        _fh: u64,
+        offset: i64,
+        size: u32,
+        _flags: i32,
+        _lock_owner: Option<u64>,
+        reply: ReplyData,
+    ) {
+        let offset = offset.max(0) as usize;
+        if let Some(buffer) = self.buffers.get(&ino) {
+            let start = offset.min(buffer.data.len());
+            let end = (start + size as usize).min(buffer.data.len());
+            return reply.data(&buffer.data[start..end]);
+        }
+
+        let Some(node) = self.tree.get(ino).cloned() else {
+            return reply.error(libc::ENOENT);
+        };
+        match self.read_remote(&node, offset as u64, size as u64) {
+            Ok(data) => reply.data(&data),
+            Err(e) => reply.error(e),
+        }
+    }
+
+    fn write(
+        &mut self,
+        _req: &Request<'_>,
+        ino: u64,
+        _fh: u64,
+        offset: i64,
+        data: &[u8],
+        _write_flags: u32,
+        _flags: i32,
+        _lock_owner: Option<u64>,
+        reply: ReplyWrite,
+    ) {
+        let limit = self.options.write_back_limit;
+        let Some(buffer) = self.buffers.get_mut(&ino) else {
+            return reply.error(libc::EBADF);
+        };
+
+        let offset = offset.max(0) as usize;
+        let end = offset + data.len();
+        if end as u64 > limit {
+            return reply.error(libc::EFBIG);
+        }
+        if buffer.data.len() < end {
+            buffer.data.resize(end, 0);
+        }
+        buffer.data[offset..end].copy_from_slice(data);
+        buffer.dirty = true;
+        reply.written(data.len() as u32)
+    }
+
+    fn flush(
+        &mut self,
+        _req: &Request<'_>,
+        ino: u64,
+        _fh: u64,
+        _lock_owner: u64,
+        reply: ReplyEmpty,
+    ) {
+        match self.flush_buffer(ino) {
+            Ok(()) => reply.ok(),
+            Err(e) => reply.error(e),
+        }
+    }
+
+    fn release(
+        &mut self,
+        _req: &Request<'_>,
+        ino: u64,
+        _fh: u64,
+        _flags: i32,
+        _lock_owner: Option<u64>,
+        _flush: bool,
+        reply: ReplyEmpty,
+    ) {
+        let result = self.flush_buffer(ino);
+        if let Some(buffer) = self.buffers.get_mut(&ino) {
+            buffer.handles = buffer.handles.saturating_sub(1);
+            // Keep unsaved data around rather than losing it
+            if buffer.handles == 0 && !buffer.dirty {
+                self.buffers.remove(&ino);
+            }
+        }
+        match result {
+            Ok(()) => reply.ok(),
+            Err(e) => reply.error(e),
+        }
+    }
+
+    fn readdir(
+        &mut self,
+        _req: &Request<'_>,
+        ino: u64,
+        _fh: u64,
+        offset: i64,
+        mut reply: ReplyDirectory,
+    ) {
+        if let Err(e) = self.ensure_listed(ino) {
+            return reply.error(e);
+        }
+        let Some(dir) = self.tree.get(ino) else {
+            return reply.error(libc::ENOENT);
+        };
+
+        let mut entries = vec![
+            (ino, FileType::Directory, ".".to_string()),
+            (dir.parent, FileType::Directory, "..".to_string()),
+        ];
+        entries.extend(self.tree.children(ino).into_iter().map(|n| {
+            let kind = if n.is_dir() {
+                FileType::Directory
+            } else {
+                FileType::RegularFile
+            };
+            (n.ino, kind, n.name.clone())
+        }));
+
+        for (i, (ino, kind, name)) in entries.into_iter().enumerate().skip(offset as usize) {
+            if reply.add(ino, (i + 1) as i64, kind, name) {
+                break;
+            }
+        }
+        reply.ok()
+    }
+}
+
+/// Map a client error to an errno
+fn errno(e: ClientError) -> i32 {
+    match e {
+        ClientError::NotFound(_) => libc::ENOENT,
+        ClientError::Api { status: 403, .. } | ClientError::Api { status: 401, .. } => libc::EACCES,
+        e => {
+            warn!(error = %e, "Gateway request failed");
+            libc::EIO
+        }
+    }
+}
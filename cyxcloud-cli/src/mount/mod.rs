@@ -0,0 +1,44 @@
+//! Bucket Mount
+//!
+//! Exposes a bucket as a local filesystem over FUSE. Directories are derived
+//! from `/`-separated keys, file contents are fetched lazily with range
+//! GETs, and small files can optionally be written back on close.
+//!
+//! The FUSE glue lives behind the `fuse` feature; the inode tree and block
+//! cache are plain data structures.
+
+pub mod cache;
+#[cfg(feature = "fuse")]
+pub mod fs;
+pub mod tree;
+
+use std::time::Duration;
+
+/// Mount settings
+#[derive(Debug, Clone)]
+pub struct MountOptions {
+    pub bucket: String,
+    /// Reject all modifications
+    pub read_only: bool,
+    /// How long attributes and directory listings are cached
+    pub metadata_ttl: Duration,
+    /// Size of the aligned blocks fetched by range reads
+    pub block_size: u64,
+    /// Number of blocks kept in the read cache
+    pub cache_blocks: usize,
+    /// Largest file that can be buffered for write-back
+    pub write_back_limit: u64,
+}
+
+impl Default for MountOptions {
+    fn default() -> Self {
+        Self {
+            bucket: String::new(),
+            read_only: true,
+            metadata_ttl: Duration::from_secs(30),
+            block_size: 4 * 1024 * 1024,
+            cache_blocks: 64,
+            write_back_limit: 64 * 1024 * 1024,
+        }
+    }
+}
@@ -0,0 +1,343 @@
+//! Inode Tree
+//!
+//! Maps the flat key space of a bucket onto directories and files. A key
+//! such as `a/b/c.txt` yields directories `a` and `a/b` and the file
+//! `a/b/c.txt`. Directory contents come from `list_objects` and are cached
+//! until the metadata TTL expires.
+
+use cyxcloud_client::ObjectInfo;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Inode of the mount root (the bucket itself)
+pub const ROOT_INO: u64 = 1;
+
+/// Kind of a tree node
+#[derive(Debug, Clone, PartialEq)]
+pub enum NodeKind {
+    Dir {
+        /// When the directory contents were last fetched
+        listed_at: Option<Instant>,
+    },
+    File {
+        size: u64,
+        mtime: SystemTime,
+    },
+}
+
+/// A file or directory in the mounted bucket
+#[derive(Debug, Clone)]
+pub struct Node {
+    pub ino: u64,
+    pub parent: u64,
+    pub name: String,
+    /// Object key (files) or key prefix without trailing slash (directories)
+    pub path: String,
+    pub kind: NodeKind,
+}
+
+impl Node {
+    pub fn is_dir(&self) -> bool {
+        matches!(self.kind, NodeKind::Dir { .. })
+    }
+
+    /// Prefix to list this directory's contents with
+    pub fn list_prefix(&self) -> String {
+        if self.path.is_empty() {
+            String::new()
+        } else {
+            format!("{}/", self.path)
+        }
+    }
+}
+
+/// Inode table for a mounted bucket
+#[derive(Debug)]
+pub struct InodeTree {
+    nodes: HashMap<u64, Node>,
+    children: HashMap<u64, BTreeMap<String, u64>>,
+    next_ino: u64,
+}
+
+impl Default for InodeTree {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InodeTree {
+    /// Create a tree holding only the root directory
+    pub fn new() -> Self {
+        let root = Node {
+            ino: ROOT_INO,
+            parent: ROOT_INO,
+            name: String::new(),
+            path: String::new(),
+            kind: NodeKind::Dir { listed_at: None },
+        };
+
+        Self {
+            nodes: HashMap::from([(ROOT_INO, root)]),
+            children: HashMap::from([(ROOT_INO, BTreeMap::new())]),
+            next_ino: ROOT_INO + 1,
+        }
+    }
+
+    pub fn get(&self, ino: u64) -> Option<&Node> {
+        self.nodes.get(&ino)
+    }
+
+    /// Look up `name` inside directory `parent`
+    pub fn child(&self, parent: u64, name: &str) -> Option<&Node> {
+        let ino = self.children.get(&parent)?.get(name)?;
+        self.nodes.get(ino)
+    }
+
+    /// Children of a directory, in name order
+    pub fn children(&self, parent: u64) -> Vec<&Node> {
+        self.children
+            .get(&parent)
+            .map(|c| c.values().filter_map(|ino| self.nodes.get(ino)).collect())
+            .unwrap_or_default()
+    }
+
+    /// Whether a directory's cached contents are missing or older than `ttl`
+    pub fn needs_listing(&self, ino: u64, ttl: Duration) -> bool {
+        match self.nodes.get(&ino).map(|n| &n.kind) {
+            Some(NodeKind::Dir { listed_at }) => listed_at.map_or(true, |at| at.elapsed() >= ttl),
+            _ => false,
+        }
+    }
+
+    /// Replace the contents of directory `dir` with a recursive listing of its prefix
+    ///
+    /// Subdirectories are filled from the same listing and marked fresh, so
+    /// walking into them does not hit the gateway again until the TTL
+    /// expires. Nodes for which `keep` returns true (e.g. files with
+    /// unflushed writes) survive even when absent from the listing.
+    pub fn apply_listing(&mut self, dir: u64, objects: &[ObjectInfo], keep: impl Fn(u64) -> bool) {
+        let Some(prefix) = self.nodes.get(&dir).map(Node::list_prefix) else {
+            return;
+        };
+        let now = Instant::now();
+
+        let mut seen = HashSet::from([dir]);
+        for object in objects {
+            let Some(rel) = object.key.strip_prefix(&prefix) else {
+                continue;
+            };
+            let mut parts: Vec<&str> = rel.split('/').collect();
+            let file_name = parts.pop().unwrap_or_default();
+
+            let mut parent = dir;
+            for part in parts.into_iter().filter(|p| !p.is_empty()) {
+                parent = self.upsert(parent, part, NodeKind::Dir { listed_at: None });
+                seen.insert(parent);
+            }
+            // Keys ending in '/' are directory markers
+            if !file_name.is_empty() {
+                let kind = NodeKind::File {
+                    size: object.size,
+                    mtime: parse_mtime(&object.last_modified),
+                };
+                seen.insert(self.upsert(parent, file_name, kind));
+            }
+        }
+
+        // Drop everything under `dir` the listing no longer contains
+        let mut stack = vec![dir];
+        while let Some(ino) = stack.pop() {
+            let Some(entries) = self.children.get(&ino) else {
+                continue;
+            };
+            let entries: Vec<u64> = entries.values().copied().collect();
+            for child in entries {
+                if seen.contains(&child) || keep(child) {
+                    stack.push(child);
+                } else {
+                    self.remove(child);
+                }
+            }
+            if let Some(Node {
+                kind: NodeKind::Dir { listed_at },
+                ..
+            }) = self.nodes.get_mut(&ino)
+            {
+                *listed_at = Some(now);
+            }
+        }
+    }
+
+    /// Insert or update `name` inside `parent`, returning its inode
+    pub fn upsert(&mut self, parent: u64, name: &str, kind: NodeKind) -> u64 {
+        if let Some(&ino) = self.children.get(&parent).and_then(|c| c.get(name)) {
+            let node = self.nodes.get_mut(&ino).expect("child inode present");
+            match (&node.kind, &kind) {
+                // Keep the existing listing timestamp for directories
+                (NodeKind::Dir { .. }, NodeKind::Dir { .. }) => return ino,
+                (NodeKind::File { .. }, NodeKind::File { .. }) => {
+                    node.kind = kind;
+                    return ino;
+                }
+                // A file became a directory or vice versa: start over
+                _ => self.remove(ino),
+            }
+        }
+
+        let path = match self.nodes.get(&parent) {
+            Some(p) if !p.path.is_empty() => format!("{}/{}", p.path, name),
+            _ => name.to_string(),
+        };
+        let ino = self.next_ino;
+        self.next_ino += 1;
+
+        if matches!(kind, NodeKind::Dir { .. }) {
+            self.children.insert(ino, BTreeMap::new());
+        }
+        self.nodes.insert(
+            ino,
+            Node {
+                ino,
+                parent,
+                name: name.to_string(),
+                path,
+                kind,
+            },
+        );
+        self.children
+            .entry(parent)
+            .or_default()
+            .insert(name.to_string(), ino);
+        ino
+    }
+
+    /// Remove a node and everything below it
+    pub fn remove(&mut self, ino: u64) {
+        if ino == ROOT_INO {
+            return;
+        }
+        let Some(node) = self.nodes.remove(&ino) else {
+            return;
+        };
+        if let Some(siblings) = self.children.get_mut(&node.parent) {
+            siblings.remove(&node.name);
+        }
+        for child in self
+            .children
+            .remove(&ino)
+            .map(|c| c.into_values().collect::<Vec<_>>())
+            .unwrap_or_default()
+        {
+            self.remove(child);
+        }
+    }
+
+    /// Update a file's size and modification time after a write
+    pub fn set_file_attrs(&mut self, ino: u64, size: u64, mtime: SystemTime) {
+        if let Some(node) = self.nodes.get_mut(&ino) {
+            if let NodeKind::File { .. } = node.kind {
+                node.kind = NodeKind::File { size, mtime };
+            }
+        }
+    }
+}
+
+/// Parse a `LastModified` timestamp, falling back to the epoch
+fn parse_mtime(value: &str) -> SystemTime {
+    chrono::DateTime::parse_from_rfc3339(value)
+        .or_else(|_| chrono::DateTime::parse_from_rfc2822(value))
+        .ok()
+        .and_then(|t| u64::try_from(t.timestamp()).ok())
+        .map(|secs| UNIX_EPOCH + Duration::from_secs(secs))
+        .unwrap_or(UNIX_EPOCH)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn object(key: &str, size: u64) -> ObjectInfo {
+        ObjectInfo {
+            key: key.to_string(),
+            size,
+            last_modified: "2024-01-01T00:00:00Z".to_string(),
+            etag: String::new(),
+            metadata: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_listing_builds_nested_tree() {
+        let mut tree = InodeTree::new();
+        let objects = [
+            object("readme.txt", 10),
+            object("data/a.bin", 100),
+            object("data/raw/b.bin", 200),
+            object("empty/", 0),
+        ];
+        tree.apply_listing(ROOT_INO, &objects, |_| false);
+
+        let names: Vec<_> = tree
+            .children(ROOT_INO)
+            .iter()
+            .map(|n| n.name.clone())
+            .collect();
+        assert_eq!(names, ["data", "empty", "readme.txt"]);
+
+        let data = tree.child(ROOT_INO, "data").unwrap().ino;
+        let raw = tree.child(data, "raw").unwrap();
+        assert!(raw.is_dir());
+        assert_eq!(raw.list_prefix(), "data/raw/");
+
+        let b = tree.child(raw.ino, "b.bin").unwrap();
+        assert_eq!(b.path, "data/raw/b.bin");
+        assert!(matches!(b.kind, NodeKind::File { size: 200, .. }));
+
+        // Subdirectories are fresh from the same listing
+        assert!(!tree.needs_listing(raw.ino, Duration::from_secs(60)));
+        assert!(tree.needs_listing(raw.ino, Duration::ZERO));
+    }
+
+    #[test]
+    fn test_relisting_drops_deleted_objects() {
+        let mut tree = InodeTree::new();
+        tree.apply_listing(
+            ROOT_INO,
+            &[
+                object("a.txt", 1),
+                object("dir/b.txt", 2),
+                object("dir/c.txt", 3),
+            ],
+            |_| false,
+        );
+        let dir = tree.child(ROOT_INO, "dir").unwrap().ino;
+        let c = tree.child(dir, "c.txt").unwrap().ino;
+        let a = tree.child(ROOT_INO, "a.txt").unwrap().ino;
+
+        // Relisting the subdirectory only touches its own entries
+        tree.apply_listing(dir, &[object("dir/b.txt", 5)], |_| false);
+        assert!(tree.get(c).is_none());
+        assert!(tree.get(a).is_some());
+        assert!(matches!(
+            tree.child(dir, "b.txt").unwrap().kind,
+            NodeKind::File { size: 5, .. }
+        ));
+
+        // Kept nodes survive; inode numbers stay stable
+        tree.apply_listing(ROOT_INO, &[object("dir/b.txt", 5)], |ino| ino == a);
+        assert_eq!(tree.child(ROOT_INO, "a.txt").unwrap().ino, a);
+        assert_eq!(tree.child(ROOT_INO, "dir").unwrap().ino, dir);
+    }
+
+    #[test]
+    fn test_remove_is_recursive() {
+        let mut tree = InodeTree::new();
+        tree.apply_listing(ROOT_INO, &[object("x/y/z.txt", 1)], |_| false);
+        let x = tree.child(ROOT_INO, "x").unwrap().ino;
+        let y = tree.child(x, "y").unwrap().ino;
+
+        tree.remove(x);
+        assert!(tree.get(y).is_none());
+        assert!(tree.children(ROOT_INO).is_empty());
+    }
+}
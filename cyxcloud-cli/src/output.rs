@@ -0,0 +1,336 @@
+//! Output Modes
+//!
+//! `--output table` (the default) prints human-readable text and progress
+//! bars, `json` prints structured results for scripts (NDJSON events for
+//! uploads and downloads) and `quiet` prints nothing but errors. The exit
+//! code tells not-found, permission and transport failures apart in every
+//! mode.
+
+use clap::ValueEnum;
+use cyxcloud_client::{ClientError, CyxWizError, ErrorCode, HasErrorCode};
+use indicatif::{ProgressBar, ProgressStyle};
+use serde::Serialize;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+/// Minimum time between two progress events of one transfer
+const PROGRESS_EVENT_INTERVAL: Duration = Duration::from_millis(500);
+
+/// How commands print their results
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Human-readable text with progress bars
+    #[default]
+    Table,
+    /// Machine-readable JSON (NDJSON events for transfers)
+    Json,
+    /// Only errors
+    Quiet,
+}
+
+impl OutputFormat {
+    pub fn is_table(self) -> bool {
+        self == OutputFormat::Table
+    }
+
+    pub fn is_json(self) -> bool {
+        self == OutputFormat::Json
+    }
+
+    /// Print a value as one line of JSON on stdout (json mode only)
+    pub fn print_json<T: Serialize>(self, value: &T) {
+        if self.is_json() {
+            match serde_json::to_string(value) {
+                Ok(line) => println!("{}", line),
+                Err(e) => eprintln!("Failed to encode output: {}", e),
+            }
+        }
+    }
+}
+
+/// Kind of failure, selecting the process exit code
+///
+/// Exit code 2 is left to argument errors, which clap reports itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExitKind {
+    /// Any other failure
+    Failure,
+    /// Bucket, object or account does not exist
+    NotFound,
+    /// Not logged in, or not allowed
+    PermissionDenied,
+    /// Gateway could not be reached or the connection failed
+    Transport,
+}
+
+impl ExitKind {
+    /// Process exit code
+    pub fn code(self) -> i32 {
+        match self {
+            ExitKind::Failure => 1,
+            ExitKind::NotFound => 3,
+            ExitKind::PermissionDenied => 4,
+            ExitKind::Transport => 5,
+        }
+    }
+
+    /// Classify a gateway client error
+    pub fn of_client_error(error: &ClientError) -> Self {
+        if let ClientError::Http(_) = error {
+            return ExitKind::Transport;
+        }
+        match error.error_code() {
+            ErrorCode::NotFound => ExitKind::NotFound,
+            ErrorCode::Unauthenticated | ErrorCode::PermissionDenied => ExitKind::PermissionDenied,
+            ErrorCode::ServiceUnavailable | ErrorCode::Timeout | ErrorCode::TransportCorruption => {
+                ExitKind::Transport
+            }
+            _ => ExitKind::Failure,
+        }
+    }
+
+    /// Classify an auth API error
+    pub fn of_cyxwiz_error(error: &CyxWizError) -> Self {
+        match error {
+            CyxWizError::Http(_) => ExitKind::Transport,
+            CyxWizError::InvalidCredentials => ExitKind::PermissionDenied,
+            CyxWizError::AccountNotFound => ExitKind::NotFound,
+            CyxWizError::Api {
+                status: 401 | 403, ..
+            } => ExitKind::PermissionDenied,
+            CyxWizError::Api { status: 404, .. } => ExitKind::NotFound,
+            _ => ExitKind::Failure,
+        }
+    }
+
+    /// Classify a command error by the first typed error in its chain
+    pub fn of(error: &anyhow::Error) -> Self {
+        for cause in error.chain() {
+            if let Some(e) = cause.downcast_ref::<CliError>() {
+                return e.kind;
+            }
+            if let Some(e) = cause.downcast_ref::<ClientError>() {
+                return ExitKind::of_client_error(e);
+            }
+            if let Some(e) = cause.downcast_ref::<CyxWizError>() {
+                return ExitKind::of_cyxwiz_error(e);
+            }
+        }
+        ExitKind::Failure
+    }
+}
+
+/// Error raised by the CLI itself with an explicit exit kind
+#[derive(Debug, Error)]
+#[error("{message}")]
+pub struct CliError {
+    pub kind: ExitKind,
+    pub message: String,
+}
+
+impl CliError {
+    pub fn new(kind: ExitKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+        }
+    }
+}
+
+/// Report a failed command on stderr
+pub fn report_error(format: OutputFormat, error: &anyhow::Error) {
+    let kind = ExitKind::of(error);
+    if format.is_json() {
+        let line = serde_json::json!({
+            "event": "error",
+            "kind": kind,
+            "exit_code": kind.code(),
+            "message": format!("{:#}", error),
+        });
+        eprintln!("{}", line);
+    } else {
+        eprintln!("{} {:#}", console::style("Error:").red().bold(), error);
+    }
+}
+
+/// Transfer event, printed as one NDJSON line in json mode
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum TransferEvent<'a> {
+    /// A file started transferring
+    Start { key: &'a str, total: u64 },
+    /// Bytes transferred so far
+    Progress {
+        key: &'a str,
+        bytes: u64,
+        total: u64,
+    },
+    /// A file finished transferring
+    Complete {
+        key: &'a str,
+        bytes: u64,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        etag: Option<&'a str>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        path: Option<&'a Path>,
+    },
+    /// A file failed; the transfer of the others goes on
+    Failed {
+        key: &'a str,
+        kind: ExitKind,
+        error: String,
+    },
+    /// Totals of the whole command
+    Summary {
+        succeeded: u64,
+        failed: u64,
+        bytes: u64,
+    },
+}
+
+/// Progress of one file transfer
+///
+/// Drives a byte progress bar in table mode and emits rate-limited
+/// `progress` events in json mode.
+#[derive(Clone)]
+pub struct TransferProgress {
+    format: OutputFormat,
+    key: Arc<str>,
+    total: u64,
+    bar: ProgressBar,
+    last_event: Arc<Mutex<Option<Instant>>>,
+}
+
+impl TransferProgress {
+    /// Start tracking a transfer, emitting its `start` event
+    ///
+    /// Without `show_bar` (e.g. for files of a directory, which have their
+    /// own overall bar) only json events are produced.
+    pub fn start(format: OutputFormat, key: &str, total: u64, show_bar: bool) -> Self {
+        let bar = if format.is_table() && show_bar {
+            let bar = ProgressBar::new(total);
+            bar.set_style(
+                ProgressStyle::default_bar()
+                    .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({eta})")
+                    .unwrap()
+                    .progress_chars("#>-"),
+            );
+            bar
+        } else {
+            ProgressBar::hidden()
+        };
+        format.print_json(&TransferEvent::Start { key, total });
+
+        Self {
+            format,
+            key: key.into(),
+            total,
+            bar,
+            last_event: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Record the bytes transferred so far
+    pub fn update(&self, bytes: u64) {
+        self.bar.set_position(bytes);
+        if !self.format.is_json() {
+            return;
+        }
+
+        let mut last_event = self.last_event.lock().unwrap();
+        let due = last_event.map_or(true, |at| at.elapsed() >= PROGRESS_EVENT_INTERVAL);
+        if due || bytes >= self.total {
+            *last_event = Some(Instant::now());
+            self.format.print_json(&TransferEvent::Progress {
+                key: &self.key,
+                bytes,
+                total: self.total,
+            });
+        }
+    }
+
+    /// Finish the bar and emit the `complete` event
+    pub fn complete(&self, bytes: u64, etag: Option<&str>, path: Option<&Path>) {
+        self.bar.finish_and_clear();
+        self.format.print_json(&TransferEvent::Complete {
+            key: &self.key,
+            bytes,
+            etag,
+            path,
+        });
+    }
+
+    /// Abandon the bar and emit the `failed` event
+    pub fn fail(&self, error: &anyhow::Error) {
+        self.bar.abandon();
+        self.format.print_json(&TransferEvent::Failed {
+            key: &self.key,
+            kind: ExitKind::of(error),
+            error: format!("{:#}", error),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn test_exit_kind_from_error_chain() {
+        let not_found: anyhow::Result<()> =
+            Err(ClientError::NotFound("b/k".into())).context("Failed to get object metadata");
+        assert_eq!(ExitKind::of(&not_found.unwrap_err()), ExitKind::NotFound);
+
+        let denied = anyhow::Error::new(CyxWizError::Api {
+            status: 403,
+            message: "Access Denied".into(),
+        });
+        assert_eq!(ExitKind::of(&denied), ExitKind::PermissionDenied);
+
+        let logged_out =
+            anyhow::Error::new(CliError::new(ExitKind::PermissionDenied, "Not logged in"));
+        assert_eq!(ExitKind::of(&logged_out), ExitKind::PermissionDenied);
+
+        assert_eq!(ExitKind::of(&anyhow::anyhow!("boom")), ExitKind::Failure);
+    }
+
+    #[test]
+    fn test_exit_codes_are_distinct() {
+        let codes = [
+            ExitKind::Failure,
+            ExitKind::NotFound,
+            ExitKind::PermissionDenied,
+            ExitKind::Transport,
+        ]
+        .map(ExitKind::code);
+        assert_eq!(codes, [1, 3, 4, 5]);
+    }
+
+    #[test]
+    fn test_transfer_event_json() {
+        let event = TransferEvent::Progress {
+            key: "data/a.bin",
+            bytes: 512,
+            total: 1024,
+        };
+        assert_eq!(
+            serde_json::to_string(&event).unwrap(),
+            r#"{"event":"progress","key":"data/a.bin","bytes":512,"total":1024}"#
+        );
+
+        let event = TransferEvent::Complete {
+            key: "a.bin",
+            bytes: 3,
+            etag: Some("abc"),
+            path: None,
+        };
+        assert_eq!(
+            serde_json::to_string(&event).unwrap(),
+            r#"{"event":"complete","key":"a.bin","bytes":3,"etag":"abc"}"#
+        );
+    }
+}
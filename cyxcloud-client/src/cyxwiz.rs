@@ -1,6 +1,7 @@
 //! CyxWiz API Client
 //!
-//! HTTP client for authenticating with the CyxWiz API service.
+//! HTTP client for authenticating with the CyxWiz API service, which issues
+//! the user tokens accepted by the gateway.
 
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
@@ -0,0 +1,204 @@
+//! Client errors
+//!
+//! Gateway failures carry the HTTP status plus the error code and request ID
+//! the gateway attaches, so callers can match on [`ErrorCode`] and quote the
+//! request ID when reporting problems.
+
+use cyxcloud_core::error::{ErrorCode, HasErrorCode, ERROR_CODE_HEADER, REQUEST_ID_HEADER};
+use reqwest::StatusCode;
+use thiserror::Error;
+
+/// Client errors
+#[derive(Error, Debug)]
+pub enum ClientError {
+    #[error("HTTP error: {0}")]
+    Http(#[from] reqwest::Error),
+
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("API error: {status} - {message}{}", api_error_context(.code, .request_id))]
+    Api {
+        status: u16,
+        /// Error code reported by the gateway, if any
+        code: Option<ErrorCode>,
+        /// Gateway request ID for correlating with server logs
+        request_id: Option<String>,
+        message: String,
+    },
+
+    #[error("Not found: {0}")]
+    NotFound(String),
+
+    #[error("Invalid response: {0}")]
+    InvalidResponse(String),
+}
+
+impl ClientError {
+    /// Whether the request may succeed if sent again
+    ///
+    /// Connection failures, timeouts, throttling and 5xx responses are
+    /// transient; everything else would fail the same way.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            ClientError::Http(e) => e.is_connect() || e.is_timeout(),
+            ClientError::Api { status, .. } => {
+                *status == StatusCode::TOO_MANY_REQUESTS.as_u16()
+                    || (500..600).contains(status) && *status != 501
+            }
+            ClientError::Io(_) | ClientError::NotFound(_) | ClientError::InvalidResponse(_) => {
+                false
+            }
+        }
+    }
+}
+
+impl HasErrorCode for ClientError {
+    fn error_code(&self) -> ErrorCode {
+        match self {
+            ClientError::Http(e) if e.is_timeout() => ErrorCode::Timeout,
+            ClientError::Http(e) if e.is_connect() => ErrorCode::ServiceUnavailable,
+            ClientError::Http(_) | ClientError::Io(_) | ClientError::InvalidResponse(_) => {
+                ErrorCode::Internal
+            }
+            ClientError::Api { status, code, .. } => {
+                code.unwrap_or_else(|| ErrorCode::from_http_status(*status))
+            }
+            ClientError::NotFound(_) => ErrorCode::NotFound,
+        }
+    }
+}
+
+pub type Result<T> = std::result::Result<T, ClientError>;
+
+/// Suffix with the error code and request ID for `ClientError::Api`
+fn api_error_context(code: &Option<ErrorCode>, request_id: &Option<String>) -> String {
+    match (code, request_id) {
+        (Some(code), Some(id)) => format!(" [{}, request id {}]", code, id),
+        (Some(code), None) => format!(" [{}]", code),
+        (None, Some(id)) => format!(" [request id {}]", id),
+        (None, None) => String::new(),
+    }
+}
+
+/// Build an API error from a failed gateway response
+///
+/// Picks up the error code and request ID the gateway attaches (headers or
+/// S3 error XML) and extracts the message from the XML body when present.
+pub(crate) async fn api_error(response: reqwest::Response) -> ClientError {
+    let status = response.status();
+    let header = |name: &str| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string)
+    };
+    let code = header(ERROR_CODE_HEADER);
+    let request_id = header(REQUEST_ID_HEADER);
+    let body = response.text().await.unwrap_or_default();
+
+    parse_api_error(status, code, request_id, &body)
+}
+
+/// Assemble an API error from status, headers and body
+fn parse_api_error(
+    status: StatusCode,
+    code: Option<String>,
+    request_id: Option<String>,
+    body: &str,
+) -> ClientError {
+    let code = code
+        .or_else(|| extract_xml_value(body, "CyxCloudCode"))
+        .or_else(|| extract_json_value(body, "code"))
+        .and_then(|c| c.parse().ok());
+    let request_id = request_id
+        .or_else(|| extract_xml_value(body, "RequestId"))
+        .filter(|id| !id.is_empty());
+    let message = extract_xml_value(body, "Message")
+        .or_else(|| extract_json_value(body, "error"))
+        .unwrap_or_else(|| body.to_string())
+        .trim()
+        .to_string();
+    let message = if message.is_empty() {
+        status
+            .canonical_reason()
+            .unwrap_or("Request failed")
+            .to_string()
+    } else {
+        message
+    };
+
+    ClientError::Api {
+        status: status.as_u16(),
+        code,
+        request_id,
+        message,
+    }
+}
+
+/// Extract value from XML tag
+pub(crate) fn extract_xml_value(xml: &str, tag: &str) -> Option<String> {
+    let open_tag = format!("<{}>", tag);
+    let close_tag = format!("</{}>", tag);
+
+    let start = xml.find(&open_tag)? + open_tag.len();
+    let end = xml[start..].find(&close_tag)?;
+
+    Some(xml[start..start + end].to_string())
+}
+
+/// Extract a string field from a JSON error body (`{"error": ..., "code": ...}`)
+fn extract_json_value(body: &str, field: &str) -> Option<String> {
+    let value: serde_json::Value = serde_json::from_str(body).ok()?;
+    value.get(field)?.as_str().map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_api_error_from_s3_xml() {
+        let body = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Error>
+    <Code>ServiceUnavailable</Code>
+    <Message>A storage node could not be reached</Message>
+    <CyxCloudCode>NODE_UNREACHABLE</CyxCloudCode>
+    <RequestId>abc123</RequestId>
+</Error>"#;
+
+        let err = parse_api_error(StatusCode::SERVICE_UNAVAILABLE, None, None, body);
+        assert_eq!(err.error_code(), ErrorCode::NodeUnreachable);
+        assert_eq!(
+            err.to_string(),
+            "API error: 503 - A storage node could not be reached [NODE_UNREACHABLE, request id abc123]"
+        );
+        assert!(err.is_retryable());
+    }
+
+    #[test]
+    fn test_parse_api_error_without_code() {
+        let err = parse_api_error(StatusCode::TOO_MANY_REQUESTS, None, None, "");
+        assert_eq!(err.error_code(), ErrorCode::RateLimited);
+        assert_eq!(err.to_string(), "API error: 429 - Too Many Requests");
+        assert!(err.is_retryable());
+    }
+
+    #[test]
+    fn test_parse_api_error_from_json() {
+        let body = r#"{"error": "Invalid refresh token", "code": "INVALID_TOKEN"}"#;
+        let err = parse_api_error(StatusCode::UNAUTHORIZED, None, None, body);
+        assert_eq!(err.to_string(), "API error: 401 - Invalid refresh token");
+        assert!(!err.is_retryable());
+    }
+
+    #[test]
+    fn test_extract_xml_value() {
+        let xml = "<Key>test/file.txt</Key>";
+        assert_eq!(
+            extract_xml_value(xml, "Key"),
+            Some("test/file.txt".to_string())
+        );
+    }
+}
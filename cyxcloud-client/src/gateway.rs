@@ -0,0 +1,1441 @@
+//! Gateway Client
+//!
+//! HTTP client for communicating with the CyxCloud gateway.
+//! Supports HTTPS with custom CA certificates and client certificates (mTLS),
+//! retries transient failures with backoff, and refreshes expired access
+//! tokens when a refresh token is configured.
+
+use crate::error::{api_error, extract_xml_value, ClientError, Result};
+use crate::retry::RetryPolicy;
+use crate::s3::{uri_encode, user_metadata, xml_unescape};
+use crate::types::{
+    AdoptChunksRequest, AdoptChunksResponse, ApiKey, BucketStats, CreateApiKeyRequest, DatasetInfo,
+    DeletedObject, FsckReport, FsckRequest, ListResponse, ObjectInfo, PublicDatasetInfo,
+    ReloadReport, SalvageChunksRequest, SalvageChunksResponse, ScanHistory, ShareResult,
+    StorageStats, TokenResponse, TrashListResponse, UserInfo, VerificationResult,
+};
+use bytes::Bytes;
+use futures::{Stream, StreamExt, TryStreamExt};
+use reqwest::header::{HeaderMap, AUTHORIZATION, CONTENT_LENGTH, CONTENT_TYPE, RANGE, RETRY_AFTER};
+use reqwest::{Body, Client, RequestBuilder, Response, StatusCode};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use std::time::Duration;
+use tokio::fs::File;
+use tokio::io::AsyncWriteExt;
+use tokio_util::io::ReaderStream;
+use tracing::{debug, warn};
+
+/// Default request timeout
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// TLS configuration for the gateway client
+#[derive(Debug, Clone, Default)]
+pub struct TlsConfig {
+    /// Path to CA certificate (PEM format) for verifying the gateway
+    pub ca_cert: Option<PathBuf>,
+    /// Path to client certificate (PEM format) for mTLS
+    pub client_cert: Option<PathBuf>,
+    /// Path to client private key (PEM format) for mTLS
+    pub client_key: Option<PathBuf>,
+    /// Skip server certificate verification (DANGEROUS - only for development)
+    pub danger_accept_invalid_certs: bool,
+}
+
+impl TlsConfig {
+    /// Create a new TLS config with only CA certificate
+    pub fn with_ca_cert(ca_cert: PathBuf) -> Self {
+        Self {
+            ca_cert: Some(ca_cert),
+            ..Default::default()
+        }
+    }
+
+    /// Create a full mTLS config
+    pub fn with_mtls(ca_cert: PathBuf, client_cert: PathBuf, client_key: PathBuf) -> Self {
+        Self {
+            ca_cert: Some(ca_cert),
+            client_cert: Some(client_cert),
+            client_key: Some(client_key),
+            danger_accept_invalid_certs: false,
+        }
+    }
+
+    /// Apply to a reqwest client builder
+    fn apply(&self, mut builder: reqwest::ClientBuilder) -> Result<reqwest::ClientBuilder> {
+        // Add CA certificate
+        if let Some(ref ca_path) = self.ca_cert {
+            let cert = reqwest::Certificate::from_pem(&std::fs::read(ca_path)?)?;
+            builder = builder.add_root_certificate(cert);
+        }
+
+        // Add client identity for mTLS
+        if let (Some(ref cert_path), Some(ref key_path)) = (&self.client_cert, &self.client_key) {
+            // Combine cert and key into a single PEM buffer
+            let mut identity_pem = std::fs::read(cert_path)?;
+            identity_pem.extend_from_slice(&std::fs::read(key_path)?);
+            builder = builder.identity(reqwest::Identity::from_pem(&identity_pem)?);
+        }
+
+        // Skip certificate verification (DANGEROUS)
+        if self.danger_accept_invalid_certs {
+            builder = builder.danger_accept_invalid_certs(true);
+        }
+
+        Ok(builder)
+    }
+}
+
+/// Builder for [`GatewayClient`]
+#[derive(Debug, Clone)]
+pub struct GatewayClientBuilder {
+    base_url: String,
+    access_token: Option<String>,
+    refresh_token: Option<String>,
+    tls: Option<TlsConfig>,
+    timeout: Duration,
+    retry: RetryPolicy,
+}
+
+impl GatewayClientBuilder {
+    /// Access token (JWT or API key) sent as `Authorization: Bearer`
+    pub fn access_token(mut self, token: impl Into<String>) -> Self {
+        self.access_token = Some(token.into());
+        self
+    }
+
+    /// Refresh token used to renew the access token after a 401
+    pub fn refresh_token(mut self, token: impl Into<String>) -> Self {
+        self.refresh_token = Some(token.into());
+        self
+    }
+
+    /// TLS settings (custom CA, mTLS)
+    pub fn tls(mut self, tls: TlsConfig) -> Self {
+        self.tls = Some(tls);
+        self
+    }
+
+    /// Per-request timeout
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Retry policy for replayable requests
+    pub fn retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Build the client
+    ///
+    /// Fails if a configured certificate or key cannot be read.
+    pub fn build(self) -> Result<GatewayClient> {
+        let mut builder = Client::builder().timeout(self.timeout);
+        if let Some(ref tls) = self.tls {
+            builder = tls.apply(builder)?;
+        }
+
+        Ok(GatewayClient {
+            client: builder.build()?,
+            base_url: self.base_url.trim_end_matches('/').to_string(),
+            access_token: RwLock::new(self.access_token),
+            refresh_token: self.refresh_token,
+            retry: self.retry,
+        })
+    }
+}
+
+/// Gateway client
+pub struct GatewayClient {
+    client: Client,
+    base_url: String,
+    access_token: RwLock<Option<String>>,
+    refresh_token: Option<String>,
+    retry: RetryPolicy,
+}
+
+impl GatewayClient {
+    /// Create a new gateway client without TLS
+    pub fn new(base_url: &str, auth_token: Option<String>) -> Self {
+        let mut builder = Self::builder(base_url);
+        builder.access_token = auth_token;
+        builder.build().expect("Failed to create HTTP client")
+    }
+
+    /// Start building a client for the gateway at `base_url`
+    pub fn builder(base_url: &str) -> GatewayClientBuilder {
+        GatewayClientBuilder {
+            base_url: base_url.to_string(),
+            access_token: None,
+            refresh_token: None,
+            tls: None,
+            timeout: DEFAULT_TIMEOUT,
+            retry: RetryPolicy::default(),
+        }
+    }
+
+    /// Gateway base URL
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Current access token
+    pub fn access_token(&self) -> Option<String> {
+        self.access_token
+            .read()
+            .expect("token lock poisoned")
+            .clone()
+    }
+
+    /// Replace the access token (e.g. after logging in)
+    pub fn set_access_token(&self, token: Option<String>) {
+        *self.access_token.write().expect("token lock poisoned") = token;
+    }
+
+    /// Authorization header value, if a token is set
+    fn auth_header(&self) -> Option<String> {
+        self.access_token().map(|t| format!("Bearer {}", t))
+    }
+
+    /// Send a request, retrying transient failures
+    ///
+    /// `build` is called for every attempt, so the body must be replayable.
+    /// Returns the final response whatever its status; 429/5xx responses are
+    /// only returned once retries are exhausted.
+    async fn send<F>(&self, build: F) -> Result<Response>
+    where
+        F: Fn(&Client) -> RequestBuilder,
+    {
+        let mut attempt = 0;
+        let mut refreshed = false;
+
+        loop {
+            let mut request = build(&self.client);
+            if let Some(auth) = self.auth_header() {
+                request = request.header(AUTHORIZATION, auth);
+            }
+
+            let (error, retry_after) = match request.send().await {
+                Ok(response) if response.status() == StatusCode::UNAUTHORIZED => {
+                    if refreshed || self.refresh_token.is_none() {
+                        return Ok(response);
+                    }
+                    refreshed = true;
+                    self.refresh_access_token().await?;
+                    continue;
+                }
+                Ok(response) => {
+                    let status = response.status();
+                    if !(status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error())
+                        || status == StatusCode::NOT_IMPLEMENTED
+                        || attempt >= self.retry.max_retries
+                    {
+                        return Ok(response);
+                    }
+                    let retry_after = retry_after_header(response.headers());
+                    (api_error(response).await, retry_after)
+                }
+                Err(e) => {
+                    let error = ClientError::Http(e);
+                    if !error.is_retryable() || attempt >= self.retry.max_retries {
+                        return Err(error);
+                    }
+                    (error, None)
+                }
+            };
+
+            attempt += 1;
+            let delay = retry_after
+                .unwrap_or_else(|| self.retry.backoff(attempt))
+                .min(self.retry.max_backoff);
+            warn!(
+                error = %error,
+                attempt,
+                delay_ms = delay.as_millis() as u64,
+                "Retrying gateway request"
+            );
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// Send a request whose body can't be replayed (streaming uploads)
+    async fn send_once(&self, mut request: RequestBuilder) -> Result<Response> {
+        if let Some(auth) = self.auth_header() {
+            request = request.header(AUTHORIZATION, auth);
+        }
+        Ok(request.send().await?)
+    }
+
+    /// Send a JSON request and decode a JSON response
+    async fn send_json<T, F>(&self, build: F, not_found: Option<&str>) -> Result<T>
+    where
+        T: serde::de::DeserializeOwned,
+        F: Fn(&Client) -> RequestBuilder,
+    {
+        let response = self.send(build).await?;
+        if response.status().is_success() {
+            Ok(response.json().await?)
+        } else if response.status() == StatusCode::NOT_FOUND && not_found.is_some() {
+            Err(ClientError::NotFound(
+                not_found.unwrap_or_default().to_string(),
+            ))
+        } else {
+            Err(api_error(response).await)
+        }
+    }
+
+    /// URL of an object
+    fn object_url(&self, bucket: &str, key: &str) -> String {
+        format!("{}/s3/{}/{}", self.base_url, bucket, key)
+    }
+
+    /// URL anyone can download a public object of `tenant` from
+    pub fn public_url(&self, tenant: &str, bucket: &str, key: &str) -> String {
+        format!("{}/public/{}/{}/{}", self.base_url, tenant, bucket, key)
+    }
+
+    /// Check gateway health
+    pub async fn health(&self) -> Result<bool> {
+        let url = format!("{}/health", self.base_url);
+        let response = self.send(|c| c.get(&url)).await?;
+        Ok(response.status().is_success())
+    }
+
+    // ==================== Buckets ====================
+
+    /// Create a bucket
+    pub async fn create_bucket(&self, bucket: &str) -> Result<()> {
+        let url = format!("{}/s3/{}", self.base_url, bucket);
+        let response = self.send(|c| c.put(&url)).await?;
+
+        if response.status().is_success() {
+            Ok(())
+        } else if response.status() == StatusCode::CONFLICT {
+            // Bucket already exists, that's fine
+            Ok(())
+        } else {
+            Err(api_error(response).await)
+        }
+    }
+
+    /// Delete an empty bucket
+    pub async fn delete_bucket(&self, bucket: &str) -> Result<()> {
+        let url = format!("{}/s3/{}", self.base_url, bucket);
+        let response = self.send(|c| c.delete(&url)).await?;
+
+        if response.status().is_success() {
+            Ok(())
+        } else if response.status() == StatusCode::NOT_FOUND {
+            Err(ClientError::NotFound(bucket.to_string()))
+        } else {
+            Err(api_error(response).await)
+        }
+    }
+
+    /// Whether a bucket exists
+    pub async fn bucket_exists(&self, bucket: &str) -> Result<bool> {
+        let url = format!("{}/s3/{}", self.base_url, bucket);
+        let response = self.send(|c| c.head(&url)).await?;
+
+        match response.status() {
+            s if s.is_success() => Ok(true),
+            StatusCode::NOT_FOUND => Ok(false),
+            _ => Err(api_error(response).await),
+        }
+    }
+
+    /// List objects in a bucket (first page)
+    pub async fn list_objects(
+        &self,
+        bucket: &str,
+        prefix: Option<&str>,
+        max_keys: Option<i32>,
+    ) -> Result<ListResponse> {
+        self.list_objects_page(bucket, prefix, max_keys, None).await
+    }
+
+    /// List one page of objects, continuing from `continuation_token`
+    pub async fn list_objects_page(
+        &self,
+        bucket: &str,
+        prefix: Option<&str>,
+        max_keys: Option<i32>,
+        continuation_token: Option<&str>,
+    ) -> Result<ListResponse> {
+        let url = format!("{}/s3/{}", self.base_url, bucket);
+
+        let mut params = vec![("list-type", "2".to_string())];
+        if let Some(p) = prefix {
+            params.push(("prefix", p.to_string()));
+        }
+        if let Some(m) = max_keys {
+            params.push(("max-keys", m.to_string()));
+        }
+        if let Some(token) = continuation_token {
+            params.push(("continuation-token", token.to_string()));
+        }
+
+        let response = self.send(|c| c.get(&url).query(&params)).await?;
+
+        if response.status().is_success() {
+            let text = response.text().await?;
+            Ok(parse_list_response(&text))
+        } else if response.status() == StatusCode::NOT_FOUND {
+            Err(ClientError::NotFound(bucket.to_string()))
+        } else {
+            Err(api_error(response).await)
+        }
+    }
+
+    /// List every object under a prefix, following continuation tokens
+    pub async fn list_all_objects(
+        &self,
+        bucket: &str,
+        prefix: Option<&str>,
+    ) -> Result<Vec<ObjectInfo>> {
+        let mut objects = Vec::new();
+        let mut token: Option<String> = None;
+
+        loop {
+            let page = self
+                .list_objects_page(bucket, prefix, None, token.as_deref())
+                .await?;
+            objects.extend(page.objects);
+
+            match page.next_token {
+                Some(next) if page.is_truncated => token = Some(next),
+                _ => return Ok(objects),
+            }
+        }
+    }
+
+    // ==================== Objects ====================
+
+    /// Upload an object from memory
+    pub async fn upload_file(
+        &self,
+        bucket: &str,
+        key: &str,
+        data: Bytes,
+        content_type: &str,
+    ) -> Result<String> {
+        let url = self.object_url(bucket, key);
+        let response = self
+            .send(|c| {
+                c.put(&url)
+                    .header(CONTENT_TYPE, content_type)
+                    .body(data.clone())
+            })
+            .await?;
+
+        put_response_etag(response).await
+    }
+
+    /// Upload an object from a stream of chunks
+    ///
+    /// The body is streamed to the gateway without buffering it in memory.
+    /// Streamed uploads are not retried.
+    pub async fn upload_stream<S>(
+        &self,
+        bucket: &str,
+        key: &str,
+        body: S,
+        content_length: Option<u64>,
+        content_type: &str,
+    ) -> Result<String>
+    where
+        S: Stream<Item = std::io::Result<Bytes>> + Send + Sync + 'static,
+    {
+        self.upload_stream_with_metadata(
+            bucket,
+            key,
+            body,
+            content_length,
+            content_type,
+            &BTreeMap::new(),
+        )
+        .await
+    }
+
+    /// Upload an object from a stream, attaching user metadata
+    ///
+    /// Each metadata entry is sent as an `x-amz-meta-<name>` header.
+    pub async fn upload_stream_with_metadata<S>(
+        &self,
+        bucket: &str,
+        key: &str,
+        body: S,
+        content_length: Option<u64>,
+        content_type: &str,
+        metadata: &BTreeMap<String, String>,
+    ) -> Result<String>
+    where
+        S: Stream<Item = std::io::Result<Bytes>> + Send + Sync + 'static,
+    {
+        let mut request = self
+            .client
+            .put(self.object_url(bucket, key))
+            .header(CONTENT_TYPE, content_type)
+            .body(Body::wrap_stream(body));
+        if let Some(length) = content_length {
+            request = request.header(CONTENT_LENGTH, length);
+        }
+        for (name, value) in metadata {
+            request = request.header(format!("x-amz-meta-{}", name), value);
+        }
+
+        let response = self.send_once(request).await?;
+        put_response_etag(response).await
+    }
+
+    /// Upload a local file, streaming it from disk
+    pub async fn upload_local_file(
+        &self,
+        bucket: &str,
+        key: &str,
+        path: &Path,
+    ) -> Result<(String, u64)> {
+        self.upload_local_file_with_progress(bucket, key, path, |_| {})
+            .await
+    }
+
+    /// Upload a local file, calling `on_progress` with the bytes read so far
+    ///
+    /// Progress counts bytes handed to the HTTP body, so it can run ahead of
+    /// what the gateway has acknowledged.
+    pub async fn upload_local_file_with_progress<F>(
+        &self,
+        bucket: &str,
+        key: &str,
+        path: &Path,
+        on_progress: F,
+    ) -> Result<(String, u64)>
+    where
+        F: Fn(u64) + Send + Sync + 'static,
+    {
+        let file = File::open(path).await?;
+        let size = file.metadata().await?.len();
+
+        // Guess content type
+        let content_type = mime_guess::from_path(path)
+            .first_or_octet_stream()
+            .to_string();
+
+        let mut sent = 0u64;
+        let body = ReaderStream::new(file).inspect_ok(move |chunk| {
+            sent += chunk.len() as u64;
+            on_progress(sent);
+        });
+        let etag = self
+            .upload_stream(bucket, key, body, Some(size), &content_type)
+            .await?;
+
+        Ok((etag, size))
+    }
+
+    /// Download an object into memory
+    pub async fn download_file(&self, bucket: &str, key: &str) -> Result<Bytes> {
+        let response = self.get_object(bucket, key).await?;
+        Ok(response.bytes().await?)
+    }
+
+    /// Download the inclusive byte range `start..=end` of an object
+    ///
+    /// The gateway only fetches the chunks covering the range. Ranges past
+    /// the end of the object are truncated to its size.
+    pub async fn download_range(
+        &self,
+        bucket: &str,
+        key: &str,
+        start: u64,
+        end: u64,
+    ) -> Result<Bytes> {
+        let url = self.object_url(bucket, key);
+        let range = format!("bytes={}-{}", start, end);
+        let response = self.send(|c| c.get(&url).header(RANGE, &range)).await?;
+
+        match response.status() {
+            StatusCode::PARTIAL_CONTENT => Ok(response.bytes().await?),
+            // Server ignored the Range header and sent the whole object
+            StatusCode::OK => {
+                let body = response.bytes().await?;
+                let len = body.len() as u64;
+                if start >= len {
+                    return Ok(Bytes::new());
+                }
+                Ok(body.slice(start as usize..end.saturating_add(1).min(len) as usize))
+            }
+            StatusCode::NOT_FOUND => Err(ClientError::NotFound(format!("{}/{}", bucket, key))),
+            _ => Err(api_error(response).await),
+        }
+    }
+
+    /// Download an object as a stream of chunks
+    pub async fn download_stream(
+        &self,
+        bucket: &str,
+        key: &str,
+    ) -> Result<impl Stream<Item = Result<Bytes>>> {
+        let response = self.get_object(bucket, key).await?;
+        Ok(response.bytes_stream().map_err(ClientError::Http))
+    }
+
+    /// Download to a local file, streaming it to disk
+    pub async fn download_to_file(&self, bucket: &str, key: &str, path: &Path) -> Result<u64> {
+        self.download_to_file_with_progress(bucket, key, path, |_| {})
+            .await
+    }
+
+    /// Download to a local file, calling `on_progress` with the bytes written so far
+    pub async fn download_to_file_with_progress(
+        &self,
+        bucket: &str,
+        key: &str,
+        path: &Path,
+        mut on_progress: impl FnMut(u64),
+    ) -> Result<u64> {
+        let mut stream = Box::pin(self.download_stream(bucket, key).await?);
+
+        let mut file = File::create(path).await?;
+        let mut size = 0u64;
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk?;
+            file.write_all(&chunk).await?;
+            size += chunk.len() as u64;
+            on_progress(size);
+        }
+        file.flush().await?;
+
+        Ok(size)
+    }
+
+    /// GET an object, mapping failures to errors
+    async fn get_object(&self, bucket: &str, key: &str) -> Result<Response> {
+        let url = self.object_url(bucket, key);
+        let response = self.send(|c| c.get(&url)).await?;
+
+        if response.status().is_success() {
+            Ok(response)
+        } else if response.status() == StatusCode::NOT_FOUND {
+            Err(ClientError::NotFound(format!("{}/{}", bucket, key)))
+        } else {
+            Err(api_error(response).await)
+        }
+    }
+
+    /// Get object metadata (HEAD request)
+    pub async fn head_object(&self, bucket: &str, key: &str) -> Result<ObjectInfo> {
+        let url = self.object_url(bucket, key);
+        let response = self.send(|c| c.head(&url)).await?;
+
+        if response.status().is_success() {
+            let headers = response.headers();
+            let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+
+            Ok(ObjectInfo {
+                key: key.to_string(),
+                size: header("content-length")
+                    .and_then(|s| s.parse().ok())
+                    .unwrap_or(0),
+                last_modified: header("last-modified").unwrap_or("").to_string(),
+                etag: header("etag").unwrap_or("").trim_matches('"').to_string(),
+                metadata: user_metadata(headers),
+            })
+        } else if response.status() == StatusCode::NOT_FOUND {
+            Err(ClientError::NotFound(format!("{}/{}", bucket, key)))
+        } else {
+            Err(api_error(response).await)
+        }
+    }
+
+    /// Copy an object, keeping its content type and user metadata
+    ///
+    /// Returns the copy's ETag.
+    pub async fn copy_object(
+        &self,
+        source_bucket: &str,
+        source_key: &str,
+        bucket: &str,
+        key: &str,
+    ) -> Result<String> {
+        self.send_copy(source_bucket, source_key, bucket, key, None)
+            .await
+    }
+
+    /// Copy an object, replacing its content type and user metadata
+    pub async fn copy_object_with_metadata(
+        &self,
+        source_bucket: &str,
+        source_key: &str,
+        bucket: &str,
+        key: &str,
+        content_type: &str,
+        metadata: &BTreeMap<String, String>,
+    ) -> Result<String> {
+        self.send_copy(
+            source_bucket,
+            source_key,
+            bucket,
+            key,
+            Some((content_type, metadata)),
+        )
+        .await
+    }
+
+    /// PUT with `x-amz-copy-source`; `replace` switches the metadata
+    /// directive to REPLACE
+    async fn send_copy(
+        &self,
+        source_bucket: &str,
+        source_key: &str,
+        bucket: &str,
+        key: &str,
+        replace: Option<(&str, &BTreeMap<String, String>)>,
+    ) -> Result<String> {
+        let url = self.object_url(bucket, key);
+        let source = uri_encode(&format!("{}/{}", source_bucket, source_key), false);
+
+        let response = self
+            .send(|c| {
+                let mut request = c.put(&url).header("x-amz-copy-source", &source);
+                if let Some((content_type, metadata)) = replace {
+                    request = request
+                        .header("x-amz-metadata-directive", "REPLACE")
+                        .header(CONTENT_TYPE, content_type);
+                    for (name, value) in metadata {
+                        request = request.header(format!("x-amz-meta-{}", name), value);
+                    }
+                }
+                request
+            })
+            .await?;
+
+        if response.status().is_success() {
+            let text = response.text().await?;
+            Ok(extract_xml_value(&text, "ETag")
+                .map(|etag| xml_unescape(&etag).trim_matches('"').to_string())
+                .unwrap_or_default())
+        } else if response.status() == StatusCode::NOT_FOUND {
+            Err(ClientError::NotFound(format!(
+                "{}/{}",
+                source_bucket, source_key
+            )))
+        } else {
+            Err(api_error(response).await)
+        }
+    }
+
+    /// Delete an object
+    pub async fn delete_object(&self, bucket: &str, key: &str) -> Result<()> {
+        let url = self.object_url(bucket, key);
+        let response = self.send(|c| c.delete(&url)).await?;
+
+        if response.status().is_success() || response.status() == StatusCode::NO_CONTENT {
+            Ok(())
+        } else {
+            Err(api_error(response).await)
+        }
+    }
+
+    // ==================== ACLs ====================
+
+    /// Let anyone read (`public`) the objects of a bucket, or stop doing so
+    pub async fn set_bucket_public(&self, bucket: &str, public: bool) -> Result<()> {
+        let url = format!("{}/s3/{}", self.base_url, bucket);
+        let response = self
+            .send(|c| {
+                c.put(&url)
+                    .query(&[("acl", "")])
+                    .header("x-amz-acl", canned_acl(public))
+            })
+            .await?;
+
+        if response.status().is_success() {
+            Ok(())
+        } else if response.status() == StatusCode::NOT_FOUND {
+            Err(ClientError::NotFound(bucket.to_string()))
+        } else {
+            Err(api_error(response).await)
+        }
+    }
+
+    /// Let anyone read (`public`) the current version of an object, or stop
+    /// doing so
+    pub async fn set_object_public(&self, bucket: &str, key: &str, public: bool) -> Result<()> {
+        let url = self.object_url(bucket, key);
+        let response = self
+            .send(|c| {
+                c.put(&url)
+                    .query(&[("acl", "")])
+                    .header("x-amz-acl", canned_acl(public))
+            })
+            .await?;
+
+        if response.status().is_success() {
+            Ok(())
+        } else if response.status() == StatusCode::NOT_FOUND {
+            Err(ClientError::NotFound(format!("{}/{}", bucket, key)))
+        } else {
+            Err(api_error(response).await)
+        }
+    }
+
+    // ==================== Export ====================
+
+    /// Export a bucket (or the keys under `prefix`) as a tar archive stream
+    ///
+    /// The archive holds every object as `{bucket}/{key}` followed by a
+    /// `manifest.json` of keys, sizes and hashes. A stream that ends in an
+    /// error is an incomplete archive.
+    pub async fn export_bucket(
+        &self,
+        bucket: &str,
+        prefix: Option<&str>,
+    ) -> Result<impl Stream<Item = Result<Bytes>>> {
+        let url = format!("{}/s3/{}", self.base_url, bucket);
+
+        let mut params = vec![("export", "tar".to_string())];
+        if let Some(p) = prefix {
+            params.push(("prefix", p.to_string()));
+        }
+
+        let response = self.send(|c| c.get(&url).query(&params)).await?;
+
+        if response.status().is_success() {
+            Ok(response.bytes_stream().map_err(ClientError::Http))
+        } else if response.status() == StatusCode::NOT_FOUND {
+            Err(ClientError::NotFound(bucket.to_string()))
+        } else {
+            Err(api_error(response).await)
+        }
+    }
+
+    /// Export a bucket to a local tar file, calling `on_progress` with the
+    /// bytes written so far
+    ///
+    /// The archive is written next to `path` and only renamed into place
+    /// once it is complete, so a failed export leaves no partial file.
+    pub async fn export_bucket_to_file(
+        &self,
+        bucket: &str,
+        prefix: Option<&str>,
+        path: &Path,
+        mut on_progress: impl FnMut(u64),
+    ) -> Result<u64> {
+        let mut stream = Box::pin(self.export_bucket(bucket, prefix).await?);
+
+        let mut partial = path.as_os_str().to_owned();
+        partial.push(".part");
+        let partial = PathBuf::from(partial);
+
+        let mut file = File::create(&partial).await?;
+        let mut size = 0u64;
+        let written: Result<()> = async {
+            while let Some(chunk) = stream.next().await {
+                let chunk = chunk?;
+                file.write_all(&chunk).await?;
+                size += chunk.len() as u64;
+                on_progress(size);
+            }
+            file.flush().await?;
+            Ok(())
+        }
+        .await;
+
+        if let Err(e) = written {
+            drop(file);
+            let _ = tokio::fs::remove_file(&partial).await;
+            return Err(e);
+        }
+        tokio::fs::rename(&partial, path).await?;
+
+        Ok(size)
+    }
+
+    // ==================== Trash ====================
+
+    /// List a bucket's deleted objects that can still be restored
+    pub async fn list_trash(
+        &self,
+        bucket: &str,
+        prefix: Option<&str>,
+        max_keys: Option<i32>,
+    ) -> Result<TrashListResponse> {
+        let url = format!("{}/s3/{}", self.base_url, bucket);
+
+        let mut params = vec![("deleted", String::new())];
+        if let Some(p) = prefix {
+            params.push(("prefix", p.to_string()));
+        }
+        if let Some(m) = max_keys {
+            params.push(("max-keys", m.to_string()));
+        }
+
+        let response = self.send(|c| c.get(&url).query(&params)).await?;
+
+        if response.status().is_success() {
+            let text = response.text().await?;
+            Ok(parse_trash_response(&text))
+        } else if response.status() == StatusCode::NOT_FOUND {
+            Err(ClientError::NotFound(bucket.to_string()))
+        } else {
+            Err(api_error(response).await)
+        }
+    }
+
+    /// Restore a deleted object, optionally a specific deleted version
+    ///
+    /// Returns the restored object's ETag.
+    pub async fn restore_object(
+        &self,
+        bucket: &str,
+        key: &str,
+        version_id: Option<&str>,
+    ) -> Result<String> {
+        let url = self.object_url(bucket, key);
+
+        let mut params = vec![("restore", String::new())];
+        if let Some(version) = version_id {
+            params.push(("versionId", version.to_string()));
+        }
+
+        let response = self.send(|c| c.post(&url).query(&params)).await?;
+
+        if response.status().is_success() {
+            Ok(response
+                .headers()
+                .get("etag")
+                .and_then(|v| v.to_str().ok())
+                .unwrap_or_default()
+                .trim_matches('"')
+                .to_string())
+        } else if response.status() == StatusCode::NOT_FOUND {
+            Err(ClientError::NotFound(format!("{}/{}", bucket, key)))
+        } else {
+            Err(api_error(response).await)
+        }
+    }
+
+    // ==================== Dataset API ====================
+
+    /// List user's datasets
+    pub async fn list_datasets(
+        &self,
+        include_shared: bool,
+        limit: i32,
+    ) -> Result<Vec<DatasetInfo>> {
+        let url = format!("{}/api/datasets", self.base_url);
+        let query = [
+            ("include_shared", include_shared.to_string()),
+            ("limit", limit.to_string()),
+        ];
+        self.send_json(|c| c.get(&url).query(&query), None).await
+    }
+
+    /// List public datasets
+    pub async fn list_public_datasets(
+        &self,
+        name_filter: Option<&str>,
+    ) -> Result<Vec<PublicDatasetInfo>> {
+        let url = format!("{}/api/datasets/public", self.base_url);
+        let query: Vec<(&str, &str)> = name_filter.map(|f| ("filter", f)).into_iter().collect();
+        self.send_json(|c| c.get(&url).query(&query), None).await
+    }
+
+    /// Create a dataset from files in a bucket
+    pub async fn create_dataset(
+        &self,
+        name: &str,
+        description: Option<&str>,
+        bucket: &str,
+        prefix: Option<&str>,
+    ) -> Result<DatasetInfo> {
+        let url = format!("{}/api/datasets", self.base_url);
+
+        #[derive(Serialize)]
+        struct CreateDatasetRequest<'a> {
+            name: &'a str,
+            description: Option<&'a str>,
+            bucket: &'a str,
+            prefix: Option<&'a str>,
+        }
+
+        let body = CreateDatasetRequest {
+            name,
+            description,
+            bucket,
+            prefix,
+        };
+        self.send_json(|c| c.post(&url).json(&body), None).await
+    }
+
+    /// Verify a dataset's integrity
+    pub async fn verify_dataset(
+        &self,
+        dataset_id: &str,
+        check_public: bool,
+        full_verification: bool,
+    ) -> Result<VerificationResult> {
+        let url = format!("{}/api/datasets/{}/verify", self.base_url, dataset_id);
+        let query = [
+            ("check_public", check_public.to_string()),
+            ("full", full_verification.to_string()),
+        ];
+        self.send_json(|c| c.post(&url).query(&query), Some(dataset_id))
+            .await
+    }
+
+    /// Share a dataset with another user
+    pub async fn share_dataset(
+        &self,
+        dataset_id: &str,
+        share_with: &str,
+        permissions: &[String],
+    ) -> Result<ShareResult> {
+        let url = format!("{}/api/datasets/{}/share", self.base_url, dataset_id);
+
+        #[derive(Serialize)]
+        struct ShareRequest<'a> {
+            share_with: &'a str,
+            permissions: &'a [String],
+        }
+
+        let body = ShareRequest {
+            share_with,
+            permissions,
+        };
+        self.send_json(|c| c.post(&url).json(&body), Some(dataset_id))
+            .await
+    }
+
+    /// Get detailed dataset information
+    pub async fn get_dataset_info(&self, dataset_id: &str) -> Result<DatasetInfo> {
+        let url = format!("{}/api/datasets/{}", self.base_url, dataset_id);
+        self.send_json(|c| c.get(&url), Some(dataset_id)).await
+    }
+
+    // ==================== Tokens ====================
+
+    /// Exchange the refresh token for a new access token
+    ///
+    /// Called automatically when a request is rejected with 401.
+    pub async fn refresh_access_token(&self) -> Result<TokenResponse> {
+        let refresh_token = self.refresh_token.as_deref().ok_or_else(|| {
+            ClientError::InvalidResponse("no refresh token configured".to_string())
+        })?;
+
+        let url = format!("{}/api/v1/auth/refresh", self.base_url);
+        let response = self
+            .client
+            .post(&url)
+            .json(&serde_json::json!({ "refresh_token": refresh_token }))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(api_error(response).await);
+        }
+
+        let tokens: TokenResponse = response.json().await?;
+        self.set_access_token(Some(tokens.access_token.clone()));
+        debug!(user_id = %tokens.user_id, "Access token refreshed");
+        Ok(tokens)
+    }
+
+    /// Create an API key for the current user
+    pub async fn create_api_key(&self, request: &CreateApiKeyRequest) -> Result<ApiKey> {
+        let url = format!("{}/api/v1/auth/api-keys", self.base_url);
+        self.send_json(|c| c.post(&url).json(request), None).await
+    }
+
+    /// Identity and permissions of the current token
+    pub async fn whoami(&self) -> Result<UserInfo> {
+        let url = format!("{}/api/v1/auth/me", self.base_url);
+        self.send_json(|c| c.get(&url), None).await
+    }
+
+    /// Revoke the current access token
+    pub async fn logout(&self) -> Result<()> {
+        let url = format!("{}/api/v1/auth/logout", self.base_url);
+        let response = self.send(|c| c.post(&url)).await?;
+
+        if response.status().is_success() {
+            self.set_access_token(None);
+            Ok(())
+        } else {
+            Err(api_error(response).await)
+        }
+    }
+
+    // ==================== Statistics ====================
+
+    /// Object counts and bytes stored across the caller's buckets
+    pub async fn storage_stats(&self) -> Result<StorageStats> {
+        let url = format!("{}/api/v1/stats", self.base_url);
+        self.send_json(|c| c.get(&url), None).await
+    }
+
+    /// Object count and bytes stored in one bucket
+    pub async fn bucket_stats(&self, bucket: &str) -> Result<BucketStats> {
+        let url = format!("{}/api/v1/stats/{}", self.base_url, bucket);
+        self.send_json(|c| c.get(&url), Some(bucket)).await
+    }
+
+    // ==================== Integrity ====================
+
+    /// Verify the chunks of the objects in a bucket, optionally queueing repairs
+    pub async fn fsck(&self, bucket: &str, request: &FsckRequest) -> Result<FsckReport> {
+        let url = format!("{}/api/v1/fsck/{}", self.base_url, bucket);
+        self.send_json(|c| c.post(&url).json(request), Some(bucket))
+            .await
+    }
+
+    // ==================== Node admin ====================
+
+    /// Export cluster topology (admin only)
+    ///
+    /// `format` is `json` or `dot`; the raw document is returned.
+    pub async fn get_topology(&self, format: &str) -> Result<String> {
+        let url = format!("{}/api/v1/admin/topology", self.base_url);
+        let response = self
+            .send(|c| c.get(&url).query(&[("format", format)]))
+            .await?;
+
+        if response.status().is_success() {
+            Ok(response.text().await?)
+        } else {
+            Err(api_error(response).await)
+        }
+    }
+
+    /// Reload the gateway configuration file (admin only)
+    pub async fn reload_config(&self) -> Result<ReloadReport> {
+        let url = format!("{}/api/v1/admin/config/reload", self.base_url);
+        self.send_json(|c| c.post(&url), None).await
+    }
+
+    /// Move chunk locations to a node after an offline import (admin only)
+    pub async fn adopt_chunks(
+        &self,
+        node_id: &str,
+        request: &AdoptChunksRequest,
+    ) -> Result<AdoptChunksResponse> {
+        let url = format!(
+            "{}/api/v1/admin/nodes/{}/chunks/adopt",
+            self.base_url, node_id
+        );
+        self.send_json(|c| c.post(&url).json(request), Some(node_id))
+            .await
+    }
+
+    /// Restore a node's chunk locations from a salvage report (admin only)
+    pub async fn salvage_chunks(
+        &self,
+        node_id: &str,
+        request: &SalvageChunksRequest,
+    ) -> Result<SalvageChunksResponse> {
+        let url = format!(
+            "{}/api/v1/admin/nodes/{}/chunks/salvage",
+            self.base_url, node_id
+        );
+        self.send_json(|c| c.post(&url).json(request), Some(node_id))
+            .await
+    }
+
+    /// Rebalancer scan history over the last `hours` (admin only)
+    pub async fn get_scan_history(&self, hours: u32) -> Result<ScanHistory> {
+        let url = format!("{}/api/v1/cluster/scans", self.base_url);
+        self.send_json(|c| c.get(&url).query(&[("hours", hours)]), None)
+            .await
+    }
+}
+
+/// ETag of a successful PUT, or the API error
+async fn put_response_etag(response: Response) -> Result<String> {
+    if response.status().is_success() {
+        Ok(response
+            .headers()
+            .get("etag")
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("")
+            .trim_matches('"')
+            .to_string())
+    } else {
+        Err(api_error(response).await)
+    }
+}
+
+/// Delay requested by a `Retry-After: <seconds>` header
+fn retry_after_header(headers: &HeaderMap) -> Option<Duration> {
+    headers
+        .get(RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+        .map(Duration::from_secs)
+}
+
+/// Parse S3 ListBucketResult XML (simplified)
+pub(crate) fn parse_list_response(xml: &str) -> ListResponse {
+    let mut objects = Vec::new();
+
+    let mut rest = xml;
+    while let Some(pos) = rest.find("<Contents>") {
+        let block = &rest[pos..];
+        let Some(end) = block.find("</Contents>") else {
+            break;
+        };
+        let block_end = end + "</Contents>".len();
+        let contents = &block[..block_end];
+
+        if let (Some(key), Some(size)) = (
+            extract_xml_value(contents, "Key"),
+            extract_xml_value(contents, "Size"),
+        ) {
+            objects.push(ObjectInfo {
+                key: xml_unescape(&key),
+                size: size.parse().unwrap_or(0),
+                last_modified: extract_xml_value(contents, "LastModified").unwrap_or_default(),
+                etag: xml_unescape(&extract_xml_value(contents, "ETag").unwrap_or_default())
+                    .trim_matches('"')
+                    .to_string(),
+                metadata: BTreeMap::new(),
+            });
+        }
+
+        rest = &block[block_end..];
+    }
+
+    ListResponse {
+        objects,
+        is_truncated: extract_xml_value(xml, "IsTruncated").as_deref() == Some("true"),
+        next_token: extract_xml_value(xml, "NextContinuationToken").map(|t| xml_unescape(&t)),
+    }
+}
+
+/// Parse a `ListDeletedObjectsResult` XML document
+pub(crate) fn parse_trash_response(xml: &str) -> TrashListResponse {
+    let mut objects = Vec::new();
+
+    let mut rest = xml;
+    while let Some(pos) = rest.find("<DeletedObject>") {
+        let block = &rest[pos..];
+        let Some(end) = block.find("</DeletedObject>") else {
+            break;
+        };
+        let block_end = end + "</DeletedObject>".len();
+        let entry = &block[..block_end];
+
+        if let (Some(key), Some(version_id)) = (
+            extract_xml_value(entry, "Key"),
+            extract_xml_value(entry, "VersionId"),
+        ) {
+            objects.push(DeletedObject {
+                key: xml_unescape(&key),
+                version_id,
+                deleted_at: extract_xml_value(entry, "DeletedAt").unwrap_or_default(),
+                size: extract_xml_value(entry, "Size")
+                    .and_then(|s| s.parse().ok())
+                    .unwrap_or(0),
+                etag: extract_xml_value(entry, "ETag").unwrap_or_default(),
+            });
+        }
+
+        rest = &block[block_end..];
+    }
+
+    TrashListResponse {
+        objects,
+        is_truncated: extract_xml_value(xml, "IsTruncated").as_deref() == Some("true"),
+    }
+}
+
+/// `x-amz-acl` value making a resource public or private
+fn canned_acl(public: bool) -> &'static str {
+    if public {
+        "public-read"
+    } else {
+        "private"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::StatusCode as AxumStatus;
+    use axum::response::IntoResponse;
+    use axum::routing::get;
+    use axum::Router;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+
+    /// Serve `router` on a random local port, returning its base URL
+    async fn serve(router: Router) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+        format!("http://{}", addr)
+    }
+
+    fn fast_retry() -> RetryPolicy {
+        RetryPolicy {
+            initial_backoff: Duration::from_millis(1),
+            jitter: false,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_parse_list_response() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<ListBucketResult>
+  <Name>test-bucket</Name>
+  <IsTruncated>true</IsTruncated>
+  <Contents>
+    <Key>file1.txt</Key>
+    <Size>1024</Size>
+    <LastModified>2024-01-01T00:00:00Z</LastModified>
+    <ETag>"abc123"</ETag>
+  </Contents>
+  <Contents>
+    <Key>file2&amp;3.txt</Key>
+    <Size>2048</Size>
+    <LastModified>2024-01-02T00:00:00Z</LastModified>
+    <ETag>&quot;def456&quot;</ETag>
+  </Contents>
+  <NextContinuationToken>file2.txt</NextContinuationToken>
+</ListBucketResult>"#;
+
+        let result = parse_list_response(xml);
+        assert_eq!(result.objects.len(), 2);
+        assert_eq!(result.objects[0].key, "file1.txt");
+        assert_eq!(result.objects[0].size, 1024);
+        assert_eq!(result.objects[1].key, "file2&3.txt");
+        assert_eq!(result.objects[1].etag, "def456");
+        assert!(result.is_truncated);
+        assert_eq!(result.next_token.as_deref(), Some("file2.txt"));
+    }
+
+    #[test]
+    fn test_parse_trash_response() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<ListDeletedObjectsResult>
+  <Name>test-bucket</Name>
+  <IsTruncated>false</IsTruncated>
+  <DeletedObject>
+    <Key>a&amp;b.txt</Key>
+    <VersionId>8f0e3a2c-5d41-4e8b-9a57-0c3f1d2b7e64</VersionId>
+    <DeletedAt>2024-01-15T12:00:00+00:00</DeletedAt>
+    <ETag>abc123</ETag>
+    <Size>42</Size>
+  </DeletedObject>
+</ListDeletedObjectsResult>"#;
+
+        let result = parse_trash_response(xml);
+        assert_eq!(result.objects.len(), 1);
+        assert_eq!(result.objects[0].key, "a&b.txt");
+        assert_eq!(
+            result.objects[0].version_id,
+            "8f0e3a2c-5d41-4e8b-9a57-0c3f1d2b7e64"
+        );
+        assert_eq!(result.objects[0].size, 42);
+        assert!(!result.is_truncated);
+    }
+
+    #[tokio::test]
+    async fn test_retries_transient_errors() {
+        let calls = Arc::new(AtomicU32::new(0));
+        let counter = calls.clone();
+        let router = Router::new().route(
+            "/s3/bucket/key",
+            get(move || {
+                let counter = counter.clone();
+                async move {
+                    if counter.fetch_add(1, Ordering::SeqCst) < 2 {
+                        (AxumStatus::SERVICE_UNAVAILABLE, "busy").into_response()
+                    } else {
+                        "payload".into_response()
+                    }
+                }
+            }),
+        );
+        let client = GatewayClient::builder(&serve(router).await)
+            .retry(fast_retry())
+            .build()
+            .unwrap();
+
+        let data = client.download_file("bucket", "key").await.unwrap();
+        assert_eq!(data.as_ref(), b"payload");
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        let err = GatewayClient::builder(client.base_url())
+            .retry(RetryPolicy::none())
+            .build()
+            .unwrap()
+            .download_file("bucket", "missing")
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ClientError::NotFound(_)));
+    }
+
+    #[tokio::test]
+    async fn test_download_range_slices_full_response() {
+        // A server that ignores Range still yields just the requested bytes
+        let router = Router::new().route("/s3/bucket/key", get(|| async { "0123456789" }));
+        let client = GatewayClient::builder(&serve(router).await)
+            .build()
+            .unwrap();
+
+        let data = client.download_range("bucket", "key", 2, 5).await.unwrap();
+        assert_eq!(data.as_ref(), b"2345");
+        let tail = client
+            .download_range("bucket", "key", 8, 100)
+            .await
+            .unwrap();
+        assert_eq!(tail.as_ref(), b"89");
+        let past = client
+            .download_range("bucket", "key", 20, 30)
+            .await
+            .unwrap();
+        assert!(past.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_refreshes_expired_token() {
+        let router = Router::new()
+            .route(
+                "/api/v1/auth/refresh",
+                axum::routing::post(|| async {
+                    axum::Json(serde_json::json!({
+                        "access_token": "fresh",
+                        "token_type": "Bearer",
+                        "expires_in": 3600,
+                        "user_id": "user-1",
+                    }))
+                }),
+            )
+            .route(
+                "/health",
+                get(|headers: axum::http::HeaderMap| async move {
+                    match headers.get("authorization").and_then(|v| v.to_str().ok()) {
+                        Some("Bearer fresh") => AxumStatus::OK,
+                        _ => AxumStatus::UNAUTHORIZED,
+                    }
+                }),
+            );
+        let client = GatewayClient::builder(&serve(router).await)
+            .access_token("expired")
+            .refresh_token("refresh")
+            .build()
+            .unwrap();
+
+        assert!(client.health().await.unwrap());
+        assert_eq!(client.access_token().as_deref(), Some("fresh"));
+    }
+}
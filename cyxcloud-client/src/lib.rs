@@ -0,0 +1,47 @@
+//! CyxCloud Client SDK
+//!
+//! Typed Rust client for the CyxCloud gateway:
+//! - Buckets and objects over the S3-compatible API, with streaming
+//!   uploads and downloads
+//! - Datasets (create, verify, share)
+//! - Tokens (refresh, API keys, identity)
+//! - Node administration (topology, config reload, chunk adoption)
+//! - Reading S3-compatible buckets for imports ([`S3Client`])
+//!
+//! Replayable requests are retried on transient failures with exponential
+//! backoff, and an expired access token is renewed automatically when a
+//! refresh token is configured.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use cyxcloud_client::GatewayClient;
+//!
+//! # async fn example() -> cyxcloud_client::Result<()> {
+//! let client = GatewayClient::builder("https://gateway.example.com")
+//!     .access_token("eyJ...")
+//!     .build()?;
+//!
+//! client.create_bucket("models").await?;
+//! let (etag, size) = client
+//!     .upload_local_file("models", "v1/weights.bin", "weights.bin".as_ref())
+//!     .await?;
+//! println!("uploaded {} bytes, etag {}", size, etag);
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod cyxwiz;
+pub mod error;
+pub mod gateway;
+pub mod retry;
+pub mod s3;
+pub mod types;
+
+pub use cyxcloud_core::error::{ErrorCode, HasErrorCode};
+pub use cyxwiz::{CyxWizClient, CyxWizError};
+pub use error::{ClientError, Result};
+pub use gateway::{GatewayClient, GatewayClientBuilder, TlsConfig};
+pub use retry::RetryPolicy;
+pub use s3::{S3Client, S3Credentials, S3Object};
+pub use types::*;
@@ -0,0 +1,88 @@
+//! Retry with exponential backoff
+//!
+//! Requests whose body can be replayed are retried on transient failures
+//! (see [`ClientError::is_retryable`](crate::ClientError::is_retryable)).
+//! Streaming uploads are sent once, since their body is consumed.
+
+use rand::Rng;
+use std::time::Duration;
+
+/// Retry policy for gateway requests
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt (0 disables retries)
+    pub max_retries: u32,
+    /// Delay before the first retry
+    pub initial_backoff: Duration,
+    /// Upper bound for a single delay
+    pub max_backoff: Duration,
+    /// Factor applied to the delay after each retry
+    pub multiplier: f64,
+    /// Randomize delays by up to +/-25% so clients don't retry in lockstep
+    pub jitter: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(200),
+            max_backoff: Duration::from_secs(10),
+            multiplier: 2.0,
+            jitter: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Policy that never retries
+    pub fn none() -> Self {
+        Self {
+            max_retries: 0,
+            ..Default::default()
+        }
+    }
+
+    /// Delay before retry number `attempt` (starting at 1)
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(32) as i32;
+        let delay = self.initial_backoff.as_secs_f64() * self.multiplier.powi(exponent);
+        let delay = delay.min(self.max_backoff.as_secs_f64());
+
+        let delay = if self.jitter {
+            delay * rand::thread_rng().gen_range(0.75..=1.25)
+        } else {
+            delay
+        };
+
+        Duration::from_secs_f64(delay.min(self.max_backoff.as_secs_f64()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_grows_and_caps() {
+        let policy = RetryPolicy {
+            jitter: false,
+            ..Default::default()
+        };
+
+        assert_eq!(policy.backoff(1), Duration::from_millis(200));
+        assert_eq!(policy.backoff(2), Duration::from_millis(400));
+        assert_eq!(policy.backoff(3), Duration::from_millis(800));
+        assert_eq!(policy.backoff(20), Duration::from_secs(10));
+    }
+
+    #[test]
+    fn test_backoff_jitter_bounds() {
+        let policy = RetryPolicy::default();
+        for _ in 0..100 {
+            let delay = policy.backoff(2);
+            assert!(delay >= Duration::from_millis(300));
+            assert!(delay <= Duration::from_millis(500));
+        }
+    }
+}
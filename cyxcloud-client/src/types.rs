@@ -0,0 +1,309 @@
+//! Request and response types for the gateway API
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Object metadata
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ObjectInfo {
+    pub key: String,
+    pub size: u64,
+    pub last_modified: String,
+    pub etag: String,
+    /// User metadata (`x-amz-meta-*`); only filled in by `head_object`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub metadata: BTreeMap<String, String>,
+}
+
+/// List objects response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListResponse {
+    pub objects: Vec<ObjectInfo>,
+    pub is_truncated: bool,
+    /// Token for fetching the next page, if truncated
+    pub next_token: Option<String>,
+}
+
+/// Restorable deleted object
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeletedObject {
+    pub key: String,
+    /// Version to restore, for keys deleted more than once
+    pub version_id: String,
+    pub deleted_at: String,
+    pub size: u64,
+    pub etag: String,
+}
+
+/// Trash listing response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrashListResponse {
+    pub objects: Vec<DeletedObject>,
+    pub is_truncated: bool,
+}
+
+/// Storage status
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StorageStatus {
+    pub total_files: u64,
+    pub total_bytes: u64,
+    pub buckets: Vec<BucketInfo>,
+}
+
+/// Bucket info
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BucketInfo {
+    pub name: String,
+    pub object_count: u64,
+    pub total_size: u64,
+}
+
+/// Dataset info
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatasetInfo {
+    pub id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub owner_id: String,
+    pub file_count: i64,
+    pub size_bytes: i64,
+    pub content_hash: Vec<u8>,
+    pub trust_level: i32,
+    pub version: String,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// Public dataset info
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PublicDatasetInfo {
+    pub id: String,
+    pub name: String,
+    pub version: String,
+    pub license: Option<String>,
+    pub verified_by: Vec<String>,
+    pub cached: bool,
+}
+
+/// Dataset verification result
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VerificationResult {
+    pub manifest_valid: bool,
+    pub all_files_valid: bool,
+    pub files_verified: i32,
+    pub files_failed: i32,
+    pub trust_level: i32,
+    pub public_match: Option<PublicDatasetMatch>,
+}
+
+/// Public dataset match
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PublicDatasetMatch {
+    pub name: String,
+    pub version: String,
+    pub verified_by: Vec<String>,
+    pub license: Option<String>,
+}
+
+/// Dataset share result
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShareResult {
+    pub success: bool,
+    pub share_id: String,
+}
+
+/// Tokens issued by the gateway (`/api/v1/auth/*`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenResponse {
+    pub access_token: String,
+    #[serde(default)]
+    pub refresh_token: Option<String>,
+    pub token_type: String,
+    /// Access token lifetime in seconds
+    pub expires_in: i64,
+    pub user_id: String,
+}
+
+/// API key creation request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateApiKeyRequest {
+    /// Name for the key (alphanumeric, dash, underscore)
+    pub name: String,
+    /// Permissions to grant; empty inherits the caller's permissions
+    #[serde(default)]
+    pub permissions: Vec<String>,
+    /// Expiration in days (gateway default 365)
+    pub expires_in_days: Option<i64>,
+}
+
+/// Created API key
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiKey {
+    pub name: String,
+    pub api_key: String,
+    pub expires_in_days: i64,
+}
+
+/// Identity of the current token
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserInfo {
+    pub user_id: String,
+    pub user_type: String,
+    pub wallet: Option<String>,
+    pub permissions: Vec<String>,
+    /// Tenant of the user's buckets (not sent by older gateways)
+    #[serde(default)]
+    pub tenant: Option<String>,
+}
+
+/// Outcome of a gateway configuration reload
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReloadReport {
+    /// Sections that changed and were applied
+    pub applied: Vec<String>,
+    /// Sections that changed but only take effect after a restart
+    pub restart_required: Vec<String>,
+}
+
+/// Request to re-associate chunks imported on a node
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdoptChunksRequest {
+    /// Node the chunks were exported from (UUID or peer ID)
+    pub from_node_id: String,
+    /// Hex-encoded chunk IDs
+    pub chunk_ids: Vec<String>,
+}
+
+/// Result of a chunk re-association
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdoptChunksResponse {
+    pub node_id: String,
+    pub requested: usize,
+    pub adopted: u64,
+}
+
+/// One shard of a `cyxcloud-node salvage` report
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SalvagedShard {
+    /// Hex-encoded chunk ID
+    pub chunk_id: String,
+    pub file_id: String,
+    pub chunk_index: u32,
+    pub shard_index: u32,
+    pub is_parity: bool,
+    /// Shard size in bytes
+    pub size: u64,
+}
+
+/// Salvage report written by `cyxcloud-node salvage`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SalvageChunksRequest {
+    pub shards: Vec<SalvagedShard>,
+}
+
+/// Result of a chunk location salvage
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SalvageChunksResponse {
+    pub node_id: String,
+    pub requested: usize,
+    /// Locations recorded again
+    pub restored: u64,
+    /// Locations that were still recorded
+    pub known: u64,
+    /// Shards of files that no longer exist
+    pub orphaned: u64,
+}
+
+/// Object count and bytes stored in one bucket
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BucketStats {
+    pub bucket: String,
+    pub object_count: i64,
+    pub total_bytes: i64,
+    pub created_at: String,
+}
+
+/// Totals across all of the caller's buckets
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StorageStats {
+    pub bucket_count: usize,
+    pub object_count: i64,
+    pub total_bytes: i64,
+    pub buckets: Vec<BucketStats>,
+}
+
+/// Options of an object integrity check
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FsckRequest {
+    /// Only check keys starting with this prefix
+    pub prefix: Option<String>,
+    /// Queue repairs for degraded objects
+    pub repair: bool,
+    /// Also list healthy objects
+    pub verbose: bool,
+}
+
+/// Integrity of one checked object
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FsckObject {
+    pub key: String,
+    pub size: i64,
+    /// `healthy`, `degraded` or `lost`
+    pub health: String,
+    pub shards: usize,
+    pub damaged_shards: usize,
+    pub corrupt_copies: usize,
+    pub unavailable_copies: usize,
+    pub repairs_queued: usize,
+}
+
+/// Result of an object integrity check
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FsckReport {
+    pub bucket: String,
+    pub prefix: Option<String>,
+    pub checked: usize,
+    pub healthy: usize,
+    pub degraded: usize,
+    pub lost: usize,
+    /// Only the first 10,000 objects were checked
+    pub truncated: bool,
+    pub repairs_queued: usize,
+    pub copies_dropped: u64,
+    /// Degraded and lost objects (all objects when verbose)
+    pub objects: Vec<FsckObject>,
+}
+
+/// Summary of one rebalancer scan
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanSummary {
+    pub scanned_at: String,
+    pub duration_ms: i64,
+    pub chunks_scanned: i64,
+    pub under_replicated: i64,
+    pub critical: i64,
+    pub over_replicated: i64,
+    pub orphaned: i64,
+    pub corrupt: i64,
+    pub incidents: i64,
+    pub bytes_at_risk: i64,
+}
+
+/// Under-replication trend over a scan window
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanTrend {
+    /// `converging`, `diverging`, `steady` or `unknown`
+    pub direction: String,
+    pub under_replicated_change: i64,
+    pub bytes_at_risk_change: i64,
+    pub peak_under_replicated: i64,
+}
+
+/// Rebalancer scan history
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanHistory {
+    pub hours: i64,
+    pub trend: ScanTrend,
+    /// Oldest first
+    pub scans: Vec<ScanSummary>,
+}
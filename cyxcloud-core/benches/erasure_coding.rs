@@ -1,9 +1,10 @@
 //! Benchmarks for Reed-Solomon erasure coding
 //!
 //! Run with: cargo bench --package cyxcloud-core
+//! Compare backends with: cargo bench --package cyxcloud-core --features rs-simd -- backend
 
 use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
-use cyxcloud_core::erasure::{ErasureConfig, ErasureEncoder};
+use cyxcloud_core::erasure::{ErasureBackend, ErasureConfig, ErasureEncoder, ShardData};
 
 /// Generate test data of specified size
 fn generate_data(size: usize) -> Vec<u8> {
@@ -135,6 +136,57 @@ fn bench_seq_vs_parallel(c: &mut Criterion) {
     group.finish();
 }
 
+/// Compare every compiled-in backend across chunk sizes
+fn bench_backends(c: &mut Criterion) {
+    let sizes = [
+        64 * 1024,        // 64 KB
+        1024 * 1024,      // 1 MB
+        4 * 1024 * 1024,  // 4 MB (default chunk size)
+        16 * 1024 * 1024, // 16 MB
+    ];
+
+    let mut encode_group = c.benchmark_group("backend_encode");
+    for backend in ErasureBackend::available() {
+        let encoder = ErasureEncoder::with_backend(ErasureConfig::default(), backend).unwrap();
+        for size in sizes {
+            let data = generate_data(size);
+            encode_group.throughput(Throughput::Bytes(size as u64));
+            encode_group.bench_with_input(
+                BenchmarkId::new(backend.as_str(), format!("{}KB", size / 1024)),
+                &data,
+                |b, data| b.iter(|| encoder.encode(black_box(data))),
+            );
+        }
+    }
+    encode_group.finish();
+
+    // Reconstruction with the maximum number of lost data shards
+    let mut decode_group = c.benchmark_group("backend_decode_4_missing");
+    for backend in ErasureBackend::available() {
+        let encoder = ErasureEncoder::with_backend(ErasureConfig::default(), backend).unwrap();
+        for size in sizes {
+            let data = generate_data(size);
+            let mut shard_opts: Vec<Option<ShardData>> = encoder
+                .encode(&data)
+                .unwrap()
+                .into_iter()
+                .map(Some)
+                .collect();
+            for i in [0, 3, 6, 9] {
+                shard_opts[i] = None;
+            }
+
+            decode_group.throughput(Throughput::Bytes(size as u64));
+            decode_group.bench_with_input(
+                BenchmarkId::new(backend.as_str(), format!("{}KB", size / 1024)),
+                &shard_opts,
+                |b, shard_opts| b.iter(|| encoder.decode(black_box(shard_opts), size)),
+            );
+        }
+    }
+    decode_group.finish();
+}
+
 criterion_group!(
     benches,
     bench_encode,
@@ -142,5 +194,6 @@ criterion_group!(
     bench_decode,
     bench_verify,
     bench_seq_vs_parallel,
+    bench_backends,
 );
 criterion_main!(benches);
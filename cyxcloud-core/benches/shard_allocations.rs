@@ -0,0 +1,193 @@
+//! Allocation benchmark for the shard data path
+//!
+//! Counts the bytes allocated while splitting, encoding and decoding an
+//! object, comparing the `Bytes` path (chunks and data shards are slices of
+//! the upload, shards are handed to gRPC as-is) with a copying baseline that
+//! mirrors the previous `Vec<u8>` round trips.
+//!
+//! Run with: cargo bench --package cyxcloud-core --bench shard_allocations
+
+use bytes::Bytes;
+use criterion::{black_box, criterion_group, BenchmarkId, Criterion, Throughput};
+use cyxcloud_core::chunk::{split_bytes_into_chunks, split_into_chunks};
+use cyxcloud_core::erasure::{ErasureEncoder, ShardData};
+use cyxcloud_core::DEFAULT_CHUNK_SIZE;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// System allocator that counts allocated bytes
+struct CountingAllocator;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATED.fetch_add(new_size.saturating_sub(layout.size()), Ordering::Relaxed);
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// Bytes and allocation count of one run
+fn measure<T>(f: impl FnOnce() -> T) -> (usize, usize) {
+    let bytes = ALLOCATED.load(Ordering::Relaxed);
+    let count = ALLOCATIONS.load(Ordering::Relaxed);
+    drop(black_box(f()));
+    (
+        ALLOCATED.load(Ordering::Relaxed) - bytes,
+        ALLOCATIONS.load(Ordering::Relaxed) - count,
+    )
+}
+
+/// Split and encode an upload, returning what would be sent to nodes
+fn encode_zero_copy(encoder: &ErasureEncoder, data: &Bytes) -> Vec<Bytes> {
+    split_bytes_into_chunks(data, DEFAULT_CHUNK_SIZE, None)
+        .unwrap()
+        .iter()
+        .flat_map(|chunk| encoder.encode_bytes(&chunk.data).unwrap())
+        .map(|shard| shard.data)
+        .collect()
+}
+
+/// Split and encode with a copy per chunk and per shard request
+fn encode_copying(encoder: &ErasureEncoder, data: &Bytes) -> Vec<Vec<u8>> {
+    split_into_chunks(data, DEFAULT_CHUNK_SIZE, None)
+        .unwrap()
+        .iter()
+        .flat_map(|chunk| encoder.encode(&chunk.data.to_vec()).unwrap())
+        .map(|shard| shard.data.to_vec())
+        .collect()
+}
+
+/// Shards of one chunk with the first four data shards lost
+fn degraded_shards(encoder: &ErasureEncoder, data: &Bytes) -> Vec<Option<ShardData>> {
+    let mut shards: Vec<Option<ShardData>> = encoder
+        .encode_bytes(data)
+        .unwrap()
+        .into_iter()
+        .map(Some)
+        .collect();
+    shards[..4].iter_mut().for_each(|s| *s = None);
+    shards
+}
+
+/// Decode shards as received from nodes
+fn decode_zero_copy(encoder: &ErasureEncoder, shards: &[Option<ShardData>], size: usize) -> Bytes {
+    encoder.decode(shards, size).unwrap()
+}
+
+/// Decode with a copy per shard response, as with `Vec<u8>` messages
+fn decode_copying(encoder: &ErasureEncoder, shards: &[Option<ShardData>], size: usize) -> Vec<u8> {
+    let received: Vec<Option<ShardData>> = shards
+        .iter()
+        .map(|s| {
+            s.as_ref()
+                .map(|s| ShardData::new(s.index, Bytes::from(s.data.to_vec()), s.is_parity))
+        })
+        .collect();
+    encoder.decode(&received, size).unwrap().to_vec()
+}
+
+const SIZES: [usize; 3] = [
+    1024 * 1024,      // 1 MB
+    4 * 1024 * 1024,  // 4 MB
+    16 * 1024 * 1024, // 16 MB
+];
+
+/// Print the allocation totals for both paths
+fn report_allocations() {
+    let encoder = ErasureEncoder::new().unwrap();
+
+    println!(
+        "{:<28} {:>8} {:>14} {:>8} {:>7}",
+        "path", "size", "allocated", "allocs", "vs data"
+    );
+    for size in SIZES {
+        let data = Bytes::from(vec![0x5a; size]);
+        let chunk = data.slice(..size.min(DEFAULT_CHUNK_SIZE));
+        let shards = degraded_shards(&encoder, &chunk);
+
+        // Decode works on a single chunk
+        let rows = [
+            (
+                "encode/zero_copy",
+                size,
+                measure(|| encode_zero_copy(&encoder, &data)),
+            ),
+            (
+                "encode/copying",
+                size,
+                measure(|| encode_copying(&encoder, &data)),
+            ),
+            (
+                "decode_4_missing/zero_copy",
+                chunk.len(),
+                measure(|| decode_zero_copy(&encoder, &shards, chunk.len())),
+            ),
+            (
+                "decode_4_missing/copying",
+                chunk.len(),
+                measure(|| decode_copying(&encoder, &shards, chunk.len())),
+            ),
+        ];
+        for (name, input, (bytes, count)) in rows {
+            println!(
+                "{:<28} {:>6}KB {:>14} {:>8} {:>6.2}x",
+                name,
+                input / 1024,
+                bytes,
+                count,
+                bytes as f64 / input as f64
+            );
+        }
+    }
+    println!();
+}
+
+/// Benchmark time of both paths
+fn bench_shard_paths(c: &mut Criterion) {
+    let encoder = ErasureEncoder::new().unwrap();
+
+    let mut group = c.benchmark_group("shard_path");
+
+    for size in SIZES {
+        let data = Bytes::from(vec![0x5a; size]);
+        let label = format!("{}MB", size / (1024 * 1024));
+
+        group.throughput(Throughput::Bytes(size as u64));
+        group.bench_with_input(
+            BenchmarkId::new("encode_zero_copy", &label),
+            &data,
+            |b, data| b.iter(|| encode_zero_copy(&encoder, black_box(data))),
+        );
+        group.bench_with_input(
+            BenchmarkId::new("encode_copying", &label),
+            &data,
+            |b, data| b.iter(|| encode_copying(&encoder, black_box(data))),
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_shard_paths);
+
+fn main() {
+    report_allocations();
+    benches();
+    Criterion::default().configure_from_args().final_summary();
+}
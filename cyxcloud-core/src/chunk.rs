@@ -6,7 +6,7 @@
 use crate::crypto::ContentHash;
 use crate::error::{CyxCloudError, Result};
 use crate::{MAX_CHUNK_SIZE, MIN_CHUNK_SIZE};
-use bytes::Bytes;
+use bytes::{Bytes, BytesMut};
 use serde::{Deserialize, Serialize};
 use std::fmt;
 use uuid::Uuid;
@@ -77,6 +77,39 @@ impl ChunkId {
     }
 }
 
+/// Checksum of a chunk or frame as sent over the wire
+///
+/// The first 8 bytes of the data's Blake3 hash, little-endian, and never 0:
+/// a checksum of 0 on the wire means the sender did not compute one.
+pub fn wire_checksum(data: &[u8]) -> u64 {
+    let hash = ContentHash::compute(data);
+    let mut prefix = [0u8; 8];
+    prefix.copy_from_slice(&hash.as_bytes()[..8]);
+    u64::from_le_bytes(prefix).max(1)
+}
+
+/// Check data received over the wire against the checksum sent with it
+///
+/// A checksum of 0 (from senders that predate checksums) is not checked.
+/// A mismatch is a [`CyxCloudError::TransportCorruption`]: the data was
+/// damaged in transit, so fetching it again, ideally from another replica,
+/// is expected to succeed.
+pub fn verify_wire_checksum(data: &[u8], checksum: u64) -> Result<()> {
+    if checksum == 0 {
+        return Ok(());
+    }
+    let computed = wire_checksum(data);
+    if computed != checksum {
+        return Err(CyxCloudError::TransportCorruption(format!(
+            "checksum {:016x} of {} bytes does not match {:016x}",
+            computed,
+            data.len(),
+            checksum
+        )));
+    }
+    Ok(())
+}
+
 impl fmt::Debug for ChunkId {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "ChunkId({})", &self.to_base58()[..8])
@@ -204,19 +237,24 @@ pub fn split_into_chunks(
     data: &[u8],
     chunk_size: usize,
     parent_id: Option<Uuid>,
+) -> Result<Vec<Chunk>> {
+    split_bytes_into_chunks(&Bytes::copy_from_slice(data), chunk_size, parent_id)
+}
+
+/// Split a file into chunks that share the buffer of `data`
+pub fn split_bytes_into_chunks(
+    data: &Bytes,
+    chunk_size: usize,
+    parent_id: Option<Uuid>,
 ) -> Result<Vec<Chunk>> {
     let chunk_size = chunk_size.clamp(MIN_CHUNK_SIZE, MAX_CHUNK_SIZE);
     let total_chunks = data.len().div_ceil(chunk_size);
 
-    let chunks: Vec<Chunk> = data
-        .chunks(chunk_size)
-        .enumerate()
-        .map(|(index, chunk_data)| {
-            let mut chunk = Chunk::new(
-                Bytes::copy_from_slice(chunk_data),
-                index as u32,
-                total_chunks as u32,
-            )?;
+    let chunks: Vec<Chunk> = (0..total_chunks)
+        .map(|index| {
+            let start = index * chunk_size;
+            let end = (start + chunk_size).min(data.len());
+            let mut chunk = Chunk::new(data.slice(start..end), index as u32, total_chunks as u32)?;
 
             if let Some(pid) = parent_id {
                 chunk.metadata = chunk.metadata.with_parent(pid);
@@ -258,14 +296,19 @@ pub fn reassemble_chunks(chunks: &[Chunk]) -> Result<Bytes> {
         }
     }
 
+    // A single chunk needs no copy
+    if let [chunk] = sorted.as_slice() {
+        return Ok(chunk.data.clone());
+    }
+
     // Concatenate data
     let total_size: usize = sorted.iter().map(|c| c.data.len()).sum();
-    let mut result = Vec::with_capacity(total_size);
+    let mut result = BytesMut::with_capacity(total_size);
     for chunk in sorted {
         result.extend_from_slice(&chunk.data);
     }
 
-    Ok(Bytes::from(result))
+    Ok(result.freeze())
 }
 
 #[cfg(test)]
@@ -308,10 +351,46 @@ mod tests {
         assert_eq!(reassembled.as_ref(), original.as_slice());
     }
 
+    #[test]
+    fn test_split_bytes_shares_buffer() {
+        let original = Bytes::from(vec![7u8; 600 * 1024]);
+        let chunk_size = 256 * 1024;
+
+        let chunks = split_bytes_into_chunks(&original, chunk_size, None).unwrap();
+        assert_eq!(chunks.len(), 3);
+        for chunk in &chunks {
+            let offset = chunk.data.as_ptr() as usize - original.as_ptr() as usize;
+            assert_eq!(offset, chunk.metadata.index as usize * chunk_size);
+        }
+        assert_eq!(chunks[2].size(), 600 * 1024 - 2 * chunk_size);
+
+        let reassembled = reassemble_chunks(&chunks).unwrap();
+        assert_eq!(reassembled, original);
+    }
+
     #[test]
     fn test_chunk_too_large() {
         let data = vec![0u8; MAX_CHUNK_SIZE + 1];
         let result = Chunk::new(Bytes::from(data), 0, 1);
         assert!(matches!(result, Err(CyxCloudError::ChunkTooLarge { .. })));
     }
+
+    #[test]
+    fn test_wire_checksum() {
+        let data = b"frame of chunk data";
+        let checksum = wire_checksum(data);
+        assert_ne!(checksum, 0);
+        assert!(verify_wire_checksum(data, checksum).is_ok());
+        // Not sent, not checked
+        assert!(verify_wire_checksum(b"anything", 0).is_ok());
+
+        let mut damaged = data.to_vec();
+        damaged[3] ^= 0x01;
+        let err = verify_wire_checksum(&damaged, checksum).unwrap_err();
+        assert!(matches!(err, CyxCloudError::TransportCorruption(_)));
+        assert_eq!(
+            crate::error::HasErrorCode::error_code(&err),
+            crate::error::ErrorCode::TransportCorruption
+        );
+    }
 }
@@ -286,6 +286,50 @@ pub fn decrypt_from_bytes(data: &[u8], key: &EncryptionKey) -> Result<Vec<u8>> {
     decrypt(&encrypted, key)
 }
 
+/// Nonce of segment `index` of a sealed stream
+///
+/// The nonce is the segment's position (STREAM construction): the index in
+/// the first 8 bytes and a flag in the last byte marking the final segment.
+/// Segments therefore cannot be reordered, dropped or truncated without
+/// failing authentication. A key must only ever seal one stream.
+fn segment_nonce(index: u64, last: bool) -> [u8; NONCE_SIZE] {
+    let mut nonce = [0u8; NONCE_SIZE];
+    nonce[..8].copy_from_slice(&index.to_be_bytes());
+    nonce[NONCE_SIZE - 1] = last as u8;
+    nonce
+}
+
+/// Seal segment `index` of a stream with AES-256-GCM
+///
+/// Returns the ciphertext with the tag appended ([`TAG_SIZE`] bytes longer
+/// than `plaintext`); no nonce is stored, it follows from the position.
+pub fn seal_segment(
+    plaintext: &[u8],
+    key: &EncryptionKey,
+    index: u64,
+    last: bool,
+) -> Result<Vec<u8>> {
+    let cipher = Aes256Gcm::new_from_slice(key.as_bytes())
+        .map_err(|e| CyxCloudError::Encryption(e.to_string()))?;
+    cipher
+        .encrypt(Nonce::from_slice(&segment_nonce(index, last)), plaintext)
+        .map_err(|e| CyxCloudError::Encryption(e.to_string()))
+}
+
+/// Open segment `index` of a stream sealed by [`seal_segment`]
+pub fn open_segment(
+    ciphertext: &[u8],
+    key: &EncryptionKey,
+    index: u64,
+    last: bool,
+) -> Result<Vec<u8>> {
+    let cipher = Aes256Gcm::new_from_slice(key.as_bytes())
+        .map_err(|e| CyxCloudError::Decryption(e.to_string()))?;
+    cipher
+        .decrypt(Nonce::from_slice(&segment_nonce(index, last)), ciphertext)
+        .map_err(|_| CyxCloudError::Decryption("Authentication failed".to_string()))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -374,4 +418,16 @@ mod tests {
         // Ciphertext should be plaintext + 16 byte auth tag
         assert_eq!(encrypted.ciphertext.len(), plaintext.len() + TAG_SIZE);
     }
+
+    #[test]
+    fn test_segment_roundtrip_binds_position() {
+        let key = EncryptionKey::generate();
+        let sealed = seal_segment(b"segment", &key, 3, false).unwrap();
+        assert_eq!(sealed.len(), 7 + TAG_SIZE);
+        assert_eq!(open_segment(&sealed, &key, 3, false).unwrap(), b"segment");
+
+        // Moved or passed off as the final segment, it no longer opens
+        assert!(open_segment(&sealed, &key, 4, false).is_err());
+        assert!(open_segment(&sealed, &key, 3, true).is_err());
+    }
 }
@@ -5,13 +5,29 @@
 //! - m=4 parity shards (redundancy)
 //! - Total 14 shards distributed across nodes
 //! - Can tolerate loss of ANY 4 nodes
+//!
+//! Two backends are available:
+//! - [`ErasureBackend::Galois8`][]: `reed-solomon-erasure` (always built; C SIMD
+//!   kernels with the `simd` feature)
+//! - [`ErasureBackend::Leopard`][]: `reed-solomon-simd`, an O(n log n) Leopard-RS
+//!   code with runtime AVX2/SSSE3/NEON dispatch (`rs-simd` feature)
+//!
+//! Data shards are identical for both, but parity shards are not: shards must
+//! be reconstructed with the backend that encoded them. `ErasureEncoder::new`
+//! picks the fastest backend at runtime; callers that persist shards record
+//! [`ErasureEncoder::backend`] and decode with [`ErasureEncoder::with_backend`].
+//!
+//! [`StripeEncoder`] encodes data that arrives incrementally one fixed-size
+//! stripe at a time, so streamed uploads never hold more than a stripe.
 
 use crate::error::{CyxCloudError, Result};
 use crate::{DATA_SHARDS, PARITY_SHARDS};
-use bytes::Bytes;
-use rayon::prelude::*;
+use bytes::{Bytes, BytesMut};
 use reed_solomon_erasure::galois_8::ReedSolomon;
 use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::OnceLock;
 
 /// Erasure coding configuration
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
@@ -66,6 +82,121 @@ impl ErasureConfig {
     }
 }
 
+/// Erasure coding implementation
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErasureBackend {
+    /// Vandermonde Reed-Solomon over GF(2^8) (`reed-solomon-erasure`)
+    #[default]
+    Galois8,
+    /// Leopard-RS FFT Reed-Solomon (`reed-solomon-simd`)
+    Leopard,
+}
+
+impl ErasureBackend {
+    /// All backends, compiled in or not
+    pub const ALL: [ErasureBackend; 2] = [ErasureBackend::Galois8, ErasureBackend::Leopard];
+
+    /// Stable name, as stored alongside encoded data
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErasureBackend::Galois8 => "galois8",
+            ErasureBackend::Leopard => "leopard",
+        }
+    }
+
+    /// Whether this backend was compiled in
+    pub fn is_available(&self) -> bool {
+        match self {
+            ErasureBackend::Galois8 => true,
+            ErasureBackend::Leopard => cfg!(feature = "rs-simd"),
+        }
+    }
+
+    /// Backends compiled into this build
+    pub fn available() -> Vec<ErasureBackend> {
+        Self::ALL.into_iter().filter(|b| b.is_available()).collect()
+    }
+
+    /// Fastest backend for this build and CPU
+    ///
+    /// `ERASURE_BACKEND` overrides the choice if it names an available
+    /// backend. Otherwise Leopard is used when compiled in and the CPU has the
+    /// SIMD extensions it dispatches to. The selection is made once.
+    pub fn fastest() -> ErasureBackend {
+        static SELECTED: OnceLock<ErasureBackend> = OnceLock::new();
+        *SELECTED.get_or_init(|| {
+            let requested = std::env::var("ERASURE_BACKEND")
+                .ok()
+                .and_then(|v| v.parse::<ErasureBackend>().ok())
+                .filter(|b| b.is_available());
+
+            requested.unwrap_or_else(|| {
+                if ErasureBackend::Leopard.is_available() && cpu_has_simd() {
+                    ErasureBackend::Leopard
+                } else {
+                    ErasureBackend::Galois8
+                }
+            })
+        })
+    }
+}
+
+impl fmt::Display for ErasureBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ErasureBackend {
+    type Err = CyxCloudError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|b| b.as_str().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| CyxCloudError::Configuration(format!("unknown erasure backend: {}", s)))
+    }
+}
+
+/// Concatenate data shards, trimmed to the original size
+fn concat_shards(shards: &[&Bytes], original_size: usize) -> Bytes {
+    let total: usize = shards.iter().map(|s| s.len()).sum();
+    let original_size = original_size.min(total);
+
+    let mut result = BytesMut::with_capacity(original_size);
+    for shard in shards {
+        let take = (original_size - result.len()).min(shard.len());
+        result.extend_from_slice(&shard[..take]);
+        if result.len() == original_size {
+            break;
+        }
+    }
+    result.freeze()
+}
+
+/// Error for a backend that is not compiled in
+#[cfg(not(feature = "rs-simd"))]
+fn backend_unavailable(backend: ErasureBackend) -> CyxCloudError {
+    CyxCloudError::Configuration(format!("erasure backend {} is not compiled in", backend))
+}
+
+/// Whether the CPU has SIMD extensions the Leopard backend can use
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+fn cpu_has_simd() -> bool {
+    std::is_x86_feature_detected!("avx2") || std::is_x86_feature_detected!("ssse3")
+}
+
+#[cfg(target_arch = "aarch64")]
+fn cpu_has_simd() -> bool {
+    std::arch::is_aarch64_feature_detected!("neon")
+}
+
+#[cfg(not(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64")))]
+fn cpu_has_simd() -> bool {
+    false
+}
+
 /// A single shard of erasure-coded data
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ShardData {
@@ -96,6 +227,7 @@ impl ShardData {
 /// Reed-Solomon encoder/decoder
 pub struct ErasureEncoder {
     config: ErasureConfig,
+    backend: ErasureBackend,
     encoder: ReedSolomon,
 }
 
@@ -105,10 +237,22 @@ impl ErasureEncoder {
         Self::with_config(ErasureConfig::default())
     }
 
-    /// Create a new encoder with custom configuration
+    /// Create a new encoder with custom configuration and the fastest backend
     pub fn with_config(config: ErasureConfig) -> Result<Self> {
+        Self::with_backend(config, ErasureBackend::fastest())
+    }
+
+    /// Create a new encoder using a specific backend
+    ///
+    /// A backend that is not compiled in can still decode when all data
+    /// shards are present; encoding and reconstruction return an error.
+    pub fn with_backend(config: ErasureConfig, backend: ErasureBackend) -> Result<Self> {
         let encoder = ReedSolomon::new(config.data_shards, config.parity_shards)?;
-        Ok(Self { config, encoder })
+        Ok(Self {
+            config,
+            backend,
+            encoder,
+        })
     }
 
     /// Get the erasure configuration
@@ -116,77 +260,68 @@ impl ErasureEncoder {
         &self.config
     }
 
+    /// Get the backend in use
+    pub fn backend(&self) -> ErasureBackend {
+        self.backend
+    }
+
     /// Encode data into shards
     ///
-    /// Returns a vector of shards (data + parity)
+    /// Returns a vector of shards (data + parity). Copies `data` once; use
+    /// [`encode_bytes`](Self::encode_bytes) when the data is already `Bytes`.
     pub fn encode(&self, data: &[u8]) -> Result<Vec<ShardData>> {
-        let shard_size = self.calculate_shard_size(data.len());
-
-        // Pad data to be evenly divisible by data_shards
-        let padded_size = shard_size * self.config.data_shards;
-        let mut padded_data = data.to_vec();
-        padded_data.resize(padded_size, 0);
-
-        // Split into data shards
-        let mut shards: Vec<Vec<u8>> = padded_data.chunks(shard_size).map(|c| c.to_vec()).collect();
+        self.encode_bytes(&Bytes::copy_from_slice(data))
+    }
 
-        // Add empty parity shards
-        for _ in 0..self.config.parity_shards {
-            shards.push(vec![0u8; shard_size]);
+    /// Encode data into shards without copying it
+    ///
+    /// Data shards are slices of `data`; only a trailing shard that needs
+    /// padding is copied. Parity shards share one allocation.
+    pub fn encode_bytes(&self, data: &Bytes) -> Result<Vec<ShardData>> {
+        let shard_size = self.calculate_shard_size(data.len());
+        if shard_size == 0 {
+            return Err(CyxCloudError::ErasureCoding(
+                "cannot encode empty data".to_string(),
+            ));
         }
 
-        // Encode (fills in parity shards)
-        self.encoder.encode(&mut shards)?;
+        let data_shards: Vec<Bytes> = (0..self.config.data_shards)
+            .map(|i| {
+                let start = (i * shard_size).min(data.len());
+                let end = (start + shard_size).min(data.len());
+                if end - start == shard_size {
+                    data.slice(start..end)
+                } else {
+                    // Pad to be evenly divisible by data_shards
+                    let mut padded = BytesMut::zeroed(shard_size);
+                    padded[..end - start].copy_from_slice(&data[start..end]);
+                    padded.freeze()
+                }
+            })
+            .collect();
+
+        let parity_shards = self.compute_parity(&data_shards, shard_size)?;
 
         // Convert to ShardData
-        let result: Vec<ShardData> = shards
+        let result: Vec<ShardData> = data_shards
             .into_iter()
+            .chain(parity_shards)
             .enumerate()
             .map(|(i, shard_data)| {
                 let is_parity = i >= self.config.data_shards;
-                ShardData::new(i as u8, Bytes::from(shard_data), is_parity)
+                ShardData::new(i as u8, shard_data, is_parity)
             })
             .collect();
 
         Ok(result)
     }
 
-    /// Encode data into shards using parallel processing
+    /// Encode data into shards
     ///
-    /// More efficient for large chunks (> 1MB)
+    /// Shards are sliced rather than copied, so this is the same as
+    /// [`encode`](Self::encode); kept for existing callers.
     pub fn encode_parallel(&self, data: &[u8]) -> Result<Vec<ShardData>> {
-        let shard_size = self.calculate_shard_size(data.len());
-
-        // Pad data
-        let padded_size = shard_size * self.config.data_shards;
-        let mut padded_data = data.to_vec();
-        padded_data.resize(padded_size, 0);
-
-        // Split into data shards (parallel)
-        let mut shards: Vec<Vec<u8>> = padded_data
-            .par_chunks(shard_size)
-            .map(|c| c.to_vec())
-            .collect();
-
-        // Add parity shards
-        for _ in 0..self.config.parity_shards {
-            shards.push(vec![0u8; shard_size]);
-        }
-
-        // Encode
-        self.encoder.encode(&mut shards)?;
-
-        // Convert to ShardData (parallel)
-        let result: Vec<ShardData> = shards
-            .into_par_iter()
-            .enumerate()
-            .map(|(i, shard_data)| {
-                let is_parity = i >= self.config.data_shards;
-                ShardData::new(i as u8, Bytes::from(shard_data), is_parity)
-            })
-            .collect();
-
-        Ok(result)
+        self.encode(data)
     }
 
     /// Decode shards back into original data
@@ -221,34 +356,129 @@ impl ErasureEncoder {
                 required: self.config.data_shards,
             })?;
 
-        // Convert to mutable shard vectors
-        let mut shard_vecs: Vec<Option<Vec<u8>>> = shards
+        if let Some(shard) = shards.iter().flatten().find(|s| s.size() != shard_size) {
+            return Err(CyxCloudError::ShardSizeMismatch {
+                expected: shard_size,
+                actual: shard.size(),
+            });
+        }
+
+        // All data shards present: just concatenate them
+        let present: Option<Vec<&Bytes>> = shards[..self.config.data_shards]
             .iter()
-            .map(|opt| opt.as_ref().map(|s| s.data.to_vec()))
+            .map(|s| s.as_ref().map(|s| &s.data))
             .collect();
-
-        // Reconstruct missing shards
-        self.encoder.reconstruct(&mut shard_vecs)?;
-
-        // Extract data shards and concatenate
-        let mut result = Vec::with_capacity(shard_size * self.config.data_shards);
-        for shard_opt in shard_vecs.iter().take(self.config.data_shards) {
-            if let Some(ref shard) = shard_opt {
-                result.extend_from_slice(shard);
-            } else {
-                return Err(CyxCloudError::Internal("Reconstruction failed".to_string()));
-            }
+        if let Some(data_shards) = present {
+            return Ok(concat_shards(&data_shards, original_size));
         }
 
-        // Trim to original size
-        result.truncate(original_size);
-        Ok(Bytes::from(result))
+        // Reconstruct missing data shards (parity is not needed for reading)
+        let data = self.reconstruct_data(shards, shard_size)?;
+        Ok(data.slice(..original_size.min(data.len())))
     }
 
     /// Calculate the size of each shard given the data size
     fn calculate_shard_size(&self, data_size: usize) -> usize {
         // Round up to ensure all data fits
-        data_size.div_ceil(self.config.data_shards)
+        let shard_size = data_size.div_ceil(self.config.data_shards);
+        match self.backend {
+            ErasureBackend::Galois8 => shard_size,
+            // Leopard works on 16-bit words
+            ErasureBackend::Leopard => shard_size.next_multiple_of(2),
+        }
+    }
+
+    /// Compute the parity shards for a set of data shards
+    fn compute_parity(&self, data_shards: &[Bytes], shard_size: usize) -> Result<Vec<Bytes>> {
+        match self.backend {
+            ErasureBackend::Galois8 => {
+                let mut parity = BytesMut::zeroed(shard_size * self.config.parity_shards);
+                {
+                    let mut parity_slices: Vec<&mut [u8]> = parity.chunks_mut(shard_size).collect();
+                    self.encoder.encode_sep(data_shards, &mut parity_slices)?;
+                }
+                let parity = parity.freeze();
+                Ok((0..self.config.parity_shards)
+                    .map(|i| parity.slice(i * shard_size..(i + 1) * shard_size))
+                    .collect())
+            }
+            #[cfg(feature = "rs-simd")]
+            ErasureBackend::Leopard => {
+                let parity = reed_solomon_simd::encode(
+                    self.config.data_shards,
+                    self.config.parity_shards,
+                    data_shards,
+                )?;
+                Ok(parity.into_iter().map(Bytes::from).collect())
+            }
+            #[cfg(not(feature = "rs-simd"))]
+            ErasureBackend::Leopard => Err(backend_unavailable(self.backend)),
+        }
+    }
+
+    /// Reconstruct the data shards from any `data_shards` available shards
+    ///
+    /// Returns the data shards as one contiguous buffer.
+    fn reconstruct_data(&self, shards: &[Option<ShardData>], shard_size: usize) -> Result<Bytes> {
+        match self.backend {
+            ErasureBackend::Galois8 => {
+                // One buffer for all shards; missing ones are rebuilt in place
+                let mut buffer = BytesMut::zeroed(shard_size * shards.len());
+                {
+                    let mut slots: Vec<(&mut [u8], bool)> = buffer
+                        .chunks_mut(shard_size)
+                        .zip(shards)
+                        .map(|(slot, shard)| match shard {
+                            Some(shard) => {
+                                slot.copy_from_slice(&shard.data);
+                                (slot, true)
+                            }
+                            None => (slot, false),
+                        })
+                        .collect();
+                    self.encoder.reconstruct_data(&mut slots)?;
+                }
+                // Data shards come first, so the buffer already holds the data
+                buffer.truncate(shard_size * self.config.data_shards);
+                Ok(buffer.freeze())
+            }
+            #[cfg(feature = "rs-simd")]
+            ErasureBackend::Leopard => {
+                let k = self.config.data_shards;
+                let original = shards[..k]
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(i, s)| s.as_ref().map(|s| (i, s.data.as_ref())));
+                let recovery = shards[k..]
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(i, s)| s.as_ref().map(|s| (i, s.data.as_ref())));
+
+                let mut restored =
+                    reed_solomon_simd::decode(k, self.config.parity_shards, original, recovery)?;
+
+                let mut data = BytesMut::with_capacity(shard_size * k);
+                for (i, shard) in shards[..k].iter().enumerate() {
+                    match shard {
+                        Some(s) => data.extend_from_slice(&s.data),
+                        None => data.extend_from_slice(
+                            restored
+                                .remove(&i)
+                                .ok_or_else(|| {
+                                    CyxCloudError::Internal("Reconstruction failed".to_string())
+                                })?
+                                .as_slice(),
+                        ),
+                    }
+                }
+                Ok(data.freeze())
+            }
+            #[cfg(not(feature = "rs-simd"))]
+            ErasureBackend::Leopard => {
+                let _ = shard_size;
+                Err(backend_unavailable(self.backend))
+            }
+        }
     }
 
     /// Verify that shards are consistent (for health checking)
@@ -263,11 +493,27 @@ impl ErasureEncoder {
             return Ok(false);
         }
 
-        // Convert to option refs for verification
-        let shard_refs: Vec<&[u8]> = shards.iter().map(|s| s.data.as_ref()).collect();
+        match self.backend {
+            ErasureBackend::Galois8 => {
+                // Convert to option refs for verification
+                let shard_refs: Vec<&[u8]> = shards.iter().map(|s| s.data.as_ref()).collect();
 
-        // Verify parity shards are correct
-        Ok(self.encoder.verify(&shard_refs)?)
+                // Verify parity shards are correct
+                Ok(self.encoder.verify(&shard_refs)?)
+            }
+            ErasureBackend::Leopard => {
+                // Recompute parity from the data shards and compare
+                let data_shards: Vec<Bytes> = shards[..self.config.data_shards]
+                    .iter()
+                    .map(|s| s.data.clone())
+                    .collect();
+                let parity = self.compute_parity(&data_shards, expected_size)?;
+                Ok(parity
+                    .iter()
+                    .zip(&shards[self.config.data_shards..])
+                    .all(|(parity, shard)| *parity == shard.data))
+            }
+        }
     }
 }
 
@@ -277,6 +523,118 @@ impl Default for ErasureEncoder {
     }
 }
 
+/// One stripe of a stream, erasure coded
+#[derive(Debug, Clone)]
+pub struct EncodedStripe {
+    /// Position of the stripe in the stream (0-based)
+    pub index: u32,
+    /// Bytes of stream data in the stripe (`stripe_size` for all but the last)
+    pub size: usize,
+    /// Data and parity shards of the stripe
+    pub shards: Vec<ShardData>,
+}
+
+/// Block-wise encoder for data that arrives incrementally
+///
+/// The stream is cut into stripes of `stripe_size` bytes and every stripe is
+/// encoded on its own, exactly as [`ErasureEncoder::encode_bytes`] encodes a
+/// chunk. At most one stripe is buffered however long the stream is, and
+/// stripes stored as consecutive chunks of `stripe_size` bytes decode with
+/// [`ErasureEncoder::decode`].
+pub struct StripeEncoder {
+    encoder: ErasureEncoder,
+    stripe_size: usize,
+    /// Start of the next stripe, shorter than `stripe_size`
+    pending: BytesMut,
+    next_index: u32,
+    total_size: u64,
+}
+
+impl StripeEncoder {
+    /// Create a stripe encoder
+    pub fn new(encoder: ErasureEncoder, stripe_size: usize) -> Result<Self> {
+        if stripe_size == 0 {
+            return Err(CyxCloudError::Configuration(
+                "stripe size must be positive".to_string(),
+            ));
+        }
+        Ok(Self {
+            encoder,
+            stripe_size,
+            pending: BytesMut::new(),
+            next_index: 0,
+            total_size: 0,
+        })
+    }
+
+    /// Get the underlying encoder
+    pub fn encoder(&self) -> &ErasureEncoder {
+        &self.encoder
+    }
+
+    /// Bytes of stream data per stripe
+    pub fn stripe_size(&self) -> usize {
+        self.stripe_size
+    }
+
+    /// Bytes pushed so far
+    pub fn total_size(&self) -> u64 {
+        self.total_size
+    }
+
+    /// Add stream data, returning the stripes it completes
+    ///
+    /// Whole stripes are sliced out of `data` without copying; only a
+    /// stripe that spans several pushes is assembled in the buffer.
+    pub fn push(&mut self, mut data: Bytes) -> Result<Vec<EncodedStripe>> {
+        self.total_size += data.len() as u64;
+        let mut stripes = Vec::new();
+
+        if !self.pending.is_empty() {
+            let take = (self.stripe_size - self.pending.len()).min(data.len());
+            self.pending.extend_from_slice(&data.split_to(take));
+            if self.pending.len() < self.stripe_size {
+                return Ok(stripes);
+            }
+            let stripe = self.pending.split().freeze();
+            stripes.push(self.encode_stripe(stripe)?);
+        }
+
+        while data.len() >= self.stripe_size {
+            let stripe = data.split_to(self.stripe_size);
+            stripes.push(self.encode_stripe(stripe)?);
+        }
+
+        if !data.is_empty() {
+            self.pending.reserve(self.stripe_size);
+            self.pending.extend_from_slice(&data);
+        }
+        Ok(stripes)
+    }
+
+    /// Encode the buffered end of the stream as the last, shorter stripe
+    ///
+    /// Returns None if the stream ended on a stripe boundary or was empty.
+    pub fn finish(mut self) -> Result<Option<EncodedStripe>> {
+        if self.pending.is_empty() {
+            return Ok(None);
+        }
+        let stripe = self.pending.split().freeze();
+        self.encode_stripe(stripe).map(Some)
+    }
+
+    fn encode_stripe(&mut self, data: Bytes) -> Result<EncodedStripe> {
+        let shards = self.encoder.encode_bytes(&data)?;
+        let stripe = EncodedStripe {
+            index: self.next_index,
+            size: data.len(),
+            shards,
+        };
+        self.next_index += 1;
+        Ok(stripe)
+    }
+}
+
 /// Convenience function to encode data with default configuration
 pub fn encode(data: &[u8]) -> Result<Vec<ShardData>> {
     ErasureEncoder::new()?.encode(data)
@@ -374,6 +732,48 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_encode_bytes_zero_copy() {
+        let encoder =
+            ErasureEncoder::with_backend(ErasureConfig::default(), ErasureBackend::Galois8)
+                .unwrap();
+        let data = Bytes::from((0..1001u32).map(|i| i as u8).collect::<Vec<u8>>());
+
+        let shards = encoder.encode_bytes(&data).unwrap();
+        let shard_size = shards[0].size();
+
+        // Full data shards point into the input buffer
+        for shard in shards.iter().take(data.len() / shard_size) {
+            let offset = shard.data.as_ptr() as usize - data.as_ptr() as usize;
+            assert_eq!(offset, shard.index as usize * shard_size);
+        }
+
+        // Odd-sized input still round-trips through reconstruction
+        let mut shards: Vec<Option<ShardData>> = shards.into_iter().map(Some).collect();
+        shards[0] = None;
+        shards[3] = None;
+        let decoded = encoder.decode(&shards, data.len()).unwrap();
+        assert_eq!(decoded, data);
+    }
+
+    #[test]
+    fn test_decode_oversized_original_size() {
+        let encoder = ErasureEncoder::new().unwrap();
+        let data = Bytes::from_static(b"some data");
+        let mut shards: Vec<Option<ShardData>> = encoder
+            .encode_bytes(&data)
+            .unwrap()
+            .into_iter()
+            .map(Some)
+            .collect();
+
+        // A size past the padded data is clamped on both decode paths
+        let padded = encoder.decode(&shards, usize::MAX).unwrap();
+        assert_eq!(&padded[..data.len()], data.as_ref());
+        shards[1] = None;
+        assert_eq!(encoder.decode(&shards, usize::MAX).unwrap(), padded);
+    }
+
     #[test]
     fn test_verify_shards() {
         let encoder = ErasureEncoder::new().unwrap();
@@ -407,6 +807,125 @@ mod tests {
         assert_eq!(decoded.as_ref(), original);
     }
 
+    #[test]
+    fn test_backend_names() {
+        for backend in ErasureBackend::ALL {
+            assert_eq!(backend.as_str().parse::<ErasureBackend>().unwrap(), backend);
+        }
+        assert_eq!(
+            "Leopard".parse::<ErasureBackend>().unwrap(),
+            ErasureBackend::Leopard
+        );
+        assert!("isa-l".parse::<ErasureBackend>().is_err());
+
+        assert!(ErasureBackend::available().contains(&ErasureBackend::Galois8));
+        assert!(ErasureBackend::fastest().is_available());
+        assert_eq!(
+            ErasureBackend::Leopard.is_available(),
+            cfg!(feature = "rs-simd")
+        );
+    }
+
+    #[test]
+    fn test_all_backends_roundtrip() {
+        // Odd length so Leopard has to pad shards to an even size
+        let original: Vec<u8> = (0..100_001u32).map(|i| (i * 7) as u8).collect();
+
+        for backend in ErasureBackend::available() {
+            let encoder = ErasureEncoder::with_backend(ErasureConfig::default(), backend).unwrap();
+            assert_eq!(encoder.backend(), backend);
+
+            let shards = encoder.encode(&original).unwrap();
+            assert_eq!(shards.len(), 14);
+            assert!(encoder.verify_shards(&shards).unwrap(), "{}", backend);
+
+            let mut shard_opts: Vec<Option<ShardData>> = shards.into_iter().map(Some).collect();
+            shard_opts[1] = None;
+            shard_opts[4] = None;
+            shard_opts[9] = None;
+            shard_opts[12] = None;
+
+            let decoded = encoder.decode(&shard_opts, original.len()).unwrap();
+            assert_eq!(decoded.as_ref(), original.as_slice(), "{}", backend);
+        }
+    }
+
+    #[cfg(not(feature = "rs-simd"))]
+    #[test]
+    fn test_unavailable_backend() {
+        let galois8 =
+            ErasureEncoder::with_backend(ErasureConfig::default(), ErasureBackend::Galois8)
+                .unwrap();
+        let leopard =
+            ErasureEncoder::with_backend(ErasureConfig::default(), ErasureBackend::Leopard)
+                .unwrap();
+
+        let original = b"data shards are the same for every backend";
+        assert!(leopard.encode(original).is_err());
+
+        // Reading with all data shards needs no reconstruction
+        let mut shard_opts: Vec<Option<ShardData>> = galois8
+            .encode(original)
+            .unwrap()
+            .into_iter()
+            .map(Some)
+            .collect();
+        let decoded = leopard.decode(&shard_opts, original.len()).unwrap();
+        assert_eq!(decoded.as_ref(), original);
+
+        shard_opts[0] = None;
+        assert!(leopard.decode(&shard_opts, original.len()).is_err());
+    }
+
+    #[test]
+    fn test_stripe_encoder() {
+        let encoder = ErasureEncoder::new().unwrap();
+        let data = Bytes::from((0..25_000u32).map(|i| (i * 13) as u8).collect::<Vec<u8>>());
+
+        // Pushes that straddle stripe boundaries, including an empty one
+        let mut stripes = StripeEncoder::new(ErasureEncoder::new().unwrap(), 10_000).unwrap();
+        let mut encoded = Vec::new();
+        for range in [0..3_000, 3_000..3_000, 3_000..21_000, 21_000..25_000] {
+            encoded.extend(stripes.push(data.slice(range)).unwrap());
+        }
+        assert_eq!(stripes.total_size(), 25_000);
+        encoded.extend(stripes.finish().unwrap());
+
+        let sizes: Vec<usize> = encoded.iter().map(|s| s.size).collect();
+        assert_eq!(sizes, vec![10_000, 10_000, 5_000]);
+
+        // Each stripe is a chunk as the block encoder would have made it
+        for (stripe, chunk) in encoded.iter().zip(data.chunks(10_000)) {
+            let expected = encoder.encode(chunk).unwrap();
+            for (shard, want) in stripe.shards.iter().zip(&expected) {
+                assert_eq!(shard.data, want.data);
+            }
+
+            let mut shards: Vec<Option<ShardData>> =
+                stripe.shards.iter().cloned().map(Some).collect();
+            shards[2] = None;
+            shards[11] = None;
+            assert_eq!(encoder.decode(&shards, stripe.size).unwrap(), chunk);
+        }
+        assert_eq!(
+            encoded.iter().map(|s| s.index).collect::<Vec<_>>(),
+            vec![0, 1, 2]
+        );
+    }
+
+    #[test]
+    fn test_stripe_encoder_boundaries() {
+        assert!(StripeEncoder::new(ErasureEncoder::new().unwrap(), 0).is_err());
+
+        // A stream ending on a stripe boundary has no short last stripe
+        let mut stripes = StripeEncoder::new(ErasureEncoder::new().unwrap(), 100).unwrap();
+        assert_eq!(stripes.push(Bytes::from(vec![7u8; 200])).unwrap().len(), 2);
+        assert!(stripes.finish().unwrap().is_none());
+
+        let empty = StripeEncoder::new(ErasureEncoder::new().unwrap(), 100).unwrap();
+        assert!(empty.finish().unwrap().is_none());
+    }
+
     #[test]
     fn test_shard_indices() {
         let encoder = ErasureEncoder::new().unwrap();
@@ -1,9 +1,262 @@
 //! Error types for CyxCloud
 //!
-//! Provides a unified error type for all CyxCloud operations.
+//! Provides a unified error type for all CyxCloud operations, plus a shared
+//! [`ErrorCode`] taxonomy that every crate maps its errors into so callers can
+//! tell failure causes apart (node unreachable vs. insufficient shards vs.
+//! metadata down) across HTTP and gRPC boundaries.
 
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
 use thiserror::Error;
 
+/// gRPC metadata key / HTTP header carrying the [`ErrorCode`]
+pub const ERROR_CODE_HEADER: &str = "x-cyxcloud-error-code";
+
+/// gRPC metadata key / HTTP header carrying the request ID
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Stable, machine-readable error codes shared by all CyxCloud services
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    /// Requested object, chunk, node or record does not exist
+    NotFound,
+    /// Resource already exists
+    AlreadyExists,
+    /// Request conflicts with the current state (e.g. bucket not empty)
+    Conflict,
+    /// Request is malformed or violates a constraint
+    InvalidArgument,
+    /// Missing or invalid credentials
+    Unauthenticated,
+    /// Credentials are valid but lack permission
+    PermissionDenied,
+    /// Caller exceeded a rate or bandwidth limit
+    RateLimited,
+    /// A storage node could not be reached or rejected the request
+    NodeUnreachable,
+    /// No storage nodes are available for placement
+    NoNodesAvailable,
+    /// Too few shards are available to reconstruct the data
+    InsufficientShards,
+    /// Data failed hash or checksum verification
+    IntegrityError,
+    /// Data was corrupted on the wire between two services
+    TransportCorruption,
+    /// Storage capacity exhausted
+    StorageFull,
+    /// Metadata database (or cache) is unavailable
+    MetadataUnavailable,
+    /// A dependent service is temporarily unavailable
+    ServiceUnavailable,
+    /// Operation timed out
+    Timeout,
+    /// Unexpected internal failure
+    Internal,
+}
+
+impl ErrorCode {
+    /// Stable string form (e.g. `NODE_UNREACHABLE`)
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorCode::NotFound => "NOT_FOUND",
+            ErrorCode::AlreadyExists => "ALREADY_EXISTS",
+            ErrorCode::Conflict => "CONFLICT",
+            ErrorCode::InvalidArgument => "INVALID_ARGUMENT",
+            ErrorCode::Unauthenticated => "UNAUTHENTICATED",
+            ErrorCode::PermissionDenied => "PERMISSION_DENIED",
+            ErrorCode::RateLimited => "RATE_LIMITED",
+            ErrorCode::NodeUnreachable => "NODE_UNREACHABLE",
+            ErrorCode::NoNodesAvailable => "NO_NODES_AVAILABLE",
+            ErrorCode::InsufficientShards => "INSUFFICIENT_SHARDS",
+            ErrorCode::IntegrityError => "INTEGRITY_ERROR",
+            ErrorCode::TransportCorruption => "TRANSPORT_CORRUPTION",
+            ErrorCode::StorageFull => "STORAGE_FULL",
+            ErrorCode::MetadataUnavailable => "METADATA_UNAVAILABLE",
+            ErrorCode::ServiceUnavailable => "SERVICE_UNAVAILABLE",
+            ErrorCode::Timeout => "TIMEOUT",
+            ErrorCode::Internal => "INTERNAL",
+        }
+    }
+
+    /// Human-readable description safe to show to clients
+    pub fn description(self) -> &'static str {
+        match self {
+            ErrorCode::NotFound => "The requested resource does not exist",
+            ErrorCode::AlreadyExists => "The resource already exists",
+            ErrorCode::Conflict => "The request conflicts with the current state of the resource",
+            ErrorCode::InvalidArgument => "The request is invalid",
+            ErrorCode::Unauthenticated => "Authentication is required",
+            ErrorCode::PermissionDenied => "Access denied",
+            ErrorCode::RateLimited => "Please reduce your request rate",
+            ErrorCode::NodeUnreachable => "A storage node could not be reached",
+            ErrorCode::NoNodesAvailable => "No storage nodes are available",
+            ErrorCode::InsufficientShards => "Not enough shards are available to serve the data",
+            ErrorCode::IntegrityError => "Stored data failed integrity verification",
+            ErrorCode::TransportCorruption => "Data was corrupted in transit, please retry",
+            ErrorCode::StorageFull => "Storage capacity exhausted",
+            ErrorCode::MetadataUnavailable => "The metadata service is unavailable",
+            ErrorCode::ServiceUnavailable => "The service is temporarily unavailable",
+            ErrorCode::Timeout => "The operation timed out",
+            ErrorCode::Internal => "An internal error occurred",
+        }
+    }
+
+    /// HTTP status code for this error
+    pub fn http_status(self) -> u16 {
+        match self {
+            ErrorCode::NotFound => 404,
+            ErrorCode::AlreadyExists | ErrorCode::Conflict => 409,
+            ErrorCode::InvalidArgument => 400,
+            ErrorCode::Unauthenticated => 401,
+            ErrorCode::PermissionDenied => 403,
+            ErrorCode::RateLimited => 429,
+            ErrorCode::StorageFull => 507,
+            ErrorCode::Timeout => 504,
+            ErrorCode::NodeUnreachable
+            | ErrorCode::NoNodesAvailable
+            | ErrorCode::MetadataUnavailable
+            | ErrorCode::ServiceUnavailable
+            | ErrorCode::TransportCorruption => 503,
+            ErrorCode::InsufficientShards | ErrorCode::IntegrityError | ErrorCode::Internal => 500,
+        }
+    }
+
+    /// gRPC status code for this error
+    pub fn grpc_code(self) -> tonic::Code {
+        match self {
+            ErrorCode::NotFound => tonic::Code::NotFound,
+            ErrorCode::AlreadyExists => tonic::Code::AlreadyExists,
+            ErrorCode::Conflict => tonic::Code::FailedPrecondition,
+            ErrorCode::InvalidArgument => tonic::Code::InvalidArgument,
+            ErrorCode::Unauthenticated => tonic::Code::Unauthenticated,
+            ErrorCode::PermissionDenied => tonic::Code::PermissionDenied,
+            ErrorCode::RateLimited | ErrorCode::StorageFull => tonic::Code::ResourceExhausted,
+            ErrorCode::NodeUnreachable
+            | ErrorCode::NoNodesAvailable
+            | ErrorCode::MetadataUnavailable
+            | ErrorCode::ServiceUnavailable => tonic::Code::Unavailable,
+            ErrorCode::InsufficientShards
+            | ErrorCode::IntegrityError
+            | ErrorCode::TransportCorruption => tonic::Code::DataLoss,
+            ErrorCode::Timeout => tonic::Code::DeadlineExceeded,
+            ErrorCode::Internal => tonic::Code::Internal,
+        }
+    }
+
+    /// Whether retrying the same request later may succeed
+    pub fn is_retryable(self) -> bool {
+        matches!(
+            self,
+            ErrorCode::RateLimited
+                | ErrorCode::NodeUnreachable
+                | ErrorCode::NoNodesAvailable
+                | ErrorCode::MetadataUnavailable
+                | ErrorCode::ServiceUnavailable
+                | ErrorCode::Timeout
+                | ErrorCode::TransportCorruption
+        )
+    }
+
+    /// Best-effort code for an HTTP status without an explicit code
+    pub fn from_http_status(status: u16) -> Self {
+        match status {
+            400 => ErrorCode::InvalidArgument,
+            401 => ErrorCode::Unauthenticated,
+            403 => ErrorCode::PermissionDenied,
+            404 => ErrorCode::NotFound,
+            409 => ErrorCode::AlreadyExists,
+            429 => ErrorCode::RateLimited,
+            503 => ErrorCode::ServiceUnavailable,
+            504 => ErrorCode::Timeout,
+            507 => ErrorCode::StorageFull,
+            _ => ErrorCode::Internal,
+        }
+    }
+
+    /// Build a gRPC status carrying this code (and request ID) in metadata
+    pub fn to_status(self, message: impl Into<String>, request_id: Option<&str>) -> tonic::Status {
+        let mut status = tonic::Status::new(self.grpc_code(), message);
+        if let Ok(value) = self.as_str().parse() {
+            status.metadata_mut().insert(ERROR_CODE_HEADER, value);
+        }
+        if let Some(Ok(value)) = request_id.map(str::parse) {
+            status.metadata_mut().insert(REQUEST_ID_HEADER, value);
+        }
+        status
+    }
+
+    /// Recover the code from a gRPC status
+    ///
+    /// Uses the code attached by [`ErrorCode::to_status`] when present and
+    /// falls back to mapping the plain gRPC code.
+    pub fn from_status(status: &tonic::Status) -> Self {
+        if let Some(code) = status
+            .metadata()
+            .get(ERROR_CODE_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse().ok())
+        {
+            return code;
+        }
+
+        match status.code() {
+            tonic::Code::NotFound => ErrorCode::NotFound,
+            tonic::Code::AlreadyExists => ErrorCode::AlreadyExists,
+            tonic::Code::FailedPrecondition | tonic::Code::Aborted => ErrorCode::Conflict,
+            tonic::Code::InvalidArgument | tonic::Code::OutOfRange => ErrorCode::InvalidArgument,
+            tonic::Code::Unauthenticated => ErrorCode::Unauthenticated,
+            tonic::Code::PermissionDenied => ErrorCode::PermissionDenied,
+            tonic::Code::ResourceExhausted => ErrorCode::RateLimited,
+            tonic::Code::Unavailable => ErrorCode::ServiceUnavailable,
+            tonic::Code::DeadlineExceeded => ErrorCode::Timeout,
+            tonic::Code::DataLoss => ErrorCode::IntegrityError,
+            _ => ErrorCode::Internal,
+        }
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ErrorCode {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let code = match s {
+            "NOT_FOUND" => ErrorCode::NotFound,
+            "ALREADY_EXISTS" => ErrorCode::AlreadyExists,
+            "CONFLICT" => ErrorCode::Conflict,
+            "INVALID_ARGUMENT" => ErrorCode::InvalidArgument,
+            "UNAUTHENTICATED" => ErrorCode::Unauthenticated,
+            "PERMISSION_DENIED" => ErrorCode::PermissionDenied,
+            "RATE_LIMITED" => ErrorCode::RateLimited,
+            "NODE_UNREACHABLE" => ErrorCode::NodeUnreachable,
+            "NO_NODES_AVAILABLE" => ErrorCode::NoNodesAvailable,
+            "INSUFFICIENT_SHARDS" => ErrorCode::InsufficientShards,
+            "INTEGRITY_ERROR" => ErrorCode::IntegrityError,
+            "TRANSPORT_CORRUPTION" => ErrorCode::TransportCorruption,
+            "STORAGE_FULL" => ErrorCode::StorageFull,
+            "METADATA_UNAVAILABLE" => ErrorCode::MetadataUnavailable,
+            "SERVICE_UNAVAILABLE" => ErrorCode::ServiceUnavailable,
+            "TIMEOUT" => ErrorCode::Timeout,
+            "INTERNAL" => ErrorCode::Internal,
+            other => return Err(format!("Unknown error code: {}", other)),
+        };
+        Ok(code)
+    }
+}
+
+/// Errors that can be classified into the shared [`ErrorCode`] taxonomy
+pub trait HasErrorCode {
+    /// Classify this error
+    fn error_code(&self) -> ErrorCode;
+}
+
 /// Result type alias for CyxCloud operations
 pub type Result<T> = std::result::Result<T, CyxCloudError>;
 
@@ -72,6 +325,9 @@ pub enum CyxCloudError {
     #[error("Quorum not met: {achieved}/{required}")]
     QuorumNotMet { achieved: usize, required: usize },
 
+    #[error("Transport corruption: {0}")]
+    TransportCorruption(String),
+
     // ===== I/O Errors =====
     #[error("I/O error: {0}")]
     Io(#[from] std::io::Error),
@@ -89,12 +345,44 @@ pub enum CyxCloudError {
     Internal(String),
 }
 
+impl HasErrorCode for CyxCloudError {
+    fn error_code(&self) -> ErrorCode {
+        match self {
+            CyxCloudError::InsufficientShards { .. } => ErrorCode::InsufficientShards,
+            CyxCloudError::HashVerificationFailed | CyxCloudError::ChunkCorrupted => {
+                ErrorCode::IntegrityError
+            }
+            CyxCloudError::InvalidKeyLength { .. }
+            | CyxCloudError::ChunkTooLarge { .. }
+            | CyxCloudError::ChunkTooSmall { .. }
+            | CyxCloudError::InvalidChunkId(_)
+            | CyxCloudError::InvalidShardIndex { .. }
+            | CyxCloudError::ShardSizeMismatch { .. } => ErrorCode::InvalidArgument,
+            CyxCloudError::ChunkNotFound(_) | CyxCloudError::PeerNotFound(_) => ErrorCode::NotFound,
+            CyxCloudError::StorageFull { .. } => ErrorCode::StorageFull,
+            CyxCloudError::Network(_) | CyxCloudError::QuorumNotMet { .. } => {
+                ErrorCode::NodeUnreachable
+            }
+            CyxCloudError::ConnectionTimeout { .. } => ErrorCode::Timeout,
+            CyxCloudError::TransportCorruption(_) => ErrorCode::TransportCorruption,
+            _ => ErrorCode::Internal,
+        }
+    }
+}
+
 impl From<reed_solomon_erasure::Error> for CyxCloudError {
     fn from(err: reed_solomon_erasure::Error) -> Self {
         CyxCloudError::ErasureCoding(err.to_string())
     }
 }
 
+#[cfg(feature = "rs-simd")]
+impl From<reed_solomon_simd::Error> for CyxCloudError {
+    fn from(err: reed_solomon_simd::Error) -> Self {
+        CyxCloudError::ErasureCoding(err.to_string())
+    }
+}
+
 impl From<bincode::Error> for CyxCloudError {
     fn from(err: bincode::Error) -> Self {
         CyxCloudError::Serialization(err.to_string())
@@ -114,6 +402,49 @@ mod tests {
         assert_eq!(err.to_string(), "Insufficient shards: have 8, need 10");
     }
 
+    #[test]
+    fn test_error_code_round_trip() {
+        for code in [
+            ErrorCode::NotFound,
+            ErrorCode::NodeUnreachable,
+            ErrorCode::InsufficientShards,
+            ErrorCode::MetadataUnavailable,
+            ErrorCode::TransportCorruption,
+        ] {
+            assert_eq!(code.as_str().parse::<ErrorCode>().unwrap(), code);
+        }
+        assert!("BOGUS".parse::<ErrorCode>().is_err());
+    }
+
+    #[test]
+    fn test_error_code_status_round_trip() {
+        let status = ErrorCode::NoNodesAvailable.to_status("no nodes", Some("req-1"));
+        assert_eq!(status.code(), tonic::Code::Unavailable);
+        assert_eq!(ErrorCode::from_status(&status), ErrorCode::NoNodesAvailable);
+        assert_eq!(status.metadata().get(REQUEST_ID_HEADER).unwrap(), "req-1");
+
+        // Plain statuses fall back to the gRPC code
+        let plain = tonic::Status::deadline_exceeded("slow");
+        assert_eq!(ErrorCode::from_status(&plain), ErrorCode::Timeout);
+    }
+
+    #[test]
+    fn test_core_error_codes() {
+        let err = CyxCloudError::InsufficientShards {
+            available: 8,
+            required: 10,
+        };
+        assert_eq!(err.error_code(), ErrorCode::InsufficientShards);
+        assert_eq!(
+            CyxCloudError::ChunkCorrupted.error_code(),
+            ErrorCode::IntegrityError
+        );
+        assert_eq!(ErrorCode::NodeUnreachable.http_status(), 503);
+        assert!(ErrorCode::NodeUnreachable.is_retryable());
+        assert!(!ErrorCode::InsufficientShards.is_retryable());
+        assert!(ErrorCode::TransportCorruption.is_retryable());
+    }
+
     #[test]
     fn test_error_from_io() {
         let io_err = std::io::Error::new(std::io::ErrorKind::NotFound, "file not found");
@@ -14,10 +14,15 @@ pub mod erasure;
 pub mod error;
 pub mod tls;
 
-pub use chunk::{reassemble_chunks, split_into_chunks, Chunk, ChunkId, ChunkMetadata};
+pub use chunk::{
+    reassemble_chunks, split_bytes_into_chunks, split_into_chunks, verify_wire_checksum,
+    wire_checksum, Chunk, ChunkId, ChunkMetadata,
+};
 pub use crypto::{decrypt, encrypt, ContentHash, EncryptedData, EncryptionKey};
-pub use erasure::{ErasureConfig, ErasureEncoder, ShardData};
-pub use error::{CyxCloudError, Result};
+pub use erasure::{
+    EncodedStripe, ErasureBackend, ErasureConfig, ErasureEncoder, ShardData, StripeEncoder,
+};
+pub use error::{CyxCloudError, ErrorCode, HasErrorCode, Result};
 
 /// Default erasure coding configuration
 /// - 10 data shards: minimum required to reconstruct
@@ -47,3 +52,13 @@ pub fn erasure_config_from_env() -> (usize, usize, usize) {
 pub const MIN_CHUNK_SIZE: usize = 256 * 1024; // 256 KB
 pub const DEFAULT_CHUNK_SIZE: usize = 4 * 1024 * 1024; // 4 MB
 pub const MAX_CHUNK_SIZE: usize = 64 * 1024 * 1024; // 64 MB
+
+/// Stripe size for streamed uploads, which are encoded stripe by stripe
+/// (each stripe stored as one chunk) to bound memory per upload
+pub const DEFAULT_STRIPE_SIZE: usize = 1024 * 1024; // 1 MB
+
+/// Objects smaller than this are stored as replicated blobs instead of being
+/// erasure coded (10+4 coding of a tiny object is mostly padding)
+pub const SMALL_OBJECT_THRESHOLD: usize = MIN_CHUNK_SIZE;
+/// Copies kept of a replicated small object
+pub const SMALL_OBJECT_REPLICAS: usize = 3;
@@ -0,0 +1,189 @@
+// CyxCloud dashboard
+//
+// Polls the cluster overview API with an admin token kept in session
+// storage. Everything is rendered with textContent, never as HTML, since
+// node addresses and event details come from the cluster.
+
+"use strict";
+
+const REFRESH_MS = 10000;
+const TOKEN_KEY = "cyxcloud.dashboard.token";
+
+const $ = (id) => document.getElementById(id);
+let timer = null;
+
+function formatBytes(bytes) {
+  const units = ["B", "KB", "MB", "GB", "TB", "PB"];
+  let value = Number(bytes) || 0;
+  let unit = 0;
+  while (value >= 1024 && unit < units.length - 1) {
+    value /= 1024;
+    unit += 1;
+  }
+  return `${value.toFixed(unit === 0 ? 0 : 1)} ${units[unit]}`;
+}
+
+function formatTime(value) {
+  return value ? new Date(value).toLocaleString() : "never";
+}
+
+function describeEvent(event) {
+  const data = event.data || {};
+  switch (event.type) {
+    case "NodeJoined":
+      return `Node ${data.node_id} joined (${data.address})`;
+    case "NodeLeft":
+      return `Node ${data.node_id} left: ${data.reason}`;
+    case "NodeHealthChanged":
+      return `Node ${data.node_id} is ${data.status}` + (data.details ? `: ${data.details}` : "");
+    default: {
+      const fields = Object.entries(data)
+        .map(([key, value]) => `${key}=${value}`)
+        .join(" ");
+      return fields ? `${event.type} ${fields}` : event.type;
+    }
+  }
+}
+
+class AuthError extends Error {}
+
+async function api(path) {
+  const response = await fetch(path, {
+    headers: { Authorization: `Bearer ${sessionStorage.getItem(TOKEN_KEY)}` },
+  });
+  if (response.status === 401 || response.status === 403) {
+    throw new AuthError("The token was rejected or lacks the node:admin permission");
+  }
+  if (!response.ok) {
+    let message = `${response.status} ${response.statusText}`;
+    try {
+      message = (await response.json()).error || message;
+    } catch (_) {
+      // Not a JSON error body
+    }
+    throw new Error(message);
+  }
+  return response.json();
+}
+
+function renderOverview(overview) {
+  const nodes = overview.nodes;
+  $("nodes-total").textContent = nodes.total;
+  $("nodes-detail").textContent =
+    `${nodes.online} online, ${nodes.offline} offline, ${nodes.draining} draining`;
+
+  const capacity = overview.capacity;
+  const percent = capacity.utilization_percent;
+  $("capacity-used").textContent = `${percent.toFixed(1)}%`;
+  $("capacity-bar").style.width = `${Math.min(percent, 100)}%`;
+  $("capacity-detail").textContent =
+    `${formatBytes(capacity.storage_used)} of ${formatBytes(capacity.storage_total - capacity.storage_reserved)}, ` +
+    `${formatBytes(capacity.online_storage_available)} writable`;
+
+  const chunks = overview.chunks;
+  $("chunks-healthy").textContent = chunks.healthy;
+  $("chunks-detail").textContent =
+    `${chunks.under_replicated} under-replicated, ${chunks.missing} missing`;
+
+  const repairs = overview.repairs;
+  $("repairs-pending").textContent = repairs.pending;
+  $("repairs-detail").textContent =
+    `${repairs.in_progress} in progress, ${repairs.failed} failed`;
+
+  const events = $("events");
+  events.replaceChildren();
+  if (overview.recent_events.length === 0) {
+    const item = document.createElement("li");
+    item.className = "muted";
+    item.textContent = "No events since the gateway started";
+    events.append(item);
+  }
+  for (const event of overview.recent_events) {
+    const item = document.createElement("li");
+    const time = document.createElement("time");
+    time.dateTime = event.at;
+    time.textContent = formatTime(event.at);
+    item.append(time, describeEvent(event));
+    events.append(item);
+  }
+
+  $("updated").textContent = `Updated ${formatTime(overview.generated_at)}`;
+}
+
+function renderNodes(nodes) {
+  const rows = $("node-rows");
+  rows.replaceChildren();
+  for (const node of nodes) {
+    const row = document.createElement("tr");
+    const cells = [
+      [node.peer_id || node.id, ""],
+      [node.grpc_address, ""],
+      [node.status, `status-${node.status}`],
+      [formatBytes(node.storage_used), "num"],
+      [formatBytes(node.storage_total), "num"],
+      [node.chunk_count, "num"],
+      [formatTime(node.last_heartbeat), ""],
+    ];
+    for (const [text, className] of cells) {
+      const cell = document.createElement("td");
+      cell.textContent = text;
+      cell.className = className;
+      row.append(cell);
+    }
+    rows.append(row);
+  }
+}
+
+async function refresh() {
+  try {
+    const [overview, nodes] = await Promise.all([
+      api("/api/v1/cluster/overview"),
+      api("/api/v1/cluster/nodes"),
+    ]);
+    renderOverview(overview);
+    renderNodes(nodes);
+    $("error").hidden = true;
+    $("overview").hidden = false;
+  } catch (error) {
+    if (error instanceof AuthError) {
+      signOut(error.message);
+      return;
+    }
+    $("error").textContent = `Failed to load cluster status: ${error.message}`;
+    $("error").hidden = false;
+  }
+}
+
+function signIn() {
+  $("login").hidden = true;
+  $("sign-out").hidden = false;
+  refresh();
+  timer = setInterval(refresh, REFRESH_MS);
+}
+
+function signOut(message) {
+  sessionStorage.removeItem(TOKEN_KEY);
+  clearInterval(timer);
+  $("overview").hidden = true;
+  $("sign-out").hidden = true;
+  $("updated").textContent = "";
+  $("login").hidden = false;
+  $("error").textContent = message || "";
+  $("error").hidden = !message;
+}
+
+$("login").addEventListener("submit", (event) => {
+  event.preventDefault();
+  const token = $("token").value.trim().replace(/^Bearer\s+/i, "");
+  $("token").value = "";
+  sessionStorage.setItem(TOKEN_KEY, token);
+  signIn();
+});
+
+$("sign-out").addEventListener("click", () => signOut());
+
+if (sessionStorage.getItem(TOKEN_KEY)) {
+  signIn();
+} else {
+  signOut();
+}
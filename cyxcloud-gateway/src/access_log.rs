@@ -0,0 +1,663 @@
+//! S3 Server Access Logging
+//!
+//! Buckets can ask for their S3 requests to be logged (`PUT /:bucket?logging`)
+//! to a target bucket of the same tenant. A middleware on the S3 routes turns
+//! every request to a logged bucket into one line of the S3 server access log
+//! format and buffers it; the access log daemon periodically writes each
+//! bucket's buffered lines as one object named
+//! `{target_prefix}{YYYY-mm-dd-HH-MM-SS}-{id}` into the target bucket.
+//!
+//! Log objects are written through [`AppState`], so logging works the same
+//! with in-memory and database storage. Requests rejected before their tenant
+//! is known (throttled or unauthenticated) are not logged.
+
+use crate::s3_api::S3ErrorCode;
+use crate::state::AppState;
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderMap, Method},
+    middleware::Next,
+    response::Response,
+};
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+use tokio::time::interval;
+use tracing::{debug, info, warn};
+
+/// Content type of access log objects
+const LOG_CONTENT_TYPE: &str = "text/plain";
+
+/// Access logging target of a bucket
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BucketLogging {
+    /// Bucket receiving the log objects (same tenant)
+    pub target_bucket: String,
+    /// Key prefix of the log objects
+    pub target_prefix: String,
+}
+
+/// Access log configuration
+#[derive(Debug, Clone)]
+pub struct AccessLogConfig {
+    /// How often buffered lines are written out
+    pub flush_interval: Duration,
+    /// Lines buffered across all buckets before new ones are dropped
+    pub max_buffered: usize,
+}
+
+impl Default for AccessLogConfig {
+    fn default() -> Self {
+        Self {
+            flush_interval: Duration::from_secs(5 * 60),
+            max_buffered: 100_000,
+        }
+    }
+}
+
+impl AccessLogConfig {
+    /// Create configuration from environment variables
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            flush_interval: Duration::from_secs(
+                std::env::var("ACCESS_LOG_FLUSH_SECS")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(defaults.flush_interval.as_secs()),
+            ),
+            max_buffered: std::env::var("ACCESS_LOG_MAX_BUFFERED")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.max_buffered),
+        }
+    }
+}
+
+/// Who made a request, recorded by the S3 handlers once it is authenticated
+#[derive(Debug, Clone)]
+struct Requester {
+    tenant: String,
+    user: Option<String>,
+    /// User the request's bandwidth is billed to
+    payer: Option<String>,
+}
+
+tokio::task_local! {
+    static REQUESTER: OnceLock<Requester>;
+}
+
+/// Record the tenant and user of the S3 request being handled
+///
+/// `user` is None for anonymous requests. Outside the access log middleware
+/// this does nothing.
+pub fn set_requester(tenant: &str, user: Option<&str>) {
+    record_requester(Requester {
+        tenant: tenant.to_string(),
+        user: user.map(str::to_string),
+        payer: user.map(str::to_string),
+    });
+}
+
+/// Record an anonymous download of a public object of `tenant`
+///
+/// Its bandwidth is billed to the bucket's `owner`.
+pub fn set_public_requester(tenant: &str, owner: Option<&str>) {
+    record_requester(Requester {
+        tenant: tenant.to_string(),
+        user: None,
+        payer: owner.map(str::to_string),
+    });
+}
+
+fn record_requester(requester: Requester) {
+    let _ = REQUESTER.try_with(|current| {
+        let _ = current.set(requester);
+    });
+}
+
+/// Tenant and user the S3 request being handled is billed to
+///
+/// Only known inside the access log middleware, once the handler has
+/// authenticated the request. None for anonymous requests, except for
+/// downloads of public objects.
+pub fn current_payer() -> Option<(String, String)> {
+    REQUESTER
+        .try_with(|requester| requester.get().cloned())
+        .ok()
+        .flatten()
+        .and_then(|requester| Some((requester.tenant, requester.payer?)))
+}
+
+/// Tenant the S3 request being handled was authenticated for
+///
+/// Only known inside the access log middleware, once the handler has
+/// authenticated the request.
+pub fn current_tenant() -> Option<String> {
+    REQUESTER
+        .try_with(|requester| requester.get().map(|r| r.tenant.clone()))
+        .ok()
+        .flatten()
+}
+
+/// One logged S3 request
+#[derive(Debug, Clone)]
+pub struct AccessLogEntry {
+    /// Tenant owning the bucket
+    pub bucket_owner: String,
+    pub bucket: String,
+    pub time: DateTime<Utc>,
+    pub remote_ip: String,
+    /// Authenticated user, None for anonymous requests
+    pub requester: Option<String>,
+    pub request_id: String,
+    /// S3 operation, e.g. `REST.GET.OBJECT`
+    pub operation: String,
+    /// Object key as sent in the URL (percent-encoded)
+    pub key: Option<String>,
+    /// Request line, e.g. `GET /photos/cat.jpg HTTP/1.1`
+    pub request_uri: String,
+    pub status: u16,
+    /// S3 error code of failed requests
+    pub error_code: Option<String>,
+    pub bytes_sent: Option<u64>,
+    pub object_size: Option<u64>,
+    /// Time until the response headers were ready
+    pub total_time_ms: u64,
+    pub referer: Option<String>,
+    pub user_agent: Option<String>,
+    pub authenticated: bool,
+    pub host: Option<String>,
+}
+
+impl AccessLogEntry {
+    /// Format the entry as an S3 server access log line
+    ///
+    /// Fields CyxCloud has no equivalent for (version ID, host ID, signature
+    /// version, cipher suite, TLS version) are written as `-`.
+    pub fn to_line(&self) -> String {
+        fn field(value: Option<&str>) -> &str {
+            value.filter(|v| !v.is_empty()).unwrap_or("-")
+        }
+        fn number(value: Option<u64>) -> String {
+            value.map_or_else(|| "-".to_string(), |v| v.to_string())
+        }
+        fn quoted(value: Option<&str>) -> String {
+            match value {
+                Some(v) => format!("\"{}\"", v.replace('"', "\\\"")),
+                None => "-".to_string(),
+            }
+        }
+
+        let auth_type = if self.authenticated {
+            "AuthHeader"
+        } else {
+            "-"
+        };
+
+        [
+            field(Some(&self.bucket_owner)).to_string(),
+            field(Some(&self.bucket)).to_string(),
+            self.time.format("[%d/%b/%Y:%H:%M:%S %z]").to_string(),
+            field(Some(&self.remote_ip)).to_string(),
+            field(self.requester.as_deref()).to_string(),
+            field(Some(&self.request_id)).to_string(),
+            self.operation.clone(),
+            field(self.key.as_deref()).to_string(),
+            quoted(Some(&self.request_uri)),
+            self.status.to_string(),
+            field(self.error_code.as_deref()).to_string(),
+            number(self.bytes_sent),
+            number(self.object_size),
+            self.total_time_ms.to_string(),
+            "-".to_string(), // turn-around time
+            quoted(self.referer.as_deref()),
+            quoted(self.user_agent.as_deref()),
+            "-".to_string(), // version ID
+            "-".to_string(), // host ID
+            "-".to_string(), // signature version
+            "-".to_string(), // cipher suite
+            auth_type.to_string(),
+            field(self.host.as_deref()).to_string(),
+            "-".to_string(), // TLS version
+        ]
+        .join(" ")
+    }
+}
+
+/// S3 operation name of a request
+///
+/// `key` is the object key, if the request addresses an object.
+fn operation_name(method: &Method, key: Option<&str>, query: Option<&str>) -> String {
+    let has_param =
+        |name: &str| query.is_some_and(|q| q.split('&').any(|p| p.split('=').next() == Some(name)));
+
+    let resource = match key {
+        Some(_) if has_param("select") => "SELECT",
+        Some(_) => "OBJECT",
+        None if has_param("logging") => "LOGGING_STATUS",
+        None if has_param("delete") => "MULTI_OBJECT_DELETE",
+        None => "BUCKET",
+    };
+    format!("REST.{}.{}", method.as_str(), resource)
+}
+
+/// Bucket and (percent-encoded) key of an S3 request path
+fn split_path(path: &str) -> Option<(&str, Option<&str>)> {
+    let path = path.strip_prefix('/')?;
+    let (bucket, key) = match path.split_once('/') {
+        Some((bucket, key)) => (bucket, Some(key).filter(|k| !k.is_empty())),
+        None => (path, None),
+    };
+    (!bucket.is_empty()).then_some((bucket, key))
+}
+
+fn header_str<'a>(headers: &'a HeaderMap, name: header::HeaderName) -> Option<&'a str> {
+    headers.get(name).and_then(|v| v.to_str().ok())
+}
+
+fn content_length(headers: &HeaderMap) -> Option<u64> {
+    header_str(headers, header::CONTENT_LENGTH).and_then(|v| v.parse().ok())
+}
+
+/// Buffered lines of one logged bucket
+struct LogBatch {
+    target: BucketLogging,
+    lines: Vec<String>,
+}
+
+/// Tenant and bucket name
+type BucketKey = (String, String);
+
+/// Buffer of access log lines, flushed by the access log daemon
+pub struct AccessLogger {
+    max_buffered: usize,
+    /// Logging targets looked up since the last flush (None = not logged)
+    targets: Mutex<HashMap<BucketKey, Option<BucketLogging>>>,
+    batches: Mutex<HashMap<BucketKey, LogBatch>>,
+    buffered: std::sync::atomic::AtomicUsize,
+    dropped: std::sync::atomic::AtomicU64,
+}
+
+impl AccessLogger {
+    /// Create an access logger buffering at most `max_buffered` lines
+    pub fn new(max_buffered: usize) -> Self {
+        Self {
+            max_buffered,
+            targets: Mutex::new(HashMap::new()),
+            batches: Mutex::new(HashMap::new()),
+            buffered: std::sync::atomic::AtomicUsize::new(0),
+            dropped: std::sync::atomic::AtomicU64::new(0),
+        }
+    }
+
+    /// Logging target of a bucket, looked up at most once per flush interval
+    async fn target(&self, state: &AppState, tenant: &str, bucket: &str) -> Option<BucketLogging> {
+        let key = (tenant.to_string(), bucket.to_string());
+        let cached = self
+            .targets
+            .lock()
+            .expect("access log lock poisoned")
+            .get(&key)
+            .cloned();
+        if let Some(target) = cached {
+            return target;
+        }
+
+        let target = match state.get_bucket_logging(tenant, bucket).await {
+            Ok(target) => target,
+            Err(e) => {
+                debug!(error = %e, bucket = bucket, "Failed to look up bucket logging");
+                return None;
+            }
+        };
+        self.targets
+            .lock()
+            .expect("access log lock poisoned")
+            .insert(key, target.clone());
+        target
+    }
+
+    /// Forget the cached logging target of a bucket after it changed
+    pub fn forget(&self, tenant: &str, bucket: &str) {
+        self.targets
+            .lock()
+            .expect("access log lock poisoned")
+            .remove(&(tenant.to_string(), bucket.to_string()));
+    }
+
+    /// Buffer an entry for a logged bucket
+    pub fn record(&self, target: BucketLogging, entry: &AccessLogEntry) {
+        use std::sync::atomic::Ordering;
+
+        if self.buffered.load(Ordering::Relaxed) >= self.max_buffered {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+
+        let key = (entry.bucket_owner.clone(), entry.bucket.clone());
+        let mut batches = self.batches.lock().expect("access log lock poisoned");
+        let batch = batches.entry(key).or_insert_with(|| LogBatch {
+            target: target.clone(),
+            lines: Vec::new(),
+        });
+        batch.target = target;
+        batch.lines.push(entry.to_line());
+        self.buffered.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Take every buffered batch and reset the target cache
+    fn take(&self) -> HashMap<BucketKey, LogBatch> {
+        use std::sync::atomic::Ordering;
+
+        self.targets
+            .lock()
+            .expect("access log lock poisoned")
+            .clear();
+        let batches = std::mem::take(&mut *self.batches.lock().expect("access log lock poisoned"));
+        self.buffered.store(0, Ordering::Relaxed);
+
+        let dropped = self.dropped.swap(0, Ordering::Relaxed);
+        if dropped > 0 {
+            warn!(dropped, "Access log buffer full, entries were dropped");
+        }
+        batches
+    }
+
+    /// Write the buffered lines of every bucket as one log object each
+    ///
+    /// Returns the number of log objects written.
+    pub async fn flush(&self, state: &AppState) -> usize {
+        let now = Utc::now();
+        let mut written = 0;
+
+        for ((tenant, bucket), batch) in self.take() {
+            let key = log_object_key(&batch.target.target_prefix, now);
+            let mut body = batch.lines.join("\n");
+            body.push('\n');
+
+            match state
+                .put_object(
+                    &tenant,
+                    &batch.target.target_bucket,
+                    &key,
+                    Bytes::from(body),
+                    LOG_CONTENT_TYPE,
+                    None,
+                )
+                .await
+            {
+                Ok(_) => {
+                    debug!(
+                        bucket = %bucket,
+                        target = %batch.target.target_bucket,
+                        key = %key,
+                        lines = batch.lines.len(),
+                        "Access log written"
+                    );
+                    written += 1;
+                }
+                Err(e) => {
+                    warn!(
+                        error = %e,
+                        bucket = %bucket,
+                        target = %batch.target.target_bucket,
+                        lines = batch.lines.len(),
+                        "Failed to write access log, entries dropped"
+                    );
+                }
+            }
+        }
+
+        written
+    }
+}
+
+/// Key of a log object written at `time`
+fn log_object_key(prefix: &str, time: DateTime<Utc>) -> String {
+    format!(
+        "{}{}-{:016X}",
+        prefix,
+        time.format("%Y-%m-%d-%H-%M-%S"),
+        rand::random::<u64>()
+    )
+}
+
+/// Middleware logging S3 requests to buckets with access logging enabled
+pub async fn log_requests(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some((bucket, key)) = split_path(request.uri().path()) else {
+        return next.run(request).await;
+    };
+    let bucket = bucket.to_string();
+    let key = key.map(str::to_string);
+
+    let time = Utc::now();
+    let started = Instant::now();
+    let method = request.method().clone();
+    let operation = operation_name(&method, key.as_deref(), request.uri().query());
+    let request_uri = format!(
+        "{} {} {:?}",
+        method,
+        request.uri().path_and_query().map_or("/", |p| p.as_str()),
+        request.version()
+    );
+    let headers = request.headers();
+    let remote_ip = crate::auth_api::extract_client_ip();
+    let referer = header_str(headers, header::REFERER).map(str::to_string);
+    let user_agent = header_str(headers, header::USER_AGENT).map(str::to_string);
+    let host = header_str(headers, header::HOST).map(str::to_string);
+    let authenticated = headers.contains_key(header::AUTHORIZATION);
+    let request_size = content_length(headers);
+
+    let (response, requester) = REQUESTER
+        .scope(OnceLock::new(), async move {
+            let response = next.run(request).await;
+            let requester = REQUESTER.with(|requester| requester.get().cloned());
+            (response, requester)
+        })
+        .await;
+    let total_time_ms = started.elapsed().as_millis() as u64;
+
+    let Some(requester) = requester else {
+        return response;
+    };
+    let logger = state.access_logger();
+    let Some(target) = logger.target(&state, &requester.tenant, &bucket).await else {
+        return response;
+    };
+
+    let bytes_sent = content_length(response.headers());
+    let object_size = if key.is_none() {
+        None
+    } else if method == Method::PUT {
+        request_size
+    } else if method == Method::GET || method == Method::HEAD {
+        bytes_sent.filter(|_| response.status().is_success())
+    } else {
+        None
+    };
+    let entry = AccessLogEntry {
+        bucket_owner: requester.tenant,
+        bucket,
+        time,
+        remote_ip,
+        requester: requester.user,
+        request_id: crate::request_id::current().unwrap_or_default(),
+        operation,
+        key,
+        request_uri,
+        status: response.status().as_u16(),
+        error_code: response
+            .extensions()
+            .get::<S3ErrorCode>()
+            .map(|code| code.0.to_string()),
+        bytes_sent,
+        object_size,
+        total_time_ms,
+        referer,
+        user_agent,
+        authenticated,
+        host,
+    };
+    logger.record(target, &entry);
+
+    response
+}
+
+/// Access log daemon writing buffered log lines to their target buckets
+pub struct AccessLogDaemon {
+    config: AccessLogConfig,
+}
+
+impl AccessLogDaemon {
+    /// Create a new access log daemon
+    pub fn new(config: AccessLogConfig) -> Self {
+        Self { config }
+    }
+
+    /// Start the flush loop (background task)
+    pub fn start(self: Arc<Self>, state: Arc<AppState>) -> JoinHandle<()> {
+        let daemon = self;
+
+        tokio::spawn(async move {
+            let mut timer = interval(daemon.config.flush_interval);
+            // The first tick completes immediately, there is nothing to flush yet
+            timer.tick().await;
+
+            info!(
+                interval_secs = daemon.config.flush_interval.as_secs(),
+                "Access log daemon started"
+            );
+
+            loop {
+                timer.tick().await;
+                let written = state.access_logger().flush(&state).await;
+                if written > 0 {
+                    debug!(objects = written, "Access logs flushed");
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry() -> AccessLogEntry {
+        AccessLogEntry {
+            bucket_owner: "acme".to_string(),
+            bucket: "photos".to_string(),
+            time: DateTime::parse_from_rfc3339("2024-02-06T00:00:38Z")
+                .unwrap()
+                .with_timezone(&Utc),
+            remote_ip: "192.0.2.3".to_string(),
+            requester: Some("user-1".to_string()),
+            request_id: "3E57427F3EXAMPLE".to_string(),
+            operation: "REST.GET.OBJECT".to_string(),
+            key: Some("cats/tom%20cat.jpg".to_string()),
+            request_uri: "GET /photos/cats/tom%20cat.jpg HTTP/1.1".to_string(),
+            status: 200,
+            error_code: None,
+            bytes_sent: Some(2662992),
+            object_size: Some(2662992),
+            total_time_ms: 70,
+            referer: None,
+            user_agent: Some("aws-cli/2.15".to_string()),
+            authenticated: true,
+            host: Some("s3.example.com".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_access_log_line() {
+        assert_eq!(
+            entry().to_line(),
+            "acme photos [06/Feb/2024:00:00:38 +0000] 192.0.2.3 user-1 3E57427F3EXAMPLE \
+             REST.GET.OBJECT cats/tom%20cat.jpg \"GET /photos/cats/tom%20cat.jpg HTTP/1.1\" \
+             200 - 2662992 2662992 70 - - \"aws-cli/2.15\" - - - - AuthHeader s3.example.com -"
+        );
+
+        let mut failed = entry();
+        failed.status = 404;
+        failed.error_code = Some("NoSuchKey".to_string());
+        failed.requester = None;
+        failed.bytes_sent = None;
+        failed.object_size = None;
+        failed.user_agent = Some("say \"hi\"".to_string());
+        let line = failed.to_line();
+        assert!(line.contains(" 192.0.2.3 - 3E57427F3EXAMPLE "));
+        assert!(line.contains(" 404 NoSuchKey - - 70 "));
+        assert!(line.contains(r#""say \"hi\"""#));
+    }
+
+    #[test]
+    fn test_operation_name() {
+        assert_eq!(
+            operation_name(&Method::GET, Some("a.txt"), None),
+            "REST.GET.OBJECT"
+        );
+        assert_eq!(
+            operation_name(&Method::POST, Some("a.csv"), Some("select&select-type=2")),
+            "REST.POST.SELECT"
+        );
+        assert_eq!(
+            operation_name(&Method::GET, None, Some("list-type=2&prefix=a")),
+            "REST.GET.BUCKET"
+        );
+        assert_eq!(
+            operation_name(&Method::PUT, None, Some("logging")),
+            "REST.PUT.LOGGING_STATUS"
+        );
+        assert_eq!(
+            operation_name(&Method::POST, None, Some("delete")),
+            "REST.POST.MULTI_OBJECT_DELETE"
+        );
+    }
+
+    #[test]
+    fn test_split_path() {
+        assert_eq!(split_path("/photos"), Some(("photos", None)));
+        assert_eq!(split_path("/photos/"), Some(("photos", None)));
+        assert_eq!(
+            split_path("/photos/cats/tom.jpg"),
+            Some(("photos", Some("cats/tom.jpg")))
+        );
+        assert_eq!(split_path("/"), None);
+    }
+
+    #[test]
+    fn test_record_and_take() {
+        let logger = AccessLogger::new(2);
+        let target = BucketLogging {
+            target_bucket: "logs".to_string(),
+            target_prefix: "photos/".to_string(),
+        };
+        for _ in 0..3 {
+            logger.record(target.clone(), &entry());
+        }
+
+        let batches = logger.take();
+        let batch = &batches[&("acme".to_string(), "photos".to_string())];
+        assert_eq!(batch.lines.len(), 2);
+        assert_eq!(batch.target, target);
+        assert!(logger.take().is_empty());
+    }
+
+    #[test]
+    fn test_log_object_key() {
+        let time = DateTime::parse_from_rfc3339("2024-02-06T13:05:09Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let key = log_object_key("logs/photos-", time);
+        assert!(key.starts_with("logs/photos-2024-02-06-13-05-09-"));
+        assert_eq!(key.len(), "logs/photos-2024-02-06-13-05-09-".len() + 16);
+    }
+}
@@ -0,0 +1,121 @@
+//! Canned ACLs and public objects
+//!
+//! CyxCloud has no per-grantee ACLs: a bucket or object is either `private`
+//! or `public-read`. The ACL is set with the `x-amz-acl` header when the
+//! bucket or object is created, or later with `PUT ?acl` (the header or an
+//! `AccessControlPolicy` body granting `READ` to the AllUsers group), and
+//! `GET ?acl` returns it as an S3 `AccessControlPolicy`.
+//!
+//! A public-read bucket lets anyone read all of its objects, a public-read
+//! object only itself; a new version of a key starts out private. A bucket
+//! policy statement allowing `s3:GetObject` to `"*"` makes objects public
+//! the same way, and one denying it keeps them private whatever the flags
+//! say.
+//!
+//! Public objects are served without authentication under
+//! `/public/{tenant}/{bucket}/{key}`, as an anonymous request to `/s3` only
+//! reaches the default tenant. Their downloads are accounted to the bucket's
+//! owner and count against the owner's egress cap.
+
+use crate::bucket_policy::Decision;
+use crate::s3_api::{S3Error, S3Result};
+
+/// Canned ACL header of bucket and object writes
+pub const ACL_HEADER: &str = "x-amz-acl";
+
+/// Grantee URI of the group of all users, anonymous ones included
+pub const ALL_USERS_URI: &str = "http://acs.amazonaws.com/groups/global/AllUsers";
+
+/// ACL of a bucket or object
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CannedAcl {
+    /// Only the owning tenant has access
+    #[default]
+    Private,
+    /// Anyone may read
+    PublicRead,
+}
+
+impl CannedAcl {
+    /// Parse an `x-amz-acl` value
+    pub fn parse(value: &str) -> S3Result<Self> {
+        match value.trim() {
+            "private" => Ok(CannedAcl::Private),
+            "public-read" => Ok(CannedAcl::PublicRead),
+            other => Err(S3Error::InvalidRequest(format!(
+                "Unsupported canned ACL: {} (expected private or public-read)",
+                other
+            ))),
+        }
+    }
+
+    /// ACL of a public (`true`) or private resource
+    pub fn from_public_read(public_read: bool) -> Self {
+        if public_read {
+            CannedAcl::PublicRead
+        } else {
+            CannedAcl::Private
+        }
+    }
+
+    /// Whether anyone may read
+    pub fn is_public_read(self) -> bool {
+        self == CannedAcl::PublicRead
+    }
+
+    /// `x-amz-acl` value
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CannedAcl::Private => "private",
+            CannedAcl::PublicRead => "public-read",
+        }
+    }
+}
+
+/// ACL and owner of a bucket
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BucketAcl {
+    /// Whether anyone may read the bucket's objects
+    pub public_read: bool,
+    /// User public downloads are billed to, None with in-memory storage
+    pub owner: Option<String>,
+}
+
+/// Whether anyone may read an object
+///
+/// `decision` is the bucket policy's decision on an anonymous
+/// `s3:GetObject`, which wins over the bucket's and the object's flags.
+pub fn is_public(decision: Decision, bucket_public_read: bool, object_public_read: bool) -> bool {
+    match decision {
+        Decision::Allow => true,
+        Decision::Deny => false,
+        Decision::NotApplicable => bucket_public_read || object_public_read,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_canned_acl_parse() {
+        assert_eq!(CannedAcl::parse("private").unwrap(), CannedAcl::Private);
+        assert_eq!(
+            CannedAcl::parse(" public-read ").unwrap(),
+            CannedAcl::PublicRead
+        );
+        assert!(CannedAcl::parse("public-read-write").is_err());
+        assert!(CannedAcl::parse("authenticated-read").is_err());
+        assert_eq!(CannedAcl::from_public_read(true).as_str(), "public-read");
+        assert!(!CannedAcl::default().is_public_read());
+    }
+
+    #[test]
+    fn test_is_public() {
+        assert!(!is_public(Decision::NotApplicable, false, false));
+        assert!(is_public(Decision::NotApplicable, true, false));
+        assert!(is_public(Decision::NotApplicable, false, true));
+        assert!(is_public(Decision::Allow, false, false));
+        assert!(!is_public(Decision::Deny, true, true));
+    }
+}
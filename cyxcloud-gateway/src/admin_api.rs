@@ -0,0 +1,1478 @@
+//! Admin REST API endpoints
+//!
+//! Provides operator endpoints for:
+//! - Cluster topology export (regions -> datacenters -> nodes) as JSON or DOT
+//! - Configuration hot reload
+//! - Chunk re-association after a node's chunk store was migrated offline
+//! - Bucket replication rules and their progress
+//! - Per-bucket durability mode (erasure coding only or with shard replicas)
+//! - Archival flag of nodes, which hold archive storage class objects
+//! - Rebalancer what-if simulation for planned node changes
+//! - Master key rotation for server-side encryption
+//! - Scoped service-account tokens for CI pipelines and ML trainers
+//! - Payout reports of distributed payment epochs
+//! - Slashing evidence from failed proof-of-storage challenges
+//! - Faults injected into calls to storage nodes (`fault-injection` builds)
+//!
+//! All endpoints require a token with the `node:admin` permission.
+
+use crate::audit::{audit_log, AuditEvent};
+use crate::auth::{is_valid_tenant, permissions, AuthError, AuthService, Claims};
+use crate::auth_api::{extract_and_validate_token, ApiError};
+use crate::rebalancer_daemon::RebalancerDaemonConfig;
+use crate::reload::ReloadReport;
+use crate::replication::{ReplicationTarget, TARGET_CYXCLOUD};
+use crate::state::KeyRotationReport;
+use crate::AppState;
+use axum::{
+    extract::{Json, Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{delete, get, post, put},
+    Router,
+};
+use chrono::{DateTime, Utc};
+use cyxcloud_core::error::HasErrorCode;
+use cyxcloud_metadata::{
+    CreateChunk, CreateReplicationRule, DurabilityMode, EpochPayoutReport, Node, ReplicationObject,
+    ReplicationRule, ReplicationStats, SalvageSummary, SlashingEvidence,
+};
+#[cfg(feature = "fault-injection")]
+use cyxcloud_network::fault::FaultConfig;
+use cyxcloud_rebalancer::simulation::{
+    self, Scenario, SimulationConfig, SimulationError, SimulationReport,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write as _;
+use std::sync::Arc;
+use tracing::{error, info};
+use uuid::Uuid;
+
+/// Placeholder name for nodes without region/datacenter information
+const UNKNOWN: &str = "unknown";
+
+/// Query params for key rotation
+#[derive(Debug, Deserialize)]
+pub struct RotateKeysQuery {
+    /// Make a new master key current first (providers that manage their own
+    /// keys only, such as Vault)
+    #[serde(default)]
+    pub master: bool,
+}
+
+/// Request to mint a service-account token
+#[derive(Debug, Deserialize)]
+pub struct ServiceTokenRequest {
+    /// Name of the service account, e.g. `ci-nightly`
+    pub name: String,
+    /// Scopes the token grants (`node`, `admin`, `datastream:read`,
+    /// `datastream:write`, `s3:read`, `s3:write`)
+    pub scopes: Vec<String>,
+    /// Tenant the account works in; the default tenant if unset
+    pub org: Option<String>,
+    /// User the account acts for, e.g. the owner of the datasets an ML
+    /// trainer streams; a new ID if unset
+    pub subject: Option<Uuid>,
+    /// Lifetime in seconds (default 30 days, at most 90 days)
+    pub expires_in_secs: Option<i64>,
+}
+
+/// Minted service-account token
+#[derive(Debug, Serialize)]
+pub struct ServiceTokenResponse {
+    pub name: String,
+    pub token: String,
+    /// Token ID to revoke the token with
+    pub token_id: String,
+    pub subject: String,
+    pub scopes: Vec<String>,
+    pub org: Option<String>,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Longest service account name
+const MAX_SERVICE_ACCOUNT_NAME: usize = 64;
+
+/// Query params for topology export
+#[derive(Debug, Deserialize)]
+pub struct TopologyQuery {
+    /// Output format: `json` (default) or `dot`
+    pub format: Option<String>,
+}
+
+/// Request to re-associate imported chunks with a node
+#[derive(Debug, Deserialize)]
+pub struct AdoptChunksRequest {
+    /// Node the chunks were exported from (UUID or peer ID)
+    pub from_node_id: String,
+    /// Hex-encoded IDs of the chunks imported on the new node
+    pub chunk_ids: Vec<String>,
+}
+
+/// One shard of a `cyxcloud-node salvage` report
+#[derive(Debug, Deserialize)]
+pub struct SalvagedShard {
+    /// Hex-encoded chunk ID
+    pub chunk_id: String,
+    pub file_id: Uuid,
+    pub chunk_index: u32,
+    pub shard_index: u32,
+    pub is_parity: bool,
+    /// Shard size in bytes
+    pub size: u64,
+}
+
+/// Salvage report written by `cyxcloud-node salvage`
+#[derive(Debug, Deserialize)]
+pub struct SalvageChunksRequest {
+    pub shards: Vec<SalvagedShard>,
+}
+
+/// Result of a chunk location salvage
+#[derive(Debug, Serialize)]
+pub struct SalvageChunksResponse {
+    pub node_id: String,
+    pub requested: usize,
+    #[serde(flatten)]
+    pub summary: SalvageSummary,
+}
+
+/// Result of a tenant cache purge
+#[derive(Debug, Serialize)]
+pub struct PurgeTenantCacheResponse {
+    pub tenant: String,
+    pub removed_keys: u64,
+}
+
+/// Durability mode of a bucket
+#[derive(Debug, Serialize)]
+pub struct BucketDurabilityResponse {
+    pub tenant: String,
+    pub bucket: String,
+    pub durability: DurabilityMode,
+    /// Nodes each shard is written to at upload
+    pub shard_replicas: usize,
+}
+
+/// Default and maximum number of payout reports per listing
+const DEFAULT_PAYOUT_REPORTS: i64 = 20;
+const MAX_PAYOUT_REPORTS: i64 = 100;
+
+/// Query params for listing payout reports
+#[derive(Debug, Deserialize)]
+pub struct PayoutReportsQuery {
+    /// Number of most recent epochs to return (default 20, max 100)
+    pub limit: Option<i64>,
+}
+
+/// Default and maximum number of slashing evidence records per listing
+const DEFAULT_SLASHING_EVIDENCE: i64 = 50;
+const MAX_SLASHING_EVIDENCE: i64 = 500;
+
+/// Query params for listing slashing evidence
+#[derive(Debug, Deserialize)]
+pub struct SlashingEvidenceQuery {
+    /// Only evidence against this node
+    pub node_id: Option<Uuid>,
+    /// Number of most recent records to return (default 50, max 500)
+    pub limit: Option<i64>,
+}
+
+/// Slashing evidence with hex-encoded chunk ID, challenge and proofs
+#[derive(Debug, Serialize)]
+pub struct SlashingEvidenceResponse {
+    pub id: Uuid,
+    pub node_id: Uuid,
+    pub chunk_id: String,
+    pub epoch: i64,
+    pub reason: String,
+    pub challenge: String,
+    pub expected_proof: String,
+    pub returned_proof: Option<String>,
+    pub error: Option<String>,
+    pub failure_count: i32,
+    pub first_failed_at: Option<DateTime<Utc>>,
+    pub last_verified: Option<DateTime<Utc>>,
+    pub challenged_at: DateTime<Utc>,
+    pub responded_at: Option<DateTime<Utc>>,
+    pub slashing_event_id: Option<Uuid>,
+    pub tx_signature: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<SlashingEvidence> for SlashingEvidenceResponse {
+    fn from(e: SlashingEvidence) -> Self {
+        Self {
+            id: e.id,
+            node_id: e.node_id,
+            chunk_id: hex::encode(&e.chunk_id),
+            epoch: e.epoch,
+            reason: e.reason,
+            challenge: hex::encode(&e.challenge),
+            expected_proof: hex::encode(&e.expected_proof),
+            returned_proof: e.returned_proof.map(hex::encode),
+            error: e.error,
+            failure_count: e.failure_count,
+            first_failed_at: e.first_failed_at,
+            last_verified: e.last_verified,
+            challenged_at: e.challenged_at,
+            responded_at: e.responded_at,
+            slashing_event_id: e.slashing_event_id,
+            tx_signature: e.tx_signature,
+            created_at: e.created_at,
+        }
+    }
+}
+
+/// Result of a chunk re-association
+#[derive(Debug, Serialize)]
+pub struct AdoptChunksResponse {
+    pub node_id: String,
+    pub requested: usize,
+    pub adopted: u64,
+}
+
+/// Request to create a bucket replication rule
+#[derive(Debug, Deserialize)]
+pub struct CreateReplicationRuleRequest {
+    /// Source bucket on this cluster
+    pub bucket: String,
+    /// Only replicate keys starting with this prefix
+    #[serde(default)]
+    pub prefix: String,
+    /// `cyxcloud` (another gateway) or `s3` (any S3-compatible service)
+    #[serde(default = "default_target_type")]
+    pub target_type: String,
+    /// Base URL of the target, e.g. `https://dr.example.com`
+    pub endpoint: String,
+    /// Bucket on the target (defaults to the source bucket name)
+    pub target_bucket: Option<String>,
+    /// Signing region for S3 targets
+    #[serde(default = "default_region")]
+    pub region: String,
+    /// Bearer token for CyxCloud targets
+    pub access_token: Option<String>,
+    /// Access key pair for S3 targets
+    pub access_key_id: Option<String>,
+    pub secret_access_key: Option<String>,
+    /// Propagate deletes to the target
+    #[serde(default)]
+    pub replicate_deletes: bool,
+    /// Tenant owning the source bucket (defaults to the admin's tenant)
+    pub tenant: Option<String>,
+}
+
+fn default_target_type() -> String {
+    TARGET_CYXCLOUD.to_string()
+}
+
+fn default_region() -> String {
+    "us-east-1".to_string()
+}
+
+/// Request to pause or resume a replication rule
+#[derive(Debug, Deserialize)]
+pub struct UpdateReplicationRuleRequest {
+    pub enabled: bool,
+}
+
+/// Replication rule with its progress (credentials are never returned)
+#[derive(Debug, Serialize)]
+pub struct ReplicationRuleStatus {
+    #[serde(flatten)]
+    pub rule: ReplicationRule,
+    pub stats: ReplicationStats,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recent_failures: Option<Vec<ReplicationObject>>,
+}
+
+/// Cluster topology document
+#[derive(Debug, Serialize)]
+pub struct ClusterTopology {
+    pub generated_at: String,
+    pub total_nodes: usize,
+    pub total_shards: i64,
+    pub regions: Vec<RegionTopology>,
+}
+
+/// Region in the topology tree
+#[derive(Debug, Serialize)]
+pub struct RegionTopology {
+    pub name: String,
+    pub datacenters: Vec<DatacenterTopology>,
+}
+
+/// Datacenter in the topology tree
+#[derive(Debug, Serialize)]
+pub struct DatacenterTopology {
+    pub name: String,
+    pub nodes: Vec<TopologyNode>,
+}
+
+/// Node leaf in the topology tree
+#[derive(Debug, Serialize)]
+pub struct TopologyNode {
+    pub id: String,
+    pub peer_id: String,
+    pub address: String,
+    pub status: String,
+    pub rack: Option<i32>,
+    pub storage_total: i64,
+    pub storage_used: i64,
+    pub utilization: f64,
+    pub shard_count: i64,
+    /// Shard count relative to the busiest node (0.0 - 1.0)
+    pub heat: f64,
+}
+
+impl ClusterTopology {
+    /// Build the topology tree from nodes and per-node shard counts
+    pub fn build(nodes: &[Node], shard_counts: &HashMap<Uuid, i64>) -> Self {
+        let max_shards = nodes
+            .iter()
+            .map(|n| shard_counts.get(&n.id).copied().unwrap_or(0))
+            .max()
+            .unwrap_or(0);
+
+        // BTreeMap keeps the output ordering stable for diffing and rendering
+        let mut tree: BTreeMap<String, BTreeMap<String, Vec<TopologyNode>>> = BTreeMap::new();
+
+        for node in nodes {
+            let shard_count = shard_counts.get(&node.id).copied().unwrap_or(0);
+            let allocatable = node.storage_allocatable();
+            let utilization = if allocatable > 0 {
+                node.storage_used as f64 / allocatable as f64
+            } else {
+                0.0
+            };
+            let heat = if max_shards > 0 {
+                shard_count as f64 / max_shards as f64
+            } else {
+                0.0
+            };
+
+            tree.entry(node.region.clone().unwrap_or_else(|| UNKNOWN.to_string()))
+                .or_default()
+                .entry(
+                    node.datacenter
+                        .clone()
+                        .unwrap_or_else(|| UNKNOWN.to_string()),
+                )
+                .or_default()
+                .push(TopologyNode {
+                    id: node.id.to_string(),
+                    peer_id: node.peer_id.clone(),
+                    address: node.grpc_address.clone(),
+                    status: node.status.clone(),
+                    rack: node.rack,
+                    storage_total: node.storage_total,
+                    storage_used: node.storage_used,
+                    utilization,
+                    shard_count,
+                    heat,
+                });
+        }
+
+        let regions = tree
+            .into_iter()
+            .map(|(name, dcs)| RegionTopology {
+                name,
+                datacenters: dcs
+                    .into_iter()
+                    .map(|(name, nodes)| DatacenterTopology { name, nodes })
+                    .collect(),
+            })
+            .collect();
+
+        Self {
+            generated_at: chrono::Utc::now().to_rfc3339(),
+            total_nodes: nodes.len(),
+            total_shards: shard_counts.values().sum(),
+            regions,
+        }
+    }
+
+    /// Render the topology as a Graphviz DOT document
+    ///
+    /// Regions and datacenters become nested clusters; node fill colour
+    /// encodes shard heat and border colour encodes status.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::new();
+        dot.push_str("digraph cyxcloud {\n");
+        dot.push_str("    rankdir=LR;\n");
+        dot.push_str("    node [shape=box, style=filled, fontname=\"Helvetica\"];\n");
+
+        for (r, region) in self.regions.iter().enumerate() {
+            let _ = writeln!(dot, "    subgraph cluster_r{} {{", r);
+            let _ = writeln!(dot, "        label=\"{}\";", dot_escape(&region.name));
+
+            for (d, dc) in region.datacenters.iter().enumerate() {
+                let _ = writeln!(dot, "        subgraph cluster_r{}_d{} {{", r, d);
+                let _ = writeln!(dot, "            label=\"{}\";", dot_escape(&dc.name));
+
+                for node in &dc.nodes {
+                    let _ = writeln!(
+                        dot,
+                        "            \"{}\" [label=\"{}\\n{}\\n{} shards, {:.0}% used\", fillcolor=\"{}\", color=\"{}\"];",
+                        node.id,
+                        dot_escape(&node.peer_id),
+                        node.status,
+                        node.shard_count,
+                        node.utilization * 100.0,
+                        heat_color(node.heat),
+                        status_color(&node.status),
+                    );
+                }
+
+                dot.push_str("        }\n");
+            }
+
+            dot.push_str("    }\n");
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+/// Escape a string for use inside a DOT quoted label
+fn dot_escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Map shard heat (0.0 - 1.0) to a white -> red fill colour
+fn heat_color(heat: f64) -> String {
+    let level = (255.0 * (1.0 - heat.clamp(0.0, 1.0))) as u8;
+    format!("#ff{:02x}{:02x}", level, level)
+}
+
+/// Border colour for a node status
+fn status_color(status: &str) -> &'static str {
+    match status {
+        "online" => "darkgreen",
+        "recovering" => "orange",
+        "draining" | "maintenance" => "blue",
+        _ => "red",
+    }
+}
+
+/// Create admin routes
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/topology", get(get_topology))
+        .route("/config/reload", post(reload_config))
+        .route("/nodes/:node_id/chunks/adopt", post(adopt_chunks))
+        .route("/nodes/:node_id/chunks/salvage", post(salvage_chunks))
+        .route("/nodes/:node_id/archival", put(set_node_archival))
+        .route(
+            "/replication/rules",
+            get(list_replication_rules).post(create_replication_rule),
+        )
+        .route(
+            "/replication/rules/:rule_id",
+            get(get_replication_rule)
+                .patch(update_replication_rule)
+                .delete(delete_replication_rule),
+        )
+        .route("/rebalancer/simulate", post(simulate_rebalance))
+        .route("/tenants/:tenant/cache", delete(purge_tenant_cache))
+        .route("/kms/rotate", post(rotate_keys))
+        .route("/service-accounts/tokens", post(mint_service_token))
+        .route(
+            "/service-accounts/tokens/:token_id",
+            delete(revoke_service_token),
+        )
+        .route(
+            "/tenants/:tenant/buckets/:bucket/durability",
+            get(get_bucket_durability).put(set_bucket_durability),
+        )
+        .route("/payouts/epochs", get(list_payout_reports))
+        .route("/payouts/epochs/:epoch", get(get_payout_report))
+        .route("/slashing/evidence", get(list_slashing_evidence))
+        .merge(fault_routes())
+}
+
+/// Fault injection routes
+#[cfg(feature = "fault-injection")]
+fn fault_routes() -> Router<Arc<AppState>> {
+    Router::new().route(
+        "/faults",
+        get(get_faults).put(set_faults).delete(clear_faults),
+    )
+}
+
+/// Fault injection is not compiled into this build
+#[cfg(not(feature = "fault-injection"))]
+fn fault_routes() -> Router<Arc<AppState>> {
+    Router::new()
+}
+
+/// Require a valid token with node admin permission
+pub(crate) async fn require_admin(
+    headers: &HeaderMap,
+    auth: &AuthService,
+) -> Result<Claims, (StatusCode, Json<ApiError>)> {
+    let claims = extract_and_validate_token(headers, auth).await?;
+
+    if !AuthService::has_permission(&claims, permissions::NODE_ADMIN) {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(ApiError::new("Admin permission required", "FORBIDDEN")),
+        ));
+    }
+
+    Ok(claims)
+}
+
+/// Export cluster topology
+async fn get_topology(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(query): Query<TopologyQuery>,
+) -> Result<Response, (StatusCode, Json<ApiError>)> {
+    require_admin(&headers, state.auth_service()).await?;
+
+    let metadata = state.metadata_service().ok_or_else(|| {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(ApiError::new(
+                "Metadata service not available",
+                "SERVICE_UNAVAILABLE",
+            )),
+        )
+    })?;
+
+    let db = metadata.database();
+    let db_error = |e: cyxcloud_metadata::DbError| {
+        error!(error = %e, "Failed to load cluster topology");
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiError::new("Failed to load topology", "DB_ERROR")),
+        )
+    };
+
+    let nodes = db.get_all_nodes().await.map_err(db_error)?;
+    let shard_counts = db.get_node_shard_counts().await.map_err(db_error)?;
+    let topology = ClusterTopology::build(&nodes, &shard_counts);
+
+    match query.format.as_deref() {
+        None | Some("json") => Ok(Json(topology).into_response()),
+        Some("dot") => Ok((
+            [(header::CONTENT_TYPE, "text/vnd.graphviz")],
+            topology.to_dot(),
+        )
+            .into_response()),
+        Some(other) => Err((
+            StatusCode::BAD_REQUEST,
+            Json(ApiError::new(
+                format!("Unsupported format: {}", other),
+                "INVALID_FORMAT",
+            )),
+        )),
+    }
+}
+
+/// Reload the gateway configuration file
+async fn reload_config(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<ReloadReport>, (StatusCode, Json<ApiError>)> {
+    let claims = require_admin(&headers, state.auth_service()).await?;
+
+    let reloader = state.config_reloader().ok_or_else(|| {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(ApiError::new(
+                "Config reload not available",
+                "SERVICE_UNAVAILABLE",
+            )),
+        )
+    })?;
+
+    info!(admin = %claims.sub, "Config reload requested");
+    reloader.reload(&state).map(Json).map_err(|e| {
+        error!(error = %e, "Config reload failed, keeping current configuration");
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ApiError::new(e.to_string(), "INVALID_CONFIG")),
+        )
+    })
+}
+
+/// Re-associate chunks imported with `cyxcloud-node import` with the new node
+async fn adopt_chunks(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(node_id): Path<String>,
+    Json(request): Json<AdoptChunksRequest>,
+) -> Result<Json<AdoptChunksResponse>, (StatusCode, Json<ApiError>)> {
+    let claims = require_admin(&headers, state.auth_service()).await?;
+
+    let metadata = state.metadata_service().ok_or_else(|| {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(ApiError::new(
+                "Metadata service not available",
+                "SERVICE_UNAVAILABLE",
+            )),
+        )
+    })?;
+
+    let chunk_ids = request
+        .chunk_ids
+        .iter()
+        .map(|id| hex::decode(id).ok().filter(|bytes| bytes.len() == 32))
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(|| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(ApiError::new(
+                    "chunk_ids must be 64-character hex strings",
+                    "INVALID_CHUNK_ID",
+                )),
+            )
+        })?;
+
+    info!(
+        admin = %claims.sub,
+        from = %request.from_node_id,
+        to = %node_id,
+        chunks = chunk_ids.len(),
+        "Chunk adoption requested"
+    );
+
+    let adopted = metadata
+        .adopt_chunks(&request.from_node_id, &node_id, &chunk_ids)
+        .await
+        .map_err(|e| {
+            error!(error = %e, "Chunk adoption failed");
+            let code = e.error_code();
+            (
+                StatusCode::from_u16(code.http_status())
+                    .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
+                Json(ApiError::new(e.to_string(), code.as_str())),
+            )
+        })?;
+
+    Ok(Json(AdoptChunksResponse {
+        node_id,
+        requested: chunk_ids.len(),
+        adopted,
+    }))
+}
+
+/// Restore a node's chunk locations from a `cyxcloud-node salvage` report
+async fn salvage_chunks(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(node_id): Path<String>,
+    Json(request): Json<SalvageChunksRequest>,
+) -> Result<Json<SalvageChunksResponse>, (StatusCode, Json<ApiError>)> {
+    let claims = require_admin(&headers, state.auth_service()).await?;
+    let metadata = require_metadata(&state)?;
+
+    // Used for chunks whose file has no other chunk left to copy it from
+    let replication_factor = RebalancerDaemonConfig::from_env().replication_factor as i32;
+    let chunks = request
+        .shards
+        .iter()
+        .map(|shard| {
+            let chunk_id = hex::decode(&shard.chunk_id)
+                .ok()
+                .filter(|bytes| bytes.len() == 32)?;
+            Some(CreateChunk {
+                chunk_id,
+                file_id: shard.file_id,
+                chunk_index: shard.chunk_index as i32,
+                shard_index: shard.shard_index as i32,
+                is_parity: shard.is_parity,
+                size_bytes: shard.size as i32,
+                replication_factor,
+            })
+        })
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(|| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(ApiError::new(
+                    "chunk_id must be a 64-character hex string",
+                    "INVALID_CHUNK_ID",
+                )),
+            )
+        })?;
+
+    info!(
+        admin = %claims.sub,
+        node = %node_id,
+        chunks = chunks.len(),
+        "Chunk salvage requested"
+    );
+
+    let summary = metadata
+        .salvage_chunks(&node_id, &chunks)
+        .await
+        .map_err(|e| {
+            error!(error = %e, "Chunk salvage failed");
+            let code = e.error_code();
+            (
+                StatusCode::from_u16(code.http_status())
+                    .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
+                Json(ApiError::new(e.to_string(), code.as_str())),
+            )
+        })?;
+
+    Ok(Json(SalvageChunksResponse {
+        node_id,
+        requested: chunks.len(),
+        summary,
+    }))
+}
+
+/// Metadata service or a 503 for admin endpoints that need it
+pub(crate) fn require_metadata(
+    state: &AppState,
+) -> Result<&cyxcloud_metadata::MetadataService, (StatusCode, Json<ApiError>)> {
+    state.metadata_service().ok_or_else(|| {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(ApiError::new(
+                "Metadata service not available",
+                "SERVICE_UNAVAILABLE",
+            )),
+        )
+    })
+}
+
+/// Map a replication database error to a 500
+fn replication_db_error(e: cyxcloud_metadata::DbError) -> (StatusCode, Json<ApiError>) {
+    error!(error = %e, "Replication rule operation failed");
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(ApiError::new(
+            "Failed to access replication rules",
+            "DB_ERROR",
+        )),
+    )
+}
+
+fn rule_not_found() -> (StatusCode, Json<ApiError>) {
+    (
+        StatusCode::NOT_FOUND,
+        Json(ApiError::new("Replication rule not found", "NOT_FOUND")),
+    )
+}
+
+/// List replication rules with their progress
+async fn list_replication_rules(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<ReplicationRuleStatus>>, (StatusCode, Json<ApiError>)> {
+    require_admin(&headers, state.auth_service()).await?;
+    let db = require_metadata(&state)?.database();
+
+    let rules = db
+        .list_replication_rules(false)
+        .await
+        .map_err(replication_db_error)?;
+    let mut statuses = Vec::with_capacity(rules.len());
+    for rule in rules {
+        let stats = db
+            .get_replication_stats(&rule)
+            .await
+            .map_err(replication_db_error)?;
+        statuses.push(ReplicationRuleStatus {
+            rule,
+            stats,
+            recent_failures: None,
+        });
+    }
+
+    Ok(Json(statuses))
+}
+
+/// Create a replication rule for a bucket
+async fn create_replication_rule(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(request): Json<CreateReplicationRuleRequest>,
+) -> Result<(StatusCode, Json<ReplicationRule>), (StatusCode, Json<ApiError>)> {
+    let claims = require_admin(&headers, state.auth_service()).await?;
+    let db = require_metadata(&state)?.database();
+
+    let bad_request = |message: String| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ApiError::new(message, "INVALID_RULE")),
+        )
+    };
+
+    let tenant = request
+        .tenant
+        .unwrap_or_else(|| claims.tenant().to_string());
+    if !is_valid_tenant(&tenant) {
+        return Err(bad_request(format!("Invalid tenant: {}", tenant)));
+    }
+
+    match state.bucket_exists(&tenant, &request.bucket).await {
+        Ok(true) => {}
+        Ok(false) => return Err(bad_request(format!("No such bucket: {}", request.bucket))),
+        Err(e) => return Err(bad_request(e.to_string())),
+    }
+
+    let rule = CreateReplicationRule {
+        tenant_id: tenant,
+        target_bucket: request
+            .target_bucket
+            .unwrap_or_else(|| request.bucket.clone()),
+        bucket: request.bucket,
+        prefix: request.prefix,
+        target_type: request.target_type,
+        endpoint: request.endpoint,
+        region: request.region,
+        access_token: request.access_token,
+        access_key_id: request.access_key_id,
+        secret_access_key: request.secret_access_key,
+        replicate_deletes: request.replicate_deletes,
+    };
+
+    ReplicationTarget::validate(&rule).map_err(|e| bad_request(format!("{:#}", e)))?;
+
+    let created = db
+        .create_replication_rule(rule)
+        .await
+        .map_err(replication_db_error)?;
+
+    info!(
+        admin = %claims.sub,
+        rule = %created.id,
+        bucket = %created.bucket,
+        target = %created.endpoint,
+        "Replication rule created"
+    );
+    Ok((StatusCode::CREATED, Json(created)))
+}
+
+/// Get a replication rule with its progress and most recent failures
+async fn get_replication_rule(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(rule_id): Path<Uuid>,
+) -> Result<Json<ReplicationRuleStatus>, (StatusCode, Json<ApiError>)> {
+    require_admin(&headers, state.auth_service()).await?;
+    let db = require_metadata(&state)?.database();
+
+    let rule = db
+        .get_replication_rule(rule_id)
+        .await
+        .map_err(replication_db_error)?
+        .ok_or_else(rule_not_found)?;
+    let stats = db
+        .get_replication_stats(&rule)
+        .await
+        .map_err(replication_db_error)?;
+    let failures = db
+        .get_recent_replication_failures(rule.id, 20)
+        .await
+        .map_err(replication_db_error)?;
+
+    Ok(Json(ReplicationRuleStatus {
+        rule,
+        stats,
+        recent_failures: Some(failures),
+    }))
+}
+
+/// Pause or resume a replication rule
+async fn update_replication_rule(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(rule_id): Path<Uuid>,
+    Json(request): Json<UpdateReplicationRuleRequest>,
+) -> Result<StatusCode, (StatusCode, Json<ApiError>)> {
+    let claims = require_admin(&headers, state.auth_service()).await?;
+    let db = require_metadata(&state)?.database();
+
+    if !db
+        .set_replication_rule_enabled(rule_id, request.enabled)
+        .await
+        .map_err(replication_db_error)?
+    {
+        return Err(rule_not_found());
+    }
+
+    info!(
+        admin = %claims.sub,
+        rule = %rule_id,
+        enabled = request.enabled,
+        "Replication rule updated"
+    );
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Delete a replication rule (objects already copied stay on the target)
+async fn delete_replication_rule(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(rule_id): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, Json<ApiError>)> {
+    let claims = require_admin(&headers, state.auth_service()).await?;
+    let db = require_metadata(&state)?.database();
+
+    if !db
+        .delete_replication_rule(rule_id)
+        .await
+        .map_err(replication_db_error)?
+    {
+        return Err(rule_not_found());
+    }
+
+    info!(admin = %claims.sub, rule = %rule_id, "Replication rule deleted");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Window the ingest rate for capacity forecasts is averaged over
+const INGEST_WINDOW_DAYS: i64 = 7;
+
+/// Simulate a node change and report the rebalancing it would cause
+async fn simulate_rebalance(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(scenario): Json<Scenario>,
+) -> Result<Json<SimulationReport>, (StatusCode, Json<ApiError>)> {
+    require_admin(&headers, state.auth_service()).await?;
+    let db = require_metadata(&state)?.database();
+
+    let db_error = |e: cyxcloud_metadata::DbError| {
+        error!(error = %e, "Failed to load cluster state for simulation");
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiError::new("Failed to load cluster state", "DB_ERROR")),
+        )
+    };
+
+    let nodes = db.get_all_nodes().await.map_err(db_error)?;
+    let since = chrono::Utc::now() - chrono::Duration::days(INGEST_WINDOW_DAYS);
+    let ingested = db.get_stored_bytes_since(since).await.map_err(db_error)?;
+
+    let rebalancer = RebalancerDaemonConfig::from_env();
+    let config = SimulationConfig {
+        bytes_per_hour: rebalancer.rate_limit_gb * 1024 * 1024 * 1024,
+        ..SimulationConfig::default()
+    };
+
+    let report = simulation::simulate(
+        &nodes,
+        &scenario,
+        &config,
+        ingested.max(0) as u64 / INGEST_WINDOW_DAYS as u64,
+    )
+    .map_err(|e| {
+        let code = match e {
+            SimulationError::UnknownNode(_) => "NODE_NOT_FOUND",
+            SimulationError::InvalidScenario(_) => "INVALID_SCENARIO",
+        };
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ApiError::new(e.to_string(), code)),
+        )
+    })?;
+
+    Ok(Json(report))
+}
+
+/// Drop every cached metadata entry of a tenant
+async fn purge_tenant_cache(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(tenant): Path<String>,
+) -> Result<Json<PurgeTenantCacheResponse>, (StatusCode, Json<ApiError>)> {
+    let claims = require_admin(&headers, state.auth_service()).await?;
+    if !is_valid_tenant(&tenant) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ApiError::new(
+                format!("Invalid tenant: {}", tenant),
+                "INVALID_TENANT",
+            )),
+        ));
+    }
+
+    let removed_keys = require_metadata(&state)?.purge_tenant_cache(&tenant).await;
+    info!(admin = %claims.sub, tenant = %tenant, removed_keys, "Tenant cache purged");
+
+    Ok(Json(PurgeTenantCacheResponse {
+        tenant,
+        removed_keys,
+    }))
+}
+
+/// Re-wrap every object's data key with the current master key
+///
+/// Run after adding a new current key to the keyring, or with
+/// `?master=true` to have the key provider rotate its master key first.
+async fn rotate_keys(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(query): Query<RotateKeysQuery>,
+) -> Result<Json<KeyRotationReport>, (StatusCode, Json<ApiError>)> {
+    let claims = require_admin(&headers, state.auth_service()).await?;
+    info!(admin = %claims.sub, master = query.master, "Key rotation requested");
+
+    let report = state.rotate_data_keys(query.master).await.map_err(|e| {
+        error!(error = %e, "Key rotation failed");
+        let code = e.error_code();
+        (
+            StatusCode::from_u16(code.http_status()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
+            Json(ApiError::new(e.to_string(), code.as_str())),
+        )
+    })?;
+    Ok(Json(report))
+}
+
+/// Faults currently injected into calls to storage nodes
+#[cfg(feature = "fault-injection")]
+async fn get_faults(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<FaultConfig>, (StatusCode, Json<ApiError>)> {
+    require_admin(&headers, state.auth_service()).await?;
+    Ok(Json(state.node_client().faults().config()))
+}
+
+/// Replace the faults injected into calls to storage nodes
+#[cfg(feature = "fault-injection")]
+async fn set_faults(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(config): Json<FaultConfig>,
+) -> Result<Json<FaultConfig>, (StatusCode, Json<ApiError>)> {
+    let claims = require_admin(&headers, state.auth_service()).await?;
+    config.validate().map_err(|e| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ApiError::new(e, "INVALID_FAULTS")),
+        )
+    })?;
+
+    info!(admin = %claims.sub, faults = ?config, "Fault injection changed");
+    state.node_client().faults().set(config.clone());
+    Ok(Json(config))
+}
+
+/// Stop injecting faults into calls to storage nodes
+#[cfg(feature = "fault-injection")]
+async fn clear_faults(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<StatusCode, (StatusCode, Json<ApiError>)> {
+    let claims = require_admin(&headers, state.auth_service()).await?;
+    info!(admin = %claims.sub, "Fault injection cleared");
+    state.node_client().faults().clear();
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Mint a scoped service-account token
+async fn mint_service_token(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(req): Json<ServiceTokenRequest>,
+) -> Result<(StatusCode, Json<ServiceTokenResponse>), (StatusCode, Json<ApiError>)> {
+    let claims = require_admin(&headers, state.auth_service()).await?;
+
+    let valid_name = !req.name.is_empty()
+        && req.name.len() <= MAX_SERVICE_ACCOUNT_NAME
+        && req
+            .name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-'));
+    if !valid_name {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ApiError::new(
+                format!("Invalid service account name: {}", req.name),
+                "INVALID_NAME",
+            )),
+        ));
+    }
+
+    let subject = req.subject.unwrap_or_else(Uuid::new_v4).to_string();
+    let lifetime = req
+        .expires_in_secs
+        .unwrap_or_else(|| crate::auth::TokenType::ServiceAccount.lifetime_secs());
+    let (token, minted) = state
+        .auth_service()
+        .generate_service_token(&subject, req.scopes, req.org, lifetime)
+        .map_err(|e| match e {
+            AuthError::InvalidScope(_) => (
+                StatusCode::BAD_REQUEST,
+                Json(ApiError::new(e.to_string(), "INVALID_SCOPE")),
+            ),
+            e => {
+                error!(error = %e, "Failed to mint service token");
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ApiError::new("Failed to mint token", "INTERNAL_ERROR")),
+                )
+            }
+        })?;
+
+    audit_log(AuditEvent::AdminAction {
+        action: "service_token_minted".to_string(),
+        user_id: claims.sub,
+        details: Some(format!(
+            "name={} token_id={} subject={} scopes={}",
+            req.name,
+            minted.jti,
+            minted.sub,
+            minted.scopes.join(",")
+        )),
+    });
+
+    Ok((
+        StatusCode::CREATED,
+        Json(ServiceTokenResponse {
+            name: req.name,
+            token,
+            token_id: minted.jti,
+            subject: minted.sub,
+            scopes: minted.scopes,
+            org: minted.org,
+            expires_at: DateTime::from_timestamp(minted.exp, 0).unwrap_or_default(),
+        }),
+    ))
+}
+
+/// Revoke a service-account token by its ID
+async fn revoke_service_token(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(token_id): Path<String>,
+) -> Result<StatusCode, (StatusCode, Json<ApiError>)> {
+    let claims = require_admin(&headers, state.auth_service()).await?;
+
+    state.auth_service().revoke_token(&token_id).await;
+    audit_log(AuditEvent::TokenRevoked {
+        jti: token_id,
+        user_id: claims.sub,
+        ip: None,
+    });
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Map a metadata error to its HTTP status
+fn metadata_error(e: cyxcloud_metadata::MetadataError) -> (StatusCode, Json<ApiError>) {
+    let code = e.error_code();
+    (
+        StatusCode::from_u16(code.http_status()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
+        Json(ApiError::new(e.to_string(), code.as_str())),
+    )
+}
+
+fn check_tenant(tenant: &str) -> Result<(), (StatusCode, Json<ApiError>)> {
+    if is_valid_tenant(tenant) {
+        return Ok(());
+    }
+    Err((
+        StatusCode::BAD_REQUEST,
+        Json(ApiError::new(
+            format!("Invalid tenant: {}", tenant),
+            "INVALID_TENANT",
+        )),
+    ))
+}
+
+fn bucket_not_found(bucket: &str) -> (StatusCode, Json<ApiError>) {
+    (
+        StatusCode::NOT_FOUND,
+        Json(ApiError::new(
+            format!("No such bucket: {}", bucket),
+            "NOT_FOUND",
+        )),
+    )
+}
+
+/// Get a bucket's durability mode
+async fn get_bucket_durability(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path((tenant, bucket)): Path<(String, String)>,
+) -> Result<Json<BucketDurabilityResponse>, (StatusCode, Json<ApiError>)> {
+    require_admin(&headers, state.auth_service()).await?;
+    check_tenant(&tenant)?;
+
+    let durability = require_metadata(&state)?
+        .get_bucket(&tenant, &bucket)
+        .await
+        .map_err(metadata_error)?
+        .ok_or_else(|| bucket_not_found(&bucket))?
+        .durability_mode();
+
+    Ok(Json(BucketDurabilityResponse {
+        tenant,
+        bucket,
+        shard_replicas: durability.shard_replicas(),
+        durability,
+    }))
+}
+
+/// Change a bucket's durability mode
+///
+/// Applies to objects uploaded afterwards; existing shards keep their
+/// replicas until the rebalancer revisits them.
+async fn set_bucket_durability(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path((tenant, bucket)): Path<(String, String)>,
+    Json(durability): Json<DurabilityMode>,
+) -> Result<Json<BucketDurabilityResponse>, (StatusCode, Json<ApiError>)> {
+    let claims = require_admin(&headers, state.auth_service()).await?;
+    check_tenant(&tenant)?;
+
+    if let Err(message) = durability.validate() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ApiError::new(message, "INVALID_DURABILITY")),
+        ));
+    }
+
+    if !require_metadata(&state)?
+        .set_bucket_durability(&tenant, &bucket, durability)
+        .await
+        .map_err(metadata_error)?
+    {
+        return Err(bucket_not_found(&bucket));
+    }
+
+    info!(
+        admin = %claims.sub,
+        tenant = %tenant,
+        bucket = %bucket,
+        ?durability,
+        "Bucket durability mode changed"
+    );
+
+    Ok(Json(BucketDurabilityResponse {
+        tenant,
+        bucket,
+        shard_replicas: durability.shard_replicas(),
+        durability,
+    }))
+}
+
+/// Request body of `PUT /nodes/{id}/archival`
+#[derive(Debug, Deserialize)]
+pub struct NodeArchivalRequest {
+    pub archival: bool,
+}
+
+/// Archival flag of a node
+#[derive(Debug, Serialize)]
+pub struct NodeArchivalResponse {
+    pub node_id: Uuid,
+    pub peer_id: String,
+    pub archival: bool,
+}
+
+/// Flag a node as archival or not (given as UUID or peer ID)
+///
+/// Archive objects are placed on archival nodes and standard ones on the
+/// others (see [`crate::tiering`]). Shards already stored stay where they
+/// are.
+async fn set_node_archival(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(node_id): Path<String>,
+    Json(request): Json<NodeArchivalRequest>,
+) -> Result<Json<NodeArchivalResponse>, (StatusCode, Json<ApiError>)> {
+    let claims = require_admin(&headers, state.auth_service()).await?;
+    let node = require_metadata(&state)?
+        .set_node_archival(&node_id, request.archival)
+        .await
+        .map_err(metadata_error)?;
+
+    info!(
+        admin = %claims.sub,
+        node_id = %node.id,
+        archival = node.archival,
+        "Node archival flag set"
+    );
+
+    Ok(Json(NodeArchivalResponse {
+        node_id: node.id,
+        peer_id: node.peer_id,
+        archival: node.archival,
+    }))
+}
+
+/// Map a payout report database error to a 500
+fn payout_db_error(e: cyxcloud_metadata::DbError) -> (StatusCode, Json<ApiError>) {
+    error!(error = %e, "Payout report query failed");
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(ApiError::new("Failed to read payout reports", "DB_ERROR")),
+    )
+}
+
+/// List payout reports of the most recent distributed epochs
+async fn list_payout_reports(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(query): Query<PayoutReportsQuery>,
+) -> Result<Json<Vec<EpochPayoutReport>>, (StatusCode, Json<ApiError>)> {
+    require_admin(&headers, state.auth_service()).await?;
+    let db = require_metadata(&state)?.database();
+
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_PAYOUT_REPORTS)
+        .clamp(1, MAX_PAYOUT_REPORTS);
+    let reports = db
+        .list_epoch_payout_reports(limit)
+        .await
+        .map_err(payout_db_error)?;
+
+    Ok(Json(reports))
+}
+
+/// Get the payout report of one epoch
+async fn get_payout_report(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(epoch): Path<i64>,
+) -> Result<Json<EpochPayoutReport>, (StatusCode, Json<ApiError>)> {
+    require_admin(&headers, state.auth_service()).await?;
+    let db = require_metadata(&state)?.database();
+
+    let report = db
+        .get_epoch_payout_report(epoch)
+        .await
+        .map_err(payout_db_error)?
+        .ok_or_else(|| {
+            (
+                StatusCode::NOT_FOUND,
+                Json(ApiError::new(
+                    format!("No payout report for epoch {}", epoch),
+                    "NOT_FOUND",
+                )),
+            )
+        })?;
+
+    Ok(Json(report))
+}
+
+/// List slashing evidence, newest first
+async fn list_slashing_evidence(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(query): Query<SlashingEvidenceQuery>,
+) -> Result<Json<Vec<SlashingEvidenceResponse>>, (StatusCode, Json<ApiError>)> {
+    require_admin(&headers, state.auth_service()).await?;
+    let db = require_metadata(&state)?.database();
+
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_SLASHING_EVIDENCE)
+        .clamp(1, MAX_SLASHING_EVIDENCE);
+    let evidence = db
+        .list_slashing_evidence(query.node_id, limit)
+        .await
+        .map_err(|e| {
+            error!(error = %e, "Slashing evidence query failed");
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiError::new(
+                    "Failed to read slashing evidence",
+                    "DB_ERROR",
+                )),
+            )
+        })?;
+
+    Ok(Json(evidence.into_iter().map(Into::into).collect()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_node(region: Option<&str>, dc: Option<&str>, status: &str) -> Node {
+        let now = chrono::Utc::now();
+        Node {
+            id: Uuid::new_v4(),
+            peer_id: format!("peer-{}", Uuid::new_v4()),
+            grpc_address: "127.0.0.1:50051".to_string(),
+            storage_total: 100,
+            storage_reserved: 0,
+            storage_used: 25,
+            bandwidth_mbps: 100,
+            measured_bandwidth_mbps: None,
+            bandwidth_measured_at: None,
+            max_connections: 100,
+            disk_available: None,
+            datacenter: dc.map(String::from),
+            rack: None,
+            region: region.map(String::from),
+            latitude: None,
+            longitude: None,
+            status: status.to_string(),
+            last_heartbeat: Some(now),
+            failure_count: 0,
+            first_offline_at: None,
+            status_changed_at: None,
+            warmup_started_at: None,
+            reputation: cyxcloud_metadata::NEUTRAL_REPUTATION,
+            archival: false,
+            version: None,
+            created_at: now,
+            updated_at: now,
+            wallet_address: None,
+            public_key: None,
+        }
+    }
+
+    #[test]
+    fn test_topology_groups_by_region_and_dc() {
+        let nodes = vec![
+            make_node(Some("eu"), Some("fra1"), "online"),
+            make_node(Some("eu"), Some("ams1"), "online"),
+            make_node(Some("us"), Some("nyc1"), "offline"),
+            make_node(None, None, "online"),
+        ];
+        let mut counts = HashMap::new();
+        counts.insert(nodes[0].id, 10);
+        counts.insert(nodes[1].id, 5);
+
+        let topology = ClusterTopology::build(&nodes, &counts);
+
+        assert_eq!(topology.total_nodes, 4);
+        assert_eq!(topology.total_shards, 15);
+        let names: Vec<_> = topology.regions.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, vec!["eu", "unknown", "us"]);
+        assert_eq!(topology.regions[0].datacenters.len(), 2);
+
+        let fra = &topology.regions[0].datacenters[1];
+        assert_eq!(fra.name, "fra1");
+        assert_eq!(fra.nodes[0].shard_count, 10);
+        assert!((fra.nodes[0].heat - 1.0).abs() < f64::EPSILON);
+        assert!((fra.nodes[0].utilization - 0.25).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_topology_to_dot() {
+        let nodes = vec![make_node(Some("eu"), Some("fra\"1"), "online")];
+        let topology = ClusterTopology::build(&nodes, &HashMap::new());
+        let dot = topology.to_dot();
+
+        assert!(dot.starts_with("digraph cyxcloud {"));
+        assert!(dot.contains("subgraph cluster_r0_d0"));
+        assert!(dot.contains("label=\"fra\\\"1\""));
+        assert!(dot.contains(&nodes[0].id.to_string()));
+        assert!(dot.trim_end().ends_with('}'));
+    }
+
+    #[test]
+    fn test_heat_color() {
+        assert_eq!(heat_color(0.0), "#ffffff");
+        assert_eq!(heat_color(1.0), "#ff0000");
+    }
+}
@@ -77,6 +77,14 @@ pub enum AuditEvent {
         user_id: String,
         details: Option<String>,
     },
+
+    /// Data stream request, with the grant that allowed it
+    DataStream {
+        user_id: String,
+        file_id: String,
+        granted: bool,
+        access: Option<String>, // "owner", "dataset", "access_token"
+    },
 }
 
 /// Structured audit log entry
@@ -100,6 +108,7 @@ pub fn audit_log(event: AuditEvent) {
         AuditEvent::NodeRegistered { .. } => "node_registered",
         AuditEvent::NodeRemoved { .. } => "node_removed",
         AuditEvent::AdminAction { .. } => "admin_action",
+        AuditEvent::DataStream { .. } => "data_stream",
     };
 
     let entry = AuditLogEntry {
@@ -158,4 +167,18 @@ mod tests {
         assert!(json.contains("path_traversal_blocked"));
         assert!(json.contains("../etc/passwd"));
     }
+
+    #[test]
+    fn test_data_stream_event() {
+        let event = AuditEvent::DataStream {
+            user_id: "user-123".to_string(),
+            file_id: "file-456".to_string(),
+            granted: false,
+            access: None,
+        };
+
+        let json = serde_json::to_string(&event).unwrap();
+        assert!(json.contains("data_stream"));
+        assert!(json.contains("\"granted\":false"));
+    }
 }
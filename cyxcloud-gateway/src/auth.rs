@@ -4,11 +4,16 @@
 //! - JWT token generation and validation
 //! - Wallet signature verification (Solana/Ed25519)
 //! - API key management
+//! - External identity providers (OIDC)
 //! - Authentication middleware
 
 #![allow(unused_imports)]
 
+use async_trait::async_trait;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
 use chrono::{Duration, Utc};
+use cyxcloud_metadata::DEFAULT_TENANT;
 use ed25519_dalek::{Signature, Verifier, VerifyingKey};
 use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, TokenData, Validation};
 use redis::AsyncCommands;
@@ -37,12 +42,18 @@ pub enum AuthError {
     #[error("Invalid wallet address")]
     InvalidWalletAddress,
 
+    #[error("Invalid challenge: {0}")]
+    InvalidChallenge(String),
+
     #[error("User not found")]
     UserNotFound,
 
     #[error("Permission denied")]
     PermissionDenied,
 
+    #[error("Invalid scope: {0}")]
+    InvalidScope(String),
+
     #[error("Internal error: {0}")]
     Internal(String),
 }
@@ -77,8 +88,143 @@ pub struct Claims {
     /// Permissions
     #[serde(default)]
     pub permissions: Vec<String>,
+
+    /// Organization the caller belongs to; selects the tenant namespace
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub org: Option<String>,
+
+    /// Scopes the token is limited to; empty for tokens without a scope claim
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub scopes: Vec<String>,
+}
+
+impl Claims {
+    /// Tenant whose buckets and objects the caller works with
+    ///
+    /// The `org` claim, or the default tenant for tokens without one.
+    pub fn tenant(&self) -> &str {
+        self.org.as_deref().unwrap_or(DEFAULT_TENANT)
+    }
+
+    /// Whether the token may call APIs that require `scope`
+    ///
+    /// The `admin` scope grants every other scope. Tokens without a scope
+    /// claim get the scopes of their kind: node tokens only `node`, other
+    /// tokens the S3 and DataStream scopes, and all scopes with the
+    /// `node:admin` permission.
+    pub fn has_scope(&self, scope: &str) -> bool {
+        if !self.scopes.is_empty() {
+            return self.scopes.iter().any(|s| s == scope || s == scopes::ADMIN);
+        }
+        if AuthService::has_permission(self, permissions::NODE_ADMIN) {
+            return true;
+        }
+        match self.user_type.as_str() {
+            "node" => scope == scopes::NODE,
+            _ => scope != scopes::NODE && scope != scopes::ADMIN,
+        }
+    }
+}
+
+/// Whether `org` is usable as a tenant name
+///
+/// Tenant names become part of cache keys and bucket namespaces, so only
+/// `[A-Za-z0-9_.-]`, up to 128 characters, is accepted.
+pub fn is_valid_tenant(org: &str) -> bool {
+    !org.is_empty()
+        && org.len() <= 128
+        && org
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-'))
+}
+
+/// User identity asserted by an external identity provider
+#[derive(Debug, Clone)]
+pub struct ExternalIdentity {
+    /// Issuer of the external token
+    pub issuer: String,
+    /// Subject of the external token, unique per issuer
+    pub subject: String,
+    /// Email address, if the provider reports a verified one
+    pub email: Option<String>,
+    /// Organization mapped from the token's claims
+    pub org: Option<String>,
+    /// Permissions granted to the user
+    pub permissions: Vec<String>,
 }
 
+/// Source of identities other than the gateway's own tokens and wallets
+///
+/// Providers verify tokens issued by someone else (e.g. an enterprise SSO)
+/// and map them to a CyxCloud identity, which is then exchanged for gateway
+/// tokens.
+#[async_trait]
+pub trait AuthProvider: Send + Sync {
+    /// Short name for logs
+    fn name(&self) -> &str;
+
+    /// Whether this provider verifies tokens from `issuer`
+    fn handles_issuer(&self, issuer: &str) -> bool;
+
+    /// Verify an external token and map its claims to an identity
+    async fn authenticate(&self, token: &str) -> AuthResult<ExternalIdentity>;
+}
+
+/// `iss` claim of a JWT, read without verifying the token
+///
+/// Only used to pick the provider that then verifies the token.
+fn unverified_issuer(token: &str) -> AuthResult<String> {
+    let payload = token
+        .split('.')
+        .nth(1)
+        .ok_or_else(|| AuthError::InvalidToken("Malformed token".to_string()))?;
+    let bytes = URL_SAFE_NO_PAD
+        .decode(payload)
+        .map_err(|_| AuthError::InvalidToken("Malformed token payload".to_string()))?;
+    let claims: serde_json::Value = serde_json::from_slice(&bytes)
+        .map_err(|_| AuthError::InvalidToken("Malformed token payload".to_string()))?;
+    claims
+        .get("iss")
+        .and_then(|v| v.as_str())
+        .map(str::to_string)
+        .ok_or_else(|| AuthError::InvalidToken("Token has no issuer".to_string()))
+}
+
+/// Decode a base58 Solana wallet address into its public key
+fn parse_wallet_address(wallet_address: &str) -> AuthResult<VerifyingKey> {
+    let pubkey_bytes = bs58::decode(wallet_address)
+        .into_vec()
+        .map_err(|_| AuthError::InvalidWalletAddress)?;
+
+    let pubkey_array: [u8; 32] = pubkey_bytes
+        .try_into()
+        .map_err(|_| AuthError::InvalidWalletAddress)?;
+
+    VerifyingKey::from_bytes(&pubkey_array).map_err(|_| AuthError::InvalidWalletAddress)
+}
+
+/// How long a wallet login challenge can be answered
+pub const CHALLENGE_TTL_SECS: i64 = 300;
+
+/// Most unanswered challenges kept in memory when Redis is not available
+const MAX_PENDING_CHALLENGES: usize = 100_000;
+
+/// Login challenge issued to a wallet and not answered yet
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PendingChallenge {
+    wallet: String,
+    message: String,
+    expires_at: i64,
+}
+
+/// Redis key of a pending challenge
+fn challenge_key(nonce: &str) -> String {
+    format!("challenge:{}", nonce)
+}
+
+/// Longest lifetime of a service-account token (90 days)
+pub const MAX_SERVICE_TOKEN_SECS: i64 = 90 * 24 * 3600;
+
 /// Token type for different authentication flows
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TokenType {
@@ -90,16 +236,19 @@ pub enum TokenType {
     Node,
     /// API key token (no expiration by default)
     ApiKey,
+    /// Scoped service-account token (30 days unless set when minted)
+    ServiceAccount,
 }
 
 impl TokenType {
     /// Get token lifetime in seconds
     pub fn lifetime_secs(&self) -> i64 {
         match self {
-            Self::Access => 3600,            // 1 hour
-            Self::Refresh => 7 * 24 * 3600,  // 7 days
-            Self::Node => 24 * 3600,         // 24 hours
-            Self::ApiKey => 365 * 24 * 3600, // 1 year (effectively no expiration)
+            Self::Access => 3600,                   // 1 hour
+            Self::Refresh => 7 * 24 * 3600,         // 7 days
+            Self::Node => 24 * 3600,                // 24 hours
+            Self::ApiKey => 365 * 24 * 3600,        // 1 year (effectively no expiration)
+            Self::ServiceAccount => 30 * 24 * 3600, // 30 days
         }
     }
 }
@@ -205,6 +354,10 @@ pub struct AuthService {
     revoked_tokens: RwLock<std::collections::HashSet<String>>,
     /// Redis connection for persistent revocation (L2) — survives restarts
     redis: Option<RwLock<redis::aio::MultiplexedConnection>>,
+    /// Unanswered wallet login challenges by nonce, when not kept in Redis
+    challenges: RwLock<std::collections::HashMap<String, PendingChallenge>>,
+    /// External identity providers, tried by token issuer
+    providers: Vec<Arc<dyn AuthProvider>>,
 }
 
 impl AuthService {
@@ -239,6 +392,8 @@ impl AuthService {
             validation,
             revoked_tokens: RwLock::new(std::collections::HashSet::new()),
             redis: None,
+            challenges: RwLock::new(std::collections::HashMap::new()),
+            providers: Vec::new(),
         }
     }
 
@@ -255,6 +410,39 @@ impl AuthService {
         self
     }
 
+    /// Register an external identity provider
+    pub fn with_provider(mut self, provider: Arc<dyn AuthProvider>) -> Self {
+        info!(provider = provider.name(), "External auth provider enabled");
+        self.providers.push(provider);
+        self
+    }
+
+    /// Whether any external identity provider is configured
+    pub fn has_providers(&self) -> bool {
+        !self.providers.is_empty()
+    }
+
+    /// Verify a token from an external identity provider
+    ///
+    /// The provider is chosen by the token's issuer; tokens from issuers
+    /// no provider handles are rejected.
+    pub async fn authenticate_external(&self, token: &str) -> AuthResult<ExternalIdentity> {
+        let issuer = unverified_issuer(token)?;
+        let provider = self
+            .providers
+            .iter()
+            .find(|p| p.handles_issuer(&issuer))
+            .ok_or_else(|| AuthError::InvalidToken(format!("Unknown issuer: {}", issuer)))?;
+
+        let identity = provider.authenticate(token).await?;
+        debug!(
+            provider = provider.name(),
+            subject = %identity.subject,
+            "External token verified"
+        );
+        Ok(identity)
+    }
+
     /// Generate a JWT token for a user
     pub fn generate_token(
         &self,
@@ -262,6 +450,18 @@ impl AuthService {
         token_type: TokenType,
         wallet: Option<String>,
         permissions: Vec<String>,
+    ) -> AuthResult<String> {
+        self.generate_token_with_org(user_id, token_type, wallet, permissions, None)
+    }
+
+    /// Generate a JWT token for a user of an organization
+    pub fn generate_token_with_org(
+        &self,
+        user_id: &str,
+        token_type: TokenType,
+        wallet: Option<String>,
+        permissions: Vec<String>,
+        org: Option<String>,
     ) -> AuthResult<String> {
         let now = Utc::now();
         let exp = now + Duration::seconds(token_type.lifetime_secs());
@@ -270,6 +470,7 @@ impl AuthService {
             TokenType::Access | TokenType::Refresh => "user",
             TokenType::Node => "node",
             TokenType::ApiKey => "api_key",
+            TokenType::ServiceAccount => "service_account",
         };
 
         let claims = Claims {
@@ -281,12 +482,75 @@ impl AuthService {
             user_type: user_type.to_string(),
             wallet,
             permissions,
+            org,
+            scopes: Vec::new(),
         };
 
         encode(&Header::default(), &claims, &self.encoding_key)
             .map_err(|e| AuthError::Internal(format!("Failed to generate token: {}", e)))
     }
 
+    /// Generate a service-account token limited to `scopes`
+    ///
+    /// Meant for automation such as CI pipelines and ML trainers. The token
+    /// expires after `lifetime_secs` (at most [`MAX_SERVICE_TOKEN_SECS`]) and
+    /// carries the permissions matching its scopes, so the REST endpoints
+    /// that check permissions treat it the same way.
+    pub fn generate_service_token(
+        &self,
+        subject: &str,
+        scopes: Vec<String>,
+        org: Option<String>,
+        lifetime_secs: i64,
+    ) -> AuthResult<(String, Claims)> {
+        if scopes.is_empty() {
+            return Err(AuthError::InvalidScope(
+                "at least one scope is required".to_string(),
+            ));
+        }
+        if let Some(unknown) = scopes.iter().find(|s| !scopes::ALL.contains(&s.as_str())) {
+            return Err(AuthError::InvalidScope(unknown.clone()));
+        }
+        if !(1..=MAX_SERVICE_TOKEN_SECS).contains(&lifetime_secs) {
+            return Err(AuthError::InvalidScope(format!(
+                "lifetime must be between 1 and {} seconds",
+                MAX_SERVICE_TOKEN_SECS
+            )));
+        }
+        if let Some(org) = &org {
+            if !is_valid_tenant(org) {
+                return Err(AuthError::InvalidScope(format!("invalid org: {}", org)));
+            }
+        }
+
+        let mut permissions: Vec<String> = Vec::new();
+        for scope in &scopes {
+            for perm in scopes::permissions_of(scope) {
+                if !permissions.iter().any(|p| p == perm) {
+                    permissions.push(perm.to_string());
+                }
+            }
+        }
+
+        let now = Utc::now();
+        let claims = Claims {
+            sub: subject.to_string(),
+            exp: (now + Duration::seconds(lifetime_secs)).timestamp(),
+            iat: now.timestamp(),
+            nbf: now.timestamp(),
+            jti: Uuid::new_v4().to_string(),
+            user_type: "service_account".to_string(),
+            wallet: None,
+            permissions,
+            org,
+            scopes,
+        };
+
+        let token = encode(&Header::default(), &claims, &self.encoding_key)
+            .map_err(|e| AuthError::Internal(format!("Failed to generate token: {}", e)))?;
+        Ok((token, claims))
+    }
+
     /// Validate a JWT token and return claims
     ///
     /// Checks revocation in two tiers:
@@ -300,6 +564,15 @@ impl AuthService {
                 _ => AuthError::InvalidToken(e.to_string()),
             })?;
 
+        if let Some(org) = &token_data.claims.org {
+            if !is_valid_tenant(org) {
+                return Err(AuthError::InvalidToken(format!(
+                    "Invalid org claim: {}",
+                    org
+                )));
+            }
+        }
+
         let jti = &token_data.claims.jti;
 
         // L1: check local cache
@@ -336,11 +609,19 @@ impl AuthService {
         Ok(token_data.claims)
     }
 
+    /// Whether a token was revoked on this gateway
+    ///
+    /// Only checks the local cache (L1), for callers that already validated
+    /// the token recently.
+    pub async fn is_revoked_locally(&self, jti: &str) -> bool {
+        self.revoked_tokens.read().await.contains(jti)
+    }
+
     /// Revoke a token by its JTI
     ///
     /// Stores in both the local in-memory cache (L1) and Redis (L2) if available.
-    /// Redis entries have a TTL matching the maximum token lifetime so they
-    /// auto-expire and don't accumulate indefinitely.
+    /// Redis entries have a TTL covering the longest refresh or service-account
+    /// token lifetime so they auto-expire and don't accumulate indefinitely.
     pub async fn revoke_token(&self, jti: &str) {
         // L1: local cache
         {
@@ -351,8 +632,10 @@ impl AuthService {
         // L2: Redis (persistent across restarts)
         if let Some(ref redis) = self.redis {
             let key = format!("revoked:{}", jti);
-            // Use max token lifetime (7 days for refresh tokens) as TTL
-            let ttl_secs = TokenType::Refresh.lifetime_secs() as u64;
+            // Outlive refresh tokens (7 days) and service-account tokens
+            let ttl_secs = TokenType::Refresh
+                .lifetime_secs()
+                .max(MAX_SERVICE_TOKEN_SECS) as u64;
             let mut conn = redis.write().await;
             if let Err(e) = conn.set_ex::<_, _, ()>(&key, "1", ttl_secs).await {
                 warn!(jti = %jti, error = %e, "Failed to persist token revocation to Redis");
@@ -372,22 +655,7 @@ impl AuthService {
         message: &[u8],
         signature_b58: &str,
     ) -> AuthResult<bool> {
-        // Decode the wallet address (base58 public key)
-        let pubkey_bytes = bs58::decode(wallet_address)
-            .into_vec()
-            .map_err(|_| AuthError::InvalidWalletAddress)?;
-
-        if pubkey_bytes.len() != 32 {
-            return Err(AuthError::InvalidWalletAddress);
-        }
-
-        // Parse the public key
-        let pubkey_array: [u8; 32] = pubkey_bytes
-            .try_into()
-            .map_err(|_| AuthError::InvalidWalletAddress)?;
-
-        let verifying_key =
-            VerifyingKey::from_bytes(&pubkey_array).map_err(|_| AuthError::InvalidWalletAddress)?;
+        let verifying_key = parse_wallet_address(wallet_address)?;
 
         // Decode the signature (base58)
         let sig_bytes = bs58::decode(signature_b58)
@@ -413,16 +681,115 @@ impl AuthService {
     }
 
     /// Generate a challenge message for wallet authentication
-    pub fn generate_challenge(&self) -> (String, String) {
+    pub fn generate_challenge(&self, wallet_address: &str) -> (String, String) {
         let nonce = Uuid::new_v4().to_string();
         let timestamp = Utc::now().timestamp();
         let message = format!(
-            "Sign this message to authenticate with CyxCloud.\n\nNonce: {}\nTimestamp: {}",
-            nonce, timestamp
+            "Sign this message to authenticate with CyxCloud.\n\n\
+             Wallet: {}\nNonce: {}\nTimestamp: {}",
+            wallet_address, nonce, timestamp
         );
         (nonce, message)
     }
 
+    /// Issue a one-time login challenge for a wallet
+    ///
+    /// The challenge is kept in Redis when available, so any gateway can
+    /// accept the answer, and in memory otherwise. It is valid for
+    /// [`CHALLENGE_TTL_SECS`] and can be answered once.
+    pub async fn issue_challenge(&self, wallet_address: &str) -> AuthResult<ChallengeResponse> {
+        parse_wallet_address(wallet_address)?;
+
+        let (nonce, message) = self.generate_challenge(wallet_address);
+        let now = Utc::now().timestamp();
+        let pending = PendingChallenge {
+            wallet: wallet_address.to_string(),
+            message: message.clone(),
+            expires_at: now + CHALLENGE_TTL_SECS,
+        };
+        let response = ChallengeResponse {
+            nonce: nonce.clone(),
+            message,
+            expires_at: pending.expires_at,
+        };
+
+        if let Some(ref redis) = self.redis {
+            let value = serde_json::to_string(&pending)
+                .map_err(|e| AuthError::Internal(format!("Failed to encode challenge: {}", e)))?;
+            let mut conn = redis.write().await;
+            match conn
+                .set_ex::<_, _, ()>(challenge_key(&nonce), value, CHALLENGE_TTL_SECS as u64)
+                .await
+            {
+                Ok(()) => return Ok(response),
+                Err(e) => warn!(error = %e, "Failed to store challenge in Redis (in-memory only)"),
+            }
+        }
+
+        let mut challenges = self.challenges.write().await;
+        challenges.retain(|_, c| c.expires_at > now);
+        if challenges.len() >= MAX_PENDING_CHALLENGES {
+            return Err(AuthError::Internal(
+                "Too many pending challenges".to_string(),
+            ));
+        }
+        challenges.insert(nonce, pending);
+
+        Ok(response)
+    }
+
+    /// Remove a pending challenge, so it can only be answered once
+    async fn take_challenge(&self, nonce: &str) -> AuthResult<Option<PendingChallenge>> {
+        if let Some(challenge) = self.challenges.write().await.remove(nonce) {
+            return Ok(Some(challenge));
+        }
+
+        let Some(ref redis) = self.redis else {
+            return Ok(None);
+        };
+        let mut conn = redis.write().await;
+        // GETDEL reads and burns the nonce atomically, so two gateways
+        // cannot both accept the same answer
+        let value: Option<String> = conn
+            .get_del(challenge_key(nonce))
+            .await
+            .map_err(|e| AuthError::Internal(format!("Challenge lookup failed: {}", e)))?;
+        value
+            .map(|v| {
+                serde_json::from_str(&v)
+                    .map_err(|e| AuthError::Internal(format!("Corrupt challenge: {}", e)))
+            })
+            .transpose()
+    }
+
+    /// Verify a wallet login against the challenge it answers
+    ///
+    /// The challenge is burned before the signature is checked, so each
+    /// one allows a single attempt whether or not it succeeds.
+    pub async fn verify_wallet_login(&self, req: &WalletLoginRequest) -> AuthResult<()> {
+        let challenge = self
+            .take_challenge(&req.nonce)
+            .await?
+            .ok_or_else(|| AuthError::InvalidChallenge("unknown or already used".to_string()))?;
+
+        if challenge.expires_at <= Utc::now().timestamp() {
+            return Err(AuthError::InvalidChallenge("expired".to_string()));
+        }
+        if challenge.wallet != req.wallet_address {
+            return Err(AuthError::InvalidChallenge(
+                "issued to another wallet".to_string(),
+            ));
+        }
+        if challenge.message != req.message {
+            return Err(AuthError::InvalidChallenge(
+                "message does not match".to_string(),
+            ));
+        }
+
+        self.verify_wallet_signature(&req.wallet_address, req.message.as_bytes(), &req.signature)?;
+        Ok(())
+    }
+
     /// Check if claims have a specific permission
     pub fn has_permission(claims: &Claims, permission: &str) -> bool {
         claims.permissions.contains(&permission.to_string())
@@ -441,6 +808,9 @@ pub struct WalletLoginRequest {
     /// Wallet address (base58 public key)
     pub wallet_address: String,
 
+    /// Nonce of the challenge being answered
+    pub nonce: String,
+
     /// Challenge message that was signed
     pub message: String,
 
@@ -485,7 +855,7 @@ pub struct AuthResponse {
 /// Response for challenge generation
 #[derive(Debug, Serialize)]
 pub struct ChallengeResponse {
-    /// Nonce (should be stored temporarily)
+    /// Nonce to send back with the signed message
     pub nonce: String,
 
     /// Message to sign
@@ -527,6 +897,47 @@ pub mod permissions {
     pub const ADMIN: &str = "*";
 }
 
+/// Token scopes, required per gRPC method and per S3 operation
+pub mod scopes {
+    use super::permissions;
+
+    /// Node registration, heartbeats and reports to the gateway
+    pub const NODE: &str = "node";
+    /// Admin API; grants every other scope
+    pub const ADMIN: &str = "admin";
+    /// Read datasets and stream training batches
+    pub const DATASTREAM_READ: &str = "datastream:read";
+    /// Create and share datasets and manage their access tokens
+    pub const DATASTREAM_WRITE: &str = "datastream:write";
+    /// Read buckets and objects
+    pub const S3_READ: &str = "s3:read";
+    /// Create, overwrite and delete buckets and objects
+    pub const S3_WRITE: &str = "s3:write";
+
+    /// All known scopes
+    pub const ALL: &[&str] = &[
+        NODE,
+        ADMIN,
+        DATASTREAM_READ,
+        DATASTREAM_WRITE,
+        S3_READ,
+        S3_WRITE,
+    ];
+
+    /// Permissions a token with `scope` is given
+    pub fn permissions_of(scope: &str) -> &'static [&'static str] {
+        match scope {
+            NODE => &[permissions::NODE_REGISTER],
+            ADMIN => &[permissions::NODE_ADMIN],
+            DATASTREAM_READ => &[permissions::DATASET_READ],
+            DATASTREAM_WRITE => &[permissions::DATASET_WRITE],
+            S3_READ => &[permissions::STORAGE_READ],
+            S3_WRITE => &[permissions::STORAGE_WRITE, permissions::STORAGE_DELETE],
+            _ => &[],
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -558,13 +969,58 @@ mod tests {
     #[test]
     fn test_generate_challenge() {
         let auth = AuthService::new(AuthConfig::default());
-        let (nonce, message) = auth.generate_challenge();
+        let (nonce, message) = auth.generate_challenge("wallet-address");
 
         assert!(!nonce.is_empty());
         assert!(message.contains(&nonce));
+        assert!(message.contains("wallet-address"));
         assert!(message.contains("CyxCloud"));
     }
 
+    #[tokio::test]
+    async fn test_wallet_challenge_is_single_use() {
+        use ed25519_dalek::{Signer, SigningKey};
+
+        let auth = AuthService::new(AuthConfig::default());
+        let key = SigningKey::from_bytes(&[7u8; 32]);
+        let wallet = bs58::encode(key.verifying_key().as_bytes()).into_string();
+
+        assert!(matches!(
+            auth.issue_challenge("not-a-wallet").await,
+            Err(AuthError::InvalidWalletAddress)
+        ));
+
+        let challenge = auth.issue_challenge(&wallet).await.unwrap();
+        let login = WalletLoginRequest {
+            wallet_address: wallet.clone(),
+            nonce: challenge.nonce.clone(),
+            message: challenge.message.clone(),
+            signature: bs58::encode(key.sign(challenge.message.as_bytes()).to_bytes())
+                .into_string(),
+        };
+
+        auth.verify_wallet_login(&login).await.unwrap();
+        // Replaying the same signed answer fails
+        assert!(matches!(
+            auth.verify_wallet_login(&login).await,
+            Err(AuthError::InvalidChallenge(_))
+        ));
+
+        // A message the gateway did not issue is rejected even if signed
+        let challenge = auth.issue_challenge(&wallet).await.unwrap();
+        let forged = "Sign this message to authenticate with CyxCloud.";
+        let login = WalletLoginRequest {
+            wallet_address: wallet,
+            nonce: challenge.nonce,
+            message: forged.to_string(),
+            signature: bs58::encode(key.sign(forged.as_bytes()).to_bytes()).into_string(),
+        };
+        assert!(matches!(
+            auth.verify_wallet_login(&login).await,
+            Err(AuthError::InvalidChallenge(_))
+        ));
+    }
+
     #[test]
     fn test_has_permission() {
         let claims = Claims {
@@ -576,6 +1032,8 @@ mod tests {
             user_type: "user".to_string(),
             wallet: None,
             permissions: vec!["storage:read".to_string(), "storage:write".to_string()],
+            org: None,
+            scopes: vec![],
         };
 
         assert!(AuthService::has_permission(&claims, "storage:read"));
@@ -594,9 +1052,144 @@ mod tests {
             user_type: "user".to_string(),
             wallet: None,
             permissions: vec!["*".to_string()],
+            org: None,
+            scopes: vec![],
         };
 
         assert!(AuthService::has_permission(&claims, "storage:read"));
         assert!(AuthService::has_permission(&claims, "anything"));
     }
+
+    #[test]
+    fn test_tenant_from_org_claim() {
+        let mut claims = Claims {
+            sub: "user-123".to_string(),
+            exp: 0,
+            iat: 0,
+            nbf: 0,
+            jti: "jti".to_string(),
+            user_type: "user".to_string(),
+            wallet: None,
+            permissions: vec![],
+            org: None,
+            scopes: vec![],
+        };
+        assert_eq!(claims.tenant(), DEFAULT_TENANT);
+
+        claims.org = Some("acme".to_string());
+        assert_eq!(claims.tenant(), "acme");
+
+        assert!(is_valid_tenant("acme-corp.eu_1"));
+        assert!(!is_valid_tenant(""));
+        assert!(!is_valid_tenant("acme:other"));
+        assert!(!is_valid_tenant("acme*"));
+    }
+
+    #[test]
+    fn test_implicit_scopes() {
+        let mut claims = Claims {
+            sub: "user-123".to_string(),
+            exp: 0,
+            iat: 0,
+            nbf: 0,
+            jti: "jti".to_string(),
+            user_type: "user".to_string(),
+            wallet: None,
+            permissions: vec![],
+            org: None,
+            scopes: vec![],
+        };
+        assert!(claims.has_scope(scopes::S3_WRITE));
+        assert!(claims.has_scope(scopes::DATASTREAM_READ));
+        assert!(!claims.has_scope(scopes::NODE));
+        assert!(!claims.has_scope(scopes::ADMIN));
+
+        claims.user_type = "node".to_string();
+        assert!(claims.has_scope(scopes::NODE));
+        assert!(!claims.has_scope(scopes::S3_READ));
+
+        claims.permissions = vec![permissions::NODE_ADMIN.to_string()];
+        assert!(claims.has_scope(scopes::ADMIN));
+        assert!(claims.has_scope(scopes::S3_READ));
+
+        // An explicit scope claim replaces the implicit scopes
+        claims.scopes = vec![scopes::S3_READ.to_string()];
+        assert!(claims.has_scope(scopes::S3_READ));
+        assert!(!claims.has_scope(scopes::S3_WRITE));
+        assert!(!claims.has_scope(scopes::ADMIN));
+    }
+
+    #[tokio::test]
+    async fn test_service_token() {
+        let auth = AuthService::new(AuthConfig::default());
+        let (token, minted) = auth
+            .generate_service_token(
+                "ci-pipeline",
+                vec![scopes::S3_WRITE.to_string()],
+                Some("acme".to_string()),
+                3600,
+            )
+            .unwrap();
+
+        let claims = auth.validate_token(&token).await.unwrap();
+        assert_eq!(claims.jti, minted.jti);
+        assert_eq!(claims.user_type, "service_account");
+        assert_eq!(claims.tenant(), "acme");
+        assert_eq!(claims.exp - claims.iat, 3600);
+        assert!(claims.has_scope(scopes::S3_WRITE));
+        assert!(!claims.has_scope(scopes::S3_READ));
+        assert!(AuthService::has_permission(
+            &claims,
+            permissions::STORAGE_DELETE
+        ));
+        assert!(!AuthService::has_permission(
+            &claims,
+            permissions::NODE_ADMIN
+        ));
+
+        for (requested, lifetime) in [
+            (vec![], 3600),
+            (vec!["s3:everything".to_string()], 3600),
+            (vec![scopes::S3_READ.to_string()], 0),
+            (
+                vec![scopes::S3_READ.to_string()],
+                MAX_SERVICE_TOKEN_SECS + 1,
+            ),
+        ] {
+            assert!(matches!(
+                auth.generate_service_token("ci", requested, None, lifetime),
+                Err(AuthError::InvalidScope(_))
+            ));
+        }
+
+        auth.revoke_token(&minted.jti).await;
+        assert!(auth.validate_token(&token).await.is_err());
+    }
+
+    #[test]
+    fn test_external_token_needs_matching_provider() {
+        let auth = AuthService::new(AuthConfig::default());
+        let token = auth
+            .generate_token_with_org(
+                "user-123",
+                TokenType::Access,
+                None,
+                vec![],
+                Some("acme".to_string()),
+            )
+            .unwrap();
+
+        assert_eq!(unverified_issuer(&token).unwrap(), "cyxcloud-gateway");
+        assert!(unverified_issuer("not-a-jwt").is_err());
+
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        assert_eq!(
+            rt.block_on(auth.validate_token(&token)).unwrap().org,
+            Some("acme".to_string())
+        );
+        assert!(matches!(
+            rt.block_on(auth.authenticate_external(&token)),
+            Err(AuthError::InvalidToken(_))
+        ));
+    }
 }
@@ -2,18 +2,19 @@
 //!
 //! Provides endpoints for:
 //! - Wallet-based authentication
+//! - SSO login with external identity provider tokens (OIDC)
 //! - API key management
 //! - Token refresh
 
 #![allow(unused_imports)]
 
 use crate::auth::{
-    AuthResponse, AuthService, AuthUser, ChallengeResponse, Claims, CreateApiKeyRequest, TokenType,
-    WalletLoginRequest,
+    AuthError, AuthResponse, AuthService, AuthUser, ChallengeResponse, Claims, CreateApiKeyRequest,
+    TokenType, WalletLoginRequest,
 };
 use crate::AppState;
 use axum::{
-    extract::{ConnectInfo, Json, State},
+    extract::{ConnectInfo, Json, Query, State},
     http::{header, HeaderMap, StatusCode},
     response::IntoResponse,
     routing::{get, post},
@@ -40,6 +41,12 @@ const RATE_LIMIT_CHALLENGE: RateLimit = RateLimit {
     window_secs: 60,
 };
 
+const RATE_LIMIT_CHALLENGE_WALLET: RateLimit = RateLimit {
+    key: "auth:challenge:wallet",
+    max_requests: 5,
+    window_secs: 60,
+};
+
 const RATE_LIMIT_LOGIN: RateLimit = RateLimit {
     key: "auth:login",
     max_requests: 10,
@@ -59,13 +66,15 @@ const RATE_LIMIT_API_KEY: RateLimit = RateLimit {
 };
 
 /// Check rate limit using Redis cache via MetadataService. Fails open if unavailable.
+///
+/// `client` is what the limit counts per, usually the client IP.
 async fn check_rate_limit(
     state: &AppState,
     limit: &RateLimit,
-    client_ip: &str,
+    client: &str,
 ) -> Result<(), (StatusCode, Json<ApiError>)> {
     if let Some(meta) = state.metadata_service() {
-        let key = format!("{}:{}", limit.key, client_ip);
+        let key = format!("{}:{}", limit.key, client);
         match meta
             .check_rate_limit(&key, limit.max_requests, limit.window_secs)
             .await
@@ -74,7 +83,7 @@ async fn check_rate_limit(
                 if !allowed {
                     warn!(
                         key = %limit.key,
-                        ip = %client_ip,
+                        client = %client,
                         "Rate limit exceeded"
                     );
                     return Err((
@@ -92,14 +101,10 @@ async fn check_rate_limit(
     Ok(())
 }
 
-/// Extract client IP from headers (X-Forwarded-For) or connection
-fn extract_client_ip(headers: &HeaderMap) -> String {
-    headers
-        .get("x-forwarded-for")
-        .and_then(|v| v.to_str().ok())
-        .and_then(|s| s.split(',').next())
-        .map(|s| s.trim().to_string())
-        .unwrap_or_else(|| "unknown".to_string())
+/// Client IP of the request being handled (see [`crate::client_ip`]),
+/// "unknown" when it cannot be determined
+pub(crate) fn extract_client_ip() -> String {
+    crate::client_ip::current().map_or_else(|| "unknown".to_string(), |ip| ip.to_string())
 }
 
 /// API error response
@@ -125,6 +130,8 @@ pub fn routes() -> Router<Arc<AppState>> {
         .route("/challenge", get(get_challenge))
         // Wallet login
         .route("/wallet", post(wallet_login))
+        // SSO login with an identity provider token
+        .route("/oidc", post(oidc_login))
         // Refresh token
         .route("/refresh", post(refresh_token))
         // API keys
@@ -135,47 +142,84 @@ pub fn routes() -> Router<Arc<AppState>> {
         .route("/me", get(get_me))
 }
 
-/// Get a challenge message for wallet authentication
+/// Query parameters for challenge generation
+#[derive(Debug, Deserialize)]
+pub struct ChallengeQuery {
+    /// Wallet address that will sign the challenge
+    pub wallet: String,
+}
+
+/// Get a one-time challenge message for wallet authentication
 async fn get_challenge(
     State(state): State<Arc<AppState>>,
-    headers: HeaderMap,
+    Query(query): Query<ChallengeQuery>,
 ) -> Result<Json<ChallengeResponse>, (StatusCode, Json<ApiError>)> {
-    let client_ip = extract_client_ip(&headers);
+    let client_ip = extract_client_ip();
     check_rate_limit(&state, &RATE_LIMIT_CHALLENGE, &client_ip).await?;
+    check_rate_limit(&state, &RATE_LIMIT_CHALLENGE_WALLET, &query.wallet).await?;
 
     let auth = state.auth_service();
-    let (nonce, message) = auth.generate_challenge();
-
-    // Challenge valid for 5 minutes
-    let expires_at = chrono::Utc::now().timestamp() + 300;
+    let challenge = auth
+        .issue_challenge(&query.wallet)
+        .await
+        .map_err(|e| match e {
+            AuthError::InvalidWalletAddress => (
+                StatusCode::BAD_REQUEST,
+                Json(ApiError::new("Invalid wallet address", "INVALID_WALLET")),
+            ),
+            e => {
+                error!(error = %e, "Failed to issue challenge");
+                (
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    Json(ApiError::new(
+                        "Could not issue challenge",
+                        "CHALLENGE_ERROR",
+                    )),
+                )
+            }
+        })?;
 
-    Ok(Json(ChallengeResponse {
-        nonce,
-        message,
-        expires_at,
-    }))
+    Ok(Json(challenge))
 }
 
 /// Login with wallet signature
 async fn wallet_login(
     State(state): State<Arc<AppState>>,
-    headers: HeaderMap,
     Json(req): Json<WalletLoginRequest>,
 ) -> Result<Json<AuthResponse>, (StatusCode, Json<ApiError>)> {
-    let client_ip = extract_client_ip(&headers);
+    let client_ip = extract_client_ip();
     check_rate_limit(&state, &RATE_LIMIT_LOGIN, &client_ip).await?;
 
     let auth = state.auth_service();
 
     info!(wallet = %req.wallet_address, "Wallet login attempt");
 
-    // Verify the signature
-    match auth.verify_wallet_signature(&req.wallet_address, req.message.as_bytes(), &req.signature)
-    {
-        Ok(true) => {
+    // Burn the challenge and verify the signature over it
+    match auth.verify_wallet_login(&req).await {
+        Ok(()) => {
             debug!(wallet = %req.wallet_address, "Signature verified");
         }
-        Ok(false) | Err(_) => {
+        Err(AuthError::InvalidChallenge(reason)) => {
+            warn!(wallet = %req.wallet_address, reason = %reason, "Invalid challenge");
+            return Err((
+                StatusCode::UNAUTHORIZED,
+                Json(ApiError::new(
+                    format!("Invalid challenge: {}", reason),
+                    "INVALID_CHALLENGE",
+                )),
+            ));
+        }
+        Err(AuthError::Internal(e)) => {
+            error!(error = %e, "Failed to check challenge");
+            return Err((
+                StatusCode::SERVICE_UNAVAILABLE,
+                Json(ApiError::new(
+                    "Could not check challenge",
+                    "CHALLENGE_ERROR",
+                )),
+            ));
+        }
+        Err(_) => {
             warn!(wallet = %req.wallet_address, "Invalid signature");
             return Err((
                 StatusCode::UNAUTHORIZED,
@@ -249,6 +293,112 @@ async fn wallet_login(
     }))
 }
 
+/// Request body for SSO login
+#[derive(Debug, Deserialize)]
+pub struct OidcLoginRequest {
+    /// ID token issued by the identity provider
+    pub id_token: String,
+}
+
+/// Login with an ID token from an external identity provider
+///
+/// The user is provisioned on first login and receives CyxCloud tokens
+/// carrying the organization and permissions mapped from the ID token.
+async fn oidc_login(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<OidcLoginRequest>,
+) -> Result<Json<AuthResponse>, (StatusCode, Json<ApiError>)> {
+    let client_ip = extract_client_ip();
+    check_rate_limit(&state, &RATE_LIMIT_LOGIN, &client_ip).await?;
+
+    let auth = state.auth_service();
+
+    if !auth.has_providers() {
+        return Err((
+            StatusCode::NOT_FOUND,
+            Json(ApiError::new("SSO login is not configured", "SSO_DISABLED")),
+        ));
+    }
+
+    let identity = auth
+        .authenticate_external(&req.id_token)
+        .await
+        .map_err(|e| {
+            warn!(error = %e, "Invalid identity provider token");
+            (
+                StatusCode::UNAUTHORIZED,
+                Json(ApiError::new(format!("{}", e), "INVALID_TOKEN")),
+            )
+        })?;
+
+    // Get or create user in database
+    let user_id = if let Some(meta) = state.metadata_service() {
+        match meta
+            .get_or_create_external_user(
+                &identity.issuer,
+                &identity.subject,
+                identity.email.as_deref(),
+            )
+            .await
+        {
+            Ok(user) => user.id.to_string(),
+            Err(e) => {
+                error!(error = %e, "Failed to get/create user");
+                return Err((
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ApiError::new("Database error", "DB_ERROR")),
+                ));
+            }
+        }
+    } else {
+        // In-memory mode: use the provider's subject as user ID
+        identity.subject.clone()
+    };
+
+    let token_error = |e: AuthError| {
+        error!(error = %e, "Failed to generate token");
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiError::new("Token generation failed", "TOKEN_ERROR")),
+        )
+    };
+
+    let access_token = auth
+        .generate_token_with_org(
+            &user_id,
+            TokenType::Access,
+            None,
+            identity.permissions.clone(),
+            identity.org.clone(),
+        )
+        .map_err(token_error)?;
+
+    let refresh_token = auth
+        .generate_token_with_org(
+            &user_id,
+            TokenType::Refresh,
+            None,
+            identity.permissions,
+            identity.org.clone(),
+        )
+        .map_err(token_error)?;
+
+    info!(
+        user_id = %user_id,
+        issuer = %identity.issuer,
+        org = ?identity.org,
+        "SSO login successful"
+    );
+
+    Ok(Json(AuthResponse {
+        access_token,
+        refresh_token: Some(refresh_token),
+        token_type: "Bearer".to_string(),
+        expires_in: TokenType::Access.lifetime_secs(),
+        user_id,
+    }))
+}
+
 /// Request body for token refresh
 #[derive(Debug, Deserialize)]
 pub struct RefreshTokenRequest {
@@ -258,10 +408,9 @@ pub struct RefreshTokenRequest {
 /// Refresh access token using refresh token
 async fn refresh_token(
     State(state): State<Arc<AppState>>,
-    headers: HeaderMap,
     Json(req): Json<RefreshTokenRequest>,
 ) -> Result<Json<AuthResponse>, (StatusCode, Json<ApiError>)> {
-    let client_ip = extract_client_ip(&headers);
+    let client_ip = extract_client_ip();
     check_rate_limit(&state, &RATE_LIMIT_REFRESH, &client_ip).await?;
 
     let auth = state.auth_service();
@@ -277,11 +426,12 @@ async fn refresh_token(
 
     // Generate new access token
     let access_token = auth
-        .generate_token(
+        .generate_token_with_org(
             &claims.sub,
             TokenType::Access,
             claims.wallet.clone(),
             claims.permissions.clone(),
+            claims.org.clone(),
         )
         .map_err(|e| {
             error!(error = %e, "Failed to generate access token");
@@ -320,7 +470,7 @@ async fn create_api_key(
     headers: HeaderMap,
     Json(req): Json<CreateApiKeyRequest>,
 ) -> Result<Json<ApiKeyResponse>, (StatusCode, Json<ApiError>)> {
-    let client_ip = extract_client_ip(&headers);
+    let client_ip = extract_client_ip();
     check_rate_limit(&state, &RATE_LIMIT_API_KEY, &client_ip).await?;
 
     let auth = state.auth_service();
@@ -381,7 +531,13 @@ async fn create_api_key(
     };
 
     let api_key = auth
-        .generate_token(&claims.sub, TokenType::ApiKey, claims.wallet, permissions)
+        .generate_token_with_org(
+            &claims.sub,
+            TokenType::ApiKey,
+            claims.wallet,
+            permissions,
+            claims.org,
+        )
         .map_err(|e| {
             error!(error = %e, "Failed to generate API key");
             (
@@ -436,6 +592,7 @@ async fn get_me(
     let claims = extract_and_validate_token(&headers, auth).await?;
 
     Ok(Json(UserInfoResponse {
+        tenant: claims.tenant().to_string(),
         user_id: claims.sub,
         user_type: claims.user_type,
         wallet: claims.wallet,
@@ -450,10 +607,12 @@ pub struct UserInfoResponse {
     pub user_type: String,
     pub wallet: Option<String>,
     pub permissions: Vec<String>,
+    /// Tenant the user's buckets belong to
+    pub tenant: String,
 }
 
 /// Extract and validate JWT from Authorization header
-async fn extract_and_validate_token(
+pub(crate) async fn extract_and_validate_token(
     headers: &HeaderMap,
     auth: &AuthService,
 ) -> Result<Claims, (StatusCode, Json<ApiError>)> {
@@ -0,0 +1,675 @@
+//! Per-user bandwidth accounting and egress caps
+//!
+//! A middleware on the S3 routes counts the request body bytes (in) and
+//! response body bytes (out) of every authenticated request and adds them to
+//! per-user, per-tenant, per-hour counters. The bandwidth daemon flushes the
+//! counters to the `bandwidth_usage` table, where several gateways add up
+//! into the same rows; with in-memory storage the gateway keeps them itself.
+//!
+//! Plans may cap a user's monthly egress. Before a download the gateway
+//! compares the user's egress since the start of the UTC month with the cap
+//! of their plan and rejects the request once it is reached. The egress
+//! total is re-read from the database every `cap_refresh`, with this
+//! gateway's own downloads added in between, so with several gateways a cap
+//! can be overshot by what the others served within one refresh interval.
+//!
+//! Anonymous requests are not accounted, except downloads of public
+//! objects (see [`crate::acl`]), which are billed to the bucket's owner and
+//! checked against the owner's cap.
+
+use crate::s3_api::S3Error;
+use crate::state::AppState;
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderMap, Method},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Datelike, TimeZone, Utc};
+use cyxcloud_metadata::{BandwidthUsage, DbError};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+use tokio::time::interval;
+use tracing::{debug, info, warn};
+
+/// Bytes in a gigabyte, the unit egress caps are configured in
+const GB: u64 = 1024 * 1024 * 1024;
+
+/// Bandwidth accounting configuration
+#[derive(Debug, Clone, PartialEq)]
+pub struct BandwidthConfig {
+    /// Count bytes per user (egress caps need this)
+    pub enabled: bool,
+    /// How often counters are written to the database
+    pub flush_interval: Duration,
+    /// Plan of users without a user record (or with in-memory storage)
+    pub default_plan: String,
+    /// Monthly egress cap in bytes per plan; plans not listed are unlimited
+    pub egress_caps: BTreeMap<String, u64>,
+    /// How long a user's plan and egress total are reused before re-reading
+    pub cap_refresh: Duration,
+}
+
+impl Default for BandwidthConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            flush_interval: Duration::from_secs(60),
+            default_plan: "free".to_string(),
+            egress_caps: BTreeMap::new(),
+            cap_refresh: Duration::from_secs(60),
+        }
+    }
+}
+
+impl BandwidthConfig {
+    /// Create configuration from environment variables
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let secs = |name: &str, default: Duration| {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .map(Duration::from_secs)
+                .unwrap_or(default)
+        };
+
+        let egress_caps = match std::env::var("BANDWIDTH_EGRESS_CAPS_GB") {
+            Ok(value) => match parse_egress_caps_gb(&value) {
+                Ok(caps) => caps.into_iter().map(|(plan, gb)| (plan, gb * GB)).collect(),
+                Err(e) => {
+                    warn!(error = %e, "Ignoring invalid BANDWIDTH_EGRESS_CAPS_GB");
+                    defaults.egress_caps.clone()
+                }
+            },
+            Err(_) => defaults.egress_caps.clone(),
+        };
+
+        Self {
+            enabled: std::env::var("BANDWIDTH_ACCOUNTING_ENABLED")
+                .map(|v| v == "true" || v == "1")
+                .unwrap_or(defaults.enabled),
+            flush_interval: secs("BANDWIDTH_FLUSH_SECS", defaults.flush_interval),
+            default_plan: std::env::var("BANDWIDTH_DEFAULT_PLAN")
+                .ok()
+                .filter(|v| !v.is_empty())
+                .unwrap_or(defaults.default_plan),
+            egress_caps,
+            cap_refresh: secs("BANDWIDTH_CAP_REFRESH_SECS", defaults.cap_refresh),
+        }
+    }
+
+    /// Monthly egress cap of a plan in bytes, None if unlimited
+    pub fn egress_cap(&self, plan: &str) -> Option<u64> {
+        self.egress_caps.get(plan).copied().filter(|&cap| cap > 0)
+    }
+}
+
+/// Parse `plan=GB` pairs, e.g. `free=50,pro=1000`
+pub fn parse_egress_caps_gb(value: &str) -> Result<BTreeMap<String, u64>, String> {
+    let mut caps = BTreeMap::new();
+    for pair in value.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        let (plan, gb) = pair
+            .split_once('=')
+            .ok_or_else(|| format!("expected plan=GB, got '{}'", pair))?;
+        let gb: u64 = gb
+            .trim()
+            .parse()
+            .map_err(|_| format!("invalid cap for plan '{}': '{}'", plan.trim(), gb.trim()))?;
+        caps.insert(plan.trim().to_string(), gb);
+    }
+    Ok(caps)
+}
+
+/// Start of the UTC hour containing `time`
+pub fn hour_start(time: DateTime<Utc>) -> DateTime<Utc> {
+    let secs = time.timestamp();
+    DateTime::from_timestamp(secs - secs.rem_euclid(3600), 0).unwrap_or(time)
+}
+
+/// Start of the UTC month containing `time`
+pub fn month_start(time: DateTime<Utc>) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(time.year(), time.month(), 1, 0, 0, 0)
+        .single()
+        .unwrap_or(time)
+}
+
+/// A download refused because the user's plan cap is used up
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EgressLimitExceeded {
+    pub plan: String,
+    pub limit_bytes: u64,
+    pub used_bytes: u64,
+}
+
+/// User, tenant and hour of a usage counter
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct UsageKey {
+    user: String,
+    tenant: String,
+    hour: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Counters {
+    bytes_in: u64,
+    bytes_out: u64,
+    requests: u64,
+}
+
+impl Counters {
+    fn add(&mut self, other: Counters) {
+        self.bytes_in += other.bytes_in;
+        self.bytes_out += other.bytes_out;
+        self.requests += other.requests;
+    }
+}
+
+fn usage_row(key: &UsageKey, counters: Counters) -> BandwidthUsage {
+    BandwidthUsage {
+        user_id: key.user.clone(),
+        tenant_id: key.tenant.clone(),
+        hour: key.hour,
+        bytes_in: counters.bytes_in as i64,
+        bytes_out: counters.bytes_out as i64,
+        requests: counters.requests as i64,
+    }
+}
+
+/// A user's plan and month-to-date egress, as last looked up
+#[derive(Debug, Clone)]
+struct EgressState {
+    plan: String,
+    month: DateTime<Utc>,
+    used_bytes: u64,
+    fetched_at: Instant,
+}
+
+/// Per-user bandwidth counters of this gateway
+pub struct BandwidthMeter {
+    config: BandwidthConfig,
+    /// Counters not yet flushed
+    pending: Mutex<HashMap<UsageKey, Counters>>,
+    /// Flushed counters, kept here when there is no database
+    recorded: Mutex<HashMap<UsageKey, Counters>>,
+    /// Egress totals of users with a capped plan
+    egress: Mutex<HashMap<String, EgressState>>,
+}
+
+impl BandwidthMeter {
+    /// Create a bandwidth meter
+    pub fn new(config: BandwidthConfig) -> Self {
+        Self {
+            config,
+            pending: Mutex::new(HashMap::new()),
+            recorded: Mutex::new(HashMap::new()),
+            egress: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Accounting configuration
+    pub fn config(&self) -> &BandwidthConfig {
+        &self.config
+    }
+
+    /// Whether requests are accounted
+    pub fn is_enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    /// Count one request of `user` in `tenant`
+    pub fn record(&self, tenant: &str, user: &str, bytes_in: u64, bytes_out: u64) {
+        self.record_at(tenant, user, bytes_in, bytes_out, Utc::now());
+    }
+
+    fn record_at(
+        &self,
+        tenant: &str,
+        user: &str,
+        bytes_in: u64,
+        bytes_out: u64,
+        time: DateTime<Utc>,
+    ) {
+        let key = UsageKey {
+            user: user.to_string(),
+            tenant: tenant.to_string(),
+            hour: hour_start(time),
+        };
+        self.pending
+            .lock()
+            .expect("bandwidth lock poisoned")
+            .entry(key)
+            .or_default()
+            .add(Counters {
+                bytes_in,
+                bytes_out,
+                requests: 1,
+            });
+
+        if bytes_out > 0 {
+            if let Some(egress) = self
+                .egress
+                .lock()
+                .expect("bandwidth lock poisoned")
+                .get_mut(user)
+            {
+                if egress.month == month_start(time) {
+                    egress.used_bytes += bytes_out;
+                }
+            }
+        }
+    }
+
+    /// Bytes `user` downloaded since `since` that were not flushed yet
+    fn pending_egress(&self, user: &str, since: DateTime<Utc>) -> u64 {
+        self.pending
+            .lock()
+            .expect("bandwidth lock poisoned")
+            .iter()
+            .filter(|(key, _)| key.user == user && key.hour >= since)
+            .map(|(_, counters)| counters.bytes_out)
+            .sum()
+    }
+
+    /// Refuse a download once `user` has used up the egress cap of their plan
+    pub async fn check_egress(
+        &self,
+        state: &AppState,
+        user: &str,
+    ) -> Result<(), EgressLimitExceeded> {
+        if self.config.egress_caps.is_empty() {
+            return Ok(());
+        }
+
+        let now = Utc::now();
+        let month = month_start(now);
+        let cached = self
+            .egress
+            .lock()
+            .expect("bandwidth lock poisoned")
+            .get(user)
+            .filter(|e| e.month == month && e.fetched_at.elapsed() < self.config.cap_refresh)
+            .cloned();
+
+        let egress = match cached {
+            Some(egress) => egress,
+            None => match self.load_egress(state, user, month).await {
+                Ok(egress) => {
+                    self.egress
+                        .lock()
+                        .expect("bandwidth lock poisoned")
+                        .insert(user.to_string(), egress.clone());
+                    egress
+                }
+                Err(e) => {
+                    // Fail open: a metadata outage must not block downloads
+                    warn!(error = %e, user = %user, "Failed to load egress usage, not enforcing cap");
+                    return Ok(());
+                }
+            },
+        };
+
+        match self.config.egress_cap(&egress.plan) {
+            Some(limit) if egress.used_bytes >= limit => Err(EgressLimitExceeded {
+                plan: egress.plan,
+                limit_bytes: limit,
+                used_bytes: egress.used_bytes,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Read a user's plan and month-to-date egress
+    async fn load_egress(
+        &self,
+        state: &AppState,
+        user: &str,
+        month: DateTime<Utc>,
+    ) -> Result<EgressState, DbError> {
+        let (plan, flushed) = match state.metadata_service() {
+            Some(meta) => {
+                let db = meta.database();
+                let plan = db.get_user_plan(user).await?;
+                let used = db.get_user_egress_since(user, month).await?;
+                (plan, used.max(0) as u64)
+            }
+            None => {
+                let used = self
+                    .recorded
+                    .lock()
+                    .expect("bandwidth lock poisoned")
+                    .iter()
+                    .filter(|(key, _)| key.user == user && key.hour >= month)
+                    .map(|(_, counters)| counters.bytes_out)
+                    .sum();
+                (None, used)
+            }
+        };
+
+        Ok(EgressState {
+            plan: plan.unwrap_or_else(|| self.config.default_plan.clone()),
+            month,
+            used_bytes: flushed + self.pending_egress(user, month),
+            fetched_at: Instant::now(),
+        })
+    }
+
+    /// Plan of a user (the default plan if they have no user record)
+    pub async fn user_plan(&self, state: &AppState, user: &str) -> Result<String, DbError> {
+        let plan = match state.metadata_service() {
+            Some(meta) => meta.database().get_user_plan(user).await?,
+            None => None,
+        };
+        Ok(plan.unwrap_or_else(|| self.config.default_plan.clone()))
+    }
+
+    /// Hourly usage of `user` in `[from, to)`, including unflushed counters
+    pub async fn user_usage(
+        &self,
+        state: &AppState,
+        user: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<BandwidthUsage>, DbError> {
+        let mut rows: BTreeMap<(DateTime<Utc>, String), Counters> = BTreeMap::new();
+        let mut add = |key: &UsageKey, counters: Counters| {
+            if key.user == user && key.hour >= from && key.hour < to {
+                rows.entry((key.hour, key.tenant.clone()))
+                    .or_default()
+                    .add(counters);
+            }
+        };
+
+        match state.metadata_service() {
+            Some(meta) => {
+                for row in meta
+                    .database()
+                    .get_user_bandwidth_usage(user, from, to)
+                    .await?
+                {
+                    let key = UsageKey {
+                        user: row.user_id,
+                        tenant: row.tenant_id,
+                        hour: row.hour,
+                    };
+                    add(
+                        &key,
+                        Counters {
+                            bytes_in: row.bytes_in.max(0) as u64,
+                            bytes_out: row.bytes_out.max(0) as u64,
+                            requests: row.requests.max(0) as u64,
+                        },
+                    );
+                }
+            }
+            None => {
+                for (key, counters) in self
+                    .recorded
+                    .lock()
+                    .expect("bandwidth lock poisoned")
+                    .iter()
+                {
+                    add(key, *counters);
+                }
+            }
+        }
+        for (key, counters) in self.pending.lock().expect("bandwidth lock poisoned").iter() {
+            add(key, *counters);
+        }
+
+        Ok(rows
+            .into_iter()
+            .map(|((hour, tenant), counters)| {
+                usage_row(
+                    &UsageKey {
+                        user: user.to_string(),
+                        tenant,
+                        hour,
+                    },
+                    counters,
+                )
+            })
+            .collect())
+    }
+
+    /// Write pending counters to the database (or the in-memory record)
+    ///
+    /// Returns the number of rows written. Counters that could not be
+    /// written are kept for the next flush.
+    pub async fn flush(&self, state: &AppState) -> usize {
+        let pending = std::mem::take(&mut *self.pending.lock().expect("bandwidth lock poisoned"));
+        if pending.is_empty() {
+            return 0;
+        }
+
+        let Some(meta) = state.metadata_service() else {
+            let mut recorded = self.recorded.lock().expect("bandwidth lock poisoned");
+            for (key, counters) in &pending {
+                recorded.entry(key.clone()).or_default().add(*counters);
+            }
+            return pending.len();
+        };
+
+        let rows: Vec<BandwidthUsage> = pending
+            .iter()
+            .map(|(key, counters)| usage_row(key, *counters))
+            .collect();
+        match meta.database().record_bandwidth_usage(&rows).await {
+            Ok(()) => rows.len(),
+            Err(e) => {
+                warn!(error = %e, rows = rows.len(), "Failed to record bandwidth usage, retrying next flush");
+                let mut current = self.pending.lock().expect("bandwidth lock poisoned");
+                for (key, counters) in pending {
+                    current.entry(key).or_default().add(counters);
+                }
+                0
+            }
+        }
+    }
+}
+
+fn content_length(headers: &HeaderMap) -> u64 {
+    headers
+        .get(header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(0)
+}
+
+/// Token subject of a bearer token, None for anonymous or invalid tokens
+async fn bearer_subject(state: &AppState, headers: &HeaderMap) -> Option<String> {
+    let token = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))?;
+    state
+        .auth_service()
+        .validate_token(token)
+        .await
+        .ok()
+        .map(|claims| claims.sub)
+}
+
+/// Whether an S3 request path addresses an object (`/bucket/key`)
+fn is_object_path(path: &str) -> bool {
+    path.trim_start_matches('/')
+        .split_once('/')
+        .is_some_and(|(bucket, key)| !bucket.is_empty() && !key.is_empty())
+}
+
+/// Middleware accounting S3 bandwidth and enforcing egress caps
+///
+/// Must run inside the access log middleware, which provides the tenant and
+/// user the S3 handlers authenticated.
+pub async fn account_bandwidth(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let meter = state.bandwidth_meter();
+    if !meter.is_enabled() {
+        return next.run(request).await;
+    }
+
+    let method = request.method().clone();
+    if method == Method::GET
+        && !meter.config().egress_caps.is_empty()
+        && is_object_path(request.uri().path())
+    {
+        if let Some(user) = bearer_subject(&state, request.headers()).await {
+            if let Err(exceeded) = meter.check_egress(&state, &user).await {
+                debug!(
+                    user = %user,
+                    plan = %exceeded.plan,
+                    used_bytes = exceeded.used_bytes,
+                    "Download refused, egress cap reached"
+                );
+                crate::metrics::record_egress_limited(&exceeded.plan);
+                return S3Error::EgressLimitExceeded {
+                    plan: exceeded.plan,
+                    limit_bytes: exceeded.limit_bytes,
+                }
+                .into_response();
+            }
+        }
+    }
+
+    let bytes_in = content_length(request.headers());
+    let response = next.run(request).await;
+
+    // HEAD and 304 responses announce a length but send no body
+    let bytes_out = if method == Method::HEAD || response.status().as_u16() == 304 {
+        0
+    } else {
+        content_length(response.headers())
+    };
+    if let Some((tenant, user)) = crate::access_log::current_payer() {
+        meter.record(&tenant, &user, bytes_in, bytes_out);
+    }
+
+    response
+}
+
+/// Bandwidth daemon flushing usage counters
+pub struct BandwidthDaemon {
+    config: BandwidthConfig,
+}
+
+impl BandwidthDaemon {
+    /// Create a new bandwidth daemon
+    pub fn new(config: BandwidthConfig) -> Self {
+        Self { config }
+    }
+
+    /// Start the flush loop (background task)
+    pub fn start(self: Arc<Self>, state: Arc<AppState>) -> JoinHandle<()> {
+        let daemon = self;
+
+        tokio::spawn(async move {
+            let mut timer = interval(daemon.config.flush_interval);
+            timer.tick().await;
+
+            info!(
+                interval_secs = daemon.config.flush_interval.as_secs(),
+                caps = daemon.config.egress_caps.len(),
+                "Bandwidth accounting daemon started"
+            );
+
+            loop {
+                timer.tick().await;
+                let rows = state.bandwidth_meter().flush(&state).await;
+                if rows > 0 {
+                    debug!(rows, "Bandwidth usage flushed");
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_parse_egress_caps() {
+        let caps = parse_egress_caps_gb("free=50, pro = 1000,").unwrap();
+        assert_eq!(caps.get("free"), Some(&50));
+        assert_eq!(caps.get("pro"), Some(&1000));
+        assert!(parse_egress_caps_gb("").unwrap().is_empty());
+        assert!(parse_egress_caps_gb("free").is_err());
+        assert!(parse_egress_caps_gb("free=lots").is_err());
+    }
+
+    #[test]
+    fn test_egress_cap_zero_is_unlimited() {
+        let config = BandwidthConfig {
+            egress_caps: BTreeMap::from([
+                ("free".to_string(), 0),
+                ("starter".to_string(), 10 * GB),
+            ]),
+            ..Default::default()
+        };
+        assert_eq!(config.egress_cap("free"), None);
+        assert_eq!(config.egress_cap("starter"), Some(10 * GB));
+        assert_eq!(config.egress_cap("enterprise"), None);
+    }
+
+    #[test]
+    fn test_hour_and_month_start() {
+        let t = time("2026-10-15T13:47:12Z");
+        assert_eq!(hour_start(t), time("2026-10-15T13:00:00Z"));
+        assert_eq!(month_start(t), time("2026-10-01T00:00:00Z"));
+    }
+
+    #[test]
+    fn test_record_rolls_up_per_hour() {
+        let meter = BandwidthMeter::new(BandwidthConfig::default());
+        meter.record_at("acme", "alice", 100, 0, time("2026-10-15T13:01:00Z"));
+        meter.record_at("acme", "alice", 0, 500, time("2026-10-15T13:59:00Z"));
+        meter.record_at("acme", "alice", 0, 7, time("2026-10-15T14:00:00Z"));
+        meter.record_at("acme", "bob", 0, 9, time("2026-10-15T13:30:00Z"));
+
+        let pending = meter.pending.lock().unwrap();
+        assert_eq!(pending.len(), 3);
+        let first = pending
+            .get(&UsageKey {
+                user: "alice".to_string(),
+                tenant: "acme".to_string(),
+                hour: time("2026-10-15T13:00:00Z"),
+            })
+            .unwrap();
+        assert_eq!(
+            *first,
+            Counters {
+                bytes_in: 100,
+                bytes_out: 500,
+                requests: 2
+            }
+        );
+        drop(pending);
+
+        assert_eq!(
+            meter.pending_egress("alice", time("2026-10-01T00:00:00Z")),
+            507
+        );
+        assert_eq!(
+            meter.pending_egress("alice", time("2026-10-15T14:00:00Z")),
+            7
+        );
+    }
+
+    #[test]
+    fn test_is_object_path() {
+        assert!(is_object_path("/photos/cat.jpg"));
+        assert!(is_object_path("/photos/a/b"));
+        assert!(!is_object_path("/photos"));
+        assert!(!is_object_path("/photos/"));
+        assert!(!is_object_path("/"));
+    }
+}
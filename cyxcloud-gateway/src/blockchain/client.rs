@@ -215,6 +215,11 @@ impl CyxCloudBlockchainClient {
     // PAYMENT POOL OPERATIONS
     // =========================================================================
 
+    /// Get payment pool state (current epoch, split percentages)
+    pub async fn get_payment_pool(&self) -> Result<Option<super::types::PaymentPoolInfo>> {
+        self.payment_pool.get_pool().await
+    }
+
     /// Get current epoch number
     pub async fn current_epoch(&self) -> Result<u64> {
         self.payment_pool.current_epoch().await
@@ -0,0 +1,475 @@
+//! Bucket CORS configuration
+//!
+//! Web apps talking to the S3 API straight from a browser need the bucket
+//! to allow their origin. A bucket's CORS rules are managed with
+//! `PUT/GET/DELETE /:bucket?cors` (an S3 `CORSConfiguration`): each rule
+//! lists the origins, methods and request headers it allows, the response
+//! headers the browser may read, and how long a preflight answer is cached.
+//!
+//! [`apply_bucket_cors`] evaluates the rules on the S3 and public object
+//! routes. It answers preflight `OPTIONS` requests itself, with `403
+//! AccessDenied` when no rule allows the request, and gives other requests
+//! the `Access-Control-*` headers of the first rule matching their origin
+//! and method. Buckets without CORS rules keep the gateway-wide `[cors]`
+//! settings.
+//!
+//! A preflight carries no token, so under `/s3` it is matched against the
+//! bucket of the default tenant, like any anonymous request (see
+//! [`crate::acl`]), and under `/public/{tenant}` against that tenant's
+//! bucket. Other requests use the rules of the tenant they were
+//! authenticated as.
+
+use crate::s3_api::{S3Error, S3Result};
+use crate::state::AppState;
+use axum::{
+    extract::{Path, Request, State},
+    http::{header, HeaderMap, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use cyxcloud_metadata::DEFAULT_TENANT;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+/// Rules a bucket can have (S3 limit)
+pub const MAX_CORS_RULES: usize = 100;
+
+/// Methods a rule can allow (S3 set)
+pub const CORS_METHODS: [&str; 5] = ["GET", "PUT", "POST", "DELETE", "HEAD"];
+
+/// How long looked-up rules are used before they are read again, so changes
+/// made through other gateways apply within this time
+const CORS_CACHE_TTL: Duration = Duration::from_secs(30);
+
+/// Response headers of a CORS answer, replaced by a bucket's rules
+const CORS_RESPONSE_HEADERS: [header::HeaderName; 6] = [
+    header::ACCESS_CONTROL_ALLOW_ORIGIN,
+    header::ACCESS_CONTROL_ALLOW_METHODS,
+    header::ACCESS_CONTROL_ALLOW_HEADERS,
+    header::ACCESS_CONTROL_ALLOW_CREDENTIALS,
+    header::ACCESS_CONTROL_EXPOSE_HEADERS,
+    header::ACCESS_CONTROL_MAX_AGE,
+];
+
+/// One CORS rule of a bucket
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CorsRule {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    /// Origins allowed, each with at most one `*` wildcard
+    pub allowed_origins: Vec<String>,
+    /// Methods allowed (GET, PUT, POST, DELETE or HEAD)
+    pub allowed_methods: Vec<String>,
+    /// Request headers a preflight may ask for, each with at most one `*`
+    #[serde(default)]
+    pub allowed_headers: Vec<String>,
+    /// Response headers the browser may read
+    #[serde(default)]
+    pub expose_headers: Vec<String>,
+    /// How long the browser may cache a preflight answer
+    #[serde(default)]
+    pub max_age_secs: Option<u32>,
+}
+
+impl CorsRule {
+    /// Check the rule can be stored
+    pub fn validate(&self) -> S3Result<()> {
+        if self.allowed_origins.is_empty() || self.allowed_methods.is_empty() {
+            return Err(S3Error::InvalidRequest(
+                "A CORS rule needs at least one AllowedOrigin and AllowedMethod".to_string(),
+            ));
+        }
+        if let Some(method) = self
+            .allowed_methods
+            .iter()
+            .find(|m| !CORS_METHODS.contains(&m.as_str()))
+        {
+            return Err(S3Error::InvalidRequest(format!(
+                "Unsupported CORS method: {} (expected one of {})",
+                method,
+                CORS_METHODS.join(", ")
+            )));
+        }
+        for value in self.allowed_origins.iter().chain(&self.allowed_headers) {
+            if value.matches('*').count() > 1 {
+                return Err(S3Error::InvalidRequest(format!(
+                    "{} can not have more than one wildcard",
+                    value
+                )));
+            }
+        }
+        for value in self
+            .allowed_origins
+            .iter()
+            .chain(&self.allowed_headers)
+            .chain(&self.expose_headers)
+        {
+            if value.is_empty() || HeaderValue::from_str(value).is_err() {
+                return Err(S3Error::InvalidRequest(format!(
+                    "Invalid CORS value: {:?}",
+                    value
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether the rule allows requests from `origin`
+    pub fn allows_origin(&self, origin: &str) -> bool {
+        self.allowed_origins
+            .iter()
+            .any(|allowed| wildcard_match(allowed, origin))
+    }
+
+    /// Whether the rule allows `method`
+    pub fn allows_method(&self, method: &str) -> bool {
+        self.allowed_methods.iter().any(|allowed| allowed == method)
+    }
+
+    /// Whether the rule allows all of the request `headers` (names are
+    /// compared case-insensitively)
+    pub fn allows_headers(&self, headers: &[String]) -> bool {
+        headers.iter().all(|name| {
+            let name = name.to_ascii_lowercase();
+            self.allowed_headers
+                .iter()
+                .any(|allowed| wildcard_match(&allowed.to_ascii_lowercase(), &name))
+        })
+    }
+
+    /// `Access-Control-Allow-Origin` for a request from `origin`
+    fn allow_origin<'a>(&self, origin: &'a str) -> &'a str {
+        if self.allowed_origins.iter().any(|allowed| allowed == "*") {
+            "*"
+        } else {
+            origin
+        }
+    }
+}
+
+/// First rule allowing a request from `origin` with `method` and the
+/// request `headers` of a preflight
+pub fn matching_rule<'a>(
+    rules: &'a [CorsRule],
+    origin: &str,
+    method: &str,
+    headers: &[String],
+) -> Option<&'a CorsRule> {
+    rules.iter().find(|rule| {
+        rule.allows_origin(origin) && rule.allows_method(method) && rule.allows_headers(headers)
+    })
+}
+
+/// Match `text` against a pattern with at most one `*` (any run)
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    match pattern.split_once('*') {
+        Some((prefix, suffix)) => {
+            text.len() >= prefix.len() + suffix.len()
+                && text.starts_with(prefix)
+                && text.ends_with(suffix)
+        }
+        None => pattern == text,
+    }
+}
+
+/// Middleware answering CORS requests to a bucket with its rules
+///
+/// Requests without an `Origin`, and those to buckets without rules, are
+/// passed on unchanged (to the gateway-wide CORS layer).
+pub async fn apply_bucket_cors(
+    State(state): State<Arc<AppState>>,
+    params: Option<Path<HashMap<String, String>>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let origin = header_str(request.headers(), header::ORIGIN).map(str::to_string);
+    let (Some(origin), Some(Path(params))) = (origin, params) else {
+        return next.run(request).await;
+    };
+    let Some(bucket) = params.get("bucket").cloned() else {
+        return next.run(request).await;
+    };
+    let tenant = params
+        .get("tenant")
+        .map_or(DEFAULT_TENANT, String::as_str)
+        .to_string();
+
+    let preflight_method = (request.method() == Method::OPTIONS)
+        .then(|| header_str(request.headers(), header::ACCESS_CONTROL_REQUEST_METHOD))
+        .flatten()
+        .map(str::to_string);
+    if let Some(method) = preflight_method {
+        let Some(rules) = bucket_rules(&state, &tenant, &bucket).await else {
+            return next.run(request).await;
+        };
+        let headers = requested_headers(request.headers());
+        return match matching_rule(&rules, &origin, &method, &headers) {
+            Some(rule) => preflight_response(rule, &origin, &headers),
+            None => {
+                debug!(bucket = %bucket, origin = %origin, method = %method, "CORS preflight refused");
+                S3Error::CorsNotAllowed.into_response()
+            }
+        };
+    }
+
+    let method = request.method().clone();
+    let mut response = next.run(request).await;
+    // The handler knows who the request was authenticated as
+    let tenant = crate::access_log::current_tenant().unwrap_or(tenant);
+    let Some(rules) = bucket_rules(&state, &tenant, &bucket).await else {
+        return response;
+    };
+
+    // The bucket's rules replace the gateway-wide CORS headers
+    let headers = response.headers_mut();
+    for name in CORS_RESPONSE_HEADERS {
+        headers.remove(name);
+    }
+    if !headers.contains_key(header::VARY) {
+        headers.insert(header::VARY, HeaderValue::from_static("Origin"));
+    }
+    if let Some(rule) = matching_rule(&rules, &origin, method.as_str(), &[]) {
+        set_header(
+            headers,
+            header::ACCESS_CONTROL_ALLOW_ORIGIN,
+            rule.allow_origin(&origin),
+        );
+        set_header(
+            headers,
+            header::ACCESS_CONTROL_ALLOW_METHODS,
+            &rule.allowed_methods.join(", "),
+        );
+        if !rule.expose_headers.is_empty() {
+            set_header(
+                headers,
+                header::ACCESS_CONTROL_EXPOSE_HEADERS,
+                &rule.expose_headers.join(", "),
+            );
+        }
+    }
+    response
+}
+
+/// Answer to a preflight allowed by `rule`
+fn preflight_response(rule: &CorsRule, origin: &str, requested: &[String]) -> Response {
+    let mut response = StatusCode::OK.into_response();
+    let headers = response.headers_mut();
+    set_header(
+        headers,
+        header::ACCESS_CONTROL_ALLOW_ORIGIN,
+        rule.allow_origin(origin),
+    );
+    set_header(
+        headers,
+        header::ACCESS_CONTROL_ALLOW_METHODS,
+        &rule.allowed_methods.join(", "),
+    );
+    if !requested.is_empty() {
+        set_header(
+            headers,
+            header::ACCESS_CONTROL_ALLOW_HEADERS,
+            &requested.join(", "),
+        );
+    }
+    if !rule.expose_headers.is_empty() {
+        set_header(
+            headers,
+            header::ACCESS_CONTROL_EXPOSE_HEADERS,
+            &rule.expose_headers.join(", "),
+        );
+    }
+    if let Some(max_age) = rule.max_age_secs {
+        set_header(
+            headers,
+            header::ACCESS_CONTROL_MAX_AGE,
+            &max_age.to_string(),
+        );
+    }
+    headers.insert(
+        header::VARY,
+        HeaderValue::from_static(
+            "Origin, Access-Control-Request-Method, Access-Control-Request-Headers",
+        ),
+    );
+    response
+}
+
+/// Rules of a bucket, None if it has none (or cannot be looked up)
+async fn bucket_rules(state: &AppState, tenant: &str, bucket: &str) -> Option<Arc<Vec<CorsRule>>> {
+    match state.bucket_cors_for_request(tenant, bucket).await {
+        Ok(rules) => rules,
+        Err(e) => {
+            debug!(error = %e, bucket = %bucket, "Failed to look up bucket CORS rules");
+            None
+        }
+    }
+}
+
+/// Header names listed in `Access-Control-Request-Headers`
+fn requested_headers(headers: &HeaderMap) -> Vec<String> {
+    headers
+        .get_all(header::ACCESS_CONTROL_REQUEST_HEADERS)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(|name| name.trim().to_ascii_lowercase())
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+fn header_str(headers: &HeaderMap, name: header::HeaderName) -> Option<&str> {
+    headers.get(name).and_then(|v| v.to_str().ok())
+}
+
+fn set_header(headers: &mut HeaderMap, name: header::HeaderName, value: &str) {
+    if let Ok(value) = HeaderValue::from_str(value) {
+        headers.insert(name, value);
+    }
+}
+
+/// Tenant and bucket name
+type BucketKey = (String, String);
+
+/// Recently looked-up bucket CORS rules
+#[derive(Default)]
+pub struct CorsCache {
+    entries: Mutex<HashMap<BucketKey, (Instant, Option<Arc<Vec<CorsRule>>>)>>,
+}
+
+impl CorsCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cached rules of a bucket, outer None if they have to be looked up
+    pub fn get(&self, tenant: &str, bucket: &str) -> Option<Option<Arc<Vec<CorsRule>>>> {
+        let entries = self.entries.lock().expect("CORS cache lock poisoned");
+        entries
+            .get(&(tenant.to_string(), bucket.to_string()))
+            .filter(|(at, _)| at.elapsed() < CORS_CACHE_TTL)
+            .map(|(_, rules)| rules.clone())
+    }
+
+    /// Remember the rules (or their absence) of a bucket
+    pub fn insert(&self, tenant: &str, bucket: &str, rules: Option<Arc<Vec<CorsRule>>>) {
+        let mut entries = self.entries.lock().expect("CORS cache lock poisoned");
+        entries.retain(|_, (at, _)| at.elapsed() < CORS_CACHE_TTL);
+        entries.insert(
+            (tenant.to_string(), bucket.to_string()),
+            (Instant::now(), rules),
+        );
+    }
+
+    /// Forget the cached rules of a bucket after they changed
+    pub fn forget(&self, tenant: &str, bucket: &str) {
+        self.entries
+            .lock()
+            .expect("CORS cache lock poisoned")
+            .remove(&(tenant.to_string(), bucket.to_string()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(origins: &[&str], methods: &[&str], headers: &[&str]) -> CorsRule {
+        CorsRule {
+            allowed_origins: origins.iter().map(|s| s.to_string()).collect(),
+            allowed_methods: methods.iter().map(|s| s.to_string()).collect(),
+            allowed_headers: headers.iter().map(|s| s.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_matching_rule() {
+        let rules = vec![
+            rule(
+                &["https://app.example.com"],
+                &["GET", "PUT"],
+                &["Authorization", "x-amz-*"],
+            ),
+            rule(&["https://*.example.org"], &["GET"], &[]),
+            rule(&["*"], &["HEAD"], &["*"]),
+        ];
+
+        let found = matching_rule(
+            &rules,
+            "https://app.example.com",
+            "PUT",
+            &names(&["authorization", "x-amz-meta-a"]),
+        );
+        assert_eq!(found, Some(&rules[0]));
+        assert!(matching_rule(
+            &rules,
+            "https://app.example.com",
+            "PUT",
+            &names(&["content-md5"])
+        )
+        .is_none());
+        assert!(matching_rule(&rules, "https://app.example.com", "DELETE", &[]).is_none());
+
+        assert_eq!(
+            matching_rule(&rules, "https://cdn.example.org", "GET", &[]),
+            Some(&rules[1])
+        );
+        assert!(matching_rule(&rules, "https://example.org", "GET", &[]).is_none());
+        assert!(
+            matching_rule(&rules, "https://cdn.example.org", "GET", &names(&["range"])).is_none()
+        );
+
+        let any =
+            matching_rule(&rules, "http://localhost:3000", "HEAD", &names(&["range"])).unwrap();
+        assert_eq!(any.allow_origin("http://localhost:3000"), "*");
+        assert_eq!(
+            rules[0].allow_origin("https://app.example.com"),
+            "https://app.example.com"
+        );
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(rule(&["*"], &["GET"], &[]).validate().is_ok());
+        assert!(rule(&[], &["GET"], &[]).validate().is_err());
+        assert!(rule(&["*"], &[], &[]).validate().is_err());
+        assert!(rule(&["*"], &["PATCH"], &[]).validate().is_err());
+        assert!(rule(&["https://*.*.example.com"], &["GET"], &[])
+            .validate()
+            .is_err());
+        assert!(rule(&["*"], &["GET"], &["x-*-*"]).validate().is_err());
+    }
+
+    #[test]
+    fn test_requested_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::ACCESS_CONTROL_REQUEST_HEADERS,
+            HeaderValue::from_static("Authorization, Content-Type,,x-amz-meta-a"),
+        );
+        assert_eq!(
+            requested_headers(&headers),
+            names(&["authorization", "content-type", "x-amz-meta-a"])
+        );
+    }
+
+    #[test]
+    fn test_cors_cache() {
+        let cache = CorsCache::new();
+        assert!(cache.get("acme", "photos").is_none());
+
+        cache.insert("acme", "photos", None);
+        assert!(matches!(cache.get("acme", "photos"), Some(None)));
+        assert!(cache.get("globex", "photos").is_none());
+
+        cache.forget("acme", "photos");
+        assert!(cache.get("acme", "photos").is_none());
+    }
+}
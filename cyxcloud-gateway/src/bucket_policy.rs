@@ -0,0 +1,766 @@
+//! S3 Bucket Policies
+//!
+//! A bucket policy is a JSON document in a subset of the AWS policy
+//! language, managed with `PUT/GET/DELETE /:bucket?policy`:
+//!
+//! ```json
+//! {
+//!   "Version": "2012-10-17",
+//!   "Statement": [{
+//!     "Effect": "Allow",
+//!     "Principal": "*",
+//!     "Action": "s3:GetObject",
+//!     "Resource": "arn:aws:s3:::photos/public/*",
+//!     "Condition": {"IpAddress": {"aws:SourceIp": "10.0.0.0/8"}}
+//!   }]
+//! }
+//! ```
+//!
+//! - `Principal` is `"*"` (anyone, anonymous requests included) or
+//!   `{"AWS": [...]}` / `{"CyxCloud": [...]}` listing token subjects
+//! - `Action` and `Resource` take `*` and `?` wildcards; resources are ARNs
+//!   of this bucket (`arn:aws:s3:::bucket`) or of keys in it
+//!   (`arn:aws:s3:::bucket/prefix*`)
+//! - `Condition` supports `IpAddress` / `NotIpAddress` on `aws:SourceIp`
+//!   and `StringEquals` / `StringNotEquals` / `StringLike` / `StringNotLike`
+//!   on `s3:prefix` (the `prefix` of a listing)
+//!
+//! The policy is evaluated before the token's scopes are checked: a
+//! matching `Deny` refuses the request, a matching `Allow` grants it even
+//! without the scope, and otherwise the scopes decide as before.
+
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+/// Largest accepted policy document (AWS limit)
+pub const MAX_POLICY_SIZE: usize = 20 * 1024;
+
+/// How long a looked-up policy is used before it is read again, so changes
+/// made through other gateways apply within this time
+const POLICY_CACHE_TTL: Duration = Duration::from_secs(30);
+
+/// ARN prefix of S3 resources
+const ARN_PREFIX: &str = "arn:aws:s3:::";
+
+/// Actions a policy statement can name
+pub mod actions {
+    pub const GET_OBJECT: &str = "s3:GetObject";
+    pub const PUT_OBJECT: &str = "s3:PutObject";
+    pub const DELETE_OBJECT: &str = "s3:DeleteObject";
+    pub const RESTORE_OBJECT: &str = "s3:RestoreObject";
+    pub const GET_OBJECT_RETENTION: &str = "s3:GetObjectRetention";
+    pub const PUT_OBJECT_RETENTION: &str = "s3:PutObjectRetention";
+    pub const LIST_BUCKET: &str = "s3:ListBucket";
+    pub const DELETE_BUCKET: &str = "s3:DeleteBucket";
+    pub const GET_BUCKET_LOGGING: &str = "s3:GetBucketLogging";
+    pub const PUT_BUCKET_LOGGING: &str = "s3:PutBucketLogging";
+    pub const GET_BUCKET_OBJECT_LOCK: &str = "s3:GetBucketObjectLockConfiguration";
+    pub const PUT_BUCKET_OBJECT_LOCK: &str = "s3:PutBucketObjectLockConfiguration";
+    pub const GET_BUCKET_ACL: &str = "s3:GetBucketAcl";
+    pub const PUT_BUCKET_ACL: &str = "s3:PutBucketAcl";
+    pub const GET_OBJECT_ACL: &str = "s3:GetObjectAcl";
+    pub const PUT_OBJECT_ACL: &str = "s3:PutObjectAcl";
+    pub const GET_BUCKET_CACHE_POLICY: &str = "s3:GetBucketCachePolicy";
+    pub const PUT_BUCKET_CACHE_POLICY: &str = "s3:PutBucketCachePolicy";
+    pub const GET_LIFECYCLE_CONFIGURATION: &str = "s3:GetLifecycleConfiguration";
+    pub const PUT_LIFECYCLE_CONFIGURATION: &str = "s3:PutLifecycleConfiguration";
+    pub const GET_BUCKET_CORS: &str = "s3:GetBucketCORS";
+    pub const PUT_BUCKET_CORS: &str = "s3:PutBucketCORS";
+
+    /// All actions a policy is evaluated for
+    pub const ALL: &[&str] = &[
+        GET_OBJECT,
+        PUT_OBJECT,
+        DELETE_OBJECT,
+        RESTORE_OBJECT,
+        GET_OBJECT_RETENTION,
+        PUT_OBJECT_RETENTION,
+        LIST_BUCKET,
+        DELETE_BUCKET,
+        GET_BUCKET_LOGGING,
+        PUT_BUCKET_LOGGING,
+        GET_BUCKET_OBJECT_LOCK,
+        PUT_BUCKET_OBJECT_LOCK,
+        GET_BUCKET_ACL,
+        PUT_BUCKET_ACL,
+        GET_OBJECT_ACL,
+        PUT_OBJECT_ACL,
+        GET_BUCKET_CACHE_POLICY,
+        PUT_BUCKET_CACHE_POLICY,
+        GET_LIFECYCLE_CONFIGURATION,
+        PUT_LIFECYCLE_CONFIGURATION,
+        GET_BUCKET_CORS,
+        PUT_BUCKET_CORS,
+    ];
+}
+
+/// Policy document that cannot be used
+#[derive(Debug, Error, PartialEq)]
+#[error("{0}")]
+pub struct PolicyError(String);
+
+type PolicyResult<T> = Result<T, PolicyError>;
+
+/// Outcome of evaluating a policy for one request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decision {
+    /// A statement allows the request and none denies it
+    Allow,
+    /// A statement denies the request
+    Deny,
+    /// No statement applies; the token's scopes decide
+    NotApplicable,
+}
+
+/// Request a policy is evaluated for
+#[derive(Debug, Clone, Copy)]
+pub struct PolicyRequest<'a> {
+    /// Token subject, None for anonymous requests
+    pub principal: Option<&'a str>,
+    /// One of [`actions`]
+    pub action: &'a str,
+    pub bucket: &'a str,
+    /// Object key, None for bucket operations
+    pub key: Option<&'a str>,
+    pub source_ip: Option<IpAddr>,
+    /// `prefix` of a listing
+    pub prefix: Option<&'a str>,
+}
+
+impl<'a> PolicyRequest<'a> {
+    /// Operation on a bucket
+    pub fn bucket(action: &'a str, bucket: &'a str) -> Self {
+        Self {
+            principal: None,
+            action,
+            bucket,
+            key: None,
+            source_ip: None,
+            prefix: None,
+        }
+    }
+
+    /// Operation on an object
+    pub fn object(action: &'a str, bucket: &'a str, key: &'a str) -> Self {
+        Self {
+            key: Some(key),
+            ..Self::bucket(action, bucket)
+        }
+    }
+
+    /// Listing of the keys under `prefix`
+    pub fn listing(bucket: &'a str, prefix: Option<&'a str>) -> Self {
+        Self {
+            prefix,
+            ..Self::bucket(actions::LIST_BUCKET, bucket)
+        }
+    }
+
+    /// Resource path matched against statement resources (`bucket/key`)
+    fn resource(&self) -> String {
+        match self.key {
+            Some(key) => format!("{}/{}", self.bucket, key),
+            None => self.bucket.to_string(),
+        }
+    }
+}
+
+/// Parsed bucket policy
+#[derive(Debug, Clone)]
+pub struct BucketPolicy {
+    /// Document as stored and returned by `GET ?policy`
+    document: serde_json::Value,
+    statements: Vec<Statement>,
+}
+
+impl BucketPolicy {
+    /// Parse and validate the policy of `bucket`
+    pub fn parse(bucket: &str, json: &str) -> PolicyResult<Self> {
+        if json.len() > MAX_POLICY_SIZE {
+            return Err(PolicyError(format!(
+                "Policies are limited to {} bytes",
+                MAX_POLICY_SIZE
+            )));
+        }
+        let document: serde_json::Value = serde_json::from_str(json)
+            .map_err(|e| PolicyError(format!("Policy is not valid JSON: {}", e)))?;
+        Self::from_document(bucket, document)
+    }
+
+    /// Validate a stored policy document of `bucket`
+    pub fn from_document(bucket: &str, document: serde_json::Value) -> PolicyResult<Self> {
+        let raw: RawPolicy = serde_json::from_value(document.clone())
+            .map_err(|e| PolicyError(format!("Invalid policy: {}", e)))?;
+        if let Some(version) = raw.version.as_deref() {
+            if version != "2012-10-17" && version != "2008-10-17" {
+                return Err(PolicyError(format!(
+                    "Unsupported policy version {}",
+                    version
+                )));
+            }
+        }
+
+        let statements = raw
+            .statement
+            .into_vec()
+            .into_iter()
+            .map(|statement| Statement::compile(bucket, statement))
+            .collect::<PolicyResult<Vec<_>>>()?;
+        if statements.is_empty() {
+            return Err(PolicyError("Policy has no statements".to_string()));
+        }
+
+        Ok(Self {
+            document,
+            statements,
+        })
+    }
+
+    /// The policy document
+    pub fn document(&self) -> &serde_json::Value {
+        &self.document
+    }
+
+    /// Evaluate the policy for a request; an explicit deny always wins
+    pub fn evaluate(&self, request: &PolicyRequest<'_>) -> Decision {
+        let resource = request.resource();
+        let mut decision = Decision::NotApplicable;
+        for statement in &self.statements {
+            if !statement.applies(request, &resource) {
+                continue;
+            }
+            match statement.effect {
+                Effect::Deny => return Decision::Deny,
+                Effect::Allow => decision = Decision::Allow,
+            }
+        }
+        decision
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+enum Effect {
+    Allow,
+    Deny,
+}
+
+/// Compiled policy statement
+#[derive(Debug, Clone)]
+struct Statement {
+    effect: Effect,
+    /// Token subjects the statement applies to, None for everyone
+    principals: Option<Vec<String>>,
+    /// Action patterns, lowercased (actions are case-insensitive)
+    actions: Vec<String>,
+    /// `bucket/key` patterns, without the ARN prefix
+    resources: Vec<String>,
+    conditions: Vec<Condition>,
+}
+
+impl Statement {
+    fn compile(bucket: &str, raw: RawStatement) -> PolicyResult<Self> {
+        let principals = match raw.principal {
+            RawPrincipal::Any(any) if any == "*" => None,
+            RawPrincipal::Any(other) => {
+                return Err(PolicyError(format!("Invalid principal {}", other)));
+            }
+            RawPrincipal::Map(map) => {
+                let mut principals = Vec::new();
+                for (kind, ids) in map {
+                    if kind != "AWS" && kind != "CyxCloud" {
+                        return Err(PolicyError(format!("Unsupported principal type {}", kind)));
+                    }
+                    principals.extend(ids.into_vec());
+                }
+                if principals.iter().any(|p| p == "*") {
+                    None
+                } else {
+                    Some(principals)
+                }
+            }
+        };
+
+        let actions = raw.action.into_vec();
+        for action in &actions {
+            let known = action == "*"
+                || actions::ALL
+                    .iter()
+                    .any(|a| glob_match(&action.to_ascii_lowercase(), &a.to_ascii_lowercase()));
+            if !known {
+                return Err(PolicyError(format!("Unsupported action {}", action)));
+            }
+        }
+
+        let mut resources = Vec::new();
+        for resource in raw.resource.into_vec() {
+            let path = resource
+                .strip_prefix(ARN_PREFIX)
+                .ok_or_else(|| PolicyError(format!("Invalid resource {}", resource)))?;
+            let resource_bucket = path.split('/').next().unwrap_or_default();
+            if !glob_match(resource_bucket, bucket) {
+                return Err(PolicyError(format!(
+                    "Resource {} is not in bucket {}",
+                    resource, bucket
+                )));
+            }
+            resources.push(path.to_string());
+        }
+
+        let mut conditions = Vec::new();
+        for (operator, entries) in raw.condition {
+            for (key, values) in entries {
+                conditions.push(Condition::compile(&operator, &key, values.into_vec())?);
+            }
+        }
+
+        if actions.is_empty() || resources.is_empty() {
+            return Err(PolicyError(
+                "Statements need at least one action and one resource".to_string(),
+            ));
+        }
+
+        Ok(Self {
+            effect: raw.effect,
+            principals,
+            actions: actions.iter().map(|a| a.to_ascii_lowercase()).collect(),
+            resources,
+            conditions,
+        })
+    }
+
+    fn applies(&self, request: &PolicyRequest<'_>, resource: &str) -> bool {
+        if let Some(ref principals) = self.principals {
+            let Some(principal) = request.principal else {
+                return false;
+            };
+            if !principals.iter().any(|p| p == principal) {
+                return false;
+            }
+        }
+        let action = request.action.to_ascii_lowercase();
+        self.actions.iter().any(|a| glob_match(a, &action))
+            && self.resources.iter().any(|r| glob_match(r, resource))
+            && self.conditions.iter().all(|c| c.holds(request))
+    }
+}
+
+/// Policy condition on one request value
+#[derive(Debug, Clone)]
+enum Condition {
+    /// `aws:SourceIp` within one of the ranges
+    SourceIp { ranges: Vec<IpRange>, negate: bool },
+    /// `s3:prefix` equal to (or, with `like`, matching) one of the values
+    Prefix {
+        values: Vec<String>,
+        like: bool,
+        negate: bool,
+    },
+}
+
+impl Condition {
+    fn compile(operator: &str, key: &str, values: Vec<String>) -> PolicyResult<Self> {
+        let unsupported = || PolicyError(format!("Unsupported condition {} on {}", operator, key));
+        match (operator, key) {
+            ("IpAddress" | "NotIpAddress", "aws:SourceIp") => Ok(Condition::SourceIp {
+                ranges: values
+                    .iter()
+                    .map(|v| IpRange::parse(v))
+                    .collect::<PolicyResult<_>>()?,
+                negate: operator == "NotIpAddress",
+            }),
+            ("StringEquals" | "StringNotEquals" | "StringLike" | "StringNotLike", "s3:prefix") => {
+                Ok(Condition::Prefix {
+                    values,
+                    like: operator.ends_with("Like"),
+                    negate: operator.starts_with("StringNot"),
+                })
+            }
+            _ => Err(unsupported()),
+        }
+    }
+
+    /// Whether the condition holds; a request without the value only meets
+    /// negated conditions, except that one whose source address is unknown
+    /// meets no IP condition at all
+    fn holds(&self, request: &PolicyRequest<'_>) -> bool {
+        let (matched, negate) = match self {
+            Condition::SourceIp { ranges, negate } => match request.source_ip {
+                Some(ip) => (ranges.iter().any(|r| r.contains(ip)), *negate),
+                None => return false,
+            },
+            Condition::Prefix {
+                values,
+                like,
+                negate,
+            } => (
+                request.prefix.is_some_and(|prefix| {
+                    values.iter().any(|v| {
+                        if *like {
+                            glob_match(v, prefix)
+                        } else {
+                            v == prefix
+                        }
+                    })
+                }),
+                *negate,
+            ),
+        };
+        matched != negate
+    }
+}
+
+/// IPv4 or IPv6 address range in CIDR notation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IpRange {
+    network: IpAddr,
+    prefix_len: u8,
+}
+
+impl IpRange {
+    /// Parse `addr/len`, or a single address
+    pub fn parse(value: &str) -> PolicyResult<Self> {
+        let invalid = || PolicyError(format!("Invalid IP range {}", value));
+        let (addr, len) = match value.split_once('/') {
+            Some((addr, len)) => (addr, Some(len)),
+            None => (value, None),
+        };
+        let network: IpAddr = addr.trim().parse().map_err(|_| invalid())?;
+        let max = if network.is_ipv4() { 32 } else { 128 };
+        let prefix_len = match len {
+            Some(len) => len.trim().parse::<u8>().map_err(|_| invalid())?,
+            None => max,
+        };
+        if prefix_len > max {
+            return Err(invalid());
+        }
+        Ok(Self {
+            network,
+            prefix_len,
+        })
+    }
+
+    /// Whether `ip` is in the range
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.network, ip) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                prefix_eq(&net.octets(), &ip.octets(), self.prefix_len)
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                prefix_eq(&net.octets(), &ip.octets(), self.prefix_len)
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Whether the first `bits` bits of two addresses are equal
+fn prefix_eq(a: &[u8], b: &[u8], bits: u8) -> bool {
+    let full = bits as usize / 8;
+    if a[..full] != b[..full] {
+        return false;
+    }
+    let rest = bits % 8;
+    if rest == 0 {
+        return true;
+    }
+    let mask = 0xffu8 << (8 - rest);
+    a[full] & mask == b[full] & mask
+}
+
+/// Match `text` against a pattern with `*` (any run) and `?` (any single
+/// character)
+pub(crate) fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Position of the last `*` and the text position it was tried at
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = star {
+            p = star_p + 1;
+            t = star_t + 1;
+            star = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum OneOrMany<T> {
+    One(T),
+    Many(Vec<T>),
+}
+
+impl<T> OneOrMany<T> {
+    fn into_vec(self) -> Vec<T> {
+        match self {
+            OneOrMany::One(one) => vec![one],
+            OneOrMany::Many(many) => many,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase", deny_unknown_fields)]
+struct RawPolicy {
+    version: Option<String>,
+    #[allow(dead_code)]
+    id: Option<String>,
+    statement: OneOrMany<RawStatement>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase", deny_unknown_fields)]
+struct RawStatement {
+    #[allow(dead_code)]
+    sid: Option<String>,
+    effect: Effect,
+    principal: RawPrincipal,
+    action: OneOrMany<String>,
+    resource: OneOrMany<String>,
+    #[serde(default)]
+    condition: HashMap<String, HashMap<String, OneOrMany<String>>>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawPrincipal {
+    Any(String),
+    Map(HashMap<String, OneOrMany<String>>),
+}
+
+/// Tenant and bucket name
+type BucketKey = (String, String);
+
+/// Recently looked-up bucket policies
+#[derive(Default)]
+pub struct PolicyCache {
+    entries: Mutex<HashMap<BucketKey, (Instant, Option<Arc<BucketPolicy>>)>>,
+}
+
+impl PolicyCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cached policy of a bucket, outer None if it has to be looked up
+    pub fn get(&self, tenant: &str, bucket: &str) -> Option<Option<Arc<BucketPolicy>>> {
+        let entries = self.entries.lock().expect("policy cache lock poisoned");
+        entries
+            .get(&(tenant.to_string(), bucket.to_string()))
+            .filter(|(at, _)| at.elapsed() < POLICY_CACHE_TTL)
+            .map(|(_, policy)| policy.clone())
+    }
+
+    /// Remember the policy (or its absence) of a bucket
+    pub fn insert(&self, tenant: &str, bucket: &str, policy: Option<Arc<BucketPolicy>>) {
+        let mut entries = self.entries.lock().expect("policy cache lock poisoned");
+        entries.retain(|_, (at, _)| at.elapsed() < POLICY_CACHE_TTL);
+        entries.insert(
+            (tenant.to_string(), bucket.to_string()),
+            (Instant::now(), policy),
+        );
+    }
+
+    /// Forget the cached policy of a bucket after it changed
+    pub fn forget(&self, tenant: &str, bucket: &str) {
+        self.entries
+            .lock()
+            .expect("policy cache lock poisoned")
+            .remove(&(tenant.to_string(), bucket.to_string()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request<'a>(
+        principal: Option<&'a str>,
+        action: &'a str,
+        key: Option<&'a str>,
+    ) -> PolicyRequest<'a> {
+        PolicyRequest {
+            principal,
+            key,
+            ..PolicyRequest::bucket(action, "photos")
+        }
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("public/*", "public/a/b.jpg"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("s3:get*", "s3:getobject"));
+        assert!(glob_match("a?c", "abc"));
+        assert!(glob_match("*.jpg", "x.y.jpg"));
+        assert!(!glob_match("public/*", "private/a"));
+        assert!(!glob_match("a?c", "ac"));
+    }
+
+    #[test]
+    fn test_ip_range() {
+        let range = IpRange::parse("10.1.0.0/16").unwrap();
+        assert!(range.contains("10.1.200.3".parse().unwrap()));
+        assert!(!range.contains("10.2.0.1".parse().unwrap()));
+        assert!(!range.contains("::1".parse().unwrap()));
+
+        let single = IpRange::parse("192.168.1.7").unwrap();
+        assert!(single.contains("192.168.1.7".parse().unwrap()));
+        assert!(!single.contains("192.168.1.8".parse().unwrap()));
+
+        let v6 = IpRange::parse("2001:db8::/33").unwrap();
+        assert!(v6.contains("2001:db8:7fff::1".parse().unwrap()));
+        assert!(!v6.contains("2001:db8:8000::1".parse().unwrap()));
+
+        assert!(IpRange::parse("10.0.0.0/33").is_err());
+        assert!(IpRange::parse("not-an-ip").is_err());
+    }
+
+    #[test]
+    fn test_anonymous_read_on_prefix() {
+        let policy = BucketPolicy::parse(
+            "photos",
+            r#"{
+                "Version": "2012-10-17",
+                "Statement": {
+                    "Effect": "Allow",
+                    "Principal": "*",
+                    "Action": ["s3:GetObject"],
+                    "Resource": "arn:aws:s3:::photos/public/*"
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let get = request(None, actions::GET_OBJECT, Some("public/cat.jpg"));
+        assert_eq!(policy.evaluate(&get), Decision::Allow);
+
+        let private = request(None, actions::GET_OBJECT, Some("private/cat.jpg"));
+        assert_eq!(policy.evaluate(&private), Decision::NotApplicable);
+
+        let put = request(None, actions::PUT_OBJECT, Some("public/cat.jpg"));
+        assert_eq!(policy.evaluate(&put), Decision::NotApplicable);
+    }
+
+    #[test]
+    fn test_deny_wins_and_principals() {
+        let policy = BucketPolicy::parse(
+            "photos",
+            r#"{
+                "Statement": [
+                    {"Effect": "Allow", "Principal": "*", "Action": "s3:*",
+                     "Resource": ["arn:aws:s3:::photos", "arn:aws:s3:::photos/*"]},
+                    {"Effect": "Deny", "Principal": {"AWS": ["mallory"]},
+                     "Action": "s3:DeleteObject", "Resource": "arn:aws:s3:::photos/*"}
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let delete = request(Some("mallory"), actions::DELETE_OBJECT, Some("a"));
+        assert_eq!(policy.evaluate(&delete), Decision::Deny);
+
+        let other = request(Some("alice"), actions::DELETE_OBJECT, Some("a"));
+        assert_eq!(policy.evaluate(&other), Decision::Allow);
+
+        let list = request(None, actions::LIST_BUCKET, None);
+        assert_eq!(policy.evaluate(&list), Decision::Allow);
+    }
+
+    #[test]
+    fn test_conditions() {
+        let policy = BucketPolicy::parse(
+            "photos",
+            r#"{
+                "Statement": [
+                    {"Effect": "Deny", "Principal": "*", "Action": "s3:*",
+                     "Resource": "arn:aws:s3:::photos/*",
+                     "Condition": {"NotIpAddress": {"aws:SourceIp": ["10.0.0.0/8"]}}},
+                    {"Effect": "Allow", "Principal": "*", "Action": "s3:ListBucket",
+                     "Resource": "arn:aws:s3:::photos",
+                     "Condition": {"StringLike": {"s3:prefix": "public/*"}}}
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        // An unknown source address meets neither IpAddress nor NotIpAddress
+        let mut get = request(Some("alice"), actions::GET_OBJECT, Some("a"));
+        assert_eq!(policy.evaluate(&get), Decision::NotApplicable);
+        get.source_ip = Some("10.3.4.5".parse().unwrap());
+        assert_eq!(policy.evaluate(&get), Decision::NotApplicable);
+        get.source_ip = Some("192.0.2.1".parse().unwrap());
+        assert_eq!(policy.evaluate(&get), Decision::Deny);
+
+        let mut list = request(None, actions::LIST_BUCKET, None);
+        assert_eq!(policy.evaluate(&list), Decision::NotApplicable);
+        list.prefix = Some("public/2024/");
+        assert_eq!(policy.evaluate(&list), Decision::Allow);
+        list.prefix = Some("private/");
+        assert_eq!(policy.evaluate(&list), Decision::NotApplicable);
+    }
+
+    #[test]
+    fn test_invalid_policies() {
+        let statement = |s: &str| format!(r#"{{"Statement": [{}]}}"#, s);
+        let invalid = [
+            "not json".to_string(),
+            r#"{"Statement": []}"#.to_string(),
+            r#"{"Version": "2020-01-01", "Statement": []}"#.to_string(),
+            // Another bucket
+            statement(
+                r#"{"Effect": "Allow", "Principal": "*", "Action": "s3:GetObject", "Resource": "arn:aws:s3:::other/*"}"#,
+            ),
+            // Not an S3 ARN
+            statement(
+                r#"{"Effect": "Allow", "Principal": "*", "Action": "s3:GetObject", "Resource": "photos/*"}"#,
+            ),
+            statement(
+                r#"{"Effect": "Allow", "Principal": "*", "Action": "s3:Frobnicate", "Resource": "arn:aws:s3:::photos/*"}"#,
+            ),
+            statement(
+                r#"{"Effect": "Maybe", "Principal": "*", "Action": "s3:GetObject", "Resource": "arn:aws:s3:::photos/*"}"#,
+            ),
+            statement(
+                r#"{"Effect": "Allow", "Principal": "someone", "Action": "s3:GetObject", "Resource": "arn:aws:s3:::photos/*"}"#,
+            ),
+            statement(
+                r#"{"Effect": "Allow", "Principal": "*", "Action": "s3:GetObject", "Resource": "arn:aws:s3:::photos/*",
+                "Condition": {"DateGreaterThan": {"aws:CurrentTime": "2024-01-01"}}}"#,
+            ),
+        ];
+        for json in invalid {
+            assert!(BucketPolicy::parse("photos", &json).is_err(), "{}", json);
+        }
+
+        let too_large = " ".repeat(MAX_POLICY_SIZE + 1);
+        assert!(BucketPolicy::parse("photos", &too_large).is_err());
+    }
+
+    #[test]
+    fn test_policy_cache() {
+        let cache = PolicyCache::new();
+        assert!(cache.get("acme", "photos").is_none());
+
+        cache.insert("acme", "photos", None);
+        assert!(matches!(cache.get("acme", "photos"), Some(None)));
+        assert!(cache.get("globex", "photos").is_none());
+
+        cache.forget("acme", "photos");
+        assert!(cache.get("acme", "photos").is_none());
+    }
+}
@@ -0,0 +1,135 @@
+//! Client address resolution
+//!
+//! The client of a request is the peer of its connection. Behind a reverse
+//! proxy that peer is the proxy, so for peers in the trusted proxy list
+//! (`[server] trusted_proxies`, `GATEWAY_TRUSTED_PROXIES`) the client is
+//! taken from `X-Forwarded-For` instead: the rightmost address that is not
+//! itself a trusted proxy, since everything left of it was sent by the
+//! client. `X-Forwarded-For` from any other peer is ignored.
+//!
+//! The address is resolved once per request and available to handlers via
+//! [`current`]. It is None when it cannot be determined (no peer address, or
+//! a malformed header from a trusted proxy).
+
+use crate::bucket_policy::IpRange;
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::HeaderMap,
+    middleware::Next,
+    response::Response,
+};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+
+/// Header listing the addresses a request was forwarded for
+const FORWARDED_FOR_HEADER: &str = "x-forwarded-for";
+
+tokio::task_local! {
+    static CLIENT_IP: Option<IpAddr>;
+}
+
+/// Client address of the request being handled, if known
+pub fn current() -> Option<IpAddr> {
+    CLIENT_IP.try_with(|ip| *ip).ok().flatten()
+}
+
+/// Resolve the client address of a request from `peer`
+pub fn resolve(peer: Option<IpAddr>, headers: &HeaderMap, trusted: &[IpRange]) -> Option<IpAddr> {
+    let peer = peer?;
+    let is_trusted = |ip: IpAddr| trusted.iter().any(|range| range.contains(ip));
+    if !is_trusted(peer) {
+        return Some(peer);
+    }
+
+    let mut hops = Vec::new();
+    for value in headers.get_all(FORWARDED_FOR_HEADER) {
+        hops.extend(value.to_str().ok()?.split(',').map(str::trim));
+    }
+
+    // Walk back from the proxy that connected to us; if every hop is a
+    // trusted proxy the leftmost one is the client
+    let mut client = peer;
+    for hop in hops.into_iter().rev() {
+        client = parse_hop(hop)?;
+        if !is_trusted(client) {
+            break;
+        }
+    }
+    Some(client)
+}
+
+/// Parse one `X-Forwarded-For` entry, which some proxies send with a port
+fn parse_hop(hop: &str) -> Option<IpAddr> {
+    hop.parse::<IpAddr>()
+        .or_else(|_| hop.parse::<SocketAddr>().map(|addr| addr.ip()))
+        .ok()
+}
+
+/// Middleware resolving the client address of every request
+pub async fn resolve_client_ip(
+    State(trusted): State<Arc<Vec<IpRange>>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let peer = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip());
+    let ip = resolve(peer, request.headers(), &trusted);
+    CLIENT_IP.scope(ip, next.run(request)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn forwarded(values: &[&str]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for value in values {
+            headers.append(FORWARDED_FOR_HEADER, value.parse().unwrap());
+        }
+        headers
+    }
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_untrusted_peer_is_the_client() {
+        let trusted = [IpRange::parse("10.0.0.0/8").unwrap()];
+        let headers = forwarded(&["10.1.1.1"]);
+
+        // A forged header from a direct client is ignored
+        assert_eq!(
+            resolve(Some(ip("192.0.2.7")), &headers, &trusted),
+            Some(ip("192.0.2.7"))
+        );
+        assert_eq!(
+            resolve(Some(ip("192.0.2.7")), &headers, &[]),
+            Some(ip("192.0.2.7"))
+        );
+        assert_eq!(resolve(None, &headers, &trusted), None);
+    }
+
+    #[test]
+    fn test_trusted_proxy_forwards_the_client() {
+        let trusted = [IpRange::parse("10.0.0.0/8").unwrap()];
+        let proxy = Some(ip("10.0.0.2"));
+
+        // The client prepended a forged address; the proxies appended theirs
+        let headers = forwarded(&["10.9.9.9, 198.51.100.4", "10.0.0.3"]);
+        assert_eq!(resolve(proxy, &headers, &trusted), Some(ip("198.51.100.4")));
+
+        let headers = forwarded(&["198.51.100.4:52311"]);
+        assert_eq!(resolve(proxy, &headers, &trusted), Some(ip("198.51.100.4")));
+
+        // Internal clients behind the proxy, or no header at all
+        let headers = forwarded(&["10.7.0.1"]);
+        assert_eq!(resolve(proxy, &headers, &trusted), Some(ip("10.7.0.1")));
+        assert_eq!(resolve(proxy, &HeaderMap::new(), &trusted), proxy);
+
+        let headers = forwarded(&["unknown"]);
+        assert_eq!(resolve(proxy, &headers, &trusted), None);
+    }
+}
@@ -0,0 +1,448 @@
+//! Cluster overview REST API
+//!
+//! Read-only endpoints for dashboards and `cyxcloud admin status`:
+//! - Overview: node counts, capacity, chunk health, repair backlog and
+//!   recent cluster events in one call
+//! - Per-node storage summary
+//! - Repair backlog with the next pending jobs
+//! - Cluster capacity
+//! - Rebalancer scan history with the under-replication trend
+//!
+//! All endpoints require a token with the `node:admin` permission.
+
+use crate::admin_api::{require_admin, require_metadata};
+use crate::auth_api::ApiError;
+use crate::websocket::RecentEvent;
+use crate::AppState;
+use axum::{
+    extract::{Json, Query, State},
+    http::{HeaderMap, StatusCode},
+    routing::get,
+    Router,
+};
+use chrono::{DateTime, Utc};
+use cyxcloud_metadata::{
+    ClusterCapacity, Database, DbError, NodeStorageSummary, RepairJob, ScanRecord,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use tracing::error;
+use uuid::Uuid;
+
+/// Recent events included in the overview
+const OVERVIEW_EVENTS: usize = 20;
+
+/// Default and maximum number of pending repair jobs listed
+const DEFAULT_REPAIR_LIMIT: i64 = 50;
+const MAX_REPAIR_LIMIT: i64 = 500;
+
+/// Default window and row cap of the scan history
+const DEFAULT_SCAN_HOURS: i64 = 24;
+const MAX_SCAN_HOURS: i64 = 24 * 30;
+const MAX_SCANS: i64 = 2_000;
+
+/// Node counts by status
+#[derive(Debug, Serialize)]
+pub struct NodeCounts {
+    pub total: i64,
+    pub online: i64,
+    pub offline: i64,
+    pub draining: i64,
+    /// Every status, including the ones above
+    pub by_status: BTreeMap<String, i64>,
+}
+
+impl From<HashMap<String, i64>> for NodeCounts {
+    fn from(counts: HashMap<String, i64>) -> Self {
+        let count = |status: &str| counts.get(status).copied().unwrap_or(0);
+        Self {
+            total: counts.values().sum(),
+            online: count("online"),
+            offline: count("offline"),
+            draining: count("draining"),
+            by_status: counts.into_iter().collect(),
+        }
+    }
+}
+
+/// Cluster capacity in bytes
+#[derive(Debug, Serialize)]
+pub struct CapacityResponse {
+    #[serde(flatten)]
+    pub capacity: ClusterCapacity,
+    /// Used share of the non-reserved capacity
+    pub utilization_percent: f64,
+}
+
+impl From<ClusterCapacity> for CapacityResponse {
+    fn from(capacity: ClusterCapacity) -> Self {
+        Self {
+            utilization_percent: capacity.utilization_percent(),
+            capacity,
+        }
+    }
+}
+
+/// Chunk counts by replication health
+#[derive(Debug, Serialize)]
+pub struct ChunkHealthCounts {
+    pub healthy: i64,
+    pub under_replicated: i64,
+    /// Chunks with no stored replica left
+    pub missing: i64,
+}
+
+impl From<HashMap<String, i64>> for ChunkHealthCounts {
+    fn from(counts: HashMap<String, i64>) -> Self {
+        let count = |health: &str| counts.get(health).copied().unwrap_or(0);
+        Self {
+            healthy: count("healthy"),
+            under_replicated: count("under_replicated"),
+            missing: count("missing"),
+        }
+    }
+}
+
+/// Repair jobs by status
+#[derive(Debug, Serialize)]
+pub struct RepairBacklog {
+    pub pending: i64,
+    pub in_progress: i64,
+    pub failed: i64,
+    /// Every status, including the ones above
+    pub by_status: BTreeMap<String, i64>,
+}
+
+impl From<HashMap<String, i64>> for RepairBacklog {
+    fn from(counts: HashMap<String, i64>) -> Self {
+        let count = |status: &str| counts.get(status).copied().unwrap_or(0);
+        Self {
+            pending: count("pending"),
+            in_progress: count("in_progress"),
+            failed: count("failed"),
+            by_status: counts.into_iter().collect(),
+        }
+    }
+}
+
+/// Everything a cluster dashboard shows, in one response
+#[derive(Debug, Serialize)]
+pub struct ClusterOverview {
+    pub nodes: NodeCounts,
+    pub capacity: CapacityResponse,
+    pub chunks: ChunkHealthCounts,
+    pub repairs: RepairBacklog,
+    /// Latest node and replication events, newest first
+    pub recent_events: Vec<RecentEvent>,
+    pub generated_at: DateTime<Utc>,
+}
+
+/// A pending repair job
+#[derive(Debug, Serialize)]
+pub struct RepairJobEntry {
+    pub id: Uuid,
+    /// Hex-encoded chunk ID
+    pub chunk_id: String,
+    pub source_node_id: Option<Uuid>,
+    pub target_node_id: Uuid,
+    /// `evacuation` or `repair`
+    pub kind: String,
+    pub priority: i32,
+    pub retry_count: i32,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<RepairJob> for RepairJobEntry {
+    fn from(job: RepairJob) -> Self {
+        Self {
+            id: job.id,
+            chunk_id: hex::encode(&job.chunk_id),
+            source_node_id: job.source_node_id,
+            target_node_id: job.target_node_id,
+            kind: job.kind,
+            priority: job.priority,
+            retry_count: job.retry_count,
+            created_at: job.created_at,
+        }
+    }
+}
+
+/// Repair backlog with the next jobs in line
+#[derive(Debug, Serialize)]
+pub struct RepairsResponse {
+    #[serde(flatten)]
+    pub backlog: RepairBacklog,
+    pub chunks: ChunkHealthCounts,
+    /// Pending jobs by priority, highest first
+    pub pending_jobs: Vec<RepairJobEntry>,
+}
+
+/// Query parameters for the repair listing
+#[derive(Debug, Deserialize)]
+pub struct RepairsQuery {
+    pub limit: Option<i64>,
+}
+
+/// Whether under-replication is shrinking over a window of scans
+#[derive(Debug, Serialize)]
+pub struct ScanTrend {
+    /// `converging`, `diverging`, `steady`, or `unknown` with fewer than two scans
+    pub direction: &'static str,
+    /// Change in under-replicated chunks from the first to the latest scan
+    pub under_replicated_change: i64,
+    /// Change in bytes at risk from the first to the latest scan
+    pub bytes_at_risk_change: i64,
+    /// Highest under-replicated count seen in the window
+    pub peak_under_replicated: i64,
+}
+
+impl ScanTrend {
+    /// Trend over scans ordered oldest first
+    fn from_scans(scans: &[ScanRecord]) -> Self {
+        let peak_under_replicated = scans.iter().map(|s| s.under_replicated).max().unwrap_or(0);
+        let (first, last) = match (scans.first(), scans.last()) {
+            (Some(first), Some(last)) if scans.len() >= 2 => (first, last),
+            _ => {
+                return Self {
+                    direction: "unknown",
+                    under_replicated_change: 0,
+                    bytes_at_risk_change: 0,
+                    peak_under_replicated,
+                }
+            }
+        };
+
+        let change = last.under_replicated - first.under_replicated;
+        Self {
+            direction: match change {
+                c if c < 0 => "converging",
+                c if c > 0 => "diverging",
+                _ => "steady",
+            },
+            under_replicated_change: change,
+            bytes_at_risk_change: last.bytes_at_risk - first.bytes_at_risk,
+            peak_under_replicated,
+        }
+    }
+}
+
+/// Rebalancer scans over a time window
+#[derive(Debug, Serialize)]
+pub struct ScanHistoryResponse {
+    pub hours: i64,
+    pub trend: ScanTrend,
+    /// Scan summaries, oldest first
+    pub scans: Vec<ScanRecord>,
+}
+
+/// Query parameters for the scan history
+#[derive(Debug, Deserialize)]
+pub struct ScanHistoryQuery {
+    /// Window in hours (default 24)
+    pub hours: Option<i64>,
+    /// Latest scans returned at most
+    pub limit: Option<i64>,
+}
+
+/// Create cluster overview routes
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/overview", get(get_overview))
+        .route("/nodes", get(list_nodes))
+        .route("/repairs", get(get_repairs))
+        .route("/capacity", get(get_capacity))
+        .route("/scans", get(get_scan_history))
+}
+
+/// Map a database error to a 500
+fn cluster_db_error(e: DbError) -> (StatusCode, Json<ApiError>) {
+    error!(error = %e, "Cluster overview query failed");
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(ApiError::new(
+            "Failed to load cluster state",
+            "INTERNAL_ERROR",
+        )),
+    )
+}
+
+/// Database of the metadata service
+fn require_database(state: &AppState) -> Result<&Database, (StatusCode, Json<ApiError>)> {
+    Ok(require_metadata(state)?.database())
+}
+
+/// Cluster overview
+async fn get_overview(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<ClusterOverview>, (StatusCode, Json<ApiError>)> {
+    require_admin(&headers, state.auth_service()).await?;
+    let db = require_database(&state)?;
+
+    let (nodes, capacity, chunks, repairs) = tokio::try_join!(
+        db.count_nodes_by_status(),
+        db.get_cluster_capacity(),
+        db.count_chunks_by_health(),
+        db.count_repair_jobs_by_status(),
+    )
+    .map_err(cluster_db_error)?;
+
+    Ok(Json(ClusterOverview {
+        nodes: nodes.into(),
+        capacity: capacity.into(),
+        chunks: chunks.into(),
+        repairs: repairs.into(),
+        recent_events: state.event_hub.recent_events(OVERVIEW_EVENTS),
+        generated_at: Utc::now(),
+    }))
+}
+
+/// Storage summary of every node
+async fn list_nodes(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<NodeStorageSummary>>, (StatusCode, Json<ApiError>)> {
+    require_admin(&headers, state.auth_service()).await?;
+    let nodes = require_database(&state)?
+        .get_node_storage_summary()
+        .await
+        .map_err(cluster_db_error)?;
+
+    Ok(Json(nodes))
+}
+
+/// Repair backlog and the next pending jobs
+async fn get_repairs(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(query): Query<RepairsQuery>,
+) -> Result<Json<RepairsResponse>, (StatusCode, Json<ApiError>)> {
+    require_admin(&headers, state.auth_service()).await?;
+    let db = require_database(&state)?;
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_REPAIR_LIMIT)
+        .clamp(1, MAX_REPAIR_LIMIT);
+
+    let (backlog, chunks, pending) = tokio::try_join!(
+        db.count_repair_jobs_by_status(),
+        db.count_chunks_by_health(),
+        db.get_pending_repair_jobs(limit),
+    )
+    .map_err(cluster_db_error)?;
+
+    Ok(Json(RepairsResponse {
+        backlog: backlog.into(),
+        chunks: chunks.into(),
+        pending_jobs: pending.into_iter().map(RepairJobEntry::from).collect(),
+    }))
+}
+
+/// Cluster capacity
+async fn get_capacity(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<CapacityResponse>, (StatusCode, Json<ApiError>)> {
+    require_admin(&headers, state.auth_service()).await?;
+    let capacity = require_database(&state)?
+        .get_cluster_capacity()
+        .await
+        .map_err(cluster_db_error)?;
+
+    Ok(Json(capacity.into()))
+}
+
+/// Rebalancer scan history and trend
+async fn get_scan_history(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(query): Query<ScanHistoryQuery>,
+) -> Result<Json<ScanHistoryResponse>, (StatusCode, Json<ApiError>)> {
+    require_admin(&headers, state.auth_service()).await?;
+    let hours = query
+        .hours
+        .unwrap_or(DEFAULT_SCAN_HOURS)
+        .clamp(1, MAX_SCAN_HOURS);
+    let limit = query.limit.unwrap_or(MAX_SCANS).clamp(1, MAX_SCANS);
+
+    let since = Utc::now() - chrono::Duration::hours(hours);
+    let scans = require_database(&state)?
+        .get_scan_history(since, limit)
+        .await
+        .map_err(cluster_db_error)?;
+
+    Ok(Json(ScanHistoryResponse {
+        hours,
+        trend: ScanTrend::from_scans(&scans),
+        scans,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_node_counts() {
+        let counts: HashMap<String, i64> = [("online", 5), ("offline", 2), ("maintenance", 1)]
+            .into_iter()
+            .map(|(s, n)| (s.to_string(), n))
+            .collect();
+        let nodes = NodeCounts::from(counts);
+        assert_eq!(nodes.total, 8);
+        assert_eq!(nodes.online, 5);
+        assert_eq!(nodes.draining, 0);
+        assert_eq!(nodes.by_status["maintenance"], 1);
+    }
+
+    #[test]
+    fn test_capacity_response_is_flat() {
+        let capacity = ClusterCapacity {
+            storage_total: 100,
+            storage_reserved: 0,
+            storage_used: 50,
+            online_storage_total: 100,
+            online_storage_available: 50,
+        };
+        let json = serde_json::to_value(CapacityResponse::from(capacity)).unwrap();
+        assert_eq!(json["storage_used"], 50);
+        assert_eq!(json["utilization_percent"], 50.0);
+    }
+
+    fn scan(under_replicated: i64) -> ScanRecord {
+        ScanRecord {
+            id: 0,
+            scanned_at: Utc::now(),
+            duration_ms: 10,
+            chunks_scanned: under_replicated,
+            under_replicated,
+            critical: 0,
+            over_replicated: 0,
+            orphaned: 0,
+            corrupt: 0,
+            deferred: 0,
+            incidents: 0,
+            bytes_at_risk: under_replicated * 1024,
+        }
+    }
+
+    #[test]
+    fn test_scan_trend() {
+        let trend = ScanTrend::from_scans(&[scan(100), scan(150), scan(40)]);
+        assert_eq!(trend.direction, "converging");
+        assert_eq!(trend.under_replicated_change, -60);
+        assert_eq!(trend.bytes_at_risk_change, -60 * 1024);
+        assert_eq!(trend.peak_under_replicated, 150);
+
+        assert_eq!(
+            ScanTrend::from_scans(&[scan(5), scan(9)]).direction,
+            "diverging"
+        );
+        assert_eq!(
+            ScanTrend::from_scans(&[scan(5), scan(5)]).direction,
+            "steady"
+        );
+        assert_eq!(ScanTrend::from_scans(&[scan(5)]).direction, "unknown");
+    }
+}
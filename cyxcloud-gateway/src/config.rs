@@ -0,0 +1,1646 @@
+//! Gateway configuration file
+//!
+//! Loads `gateway.toml` with the same layering as the node config:
+//! defaults < config file < environment variables < CLI flags.
+//! The resulting [`GatewaySettings`] is validated once and converted into the
+//! runtime configs of the individual components.
+
+use crate::access_log::AccessLogConfig;
+use crate::bandwidth::{parse_egress_caps_gb, BandwidthConfig};
+use crate::bucket_policy::IpRange;
+use crate::health_api::ReadinessConfig;
+use crate::heat::HeatConfig;
+use crate::kms::KmsConfig;
+use crate::node_client::NodeClientConfig;
+use crate::node_monitor::NodeMonitorConfig;
+use crate::payment_daemon::PaymentDaemonConfig;
+use crate::preflight::PreflightConfig;
+use crate::proof_audit::ProofAuditConfig;
+use crate::rate_limit::RateLimitConfig;
+use crate::rebalancer_daemon::RebalancerDaemonConfig;
+use crate::replication::ReplicationConfig;
+use crate::state::{GatewayConfig, OverwritePolicy, WriteConfig};
+use crate::upload_janitor::UploadJanitorConfig;
+use crate::upload_limits::UploadLimits;
+use axum::http::HeaderValue;
+use cyxcloud_core::tls::TlsServerConfig;
+use cyxcloud_metadata::{AntiAffinity, CacheConfig, DbConfig, PlacementConfig};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+use thiserror::Error;
+use tracing_subscriber::EnvFilter;
+
+/// Config file loaded when `--config` is not given (if it exists)
+pub const DEFAULT_CONFIG_PATH: &str = "gateway.toml";
+
+/// Configuration errors
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("Failed to read config file: {0}")]
+    ReadError(#[from] std::io::Error),
+
+    #[error("Failed to parse config file: {0}")]
+    ParseError(#[from] toml::de::Error),
+
+    #[error("Invalid configuration: {0}")]
+    ValidationError(String),
+}
+
+/// Complete gateway configuration
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GatewaySettings {
+    /// Listen addresses and request handling
+    #[serde(default)]
+    pub server: ServerSettings,
+
+    /// TLS for HTTPS and gRPC
+    #[serde(default)]
+    pub tls: TlsSettings,
+
+    /// PostgreSQL metadata database
+    #[serde(default)]
+    pub database: DatabaseSettings,
+
+    /// Redis cache
+    #[serde(default)]
+    pub cache: CacheSettings,
+
+    /// Shard placement
+    #[serde(default)]
+    pub placement: PlacementSettings,
+
+    /// Background daemon intervals
+    #[serde(default)]
+    pub daemons: DaemonSettings,
+
+    /// S3 API rate limits
+    #[serde(default)]
+    pub rate_limit: RateLimitSettings,
+
+    /// Per-user bandwidth accounting and egress caps
+    #[serde(default)]
+    pub bandwidth: BandwidthSettings,
+
+    /// Hedged shard reads to storage nodes
+    #[serde(default)]
+    pub hedging: HedgingSettings,
+
+    /// Concurrent uploads of the same object key
+    #[serde(default)]
+    pub writes: WriteSettings,
+
+    /// Readiness probe thresholds
+    #[serde(default)]
+    pub health: HealthSettings,
+
+    /// Startup checks gating S3 writes
+    #[serde(default)]
+    pub preflight: PreflightSettings,
+
+    /// Cross-origin resource sharing
+    #[serde(default)]
+    pub cors: CorsSettings,
+
+    /// Log filter
+    #[serde(default)]
+    pub logging: LoggingSettings,
+}
+
+impl GatewaySettings {
+    /// Load configuration from a TOML file
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let content = std::fs::read_to_string(path)?;
+        Ok(toml::from_str(&content)?)
+    }
+
+    /// Load configuration from `path`, or from `gateway.toml` if present
+    ///
+    /// An explicitly given file must exist; the default file is optional.
+    pub fn load(path: Option<&Path>) -> Result<Self, ConfigError> {
+        match path {
+            Some(path) => Self::from_file(path),
+            None if Path::new(DEFAULT_CONFIG_PATH).exists() => Self::from_file(DEFAULT_CONFIG_PATH),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// Validate the configuration
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let invalid = |msg: String| Err(ConfigError::ValidationError(msg));
+
+        for (name, addr) in [
+            ("server.http_addr", &self.server.http_addr),
+            ("server.grpc_addr", &self.server.grpc_addr),
+        ] {
+            if addr.parse::<SocketAddr>().is_err() {
+                return invalid(format!("{} is not a valid socket address: {}", name, addr));
+            }
+        }
+
+        if self.server.max_body_mb == 0 {
+            return invalid("server.max_body_mb cannot be 0".to_string());
+        }
+        for (name, value) in [
+            ("server.max_object_mb", self.server.max_object_mb),
+            (
+                "server.upload_timeout_secs",
+                self.server.upload_timeout_secs,
+            ),
+            (
+                "server.slow_upload_grace_secs",
+                self.server.slow_upload_grace_secs,
+            ),
+        ] {
+            if value == 0 {
+                return invalid(format!("{} cannot be 0", name));
+            }
+        }
+
+        for proxy in &self.server.trusted_proxies {
+            if let Err(e) = IpRange::parse(proxy) {
+                return invalid(format!("server.trusted_proxies: {}", e));
+            }
+        }
+
+        if self.tls.cert.is_some() != self.tls.key.is_some() {
+            return invalid("tls.cert and tls.key must be set together".to_string());
+        }
+        if self.tls.require_client_cert && self.tls.ca_cert.is_none() {
+            return invalid(
+                "tls.ca_cert is required when tls.require_client_cert is set".to_string(),
+            );
+        }
+        for path in [&self.tls.cert, &self.tls.key, &self.tls.ca_cert]
+            .into_iter()
+            .flatten()
+        {
+            if !path.exists() {
+                return invalid(format!("TLS file not found: {}", path.display()));
+            }
+        }
+
+        if self.database.max_connections == 0
+            || self.database.min_connections > self.database.max_connections
+        {
+            return invalid(format!(
+                "database connections must satisfy 0 < min ({}) <= max ({})",
+                self.database.min_connections, self.database.max_connections
+            ));
+        }
+        if self.database.max_lock_connections == 0 {
+            return invalid("database.max_lock_connections cannot be 0".to_string());
+        }
+        if self.database.health_check_interval_secs == 0 {
+            return invalid("database.health_check_interval_secs cannot be 0".to_string());
+        }
+
+        if let Err(e) = self.placement.anti_affinity.parse::<AntiAffinity>() {
+            return invalid(format!("placement.anti_affinity: {}", e));
+        }
+        if !(0.0..=1.0).contains(&self.placement.warmup_min_weight) {
+            return invalid("placement.warmup_min_weight must be between 0.0 and 1.0".to_string());
+        }
+        if !(0.0..=1.0).contains(&self.placement.reputation_weight) {
+            return invalid("placement.reputation_weight must be between 0.0 and 1.0".to_string());
+        }
+
+        for (name, secs) in [
+            (
+                "daemons.node_monitor_interval_secs",
+                self.daemons.node_monitor_interval_secs,
+            ),
+            (
+                "daemons.payment_interval_secs",
+                self.daemons.payment_interval_secs,
+            ),
+            (
+                "daemons.proof_audit_interval_secs",
+                self.daemons.proof_audit_interval_secs,
+            ),
+            (
+                "daemons.rebalancer_interval_secs",
+                self.daemons.rebalancer_interval_secs,
+            ),
+            (
+                "daemons.upload_janitor_interval_secs",
+                self.daemons.upload_janitor_interval_secs,
+            ),
+            (
+                "daemons.replication_interval_secs",
+                self.daemons.replication_interval_secs,
+            ),
+            (
+                "daemons.access_log_interval_secs",
+                self.daemons.access_log_interval_secs,
+            ),
+        ] {
+            if secs == 0 {
+                return invalid(format!("{} cannot be 0", name));
+            }
+        }
+
+        if self.rate_limit.global_rps < 0.0
+            || self.rate_limit.user_rps < 0.0
+            || self.rate_limit.burst_secs <= 0.0
+        {
+            return invalid(
+                "rate_limit rates cannot be negative and burst_secs must be positive".to_string(),
+            );
+        }
+
+        if self.bandwidth.flush_interval_secs == 0 || self.bandwidth.cap_refresh_secs == 0 {
+            return invalid(
+                "bandwidth.flush_interval_secs and bandwidth.cap_refresh_secs cannot be 0"
+                    .to_string(),
+            );
+        }
+        if self.bandwidth.default_plan.is_empty() {
+            return invalid("bandwidth.default_plan cannot be empty".to_string());
+        }
+
+        if !(0.0..=1.0).contains(&self.hedging.percentile) {
+            return invalid("hedging.percentile must be between 0.0 and 1.0".to_string());
+        }
+        if !(0.0..=1.0).contains(&self.hedging.max_ratio) {
+            return invalid("hedging.max_ratio must be between 0.0 and 1.0".to_string());
+        }
+
+        if let Err(e) = self.writes.overwrite_policy.parse::<OverwritePolicy>() {
+            return invalid(format!("writes.overwrite_policy: {}", e));
+        }
+        if self.writes.lock_timeout_secs == 0 {
+            return invalid("writes.lock_timeout_secs cannot be 0".to_string());
+        }
+        let stripe_range =
+            cyxcloud_core::MIN_CHUNK_SIZE / 1024..=cyxcloud_core::MAX_CHUNK_SIZE / 1024;
+        if !stripe_range.contains(&self.writes.stripe_size_kb) {
+            return invalid(format!(
+                "writes.stripe_size_kb must be between {} and {}",
+                stripe_range.start(),
+                stripe_range.end()
+            ));
+        }
+
+        if self.health.check_timeout_ms == 0 {
+            return invalid("health.check_timeout_ms cannot be 0".to_string());
+        }
+        if self.preflight.retry_interval_secs == 0 {
+            return invalid("preflight.retry_interval_secs cannot be 0".to_string());
+        }
+
+        if let Some(origin) = self
+            .cors
+            .allowed_origins
+            .iter()
+            .find(|o| o.parse::<HeaderValue>().is_err())
+        {
+            return invalid(format!(
+                "cors.allowed_origins contains an invalid origin: {}",
+                origin
+            ));
+        }
+
+        if let Err(e) = EnvFilter::try_new(&self.logging.level) {
+            return invalid(format!("logging.level: {}", e));
+        }
+
+        Ok(())
+    }
+
+    /// Apply environment variable overrides to all settings
+    pub fn with_env_overrides(mut self) -> Self {
+        // Server
+        if let Some(addr) = env_var("GATEWAY_HTTP_ADDR") {
+            self.server.http_addr = addr;
+        }
+        if let Some(addr) = env_var("GATEWAY_GRPC_ADDR") {
+            self.server.grpc_addr = addr;
+        }
+        if let Some(mb) = env_parse("GATEWAY_MAX_OBJECT_MB") {
+            self.server.max_object_mb = mb;
+        }
+        if let Some(secs) = env_parse("GATEWAY_UPLOAD_TIMEOUT_SECS") {
+            self.server.upload_timeout_secs = secs;
+        }
+        if let Some(kbps) = env_parse("GATEWAY_MIN_UPLOAD_KBPS") {
+            self.server.min_upload_kbps = kbps;
+        }
+        if let Some(secs) = env_parse("GATEWAY_SLOW_UPLOAD_GRACE_SECS") {
+            self.server.slow_upload_grace_secs = secs;
+        }
+        if let Some(enabled) = env_flag("GATEWAY_DASHBOARD") {
+            self.server.dashboard = enabled;
+        }
+        if let Some(proxies) = env_var("GATEWAY_TRUSTED_PROXIES") {
+            self.server.trusted_proxies = proxies
+                .split(',')
+                .map(|p| p.trim().to_string())
+                .filter(|p| !p.is_empty())
+                .collect();
+        }
+
+        // TLS
+        if let Some(cert) = env_var("TLS_CERT") {
+            self.tls.cert = Some(PathBuf::from(cert));
+        }
+        if let Some(key) = env_var("TLS_KEY") {
+            self.tls.key = Some(PathBuf::from(key));
+        }
+        if let Some(ca) = env_var("TLS_CA_CERT") {
+            self.tls.ca_cert = Some(PathBuf::from(ca));
+        }
+        if let Some(require) = env_flag("TLS_REQUIRE_CLIENT_CERT") {
+            self.tls.require_client_cert = require;
+        }
+
+        // Database and cache
+        if let Some(url) = env_var("DATABASE_URL") {
+            self.database.url = Some(url);
+        }
+        if let Some(url) = env_var("DATABASE_READ_URL") {
+            self.database.read_url = Some(url);
+        }
+        if let Some(memory_only) = env_flag("USE_MEMORY_STORAGE") {
+            self.database.memory_only = memory_only;
+        }
+        if let Some(url) = env_var("REDIS_URL") {
+            self.cache.redis_url = Some(url);
+        }
+
+        // Placement
+        if let Some(secs) = env_parse("NODE_WARMUP_PERIOD_SECS") {
+            self.placement.warmup_period_secs = secs;
+        }
+        if let Some(weight) = env_parse("NODE_WARMUP_MIN_WEIGHT") {
+            self.placement.warmup_min_weight = weight;
+        }
+        if let Some(weight) = env_parse("PLACEMENT_REPUTATION_WEIGHT") {
+            self.placement.reputation_weight = weight;
+        }
+        if let Some(level) = env_var("PLACEMENT_ANTI_AFFINITY") {
+            self.placement.anti_affinity = level;
+        }
+        if let Some(min_nodes) = env_parse("PLACEMENT_ANTI_AFFINITY_MIN_NODES") {
+            self.placement.anti_affinity_min_nodes = min_nodes;
+        }
+
+        // Daemons
+        if let Some(secs) = env_parse("NODE_MONITOR_INTERVAL_SECS") {
+            self.daemons.node_monitor_interval_secs = secs;
+        }
+        if let Some(secs) = env_parse("PAYMENT_ACCUMULATE_INTERVAL_SECS") {
+            self.daemons.payment_interval_secs = secs;
+        }
+        if let Some(secs) = env_parse("PROOF_AUDIT_INTERVAL_SECS") {
+            self.daemons.proof_audit_interval_secs = secs;
+        }
+        if let Some(secs) = env_parse("REBALANCER_SCAN_INTERVAL_SECS") {
+            self.daemons.rebalancer_interval_secs = secs;
+        }
+        if let Some(secs) = env_parse("UPLOAD_JANITOR_INTERVAL_SECS") {
+            self.daemons.upload_janitor_interval_secs = secs;
+        }
+        if let Some(secs) = env_parse("REPLICATION_INTERVAL_SECS") {
+            self.daemons.replication_interval_secs = secs;
+        }
+        if let Some(secs) = env_parse("ACCESS_LOG_FLUSH_SECS") {
+            self.daemons.access_log_interval_secs = secs;
+        }
+
+        // Rate limits
+        if let Some(enabled) = env_flag("RATE_LIMIT_ENABLED") {
+            self.rate_limit.enabled = enabled;
+        }
+        if let Some(rps) = env_parse("RATE_LIMIT_GLOBAL_RPS") {
+            self.rate_limit.global_rps = rps;
+        }
+        if let Some(rps) = env_parse("RATE_LIMIT_USER_RPS") {
+            self.rate_limit.user_rps = rps;
+        }
+        if let Some(mb) = env_parse("RATE_LIMIT_GLOBAL_BANDWIDTH_MB") {
+            self.rate_limit.global_bandwidth_mb = mb;
+        }
+        if let Some(mb) = env_parse("RATE_LIMIT_USER_BANDWIDTH_MB") {
+            self.rate_limit.user_bandwidth_mb = mb;
+        }
+        if let Some(secs) = env_parse("RATE_LIMIT_BURST_SECS") {
+            self.rate_limit.burst_secs = secs;
+        }
+
+        // Bandwidth accounting
+        if let Some(enabled) = env_flag("BANDWIDTH_ACCOUNTING_ENABLED") {
+            self.bandwidth.enabled = enabled;
+        }
+        if let Some(secs) = env_parse("BANDWIDTH_FLUSH_SECS") {
+            self.bandwidth.flush_interval_secs = secs;
+        }
+        if let Some(plan) = env_var("BANDWIDTH_DEFAULT_PLAN") {
+            self.bandwidth.default_plan = plan;
+        }
+        if let Some(caps) = env_var("BANDWIDTH_EGRESS_CAPS_GB") {
+            match parse_egress_caps_gb(&caps) {
+                Ok(caps) => self.bandwidth.egress_caps_gb = caps,
+                Err(e) => tracing::warn!(error = %e, "Ignoring invalid BANDWIDTH_EGRESS_CAPS_GB"),
+            }
+        }
+        if let Some(secs) = env_parse("BANDWIDTH_CAP_REFRESH_SECS") {
+            self.bandwidth.cap_refresh_secs = secs;
+        }
+
+        // Hedged reads
+        if let Some(enabled) = env_flag("HEDGE_READS") {
+            self.hedging.enabled = enabled;
+        }
+        if let Some(ms) = env_parse("HEDGE_MIN_DELAY_MS") {
+            self.hedging.min_delay_ms = ms;
+        }
+        if let Some(percentile) = env_parse("HEDGE_PERCENTILE") {
+            self.hedging.percentile = percentile;
+        }
+        if let Some(ratio) = env_parse("HEDGE_MAX_RATIO") {
+            self.hedging.max_ratio = ratio;
+        }
+
+        // Concurrent writes
+        if let Some(policy) = env_var("GATEWAY_OVERWRITE_POLICY") {
+            self.writes.overwrite_policy = policy;
+        }
+        if let Some(secs) = env_parse("GATEWAY_WRITE_LOCK_TIMEOUT_SECS") {
+            self.writes.lock_timeout_secs = secs;
+        }
+        if let Some(kb) = env_parse("GATEWAY_STRIPE_SIZE_KB") {
+            self.writes.stripe_size_kb = kb;
+        }
+
+        // Readiness probe
+        if let Some(nodes) = env_parse("HEALTH_MIN_ONLINE_NODES") {
+            self.health.min_online_nodes = nodes;
+        }
+        if let Some(require) = env_flag("HEALTH_REQUIRE_REDIS") {
+            self.health.require_redis = require;
+        }
+        if let Some(ms) = env_parse("HEALTH_CHECK_TIMEOUT_MS") {
+            self.health.check_timeout_ms = ms;
+        }
+
+        // Startup preflight
+        if let Some(nodes) = env_parse("GATEWAY_PREFLIGHT_MIN_NODES") {
+            self.preflight.min_registered_nodes = nodes;
+        }
+
+        // CORS
+        if let Some(origins) = env_var("CORS_ALLOWED_ORIGINS") {
+            self.cors.allowed_origins = origins
+                .split(',')
+                .map(|o| o.trim().to_string())
+                .filter(|o| !o.is_empty())
+                .collect();
+        }
+
+        // Logging
+        if let Some(level) = env_var("RUST_LOG") {
+            self.logging.level = level;
+        }
+
+        self
+    }
+
+    /// Whether HTTPS/gRPC TLS is configured
+    pub fn tls_enabled(&self) -> bool {
+        self.tls.cert.is_some() && self.tls.key.is_some()
+    }
+
+    /// TLS server config, if TLS is enabled
+    pub fn tls_server_config(&self) -> Option<TlsServerConfig> {
+        match (&self.tls.cert, &self.tls.key) {
+            (Some(cert), Some(key)) => Some(TlsServerConfig {
+                cert_path: cert.clone(),
+                key_path: key.clone(),
+                ca_cert_path: self.tls.ca_cert.clone(),
+                require_client_cert: self.tls.require_client_cert,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Reverse proxies trusted to report the client address (invalid
+    /// entries are rejected by [`GatewaySettings::validate`])
+    pub fn trusted_proxies(&self) -> Vec<IpRange> {
+        self.server
+            .trusted_proxies
+            .iter()
+            .filter_map(|proxy| IpRange::parse(proxy).ok())
+            .collect()
+    }
+
+    /// Application state configuration
+    pub fn gateway_config(&self) -> GatewayConfig {
+        GatewayConfig {
+            database_url: self.database.url.clone(),
+            redis_url: self.cache.redis_url.clone(),
+            use_memory_storage: self.database.memory_only,
+            db_config: self.db_config(),
+            cache_config: self.cache_config(),
+            placement: self.placement_config(),
+            rate_limit: self.rate_limit_config(),
+            node_client: self.node_client_config(),
+            writes: self.write_config(),
+            upload_limits: self.upload_limits_config(),
+            access_log: self.access_log_config(),
+            bandwidth: self.bandwidth_config(),
+            // Heat tracking is only configured through the environment
+            heat: HeatConfig::from_env(),
+            readiness: self.readiness_config(),
+            // Master keys and Vault tokens only come from the environment
+            kms: KmsConfig::from_env(),
+            #[cfg(feature = "blockchain")]
+            enable_blockchain: false,
+            #[cfg(feature = "blockchain")]
+            solana_rpc_url: None,
+            #[cfg(feature = "blockchain")]
+            keypair_path: None,
+        }
+    }
+
+    /// Bandwidth accounting configuration
+    pub fn bandwidth_config(&self) -> BandwidthConfig {
+        BandwidthConfig {
+            enabled: self.bandwidth.enabled,
+            flush_interval: Duration::from_secs(self.bandwidth.flush_interval_secs),
+            default_plan: self.bandwidth.default_plan.clone(),
+            egress_caps: self
+                .bandwidth
+                .egress_caps_gb
+                .iter()
+                .map(|(plan, gb)| (plan.clone(), gb * 1024 * 1024 * 1024))
+                .collect(),
+            cap_refresh: Duration::from_secs(self.bandwidth.cap_refresh_secs),
+        }
+    }
+
+    /// Database pool configuration
+    pub fn db_config(&self) -> DbConfig {
+        DbConfig {
+            max_connections: self.database.max_connections,
+            min_connections: self.database.min_connections,
+            max_lock_connections: self.database.max_lock_connections,
+            connect_timeout: Duration::from_secs(self.database.connect_timeout_secs),
+            read_url: self.database.read_url.clone(),
+            connect_retries: self.database.connect_retries,
+            health_check_interval: Duration::from_secs(self.database.health_check_interval_secs),
+            ..Default::default()
+        }
+    }
+
+    /// Redis cache configuration
+    pub fn cache_config(&self) -> CacheConfig {
+        CacheConfig {
+            default_ttl: Duration::from_secs(self.cache.default_ttl_secs),
+            chunk_location_ttl: Duration::from_secs(self.cache.chunk_location_ttl_secs),
+            node_info_ttl: Duration::from_secs(self.cache.node_info_ttl_secs),
+            prefix: self.cache.prefix.clone(),
+            ..Default::default()
+        }
+    }
+
+    /// Shard placement configuration
+    pub fn placement_config(&self) -> PlacementConfig {
+        PlacementConfig {
+            max_shards_per_dc: self.placement.max_shards_per_dc,
+            max_shards_per_rack: self.placement.max_shards_per_rack,
+            warmup_period: Duration::from_secs(self.placement.warmup_period_secs),
+            warmup_min_weight: self.placement.warmup_min_weight,
+            reputation_weight: self.placement.reputation_weight,
+            anti_affinity: self.placement.anti_affinity.parse().unwrap_or_default(),
+            anti_affinity_min_nodes: self.placement.anti_affinity_min_nodes,
+            ..Default::default()
+        }
+    }
+
+    /// S3 rate limit configuration
+    pub fn rate_limit_config(&self) -> RateLimitConfig {
+        RateLimitConfig {
+            enabled: self.rate_limit.enabled,
+            global_rps: self.rate_limit.global_rps,
+            user_rps: self.rate_limit.user_rps,
+            global_bandwidth: self.rate_limit.global_bandwidth_mb * 1024 * 1024,
+            user_bandwidth: self.rate_limit.user_bandwidth_mb * 1024 * 1024,
+            burst_secs: self.rate_limit.burst_secs,
+        }
+    }
+
+    /// Storage node client configuration
+    pub fn node_client_config(&self) -> NodeClientConfig {
+        NodeClientConfig {
+            hedge_reads: self.hedging.enabled,
+            hedge_min_delay_ms: self.hedging.min_delay_ms,
+            hedge_percentile: self.hedging.percentile,
+            hedge_max_ratio: self.hedging.max_ratio,
+            cluster_token: env_var("CYXCLOUD_CLUSTER_TOKEN").filter(|t| !t.is_empty()),
+            ..Default::default()
+        }
+    }
+
+    /// Concurrent write configuration
+    pub fn write_config(&self) -> WriteConfig {
+        WriteConfig {
+            overwrite_policy: self.writes.overwrite_policy.parse().unwrap_or_default(),
+            lock_timeout: Duration::from_secs(self.writes.lock_timeout_secs),
+            stripe_size: self.writes.stripe_size_kb * 1024,
+        }
+    }
+
+    /// S3 upload body limits
+    pub fn upload_limits_config(&self) -> UploadLimits {
+        UploadLimits {
+            max_object_size: self.server.max_object_mb * 1024 * 1024,
+            timeout: Duration::from_secs(self.server.upload_timeout_secs),
+            min_throughput: self.server.min_upload_kbps * 1024,
+            grace_period: Duration::from_secs(self.server.slow_upload_grace_secs),
+        }
+    }
+
+    /// Readiness probe configuration
+    pub fn readiness_config(&self) -> ReadinessConfig {
+        ReadinessConfig {
+            min_online_nodes: self.health.min_online_nodes,
+            require_redis: self.health.require_redis,
+            check_timeout: Duration::from_millis(self.health.check_timeout_ms),
+        }
+    }
+
+    /// Startup preflight configuration
+    pub fn preflight_config(&self) -> PreflightConfig {
+        PreflightConfig {
+            enabled: self.preflight.enabled,
+            min_registered_nodes: self.preflight.min_registered_nodes,
+            retry_interval: Duration::from_secs(self.preflight.retry_interval_secs),
+            check_timeout: Duration::from_millis(self.health.check_timeout_ms),
+        }
+    }
+
+    /// Node lifecycle monitor configuration
+    pub fn node_monitor_config(&self) -> NodeMonitorConfig {
+        NodeMonitorConfig {
+            check_interval: Duration::from_secs(self.daemons.node_monitor_interval_secs),
+            warmup_period: Duration::from_secs(self.placement.warmup_period_secs),
+            ..NodeMonitorConfig::from_env()
+        }
+    }
+
+    /// Payment daemon configuration
+    pub fn payment_daemon_config(&self) -> PaymentDaemonConfig {
+        PaymentDaemonConfig {
+            accumulate_interval: Duration::from_secs(self.daemons.payment_interval_secs),
+            ..PaymentDaemonConfig::from_env()
+        }
+    }
+
+    /// Proof-of-storage auditor configuration
+    pub fn proof_audit_config(&self) -> ProofAuditConfig {
+        ProofAuditConfig {
+            audit_interval: Duration::from_secs(self.daemons.proof_audit_interval_secs),
+            ..ProofAuditConfig::from_env()
+        }
+    }
+
+    /// Rebalancer daemon configuration
+    pub fn rebalancer_daemon_config(&self) -> RebalancerDaemonConfig {
+        RebalancerDaemonConfig {
+            scan_interval: Duration::from_secs(self.daemons.rebalancer_interval_secs),
+            ..RebalancerDaemonConfig::from_env()
+        }
+    }
+
+    /// Upload janitor configuration
+    pub fn upload_janitor_config(&self) -> UploadJanitorConfig {
+        UploadJanitorConfig {
+            scan_interval: Duration::from_secs(self.daemons.upload_janitor_interval_secs),
+            ..UploadJanitorConfig::from_env()
+        }
+    }
+
+    /// Bucket replication daemon configuration
+    pub fn replication_config(&self) -> ReplicationConfig {
+        ReplicationConfig {
+            scan_interval: Duration::from_secs(self.daemons.replication_interval_secs),
+            ..ReplicationConfig::from_env()
+        }
+    }
+
+    /// Access log daemon configuration
+    pub fn access_log_config(&self) -> AccessLogConfig {
+        AccessLogConfig {
+            flush_interval: Duration::from_secs(self.daemons.access_log_interval_secs),
+            ..AccessLogConfig::from_env()
+        }
+    }
+}
+
+fn env_var(name: &str) -> Option<String> {
+    std::env::var(name).ok()
+}
+
+fn env_parse<T: FromStr>(name: &str) -> Option<T> {
+    env_var(name).and_then(|v| v.parse().ok())
+}
+
+fn env_flag(name: &str) -> Option<bool> {
+    env_var(name).map(|v| v == "true" || v == "1")
+}
+
+/// Listen addresses and request handling
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ServerSettings {
+    /// HTTP (S3 API) listen address
+    #[serde(default = "default_http_addr")]
+    pub http_addr: String,
+
+    /// gRPC listen address
+    #[serde(default = "default_grpc_addr")]
+    pub grpc_addr: String,
+
+    /// Require JWT authentication on gRPC services
+    #[serde(default = "default_true")]
+    pub grpc_auth: bool,
+
+    /// Maximum HTTP request body size (MB) of API requests other than S3
+    /// object uploads
+    #[serde(default = "default_max_body_mb")]
+    pub max_body_mb: usize,
+
+    /// Largest object a single S3 PUT or gRPC upload may store (MB)
+    #[serde(default = "default_max_object_mb")]
+    pub max_object_mb: u64,
+
+    /// Time allowed for receiving an upload body (seconds)
+    #[serde(default = "default_upload_timeout_secs")]
+    pub upload_timeout_secs: u64,
+
+    /// Slowest accepted upload client (KB/s, 0 disables the check)
+    #[serde(default = "default_min_upload_kbps")]
+    pub min_upload_kbps: u64,
+
+    /// Seconds before the throughput check starts, and longest an upload
+    /// client may send nothing
+    #[serde(default = "default_slow_upload_grace_secs")]
+    pub slow_upload_grace_secs: u64,
+
+    /// Serve the built-in web dashboard at `/dashboard`
+    #[serde(default = "default_true")]
+    pub dashboard: bool,
+
+    /// Reverse proxies (addresses or CIDR ranges) whose `X-Forwarded-For`
+    /// is trusted for the client address
+    #[serde(default)]
+    pub trusted_proxies: Vec<String>,
+}
+
+impl Default for ServerSettings {
+    fn default() -> Self {
+        Self {
+            http_addr: default_http_addr(),
+            grpc_addr: default_grpc_addr(),
+            grpc_auth: true,
+            max_body_mb: default_max_body_mb(),
+            max_object_mb: default_max_object_mb(),
+            upload_timeout_secs: default_upload_timeout_secs(),
+            min_upload_kbps: default_min_upload_kbps(),
+            slow_upload_grace_secs: default_slow_upload_grace_secs(),
+            dashboard: true,
+            trusted_proxies: Vec::new(),
+        }
+    }
+}
+
+fn default_http_addr() -> String {
+    "0.0.0.0:8180".to_string()
+}
+
+fn default_grpc_addr() -> String {
+    "0.0.0.0:50052".to_string()
+}
+
+fn default_max_body_mb() -> usize {
+    256
+}
+
+fn default_max_object_mb() -> u64 {
+    5 * 1024
+}
+
+fn default_upload_timeout_secs() -> u64 {
+    3600
+}
+
+fn default_min_upload_kbps() -> u64 {
+    16
+}
+
+fn default_slow_upload_grace_secs() -> u64 {
+    30
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// TLS settings (HTTPS and gRPC share the same certificate)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TlsSettings {
+    /// Server certificate (PEM)
+    #[serde(default)]
+    pub cert: Option<PathBuf>,
+
+    /// Server private key (PEM)
+    #[serde(default)]
+    pub key: Option<PathBuf>,
+
+    /// CA certificate for client verification (mTLS)
+    #[serde(default)]
+    pub ca_cert: Option<PathBuf>,
+
+    /// Require client certificates (node authentication)
+    #[serde(default)]
+    pub require_client_cert: bool,
+}
+
+/// Metadata database settings
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DatabaseSettings {
+    /// PostgreSQL connection URL (in-memory storage when unset)
+    #[serde(default)]
+    pub url: Option<String>,
+
+    /// Force in-memory storage even if a database is configured
+    #[serde(default)]
+    pub memory_only: bool,
+
+    /// Maximum pool connections
+    #[serde(default = "default_max_connections")]
+    pub max_connections: u32,
+
+    /// Minimum idle pool connections
+    #[serde(default = "default_min_connections")]
+    pub min_connections: u32,
+
+    /// Connections for per-key write locks, each held for a whole upload
+    #[serde(default = "default_max_lock_connections")]
+    pub max_lock_connections: u32,
+
+    /// Connection timeout in seconds
+    #[serde(default = "default_connect_timeout")]
+    pub connect_timeout_secs: u64,
+
+    /// Read replica URL for lag-tolerant queries
+    #[serde(default)]
+    pub read_url: Option<String>,
+
+    /// Further attempts to reach the database at startup
+    #[serde(default = "default_connect_retries")]
+    pub connect_retries: u32,
+
+    /// How often primary and replica reachability is checked
+    #[serde(default = "default_health_check_interval")]
+    pub health_check_interval_secs: u64,
+}
+
+impl Default for DatabaseSettings {
+    fn default() -> Self {
+        Self {
+            url: None,
+            memory_only: false,
+            max_connections: default_max_connections(),
+            min_connections: default_min_connections(),
+            max_lock_connections: default_max_lock_connections(),
+            connect_timeout_secs: default_connect_timeout(),
+            read_url: None,
+            connect_retries: default_connect_retries(),
+            health_check_interval_secs: default_health_check_interval(),
+        }
+    }
+}
+
+fn default_max_connections() -> u32 {
+    10
+}
+
+fn default_min_connections() -> u32 {
+    2
+}
+
+fn default_connect_timeout() -> u64 {
+    5
+}
+
+fn default_max_lock_connections() -> u32 {
+    20
+}
+
+fn default_connect_retries() -> u32 {
+    3
+}
+
+fn default_health_check_interval() -> u64 {
+    5
+}
+
+/// Redis cache settings
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CacheSettings {
+    /// Redis connection URL (cache disabled when unset)
+    #[serde(default)]
+    pub redis_url: Option<String>,
+
+    /// Default TTL for cached items (seconds)
+    #[serde(default = "default_cache_ttl")]
+    pub default_ttl_secs: u64,
+
+    /// TTL for chunk location entries (seconds)
+    #[serde(default = "default_chunk_location_ttl")]
+    pub chunk_location_ttl_secs: u64,
+
+    /// TTL for node info entries (seconds)
+    #[serde(default = "default_node_info_ttl")]
+    pub node_info_ttl_secs: u64,
+
+    /// Key prefix
+    #[serde(default = "default_cache_prefix")]
+    pub prefix: String,
+}
+
+impl Default for CacheSettings {
+    fn default() -> Self {
+        Self {
+            redis_url: None,
+            default_ttl_secs: default_cache_ttl(),
+            chunk_location_ttl_secs: default_chunk_location_ttl(),
+            node_info_ttl_secs: default_node_info_ttl(),
+            prefix: default_cache_prefix(),
+        }
+    }
+}
+
+fn default_cache_ttl() -> u64 {
+    300
+}
+
+fn default_chunk_location_ttl() -> u64 {
+    60
+}
+
+fn default_node_info_ttl() -> u64 {
+    120
+}
+
+fn default_cache_prefix() -> String {
+    "cyxcloud".to_string()
+}
+
+/// Shard placement settings
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlacementSettings {
+    /// Maximum shards of a chunk per datacenter
+    #[serde(default = "default_max_shards_per_dc")]
+    pub max_shards_per_dc: usize,
+
+    /// Maximum shards of a chunk per rack
+    #[serde(default = "default_max_shards_per_rack")]
+    pub max_shards_per_rack: usize,
+
+    /// Warm-up period for newly joined nodes (seconds)
+    #[serde(default = "default_warmup_period")]
+    pub warmup_period_secs: u64,
+
+    /// Placement weight at the start of warm-up (0.0 - 1.0)
+    #[serde(default = "default_warmup_min_weight")]
+    pub warmup_min_weight: f64,
+
+    /// How strongly node reputation scales placement (0.0 - 1.0)
+    #[serde(default = "default_reputation_weight")]
+    pub reputation_weight: f64,
+
+    /// Anti-affinity level: `strict`, `best-effort` or `off`
+    #[serde(default = "default_anti_affinity")]
+    pub anti_affinity: String,
+
+    /// Minimum eligible nodes for strict anti-affinity
+    #[serde(default = "default_anti_affinity_min_nodes")]
+    pub anti_affinity_min_nodes: usize,
+}
+
+impl Default for PlacementSettings {
+    fn default() -> Self {
+        Self {
+            max_shards_per_dc: default_max_shards_per_dc(),
+            max_shards_per_rack: default_max_shards_per_rack(),
+            warmup_period_secs: default_warmup_period(),
+            warmup_min_weight: default_warmup_min_weight(),
+            reputation_weight: default_reputation_weight(),
+            anti_affinity: default_anti_affinity(),
+            anti_affinity_min_nodes: default_anti_affinity_min_nodes(),
+        }
+    }
+}
+
+fn default_max_shards_per_dc() -> usize {
+    6
+}
+
+fn default_max_shards_per_rack() -> usize {
+    2
+}
+
+fn default_warmup_period() -> u64 {
+    6 * 60 * 60 // 6 hours
+}
+
+fn default_warmup_min_weight() -> f64 {
+    0.1
+}
+
+fn default_reputation_weight() -> f64 {
+    1.0
+}
+
+fn default_anti_affinity() -> String {
+    "best-effort".to_string()
+}
+
+fn default_anti_affinity_min_nodes() -> usize {
+    14 // 10+4 erasure coding
+}
+
+/// Background daemon intervals
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DaemonSettings {
+    /// Node lifecycle monitor interval (seconds)
+    #[serde(default = "default_node_monitor_interval")]
+    pub node_monitor_interval_secs: u64,
+
+    /// Payment uptime accumulation interval (seconds)
+    #[serde(default = "default_payment_interval")]
+    pub payment_interval_secs: u64,
+
+    /// Proof-of-storage audit interval (seconds)
+    #[serde(default = "default_proof_audit_interval")]
+    pub proof_audit_interval_secs: u64,
+
+    /// Rebalancer scan interval (seconds)
+    #[serde(default = "default_rebalancer_interval")]
+    pub rebalancer_interval_secs: u64,
+
+    /// Upload janitor scan interval (seconds)
+    #[serde(default = "default_upload_janitor_interval")]
+    pub upload_janitor_interval_secs: u64,
+
+    /// Bucket replication scan interval (seconds)
+    #[serde(default = "default_replication_interval")]
+    pub replication_interval_secs: u64,
+
+    /// Access log flush interval (seconds)
+    #[serde(default = "default_access_log_interval")]
+    pub access_log_interval_secs: u64,
+}
+
+impl Default for DaemonSettings {
+    fn default() -> Self {
+        Self {
+            node_monitor_interval_secs: default_node_monitor_interval(),
+            payment_interval_secs: default_payment_interval(),
+            proof_audit_interval_secs: default_proof_audit_interval(),
+            rebalancer_interval_secs: default_rebalancer_interval(),
+            upload_janitor_interval_secs: default_upload_janitor_interval(),
+            replication_interval_secs: default_replication_interval(),
+            access_log_interval_secs: default_access_log_interval(),
+        }
+    }
+}
+
+fn default_node_monitor_interval() -> u64 {
+    30
+}
+
+fn default_payment_interval() -> u64 {
+    60
+}
+
+fn default_proof_audit_interval() -> u64 {
+    5 * 60
+}
+
+fn default_rebalancer_interval() -> u64 {
+    60
+}
+
+fn default_upload_janitor_interval() -> u64 {
+    5 * 60
+}
+
+fn default_replication_interval() -> u64 {
+    60
+}
+
+fn default_access_log_interval() -> u64 {
+    5 * 60
+}
+
+/// S3 API rate limit settings (0 = unlimited)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RateLimitSettings {
+    /// Enable rate limiting
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+
+    /// Requests per second across all clients
+    #[serde(default = "default_global_rps")]
+    pub global_rps: f64,
+
+    /// Requests per second per user
+    #[serde(default = "default_user_rps")]
+    pub user_rps: f64,
+
+    /// Bandwidth across all clients (MB/s)
+    #[serde(default)]
+    pub global_bandwidth_mb: u64,
+
+    /// Bandwidth per user (MB/s)
+    #[serde(default)]
+    pub user_bandwidth_mb: u64,
+
+    /// Burst size, in seconds of sustained rate
+    #[serde(default = "default_burst_secs")]
+    pub burst_secs: f64,
+}
+
+impl Default for RateLimitSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            global_rps: default_global_rps(),
+            user_rps: default_user_rps(),
+            global_bandwidth_mb: 0,
+            user_bandwidth_mb: 0,
+            burst_secs: default_burst_secs(),
+        }
+    }
+}
+
+fn default_global_rps() -> f64 {
+    1000.0
+}
+
+fn default_user_rps() -> f64 {
+    100.0
+}
+
+fn default_burst_secs() -> f64 {
+    2.0
+}
+
+/// Per-user bandwidth accounting settings
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BandwidthSettings {
+    /// Count S3 bytes in/out per user and hour
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+
+    /// How often counters are written to the database
+    #[serde(default = "default_bandwidth_flush_interval")]
+    pub flush_interval_secs: u64,
+
+    /// Plan of users without a user record
+    #[serde(default = "default_plan")]
+    pub default_plan: String,
+
+    /// Monthly egress cap per plan (GB); unlisted plans and 0 are unlimited
+    #[serde(default)]
+    pub egress_caps_gb: BTreeMap<String, u64>,
+
+    /// How long a user's egress total is reused before re-reading it
+    #[serde(default = "default_cap_refresh")]
+    pub cap_refresh_secs: u64,
+}
+
+impl Default for BandwidthSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            flush_interval_secs: default_bandwidth_flush_interval(),
+            default_plan: default_plan(),
+            egress_caps_gb: BTreeMap::new(),
+            cap_refresh_secs: default_cap_refresh(),
+        }
+    }
+}
+
+fn default_bandwidth_flush_interval() -> u64 {
+    60
+}
+
+fn default_plan() -> String {
+    "free".to_string()
+}
+
+fn default_cap_refresh() -> u64 {
+    60
+}
+
+/// Hedged read settings
+///
+/// A shard read that takes longer than the recent `percentile` latency is
+/// also sent to a second replica; the first answer wins.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HedgingSettings {
+    /// Enable hedged reads
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+
+    /// Delay before hedging until enough latencies are sampled, and the
+    /// lower bound afterwards (milliseconds)
+    #[serde(default = "default_hedge_min_delay")]
+    pub min_delay_ms: u64,
+
+    /// Latency percentile after which a read is hedged
+    #[serde(default = "default_hedge_percentile")]
+    pub percentile: f64,
+
+    /// Fraction of reads allowed to issue a hedge
+    #[serde(default = "default_hedge_max_ratio")]
+    pub max_ratio: f64,
+}
+
+impl Default for HedgingSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min_delay_ms: default_hedge_min_delay(),
+            percentile: default_hedge_percentile(),
+            max_ratio: default_hedge_max_ratio(),
+        }
+    }
+}
+
+fn default_hedge_min_delay() -> u64 {
+    20
+}
+
+fn default_hedge_percentile() -> f64 {
+    0.95
+}
+
+fn default_hedge_max_ratio() -> f64 {
+    0.1
+}
+
+/// Concurrent write settings
+///
+/// Uploads of the same key are serialized by a per-key lock in the metadata
+/// database. With `last-write-wins` a writer waits up to `lock_timeout_secs`
+/// for its turn; with `reject` it fails right away with a conflict.
+///
+/// Streamed uploads are erasure coded one stripe of `stripe_size_kb` at a
+/// time, which bounds the memory an upload holds in the gateway.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WriteSettings {
+    /// `last-write-wins` or `reject`
+    #[serde(default = "default_overwrite_policy")]
+    pub overwrite_policy: String,
+
+    /// How long a writer waits for the key's lock
+    #[serde(default = "default_write_lock_timeout")]
+    pub lock_timeout_secs: u64,
+
+    /// Stripe size of streamed uploads, in KB
+    #[serde(default = "default_stripe_size_kb")]
+    pub stripe_size_kb: usize,
+}
+
+impl Default for WriteSettings {
+    fn default() -> Self {
+        Self {
+            overwrite_policy: default_overwrite_policy(),
+            lock_timeout_secs: default_write_lock_timeout(),
+            stripe_size_kb: default_stripe_size_kb(),
+        }
+    }
+}
+
+fn default_overwrite_policy() -> String {
+    "last-write-wins".to_string()
+}
+
+fn default_write_lock_timeout() -> u64 {
+    30
+}
+
+fn default_stripe_size_kb() -> usize {
+    cyxcloud_core::DEFAULT_STRIPE_SIZE / 1024
+}
+
+/// Readiness probe settings
+///
+/// `/readyz` answers 503 while the metadata database is unreachable or
+/// fewer than `min_online_nodes` storage nodes are online. Redis only counts
+/// when `require_redis` is set, since the gateway keeps working without it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HealthSettings {
+    /// Online storage nodes needed to be ready
+    #[serde(default = "default_min_online_nodes")]
+    pub min_online_nodes: usize,
+
+    /// Report not ready while Redis is unreachable
+    #[serde(default)]
+    pub require_redis: bool,
+
+    /// Time limit of each dependency check
+    #[serde(default = "default_health_check_timeout")]
+    pub check_timeout_ms: u64,
+}
+
+impl Default for HealthSettings {
+    fn default() -> Self {
+        Self {
+            min_online_nodes: default_min_online_nodes(),
+            require_redis: false,
+            check_timeout_ms: default_health_check_timeout(),
+        }
+    }
+}
+
+fn default_min_online_nodes() -> usize {
+    1
+}
+
+fn default_health_check_timeout() -> u64 {
+    2000
+}
+
+/// Startup preflight settings
+///
+/// Until the metadata schema matches this binary, a configured Redis answers
+/// and `min_registered_nodes` storage nodes are registered, the S3 API
+/// refuses writes. `--skip-preflight` turns the checks off.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PreflightSettings {
+    /// Run the checks (false accepts writes at once)
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+
+    /// Registered storage nodes needed (0 skips the check)
+    #[serde(default = "default_preflight_min_nodes")]
+    pub min_registered_nodes: usize,
+
+    /// Wait between attempts while a check fails
+    #[serde(default = "default_preflight_retry_interval")]
+    pub retry_interval_secs: u64,
+}
+
+impl Default for PreflightSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min_registered_nodes: default_preflight_min_nodes(),
+            retry_interval_secs: default_preflight_retry_interval(),
+        }
+    }
+}
+
+fn default_preflight_min_nodes() -> usize {
+    1
+}
+
+fn default_preflight_retry_interval() -> u64 {
+    10
+}
+
+/// CORS settings
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CorsSettings {
+    /// Allow all origins (development only)
+    #[serde(default)]
+    pub permissive: bool,
+
+    /// Allowed origins when not permissive
+    #[serde(default = "default_allowed_origins")]
+    pub allowed_origins: Vec<String>,
+}
+
+impl Default for CorsSettings {
+    fn default() -> Self {
+        Self {
+            permissive: false,
+            allowed_origins: default_allowed_origins(),
+        }
+    }
+}
+
+fn default_allowed_origins() -> Vec<String> {
+    vec!["http://localhost:3000".to_string()]
+}
+
+/// Log filter settings
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LoggingSettings {
+    /// Filter directives, e.g. `info` or `info,cyxcloud_gateway=debug`
+    #[serde(default = "default_log_level")]
+    pub level: String,
+}
+
+impl Default for LoggingSettings {
+    fn default() -> Self {
+        Self {
+            level: default_log_level(),
+        }
+    }
+}
+
+fn default_log_level() -> String {
+    "debug".to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_settings() {
+        let settings = GatewaySettings::default();
+        assert_eq!(settings.server.http_addr, "0.0.0.0:8180");
+        assert_eq!(settings.server.grpc_addr, "0.0.0.0:50052");
+        assert!(settings.server.grpc_auth);
+        assert!(settings.server.dashboard);
+        assert!(!settings.tls_enabled());
+        assert!(settings.validate().is_ok());
+    }
+
+    #[test]
+    fn test_settings_from_toml() {
+        let toml = r#"
+            [server]
+            http_addr = "127.0.0.1:9000"
+            max_object_mb = 1024
+            min_upload_kbps = 64
+
+            [database]
+            url = "postgres://db/cyxcloud"
+            read_url = "postgres://replica/cyxcloud"
+            max_connections = 20
+
+            [placement]
+            anti_affinity = "strict"
+
+            [daemons]
+            rebalancer_interval_secs = 120
+
+            [rate_limit]
+            user_rps = 5.0
+            user_bandwidth_mb = 10
+
+            [bandwidth.egress_caps_gb]
+            free = 50
+
+            [hedging]
+            percentile = 0.99
+
+            [writes]
+            overwrite_policy = "reject"
+            stripe_size_kb = 512
+
+            [health]
+            min_online_nodes = 3
+
+            [preflight]
+            min_registered_nodes = 0
+
+            [cors]
+            allowed_origins = ["https://app.example.com"]
+        "#;
+
+        let settings: GatewaySettings = toml::from_str(toml).unwrap();
+        assert!(settings.validate().is_ok());
+        assert_eq!(settings.server.http_addr, "127.0.0.1:9000");
+        assert_eq!(settings.server.grpc_addr, "0.0.0.0:50052");
+        assert_eq!(
+            settings.upload_limits_config().max_object_size,
+            1024 * 1024 * 1024
+        );
+        assert_eq!(settings.upload_limits_config().min_throughput, 64 * 1024);
+        assert_eq!(
+            settings.upload_limits_config().timeout,
+            Duration::from_secs(3600)
+        );
+        assert_eq!(settings.db_config().max_connections, 20);
+        assert_eq!(
+            settings.db_config().read_url.as_deref(),
+            Some("postgres://replica/cyxcloud")
+        );
+        assert_eq!(
+            settings.placement_config().anti_affinity,
+            AntiAffinity::Strict
+        );
+        assert_eq!(
+            settings.rebalancer_daemon_config().scan_interval,
+            Duration::from_secs(120)
+        );
+        assert_eq!(
+            settings.rate_limit_config().user_bandwidth,
+            10 * 1024 * 1024
+        );
+        assert_eq!(
+            settings.bandwidth_config().egress_cap("free"),
+            Some(50 * 1024 * 1024 * 1024)
+        );
+        assert_eq!(settings.bandwidth_config().egress_cap("pro"), None);
+        assert_eq!(settings.node_client_config().hedge_percentile, 0.99);
+        assert!(settings.node_client_config().hedge_reads);
+        assert_eq!(
+            settings.write_config().overwrite_policy,
+            OverwritePolicy::Reject
+        );
+        assert_eq!(
+            settings.write_config().lock_timeout,
+            Duration::from_secs(30)
+        );
+        assert_eq!(settings.write_config().stripe_size, 512 * 1024);
+        assert_eq!(settings.readiness_config().min_online_nodes, 3);
+        assert!(settings.preflight_config().enabled);
+        assert_eq!(settings.preflight_config().min_registered_nodes, 0);
+        assert_eq!(
+            settings.preflight_config().retry_interval,
+            Duration::from_secs(10)
+        );
+        assert_eq!(
+            settings.readiness_config().check_timeout,
+            Duration::from_secs(2)
+        );
+        assert_eq!(
+            settings.gateway_config().database_url.as_deref(),
+            Some("postgres://db/cyxcloud")
+        );
+    }
+
+    #[test]
+    fn test_settings_validation() {
+        let mut settings = GatewaySettings::default();
+        settings.server.grpc_addr = "not-an-address".to_string();
+        assert!(settings.validate().is_err());
+
+        let mut settings = GatewaySettings::default();
+        settings.tls.cert = Some(PathBuf::from("/tmp/cert.pem"));
+        assert!(settings.validate().is_err());
+
+        let mut settings = GatewaySettings::default();
+        settings.server.upload_timeout_secs = 0;
+        assert!(settings.validate().is_err());
+
+        let mut settings = GatewaySettings::default();
+        settings.placement.anti_affinity = "sometimes".to_string();
+        assert!(settings.validate().is_err());
+
+        let mut settings = GatewaySettings::default();
+        settings.placement.reputation_weight = 1.5;
+        assert!(settings.validate().is_err());
+
+        let mut settings = GatewaySettings::default();
+        settings.daemons.payment_interval_secs = 0;
+        assert!(settings.validate().is_err());
+
+        let mut settings = GatewaySettings::default();
+        settings.hedging.max_ratio = 2.0;
+        assert!(settings.validate().is_err());
+
+        let mut settings = GatewaySettings::default();
+        settings.writes.overwrite_policy = "first-write-wins".to_string();
+        assert!(settings.validate().is_err());
+
+        let mut settings = GatewaySettings::default();
+        settings.writes.stripe_size_kb = 4;
+        assert!(settings.validate().is_err());
+
+        let mut settings = GatewaySettings::default();
+        settings.health.check_timeout_ms = 0;
+        assert!(settings.validate().is_err());
+
+        let mut settings = GatewaySettings::default();
+        settings.preflight.retry_interval_secs = 0;
+        assert!(settings.validate().is_err());
+
+        let mut settings = GatewaySettings::default();
+        settings.logging.level = "info,cyxcloud_gateway=loud".to_string();
+        assert!(settings.validate().is_err());
+    }
+
+    #[test]
+    fn test_invalid_value_rejected() {
+        let toml = r#"
+            [server]
+            max_body_mb = "lots"
+        "#;
+        assert!(toml::from_str::<GatewaySettings>(toml).is_err());
+    }
+}
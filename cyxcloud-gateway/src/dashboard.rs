@@ -0,0 +1,148 @@
+//! Built-in web dashboard
+//!
+//! A small single-page app served at `/dashboard`, showing nodes, capacity,
+//! chunk health, the repair backlog and recent events from the cluster
+//! overview API. Its files (`cyxcloud-gateway/dashboard/`) are embedded in
+//! the binary, so small deployments get a status page without running a
+//! separate frontend.
+//!
+//! The pages themselves are public; the data is not. The app asks for a
+//! token with the `node:admin` permission and sends it with every API call.
+//! Disable with `server.dashboard = false` (`GATEWAY_DASHBOARD=false`).
+
+use crate::AppState;
+use axum::{
+    extract::Path,
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Redirect, Response},
+    routing::get,
+    Router,
+};
+use rust_embed::RustEmbed;
+use std::sync::Arc;
+
+/// The app shell, also served for client-side routes
+const INDEX: &str = "index.html";
+
+/// Only the dashboard's own scripts, styles and API calls
+const CONTENT_SECURITY_POLICY: &str =
+    "default-src 'self'; img-src 'self' data:; frame-ancestors 'none'";
+
+/// Dashboard files, embedded at build time
+#[derive(RustEmbed)]
+#[folder = "dashboard/"]
+struct Assets;
+
+/// Create dashboard routes
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route(
+            "/dashboard",
+            get(|| async { Redirect::permanent("/dashboard/") }),
+        )
+        .route("/dashboard/", get(index))
+        .route("/dashboard/*path", get(asset))
+}
+
+/// The app shell
+async fn index(headers: HeaderMap) -> Response {
+    serve(INDEX, &headers)
+}
+
+/// A dashboard file, or the app shell for paths that are not files
+async fn asset(Path(path): Path<String>, headers: HeaderMap) -> Response {
+    if Assets::get(&path).is_none() && !path.contains('.') {
+        return serve(INDEX, &headers);
+    }
+    serve(&path, &headers)
+}
+
+/// Serve an embedded file, answering 304 when the client's copy is current
+///
+/// File names carry no content hash, so browsers revalidate every time
+/// (`no-cache`) and the ETag keeps that to a header round trip.
+fn serve(path: &str, headers: &HeaderMap) -> Response {
+    let Some(file) = Assets::get(path) else {
+        return (StatusCode::NOT_FOUND, "Not found").into_response();
+    };
+
+    let etag = format!("\"{}\"", hex::encode(&file.metadata.sha256_hash()[..16]));
+    let etag = HeaderValue::from_str(&etag).expect("hex ETag is a valid header value");
+    let cached = headers
+        .get(header::IF_NONE_MATCH)
+        .is_some_and(|value| *value == etag);
+
+    let mut response = if cached {
+        StatusCode::NOT_MODIFIED.into_response()
+    } else {
+        (
+            [(header::CONTENT_TYPE, file.metadata.mimetype().to_string())],
+            file.data.into_owned(),
+        )
+            .into_response()
+    };
+
+    let headers = response.headers_mut();
+    headers.insert(header::ETAG, etag);
+    headers.insert(header::CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+    headers.insert(
+        header::X_CONTENT_TYPE_OPTIONS,
+        HeaderValue::from_static("nosniff"),
+    );
+    headers.insert(
+        header::CONTENT_SECURITY_POLICY,
+        HeaderValue::from_static(CONTENT_SECURITY_POLICY),
+    );
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn content_type(response: &Response) -> &str {
+        response.headers()[header::CONTENT_TYPE].to_str().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_serves_app_files() {
+        let response = index(HeaderMap::new()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(content_type(&response).starts_with("text/html"));
+        assert_eq!(response.headers()[header::CACHE_CONTROL], "no-cache");
+
+        let response = asset(Path("app.js".to_string()), HeaderMap::new()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(content_type(&response).contains("javascript"));
+
+        let response = asset(Path("style.css".to_string()), HeaderMap::new()).await;
+        assert!(content_type(&response).starts_with("text/css"));
+    }
+
+    #[tokio::test]
+    async fn test_unknown_paths() {
+        // Client-side routes get the app shell, missing files a 404
+        let response = asset(Path("nodes".to_string()), HeaderMap::new()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(content_type(&response).starts_with("text/html"));
+
+        let response = asset(Path("missing.js".to_string()), HeaderMap::new()).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_not_modified() {
+        let response = index(HeaderMap::new()).await;
+        let etag = response.headers()[header::ETAG].clone();
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::IF_NONE_MATCH, etag.clone());
+        let response = index(headers).await;
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers()[header::ETAG], etag);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::IF_NONE_MATCH, HeaderValue::from_static("\"stale\""));
+        assert_eq!(index(headers).await.status(), StatusCode::OK);
+    }
+}
@@ -267,7 +267,7 @@ async fn create_dataset(
     // List files in the bucket with optional prefix to calculate dataset stats
     let files = metadata
         .database()
-        .list_files_by_bucket_prefix(&req.bucket, req.prefix.as_deref(), 10000)
+        .list_files_by_bucket_prefix(claims.tenant(), &req.bucket, req.prefix.as_deref(), 10000)
         .await
         .map_err(|e| {
             error!(error = %e, "Failed to list files");
@@ -7,6 +7,7 @@
 //! - Data access tokens for Server Nodes
 //! - Verification against public dataset registry
 
+use crate::auth::scopes;
 use crate::grpc_api::RequestClaimsExt;
 use crate::AppState;
 use cyxcloud_metadata::{
@@ -129,6 +130,7 @@ impl DataStreamService for DataStreamServiceImpl {
         &self,
         request: Request<StreamBatchesRequest>,
     ) -> Result<Response<Self::StreamBatchesStream>, Status> {
+        request.require_scope(scopes::DATASTREAM_READ)?;
         let req = request.into_inner();
         let dataset_id_str = req.dataset_id.clone();
         tracing::Span::current().record("dataset_id", &dataset_id_str);
@@ -298,6 +300,7 @@ impl DataStreamService for DataStreamServiceImpl {
         &self,
         request: Request<GetDatasetInfoRequest>,
     ) -> Result<Response<DatasetInfoResponse>, Status> {
+        request.require_scope(scopes::DATASTREAM_READ)?;
         let req = request.into_inner();
         tracing::Span::current().record("dataset_id", &req.dataset_id);
 
@@ -340,6 +343,7 @@ impl DataStreamService for DataStreamServiceImpl {
         &self,
         request: Request<ListDatasetsRequest>,
     ) -> Result<Response<ListDatasetsResponse>, Status> {
+        request.require_scope(scopes::DATASTREAM_READ)?;
         let user_id = Self::get_user_id(&request)?;
         let req = request.into_inner();
 
@@ -398,6 +402,7 @@ impl DataStreamService for DataStreamServiceImpl {
         &self,
         request: Request<CreateDatasetRequest>,
     ) -> Result<Response<CreateDatasetResponse>, Status> {
+        request.require_scope(scopes::DATASTREAM_WRITE)?;
         let user_id = Self::get_user_id(&request)?;
         let req = request.into_inner();
         tracing::Span::current().record("name", &req.name);
@@ -514,6 +519,7 @@ impl DataStreamService for DataStreamServiceImpl {
         &self,
         request: Request<CreateAccessTokenRequest>,
     ) -> Result<Response<AccessTokenResponse>, Status> {
+        request.require_scope(scopes::DATASTREAM_WRITE)?;
         let user_id = Self::get_user_id(&request)?;
         let req = request.into_inner();
         tracing::Span::current().record("dataset_id", &req.dataset_id);
@@ -619,6 +625,7 @@ impl DataStreamService for DataStreamServiceImpl {
         &self,
         request: Request<RevokeAccessTokenRequest>,
     ) -> Result<Response<RevokeAccessTokenResponse>, Status> {
+        request.require_scope(scopes::DATASTREAM_WRITE)?;
         let _user_id = Self::get_user_id(&request)?;
         let req = request.into_inner();
         tracing::Span::current().record("token_id", &req.token_id);
@@ -648,6 +655,7 @@ impl DataStreamService for DataStreamServiceImpl {
         &self,
         request: Request<VerifyDatasetRequest>,
     ) -> Result<Response<VerificationResult>, Status> {
+        request.require_scope(scopes::DATASTREAM_READ)?;
         let req = request.into_inner();
         tracing::Span::current().record("dataset_id", &req.dataset_id);
 
@@ -789,6 +797,7 @@ impl DataStreamService for DataStreamServiceImpl {
         &self,
         request: Request<ShareDatasetRequest>,
     ) -> Result<Response<ShareDatasetResponse>, Status> {
+        request.require_scope(scopes::DATASTREAM_WRITE)?;
         let user_id = Self::get_user_id(&request)?;
         let req = request.into_inner();
         tracing::Span::current().record("dataset_id", &req.dataset_id);
@@ -866,6 +875,7 @@ impl DataStreamService for DataStreamServiceImpl {
         &self,
         request: Request<ListPublicDatasetsRequest>,
     ) -> Result<Response<ListPublicDatasetsResponse>, Status> {
+        request.require_scope(scopes::DATASTREAM_READ)?;
         let req = request.into_inner();
         let metadata = self.metadata()?;
 
@@ -0,0 +1,303 @@
+//! Bucket Export
+//!
+//! One-shot backups of a bucket (`GET /s3/{bucket}?export=tar`). The
+//! archive is a POSIX tar stream built while the objects are read:
+//!
+//! - every object is an entry `{bucket}/{key}`, with its size and
+//!   modification time
+//! - the last entry is `manifest.json`, listing each exported key with its
+//!   size, ETag and the Blake3 hash of the bytes written to the archive
+//!
+//! The manifest comes last because the hashes are only known once the data
+//! has been streamed. Keys longer than a tar header allows and objects of
+//! 8 GiB or more get a pax extended header, which every current tar reads.
+//!
+//! Only the archive framing lives here; the S3 API reads the objects window
+//! by window and interleaves their data with these headers.
+
+use crate::s3_api::{S3Error, S3Result};
+use serde::Serialize;
+use std::str::FromStr;
+
+/// Size of a tar block; headers are one block and data is padded to one
+pub const BLOCK_SIZE: usize = 512;
+
+/// Path of the manifest entry at the end of the archive
+pub const MANIFEST_PATH: &str = "manifest.json";
+
+/// Longest path a plain ustar header holds
+const MAX_NAME_LEN: usize = 100;
+
+/// Largest size that fits the 11 octal digits of a ustar header
+const MAX_OCTAL_SIZE: u64 = 0o77777777777;
+
+/// Archive format of an export (`?export=`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Tar,
+}
+
+impl ExportFormat {
+    /// MIME type of the archive
+    pub fn content_type(self) -> &'static str {
+        match self {
+            ExportFormat::Tar => "application/x-tar",
+        }
+    }
+
+    /// File extension of the archive
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Tar => "tar",
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = S3Error;
+
+    fn from_str(s: &str) -> S3Result<Self> {
+        match s {
+            "tar" => Ok(ExportFormat::Tar),
+            other => Err(S3Error::InvalidRequest(format!(
+                "Unsupported export format '{}', expected 'tar'",
+                other
+            ))),
+        }
+    }
+}
+
+/// Header block(s) of a regular file entry
+///
+/// A pax extended header precedes the ustar header when the path or the
+/// size does not fit it.
+pub fn entry_header(path: &str, size: u64, mtime: i64) -> Vec<u8> {
+    let mtime = mtime.max(0) as u64;
+    let mut records = String::new();
+    if path.len() > MAX_NAME_LEN {
+        records.push_str(&pax_record("path", path));
+    }
+    if size > MAX_OCTAL_SIZE {
+        records.push_str(&pax_record("size", &size.to_string()));
+    }
+
+    let mut out = Vec::with_capacity(BLOCK_SIZE * 2);
+    if !records.is_empty() {
+        let records = records.into_bytes();
+        out.extend_from_slice(&ustar_header(
+            b"././@PaxHeader",
+            records.len() as u64,
+            mtime,
+            b'x',
+        ));
+        out.extend_from_slice(&records);
+        out.resize(out.len() + padding(records.len() as u64), 0);
+    }
+    out.extend_from_slice(&ustar_header(
+        truncate_name(path),
+        size.min(MAX_OCTAL_SIZE),
+        mtime,
+        b'0',
+    ));
+    out
+}
+
+/// Zero bytes that pad `size` bytes of data to a whole block
+pub fn padding(size: u64) -> usize {
+    (BLOCK_SIZE - (size % BLOCK_SIZE as u64) as usize) % BLOCK_SIZE
+}
+
+/// End-of-archive marker: two zero blocks
+pub fn trailer() -> [u8; BLOCK_SIZE * 2] {
+    [0; BLOCK_SIZE * 2]
+}
+
+/// One pax record: `"<len> <key>=<value>\n"`, where `<len>` counts itself
+fn pax_record(key: &str, value: &str) -> String {
+    let rest = key.len() + value.len() + 3; // space, '=' and newline
+    let mut len = rest + 1;
+    while rest + len.to_string().len() != len {
+        len = rest + len.to_string().len();
+    }
+    format!("{} {}={}\n", len, key, value)
+}
+
+/// The longest prefix of `path` that fits a ustar name, on a char boundary
+fn truncate_name(path: &str) -> &[u8] {
+    let mut end = path.len().min(MAX_NAME_LEN);
+    while !path.is_char_boundary(end) {
+        end -= 1;
+    }
+    &path.as_bytes()[..end]
+}
+
+/// A ustar header block
+fn ustar_header(name: &[u8], size: u64, mtime: u64, typeflag: u8) -> [u8; BLOCK_SIZE] {
+    let mut block = [0u8; BLOCK_SIZE];
+    block[..name.len()].copy_from_slice(name);
+    write_octal(&mut block[100..108], 0o644);
+    write_octal(&mut block[108..116], 0);
+    write_octal(&mut block[116..124], 0);
+    write_octal(&mut block[124..136], size);
+    write_octal(&mut block[136..148], mtime.min(MAX_OCTAL_SIZE));
+    block[156] = typeflag;
+    block[257..263].copy_from_slice(b"ustar\0");
+    block[263..265].copy_from_slice(b"00");
+
+    // The checksum is computed with its own field set to spaces
+    block[148..156].fill(b' ');
+    let checksum: u32 = block.iter().map(|&b| b as u32).sum();
+    write_octal(&mut block[148..155], checksum as u64);
+    block
+}
+
+/// Write `value` as zero-padded octal, NUL-terminated, filling `field`
+fn write_octal(field: &mut [u8], value: u64) {
+    let digits = field.len() - 1;
+    let text = format!("{:0width$o}", value, width = digits);
+    field[..digits].copy_from_slice(&text.as_bytes()[text.len() - digits..]);
+    field[digits] = 0;
+}
+
+/// One exported object in the manifest
+#[derive(Debug, Clone, Serialize)]
+pub struct ManifestEntry {
+    pub key: String,
+    pub size: u64,
+    pub etag: String,
+    /// Blake3 hash (hex) of the bytes written to the archive
+    pub blake3: String,
+}
+
+/// Manifest of an export, written as the archive's last entry
+#[derive(Debug, Serialize)]
+pub struct ExportManifest {
+    pub bucket: String,
+    pub prefix: String,
+    pub exported_at: String,
+    pub object_count: u64,
+    pub total_bytes: u64,
+    pub objects: Vec<ManifestEntry>,
+}
+
+impl ExportManifest {
+    /// Empty manifest for an export starting now
+    pub fn new(bucket: &str, prefix: &str) -> Self {
+        Self {
+            bucket: bucket.to_string(),
+            prefix: prefix.to_string(),
+            exported_at: chrono::Utc::now().to_rfc3339(),
+            object_count: 0,
+            total_bytes: 0,
+            objects: Vec::new(),
+        }
+    }
+
+    /// Record an object that has been written to the archive
+    pub fn push(&mut self, entry: ManifestEntry) {
+        self.object_count += 1;
+        self.total_bytes += entry.size;
+        self.objects.push(entry);
+    }
+
+    /// The manifest as pretty-printed JSON
+    pub fn to_json(&self) -> Vec<u8> {
+        serde_json::to_vec_pretty(self).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn octal_field(field: &[u8]) -> u64 {
+        let text = std::str::from_utf8(field).unwrap();
+        u64::from_str_radix(text.trim_matches(|c| c == '\0' || c == ' '), 8).unwrap()
+    }
+
+    fn checksum_ok(block: &[u8]) -> bool {
+        let mut copy = block.to_vec();
+        copy[148..156].fill(b' ');
+        let sum: u64 = copy.iter().map(|&b| b as u64).sum();
+        octal_field(&block[148..156]) == sum
+    }
+
+    #[test]
+    fn test_entry_header() {
+        let header = entry_header("photos/cat.jpg", 1234, 1_700_000_000);
+        assert_eq!(header.len(), BLOCK_SIZE);
+        assert!(header.starts_with(b"photos/cat.jpg\0"));
+        assert_eq!(octal_field(&header[124..136]), 1234);
+        assert_eq!(octal_field(&header[136..148]), 1_700_000_000);
+        assert_eq!(header[156], b'0');
+        assert_eq!(&header[257..263], b"ustar\0");
+        assert!(checksum_ok(&header));
+    }
+
+    #[test]
+    fn test_long_path_gets_pax_header() {
+        let path = format!("bucket/{}", "d/".repeat(80));
+        let header = entry_header(&path, 10, 0);
+        assert_eq!(header.len(), BLOCK_SIZE * 3);
+
+        let pax = &header[..BLOCK_SIZE];
+        assert_eq!(pax[156], b'x');
+        assert!(checksum_ok(pax));
+        let len = octal_field(&pax[124..136]) as usize;
+        let record = std::str::from_utf8(&header[BLOCK_SIZE..BLOCK_SIZE + len]).unwrap();
+        assert_eq!(record, format!("{} path={}\n", record.len(), path));
+
+        let ustar = &header[BLOCK_SIZE * 2..];
+        assert_eq!(ustar[156], b'0');
+        assert_eq!(octal_field(&ustar[124..136]), 10);
+        assert!(checksum_ok(ustar));
+    }
+
+    #[test]
+    fn test_huge_size_gets_pax_header() {
+        let size = 10 * 1024 * 1024 * 1024u64;
+        let header = entry_header("bucket/big.bin", size, 0);
+        assert_eq!(header.len(), BLOCK_SIZE * 3);
+        let record = std::str::from_utf8(&header[BLOCK_SIZE..BLOCK_SIZE * 2]).unwrap();
+        assert!(record.starts_with(&format!("20 size={}\n", size)));
+    }
+
+    #[test]
+    fn test_pax_record_length() {
+        // The length prefix counts its own digits
+        assert_eq!(pax_record("path", "a"), "9 path=a\n");
+        // 99 bytes without the prefix, so it takes three digits
+        let record = pax_record("path", &"x".repeat(92));
+        assert_eq!(record.len(), 102);
+        assert!(record.starts_with("102 "));
+    }
+
+    #[test]
+    fn test_padding() {
+        assert_eq!(padding(0), 0);
+        assert_eq!(padding(1), 511);
+        assert_eq!(padding(512), 0);
+        assert_eq!(padding(1000), 24);
+    }
+
+    #[test]
+    fn test_export_format() {
+        assert_eq!("tar".parse::<ExportFormat>().unwrap(), ExportFormat::Tar);
+        assert!("zip".parse::<ExportFormat>().is_err());
+    }
+
+    #[test]
+    fn test_manifest() {
+        let mut manifest = ExportManifest::new("photos", "2024/");
+        manifest.push(ManifestEntry {
+            key: "2024/cat.jpg".to_string(),
+            size: 10,
+            etag: "abc".to_string(),
+            blake3: blake3::hash(b"0123456789").to_hex().to_string(),
+        });
+        let json: serde_json::Value = serde_json::from_slice(&manifest.to_json()).unwrap();
+        assert_eq!(json["object_count"], 1);
+        assert_eq!(json["total_bytes"], 10);
+        assert_eq!(json["objects"][0]["key"], "2024/cat.jpg");
+    }
+}
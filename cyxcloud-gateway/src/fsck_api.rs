@@ -0,0 +1,493 @@
+//! File integrity check REST API
+//!
+//! Lets users check the objects in their own buckets (`cyxcloud fsck`):
+//! - Every copy of every shard is verified against its content hash by the
+//!   node holding it
+//! - Objects are reported as healthy, degraded (missing or corrupt copies,
+//!   but still readable) or lost
+//! - With `repair`, corrupt copies are dropped and repair jobs copy the
+//!   surviving replicas of degraded objects to other nodes
+
+use crate::admin_api::require_metadata;
+use crate::auth_api::{extract_and_validate_token, ApiError};
+use crate::AppState;
+use axum::{
+    extract::{Json, Path, State},
+    http::{HeaderMap, StatusCode},
+    routing::post,
+    Router,
+};
+use cyxcloud_metadata::{Chunk, Database, DbError, File, Node};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+/// Most objects checked per request
+const MAX_FSCK_OBJECTS: i32 = 10_000;
+
+/// Priority of repair jobs queued by fsck (evacuations use 100)
+const FSCK_REPAIR_PRIORITY: i32 = 50;
+
+/// Integrity of one object
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ObjectHealth {
+    /// Every shard has all its copies, and they verify
+    Healthy,
+    /// Copies are missing or corrupt, but every chunk can still be read
+    Degraded,
+    /// At least one chunk no longer has enough shards to be decoded
+    Lost,
+}
+
+/// Verified copies of one shard
+#[derive(Debug, Clone)]
+struct ShardCheck {
+    chunk: Chunk,
+    /// Online nodes whose copy verified
+    valid: Vec<Uuid>,
+    /// Online nodes whose copy is missing or corrupt
+    corrupt: Vec<Uuid>,
+    /// Copies recorded on offline nodes
+    unavailable: usize,
+}
+
+impl ShardCheck {
+    fn is_complete(&self) -> bool {
+        self.corrupt.is_empty() && self.valid.len() >= self.chunk.replication_factor.max(1) as usize
+    }
+}
+
+/// Classify an object from the checks of its shards
+///
+/// A chunk is readable while `data_shards` of its shards have a valid copy.
+fn classify(file: &File, shards: &[ShardCheck]) -> ObjectHealth {
+    let mut readable: HashMap<i32, usize> = HashMap::new();
+    for shard in shards.iter().filter(|s| !s.valid.is_empty()) {
+        *readable.entry(shard.chunk.chunk_index).or_default() += 1;
+    }
+
+    let data_shards = if file.is_replicated() {
+        1
+    } else {
+        file.data_shards.max(1) as usize
+    };
+    let lost = (0..file.chunk_count.max(1))
+        .any(|index| readable.get(&index).copied().unwrap_or(0) < data_shards);
+
+    if lost {
+        ObjectHealth::Lost
+    } else if shards.iter().all(ShardCheck::is_complete) {
+        ObjectHealth::Healthy
+    } else {
+        ObjectHealth::Degraded
+    }
+}
+
+/// Result for one object
+#[derive(Debug, Serialize)]
+pub struct ObjectReport {
+    pub key: String,
+    pub size: i64,
+    pub health: ObjectHealth,
+    pub shards: usize,
+    /// Shards missing copies or holding corrupt ones
+    pub damaged_shards: usize,
+    pub corrupt_copies: usize,
+    /// Copies on offline nodes
+    pub unavailable_copies: usize,
+    /// Repair jobs queued for this object
+    pub repairs_queued: usize,
+}
+
+/// Integrity report for a bucket or prefix
+#[derive(Debug, Default, Serialize)]
+pub struct FsckReport {
+    pub bucket: String,
+    pub prefix: Option<String>,
+    pub checked: usize,
+    pub healthy: usize,
+    pub degraded: usize,
+    pub lost: usize,
+    /// Stopped after the first `MAX_FSCK_OBJECTS` objects
+    pub truncated: bool,
+    pub repairs_queued: usize,
+    /// Corrupt copies dropped so the rebalancer rebuilds them
+    pub copies_dropped: u64,
+    /// Degraded and lost objects (every object with `verbose`)
+    pub objects: Vec<ObjectReport>,
+}
+
+/// Request body for a check
+#[derive(Debug, Default, Deserialize)]
+pub struct FsckRequest {
+    /// Only check keys starting with this prefix
+    pub prefix: Option<String>,
+    /// Queue repairs for degraded objects
+    #[serde(default)]
+    pub repair: bool,
+    /// Also list healthy objects
+    #[serde(default)]
+    pub verbose: bool,
+}
+
+/// Create fsck routes
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new().route("/:bucket", post(check_bucket))
+}
+
+/// Map a database error to a 500
+fn fsck_db_error(e: DbError) -> (StatusCode, Json<ApiError>) {
+    error!(error = %e, "Integrity check query failed");
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(ApiError::new("Failed to check objects", "INTERNAL_ERROR")),
+    )
+}
+
+/// Check the objects of a bucket, optionally queueing repairs
+async fn check_bucket(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(bucket): Path<String>,
+    Json(req): Json<FsckRequest>,
+) -> Result<Json<FsckReport>, (StatusCode, Json<ApiError>)> {
+    let claims = extract_and_validate_token(&headers, state.auth_service()).await?;
+    let db = require_metadata(&state)?.database();
+    let tenant = claims.tenant();
+
+    if db
+        .get_bucket(tenant, &bucket)
+        .await
+        .map_err(fsck_db_error)?
+        .is_none()
+    {
+        return Err((
+            StatusCode::NOT_FOUND,
+            Json(ApiError::new(
+                format!("Bucket not found: {}", bucket),
+                "NO_SUCH_BUCKET",
+            )),
+        ));
+    }
+
+    let mut files = db
+        .list_files_by_bucket_prefix(tenant, &bucket, req.prefix.as_deref(), MAX_FSCK_OBJECTS + 1)
+        .await
+        .map_err(fsck_db_error)?;
+    let truncated = files.len() > MAX_FSCK_OBJECTS as usize;
+    files.truncate(MAX_FSCK_OBJECTS as usize);
+
+    let nodes: HashMap<Uuid, Node> = db
+        .get_online_nodes()
+        .await
+        .map_err(fsck_db_error)?
+        .into_iter()
+        .map(|n| (n.id, n))
+        .collect();
+
+    info!(
+        user = %claims.sub,
+        tenant = tenant,
+        bucket = %bucket,
+        objects = files.len(),
+        repair = req.repair,
+        "Checking object integrity"
+    );
+
+    let mut report = FsckReport {
+        bucket: bucket.clone(),
+        prefix: req.prefix.clone(),
+        truncated,
+        ..Default::default()
+    };
+    let key_offset = bucket.len() + 1;
+
+    for file in &files {
+        let shards = check_file(&state, db, &nodes, file)
+            .await
+            .map_err(fsck_db_error)?;
+        let health = classify(file, &shards);
+
+        let mut repairs_queued = 0;
+        if req.repair && health == ObjectHealth::Degraded {
+            let (queued, dropped) = repair_file(db, &nodes, &shards).await;
+            repairs_queued = queued;
+            report.repairs_queued += queued;
+            report.copies_dropped += dropped;
+        }
+
+        match health {
+            ObjectHealth::Healthy => report.healthy += 1,
+            ObjectHealth::Degraded => report.degraded += 1,
+            ObjectHealth::Lost => report.lost += 1,
+        }
+        report.checked += 1;
+
+        if health != ObjectHealth::Healthy || req.verbose {
+            report.objects.push(ObjectReport {
+                key: file
+                    .path
+                    .get(key_offset..)
+                    .unwrap_or(&file.path)
+                    .to_string(),
+                size: file.size_bytes,
+                health,
+                shards: shards.len(),
+                damaged_shards: shards.iter().filter(|s| !s.is_complete()).count(),
+                corrupt_copies: shards.iter().map(|s| s.corrupt.len()).sum(),
+                unavailable_copies: shards.iter().map(|s| s.unavailable).sum(),
+                repairs_queued,
+            });
+        }
+    }
+
+    if report.degraded > 0 || report.lost > 0 {
+        warn!(
+            bucket = %bucket,
+            degraded = report.degraded,
+            lost = report.lost,
+            repairs_queued = report.repairs_queued,
+            "Integrity check found damaged objects"
+        );
+    }
+
+    Ok(Json(report))
+}
+
+/// Verify every recorded copy of a file's shards
+async fn check_file(
+    state: &AppState,
+    db: &Database,
+    nodes: &HashMap<Uuid, Node>,
+    file: &File,
+) -> Result<Vec<ShardCheck>, DbError> {
+    let chunks = db.get_file_chunks(file.id).await?;
+    let mut holders: HashMap<Vec<u8>, Vec<Uuid>> = HashMap::new();
+    for (chunk_id, node_id) in db.get_file_shard_locations(file.id).await? {
+        holders.entry(chunk_id).or_default().push(node_id);
+    }
+
+    let mut shards = Vec::with_capacity(chunks.len());
+    for chunk in chunks {
+        let mut check = ShardCheck {
+            valid: Vec::new(),
+            corrupt: Vec::new(),
+            unavailable: 0,
+            chunk,
+        };
+
+        for node_id in holders.remove(&check.chunk.chunk_id).unwrap_or_default() {
+            let Some(node) = nodes.get(&node_id) else {
+                check.unavailable += 1;
+                continue;
+            };
+            match state
+                .node_client()
+                .verify_chunk(&node.grpc_address, &check.chunk.chunk_id)
+                .await
+            {
+                Ok(true) => check.valid.push(node_id),
+                Ok(false) => check.corrupt.push(node_id),
+                Err(e) => {
+                    warn!(
+                        error = %e,
+                        node_id = %node_id,
+                        chunk_id = %hex::encode(&check.chunk.chunk_id),
+                        "Failed to verify chunk copy"
+                    );
+                    check.unavailable += 1;
+                }
+            }
+        }
+        shards.push(check);
+    }
+
+    Ok(shards)
+}
+
+/// Queue repairs for a degraded file's shards
+///
+/// Corrupt copies are dropped, and every shard short of copies gets a
+/// repair job per missing copy, copying from a verified replica to an
+/// online node that does not hold the shard yet. Shards with no verified
+/// copy left are rebuilt by the rebalancer from their siblings. Returns the
+/// jobs queued and the copies dropped.
+async fn repair_file(
+    db: &Database,
+    nodes: &HashMap<Uuid, Node>,
+    shards: &[ShardCheck],
+) -> (usize, u64) {
+    let mut targets: Vec<Uuid> = nodes.keys().copied().collect();
+    targets.sort();
+    let mut next_target = 0;
+    let mut queued = 0;
+    let mut dropped = 0;
+
+    for shard in shards.iter().filter(|s| !s.is_complete()) {
+        let chunk_id = &shard.chunk.chunk_id;
+
+        for node_id in &shard.corrupt {
+            match db
+                .remove_damaged_chunk_locations(*node_id, std::slice::from_ref(chunk_id))
+                .await
+            {
+                Ok(removed) => dropped += removed,
+                Err(e) => warn!(
+                    error = %e,
+                    chunk_id = %hex::encode(chunk_id),
+                    "Failed to drop corrupt chunk copy"
+                ),
+            }
+        }
+
+        let Some(&source) = shard.valid.first() else {
+            continue;
+        };
+        let holding: HashSet<Uuid> = shard.valid.iter().copied().collect();
+        let missing =
+            (shard.chunk.replication_factor.max(1) as usize).saturating_sub(shard.valid.len());
+
+        for _ in 0..missing {
+            let Some(target) = (0..targets.len())
+                .map(|i| targets[(next_target + i) % targets.len()])
+                .find(|id| !holding.contains(id))
+            else {
+                break;
+            };
+            next_target += 1;
+
+            match db
+                .create_replica_repair_job(chunk_id, source, target, FSCK_REPAIR_PRIORITY)
+                .await
+            {
+                Ok(_) => queued += 1,
+                Err(e) => warn!(
+                    error = %e,
+                    chunk_id = %hex::encode(chunk_id),
+                    "Failed to queue repair job"
+                ),
+            }
+        }
+    }
+
+    (queued, dropped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn file(chunk_count: i32, storage_mode: &str) -> File {
+        File {
+            id: Uuid::new_v4(),
+            name: "obj".to_string(),
+            path: "bucket/obj".to_string(),
+            content_hash: vec![],
+            size_bytes: 1024,
+            chunk_count,
+            data_shards: 2,
+            parity_shards: 1,
+            chunk_size: 512,
+            erasure_backend: "reed-solomon".to_string(),
+            storage_mode: storage_mode.to_string(),
+            owner_id: None,
+            bucket: Some("bucket".to_string()),
+            tenant_id: "default".to_string(),
+            status: "active".to_string(),
+            content_type: None,
+            metadata: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            deleted_at: None,
+            expires_at: None,
+            lock_mode: None,
+            retain_until: None,
+            sse_algorithm: None,
+            sse_key_id: None,
+            sse_data_key: None,
+            public_read: false,
+            cache_control: None,
+            content_disposition: None,
+            content_encoding: None,
+            storage_class: "STANDARD".to_string(),
+            restored_until: None,
+            restored_copy_id: None,
+        }
+    }
+
+    fn shard(chunk_index: i32, valid: usize, corrupt: usize) -> ShardCheck {
+        ShardCheck {
+            chunk: Chunk {
+                id: Uuid::new_v4(),
+                chunk_id: vec![chunk_index as u8],
+                file_id: Uuid::nil(),
+                chunk_index,
+                shard_index: 0,
+                is_parity: false,
+                size_bytes: 256,
+                replication_factor: 1,
+                current_replicas: 1,
+                status: "stored".to_string(),
+                created_at: Utc::now(),
+                verified_at: None,
+            },
+            valid: (0..valid).map(|_| Uuid::new_v4()).collect(),
+            corrupt: (0..corrupt).map(|_| Uuid::new_v4()).collect(),
+            unavailable: 0,
+        }
+    }
+
+    #[test]
+    fn test_classify_erasure() {
+        let f = file(2, "erasure");
+        let all = [
+            shard(0, 1, 0),
+            shard(0, 1, 0),
+            shard(0, 1, 0),
+            shard(1, 1, 0),
+            shard(1, 1, 0),
+            shard(1, 1, 0),
+        ];
+        assert_eq!(classify(&f, &all), ObjectHealth::Healthy);
+
+        // One shard of each chunk corrupt: two data shards remain
+        let degraded = [
+            shard(0, 1, 0),
+            shard(0, 1, 0),
+            shard(0, 0, 1),
+            shard(1, 0, 1),
+            shard(1, 1, 0),
+            shard(1, 1, 0),
+        ];
+        assert_eq!(classify(&f, &degraded), ObjectHealth::Degraded);
+
+        // Chunk 1 is down to a single shard
+        let lost = [
+            shard(0, 1, 0),
+            shard(0, 1, 0),
+            shard(0, 1, 0),
+            shard(1, 0, 1),
+            shard(1, 0, 0),
+            shard(1, 1, 0),
+        ];
+        assert_eq!(classify(&f, &lost), ObjectHealth::Lost);
+
+        // No shards recorded for chunk 1 at all
+        assert_eq!(classify(&f, &all[..3]), ObjectHealth::Lost);
+    }
+
+    #[test]
+    fn test_classify_replicated() {
+        let f = file(1, "replicated");
+        let mut copy = shard(0, 2, 1);
+        copy.chunk.replication_factor = 3;
+        assert_eq!(classify(&f, &[copy.clone()]), ObjectHealth::Degraded);
+
+        copy.valid.clear();
+        assert_eq!(classify(&f, &[copy]), ObjectHealth::Lost);
+    }
+}
@@ -3,29 +3,172 @@
 //! Provides:
 //! - NodeService: Node registration and heartbeat handling
 //! - DataService: Streaming data access for ML training pipelines
+//! - ObjectService: Bucket and object management
 
+use crate::audit::{audit_log, AuditEvent};
+use crate::auth::scopes;
 use crate::node_client::NodeClient;
+use crate::node_monitor::NodeMonitor;
+use crate::s3_api::{validate_object_key, validate_user_metadata, S3Error, S3Result, UserMetadata};
 use crate::AppState;
-use cyxcloud_metadata::{CreateNode, MetadataService, Node};
+use bytes::Bytes;
+use cyxcloud_core::error::{HasErrorCode, REQUEST_ID_HEADER};
+use cyxcloud_metadata::{
+    CreateNode, MetadataError, MetadataService, Node, NodeDrain, NodeLoadReport, DEFAULT_TENANT,
+};
 use cyxcloud_protocol::data::{
     data_service_server::DataService, DataChunk, DatasetInfo as ProtoDatasetInfo,
     GetDatasetRequest, PrefetchRequest, PrefetchResponse, StreamDataRequest,
 };
 use cyxcloud_protocol::node::{
-    node_service_server::NodeService, DrainNodeRequest, DrainNodeResponse, GetNodeRequest,
-    GetNodeResponse, HeartbeatRequest, HeartbeatResponse, ListNodesRequest, ListNodesResponse,
-    NodeCapacity, NodeInfo, NodeLocation, NodeStatus, RegisterNodeRequest, RegisterNodeResponse,
-    ReportMetricsRequest, ReportMetricsResponse,
+    node_service_server::NodeService, DrainNodeRequest, DrainNodeResponse, DrainProgress,
+    GetNodeRequest, GetNodeResponse, HeartbeatRequest, HeartbeatResponse, ListNodesRequest,
+    ListNodesResponse, NodeCapacity, NodeInfo, NodeLocation, NodeMetrics as ProtoNodeMetrics,
+    NodeStatus, RegisterNodeRequest, RegisterNodeResponse, ReportChunkDamageRequest,
+    ReportChunkDamageResponse, ReportMetricsRequest, ReportMetricsResponse,
+};
+use cyxcloud_protocol::object::{
+    get_object_response, object_service_server::ObjectService, put_object_request,
+    CreateBucketRequest, CreateBucketResponse, DeleteBucketRequest, DeleteBucketResponse,
+    DeleteObjectRequest, DeleteObjectResponse, GetObjectRequest, GetObjectResponse,
+    ListObjectsRequest, ListObjectsResponse, ObjectEntry, ObjectInfo, PutObjectRequest,
+    PutObjectResponse,
 };
+use std::collections::HashMap;
 use std::pin::Pin;
 use std::sync::Arc;
 use tokio::sync::mpsc;
 use tokio_stream::wrappers::ReceiverStream;
 use tokio_stream::Stream;
-use tonic::{Request, Response, Status};
+use tonic::{Request, Response, Status, Streaming};
 use tracing::{debug, error, info, instrument, warn};
 use uuid::Uuid;
 
+/// Request ID from incoming metadata, or a fresh one
+fn grpc_request_id<T>(request: &Request<T>) -> String {
+    request
+        .metadata()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string)
+        .unwrap_or_else(|| Uuid::new_v4().simple().to_string())
+}
+
+/// User metadata from a gRPC map, with names lowercased like HTTP headers
+fn user_metadata_from_proto(metadata: HashMap<String, String>) -> UserMetadata {
+    metadata
+        .into_iter()
+        .map(|(name, value)| (name.to_ascii_lowercase(), value))
+        .collect()
+}
+
+/// Convert a metadata error into a gRPC status carrying its error code
+fn metadata_status(e: &MetadataError, request_id: &str) -> Status {
+    e.error_code()
+        .to_status(format!("Database error: {}", e), Some(request_id))
+}
+
+/// Warn about disk health thresholds a node reports as crossed
+fn log_disk_problems(node_id: &str, metrics: Option<&ProtoNodeMetrics>) {
+    if let Some(disk) = metrics.and_then(|m| m.disk_health.as_ref()) {
+        if !disk.problems.is_empty() {
+            warn!(
+                node_id = %node_id,
+                device = %disk.device,
+                problems = ?disk.problems,
+                "Node reports failing disk"
+            );
+        }
+    }
+}
+
+/// Store the load a node reports so the rebalancer can pick idle repair sources
+async fn record_node_load(
+    metadata: &MetadataService,
+    node_id: &str,
+    metrics: Option<&ProtoNodeMetrics>,
+) {
+    let Some(metrics) = metrics else {
+        return;
+    };
+    let report = NodeLoadReport {
+        cpu_usage: metrics.cpu_usage,
+        memory_usage: metrics.memory_usage,
+        active_connections: metrics.active_connections as i64,
+        bytes_uploaded: metrics.bytes_uploaded as i64,
+        bytes_downloaded: metrics.bytes_downloaded as i64,
+    };
+    if let Err(e) = metadata.database().record_node_load(node_id, &report).await {
+        warn!(error = %e, node_id = %node_id, "Failed to record node load");
+    }
+}
+
+/// Store the capacity and free disk space a node reports
+///
+/// Placement caps a node's available space by its free disk space, so a
+/// full disk takes the node out of placement even if its capacity is not
+/// used up.
+async fn record_node_capacity(
+    metadata: &MetadataService,
+    node_id: &str,
+    metrics: Option<&ProtoNodeMetrics>,
+) {
+    let Some(metrics) = metrics else {
+        return;
+    };
+    let disk_available = metrics
+        .disk_space
+        .as_ref()
+        .map(|disk| disk.available_bytes as i64);
+    if let Err(e) = metadata
+        .database()
+        .update_node_capacity(
+            node_id,
+            metrics.storage_total as i64,
+            metrics.storage_used as i64,
+            disk_available,
+        )
+        .await
+    {
+        warn!(error = %e, node_id = %node_id, "Failed to record node capacity");
+    }
+}
+
+/// Convert a reported location, treating empty strings, rack 0 and 0/0
+/// coordinates as not configured
+fn location_from_proto(location: &NodeLocation) -> cyxcloud_metadata::NodeLocation {
+    let non_empty = |s: &str| (!s.is_empty()).then(|| s.to_string());
+    let has_coordinates = location.latitude != 0.0 || location.longitude != 0.0;
+    cyxcloud_metadata::NodeLocation {
+        datacenter: non_empty(&location.datacenter),
+        rack: (location.rack > 0).then_some(location.rack as i32),
+        region: non_empty(&location.region),
+        latitude: has_coordinates.then_some(location.latitude),
+        longitude: has_coordinates.then_some(location.longitude),
+    }
+}
+
+/// Store the location a node reports with its heartbeat, if it moved
+async fn record_node_location(
+    metadata: &MetadataService,
+    node_id: &str,
+    location: Option<&NodeLocation>,
+) {
+    // Nodes that predate location reporting send none; keep what they registered
+    let Some(location) = location else {
+        return;
+    };
+    match metadata
+        .database()
+        .update_node_location(node_id, &location_from_proto(location))
+        .await
+    {
+        Ok(true) => info!(node_id = %node_id, ?location, "Node location updated"),
+        Ok(false) => {}
+        Err(e) => warn!(error = %e, node_id = %node_id, "Failed to record node location"),
+    }
+}
+
 // =============================================================================
 // NODE SERVICE IMPLEMENTATION
 // =============================================================================
@@ -76,6 +219,31 @@ impl NodeServiceImpl {
             }),
             status: status.into(),
             registered_at: node.created_at.timestamp(),
+            reputation: node.reputation.clamp(0, 10000) as u32,
+        }
+    }
+
+    /// Convert drain progress to proto DrainProgress
+    fn drain_to_proto(drain: &NodeDrain) -> DrainProgress {
+        DrainProgress {
+            total_chunks: drain.total_chunks.max(0) as u64,
+            evacuated_chunks: drain.evacuated_chunks.max(0) as u64,
+            bytes_remaining: drain.bytes_remaining() as u64,
+            throughput_bps: drain.throughput_bps() as u64,
+            eta_secs: drain.eta_secs().unwrap_or(0),
+            started_at: drain.started_at.timestamp(),
+            completed: drain.is_complete(),
+        }
+    }
+
+    /// Drain progress of a node, if it has been drained (lookup errors are logged)
+    async fn drain_progress(metadata: &MetadataService, node_id: Uuid) -> Option<DrainProgress> {
+        match metadata.database().get_drain_progress(node_id).await {
+            Ok(drain) => drain.as_ref().map(Self::drain_to_proto),
+            Err(e) => {
+                warn!(error = %e, node_id = %node_id, "Failed to load drain progress");
+                None
+            }
         }
     }
 }
@@ -104,7 +272,7 @@ impl NodeService for NodeServiceImpl {
             .ok_or_else(|| Status::invalid_argument("NodeInfo is required"))?;
 
         // Get location and capacity
-        let location = info.location.unwrap_or_default();
+        let location = location_from_proto(&info.location.unwrap_or_default());
         let capacity = info.capacity.unwrap_or_default();
 
         // Create the node in metadata service
@@ -116,16 +284,11 @@ impl NodeService for NodeServiceImpl {
             storage_total: capacity.storage_total as i64,
             storage_reserved: GATEWAY_RESERVED_BYTES,
             bandwidth_mbps: capacity.bandwidth_mbps as i32,
-            datacenter: if location.datacenter.is_empty() {
-                None
-            } else {
-                Some(location.datacenter)
-            },
-            region: if location.region.is_empty() {
-                None
-            } else {
-                Some(location.region)
-            },
+            datacenter: location.datacenter,
+            rack: location.rack,
+            region: location.region,
+            latitude: location.latitude,
+            longitude: location.longitude,
             version: None,
             wallet_address: if info.wallet_address.is_empty() {
                 None
@@ -198,16 +361,76 @@ impl NodeService for NodeServiceImpl {
         &self,
         request: Request<HeartbeatRequest>,
     ) -> Result<Response<HeartbeatResponse>, Status> {
+        request.require_scope(scopes::NODE)?;
         let req = request.into_inner();
         let node_id_str = req.node_id.clone();
         tracing::Span::current().record("node_id", &node_id_str);
 
         debug!(node_id = %node_id_str, "Processing heartbeat");
+        log_disk_problems(&node_id_str, req.metrics.as_ref());
 
         let metadata = self
             .metadata()
             .ok_or_else(|| Status::unavailable("Metadata service not configured"))?;
 
+        // A node draining before shutdown polls for evacuation progress
+        let reported = NodeStatus::try_from(req.status).unwrap_or(NodeStatus::Unknown);
+        if reported == NodeStatus::Draining {
+            return match metadata.pending_evacuations(&node_id_str).await {
+                Ok(pending) => Ok(Response::new(HeartbeatResponse {
+                    acknowledged: true,
+                    commands: vec![],
+                    pending_evacuations: pending,
+                })),
+                Err(e) => {
+                    warn!(error = %e, node_id = %node_id_str, "Failed to check drain progress");
+                    Ok(Response::new(HeartbeatResponse {
+                        acknowledged: false,
+                        commands: vec![],
+                        pending_evacuations: 0,
+                    }))
+                }
+            };
+        }
+
+        // A node shutting down reports its new status so it leaves placement
+        // immediately instead of waiting for the offline threshold
+        if matches!(reported, NodeStatus::Offline | NodeStatus::Maintenance) {
+            let status = if reported == NodeStatus::Offline {
+                cyxcloud_metadata::NodeStatus::Offline
+            } else {
+                cyxcloud_metadata::NodeStatus::Maintenance
+            };
+            let reason = if req.status_reason.is_empty() {
+                "unspecified"
+            } else {
+                req.status_reason.as_str()
+            };
+
+            return match metadata
+                .report_node_shutdown(&node_id_str, status, reason)
+                .await
+            {
+                Ok(()) => Ok(Response::new(HeartbeatResponse {
+                    acknowledged: true,
+                    commands: vec![],
+                    pending_evacuations: 0,
+                })),
+                Err(e) => {
+                    warn!(error = %e, node_id = %node_id_str, "Failed to record node shutdown");
+                    Ok(Response::new(HeartbeatResponse {
+                        acknowledged: false,
+                        commands: vec![],
+                        pending_evacuations: 0,
+                    }))
+                }
+            };
+        }
+
+        record_node_load(metadata, &node_id_str, req.metrics.as_ref()).await;
+        record_node_capacity(metadata, &node_id_str, req.metrics.as_ref()).await;
+        record_node_location(metadata, &node_id_str, req.location.as_ref()).await;
+
         // Use peer_id directly - the node sends its own ID which is stored as peer_id
         // Update heartbeat with recovery-aware logic
         match metadata.heartbeat_by_peer_id(&node_id_str).await {
@@ -225,6 +448,7 @@ impl NodeService for NodeServiceImpl {
                 Ok(Response::new(HeartbeatResponse {
                     acknowledged: true,
                     commands: vec![], // TODO: Return pending commands
+                    pending_evacuations: 0,
                 }))
             }
             Err(e) => {
@@ -232,6 +456,7 @@ impl NodeService for NodeServiceImpl {
                 Ok(Response::new(HeartbeatResponse {
                     acknowledged: false,
                     commands: vec![],
+                    pending_evacuations: 0,
                 }))
             }
         }
@@ -242,6 +467,8 @@ impl NodeService for NodeServiceImpl {
         &self,
         request: Request<GetNodeRequest>,
     ) -> Result<Response<GetNodeResponse>, Status> {
+        request.require_scope(scopes::NODE)?;
+        let request_id = grpc_request_id(&request);
         let req = request.into_inner();
         tracing::Span::current().record("node_id", &req.node_id);
 
@@ -253,20 +480,23 @@ impl NodeService for NodeServiceImpl {
         match metadata.database().get_node_by_peer_id(&req.node_id).await {
             Ok(Some(node)) => {
                 let info = Self::node_to_proto(&node);
+                let drain = Self::drain_progress(metadata, node.id).await;
                 Ok(Response::new(GetNodeResponse {
                     info: Some(info),
                     metrics: None, // TODO: Add metrics tracking
                     found: true,
+                    drain,
                 }))
             }
             Ok(None) => Ok(Response::new(GetNodeResponse {
                 info: None,
                 metrics: None,
                 found: false,
+                drain: None,
             })),
             Err(e) => {
-                error!(error = %e, "Failed to get node");
-                Err(Status::internal(format!("Database error: {}", e)))
+                error!(error = %e, request_id = %request_id, "Failed to get node");
+                Err(metadata_status(&e, &request_id))
             }
         }
     }
@@ -276,6 +506,8 @@ impl NodeService for NodeServiceImpl {
         &self,
         request: Request<ListNodesRequest>,
     ) -> Result<Response<ListNodesResponse>, Status> {
+        request.require_scope(scopes::NODE)?;
+        let request_id = grpc_request_id(&request);
         let req = request.into_inner();
 
         let metadata = self
@@ -286,8 +518,8 @@ impl NodeService for NodeServiceImpl {
         let nodes = match metadata.get_online_nodes().await {
             Ok(nodes) => nodes,
             Err(e) => {
-                error!(error = %e, "Failed to list nodes");
-                return Err(Status::internal(format!("Database error: {}", e)));
+                error!(error = %e, request_id = %request_id, "Failed to list nodes");
+                return Err(metadata_status(&e, &request_id));
             }
         };
 
@@ -333,6 +565,7 @@ impl NodeService for NodeServiceImpl {
         &self,
         request: Request<ReportMetricsRequest>,
     ) -> Result<Response<ReportMetricsResponse>, Status> {
+        request.require_scope(scopes::NODE)?;
         let req = request.into_inner();
         tracing::Span::current().record("node_id", &req.node_id);
 
@@ -341,9 +574,11 @@ impl NodeService for NodeServiceImpl {
             metrics = ?req.metrics,
             "Received metrics report"
         );
+        log_disk_problems(&req.node_id, req.metrics.as_ref());
 
-        // TODO: Store metrics in time-series database or update node record
-        // For now, just acknowledge receipt
+        if let Some(metadata) = self.metadata() {
+            record_node_load(metadata, &req.node_id, req.metrics.as_ref()).await;
+        }
 
         Ok(Response::new(ReportMetricsResponse { success: true }))
     }
@@ -353,6 +588,7 @@ impl NodeService for NodeServiceImpl {
         &self,
         request: Request<DrainNodeRequest>,
     ) -> Result<Response<DrainNodeResponse>, Status> {
+        request.require_scope(scopes::NODE)?;
         let req = request.into_inner();
         tracing::Span::current().record("node_id", &req.node_id);
 
@@ -366,21 +602,20 @@ impl NodeService for NodeServiceImpl {
             .metadata()
             .ok_or_else(|| Status::unavailable("Metadata service not configured"))?;
 
-        // Parse node UUID
-        let node_uuid = Uuid::parse_str(&req.node_id)
-            .map_err(|e| Status::invalid_argument(format!("Invalid node_id format: {}", e)))?;
-
-        // Update node status to draining
-        match metadata
-            .database()
-            .update_node_status(node_uuid, "draining")
-            .await
-        {
-            Ok(()) => {
-                info!(node_id = %req.node_id, "Node marked as draining");
+        // Accept either the node UUID or its peer ID (what nodes know themselves by)
+        match metadata.start_node_drain(&req.node_id, &req.reason).await {
+            Ok((node_uuid, started)) => {
+                // Create the evacuation jobs before answering so a node
+                // polling for progress never sees an empty queue too early
+                if started {
+                    info!(node_id = %req.node_id, "Node marked as draining");
+                    NodeMonitor::trigger_chunk_evacuation(metadata, node_uuid, &req.reason).await;
+                }
+                let progress = Self::drain_progress(metadata, node_uuid).await;
                 Ok(Response::new(DrainNodeResponse {
                     accepted: true,
-                    estimated_duration_secs: 300, // 5 minutes default estimate
+                    estimated_duration_secs: progress.as_ref().map_or(0, |p| p.eta_secs),
+                    progress,
                 }))
             }
             Err(e) => {
@@ -388,16 +623,66 @@ impl NodeService for NodeServiceImpl {
                 Ok(Response::new(DrainNodeResponse {
                     accepted: false,
                     estimated_duration_secs: 0,
+                    progress: None,
                 }))
             }
         }
     }
+
+    #[instrument(skip(self, request), fields(node_id))]
+    async fn report_chunk_damage(
+        &self,
+        request: Request<ReportChunkDamageRequest>,
+    ) -> Result<Response<ReportChunkDamageResponse>, Status> {
+        request.require_scope(scopes::NODE)?;
+        let req = request.into_inner();
+        tracing::Span::current().record("node_id", &req.node_id);
+
+        let metadata = self
+            .metadata()
+            .ok_or_else(|| Status::unavailable("Metadata service not configured"))?;
+
+        warn!(
+            node_id = %req.node_id,
+            corrupted = req.corrupted_chunks.len(),
+            missing = req.missing_chunks.len(),
+            "Node reported damaged chunks"
+        );
+
+        let mut chunk_ids = req.corrupted_chunks;
+        chunk_ids.extend(req.missing_chunks);
+        if chunk_ids.is_empty() {
+            return Ok(Response::new(ReportChunkDamageResponse {
+                locations_removed: 0,
+            }));
+        }
+
+        let removed = metadata
+            .report_damaged_chunks(&req.node_id, &chunk_ids)
+            .await
+            .map_err(|e| match e {
+                MetadataError::NotFound(msg) => Status::not_found(msg),
+                e => Status::internal(format!("Failed to record damaged chunks: {}", e)),
+            })?;
+
+        info!(
+            node_id = %req.node_id,
+            removed = removed,
+            "Damaged chunk locations removed, chunks queued for repair"
+        );
+        Ok(Response::new(ReportChunkDamageResponse {
+            locations_removed: removed,
+        }))
+    }
 }
 
 // =============================================================================
 // DATA SERVICE IMPLEMENTATION
 // =============================================================================
 
+/// Metadata header carrying a data access token for `stream_data`
+pub const DATA_ACCESS_TOKEN_HEADER: &str = "x-data-access-token";
+
 /// gRPC Data Service implementation for ML training data streaming
 pub struct DataServiceImpl {
     state: Arc<AppState>,
@@ -418,6 +703,61 @@ impl DataServiceImpl {
     fn node_client(&self) -> &NodeClient {
         self.state.node_client()
     }
+
+    /// Decide whether a user may stream a file
+    ///
+    /// Allowed for the file's owner, for owners and `stream` sharees of a
+    /// dataset holding it, and for holders of an unexpired data access token
+    /// with the `stream` scope on such a dataset. Returns the kind of grant.
+    async fn stream_access(
+        metadata: &MetadataService,
+        user_id: Uuid,
+        file_id: Uuid,
+        access_token: Option<&str>,
+    ) -> Result<Option<&'static str>, Status> {
+        let db = metadata.database();
+        let database_error = |e: cyxcloud_metadata::DbError| {
+            error!(error = %e, "Failed to check data stream access");
+            Status::internal("Failed to check dataset access")
+        };
+
+        let file = db
+            .get_file(file_id)
+            .await
+            .map_err(database_error)?
+            .ok_or_else(|| Status::not_found(format!("Dataset not found: {}", file_id)))?;
+        if file.owner_id == Some(user_id) {
+            return Ok(Some("owner"));
+        }
+
+        if db
+            .find_file_stream_grant(file_id, user_id)
+            .await
+            .map_err(database_error)?
+            .is_some()
+        {
+            return Ok(Some("dataset"));
+        }
+
+        if let Some(token) = access_token {
+            let token_hash = blake3::hash(token.as_bytes());
+            let token = db
+                .validate_data_access_token(token_hash.as_bytes())
+                .await
+                .map_err(database_error)?;
+            if let Some(token) = token.filter(|t| t.is_valid() && t.has_scope("stream")) {
+                if db
+                    .dataset_contains_file(token.dataset_id, file_id)
+                    .await
+                    .map_err(database_error)?
+                {
+                    return Ok(Some("access_token"));
+                }
+            }
+        }
+
+        Ok(None)
+    }
 }
 
 #[tonic::async_trait]
@@ -429,6 +769,13 @@ impl DataService for DataServiceImpl {
         &self,
         request: Request<StreamDataRequest>,
     ) -> Result<Response<Self::StreamDataStream>, Status> {
+        request.require_scope(scopes::DATASTREAM_READ)?;
+        let caller = request.require_auth()?.sub.clone();
+        let access_token = request
+            .metadata()
+            .get(DATA_ACCESS_TOKEN_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
         let req = request.into_inner();
         let dataset_id = req.dataset_id.clone();
         tracing::Span::current().record("dataset_id", &dataset_id);
@@ -449,6 +796,24 @@ impl DataService for DataServiceImpl {
         let file_uuid = Uuid::parse_str(&dataset_id)
             .map_err(|e| Status::invalid_argument(format!("Invalid dataset_id: {}", e)))?;
 
+        // Only the owner, dataset sharees and token holders may stream
+        let access = match Uuid::parse_str(&caller) {
+            Ok(user_id) => {
+                Self::stream_access(metadata, user_id, file_uuid, access_token.as_deref()).await?
+            }
+            Err(_) => None,
+        };
+        audit_log(AuditEvent::DataStream {
+            user_id: caller.clone(),
+            file_id: dataset_id.clone(),
+            granted: access.is_some(),
+            access: access.map(str::to_string),
+        });
+        if access.is_none() {
+            warn!(user = %caller, dataset_id = %dataset_id, "Data stream denied");
+            return Err(Status::permission_denied("No access to dataset"));
+        }
+
         // Get file chunks from metadata
         let chunks = metadata.get_file_chunks(file_uuid).await.map_err(|e| {
             error!(error = %e, "Failed to get file chunks");
@@ -571,6 +936,9 @@ impl DataService for DataServiceImpl {
         &self,
         request: Request<GetDatasetRequest>,
     ) -> Result<Response<ProtoDatasetInfo>, Status> {
+        request.require_scope(scopes::DATASTREAM_READ)?;
+        let request_id = grpc_request_id(&request);
+        let tenant = grpc_tenant(&request);
         let req = request.into_inner();
         tracing::Span::current().record("dataset_id", &req.dataset_id);
 
@@ -583,9 +951,9 @@ impl DataService for DataServiceImpl {
             .map_err(|e| Status::invalid_argument(format!("Invalid dataset_id: {}", e)))?;
 
         // Get file metadata
-        let file = metadata.get_file(file_uuid).await.map_err(|e| {
-            error!(error = %e, "Failed to get file metadata");
-            Status::internal(format!("Database error: {}", e))
+        let file = metadata.get_file(&tenant, file_uuid).await.map_err(|e| {
+            error!(error = %e, request_id = %request_id, "Failed to get file metadata");
+            metadata_status(&e, &request_id)
         })?;
 
         let file = file
@@ -625,6 +993,7 @@ impl DataService for DataServiceImpl {
         &self,
         request: Request<PrefetchRequest>,
     ) -> Result<Response<PrefetchResponse>, Status> {
+        request.require_scope(scopes::DATASTREAM_READ)?;
         let req = request.into_inner();
         tracing::Span::current().record("dataset_id", &req.dataset_id);
 
@@ -749,97 +1118,612 @@ fn parse_dataset_metadata(metadata_json: &Option<String>) -> (Vec<i32>, String,
 }
 
 // =============================================================================
-// gRPC AUTHENTICATION INTERCEPTOR
+// OBJECT SERVICE IMPLEMENTATION
+// =============================================================================
+
+/// Bytes of an object read from storage per GetObject message (one default chunk)
+const OBJECT_STREAM_WINDOW: u64 = cyxcloud_core::DEFAULT_CHUNK_SIZE as u64;
+
+/// Convert an S3 layer error into a gRPC status carrying its error code
+fn s3_status(e: &S3Error, request_id: &str) -> Status {
+    e.error_code().to_status(e.to_string(), Some(request_id))
+}
+
+/// Tenant of a gRPC request: the caller's `org` claim, or the default tenant
+fn grpc_tenant<T>(request: &Request<T>) -> String {
+    request
+        .claims()
+        .map_or(DEFAULT_TENANT, |c| c.tenant())
+        .to_string()
+}
+
+/// Object data of a PutObject stream, after its header
+///
+/// Fails on a second header and once the data exceeds `max_object_size`.
+fn put_object_body(
+    stream: Streaming<PutObjectRequest>,
+    max_object_size: usize,
+) -> impl Stream<Item = S3Result<Bytes>> + Unpin + Send {
+    Box::pin(futures::stream::try_unfold(
+        (stream, 0usize),
+        move |(mut stream, received)| async move {
+            while let Some(msg) = stream.message().await.map_err(|status| {
+                S3Error::InvalidRequest(format!(
+                    "Failed to receive object data: {}",
+                    status.message()
+                ))
+            })? {
+                match msg.payload {
+                    Some(put_object_request::Payload::Data(chunk)) => {
+                        let received = received + chunk.len();
+                        if received > max_object_size {
+                            return Err(S3Error::InvalidRequest(format!(
+                                "Object exceeds maximum size of {} bytes",
+                                max_object_size
+                            )));
+                        }
+                        return Ok(Some((chunk, (stream, received))));
+                    }
+                    Some(put_object_request::Payload::Header(_)) => {
+                        return Err(S3Error::InvalidRequest(
+                            "Only the first PutObject message may be a header".to_string(),
+                        ));
+                    }
+                    None => {}
+                }
+            }
+            Ok(None)
+        },
+    ))
+}
+
+/// gRPC Object Service implementation
+///
+/// Bucket and object operations for services that already talk gRPC to the
+/// gateway, backed by the same `AppState` paths as the S3 API.
+pub struct ObjectServiceImpl {
+    state: Arc<AppState>,
+    /// Largest object accepted by PutObject
+    max_object_size: usize,
+}
+
+impl ObjectServiceImpl {
+    /// Create a new ObjectService with application state
+    pub fn new(state: Arc<AppState>, max_object_size: usize) -> Self {
+        Self {
+            state,
+            max_object_size,
+        }
+    }
+
+    /// Fail with NOT_FOUND unless the tenant owns `bucket`
+    async fn require_bucket(&self, tenant: &str, bucket: &str) -> S3Result<()> {
+        if !self.state.bucket_exists(tenant, bucket).await? {
+            return Err(S3Error::NoSuchBucket(bucket.to_string()));
+        }
+        Ok(())
+    }
+}
+
+#[tonic::async_trait]
+impl ObjectService for ObjectServiceImpl {
+    type GetObjectStream =
+        Pin<Box<dyn Stream<Item = Result<GetObjectResponse, Status>> + Send + 'static>>;
+
+    #[instrument(skip(self, request), fields(bucket))]
+    async fn create_bucket(
+        &self,
+        request: Request<CreateBucketRequest>,
+    ) -> Result<Response<CreateBucketResponse>, Status> {
+        request.require_scope(scopes::S3_WRITE)?;
+        let request_id = grpc_request_id(&request);
+        let tenant = grpc_tenant(&request);
+        let req = request.into_inner();
+        tracing::Span::current().record("bucket", &req.bucket);
+
+        info!(tenant = %tenant, bucket = %req.bucket, "Creating bucket");
+
+        let result = async {
+            if self.state.bucket_exists(&tenant, &req.bucket).await? {
+                return Err(S3Error::BucketAlreadyExists(req.bucket.clone()));
+            }
+            self.state.create_bucket(&tenant, &req.bucket).await
+        }
+        .await;
+        result.map_err(|e| s3_status(&e, &request_id))?;
+
+        Ok(Response::new(CreateBucketResponse { bucket: req.bucket }))
+    }
+
+    #[instrument(skip(self, request), fields(bucket))]
+    async fn delete_bucket(
+        &self,
+        request: Request<DeleteBucketRequest>,
+    ) -> Result<Response<DeleteBucketResponse>, Status> {
+        request.require_scope(scopes::S3_WRITE)?;
+        let request_id = grpc_request_id(&request);
+        let tenant = grpc_tenant(&request);
+        let req = request.into_inner();
+        tracing::Span::current().record("bucket", &req.bucket);
+
+        info!(tenant = %tenant, bucket = %req.bucket, "Deleting bucket");
+
+        let result = async {
+            self.require_bucket(&tenant, &req.bucket).await?;
+            if !self.state.bucket_is_empty(&tenant, &req.bucket).await? {
+                return Err(S3Error::BucketNotEmpty(req.bucket.clone()));
+            }
+            self.state.delete_bucket(&tenant, &req.bucket).await
+        }
+        .await;
+        result.map_err(|e| s3_status(&e, &request_id))?;
+
+        Ok(Response::new(DeleteBucketResponse {}))
+    }
+
+    #[instrument(skip(self, request), fields(bucket, key))]
+    async fn put_object(
+        &self,
+        request: Request<Streaming<PutObjectRequest>>,
+    ) -> Result<Response<PutObjectResponse>, Status> {
+        request.require_scope(scopes::S3_WRITE)?;
+        let request_id = grpc_request_id(&request);
+        let tenant = grpc_tenant(&request);
+        let mut stream = request.into_inner();
+
+        let header = match stream.message().await? {
+            Some(PutObjectRequest {
+                payload: Some(put_object_request::Payload::Header(header)),
+            }) => header,
+            _ => {
+                return Err(Status::invalid_argument(
+                    "First PutObject message must be a header",
+                ))
+            }
+        };
+        tracing::Span::current().record("bucket", &header.bucket);
+        tracing::Span::current().record("key", &header.key);
+
+        let expires_at = if header.expires_at > 0 {
+            Some(
+                chrono::DateTime::from_timestamp(header.expires_at, 0)
+                    .ok_or_else(|| Status::invalid_argument("Invalid expires_at timestamp"))?,
+            )
+        } else {
+            None
+        };
+        let content_type = if header.content_type.is_empty() {
+            "application/octet-stream"
+        } else {
+            header.content_type.as_str()
+        };
+        let user_metadata = user_metadata_from_proto(header.metadata);
+
+        validate_object_key(&header.key).map_err(|e| s3_status(&e, &request_id))?;
+        validate_user_metadata(&user_metadata).map_err(|e| s3_status(&e, &request_id))?;
+        self.require_bucket(&tenant, &header.bucket)
+            .await
+            .map_err(|e| s3_status(&e, &request_id))?;
+
+        info!(
+            tenant = %tenant,
+            bucket = %header.bucket,
+            key = %header.key,
+            "Uploading object"
+        );
+
+        // The body is stored stripe by stripe as it arrives
+        let output = self
+            .state
+            .put_object_stream(
+                &tenant,
+                &header.bucket,
+                &header.key,
+                put_object_body(stream, self.max_object_size),
+                content_type,
+                expires_at,
+                &user_metadata,
+                &Default::default(),
+                None,
+            )
+            .await
+            .map_err(|e| s3_status(&e, &request_id))?;
+
+        Ok(Response::new(PutObjectResponse {
+            etag: output.etag,
+            size: output.size,
+        }))
+    }
+
+    #[instrument(skip(self, request), fields(bucket, key))]
+    async fn get_object(
+        &self,
+        request: Request<GetObjectRequest>,
+    ) -> Result<Response<Self::GetObjectStream>, Status> {
+        request.require_scope(scopes::S3_READ)?;
+        let request_id = grpc_request_id(&request);
+        let tenant = grpc_tenant(&request);
+        let req = request.into_inner();
+        tracing::Span::current().record("bucket", &req.bucket);
+        tracing::Span::current().record("key", &req.key);
+
+        debug!(tenant = %tenant, bucket = %req.bucket, key = %req.key, "Getting object");
+
+        let result = async {
+            validate_object_key(&req.key)?;
+            self.require_bucket(&tenant, &req.bucket).await?;
+            self.state
+                .get_object_metadata(&tenant, &req.bucket, &req.key)
+                .await?
+                .ok_or_else(|| S3Error::NoSuchKey(req.key.clone()))
+        }
+        .await;
+        let metadata = result.map_err(|e| s3_status(&e, &request_id))?;
+
+        if req.offset > 0 && req.offset >= metadata.size {
+            return Err(Status::out_of_range(format!(
+                "Offset {} is beyond object size {}",
+                req.offset, metadata.size
+            )));
+        }
+        let end = match req.length {
+            0 => metadata.size,
+            length => req.offset.saturating_add(length).min(metadata.size),
+        };
+
+        let info = ObjectInfo {
+            key: metadata.key.clone(),
+            size: metadata.size,
+            content_type: metadata.content_type.clone(),
+            etag: metadata.etag.clone(),
+            last_modified: metadata.last_modified.clone(),
+            expires_at: metadata.expires_at.map_or(0, |t| t.timestamp()),
+            metadata: metadata.user_metadata.clone().into_iter().collect(),
+        };
+
+        let (tx, rx) = mpsc::channel(2);
+        let state = self.state.clone();
+        let (bucket, key) = (req.bucket, req.key);
+        let mut offset = req.offset;
+
+        // Read the object one window at a time so large objects are never
+        // held in memory whole
+        tokio::spawn(async move {
+            let first = GetObjectResponse {
+                payload: Some(get_object_response::Payload::Info(info)),
+            };
+            if tx.send(Ok(first)).await.is_err() {
+                return;
+            }
+
+            while offset < end {
+                let last = (offset + OBJECT_STREAM_WINDOW).min(end) - 1;
+                let msg = match state
+                    .get_object_range(&tenant, &bucket, &key, offset, last)
+                    .await
+                {
+                    Ok(data) => Ok(GetObjectResponse {
+                        payload: Some(get_object_response::Payload::Data(data)),
+                    }),
+                    Err(e) => {
+                        error!(bucket = %bucket, key = %key, error = %e, "Object read failed");
+                        Err(s3_status(&e, &request_id))
+                    }
+                };
+                let failed = msg.is_err();
+                if tx.send(msg).await.is_err() {
+                    debug!("Client disconnected, stopping object stream");
+                    return;
+                }
+                if failed {
+                    return;
+                }
+                offset = last + 1;
+            }
+        });
+
+        let stream = ReceiverStream::new(rx);
+        Ok(Response::new(Box::pin(stream) as Self::GetObjectStream))
+    }
+
+    #[instrument(skip(self, request), fields(bucket))]
+    async fn list_objects(
+        &self,
+        request: Request<ListObjectsRequest>,
+    ) -> Result<Response<ListObjectsResponse>, Status> {
+        request.require_scope(scopes::S3_READ)?;
+        let request_id = grpc_request_id(&request);
+        let tenant = grpc_tenant(&request);
+        let req = request.into_inner();
+        tracing::Span::current().record("bucket", &req.bucket);
+
+        debug!(tenant = %tenant, bucket = %req.bucket, prefix = %req.prefix, "Listing objects");
+
+        let max_keys = match req.max_keys {
+            n if n <= 0 => 1000,
+            n => n.min(1000),
+        };
+        let token = Some(req.continuation_token.as_str()).filter(|t| !t.is_empty());
+        let metadata_filter = user_metadata_from_proto(req.metadata_filter);
+
+        let result = async {
+            self.require_bucket(&tenant, &req.bucket).await?;
+            self.state
+                .list_objects_matching(
+                    &tenant,
+                    &req.bucket,
+                    &req.prefix,
+                    None,
+                    max_keys,
+                    token,
+                    None,
+                    &metadata_filter,
+                )
+                .await
+        }
+        .await;
+        let (objects, is_truncated, next_token) = result.map_err(|e| s3_status(&e, &request_id))?;
+
+        Ok(Response::new(ListObjectsResponse {
+            objects: objects
+                .into_iter()
+                .map(|o| ObjectEntry {
+                    key: o.key,
+                    size: o.size,
+                    etag: o.etag,
+                    last_modified: o.last_modified,
+                    metadata: o.user_metadata.into_iter().collect(),
+                })
+                .collect(),
+            is_truncated,
+            next_continuation_token: next_token.unwrap_or_default(),
+        }))
+    }
+
+    #[instrument(skip(self, request), fields(bucket, key))]
+    async fn delete_object(
+        &self,
+        request: Request<DeleteObjectRequest>,
+    ) -> Result<Response<DeleteObjectResponse>, Status> {
+        request.require_scope(scopes::S3_WRITE)?;
+        let request_id = grpc_request_id(&request);
+        let tenant = grpc_tenant(&request);
+        let req = request.into_inner();
+        tracing::Span::current().record("bucket", &req.bucket);
+        tracing::Span::current().record("key", &req.key);
+
+        info!(tenant = %tenant, bucket = %req.bucket, key = %req.key, "Deleting object");
+
+        let result = async {
+            validate_object_key(&req.key)?;
+            self.require_bucket(&tenant, &req.bucket).await?;
+            self.state
+                .delete_object(&tenant, &req.bucket, &req.key, false)
+                .await
+        }
+        .await;
+        result.map_err(|e| s3_status(&e, &request_id))?;
+
+        Ok(Response::new(DeleteObjectResponse {}))
+    }
+}
+
+// =============================================================================
+// gRPC AUTHENTICATION LAYER
 // =============================================================================
 
 use crate::auth::{AuthService, Claims};
-use tonic::service::Interceptor;
+use std::time::{Duration, Instant};
+use tonic::codegen::{http, BoxFuture, Context, Poll, Service};
+
+/// How long a validated token is accepted without validating it again
+///
+/// Revocations made on this gateway take effect immediately; revocations
+/// made on another gateway are seen once the entry expires.
+const TOKEN_CACHE_TTL: Duration = Duration::from_secs(30);
+
+/// Most validated tokens kept in the cache
+const TOKEN_CACHE_CAPACITY: usize = 10_000;
+
+/// Validated token, cached by the hash of the token
+struct CachedToken {
+    claims: Claims,
+    expires_at: Instant,
+}
+
+/// Recently validated tokens, so the signature is not checked and Redis not
+/// asked about revocation on every call of a busy client
+struct TokenCache {
+    entries: std::sync::Mutex<HashMap<[u8; 32], CachedToken>>,
+    ttl: Duration,
+    capacity: usize,
+}
+
+impl TokenCache {
+    fn new(ttl: Duration, capacity: usize) -> Self {
+        Self {
+            entries: std::sync::Mutex::new(HashMap::new()),
+            ttl,
+            capacity,
+        }
+    }
+
+    fn key(token: &str) -> [u8; 32] {
+        *blake3::hash(token.as_bytes()).as_bytes()
+    }
+
+    /// Claims of a token validated less than the TTL ago
+    fn get(&self, token: &str) -> Option<Claims> {
+        let key = Self::key(token);
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        match entries.get(&key) {
+            Some(entry) if entry.expires_at > Instant::now() => Some(entry.claims.clone()),
+            Some(_) => {
+                entries.remove(&key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// Remember a validated token, never beyond its own expiry
+    fn insert(&self, token: &str, claims: &Claims) {
+        let remaining = claims.exp - chrono::Utc::now().timestamp();
+        if remaining <= 0 {
+            return;
+        }
+        let now = Instant::now();
+        let expires_at = now + self.ttl.min(Duration::from_secs(remaining as u64));
+
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        if entries.len() >= self.capacity {
+            entries.retain(|_, e| e.expires_at > now);
+            if entries.len() >= self.capacity {
+                entries.clear();
+            }
+        }
+        entries.insert(
+            Self::key(token),
+            CachedToken {
+                claims: claims.clone(),
+                expires_at,
+            },
+        );
+    }
+}
 
-/// gRPC authentication interceptor
+/// gRPC authentication layer
 ///
-/// Validates JWT tokens in the `authorization` metadata header.
-/// Adds validated claims to request extensions for use by service handlers.
+/// Validates JWT tokens in the `authorization` metadata header before the
+/// request reaches a service, and adds the validated claims to the request
+/// extensions for use by service handlers, which check the scope their
+/// method requires with [`RequestClaimsExt::require_scope`]. Validation is
+/// async and validated tokens are cached for [`TOKEN_CACHE_TTL`].
+///
+/// Methods on the skip list (node registration by default) can be called
+/// without a token; a token sent to them is still validated.
 #[derive(Clone)]
-pub struct AuthInterceptor {
+pub struct AuthLayer {
     auth: Arc<AuthService>,
-    /// Skip authentication for these methods (e.g., health checks)
-    skip_methods: Vec<String>,
+    /// Full paths of methods that can be called without a token
+    skip_methods: Arc<Vec<String>>,
+    cache: Arc<TokenCache>,
 }
 
-impl AuthInterceptor {
-    /// Create a new auth interceptor
+impl AuthLayer {
+    /// Create a new auth layer
     pub fn new(auth: Arc<AuthService>) -> Self {
         Self {
             auth,
-            skip_methods: vec![
+            skip_methods: Arc::new(vec![
                 // Allow unauthenticated node registration
+                // (RegisterNode is typically the first call from a node)
                 "/cyxcloud.node.NodeService/RegisterNode".to_string(),
-            ],
+            ]),
+            cache: Arc::new(TokenCache::new(TOKEN_CACHE_TTL, TOKEN_CACHE_CAPACITY)),
         }
     }
 
-    /// Add a method to skip authentication for
+    /// Add a method (full path, e.g. `/cyxcloud.node.NodeService/Heartbeat`)
+    /// to skip authentication for
     pub fn skip_method(mut self, method: impl Into<String>) -> Self {
-        self.skip_methods.push(method.into());
+        Arc::make_mut(&mut self.skip_methods).push(method.into());
         self
     }
-}
-
-impl Interceptor for AuthInterceptor {
-    fn call(&mut self, mut request: tonic::Request<()>) -> Result<tonic::Request<()>, Status> {
-        // Check if we have an authorization header
-        let auth_header = request.metadata().get("authorization");
 
-        // If no auth header and we allow some methods to skip, check the grpc-method header
-        if auth_header.is_none() {
-            // Try to get the method from a custom header (set by client if needed)
-            let method_name = request
-                .metadata()
-                .get("grpc-method")
-                .and_then(|v| v.to_str().ok())
-                .unwrap_or("");
-
-            // Check if method should skip auth
-            if self.skip_methods.iter().any(|m| method_name.contains(m)) {
-                return Ok(request);
+    /// Claims of the caller, or None for a skipped method called without a token
+    async fn authenticate(
+        &self,
+        path: &str,
+        authorization: Option<&http::HeaderValue>,
+    ) -> Result<Option<Claims>, Status> {
+        let Some(auth_header) = authorization else {
+            if self.skip_methods.iter().any(|m| m == path) {
+                return Ok(None);
             }
-
-            // For node registration, allow without auth
-            // (RegisterNode is typically the first call from a node)
             return Err(Status::unauthenticated("Missing authorization header"));
-        }
+        };
 
-        // Get authorization header
         let token = auth_header
-            .and_then(|v| v.to_str().ok())
+            .to_str()
+            .ok()
             .and_then(|s| s.strip_prefix("Bearer "))
             .ok_or_else(|| {
                 Status::unauthenticated("Invalid authorization format (expected 'Bearer <token>')")
             })?;
 
-        // Validate token synchronously (we can't use async in interceptor)
-        // For production, consider using a tower layer instead
-        let claims = validate_token_sync(token, &self.auth)?;
-
-        // Add claims to request extensions
-        request.extensions_mut().insert(claims);
+        if let Some(claims) = self.cache.get(token) {
+            if !self.auth.is_revoked_locally(&claims.jti).await {
+                return Ok(Some(claims));
+            }
+        }
 
-        Ok(request)
+        let claims = self
+            .auth
+            .validate_token(token)
+            .await
+            .map_err(|e| Status::unauthenticated(format!("Invalid token: {}", e)))?;
+        self.cache.insert(token, &claims);
+        Ok(Some(claims))
     }
 }
 
-/// Synchronous token validation for use in interceptor.
-/// Uses the existing Tokio runtime handle instead of creating a new one per request.
-fn validate_token_sync(token: &str, auth: &AuthService) -> Result<Claims, Status> {
-    let handle = tokio::runtime::Handle::try_current()
-        .map_err(|_| Status::internal("No Tokio runtime available"))?;
+impl<S> tower::Layer<S> for AuthLayer {
+    type Service = AuthMiddleware<S>;
 
-    tokio::task::block_in_place(|| {
-        handle.block_on(auth.validate_token(token))
-    })
-    .map_err(|e| Status::unauthenticated(format!("Invalid token: {}", e)))
+    fn layer(&self, inner: S) -> Self::Service {
+        AuthMiddleware {
+            inner,
+            layer: self.clone(),
+        }
+    }
 }
 
+/// Service wrapped by [`AuthLayer`]
+#[derive(Clone)]
+pub struct AuthMiddleware<S> {
+    inner: S,
+    layer: AuthLayer,
+}
+
+impl<S, B> Service<http::Request<B>> for AuthMiddleware<S>
+where
+    S: Service<http::Request<B>, Response = http::Response<tonic::body::BoxBody>>
+        + Clone
+        + Send
+        + 'static,
+    S::Future: Send + 'static,
+    B: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: http::Request<B>) -> Self::Future {
+        // The clone may not be ready; call the service that was polled
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let layer = self.layer.clone();
+
+        Box::pin(async move {
+            // Owned, so the request body need not be Sync across the await
+            let path = request.uri().path().to_string();
+            let authorization = request.headers().get(http::header::AUTHORIZATION).cloned();
+            match layer.authenticate(&path, authorization.as_ref()).await {
+                Ok(Some(claims)) => {
+                    request.extensions_mut().insert(claims);
+                }
+                Ok(None) => {}
+                Err(status) => return Ok(status.to_http()),
+            }
+            inner.call(request).await
+        })
+    }
+}
 /// Extension trait for extracting claims from request
 pub trait RequestClaimsExt {
     /// Get authenticated user claims from request
@@ -847,6 +1731,12 @@ pub trait RequestClaimsExt {
 
     /// Require authentication and return claims
     fn require_auth(&self) -> Result<&Claims, Status>;
+
+    /// Require the caller's token to grant `scope`
+    ///
+    /// Requests without claims pass; they only reach a handler when gRPC
+    /// authentication is disabled or skipped for the method.
+    fn require_scope(&self, scope: &str) -> Result<(), Status>;
 }
 
 impl<T> RequestClaimsExt for tonic::Request<T> {
@@ -858,6 +1748,16 @@ impl<T> RequestClaimsExt for tonic::Request<T> {
         self.claims()
             .ok_or_else(|| Status::unauthenticated("Authentication required"))
     }
+
+    fn require_scope(&self, scope: &str) -> Result<(), Status> {
+        match self.claims() {
+            Some(claims) if !claims.has_scope(scope) => Err(Status::permission_denied(format!(
+                "Token lacks the {} scope",
+                scope
+            ))),
+            _ => Ok(()),
+        }
+    }
 }
 
 // =============================================================================
@@ -899,9 +1799,277 @@ mod tests {
             user_type: "user".to_string(),
             wallet: None,
             permissions: vec!["read".to_string(), "write".to_string()],
+            org: None,
+            scopes: vec![],
         };
 
         assert_eq!(claims.sub, "user123");
         assert!(claims.permissions.contains(&"read".to_string()));
     }
+
+    #[tokio::test]
+    async fn test_stream_data_requires_caller() {
+        use super::*;
+
+        let service = DataServiceImpl::new(Arc::new(AppState::new()));
+        let request = Request::new(StreamDataRequest {
+            dataset_id: Uuid::new_v4().to_string(),
+            ..Default::default()
+        });
+        let result = service.stream_data(request).await;
+        assert_eq!(result.err().unwrap().code(), tonic::Code::Unauthenticated);
+    }
+
+    /// Service behind the auth layer, answering with the caller's subject
+    #[derive(Clone)]
+    struct Echo;
+
+    impl super::Service<super::http::Request<()>> for Echo {
+        type Response = super::http::Response<tonic::body::BoxBody>;
+        type Error = std::convert::Infallible;
+        type Future = std::future::Ready<Result<Self::Response, Self::Error>>;
+
+        fn poll_ready(
+            &mut self,
+            _: &mut super::Context<'_>,
+        ) -> super::Poll<Result<(), Self::Error>> {
+            super::Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, request: super::http::Request<()>) -> Self::Future {
+            let sub = request
+                .extensions()
+                .get::<Claims>()
+                .map_or(String::new(), |c| c.sub.clone());
+            std::future::ready(Ok(super::http::Response::builder()
+                .header("x-sub", sub)
+                .body(tonic::body::empty_body())
+                .unwrap()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_auth_layer() {
+        use super::*;
+        use crate::auth::{AuthConfig, TokenType};
+        use tower::Layer;
+
+        let auth = Arc::new(AuthService::new(AuthConfig::default()));
+        let mut service = AuthLayer::new(auth.clone()).layer(Echo);
+        let request = |path: &str, authorization: Option<&str>| {
+            let mut builder = http::Request::builder().uri(path);
+            if let Some(value) = authorization {
+                builder = builder.header("authorization", value);
+            }
+            builder.body(()).unwrap()
+        };
+        let grpc_status = |response: &http::Response<tonic::body::BoxBody>| {
+            response
+                .headers()
+                .get("grpc-status")
+                .map(|v| v.to_str().unwrap().to_string())
+        };
+
+        let heartbeat = "/cyxcloud.node.NodeService/Heartbeat";
+
+        // Skipped methods need no token, others do
+        let response = service
+            .call(request("/cyxcloud.node.NodeService/RegisterNode", None))
+            .await
+            .unwrap();
+        assert_eq!(grpc_status(&response), None);
+        let response = service.call(request(heartbeat, None)).await.unwrap();
+        assert_eq!(grpc_status(&response).as_deref(), Some("16"));
+        let response = service
+            .call(request(heartbeat, Some("Basic abc")))
+            .await
+            .unwrap();
+        assert_eq!(grpc_status(&response).as_deref(), Some("16"));
+
+        let token = auth
+            .generate_token("node-1", TokenType::Node, None, vec![])
+            .unwrap();
+        let bearer = format!("Bearer {}", token);
+        for _ in 0..2 {
+            let response = service
+                .call(request(heartbeat, Some(&bearer)))
+                .await
+                .unwrap();
+            assert_eq!(grpc_status(&response), None);
+            assert_eq!(response.headers()["x-sub"], "node-1");
+        }
+
+        // A cached token is rejected as soon as it is revoked here
+        let claims = auth.validate_token(&token).await.unwrap();
+        auth.revoke_token(&claims.jti).await;
+        let response = service
+            .call(request(heartbeat, Some(&bearer)))
+            .await
+            .unwrap();
+        assert_eq!(grpc_status(&response).as_deref(), Some("16"));
+    }
+
+    #[test]
+    fn test_token_cache() {
+        use super::*;
+
+        let auth = AuthService::new(crate::auth::AuthConfig::default());
+        let (_, claims) = auth
+            .generate_service_token("ci", vec![scopes::S3_READ.to_string()], None, 60)
+            .unwrap();
+
+        let cache = TokenCache::new(Duration::from_secs(60), 1);
+        cache.insert("a", &claims);
+        assert_eq!(cache.get("a").unwrap().jti, claims.jti);
+        assert!(cache.get("b").is_none());
+
+        // A full cache makes room for the newest token
+        cache.insert("b", &claims);
+        assert!(cache.get("a").is_none());
+        assert!(cache.get("b").is_some());
+
+        // Entries expire with the TTL and never outlive the token
+        let cache = TokenCache::new(Duration::ZERO, 10);
+        cache.insert("a", &claims);
+        assert!(cache.get("a").is_none());
+        let expired = Claims { exp: 0, ..claims };
+        let cache = TokenCache::new(Duration::from_secs(60), 10);
+        cache.insert("a", &expired);
+        assert!(cache.get("a").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_object_service_requires_scope() {
+        use super::*;
+        use crate::auth::{AuthConfig, AuthService};
+
+        let auth = AuthService::new(AuthConfig::default());
+        let (_, claims) = auth
+            .generate_service_token("trainer", vec![scopes::S3_READ.to_string()], None, 60)
+            .unwrap();
+        let service = ObjectServiceImpl::new(Arc::new(AppState::new()), 1024);
+
+        let mut request = Request::new(CreateBucketRequest {
+            bucket: "models".to_string(),
+        });
+        request.extensions_mut().insert(claims.clone());
+        let denied = service.create_bucket(request).await;
+        assert_eq!(denied.unwrap_err().code(), tonic::Code::PermissionDenied);
+
+        let mut request = Request::new(ListObjectsRequest {
+            bucket: "models".to_string(),
+            ..Default::default()
+        });
+        request.extensions_mut().insert(claims);
+        let listing = service.list_objects(request).await;
+        assert_eq!(listing.unwrap_err().code(), tonic::Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_object_service_round_trip() {
+        use super::*;
+        use tokio_stream::StreamExt;
+
+        let state = Arc::new(AppState::new());
+        let service = ObjectServiceImpl::new(state.clone(), 1024 * 1024);
+
+        service
+            .create_bucket(Request::new(CreateBucketRequest {
+                bucket: "models".to_string(),
+            }))
+            .await
+            .unwrap();
+        let duplicate = service
+            .create_bucket(Request::new(CreateBucketRequest {
+                bucket: "models".to_string(),
+            }))
+            .await;
+        assert_eq!(duplicate.unwrap_err().code(), tonic::Code::AlreadyExists);
+
+        let metadata = UserMetadata::from([("stage".to_string(), "prod".to_string())]);
+        state
+            .put_object_if(
+                DEFAULT_TENANT,
+                "models",
+                "weights.bin",
+                bytes::Bytes::from_static(b"0123456789"),
+                "application/octet-stream",
+                None,
+                &metadata,
+                &Default::default(),
+                None,
+                None,
+                |_| Ok(()),
+            )
+            .await
+            .unwrap();
+        state
+            .put_object(
+                DEFAULT_TENANT,
+                "models",
+                "notes.txt",
+                bytes::Bytes::from_static(b"draft"),
+                "text/plain",
+                None,
+            )
+            .await
+            .unwrap();
+
+        let listing = service
+            .list_objects(Request::new(ListObjectsRequest {
+                bucket: "models".to_string(),
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(listing.objects.len(), 2);
+
+        // Filter names are matched case-insensitively, values exactly
+        let filtered = service
+            .list_objects(Request::new(ListObjectsRequest {
+                bucket: "models".to_string(),
+                metadata_filter: HashMap::from([("Stage".to_string(), "prod".to_string())]),
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(filtered.objects.len(), 1);
+        assert_eq!(filtered.objects[0].key, "weights.bin");
+        assert_eq!(filtered.objects[0].metadata["stage"], "prod");
+
+        let mut stream = service
+            .get_object(Request::new(GetObjectRequest {
+                bucket: "models".to_string(),
+                key: "weights.bin".to_string(),
+                offset: 2,
+                length: 5,
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        let mut data = Vec::new();
+        while let Some(msg) = stream.next().await {
+            match msg.unwrap().payload {
+                Some(get_object_response::Payload::Info(info)) => {
+                    assert_eq!(info.size, 10);
+                    assert_eq!(info.metadata["stage"], "prod");
+                }
+                Some(get_object_response::Payload::Data(chunk)) => data.extend_from_slice(&chunk),
+                None => {}
+            }
+        }
+        assert_eq!(data, b"23456");
+
+        let not_empty = service
+            .delete_bucket(Request::new(DeleteBucketRequest {
+                bucket: "models".to_string(),
+            }))
+            .await;
+        assert_eq!(
+            not_empty.unwrap_err().code(),
+            tonic::Code::FailedPrecondition
+        );
+    }
 }
@@ -0,0 +1,436 @@
+//! Liveness and readiness probes
+//!
+//! - `GET /healthz` (and the older `GET /health`): the process is up and
+//!   serving HTTP. Never checks dependencies, so an orchestrator does not
+//!   restart a gateway just because Postgres is down.
+//! - `GET /readyz`: the gateway can serve traffic. Checks the metadata
+//!   database (and its read replica), Redis and the number of online
+//!   storage nodes, and answers 503 with the per-dependency report when a
+//!   required one fails, so load balancers stop routing to it. A gateway
+//!   whose primary is down but whose replica answers stays ready in
+//!   read-only mode and reports the database as `degraded`. The startup
+//!   preflight is reported too, but a gateway still waiting for it serves
+//!   reads and stays ready.
+//!
+//! Neither endpoint requires authentication.
+
+use crate::AppState;
+use axum::{
+    extract::{Json, State},
+    http::StatusCode,
+    response::IntoResponse,
+    routing::get,
+    Router,
+};
+use redis::aio::MultiplexedConnection;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+/// Readiness check configuration
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReadinessConfig {
+    /// Online storage nodes needed to be ready (metadata mode only)
+    pub min_online_nodes: usize,
+    /// Whether an unreachable Redis makes the gateway not ready
+    pub require_redis: bool,
+    /// Time limit of each dependency check
+    pub check_timeout: Duration,
+}
+
+impl Default for ReadinessConfig {
+    fn default() -> Self {
+        Self {
+            min_online_nodes: 1,
+            require_redis: false,
+            check_timeout: Duration::from_secs(2),
+        }
+    }
+}
+
+impl ReadinessConfig {
+    /// Defaults overridden by `HEALTH_MIN_ONLINE_NODES`,
+    /// `HEALTH_REQUIRE_REDIS` and `HEALTH_CHECK_TIMEOUT_MS`
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            min_online_nodes: std::env::var("HEALTH_MIN_ONLINE_NODES")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.min_online_nodes),
+            require_redis: std::env::var("HEALTH_REQUIRE_REDIS")
+                .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+                .unwrap_or(defaults.require_redis),
+            check_timeout: std::env::var("HEALTH_CHECK_TIMEOUT_MS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .map(Duration::from_millis)
+                .unwrap_or(defaults.check_timeout),
+        }
+    }
+}
+
+/// Dependencies the readiness probe checks, captured at startup
+pub struct ReadinessProbe {
+    config: ReadinessConfig,
+    /// Whether a metadata database was configured (a gateway that fell back
+    /// to memory storage after failing to connect is not ready)
+    database_configured: bool,
+    /// None when no Redis URL is configured, Err when connecting failed
+    redis: Option<Result<MultiplexedConnection, String>>,
+}
+
+impl ReadinessProbe {
+    /// Create a probe for the dependencies the gateway was started with
+    pub fn new(
+        config: ReadinessConfig,
+        database_configured: bool,
+        redis: Option<Result<MultiplexedConnection, String>>,
+    ) -> Self {
+        Self {
+            config,
+            database_configured,
+            redis,
+        }
+    }
+
+    /// Probe for a gateway running on memory storage without Redis
+    pub fn memory() -> Self {
+        Self::new(ReadinessConfig::from_env(), false, None)
+    }
+
+    /// Check every dependency concurrently
+    pub async fn check(&self, state: &AppState) -> Readiness {
+        let (database, replica, redis, nodes) = tokio::join!(
+            self.check_database(state),
+            self.check_replica(state),
+            self.check_redis(),
+            self.check_nodes(state),
+        );
+
+        Readiness::from_checks(BTreeMap::from([
+            ("database", database),
+            ("database_replica", replica),
+            ("redis", redis),
+            ("nodes", nodes),
+            ("preflight", Self::check_preflight(state)),
+        ]))
+    }
+
+    fn check_preflight(state: &AppState) -> DependencyCheck {
+        let gate = state.preflight();
+        if gate.is_open() {
+            return DependencyCheck {
+                status: CheckStatus::Up,
+                required: false,
+                latency_ms: None,
+                detail: None,
+            };
+        }
+        let failures = gate.failures();
+        let detail = if failures.is_empty() {
+            "running, S3 writes refused".to_string()
+        } else {
+            format!("S3 writes refused: {}", failures.join("; "))
+        };
+        DependencyCheck::down(false, detail)
+    }
+
+    async fn check_database(&self, state: &AppState) -> DependencyCheck {
+        match state.metadata_service() {
+            Some(meta) => {
+                let db = meta.database();
+                let mut check = timed(self.config.check_timeout, true, async {
+                    db.ping().await.map(|()| None).map_err(|e| e.to_string())
+                })
+                .await;
+                if check.status == CheckStatus::Down && db.replica_available() {
+                    check.status = CheckStatus::Degraded;
+                    check.detail = Some(format!(
+                        "primary: {}; serving reads from the replica, writes refused",
+                        check.detail.unwrap_or_default()
+                    ));
+                }
+                check
+            }
+            None if self.database_configured => {
+                DependencyCheck::down(true, "not connected, running on memory storage".to_string())
+            }
+            None => DependencyCheck::disabled("memory storage"),
+        }
+    }
+
+    async fn check_replica(&self, state: &AppState) -> DependencyCheck {
+        let Some(db) = state.metadata_service().map(|meta| meta.database()) else {
+            return DependencyCheck::disabled("memory storage");
+        };
+        if !db.has_replica() {
+            return DependencyCheck::disabled("no read replica configured");
+        }
+
+        timed(self.config.check_timeout, false, async {
+            match db.ping_replica().await {
+                Some(result) => result.map(|()| None).map_err(|e| e.to_string()),
+                None => Ok(None),
+            }
+        })
+        .await
+    }
+
+    /// Ping Redis (also used by the startup preflight)
+    pub(crate) async fn check_redis(&self) -> DependencyCheck {
+        let required = self.config.require_redis;
+        match &self.redis {
+            Some(Ok(conn)) => {
+                let mut conn = conn.clone();
+                timed(self.config.check_timeout, required, async move {
+                    let _: String = redis::cmd("PING")
+                        .query_async(&mut conn)
+                        .await
+                        .map_err(|e| e.to_string())?;
+                    Ok(None)
+                })
+                .await
+            }
+            Some(Err(e)) => DependencyCheck::down(required, format!("not connected: {}", e)),
+            None => DependencyCheck::disabled("no Redis URL configured"),
+        }
+    }
+
+    async fn check_nodes(&self, state: &AppState) -> DependencyCheck {
+        let Some(meta) = state.metadata_service() else {
+            return DependencyCheck::disabled("memory storage");
+        };
+        let min_online = self.config.min_online_nodes;
+
+        timed(self.config.check_timeout, true, async {
+            let counts = meta
+                .database()
+                .count_nodes_by_status()
+                .await
+                .map_err(|e| e.to_string())?;
+            let online = counts.get("online").copied().unwrap_or(0).max(0) as usize;
+            let detail = format!("{} online, {} required", online, min_online);
+            if online < min_online {
+                Err(detail)
+            } else {
+                Ok(Some(detail))
+            }
+        })
+        .await
+    }
+}
+
+/// Run a check with a time limit and measure its latency
+///
+/// The check returns an optional detail on success and the failure reason
+/// otherwise.
+async fn timed<F>(limit: Duration, required: bool, check: F) -> DependencyCheck
+where
+    F: Future<Output = Result<Option<String>, String>>,
+{
+    let start = Instant::now();
+    let outcome = tokio::time::timeout(limit, check).await;
+    let latency_ms = Some(start.elapsed().as_millis() as u64);
+
+    let (status, detail) = match outcome {
+        Ok(Ok(detail)) => (CheckStatus::Up, detail),
+        Ok(Err(e)) => (CheckStatus::Down, Some(e)),
+        Err(_) => (
+            CheckStatus::Down,
+            Some(format!("timed out after {}ms", limit.as_millis())),
+        ),
+    };
+    DependencyCheck {
+        status,
+        required,
+        latency_ms,
+        detail,
+    }
+}
+
+/// Outcome of one dependency check
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckStatus {
+    Up,
+    Down,
+    /// Down, but the gateway keeps serving from a fallback (the database
+    /// primary is unreachable and reads go to the replica)
+    Degraded,
+    /// Not used in this deployment (e.g. memory storage has no database)
+    Disabled,
+}
+
+/// Status of one dependency
+#[derive(Debug, Clone, Serialize)]
+pub struct DependencyCheck {
+    pub status: CheckStatus,
+    /// Whether the gateway is not ready while this dependency is down
+    pub required: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<u64>,
+    /// Failure reason, or extra information such as node counts
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+impl DependencyCheck {
+    fn down(required: bool, reason: String) -> Self {
+        Self {
+            status: CheckStatus::Down,
+            required,
+            latency_ms: None,
+            detail: Some(reason),
+        }
+    }
+
+    fn disabled(reason: &str) -> Self {
+        Self {
+            status: CheckStatus::Disabled,
+            required: false,
+            latency_ms: None,
+            detail: Some(reason.to_string()),
+        }
+    }
+
+    /// Whether this check keeps the gateway from being ready
+    fn is_blocking(&self) -> bool {
+        self.required && self.status == CheckStatus::Down
+    }
+}
+
+/// Readiness report returned by `/readyz`
+#[derive(Debug, Serialize)]
+pub struct Readiness {
+    pub ready: bool,
+    pub checks: BTreeMap<&'static str, DependencyCheck>,
+}
+
+impl Readiness {
+    fn from_checks(checks: BTreeMap<&'static str, DependencyCheck>) -> Self {
+        Self {
+            ready: !checks.values().any(DependencyCheck::is_blocking),
+            checks,
+        }
+    }
+}
+
+/// Liveness response
+#[derive(Debug, Serialize)]
+pub struct Liveness {
+    pub status: &'static str,
+    pub version: &'static str,
+}
+
+/// Create health routes
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/health", get(health))
+        .route("/healthz", get(liveness))
+        .route("/readyz", get(readiness))
+}
+
+/// GET /health - Plain-text liveness (kept for existing probes)
+async fn health() -> &'static str {
+    "OK"
+}
+
+/// GET /healthz - Liveness
+async fn liveness() -> Json<Liveness> {
+    Json(Liveness {
+        status: "ok",
+        version: env!("CARGO_PKG_VERSION"),
+    })
+}
+
+/// GET /readyz - Readiness with per-dependency status
+async fn readiness(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    let report = state.readiness().check(&state).await;
+
+    let status = if report.ready {
+        StatusCode::OK
+    } else {
+        let failing: Vec<_> = report
+            .checks
+            .iter()
+            .filter(|(_, check)| check.is_blocking())
+            .map(|(name, _)| *name)
+            .collect();
+        warn!(failing = ?failing, "Gateway not ready");
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    (status, Json(report))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn up(required: bool) -> DependencyCheck {
+        DependencyCheck {
+            status: CheckStatus::Up,
+            required,
+            latency_ms: Some(1),
+            detail: None,
+        }
+    }
+
+    #[test]
+    fn test_readiness_from_checks() {
+        let ready = Readiness::from_checks(BTreeMap::from([
+            ("database", up(true)),
+            ("redis", DependencyCheck::down(false, "refused".to_string())),
+            ("nodes", DependencyCheck::disabled("memory storage")),
+        ]));
+        assert!(ready.ready);
+
+        let not_ready = Readiness::from_checks(BTreeMap::from([
+            (
+                "database",
+                DependencyCheck::down(true, "refused".to_string()),
+            ),
+            ("redis", up(false)),
+        ]));
+        assert!(!not_ready.ready);
+
+        // Degraded dependencies keep the gateway ready
+        let mut degraded = up(true);
+        degraded.status = CheckStatus::Degraded;
+        assert!(Readiness::from_checks(BTreeMap::from([("database", degraded)])).ready);
+
+        let json = serde_json::to_value(&not_ready).unwrap();
+        assert_eq!(json["checks"]["database"]["status"], "down");
+        assert_eq!(json["checks"]["database"]["detail"], "refused");
+        assert!(json["checks"]["redis"].get("detail").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_timed_check() {
+        let check = timed(Duration::from_secs(1), true, async { Ok(None) }).await;
+        assert_eq!(check.status, CheckStatus::Up);
+        assert!(check.latency_ms.is_some());
+
+        let check = timed(Duration::from_millis(10), true, async {
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            Ok(None)
+        })
+        .await;
+        assert_eq!(check.status, CheckStatus::Down);
+        assert!(check.is_blocking());
+    }
+
+    #[tokio::test]
+    async fn test_memory_mode_is_ready() {
+        let state = AppState::new();
+        let report = state.readiness().check(&state).await;
+        assert!(report.ready);
+        assert_eq!(report.checks["database"].status, CheckStatus::Disabled);
+        assert_eq!(report.checks["nodes"].status, CheckStatus::Disabled);
+        assert_eq!(report.checks["preflight"].status, CheckStatus::Up);
+    }
+}
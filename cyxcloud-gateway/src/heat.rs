@@ -0,0 +1,295 @@
+//! Object access heat
+//!
+//! Object downloads are sampled (`sample_rate` of them) and counted per
+//! object in memory, each sampled download standing for `1 / sample_rate`.
+//! The heat daemon flushes the counts to the `object_heat` table, where
+//! several gateways add up into the same rows, so a download costs no
+//! database write of its own.
+//!
+//! Besides its estimated access count and last access, every object keeps a
+//! heat score that halves every `half_life` without downloads. The hottest
+//! objects of a bucket are listed by `GET /api/v1/stats/{bucket}/hot`, the
+//! basis for tiering, cache warming and archiving cold buckets.
+//!
+//! Heat is only stored with a metadata service; without one, counts are
+//! dropped at each flush.
+
+use crate::state::AppState;
+use chrono::{DateTime, Utc};
+use cyxcloud_metadata::ObjectHeat;
+use rand::Rng;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio::time::interval;
+use tracing::{debug, info, warn};
+
+/// Objects counted between flushes before further objects are dropped
+const MAX_PENDING_OBJECTS: usize = 100_000;
+
+/// Access heat configuration
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeatConfig {
+    /// Count object downloads
+    pub enabled: bool,
+    /// Fraction of downloads counted (0-1)
+    pub sample_rate: f64,
+    /// How often counts are written to the database
+    pub flush_interval: Duration,
+    /// Time without downloads that halves an object's heat
+    pub half_life: Duration,
+}
+
+impl Default for HeatConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            sample_rate: 0.1,
+            flush_interval: Duration::from_secs(60),
+            half_life: Duration::from_secs(24 * 60 * 60),
+        }
+    }
+}
+
+impl HeatConfig {
+    /// Create configuration from environment variables
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let secs = |name: &str, default: Duration| {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|&v: &u64| v > 0)
+                .map(Duration::from_secs)
+                .unwrap_or(default)
+        };
+
+        Self {
+            enabled: std::env::var("HEAT_TRACKING_ENABLED")
+                .map(|v| v == "true" || v == "1")
+                .unwrap_or(defaults.enabled),
+            sample_rate: std::env::var("HEAT_SAMPLE_RATE")
+                .ok()
+                .and_then(|v| v.parse::<f64>().ok())
+                .filter(|v| *v > 0.0 && *v <= 1.0)
+                .unwrap_or(defaults.sample_rate),
+            flush_interval: secs("HEAT_FLUSH_SECS", defaults.flush_interval),
+            half_life: secs("HEAT_HALF_LIFE_SECS", defaults.half_life),
+        }
+    }
+}
+
+/// Tenant, bucket and key of an object
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct ObjectKey {
+    tenant: String,
+    bucket: String,
+    key: String,
+}
+
+/// Downloads of one object since the last flush
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Hits {
+    /// Estimated downloads (sampled ones scaled up)
+    count: f64,
+    last: DateTime<Utc>,
+}
+
+/// Per-object download counters of this gateway
+pub struct HeatTracker {
+    config: HeatConfig,
+    /// Counts not yet flushed
+    pending: Mutex<HashMap<ObjectKey, Hits>>,
+}
+
+impl HeatTracker {
+    /// Create a heat tracker
+    pub fn new(config: HeatConfig) -> Self {
+        Self {
+            config,
+            pending: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Heat configuration
+    pub fn config(&self) -> &HeatConfig {
+        &self.config
+    }
+
+    /// Count a download of an object, if it is sampled
+    pub fn record(&self, tenant: &str, bucket: &str, key: &str) {
+        if !self.config.enabled {
+            return;
+        }
+        if self.config.sample_rate < 1.0
+            && rand::thread_rng().gen::<f64>() >= self.config.sample_rate
+        {
+            return;
+        }
+        self.record_at(
+            tenant,
+            bucket,
+            key,
+            1.0 / self.config.sample_rate,
+            Utc::now(),
+        );
+    }
+
+    fn record_at(&self, tenant: &str, bucket: &str, key: &str, count: f64, time: DateTime<Utc>) {
+        let object = ObjectKey {
+            tenant: tenant.to_string(),
+            bucket: bucket.to_string(),
+            key: key.to_string(),
+        };
+        let mut pending = self.pending.lock().expect("heat lock poisoned");
+        if let Some(hits) = pending.get_mut(&object) {
+            hits.count += count;
+            hits.last = hits.last.max(time);
+        } else if pending.len() < MAX_PENDING_OBJECTS {
+            pending.insert(object, Hits { count, last: time });
+        } else {
+            crate::metrics::record_heat_dropped();
+        }
+    }
+
+    /// Write pending counts to the database
+    ///
+    /// Returns the number of objects written. Counts that could not be
+    /// written are kept for the next flush.
+    pub async fn flush(&self, state: &AppState) -> usize {
+        let pending = std::mem::take(&mut *self.pending.lock().expect("heat lock poisoned"));
+        if pending.is_empty() {
+            return 0;
+        }
+        let Some(meta) = state.metadata_service() else {
+            return 0;
+        };
+
+        let rows: Vec<ObjectHeat> = pending
+            .iter()
+            .map(|(object, hits)| ObjectHeat {
+                tenant_id: object.tenant.clone(),
+                bucket: object.bucket.clone(),
+                object_key: object.key.clone(),
+                access_count: hits.count.round() as i64,
+                heat: hits.count,
+                last_accessed_at: hits.last,
+            })
+            .collect();
+        match meta
+            .database()
+            .record_object_heat(&rows, self.config.half_life.as_secs_f64())
+            .await
+        {
+            Ok(()) => rows.len(),
+            Err(e) => {
+                warn!(error = %e, objects = rows.len(), "Failed to record object heat, retrying next flush");
+                for (object, hits) in pending {
+                    self.record_at(
+                        &object.tenant,
+                        &object.bucket,
+                        &object.key,
+                        hits.count,
+                        hits.last,
+                    );
+                }
+                0
+            }
+        }
+    }
+}
+
+/// Heat daemon flushing download counts
+pub struct HeatDaemon {
+    config: HeatConfig,
+}
+
+impl HeatDaemon {
+    /// Create a new heat daemon
+    pub fn new(config: HeatConfig) -> Self {
+        Self { config }
+    }
+
+    /// Start the flush loop (background task)
+    pub fn start(self: Arc<Self>, state: Arc<AppState>) -> JoinHandle<()> {
+        let daemon = self;
+
+        tokio::spawn(async move {
+            let mut timer = interval(daemon.config.flush_interval);
+            timer.tick().await;
+
+            info!(
+                interval_secs = daemon.config.flush_interval.as_secs(),
+                sample_rate = daemon.config.sample_rate,
+                "Object heat daemon started"
+            );
+
+            loop {
+                timer.tick().await;
+                let objects = state.heat_tracker().flush(&state).await;
+                if objects > 0 {
+                    debug!(objects, "Object heat flushed");
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_record_adds_up_per_object() {
+        let tracker = HeatTracker::new(HeatConfig::default());
+        tracker.record_at("acme", "logs", "a.txt", 10.0, time("2026-10-15T13:05:00Z"));
+        tracker.record_at("acme", "logs", "a.txt", 10.0, time("2026-10-15T13:01:00Z"));
+        tracker.record_at("acme", "logs", "b.txt", 10.0, time("2026-10-15T13:02:00Z"));
+        tracker.record_at("other", "logs", "a.txt", 10.0, time("2026-10-15T13:03:00Z"));
+
+        let pending = tracker.pending.lock().unwrap();
+        assert_eq!(pending.len(), 3);
+        let a = pending
+            .get(&ObjectKey {
+                tenant: "acme".to_string(),
+                bucket: "logs".to_string(),
+                key: "a.txt".to_string(),
+            })
+            .unwrap();
+        assert_eq!(a.count, 20.0);
+        assert_eq!(a.last, time("2026-10-15T13:05:00Z"));
+    }
+
+    #[test]
+    fn test_disabled_and_full_sampling() {
+        let tracker = HeatTracker::new(HeatConfig {
+            enabled: false,
+            ..Default::default()
+        });
+        tracker.record("acme", "logs", "a.txt");
+        assert!(tracker.pending.lock().unwrap().is_empty());
+
+        let tracker = HeatTracker::new(HeatConfig {
+            sample_rate: 1.0,
+            ..Default::default()
+        });
+        for _ in 0..3 {
+            tracker.record("acme", "logs", "a.txt");
+        }
+        let pending = tracker.pending.lock().unwrap();
+        assert_eq!(pending.values().next().unwrap().count, 3.0);
+    }
+
+    #[test]
+    fn test_config_default() {
+        let config = HeatConfig::default();
+        assert!(config.enabled);
+        assert_eq!(config.sample_rate, 0.1);
+        assert_eq!(config.half_life, Duration::from_secs(86400));
+    }
+}
@@ -0,0 +1,936 @@
+//! Key Management
+//!
+//! Encryption at rest with envelope encryption. Every encrypted object
+//! version is sealed with a random data key of its own before it is erasure
+//! coded, so storage nodes only ever hold ciphertext, and the file record
+//! keeps that data key wrapped by a master key. Master keys stay inside a
+//! [`KeyProvider`]:
+//!
+//! - [`LocalKeyring`][]: a key from `GATEWAY_KMS_MASTER_KEY`, or a keyring file
+//!   (`GATEWAY_KMS_KEYRING_FILE`) holding the current and retired keys
+//! - [`VaultTransit`][]: the transit secrets engine of HashiCorp Vault
+//!
+//! Objects are sealed in segments of [`SEGMENT_SIZE`] bytes, each with
+//! AES-256-GCM and a nonce bound to its position, so a range read only opens
+//! the segments it covers. Rotating the master key re-wraps the data keys
+//! (`POST /api/v1/admin/kms/rotate`) without rewriting any object data; a retired
+//! master key must stay available until that has finished.
+//!
+//! A PUT asks for encryption with `x-amz-server-side-encryption` (`AES256`
+//! and `aws:kms` are handled alike), and `GATEWAY_SSE_DEFAULT` encrypts every
+//! new object. GET and HEAD decrypt transparently.
+
+use async_trait::async_trait;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use bytes::{Bytes, BytesMut};
+use cyxcloud_core::crypto::{
+    decrypt_from_bytes, encrypt_to_bytes, open_segment, seal_segment, EncryptionKey, TAG_SIZE,
+};
+use cyxcloud_core::error::{ErrorCode, HasErrorCode};
+use cyxcloud_metadata::FileEncryption;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
+use tracing::{info, warn};
+
+/// Plaintext bytes per sealed segment
+pub const SEGMENT_SIZE: usize = 64 * 1024;
+
+/// Stored bytes per full sealed segment
+const SEALED_SEGMENT_SIZE: u64 = (SEGMENT_SIZE + TAG_SIZE) as u64;
+
+/// Key management errors
+#[derive(Error, Debug)]
+pub enum KmsError {
+    #[error("Invalid KMS configuration: {0}")]
+    Config(String),
+
+    #[error("Unknown master key: {0}")]
+    UnknownKey(String),
+
+    #[error("Key provider request failed: {0}")]
+    Provider(String),
+
+    #[error("The {0} key provider cannot rotate its master key")]
+    RotationUnsupported(&'static str),
+
+    #[error("Decryption failed: {0}")]
+    Decryption(String),
+}
+
+impl HasErrorCode for KmsError {
+    fn error_code(&self) -> ErrorCode {
+        match self {
+            KmsError::Config(_) | KmsError::UnknownKey(_) => ErrorCode::Internal,
+            KmsError::Provider(_) => ErrorCode::ServiceUnavailable,
+            KmsError::RotationUnsupported(_) => ErrorCode::InvalidArgument,
+            KmsError::Decryption(_) => ErrorCode::IntegrityError,
+        }
+    }
+}
+
+pub type KmsResult<T> = Result<T, KmsError>;
+
+/// S3 server-side encryption algorithm
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SseAlgorithm {
+    /// `AES256` (SSE-S3)
+    Aes256,
+    /// `aws:kms` (SSE-KMS), with the gateway's master key
+    AwsKms,
+}
+
+impl SseAlgorithm {
+    /// Value of the `x-amz-server-side-encryption` header
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SseAlgorithm::Aes256 => "AES256",
+            SseAlgorithm::AwsKms => "aws:kms",
+        }
+    }
+}
+
+impl std::str::FromStr for SseAlgorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "AES256" => Ok(SseAlgorithm::Aes256),
+            "aws:kms" => Ok(SseAlgorithm::AwsKms),
+            other => Err(format!("unsupported server-side encryption: {}", other)),
+        }
+    }
+}
+
+/// Data key wrapped by a master key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WrappedKey {
+    /// Master key (and version) that wrapped the data key
+    pub key_id: String,
+    pub ciphertext: Vec<u8>,
+}
+
+/// How an object version is encrypted at rest
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ObjectEncryption {
+    pub algorithm: SseAlgorithm,
+    pub data_key: WrappedKey,
+}
+
+impl ObjectEncryption {
+    /// Encryption recorded in a file record
+    pub fn from_file(file: &FileEncryption) -> KmsResult<Self> {
+        Ok(Self {
+            algorithm: file.algorithm.parse().map_err(KmsError::Decryption)?,
+            data_key: WrappedKey {
+                key_id: file.key_id.clone(),
+                ciphertext: file.data_key.clone(),
+            },
+        })
+    }
+
+    /// Encryption to record in a file record
+    pub fn to_file(&self) -> FileEncryption {
+        FileEncryption {
+            algorithm: self.algorithm.as_str().to_string(),
+            key_id: self.data_key.key_id.clone(),
+            data_key: self.data_key.ciphertext.clone(),
+        }
+    }
+}
+
+/// Holder of the master keys that wrap data keys
+///
+/// Providers never hand out master keys; they only wrap and unwrap data
+/// keys with them.
+#[async_trait]
+pub trait KeyProvider: Send + Sync {
+    /// Short name for logs
+    fn name(&self) -> &'static str;
+
+    /// ID of the master key new data keys are wrapped with
+    async fn current_key_id(&self) -> KmsResult<String>;
+
+    /// Wrap a data key with the current master key
+    async fn wrap(&self, data_key: &EncryptionKey) -> KmsResult<WrappedKey>;
+
+    /// Unwrap a data key wrapped by this provider
+    async fn unwrap(&self, wrapped: &WrappedKey) -> KmsResult<EncryptionKey>;
+
+    /// Wrap a data key again with the current master key
+    async fn rewrap(&self, wrapped: &WrappedKey) -> KmsResult<WrappedKey> {
+        self.wrap(&self.unwrap(wrapped).await?).await
+    }
+
+    /// Create a new master key and make it current, returning its ID
+    async fn rotate_master_key(&self) -> KmsResult<String> {
+        Err(KmsError::RotationUnsupported(self.name()))
+    }
+}
+
+/// Master keys held by the gateway itself
+///
+/// Rotation means adding a new current key to the keyring file and keeping
+/// the old ones until every data key has been re-wrapped.
+pub struct LocalKeyring {
+    current: String,
+    keys: HashMap<String, EncryptionKey>,
+}
+
+/// Keyring file: the current key's ID and base64 keys by ID
+#[derive(Deserialize)]
+struct KeyringFile {
+    current: String,
+    keys: HashMap<String, String>,
+}
+
+impl LocalKeyring {
+    /// Create a keyring wrapping with key `current`
+    pub fn new(
+        current: impl Into<String>,
+        keys: HashMap<String, EncryptionKey>,
+    ) -> KmsResult<Self> {
+        let current = current.into();
+        if !keys.contains_key(&current) {
+            return Err(KmsError::Config(format!(
+                "current master key {} is not in the keyring",
+                current
+            )));
+        }
+        Ok(Self { current, keys })
+    }
+
+    /// Keyring of a single base64 master key
+    pub fn single(key_id: &str, key: &str) -> KmsResult<Self> {
+        Self::new(
+            key_id,
+            HashMap::from([(key_id.to_string(), parse_key(key_id, key)?)]),
+        )
+    }
+
+    /// Load a keyring file
+    ///
+    /// ```toml
+    /// current = "2026-10"
+    ///
+    /// [keys]
+    /// "2026-01" = "<base64 of 32 bytes>"
+    /// "2026-10" = "<base64 of 32 bytes>"
+    /// ```
+    pub fn from_file(path: &Path) -> KmsResult<Self> {
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            KmsError::Config(format!("failed to read keyring {}: {}", path.display(), e))
+        })?;
+        let file: KeyringFile = toml::from_str(&content).map_err(|e| {
+            KmsError::Config(format!("failed to parse keyring {}: {}", path.display(), e))
+        })?;
+        let keys = file
+            .keys
+            .iter()
+            .map(|(id, key)| Ok((id.clone(), parse_key(id, key)?)))
+            .collect::<KmsResult<HashMap<_, _>>>()?;
+        Self::new(file.current, keys)
+    }
+
+    fn key(&self, key_id: &str) -> KmsResult<&EncryptionKey> {
+        self.keys
+            .get(key_id)
+            .ok_or_else(|| KmsError::UnknownKey(key_id.to_string()))
+    }
+}
+
+/// Decode a base64 master key
+fn parse_key(key_id: &str, key: &str) -> KmsResult<EncryptionKey> {
+    let bytes = STANDARD
+        .decode(key.trim())
+        .map_err(|e| KmsError::Config(format!("master key {} is not base64: {}", key_id, e)))?;
+    EncryptionKey::from_slice(&bytes).map_err(|_| {
+        KmsError::Config(format!(
+            "master key {} must be 32 bytes, not {}",
+            key_id,
+            bytes.len()
+        ))
+    })
+}
+
+#[async_trait]
+impl KeyProvider for LocalKeyring {
+    fn name(&self) -> &'static str {
+        "local"
+    }
+
+    async fn current_key_id(&self) -> KmsResult<String> {
+        Ok(self.current.clone())
+    }
+
+    async fn wrap(&self, data_key: &EncryptionKey) -> KmsResult<WrappedKey> {
+        let ciphertext = encrypt_to_bytes(data_key.as_bytes(), self.key(&self.current)?)
+            .map_err(|e| KmsError::Provider(e.to_string()))?;
+        Ok(WrappedKey {
+            key_id: self.current.clone(),
+            ciphertext,
+        })
+    }
+
+    async fn unwrap(&self, wrapped: &WrappedKey) -> KmsResult<EncryptionKey> {
+        let mut bytes = decrypt_from_bytes(&wrapped.ciphertext, self.key(&wrapped.key_id)?)
+            .map_err(|e| KmsError::Decryption(format!("data key: {}", e)))?;
+        let key = EncryptionKey::from_slice(&bytes)
+            .map_err(|e| KmsError::Decryption(format!("data key: {}", e)));
+        bytes.iter_mut().for_each(|b| *b = 0);
+        key
+    }
+}
+
+/// HashiCorp Vault connection for the transit secrets engine
+#[derive(Clone)]
+pub struct VaultConfig {
+    /// Vault address, e.g. `https://vault.internal:8200`
+    pub addr: String,
+    pub token: String,
+    /// Vault Enterprise namespace
+    pub namespace: Option<String>,
+    /// Mount path of the transit engine
+    pub mount: String,
+    /// Transit key that wraps data keys
+    pub key_name: String,
+}
+
+impl fmt::Debug for VaultConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VaultConfig")
+            .field("addr", &self.addr)
+            .field("token", &"[REDACTED]")
+            .field("namespace", &self.namespace)
+            .field("mount", &self.mount)
+            .field("key_name", &self.key_name)
+            .finish()
+    }
+}
+
+/// Master key kept in Vault's transit engine
+///
+/// Data keys are sent to Vault to be wrapped and unwrapped; the transit key
+/// never leaves Vault. Key IDs name the transit key version
+/// (`cyxcloud:v3`), so data keys wrapped before a rotation are found and
+/// re-wrapped with Vault's `rewrap` endpoint.
+pub struct VaultTransit {
+    config: VaultConfig,
+    client: reqwest::Client,
+}
+
+impl VaultTransit {
+    pub fn new(config: VaultConfig) -> Self {
+        info!(addr = %config.addr, key = %config.key_name, "Vault transit key provider configured");
+        Self {
+            config,
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(10))
+                .build()
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Call a transit endpoint, returning the `data` of the response
+    async fn call(
+        &self,
+        method: reqwest::Method,
+        endpoint: &str,
+        body: Option<serde_json::Value>,
+    ) -> KmsResult<serde_json::Value> {
+        let url = format!(
+            "{}/v1/{}/{}",
+            self.config.addr.trim_end_matches('/'),
+            self.config.mount.trim_matches('/'),
+            endpoint
+        );
+        let mut request = self
+            .client
+            .request(method, &url)
+            .header("X-Vault-Token", &self.config.token);
+        if let Some(namespace) = &self.config.namespace {
+            request = request.header("X-Vault-Namespace", namespace);
+        }
+        if let Some(body) = body {
+            request = request.json(&body);
+        }
+
+        let response = request
+            .send()
+            .await
+            .map_err(|e| KmsError::Provider(format!("Vault unreachable: {}", e)))?;
+        let status = response.status();
+        let mut reply: serde_json::Value = response.json().await.unwrap_or_default();
+        if !status.is_success() {
+            return Err(KmsError::Provider(format!(
+                "Vault returned {} for {}: {}",
+                status, endpoint, reply["errors"]
+            )));
+        }
+        Ok(reply
+            .get_mut("data")
+            .map(serde_json::Value::take)
+            .unwrap_or_default())
+    }
+
+    /// Field of a Vault reply as a string
+    fn field<'a>(data: &'a serde_json::Value, name: &str) -> KmsResult<&'a str> {
+        data[name]
+            .as_str()
+            .ok_or_else(|| KmsError::Provider(format!("Vault reply has no {}", name)))
+    }
+
+    /// Wrapped key from a transit ciphertext (`vault:v<version>:...`)
+    fn wrapped(&self, ciphertext: &str) -> KmsResult<WrappedKey> {
+        Ok(WrappedKey {
+            key_id: vault_key_id(&self.config.key_name, ciphertext)?,
+            ciphertext: ciphertext.as_bytes().to_vec(),
+        })
+    }
+}
+
+/// Key ID of a transit ciphertext: the key name and the version it names
+fn vault_key_id(key_name: &str, ciphertext: &str) -> KmsResult<String> {
+    let mut parts = ciphertext.splitn(3, ':');
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some("vault"), Some(version), Some(_))
+            if version.len() > 1
+                && version.starts_with('v')
+                && version[1..].bytes().all(|b| b.is_ascii_digit()) =>
+        {
+            Ok(format!("{}:{}", key_name, version))
+        }
+        _ => Err(KmsError::Provider(
+            "Vault returned a malformed ciphertext".to_string(),
+        )),
+    }
+}
+
+#[async_trait]
+impl KeyProvider for VaultTransit {
+    fn name(&self) -> &'static str {
+        "vault"
+    }
+
+    async fn current_key_id(&self) -> KmsResult<String> {
+        let data = self
+            .call(
+                reqwest::Method::GET,
+                &format!("keys/{}", self.config.key_name),
+                None,
+            )
+            .await?;
+        let version = data["latest_version"]
+            .as_u64()
+            .ok_or_else(|| KmsError::Provider("Vault reply has no latest_version".to_string()))?;
+        Ok(format!("{}:v{}", self.config.key_name, version))
+    }
+
+    async fn wrap(&self, data_key: &EncryptionKey) -> KmsResult<WrappedKey> {
+        let data = self
+            .call(
+                reqwest::Method::POST,
+                &format!("encrypt/{}", self.config.key_name),
+                Some(serde_json::json!({ "plaintext": STANDARD.encode(data_key.as_bytes()) })),
+            )
+            .await?;
+        self.wrapped(Self::field(&data, "ciphertext")?)
+    }
+
+    async fn unwrap(&self, wrapped: &WrappedKey) -> KmsResult<EncryptionKey> {
+        let ciphertext = std::str::from_utf8(&wrapped.ciphertext)
+            .map_err(|_| KmsError::Decryption("data key is not a Vault ciphertext".to_string()))?;
+        let data = self
+            .call(
+                reqwest::Method::POST,
+                &format!("decrypt/{}", self.config.key_name),
+                Some(serde_json::json!({ "ciphertext": ciphertext })),
+            )
+            .await?;
+        let mut bytes = STANDARD
+            .decode(Self::field(&data, "plaintext")?)
+            .map_err(|e| KmsError::Decryption(format!("data key: {}", e)))?;
+        let key = EncryptionKey::from_slice(&bytes)
+            .map_err(|e| KmsError::Decryption(format!("data key: {}", e)));
+        bytes.iter_mut().for_each(|b| *b = 0);
+        key
+    }
+
+    async fn rewrap(&self, wrapped: &WrappedKey) -> KmsResult<WrappedKey> {
+        let ciphertext = std::str::from_utf8(&wrapped.ciphertext)
+            .map_err(|_| KmsError::Decryption("data key is not a Vault ciphertext".to_string()))?;
+        let data = self
+            .call(
+                reqwest::Method::POST,
+                &format!("rewrap/{}", self.config.key_name),
+                Some(serde_json::json!({ "ciphertext": ciphertext })),
+            )
+            .await?;
+        self.wrapped(Self::field(&data, "ciphertext")?)
+    }
+
+    async fn rotate_master_key(&self) -> KmsResult<String> {
+        self.call(
+            reqwest::Method::POST,
+            &format!("keys/{}/rotate", self.config.key_name),
+            None,
+        )
+        .await?;
+        let key_id = self.current_key_id().await?;
+        info!(key_id = %key_id, "Vault transit key rotated");
+        Ok(key_id)
+    }
+}
+
+/// Where master keys come from
+#[derive(Clone, Default)]
+pub enum KeyProviderConfig {
+    /// No key management; encrypted uploads are refused
+    #[default]
+    None,
+    /// A single base64 master key
+    Local { key_id: String, key: String },
+    /// A keyring file with the current and retired master keys
+    Keyring(PathBuf),
+    /// HashiCorp Vault transit engine
+    Vault(VaultConfig),
+}
+
+impl fmt::Debug for KeyProviderConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyProviderConfig::None => write!(f, "None"),
+            KeyProviderConfig::Local { key_id, .. } => {
+                write!(f, "Local {{ key_id: {:?}, key: [REDACTED] }}", key_id)
+            }
+            KeyProviderConfig::Keyring(path) => f.debug_tuple("Keyring").field(path).finish(),
+            KeyProviderConfig::Vault(config) => f.debug_tuple("Vault").field(config).finish(),
+        }
+    }
+}
+
+/// Key management configuration
+#[derive(Debug, Clone, Default)]
+pub struct KmsConfig {
+    /// Source of master keys
+    pub provider: KeyProviderConfig,
+    /// Encryption of uploads that do not ask for any (None = plaintext)
+    pub default_algorithm: Option<SseAlgorithm>,
+}
+
+impl KmsConfig {
+    /// Create configuration from environment variables
+    ///
+    /// Master keys and Vault tokens are only read from the environment (or
+    /// the keyring file it names), never from `gateway.toml`.
+    pub fn from_env() -> Self {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+
+        let provider = match var("GATEWAY_KMS_PROVIDER").as_deref() {
+            Some("vault") => KeyProviderConfig::Vault(VaultConfig {
+                addr: var("VAULT_ADDR").unwrap_or_else(|| "http://127.0.0.1:8200".to_string()),
+                token: var("VAULT_TOKEN").unwrap_or_default(),
+                namespace: var("VAULT_NAMESPACE"),
+                mount: var("GATEWAY_KMS_VAULT_MOUNT").unwrap_or_else(|| "transit".to_string()),
+                key_name: var("GATEWAY_KMS_VAULT_KEY").unwrap_or_else(|| "cyxcloud".to_string()),
+            }),
+            Some("none") => KeyProviderConfig::None,
+            other => {
+                if let Some(other) = other.filter(|p| *p != "local") {
+                    warn!(
+                        provider = other,
+                        "Unknown GATEWAY_KMS_PROVIDER, using local keys"
+                    );
+                }
+                if let Some(path) = var("GATEWAY_KMS_KEYRING_FILE") {
+                    KeyProviderConfig::Keyring(PathBuf::from(path))
+                } else if let Some(key) = var("GATEWAY_KMS_MASTER_KEY") {
+                    KeyProviderConfig::Local {
+                        key_id: var("GATEWAY_KMS_MASTER_KEY_ID")
+                            .unwrap_or_else(|| "default".to_string()),
+                        key,
+                    }
+                } else {
+                    KeyProviderConfig::None
+                }
+            }
+        };
+
+        let default_algorithm = var("GATEWAY_SSE_DEFAULT").and_then(|v| match v.parse() {
+            Ok(algorithm) => Some(algorithm),
+            Err(e) => {
+                warn!(error = %e, "Ignoring GATEWAY_SSE_DEFAULT");
+                None
+            }
+        });
+
+        Self {
+            provider,
+            default_algorithm,
+        }
+    }
+}
+
+/// Key management service of the gateway
+pub struct Kms {
+    provider: Arc<dyn KeyProvider>,
+}
+
+impl Kms {
+    pub fn new(provider: Arc<dyn KeyProvider>) -> Self {
+        Self { provider }
+    }
+
+    /// Key management for a configuration (None if no provider is set)
+    pub fn from_config(config: &KmsConfig) -> KmsResult<Option<Self>> {
+        let provider: Arc<dyn KeyProvider> = match &config.provider {
+            KeyProviderConfig::None => return Ok(None),
+            KeyProviderConfig::Local { key_id, key } => {
+                Arc::new(LocalKeyring::single(key_id, key)?)
+            }
+            KeyProviderConfig::Keyring(path) => Arc::new(LocalKeyring::from_file(path)?),
+            KeyProviderConfig::Vault(vault) => {
+                if vault.token.is_empty() {
+                    return Err(KmsError::Config("VAULT_TOKEN is not set".to_string()));
+                }
+                Arc::new(VaultTransit::new(vault.clone()))
+            }
+        };
+        Ok(Some(Self::new(provider)))
+    }
+
+    /// Name of the key provider
+    pub fn provider_name(&self) -> &'static str {
+        self.provider.name()
+    }
+
+    /// A new random data key and its wrapped form
+    pub async fn generate_data_key(&self) -> KmsResult<(EncryptionKey, WrappedKey)> {
+        let data_key = EncryptionKey::generate();
+        let wrapped = self.provider.wrap(&data_key).await?;
+        Ok((data_key, wrapped))
+    }
+
+    /// Unwrap the data key of an object
+    pub async fn unwrap(&self, wrapped: &WrappedKey) -> KmsResult<EncryptionKey> {
+        self.provider.unwrap(wrapped).await
+    }
+
+    /// ID of the master key new data keys are wrapped with
+    pub async fn current_key_id(&self) -> KmsResult<String> {
+        self.provider.current_key_id().await
+    }
+
+    /// Wrap a data key again with the current master key
+    pub async fn rewrap(&self, wrapped: &WrappedKey) -> KmsResult<WrappedKey> {
+        self.provider.rewrap(wrapped).await
+    }
+
+    /// Make a new master key current, if the provider can create one
+    pub async fn rotate_master_key(&self) -> KmsResult<String> {
+        self.provider.rotate_master_key().await
+    }
+}
+
+/// Number of segments an object of `plain_len` bytes is sealed in
+///
+/// An empty object still has one (empty) final segment, so that truncating
+/// a sealed object to nothing is detected.
+fn segment_count(plain_len: u64) -> u64 {
+    plain_len.div_ceil(SEGMENT_SIZE as u64).max(1)
+}
+
+/// Stored size of an object of `plain_len` bytes once sealed
+pub fn sealed_len(plain_len: u64) -> u64 {
+    plain_len + segment_count(plain_len) * TAG_SIZE as u64
+}
+
+/// Plaintext size of an object stored sealed in `sealed_len` bytes
+pub fn plain_len(sealed_len: u64) -> u64 {
+    sealed_len.saturating_sub(sealed_len.div_ceil(SEALED_SEGMENT_SIZE) * TAG_SIZE as u64)
+}
+
+/// Seals a stream of object data segment by segment
+pub struct Sealer {
+    key: EncryptionKey,
+    pending: BytesMut,
+    next_index: u64,
+}
+
+impl Sealer {
+    pub fn new(key: EncryptionKey) -> Self {
+        Self {
+            key,
+            pending: BytesMut::new(),
+            next_index: 0,
+        }
+    }
+
+    /// Add plaintext, returning the sealed segments it completes
+    ///
+    /// A full segment is held back until more data arrives, since only then
+    /// is it known not to be the final one.
+    pub fn push(&mut self, data: &[u8]) -> KmsResult<Bytes> {
+        self.pending.extend_from_slice(data);
+        let mut sealed = BytesMut::new();
+        while self.pending.len() > SEGMENT_SIZE {
+            let segment = self.pending.split_to(SEGMENT_SIZE);
+            sealed.extend_from_slice(&self.seal(&segment, false)?);
+        }
+        Ok(sealed.freeze())
+    }
+
+    /// Seal the final segment
+    pub fn finish(mut self) -> KmsResult<Bytes> {
+        let segment = self.pending.split();
+        self.seal(&segment, true).map(Bytes::from)
+    }
+
+    fn seal(&mut self, segment: &[u8], last: bool) -> KmsResult<Vec<u8>> {
+        let sealed = seal_segment(segment, &self.key, self.next_index, last)
+            .map_err(|e| KmsError::Provider(e.to_string()))?;
+        self.next_index += 1;
+        Ok(sealed)
+    }
+}
+
+/// Seal a whole object
+pub fn seal(key: &EncryptionKey, data: &[u8]) -> KmsResult<Bytes> {
+    let mut sealer = Sealer::new(key.clone());
+    let mut sealed = BytesMut::with_capacity(sealed_len(data.len() as u64) as usize);
+    sealed.extend_from_slice(&sealer.push(data)?);
+    sealed.extend_from_slice(&sealer.finish()?);
+    Ok(sealed.freeze())
+}
+
+/// Stored bytes holding a plaintext range of a sealed object
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SealedRange {
+    /// Half-open range of stored bytes to read
+    pub stored: (u64, u64),
+    first_segment: u64,
+    last_segment: u64,
+    /// Plaintext bytes to skip in the first segment, and to return
+    skip: usize,
+    len: usize,
+}
+
+impl SealedRange {
+    /// Stored bytes covering the half-open plaintext range `[start, end)` of
+    /// an object of `plain_len` bytes
+    pub fn new(plain_len: u64, (start, end): (u64, u64)) -> Self {
+        let segment = SEGMENT_SIZE as u64;
+        let first_segment = start / segment;
+        let last_needed = if end > start {
+            (end - 1) / segment
+        } else {
+            first_segment
+        };
+        Self {
+            stored: (
+                first_segment * SEALED_SEGMENT_SIZE,
+                ((last_needed + 1) * SEALED_SEGMENT_SIZE).min(sealed_len(plain_len)),
+            ),
+            first_segment,
+            last_segment: segment_count(plain_len) - 1,
+            skip: (start - first_segment * segment) as usize,
+            len: end.saturating_sub(start) as usize,
+        }
+    }
+
+    /// The whole of an object of `plain_len` bytes
+    pub fn whole(plain_len: u64) -> Self {
+        Self::new(plain_len, (0, plain_len))
+    }
+
+    /// Open the stored bytes of the range and return its plaintext
+    pub fn open(&self, key: &EncryptionKey, stored: &[u8]) -> KmsResult<Bytes> {
+        if stored.len() as u64 != self.stored.1 - self.stored.0 {
+            return Err(KmsError::Decryption(format!(
+                "expected {} sealed bytes, got {}",
+                self.stored.1 - self.stored.0,
+                stored.len()
+            )));
+        }
+
+        let mut plain = Vec::with_capacity(stored.len());
+        for (i, segment) in stored.chunks(SEALED_SEGMENT_SIZE as usize).enumerate() {
+            let index = self.first_segment + i as u64;
+            let opened = open_segment(segment, key, index, index == self.last_segment)
+                .map_err(|e| KmsError::Decryption(format!("segment {}: {}", index, e)))?;
+            plain.extend_from_slice(&opened);
+        }
+
+        if self.skip + self.len > plain.len() {
+            return Err(KmsError::Decryption(
+                "sealed object is truncated".to_string(),
+            ));
+        }
+        Ok(Bytes::from(plain).slice(self.skip..self.skip + self.len))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+
+    #[test]
+    fn test_sealed_len_roundtrip() {
+        for len in [
+            0,
+            1,
+            SEGMENT_SIZE - 1,
+            SEGMENT_SIZE,
+            SEGMENT_SIZE + 1,
+            3 * SEGMENT_SIZE,
+        ] {
+            let sealed = sealed_len(len as u64);
+            assert_eq!(plain_len(sealed), len as u64, "{}", len);
+        }
+        assert_eq!(sealed_len(0), TAG_SIZE as u64);
+        assert_eq!(
+            sealed_len(SEGMENT_SIZE as u64 + 1),
+            SEALED_SEGMENT_SIZE + 1 + 16
+        );
+    }
+
+    #[test]
+    fn test_stream_sealing_matches_whole_and_opens_ranges() {
+        let key = EncryptionKey::generate();
+        let plain = data(2 * SEGMENT_SIZE + 100);
+
+        let mut sealer = Sealer::new(key.clone());
+        let mut streamed = Vec::new();
+        for piece in plain.chunks(7000) {
+            streamed.extend_from_slice(&sealer.push(piece).unwrap());
+        }
+        streamed.extend_from_slice(&sealer.finish().unwrap());
+        assert_eq!(streamed, seal(&key, &plain).unwrap().to_vec());
+        assert_eq!(streamed.len() as u64, sealed_len(plain.len() as u64));
+
+        let len = plain.len() as u64;
+        let whole = SealedRange::whole(len);
+        assert_eq!(whole.open(&key, &streamed).unwrap(), plain);
+
+        // A range across a segment boundary only needs those two segments
+        let (start, end) = (SEGMENT_SIZE as u64 - 10, SEGMENT_SIZE as u64 + 10);
+        let range = SealedRange::new(len, (start, end));
+        assert_eq!(range.stored, (0, 2 * SEALED_SEGMENT_SIZE));
+        let stored = &streamed[range.stored.0 as usize..range.stored.1 as usize];
+        assert_eq!(
+            range.open(&key, stored).unwrap(),
+            plain[start as usize..end as usize]
+        );
+
+        // The final segment cannot be dropped
+        let truncated = &streamed[..2 * SEALED_SEGMENT_SIZE as usize];
+        let shorter = SealedRange::whole(2 * SEGMENT_SIZE as u64);
+        assert!(shorter.open(&key, truncated).is_err());
+    }
+
+    #[test]
+    fn test_empty_object() {
+        let key = EncryptionKey::generate();
+        let sealed = seal(&key, b"").unwrap();
+        assert_eq!(sealed.len(), TAG_SIZE);
+        assert!(SealedRange::whole(0)
+            .open(&key, &sealed)
+            .unwrap()
+            .is_empty());
+        assert!(SealedRange::whole(0).open(&key, b"").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_keyring_rotation_rewraps() {
+        let old_key = EncryptionKey::generate();
+        let new_key = EncryptionKey::generate();
+        let old =
+            LocalKeyring::new("k1", HashMap::from([("k1".to_string(), old_key.clone())])).unwrap();
+        let (data_key, wrapped) = Kms::new(Arc::new(old)).generate_data_key().await.unwrap();
+        assert_eq!(wrapped.key_id, "k1");
+
+        let rotated = Kms::new(Arc::new(
+            LocalKeyring::new(
+                "k2",
+                HashMap::from([("k1".to_string(), old_key), ("k2".to_string(), new_key)]),
+            )
+            .unwrap(),
+        ));
+        assert_eq!(rotated.current_key_id().await.unwrap(), "k2");
+        assert_eq!(
+            rotated.unwrap(&wrapped).await.unwrap().as_bytes(),
+            data_key.as_bytes()
+        );
+
+        let rewrapped = rotated.rewrap(&wrapped).await.unwrap();
+        assert_eq!(rewrapped.key_id, "k2");
+        assert_eq!(
+            rotated.unwrap(&rewrapped).await.unwrap().as_bytes(),
+            data_key.as_bytes()
+        );
+        assert!(matches!(
+            rotated.rotate_master_key().await,
+            Err(KmsError::RotationUnsupported("local"))
+        ));
+
+        let forged = WrappedKey {
+            key_id: "k3".to_string(),
+            ..rewrapped
+        };
+        assert!(matches!(
+            rotated.unwrap(&forged).await,
+            Err(KmsError::UnknownKey(_))
+        ));
+    }
+
+    #[test]
+    fn test_keyring_file() {
+        let key = STANDARD.encode([7u8; 32]);
+        let path = std::env::temp_dir().join(format!("keyring-{}.toml", uuid::Uuid::new_v4()));
+        std::fs::write(
+            &path,
+            format!(
+                "current = \"b\"\n\n[keys]\na = \"{}\"\nb = \"{}\"\n",
+                key, key
+            ),
+        )
+        .unwrap();
+        let keyring = LocalKeyring::from_file(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(keyring.unwrap().current, "b");
+
+        assert!(LocalKeyring::single("a", &STANDARD.encode([7u8; 16])).is_err());
+        assert!(LocalKeyring::new("missing", HashMap::new()).is_err());
+    }
+
+    #[test]
+    fn test_vault_key_id() {
+        assert_eq!(
+            vault_key_id("cyxcloud", "vault:v3:AbCd==").unwrap(),
+            "cyxcloud:v3"
+        );
+        for malformed in ["v3:AbCd", "vault:3:AbCd", "vault:v:AbCd", "vault:v3"] {
+            assert!(
+                vault_key_id("cyxcloud", malformed).is_err(),
+                "{}",
+                malformed
+            );
+        }
+    }
+
+    #[test]
+    fn test_sse_algorithm() {
+        for algorithm in [SseAlgorithm::Aes256, SseAlgorithm::AwsKms] {
+            assert_eq!(algorithm.as_str().parse::<SseAlgorithm>(), Ok(algorithm));
+        }
+        assert!("aws:kms:dsse".parse::<SseAlgorithm>().is_err());
+    }
+}
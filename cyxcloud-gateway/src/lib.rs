@@ -9,27 +9,63 @@
 #![allow(clippy::type_complexity)]
 #![allow(dead_code)]
 
+mod access_log;
+pub mod acl;
+mod admin_api;
 pub mod audit;
 pub mod auth;
 mod auth_api;
+mod bandwidth;
+pub mod bucket_cors;
+pub mod bucket_policy;
 #[cfg(feature = "blockchain")]
 pub mod blockchain;
+mod client_ip;
+mod cluster_api;
+pub mod config;
+mod dashboard;
 mod data_access;
 mod dataset_api;
 mod datastream;
+mod export;
+mod fsck_api;
 mod grpc_api;
+mod health_api;
+mod heat;
+pub mod kms;
+mod location_repair;
 pub mod metrics;
+mod node_api;
 mod node_client;
+mod node_events;
+mod node_gossip;
 mod node_monitor;
+pub mod object_lock;
+mod oidc;
 mod payment_daemon;
+mod preflight;
+mod proof_audit;
 mod public_registry;
+mod rate_limit;
 mod rebalancer_daemon;
+mod reload;
+mod replication;
+mod request_id;
 mod s3_api;
+mod s3_xml;
+mod select;
 pub mod state;
+mod stats_api;
+mod storage_class_api;
+mod tiering;
+mod upload_janitor;
+mod upload_limits;
+mod usage_api;
 mod verification;
 mod websocket;
 
 pub use auth::{AuthConfig, AuthService};
 #[cfg(feature = "blockchain")]
 pub use blockchain::{BlockchainConfig, CyxCloudBlockchainClient};
+pub use config::{ConfigError, GatewaySettings};
 pub use state::{AppState, GatewayConfig};
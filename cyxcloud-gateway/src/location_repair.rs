@@ -0,0 +1,220 @@
+//! Chunk Location Repair
+//!
+//! Metadata can list a node as holding a chunk it no longer has: its disk
+//! was replaced, the chunk was lost in a crash, or an operator wiped the data
+//! directory. Reads keep being routed there until something corrects the
+//! record, and the chunk counts a replica it does not have.
+//!
+//! Every replica read that a node answers with "not found" is queued by the
+//! node client as a location miss. This daemon drains that queue: it drops
+//! the stale location (lowering the chunk's replica count) and queues a
+//! repair job copying the chunk from a remaining replica to another node, so
+//! each failed read corrects the metadata it tripped over.
+//!
+//! Misses are reported per read, so a popular chunk can produce a burst of
+//! them before the location is gone; repeats within the dedup window are
+//! skipped.
+
+use crate::node_client::LocationMiss;
+use crate::state::AppState;
+use cyxcloud_metadata::{MetadataError, MetadataService};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+/// Misses remembered for deduplication before old ones are pruned
+const MAX_RECENT_MISSES: usize = 10_000;
+
+/// Location repair configuration
+#[derive(Debug, Clone)]
+pub struct LocationRepairConfig {
+    /// Correct locations nodes answer "not found" for
+    pub enabled: bool,
+    /// Repeats of a miss within this window are skipped
+    pub dedup_window: Duration,
+}
+
+impl Default for LocationRepairConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            dedup_window: Duration::from_secs(10 * 60),
+        }
+    }
+}
+
+impl LocationRepairConfig {
+    /// Create configuration from environment variables
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            enabled: std::env::var("LOCATION_REPAIR_ENABLED")
+                .map(|v| v == "true" || v == "1")
+                .unwrap_or(defaults.enabled),
+            dedup_window: Duration::from_secs(
+                std::env::var("LOCATION_REPAIR_DEDUP_SECS")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(defaults.dedup_window.as_secs()),
+            ),
+        }
+    }
+}
+
+/// Misses handled recently
+struct RecentMisses {
+    window: Duration,
+    seen: HashMap<LocationMiss, Instant>,
+}
+
+impl RecentMisses {
+    fn new(window: Duration) -> Self {
+        Self {
+            window,
+            seen: HashMap::new(),
+        }
+    }
+
+    /// Record a miss, returning false if it was already handled in the window
+    fn insert(&mut self, miss: &LocationMiss, now: Instant) -> bool {
+        if let Some(at) = self.seen.get(miss) {
+            if now.duration_since(*at) < self.window {
+                return false;
+            }
+        }
+
+        if self.seen.len() >= MAX_RECENT_MISSES {
+            let window = self.window;
+            self.seen.retain(|_, at| now.duration_since(*at) < window);
+        }
+        // Still full of recent misses: forget them rather than grow
+        if self.seen.len() >= MAX_RECENT_MISSES {
+            self.seen.clear();
+        }
+
+        self.seen.insert(miss.clone(), now);
+        true
+    }
+}
+
+/// Daemon correcting chunk locations nodes turned out not to hold
+pub struct LocationRepairDaemon {
+    config: LocationRepairConfig,
+}
+
+impl LocationRepairDaemon {
+    /// Create a new location repair daemon
+    pub fn new(config: LocationRepairConfig) -> Self {
+        Self { config }
+    }
+
+    /// Start draining the node client's miss queue as a background task
+    ///
+    /// Returns None if the queue was already taken.
+    pub fn start(self: Arc<Self>, state: Arc<AppState>) -> Option<JoinHandle<()>> {
+        let mut misses = state.node_client().take_location_misses()?;
+        let daemon = self;
+
+        Some(tokio::spawn(async move {
+            let mut recent = RecentMisses::new(daemon.config.dedup_window);
+
+            info!(
+                dedup_secs = daemon.config.dedup_window.as_secs(),
+                "Location repair daemon started"
+            );
+
+            while let Some(miss) = misses.recv().await {
+                if !recent.insert(&miss, Instant::now()) {
+                    continue;
+                }
+                if let Some(metadata) = state.metadata_service() {
+                    repair(metadata, &miss).await;
+                }
+            }
+
+            warn!("Location repair daemon stopped");
+        }))
+    }
+}
+
+/// Drop a missed location and queue a repair of the chunk
+async fn repair(metadata: &MetadataService, miss: &LocationMiss) {
+    let chunk_id = hex::encode(&miss.chunk_id);
+
+    match metadata
+        .repair_stale_location(&miss.chunk_id, &miss.node_address)
+        .await
+    {
+        Ok(Some(repair)) => {
+            debug!(
+                node = %miss.node_address,
+                chunk_id = %chunk_id,
+                remaining_replicas = repair.remaining_replicas,
+                repair_job = ?repair.repair_job.as_ref().map(|j| j.id),
+                "Stale chunk location repaired"
+            );
+        }
+        Ok(None) => {
+            debug!(node = %miss.node_address, chunk_id = %chunk_id, "Chunk location already gone");
+        }
+        Err(MetadataError::NotFound(_)) => {
+            debug!(node = %miss.node_address, chunk_id = %chunk_id, "Missed chunk on an unknown node");
+        }
+        Err(e) => {
+            warn!(
+                node = %miss.node_address,
+                chunk_id = %chunk_id,
+                error = %e,
+                "Failed to repair stale chunk location"
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn miss(node: &str, chunk: u8) -> LocationMiss {
+        LocationMiss {
+            node_address: node.to_string(),
+            chunk_id: vec![chunk],
+        }
+    }
+
+    #[test]
+    fn test_location_repair_config_default() {
+        let config = LocationRepairConfig::default();
+        assert!(config.enabled);
+        assert_eq!(config.dedup_window.as_secs(), 600);
+    }
+
+    #[test]
+    fn test_recent_misses_dedup() {
+        let window = Duration::from_secs(60);
+        let mut recent = RecentMisses::new(window);
+        let now = Instant::now();
+
+        assert!(recent.insert(&miss("a:1", 1), now));
+        assert!(!recent.insert(&miss("a:1", 1), now + Duration::from_secs(30)));
+
+        // Same chunk on another node, another chunk on the same node
+        assert!(recent.insert(&miss("b:1", 1), now));
+        assert!(recent.insert(&miss("a:1", 2), now));
+
+        // Handled again once the window has passed
+        assert!(recent.insert(&miss("a:1", 1), now + window));
+    }
+
+    #[test]
+    fn test_recent_misses_bounded() {
+        let mut recent = RecentMisses::new(Duration::from_secs(60));
+        let now = Instant::now();
+        for i in 0..MAX_RECENT_MISSES + 10 {
+            recent.insert(&miss(&format!("node-{}:1", i), 0), now);
+        }
+        assert!(recent.seen.len() <= MAX_RECENT_MISSES);
+    }
+}
@@ -1,7 +1,7 @@
 //! CyxCloud API Gateway
 //!
 //! Provides:
-//! - S3-compatible REST API (HTTP/HTTPS)
+//! - S3-compatible REST API (HTTP/1.1 and HTTP/2, plain or TLS)
 //! - gRPC API for ecosystem integration (NodeService, DataService)
 //! - WebSocket for real-time sync
 //! - Authentication (JWT, wallet signatures)
@@ -16,39 +16,76 @@
 // are fully integrated. Currently 40 dead_code items in partially-implemented modules.
 #![allow(dead_code)]
 
+mod access_log;
+mod acl;
+mod admin_api;
 mod audit;
 pub mod auth;
 mod auth_api;
+mod bandwidth;
+mod bucket_cors;
+mod bucket_policy;
 #[cfg(feature = "blockchain")]
 pub mod blockchain;
+mod client_ip;
+mod cluster_api;
+mod config;
+mod dashboard;
 mod data_access;
 mod dataset_api;
 mod datastream;
+mod export;
+mod fsck_api;
 mod grpc_api;
+mod health_api;
+mod heat;
+mod kms;
+mod location_repair;
 mod metrics;
+mod node_api;
 mod node_client;
+mod node_events;
+mod node_gossip;
 mod node_monitor;
+mod object_lock;
+mod oidc;
 mod payment_daemon;
+mod preflight;
+mod proof_audit;
 mod public_registry;
+mod rate_limit;
 mod rebalancer_daemon;
+mod reload;
+mod replication;
+mod request_id;
 mod s3_api;
+mod s3_xml;
+mod select;
 mod state;
+mod stats_api;
+mod storage_class_api;
+mod tiering;
+mod upload_janitor;
+mod upload_limits;
+mod usage_api;
 mod verification;
 mod websocket;
 
 pub use auth::{AuthConfig, AuthService};
 #[cfg(feature = "blockchain")]
 pub use blockchain::{BlockchainConfig, CyxCloudBlockchainClient};
+pub use config::{ConfigError, GatewaySettings};
 pub use state::{AppState, GatewayConfig};
 
 use axum::{extract::DefaultBodyLimit, routing::get, Router};
 use clap::Parser;
-use cyxcloud_core::tls::{create_tonic_server_tls, TlsServerConfig};
+use cyxcloud_core::tls::create_tonic_server_tls;
 use cyxcloud_protocol::data::data_service_server::DataServiceServer;
 use cyxcloud_protocol::datastream::data_stream_service_server::DataStreamServiceServer;
 use cyxcloud_protocol::node::node_service_server::NodeServiceServer;
+use cyxcloud_protocol::object::object_service_server::ObjectServiceServer;
 use datastream::DataStreamServiceImpl;
-use grpc_api::{AuthInterceptor, DataServiceImpl, NodeServiceImpl};
+use grpc_api::{AuthLayer, DataServiceImpl, NodeServiceImpl, ObjectServiceImpl};
 use std::net::SocketAddr;
 use std::path::PathBuf;
 use std::sync::Arc;
@@ -56,56 +93,70 @@ use tokio::signal;
 use tonic::transport::Server as TonicServer;
 use tower_http::cors::CorsLayer;
 use tower_http::trace::TraceLayer;
-use tracing::{error, info, warn, Level};
+use tracing::{error, info, warn};
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
 
 #[derive(Parser)]
 #[command(name = "cyxcloud-gateway")]
 #[command(about = "CyxCloud API gateway")]
 struct Cli {
-    /// HTTP listen address
-    #[arg(long, default_value = "0.0.0.0:8180")]
-    http_addr: String,
+    /// Configuration file (defaults to ./gateway.toml if present)
+    #[arg(short, long, env = "GATEWAY_CONFIG")]
+    config: Option<PathBuf>,
 
-    /// gRPC listen address
-    #[arg(long, default_value = "0.0.0.0:50052")]
-    grpc_addr: String,
+    /// Validate the configuration and exit
+    #[arg(long)]
+    check_config: bool,
+
+    /// HTTP listen address (overrides config file)
+    #[arg(long)]
+    http_addr: Option<String>,
+
+    /// gRPC listen address (overrides config file)
+    #[arg(long)]
+    grpc_addr: Option<String>,
 
     /// Enable CORS for all origins (development only)
-    #[arg(long, default_value = "false")]
+    #[arg(long)]
     cors_permissive: bool,
 
     /// PostgreSQL database URL (enables persistent storage)
-    #[arg(long, env = "DATABASE_URL")]
+    #[arg(long)]
     database_url: Option<String>,
 
     /// Redis URL for caching
-    #[arg(long, env = "REDIS_URL")]
+    #[arg(long)]
     redis_url: Option<String>,
 
     /// Force in-memory storage even if database is configured
-    #[arg(long, default_value = "false")]
+    #[arg(long)]
     memory_only: bool,
 
+    /// Accept S3 writes without waiting for the startup preflight (schema
+    /// version, Redis and registered nodes)
+    #[arg(long, env = "GATEWAY_SKIP_PREFLIGHT")]
+    skip_preflight: bool,
+
     /// Enable gRPC authentication (requires JWT). Enabled by default for security.
-    /// Use --no-grpc-auth to disable (development only).
-    #[arg(long, default_value = "true")]
-    grpc_auth: bool,
+    /// Use `--grpc-auth false` to disable (development only).
+    #[arg(long)]
+    grpc_auth: Option<bool>,
 
     // ===== TLS Configuration =====
     /// Path to server TLS certificate (PEM format)
-    #[arg(long, env = "TLS_CERT")]
+    #[arg(long)]
     tls_cert: Option<PathBuf>,
 
     /// Path to server TLS private key (PEM format)
-    #[arg(long, env = "TLS_KEY")]
+    #[arg(long)]
     tls_key: Option<PathBuf>,
 
     /// Path to CA certificate for client verification (mTLS)
-    #[arg(long, env = "TLS_CA_CERT")]
+    #[arg(long)]
     tls_ca_cert: Option<PathBuf>,
 
     /// Require client certificates for mTLS (node authentication)
-    #[arg(long, default_value = "false", env = "TLS_REQUIRE_CLIENT_CERT")]
+    #[arg(long)]
     tls_require_client_cert: bool,
 
     /// Solana RPC URL for blockchain operations (requires 'blockchain' feature)
@@ -128,8 +179,47 @@ struct Cli {
     enable_blockchain: bool,
 }
 
-async fn health() -> &'static str {
-    "OK"
+impl Cli {
+    /// Apply CLI flags on top of file and environment settings
+    fn apply_overrides(&self, mut settings: GatewaySettings) -> GatewaySettings {
+        if let Some(ref addr) = self.http_addr {
+            settings.server.http_addr = addr.clone();
+        }
+        if let Some(ref addr) = self.grpc_addr {
+            settings.server.grpc_addr = addr.clone();
+        }
+        if let Some(grpc_auth) = self.grpc_auth {
+            settings.server.grpc_auth = grpc_auth;
+        }
+        if self.cors_permissive {
+            settings.cors.permissive = true;
+        }
+        if let Some(ref url) = self.database_url {
+            settings.database.url = Some(url.clone());
+        }
+        if let Some(ref url) = self.redis_url {
+            settings.cache.redis_url = Some(url.clone());
+        }
+        if self.memory_only {
+            settings.database.memory_only = true;
+        }
+        if self.skip_preflight {
+            settings.preflight.enabled = false;
+        }
+        if let Some(ref cert) = self.tls_cert {
+            settings.tls.cert = Some(cert.clone());
+        }
+        if let Some(ref key) = self.tls_key {
+            settings.tls.key = Some(key.clone());
+        }
+        if let Some(ref ca) = self.tls_ca_cert {
+            settings.tls.ca_cert = Some(ca.clone());
+        }
+        if self.tls_require_client_cert {
+            settings.tls.require_client_cert = true;
+        }
+        settings
+    }
 }
 
 async fn version() -> &'static str {
@@ -144,48 +234,57 @@ async fn main() -> anyhow::Result<()> {
         .install_default()
         .expect("Failed to install rustls crypto provider");
 
-    // Initialize tracing
-    tracing_subscriber::fmt()
-        .with_max_level(Level::DEBUG)
-        .with_target(true)
-        .init();
-
     let cli = Cli::parse();
 
-    // Check TLS configuration
-    let tls_enabled = cli.tls_cert.is_some() && cli.tls_key.is_some();
-    if cli.tls_cert.is_some() != cli.tls_key.is_some() {
-        anyhow::bail!("Both --tls-cert and --tls-key must be provided for TLS");
-    }
-    if cli.tls_require_client_cert && cli.tls_ca_cert.is_none() {
-        anyhow::bail!("--tls-ca-cert is required when --tls-require-client-cert is enabled");
+    // Load configuration
+    // Priority: CLI args > environment > gateway.toml > defaults
+    let settings = GatewaySettings::load(cli.config.as_deref())
+        .map(|settings| cli.apply_overrides(settings.with_env_overrides()))
+        .and_then(|settings| settings.validate().map(|()| settings));
+
+    if cli.check_config {
+        match settings {
+            Ok(_) => {
+                println!("Configuration OK");
+                return Ok(());
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
     }
+    let settings = settings?;
+
+    // Initialize tracing with a reloadable filter
+    let (log_filter, log_filter_handle) =
+        tracing_subscriber::reload::Layer::new(EnvFilter::new(&settings.logging.level));
+    tracing_subscriber::registry()
+        .with(log_filter)
+        .with(tracing_subscriber::fmt::layer().with_target(true))
+        .init();
+
+    let tls_enabled = settings.tls_enabled();
 
     info!(
-        http = %cli.http_addr,
-        grpc = %cli.grpc_addr,
-        database = ?cli.database_url,
+        http = %settings.server.http_addr,
+        grpc = %settings.server.grpc_addr,
+        database = settings.database.url.is_some(),
         tls = tls_enabled,
+        config = ?cli.config,
         "Starting CyxCloud gateway"
     );
 
     // Create gateway configuration
-    #[cfg(feature = "blockchain")]
-    let config = GatewayConfig {
-        database_url: cli.database_url,
-        redis_url: cli.redis_url,
-        use_memory_storage: cli.memory_only,
-        enable_blockchain: cli.enable_blockchain,
-        solana_rpc_url: Some(cli.solana_rpc_url),
-        keypair_path: cli.keypair_path,
-    };
+    #[allow(unused_mut)]
+    let mut config = settings.gateway_config();
 
-    #[cfg(not(feature = "blockchain"))]
-    let config = GatewayConfig {
-        database_url: cli.database_url,
-        redis_url: cli.redis_url,
-        use_memory_storage: cli.memory_only,
-    };
+    #[cfg(feature = "blockchain")]
+    {
+        config.enable_blockchain = cli.enable_blockchain;
+        config.solana_rpc_url = Some(cli.solana_rpc_url.clone());
+        config.keypair_path = cli.keypair_path.clone();
+    }
 
     // Create shared application state
     let state = Arc::new(
@@ -194,39 +293,126 @@ async fn main() -> anyhow::Result<()> {
             .expect("Failed to initialize application state"),
     );
 
+    // Startup preflight: S3 writes are refused until it passes
+    let preflight_config = settings.preflight_config();
+    if preflight_config.enabled {
+        let preflight = Arc::new(preflight::Preflight::new(preflight_config));
+        let _preflight_handle = preflight.start(state.clone());
+    } else {
+        warn!("Startup preflight skipped, accepting S3 writes immediately");
+    }
+
+    // Config reload on SIGHUP and via the admin API
+    let reloader = Arc::new(reload::ConfigReloader::new(
+        cli.config.clone(),
+        settings.clone(),
+        Box::new(move |settings| cli.apply_overrides(settings)),
+        Some(log_filter_handle),
+    ));
+    state.set_config_reloader(reloader.clone());
+    let _reload_handle = reloader.start_signal_listener(state.clone());
+
+    // Start access log daemon (writes S3 access logs of logged buckets)
+    let access_log = Arc::new(access_log::AccessLogDaemon::new(
+        settings.access_log_config(),
+    ));
+    let _access_log_handle = access_log.start(state.clone());
+
+    // Start bandwidth daemon (flushes per-user bandwidth counters)
+    let bandwidth_config = settings.bandwidth_config();
+    if bandwidth_config.enabled {
+        let bandwidth = Arc::new(bandwidth::BandwidthDaemon::new(bandwidth_config));
+        let _bandwidth_handle = bandwidth.start(state.clone());
+    }
+
+    // Start object heat daemon (flushes sampled download counts)
+    let heat_config = state.heat_tracker().config().clone();
+    if heat_config.enabled {
+        let heat = Arc::new(heat::HeatDaemon::new(heat_config));
+        let _heat_handle = heat.start(state.clone());
+    }
+
     // Start node lifecycle monitor (background task)
     if state.metadata_service().is_some() {
-        let monitor_config = node_monitor::NodeMonitorConfig::from_env();
+        let monitor_config = settings.node_monitor_config();
         let monitor = Arc::new(node_monitor::NodeMonitor::new(monitor_config));
         let _monitor_handle = monitor.start(state.clone());
         info!("Node lifecycle monitor started");
 
         // Start payment daemon (background task)
-        let payment_config = payment_daemon::PaymentDaemonConfig::from_env();
+        let payment_config = settings.payment_daemon_config();
         let payment_daemon = Arc::new(payment_daemon::PaymentDaemon::new(payment_config));
         let _payment_handle = payment_daemon.start(state.clone());
         info!("Payment daemon started");
 
+        // Start proof-of-storage auditor (records slashing evidence)
+        let audit_config = settings.proof_audit_config();
+        let auditor = Arc::new(proof_audit::ProofAuditor::new(audit_config));
+        let _audit_handle = auditor.start(state.clone());
+        info!("Proof-of-storage auditor started");
+
         // Start rebalancer daemon (background task)
-        let rebalancer_config = rebalancer_daemon::RebalancerDaemonConfig::from_env();
+        let rebalancer_config = settings.rebalancer_daemon_config();
         let rebalancer = Arc::new(rebalancer_daemon::RebalancerDaemon::new(rebalancer_config));
         let _rebalancer_handle = rebalancer.start(state.clone());
         info!("Rebalancer daemon started");
+
+        // Start upload janitor (cleans up abandoned partial uploads and expired objects)
+        let janitor_config = settings.upload_janitor_config();
+        let janitor = Arc::new(upload_janitor::UploadJanitor::new(janitor_config));
+        let _janitor_handle = janitor.start(state.clone());
+        info!("Upload janitor started");
+
+        // Start bucket replication daemon (copies replicated buckets to their targets)
+        let replication_config = settings.replication_config();
+        let replication = Arc::new(replication::ReplicationDaemon::new(replication_config));
+        let _replication_handle = replication.start(state.clone());
+        info!("Replication daemon started");
+
+        // Start location repair (corrects chunk locations nodes answer "not found" for)
+        let location_repair_config = location_repair::LocationRepairConfig::from_env();
+        if location_repair_config.enabled {
+            let location_repair = Arc::new(location_repair::LocationRepairDaemon::new(
+                location_repair_config,
+            ));
+            let _location_repair_handle = location_repair.start(state.clone());
+        }
+
+        // Join the node network to hear about drains and shutdowns before the next heartbeat
+        let gossip_config = node_gossip::NodeGossipConfig::from_env();
+        if gossip_config.enabled {
+            let gossip = Arc::new(node_gossip::NodeGossipSubscriber::new(gossip_config));
+            let _gossip_handle = gossip.start(state.clone());
+        }
+
+        // Drop the placement node list as soon as any gateway changes a node's status
+        let node_events_config = node_events::NodeEventsConfig::from_env();
+        if node_events_config.enabled {
+            let node_events = Arc::new(node_events::NodeEventListener::new(node_events_config));
+            let _node_events_handle = node_events.start(state.clone());
+        }
+
+        // Start tiering daemon (storage class transitions, restores and lifecycle rules)
+        let tiering_config = tiering::TieringConfig::from_env();
+        if tiering_config.enabled {
+            let tiering = Arc::new(tiering::TieringDaemon::new(tiering_config));
+            let _tiering_handle = tiering.start(state.clone());
+        }
     } else {
-        info!("Metadata service not configured, node monitor, payment daemon, and rebalancer disabled");
+        info!("Metadata service not configured, node monitor, payment daemon, proof auditor, rebalancer, upload janitor, replication, location repair and tiering disabled");
     }
 
-    // Build CORS layer
-    let cors = if cli.cors_permissive {
+    // Build CORS layer (S3 buckets with CORS rules of their own answer
+    // with those instead, see bucket_cors)
+    let cors = if settings.cors.permissive {
         warn!("CORS permissive mode enabled - do NOT use in production");
         CorsLayer::permissive()
     } else {
-        // Read allowed origins from environment, default to localhost for development
-        let allowed_origins = std::env::var("CORS_ALLOWED_ORIGINS")
-            .unwrap_or_else(|_| "http://localhost:3000".to_string());
+        // Origins come from config/CORS_ALLOWED_ORIGINS, default to localhost for development
+        let allowed_origins = &settings.cors.allowed_origins;
         let origins: Vec<_> = allowed_origins
-            .split(',')
-            .filter_map(|s| s.trim().parse::<axum::http::HeaderValue>().ok())
+            .iter()
+            .filter_map(|s| s.parse::<axum::http::HeaderValue>().ok())
             .collect();
         info!(origins = ?allowed_origins, "CORS configured with specific origins");
         CorsLayer::new()
@@ -244,16 +430,28 @@ async fn main() -> anyhow::Result<()> {
                 axum::http::header::CONTENT_TYPE,
                 axum::http::header::ACCEPT,
             ])
+            .expose_headers([
+                axum::http::HeaderName::from_static("x-request-id"),
+                axum::http::HeaderName::from_static("x-cyxcloud-error-code"),
+            ])
     };
 
     // Initialize Prometheus metrics
     let metrics_handle = metrics::init_metrics();
     info!("Prometheus metrics initialized (GET /metrics)");
 
+    // Built-in dashboard
+    let dashboard = if settings.server.dashboard {
+        info!("Dashboard enabled (GET /dashboard)");
+        dashboard::routes()
+    } else {
+        Router::new()
+    };
+
     // Build HTTP router
     let app = Router::new()
-        // Health and version endpoints
-        .route("/health", get(health))
+        // Liveness, readiness and version endpoints
+        .merge(health_api::routes())
         .route("/version", get(version))
         // Prometheus metrics endpoint
         .merge(metrics::routes(metrics_handle))
@@ -261,36 +459,93 @@ async fn main() -> anyhow::Result<()> {
         .nest("/api/v1/auth", auth_api::routes())
         // Dataset API
         .nest("/api/datasets", dataset_api::routes())
-        // S3-compatible API
-        .nest("/s3", s3_api::routes())
+        // Admin API
+        .nest("/api/v1/admin", admin_api::routes())
+        // Node API
+        .nest("/api/v1/nodes", node_api::routes())
+        // Cluster overview API
+        .nest("/api/v1/cluster", cluster_api::routes())
+        // Object integrity check API
+        .nest("/api/v1/fsck", fsck_api::routes())
+        // Storage statistics API
+        .nest("/api/v1/stats", stats_api::routes())
+        // Storage class transition API
+        .nest("/api/v1/storage-class", storage_class_api::routes())
+        // Bandwidth usage API
+        .nest("/api/v1/usage", usage_api::routes())
         // WebSocket endpoint
         .merge(websocket::routes())
+        // Cluster status dashboard
+        .merge(dashboard)
+        .layer(cors.clone())
+        // S3-compatible API (read-only while the metadata primary is down,
+        // rate limited, bandwidth accounted per user, CORS rules per bucket,
+        // access logged per bucket)
+        .nest(
+            "/s3",
+            s3_api::routes()
+                .layer(axum::middleware::from_fn_with_state(
+                    state.clone(),
+                    s3_api::reject_writes_when_read_only,
+                ))
+                .layer(rate_limit::RateLimitLayer::new(state.clone()))
+                .layer(axum::middleware::from_fn_with_state(
+                    state.clone(),
+                    bandwidth::account_bandwidth,
+                ))
+                .layer(cors.clone())
+                .layer(axum::middleware::from_fn_with_state(
+                    state.clone(),
+                    bucket_cors::apply_bucket_cors,
+                ))
+                .layer(axum::middleware::from_fn_with_state(
+                    state.clone(),
+                    access_log::log_requests,
+                )),
+        )
+        // Public objects, readable without a token (bandwidth billed to the
+        // bucket owner)
+        .nest(
+            "/public/:tenant",
+            s3_api::public_routes()
+                .layer(rate_limit::RateLimitLayer::new(state.clone()))
+                .layer(axum::middleware::from_fn_with_state(
+                    state.clone(),
+                    bandwidth::account_bandwidth,
+                ))
+                .layer(cors)
+                .layer(axum::middleware::from_fn_with_state(
+                    state.clone(),
+                    bucket_cors::apply_bucket_cors,
+                ))
+                .layer(axum::middleware::from_fn_with_state(
+                    state.clone(),
+                    access_log::log_requests,
+                )),
+        )
         // Add middleware
-        .layer(DefaultBodyLimit::max(256 * 1024 * 1024))
+        .layer(axum::middleware::from_fn_with_state(
+            Arc::new(settings.trusted_proxies()),
+            client_ip::resolve_client_ip,
+        ))
+        .layer(axum::middleware::from_fn(request_id::propagate))
+        // Buffered bodies only; S3 object uploads stream under the upload limits
+        .layer(DefaultBodyLimit::max(settings.server.max_body_mb * 1024 * 1024))
         .layer(TraceLayer::new_for_http())
-        .layer(cors)
         .with_state(state.clone());
 
     // Parse addresses
-    let http_addr: SocketAddr = cli.http_addr.parse()?;
-    let grpc_addr: SocketAddr = cli.grpc_addr.parse()?;
+    let http_addr: SocketAddr = settings.server.http_addr.parse()?;
+    let grpc_addr: SocketAddr = settings.server.grpc_addr.parse()?;
 
     // Build TLS config if enabled
-    let tls_server_config = if tls_enabled {
-        Some(TlsServerConfig {
-            cert_path: cli.tls_cert.clone().expect("tls_cert checked above"),
-            key_path: cli.tls_key.clone().expect("tls_key checked above"),
-            ca_cert_path: cli.tls_ca_cert.clone(),
-            require_client_cert: cli.tls_require_client_cert,
-        })
-    } else {
-        None
-    };
+    let tls_server_config = settings.tls_server_config();
 
     // Start gRPC server in separate task
     let grpc_state = state.clone();
-    let enable_grpc_auth = cli.grpc_auth;
+    let enable_grpc_auth = settings.server.grpc_auth;
     let grpc_tls_config = tls_server_config.clone();
+    let max_object_size = state.upload_limits().max_object_size as usize;
     tokio::spawn(async move {
         // Node service for node registration and heartbeat
         let node_service = NodeServiceImpl::new(grpc_state.clone());
@@ -301,6 +556,9 @@ async fn main() -> anyhow::Result<()> {
         // DataStream service for zero-copy ML training data with verification
         let datastream_service = DataStreamServiceImpl::new(grpc_state.clone());
 
+        // Object service for bucket/object management outside the S3 API
+        let object_service = ObjectServiceImpl::new(grpc_state.clone(), max_object_size);
+
         let tls_status = if grpc_tls_config.is_some() {
             "enabled"
         } else {
@@ -336,21 +594,20 @@ async fn main() -> anyhow::Result<()> {
         }
 
         if enable_grpc_auth {
-            // Create auth interceptor
-            let auth_interceptor = AuthInterceptor::new(grpc_state.auth_service_arc());
+            // Authenticate every call before it reaches a service
+            let auth_layer = AuthLayer::new(grpc_state.auth_service_arc());
 
-            // Wrap services with authentication
-            let node_server =
-                NodeServiceServer::with_interceptor(node_service, auth_interceptor.clone());
-            let data_server =
-                DataServiceServer::with_interceptor(data_service, auth_interceptor.clone());
-            let datastream_server =
-                DataStreamServiceServer::with_interceptor(datastream_service, auth_interceptor);
+            let node_server = NodeServiceServer::new(node_service);
+            let data_server = DataServiceServer::new(data_service);
+            let datastream_server = DataStreamServiceServer::new(datastream_service);
+            let object_server = ObjectServiceServer::new(object_service);
 
             if let Err(e) = builder
+                .layer(auth_layer)
                 .add_service(node_server)
                 .add_service(data_server)
                 .add_service(datastream_server)
+                .add_service(object_server)
                 .serve(grpc_addr)
                 .await
             {
@@ -361,11 +618,13 @@ async fn main() -> anyhow::Result<()> {
             let node_server = NodeServiceServer::new(node_service);
             let data_server = DataServiceServer::new(data_service);
             let datastream_server = DataStreamServiceServer::new(datastream_service);
+            let object_server = ObjectServiceServer::new(object_service);
 
             if let Err(e) = builder
                 .add_service(node_server)
                 .add_service(data_server)
                 .add_service(datastream_server)
+                .add_service(object_server)
                 .serve(grpc_addr)
                 .await
             {
@@ -386,7 +645,7 @@ async fn main() -> anyhow::Result<()> {
         info!("HTTPS server listening on {} (TLS enabled)", http_addr);
 
         axum_server::bind_rustls(http_addr, rustls_config)
-            .serve(app.into_make_service())
+            .serve(app.into_make_service_with_connect_info::<SocketAddr>())
             .await?;
     } else {
         // Plain HTTP mode
@@ -405,9 +664,12 @@ async fn main() -> anyhow::Result<()> {
         info!("HTTP server listening on {} (TLS disabled)", http_addr);
         warn!("Running without TLS - use --tls-cert and --tls-key for production");
 
-        axum::serve(http_listener, app)
-            .with_graceful_shutdown(shutdown_signal())
-            .await?;
+        axum::serve(
+            http_listener,
+            app.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .with_graceful_shutdown(shutdown_signal())
+        .await?;
     }
 
     info!("Gateway shutdown complete");
@@ -45,6 +45,21 @@ pub fn record_s3_latency(method: &str, duration_secs: f64) {
     histogram!("s3_request_duration_seconds", "method" => method.to_string()).record(duration_secs);
 }
 
+/// Record a request rejected by the rate limiter
+pub fn record_rate_limited(method: &str) {
+    counter!("s3_requests_throttled_total", "method" => method.to_string()).increment(1);
+}
+
+/// Record a download refused because the user's plan egress cap is used up
+pub fn record_egress_limited(plan: &str) {
+    counter!("s3_egress_limited_total", "plan" => plan.to_string()).increment(1);
+}
+
+/// Record an upload cut off by the body limits (`too_large` or `timeout`)
+pub fn record_upload_rejected(reason: &str) {
+    counter!("s3_uploads_rejected_total", "reason" => reason.to_string()).increment(1);
+}
+
 /// Record bytes uploaded
 pub fn record_bytes_uploaded(bytes: u64) {
     counter!("s3_bytes_uploaded_total").increment(bytes);
@@ -76,7 +91,122 @@ pub fn record_token_revocation() {
 }
 
 /// Record circuit breaker state change
+///
+/// The gauge of the current state is 1, those of the other states 0.
 pub fn record_circuit_breaker_state(name: &str, state: &str) {
-    gauge!("circuit_breaker_state", "name" => name.to_string(), "state" => state.to_string())
-        .set(1.0);
+    for s in ["closed", "open", "half_open"] {
+        gauge!("circuit_breaker_state", "name" => name.to_string(), "state" => s)
+            .set(if s == state { 1.0 } else { 0.0 });
+    }
+}
+
+/// Record a slow shard read that was (or could not be) hedged
+///
+/// `outcome` is `primary_won`, `hedge_won` or `budget_exhausted`.
+pub fn record_hedged_read(outcome: &str) {
+    counter!("node_hedged_reads_total", "outcome" => outcome.to_string()).increment(1);
+}
+
+/// Record chunk data that failed its wire checksum
+///
+/// `direction` is `read` (damaged on the way from a node) or `write`
+/// (rejected by the node it was sent to).
+pub fn record_transport_corruption(direction: &str) {
+    counter!("node_transport_corruption_total", "direction" => direction.to_string()).increment(1);
+}
+
+/// Record a node answering "not found" for a chunk it is listed as holding
+///
+/// `outcome` is `queued` for location repair or `dropped` (queue full).
+pub fn record_location_miss(outcome: &str) {
+    counter!("node_location_misses_total", "outcome" => outcome.to_string()).increment(1);
+}
+
+/// Record what a node status gossip message led to
+///
+/// `action` is the applied status, `storage`, `unchanged`, `unknown_node` or
+/// `source_mismatch`.
+pub fn record_node_gossip(action: &str) {
+    counter!("node_gossip_messages_total", "action" => action.to_string()).increment(1);
+}
+
+/// Record the bandwidth a probe measured from a node
+pub fn set_node_measured_bandwidth(node: &str, mbps: f64) {
+    gauge!("node_measured_bandwidth_mbps", "node" => node.to_string()).set(mbps);
+}
+
+/// Record a bandwidth probe of a node
+pub fn record_bandwidth_probe(success: bool) {
+    let outcome = if success { "success" } else { "failure" };
+    counter!("node_bandwidth_probes_total", "outcome" => outcome).increment(1);
+}
+
+/// Record the outcome of replicating one object
+pub fn record_replication(operation: &str, success: bool) {
+    let outcome = if success { "success" } else { "failure" };
+    counter!(
+        "replication_objects_total",
+        "operation" => operation.to_string(),
+        "outcome" => outcome
+    )
+    .increment(1);
+}
+
+/// Record bytes copied to replication targets
+pub fn record_replication_bytes(bytes: u64) {
+    counter!("replication_bytes_total").increment(bytes);
+}
+
+/// Record the number of objects a replication rule has yet to copy
+pub fn set_replication_backlog(rule: &str, bucket: &str, objects: u64) {
+    gauge!("replication_backlog", "rule" => rule.to_string(), "bucket" => bucket.to_string())
+        .set(objects as f64);
+}
+
+/// Record a node status notification
+///
+/// `action` is `invalidated` (the online node list was dropped), `ignored`
+/// (the online set did not change) or `missed` (the listen connection was
+/// lost, so the list was dropped in case a notification went missing).
+pub fn record_node_status_event(action: &str) {
+    counter!("node_status_events_total", "action" => action.to_string()).increment(1);
+}
+
+/// Record how long ago the online node list placement uses was loaded
+///
+/// 0 while none is loaded: the next placement reads the database.
+pub fn set_placement_nodes_age(secs: f64) {
+    gauge!("placement_nodes_age_seconds").set(secs);
+}
+
+/// Record a sampled download not counted because too many objects were
+/// waiting for the next heat flush
+pub fn record_heat_dropped() {
+    counter!("object_heat_dropped_total").increment(1);
+}
+
+/// Record a finished storage class job run
+///
+/// `kind` is `transition` or `restore`; `outcome` is `done`, `retry` (failed,
+/// to be retried) or `failed` (given up).
+pub fn record_storage_class_job(kind: &str, outcome: &str) {
+    counter!(
+        "storage_class_jobs_total",
+        "kind" => kind.to_string(),
+        "outcome" => outcome.to_string()
+    )
+    .increment(1);
+}
+
+/// Record the queued shards of deleted files that have failed to delete too
+/// often, and are only retried every few hours
+pub fn set_shard_gc_stuck(shards: u64) {
+    gauge!("shard_gc_stuck").set(shards as f64);
+}
+
+/// Record the under-replicated data the last rebalancer scan found one lost
+/// copy away from permanent loss
+pub fn set_data_at_risk(chunks: usize, bytes: u64) {
+    gauge!("rebalancer_chunks_at_risk").set(chunks as f64);
+    gauge!("rebalancer_data_at_risk_bytes").set(bytes as f64);
 }
@@ -0,0 +1,105 @@
+//! Node REST API endpoints
+//!
+//! Provides operator endpoints for:
+//! - Drain progress of a node (chunks evacuated, bytes remaining, ETA)
+//!
+//! All endpoints require a token with the `node:admin` permission.
+
+use crate::admin_api::{require_admin, require_metadata};
+use crate::auth_api::ApiError;
+use crate::AppState;
+use axum::{
+    extract::{Json, Path, State},
+    http::{HeaderMap, StatusCode},
+    routing::get,
+    Router,
+};
+use chrono::{DateTime, Utc};
+use cyxcloud_core::error::HasErrorCode;
+use cyxcloud_metadata::NodeDrain;
+use serde::Serialize;
+use std::sync::Arc;
+use tracing::error;
+use uuid::Uuid;
+
+/// Drain progress of a node
+#[derive(Debug, Serialize)]
+pub struct DrainProgressResponse {
+    pub node_id: Uuid,
+    /// `draining` or `completed`
+    pub state: &'static str,
+    pub reason: Option<String>,
+    pub total_chunks: i64,
+    pub evacuated_chunks: i64,
+    pub remaining_chunks: i64,
+    pub bytes_remaining: i64,
+    /// Evacuation rate so far (bytes/second)
+    pub throughput_bps: u64,
+    /// Seconds until completion at the current throughput (null until measurable)
+    pub eta_secs: Option<u64>,
+    pub started_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+impl From<NodeDrain> for DrainProgressResponse {
+    fn from(drain: NodeDrain) -> Self {
+        Self {
+            node_id: drain.node_id,
+            state: if drain.is_complete() {
+                "completed"
+            } else {
+                "draining"
+            },
+            total_chunks: drain.total_chunks,
+            evacuated_chunks: drain.evacuated_chunks,
+            remaining_chunks: drain.remaining_chunks(),
+            bytes_remaining: drain.bytes_remaining(),
+            throughput_bps: drain.throughput_bps() as u64,
+            eta_secs: drain.eta_secs(),
+            started_at: drain.started_at,
+            updated_at: drain.updated_at,
+            completed_at: drain.completed_at,
+            reason: drain.reason,
+        }
+    }
+}
+
+/// Create node routes
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new().route("/:node_id/drain", get(get_drain_progress))
+}
+
+/// Get drain progress of a node (UUID or peer ID)
+async fn get_drain_progress(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(node_id): Path<String>,
+) -> Result<Json<DrainProgressResponse>, (StatusCode, Json<ApiError>)> {
+    require_admin(&headers, state.auth_service()).await?;
+    let metadata = require_metadata(&state)?;
+
+    let drain = metadata
+        .drain_progress(&node_id)
+        .await
+        .map_err(|e| {
+            error!(error = %e, node_id = %node_id, "Drain progress lookup failed");
+            let code = e.error_code();
+            (
+                StatusCode::from_u16(code.http_status())
+                    .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
+                Json(ApiError::new(e.to_string(), code.as_str())),
+            )
+        })?
+        .ok_or_else(|| {
+            (
+                StatusCode::NOT_FOUND,
+                Json(ApiError::new(
+                    format!("Node {} has not been drained", node_id),
+                    "NOT_FOUND",
+                )),
+            )
+        })?;
+
+    Ok(Json(drain.into()))
+}
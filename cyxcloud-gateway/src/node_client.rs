@@ -2,17 +2,45 @@
 //!
 //! Manages connections to storage nodes for chunk operations.
 //! Uses the ChunkService gRPC interface defined in cyxcloud-protocol.
+//!
+//! Reads from replicated chunks are hedged: if the first node has not answered
+//! within the recent P95 read latency, the same fetch goes to a second replica
+//! and the first answer wins. The slower request is dropped, which cancels its
+//! gRPC stream. A token budget caps hedges at a fraction of reads so a
+//! cluster-wide slowdown does not turn into double the load.
+//!
+//! Chunk data carries a wire checksum in both directions. A chunk that
+//! arrives damaged is a [`NodeClientError::TransportCorruption`], which the
+//! replica reads treat like any other failed node and move on to the next
+//! replica; a store the node rejects as damaged is sent once more.
+//!
+//! A replica read answered with "not found" means metadata lists a location
+//! the node does not have. Each such answer is queued as a [`LocationMiss`]
+//! for the location repair daemon, which corrects the record.
+//!
+//! Stores feed a circuit breaker per node: after consecutive failed stores a
+//! node is reported as not accepting writes until its recovery timeout has
+//! passed, so uploads place shards elsewhere in the meantime.
 
 #![allow(unused_imports)]
 
+use crate::metrics;
 use bytes::Bytes;
+use cyxcloud_core::chunk::{verify_wire_checksum, wire_checksum};
+use cyxcloud_core::circuit_breaker::{CircuitBreaker, CircuitBreakerConfig, CircuitState};
+use cyxcloud_core::error::{ErrorCode, HasErrorCode};
+#[cfg(feature = "fault-injection")]
+use cyxcloud_network::fault::{Fault, FaultInjector};
+use cyxcloud_network::grpc_server::CLUSTER_TOKEN_METADATA;
 use cyxcloud_protocol::chunk::{
-    chunk_service_client::ChunkServiceClient, ChunkMetadata as ProtoChunkMetadata, GetChunkRequest,
-    StoreChunkRequest,
+    chunk_service_client::ChunkServiceClient, ChunkMetadata as ProtoChunkMetadata,
+    DeleteChunkRequest, GetChunkRequest, ProbeBandwidthRequest, StoreChunkRequest,
+    VerifyChunkRequest,
 };
-use std::collections::HashMap;
-use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, RwLock};
 use tonic::transport::Channel;
 use tracing::{debug, error, info, warn};
 
@@ -31,6 +59,9 @@ pub enum NodeClientError {
     #[error("No nodes available")]
     NoNodesAvailable,
 
+    #[error("Chunk corrupted in transit: {0}")]
+    TransportCorruption(String),
+
     #[error("All nodes failed to store chunk")]
     AllNodesFailed,
 
@@ -41,6 +72,25 @@ pub enum NodeClientError {
     TransportError(#[from] tonic::transport::Error),
 }
 
+impl HasErrorCode for NodeClientError {
+    fn error_code(&self) -> ErrorCode {
+        match self {
+            NodeClientError::ConnectionFailed(_)
+            | NodeClientError::StoreFailed(_)
+            | NodeClientError::AllNodesFailed
+            | NodeClientError::TransportError(_) => ErrorCode::NodeUnreachable,
+            NodeClientError::ChunkNotFound(_) => ErrorCode::NotFound,
+            NodeClientError::NoNodesAvailable => ErrorCode::NoNodesAvailable,
+            NodeClientError::TransportCorruption(_) => ErrorCode::TransportCorruption,
+            NodeClientError::GrpcError(status) => match ErrorCode::from_status(status) {
+                // A bare UNAVAILABLE from a storage node means the node is down
+                ErrorCode::ServiceUnavailable => ErrorCode::NodeUnreachable,
+                code => code,
+            },
+        }
+    }
+}
+
 /// Configuration for the node client
 #[derive(Debug, Clone)]
 pub struct NodeClientConfig {
@@ -61,6 +111,31 @@ pub struct NodeClientConfig {
 
     /// Evict connections unused for this many seconds
     pub stale_connection_secs: u64,
+
+    /// Send slow reads to a second replica as well
+    pub hedge_reads: bool,
+
+    /// Hedge delay in milliseconds until enough latencies are sampled; also
+    /// the lower bound for the observed delay
+    pub hedge_min_delay_ms: u64,
+
+    /// Read latency percentile after which a read is hedged
+    pub hedge_percentile: f64,
+
+    /// Fraction of reads allowed to issue a hedge
+    pub hedge_max_ratio: f64,
+
+    /// Location misses queued for repair; further misses are dropped while full
+    pub miss_queue_size: usize,
+
+    /// Shared cluster secret presented to nodes on DeleteChunk
+    pub cluster_token: Option<String>,
+
+    /// Consecutive failed stores after which a node stops getting writes
+    pub write_failure_threshold: u64,
+
+    /// Seconds until a node that stopped getting writes is tried again
+    pub write_recovery_secs: u64,
 }
 
 impl Default for NodeClientConfig {
@@ -72,10 +147,82 @@ impl Default for NodeClientConfig {
             write_replicas: 3, // Store each chunk on 3 nodes
             max_connections: 100,
             stale_connection_secs: 300, // 5 minutes
+            hedge_reads: true,
+            hedge_min_delay_ms: 20,
+            hedge_percentile: 0.95,
+            hedge_max_ratio: 0.1, // At most 10% extra reads
+            miss_queue_size: 1024,
+            cluster_token: None,
+            write_failure_threshold: 3,
+            write_recovery_secs: 30,
+        }
+    }
+}
+
+/// Read latencies kept for the hedge delay
+const LATENCY_WINDOW: usize = 512;
+
+/// Samples needed before the observed percentile replaces the minimum delay
+const MIN_LATENCY_SAMPLES: usize = 32;
+
+/// Hedges that may be issued back to back once the budget has filled up
+const MAX_HEDGE_BURST: f64 = 10.0;
+
+/// Recent read latencies and the hedge budget
+struct HedgeState {
+    latencies: VecDeque<Duration>,
+    tokens: f64,
+}
+
+impl HedgeState {
+    fn new() -> Self {
+        Self {
+            latencies: VecDeque::with_capacity(LATENCY_WINDOW),
+            tokens: MAX_HEDGE_BURST,
+        }
+    }
+
+    fn record(&mut self, latency: Duration) {
+        if self.latencies.len() == LATENCY_WINDOW {
+            self.latencies.pop_front();
+        }
+        self.latencies.push_back(latency);
+    }
+
+    /// How long to wait for the first node before hedging
+    fn delay(&self, percentile: f64, min: Duration) -> Duration {
+        if self.latencies.len() < MIN_LATENCY_SAMPLES {
+            return min;
+        }
+        let mut sorted: Vec<Duration> = self.latencies.iter().copied().collect();
+        let rank = ((sorted.len() - 1) as f64 * percentile.clamp(0.0, 1.0)).round() as usize;
+        let (_, p, _) = sorted.select_nth_unstable(rank);
+        (*p).max(min)
+    }
+
+    /// Credit the budget for one read
+    fn earn(&mut self, ratio: f64) {
+        self.tokens = (self.tokens + ratio).min(MAX_HEDGE_BURST);
+    }
+
+    /// Spend one hedge from the budget
+    fn spend(&mut self) -> bool {
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
         }
     }
 }
 
+/// A node that answered "not found" for a chunk metadata lists it as holding
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LocationMiss {
+    pub node_address: String,
+    pub chunk_id: Vec<u8>,
+}
+
 /// A pooled connection with last-used tracking
 struct PooledConnection {
     client: ChunkServiceClient<Channel>,
@@ -89,22 +236,63 @@ pub struct NodeClient {
 
     /// Connection pool: node_address -> pooled connection
     connections: RwLock<HashMap<String, PooledConnection>>,
+
+    /// Read latency window and hedge budget
+    hedge: Mutex<HedgeState>,
+
+    /// Queue of location misses found by replica reads
+    misses: mpsc::Sender<LocationMiss>,
+
+    /// Receiving end of the miss queue, until the repair daemon takes it
+    miss_receiver: Mutex<Option<mpsc::Receiver<LocationMiss>>>,
+
+    /// Circuit breakers of stores, by node address
+    write_breakers: Mutex<HashMap<String, Arc<CircuitBreaker>>>,
+
+    /// Faults injected into calls to storage nodes
+    #[cfg(feature = "fault-injection")]
+    faults: Arc<FaultInjector>,
 }
 
 impl NodeClient {
     /// Create a new node client
     pub fn new(config: NodeClientConfig) -> Self {
+        let (misses, miss_receiver) = mpsc::channel(config.miss_queue_size.max(1));
         Self {
             config,
             connections: RwLock::new(HashMap::new()),
+            hedge: Mutex::new(HedgeState::new()),
+            misses,
+            miss_receiver: Mutex::new(Some(miss_receiver)),
+            write_breakers: Mutex::new(HashMap::new()),
+            #[cfg(feature = "fault-injection")]
+            faults: Arc::new(FaultInjector::from_env()),
         }
     }
 
+    /// Faults injected into calls to storage nodes
+    #[cfg(feature = "fault-injection")]
+    pub fn faults(&self) -> Arc<FaultInjector> {
+        self.faults.clone()
+    }
+
     /// Get or create a connection to a storage node
     pub async fn get_connection(
         &self,
         address: &str,
     ) -> Result<ChunkServiceClient<Channel>, NodeClientError> {
+        // An injected reset drops the pooled connection like a real one would
+        #[cfg(feature = "fault-injection")]
+        if let Some(fault) = self.faults.before_call(address).await {
+            if fault == Fault::ConnectionReset {
+                self.close_connection(address).await;
+            }
+            return Err(NodeClientError::ConnectionFailed(format!(
+                "{}: {}",
+                address, fault
+            )));
+        }
+
         // Check if we have an existing connection
         {
             let mut connections = self.connections.write().await;
@@ -173,12 +361,30 @@ impl NodeClient {
     }
 
     /// Store a chunk on a storage node
+    ///
+    /// Sent a second time if the node reports the data arrived damaged. The
+    /// outcome counts towards the node's store circuit breaker.
     pub async fn store_chunk(
         &self,
         node_address: &str,
         chunk_id: &[u8],
         data: Bytes,
         metadata: Option<ChunkMeta>,
+    ) -> Result<(), NodeClientError> {
+        let result = self
+            .send_chunk(node_address, chunk_id, data, metadata)
+            .await;
+        self.record_store(node_address, result.is_ok());
+        result
+    }
+
+    /// Send a StoreChunk request, resending it once if it arrived damaged
+    async fn send_chunk(
+        &self,
+        node_address: &str,
+        chunk_id: &[u8],
+        data: Bytes,
+        metadata: Option<ChunkMeta>,
     ) -> Result<(), NodeClientError> {
         let mut client = self.get_connection(node_address).await?;
 
@@ -194,15 +400,25 @@ impl NodeClient {
             created_at: m.created_at,
             encrypted: m.encrypted,
             shard_index: m.shard_index.unwrap_or(0),
+            is_parity: m.is_parity,
+            original_size: m.original_size,
         });
 
         let request = StoreChunkRequest {
             chunk_id: chunk_id.to_vec(),
-            data: data.to_vec(),
+            checksum: wire_checksum(&data),
+            data,
             metadata: proto_metadata,
         };
 
-        let response = client.store_chunk(request).await?;
+        let response = match client.store_chunk(request.clone()).await {
+            Err(status) if ErrorCode::from_status(&status) == ErrorCode::TransportCorruption => {
+                warn!(node = %node_address, chunk_id = %hex::encode(chunk_id), "Chunk corrupted in transit, resending");
+                metrics::record_transport_corruption("write");
+                client.store_chunk(request).await?
+            }
+            response => response?,
+        };
         let inner = response.into_inner();
 
         if inner.success {
@@ -214,6 +430,57 @@ impl NodeClient {
         }
     }
 
+    /// Whether a node is accepting writes, i.e. its store circuit is not open
+    ///
+    /// Once the recovery timeout has passed this lets a trial write through.
+    pub fn accepts_writes(&self, node_address: &str) -> bool {
+        let breaker = self.write_breakers().get(node_address).cloned();
+        breaker.map_or(true, |b| b.allow_request())
+    }
+
+    /// Feed the outcome of a store into the node's circuit breaker
+    fn record_store(&self, node_address: &str, success: bool) {
+        let breaker = self
+            .write_breakers()
+            .entry(node_address.to_string())
+            .or_insert_with(|| {
+                Arc::new(CircuitBreaker::new(CircuitBreakerConfig {
+                    failure_threshold: self.config.write_failure_threshold.max(1),
+                    recovery_timeout: Duration::from_secs(self.config.write_recovery_secs),
+                    name: format!("node_write:{}", node_address),
+                }))
+            })
+            .clone();
+
+        let before = breaker.state();
+        if success {
+            breaker.record_success();
+        } else {
+            breaker.record_failure();
+        }
+        let after = breaker.state();
+        if after == before {
+            return;
+        }
+
+        match after {
+            CircuitState::Open => warn!(
+                node = %node_address,
+                failures = breaker.failure_count(),
+                "Node failing stores, no longer placing writes on it"
+            ),
+            CircuitState::Closed => info!(node = %node_address, "Node accepting writes again"),
+            CircuitState::HalfOpen => {}
+        }
+        metrics::record_circuit_breaker_state(breaker.name(), circuit_state_name(after));
+    }
+
+    fn write_breakers(&self) -> std::sync::MutexGuard<'_, HashMap<String, Arc<CircuitBreaker>>> {
+        self.write_breakers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
     /// Retrieve a chunk from a storage node
     pub async fn get_chunk(
         &self,
@@ -230,13 +497,114 @@ impl NodeClient {
         let inner = response.into_inner();
 
         if inner.found {
+            if let Err(e) = verify_wire_checksum(&inner.data, inner.checksum) {
+                warn!(node = %node_address, chunk_id = %hex::encode(chunk_id), error = %e, "Chunk corrupted in transit");
+                metrics::record_transport_corruption("read");
+                return Err(NodeClientError::TransportCorruption(e.to_string()));
+            }
             debug!(node = %node_address, chunk_id = %hex::encode(chunk_id), "Chunk retrieved");
-            Ok(Bytes::from(inner.data))
+            Ok(inner.data)
         } else {
             Err(NodeClientError::ChunkNotFound(hex::encode(chunk_id)))
         }
     }
 
+    /// Ask a storage node to check a chunk against its content hash
+    ///
+    /// Returns false if the node does not have the chunk or its data is corrupt.
+    pub async fn verify_chunk(
+        &self,
+        node_address: &str,
+        chunk_id: &[u8],
+    ) -> Result<bool, NodeClientError> {
+        let mut client = self.get_connection(node_address).await?;
+
+        let request = VerifyChunkRequest {
+            chunk_id: chunk_id.to_vec(),
+        };
+
+        let valid = client.verify_chunk(request).await?.into_inner().valid;
+        if !valid {
+            debug!(node = %node_address, chunk_id = %hex::encode(chunk_id), "Chunk failed verification");
+        }
+        Ok(valid)
+    }
+
+    /// Measure the throughput from a storage node by timing a stream of
+    /// `size` random bytes
+    ///
+    /// The clock starts when the first frame arrives, so connection setup
+    /// and the node's admission queue do not count against its link.
+    pub async fn probe_bandwidth(
+        &self,
+        node_address: &str,
+        size: u64,
+    ) -> Result<BandwidthProbe, NodeClientError> {
+        let mut client = self.get_connection(node_address).await?;
+
+        let request = ProbeBandwidthRequest {
+            size,
+            frame_size: 0,
+        };
+        let mut stream = client.probe_bandwidth(request).await?.into_inner();
+
+        let mut started = None;
+        let mut bytes = 0;
+        while let Some(frame) = stream.message().await? {
+            // The first frame only starts the clock
+            match started {
+                None => started = Some(Instant::now()),
+                Some(_) => bytes += frame.data.len() as u64,
+            }
+        }
+        let elapsed = started.map_or(Duration::ZERO, |started| started.elapsed());
+        if bytes == 0 || elapsed.is_zero() {
+            return Err(NodeClientError::ConnectionFailed(format!(
+                "bandwidth probe of {} too short to measure",
+                node_address
+            )));
+        }
+
+        let probe = BandwidthProbe { bytes, elapsed };
+        debug!(
+            node = %node_address,
+            bytes,
+            elapsed_ms = elapsed.as_millis() as u64,
+            mbps = probe.mbps(),
+            "Bandwidth probed"
+        );
+        Ok(probe)
+    }
+
+    /// Delete a chunk from a storage node
+    ///
+    /// Returns whether the node reported the chunk as deleted.
+    pub async fn delete_chunk(
+        &self,
+        node_address: &str,
+        chunk_id: &[u8],
+    ) -> Result<bool, NodeClientError> {
+        let mut client = self.get_connection(node_address).await?;
+
+        let mut request = tonic::Request::new(DeleteChunkRequest {
+            chunk_id: chunk_id.to_vec(),
+        });
+        if let Some(token) = self
+            .config
+            .cluster_token
+            .as_deref()
+            .and_then(|t| t.parse().ok())
+        {
+            request.metadata_mut().insert(CLUSTER_TOKEN_METADATA, token);
+        }
+
+        let response = client.delete_chunk(request).await?;
+        let deleted = response.into_inner().deleted;
+
+        debug!(node = %node_address, chunk_id = %hex::encode(chunk_id), deleted, "Chunk delete requested");
+        Ok(deleted)
+    }
+
     /// Store a chunk on multiple nodes for redundancy
     pub async fn store_chunk_replicated(
         &self,
@@ -254,7 +622,7 @@ impl NodeClient {
 
         for address in node_addresses.iter().take(self.config.write_replicas) {
             match self
-                .store_chunk(address, chunk_id, data.clone(), metadata.clone())
+                .store_chunk(address, chunk_id, data.clone(), metadata)
                 .await
             {
                 Ok(()) => {
@@ -275,6 +643,10 @@ impl NodeClient {
     }
 
     /// Retrieve a chunk from any available node
+    ///
+    /// The first two nodes are raced when hedging is enabled; the rest are
+    /// tried in order if both fail. The nodes are the chunk's recorded
+    /// locations, so any that answers "not found" is queued as a miss.
     pub async fn get_chunk_from_any(
         &self,
         node_addresses: &[String],
@@ -285,9 +657,21 @@ impl NodeClient {
         }
 
         let mut last_error = None;
+        let mut remaining = node_addresses;
+
+        if self.config.hedge_reads && node_addresses.len() > 1 {
+            let (result, tried) = self
+                .get_chunk_hedged(&node_addresses[0], &node_addresses[1], chunk_id)
+                .await;
+            match result {
+                Ok(data) => return Ok(data),
+                Err(e) => last_error = Some(e),
+            }
+            remaining = &node_addresses[tried..];
+        }
 
-        for address in node_addresses {
-            match self.get_chunk(address, chunk_id).await {
+        for address in remaining {
+            match self.get_listed_chunk(address, chunk_id).await {
                 Ok(data) => return Ok(data),
                 Err(e) => {
                     warn!(node = %address, error = %e, "Failed to get chunk from node");
@@ -299,6 +683,130 @@ impl NodeClient {
         Err(last_error.unwrap_or(NodeClientError::ChunkNotFound(hex::encode(chunk_id))))
     }
 
+    /// Fetch a chunk from `primary`, hedging to `backup` if it is slow
+    ///
+    /// Returns the result and how many of the two nodes were tried. The
+    /// request that loses the race is dropped, cancelling it.
+    async fn get_chunk_hedged(
+        &self,
+        primary: &str,
+        backup: &str,
+        chunk_id: &[u8],
+    ) -> (Result<Bytes, NodeClientError>, usize) {
+        let delay = {
+            let mut hedge = self.hedge_state();
+            hedge.earn(self.config.hedge_max_ratio);
+            hedge.delay(
+                self.config.hedge_percentile,
+                Duration::from_millis(self.config.hedge_min_delay_ms),
+            )
+        };
+
+        let first = self.timed_get_chunk(primary, chunk_id);
+        tokio::pin!(first);
+
+        tokio::select! {
+            result = &mut first => return (result, 1),
+            _ = tokio::time::sleep(delay) => {}
+        }
+
+        if !self.hedge_state().spend() {
+            metrics::record_hedged_read("budget_exhausted");
+            return (first.await, 1);
+        }
+
+        debug!(
+            primary = %primary,
+            backup = %backup,
+            chunk_id = %hex::encode(chunk_id),
+            delay_ms = delay.as_millis() as u64,
+            "Hedging slow chunk read"
+        );
+
+        let second = self.timed_get_chunk(backup, chunk_id);
+        tokio::pin!(second);
+
+        let result = tokio::select! {
+            result = &mut first => match result {
+                Ok(data) => {
+                    metrics::record_hedged_read("primary_won");
+                    Ok(data)
+                }
+                Err(e) => {
+                    warn!(node = %primary, error = %e, "Failed to get chunk from node");
+                    second.await
+                }
+            },
+            result = &mut second => match result {
+                Ok(data) => {
+                    metrics::record_hedged_read("hedge_won");
+                    Ok(data)
+                }
+                Err(e) => {
+                    warn!(node = %backup, error = %e, "Failed to get chunk from node");
+                    first.await
+                }
+            },
+        };
+        (result, 2)
+    }
+
+    /// `get_chunk`, recording the latency of successful reads
+    async fn timed_get_chunk(
+        &self,
+        node_address: &str,
+        chunk_id: &[u8],
+    ) -> Result<Bytes, NodeClientError> {
+        let start = Instant::now();
+        let result = self.get_listed_chunk(node_address, chunk_id).await;
+        if result.is_ok() {
+            self.hedge_state().record(start.elapsed());
+        }
+        result
+    }
+
+    /// `get_chunk` from a node recorded as holding the chunk, queueing a
+    /// location miss if it does not have it
+    async fn get_listed_chunk(
+        &self,
+        node_address: &str,
+        chunk_id: &[u8],
+    ) -> Result<Bytes, NodeClientError> {
+        let result = self.get_chunk(node_address, chunk_id).await;
+        if let Err(NodeClientError::ChunkNotFound(_)) = result {
+            self.report_miss(node_address, chunk_id);
+        }
+        result
+    }
+
+    /// Queue a location miss, dropping it if the queue is full
+    fn report_miss(&self, node_address: &str, chunk_id: &[u8]) {
+        let miss = LocationMiss {
+            node_address: node_address.to_string(),
+            chunk_id: chunk_id.to_vec(),
+        };
+        let outcome = match self.misses.try_send(miss) {
+            Ok(()) => "queued",
+            Err(_) => "dropped",
+        };
+        debug!(node = %node_address, chunk_id = %hex::encode(chunk_id), outcome, "Chunk location miss");
+        metrics::record_location_miss(outcome);
+    }
+
+    /// Take the receiving end of the location miss queue
+    ///
+    /// Returns None after the first call: misses have a single consumer.
+    pub fn take_location_misses(&self) -> Option<mpsc::Receiver<LocationMiss>> {
+        self.miss_receiver
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take()
+    }
+
+    fn hedge_state(&self) -> std::sync::MutexGuard<'_, HedgeState> {
+        self.hedge.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
     /// Close a specific connection
     pub async fn close_connection(&self, address: &str) {
         let mut connections = self.connections.write().await;
@@ -320,8 +828,37 @@ impl NodeClient {
     }
 }
 
+/// Metrics label of a circuit breaker state
+fn circuit_state_name(state: CircuitState) -> &'static str {
+    match state {
+        CircuitState::Closed => "closed",
+        CircuitState::Open => "open",
+        CircuitState::HalfOpen => "half_open",
+    }
+}
+
+/// Result of a bandwidth probe
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BandwidthProbe {
+    /// Bytes timed
+    pub bytes: u64,
+    /// Time they took to arrive
+    pub elapsed: Duration,
+}
+
+impl BandwidthProbe {
+    /// Measured bandwidth in Mbit/s
+    pub fn mbps(&self) -> f64 {
+        let secs = self.elapsed.as_secs_f64();
+        if secs <= 0.0 {
+            return 0.0;
+        }
+        self.bytes as f64 * 8.0 / 1_000_000.0 / secs
+    }
+}
+
 /// Simplified chunk metadata for client operations
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy)]
 pub struct ChunkMeta {
     pub size: u64,
     pub index: u32,
@@ -330,6 +867,10 @@ pub struct ChunkMeta {
     pub created_at: i64,
     pub encrypted: bool,
     pub shard_index: Option<u32>,
+    /// Parity (vs data) shard, recorded in the node's shard header
+    pub is_parity: bool,
+    /// Size of the chunk before erasure coding
+    pub original_size: u64,
 }
 
 impl From<&cyxcloud_core::ChunkMetadata> for ChunkMeta {
@@ -342,6 +883,8 @@ impl From<&cyxcloud_core::ChunkMetadata> for ChunkMeta {
             created_at: meta.created_at,
             encrypted: meta.encrypted,
             shard_index: meta.shard_index.map(|i| i as u32),
+            is_parity: false,
+            original_size: meta.size,
         }
     }
 }
@@ -350,6 +893,20 @@ impl From<&cyxcloud_core::ChunkMetadata> for ChunkMeta {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_bandwidth_probe_mbps() {
+        let probe = BandwidthProbe {
+            bytes: 12_500_000,
+            elapsed: Duration::from_secs(1),
+        };
+        assert_eq!(probe.mbps(), 100.0);
+        let instant = BandwidthProbe {
+            bytes: 1,
+            elapsed: Duration::ZERO,
+        };
+        assert_eq!(instant.mbps(), 0.0);
+    }
+
     #[test]
     fn test_config_default() {
         let config = NodeClientConfig::default();
@@ -359,6 +916,99 @@ mod tests {
         assert_eq!(config.stale_connection_secs, 300);
     }
 
+    #[test]
+    fn test_failing_node_stops_accepting_writes() {
+        let client = NodeClient::new(NodeClientConfig {
+            write_failure_threshold: 2,
+            write_recovery_secs: 3600,
+            ..Default::default()
+        });
+        let node = "10.0.0.1:50051";
+        assert!(client.accepts_writes(node));
+
+        // A success in between resets the streak
+        client.record_store(node, false);
+        client.record_store(node, true);
+        client.record_store(node, false);
+        assert!(client.accepts_writes(node));
+
+        client.record_store(node, false);
+        assert!(!client.accepts_writes(node));
+        assert!(client.accepts_writes("10.0.0.2:50051"));
+    }
+
+    #[test]
+    fn test_location_misses_queue() {
+        let client = NodeClient::new(NodeClientConfig {
+            miss_queue_size: 2,
+            ..Default::default()
+        });
+        for chunk in 0..3u8 {
+            client.report_miss("10.0.0.1:50051", &[chunk]);
+        }
+
+        let mut misses = client.take_location_misses().unwrap();
+        assert!(client.take_location_misses().is_none());
+
+        // The third miss found the queue full
+        assert_eq!(misses.try_recv().unwrap().chunk_id, vec![0]);
+        assert_eq!(misses.try_recv().unwrap().chunk_id, vec![1]);
+        assert!(misses.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_hedge_delay_tracks_percentile() {
+        let min = Duration::from_millis(20);
+        let mut state = HedgeState::new();
+        assert_eq!(state.delay(0.95, min), min);
+
+        for ms in 1..=100 {
+            state.record(Duration::from_millis(ms));
+        }
+        assert_eq!(state.delay(0.95, min), Duration::from_millis(95));
+        assert_eq!(state.delay(0.10, min), min);
+
+        // Only the most recent window counts
+        for _ in 0..LATENCY_WINDOW {
+            state.record(Duration::from_millis(200));
+        }
+        assert_eq!(state.delay(0.95, min), Duration::from_millis(200));
+    }
+
+    #[test]
+    fn test_hedge_budget() {
+        let mut state = HedgeState::new();
+        let mut hedges = 0;
+        while state.spend() {
+            hedges += 1;
+        }
+        assert_eq!(hedges, MAX_HEDGE_BURST as usize);
+
+        // 10% ratio: roughly one hedge per ten reads
+        for _ in 0..5 {
+            state.earn(0.1);
+            assert!(!state.spend());
+        }
+        for _ in 0..10 {
+            state.earn(0.1);
+        }
+        assert!(state.spend());
+        assert!(!state.spend());
+    }
+
+    #[test]
+    fn test_transport_corruption_error_code() {
+        let err = NodeClientError::TransportCorruption("checksum mismatch".to_string());
+        assert_eq!(err.error_code(), ErrorCode::TransportCorruption);
+        assert!(err.error_code().is_retryable());
+
+        let status = ErrorCode::TransportCorruption.to_status("damaged", None);
+        assert_eq!(
+            NodeClientError::GrpcError(status).error_code(),
+            ErrorCode::TransportCorruption
+        );
+    }
+
     #[test]
     fn test_chunk_meta_conversion() {
         let core_meta = cyxcloud_core::ChunkMetadata::new(
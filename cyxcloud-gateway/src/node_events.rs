@@ -0,0 +1,205 @@
+//! Node Status Events
+//!
+//! Placement picks nodes from an online node list each gateway keeps in
+//! memory for up to a minute. Node status changes are made by whichever
+//! gateway's node monitor, heartbeat handler or gossip subscriber noticed
+//! them, so the other gateways would keep placing shards on a node that just
+//! went offline (or ignore one that came back) until their list expired.
+//!
+//! A trigger on `nodes` notifies every status and address change on
+//! [`NODE_STATUS_CHANNEL`]. This listener drops the gateway's online node
+//! list whenever a node joins or leaves the online set, so the next upload
+//! reloads it, typically within a second of the change.
+//!
+//! Notifications sent while the listen connection is down are lost, so the
+//! list is also dropped whenever the listener (re)connects. The list's age is
+//! exported as `placement_nodes_age_seconds`.
+
+use crate::state::AppState;
+use cyxcloud_metadata::{MetadataService, NodeStatusEvent, NODE_STATUS_CHANNEL};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio::time::interval;
+use tracing::{debug, info, warn};
+
+/// How often the age of the online node list is exported
+const AGE_REPORT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Node status event configuration
+#[derive(Debug, Clone)]
+pub struct NodeEventsConfig {
+    /// Listen for node status notifications
+    pub enabled: bool,
+    /// Wait before reconnecting after the listen connection failed
+    pub reconnect_delay: Duration,
+}
+
+impl Default for NodeEventsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            reconnect_delay: Duration::from_secs(5),
+        }
+    }
+}
+
+impl NodeEventsConfig {
+    /// Create configuration from environment variables
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            enabled: std::env::var("NODE_EVENTS_ENABLED")
+                .map(|v| v == "true" || v == "1")
+                .unwrap_or(defaults.enabled),
+            reconnect_delay: Duration::from_secs(
+                std::env::var("NODE_EVENTS_RECONNECT_SECS")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(defaults.reconnect_delay.as_secs()),
+            ),
+        }
+    }
+}
+
+/// Whether a notification payload may change the online node list
+///
+/// Payloads that cannot be parsed count as a change: dropping the list
+/// only costs a reload.
+fn changes_placement(payload: &str) -> bool {
+    match serde_json::from_str::<NodeStatusEvent>(payload) {
+        Ok(event) => {
+            debug!(
+                node_id = %event.node_id,
+                old_status = ?event.old_status,
+                status = ?event.status,
+                "Node status notification"
+            );
+            event.changes_placement()
+        }
+        Err(e) => {
+            warn!(error = %e, payload = %payload, "Unreadable node status notification");
+            true
+        }
+    }
+}
+
+/// Listener keeping the online node list in step with node status changes
+pub struct NodeEventListener {
+    config: NodeEventsConfig,
+}
+
+impl NodeEventListener {
+    /// Create a new listener
+    pub fn new(config: NodeEventsConfig) -> Self {
+        Self { config }
+    }
+
+    /// Start listening as a background task
+    ///
+    /// Returns None without a metadata service.
+    pub fn start(self: Arc<Self>, state: Arc<AppState>) -> Option<JoinHandle<()>> {
+        state.metadata_service()?;
+        let listener = self;
+
+        let age_state = state.clone();
+        tokio::spawn(async move {
+            let mut timer = interval(AGE_REPORT_INTERVAL);
+            loop {
+                timer.tick().await;
+                if let Some(metadata) = age_state.metadata_service() {
+                    let age = metadata.online_nodes_age().unwrap_or_default();
+                    crate::metrics::set_placement_nodes_age(age.as_secs_f64());
+                }
+            }
+        });
+
+        Some(tokio::spawn(async move {
+            info!(
+                channel = NODE_STATUS_CHANNEL,
+                "Node status listener started"
+            );
+            loop {
+                if let Some(metadata) = state.metadata_service() {
+                    listener.listen(metadata).await;
+                }
+                tokio::time::sleep(listener.config.reconnect_delay).await;
+            }
+        }))
+    }
+
+    /// Apply notifications until the listen connection fails for good
+    async fn listen(&self, metadata: &MetadataService) {
+        let mut pg_listener = match metadata.database().listen_node_status().await {
+            Ok(pg_listener) => pg_listener,
+            Err(e) => {
+                warn!(error = %e, "Failed to listen for node status changes");
+                return;
+            }
+        };
+        // Changes made while not listening were missed
+        metadata.invalidate_online_nodes();
+
+        loop {
+            match pg_listener.try_recv().await {
+                Ok(Some(notification)) => {
+                    if changes_placement(notification.payload()) {
+                        metadata.invalidate_online_nodes();
+                        crate::metrics::record_node_status_event("invalidated");
+                    } else {
+                        crate::metrics::record_node_status_event("ignored");
+                    }
+                }
+                Ok(None) => {
+                    // The next try_recv reconnects
+                    warn!("Node status listen connection lost, reconnecting");
+                    metadata.invalidate_online_nodes();
+                    crate::metrics::record_node_status_event("missed");
+                }
+                Err(e) => {
+                    warn!(error = %e, "Node status listener failed");
+                    metadata.invalidate_online_nodes();
+                    crate::metrics::record_node_status_event("missed");
+                    return;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_changes_placement() {
+        let payload = |old: Option<&str>, new: Option<&str>| {
+            serde_json::json!({
+                "node_id": uuid::Uuid::new_v4(),
+                "old_status": old,
+                "status": new,
+            })
+            .to_string()
+        };
+
+        assert!(changes_placement(&payload(Some("online"), Some("offline"))));
+        assert!(changes_placement(&payload(
+            Some("recovering"),
+            Some("online")
+        )));
+        assert!(changes_placement(&payload(None, Some("online"))));
+        assert!(!changes_placement(&payload(
+            Some("offline"),
+            Some("recovering")
+        )));
+        assert!(!changes_placement(&payload(Some("offline"), None)));
+        assert!(changes_placement("not json"));
+    }
+
+    #[test]
+    fn test_config_default() {
+        let config = NodeEventsConfig::default();
+        assert!(config.enabled);
+        assert_eq!(config.reconnect_delay, Duration::from_secs(5));
+    }
+}
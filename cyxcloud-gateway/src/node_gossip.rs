@@ -0,0 +1,412 @@
+//! Node Status Gossip Subscriber
+//!
+//! Joins the nodes' libp2p network and listens for the status announcements
+//! they gossip whenever they start draining, shut down or their usage moves.
+//! The gateway would otherwise only notice these changes on the next
+//! heartbeat (or, for a node that vanished, after the offline timeout), so
+//! placement keeps choosing nodes that are already on their way out.
+//!
+//! Gossip is treated as a hint, never as a substitute for heartbeats:
+//! - only announcements newer than the node's last heartbeat change its status
+//! - gossip can take a node out of placement (draining, maintenance, offline)
+//!   but never bring one back online
+//! - the announced gRPC address must match the registered one, and each node
+//!   ID is pinned to the first libp2p peer heard announcing it
+//! - unknown nodes and old announcements are ignored
+
+use crate::node_monitor::NodeMonitor;
+use crate::state::AppState;
+use chrono::{DateTime, Utc};
+use cyxcloud_metadata::{MetadataService, Node, NodeStatus};
+use cyxcloud_network::protocol::NodeStatus as GossipStatus;
+use cyxcloud_network::{
+    DiscoveryConfig, DiscoveryEvent, DiscoveryService, Multiaddr, NodeAnnouncement, PeerId,
+};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, Mutex};
+use tokio::task::JoinHandle;
+use tracing::{debug, error, info, warn};
+
+/// Node status gossip configuration
+#[derive(Debug, Clone)]
+pub struct NodeGossipConfig {
+    /// Join the node network at all
+    pub enabled: bool,
+    /// libp2p port to listen on (TCP and QUIC)
+    pub port: u16,
+    /// Node multiaddrs (ending in `/p2p/<peer id>`) to join through
+    pub bootstrap_peers: Vec<String>,
+    /// Directory for the peer store; temporary if unset
+    pub peer_store: Option<PathBuf>,
+    /// Announcements older than this are ignored
+    pub max_age: Duration,
+}
+
+impl Default for NodeGossipConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: 4101,
+            bootstrap_peers: Vec::new(),
+            peer_store: None,
+            max_age: Duration::from_secs(120),
+        }
+    }
+}
+
+impl NodeGossipConfig {
+    /// Create configuration from environment variables
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            enabled: std::env::var("NODE_GOSSIP_ENABLED")
+                .map(|v| v == "true" || v == "1")
+                .unwrap_or(defaults.enabled),
+            port: std::env::var("NODE_GOSSIP_PORT")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.port),
+            bootstrap_peers: std::env::var("NODE_GOSSIP_BOOTSTRAP")
+                .map(|v| {
+                    v.split(',')
+                        .map(|s| s.trim().to_string())
+                        .filter(|s| !s.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default(),
+            peer_store: std::env::var("NODE_GOSSIP_PEER_STORE")
+                .ok()
+                .map(PathBuf::from),
+            max_age: Duration::from_secs(
+                std::env::var("NODE_GOSSIP_MAX_AGE_SECS")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(defaults.max_age.as_secs()),
+            ),
+        }
+    }
+
+    /// Discovery service configuration for the gateway's libp2p peer
+    fn discovery_config(&self) -> DiscoveryConfig {
+        let mut discovery = DiscoveryConfig {
+            listen_addrs: [
+                format!("/ip4/0.0.0.0/udp/{}/quic-v1", self.port),
+                format!("/ip4/0.0.0.0/tcp/{}", self.port),
+            ]
+            .iter()
+            .filter_map(|addr| addr.parse().ok())
+            .collect(),
+            ..DiscoveryConfig::default()
+        };
+        if let Some(path) = &self.peer_store {
+            discovery = discovery.with_peer_store(path);
+        }
+
+        for peer in &self.bootstrap_peers {
+            match peer.parse::<Multiaddr>() {
+                Ok(addr) if discovery.add_bootstrap_addr(addr) => {}
+                _ => warn!(peer = %peer, "Ignoring node gossip bootstrap address"),
+            }
+        }
+        discovery
+    }
+}
+
+/// What to do with the database row of an announcing node
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Reconciliation {
+    /// Status transition to apply
+    status: Option<NodeStatus>,
+    /// New storage usage to record
+    storage_used: Option<i64>,
+}
+
+/// Compare an announcement with the node's row
+fn reconcile(
+    node: &Node,
+    announcement: &NodeAnnouncement,
+    now: DateTime<Utc>,
+    max_age: Duration,
+) -> Reconciliation {
+    let announced_at = announcement.timestamp as i64;
+    if now.timestamp().saturating_sub(announced_at) > max_age.as_secs() as i64
+        || announcement.grpc_address != node.grpc_address
+    {
+        return Reconciliation::default();
+    }
+
+    // Heartbeats win over anything gossiped before them
+    let newer = node
+        .last_heartbeat
+        .map_or(true, |at| announced_at > at.timestamp());
+    let status = match (announcement.status, node.status.as_str()) {
+        _ if !newer => None,
+        (GossipStatus::Offline, "online" | "recovering" | "maintenance") => {
+            Some(NodeStatus::Offline)
+        }
+        (GossipStatus::Maintenance, "online" | "recovering") => Some(NodeStatus::Maintenance),
+        (GossipStatus::Draining, "online" | "recovering" | "maintenance") => {
+            Some(NodeStatus::Draining)
+        }
+        _ => None,
+    };
+
+    let used = announcement.capacity.storage_used as i64;
+    Reconciliation {
+        status,
+        storage_used: (used != node.storage_used).then_some(used),
+    }
+}
+
+/// Gateway-side subscriber to node status gossip
+pub struct NodeGossipSubscriber {
+    config: NodeGossipConfig,
+    /// libp2p peer each node ID was first announced by
+    sources: Mutex<HashMap<String, PeerId>>,
+}
+
+impl NodeGossipSubscriber {
+    /// Create a new subscriber
+    pub fn new(config: NodeGossipConfig) -> Self {
+        Self {
+            config,
+            sources: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Join the node network and reconcile announcements as a background task
+    pub fn start(self: Arc<Self>, state: Arc<AppState>) -> JoinHandle<()> {
+        let subscriber = self;
+
+        tokio::spawn(async move {
+            let (tx, mut rx) = mpsc::channel(256);
+            let mut discovery = DiscoveryService::new(subscriber.config.discovery_config());
+            discovery.set_event_channel(tx);
+            let peer_id = discovery.local_peer_id();
+
+            let network = tokio::spawn(async move {
+                if let Err(e) = discovery.run().await {
+                    error!(error = %e, "Node gossip network failed");
+                }
+            });
+
+            info!(
+                peer_id = %peer_id,
+                port = subscriber.config.port,
+                bootstrap_peers = subscriber.config.bootstrap_peers.len(),
+                "Node gossip subscriber started"
+            );
+
+            while let Some(event) = rx.recv().await {
+                let DiscoveryEvent::NodeStatus {
+                    source,
+                    announcement,
+                } = event
+                else {
+                    continue;
+                };
+
+                if let Some(metadata) = state.metadata_service() {
+                    subscriber.apply(metadata, source, &announcement).await;
+                }
+            }
+
+            network.abort();
+            warn!("Node gossip subscriber stopped");
+        })
+    }
+
+    /// Reconcile one announcement with the database
+    async fn apply(
+        &self,
+        metadata: &MetadataService,
+        source: Option<PeerId>,
+        announcement: &NodeAnnouncement,
+    ) {
+        let node_id = &announcement.node_id;
+        if let Some(source) = source {
+            let mut sources = self.sources.lock().await;
+            let pinned = sources.entry(node_id.clone()).or_insert(source);
+            if *pinned != source {
+                warn!(
+                    node_id = %node_id,
+                    source = %source,
+                    pinned = %pinned,
+                    "Ignoring node status gossip from an unexpected peer"
+                );
+                crate::metrics::record_node_gossip("source_mismatch");
+                return;
+            }
+        }
+
+        let db = metadata.database();
+        let node = match db.get_node_by_peer_id(node_id).await {
+            Ok(Some(node)) => node,
+            Ok(None) => {
+                debug!(node_id = %node_id, "Ignoring node status gossip for unknown node");
+                crate::metrics::record_node_gossip("unknown_node");
+                return;
+            }
+            Err(e) => {
+                error!(node_id = %node_id, error = %e, "Failed to look up gossiping node");
+                return;
+            }
+        };
+
+        let result = reconcile(&node, announcement, Utc::now(), self.config.max_age);
+        if result == Reconciliation::default() {
+            crate::metrics::record_node_gossip("unchanged");
+            return;
+        }
+
+        if let Some(used) = result.storage_used {
+            match db.update_node_storage(node.id, used).await {
+                Ok(()) => crate::metrics::record_node_gossip("storage"),
+                Err(e) => error!(node_id = %node_id, error = %e, "Failed to record gossiped usage"),
+            }
+        }
+
+        let reason = "status gossip";
+        match result.status {
+            Some(NodeStatus::Draining) => match metadata.start_node_drain(node_id, reason).await {
+                Ok((node_uuid, started)) => {
+                    if started {
+                        info!(node_id = %node_id, "Node draining (from gossip)");
+                        NodeMonitor::trigger_chunk_evacuation(metadata, node_uuid, reason).await;
+                    }
+                    crate::metrics::record_node_gossip("draining");
+                }
+                Err(e) => error!(node_id = %node_id, error = %e, "Failed to apply gossiped drain"),
+            },
+            Some(status) => match metadata.report_node_shutdown(node_id, status, reason).await {
+                Ok(()) => crate::metrics::record_node_gossip(&status.to_string()),
+                Err(e) => {
+                    error!(node_id = %node_id, error = %e, "Failed to apply gossiped status")
+                }
+            },
+            None => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(status: &str, last_heartbeat: Option<DateTime<Utc>>) -> Node {
+        let now = Utc::now();
+        Node {
+            id: uuid::Uuid::new_v4(),
+            peer_id: "node-1".to_string(),
+            grpc_address: "10.0.0.1:50051".to_string(),
+            storage_total: 1000,
+            storage_reserved: 0,
+            storage_used: 100,
+            bandwidth_mbps: 100,
+            measured_bandwidth_mbps: None,
+            bandwidth_measured_at: None,
+            max_connections: 100,
+            disk_available: None,
+            datacenter: None,
+            rack: None,
+            region: None,
+            latitude: None,
+            longitude: None,
+            status: status.to_string(),
+            last_heartbeat,
+            failure_count: 0,
+            first_offline_at: None,
+            status_changed_at: None,
+            warmup_started_at: None,
+            reputation: cyxcloud_metadata::NEUTRAL_REPUTATION,
+            archival: false,
+            version: None,
+            created_at: now,
+            updated_at: now,
+            wallet_address: None,
+            public_key: None,
+        }
+    }
+
+    fn announcement(status: GossipStatus, at: DateTime<Utc>) -> NodeAnnouncement {
+        let mut announcement =
+            NodeAnnouncement::new("node-1", "10.0.0.1:50051").with_status(status);
+        announcement.timestamp = at.timestamp() as u64;
+        announcement.capacity.storage_used = 100;
+        announcement
+    }
+
+    #[test]
+    fn test_reconcile_status() {
+        let now = Utc::now();
+        let max_age = Duration::from_secs(120);
+        let heartbeat = Some(now - chrono::Duration::seconds(30));
+
+        // Newer than the last heartbeat: the node leaves placement
+        let result = reconcile(
+            &node("online", heartbeat),
+            &announcement(GossipStatus::Draining, now),
+            now,
+            max_age,
+        );
+        assert_eq!(result.status, Some(NodeStatus::Draining));
+        assert_eq!(result.storage_used, None);
+
+        // Older than the last heartbeat: ignored
+        let result = reconcile(
+            &node("online", heartbeat),
+            &announcement(GossipStatus::Offline, now - chrono::Duration::seconds(60)),
+            now,
+            max_age,
+        );
+        assert_eq!(result.status, None);
+
+        // Gossip never brings a node back online
+        let result = reconcile(
+            &node("offline", heartbeat),
+            &announcement(GossipStatus::Online, now),
+            now,
+            max_age,
+        );
+        assert_eq!(result.status, None);
+    }
+
+    #[test]
+    fn test_reconcile_ignores_stale_and_mismatched() {
+        let now = Utc::now();
+        let max_age = Duration::from_secs(120);
+
+        let mut stale = announcement(GossipStatus::Offline, now - chrono::Duration::seconds(600));
+        stale.capacity.storage_used = 500;
+        assert_eq!(
+            reconcile(&node("online", None), &stale, now, max_age),
+            Reconciliation::default()
+        );
+
+        let mut moved = announcement(GossipStatus::Offline, now);
+        moved.grpc_address = "10.0.0.2:50051".to_string();
+        assert_eq!(
+            reconcile(&node("online", None), &moved, now, max_age),
+            Reconciliation::default()
+        );
+    }
+
+    #[test]
+    fn test_reconcile_storage() {
+        let now = Utc::now();
+        let mut update = announcement(GossipStatus::Online, now);
+        update.capacity.storage_used = 4096;
+
+        let result = reconcile(
+            &node("online", Some(now)),
+            &update,
+            now,
+            Duration::from_secs(120),
+        );
+        assert_eq!(result.status, None);
+        assert_eq!(result.storage_used, Some(4096));
+    }
+}
@@ -4,10 +4,28 @@
 //! - online -> offline (5 min no heartbeat)
 //! - offline -> draining (4 hours offline)
 //! - offline/draining -> removed (7 days offline)
+//! - online -> draining (node requested drain before shutdown)
 //! - recovering -> online (5 min quarantine complete)
+//!
+//! Newly joined nodes are also probed during their warm-up ramp: a small test
+//! chunk is written, read back and deleted. A failed probe restarts the ramp.
+//!
+//! Every cycle also refreshes node reputations: offline transitions count
+//! against a node, and the decayed signals are folded into the score used by
+//! placement.
+//!
+//! Online nodes have their bandwidth measured periodically: the node streams
+//! random data (ChunkService.ProbeBandwidth) and the throughput is stored next
+//! to the bandwidth the node reported. A few nodes are probed per cycle, one
+//! at a time, so probes neither compete with each other for the gateway's
+//! link nor hold up the lifecycle checks.
 
+use crate::node_client::NodeClient;
 use crate::state::AppState;
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
 use cyxcloud_metadata::{FaultToleranceConfig, MetadataService};
+use rand::RngCore;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::RwLock;
@@ -24,6 +42,18 @@ pub struct NodeMonitorConfig {
     pub fault_tolerance: FaultToleranceConfig,
     /// Enable metrics reporting
     pub enable_metrics: bool,
+    /// Warm-up period before a new node receives full placement weight
+    pub warmup_period: Duration,
+    /// Size of the test chunk written during warm-up probes
+    pub warmup_probe_size: usize,
+    /// Time for reputation signals to lose half their weight
+    pub reputation_half_life: Duration,
+    /// How often each online node's bandwidth is measured (zero disables)
+    pub bandwidth_probe_interval: Duration,
+    /// Bytes a bandwidth probe streams
+    pub bandwidth_probe_size: u64,
+    /// Most nodes probed per cycle
+    pub bandwidth_probes_per_cycle: usize,
 }
 
 impl Default for NodeMonitorConfig {
@@ -32,6 +62,12 @@ impl Default for NodeMonitorConfig {
             check_interval: Duration::from_secs(30),
             fault_tolerance: FaultToleranceConfig::default(),
             enable_metrics: true,
+            warmup_period: Duration::from_secs(6 * 60 * 60), // 6 hours
+            warmup_probe_size: 64 * 1024,                    // 64 KB
+            reputation_half_life: Duration::from_secs(7 * 24 * 60 * 60), // 7 days
+            bandwidth_probe_interval: Duration::from_secs(60 * 60), // 1 hour
+            bandwidth_probe_size: 8 * 1024 * 1024,           // 8 MB
+            bandwidth_probes_per_cycle: 4,
         }
     }
 }
@@ -48,6 +84,36 @@ impl NodeMonitorConfig {
             ),
             fault_tolerance: FaultToleranceConfig::from_env(),
             enable_metrics: true,
+            warmup_period: Duration::from_secs(
+                std::env::var("NODE_WARMUP_PERIOD_SECS")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(6 * 60 * 60),
+            ),
+            warmup_probe_size: std::env::var("NODE_WARMUP_PROBE_BYTES")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(64 * 1024),
+            reputation_half_life: Duration::from_secs(
+                std::env::var("NODE_REPUTATION_HALF_LIFE_SECS")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(7 * 24 * 60 * 60),
+            ),
+            bandwidth_probe_interval: Duration::from_secs(
+                std::env::var("NODE_BANDWIDTH_PROBE_INTERVAL_SECS")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(60 * 60),
+            ),
+            bandwidth_probe_size: std::env::var("NODE_BANDWIDTH_PROBE_BYTES")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(8 * 1024 * 1024),
+            bandwidth_probes_per_cycle: std::env::var("NODE_BANDWIDTH_PROBES_PER_CYCLE")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(4),
         }
     }
 }
@@ -60,6 +126,12 @@ pub struct NodeMonitorMetrics {
     pub nodes_removed: u64,
     pub nodes_entered_recovery: u64,
     pub nodes_recovered: u64,
+    pub warmup_probes_passed: u64,
+    pub warmup_probes_failed: u64,
+    pub nodes_warmed_up: u64,
+    pub reputations_refreshed: u64,
+    pub bandwidth_probes_passed: u64,
+    pub bandwidth_probes_failed: u64,
     pub last_check_at: Option<std::time::Instant>,
     pub last_check_duration_ms: u64,
     pub check_cycles_completed: u64,
@@ -108,6 +180,18 @@ impl NodeMonitor {
                     if let Err(e) = monitor.run_check_cycle(metadata).await {
                         error!(error = %e, "Node monitor check cycle failed");
                     }
+                    if let Err(e) = monitor
+                        .run_warmup_probes(metadata, state.node_client())
+                        .await
+                    {
+                        error!(error = %e, "Node warm-up probe cycle failed");
+                    }
+                    if let Err(e) = monitor
+                        .run_bandwidth_probes(metadata, state.node_client())
+                        .await
+                    {
+                        error!(error = %e, "Node bandwidth probe cycle failed");
+                    }
                 } else {
                     debug!("Metadata service not available, skipping node monitor cycle");
                 }
@@ -142,6 +226,9 @@ impl NodeMonitor {
                 error!(error = %e, node_id = %node.id, "Failed to mark node offline");
             } else {
                 stale_count += 1;
+                if let Err(e) = db.record_node_offline_event(node.id).await {
+                    warn!(error = %e, node_id = %node.id, "Failed to record offline event");
+                }
             }
         }
 
@@ -164,7 +251,7 @@ impl NodeMonitor {
                 draining_count += 1;
 
                 // Trigger chunk evacuation
-                self.trigger_chunk_evacuation(metadata, node.id).await;
+                Self::trigger_chunk_evacuation(metadata, node.id, "offline too long").await;
             }
         }
 
@@ -208,10 +295,29 @@ impl NodeMonitor {
             }
         }
 
+        // Step 5: Decay reputation signals and store scores for placement
+        let reputations_refreshed = match db
+            .refresh_node_reputations(self.config.reputation_half_life)
+            .await
+        {
+            Ok(reputations) => reputations.len() as u64,
+            Err(e) => {
+                error!(error = %e, "Failed to refresh node reputations");
+                0
+            }
+        };
+
+        // Placement must stop (or start) using these nodes right away; other
+        // gateways hear about it from the node status notifications
+        if stale_count > 0 || recovered_count > 0 {
+            metadata.invalidate_online_nodes();
+        }
+
         // Update metrics
         let duration = start.elapsed();
         {
             let mut metrics = self.metrics.write().await;
+            metrics.reputations_refreshed += reputations_refreshed;
             metrics.nodes_marked_offline += stale_count;
             metrics.nodes_started_draining += draining_count;
             metrics.nodes_removed += removed_count;
@@ -241,10 +347,179 @@ impl NodeMonitor {
         Ok(())
     }
 
+    /// Probe nodes that are still warming up and advance or restart their ramp
+    async fn run_warmup_probes(
+        &self,
+        metadata: &MetadataService,
+        node_client: &NodeClient,
+    ) -> anyhow::Result<()> {
+        let db = metadata.database();
+        let warming_nodes = db.get_warming_nodes().await?;
+
+        if warming_nodes.is_empty() {
+            return Ok(());
+        }
+
+        let now = Utc::now();
+        let mut passed = 0;
+        let mut failed = 0;
+        let mut completed = 0;
+
+        for node in &warming_nodes {
+            match probe_node(
+                node_client,
+                &node.grpc_address,
+                self.config.warmup_probe_size,
+            )
+            .await
+            {
+                Ok(()) => {
+                    passed += 1;
+
+                    if warmup_elapsed(node.warmup_started_at, self.config.warmup_period, now) {
+                        if let Err(e) = db.complete_node_warmup(node.id).await {
+                            error!(error = %e, node_id = %node.id, "Failed to complete node warm-up");
+                        } else {
+                            info!(
+                                node_id = %node.id,
+                                peer_id = %node.peer_id,
+                                "Node completed warm-up, full placement weight enabled"
+                            );
+                            completed += 1;
+                        }
+                    }
+                }
+                Err(e) => {
+                    failed += 1;
+                    warn!(
+                        node_id = %node.id,
+                        peer_id = %node.peer_id,
+                        error = %e,
+                        "Warm-up probe failed, restarting placement ramp"
+                    );
+
+                    if let Err(e) = db.restart_node_warmup(node.id).await {
+                        error!(error = %e, node_id = %node.id, "Failed to restart node warm-up");
+                    }
+                    if let Err(e) = db.increment_node_failures(node.id).await {
+                        error!(error = %e, node_id = %node.id, "Failed to record probe failure");
+                    }
+                }
+            }
+        }
+
+        {
+            let mut metrics = self.metrics.write().await;
+            metrics.warmup_probes_passed += passed;
+            metrics.warmup_probes_failed += failed;
+            metrics.nodes_warmed_up += completed;
+        }
+
+        debug!(
+            warming = warming_nodes.len(),
+            passed = passed,
+            failed = failed,
+            completed = completed,
+            "Warm-up probe cycle complete"
+        );
+
+        Ok(())
+    }
+
+    /// Measure the bandwidth of the online nodes whose last measurement is
+    /// older than the probe interval
+    async fn run_bandwidth_probes(
+        &self,
+        metadata: &MetadataService,
+        node_client: &NodeClient,
+    ) -> anyhow::Result<()> {
+        if self.config.bandwidth_probe_interval.is_zero()
+            || self.config.bandwidth_probes_per_cycle == 0
+        {
+            return Ok(());
+        }
+
+        let db = metadata.database();
+        let due_nodes = db
+            .get_nodes_due_for_bandwidth_probe(
+                self.config.bandwidth_probe_interval,
+                self.config.bandwidth_probes_per_cycle as i64,
+            )
+            .await?;
+
+        let mut passed = 0;
+        let mut failed = 0;
+        for node in &due_nodes {
+            match node_client
+                .probe_bandwidth(&node.grpc_address, self.config.bandwidth_probe_size)
+                .await
+            {
+                Ok(probe) => {
+                    let mbps = probe.mbps();
+                    crate::metrics::record_bandwidth_probe(true);
+                    crate::metrics::set_node_measured_bandwidth(&node.peer_id, mbps);
+                    if (mbps as i32) < node.bandwidth_mbps / 2 {
+                        info!(
+                            node_id = %node.id,
+                            peer_id = %node.peer_id,
+                            reported_mbps = node.bandwidth_mbps,
+                            measured_mbps = mbps,
+                            "Node measured well below its reported bandwidth"
+                        );
+                    }
+                    if let Err(e) = db.record_node_bandwidth(node.id, mbps.round() as i32).await {
+                        error!(error = %e, node_id = %node.id, "Failed to store node bandwidth");
+                    }
+                    passed += 1;
+                }
+                Err(e) => {
+                    // Failed probes are retried next cycle; liveness is up
+                    // to heartbeats, not to probes
+                    crate::metrics::record_bandwidth_probe(false);
+                    warn!(
+                        node_id = %node.id,
+                        peer_id = %node.peer_id,
+                        error = %e,
+                        "Bandwidth probe failed"
+                    );
+                    failed += 1;
+                }
+            }
+        }
+
+        {
+            let mut metrics = self.metrics.write().await;
+            metrics.bandwidth_probes_passed += passed;
+            metrics.bandwidth_probes_failed += failed;
+        }
+
+        if !due_nodes.is_empty() {
+            debug!(
+                probed = due_nodes.len(),
+                passed = passed,
+                failed = failed,
+                "Bandwidth probe cycle complete"
+            );
+        }
+
+        Ok(())
+    }
+
     /// Trigger chunk evacuation for a draining node
-    async fn trigger_chunk_evacuation(&self, metadata: &MetadataService, node_id: Uuid) {
+    ///
+    /// Also used when a node asks to be drained before shutting down.
+    /// Snapshots the drain progress counters before creating the jobs.
+    pub(crate) async fn trigger_chunk_evacuation(
+        metadata: &MetadataService,
+        node_id: Uuid,
+        reason: &str,
+    ) {
         let db = metadata.database();
 
+        if let Err(e) = db.start_drain_progress(node_id, reason).await {
+            warn!(error = %e, node_id = %node_id, "Failed to start drain progress tracking");
+        }
+
         // Get all chunks on this node
         match db.get_chunks_on_node(node_id).await {
             Ok(chunks) => {
@@ -327,6 +602,45 @@ impl NodeMonitor {
     }
 }
 
+/// Write, read back and delete a random test chunk on a node
+async fn probe_node(
+    node_client: &NodeClient,
+    address: &str,
+    probe_size: usize,
+) -> anyhow::Result<()> {
+    let mut data = vec![0u8; probe_size.max(1)];
+    rand::thread_rng().fill_bytes(&mut data);
+    let chunk_id = blake3::hash(&data);
+    let data = Bytes::from(data);
+
+    node_client
+        .store_chunk(address, chunk_id.as_bytes(), data.clone(), None)
+        .await?;
+
+    let read_back = node_client.get_chunk(address, chunk_id.as_bytes()).await?;
+    if read_back != data {
+        anyhow::bail!("probe chunk mismatch on read-back");
+    }
+
+    if let Err(e) = node_client.delete_chunk(address, chunk_id.as_bytes()).await {
+        debug!(address = %address, error = %e, "Failed to delete warm-up probe chunk");
+    }
+
+    Ok(())
+}
+
+/// Whether a node's warm-up period has fully elapsed
+fn warmup_elapsed(started_at: Option<DateTime<Utc>>, period: Duration, now: DateTime<Utc>) -> bool {
+    match started_at {
+        Some(started_at) => {
+            let period =
+                chrono::Duration::from_std(period).unwrap_or_else(|_| chrono::Duration::zero());
+            now - started_at >= period
+        }
+        None => true,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -336,6 +650,8 @@ mod tests {
         let config = NodeMonitorConfig::default();
         assert_eq!(config.check_interval.as_secs(), 30);
         assert_eq!(config.fault_tolerance.offline_threshold.as_secs(), 5 * 60);
+        assert_eq!(config.bandwidth_probe_interval.as_secs(), 60 * 60);
+        assert_eq!(config.bandwidth_probes_per_cycle, 4);
     }
 
     #[test]
@@ -343,5 +659,25 @@ mod tests {
         let metrics = NodeMonitorMetrics::default();
         assert_eq!(metrics.nodes_marked_offline, 0);
         assert_eq!(metrics.check_cycles_completed, 0);
+        assert_eq!(metrics.nodes_warmed_up, 0);
+    }
+
+    #[test]
+    fn test_warmup_elapsed() {
+        let now = Utc::now();
+        let period = Duration::from_secs(3600);
+
+        assert!(warmup_elapsed(None, period, now));
+        assert!(!warmup_elapsed(Some(now), period, now));
+        assert!(!warmup_elapsed(
+            Some(now - chrono::Duration::minutes(30)),
+            period,
+            now
+        ));
+        assert!(warmup_elapsed(
+            Some(now - chrono::Duration::hours(2)),
+            period,
+            now
+        ));
     }
 }
@@ -0,0 +1,194 @@
+//! S3 Object Lock
+//!
+//! Write-once-read-many retention for compliance. Once a bucket has object
+//! lock enabled (`PUT /:bucket?object-lock`, which cannot be undone) it may
+//! give every new object a default retention of some days, and
+//! `PUT /:bucket/*key?retention` sets the retention of a single object.
+//! While an object is under retention:
+//!
+//! - it cannot be overwritten
+//! - it cannot be deleted, except in governance mode by a request carrying
+//!   `x-amz-bypass-governance-retention: true`
+//! - its retention can be extended but never shortened, and a compliance
+//!   lock cannot be turned into a governance lock
+//!
+//! Expiry and the shard garbage collector leave the data of a locked version
+//! alone until its retention has passed. HEAD and GET return the lock in the
+//! `x-amz-object-lock-mode` and `x-amz-object-lock-retain-until-date`
+//! headers.
+
+use crate::s3_api::{S3Error, S3Result};
+use chrono::{DateTime, Duration, Utc};
+
+pub use cyxcloud_metadata::RetentionMode;
+
+/// Longest default retention of a bucket (S3 limit, about 100 years)
+pub const MAX_RETENTION_DAYS: u32 = 36500;
+
+/// Object lock configuration of a bucket that has object lock enabled
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ObjectLockConfig {
+    /// Retention given to new objects, None if they are not locked by default
+    pub default_retention: Option<DefaultRetention>,
+}
+
+/// Retention given to every new object of a bucket
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DefaultRetention {
+    pub mode: RetentionMode,
+    pub days: u32,
+}
+
+impl DefaultRetention {
+    /// Check the number of days is in range
+    pub fn validate(&self) -> S3Result<()> {
+        if !(1..=MAX_RETENTION_DAYS).contains(&self.days) {
+            return Err(S3Error::InvalidRequest(format!(
+                "Default retention must be between 1 and {} days",
+                MAX_RETENTION_DAYS
+            )));
+        }
+        Ok(())
+    }
+
+    /// Retention of an object created at `now`
+    pub fn retention_from(&self, now: DateTime<Utc>) -> ObjectRetention {
+        ObjectRetention {
+            mode: self.mode,
+            retain_until: now + Duration::days(self.days as i64),
+        }
+    }
+}
+
+/// Retention of one object version
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ObjectRetention {
+    pub mode: RetentionMode,
+    pub retain_until: DateTime<Utc>,
+}
+
+impl ObjectRetention {
+    /// Whether the retention has not passed yet at `now`
+    pub fn is_active(&self, now: DateTime<Utc>) -> bool {
+        self.retain_until > now
+    }
+
+    /// Refuse overwriting the object while it is under retention
+    pub fn check_overwrite(&self, key: &str, now: DateTime<Utc>) -> S3Result<()> {
+        if self.is_active(now) {
+            return Err(S3Error::ObjectLocked(format!(
+                "{} is under {} retention until {}",
+                key,
+                self.mode.as_str(),
+                self.retain_until.to_rfc3339()
+            )));
+        }
+        Ok(())
+    }
+
+    /// Whether a delete at `now` must be refused
+    pub fn blocks_delete(&self, now: DateTime<Utc>, bypass_governance: bool) -> bool {
+        self.is_active(now) && !(bypass_governance && self.mode == RetentionMode::Governance)
+    }
+
+    /// Check that `new` may replace the retention `current` at `now`
+    ///
+    /// An active retention can only be extended, and compliance mode cannot
+    /// be relaxed to governance mode.
+    pub fn check_update(current: Option<&Self>, new: &Self, now: DateTime<Utc>) -> S3Result<()> {
+        if new.retain_until <= now {
+            return Err(S3Error::InvalidRequest(
+                "RetainUntilDate must be in the future".to_string(),
+            ));
+        }
+        let Some(current) = current.filter(|c| c.is_active(now)) else {
+            return Ok(());
+        };
+        if new.retain_until < current.retain_until {
+            return Err(S3Error::ObjectLocked(format!(
+                "Retention can only be extended, it runs until {}",
+                current.retain_until.to_rfc3339()
+            )));
+        }
+        if current.mode == RetentionMode::Compliance && new.mode == RetentionMode::Governance {
+            return Err(S3Error::ObjectLocked(
+                "Compliance retention cannot be changed to governance".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn retention(mode: RetentionMode, days: i64, now: DateTime<Utc>) -> ObjectRetention {
+        ObjectRetention {
+            mode,
+            retain_until: now + Duration::days(days),
+        }
+    }
+
+    #[test]
+    fn test_default_retention() {
+        let now = Utc::now();
+        let default = DefaultRetention {
+            mode: RetentionMode::Compliance,
+            days: 30,
+        };
+        assert!(default.validate().is_ok());
+        assert_eq!(
+            default.retention_from(now),
+            retention(RetentionMode::Compliance, 30, now)
+        );
+
+        for days in [0, MAX_RETENTION_DAYS + 1] {
+            assert!(DefaultRetention { days, ..default }.validate().is_err());
+        }
+    }
+
+    #[test]
+    fn test_blocks_delete() {
+        let now = Utc::now();
+        let governance = retention(RetentionMode::Governance, 1, now);
+        assert!(governance.blocks_delete(now, false));
+        assert!(!governance.blocks_delete(now, true));
+
+        let compliance = retention(RetentionMode::Compliance, 1, now);
+        assert!(compliance.blocks_delete(now, true));
+
+        // Passed retention no longer blocks anything
+        let passed = retention(RetentionMode::Compliance, -1, now);
+        assert!(!passed.blocks_delete(now, false));
+        assert!(passed.check_overwrite("k", now).is_ok());
+        assert!(compliance.check_overwrite("k", now).is_err());
+    }
+
+    #[test]
+    fn test_check_update_extends_only() {
+        let now = Utc::now();
+        let current = retention(RetentionMode::Compliance, 10, now);
+
+        let longer = retention(RetentionMode::Compliance, 20, now);
+        assert!(ObjectRetention::check_update(Some(&current), &longer, now).is_ok());
+
+        let shorter = retention(RetentionMode::Compliance, 5, now);
+        assert!(matches!(
+            ObjectRetention::check_update(Some(&current), &shorter, now),
+            Err(S3Error::ObjectLocked(_))
+        ));
+
+        let relaxed = retention(RetentionMode::Governance, 20, now);
+        assert!(ObjectRetention::check_update(Some(&current), &relaxed, now).is_err());
+
+        // Governance can be tightened to compliance
+        let governance = retention(RetentionMode::Governance, 10, now);
+        assert!(ObjectRetention::check_update(Some(&governance), &longer, now).is_ok());
+
+        // Once passed, any future retention may be set
+        let passed = retention(RetentionMode::Compliance, -1, now);
+        assert!(ObjectRetention::check_update(Some(&passed), &relaxed, now).is_ok());
+        assert!(ObjectRetention::check_update(None, &passed, now).is_err());
+    }
+}
@@ -0,0 +1,438 @@
+//! OpenID Connect Provider
+//!
+//! Verifies ID tokens from an enterprise identity provider so users can sign
+//! in with their SSO. Signing keys come from the provider's JWKS, which is
+//! fetched lazily, refreshed periodically and refetched early when a token
+//! names a key that is not cached yet (key rotation). Verified tokens are
+//! mapped to an [`ExternalIdentity`]: the organization claim selects the
+//! tenant and group claims grant permissions.
+
+use crate::auth::{is_valid_tenant, AuthError, AuthProvider, AuthResult, ExternalIdentity};
+use async_trait::async_trait;
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+use tracing::{debug, info, warn};
+
+/// Shortest time between two JWKS fetches triggered by unknown key IDs
+const MIN_JWKS_REFETCH: Duration = Duration::from_secs(60);
+
+/// Signature algorithms accepted on ID tokens
+///
+/// Only asymmetric algorithms: the keys come from a public JWKS, so an
+/// HMAC token could be forged by anyone who has read it.
+const ALLOWED_ALGORITHMS: &[Algorithm] = &[
+    Algorithm::RS256,
+    Algorithm::RS384,
+    Algorithm::RS512,
+    Algorithm::PS256,
+    Algorithm::PS384,
+    Algorithm::PS512,
+    Algorithm::ES256,
+    Algorithm::ES384,
+    Algorithm::EdDSA,
+];
+
+/// Configuration of an OIDC identity provider
+#[derive(Debug, Clone)]
+pub struct OidcConfig {
+    /// Issuer URL; must equal the `iss` claim of accepted tokens
+    pub issuer: String,
+
+    /// JWKS endpoint (default: `jwks_uri` from the issuer's discovery document)
+    pub jwks_url: Option<String>,
+
+    /// Accepted `aud` values, usually the client ID (empty: not checked)
+    pub audiences: Vec<String>,
+
+    /// Claim holding the user's organization, used as tenant
+    pub org_claim: Option<String>,
+
+    /// Claim holding the user's groups or roles
+    pub groups_claim: String,
+
+    /// Permissions every user of the provider gets
+    pub default_permissions: Vec<String>,
+
+    /// Additional permissions per group
+    pub group_permissions: HashMap<String, Vec<String>>,
+
+    /// How long fetched signing keys are used before refetching
+    pub jwks_refresh: Duration,
+}
+
+impl OidcConfig {
+    /// Create config from environment variables
+    ///
+    /// Returns `None` unless `OIDC_ISSUER` is set.
+    pub fn from_env() -> Option<Self> {
+        let issuer = std::env::var("OIDC_ISSUER").ok()?;
+        let list = |name: &str| -> Option<Vec<String>> {
+            std::env::var(name).ok().map(|v| {
+                v.split(',')
+                    .map(|s| s.trim().to_string())
+                    .filter(|s| !s.is_empty())
+                    .collect()
+            })
+        };
+
+        let audiences = list("OIDC_AUDIENCE").unwrap_or_default();
+        if audiences.is_empty() {
+            warn!("OIDC_AUDIENCE not set, accepting ID tokens issued to any client");
+        }
+
+        Some(Self {
+            issuer: issuer.trim_end_matches('/').to_string(),
+            jwks_url: std::env::var("OIDC_JWKS_URL").ok(),
+            audiences,
+            org_claim: std::env::var("OIDC_ORG_CLAIM").ok(),
+            groups_claim: std::env::var("OIDC_GROUPS_CLAIM")
+                .unwrap_or_else(|_| "groups".to_string()),
+            default_permissions: list("OIDC_DEFAULT_PERMISSIONS").unwrap_or_else(|| {
+                vec![
+                    "storage:read".to_string(),
+                    "storage:write".to_string(),
+                    "dataset:read".to_string(),
+                ]
+            }),
+            group_permissions: std::env::var("OIDC_GROUP_PERMISSIONS")
+                .map(|v| parse_group_permissions(&v))
+                .unwrap_or_default(),
+            jwks_refresh: Duration::from_secs(
+                std::env::var("OIDC_JWKS_REFRESH_SECS")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(3600),
+            ),
+        })
+    }
+}
+
+/// Parse `group=perm,perm;group=perm` into a group to permissions map
+fn parse_group_permissions(spec: &str) -> HashMap<String, Vec<String>> {
+    spec.split(';')
+        .filter_map(|entry| {
+            let (group, perms) = entry.split_once('=')?;
+            let perms = perms
+                .split(',')
+                .map(|p| p.trim().to_string())
+                .filter(|p| !p.is_empty())
+                .collect();
+            Some((group.trim().to_string(), perms))
+        })
+        .collect()
+}
+
+/// Signing keys of the provider and when they were fetched
+#[derive(Default)]
+struct KeyCache {
+    keys: Option<JwkSet>,
+    /// Last successful fetch
+    fetched_at: Option<Instant>,
+    /// Last fetch attempt, successful or not
+    attempted_at: Option<Instant>,
+}
+
+/// Subset of the OIDC discovery document
+#[derive(Deserialize)]
+struct Discovery {
+    jwks_uri: String,
+}
+
+/// Identity provider speaking OpenID Connect
+pub struct OidcProvider {
+    config: OidcConfig,
+    client: reqwest::Client,
+    cache: RwLock<KeyCache>,
+}
+
+impl OidcProvider {
+    /// Create a provider; keys are fetched on first use
+    pub fn new(config: OidcConfig) -> Self {
+        info!(issuer = %config.issuer, "OIDC provider configured");
+        Self {
+            config,
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(10))
+                .build()
+                .unwrap_or_default(),
+            cache: RwLock::new(KeyCache::default()),
+        }
+    }
+
+    /// Decoding key for `kid`, refetching the JWKS when stale or missing it
+    async fn decoding_key(&self, kid: Option<&str>) -> AuthResult<DecodingKey> {
+        {
+            let cache = self.cache.read().await;
+            let fresh = cache
+                .fetched_at
+                .is_some_and(|t| t.elapsed() < self.config.jwks_refresh);
+            if fresh {
+                if let Some(key) = find_key(cache.keys.as_ref(), kid)? {
+                    return Ok(key);
+                }
+            }
+        }
+
+        let mut cache = self.cache.write().await;
+        // Fetches are at most once per MIN_JWKS_REFETCH, so tokens with
+        // made-up key IDs cannot hammer the provider
+        let recently_attempted = cache
+            .attempted_at
+            .is_some_and(|t| t.elapsed() < MIN_JWKS_REFETCH);
+        if !recently_attempted {
+            cache.attempted_at = Some(Instant::now());
+            match self.fetch_keys().await {
+                Ok(keys) => {
+                    debug!(issuer = %self.config.issuer, keys = keys.keys.len(), "JWKS refreshed");
+                    cache.keys = Some(keys);
+                    cache.fetched_at = cache.attempted_at;
+                }
+                // Keep using the previous keys until the provider is back
+                Err(e) => warn!(issuer = %self.config.issuer, error = %e, "JWKS refresh failed"),
+            }
+        }
+
+        find_key(cache.keys.as_ref(), kid)?
+            .ok_or_else(|| AuthError::InvalidToken("Unknown signing key".to_string()))
+    }
+
+    /// Download the provider's signing keys
+    async fn fetch_keys(&self) -> anyhow::Result<JwkSet> {
+        let jwks_url = match &self.config.jwks_url {
+            Some(url) => url.clone(),
+            None => {
+                let url = format!("{}/.well-known/openid-configuration", self.config.issuer);
+                let discovery: Discovery = self
+                    .client
+                    .get(&url)
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .json()
+                    .await?;
+                discovery.jwks_uri
+            }
+        };
+
+        Ok(self
+            .client
+            .get(&jwks_url)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?)
+    }
+
+    /// Map verified ID token claims to an identity
+    fn map_claims(&self, claims: &serde_json::Value) -> AuthResult<ExternalIdentity> {
+        let subject = claims
+            .get("sub")
+            .and_then(|v| v.as_str())
+            .filter(|s| !s.is_empty())
+            .ok_or_else(|| AuthError::InvalidToken("Token has no subject".to_string()))?;
+
+        // Unverified addresses are not reported, so they cannot be mistaken
+        // for proof of owning the mailbox
+        let email = claims
+            .get("email")
+            .and_then(|v| v.as_str())
+            .filter(|_| claims.get("email_verified").and_then(|v| v.as_bool()) != Some(false))
+            .map(str::to_string);
+
+        let org = match &self.config.org_claim {
+            Some(name) => match claims.get(name).and_then(|v| v.as_str()) {
+                Some(org) if is_valid_tenant(org) => Some(org.to_string()),
+                Some(org) => {
+                    return Err(AuthError::InvalidToken(format!(
+                        "Invalid {} claim: {}",
+                        name, org
+                    )))
+                }
+                None => None,
+            },
+            None => None,
+        };
+
+        let groups: Vec<&str> = match claims.get(&self.config.groups_claim) {
+            Some(serde_json::Value::Array(values)) => {
+                values.iter().filter_map(|v| v.as_str()).collect()
+            }
+            Some(serde_json::Value::String(group)) => vec![group.as_str()],
+            _ => Vec::new(),
+        };
+
+        let mut permissions = self.config.default_permissions.clone();
+        for group in groups {
+            for perm in self
+                .config
+                .group_permissions
+                .get(group)
+                .into_iter()
+                .flatten()
+            {
+                if !permissions.contains(perm) {
+                    permissions.push(perm.clone());
+                }
+            }
+        }
+
+        Ok(ExternalIdentity {
+            issuer: self.config.issuer.clone(),
+            subject: subject.to_string(),
+            email,
+            org,
+            permissions,
+        })
+    }
+}
+
+/// Look up a key in a JWKS; without a key ID only a single-key set matches
+fn find_key(keys: Option<&JwkSet>, kid: Option<&str>) -> AuthResult<Option<DecodingKey>> {
+    let Some(keys) = keys else {
+        return Ok(None);
+    };
+    let jwk = match kid {
+        Some(kid) => keys.find(kid),
+        None if keys.keys.len() == 1 => keys.keys.first(),
+        None => None,
+    };
+    jwk.map(|jwk| {
+        DecodingKey::from_jwk(jwk)
+            .map_err(|e| AuthError::InvalidToken(format!("Unusable signing key: {}", e)))
+    })
+    .transpose()
+}
+
+#[async_trait]
+impl AuthProvider for OidcProvider {
+    fn name(&self) -> &str {
+        "oidc"
+    }
+
+    fn handles_issuer(&self, issuer: &str) -> bool {
+        issuer.trim_end_matches('/') == self.config.issuer
+    }
+
+    async fn authenticate(&self, token: &str) -> AuthResult<ExternalIdentity> {
+        let header = decode_header(token).map_err(|e| AuthError::InvalidToken(e.to_string()))?;
+        if !ALLOWED_ALGORITHMS.contains(&header.alg) {
+            return Err(AuthError::InvalidToken(format!(
+                "Unsupported signature algorithm: {:?}",
+                header.alg
+            )));
+        }
+
+        let key = self.decoding_key(header.kid.as_deref()).await?;
+
+        let mut validation = Validation::new(header.alg);
+        // Accept the issuer with and without a trailing slash, as providers differ
+        validation.set_issuer(&[
+            self.config.issuer.clone(),
+            format!("{}/", self.config.issuer),
+        ]);
+        if self.config.audiences.is_empty() {
+            validation.validate_aud = false;
+        } else {
+            validation.set_audience(&self.config.audiences);
+        }
+        validation.set_required_spec_claims(&["exp", "iss", "sub"]);
+
+        let data =
+            decode::<serde_json::Value>(token, &key, &validation).map_err(|e| match e.kind() {
+                jsonwebtoken::errors::ErrorKind::ExpiredSignature => AuthError::TokenExpired,
+                _ => AuthError::InvalidToken(e.to_string()),
+            })?;
+
+        self.map_claims(&data.claims)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn provider() -> OidcProvider {
+        OidcProvider::new(OidcConfig {
+            issuer: "https://sso.example.com".to_string(),
+            jwks_url: Some("http://127.0.0.1:1/jwks".to_string()),
+            audiences: vec!["cyxcloud".to_string()],
+            org_claim: Some("org".to_string()),
+            groups_claim: "groups".to_string(),
+            default_permissions: vec!["storage:read".to_string()],
+            group_permissions: parse_group_permissions("admins=*;writers=storage:write"),
+            jwks_refresh: Duration::from_secs(3600),
+        })
+    }
+
+    #[test]
+    fn test_parse_group_permissions() {
+        let map = parse_group_permissions("admins=*; writers=storage:write, storage:delete;bad");
+        assert_eq!(map["admins"], vec!["*"]);
+        assert_eq!(map["writers"], vec!["storage:write", "storage:delete"]);
+        assert!(!map.contains_key("bad"));
+    }
+
+    #[test]
+    fn test_map_claims() {
+        let provider = provider();
+        let identity = provider
+            .map_claims(&json!({
+                "sub": "abc123",
+                "email": "alice@example.com",
+                "email_verified": true,
+                "org": "acme",
+                "groups": ["writers", "unrelated"],
+            }))
+            .unwrap();
+
+        assert_eq!(identity.issuer, "https://sso.example.com");
+        assert_eq!(identity.subject, "abc123");
+        assert_eq!(identity.email.as_deref(), Some("alice@example.com"));
+        assert_eq!(identity.org.as_deref(), Some("acme"));
+        assert_eq!(identity.permissions, vec!["storage:read", "storage:write"]);
+    }
+
+    #[test]
+    fn test_map_claims_rejects_bad_org_and_drops_unverified_email() {
+        let provider = provider();
+        assert!(provider
+            .map_claims(&json!({"sub": "abc", "org": "acme:other"}))
+            .is_err());
+        assert!(provider.map_claims(&json!({"org": "acme"})).is_err());
+
+        let identity = provider
+            .map_claims(&json!({
+                "sub": "abc",
+                "email": "mallory@example.com",
+                "email_verified": false,
+                "groups": "admins",
+            }))
+            .unwrap();
+        assert_eq!(identity.email, None);
+        assert_eq!(identity.org, None);
+        assert!(identity.permissions.contains(&"*".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_symmetric_tokens_rejected() {
+        let token = jsonwebtoken::encode(
+            &jsonwebtoken::Header::new(Algorithm::HS256),
+            &json!({"sub": "abc", "iss": "https://sso.example.com", "exp": 4_000_000_000u64}),
+            &jsonwebtoken::EncodingKey::from_secret(b"secret"),
+        )
+        .unwrap();
+
+        let provider = provider();
+        assert!(provider.handles_issuer("https://sso.example.com/"));
+        assert!(matches!(
+            provider.authenticate(&token).await,
+            Err(AuthError::InvalidToken(_))
+        ));
+    }
+}
@@ -5,15 +5,25 @@
 //! - Ends epochs and distributes payments when due
 //! - Handles slashing for extended downtime
 //! - Claims platform and community shares
+//! - Stores a payout report per epoch (served by the admin API)
+//!
+//! Node weights are stored bytes x uptime x SLA reputation; the nodes' 85%
+//! of the pool is divided by weight, the platform (10%) and community (5%)
+//! shares are claimed after the epoch is finalized on-chain.
 
 use crate::state::AppState;
 use chrono::{DateTime, Utc};
-use cyxcloud_metadata::{MetadataService, NodeWeight, SlashReason};
+use cyxcloud_metadata::{
+    EpochPayoutReport, MetadataService, NodeEpochUptime, NodePayout, NodeWeight, SlashReason,
+    SlashingEvent,
+};
+use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::RwLock;
 use tokio::time::interval;
 use tracing::{debug, error, info, warn};
+use uuid::Uuid;
 
 #[cfg(feature = "blockchain")]
 use crate::blockchain::CyxCloudBlockchainClient;
@@ -42,6 +52,9 @@ pub struct PaymentDaemonConfig {
     pub extended_downtime_threshold: Duration,
     /// Enable blockchain transactions (false for dry-run mode)
     pub enable_blockchain: bool,
+    /// Payment pool token account the platform and community shares are
+    /// claimed from (shares stay in the pool when unset)
+    pub pool_token_account: Option<String>,
 }
 
 impl Default for PaymentDaemonConfig {
@@ -50,6 +63,7 @@ impl Default for PaymentDaemonConfig {
             accumulate_interval: Duration::from_secs(60),
             extended_downtime_threshold: Duration::from_secs(4 * 60 * 60), // 4 hours
             enable_blockchain: true,
+            pool_token_account: None,
         }
     }
 }
@@ -73,6 +87,9 @@ impl PaymentDaemonConfig {
             enable_blockchain: std::env::var("ENABLE_BLOCKCHAIN_PAYMENTS")
                 .map(|v| v == "true" || v == "1")
                 .unwrap_or(true),
+            pool_token_account: std::env::var("PAYMENT_POOL_TOKEN_ACCOUNT")
+                .ok()
+                .filter(|v| !v.is_empty()),
         }
     }
 }
@@ -94,6 +111,22 @@ pub struct PaymentDaemonMetrics {
 /// Epoch duration in seconds (7 days)
 const EPOCH_DURATION_SECONDS: i64 = 7 * 24 * 60 * 60;
 
+/// Pool split percentages (nodes / platform / community)
+const NODE_PERCENT: u8 = 85;
+const PLATFORM_PERCENT: u8 = 10;
+const COMMUNITY_PERCENT: u8 = 5;
+
+/// Theoretical pool distributed in dry-run mode (1 CYXWIZ)
+const DRY_RUN_POOL_AMOUNT: u64 = 1_000_000_000;
+
+/// Payout statuses recorded in epoch reports
+const PAYOUT_PENDING: &str = "pending";
+const PAYOUT_ALLOCATED: &str = "allocated";
+const PAYOUT_DRY_RUN: &str = "dry_run";
+const PAYOUT_NO_WALLET: &str = "no_wallet";
+const PAYOUT_FAILED: &str = "failed";
+const PAYOUT_ZERO_REWARD: &str = "zero_reward";
+
 /// Payment daemon for managing node payments
 pub struct PaymentDaemon {
     config: PaymentDaemonConfig,
@@ -263,6 +296,7 @@ impl PaymentDaemon {
         blockchain: BlockchainRef<'_>,
     ) -> anyhow::Result<()> {
         let db = metadata.database();
+        let chain = self.chain(blockchain);
 
         // Get current epoch info
         let (current_epoch, epoch_start) = {
@@ -279,36 +313,49 @@ impl PaymentDaemon {
             None => return Ok(()),
         };
 
-        // Check if epoch duration has elapsed
+        // The payment pool decides when its epoch is over; without it, use
+        // the local epoch duration
         let now = Utc::now();
         let elapsed = (now - epoch_start).num_seconds();
+        let due = match chain.should_end_epoch().await {
+            Some(due) => due,
+            None => elapsed >= EPOCH_DURATION_SECONDS,
+        };
 
-        if elapsed < EPOCH_DURATION_SECONDS {
+        if !due {
             return Ok(()); // Epoch not ended yet
         }
 
         info!(
             epoch = current_epoch,
             elapsed_seconds = elapsed,
-            "Epoch duration elapsed, finalizing payments"
+            "Epoch ended, finalizing payments"
         );
 
         // Step 1: End uptime tracking for this epoch
         db.end_epoch_uptime(current_epoch).await?;
 
         // Step 2: Check for slashing conditions
-        self.check_and_slash_nodes(metadata, blockchain, current_epoch)
+        self.check_and_slash_nodes(metadata, &chain, current_epoch)
             .await?;
 
         // Step 3: Calculate weights and distribute payments
-        self.finalize_and_pay_epoch(metadata, blockchain, current_epoch)
+        let report = self
+            .finalize_and_pay_epoch(metadata, &chain, current_epoch)
             .await?;
 
-        // Step 4: Start new epoch
+        // Step 4: Start new epoch, on-chain first so deposits go to it
+        let next_epoch_tx = chain.start_new_epoch().await;
         let new_epoch = current_epoch + 1;
         let new_epoch_record = db.create_payment_epoch(new_epoch).await?;
         let nodes_initialized = db.initialize_epoch_for_all_nodes(new_epoch, now).await?;
 
+        // Step 5: Persist the payout report
+        if let Some(mut report) = report {
+            report.next_epoch_tx_signature = next_epoch_tx;
+            db.save_epoch_payout_report(&report).await?;
+        }
+
         // Update metrics
         {
             let mut metrics = self.metrics.write().await;
@@ -327,12 +374,19 @@ impl PaymentDaemon {
         Ok(())
     }
 
+    /// On-chain operations, disabled in dry-run mode
+    fn chain<'a>(&self, blockchain: BlockchainRef<'a>) -> Chain<'a> {
+        Chain {
+            client: blockchain.filter(|_| self.config.enable_blockchain),
+            pool_token_account: self.config.pool_token_account.clone(),
+        }
+    }
+
     /// Check for slashing conditions and apply penalties
-    #[cfg(feature = "blockchain")]
     async fn check_and_slash_nodes(
         &self,
         metadata: &MetadataService,
-        blockchain: BlockchainRef<'_>,
+        chain: &Chain<'_>,
         epoch: i64,
     ) -> anyhow::Result<()> {
         let db = metadata.database();
@@ -352,6 +406,7 @@ impl PaymentDaemon {
             epoch = epoch,
             count = extended_downtime_nodes.len(),
             threshold_hours = threshold_seconds / 3600,
+            dry_run = !chain.enabled(),
             "Found nodes with extended downtime, applying slashing"
         );
 
@@ -365,42 +420,14 @@ impl PaymentDaemon {
             let reason = SlashReason::ExtendedDowntime;
             let slash_percent = reason.slash_percent();
 
-            // Record slashing event in database
             let details = serde_json::json!({
                 "seconds_offline": uptime.seconds_offline,
                 "threshold_seconds": threshold_seconds,
             });
 
-            // Execute slash on blockchain if enabled
-            let tx_signature = if self.config.enable_blockchain {
-                if let Some(blockchain) = blockchain {
-                    if let Some(ref wallet) = node.wallet_address {
-                        if let Ok(owner) = Pubkey::from_str(wallet) {
-                            match blockchain
-                                .slash_node(&owner, &node.peer_id, 0, &reason.to_string())
-                                .await
-                            {
-                                Ok(sig) => Some(sig),
-                                Err(e) => {
-                                    warn!(
-                                        error = %e,
-                                        node_id = %node.id,
-                                        "Failed to execute slash on blockchain"
-                                    );
-                                    None
-                                }
-                            }
-                        } else {
-                            None
-                        }
-                    } else {
-                        None
-                    }
-                } else {
-                    None
-                }
-            } else {
-                None
+            let tx_signature = match node.wallet_address.as_deref() {
+                Some(wallet) => chain.slash(wallet, &node.peer_id, reason).await,
+                None => None,
             };
 
             db.record_slashing_event(
@@ -414,7 +441,6 @@ impl PaymentDaemon {
             )
             .await?;
 
-            // Update metrics
             {
                 let mut metrics = self.metrics.write().await;
                 metrics.slashing_events += 1;
@@ -426,6 +452,7 @@ impl PaymentDaemon {
                 reason = %reason,
                 slash_percent = slash_percent,
                 seconds_offline = uptime.seconds_offline,
+                on_chain = tx_signature.is_some(),
                 "Slashing applied for extended downtime"
             );
         }
@@ -433,232 +460,181 @@ impl PaymentDaemon {
         Ok(())
     }
 
-    /// Check for slashing conditions and apply penalties (no-op when blockchain disabled)
-    #[cfg(not(feature = "blockchain"))]
-    async fn check_and_slash_nodes(
-        &self,
-        metadata: &MetadataService,
-        _blockchain: BlockchainRef<'_>,
-        epoch: i64,
-    ) -> anyhow::Result<()> {
-        let db = metadata.database();
-        let threshold_seconds = self.config.extended_downtime_threshold.as_secs() as i64;
-
-        // Get nodes with extended downtime
-        let extended_downtime_nodes = db
-            .get_nodes_with_extended_downtime(epoch, threshold_seconds)
-            .await?;
-
-        if extended_downtime_nodes.is_empty() {
-            debug!(epoch = epoch, "No nodes with extended downtime");
-            return Ok(());
-        }
-
-        info!(
-            epoch = epoch,
-            count = extended_downtime_nodes.len(),
-            threshold_hours = threshold_seconds / 3600,
-            "Found nodes with extended downtime, recording slashing (blockchain disabled)"
-        );
-
-        for uptime in &extended_downtime_nodes {
-            let node = match db.get_node(uptime.node_id).await? {
-                Some(n) => n,
-                None => continue,
-            };
-
-            let reason = SlashReason::ExtendedDowntime;
-            let slash_percent = reason.slash_percent();
-
-            let details = serde_json::json!({
-                "seconds_offline": uptime.seconds_offline,
-                "threshold_seconds": threshold_seconds,
-            });
-
-            db.record_slashing_event(
-                uptime.node_id,
-                epoch,
-                &reason.to_string(),
-                slash_percent,
-                None,
-                None, // No tx signature without blockchain
-                Some(details),
-            )
-            .await?;
-
-            {
-                let mut metrics = self.metrics.write().await;
-                metrics.slashing_events += 1;
-            }
-
-            info!(
-                node_id = %uptime.node_id,
-                peer_id = %node.peer_id,
-                reason = %reason,
-                slash_percent = slash_percent,
-                "Slashing recorded (blockchain disabled)"
-            );
-        }
-
-        Ok(())
-    }
-
-    /// Finalize epoch and distribute payments (with blockchain)
-    #[cfg(feature = "blockchain")]
+    /// Weigh the epoch's nodes, distribute the pool and finalize the epoch
+    ///
+    /// Returns the payout report, or `None` if there was nothing to
+    /// distribute and the epoch was left unfinalized.
     async fn finalize_and_pay_epoch(
         &self,
         metadata: &MetadataService,
-        blockchain: BlockchainRef<'_>,
+        chain: &Chain<'_>,
         epoch: i64,
-    ) -> anyhow::Result<()> {
+    ) -> anyhow::Result<Option<EpochPayoutReport>> {
         let db = metadata.database();
 
-        // Get all uptime records for this epoch
         let uptimes = db.get_epoch_uptime(epoch).await?;
-
         if uptimes.is_empty() {
             warn!(
                 epoch = epoch,
                 "No uptime records for epoch, skipping payment"
             );
-            return Ok(());
+            return Ok(None);
         }
 
-        // Get node info for each uptime record to calculate weights
+        // Weight = stored bytes x uptime x reputation; reputation comes from
+        // the node's SLA record for the epoch (uptime ratio and slashes)
+        let slashes = db.get_epoch_slashing_events(epoch).await?;
         let mut weights: Vec<NodeWeight> = Vec::new();
-
+        let mut already_paid: HashMap<Uuid, (u64, Option<String>)> = HashMap::new();
         for uptime in &uptimes {
-            if let Some(node) = db.get_node(uptime.node_id).await? {
-                let reputation = 5000u16; // Average reputation
-                let weight = NodeWeight::calculate(
+            let Some(node) = db.get_node(uptime.node_id).await? else {
+                continue;
+            };
+            let node_slashes: Vec<&SlashingEvent> = slashes
+                .iter()
+                .filter(|s| s.node_id == uptime.node_id)
+                .collect();
+            weights.push(NodeWeight::calculate(
+                uptime.node_id,
+                node.peer_id.clone(),
+                node.wallet_address.clone(),
+                node.storage_used,
+                uptime.seconds_online,
+                EPOCH_DURATION_SECONDS,
+                sla_reputation(uptime, &node_slashes),
+            ));
+            if uptime.payment_allocated {
+                already_paid.insert(
                     uptime.node_id,
-                    node.peer_id.clone(),
-                    node.wallet_address.clone(),
-                    node.storage_total,
-                    uptime.seconds_online,
-                    EPOCH_DURATION_SECONDS,
-                    reputation,
+                    (
+                        uptime.payment_amount.unwrap_or(0) as u64,
+                        uptime.payment_tx_signature.clone(),
+                    ),
                 );
-                weights.push(weight);
             }
         }
 
         if weights.is_empty() {
             warn!(epoch = epoch, "No valid node weights for epoch");
-            return Ok(());
+            return Ok(None);
         }
 
-        let total_weight: u64 = weights.iter().map(|w| w.weight).sum();
-
-        // Get pool amount from blockchain
-        let (total_pool_amount, nodes_share) = if self.config.enable_blockchain {
-            if let Some(blockchain) = blockchain {
-                if let Ok(Some(rewards)) = blockchain.get_epoch_rewards(epoch as u64).await {
-                    (rewards.total_pool_amount, rewards.nodes_share)
-                } else {
+        let split = if chain.enabled() {
+            match chain.epoch_pool(epoch).await {
+                Some(split) => split,
+                None => {
                     warn!(epoch = epoch, "No pool data available for epoch");
-                    return Ok(());
+                    return Ok(None);
                 }
-            } else {
-                (0, 0)
             }
         } else {
-            (1_000_000_000, 850_000_000) // Dry-run mode
+            PoolSplit::new(
+                DRY_RUN_POOL_AMOUNT,
+                NODE_PERCENT,
+                PLATFORM_PERCENT,
+                COMMUNITY_PERCENT,
+            )
         };
 
-        if nodes_share == 0 {
+        if split.nodes == 0 {
             warn!(epoch = epoch, "No tokens in pool for distribution");
-            return Ok(());
+            return Ok(None);
         }
 
+        let (total_weight, mut payouts) = plan_payouts(&weights, split.nodes);
+
         info!(
             epoch = epoch,
-            total_pool = total_pool_amount,
-            nodes_share = nodes_share,
+            total_pool = split.total,
+            nodes_share = split.nodes,
             total_weight = total_weight,
-            node_count = weights.len(),
+            node_count = payouts.len(),
+            dry_run = !chain.enabled(),
             "Distributing epoch rewards"
         );
 
         let mut nodes_paid = 0;
         let mut total_paid = 0u64;
 
-        for weight in &weights {
-            let reward = weight.calculate_share(nodes_share, total_weight);
-            if reward == 0 {
+        for payout in &mut payouts {
+            // Allocated before a restart; do not pay twice
+            if let Some((amount, tx)) = already_paid.remove(&payout.node_id) {
+                payout.reward = amount;
+                payout.tx_signature = tx;
+                payout.status = PAYOUT_ALLOCATED.to_string();
+                nodes_paid += 1;
+                total_paid += amount;
+                continue;
+            }
+            if payout.reward == 0 {
                 continue;
             }
 
-            let tx_signature = if self.config.enable_blockchain {
-                if let Some(blockchain) = blockchain {
-                    if let Some(ref wallet) = weight.wallet_address {
-                        if let Ok(owner) = Pubkey::from_str(wallet) {
-                            match blockchain
-                                .allocate_node_reward(epoch as u64, &owner, &weight.peer_id, reward)
-                                .await
-                            {
-                                Ok(sig) => Some(sig),
-                                Err(e) => {
-                                    warn!(error = %e, node_id = %weight.node_id, "Failed to allocate reward");
-                                    None
-                                }
-                            }
-                        } else {
-                            None
-                        }
-                    } else {
-                        None
+            if chain.enabled() {
+                let Some(wallet) = payout.wallet_address.clone() else {
+                    payout.status = PAYOUT_NO_WALLET.to_string();
+                    continue;
+                };
+                match chain
+                    .allocate_reward(epoch, &wallet, &payout.peer_id, payout.reward)
+                    .await
+                {
+                    Ok(sig) => {
+                        payout.tx_signature = Some(sig);
+                        payout.status = PAYOUT_ALLOCATED.to_string();
+                    }
+                    Err(e) => {
+                        warn!(error = %e, node_id = %payout.node_id, "Failed to allocate reward");
+                        payout.status = PAYOUT_FAILED.to_string();
+                        continue;
                     }
-                } else {
-                    None
                 }
             } else {
-                None
-            };
+                payout.status = PAYOUT_DRY_RUN.to_string();
+            }
 
             db.mark_payment_allocated(
-                weight.node_id,
+                payout.node_id,
                 epoch,
-                reward as i64,
-                tx_signature.as_deref(),
+                payout.reward as i64,
+                payout.tx_signature.as_deref(),
             )
             .await?;
             nodes_paid += 1;
-            total_paid += reward;
+            total_paid += payout.reward;
 
             debug!(
-                node_id = %weight.node_id,
-                peer_id = %weight.peer_id,
-                reward = reward,
-                weight = weight.weight,
+                node_id = %payout.node_id,
+                peer_id = %payout.peer_id,
+                reward = payout.reward,
+                weight = payout.weight,
                 "Reward allocated"
             );
         }
 
-        let finalize_tx = if self.config.enable_blockchain {
-            if let Some(blockchain) = blockchain {
-                blockchain.finalize_epoch(epoch as u64).await.ok()
-            } else {
-                None
-            }
-        } else {
-            None
-        };
-
-        let platform_share = (total_pool_amount as f64 * 0.10) as i64;
-        let community_share = (total_pool_amount as f64 * 0.05) as i64;
+        let finalize_tx = chain.finalize_epoch(epoch).await;
 
         db.finalize_payment_epoch(
             epoch,
-            total_pool_amount as i64,
-            nodes_share as i64,
-            platform_share,
-            community_share,
+            split.total as i64,
+            split.nodes as i64,
+            split.platform as i64,
+            split.community as i64,
             nodes_paid,
             finalize_tx.as_deref(),
         )
         .await?;
 
+        // Platform and community shares can only be claimed once finalized
+        let platform_claim_tx = chain.claim_platform_share(epoch).await;
+        if platform_claim_tx.is_some() {
+            db.mark_platform_claimed(epoch).await?;
+        }
+        let community_claim_tx = chain.claim_community_share(epoch).await;
+        if community_claim_tx.is_some() {
+            db.mark_community_claimed(epoch).await?;
+        }
+
         {
             let mut metrics = self.metrics.write().await;
             metrics.nodes_paid += nodes_paid as u64;
@@ -669,128 +645,281 @@ impl PaymentDaemon {
             epoch = epoch,
             nodes_paid = nodes_paid,
             total_paid = total_paid,
+            dry_run = !chain.enabled(),
             "Epoch payment complete"
         );
-        Ok(())
+
+        Ok(Some(EpochPayoutReport {
+            epoch,
+            total_pool_amount: split.total as i64,
+            nodes_share: split.nodes as i64,
+            platform_share: split.platform as i64,
+            community_share: split.community as i64,
+            total_weight: i64::try_from(total_weight).unwrap_or(i64::MAX),
+            nodes_paid,
+            total_paid: total_paid as i64,
+            payouts: serde_json::to_value(&payouts)?,
+            dry_run: !chain.enabled(),
+            finalize_tx_signature: finalize_tx,
+            platform_claim_tx_signature: platform_claim_tx,
+            community_claim_tx_signature: community_claim_tx,
+            next_epoch_tx_signature: None,
+            created_at: Utc::now(),
+        }))
     }
 
-    /// Finalize epoch and distribute payments (without blockchain - dry-run mode)
-    #[cfg(not(feature = "blockchain"))]
-    async fn finalize_and_pay_epoch(
-        &self,
-        metadata: &MetadataService,
-        _blockchain: BlockchainRef<'_>,
-        epoch: i64,
-    ) -> anyhow::Result<()> {
-        let db = metadata.database();
+    /// Record an error in metrics
+    async fn record_error(&self, error: &str) {
+        let mut metrics = self.metrics.write().await;
+        metrics.last_error = Some(error.to_string());
+    }
 
-        let uptimes = db.get_epoch_uptime(epoch).await?;
-        if uptimes.is_empty() {
-            warn!(
-                epoch = epoch,
-                "No uptime records for epoch, skipping payment"
-            );
-            return Ok(());
-        }
+    /// Get current metrics
+    pub async fn get_metrics(&self) -> PaymentDaemonMetrics {
+        self.metrics.read().await.clone()
+    }
+}
 
-        let mut weights: Vec<NodeWeight> = Vec::new();
-        for uptime in &uptimes {
-            if let Some(node) = db.get_node(uptime.node_id).await? {
-                let reputation = 5000u16;
-                let weight = NodeWeight::calculate(
-                    uptime.node_id,
-                    node.peer_id.clone(),
-                    node.wallet_address.clone(),
-                    node.storage_total,
-                    uptime.seconds_online,
-                    EPOCH_DURATION_SECONDS,
-                    reputation,
-                );
-                weights.push(weight);
+/// Payment pool transactions for one daemon cycle
+///
+/// Every operation is a no-op returning `None` when blockchain payments are
+/// disabled, so the epoch pipeline runs unchanged in dry-run mode.
+struct Chain<'a> {
+    client: BlockchainRef<'a>,
+    #[cfg_attr(not(feature = "blockchain"), allow(dead_code))]
+    pool_token_account: Option<String>,
+}
+
+impl Chain<'_> {
+    /// Whether transactions are sent
+    fn enabled(&self) -> bool {
+        self.client.is_some()
+    }
+
+    /// Whether the on-chain epoch is over (`None` = decide locally)
+    async fn should_end_epoch(&self) -> Option<bool> {
+        #[cfg(feature = "blockchain")]
+        if let Some(client) = self.client {
+            match client.should_end_epoch().await {
+                Ok(due) => return Some(due),
+                Err(e) => warn!(error = %e, "Failed to query epoch end, using local duration"),
             }
         }
+        None
+    }
 
-        if weights.is_empty() {
-            warn!(epoch = epoch, "No valid node weights for epoch");
-            return Ok(());
+    /// Pool split recorded on-chain for an epoch
+    async fn epoch_pool(&self, epoch: i64) -> Option<PoolSplit> {
+        #[cfg(feature = "blockchain")]
+        if let Some(client) = self.client {
+            match client.get_epoch_rewards(epoch as u64).await {
+                Ok(Some(rewards)) => {
+                    return Some(PoolSplit {
+                        total: rewards.total_pool_amount,
+                        nodes: rewards.nodes_share,
+                        platform: rewards.platform_share,
+                        community: rewards.community_share,
+                    })
+                }
+                Ok(None) => {}
+                Err(e) => warn!(error = %e, epoch = epoch, "Failed to read epoch rewards"),
+            }
         }
+        #[cfg(not(feature = "blockchain"))]
+        let _ = epoch;
+        None
+    }
 
-        let total_weight: u64 = weights.iter().map(|w| w.weight).sum();
-
-        // Dry-run mode: calculate theoretical amounts
-        let total_pool_amount = 1_000_000_000u64;
-        let nodes_share = 850_000_000u64;
+    /// Allocate a node's reward, returning the transaction signature
+    async fn allocate_reward(
+        &self,
+        epoch: i64,
+        wallet: &str,
+        peer_id: &str,
+        amount: u64,
+    ) -> anyhow::Result<String> {
+        #[cfg(feature = "blockchain")]
+        if let Some(client) = self.client {
+            let owner = Pubkey::from_str(wallet)?;
+            return client
+                .allocate_node_reward(epoch as u64, &owner, peer_id, amount)
+                .await;
+        }
+        #[cfg(not(feature = "blockchain"))]
+        let _ = (epoch, wallet, peer_id, amount);
+        anyhow::bail!("blockchain payments are disabled")
+    }
 
-        info!(
-            epoch = epoch,
-            total_pool = total_pool_amount,
-            nodes_share = nodes_share,
-            total_weight = total_weight,
-            node_count = weights.len(),
-            "Distributing epoch rewards (dry-run)"
-        );
+    /// Slash a node's stake
+    async fn slash(&self, wallet: &str, peer_id: &str, reason: SlashReason) -> Option<String> {
+        #[cfg(feature = "blockchain")]
+        if let Some(client) = self.client {
+            let owner = Pubkey::from_str(wallet).ok()?;
+            match client
+                .slash_node(&owner, peer_id, 0, &reason.to_string())
+                .await
+            {
+                Ok(sig) => return Some(sig),
+                Err(e) => warn!(
+                    error = %e,
+                    peer_id = %peer_id,
+                    "Failed to execute slash on blockchain"
+                ),
+            }
+        }
+        #[cfg(not(feature = "blockchain"))]
+        let _ = (wallet, peer_id, reason);
+        None
+    }
 
-        let mut nodes_paid = 0;
-        let mut total_paid = 0u64;
+    /// Close the epoch's reward allocation
+    async fn finalize_epoch(&self, epoch: i64) -> Option<String> {
+        #[cfg(feature = "blockchain")]
+        if let Some(client) = self.client {
+            match client.finalize_epoch(epoch as u64).await {
+                Ok(sig) => return Some(sig),
+                Err(e) => warn!(error = %e, epoch = epoch, "Failed to finalize epoch on-chain"),
+            }
+        }
+        #[cfg(not(feature = "blockchain"))]
+        let _ = epoch;
+        None
+    }
 
-        for weight in &weights {
-            let reward = weight.calculate_share(nodes_share, total_weight);
-            if reward == 0 {
-                continue;
+    /// Transfer the platform share to the treasury
+    async fn claim_platform_share(&self, epoch: i64) -> Option<String> {
+        #[cfg(feature = "blockchain")]
+        if let (Some(client), Some(pool_account)) = (self.client, self.pool_account()) {
+            match client
+                .claim_platform_share(epoch as u64, &pool_account)
+                .await
+            {
+                Ok(sig) => return Some(sig),
+                Err(e) => warn!(error = %e, epoch = epoch, "Failed to claim platform share"),
             }
+        }
+        #[cfg(not(feature = "blockchain"))]
+        let _ = epoch;
+        None
+    }
 
-            db.mark_payment_allocated(weight.node_id, epoch, reward as i64, None)
-                .await?;
-            nodes_paid += 1;
-            total_paid += reward;
+    /// Transfer the community share to the community fund
+    async fn claim_community_share(&self, epoch: i64) -> Option<String> {
+        #[cfg(feature = "blockchain")]
+        if let (Some(client), Some(pool_account)) = (self.client, self.pool_account()) {
+            match client
+                .claim_community_share(epoch as u64, &pool_account)
+                .await
+            {
+                Ok(sig) => return Some(sig),
+                Err(e) => warn!(error = %e, epoch = epoch, "Failed to claim community share"),
+            }
+        }
+        #[cfg(not(feature = "blockchain"))]
+        let _ = epoch;
+        None
+    }
 
-            debug!(
-                node_id = %weight.node_id,
-                peer_id = %weight.peer_id,
-                reward = reward,
-                "Reward allocated (dry-run)"
-            );
+    /// Open the next on-chain epoch
+    async fn start_new_epoch(&self) -> Option<String> {
+        #[cfg(feature = "blockchain")]
+        if let Some(client) = self.client {
+            match client.start_new_epoch().await {
+                Ok(sig) => return Some(sig),
+                Err(e) => warn!(error = %e, "Failed to start new epoch on-chain"),
+            }
         }
+        None
+    }
 
-        let platform_share = (total_pool_amount as f64 * 0.10) as i64;
-        let community_share = (total_pool_amount as f64 * 0.05) as i64;
+    /// Pool token account the shares are claimed from
+    #[cfg(feature = "blockchain")]
+    fn pool_account(&self) -> Option<Pubkey> {
+        let account = self.pool_token_account.as_deref()?;
+        match Pubkey::from_str(account) {
+            Ok(pubkey) => Some(pubkey),
+            Err(e) => {
+                warn!(error = %e, "Invalid PAYMENT_POOL_TOKEN_ACCOUNT, not claiming shares");
+                None
+            }
+        }
+    }
+}
 
-        db.finalize_payment_epoch(
-            epoch,
-            total_pool_amount as i64,
-            nodes_share as i64,
-            platform_share,
-            community_share,
-            nodes_paid,
-            None,
-        )
-        .await?;
+/// Division of an epoch's pool between nodes, platform and community
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PoolSplit {
+    pub total: u64,
+    pub nodes: u64,
+    pub platform: u64,
+    pub community: u64,
+}
 
-        {
-            let mut metrics = self.metrics.write().await;
-            metrics.nodes_paid += nodes_paid as u64;
-            metrics.total_paid_amount += total_paid;
+impl PoolSplit {
+    /// Split `total` by percentages
+    ///
+    /// When the percentages add up to 100, the rounding remainder goes to
+    /// the nodes so the shares add up to the pool.
+    pub fn new(total: u64, node_percent: u8, platform_percent: u8, community_percent: u8) -> Self {
+        let part = |percent: u8| (total as u128 * percent as u128 / 100) as u64;
+        let platform = part(platform_percent);
+        let community = part(community_percent);
+        let nodes =
+            if node_percent as u16 + platform_percent as u16 + community_percent as u16 == 100 {
+                total - platform - community
+            } else {
+                part(node_percent)
+            };
+        Self {
+            total,
+            nodes,
+            platform,
+            community,
         }
-
-        info!(
-            epoch = epoch,
-            nodes_paid = nodes_paid,
-            total_paid = total_paid,
-            "Epoch payment complete (dry-run)"
-        );
-        Ok(())
     }
+}
 
-    /// Record an error in metrics
-    async fn record_error(&self, error: &str) {
-        let mut metrics = self.metrics.write().await;
-        metrics.last_error = Some(error.to_string());
-    }
+/// Reputation (0-10000) from a node's service record in an epoch
+///
+/// Starts from the share of tracked time the node was online and loses 100
+/// points per percent of stake slashed during the epoch.
+pub fn sla_reputation(uptime: &NodeEpochUptime, slashes: &[&SlashingEvent]) -> u16 {
+    let base = (uptime.uptime_ratio() * 10000.0).round() as i64;
+    let penalty: i64 = slashes.iter().map(|s| s.slash_percent as i64 * 100).sum();
+    (base - penalty).clamp(0, 10000) as u16
+}
 
-    /// Get current metrics
-    pub async fn get_metrics(&self) -> PaymentDaemonMetrics {
-        self.metrics.read().await.clone()
-    }
+/// Each node's reward from the nodes' share, proportional to its weight
+///
+/// Returns the total weight and one payout per node; rewards round down, so
+/// the remainder stays in the pool.
+pub fn plan_payouts(weights: &[NodeWeight], nodes_share: u64) -> (u64, Vec<NodePayout>) {
+    let total_weight: u64 = weights.iter().map(|w| w.weight).sum();
+    let payouts = weights
+        .iter()
+        .map(|w| {
+            let reward = w.calculate_share(nodes_share, total_weight);
+            NodePayout {
+                node_id: w.node_id,
+                peer_id: w.peer_id.clone(),
+                wallet_address: w.wallet_address.clone(),
+                storage_bytes: w.storage_bytes,
+                uptime_seconds: w.uptime_seconds,
+                uptime_factor: w.uptime_factor,
+                reputation: w.reputation,
+                weight: w.weight,
+                reward,
+                tx_signature: None,
+                status: if reward == 0 {
+                    PAYOUT_ZERO_REWARD
+                } else {
+                    PAYOUT_PENDING
+                }
+                .to_string(),
+            }
+        })
+        .collect();
+    (total_weight, payouts)
 }
 
 #[cfg(test)]
@@ -803,6 +932,7 @@ mod tests {
         assert_eq!(config.accumulate_interval.as_secs(), 60);
         assert_eq!(config.extended_downtime_threshold.as_secs(), 4 * 60 * 60);
         assert!(config.enable_blockchain);
+        assert!(config.pool_token_account.is_none());
     }
 
     #[test]
@@ -818,4 +948,94 @@ mod tests {
         assert_eq!(metrics.epochs_finalized, 0);
         assert_eq!(metrics.nodes_paid, 0);
     }
+
+    fn uptime(seconds_online: i64, seconds_offline: i64) -> NodeEpochUptime {
+        NodeEpochUptime {
+            id: Uuid::new_v4(),
+            node_id: Uuid::new_v4(),
+            epoch: 1,
+            epoch_start: Utc::now(),
+            epoch_end: None,
+            seconds_online,
+            seconds_offline,
+            last_status_change: None,
+            payment_allocated: false,
+            payment_amount: None,
+            payment_tx_signature: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn slash(node_id: Uuid, slash_percent: i16) -> SlashingEvent {
+        SlashingEvent {
+            id: Uuid::new_v4(),
+            node_id,
+            epoch: 1,
+            reason: SlashReason::ExtendedDowntime.to_string(),
+            slash_percent,
+            slash_amount: None,
+            tx_signature: None,
+            details: None,
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_pool_split_default_percentages() {
+        let split = PoolSplit::new(
+            1_000_000_001,
+            NODE_PERCENT,
+            PLATFORM_PERCENT,
+            COMMUNITY_PERCENT,
+        );
+        assert_eq!(split.platform, 100_000_000);
+        assert_eq!(split.community, 50_000_000);
+        // Rounding remainder goes to the nodes
+        assert_eq!(split.nodes, 850_000_001);
+        assert_eq!(split.nodes + split.platform + split.community, split.total);
+    }
+
+    #[test]
+    fn test_sla_reputation() {
+        let full = uptime(EPOCH_DURATION_SECONDS, 0);
+        assert_eq!(sla_reputation(&full, &[]), 10000);
+
+        let half = uptime(100, 100);
+        assert_eq!(sla_reputation(&half, &[]), 5000);
+
+        let slashed = slash(half.node_id, 5);
+        assert_eq!(sla_reputation(&half, &[&slashed]), 4500);
+
+        let never_seen = uptime(0, 0);
+        assert_eq!(sla_reputation(&never_seen, &[&slashed]), 0);
+    }
+
+    #[test]
+    fn test_plan_payouts_by_stored_bytes() {
+        let weight = |stored: i64, online: i64| {
+            NodeWeight::calculate(
+                Uuid::new_v4(),
+                "peer".to_string(),
+                Some("wallet".to_string()),
+                stored,
+                online,
+                EPOCH_DURATION_SECONDS,
+                5000,
+            )
+        };
+        let weights = vec![
+            weight(3_000, EPOCH_DURATION_SECONDS),
+            weight(1_000, EPOCH_DURATION_SECONDS),
+            weight(0, EPOCH_DURATION_SECONDS),
+        ];
+
+        let (total_weight, payouts) = plan_payouts(&weights, 850);
+        assert_eq!(total_weight, 4_000);
+        assert_eq!(payouts[0].reward, 637);
+        assert_eq!(payouts[1].reward, 212);
+        assert_eq!(payouts[2].reward, 0);
+        assert_eq!(payouts[2].status, PAYOUT_ZERO_REWARD);
+        assert!(payouts.iter().map(|p| p.reward).sum::<u64>() <= 850);
+    }
 }
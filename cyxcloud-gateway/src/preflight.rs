@@ -0,0 +1,293 @@
+//! Startup Preflight
+//!
+//! The gateway used to log a failed migration and carry on, so a schema that
+//! did not match the binary only showed up as confusing errors on the first
+//! writes. Before accepting writes the gateway now checks that:
+//!
+//! - the metadata database has exactly the migrations this binary ships
+//!   (none pending or failed, none from a newer gateway)
+//! - Redis answers, if a Redis URL is configured
+//! - at least `min_registered_nodes` storage nodes are registered
+//!
+//! Until every check passes the S3 API is read-only: reads are served and
+//! writes answer 503. Failing checks are retried every `retry_interval` and
+//! reported on `/readyz`. `--skip-preflight` (or `[preflight] enabled =
+//! false`) accepts writes at once.
+
+use crate::health_api::CheckStatus;
+use crate::state::AppState;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+/// Preflight configuration
+#[derive(Debug, Clone, PartialEq)]
+pub struct PreflightConfig {
+    /// Gate S3 writes on the checks
+    pub enabled: bool,
+    /// Registered storage nodes needed (0 skips the check)
+    pub min_registered_nodes: usize,
+    /// Wait between attempts while a check fails
+    pub retry_interval: Duration,
+    /// Time limit of each check
+    pub check_timeout: Duration,
+}
+
+impl Default for PreflightConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min_registered_nodes: 1,
+            retry_interval: Duration::from_secs(10),
+            check_timeout: Duration::from_secs(2),
+        }
+    }
+}
+
+/// Outcome of one preflight check
+#[derive(Debug, Clone, PartialEq)]
+pub struct PreflightCheck {
+    pub name: &'static str,
+    pub passed: bool,
+    pub detail: String,
+}
+
+impl PreflightCheck {
+    fn pass(name: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            passed: true,
+            detail: detail.into(),
+        }
+    }
+
+    fn fail(name: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            passed: false,
+            detail: detail.into(),
+        }
+    }
+}
+
+/// Outcome of one preflight attempt
+#[derive(Debug, Clone, Default)]
+pub struct PreflightReport {
+    pub checks: Vec<PreflightCheck>,
+}
+
+impl PreflightReport {
+    /// Whether every check passed
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(|check| check.passed)
+    }
+
+    /// `name: detail` of every failed check
+    pub fn failures(&self) -> Vec<String> {
+        self.checks
+            .iter()
+            .filter(|check| !check.passed)
+            .map(|check| format!("{}: {}", check.name, check.detail))
+            .collect()
+    }
+}
+
+/// Whether S3 writes wait for the preflight
+///
+/// Open by default, so a gateway that never runs the preflight (tests,
+/// `--skip-preflight`) accepts writes.
+#[derive(Debug, Default)]
+pub struct PreflightGate {
+    pending: AtomicBool,
+    failures: Mutex<Vec<String>>,
+}
+
+impl PreflightGate {
+    /// Whether the preflight passed (or is not run)
+    pub fn is_open(&self) -> bool {
+        !self.pending.load(Ordering::Acquire)
+    }
+
+    /// Failed checks of the last attempt
+    pub fn failures(&self) -> Vec<String> {
+        self.failures.lock().unwrap().clone()
+    }
+
+    /// Refuse writes until a report passes
+    fn close(&self) {
+        self.pending.store(true, Ordering::Release);
+    }
+
+    /// Record an attempt, opening the gate if it passed
+    fn record(&self, report: &PreflightReport) {
+        *self.failures.lock().unwrap() = report.failures();
+        if report.passed() {
+            self.pending.store(false, Ordering::Release);
+        }
+    }
+}
+
+/// Runs the startup checks until they pass
+pub struct Preflight {
+    config: PreflightConfig,
+}
+
+impl Preflight {
+    /// Create a new preflight
+    pub fn new(config: PreflightConfig) -> Self {
+        Self { config }
+    }
+
+    /// Close the write gate and retry the checks in the background until
+    /// they pass
+    pub fn start(self: Arc<Self>, state: Arc<AppState>) -> JoinHandle<()> {
+        state.preflight().close();
+        info!("Startup preflight running, S3 writes refused until it passes");
+
+        tokio::spawn(async move {
+            let mut attempt = 1u32;
+            loop {
+                let report = self.run(&state).await;
+                state.preflight().record(&report);
+                if report.passed() {
+                    info!(
+                        attempts = attempt,
+                        "Startup preflight passed, S3 writes accepted"
+                    );
+                    return;
+                }
+                for failure in report.failures() {
+                    warn!(attempt = attempt, "Preflight check failed: {}", failure);
+                }
+                attempt += 1;
+                tokio::time::sleep(self.config.retry_interval).await;
+            }
+        })
+    }
+
+    /// Run every check once
+    pub async fn run(&self, state: &AppState) -> PreflightReport {
+        let (schema, redis, nodes) = tokio::join!(
+            self.check_schema(state),
+            self.check_redis(state),
+            self.check_nodes(state),
+        );
+        PreflightReport {
+            checks: vec![schema, redis, nodes],
+        }
+    }
+
+    async fn check_schema(&self, state: &AppState) -> PreflightCheck {
+        let Some(meta) = state.metadata_service() else {
+            return PreflightCheck::pass("schema", "memory storage");
+        };
+        match self
+            .timed(meta.database().schema_status())
+            .await
+            .and_then(|result| result.map_err(|e| e.to_string()))
+        {
+            Ok(status) if status.is_current() => PreflightCheck::pass("schema", status.to_string()),
+            Ok(status) => PreflightCheck::fail("schema", status.to_string()),
+            Err(e) => PreflightCheck::fail("schema", e),
+        }
+    }
+
+    async fn check_redis(&self, state: &AppState) -> PreflightCheck {
+        let check = state.readiness().check_redis().await;
+        let detail = check.detail.unwrap_or_else(|| "reachable".to_string());
+        match check.status {
+            CheckStatus::Down => PreflightCheck::fail("redis", detail),
+            _ => PreflightCheck::pass("redis", detail),
+        }
+    }
+
+    async fn check_nodes(&self, state: &AppState) -> PreflightCheck {
+        let min_nodes = self.config.min_registered_nodes;
+        let Some(meta) = state.metadata_service() else {
+            return PreflightCheck::pass("nodes", "memory storage");
+        };
+        if min_nodes == 0 {
+            return PreflightCheck::pass("nodes", "check disabled");
+        }
+
+        let counts = self
+            .timed(meta.database().count_nodes_by_status())
+            .await
+            .and_then(|result| result.map_err(|e| e.to_string()));
+        match counts {
+            Ok(counts) => {
+                let registered = counts.values().map(|n| (*n).max(0) as usize).sum::<usize>();
+                let detail = format!("{} registered, {} required", registered, min_nodes);
+                if registered >= min_nodes {
+                    PreflightCheck::pass("nodes", detail)
+                } else {
+                    PreflightCheck::fail("nodes", detail)
+                }
+            }
+            Err(e) => PreflightCheck::fail("nodes", e),
+        }
+    }
+
+    /// Run a check with the configured time limit
+    async fn timed<T>(&self, check: impl Future<Output = T>) -> Result<T, String> {
+        tokio::time::timeout(self.config.check_timeout, check)
+            .await
+            .map_err(|_| {
+                format!(
+                    "timed out after {}ms",
+                    self.config.check_timeout.as_millis()
+                )
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preflight_gate() {
+        let gate = PreflightGate::default();
+        assert!(gate.is_open());
+
+        gate.close();
+        assert!(!gate.is_open());
+
+        let failing = PreflightReport {
+            checks: vec![
+                PreflightCheck::pass("redis", "reachable"),
+                PreflightCheck::fail("schema", "pending: [41]"),
+            ],
+        };
+        assert!(!failing.passed());
+        gate.record(&failing);
+        assert!(!gate.is_open());
+        assert_eq!(gate.failures(), vec!["schema: pending: [41]".to_string()]);
+
+        let passing = PreflightReport {
+            checks: vec![PreflightCheck::pass("schema", "current")],
+        };
+        gate.record(&passing);
+        assert!(gate.is_open());
+        assert!(gate.failures().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_memory_mode_passes() {
+        let state = Arc::new(AppState::new());
+        let report = Preflight::new(PreflightConfig::default()).run(&state).await;
+        assert!(report.passed(), "{:?}", report.failures());
+        assert_eq!(report.checks.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_start_opens_gate() {
+        let state = Arc::new(AppState::new());
+        let handle = Arc::new(Preflight::new(PreflightConfig::default())).start(state.clone());
+        handle.await.unwrap();
+        assert!(state.preflight().is_open());
+    }
+}
@@ -0,0 +1,512 @@
+//! Proof-of-Storage Auditor
+//!
+//! Background task that challenges nodes for chunks they are recorded as
+//! storing. Every challenge gets a random nonce that identifies it in evidence
+//! records; the node must return the chunk, and the BLAKE3 hash of what it
+//! returns (the proof) has to match the chunk ID. Results go to
+//! `chunk_locations`: a pass refreshes `last_verified`, a failure extends the
+//! location's failure streak. Every result and the read latency also feed the
+//! node's reputation.
+//!
+//! When a streak reaches the evidence threshold the auditor persists a
+//! slashing evidence record with the chunk, challenge, expected and returned
+//! proof and timestamps. With slashing enabled it also records a slashing
+//! event (at most one per node, reason and epoch) and submits the slash
+//! through the node registry when a blockchain client is available. Evidence
+//! is stored either way.
+
+use crate::node_client::{NodeClient, NodeClientError};
+use crate::state::AppState;
+use chrono::{DateTime, Utc};
+use cyxcloud_metadata::{
+    ChunkAuditTarget, ChunkLocation, CreateSlashingEvidence, Database, MetadataService, SlashReason,
+};
+use rand::RngCore;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
+use tokio::time::interval;
+use tracing::{debug, error, info, warn};
+use uuid::Uuid;
+
+#[cfg(feature = "blockchain")]
+use solana_sdk::pubkey::Pubkey;
+#[cfg(feature = "blockchain")]
+use std::str::FromStr;
+
+/// Consecutive failures at which `chunk_locations` marks a location failed.
+/// Failed locations are no longer audited, so the evidence threshold is
+/// capped here.
+const MAX_EVIDENCE_THRESHOLD: i32 = 3;
+
+/// Proof-of-storage auditor configuration
+#[derive(Debug, Clone)]
+pub struct ProofAuditConfig {
+    /// How often to run an audit cycle
+    pub audit_interval: Duration,
+    /// Chunk locations challenged per cycle
+    pub batch_size: i64,
+    /// Consecutive failures of a location that produce evidence (1-3)
+    pub evidence_threshold: i32,
+    /// Record slashing events and submit slash transactions for evidence
+    pub slash_on_evidence: bool,
+}
+
+impl Default for ProofAuditConfig {
+    fn default() -> Self {
+        Self {
+            audit_interval: Duration::from_secs(5 * 60),
+            batch_size: 50,
+            evidence_threshold: MAX_EVIDENCE_THRESHOLD,
+            slash_on_evidence: false,
+        }
+    }
+}
+
+impl ProofAuditConfig {
+    /// Create configuration from environment variables
+    pub fn from_env() -> Self {
+        Self {
+            audit_interval: Duration::from_secs(
+                std::env::var("PROOF_AUDIT_INTERVAL_SECS")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(5 * 60),
+            ),
+            batch_size: std::env::var("PROOF_AUDIT_BATCH_SIZE")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(50),
+            evidence_threshold: std::env::var("PROOF_AUDIT_EVIDENCE_THRESHOLD")
+                .ok()
+                .and_then(|v| v.parse::<i32>().ok())
+                .unwrap_or(MAX_EVIDENCE_THRESHOLD)
+                .clamp(1, MAX_EVIDENCE_THRESHOLD),
+            slash_on_evidence: std::env::var("PROOF_AUDIT_SLASH")
+                .map(|v| v == "true" || v == "1")
+                .unwrap_or(false),
+        }
+    }
+}
+
+/// Proof-of-storage auditor metrics
+#[derive(Debug, Default, Clone)]
+pub struct ProofAuditMetrics {
+    pub challenges_passed: u64,
+    pub challenges_failed: u64,
+    pub evidence_recorded: u64,
+    pub slashing_events: u64,
+    pub last_cycle_at: Option<DateTime<Utc>>,
+}
+
+/// Response of a node to one challenge
+#[derive(Debug, Clone)]
+struct ChallengeOutcome {
+    /// Random nonce identifying the challenge
+    challenge: Vec<u8>,
+    challenged_at: DateTime<Utc>,
+    /// When the node answered (`None` if it could not be reached)
+    responded_at: Option<DateTime<Utc>>,
+    /// BLAKE3 hash of the returned chunk data
+    returned_proof: Option<Vec<u8>>,
+    /// The node answered that it does not have the chunk
+    missing: bool,
+    error: Option<String>,
+}
+
+impl ChallengeOutcome {
+    /// Whether the returned proof matches the expected one
+    fn passed(&self, expected_proof: &[u8]) -> bool {
+        self.returned_proof.as_deref() == Some(expected_proof)
+    }
+
+    /// Time the node took to return the chunk
+    fn read_latency_ms(&self) -> Option<f64> {
+        self.returned_proof.as_ref()?;
+        let responded_at = self.responded_at?;
+        Some((responded_at - self.challenged_at).num_microseconds()? as f64 / 1000.0)
+    }
+
+    /// Slashing reason for a failed challenge
+    fn reason(&self) -> SlashReason {
+        if self.missing {
+            SlashReason::DataLoss
+        } else if self.returned_proof.is_some() {
+            SlashReason::CorruptedData
+        } else {
+            SlashReason::FailedProofs
+        }
+    }
+}
+
+/// Proof-of-storage auditor
+pub struct ProofAuditor {
+    config: ProofAuditConfig,
+    metrics: Arc<RwLock<ProofAuditMetrics>>,
+}
+
+impl ProofAuditor {
+    /// Create a new proof-of-storage auditor
+    pub fn new(config: ProofAuditConfig) -> Self {
+        Self {
+            config,
+            metrics: Arc::new(RwLock::new(ProofAuditMetrics::default())),
+        }
+    }
+
+    /// Start the auditor as a background task
+    pub fn start(self: Arc<Self>, state: Arc<AppState>) -> JoinHandle<()> {
+        let auditor = self;
+
+        tokio::spawn(async move {
+            let mut timer = interval(auditor.config.audit_interval);
+
+            info!(
+                interval_secs = auditor.config.audit_interval.as_secs(),
+                batch_size = auditor.config.batch_size,
+                evidence_threshold = auditor.config.evidence_threshold,
+                slash_on_evidence = auditor.config.slash_on_evidence,
+                "Proof-of-storage auditor started"
+            );
+
+            loop {
+                timer.tick().await;
+
+                if let Some(metadata) = state.metadata_service() {
+                    if let Err(e) = auditor.run_audit_cycle(&state, metadata).await {
+                        error!(error = %e, "Proof-of-storage audit cycle failed");
+                    }
+                } else {
+                    debug!("Metadata service not available, skipping proof-of-storage audit");
+                }
+            }
+        })
+    }
+
+    /// Challenge a batch of chunk locations
+    async fn run_audit_cycle(
+        &self,
+        state: &AppState,
+        metadata: &MetadataService,
+    ) -> anyhow::Result<()> {
+        let db = metadata.database();
+        let targets = db.get_chunk_audit_targets(self.config.batch_size).await?;
+
+        let mut passed = 0u64;
+        let mut failed = 0u64;
+
+        for target in &targets {
+            let outcome = challenge_chunk(state.node_client(), target).await;
+            let valid = outcome.passed(&target.chunk_id);
+
+            let location = db
+                .update_chunk_verification(&target.chunk_id, target.node_id, valid)
+                .await?;
+            if let Err(e) = db
+                .record_node_verification(target.node_id, valid, outcome.read_latency_ms())
+                .await
+            {
+                warn!(error = %e, node_id = %target.node_id, "Failed to record node verification");
+            }
+
+            if valid {
+                passed += 1;
+                continue;
+            }
+            failed += 1;
+
+            warn!(
+                node_id = %target.node_id,
+                chunk_id = %hex::encode(&target.chunk_id),
+                reason = %outcome.reason(),
+                error = outcome.error.as_deref().unwrap_or(""),
+                "Proof-of-storage challenge failed"
+            );
+
+            // The location may have been removed while it was challenged
+            let Some(location) = location else {
+                continue;
+            };
+            if location.verification_failures != self.config.evidence_threshold {
+                continue;
+            }
+
+            if let Err(e) = self
+                .record_evidence(state, db, target, &location, outcome)
+                .await
+            {
+                error!(
+                    error = %e,
+                    node_id = %target.node_id,
+                    chunk_id = %hex::encode(&target.chunk_id),
+                    "Failed to record slashing evidence"
+                );
+            }
+        }
+
+        {
+            let mut metrics = self.metrics.write().await;
+            metrics.challenges_passed += passed;
+            metrics.challenges_failed += failed;
+            metrics.last_cycle_at = Some(Utc::now());
+        }
+
+        if !targets.is_empty() {
+            debug!(
+                challenged = targets.len(),
+                passed = passed,
+                failed = failed,
+                "Proof-of-storage audit cycle complete"
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Persist evidence for a location whose failure streak hit the threshold
+    async fn record_evidence(
+        &self,
+        state: &AppState,
+        db: &Database,
+        target: &ChunkAuditTarget,
+        location: &ChunkLocation,
+        outcome: ChallengeOutcome,
+    ) -> anyhow::Result<()> {
+        let reason = outcome.reason();
+        let epoch = db
+            .get_current_payment_epoch()
+            .await?
+            .map(|e| e.epoch)
+            .unwrap_or(0);
+
+        let (slashing_event_id, tx_signature) = if self.config.slash_on_evidence {
+            self.slash(state, db, target, location, &outcome, epoch, reason)
+                .await?
+        } else {
+            (None, None)
+        };
+
+        let evidence = db
+            .record_slashing_evidence(&CreateSlashingEvidence {
+                node_id: target.node_id,
+                chunk_id: target.chunk_id.clone(),
+                epoch,
+                reason,
+                challenge: outcome.challenge,
+                expected_proof: target.chunk_id.clone(),
+                returned_proof: outcome.returned_proof,
+                error: outcome.error,
+                failure_count: location.verification_failures,
+                first_failed_at: location.first_failed_at,
+                last_verified: location.last_verified,
+                challenged_at: outcome.challenged_at,
+                responded_at: outcome.responded_at,
+                slashing_event_id,
+                tx_signature,
+            })
+            .await?;
+
+        {
+            let mut metrics = self.metrics.write().await;
+            metrics.evidence_recorded += 1;
+        }
+
+        warn!(
+            evidence_id = %evidence.id,
+            node_id = %target.node_id,
+            peer_id = %target.peer_id,
+            chunk_id = %hex::encode(&target.chunk_id),
+            reason = %reason,
+            failures = location.verification_failures,
+            slashed = evidence.slashing_event_id.is_some(),
+            on_chain = evidence.tx_signature.is_some(),
+            "Slashing evidence recorded"
+        );
+
+        Ok(())
+    }
+
+    /// Record a slashing event for evidence, submitting it on-chain if possible
+    ///
+    /// A node is slashed at most once per reason and epoch; further evidence
+    /// is linked to the existing event. Returns the event ID and the
+    /// signature of a transaction sent for this evidence.
+    async fn slash(
+        &self,
+        state: &AppState,
+        db: &Database,
+        target: &ChunkAuditTarget,
+        location: &ChunkLocation,
+        outcome: &ChallengeOutcome,
+        epoch: i64,
+        reason: SlashReason,
+    ) -> anyhow::Result<(Option<Uuid>, Option<String>)> {
+        if let Some(event) = db
+            .find_slashing_event(target.node_id, epoch, &reason.to_string())
+            .await?
+        {
+            debug!(
+                node_id = %target.node_id,
+                epoch = epoch,
+                reason = %reason,
+                "Node already slashed this epoch, linking evidence"
+            );
+            return Ok((Some(event.id), None));
+        }
+
+        let tx_signature = match target.wallet_address.as_deref() {
+            Some(wallet) => submit_slash(state, wallet, &target.peer_id, reason).await,
+            None => None,
+        };
+
+        let details = serde_json::json!({
+            "chunk_id": hex::encode(&target.chunk_id),
+            "challenge": hex::encode(&outcome.challenge),
+            "failure_count": location.verification_failures,
+        });
+
+        let event = db
+            .record_slashing_event(
+                target.node_id,
+                epoch,
+                &reason.to_string(),
+                reason.slash_percent(),
+                None, // Amount calculated on-chain
+                tx_signature.as_deref(),
+                Some(details),
+            )
+            .await?;
+
+        {
+            let mut metrics = self.metrics.write().await;
+            metrics.slashing_events += 1;
+        }
+
+        Ok((Some(event.id), tx_signature))
+    }
+
+    /// Get current metrics
+    pub async fn get_metrics(&self) -> ProofAuditMetrics {
+        self.metrics.read().await.clone()
+    }
+}
+
+/// Challenge a node to return a chunk and hash what it returns
+async fn challenge_chunk(node_client: &NodeClient, target: &ChunkAuditTarget) -> ChallengeOutcome {
+    let mut challenge = vec![0u8; 32];
+    rand::thread_rng().fill_bytes(&mut challenge);
+    let challenged_at = Utc::now();
+
+    let mut outcome = ChallengeOutcome {
+        challenge,
+        challenged_at,
+        responded_at: None,
+        returned_proof: None,
+        missing: false,
+        error: None,
+    };
+
+    match node_client
+        .get_chunk(&target.grpc_address, &target.chunk_id)
+        .await
+    {
+        Ok(data) => {
+            outcome.responded_at = Some(Utc::now());
+            outcome.returned_proof = Some(blake3::hash(&data).as_bytes().to_vec());
+        }
+        Err(e @ NodeClientError::ChunkNotFound(_)) => {
+            outcome.responded_at = Some(Utc::now());
+            outcome.missing = true;
+            outcome.error = Some(e.to_string());
+        }
+        Err(e) => {
+            outcome.error = Some(e.to_string());
+        }
+    }
+
+    outcome
+}
+
+/// Submit a slash transaction through the node registry
+#[cfg(feature = "blockchain")]
+async fn submit_slash(
+    state: &AppState,
+    wallet: &str,
+    peer_id: &str,
+    reason: SlashReason,
+) -> Option<String> {
+    let client = state.blockchain_client()?;
+    let owner = Pubkey::from_str(wallet).ok()?;
+    match client
+        .slash_node(&owner, peer_id, 0, &reason.to_string())
+        .await
+    {
+        Ok(sig) => Some(sig),
+        Err(e) => {
+            warn!(
+                error = %e,
+                peer_id = %peer_id,
+                "Failed to execute slash on blockchain"
+            );
+            None
+        }
+    }
+}
+
+/// Slashes stay off-chain without the blockchain feature
+#[cfg(not(feature = "blockchain"))]
+async fn submit_slash(
+    _state: &AppState,
+    _wallet: &str,
+    _peer_id: &str,
+    _reason: SlashReason,
+) -> Option<String> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn outcome(returned_proof: Option<Vec<u8>>, missing: bool) -> ChallengeOutcome {
+        ChallengeOutcome {
+            challenge: vec![7; 32],
+            challenged_at: Utc::now(),
+            responded_at: None,
+            returned_proof,
+            missing,
+            error: None,
+        }
+    }
+
+    #[test]
+    fn test_proof_audit_config_default() {
+        let config = ProofAuditConfig::default();
+        assert_eq!(config.audit_interval, Duration::from_secs(300));
+        assert_eq!(config.batch_size, 50);
+        assert_eq!(config.evidence_threshold, MAX_EVIDENCE_THRESHOLD);
+        assert!(!config.slash_on_evidence);
+    }
+
+    #[test]
+    fn test_challenge_passes_on_matching_proof() {
+        let data = b"chunk data";
+        let chunk_id = blake3::hash(data).as_bytes().to_vec();
+
+        assert!(outcome(Some(chunk_id.clone()), false).passed(&chunk_id));
+        assert!(!outcome(Some(vec![0; 32]), false).passed(&chunk_id));
+        assert!(!outcome(None, true).passed(&chunk_id));
+        assert!(!outcome(None, false).passed(&chunk_id));
+    }
+
+    #[test]
+    fn test_failed_challenge_reason() {
+        assert_eq!(outcome(None, true).reason(), SlashReason::DataLoss);
+        assert_eq!(
+            outcome(Some(vec![0; 32]), false).reason(),
+            SlashReason::CorruptedData
+        );
+        assert_eq!(outcome(None, false).reason(), SlashReason::FailedProofs);
+    }
+}
@@ -0,0 +1,656 @@
+//! Request Rate Limiting
+//!
+//! Tower middleware that throttles S3 API traffic with token buckets:
+//! - Global limit shared by all clients
+//! - Per-user limit keyed by token subject, access key, or client IP
+//! - Request rate (RPS) and bandwidth (bytes/sec) for both
+//!
+//! When Redis is configured the counters live there (one-second windows), so
+//! limits hold across multiple gateway instances. Without Redis, or if Redis
+//! is unreachable, each gateway falls back to in-memory token buckets.
+//!
+//! Throttled requests get an S3-style `SlowDown` error with status 429 and a
+//! `Retry-After` header.
+
+use crate::auth::AuthService;
+use crate::auth_api::extract_client_ip;
+use crate::s3_api::S3Error;
+use crate::state::AppState;
+use axum::{
+    body::Body,
+    http::{header, HeaderMap, HeaderValue, Request},
+    response::{IntoResponse, Response},
+};
+use futures::future::BoxFuture;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::watch;
+use tower::{Layer, Service};
+use tracing::{debug, info, warn};
+
+/// Maximum tracked per-user buckets before idle ones are pruned
+const MAX_TRACKED_USERS: usize = 10_000;
+
+/// Redis key prefix for rate limit counters
+const REDIS_KEY_PREFIX: &str = "ratelimit:s3";
+
+/// Rate limit configuration
+///
+/// A limit of 0 means unlimited.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RateLimitConfig {
+    /// Enable rate limiting
+    pub enabled: bool,
+    /// Requests per second across all clients
+    pub global_rps: f64,
+    /// Requests per second per user
+    pub user_rps: f64,
+    /// Bytes per second across all clients
+    pub global_bandwidth: u64,
+    /// Bytes per second per user
+    pub user_bandwidth: u64,
+    /// Burst size, in seconds of sustained rate
+    pub burst_secs: f64,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            global_rps: 1000.0,
+            user_rps: 100.0,
+            global_bandwidth: 0,
+            user_bandwidth: 0,
+            burst_secs: 2.0,
+        }
+    }
+}
+
+impl RateLimitConfig {
+    /// Create configuration from environment variables
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let mbps = |name: &str| {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.parse::<u64>().ok())
+                .map(|v| v * 1024 * 1024)
+        };
+
+        Self {
+            enabled: std::env::var("RATE_LIMIT_ENABLED")
+                .map(|v| v == "true" || v == "1")
+                .unwrap_or(defaults.enabled),
+            global_rps: std::env::var("RATE_LIMIT_GLOBAL_RPS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.global_rps),
+            user_rps: std::env::var("RATE_LIMIT_USER_RPS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.user_rps),
+            global_bandwidth: mbps("RATE_LIMIT_GLOBAL_BANDWIDTH_MB")
+                .unwrap_or(defaults.global_bandwidth),
+            user_bandwidth: mbps("RATE_LIMIT_USER_BANDWIDTH_MB").unwrap_or(defaults.user_bandwidth),
+            burst_secs: std::env::var("RATE_LIMIT_BURST_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.burst_secs),
+        }
+    }
+}
+
+/// Token bucket
+///
+/// A rate of 0 disables the bucket. Costs larger than the capacity are
+/// admitted once the bucket is full and leave it in debt, so a single large
+/// upload is never rejected forever.
+#[derive(Debug, Clone)]
+struct TokenBucket {
+    rate: f64,
+    capacity: f64,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn new(rate: f64, burst_secs: f64, now: Instant) -> Self {
+        let capacity = (rate * burst_secs).max(1.0);
+        Self {
+            rate,
+            capacity,
+            tokens: capacity,
+            last_refill: now,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now
+            .saturating_duration_since(self.last_refill)
+            .as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.capacity);
+        self.last_refill = now;
+    }
+
+    /// Take `cost` tokens, or return how long to wait before retrying
+    fn try_take(&mut self, cost: f64, now: Instant) -> Result<(), Duration> {
+        if self.rate <= 0.0 {
+            return Ok(());
+        }
+        self.refill(now);
+
+        let needed = cost.min(self.capacity);
+        if self.tokens >= needed {
+            self.tokens -= cost;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((needed - self.tokens) / self.rate))
+        }
+    }
+
+    /// Charge `cost` tokens unconditionally (may go into debt)
+    fn charge(&mut self, cost: f64, now: Instant) {
+        if self.rate <= 0.0 {
+            return;
+        }
+        self.refill(now);
+        self.tokens -= cost;
+    }
+
+    fn is_full(&self, now: Instant) -> bool {
+        let elapsed = now
+            .saturating_duration_since(self.last_refill)
+            .as_secs_f64();
+        self.rate <= 0.0 || self.tokens + elapsed * self.rate >= self.capacity
+    }
+}
+
+/// Request and bandwidth buckets for one scope (global or a user)
+#[derive(Debug, Clone)]
+struct ScopeBuckets {
+    requests: TokenBucket,
+    bytes: TokenBucket,
+}
+
+impl ScopeBuckets {
+    fn new(rps: f64, bandwidth: u64, burst_secs: f64, now: Instant) -> Self {
+        Self {
+            requests: TokenBucket::new(rps, burst_secs, now),
+            bytes: TokenBucket::new(bandwidth as f64, burst_secs, now),
+        }
+    }
+
+    fn try_admit(&mut self, request_bytes: u64, now: Instant) -> Result<(), Duration> {
+        // Check both before taking so a rejected request costs nothing
+        let mut requests = self.requests.clone();
+        let mut bytes = self.bytes.clone();
+        requests.try_take(1.0, now)?;
+        bytes.try_take(request_bytes as f64, now)?;
+        self.requests = requests;
+        self.bytes = bytes;
+        Ok(())
+    }
+}
+
+/// Gateway rate limiter
+pub struct RateLimiter {
+    config: watch::Sender<RateLimitConfig>,
+    global: Mutex<ScopeBuckets>,
+    users: Mutex<HashMap<String, ScopeBuckets>>,
+    redis: Option<redis::aio::MultiplexedConnection>,
+}
+
+impl RateLimiter {
+    /// Create an in-memory rate limiter
+    pub fn new(config: RateLimitConfig) -> Self {
+        let now = Instant::now();
+        let global = ScopeBuckets::new(
+            config.global_rps,
+            config.global_bandwidth,
+            config.burst_secs,
+            now,
+        );
+
+        Self {
+            config: watch::Sender::new(config),
+            global: Mutex::new(global),
+            users: Mutex::new(HashMap::new()),
+            redis: None,
+        }
+    }
+
+    /// Share counters with other gateways through Redis
+    pub fn with_redis(mut self, conn: redis::aio::MultiplexedConnection) -> Self {
+        self.redis = Some(conn);
+        info!("Rate limit counters backed by Redis");
+        self
+    }
+
+    /// Current limits
+    pub fn config(&self) -> RateLimitConfig {
+        self.config.borrow().clone()
+    }
+
+    /// Replace the limits at runtime
+    ///
+    /// Local buckets are rebuilt so new rates and burst sizes apply at once.
+    pub fn update_config(&self, config: RateLimitConfig) {
+        let now = Instant::now();
+        *self.global.lock().expect("rate limit lock poisoned") = ScopeBuckets::new(
+            config.global_rps,
+            config.global_bandwidth,
+            config.burst_secs,
+            now,
+        );
+        self.users.lock().expect("rate limit lock poisoned").clear();
+        self.config.send_replace(config);
+    }
+
+    /// Whether any limit is active
+    pub fn is_enabled(&self) -> bool {
+        let config = self.config.borrow();
+        config.enabled
+            && (config.global_rps > 0.0
+                || config.user_rps > 0.0
+                || config.global_bandwidth > 0
+                || config.user_bandwidth > 0)
+    }
+
+    /// Admit a request, or return how long the client should wait
+    pub async fn check(&self, user: &str, request_bytes: u64) -> Result<(), Duration> {
+        if let Some(ref conn) = self.redis {
+            match self.check_redis(conn.clone(), user, request_bytes).await {
+                Ok(result) => return result,
+                Err(e) => {
+                    // Fail-open to local buckets if Redis is down
+                    warn!(error = %e, "Redis rate limit check failed, using local limits");
+                }
+            }
+        }
+
+        self.check_local(user, request_bytes, Instant::now())
+    }
+
+    /// Charge response bytes against the bandwidth limits
+    pub async fn charge_bandwidth(&self, user: &str, bytes: u64) {
+        let config = self.config();
+        if bytes == 0 || (config.global_bandwidth == 0 && config.user_bandwidth == 0) {
+            return;
+        }
+
+        if let Some(ref conn) = self.redis {
+            let window = current_window();
+            let mut pipe = redis::pipe();
+            for key in [
+                redis_key("global", "bytes", window),
+                redis_key(user, "bytes", window),
+            ] {
+                pipe.incr(&key, bytes).ignore().expire(&key, 2).ignore();
+            }
+            match pipe.query_async::<_, ()>(&mut conn.clone()).await {
+                Ok(()) => return,
+                Err(e) => debug!(error = %e, "Failed to charge bandwidth in Redis"),
+            }
+        }
+
+        let now = Instant::now();
+        self.global
+            .lock()
+            .expect("rate limit lock poisoned")
+            .bytes
+            .charge(bytes as f64, now);
+        if let Some(buckets) = self
+            .users
+            .lock()
+            .expect("rate limit lock poisoned")
+            .get_mut(user)
+        {
+            buckets.bytes.charge(bytes as f64, now);
+        }
+    }
+
+    fn check_local(&self, user: &str, request_bytes: u64, now: Instant) -> Result<(), Duration> {
+        let config = self.config();
+        let mut users = self.users.lock().expect("rate limit lock poisoned");
+
+        if users.len() >= MAX_TRACKED_USERS && !users.contains_key(user) {
+            users.retain(|_, b| !(b.requests.is_full(now) && b.bytes.is_full(now)));
+        }
+
+        let user_buckets = users.entry(user.to_string()).or_insert_with(|| {
+            ScopeBuckets::new(
+                config.user_rps,
+                config.user_bandwidth,
+                config.burst_secs,
+                now,
+            )
+        });
+
+        // Check the user first so one noisy client doesn't drain the global bucket
+        let mut admitted_user = user_buckets.clone();
+        admitted_user.try_admit(request_bytes, now)?;
+
+        self.global
+            .lock()
+            .expect("rate limit lock poisoned")
+            .try_admit(request_bytes, now)?;
+
+        *user_buckets = admitted_user;
+        Ok(())
+    }
+
+    async fn check_redis(
+        &self,
+        mut conn: redis::aio::MultiplexedConnection,
+        user: &str,
+        request_bytes: u64,
+    ) -> redis::RedisResult<Result<(), Duration>> {
+        let window = current_window();
+        let config = self.config();
+        let scopes = [
+            ("global", config.global_rps, config.global_bandwidth),
+            (user, config.user_rps, config.user_bandwidth),
+        ];
+
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        for (scope, _, _) in &scopes {
+            let req_key = redis_key(scope, "req", window);
+            let bytes_key = redis_key(scope, "bytes", window);
+            pipe.incr(&req_key, 1u64)
+                .expire(&req_key, 2)
+                .ignore()
+                .incr(&bytes_key, request_bytes)
+                .expire(&bytes_key, 2)
+                .ignore();
+        }
+        let counts: Vec<u64> = pipe.query_async(&mut conn).await?;
+
+        for (i, (_, rps, bandwidth)) in scopes.iter().enumerate() {
+            let requests = counts[i * 2];
+            let bytes = counts[i * 2 + 1];
+            if window_exceeded(requests, 1, rps.ceil() as u64)
+                || window_exceeded(bytes, request_bytes, *bandwidth)
+            {
+                return Ok(Err(until_next_window()));
+            }
+        }
+
+        Ok(Ok(()))
+    }
+}
+
+/// Whether a one-second window counter exceeds its limit after adding `cost`
+///
+/// Mirrors the token bucket debt rule: the request that crosses the limit is
+/// admitted, later ones in the same window are not.
+fn window_exceeded(count: u64, cost: u64, limit: u64) -> bool {
+    limit > 0 && count.saturating_sub(cost) >= limit
+}
+
+fn current_window() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+fn until_next_window() -> Duration {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .subsec_nanos();
+    Duration::from_secs(1) - Duration::from_nanos(nanos as u64)
+}
+
+fn redis_key(scope: &str, kind: &str, window: u64) -> String {
+    format!("{}:{}:{}:{}", REDIS_KEY_PREFIX, scope, kind, window)
+}
+
+/// Identify the client for per-user limits
+///
+/// Uses the JWT subject when a valid bearer token is present, then the AWS
+/// access key from a SigV4 header, then the forwarded client IP.
+async fn identify_client(headers: &HeaderMap, auth: &AuthService) -> String {
+    let authorization = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("");
+
+    if let Some(token) = authorization.strip_prefix("Bearer ") {
+        if let Ok(claims) = auth.validate_token(token).await {
+            return format!("user:{}", claims.sub);
+        }
+    }
+
+    if let Some(access_key) = sigv4_access_key(authorization) {
+        return format!("key:{}", access_key);
+    }
+
+    format!("ip:{}", extract_client_ip())
+}
+
+/// Extract the access key ID from an `AWS4-HMAC-SHA256 Credential=...` header
+fn sigv4_access_key(authorization: &str) -> Option<&str> {
+    let credential = authorization
+        .strip_prefix("AWS4-HMAC-SHA256")?
+        .split(',')
+        .find_map(|part| part.trim().strip_prefix("Credential="))?;
+    credential.split('/').next().filter(|k| !k.is_empty())
+}
+
+fn content_length(headers: &HeaderMap) -> u64 {
+    headers
+        .get(header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(0)
+}
+
+/// Build an S3 `SlowDown` response with a `Retry-After` hint
+fn slow_down_response(retry_after: Duration) -> Response {
+    let mut response = S3Error::SlowDown.into_response();
+    let secs = retry_after.as_secs_f64().ceil().max(1.0) as u64;
+    if let Ok(value) = HeaderValue::from_str(&secs.to_string()) {
+        response.headers_mut().insert(header::RETRY_AFTER, value);
+    }
+    response
+}
+
+/// Tower layer applying the gateway rate limiter
+#[derive(Clone)]
+pub struct RateLimitLayer {
+    state: Arc<AppState>,
+}
+
+impl RateLimitLayer {
+    /// Create a rate limit layer using the limiter in `state`
+    pub fn new(state: Arc<AppState>) -> Self {
+        Self { state }
+    }
+}
+
+impl<S> Layer<S> for RateLimitLayer {
+    type Service = RateLimitService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RateLimitService {
+            inner,
+            state: self.state.clone(),
+        }
+    }
+}
+
+/// Service produced by [`RateLimitLayer`]
+#[derive(Clone)]
+pub struct RateLimitService<S> {
+    inner: S,
+    state: Arc<AppState>,
+}
+
+impl<S> Service<Request<Body>> for RateLimitService<S>
+where
+    S: Service<Request<Body>, Response = Response> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Response, S::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        // Take the service that was polled ready, leave a fresh clone behind
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let state = self.state.clone();
+
+        Box::pin(async move {
+            let limiter = state.rate_limiter();
+            if !limiter.is_enabled() {
+                return inner.call(req).await;
+            }
+
+            let client = identify_client(req.headers(), state.auth_service()).await;
+            if let Err(retry_after) = limiter.check(&client, content_length(req.headers())).await {
+                debug!(
+                    client = %client,
+                    retry_after_ms = retry_after.as_millis() as u64,
+                    "Request throttled"
+                );
+                crate::metrics::record_rate_limited(req.method().as_str());
+                return Ok(slow_down_response(retry_after));
+            }
+
+            let response = inner.call(req).await?;
+            limiter
+                .charge_bandwidth(&client, content_length(response.headers()))
+                .await;
+            Ok(response)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_limit_config_default() {
+        let config = RateLimitConfig::default();
+        assert!(config.enabled);
+        assert_eq!(config.user_rps, 100.0);
+        assert_eq!(config.global_bandwidth, 0);
+    }
+
+    #[test]
+    fn test_token_bucket_burst_and_refill() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(10.0, 1.0, start);
+
+        for _ in 0..10 {
+            assert!(bucket.try_take(1.0, start).is_ok());
+        }
+        let wait = bucket.try_take(1.0, start).unwrap_err();
+        assert!((wait.as_secs_f64() - 0.1).abs() < 1e-6);
+
+        assert!(bucket
+            .try_take(1.0, start + Duration::from_millis(100))
+            .is_ok());
+    }
+
+    #[test]
+    fn test_token_bucket_large_cost_goes_into_debt() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(100.0, 1.0, start);
+
+        assert!(bucket.try_take(1000.0, start).is_ok());
+        assert!(bucket
+            .try_take(1.0, start + Duration::from_secs(5))
+            .is_err());
+        assert!(bucket
+            .try_take(1.0, start + Duration::from_secs(10))
+            .is_ok());
+    }
+
+    #[test]
+    fn test_local_limits_per_user() {
+        let limiter = RateLimiter::new(RateLimitConfig {
+            global_rps: 0.0,
+            user_rps: 2.0,
+            burst_secs: 1.0,
+            ..Default::default()
+        });
+        let now = Instant::now();
+
+        assert!(limiter.check_local("alice", 0, now).is_ok());
+        assert!(limiter.check_local("alice", 0, now).is_ok());
+        assert!(limiter.check_local("alice", 0, now).is_err());
+        // Other users have their own bucket
+        assert!(limiter.check_local("bob", 0, now).is_ok());
+    }
+
+    #[test]
+    fn test_rejected_by_global_does_not_charge_user() {
+        let limiter = RateLimiter::new(RateLimitConfig {
+            global_rps: 1.0,
+            user_rps: 1.0,
+            burst_secs: 1.0,
+            ..Default::default()
+        });
+        let now = Instant::now();
+
+        assert!(limiter.check_local("alice", 0, now).is_ok());
+        assert!(limiter.check_local("bob", 0, now).is_err());
+        let later = now + Duration::from_secs(1);
+        assert!(limiter.check_local("bob", 0, later).is_ok());
+    }
+
+    #[test]
+    fn test_update_config_applies_new_limits() {
+        let limiter = RateLimiter::new(RateLimitConfig {
+            global_rps: 0.0,
+            user_rps: 1.0,
+            burst_secs: 1.0,
+            ..Default::default()
+        });
+        let now = Instant::now();
+
+        assert!(limiter.check_local("alice", 0, now).is_ok());
+        assert!(limiter.check_local("alice", 0, now).is_err());
+
+        limiter.update_config(RateLimitConfig {
+            global_rps: 0.0,
+            user_rps: 5.0,
+            burst_secs: 1.0,
+            ..Default::default()
+        });
+        assert_eq!(limiter.config().user_rps, 5.0);
+        let now = Instant::now();
+        for _ in 0..5 {
+            assert!(limiter.check_local("alice", 0, now).is_ok());
+        }
+        assert!(limiter.check_local("alice", 0, now).is_err());
+    }
+
+    #[test]
+    fn test_window_exceeded() {
+        assert!(!window_exceeded(5, 1, 0));
+        assert!(!window_exceeded(10, 1, 10));
+        assert!(window_exceeded(11, 1, 10));
+        // A single large transfer may cross the limit
+        assert!(!window_exceeded(5000, 5000, 1000));
+        assert!(window_exceeded(5001, 1, 1000));
+    }
+
+    #[test]
+    fn test_sigv4_access_key() {
+        let header = "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20250101/us-east-1/s3/aws4_request, SignedHeaders=host, Signature=abc";
+        assert_eq!(sigv4_access_key(header), Some("AKIDEXAMPLE"));
+        assert_eq!(sigv4_access_key("Bearer token"), None);
+    }
+}
@@ -5,15 +5,20 @@
 
 use crate::state::AppState;
 use cyxcloud_metadata::postgres::Database;
+use cyxcloud_metadata::{AntiAffinity, CreateScanRecord};
 use cyxcloud_rebalancer::{
-    Detector, DetectorConfig, Executor, ExecutorConfig, GrpcNetworkClient, Planner, PlannerConfig,
-    PostgresMetadataClient,
+    ChunkTransferService, Detector, DetectorConfig, Executor, ExecutorConfig, GrpcNetworkClient,
+    KeyspaceLease, Planner, PlannerConfig, PostgresMetadataClient, ScanResult, ShardConfig,
 };
+use futures::StreamExt;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::task::JoinHandle;
 use tracing::{debug, error, info, warn};
 
+/// Default number of scan summaries kept (a week at one scan a minute)
+pub const DEFAULT_SCAN_HISTORY: usize = 7 * 24 * 60;
+
 /// Rebalancer daemon configuration
 #[derive(Debug, Clone)]
 pub struct RebalancerDaemonConfig {
@@ -27,6 +32,30 @@ pub struct RebalancerDaemonConfig {
     pub rate_limit_gb: u64,
     /// Dry run mode (scan but don't repair)
     pub dry_run: bool,
+    /// Anti-affinity between repaired replicas and sibling shards
+    pub anti_affinity: AntiAffinity,
+    /// How long repairs are held after the latest node failure of a large outage
+    pub outage_stabilization: Duration,
+    /// Scan summaries kept in the database for trend reporting
+    pub scan_history: usize,
+    /// Shared secret presented to nodes asked to push chunks
+    pub cluster_token: Option<String>,
+    /// Encrypt repaired chunks to each target node's transfer key
+    pub encrypt_transfers: bool,
+    /// Spot-check a sample of each node's copies every scan
+    pub verify_integrity: bool,
+    /// Copies verified per node each scan when integrity checks are enabled
+    pub integrity_samples: usize,
+    /// Scan only chunks changed since the previous scan plus a rolling sweep
+    pub incremental_scan: bool,
+    /// How long the rolling sweep takes to cover the keyspace
+    pub full_sweep_interval: Duration,
+    /// Keyspace shards split between rebalancer instances (1 = unsharded)
+    pub shard_count: usize,
+    /// Keyspace shard this instance owns (`None` claims a free one)
+    pub shard_index: Option<usize>,
+    /// Name of this instance in keyspace leases
+    pub instance_id: String,
 }
 
 impl Default for RebalancerDaemonConfig {
@@ -37,6 +66,18 @@ impl Default for RebalancerDaemonConfig {
             repair_parallelism: 4,
             rate_limit_gb: 10,
             dry_run: false,
+            anti_affinity: AntiAffinity::default(),
+            outage_stabilization: Duration::from_secs(300),
+            scan_history: DEFAULT_SCAN_HISTORY,
+            cluster_token: None,
+            encrypt_transfers: false,
+            verify_integrity: false,
+            integrity_samples: 10,
+            incremental_scan: true,
+            full_sweep_interval: Duration::from_secs(60 * 60),
+            shard_count: 1,
+            shard_index: None,
+            instance_id: format!("gateway-{}", uuid::Uuid::new_v4()),
         }
     }
 }
@@ -66,6 +107,49 @@ impl RebalancerDaemonConfig {
             dry_run: std::env::var("REBALANCER_DRY_RUN")
                 .map(|v| v == "true" || v == "1")
                 .unwrap_or(false),
+            anti_affinity: std::env::var("REBALANCER_ANTI_AFFINITY")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or_default(),
+            outage_stabilization: Duration::from_secs(
+                std::env::var("REBALANCER_OUTAGE_STABILIZATION_SECS")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(300),
+            ),
+            scan_history: std::env::var("REBALANCER_SCAN_HISTORY")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(DEFAULT_SCAN_HISTORY),
+            cluster_token: std::env::var("CYXCLOUD_CLUSTER_TOKEN").ok(),
+            encrypt_transfers: std::env::var("CYXCLOUD_ENCRYPT_TRANSFERS")
+                .map(|v| v == "true" || v == "1")
+                .unwrap_or(false),
+            verify_integrity: std::env::var("REBALANCER_VERIFY_INTEGRITY")
+                .map(|v| v == "true" || v == "1")
+                .unwrap_or(false),
+            integrity_samples: std::env::var("REBALANCER_INTEGRITY_SAMPLES")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(10),
+            incremental_scan: std::env::var("REBALANCER_INCREMENTAL_SCAN")
+                .map(|v| v == "true" || v == "1")
+                .unwrap_or(true),
+            full_sweep_interval: Duration::from_secs(
+                std::env::var("REBALANCER_FULL_SWEEP_SECS")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(60 * 60),
+            ),
+            shard_count: std::env::var("REBALANCER_SHARD_COUNT")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(1),
+            shard_index: std::env::var("REBALANCER_SHARD_INDEX")
+                .ok()
+                .and_then(|v| v.parse().ok()),
+            instance_id: std::env::var("REBALANCER_INSTANCE_ID")
+                .unwrap_or_else(|_| format!("gateway-{}", uuid::Uuid::new_v4())),
         }
     }
 }
@@ -100,6 +184,7 @@ impl RebalancerDaemon {
                 replication_factor = config.replication_factor,
                 parallelism = config.repair_parallelism,
                 dry_run = config.dry_run,
+                anti_affinity = ?config.anti_affinity,
                 "Starting rebalancer daemon"
             );
 
@@ -111,8 +196,13 @@ impl RebalancerDaemon {
                 replication_factor: config.replication_factor,
                 batch_size: 1000,
                 scan_interval: config.scan_interval,
-                verify_integrity: false,
+                verify_integrity: config.verify_integrity,
+                integrity_samples_per_node: config.integrity_samples,
                 health_check_timeout: Duration::from_secs(5),
+                outage_stabilization_delay: config.outage_stabilization,
+                incremental: config.incremental_scan,
+                full_sweep_interval: config.full_sweep_interval,
+                ..Default::default()
             };
 
             let planner_config = PlannerConfig {
@@ -122,12 +212,16 @@ impl RebalancerDaemon {
                 prefer_local: true,
                 max_node_load: 0.8,
                 node_rate_limit: 100 * 1024 * 1024,
+                anti_affinity: config.anti_affinity,
+                anti_affinity_min_nodes: 14,
+                max_source_repairs: 3,
             };
 
             let executor_config = ExecutorConfig {
                 max_concurrent: config.repair_parallelism,
                 max_per_source: 3,
                 max_per_target: 3,
+                source_queue_threshold: 6,
                 transfer_timeout: Duration::from_secs(300),
                 max_retries: 3,
                 retry_delay: Duration::from_secs(5),
@@ -138,10 +232,36 @@ impl RebalancerDaemon {
             let mut detector = Detector::new(detector_config);
             let mut planner = Planner::new(planner_config);
             let (executor, _progress_rx) = Executor::with_progress(executor_config);
+            let transfer_service = ChunkTransferService::new(db.clone())
+                .with_cluster_token(config.cluster_token.clone())
+                .with_transfer_encryption(config.encrypt_transfers);
+
+            let mut keyspace = None;
+            if config.shard_count > 1 {
+                let shard_config = ShardConfig {
+                    shard_count: config.shard_count,
+                    shard_index: config.shard_index,
+                    instance_id: config.instance_id.clone(),
+                    lease_ttl: ShardConfig::lease_ttl_for(config.scan_interval),
+                };
+                match KeyspaceLease::new(db.clone(), shard_config) {
+                    Ok(lease) => keyspace = Some(lease),
+                    Err(e) => {
+                        error!(error = %e, "Rebalancer daemon disabled");
+                        return;
+                    }
+                }
+            }
 
             // Main loop
             loop {
-                if detector.should_scan() {
+                // Evacuation jobs of draining nodes run every iteration so
+                // drain progress keeps moving between scans
+                if !config.dry_run {
+                    run_evacuations(&transfer_service, &db, config.repair_parallelism).await;
+                }
+
+                if detector.should_scan() && renew_keyspace(&mut keyspace, &metadata_client).await {
                     if let Err(e) = run_scan_cycle(
                         &mut detector,
                         &mut planner,
@@ -149,7 +269,7 @@ impl RebalancerDaemon {
                         &metadata_client,
                         &network_client,
                         &db,
-                        config.dry_run,
+                        &config,
                     )
                     .await
                     {
@@ -164,6 +284,76 @@ impl RebalancerDaemon {
     }
 }
 
+/// Renew a sharded daemon's keyspace lease and restrict scans to its range
+///
+/// Returns false when this instance holds no range and should not scan.
+async fn renew_keyspace(
+    lease: &mut Option<KeyspaceLease>,
+    metadata_client: &PostgresMetadataClient,
+) -> bool {
+    let Some(lease) = lease.as_mut() else {
+        return true;
+    };
+    match lease.renew().await {
+        Ok(Some(range)) => {
+            metadata_client.set_keyspace(range);
+            true
+        }
+        Ok(None) => {
+            warn!("Every keyspace range is leased to another rebalancer, skipping scan");
+            false
+        }
+        Err(e) => {
+            error!(error = %e, "Failed to renew keyspace lease");
+            false
+        }
+    }
+}
+
+/// Maximum evacuation jobs picked up per iteration
+const EVACUATION_BATCH: i64 = 50;
+
+/// Execute queued jobs copying chunks off draining nodes and onto nodes
+/// replacing lost replicas
+async fn run_evacuations(transfer: &ChunkTransferService, db: &Database, parallelism: usize) {
+    let jobs = match db.get_pending_repair_jobs(EVACUATION_BATCH).await {
+        Ok(jobs) => jobs,
+        Err(e) => {
+            error!(error = %e, "Failed to fetch pending evacuation jobs");
+            return;
+        }
+    };
+
+    let jobs: Vec<_> = jobs
+        .into_iter()
+        .filter(|job| job.source_node_id.is_some())
+        .collect();
+    if jobs.is_empty() {
+        return;
+    }
+
+    debug!(jobs = jobs.len(), "Running evacuation jobs");
+
+    futures::stream::iter(jobs)
+        .for_each_concurrent(parallelism.max(1), |job| async move {
+            match transfer.execute_repair_job(&job).await {
+                Ok(Some(progress)) if progress.completed_at.is_some() => {
+                    info!(
+                        node_id = %progress.node_id,
+                        chunks = progress.evacuated_chunks,
+                        bytes = progress.evacuated_bytes,
+                        "Node drain complete, all chunks evacuated"
+                    );
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    warn!(job_id = %job.id, kind = %job.kind, error = %e, "Repair job failed");
+                }
+            }
+        })
+        .await;
+}
+
 /// Run a single scan and repair cycle
 async fn run_scan_cycle(
     detector: &mut Detector,
@@ -172,7 +362,7 @@ async fn run_scan_cycle(
     metadata_client: &Arc<PostgresMetadataClient>,
     network_client: &Arc<GrpcNetworkClient>,
     db: &Arc<Database>,
-    dry_run: bool,
+    config: &RebalancerDaemonConfig,
 ) -> anyhow::Result<()> {
     debug!("Starting rebalancer scan cycle");
 
@@ -183,6 +373,18 @@ async fn run_scan_cycle(
         .map_err(|e| anyhow::anyhow!("Scan failed: {}", e))?;
 
     debug!(summary = %scan_result.summary(), "Scan complete");
+    crate::metrics::set_data_at_risk(
+        scan_result.chunks_at_permanent_risk,
+        scan_result.bytes_at_permanent_risk,
+    );
+
+    // Keep a summary for trend reporting
+    if let Err(e) = db
+        .record_scan(&scan_record(&scan_result), config.scan_history as i64)
+        .await
+    {
+        warn!(error = %e, "Failed to record scan summary");
+    }
 
     if scan_result.has_critical_issues() {
         warn!("Critical replication issues detected!");
@@ -197,6 +399,7 @@ async fn run_scan_cycle(
 
     info!(
         under_replicated = scan_result.under_replicated.len(),
+        corrupt = scan_result.corrupt.len(),
         "Found chunks needing repair"
     );
 
@@ -213,16 +416,48 @@ async fn run_scan_cycle(
 
     info!(summary = %plan.summary(), "Repair plan created");
 
-    if dry_run {
+    if config.dry_run {
         info!("Dry run mode, skipping execution");
         return Ok(());
     }
 
     // Step 3: Execute repairs
-    let transfer_fn = cyxcloud_rebalancer::transfer::create_transfer_fn(db.clone());
-    let result = executor.execute(plan, transfer_fn).await;
+    let transfer_fn = cyxcloud_rebalancer::transfer::create_transfer_fn(
+        db.clone(),
+        config.cluster_token.clone(),
+        config.encrypt_transfers,
+    );
+    let rebuild_fn = cyxcloud_rebalancer::transfer::create_rebuild_fn(
+        db.clone(),
+        config.cluster_token.clone(),
+        config.encrypt_transfers,
+    );
+    let result = executor
+        .execute_with_rebuild(plan, transfer_fn, rebuild_fn)
+        .await;
 
     info!(summary = %result.summary(), "Repair execution complete");
 
+    let retired = cyxcloud_rebalancer::transfer::retire_replaced_copies(&db, &result).await;
+    if retired > 0 {
+        info!(retired = retired, "Corrupt chunk copies retired");
+    }
+
     Ok(())
 }
+
+/// Scan summary row for a scan result
+fn scan_record(result: &ScanResult) -> CreateScanRecord {
+    CreateScanRecord {
+        duration_ms: result.duration.as_millis() as i64,
+        chunks_scanned: result.total_scanned as i64,
+        under_replicated: (result.under_replicated.len() + result.deferred.len()) as i64,
+        critical: result.critical_count() as i64,
+        over_replicated: result.over_replicated.len() as i64,
+        orphaned: result.orphaned.len() as i64,
+        corrupt: result.corrupt.len() as i64,
+        deferred: result.deferred.len() as i64,
+        incidents: result.incidents.len() as i64,
+        bytes_at_risk: result.bytes_at_risk as i64,
+    }
+}
@@ -0,0 +1,248 @@
+//! Configuration hot reload
+//!
+//! Re-reads the gateway configuration on SIGHUP or
+//! `POST /api/v1/admin/config/reload`, validates it, and swaps the components
+//! that can change at runtime:
+//! - S3 rate limits (rate limiter buckets are rebuilt)
+//! - Shard placement constraints (used by new uploads)
+//! - Log filter
+//!
+//! Changes to sections that are bound at startup (listen addresses, TLS,
+//! database, cache, CORS, daemon intervals, concurrent writes, readiness
+//! thresholds) are reported as requiring a restart and otherwise ignored.
+//! An invalid file leaves the running configuration untouched.
+
+use crate::config::{ConfigError, GatewaySettings};
+use crate::state::AppState;
+use serde::Serialize;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tokio::task::JoinHandle;
+use tracing::{error, info, warn};
+use tracing_subscriber::{reload, EnvFilter, Registry};
+
+/// Handle for swapping the global log filter
+pub type LogFilterHandle = reload::Handle<EnvFilter, Registry>;
+
+/// Overrides applied on top of the file and environment (CLI flags)
+pub type SettingsOverrides = Box<dyn Fn(GatewaySettings) -> GatewaySettings + Send + Sync>;
+
+/// Outcome of a reload
+#[derive(Debug, Default, Serialize)]
+pub struct ReloadReport {
+    /// Sections that changed and were applied
+    pub applied: Vec<&'static str>,
+    /// Sections that changed but only take effect after a restart
+    pub restart_required: Vec<&'static str>,
+}
+
+impl ReloadReport {
+    /// Compare two configurations section by section
+    pub fn diff(old: &GatewaySettings, new: &GatewaySettings) -> Self {
+        let mut report = Self::default();
+
+        let mut check = |changed: bool, section: &'static str, reloadable: bool| {
+            if changed {
+                if reloadable {
+                    report.applied.push(section);
+                } else {
+                    report.restart_required.push(section);
+                }
+            }
+        };
+
+        check(old.rate_limit != new.rate_limit, "rate_limit", true);
+        check(old.placement != new.placement, "placement", true);
+        check(old.logging != new.logging, "logging", true);
+        check(old.server != new.server, "server", false);
+        check(old.tls != new.tls, "tls", false);
+        check(old.database != new.database, "database", false);
+        check(old.cache != new.cache, "cache", false);
+        check(old.daemons != new.daemons, "daemons", false);
+        check(old.cors != new.cors, "cors", false);
+        check(old.writes != new.writes, "writes", false);
+        check(old.health != new.health, "health", false);
+
+        report
+    }
+
+    /// Whether nothing changed
+    pub fn is_empty(&self) -> bool {
+        self.applied.is_empty() && self.restart_required.is_empty()
+    }
+}
+
+/// Reloads the gateway configuration and applies runtime-changeable parts
+pub struct ConfigReloader {
+    path: Option<PathBuf>,
+    overrides: SettingsOverrides,
+    current: Mutex<GatewaySettings>,
+    log_filter: Option<LogFilterHandle>,
+}
+
+impl ConfigReloader {
+    /// Create a reloader for the configuration the gateway started with
+    pub fn new(
+        path: Option<PathBuf>,
+        settings: GatewaySettings,
+        overrides: SettingsOverrides,
+        log_filter: Option<LogFilterHandle>,
+    ) -> Self {
+        Self {
+            path,
+            overrides,
+            current: Mutex::new(settings),
+            log_filter,
+        }
+    }
+
+    /// Configuration currently in effect
+    pub fn current(&self) -> GatewaySettings {
+        self.current.lock().expect("config lock poisoned").clone()
+    }
+
+    /// Re-read, validate and apply the configuration
+    pub fn reload(&self, state: &AppState) -> Result<ReloadReport, ConfigError> {
+        let settings =
+            (self.overrides)(GatewaySettings::load(self.path.as_deref())?.with_env_overrides());
+        settings.validate()?;
+
+        let mut current = self.current.lock().expect("config lock poisoned");
+        let report = ReloadReport::diff(&current, &settings);
+
+        if current.logging != settings.logging {
+            if let Some(ref handle) = self.log_filter {
+                let filter = EnvFilter::try_new(&settings.logging.level)
+                    .map_err(|e| ConfigError::ValidationError(format!("logging.level: {}", e)))?;
+                handle
+                    .reload(filter)
+                    .map_err(|e| ConfigError::ValidationError(format!("log filter: {}", e)))?;
+            }
+        }
+        if current.rate_limit != settings.rate_limit {
+            state
+                .rate_limiter()
+                .update_config(settings.rate_limit_config());
+        }
+        if current.placement != settings.placement {
+            state.update_placement_config(settings.placement_config());
+        }
+
+        // Keep the startup value for sections that need a restart, so they
+        // are reported again until the gateway is restarted
+        current.rate_limit = settings.rate_limit;
+        current.placement = settings.placement;
+        current.logging = settings.logging;
+
+        if !report.restart_required.is_empty() {
+            warn!(
+                sections = ?report.restart_required,
+                "Changed config sections require a restart to take effect"
+            );
+        }
+        info!(applied = ?report.applied, "Configuration reloaded");
+
+        Ok(report)
+    }
+
+    /// Reload the configuration whenever the process receives SIGHUP
+    #[cfg(unix)]
+    pub fn start_signal_listener(self: Arc<Self>, state: Arc<AppState>) -> JoinHandle<()> {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        tokio::spawn(async move {
+            let mut hangup = match signal(SignalKind::hangup()) {
+                Ok(hangup) => hangup,
+                Err(e) => {
+                    error!(error = %e, "Failed to install SIGHUP handler, config reload via signal disabled");
+                    return;
+                }
+            };
+
+            info!("Config reload on SIGHUP enabled");
+            while hangup.recv().await.is_some() {
+                info!("Received SIGHUP, reloading configuration");
+                if let Err(e) = self.reload(&state) {
+                    error!(error = %e, "Config reload failed, keeping current configuration");
+                }
+            }
+        })
+    }
+
+    /// Signal-triggered reload is not available on this platform
+    #[cfg(not(unix))]
+    pub fn start_signal_listener(self: Arc<Self>, _state: Arc<AppState>) -> JoinHandle<()> {
+        tokio::spawn(async {})
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reload_report_diff() {
+        let old = GatewaySettings::default();
+        let mut new = old.clone();
+        assert!(ReloadReport::diff(&old, &new).is_empty());
+
+        new.rate_limit.user_rps = 5.0;
+        new.placement.anti_affinity = "strict".to_string();
+        new.server.http_addr = "0.0.0.0:9000".to_string();
+
+        let report = ReloadReport::diff(&old, &new);
+        assert_eq!(report.applied, vec!["rate_limit", "placement"]);
+        assert_eq!(report.restart_required, vec!["server"]);
+    }
+
+    #[test]
+    fn test_reload_applies_runtime_sections() {
+        let path = std::env::temp_dir().join(format!("gateway-{}.toml", uuid::Uuid::new_v4()));
+        std::fs::write(
+            &path,
+            r#"
+                [server]
+                http_addr = "0.0.0.0:9999"
+
+                [rate_limit]
+                user_rps = 7.0
+
+                [placement]
+                max_shards_per_dc = 3
+            "#,
+        )
+        .unwrap();
+
+        let state = AppState::new();
+        let reloader = ConfigReloader::new(
+            Some(path.clone()),
+            GatewaySettings::default(),
+            Box::new(|settings| settings),
+            None,
+        );
+
+        let report = reloader.reload(&state).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(report.applied, vec!["rate_limit", "placement"]);
+        assert_eq!(report.restart_required, vec!["server"]);
+        assert_eq!(state.rate_limiter().config().user_rps, 7.0);
+        assert_eq!(state.placement_config().max_shards_per_dc, 3);
+        // Restart-only sections keep their startup value
+        assert_eq!(reloader.current().server.http_addr, "0.0.0.0:8180");
+    }
+
+    #[test]
+    fn test_reload_invalid_config_keeps_current() {
+        let state = AppState::new();
+        let reloader = ConfigReloader::new(
+            Some(PathBuf::from("/nonexistent/gateway.toml")),
+            GatewaySettings::default(),
+            Box::new(|settings| settings),
+            None,
+        );
+
+        assert!(reloader.reload(&state).is_err());
+        assert_eq!(reloader.current(), GatewaySettings::default());
+    }
+}
@@ -0,0 +1,404 @@
+//! Bucket Replication
+//!
+//! Background task that copies objects of replicated buckets to a second
+//! CyxCloud cluster or an external S3 service. Each rule keeps a cursor over
+//! `(updated_at, id)` of the `files` table; every cycle tails the changes
+//! after the cursor, uploads new objects, propagates deletes if the rule asks
+//! for it and records the outcome per object. Failed objects are retried on
+//! later cycles until `max_attempts` is reached.
+//!
+//! Objects are streamed window by window, so replicating large files never
+//! holds more than one window in memory.
+
+mod target;
+
+pub use target::{ReplicationTarget, TARGET_CYXCLOUD, TARGET_S3};
+
+use crate::metrics;
+use crate::state::AppState;
+use bytes::Bytes;
+use cyxcloud_metadata::{Database, File, MetadataService, ReplicationRule};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio::time::interval;
+use tokio_stream::wrappers::ReceiverStream;
+use tracing::{debug, error, info, warn};
+
+/// Bytes read from the source cluster per window
+const STREAM_WINDOW: u64 = cyxcloud_core::DEFAULT_CHUNK_SIZE as u64;
+
+/// Replication daemon configuration
+#[derive(Debug, Clone)]
+pub struct ReplicationConfig {
+    /// How often to tail the rules for changes
+    pub scan_interval: Duration,
+    /// Maximum changes fetched per query
+    pub batch_size: i64,
+    /// Attempts before a failed object is left for the operator
+    pub max_attempts: i32,
+    /// Age a change must reach before it is replicated
+    pub settle_delay: Duration,
+    /// Timeout for connecting to replication targets
+    pub connect_timeout: Duration,
+}
+
+impl Default for ReplicationConfig {
+    fn default() -> Self {
+        Self {
+            scan_interval: Duration::from_secs(60),
+            batch_size: 100,
+            max_attempts: 10,
+            settle_delay: Duration::from_secs(5),
+            connect_timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+impl ReplicationConfig {
+    /// Create configuration from environment variables
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            scan_interval: Duration::from_secs(
+                std::env::var("REPLICATION_INTERVAL_SECS")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(defaults.scan_interval.as_secs()),
+            ),
+            batch_size: std::env::var("REPLICATION_BATCH_SIZE")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.batch_size),
+            max_attempts: std::env::var("REPLICATION_MAX_ATTEMPTS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.max_attempts),
+            settle_delay: Duration::from_secs(
+                std::env::var("REPLICATION_SETTLE_SECS")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(defaults.settle_delay.as_secs()),
+            ),
+            connect_timeout: defaults.connect_timeout,
+        }
+    }
+}
+
+/// Operation applied to the target for a change
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Operation {
+    Put,
+    Delete,
+}
+
+impl Operation {
+    fn as_str(self) -> &'static str {
+        match self {
+            Operation::Put => "put",
+            Operation::Delete => "delete",
+        }
+    }
+}
+
+/// Bucket replication daemon
+pub struct ReplicationDaemon {
+    config: ReplicationConfig,
+    client: reqwest::Client,
+}
+
+impl ReplicationDaemon {
+    /// Create a new replication daemon
+    pub fn new(config: ReplicationConfig) -> Self {
+        let client = reqwest::Client::builder()
+            .connect_timeout(config.connect_timeout)
+            .build()
+            .unwrap_or_default();
+        Self { config, client }
+    }
+
+    /// Start the daemon as a background task
+    pub fn start(self: Arc<Self>, state: Arc<AppState>) -> JoinHandle<()> {
+        let daemon = self;
+
+        tokio::spawn(async move {
+            let mut timer = interval(daemon.config.scan_interval);
+
+            info!(
+                interval_secs = daemon.config.scan_interval.as_secs(),
+                batch_size = daemon.config.batch_size,
+                "Replication daemon started"
+            );
+
+            loop {
+                timer.tick().await;
+
+                if let Some(metadata) = state.metadata_service() {
+                    if let Err(e) = daemon.run_cycle(&state, metadata).await {
+                        error!(error = %e, "Replication cycle failed");
+                    }
+                } else {
+                    debug!("Metadata service not available, skipping replication cycle");
+                }
+            }
+        })
+    }
+
+    /// Process every enabled rule once
+    async fn run_cycle(
+        &self,
+        state: &Arc<AppState>,
+        metadata: &MetadataService,
+    ) -> anyhow::Result<()> {
+        let db = metadata.database();
+        let rules = db.list_replication_rules(true).await?;
+
+        for rule in rules {
+            let target = match ReplicationTarget::from_rule(self.client.clone(), &rule) {
+                Ok(target) => target,
+                Err(e) => {
+                    warn!(rule = %rule.id, error = %e, "Skipping misconfigured replication rule");
+                    continue;
+                }
+            };
+
+            if let Err(e) = self.run_rule(state, db, &rule, &target).await {
+                error!(
+                    rule = %rule.id,
+                    bucket = %rule.bucket,
+                    error = %e,
+                    "Replication rule failed"
+                );
+            }
+
+            match db.get_replication_stats(&rule).await {
+                Ok(stats) => metrics::set_replication_backlog(
+                    &rule.id.to_string(),
+                    &rule.bucket,
+                    (stats.pending + stats.failed) as u64,
+                ),
+                Err(e) => warn!(rule = %rule.id, error = %e, "Failed to load replication stats"),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Tail the changes of one rule, then retry its failed objects
+    async fn run_rule(
+        &self,
+        state: &Arc<AppState>,
+        db: &Database,
+        rule: &ReplicationRule,
+        target: &ReplicationTarget,
+    ) -> anyhow::Result<()> {
+        let mut rule = rule.clone();
+        let mut replicated = 0usize;
+
+        loop {
+            let changes = db
+                .get_replication_changes(&rule, self.config.settle_delay, self.config.batch_size)
+                .await?;
+            let Some(last) = changes.last() else {
+                break;
+            };
+            let (cursor_at, cursor_id) = (last.updated_at, last.id);
+
+            for change in &changes {
+                if self
+                    .replicate_change(state, db, &rule, target, change)
+                    .await?
+                {
+                    replicated += 1;
+                }
+            }
+
+            db.advance_replication_cursor(rule.id, cursor_at, cursor_id)
+                .await?;
+            rule.cursor_updated_at = cursor_at;
+            rule.cursor_file_id = cursor_id;
+
+            if (changes.len() as i64) < self.config.batch_size {
+                break;
+            }
+        }
+
+        let failed = db
+            .get_failed_replication_objects(
+                rule.id,
+                self.config.max_attempts,
+                self.config.batch_size,
+            )
+            .await?;
+        for object in &failed {
+            let current = db.get_file_by_path(&rule.tenant_id, &object.path).await?;
+            let operation = match current {
+                Some(_) => Operation::Put,
+                None => Operation::Delete,
+            };
+            if operation == Operation::Delete && !rule.replicate_deletes {
+                continue;
+            }
+            let file_id = current.as_ref().map_or(object.file_id, |f| f.id);
+            self.apply(
+                state,
+                db,
+                &rule,
+                target,
+                &object.path,
+                file_id,
+                operation,
+                current.as_ref(),
+            )
+            .await?;
+        }
+
+        if replicated > 0 || !failed.is_empty() {
+            info!(
+                rule = %rule.id,
+                bucket = %rule.bucket,
+                replicated,
+                retried = failed.len(),
+                "Replication cycle complete"
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Replicate a single change; returns whether anything was sent
+    async fn replicate_change(
+        &self,
+        state: &Arc<AppState>,
+        db: &Database,
+        rule: &ReplicationRule,
+        target: &ReplicationTarget,
+        change: &File,
+    ) -> anyhow::Result<bool> {
+        // The path may have changed again since this row was written; only
+        // the current state of the path is replicated
+        let current = db.get_file_by_path(&rule.tenant_id, &change.path).await?;
+
+        let operation = if change.status == "deleted" {
+            if current.is_some() || !rule.replicate_deletes {
+                return Ok(false);
+            }
+            Operation::Delete
+        } else {
+            match &current {
+                Some(file) if file.id == change.id => Operation::Put,
+                _ => return Ok(false),
+            }
+        };
+
+        self.apply(
+            state,
+            db,
+            rule,
+            target,
+            &change.path,
+            change.id,
+            operation,
+            current.as_ref(),
+        )
+        .await?;
+        Ok(true)
+    }
+
+    /// Send one operation to the target and record the outcome
+    #[allow(clippy::too_many_arguments)]
+    async fn apply(
+        &self,
+        state: &Arc<AppState>,
+        db: &Database,
+        rule: &ReplicationRule,
+        target: &ReplicationTarget,
+        path: &str,
+        file_id: uuid::Uuid,
+        operation: Operation,
+        file: Option<&File>,
+    ) -> anyhow::Result<()> {
+        let Some(key) = path
+            .strip_prefix(&rule.bucket)
+            .and_then(|p| p.strip_prefix('/'))
+        else {
+            return Ok(());
+        };
+
+        let result = match (operation, file) {
+            (Operation::Put, Some(file)) => {
+                let size = file.size_bytes.max(0) as u64;
+                let body = stream_object(
+                    state,
+                    rule.tenant_id.clone(),
+                    rule.bucket.clone(),
+                    key.to_string(),
+                    size,
+                );
+                let content_type = file
+                    .content_type
+                    .as_deref()
+                    .unwrap_or("application/octet-stream");
+                let result = target
+                    .put(key, body, size, content_type, file.expires_at)
+                    .await;
+                if result.is_ok() {
+                    metrics::record_replication_bytes(size);
+                }
+                result
+            }
+            (Operation::Delete, _) => target.delete(key).await,
+            (Operation::Put, None) => return Ok(()),
+        };
+
+        let error = result.as_ref().err().map(|e| format!("{:#}", e));
+        match &error {
+            None => debug!(rule = %rule.id, key, op = operation.as_str(), "Object replicated"),
+            Some(e) => {
+                warn!(
+                    rule = %rule.id,
+                    key,
+                    op = operation.as_str(),
+                    error = %e,
+                    "Replication failed"
+                )
+            }
+        }
+        metrics::record_replication(operation.as_str(), error.is_none());
+
+        db.record_replication_result(rule.id, path, file_id, operation.as_str(), error.as_deref())
+            .await?;
+        Ok(())
+    }
+}
+
+/// Stream an object from this cluster as a request body
+fn stream_object(
+    state: &Arc<AppState>,
+    tenant: String,
+    bucket: String,
+    key: String,
+    size: u64,
+) -> reqwest::Body {
+    let (tx, rx) = mpsc::channel::<Result<Bytes, std::io::Error>>(2);
+    let state = state.clone();
+
+    tokio::spawn(async move {
+        let mut offset = 0;
+        while offset < size {
+            let end = (offset + STREAM_WINDOW).min(size) - 1;
+            let window = state
+                .get_object_range(&tenant, &bucket, &key, offset, end)
+                .await
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.to_string()));
+            let failed = window.is_err();
+            if tx.send(window).await.is_err() || failed {
+                return;
+            }
+            offset = end + 1;
+        }
+    });
+
+    reqwest::Body::wrap_stream(ReceiverStream::new(rx))
+}
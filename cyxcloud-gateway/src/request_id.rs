@@ -0,0 +1,89 @@
+//! Request ID propagation
+//!
+//! Every HTTP request gets an ID, taken from an incoming `x-request-id`
+//! header or generated. The ID is echoed in the `x-request-id` and
+//! `x-amz-request-id` response headers, attached to the tracing span, and
+//! available to error responses via [`current`] so clients can quote it when
+//! reporting failures.
+
+use axum::{
+    extract::Request,
+    http::{HeaderName, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use cyxcloud_core::error::REQUEST_ID_HEADER;
+use tracing::Instrument;
+use uuid::Uuid;
+
+/// S3 request ID response header
+const AMZ_REQUEST_ID_HEADER: &str = "x-amz-request-id";
+
+/// Longest client-supplied request ID that is accepted
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/// Request ID of the request being handled, if any
+pub fn current() -> Option<String> {
+    REQUEST_ID.try_with(|id| id.clone()).ok()
+}
+
+/// Accept a client-supplied ID only if it is short and printable
+fn sanitize(id: &str) -> Option<&str> {
+    let valid = !id.is_empty()
+        && id.len() <= MAX_REQUEST_ID_LEN
+        && id
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.'));
+    valid.then_some(id)
+}
+
+/// Middleware assigning a request ID to every request
+pub async fn propagate(request: Request, next: Next) -> Response {
+    let id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(sanitize)
+        .map(str::to_string)
+        .unwrap_or_else(|| Uuid::new_v4().simple().to_string());
+
+    let span = tracing::info_span!("request", request_id = %id);
+    let mut response = REQUEST_ID
+        .scope(id.clone(), next.run(request).instrument(span))
+        .await;
+
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        let headers = response.headers_mut();
+        headers.insert(HeaderName::from_static(REQUEST_ID_HEADER), value.clone());
+        headers.insert(HeaderName::from_static(AMZ_REQUEST_ID_HEADER), value);
+    }
+
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sanitize_request_id() {
+        assert_eq!(sanitize("abc-123_x.y"), Some("abc-123_x.y"));
+        assert_eq!(sanitize(""), None);
+        assert_eq!(sanitize("bad id"), None);
+        assert_eq!(sanitize("<script>"), None);
+        assert_eq!(sanitize(&"a".repeat(200)), None);
+    }
+
+    #[tokio::test]
+    async fn test_current_inside_scope() {
+        assert_eq!(current(), None);
+        let id = REQUEST_ID
+            .scope("req-1".to_string(), async { current() })
+            .await;
+        assert_eq!(id.as_deref(), Some("req-1"));
+    }
+}
@@ -1,27 +1,182 @@
 //! S3-Compatible REST API
 //!
 //! Implements a subset of the AWS S3 API for object storage operations.
-//! Supports: PUT, GET, DELETE, HEAD, and LIST operations.
+//! Supports: PUT, GET, DELETE (single and multi-object), HEAD, LIST and
+//! (restricted) SELECT operations.
+//!
+//! GET and HEAD honour `If-Match`, `If-None-Match`, `If-Modified-Since` and
+//! `If-Unmodified-Since` (304 / 412); PUT honours `If-Match` and
+//! `If-None-Match: *` so overwrites can be made conditional on the ETag.
+//!
+//! A PUT carrying `x-cyxcloud-idempotency-key` can be retried safely: a retry
+//! with the same key and content returns the first upload's ETag for 24 hours.
+//!
+//! `x-amz-meta-*` headers of a PUT are stored with the object (2 KB at most)
+//! and returned on GET and HEAD. A PUT with `x-amz-copy-source` copies an
+//! object; `x-amz-metadata-directive: REPLACE` takes the copy's content type
+//! and user metadata from the request instead of the source.
+//!
+//! `GET/PUT /:bucket?logging` read and change a bucket's access logging
+//! target (see [`crate::access_log`]).
+//!
+//! `GET /:bucket?export=tar` streams the bucket (or the keys under
+//! `prefix`) as a tar archive with a manifest (see [`crate::export`]).
+//!
+//! `GET/PUT /:bucket?object-lock` and `GET/PUT /:bucket/*key?retention`
+//! manage WORM retention (see [`crate::object_lock`]); locked objects
+//! reject overwrites and deletes until their retention has passed.
+//!
+//! `x-amz-server-side-encryption: AES256 | aws:kms` on a PUT or copy stores
+//! the object encrypted with a data key of its own (see [`crate::kms`]);
+//! GET decrypts it transparently and the header is returned on GET and HEAD.
+//!
+//! Bearer tokens must grant `s3:read` for reads (GET, HEAD, listings,
+//! SELECT) and `s3:write` for everything else; others get `403 AccessDenied`.
+//! A bucket policy (`PUT/GET/DELETE /:bucket?policy`, see
+//! [`crate::bucket_policy`]) is evaluated first and can deny a request or
+//! allow it without the scope.
+//!
+//! `x-amz-acl: public-read` on a bucket or object creation, or
+//! `PUT /:bucket?acl` and `PUT /:bucket/*key?acl`, let anyone read the
+//! objects; they are served without a token under
+//! `/public/:tenant/:bucket/*key` (see [`crate::acl`]).
+//!
+//! `Cache-Control`, `Content-Disposition` and `Content-Encoding` of a PUT
+//! are stored with the object and returned on GET and HEAD, so a CDN can
+//! cache it. `GET/PUT /:bucket?cache-policy` read and change the
+//! `Cache-Control` given to new objects uploaded without one.
+//!
+//! `PUT/GET/DELETE /:bucket?cors` manage the bucket's CORS rules, which
+//! answer browser preflight requests to it (see [`crate::bucket_cors`]).
+//!
+//! Deleted objects go to the bucket's trash for the retention window
+//! (`TRASH_RETENTION_SECS`, 7 days by default): `GET /:bucket?deleted` lists
+//! them and `POST /:bucket/*key?restore` makes one the current version again.
+//!
+//! While the metadata primary is unreachable, or until the startup preflight
+//! has passed (see [`crate::preflight`]), the API is read-only: writes fail
+//! fast with `503 ServiceUnavailable` (see [`reject_writes_when_read_only`]).
 
 #![allow(unused_imports)]
 
 use axum::{
     body::Body,
-    extract::{Path, Query, State},
-    http::{header, HeaderMap, StatusCode},
+    extract::{Path, Query, Request, State},
+    http::{header, HeaderMap, Method, StatusCode},
+    middleware::Next,
     response::{IntoResponse, Response},
-    routing::{delete, get, head, put},
+    routing::{delete, get, head, post, put},
     Router,
 };
 use bytes::Bytes;
+use cyxcloud_core::error::{ErrorCode, HasErrorCode, ERROR_CODE_HEADER};
+use cyxcloud_core::CyxCloudError;
+use cyxcloud_metadata::{DbError, MetadataError, StorageClass, DEFAULT_TENANT};
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
 use thiserror::Error;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
 use tokio_stream::StreamExt;
-use tracing::{debug, info, instrument};
+use tracing::{debug, error, info, instrument};
+use uuid::Uuid;
 
+use crate::access_log::BucketLogging;
+use crate::acl::{self, CannedAcl, ACL_HEADER, ALL_USERS_URI};
+use crate::auth::{scopes, Claims};
+use crate::bucket_cors::{CorsRule, MAX_CORS_RULES};
+use crate::bucket_policy::{actions, BucketPolicy, Decision, PolicyError, PolicyRequest};
+use crate::export::{self, ExportFormat, ExportManifest, ManifestEntry};
+use crate::kms::{KmsError, SseAlgorithm};
+use crate::node_client::NodeClientError;
+use crate::object_lock::{DefaultRetention, ObjectLockConfig, ObjectRetention, RetentionMode};
+use crate::s3_xml::{self, AwsErrorCode, ErrorResponse};
+use crate::select::{xml_unescape, SelectError, SelectProcessor, SelectRequest};
+use crate::tiering::{
+    LifecycleRule, RestoreRequestOutcome, RestoreStatus, MAX_LIFECYCLE_RULES, MAX_RESTORE_DAYS,
+};
 use crate::AppState;
 
+/// Absolute object expiry (RFC 3339 or HTTP date); also returned on GET/HEAD
+const EXPIRATION_HEADER: &str = "x-amz-expiration";
+
+/// Relative object expiry in seconds from upload
+const TTL_HEADER: &str = "x-cyx-ttl";
+
+/// Client-supplied key that makes retries of a PUT return the first result
+const IDEMPOTENCY_KEY_HEADER: &str = "x-cyxcloud-idempotency-key";
+
+/// Set on a PUT response that replayed an earlier upload
+const IDEMPOTENT_REPLAY_HEADER: &str = "x-cyxcloud-idempotent-replay";
+
+/// Longest accepted idempotency key
+const MAX_IDEMPOTENCY_KEY_LEN: usize = 255;
+
+/// Prefix of user metadata headers
+const USER_METADATA_PREFIX: &str = "x-amz-meta-";
+
+/// Longest object key in bytes (S3 limit)
+const MAX_KEY_LEN: usize = 1024;
+
+/// Most bytes of user metadata (names and values) per object (S3 limit)
+const MAX_USER_METADATA_SIZE: usize = 2048;
+
+/// Source of a CopyObject (`/bucket/key` or `bucket/key`, URL-encoded)
+const COPY_SOURCE_HEADER: &str = "x-amz-copy-source";
+
+/// Whether a copy keeps (`COPY`) or replaces (`REPLACE`) the source metadata
+const METADATA_DIRECTIVE_HEADER: &str = "x-amz-metadata-directive";
+
+/// Bytes of an object decoded per step of a SELECT scan (one default chunk)
+const SELECT_WINDOW: u64 = cyxcloud_core::DEFAULT_CHUNK_SIZE as u64;
+
+/// Bytes of an object decoded per step of a bucket export
+const EXPORT_WINDOW: u64 = cyxcloud_core::DEFAULT_CHUNK_SIZE as u64;
+
+/// Keys listed per page while exporting a bucket
+const EXPORT_PAGE_KEYS: i32 = 1000;
+
+/// Most keys accepted by one multi-object delete (S3 limit)
+const MAX_DELETE_KEYS: usize = 1000;
+
+/// Retention mode of a locked object, returned on GET/HEAD
+const OBJECT_LOCK_MODE_HEADER: &str = "x-amz-object-lock-mode";
+
+/// End of a locked object's retention (RFC 3339), returned on GET/HEAD
+const OBJECT_LOCK_RETAIN_UNTIL_HEADER: &str = "x-amz-object-lock-retain-until-date";
+
+/// Largest accepted `?retention` request body
+const MAX_RETENTION_BODY: usize = 64 * 1024;
+
+/// Largest accepted `?acl` request body
+const MAX_ACL_BODY: usize = 64 * 1024;
+
+/// Content-Encoding an SDK gives a streamed upload, which is not the
+/// encoding of the stored object
+const AWS_CHUNKED_ENCODING: &str = "aws-chunked";
+
+/// Creates a bucket with object lock enabled (and no default retention)
+const OBJECT_LOCK_ENABLED_HEADER: &str = "x-amz-bucket-object-lock-enabled";
+
+/// Lets a DELETE remove objects under governance retention
+const BYPASS_GOVERNANCE_HEADER: &str = "x-amz-bypass-governance-retention";
+
+/// Server-side encryption of an object, on PUT and in responses
+const SSE_HEADER: &str = "x-amz-server-side-encryption";
+
+/// Storage class of an object, on PUT and in responses
+const STORAGE_CLASS_HEADER: &str = "x-amz-storage-class";
+
+/// Restore state of an archived object, in responses
+const RESTORE_HEADER: &str = "x-amz-restore";
+
+/// Header prefix of SSE-C, encryption with keys supplied by the client
+const SSE_CUSTOMER_PREFIX: &str = "x-amz-server-side-encryption-customer-";
+
+/// Master key of SSE-KMS; the gateway only has its own master key
+const SSE_KMS_KEY_ID_HEADER: &str = "x-amz-server-side-encryption-aws-kms-key-id";
+
 /// S3 API error types
 #[derive(Error, Debug)]
 pub enum S3Error {
@@ -37,68 +192,291 @@ pub enum S3Error {
     #[error("Bucket not empty: {0}")]
     BucketNotEmpty(String),
 
+    #[error("Bucket has no policy: {0}")]
+    NoSuchBucketPolicy(String),
+
+    #[error("Malformed bucket policy: {0}")]
+    MalformedPolicy(String),
+
+    /// XML request body that could not be parsed
+    #[error("Malformed XML: {0}")]
+    MalformedXml(String),
+
     #[error("Access denied")]
     AccessDenied,
 
     #[error("Invalid request: {0}")]
     InvalidRequest(String),
 
+    /// Feature of the S3 API the gateway does not implement
+    #[error("Not implemented: {0}")]
+    NotImplemented(String),
+
+    #[error("Range not satisfiable")]
+    InvalidRange,
+
+    #[error("Key exceeds {max_len} bytes")]
+    KeyTooLong { max_len: usize },
+
+    #[error("User metadata exceeds {max_size} bytes")]
+    MetadataTooLarge { max_size: usize },
+
+    #[error("Request rate exceeded")]
+    SlowDown,
+
+    #[error("Egress limit of plan {plan} exceeded")]
+    EgressLimitExceeded { plan: String, limit_bytes: u64 },
+
+    #[error("Precondition failed")]
+    PreconditionFailed,
+
+    /// Object under retention (see [`crate::object_lock`])
+    #[error("Object is locked: {0}")]
+    ObjectLocked(String),
+
+    #[error("Object exceeds the maximum size of {max_size} bytes")]
+    EntityTooLarge { max_size: u64 },
+
+    /// Archived object read without a restored copy (see [`crate::tiering`])
+    #[error("Invalid object state: {0}")]
+    InvalidObjectState(String),
+
+    #[error("Restore already in progress: {0}")]
+    RestoreAlreadyInProgress(String),
+
+    #[error("Invalid storage class: {0}")]
+    InvalidStorageClass(String),
+
+    #[error("Bucket has no lifecycle configuration: {0}")]
+    NoSuchLifecycleConfiguration(String),
+
+    #[error("Bucket has no CORS configuration: {0}")]
+    NoSuchCorsConfiguration(String),
+
+    /// Preflight no CORS rule of the bucket allows (see [`crate::bucket_cors`])
+    #[error("CORS request not allowed")]
+    CorsNotAllowed,
+
+    /// Upload body too slow, stalled or not completed in time
+    #[error("Request timeout: {0}")]
+    RequestTimeout(String),
+
+    /// Failure classified by the shared error taxonomy
+    #[error("{code}: {message}")]
+    Service { code: ErrorCode, message: String },
+
     #[error("Internal error: {0}")]
     Internal(String),
 }
 
-impl IntoResponse for S3Error {
-    fn into_response(self) -> Response {
-        let (status, error_code, message) = match &self {
-            S3Error::NoSuchBucket(_) => (
-                StatusCode::NOT_FOUND,
-                "NoSuchBucket",
-                "The specified bucket does not exist".to_string(),
-            ),
-            S3Error::NoSuchKey(_) => (
-                StatusCode::NOT_FOUND,
-                "NoSuchKey",
-                "The specified key does not exist".to_string(),
-            ),
-            S3Error::BucketAlreadyExists(_) => (
-                StatusCode::CONFLICT,
-                "BucketAlreadyExists",
-                "The specified bucket already exists".to_string(),
+impl S3Error {
+    /// Create a classified service error
+    pub fn service(code: ErrorCode, message: impl Into<String>) -> Self {
+        S3Error::Service {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl HasErrorCode for S3Error {
+    fn error_code(&self) -> ErrorCode {
+        match self {
+            S3Error::NoSuchBucket(_) | S3Error::NoSuchKey(_) => ErrorCode::NotFound,
+            S3Error::BucketAlreadyExists(_) => ErrorCode::AlreadyExists,
+            S3Error::BucketNotEmpty(_) => ErrorCode::Conflict,
+            S3Error::NoSuchBucketPolicy(_) => ErrorCode::NotFound,
+            S3Error::MalformedPolicy(_) | S3Error::MalformedXml(_) => ErrorCode::InvalidArgument,
+            S3Error::AccessDenied => ErrorCode::PermissionDenied,
+            S3Error::InvalidRequest(_)
+            | S3Error::NotImplemented(_)
+            | S3Error::InvalidRange
+            | S3Error::KeyTooLong { .. }
+            | S3Error::MetadataTooLarge { .. } => ErrorCode::InvalidArgument,
+            S3Error::SlowDown => ErrorCode::RateLimited,
+            S3Error::EgressLimitExceeded { .. } => ErrorCode::PermissionDenied,
+            S3Error::PreconditionFailed => ErrorCode::Conflict,
+            S3Error::ObjectLocked(_) => ErrorCode::PermissionDenied,
+            S3Error::EntityTooLarge { .. } => ErrorCode::InvalidArgument,
+            S3Error::InvalidObjectState(_) => ErrorCode::PermissionDenied,
+            S3Error::RestoreAlreadyInProgress(_) => ErrorCode::Conflict,
+            S3Error::InvalidStorageClass(_) => ErrorCode::InvalidArgument,
+            S3Error::NoSuchLifecycleConfiguration(_) => ErrorCode::NotFound,
+            S3Error::NoSuchCorsConfiguration(_) => ErrorCode::NotFound,
+            S3Error::CorsNotAllowed => ErrorCode::PermissionDenied,
+            S3Error::RequestTimeout(_) => ErrorCode::Timeout,
+            S3Error::Service { code, .. } => *code,
+            S3Error::Internal(_) => ErrorCode::Internal,
+        }
+    }
+}
+
+impl From<MetadataError> for S3Error {
+    fn from(e: MetadataError) -> Self {
+        S3Error::service(e.error_code(), e.to_string())
+    }
+}
+
+impl From<DbError> for S3Error {
+    fn from(e: DbError) -> Self {
+        S3Error::service(e.error_code(), e.to_string())
+    }
+}
+
+impl From<CyxCloudError> for S3Error {
+    fn from(e: CyxCloudError) -> Self {
+        S3Error::service(e.error_code(), e.to_string())
+    }
+}
+
+impl From<SelectError> for S3Error {
+    fn from(e: SelectError) -> Self {
+        S3Error::InvalidRequest(e.to_string())
+    }
+}
+
+impl From<NodeClientError> for S3Error {
+    fn from(e: NodeClientError) -> Self {
+        S3Error::service(e.error_code(), e.to_string())
+    }
+}
+
+impl From<PolicyError> for S3Error {
+    fn from(e: PolicyError) -> Self {
+        S3Error::MalformedPolicy(e.to_string())
+    }
+}
+
+impl From<KmsError> for S3Error {
+    fn from(e: KmsError) -> Self {
+        S3Error::service(e.error_code(), e.to_string())
+    }
+}
+
+/// S3 error code for a taxonomy code
+fn s3_error_code(code: ErrorCode) -> AwsErrorCode {
+    match code {
+        ErrorCode::NotFound => AwsErrorCode::NoSuchKey,
+        ErrorCode::AlreadyExists | ErrorCode::Conflict => AwsErrorCode::OperationAborted,
+        ErrorCode::InvalidArgument => AwsErrorCode::InvalidArgument,
+        ErrorCode::Unauthenticated | ErrorCode::PermissionDenied => AwsErrorCode::AccessDenied,
+        ErrorCode::RateLimited => AwsErrorCode::SlowDown,
+        ErrorCode::Timeout => AwsErrorCode::RequestTimeout,
+        ErrorCode::NodeUnreachable
+        | ErrorCode::NoNodesAvailable
+        | ErrorCode::MetadataUnavailable
+        | ErrorCode::ServiceUnavailable
+        | ErrorCode::StorageFull
+        | ErrorCode::TransportCorruption => AwsErrorCode::ServiceUnavailable,
+        ErrorCode::InsufficientShards | ErrorCode::IntegrityError | ErrorCode::Internal => {
+            AwsErrorCode::InternalError
+        }
+    }
+}
+
+impl S3Error {
+    /// S3 error code of the response, with the message when it has details
+    /// beyond the code's default one
+    fn s3_code(&self) -> (AwsErrorCode, Option<String>) {
+        match self {
+            S3Error::NoSuchBucket(_) => (AwsErrorCode::NoSuchBucket, None),
+            S3Error::NoSuchKey(_) => (AwsErrorCode::NoSuchKey, None),
+            S3Error::BucketAlreadyExists(_) => (AwsErrorCode::BucketAlreadyExists, None),
+            S3Error::BucketNotEmpty(_) => (AwsErrorCode::BucketNotEmpty, None),
+            S3Error::NoSuchBucketPolicy(_) => (AwsErrorCode::NoSuchBucketPolicy, None),
+            S3Error::MalformedPolicy(m) => (AwsErrorCode::MalformedPolicy, Some(m.clone())),
+            S3Error::MalformedXml(m) => (AwsErrorCode::MalformedXml, Some(m.clone())),
+            S3Error::AccessDenied => (AwsErrorCode::AccessDenied, None),
+            S3Error::InvalidRequest(m) => (AwsErrorCode::InvalidRequest, Some(m.clone())),
+            S3Error::NotImplemented(m) => (AwsErrorCode::NotImplemented, Some(m.clone())),
+            S3Error::InvalidRange => (AwsErrorCode::InvalidRange, None),
+            S3Error::KeyTooLong { max_len } => (
+                AwsErrorCode::KeyTooLongError,
+                Some(format!("Your key exceeds the maximum of {} bytes", max_len)),
             ),
-            S3Error::BucketNotEmpty(_) => (
-                StatusCode::CONFLICT,
-                "BucketNotEmpty",
-                "The bucket is not empty".to_string(),
+            S3Error::MetadataTooLarge { max_size } => (
+                AwsErrorCode::MetadataTooLarge,
+                Some(format!(
+                    "Your metadata headers exceed the maximum of {} bytes",
+                    max_size
+                )),
             ),
-            S3Error::AccessDenied => (
-                StatusCode::FORBIDDEN,
-                "AccessDenied",
-                "Access Denied".to_string(),
+            S3Error::SlowDown => (AwsErrorCode::SlowDown, None),
+            S3Error::EgressLimitExceeded { plan, limit_bytes } => (
+                AwsErrorCode::AccessDenied,
+                Some(format!(
+                    "Monthly download limit of the {} plan ({} GB) exceeded",
+                    plan,
+                    limit_bytes / (1024 * 1024 * 1024)
+                )),
             ),
-            S3Error::InvalidRequest(m) => (
-                StatusCode::BAD_REQUEST,
-                "InvalidRequest",
-                xml_escape(m),
+            S3Error::PreconditionFailed => (AwsErrorCode::PreconditionFailed, None),
+            S3Error::ObjectLocked(m) => (AwsErrorCode::AccessDenied, Some(m.clone())),
+            S3Error::EntityTooLarge { max_size } => (
+                AwsErrorCode::EntityTooLarge,
+                Some(format!(
+                    "Your proposed upload exceeds the maximum allowed object size of {} bytes",
+                    max_size
+                )),
             ),
-            S3Error::Internal(_) => (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "InternalError",
-                "An internal error occurred".to_string(),
+            S3Error::InvalidObjectState(m) => (AwsErrorCode::InvalidObjectState, Some(m.clone())),
+            S3Error::RestoreAlreadyInProgress(_) => (AwsErrorCode::RestoreAlreadyInProgress, None),
+            S3Error::InvalidStorageClass(_) => (AwsErrorCode::InvalidStorageClass, None),
+            S3Error::NoSuchLifecycleConfiguration(_) => {
+                (AwsErrorCode::NoSuchLifecycleConfiguration, None)
+            }
+            S3Error::NoSuchCorsConfiguration(_) => (AwsErrorCode::NoSuchCorsConfiguration, None),
+            S3Error::CorsNotAllowed => (
+                AwsErrorCode::AccessDenied,
+                Some(
+                    "CORSResponse: This CORS request is not allowed. The Origin, \
+                     Access-Control-Request-Method or Access-Control-Request-Headers \
+                     are not allowed by the bucket's CORS configuration"
+                        .to_string(),
+                ),
             ),
+            S3Error::RequestTimeout(_) => (AwsErrorCode::RequestTimeout, None),
+            S3Error::Service { code, .. } => {
+                (s3_error_code(*code), Some(code.description().to_string()))
+            }
+            S3Error::Internal(_) => (AwsErrorCode::InternalError, None),
+        }
+    }
+}
+
+impl IntoResponse for S3Error {
+    fn into_response(self) -> Response {
+        let (s3_code, message) = self.s3_code();
+        let status = match &self {
+            // The taxonomy tells apart unavailable from failed
+            S3Error::Service { code, .. } => StatusCode::from_u16(code.http_status())
+                .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
+            _ => s3_code.status(),
         };
 
-        let body = format!(
-            r#"<?xml version="1.0" encoding="UTF-8"?>
-<Error>
-    <Code>{}</Code>
-    <Message>{}</Message>
-</Error>"#,
-            error_code, message
-        );
+        let code = self.error_code();
+        let request_id = crate::request_id::current().unwrap_or_default();
+
+        // Details stay in the logs; clients get the code and request ID
+        if status.is_server_error() {
+            error!(request_id = %request_id, code = %code, error = %self, "S3 request failed");
+        } else {
+            debug!(request_id = %request_id, code = %code, error = %self, "S3 request rejected");
+        }
+
+        let body = s3_xml::to_xml(&ErrorResponse {
+            code: s3_code.as_str().to_string(),
+            message: message.unwrap_or_else(|| s3_code.default_message().to_string()),
+            resource: None,
+            cyxcloud_code: Some(code.to_string()),
+            request_id,
+        });
 
         Response::builder()
             .status(status)
             .header(header::CONTENT_TYPE, "application/xml")
+            .header(ERROR_CODE_HEADER, code.as_str())
+            .extension(S3ErrorCode(s3_code.as_str()))
             .body(Body::from(body))
             .expect("S3 error response construction should never fail")
     }
@@ -106,8 +484,12 @@ impl IntoResponse for S3Error {
 
 pub type S3Result<T> = Result<T, S3Error>;
 
+/// S3 error code of an error response (response extension for access logs)
+#[derive(Debug, Clone, Copy)]
+pub struct S3ErrorCode(pub &'static str);
+
 /// Validate an S3 object key for path traversal and invalid characters
-fn validate_object_key(key: &str) -> S3Result<()> {
+pub(crate) fn validate_object_key(key: &str) -> S3Result<()> {
     if key.is_empty() {
         return Err(S3Error::InvalidRequest("Key cannot be empty".to_string()));
     }
@@ -131,10 +513,10 @@ fn validate_object_key(key: &str) -> S3Result<()> {
             "Key cannot contain control characters".to_string(),
         ));
     }
-    if key.len() > 1024 {
-        return Err(S3Error::InvalidRequest(
-            "Key cannot exceed 1024 characters".to_string(),
-        ));
+    if key.len() > MAX_KEY_LEN {
+        return Err(S3Error::KeyTooLong {
+            max_len: MAX_KEY_LEN,
+        });
     }
     Ok(())
 }
@@ -161,6 +543,26 @@ pub struct ListObjectsQuery {
     pub continuation_token: Option<String>,
     #[serde(rename = "start-after")]
     pub start_after: Option<String>,
+    /// `?logging` asks for the bucket's access logging status instead
+    pub logging: Option<String>,
+    /// `?object-lock` asks for the bucket's object lock configuration instead
+    #[serde(rename = "object-lock")]
+    pub object_lock: Option<String>,
+    /// `?policy` asks for the bucket's policy instead
+    pub policy: Option<String>,
+    /// `?acl` asks for the bucket's ACL instead
+    pub acl: Option<String>,
+    /// `?cache-policy` asks for the bucket's default Cache-Control instead
+    #[serde(rename = "cache-policy")]
+    pub cache_policy: Option<String>,
+    /// `?lifecycle` asks for the bucket's lifecycle rules instead
+    pub lifecycle: Option<String>,
+    /// `?cors` asks for the bucket's CORS rules instead
+    pub cors: Option<String>,
+    /// `?deleted` lists the bucket's restorable deleted objects instead
+    pub deleted: Option<String>,
+    /// `?export=tar` streams the bucket as an archive instead
+    pub export: Option<String>,
 }
 
 /// Object metadata for listings
@@ -171,6 +573,53 @@ pub struct ObjectInfo {
     pub etag: String,
     pub size: u64,
     pub storage_class: String,
+    /// `x-amz-meta-*` metadata (not part of S3 listings)
+    #[serde(skip)]
+    pub user_metadata: UserMetadata,
+}
+
+/// Restorable deleted object, for trash listings
+#[derive(Debug, Serialize)]
+pub struct DeletedObjectInfo {
+    pub key: String,
+    /// Version to pass to `?restore&versionId=`
+    pub version_id: String,
+    pub deleted_at: String,
+    pub etag: String,
+    pub size: u64,
+}
+
+/// Trash listing response (`GET /:bucket?deleted`)
+#[derive(Debug, Serialize)]
+pub struct ListDeletedObjectsResponse {
+    pub name: String,
+    pub prefix: String,
+    pub max_keys: i32,
+    pub is_truncated: bool,
+    pub objects: Vec<DeletedObjectInfo>,
+}
+
+impl ListDeletedObjectsResponse {
+    fn to_xml(&self) -> String {
+        s3_xml::to_xml(&s3_xml::ListDeletedObjectsResult {
+            xmlns: s3_xml::S3Namespace,
+            name: self.name.clone(),
+            prefix: self.prefix.clone(),
+            max_keys: self.max_keys,
+            is_truncated: self.is_truncated,
+            deleted_objects: self
+                .objects
+                .iter()
+                .map(|obj| s3_xml::DeletedObject {
+                    key: obj.key.clone(),
+                    version_id: obj.version_id.clone(),
+                    deleted_at: obj.deleted_at.clone(),
+                    etag: obj.etag.clone(),
+                    size: obj.size,
+                })
+                .collect(),
+        })
+    }
 }
 
 /// List objects response
@@ -189,409 +638,4515 @@ pub struct ListObjectsV2Response {
 
 impl ListObjectsV2Response {
     fn to_xml(&self) -> String {
-        let mut xml = String::from(r#"<?xml version="1.0" encoding="UTF-8"?>"#);
-        xml.push_str("\n<ListBucketResult xmlns=\"http://s3.amazonaws.com/doc/2006-03-01/\">");
-        xml.push_str(&format!("\n  <Name>{}</Name>", self.name));
+        s3_xml::to_xml(&s3_xml::ListBucketResult {
+            xmlns: s3_xml::S3Namespace,
+            name: self.name.clone(),
+            prefix: self.prefix.clone().unwrap_or_default(),
+            delimiter: None,
+            max_keys: self.max_keys,
+            is_truncated: self.is_truncated,
+            key_count: self.key_count,
+            continuation_token: self.continuation_token.clone(),
+            start_after: None,
+            contents: self
+                .contents
+                .iter()
+                .map(|obj| s3_xml::ListedObject {
+                    key: obj.key.clone(),
+                    last_modified: obj.last_modified.clone(),
+                    etag: obj.etag.clone(),
+                    size: obj.size,
+                    storage_class: obj.storage_class.clone(),
+                })
+                .collect(),
+            common_prefixes: self
+                .common_prefixes
+                .iter()
+                .map(|prefix| s3_xml::CommonPrefix {
+                    prefix: prefix.clone(),
+                })
+                .collect(),
+            next_continuation_token: self.next_continuation_token.clone(),
+        })
+    }
+}
 
-        if let Some(prefix) = &self.prefix {
-            xml.push_str(&format!("\n  <Prefix>{}</Prefix>", prefix));
-        } else {
-            xml.push_str("\n  <Prefix/>");
-        }
+/// Result of a CopyObject
+#[derive(Debug)]
+pub struct CopyObjectResult {
+    pub etag: String,
+    pub last_modified: String,
+}
 
-        xml.push_str(&format!("\n  <MaxKeys>{}</MaxKeys>", self.max_keys));
-        xml.push_str(&format!(
-            "\n  <IsTruncated>{}</IsTruncated>",
-            self.is_truncated
-        ));
-        xml.push_str(&format!("\n  <KeyCount>{}</KeyCount>", self.key_count));
+impl CopyObjectResult {
+    fn to_xml(&self) -> String {
+        s3_xml::to_xml(&s3_xml::CopyObjectResult {
+            xmlns: s3_xml::S3Namespace,
+            last_modified: self.last_modified.clone(),
+            etag: format!("\"{}\"", self.etag),
+        })
+    }
+}
 
-        for obj in &self.contents {
-            xml.push_str("\n  <Contents>");
-            xml.push_str(&format!("\n    <Key>{}</Key>", obj.key));
-            xml.push_str(&format!(
-                "\n    <LastModified>{}</LastModified>",
-                obj.last_modified
+/// Bucket access logging status (`GET/PUT /:bucket?logging`)
+#[derive(Debug, PartialEq)]
+pub struct BucketLoggingStatus {
+    /// Logging target, None when logging is disabled
+    pub logging: Option<BucketLogging>,
+}
+
+impl BucketLoggingStatus {
+    /// Parse the `<BucketLoggingStatus>` XML body
+    ///
+    /// A status without `<LoggingEnabled>` (or an empty document element)
+    /// disables logging.
+    pub fn from_xml(body: &str) -> S3Result<Self> {
+        let malformed = |msg: &str| S3Error::MalformedXml(format!("BucketLoggingStatus: {}", msg));
+
+        let Some((status, _)) = xml_element(body, "BucketLoggingStatus") else {
+            if body.contains("<BucketLoggingStatus") {
+                return Ok(Self { logging: None });
+            }
+            return Err(malformed("missing BucketLoggingStatus"));
+        };
+        let Some((enabled, _)) = xml_element(status, "LoggingEnabled") else {
+            return Ok(Self { logging: None });
+        };
+
+        let target_bucket = xml_element(enabled, "TargetBucket")
+            .map(|(bucket, _)| xml_unescape(bucket.trim()))
+            .filter(|bucket| !bucket.is_empty())
+            .ok_or_else(|| malformed("LoggingEnabled without TargetBucket"))?;
+        let target_prefix = xml_element(enabled, "TargetPrefix")
+            .map(|(prefix, _)| xml_unescape(prefix))
+            .unwrap_or_default();
+        if target_prefix.contains("..") || target_prefix.starts_with('/') {
+            return Err(S3Error::InvalidRequest(
+                "TargetPrefix cannot contain '..' or start with '/'".to_string(),
             ));
-            xml.push_str(&format!("\n    <ETag>{}</ETag>", obj.etag));
-            xml.push_str(&format!("\n    <Size>{}</Size>", obj.size));
+        }
+
+        Ok(Self {
+            logging: Some(BucketLogging {
+                target_bucket,
+                target_prefix,
+            }),
+        })
+    }
+
+    /// Render as S3 `BucketLoggingStatus` XML
+    pub fn to_xml(&self) -> String {
+        let mut xml = String::from(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<BucketLoggingStatus xmlns="http://s3.amazonaws.com/doc/2006-03-01/">"#,
+        );
+        if let Some(ref logging) = self.logging {
             xml.push_str(&format!(
-                "\n    <StorageClass>{}</StorageClass>",
-                obj.storage_class
+                "\n  <LoggingEnabled>\n    <TargetBucket>{}</TargetBucket>\n    <TargetPrefix>{}</TargetPrefix>\n  </LoggingEnabled>",
+                xml_escape(&logging.target_bucket),
+                xml_escape(&logging.target_prefix)
             ));
-            xml.push_str("\n  </Contents>");
         }
+        xml.push_str("\n</BucketLoggingStatus>");
+        xml
+    }
+}
+
+/// Default Cache-Control of a bucket (`GET/PUT /:bucket?cache-policy`)
+#[derive(Debug, PartialEq)]
+pub struct BucketCachePolicy {
+    /// Cache-Control given to new objects, None when there is none
+    pub cache_control: Option<String>,
+}
 
-        for prefix in &self.common_prefixes {
-            xml.push_str("\n  <CommonPrefixes>");
-            xml.push_str(&format!("\n    <Prefix>{}</Prefix>", prefix));
-            xml.push_str("\n  </CommonPrefixes>");
+impl BucketCachePolicy {
+    /// Parse the `<CachePolicy>` XML body
+    ///
+    /// A policy without `<CacheControl>` (or with an empty one) removes the
+    /// default.
+    pub fn from_xml(body: &str) -> S3Result<Self> {
+        let Some((policy, _)) = xml_element(body, "CachePolicy") else {
+            if body.contains("<CachePolicy") {
+                return Ok(Self {
+                    cache_control: None,
+                });
+            }
+            return Err(S3Error::MalformedXml(
+                "CachePolicy: missing CachePolicy".to_string(),
+            ));
+        };
+        let cache_control = xml_element(policy, "CacheControl")
+            .map(|(value, _)| xml_unescape(value.trim()))
+            .filter(|value| !value.is_empty());
+        if let Some(ref value) = cache_control {
+            if header::HeaderValue::from_str(value).is_err() {
+                return Err(S3Error::InvalidRequest(format!(
+                    "Invalid CacheControl: {}",
+                    value
+                )));
+            }
         }
+        Ok(Self { cache_control })
+    }
 
-        if let Some(token) = &self.next_continuation_token {
+    /// Render as `CachePolicy` XML
+    pub fn to_xml(&self) -> String {
+        let mut xml = String::from(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<CachePolicy xmlns="http://s3.amazonaws.com/doc/2006-03-01/">"#,
+        );
+        if let Some(ref cache_control) = self.cache_control {
             xml.push_str(&format!(
-                "\n  <NextContinuationToken>{}</NextContinuationToken>",
-                token
+                "\n  <CacheControl>{}</CacheControl>",
+                xml_escape(cache_control)
             ));
         }
-
-        xml.push_str("\n</ListBucketResult>");
+        xml.push_str("\n</CachePolicy>");
         xml
     }
 }
 
-/// Create S3 API routes
-pub fn routes() -> Router<Arc<AppState>> {
-    Router::new()
-        // Bucket operations
-        .route("/:bucket", put(create_bucket))
-        .route("/:bucket", delete(delete_bucket))
-        .route("/:bucket", head(head_bucket))
-        .route("/:bucket", get(list_objects))
-        // Object operations
-        .route("/:bucket/*key", put(put_object))
-        .route("/:bucket/*key", get(get_object))
-        .route("/:bucket/*key", delete(delete_object))
-        .route("/:bucket/*key", head(head_object))
+/// Bucket lifecycle rules (`GET/PUT/DELETE /:bucket?lifecycle`)
+#[derive(Debug, PartialEq)]
+pub struct BucketLifecycleConfiguration {
+    pub rules: Vec<LifecycleRule>,
 }
 
-// =============================================================================
-// BUCKET OPERATIONS
-// =============================================================================
+impl BucketLifecycleConfiguration {
+    /// Parse the `<LifecycleConfiguration>` XML body
+    ///
+    /// Each `Rule` has a `Transition` after a number of `Days` to the
+    /// ARCHIVE storage class, for the keys under its `Filter` (or legacy
+    /// top-level) `Prefix`. Expirations and other actions are not
+    /// supported.
+    pub fn from_xml(body: &str) -> S3Result<Self> {
+        let malformed =
+            |msg: &str| S3Error::MalformedXml(format!("LifecycleConfiguration: {}", msg));
 
-/// PUT /:bucket - Create bucket
-#[instrument(skip(state))]
-async fn create_bucket(
-    State(state): State<Arc<AppState>>,
-    Path(bucket): Path<String>,
-) -> S3Result<impl IntoResponse> {
-    info!(bucket = %bucket, "Creating bucket");
+        let (configuration, _) = xml_element(body, "LifecycleConfiguration")
+            .ok_or_else(|| malformed("missing LifecycleConfiguration"))?;
 
-    // Check if bucket exists
-    if state.bucket_exists(&bucket).await? {
-        return Err(S3Error::BucketAlreadyExists(bucket));
-    }
+        let mut rules = Vec::new();
+        let mut rest = configuration;
+        while let Some((rule, end)) = xml_element(rest, "Rule") {
+            rest = &rest[end..];
 
-    // Create bucket in metadata
-    state.create_bucket(&bucket).await?;
+            for action in [
+                "Expiration",
+                "NoncurrentVersionTransition",
+                "NoncurrentVersionExpiration",
+                "AbortIncompleteMultipartUpload",
+            ] {
+                if xml_element(rule, action).is_some() {
+                    return Err(S3Error::NotImplemented(format!(
+                        "Lifecycle {} is not supported",
+                        action
+                    )));
+                }
+            }
 
-    Ok((StatusCode::OK, [(header::LOCATION, format!("/{}", bucket))]))
-}
+            let id = xml_element(rule, "ID")
+                .map(|(id, _)| xml_unescape(id.trim()))
+                .unwrap_or_else(|| format!("rule-{}", rules.len() + 1));
+            let enabled = match xml_element(rule, "Status").map(|(s, _)| s.trim()) {
+                Some("Enabled") => true,
+                Some("Disabled") => false,
+                _ => return Err(malformed("Rule Status must be Enabled or Disabled")),
+            };
 
-/// DELETE /:bucket - Delete bucket
-#[instrument(skip(state))]
-async fn delete_bucket(
-    State(state): State<Arc<AppState>>,
-    Path(bucket): Path<String>,
-) -> S3Result<impl IntoResponse> {
-    info!(bucket = %bucket, "Deleting bucket");
+            let prefix = match xml_element(rule, "Filter") {
+                Some((filter, _)) => {
+                    if xml_element(filter, "And").is_some() || xml_element(filter, "Tag").is_some()
+                    {
+                        return Err(S3Error::NotImplemented(
+                            "Lifecycle filters other than Prefix are not supported".to_string(),
+                        ));
+                    }
+                    xml_element(filter, "Prefix")
+                }
+                None => xml_element(rule, "Prefix"),
+            }
+            .map(|(prefix, _)| xml_unescape(prefix))
+            .unwrap_or_default();
 
-    // Check if bucket exists
-    if !state.bucket_exists(&bucket).await? {
-        return Err(S3Error::NoSuchBucket(bucket));
-    }
+            let (transition, _) = xml_element(rule, "Transition")
+                .ok_or_else(|| malformed("Rule without Transition"))?;
+            if xml_element(transition, "Date").is_some() {
+                return Err(S3Error::NotImplemented(
+                    "Lifecycle transitions on a Date are not supported".to_string(),
+                ));
+            }
+            let days = xml_element(transition, "Days")
+                .and_then(|(days, _)| days.trim().parse::<u32>().ok())
+                .ok_or_else(|| malformed("Transition without a valid Days"))?;
+            let class = xml_element(transition, "StorageClass")
+                .map(|(class, _)| class.trim())
+                .ok_or_else(|| malformed("Transition without StorageClass"))?;
+            let storage_class = match StorageClass::from_str(class) {
+                Some(StorageClass::Archive) => StorageClass::Archive,
+                _ => return Err(S3Error::InvalidStorageClass(class.to_string())),
+            };
 
-    // Check if bucket is empty
-    if !state.bucket_is_empty(&bucket).await? {
-        return Err(S3Error::InvalidRequest("Bucket is not empty".to_string()));
+            rules.push(LifecycleRule {
+                id,
+                enabled,
+                prefix,
+                days,
+                storage_class,
+            });
+        }
+
+        if rules.is_empty() {
+            return Err(malformed("no Rule entries"));
+        }
+        if rules.len() > MAX_LIFECYCLE_RULES {
+            return Err(S3Error::InvalidRequest(format!(
+                "A bucket can have at most {} lifecycle rules",
+                MAX_LIFECYCLE_RULES
+            )));
+        }
+        Ok(Self { rules })
     }
 
-    // Delete bucket
-    state.delete_bucket(&bucket).await?;
+    /// Render as S3 `LifecycleConfiguration` XML
+    pub fn to_xml(&self) -> String {
+        let mut xml = String::from(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<LifecycleConfiguration xmlns="http://s3.amazonaws.com/doc/2006-03-01/">"#,
+        );
+        for rule in &self.rules {
+            xml.push_str(&format!(
+                "\n  <Rule>\n    <ID>{}</ID>\n    <Filter><Prefix>{}</Prefix></Filter>\n    <Status>{}</Status>\n    <Transition>\n      <Days>{}</Days>\n      <StorageClass>{}</StorageClass>\n    </Transition>\n  </Rule>",
+                xml_escape(&rule.id),
+                xml_escape(&rule.prefix),
+                if rule.enabled { "Enabled" } else { "Disabled" },
+                rule.days,
+                rule.storage_class.as_str()
+            ));
+        }
+        xml.push_str("\n</LifecycleConfiguration>");
+        xml
+    }
+}
 
-    Ok(StatusCode::NO_CONTENT)
+/// Archive restore request (`POST /:bucket/*key?restore` with a body)
+#[derive(Debug, PartialEq)]
+pub struct RestoreRequest {
+    /// Days the restored copy is kept
+    pub days: u32,
 }
 
-/// HEAD /:bucket - Check if bucket exists
-#[instrument(skip(state))]
-async fn head_bucket(
-    State(state): State<Arc<AppState>>,
-    Path(bucket): Path<String>,
-) -> S3Result<impl IntoResponse> {
-    debug!(bucket = %bucket, "Checking bucket");
+impl RestoreRequest {
+    /// Parse the `<RestoreRequest>` XML body
+    pub fn from_xml(body: &str) -> S3Result<Self> {
+        let malformed = |msg: &str| S3Error::MalformedXml(format!("RestoreRequest: {}", msg));
 
-    if !state.bucket_exists(&bucket).await? {
-        return Err(S3Error::NoSuchBucket(bucket));
+        let (request, _) = xml_element(body, "RestoreRequest")
+            .ok_or_else(|| malformed("missing RestoreRequest"))?;
+        let days = xml_element(request, "Days")
+            .and_then(|(days, _)| days.trim().parse::<u32>().ok())
+            .ok_or_else(|| malformed("missing or invalid Days"))?;
+        if !(1..=MAX_RESTORE_DAYS).contains(&days) {
+            return Err(S3Error::InvalidRequest(format!(
+                "Days must be between 1 and {}",
+                MAX_RESTORE_DAYS
+            )));
+        }
+        Ok(Self { days })
     }
+}
 
-    Ok(StatusCode::OK)
+/// Bucket CORS rules (`GET/PUT/DELETE /:bucket?cors`)
+#[derive(Debug, PartialEq)]
+pub struct BucketCorsConfiguration {
+    pub rules: Vec<CorsRule>,
 }
 
-/// GET /:bucket - List objects in bucket
-#[instrument(skip(state))]
-async fn list_objects(
-    State(state): State<Arc<AppState>>,
-    Path(bucket): Path<String>,
-    Query(query): Query<ListObjectsQuery>,
-) -> S3Result<impl IntoResponse> {
-    debug!(bucket = %bucket, prefix = ?query.prefix, "Listing objects");
+impl BucketCorsConfiguration {
+    /// Parse the `<CORSConfiguration>` XML body
+    pub fn from_xml(body: &str) -> S3Result<Self> {
+        let malformed = |msg: &str| S3Error::MalformedXml(format!("CORSConfiguration: {}", msg));
 
-    if !state.bucket_exists(&bucket).await? {
-        return Err(S3Error::NoSuchBucket(bucket));
-    }
+        let (configuration, _) = xml_element(body, "CORSConfiguration")
+            .ok_or_else(|| malformed("missing CORSConfiguration"))?;
 
-    let max_keys = query.max_keys.unwrap_or(1000).min(1000);
-    let prefix = query.prefix.clone().unwrap_or_default();
-    let delimiter = query.delimiter.clone();
+        let mut rules = Vec::new();
+        let mut rest = configuration;
+        while let Some((rule, end)) = xml_element(rest, "CORSRule") {
+            rest = &rest[end..];
 
-    // Get objects from metadata
-    let (objects, is_truncated, next_token) = state
-        .list_objects(
-            &bucket,
-            &prefix,
-            delimiter.as_deref(),
-            max_keys,
-            query.continuation_token.as_deref(),
-        )
-        .await?;
+            let max_age_secs = match xml_element(rule, "MaxAgeSeconds") {
+                Some((secs, _)) => Some(
+                    secs.trim()
+                        .parse::<u32>()
+                        .map_err(|_| malformed("invalid MaxAgeSeconds"))?,
+                ),
+                None => None,
+            };
+            let rule = CorsRule {
+                id: xml_element(rule, "ID").map(|(id, _)| xml_unescape(id.trim())),
+                allowed_origins: xml_elements(rule, "AllowedOrigin"),
+                allowed_methods: xml_elements(rule, "AllowedMethod"),
+                allowed_headers: xml_elements(rule, "AllowedHeader"),
+                expose_headers: xml_elements(rule, "ExposeHeader"),
+                max_age_secs,
+            };
+            rule.validate()?;
+            rules.push(rule);
+        }
 
-    // Build response
-    let response = ListObjectsV2Response {
-        name: bucket,
-        prefix: Some(prefix),
-        max_keys,
-        is_truncated,
-        key_count: objects.len() as i32,
-        contents: objects,
-        common_prefixes: Vec::new(), // TODO: Implement delimiter handling
-        continuation_token: query.continuation_token,
-        next_continuation_token: next_token,
-    };
+        if rules.is_empty() {
+            return Err(malformed("no CORSRule entries"));
+        }
+        if rules.len() > MAX_CORS_RULES {
+            return Err(S3Error::InvalidRequest(format!(
+                "A bucket can have at most {} CORS rules",
+                MAX_CORS_RULES
+            )));
+        }
+        Ok(Self { rules })
+    }
+
+    /// Render as S3 `CORSConfiguration` XML
+    pub fn to_xml(&self) -> String {
+        let mut xml = String::from(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<CORSConfiguration xmlns="http://s3.amazonaws.com/doc/2006-03-01/">"#,
+        );
+        for rule in &self.rules {
+            xml.push_str("\n  <CORSRule>");
+            if let Some(ref id) = rule.id {
+                xml.push_str(&format!("\n    <ID>{}</ID>", xml_escape(id)));
+            }
+            for (tag, values) in [
+                ("AllowedOrigin", &rule.allowed_origins),
+                ("AllowedMethod", &rule.allowed_methods),
+                ("AllowedHeader", &rule.allowed_headers),
+                ("ExposeHeader", &rule.expose_headers),
+            ] {
+                for value in values {
+                    xml.push_str(&format!("\n    <{0}>{1}</{0}>", tag, xml_escape(value)));
+                }
+            }
+            if let Some(max_age) = rule.max_age_secs {
+                xml.push_str(&format!("\n    <MaxAgeSeconds>{}</MaxAgeSeconds>", max_age));
+            }
+            xml.push_str("\n  </CORSRule>");
+        }
+        xml.push_str("\n</CORSConfiguration>");
+        xml
+    }
+}
+
+/// Bucket object lock configuration (`GET/PUT /:bucket?object-lock`)
+#[derive(Debug, PartialEq)]
+pub struct ObjectLockConfiguration {
+    pub config: ObjectLockConfig,
+}
+
+impl ObjectLockConfiguration {
+    /// Parse the `<ObjectLockConfiguration>` XML body
+    ///
+    /// `ObjectLockEnabled` must be `Enabled`. Without a `Rule` new objects
+    /// get no default retention; a rule's period is given in `Days` or
+    /// `Years` (of 365 days).
+    pub fn from_xml(body: &str) -> S3Result<Self> {
+        let malformed =
+            |msg: &str| S3Error::MalformedXml(format!("ObjectLockConfiguration: {}", msg));
+
+        let (configuration, _) = xml_element(body, "ObjectLockConfiguration")
+            .ok_or_else(|| malformed("missing ObjectLockConfiguration"))?;
+        if xml_element(configuration, "ObjectLockEnabled").map(|(e, _)| e.trim()) != Some("Enabled")
+        {
+            return Err(malformed("ObjectLockEnabled must be Enabled"));
+        }
+        let Some((rule, _)) = xml_element(configuration, "Rule") else {
+            return Ok(Self {
+                config: ObjectLockConfig::default(),
+            });
+        };
+
+        let (retention, _) = xml_element(rule, "DefaultRetention")
+            .ok_or_else(|| malformed("Rule without DefaultRetention"))?;
+        let mode = parse_retention_mode(retention)
+            .ok_or_else(|| malformed("DefaultRetention Mode must be GOVERNANCE or COMPLIANCE"))?;
+        let period = |tag: &str| {
+            xml_element(retention, tag)
+                .map(|(n, _)| n.trim().parse::<u32>())
+                .transpose()
+                .map_err(|_| malformed(&format!("{} must be a positive number", tag)))
+        };
+        let days = match (period("Days")?, period("Years")?) {
+            (Some(days), None) => days,
+            (None, Some(years)) => years.saturating_mul(365),
+            _ => return Err(malformed("DefaultRetention needs either Days or Years")),
+        };
+
+        let default_retention = DefaultRetention { mode, days };
+        default_retention.validate()?;
+        Ok(Self {
+            config: ObjectLockConfig {
+                default_retention: Some(default_retention),
+            },
+        })
+    }
+
+    /// Render as S3 `ObjectLockConfiguration` XML
+    pub fn to_xml(&self) -> String {
+        let mut xml = String::from(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<ObjectLockConfiguration xmlns="http://s3.amazonaws.com/doc/2006-03-01/">
+  <ObjectLockEnabled>Enabled</ObjectLockEnabled>"#,
+        );
+        if let Some(retention) = self.config.default_retention {
+            xml.push_str(&format!(
+                "\n  <Rule>\n    <DefaultRetention>\n      <Mode>{}</Mode>\n      <Days>{}</Days>\n    </DefaultRetention>\n  </Rule>",
+                retention.mode.as_str(),
+                retention.days
+            ));
+        }
+        xml.push_str("\n</ObjectLockConfiguration>");
+        xml
+    }
+}
+
+/// Object retention (`GET/PUT /:bucket/*key?retention`)
+#[derive(Debug, PartialEq)]
+pub struct Retention {
+    pub retention: ObjectRetention,
+}
+
+impl Retention {
+    /// Parse the `<Retention>` XML body
+    pub fn from_xml(body: &str) -> S3Result<Self> {
+        let malformed = |msg: &str| S3Error::MalformedXml(format!("Retention: {}", msg));
+
+        let (retention, _) =
+            xml_element(body, "Retention").ok_or_else(|| malformed("missing Retention"))?;
+        let mode = parse_retention_mode(retention)
+            .ok_or_else(|| malformed("Mode must be GOVERNANCE or COMPLIANCE"))?;
+        let retain_until = xml_element(retention, "RetainUntilDate")
+            .and_then(|(date, _)| chrono::DateTime::parse_from_rfc3339(date.trim()).ok())
+            .map(|date| date.with_timezone(&chrono::Utc))
+            .ok_or_else(|| malformed("RetainUntilDate must be an ISO 8601 timestamp"))?;
+
+        Ok(Self {
+            retention: ObjectRetention { mode, retain_until },
+        })
+    }
+
+    /// Render as S3 `Retention` XML
+    pub fn to_xml(&self) -> String {
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<Retention xmlns="http://s3.amazonaws.com/doc/2006-03-01/">
+  <Mode>{}</Mode>
+  <RetainUntilDate>{}</RetainUntilDate>
+</Retention>"#,
+            self.retention.mode.as_str(),
+            retention_date(self.retention.retain_until)
+        )
+    }
+}
+
+/// Canned ACL as an S3 access control policy (`GET/PUT ?acl`)
+///
+/// The owning tenant has full control; a public-read resource also grants
+/// `READ` to the AllUsers group.
+#[derive(Debug, PartialEq)]
+pub struct AccessControlPolicy {
+    /// Tenant owning the bucket
+    pub owner: String,
+    pub acl: CannedAcl,
+}
+
+impl AccessControlPolicy {
+    /// Canned ACL of an `<AccessControlPolicy>` XML body
+    ///
+    /// Grants to canonical users (the owner) are ignored. The AllUsers group
+    /// can only be granted `READ`; other groups are not supported.
+    pub fn acl_from_xml(body: &str) -> S3Result<CannedAcl> {
+        let malformed = |msg: &str| S3Error::MalformedXml(format!("AccessControlPolicy: {}", msg));
+
+        let (policy, _) = xml_element(body, "AccessControlPolicy")
+            .ok_or_else(|| malformed("missing AccessControlPolicy"))?;
+        let Some((mut grants, _)) = xml_element(policy, "AccessControlList") else {
+            return Ok(CannedAcl::Private);
+        };
+
+        let mut acl = CannedAcl::Private;
+        while let Some((grant, end)) = xml_element(grants, "Grant") {
+            grants = &grants[end..];
+            let (grantee, _) =
+                xml_element(grant, "Grantee").ok_or_else(|| malformed("Grant without Grantee"))?;
+            let Some((uri, _)) = xml_element(grantee, "URI") else {
+                continue;
+            };
+            if xml_unescape(uri.trim()) != ALL_USERS_URI {
+                return Err(malformed(&format!(
+                    "unsupported grantee group {}",
+                    uri.trim()
+                )));
+            }
+            match xml_element(grant, "Permission").map(|(p, _)| p.trim()) {
+                Some("READ") => acl = CannedAcl::PublicRead,
+                _ => return Err(malformed("the AllUsers group can only be granted READ")),
+            }
+        }
+        Ok(acl)
+    }
+
+    /// Render as S3 `AccessControlPolicy` XML
+    pub fn to_xml(&self) -> String {
+        let owner = xml_escape(&self.owner);
+        let mut xml = format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<AccessControlPolicy xmlns="http://s3.amazonaws.com/doc/2006-03-01/">
+  <Owner>
+    <ID>{owner}</ID>
+  </Owner>
+  <AccessControlList>
+    <Grant>
+      <Grantee xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xsi:type="CanonicalUser">
+        <ID>{owner}</ID>
+      </Grantee>
+      <Permission>FULL_CONTROL</Permission>
+    </Grant>"#
+        );
+        if self.acl.is_public_read() {
+            xml.push_str(&format!(
+                r#"
+    <Grant>
+      <Grantee xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xsi:type="Group">
+        <URI>{}</URI>
+      </Grantee>
+      <Permission>READ</Permission>
+    </Grant>"#,
+                ALL_USERS_URI
+            ));
+        }
+        xml.push_str("\n  </AccessControlList>\n</AccessControlPolicy>");
+        xml
+    }
+}
+
+/// Canned ACL of a `PUT ?acl` request: the `x-amz-acl` header, or else the
+/// `AccessControlPolicy` body
+fn requested_acl(headers: &HeaderMap, body: &str) -> S3Result<CannedAcl> {
+    match header_str(headers, ACL_HEADER)? {
+        Some(value) => CannedAcl::parse(value),
+        None if body.trim().is_empty() => Err(S3Error::InvalidRequest(
+            "Expected an x-amz-acl header or an AccessControlPolicy body".to_string(),
+        )),
+        None => AccessControlPolicy::acl_from_xml(body),
+    }
+}
+
+/// `<Mode>` of an object lock XML element
+fn parse_retention_mode(xml: &str) -> Option<RetentionMode> {
+    xml_element(xml, "Mode").and_then(|(mode, _)| RetentionMode::from_str(mode.trim()))
+}
+
+/// Multi-object delete request (`POST /:bucket?delete`)
+#[derive(Debug, PartialEq)]
+pub struct DeleteObjectsRequest {
+    /// Only report errors, not deleted keys
+    pub quiet: bool,
+    pub keys: Vec<String>,
+}
+
+impl DeleteObjectsRequest {
+    /// Parse the `<Delete>` XML body
+    pub fn from_xml(body: &str) -> S3Result<Self> {
+        let malformed = |msg: &str| S3Error::MalformedXml(format!("Delete: {}", msg));
+
+        let (delete, _) = xml_element(body, "Delete").ok_or_else(|| malformed("missing Delete"))?;
+        let quiet = xml_element(delete, "Quiet").is_some_and(|(q, _)| q.trim() == "true");
+
+        let mut keys = Vec::new();
+        let mut rest = delete;
+        while let Some((object, end)) = xml_element(rest, "Object") {
+            let (key, _) =
+                xml_element(object, "Key").ok_or_else(|| malformed("Object without Key"))?;
+            keys.push(xml_unescape(key));
+            rest = &rest[end..];
+        }
+
+        if keys.is_empty() {
+            return Err(malformed("no Object entries"));
+        }
+        if keys.len() > MAX_DELETE_KEYS {
+            return Err(S3Error::InvalidRequest(format!(
+                "At most {} keys can be deleted per request",
+                MAX_DELETE_KEYS
+            )));
+        }
+
+        Ok(Self { quiet, keys })
+    }
+}
+
+/// Contents of the first `<tag>` element (which may carry attributes) and
+/// the offset just past its closing tag
+fn xml_element<'a>(xml: &'a str, tag: &str) -> Option<(&'a str, usize)> {
+    let open = format!("<{}", tag);
+    let close = format!("</{}>", tag);
+    let mut from = 0;
+    loop {
+        let name_end = from + xml[from..].find(&open)? + open.len();
+        // Skip longer tag names sharing the prefix (e.g. <DeleteMarker>)
+        if !xml[name_end..].starts_with(|c: char| c == '>' || c.is_ascii_whitespace()) {
+            from = name_end;
+            continue;
+        }
+        let start = name_end + xml[name_end..].find('>')? + 1;
+        let end = start + xml[start..].find(&close)?;
+        return Some((&xml[start..end], end + close.len()));
+    }
+}
+
+/// Unescaped, trimmed contents of every `<tag>` element in `xml`
+fn xml_elements(xml: &str, tag: &str) -> Vec<String> {
+    let mut values = Vec::new();
+    let mut rest = xml;
+    while let Some((value, end)) = xml_element(rest, tag) {
+        values.push(xml_unescape(value.trim()));
+        rest = &rest[end..];
+    }
+    values
+}
+
+/// Per-key failure of a multi-object delete
+#[derive(Debug)]
+pub struct DeleteError {
+    pub key: String,
+    pub code: AwsErrorCode,
+    pub message: String,
+}
+
+/// Multi-object delete result
+#[derive(Debug)]
+pub struct DeleteObjectsResult {
+    pub quiet: bool,
+    pub deleted: Vec<String>,
+    pub errors: Vec<DeleteError>,
+}
+
+impl DeleteObjectsResult {
+    fn to_xml(&self) -> String {
+        s3_xml::to_xml(&s3_xml::DeleteResult {
+            xmlns: s3_xml::S3Namespace,
+            // Quiet mode only lists errors
+            deleted: self
+                .deleted
+                .iter()
+                .filter(|_| !self.quiet)
+                .map(|key| s3_xml::DeletedKey { key: key.clone() })
+                .collect(),
+            errors: self
+                .errors
+                .iter()
+                .map(|error| s3_xml::KeyError {
+                    key: error.key.clone(),
+                    code: error.code.as_str().to_string(),
+                    message: error.message.clone(),
+                })
+                .collect(),
+        })
+    }
+}
+
+/// Create S3 API routes
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        // Bucket operations
+        .route("/:bucket", put(create_bucket))
+        .route("/:bucket", delete(delete_bucket))
+        .route("/:bucket", head(head_bucket))
+        .route("/:bucket", get(list_objects))
+        .route("/:bucket", post(delete_objects))
+        // Object operations
+        .route("/:bucket/*key", put(put_object))
+        .route("/:bucket/*key", get(get_object))
+        .route("/:bucket/*key", delete(delete_object))
+        .route("/:bucket/*key", head(head_object))
+        .route("/:bucket/*key", post(post_object))
+}
+
+/// Routes serving public objects without authentication, to be nested under
+/// `/public/:tenant`
+pub fn public_routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/:bucket/*key", get(get_public_object))
+        .route("/:bucket/*key", head(head_public_object))
+}
+
+/// Whether an S3 request changes data (SELECT is the only read sent as POST)
+fn is_write_request(method: &Method, query: Option<&str>) -> bool {
+    if method == Method::POST {
+        let select = query.is_some_and(|q| {
+            q.split('&')
+                .any(|param| param == "select" || param.starts_with("select="))
+        });
+        return !select;
+    }
+    method == Method::PUT || method == Method::DELETE
+}
+
+/// Middleware refusing writes while the gateway is read-only
+///
+/// Without it every write would wait for the pool to time out against the
+/// unreachable primary before failing, or run against a schema that does
+/// not match this binary.
+pub async fn reject_writes_when_read_only(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if is_write_request(request.method(), request.uri().query()) {
+        if !state.preflight().is_open() {
+            return S3Error::service(
+                ErrorCode::ServiceUnavailable,
+                "startup preflight has not passed, gateway is read-only",
+            )
+            .into_response();
+        }
+        if state.is_read_only() {
+            return S3Error::service(
+                ErrorCode::MetadataUnavailable,
+                "metadata primary unavailable, gateway is read-only",
+            )
+            .into_response();
+        }
+    }
+    next.run(request).await
+}
+
+// =============================================================================
+// BUCKET OPERATIONS
+// =============================================================================
+
+/// PUT /:bucket - Create bucket
+#[instrument(skip(state, query, headers, body))]
+async fn create_bucket(
+    State(state): State<Arc<AppState>>,
+    Path(bucket): Path<String>,
+    Query(query): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+    body: String,
+) -> S3Result<Response> {
+    if query.contains_key("logging") {
+        return put_bucket_logging(&state, bucket, &headers, &body).await;
+    }
+    if query.contains_key("object-lock") {
+        return put_bucket_object_lock(&state, bucket, &headers, &body).await;
+    }
+    if query.contains_key("policy") {
+        return put_bucket_policy(&state, bucket, &headers, &body).await;
+    }
+    if query.contains_key("acl") {
+        return put_bucket_acl(&state, bucket, &headers, &body).await;
+    }
+    if query.contains_key("cache-policy") {
+        return put_bucket_cache_policy(&state, bucket, &headers, &body).await;
+    }
+    if query.contains_key("lifecycle") {
+        return put_bucket_lifecycle(&state, bucket, &headers, &body).await;
+    }
+    if query.contains_key("cors") {
+        return put_bucket_cors(&state, bucket, &headers, &body).await;
+    }
+
+    let tenant = request_tenant(&state, &headers, scopes::S3_WRITE).await?;
+    let acl = header_str(&headers, ACL_HEADER)?
+        .map(CannedAcl::parse)
+        .transpose()?;
+    info!(tenant = %tenant, bucket = %bucket, "Creating bucket");
+
+    // Check if bucket exists
+    if state.bucket_exists(&tenant, &bucket).await? {
+        return Err(S3Error::BucketAlreadyExists(bucket));
+    }
+
+    // Create bucket in metadata
+    state.create_bucket(&tenant, &bucket).await?;
+    if header_str(&headers, OBJECT_LOCK_ENABLED_HEADER)?
+        .is_some_and(|v| v.eq_ignore_ascii_case("true"))
+    {
+        state
+            .set_bucket_object_lock(&tenant, &bucket, ObjectLockConfig::default())
+            .await?;
+    }
+    if let Some(acl) = acl.filter(|acl| acl.is_public_read()) {
+        state.set_bucket_acl(&tenant, &bucket, acl).await?;
+    }
+
+    Ok((StatusCode::OK, [(header::LOCATION, format!("/{}", bucket))]).into_response())
+}
+
+/// PUT /:bucket?logging - Enable or disable access logging
+///
+/// The target bucket must belong to the same tenant.
+async fn put_bucket_logging(
+    state: &AppState,
+    bucket: String,
+    headers: &HeaderMap,
+    body: &str,
+) -> S3Result<Response> {
+    let tenant = authorize(
+        state,
+        headers,
+        scopes::S3_WRITE,
+        PolicyRequest::bucket(actions::PUT_BUCKET_LOGGING, &bucket),
+    )
+    .await?;
+    let status = BucketLoggingStatus::from_xml(body)?;
+
+    if !state.bucket_exists(&tenant, &bucket).await? {
+        return Err(S3Error::NoSuchBucket(bucket));
+    }
+    if let Some(ref logging) = status.logging {
+        if !state.bucket_exists(&tenant, &logging.target_bucket).await? {
+            return Err(S3Error::InvalidRequest(format!(
+                "Target bucket {} does not exist",
+                logging.target_bucket
+            )));
+        }
+    }
+
+    info!(
+        tenant = %tenant,
+        bucket = %bucket,
+        target = ?status.logging,
+        "Setting bucket access logging"
+    );
+    state
+        .set_bucket_logging(&tenant, &bucket, status.logging)
+        .await?;
+
+    Ok(StatusCode::OK.into_response())
+}
+
+/// GET /:bucket?logging - Access logging status
+async fn get_bucket_logging(
+    state: &AppState,
+    bucket: String,
+    headers: &HeaderMap,
+) -> S3Result<Response> {
+    let tenant = authorize(
+        state,
+        headers,
+        scopes::S3_READ,
+        PolicyRequest::bucket(actions::GET_BUCKET_LOGGING, &bucket),
+    )
+    .await?;
+    let logging = state.get_bucket_logging(&tenant, &bucket).await?;
 
     Ok((
         StatusCode::OK,
         [(header::CONTENT_TYPE, "application/xml")],
-        response.to_xml(),
-    ))
+        BucketLoggingStatus { logging }.to_xml(),
+    )
+        .into_response())
+}
+
+/// PUT /:bucket?object-lock - Enable object lock and set default retention
+///
+/// Object lock cannot be disabled once enabled; the default retention
+/// applies to objects written afterwards.
+async fn put_bucket_object_lock(
+    state: &AppState,
+    bucket: String,
+    headers: &HeaderMap,
+    body: &str,
+) -> S3Result<Response> {
+    let tenant = authorize(
+        state,
+        headers,
+        scopes::S3_WRITE,
+        PolicyRequest::bucket(actions::PUT_BUCKET_OBJECT_LOCK, &bucket),
+    )
+    .await?;
+    let configuration = ObjectLockConfiguration::from_xml(body)?;
+
+    info!(
+        tenant = %tenant,
+        bucket = %bucket,
+        default_retention = ?configuration.config.default_retention,
+        "Setting bucket object lock"
+    );
+    state
+        .set_bucket_object_lock(&tenant, &bucket, configuration.config)
+        .await?;
+
+    Ok(StatusCode::OK.into_response())
+}
+
+/// GET /:bucket?object-lock - Object lock configuration
+async fn get_bucket_object_lock(
+    state: &AppState,
+    bucket: String,
+    headers: &HeaderMap,
+) -> S3Result<Response> {
+    let tenant = authorize(
+        state,
+        headers,
+        scopes::S3_READ,
+        PolicyRequest::bucket(actions::GET_BUCKET_OBJECT_LOCK, &bucket),
+    )
+    .await?;
+    let config = state
+        .get_bucket_object_lock(&tenant, &bucket)
+        .await?
+        .ok_or_else(|| {
+            S3Error::service(
+                ErrorCode::NotFound,
+                format!("Object lock is not enabled on bucket {}", bucket),
+            )
+        })?;
+
+    Ok((
+        StatusCode::OK,
+        [(header::CONTENT_TYPE, "application/xml")],
+        ObjectLockConfiguration { config }.to_xml(),
+    )
+        .into_response())
+}
+
+/// PUT /:bucket?policy - Set the bucket policy
+///
+/// Managing the policy is only subject to the token's scopes, not to the
+/// policy itself, so a policy cannot lock the bucket's owners out.
+async fn put_bucket_policy(
+    state: &AppState,
+    bucket: String,
+    headers: &HeaderMap,
+    body: &str,
+) -> S3Result<Response> {
+    let tenant = request_tenant(state, headers, scopes::S3_WRITE).await?;
+    let policy = BucketPolicy::parse(&bucket, body)?;
+
+    info!(tenant = %tenant, bucket = %bucket, "Setting bucket policy");
+    state
+        .set_bucket_policy(&tenant, &bucket, Some(policy))
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+/// GET /:bucket?policy - Bucket policy document
+async fn get_bucket_policy(
+    state: &AppState,
+    bucket: String,
+    headers: &HeaderMap,
+) -> S3Result<Response> {
+    let tenant = request_tenant(state, headers, scopes::S3_READ).await?;
+    let policy = state
+        .get_bucket_policy(&tenant, &bucket)
+        .await?
+        .ok_or(S3Error::NoSuchBucketPolicy(bucket))?;
+
+    Ok((
+        StatusCode::OK,
+        [(header::CONTENT_TYPE, "application/json")],
+        policy.document().to_string(),
+    )
+        .into_response())
+}
+
+/// DELETE /:bucket?policy - Remove the bucket policy
+async fn delete_bucket_policy(
+    state: &AppState,
+    bucket: String,
+    headers: &HeaderMap,
+) -> S3Result<Response> {
+    let tenant = request_tenant(state, headers, scopes::S3_WRITE).await?;
+    info!(tenant = %tenant, bucket = %bucket, "Deleting bucket policy");
+    state.set_bucket_policy(&tenant, &bucket, None).await?;
+
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+/// PUT /:bucket?acl - Make a bucket's objects public or private
+async fn put_bucket_acl(
+    state: &AppState,
+    bucket: String,
+    headers: &HeaderMap,
+    body: &str,
+) -> S3Result<Response> {
+    let tenant = authorize(
+        state,
+        headers,
+        scopes::S3_WRITE,
+        PolicyRequest::bucket(actions::PUT_BUCKET_ACL, &bucket),
+    )
+    .await?;
+    let acl = requested_acl(headers, body)?;
+
+    info!(tenant = %tenant, bucket = %bucket, acl = acl.as_str(), "Setting bucket ACL");
+    state.set_bucket_acl(&tenant, &bucket, acl).await?;
+
+    Ok(StatusCode::OK.into_response())
+}
+
+/// GET /:bucket?acl - Bucket ACL
+async fn get_bucket_acl(
+    state: &AppState,
+    bucket: String,
+    headers: &HeaderMap,
+) -> S3Result<Response> {
+    let tenant = authorize(
+        state,
+        headers,
+        scopes::S3_READ,
+        PolicyRequest::bucket(actions::GET_BUCKET_ACL, &bucket),
+    )
+    .await?;
+    let acl = state.get_bucket_acl(&tenant, &bucket).await?;
+
+    Ok((
+        StatusCode::OK,
+        [(header::CONTENT_TYPE, "application/xml")],
+        AccessControlPolicy {
+            owner: tenant,
+            acl: CannedAcl::from_public_read(acl.public_read),
+        }
+        .to_xml(),
+    )
+        .into_response())
+}
+
+/// PUT /:bucket?cache-policy - Set or remove the default Cache-Control
+///
+/// The default is given to objects uploaded afterwards without a
+/// Cache-Control of their own; objects already stored keep theirs.
+async fn put_bucket_cache_policy(
+    state: &AppState,
+    bucket: String,
+    headers: &HeaderMap,
+    body: &str,
+) -> S3Result<Response> {
+    let tenant = authorize(
+        state,
+        headers,
+        scopes::S3_WRITE,
+        PolicyRequest::bucket(actions::PUT_BUCKET_CACHE_POLICY, &bucket),
+    )
+    .await?;
+    let policy = BucketCachePolicy::from_xml(body)?;
+
+    info!(
+        tenant = %tenant,
+        bucket = %bucket,
+        cache_control = ?policy.cache_control,
+        "Setting bucket cache policy"
+    );
+    state
+        .set_bucket_cache_policy(&tenant, &bucket, policy.cache_control)
+        .await?;
+
+    Ok(StatusCode::OK.into_response())
+}
+
+/// GET /:bucket?cache-policy - Default Cache-Control of new objects
+async fn get_bucket_cache_policy(
+    state: &AppState,
+    bucket: String,
+    headers: &HeaderMap,
+) -> S3Result<Response> {
+    let tenant = authorize(
+        state,
+        headers,
+        scopes::S3_READ,
+        PolicyRequest::bucket(actions::GET_BUCKET_CACHE_POLICY, &bucket),
+    )
+    .await?;
+    let cache_control = state.get_bucket_cache_policy(&tenant, &bucket).await?;
+
+    Ok((
+        StatusCode::OK,
+        [(header::CONTENT_TYPE, "application/xml")],
+        BucketCachePolicy { cache_control }.to_xml(),
+    )
+        .into_response())
 }
 
-// =============================================================================
-// OBJECT OPERATIONS
-// =============================================================================
+/// PUT /:bucket?lifecycle - Set the bucket's lifecycle rules
+///
+/// The rules replace any earlier ones; the tiering daemon queues the
+/// transitions of matching objects (see [`crate::tiering`]).
+async fn put_bucket_lifecycle(
+    state: &AppState,
+    bucket: String,
+    headers: &HeaderMap,
+    body: &str,
+) -> S3Result<Response> {
+    let tenant = authorize(
+        state,
+        headers,
+        scopes::S3_WRITE,
+        PolicyRequest::bucket(actions::PUT_LIFECYCLE_CONFIGURATION, &bucket),
+    )
+    .await?;
+    let configuration = BucketLifecycleConfiguration::from_xml(body)?;
+
+    info!(
+        tenant = %tenant,
+        bucket = %bucket,
+        rules = configuration.rules.len(),
+        "Setting bucket lifecycle"
+    );
+    state
+        .set_bucket_lifecycle(&tenant, &bucket, Some(configuration.rules))
+        .await?;
+
+    Ok(StatusCode::OK.into_response())
+}
+
+/// GET /:bucket?lifecycle - Bucket lifecycle rules
+async fn get_bucket_lifecycle(
+    state: &AppState,
+    bucket: String,
+    headers: &HeaderMap,
+) -> S3Result<Response> {
+    let tenant = authorize(
+        state,
+        headers,
+        scopes::S3_READ,
+        PolicyRequest::bucket(actions::GET_LIFECYCLE_CONFIGURATION, &bucket),
+    )
+    .await?;
+    let rules = state.get_bucket_lifecycle(&tenant, &bucket).await?;
+
+    Ok((
+        StatusCode::OK,
+        [(header::CONTENT_TYPE, "application/xml")],
+        BucketLifecycleConfiguration { rules }.to_xml(),
+    )
+        .into_response())
+}
+
+/// DELETE /:bucket?lifecycle - Remove the bucket's lifecycle rules
+///
+/// Transitions already queued still run.
+async fn delete_bucket_lifecycle(
+    state: &AppState,
+    bucket: String,
+    headers: &HeaderMap,
+) -> S3Result<Response> {
+    let tenant = authorize(
+        state,
+        headers,
+        scopes::S3_WRITE,
+        PolicyRequest::bucket(actions::PUT_LIFECYCLE_CONFIGURATION, &bucket),
+    )
+    .await?;
+    info!(tenant = %tenant, bucket = %bucket, "Deleting bucket lifecycle");
+    state.set_bucket_lifecycle(&tenant, &bucket, None).await?;
+
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+/// PUT /:bucket?cors - Set the bucket's CORS rules
+///
+/// The rules replace any earlier ones.
+async fn put_bucket_cors(
+    state: &AppState,
+    bucket: String,
+    headers: &HeaderMap,
+    body: &str,
+) -> S3Result<Response> {
+    let tenant = authorize(
+        state,
+        headers,
+        scopes::S3_WRITE,
+        PolicyRequest::bucket(actions::PUT_BUCKET_CORS, &bucket),
+    )
+    .await?;
+    let configuration = BucketCorsConfiguration::from_xml(body)?;
+
+    info!(
+        tenant = %tenant,
+        bucket = %bucket,
+        rules = configuration.rules.len(),
+        "Setting bucket CORS"
+    );
+    state
+        .set_bucket_cors(&tenant, &bucket, Some(configuration.rules))
+        .await?;
+
+    Ok(StatusCode::OK.into_response())
+}
+
+/// GET /:bucket?cors - Bucket CORS rules
+async fn get_bucket_cors(
+    state: &AppState,
+    bucket: String,
+    headers: &HeaderMap,
+) -> S3Result<Response> {
+    let tenant = authorize(
+        state,
+        headers,
+        scopes::S3_READ,
+        PolicyRequest::bucket(actions::GET_BUCKET_CORS, &bucket),
+    )
+    .await?;
+    let rules = state.get_bucket_cors(&tenant, &bucket).await?;
+
+    Ok((
+        StatusCode::OK,
+        [(header::CONTENT_TYPE, "application/xml")],
+        BucketCorsConfiguration { rules }.to_xml(),
+    )
+        .into_response())
+}
+
+/// DELETE /:bucket?cors - Remove the bucket's CORS rules
+///
+/// The bucket then falls back to the gateway-wide CORS settings.
+async fn delete_bucket_cors(
+    state: &AppState,
+    bucket: String,
+    headers: &HeaderMap,
+) -> S3Result<Response> {
+    let tenant = authorize(
+        state,
+        headers,
+        scopes::S3_WRITE,
+        PolicyRequest::bucket(actions::PUT_BUCKET_CORS, &bucket),
+    )
+    .await?;
+    info!(tenant = %tenant, bucket = %bucket, "Deleting bucket CORS");
+    state.set_bucket_cors(&tenant, &bucket, None).await?;
+
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+/// DELETE /:bucket - Delete bucket
+///
+/// `?policy` removes the bucket policy instead, `?lifecycle` the lifecycle
+/// rules and `?cors` the CORS rules.
+#[instrument(skip(state, query, headers))]
+async fn delete_bucket(
+    State(state): State<Arc<AppState>>,
+    Path(bucket): Path<String>,
+    Query(query): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+) -> S3Result<Response> {
+    if query.contains_key("policy") {
+        return delete_bucket_policy(&state, bucket, &headers).await;
+    }
+    if query.contains_key("lifecycle") {
+        return delete_bucket_lifecycle(&state, bucket, &headers).await;
+    }
+    if query.contains_key("cors") {
+        return delete_bucket_cors(&state, bucket, &headers).await;
+    }
+
+    let tenant = authorize(
+        &state,
+        &headers,
+        scopes::S3_WRITE,
+        PolicyRequest::bucket(actions::DELETE_BUCKET, &bucket),
+    )
+    .await?;
+    info!(tenant = %tenant, bucket = %bucket, "Deleting bucket");
+
+    // Check if bucket exists
+    if !state.bucket_exists(&tenant, &bucket).await? {
+        return Err(S3Error::NoSuchBucket(bucket));
+    }
+
+    // Check if bucket is empty
+    if !state.bucket_is_empty(&tenant, &bucket).await? {
+        return Err(S3Error::BucketNotEmpty(bucket));
+    }
+
+    // Delete bucket
+    state.delete_bucket(&tenant, &bucket).await?;
+
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+/// HEAD /:bucket - Check if bucket exists
+#[instrument(skip(state, headers))]
+async fn head_bucket(
+    State(state): State<Arc<AppState>>,
+    Path(bucket): Path<String>,
+    headers: HeaderMap,
+) -> S3Result<impl IntoResponse> {
+    let tenant = authorize(
+        &state,
+        &headers,
+        scopes::S3_READ,
+        PolicyRequest::bucket(actions::LIST_BUCKET, &bucket),
+    )
+    .await?;
+    debug!(tenant = %tenant, bucket = %bucket, "Checking bucket");
+
+    if !state.bucket_exists(&tenant, &bucket).await? {
+        return Err(S3Error::NoSuchBucket(bucket));
+    }
+
+    Ok(StatusCode::OK)
+}
+
+/// GET /:bucket - List objects in bucket
+#[instrument(skip(state, headers))]
+async fn list_objects(
+    State(state): State<Arc<AppState>>,
+    Path(bucket): Path<String>,
+    Query(query): Query<ListObjectsQuery>,
+    headers: HeaderMap,
+) -> S3Result<Response> {
+    if query.logging.is_some() {
+        return get_bucket_logging(&state, bucket, &headers).await;
+    }
+    if query.object_lock.is_some() {
+        return get_bucket_object_lock(&state, bucket, &headers).await;
+    }
+    if query.policy.is_some() {
+        return get_bucket_policy(&state, bucket, &headers).await;
+    }
+    if query.acl.is_some() {
+        return get_bucket_acl(&state, bucket, &headers).await;
+    }
+    if query.cache_policy.is_some() {
+        return get_bucket_cache_policy(&state, bucket, &headers).await;
+    }
+    if query.lifecycle.is_some() {
+        return get_bucket_lifecycle(&state, bucket, &headers).await;
+    }
+    if query.cors.is_some() {
+        return get_bucket_cors(&state, bucket, &headers).await;
+    }
+    if query.deleted.is_some() {
+        return list_deleted_objects(&state, bucket, query, &headers).await;
+    }
+    if let Some(format) = query.export.as_deref() {
+        let format: ExportFormat = format.parse()?;
+        return export_bucket(state, bucket, format, query.prefix, &headers).await;
+    }
+
+    let tenant = authorize(
+        &state,
+        &headers,
+        scopes::S3_READ,
+        PolicyRequest::listing(&bucket, query.prefix.as_deref()),
+    )
+    .await?;
+    debug!(tenant = %tenant, bucket = %bucket, prefix = ?query.prefix, "Listing objects");
+
+    if !state.bucket_exists(&tenant, &bucket).await? {
+        return Err(S3Error::NoSuchBucket(bucket));
+    }
+
+    let max_keys = query.max_keys.unwrap_or(1000).min(1000);
+    let prefix = query.prefix.clone().unwrap_or_default();
+    let delimiter = query.delimiter.clone();
+
+    // Get objects from metadata
+    let (objects, is_truncated, next_token) = state
+        .list_objects(
+            &tenant,
+            &bucket,
+            &prefix,
+            delimiter.as_deref(),
+            max_keys,
+            query.continuation_token.as_deref(),
+            query.start_after.as_deref(),
+        )
+        .await?;
+
+    // Build response
+    let response = ListObjectsV2Response {
+        name: bucket,
+        prefix: Some(prefix),
+        max_keys,
+        is_truncated,
+        key_count: objects.len() as i32,
+        contents: objects,
+        common_prefixes: Vec::new(), // TODO: Implement delimiter handling
+        continuation_token: query.continuation_token,
+        next_continuation_token: next_token,
+    };
+
+    Ok((
+        StatusCode::OK,
+        [(header::CONTENT_TYPE, "application/xml")],
+        response.to_xml(),
+    )
+        .into_response())
+}
+
+/// GET /:bucket?deleted - List restorable deleted objects
+async fn list_deleted_objects(
+    state: &AppState,
+    bucket: String,
+    query: ListObjectsQuery,
+    headers: &HeaderMap,
+) -> S3Result<Response> {
+    let tenant = authorize(
+        state,
+        headers,
+        scopes::S3_READ,
+        PolicyRequest::listing(&bucket, query.prefix.as_deref()),
+    )
+    .await?;
+    if !state.bucket_exists(&tenant, &bucket).await? {
+        return Err(S3Error::NoSuchBucket(bucket));
+    }
+
+    let max_keys = query.max_keys.unwrap_or(1000).clamp(0, 1000);
+    let prefix = query.prefix.unwrap_or_default();
+    let (objects, is_truncated) = state
+        .list_deleted_objects(&tenant, &bucket, &prefix, max_keys)
+        .await?;
+
+    let response = ListDeletedObjectsResponse {
+        name: bucket,
+        prefix,
+        max_keys,
+        is_truncated,
+        objects,
+    };
+
+    Ok((
+        StatusCode::OK,
+        [(header::CONTENT_TYPE, "application/xml")],
+        response.to_xml(),
+    )
+        .into_response())
+}
+
+/// GET /:bucket?export=tar - Stream the bucket as an archive
+///
+/// Objects are read window by window as the archive is sent, so the gateway
+/// holds one window at a time rather than the bucket. Objects the bucket
+/// policy does not let the requester read are left out.
+async fn export_bucket(
+    state: Arc<AppState>,
+    bucket: String,
+    format: ExportFormat,
+    prefix: Option<String>,
+    headers: &HeaderMap,
+) -> S3Result<Response> {
+    let access = BucketAccess::new(&state, headers, &bucket).await?;
+    if !access.permits(
+        scopes::S3_READ,
+        PolicyRequest::listing(&bucket, prefix.as_deref()),
+    ) {
+        return Err(S3Error::AccessDenied);
+    }
+    if !state.bucket_exists(&access.tenant, &bucket).await? {
+        return Err(S3Error::NoSuchBucket(bucket));
+    }
+
+    let prefix = prefix.unwrap_or_default();
+    info!(tenant = %access.tenant, bucket = %bucket, prefix = %prefix, "Exporting bucket");
+
+    let disposition = format!("attachment; filename=\"{}.{}\"", bucket, format.extension());
+    let (tx, rx) = mpsc::channel(4);
+    tokio::spawn(run_export(state, access, bucket, prefix, tx));
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, format.content_type())
+        .header(header::CONTENT_DISPOSITION, disposition)
+        .body(Body::from_stream(ReceiverStream::new(rx)))
+        .map_err(|e| S3Error::Internal(e.to_string()))
+}
+
+/// Write a bucket export to `tx`
+///
+/// Failures after the response has started abort the body stream, so the
+/// client never gets an archive without its manifest and trailer.
+async fn run_export(
+    state: Arc<AppState>,
+    access: BucketAccess,
+    bucket: String,
+    prefix: String,
+    tx: mpsc::Sender<std::io::Result<Bytes>>,
+) {
+    match write_export(&state, &access, &bucket, &prefix, &tx).await {
+        Ok(Some(manifest)) => info!(
+            bucket = %bucket,
+            objects = manifest.object_count,
+            bytes = manifest.total_bytes,
+            "Bucket export complete"
+        ),
+        Ok(None) => debug!(bucket = %bucket, "Client disconnected during export"),
+        Err(e) => {
+            error!(bucket = %bucket, error = %e, "Bucket export failed");
+            let fail = std::io::Error::new(std::io::ErrorKind::Other, e.to_string());
+            let _ = tx.send(Err(fail)).await;
+        }
+    }
+}
+
+/// Send every object under `prefix` as a tar entry, then the manifest
+///
+/// Returns None if the client went away.
+async fn write_export(
+    state: &AppState,
+    access: &BucketAccess,
+    bucket: &str,
+    prefix: &str,
+    tx: &mpsc::Sender<std::io::Result<Bytes>>,
+) -> S3Result<Option<ExportManifest>> {
+    let send = |data: Bytes| async move { tx.send(Ok(data)).await.is_ok() };
+    let tenant = access.tenant.as_str();
+
+    let mut manifest = ExportManifest::new(bucket, prefix);
+    let mut token = None;
+    loop {
+        let (objects, is_truncated, next_token) = state
+            .list_objects(
+                tenant,
+                bucket,
+                prefix,
+                None,
+                EXPORT_PAGE_KEYS,
+                token.as_deref(),
+                None,
+            )
+            .await?;
+
+        for object in objects {
+            let readable = PolicyRequest::object(actions::GET_OBJECT, bucket, &object.key);
+            if !access.permits(scopes::S3_READ, readable) {
+                continue;
+            }
+            // Deleted since it was listed
+            let Some(metadata) = state
+                .get_object_metadata(tenant, bucket, &object.key)
+                .await?
+            else {
+                continue;
+            };
+
+            let path = format!("{}/{}", bucket, metadata.key);
+            let mtime = metadata.last_modified_at().map_or(0, |t| t.timestamp());
+            let header = export::entry_header(&path, metadata.size, mtime);
+            if !send(Bytes::from(header)).await {
+                return Ok(None);
+            }
+
+            let mut hasher = blake3::Hasher::new();
+            let mut offset = 0;
+            while offset < metadata.size {
+                let end = (offset + EXPORT_WINDOW).min(metadata.size) - 1;
+                let data = state
+                    .get_object_range(tenant, bucket, &metadata.key, offset, end)
+                    .await?;
+                // The header has gone out, so the size can't change now
+                if data.len() as u64 != end - offset + 1 {
+                    return Err(S3Error::Internal(format!(
+                        "{} changed while it was being exported",
+                        metadata.key
+                    )));
+                }
+                hasher.update(&data);
+                offset = end + 1;
+                if !send(data).await {
+                    return Ok(None);
+                }
+            }
+            let padding = export::padding(metadata.size);
+            if padding > 0 && !send(Bytes::from(vec![0; padding])).await {
+                return Ok(None);
+            }
+
+            manifest.push(ManifestEntry {
+                key: metadata.key,
+                size: metadata.size,
+                etag: metadata.etag,
+                blake3: hasher.finalize().to_hex().to_string(),
+            });
+        }
+
+        match next_token {
+            Some(next) if is_truncated => token = Some(next),
+            _ => break,
+        }
+    }
+
+    let json = manifest.to_json();
+    let mut tail = export::entry_header(
+        export::MANIFEST_PATH,
+        json.len() as u64,
+        chrono::Utc::now().timestamp(),
+    );
+    tail.extend_from_slice(&json);
+    tail.resize(tail.len() + export::padding(json.len() as u64), 0);
+    tail.extend_from_slice(&export::trailer());
+    if !send(Bytes::from(tail)).await {
+        return Ok(None);
+    }
+    Ok(Some(manifest))
+}
+
+/// POST /:bucket?delete - Delete multiple objects
+///
+/// Valid keys are deleted in one batch; each key gets a `Deleted` or `Error`
+/// entry. Missing keys count as deleted, like single-object DELETE. Keys
+/// the bucket policy does not let the requester delete get `AccessDenied`.
+#[instrument(skip(state, query, headers, body))]
+async fn delete_objects(
+    State(state): State<Arc<AppState>>,
+    Path(bucket): Path<String>,
+    Query(query): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+    body: String,
+) -> S3Result<impl IntoResponse> {
+    if !query.contains_key("delete") {
+        return Err(S3Error::InvalidRequest(
+            "Unsupported POST operation on bucket".to_string(),
+        ));
+    }
+
+    let access = BucketAccess::new(&state, &headers, &bucket).await?;
+    // Without a policy the token's scope decides for all keys at once
+    let any_key = PolicyRequest::bucket(actions::DELETE_OBJECT, &bucket);
+    if access.policy.is_none() && !access.permits(scopes::S3_WRITE, any_key) {
+        return Err(S3Error::AccessDenied);
+    }
+    let tenant = access.tenant.as_str();
+    if !state.bucket_exists(tenant, &bucket).await? {
+        return Err(S3Error::NoSuchBucket(bucket));
+    }
+
+    let request = DeleteObjectsRequest::from_xml(&body)?;
+    info!(tenant = %tenant, bucket = %bucket, keys = request.keys.len(), "Deleting objects");
+
+    let mut errors = Vec::new();
+    let mut keys = Vec::with_capacity(request.keys.len());
+    for key in request.keys {
+        if let Err(e) = validate_object_key(&key) {
+            errors.push(DeleteError {
+                key,
+                code: AwsErrorCode::InvalidArgument,
+                message: e.to_string(),
+            });
+            continue;
+        }
+        let deletion = PolicyRequest::object(actions::DELETE_OBJECT, &bucket, &key);
+        if !access.permits(scopes::S3_WRITE, deletion) {
+            errors.push(DeleteError {
+                key,
+                code: AwsErrorCode::AccessDenied,
+                message: "Access Denied".to_string(),
+            });
+            continue;
+        }
+        keys.push(key);
+    }
+
+    let bypass_governance = bypass_governance(&headers)?;
+    let mut deleted = Vec::new();
+    if !keys.is_empty() {
+        match state
+            .delete_objects(tenant, &bucket, &keys, bypass_governance)
+            .await
+        {
+            Ok(output) => {
+                errors.extend(output.locked.iter().map(|key| DeleteError {
+                    key: key.clone(),
+                    code: AwsErrorCode::AccessDenied,
+                    message: "Object is under retention".to_string(),
+                }));
+                deleted = keys
+                    .into_iter()
+                    .filter(|key| !output.locked.contains(key))
+                    .collect();
+            }
+            Err(e) => {
+                error!(error = %e, bucket = %bucket, "Multi-object delete failed");
+                errors.extend(keys.into_iter().map(|key| DeleteError {
+                    key,
+                    code: AwsErrorCode::InternalError,
+                    message: e.to_string(),
+                }));
+            }
+        }
+    }
+
+    let result = DeleteObjectsResult {
+        quiet: request.quiet,
+        deleted,
+        errors,
+    };
+
+    Ok((
+        StatusCode::OK,
+        [(header::CONTENT_TYPE, "application/xml")],
+        result.to_xml(),
+    ))
+}
+
+// =============================================================================
+// OBJECT OPERATIONS
+// =============================================================================
+
+/// PUT /:bucket/*key - Upload object
+///
+/// The body is read as a stream under the upload limits (see
+/// [`crate::upload_limits`]). Plain uploads are stored stripe by stripe as
+/// the body arrives; conditional and idempotent ones are buffered, since
+/// their checks need the whole body before anything is written.
+/// `?retention` sets the object's retention instead.
+#[instrument(skip(state, query, headers, body))]
+async fn put_object(
+    State(state): State<Arc<AppState>>,
+    Path((bucket, key)): Path<(String, String)>,
+    Query(query): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+    body: Body,
+) -> S3Result<impl IntoResponse> {
+    validate_object_key(&key)?;
+    if query.contains_key("retention") {
+        return put_object_retention(&state, bucket, key, &headers, body).await;
+    }
+    if query.contains_key("acl") {
+        return put_object_acl(&state, bucket, key, &headers, body).await;
+    }
+    let limits = state.upload_limits();
+    if let Err(e) = limits.check_content_length(&headers) {
+        crate::metrics::record_upload_rejected("too_large");
+        return Err(e);
+    }
+    let tenant = authorize(
+        &state,
+        &headers,
+        scopes::S3_WRITE,
+        PolicyRequest::object(actions::PUT_OBJECT, &bucket, &key),
+    )
+    .await?;
+    // A new version starts out private
+    let public_read = header_str(&headers, ACL_HEADER)?
+        .map(CannedAcl::parse)
+        .transpose()?
+        .filter(|acl| acl.is_public_read());
+    if let Some(source) = header_str(&headers, COPY_SOURCE_HEADER)? {
+        let response = copy_object(&state, &tenant, &bucket, &key, source, &headers).await?;
+        if let Some(acl) = public_read {
+            state.set_object_acl(&tenant, &bucket, &key, acl).await?;
+        }
+        return Ok(response);
+    }
+    let content_length = header_str(&headers, header::CONTENT_LENGTH.as_str())?;
+    info!(
+        tenant = %tenant,
+        bucket = %bucket,
+        key = %key,
+        size = content_length.unwrap_or("-"),
+        "Uploading object"
+    );
+
+    // Validate bucket exists
+    if !state.bucket_exists(&tenant, &bucket).await? {
+        return Err(S3Error::NoSuchBucket(bucket));
+    }
+
+    // Get content type from headers
+    let content_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("application/octet-stream")
+        .to_string();
+
+    let expires_at = parse_expiration(&headers, chrono::Utc::now())?;
+    let user_metadata = parse_user_metadata(&headers)?;
+    let content_headers = ContentHeaders::from_headers(&headers)?;
+    let idempotency_key = parse_idempotency_key(&headers)?;
+    let sse = parse_sse(&headers)?;
+    let storage_class = parse_storage_class(&headers)?;
+    if storage_class != StorageClass::Standard && state.metadata_service().is_none() {
+        return Err(S3Error::NotImplemented(
+            "Storage classes need a metadata service".to_string(),
+        ));
+    }
+
+    let body = limits.limit(body.into_data_stream());
+    let output = if has_preconditions(&headers) || idempotency_key.is_some() {
+        // A conditional overwrite is checked against the object currently
+        // stored while the key's write lock is held
+        let data = collect_body(body).await.inspect_err(record_rejection)?;
+        state
+            .put_object_if(
+                &tenant,
+                &bucket,
+                &key,
+                data,
+                &content_type,
+                expires_at,
+                &user_metadata,
+                &content_headers,
+                sse,
+                idempotency_key,
+                |current| {
+                    if has_preconditions(&headers) {
+                        evaluate_preconditions(&headers, current, false)?;
+                    }
+                    Ok(())
+                },
+            )
+            .await?
+    } else {
+        let body = futures::StreamExt::inspect(body, |piece| {
+            if let Err(e) = piece {
+                record_rejection(e);
+            }
+        });
+        state
+            .put_object_stream(
+                &tenant,
+                &bucket,
+                &key,
+                body,
+                &content_type,
+                expires_at,
+                &user_metadata,
+                &content_headers,
+                sse,
+            )
+            .await?
+    };
+    if let Some(acl) = public_read {
+        state.set_object_acl(&tenant, &bucket, &key, acl).await?;
+    }
+    // The object is stored as STANDARD and re-encoded by the tiering daemon
+    if storage_class != StorageClass::Standard && !output.replayed {
+        state
+            .transition_object(&tenant, &bucket, &key, storage_class)
+            .await?;
+    }
+
+    let mut response = Response::builder()
+        .status(StatusCode::OK)
+        .header(header::ETAG, format!("\"{}\"", output.etag));
+    if output.replayed {
+        response = response.header(IDEMPOTENT_REPLAY_HEADER, "true");
+    }
+    response = with_encryption(response, output.encryption);
+    response
+        .body(Body::empty())
+        .map_err(|e| S3Error::Internal(e.to_string()))
+}
+
+/// PUT /:bucket/*key?retention - Set or extend an object's retention
+async fn put_object_retention(
+    state: &AppState,
+    bucket: String,
+    key: String,
+    headers: &HeaderMap,
+    body: Body,
+) -> S3Result<Response> {
+    let tenant = authorize(
+        state,
+        headers,
+        scopes::S3_WRITE,
+        PolicyRequest::object(actions::PUT_OBJECT_RETENTION, &bucket, &key),
+    )
+    .await?;
+    let body = axum::body::to_bytes(body, MAX_RETENTION_BODY)
+        .await
+        .map_err(|e| S3Error::InvalidRequest(format!("Failed to read request body: {}", e)))?;
+    let request = Retention::from_xml(&String::from_utf8_lossy(&body))?;
+
+    if !state.bucket_exists(&tenant, &bucket).await? {
+        return Err(S3Error::NoSuchBucket(bucket));
+    }
+    info!(
+        tenant = %tenant,
+        bucket = %bucket,
+        key = %key,
+        retention = ?request.retention,
+        "Setting object retention"
+    );
+    state
+        .set_object_retention(&tenant, &bucket, &key, request.retention)
+        .await?;
+
+    Ok(StatusCode::OK.into_response())
+}
+
+/// PUT /:bucket/*key?acl - Make an object public or private
+async fn put_object_acl(
+    state: &AppState,
+    bucket: String,
+    key: String,
+    headers: &HeaderMap,
+    body: Body,
+) -> S3Result<Response> {
+    let tenant = authorize(
+        state,
+        headers,
+        scopes::S3_WRITE,
+        PolicyRequest::object(actions::PUT_OBJECT_ACL, &bucket, &key),
+    )
+    .await?;
+    let body = axum::body::to_bytes(body, MAX_ACL_BODY)
+        .await
+        .map_err(|e| S3Error::InvalidRequest(format!("Failed to read request body: {}", e)))?;
+    let acl = requested_acl(headers, &String::from_utf8_lossy(&body))?;
+
+    info!(
+        tenant = %tenant,
+        bucket = %bucket,
+        key = %key,
+        acl = acl.as_str(),
+        "Setting object ACL"
+    );
+    state.set_object_acl(&tenant, &bucket, &key, acl).await?;
+
+    Ok(StatusCode::OK.into_response())
+}
+
+/// GET /:bucket/*key?acl - Object ACL
+async fn get_object_acl(
+    state: &AppState,
+    bucket: String,
+    key: String,
+    headers: &HeaderMap,
+) -> S3Result<Response> {
+    let tenant = authorize(
+        state,
+        headers,
+        scopes::S3_READ,
+        PolicyRequest::object(actions::GET_OBJECT_ACL, &bucket, &key),
+    )
+    .await?;
+    let metadata = state
+        .get_object_metadata(&tenant, &bucket, &key)
+        .await?
+        .ok_or(S3Error::NoSuchKey(key))?;
+
+    Ok((
+        StatusCode::OK,
+        [(header::CONTENT_TYPE, "application/xml")],
+        AccessControlPolicy {
+            owner: tenant,
+            acl: CannedAcl::from_public_read(metadata.public_read),
+        }
+        .to_xml(),
+    )
+        .into_response())
+}
+
+/// GET /:bucket/*key?retention - Retention of an object
+async fn get_object_retention(
+    state: &AppState,
+    bucket: String,
+    key: String,
+    headers: &HeaderMap,
+) -> S3Result<Response> {
+    let tenant = authorize(
+        state,
+        headers,
+        scopes::S3_READ,
+        PolicyRequest::object(actions::GET_OBJECT_RETENTION, &bucket, &key),
+    )
+    .await?;
+    if !state.bucket_exists(&tenant, &bucket).await? {
+        return Err(S3Error::NoSuchBucket(bucket));
+    }
+    let retention = state
+        .get_object_metadata(&tenant, &bucket, &key)
+        .await?
+        .ok_or_else(|| S3Error::NoSuchKey(key.clone()))?
+        .retention
+        .ok_or_else(|| {
+            S3Error::service(
+                ErrorCode::NotFound,
+                format!("{} has no retention configuration", key),
+            )
+        })?;
+
+    Ok((
+        StatusCode::OK,
+        [(header::CONTENT_TYPE, "application/xml")],
+        Retention { retention }.to_xml(),
+    )
+        .into_response())
+}
+
+/// Read a whole (limited) request body into memory
+async fn collect_body<S>(mut body: S) -> S3Result<Bytes>
+where
+    S: futures::Stream<Item = S3Result<Bytes>> + Unpin,
+{
+    let mut data = bytes::BytesMut::new();
+    while let Some(piece) = body.next().await {
+        data.extend_from_slice(&piece?);
+    }
+    Ok(data.freeze())
+}
+
+/// Count an upload cut off by the upload limits
+fn record_rejection(e: &S3Error) {
+    match e {
+        S3Error::EntityTooLarge { .. } => crate::metrics::record_upload_rejected("too_large"),
+        S3Error::RequestTimeout(_) => crate::metrics::record_upload_rejected("timeout"),
+        _ => {}
+    }
+}
+
+/// PUT /:bucket/*key with `x-amz-copy-source` - Copy an object
+///
+/// The copy keeps the source's content type, content headers and user
+/// metadata unless the metadata directive is `REPLACE`; copying an object onto itself is only
+/// allowed when replacing them. Expiry, encryption and conditional headers
+/// apply to the destination, as on an upload. The requester must be allowed
+/// to read the source as well as write the destination.
+async fn copy_object(
+    state: &AppState,
+    tenant: &str,
+    bucket: &str,
+    key: &str,
+    source: &str,
+    headers: &HeaderMap,
+) -> S3Result<Response> {
+    let (source_bucket, source_key) = parse_copy_source(source)?;
+    validate_object_key(&source_key)?;
+    let replace = match header_str(headers, METADATA_DIRECTIVE_HEADER)? {
+        None | Some("COPY") => false,
+        Some("REPLACE") => true,
+        Some(other) => {
+            return Err(S3Error::InvalidRequest(format!(
+                "{} must be COPY or REPLACE, not {}",
+                METADATA_DIRECTIVE_HEADER, other
+            )))
+        }
+    };
+    if !replace && source_bucket == bucket && source_key == key {
+        return Err(S3Error::InvalidRequest(
+            "Copying an object onto itself requires replacing its metadata".to_string(),
+        ));
+    }
+    info!(
+        tenant = %tenant,
+        source_bucket = %source_bucket,
+        source_key = %source_key,
+        bucket = %bucket,
+        key = %key,
+        replace,
+        "Copying object"
+    );
+
+    authorize(
+        state,
+        headers,
+        scopes::S3_READ,
+        PolicyRequest::object(actions::GET_OBJECT, &source_bucket, &source_key),
+    )
+    .await?;
+    for name in [source_bucket.as_str(), bucket] {
+        if !state.bucket_exists(tenant, name).await? {
+            return Err(S3Error::NoSuchBucket(name.to_string()));
+        }
+    }
+    let source = state
+        .get_object_metadata(tenant, &source_bucket, &source_key)
+        .await?
+        .ok_or_else(|| S3Error::NoSuchKey(source_key.clone()))?;
+
+    let (content_type, user_metadata, content_headers) = if replace {
+        let content_type = header_str(headers, header::CONTENT_TYPE.as_str())?
+            .unwrap_or("application/octet-stream")
+            .to_string();
+        (
+            content_type,
+            parse_user_metadata(headers)?,
+            ContentHeaders::from_headers(headers)?,
+        )
+    } else {
+        (
+            source.content_type,
+            source.user_metadata,
+            source.content_headers,
+        )
+    };
+    let expires_at = parse_expiration(headers, chrono::Utc::now())?;
+    let sse = parse_sse(headers)?;
+    let data = state
+        .get_object(tenant, &source_bucket, &source_key)
+        .await?;
+
+    let output = state
+        .put_object_if(
+            tenant,
+            bucket,
+            key,
+            data,
+            &content_type,
+            expires_at,
+            &user_metadata,
+            &content_headers,
+            sse,
+            None,
+            |current| {
+                if has_preconditions(headers) {
+                    evaluate_preconditions(headers, current, false)?;
+                }
+                Ok(())
+            },
+        )
+        .await?;
+
+    let result = CopyObjectResult {
+        etag: output.etag,
+        last_modified: chrono::Utc::now().to_rfc3339(),
+    };
+    let response = Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "application/xml");
+    with_encryption(response, output.encryption)
+        .body(Body::from(result.to_xml()))
+        .map_err(|e| S3Error::Internal(e.to_string()))
+}
+
+/// GET /:bucket/*key - Download object
+///
+/// `?retention` and `?acl` return the object's retention or ACL instead.
+#[instrument(skip(state, query, headers))]
+async fn get_object(
+    State(state): State<Arc<AppState>>,
+    Path((bucket, key)): Path<(String, String)>,
+    Query(query): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+) -> S3Result<Response> {
+    validate_object_key(&key)?;
+    if query.contains_key("retention") {
+        return get_object_retention(&state, bucket, key, &headers).await;
+    }
+    if query.contains_key("acl") {
+        return get_object_acl(&state, bucket, key, &headers).await;
+    }
+    let tenant = authorize(
+        &state,
+        &headers,
+        scopes::S3_READ,
+        PolicyRequest::object(actions::GET_OBJECT, &bucket, &key),
+    )
+    .await?;
+    debug!(tenant = %tenant, bucket = %bucket, key = %key, "Getting object");
+
+    // Validate bucket exists
+    if !state.bucket_exists(&tenant, &bucket).await? {
+        return Err(S3Error::NoSuchBucket(bucket));
+    }
+
+    // Get object metadata
+    let metadata = state
+        .get_object_metadata(&tenant, &bucket, &key)
+        .await?
+        .ok_or_else(|| S3Error::NoSuchKey(key.clone()))?;
+
+    object_response(&state, &tenant, &bucket, &key, &headers, metadata).await
+}
+
+/// Response to a GET of an object: its data, or the part of it a `Range`
+/// header asks for, unless the preconditions stop it
+async fn object_response(
+    state: &AppState,
+    tenant: &str,
+    bucket: &str,
+    key: &str,
+    headers: &HeaderMap,
+    metadata: ObjectMetadata,
+) -> S3Result<Response> {
+    if evaluate_preconditions(headers, Some(&metadata), true)? == Precondition::NotModified {
+        return not_modified(&metadata);
+    }
+
+    // Check for Range header
+    let range = headers
+        .get(header::RANGE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|s| parse_range_header(s, metadata.size));
+
+    // Get object data
+    let (data, status) = if let Some((start, end)) = range {
+        let partial = state
+            .get_object_range(tenant, bucket, key, start, end)
+            .await?;
+        (partial, StatusCode::PARTIAL_CONTENT)
+    } else {
+        let full = state.get_object(tenant, bucket, key).await?;
+        (full, StatusCode::OK)
+    };
+    state.heat_tracker().record(tenant, bucket, key);
+
+    let mut response = Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, &metadata.content_type)
+        .header(header::CONTENT_LENGTH, data.len())
+        .header(header::ETAG, format!("\"{}\"", metadata.etag))
+        .header(header::LAST_MODIFIED, last_modified_header_value(&metadata));
+
+    if let Some(expires_at) = metadata.expires_at {
+        response = response.header(EXPIRATION_HEADER, expiration_header_value(expires_at));
+    }
+    response = with_content_headers(response, &metadata.content_headers);
+    response = with_user_metadata(response, &metadata.user_metadata);
+    response = with_object_lock(response, metadata.retention);
+    response = with_encryption(response, metadata.encryption);
+    response = with_storage_class(response, &metadata);
+
+    if let Some((start, end)) = range {
+        response = response.header(
+            header::CONTENT_RANGE,
+            format!("bytes {}-{}/{}", start, end, metadata.size),
+        );
+    }
+
+    response
+        .body(Body::from(data))
+        .map_err(|e| S3Error::Internal(e.to_string()))
+}
+
+/// POST /:bucket/*key?select - Filter a CSV/JSON object server-side
+///
+/// The object is decoded one window at a time and only matching records
+/// are streamed back, so the full object never leaves the gateway.
+/// `?restore` restores the object from the trash instead, or with a
+/// `RestoreRequest` body, requests a restored copy of an archived object.
+#[instrument(skip(state, query, headers, body))]
+async fn post_object(
+    State(state): State<Arc<AppState>>,
+    Path((bucket, key)): Path<(String, String)>,
+    Query(query): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+    body: String,
+) -> S3Result<Response> {
+    validate_object_key(&key)?;
+    if query.contains_key("restore") {
+        if !body.trim().is_empty() {
+            return restore_archived_object(&state, bucket, key, &headers, &body).await;
+        }
+        return restore_object(&state, bucket, key, &query, &headers).await;
+    }
+    if !query.contains_key("select") {
+        return Err(S3Error::InvalidRequest(
+            "Unsupported POST operation on object".to_string(),
+        ));
+    }
+
+    let tenant = authorize(
+        &state,
+        &headers,
+        scopes::S3_READ,
+        PolicyRequest::object(actions::GET_OBJECT, &bucket, &key),
+    )
+    .await?;
+    if !state.bucket_exists(&tenant, &bucket).await? {
+        return Err(S3Error::NoSuchBucket(bucket));
+    }
+    let metadata = state
+        .get_object_metadata(&tenant, &bucket, &key)
+        .await?
+        .ok_or_else(|| S3Error::NoSuchKey(key.clone()))?;
+
+    let request = SelectRequest::from_xml(&body)?;
+    info!(bucket = %bucket, key = %key, size = metadata.size, "Selecting from object");
+
+    let processor = SelectProcessor::new(request);
+    let content_type = processor.output_format().content_type();
+    let (tx, rx) = mpsc::channel(4);
+    tokio::spawn(run_select(
+        state,
+        tenant,
+        bucket,
+        key,
+        metadata.size,
+        processor,
+        tx,
+    ));
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, content_type)
+        .body(Body::from_stream(ReceiverStream::new(rx)))
+        .map_err(|e| S3Error::Internal(e.to_string()))
+}
+
+/// POST /:bucket/*key?restore - Restore a deleted object
+///
+/// Restores the version given as `versionId` (from a `?deleted` listing),
+/// or else the most recently deleted one. Fails with 409 if the key has a
+/// current version.
+async fn restore_object(
+    state: &AppState,
+    bucket: String,
+    key: String,
+    query: &HashMap<String, String>,
+    headers: &HeaderMap,
+) -> S3Result<Response> {
+    let version_id = query
+        .get("versionId")
+        .map(|v| {
+            v.parse::<Uuid>()
+                .map_err(|_| S3Error::InvalidRequest(format!("Invalid versionId: {}", v)))
+        })
+        .transpose()?;
+
+    let tenant = authorize(
+        state,
+        headers,
+        scopes::S3_WRITE,
+        PolicyRequest::object(actions::RESTORE_OBJECT, &bucket, &key),
+    )
+    .await?;
+    if !state.bucket_exists(&tenant, &bucket).await? {
+        return Err(S3Error::NoSuchBucket(bucket));
+    }
+
+    let metadata = state
+        .restore_object(&tenant, &bucket, &key, version_id)
+        .await?;
+    info!(tenant = %tenant, bucket = %bucket, key = %key, "Object restored from trash");
+
+    Ok((
+        StatusCode::OK,
+        [(header::ETAG, format!("\"{}\"", metadata.etag))],
+    )
+        .into_response())
+}
+
+/// POST /:bucket/*key?restore with a `RestoreRequest` - Restore an archived
+/// object
+///
+/// Answers 202 once a restore is queued, or 200 if the object is already
+/// restored, in which case its copy is kept for the requested days from now.
+async fn restore_archived_object(
+    state: &AppState,
+    bucket: String,
+    key: String,
+    headers: &HeaderMap,
+    body: &str,
+) -> S3Result<Response> {
+    let tenant = authorize(
+        state,
+        headers,
+        scopes::S3_WRITE,
+        PolicyRequest::object(actions::RESTORE_OBJECT, &bucket, &key),
+    )
+    .await?;
+    let request = RestoreRequest::from_xml(body)?;
+    if !state.bucket_exists(&tenant, &bucket).await? {
+        return Err(S3Error::NoSuchBucket(bucket));
+    }
+
+    let outcome = state
+        .restore_archived_object(&tenant, &bucket, &key, request.days)
+        .await?;
+    info!(
+        tenant = %tenant,
+        bucket = %bucket,
+        key = %key,
+        days = request.days,
+        outcome = ?outcome,
+        "Archive restore requested"
+    );
+
+    let status = match outcome {
+        RestoreRequestOutcome::Queued => StatusCode::ACCEPTED,
+        RestoreRequestOutcome::AlreadyRestored => StatusCode::OK,
+    };
+    Ok(status.into_response())
+}
+
+/// Scan an object window by window, sending matching records to `tx`
+///
+/// Failures after the response has started abort the body stream.
+async fn run_select(
+    state: Arc<AppState>,
+    tenant: String,
+    bucket: String,
+    key: String,
+    size: u64,
+    mut processor: SelectProcessor,
+    tx: mpsc::Sender<std::io::Result<Bytes>>,
+) {
+    let fail = |e: String| std::io::Error::new(std::io::ErrorKind::Other, e);
+
+    let mut offset = 0;
+    while offset < size && !processor.is_done() {
+        let end = (offset + SELECT_WINDOW).min(size) - 1;
+        let data = match state
+            .get_object_range(&tenant, &bucket, &key, offset, end)
+            .await
+        {
+            Ok(data) => data,
+            Err(e) => {
+                error!(bucket = %bucket, key = %key, error = %e, "Select read failed");
+                let _ = tx.send(Err(fail(e.to_string()))).await;
+                return;
+            }
+        };
+        offset = end + 1;
+
+        let mut out = Vec::new();
+        if let Err(e) = processor.feed(&data, &mut out) {
+            let _ = tx.send(Err(fail(e.to_string()))).await;
+            return;
+        }
+        // Stop scanning once the client has gone away
+        if !out.is_empty() && tx.send(Ok(Bytes::from(out))).await.is_err() {
+            return;
+        }
+    }
+
+    let mut out = Vec::new();
+    let result = processor.finish(&mut out);
+    let (scanned, returned) = processor.stats();
+    match result {
+        Ok(()) => {
+            if !out.is_empty() {
+                let _ = tx.send(Ok(Bytes::from(out))).await;
+            }
+            debug!(bucket = %bucket, key = %key, scanned, returned, "Select complete");
+        }
+        Err(e) => {
+            let _ = tx.send(Err(fail(e.to_string()))).await;
+        }
+    }
+}
+
+/// DELETE /:bucket/*key - Delete object
+#[instrument(skip(state, headers))]
+async fn delete_object(
+    State(state): State<Arc<AppState>>,
+    Path((bucket, key)): Path<(String, String)>,
+    headers: HeaderMap,
+) -> S3Result<impl IntoResponse> {
+    validate_object_key(&key)?;
+    let tenant = authorize(
+        &state,
+        &headers,
+        scopes::S3_WRITE,
+        PolicyRequest::object(actions::DELETE_OBJECT, &bucket, &key),
+    )
+    .await?;
+    info!(tenant = %tenant, bucket = %bucket, key = %key, "Deleting object");
+
+    // Validate bucket exists
+    if !state.bucket_exists(&tenant, &bucket).await? {
+        return Err(S3Error::NoSuchBucket(bucket));
+    }
+
+    // Delete object (idempotent - don't error if not found)
+    state
+        .delete_object(&tenant, &bucket, &key, bypass_governance(&headers)?)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// HEAD /:bucket/*key - Get object metadata
+#[instrument(skip(state, headers))]
+async fn head_object(
+    State(state): State<Arc<AppState>>,
+    Path((bucket, key)): Path<(String, String)>,
+    headers: HeaderMap,
+) -> S3Result<Response> {
+    validate_object_key(&key)?;
+    let tenant = authorize(
+        &state,
+        &headers,
+        scopes::S3_READ,
+        PolicyRequest::object(actions::GET_OBJECT, &bucket, &key),
+    )
+    .await?;
+    debug!(tenant = %tenant, bucket = %bucket, key = %key, "Head object");
+
+    // Validate bucket exists
+    if !state.bucket_exists(&tenant, &bucket).await? {
+        return Err(S3Error::NoSuchBucket(bucket));
+    }
+
+    // Get object metadata
+    let metadata = state
+        .get_object_metadata(&tenant, &bucket, &key)
+        .await?
+        .ok_or_else(|| S3Error::NoSuchKey(key.clone()))?;
+
+    head_response(&headers, &metadata)
+}
+
+/// Response to a HEAD of an object, unless the preconditions stop it
+fn head_response(headers: &HeaderMap, metadata: &ObjectMetadata) -> S3Result<Response> {
+    if evaluate_preconditions(headers, Some(metadata), true)? == Precondition::NotModified {
+        return not_modified(metadata);
+    }
+
+    let mut response = Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, &metadata.content_type)
+        .header(header::CONTENT_LENGTH, metadata.size)
+        .header(header::ETAG, format!("\"{}\"", metadata.etag))
+        .header(header::LAST_MODIFIED, last_modified_header_value(metadata));
+
+    if let Some(expires_at) = metadata.expires_at {
+        response = response.header(EXPIRATION_HEADER, expiration_header_value(expires_at));
+    }
+    response = with_content_headers(response, &metadata.content_headers);
+    response = with_user_metadata(response, &metadata.user_metadata);
+    response = with_object_lock(response, metadata.retention);
+    response = with_encryption(response, metadata.encryption);
+    response = with_storage_class(response, metadata);
+
+    response
+        .body(Body::empty())
+        .map_err(|e| S3Error::Internal(e.to_string()))
+}
+
+// =============================================================================
+// PUBLIC OBJECTS
+// =============================================================================
+
+/// GET /public/:tenant/:bucket/*key - Download a public object
+///
+/// Needs no token; the download is billed to the bucket's owner and refused
+/// once the owner's egress cap is reached.
+#[instrument(skip(state, headers))]
+async fn get_public_object(
+    State(state): State<Arc<AppState>>,
+    Path((tenant, bucket, key)): Path<(String, String, String)>,
+    headers: HeaderMap,
+) -> S3Result<Response> {
+    validate_object_key(&key)?;
+    let (metadata, owner) = public_object(&state, &tenant, &bucket, &key).await?;
+    debug!(tenant = %tenant, bucket = %bucket, key = %key, "Getting public object");
+
+    if let Some(owner) = owner {
+        let meter = state.bandwidth_meter();
+        if meter.is_enabled() {
+            if let Err(exceeded) = meter.check_egress(&state, &owner).await {
+                crate::metrics::record_egress_limited(&exceeded.plan);
+                return Err(S3Error::EgressLimitExceeded {
+                    plan: exceeded.plan,
+                    limit_bytes: exceeded.limit_bytes,
+                });
+            }
+        }
+    }
+
+    object_response(&state, &tenant, &bucket, &key, &headers, metadata).await
+}
+
+/// HEAD /public/:tenant/:bucket/*key - Public object metadata
+#[instrument(skip(state, headers))]
+async fn head_public_object(
+    State(state): State<Arc<AppState>>,
+    Path((tenant, bucket, key)): Path<(String, String, String)>,
+    headers: HeaderMap,
+) -> S3Result<Response> {
+    validate_object_key(&key)?;
+    let (metadata, _) = public_object(&state, &tenant, &bucket, &key).await?;
+    head_response(&headers, &metadata)
+}
+
+/// Metadata of an object anyone may read, and the bucket owner its
+/// downloads are billed to
+///
+/// Objects that are not public are refused with `403 AccessDenied` whether
+/// they exist or not, so their keys cannot be probed.
+async fn public_object(
+    state: &AppState,
+    tenant: &str,
+    bucket: &str,
+    key: &str,
+) -> S3Result<(ObjectMetadata, Option<String>)> {
+    if !crate::auth::is_valid_tenant(tenant) {
+        return Err(S3Error::NoSuchBucket(bucket.to_string()));
+    }
+    let bucket_acl = state.get_bucket_acl(tenant, bucket).await?;
+    crate::access_log::set_public_requester(tenant, bucket_acl.owner.as_deref());
+
+    let decision = match state.bucket_policy_for_request(tenant, bucket).await? {
+        Some(policy) => policy.evaluate(&PolicyRequest {
+            source_ip: crate::client_ip::current(),
+            ..PolicyRequest::object(actions::GET_OBJECT, bucket, key)
+        }),
+        None => Decision::NotApplicable,
+    };
+    let metadata = state.get_object_metadata(tenant, bucket, key).await?;
+    let object_public_read = metadata.as_ref().is_some_and(|m| m.public_read);
+    if !acl::is_public(decision, bucket_acl.public_read, object_public_read) {
+        return Err(S3Error::AccessDenied);
+    }
+
+    let metadata = metadata.ok_or_else(|| S3Error::NoSuchKey(key.to_string()))?;
+    Ok((metadata, bucket_acl.owner))
+}
+
+// =============================================================================
+// HELPERS
+// =============================================================================
+
+/// Tenant namespace of a request
+///
+/// Taken from the `org` claim of a bearer token. Requests without a bearer
+/// token use the default tenant; an invalid token is rejected rather than
+/// silently falling back, so it can never reach another tenant's buckets.
+/// So is a token that does not grant `scope`, the scope the operation
+/// requires (`s3:read` or `s3:write`).
+async fn request_tenant(state: &AppState, headers: &HeaderMap, scope: &str) -> S3Result<String> {
+    match authenticate(state, headers).await? {
+        Some(claims) if !claims.has_scope(scope) => Err(S3Error::AccessDenied),
+        claims => Ok(tenant_of(claims.as_ref()).to_string()),
+    }
+}
+
+/// Tenant namespace of a request authorized to perform `request`
+///
+/// Like [`request_tenant`], but the bucket's policy is evaluated before the
+/// token's scopes (see [`BucketAccess::permits`]).
+async fn authorize(
+    state: &AppState,
+    headers: &HeaderMap,
+    scope: &str,
+    request: PolicyRequest<'_>,
+) -> S3Result<String> {
+    let access = BucketAccess::new(state, headers, request.bucket).await?;
+    if !access.permits(scope, request) {
+        return Err(S3Error::AccessDenied);
+    }
+    Ok(access.tenant)
+}
+
+/// Claims of a request's bearer token, None for anonymous requests
+async fn authenticate(state: &AppState, headers: &HeaderMap) -> S3Result<Option<Claims>> {
+    let token = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+
+    match token {
+        Some(token) => {
+            let claims = state
+                .auth_service()
+                .validate_token(token)
+                .await
+                .map_err(|_| S3Error::AccessDenied)?;
+            crate::access_log::set_requester(claims.tenant(), Some(&claims.sub));
+            Ok(Some(claims))
+        }
+        None => {
+            crate::access_log::set_requester(DEFAULT_TENANT, None);
+            Ok(None)
+        }
+    }
+}
+
+/// Tenant of a requester
+fn tenant_of(claims: Option<&Claims>) -> &str {
+    claims.map_or(DEFAULT_TENANT, Claims::tenant)
+}
+
+/// Requester of an S3 request and the policy of the bucket it targets
+struct BucketAccess {
+    claims: Option<Claims>,
+    tenant: String,
+    policy: Option<Arc<BucketPolicy>>,
+    source_ip: Option<std::net::IpAddr>,
+}
+
+impl BucketAccess {
+    /// Authenticate a request to `bucket` and look up the bucket's policy
+    async fn new(state: &AppState, headers: &HeaderMap, bucket: &str) -> S3Result<Self> {
+        let claims = authenticate(state, headers).await?;
+        let tenant = tenant_of(claims.as_ref()).to_string();
+        let policy = state.bucket_policy_for_request(&tenant, bucket).await?;
+        Ok(Self {
+            claims,
+            tenant,
+            policy,
+            source_ip: crate::client_ip::current(),
+        })
+    }
+
+    /// Whether the requester may perform `request`
+    ///
+    /// A policy statement denying the request refuses it and one allowing
+    /// it grants it; when none applies the token must grant `scope`.
+    fn permits(&self, scope: &str, request: PolicyRequest<'_>) -> bool {
+        let decision = match self.policy {
+            Some(ref policy) => policy.evaluate(&PolicyRequest {
+                principal: self.claims.as_ref().map(|claims| claims.sub.as_str()),
+                source_ip: self.source_ip,
+                ..request
+            }),
+            None => Decision::NotApplicable,
+        };
+        match decision {
+            Decision::Allow => true,
+            Decision::Deny => false,
+            Decision::NotApplicable => match self.claims {
+                Some(ref claims) => claims.has_scope(scope),
+                None => true,
+            },
+        }
+    }
+}
+
+/// Parse Range header (e.g., "bytes=0-999")
+fn parse_range_header(header: &str, total_size: u64) -> Option<(u64, u64)> {
+    let header = header.strip_prefix("bytes=")?;
+    let parts: Vec<&str> = header.split('-').collect();
+
+    if parts.len() != 2 {
+        return None;
+    }
+
+    let (start, end): (u64, u64) = if parts[0].is_empty() {
+        // Suffix range: -500 means last 500 bytes
+        let suffix: u64 = parts[1].parse().ok()?;
+        (total_size.saturating_sub(suffix), total_size - 1)
+    } else {
+        let start: u64 = parts[0].parse().ok()?;
+        let end: u64 = if parts[1].is_empty() {
+            total_size - 1
+        } else {
+            parts[1].parse().ok()?
+        };
+        (start, end)
+    };
+
+    if start > end || start >= total_size {
+        return None;
+    }
+
+    Some((start, end.min(total_size - 1)))
+}
+
+/// Parse the requested object expiry from `x-amz-expiration` or `x-cyx-ttl`
+fn parse_expiration(
+    headers: &HeaderMap,
+    now: chrono::DateTime<chrono::Utc>,
+) -> S3Result<Option<chrono::DateTime<chrono::Utc>>> {
+    let expires_at = if let Some(value) = header_str(headers, EXPIRATION_HEADER)? {
+        chrono::DateTime::parse_from_rfc3339(value)
+            .or_else(|_| chrono::DateTime::parse_from_rfc2822(value))
+            .map(|t| t.with_timezone(&chrono::Utc))
+            .map_err(|_| {
+                S3Error::InvalidRequest(format!(
+                    "{} must be an RFC 3339 or HTTP date",
+                    EXPIRATION_HEADER
+                ))
+            })?
+    } else if let Some(value) = header_str(headers, TTL_HEADER)? {
+        let secs: i64 = value.parse().ok().filter(|secs| *secs > 0).ok_or_else(|| {
+            S3Error::InvalidRequest(format!(
+                "{} must be a positive number of seconds",
+                TTL_HEADER
+            ))
+        })?;
+        now + chrono::Duration::seconds(secs)
+    } else {
+        return Ok(None);
+    };
+
+    if expires_at <= now {
+        return Err(S3Error::InvalidRequest(
+            "Object expiration must be in the future".to_string(),
+        ));
+    }
+
+    Ok(Some(expires_at))
+}
+
+/// Parse the idempotency key of an upload, if one was sent
+///
+/// Keys are 1-255 visible ASCII characters.
+fn parse_idempotency_key(headers: &HeaderMap) -> S3Result<Option<&str>> {
+    let Some(value) = header_str(headers, IDEMPOTENCY_KEY_HEADER)? else {
+        return Ok(None);
+    };
+    if value.is_empty()
+        || value.len() > MAX_IDEMPOTENCY_KEY_LEN
+        || !value.bytes().all(|b| b.is_ascii_graphic())
+    {
+        return Err(S3Error::InvalidRequest(format!(
+            "{} must be 1-{} visible ASCII characters",
+            IDEMPOTENCY_KEY_HEADER, MAX_IDEMPOTENCY_KEY_LEN
+        )));
+    }
+    Ok(Some(value))
+}
+
+/// Parse the server-side encryption a PUT asks for
+///
+/// Client-supplied keys (SSE-C) and naming a KMS key are refused rather than
+/// ignored, so a client never believes its object is protected in a way it
+/// is not.
+fn parse_sse(headers: &HeaderMap) -> S3Result<Option<SseAlgorithm>> {
+    if headers
+        .keys()
+        .any(|name| name.as_str().starts_with(SSE_CUSTOMER_PREFIX))
+    {
+        return Err(S3Error::NotImplemented(
+            "Server-side encryption with customer-provided keys is not supported".to_string(),
+        ));
+    }
+    if headers.contains_key(SSE_KMS_KEY_ID_HEADER) {
+        return Err(S3Error::NotImplemented(format!(
+            "{} is not supported; objects are encrypted with the gateway's master key",
+            SSE_KMS_KEY_ID_HEADER
+        )));
+    }
+    header_str(headers, SSE_HEADER)?
+        .map(|value| value.parse().map_err(S3Error::InvalidRequest))
+        .transpose()
+}
+
+/// Parse the `x-amz-storage-class` header of an upload (STANDARD without one)
+fn parse_storage_class(headers: &HeaderMap) -> S3Result<StorageClass> {
+    match header_str(headers, STORAGE_CLASS_HEADER)? {
+        Some(value) => StorageClass::from_str(value)
+            .ok_or_else(|| S3Error::InvalidStorageClass(value.to_string())),
+        None => Ok(StorageClass::Standard),
+    }
+}
+
+/// Parse the `x-amz-meta-*` headers of a request into user metadata
+///
+/// Repeated headers are joined with commas.
+fn parse_user_metadata(headers: &HeaderMap) -> S3Result<UserMetadata> {
+    let mut metadata = UserMetadata::new();
+    for (name, value) in headers {
+        let Some(name) = name.as_str().strip_prefix(USER_METADATA_PREFIX) else {
+            continue;
+        };
+        let value = value.to_str().map_err(|_| {
+            S3Error::InvalidRequest(format!("Invalid {}{} header", USER_METADATA_PREFIX, name))
+        })?;
+        metadata
+            .entry(name.to_string())
+            .and_modify(|joined| {
+                joined.push(',');
+                joined.push_str(value.trim());
+            })
+            .or_insert_with(|| value.trim().to_string());
+    }
+    validate_user_metadata(&metadata)?;
+    Ok(metadata)
+}
+
+/// Check user metadata before storing it
+///
+/// Names must be lowercase and form valid header names with the
+/// `x-amz-meta-` prefix, values must be valid header values, and names and
+/// values together may not exceed 2 KB.
+pub fn validate_user_metadata(metadata: &UserMetadata) -> S3Result<()> {
+    let mut size = 0;
+    for (name, value) in metadata {
+        if !is_valid_user_metadata_entry(name, value) {
+            return Err(S3Error::InvalidRequest(format!(
+                "Invalid user metadata entry: {}",
+                name
+            )));
+        }
+        size += name.len() + value.len();
+    }
+    if size > MAX_USER_METADATA_SIZE {
+        return Err(S3Error::MetadataTooLarge {
+            max_size: MAX_USER_METADATA_SIZE,
+        });
+    }
+    Ok(())
+}
+
+/// Whether a user metadata entry can be sent back as a response header
+fn is_valid_user_metadata_entry(name: &str, value: &str) -> bool {
+    let header_name = format!("{}{}", USER_METADATA_PREFIX, name);
+    !name.is_empty()
+        && !name.bytes().any(|b| b.is_ascii_uppercase())
+        && header::HeaderName::from_bytes(header_name.as_bytes()).is_ok()
+        && header::HeaderValue::from_str(value).is_ok()
+}
+
+/// Add an object's content headers to a response
+fn with_content_headers(
+    mut response: axum::http::response::Builder,
+    content_headers: &ContentHeaders,
+) -> axum::http::response::Builder {
+    for (name, value) in content_headers.iter() {
+        response = response.header(name, value);
+    }
+    response
+}
+
+/// Add an object's user metadata to a response as `x-amz-meta-*` headers
+fn with_user_metadata(
+    mut response: axum::http::response::Builder,
+    metadata: &UserMetadata,
+) -> axum::http::response::Builder {
+    for (name, value) in metadata {
+        response = response.header(format!("{}{}", USER_METADATA_PREFIX, name), value);
+    }
+    response
+}
+
+/// Add the object lock headers of a locked object to a response
+fn with_object_lock(
+    response: axum::http::response::Builder,
+    retention: Option<ObjectRetention>,
+) -> axum::http::response::Builder {
+    match retention {
+        Some(retention) => response
+            .header(OBJECT_LOCK_MODE_HEADER, retention.mode.as_str())
+            .header(
+                OBJECT_LOCK_RETAIN_UNTIL_HEADER,
+                retention_date(retention.retain_until),
+            ),
+        None => response,
+    }
+}
+
+/// Add the server-side encryption header of an encrypted object to a response
+fn with_encryption(
+    response: axum::http::response::Builder,
+    encryption: Option<SseAlgorithm>,
+) -> axum::http::response::Builder {
+    match encryption {
+        Some(algorithm) => response.header(SSE_HEADER, algorithm.as_str()),
+        None => response,
+    }
+}
+
+/// Add the storage class and restore headers of an archived object to a
+/// response (STANDARD is not sent, as in S3)
+fn with_storage_class(
+    mut response: axum::http::response::Builder,
+    metadata: &ObjectMetadata,
+) -> axum::http::response::Builder {
+    if metadata.storage_class != StorageClass::Standard {
+        response = response.header(STORAGE_CLASS_HEADER, metadata.storage_class.as_str());
+    }
+    match metadata.restore {
+        Some(restore) => response.header(RESTORE_HEADER, restore.header_value()),
+        None => response,
+    }
+}
+
+/// Whether a request asks to bypass governance retention
+fn bypass_governance(headers: &HeaderMap) -> S3Result<bool> {
+    Ok(header_str(headers, BYPASS_GOVERNANCE_HEADER)?
+        .is_some_and(|v| v.eq_ignore_ascii_case("true")))
+}
+
+/// Parse `x-amz-copy-source` into the source bucket and key
+///
+/// Accepts `bucket/key` with or without a leading slash, URL-encoded.
+/// Copying a specific version (`?versionId=`) is not supported.
+fn parse_copy_source(value: &str) -> S3Result<(String, String)> {
+    let invalid = || S3Error::InvalidRequest(format!("Invalid {} header", COPY_SOURCE_HEADER));
+    let (path, query) = value.split_once('?').unwrap_or((value, ""));
+    if !query.is_empty() {
+        return Err(S3Error::NotImplemented(
+            "Copying a specific object version is not supported".to_string(),
+        ));
+    }
+
+    let path = percent_decode(path.trim_start_matches('/')).ok_or_else(invalid)?;
+    match path.split_once('/') {
+        Some((bucket, key)) if !bucket.is_empty() && !key.is_empty() => {
+            Ok((bucket.to_string(), key.to_string()))
+        }
+        _ => Err(invalid()),
+    }
+}
+
+/// Decode `%XX` escapes; None if an escape is malformed or the result is
+/// not UTF-8
+fn percent_decode(value: &str) -> Option<String> {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = value.get(i + 1..i + 3)?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+/// Read an optional header as a string
+fn header_str<'a>(headers: &'a HeaderMap, name: &str) -> S3Result<Option<&'a str>> {
+    headers
+        .get(name)
+        .map(|v| {
+            v.to_str()
+                .map(str::trim)
+                .map_err(|_| S3Error::InvalidRequest(format!("Invalid {} header", name)))
+        })
+        .transpose()
+}
+
+/// Value of the `x-amz-expiration` response header
+fn expiration_header_value(expires_at: chrono::DateTime<chrono::Utc>) -> String {
+    format!("expiry-date=\"{}\"", http_date(expires_at))
+}
+
+/// Format a retention time as S3 does (`2027-01-01T00:00:00.000Z`)
+fn retention_date(t: chrono::DateTime<chrono::Utc>) -> String {
+    t.to_rfc3339_opts(chrono::SecondsFormat::Millis, true)
+}
+
+/// Format a time as an HTTP date (`Sun, 06 Nov 1994 08:49:37 GMT`)
+fn http_date(t: chrono::DateTime<chrono::Utc>) -> String {
+    t.format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+}
+
+/// Parse an HTTP date, also accepting RFC 3339
+fn parse_http_date(value: &str) -> Option<chrono::DateTime<chrono::Utc>> {
+    chrono::DateTime::parse_from_rfc2822(value)
+        .or_else(|_| chrono::DateTime::parse_from_rfc3339(value))
+        .ok()
+        .map(|t| t.with_timezone(&chrono::Utc))
+}
+
+/// Value of the `Last-Modified` response header
+fn last_modified_header_value(metadata: &ObjectMetadata) -> String {
+    metadata
+        .last_modified_at()
+        .map(http_date)
+        .unwrap_or_else(|| metadata.last_modified.clone())
+}
+
+/// Result of evaluating the conditional headers of a request
+#[derive(Debug, PartialEq, Eq)]
+enum Precondition {
+    /// Serve the request normally
+    Proceed,
+    /// Answer a GET or HEAD with 304 Not Modified
+    NotModified,
+}
+
+/// Whether a request carries any conditional header
+fn has_preconditions(headers: &HeaderMap) -> bool {
+    [
+        header::IF_MATCH,
+        header::IF_NONE_MATCH,
+        header::IF_MODIFIED_SINCE,
+        header::IF_UNMODIFIED_SINCE,
+    ]
+    .iter()
+    .any(|name| headers.contains_key(name))
+}
+
+/// Evaluate conditional headers against the current object
+///
+/// Follows the precedence of RFC 9110 section 13.2.2: `If-Match` (or else
+/// `If-Unmodified-Since`) fails with 412; `If-None-Match` (or else
+/// `If-Modified-Since`, for reads only) yields 304 on GET/HEAD and 412 on
+/// writes. `current` is None if the object does not exist yet. Dates have
+/// one-second resolution and unparseable dates are ignored.
+fn evaluate_preconditions(
+    headers: &HeaderMap,
+    current: Option<&ObjectMetadata>,
+    read: bool,
+) -> S3Result<Precondition> {
+    let etag = current.map(|m| m.etag.as_str());
+    let modified = current.and_then(ObjectMetadata::last_modified_at);
+
+    if let Some(value) = header_str(headers, header::IF_MATCH.as_str())? {
+        if !etag.is_some_and(|etag| etag_matches(value, etag, true)) {
+            return Err(S3Error::PreconditionFailed);
+        }
+    } else if let Some(value) = header_str(headers, header::IF_UNMODIFIED_SINCE.as_str())? {
+        if let (Some(since), Some(modified)) = (parse_http_date(value), modified) {
+            if modified.timestamp() > since.timestamp() {
+                return Err(S3Error::PreconditionFailed);
+            }
+        }
+    }
+
+    if let Some(value) = header_str(headers, header::IF_NONE_MATCH.as_str())? {
+        if etag.is_some_and(|etag| etag_matches(value, etag, false)) {
+            return if read {
+                Ok(Precondition::NotModified)
+            } else {
+                Err(S3Error::PreconditionFailed)
+            };
+        }
+    } else if read {
+        if let Some(value) = header_str(headers, header::IF_MODIFIED_SINCE.as_str())? {
+            if let (Some(since), Some(modified)) = (parse_http_date(value), modified) {
+                if modified.timestamp() <= since.timestamp() {
+                    return Ok(Precondition::NotModified);
+                }
+            }
+        }
+    }
+
+    Ok(Precondition::Proceed)
+}
+
+/// Whether an `If-Match` / `If-None-Match` list names `etag`
+///
+/// `*` matches any existing object. Weak tags (`W/"..."`) only match
+/// when `strong` comparison is not required.
+fn etag_matches(list: &str, etag: &str, strong: bool) -> bool {
+    list.split(',').map(str::trim).any(|candidate| {
+        if candidate == "*" {
+            return true;
+        }
+        let (weak, tag) = match candidate.strip_prefix("W/") {
+            Some(tag) => (true, tag),
+            None => (false, candidate),
+        };
+        !(weak && strong) && tag.trim_matches('"') == etag
+    })
+}
+
+/// 304 response for a GET/HEAD whose cached copy is still current
+fn not_modified(metadata: &ObjectMetadata) -> S3Result<Response> {
+    Response::builder()
+        .status(StatusCode::NOT_MODIFIED)
+        .header(header::ETAG, format!("\"{}\"", metadata.etag))
+        .header(header::LAST_MODIFIED, last_modified_header_value(metadata))
+        .body(Body::empty())
+        .map_err(|e| S3Error::Internal(e.to_string()))
+}
+
+/// Object metadata returned by storage
+#[derive(Debug, Clone)]
+pub struct ObjectMetadata {
+    pub key: String,
+    pub size: u64,
+    pub content_type: String,
+    pub etag: String,
+    pub last_modified: String,
+    /// Expiry time, if the object was uploaded with one
+    pub expires_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// `x-amz-meta-*` metadata
+    pub user_metadata: UserMetadata,
+    /// Object lock retention, if the object was ever locked
+    pub retention: Option<ObjectRetention>,
+    /// Server-side encryption, None if the object is stored in plaintext
+    pub encryption: Option<SseAlgorithm>,
+    /// Whether anyone may read the object (see [`crate::acl`])
+    pub public_read: bool,
+    pub content_headers: ContentHeaders,
+    /// Storage class (see [`crate::tiering`])
+    pub storage_class: StorageClass,
+    /// Restore state, for archived objects
+    pub restore: Option<RestoreStatus>,
+}
+
+impl ObjectMetadata {
+    /// Modification time, parsed from `last_modified` (RFC 3339)
+    pub fn last_modified_at(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        parse_http_date(&self.last_modified)
+    }
+}
+
+/// User metadata of an object: `x-amz-meta-*` header names, lowercase and
+/// without the prefix, to values
+pub type UserMetadata = BTreeMap<String, String>;
+
+/// Headers of an object's upload that are returned on GET and HEAD
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ContentHeaders {
+    /// `Cache-Control`, or the bucket's default cache policy if None on upload
+    pub cache_control: Option<String>,
+    pub content_disposition: Option<String>,
+    /// `Content-Encoding` of the stored bytes (the gateway never decodes them)
+    pub content_encoding: Option<String>,
+}
+
+impl ContentHeaders {
+    /// Content headers of a PUT
+    ///
+    /// `aws-chunked` describes how the body was sent rather than the object,
+    /// so it is left out of the stored `Content-Encoding`.
+    pub fn from_headers(headers: &HeaderMap) -> S3Result<Self> {
+        let value = |name: header::HeaderName| -> S3Result<Option<String>> {
+            Ok(header_str(headers, name.as_str())?
+                .filter(|v| !v.is_empty())
+                .map(str::to_string))
+        };
+        let content_encoding = value(header::CONTENT_ENCODING)?.and_then(|encoding| {
+            let codings: Vec<_> = encoding
+                .split(',')
+                .map(str::trim)
+                .filter(|c| !c.is_empty() && !c.eq_ignore_ascii_case(AWS_CHUNKED_ENCODING))
+                .collect();
+            (!codings.is_empty()).then(|| codings.join(", "))
+        });
+        Ok(Self {
+            cache_control: value(header::CACHE_CONTROL)?,
+            content_disposition: value(header::CONTENT_DISPOSITION)?,
+            content_encoding,
+        })
+    }
+
+    /// Headers to send, with their values
+    pub fn iter(&self) -> impl Iterator<Item = (header::HeaderName, &str)> {
+        [
+            (header::CACHE_CONTROL, &self.cache_control),
+            (header::CONTENT_DISPOSITION, &self.content_disposition),
+            (header::CONTENT_ENCODING, &self.content_encoding),
+        ]
+        .into_iter()
+        .filter_map(|(name, value)| Some((name, value.as_deref()?)))
+        .filter(|(_, value)| header::HeaderValue::from_str(value).is_ok())
+    }
+}
+
+/// User metadata as stored in `files.metadata` (None if there is none)
+pub fn user_metadata_to_json(metadata: &UserMetadata) -> Option<serde_json::Value> {
+    if metadata.is_empty() {
+        return None;
+    }
+    Some(serde_json::Value::Object(
+        metadata
+            .iter()
+            .map(|(name, value)| (name.clone(), serde_json::Value::String(value.clone())))
+            .collect(),
+    ))
+}
+
+/// User metadata from `files.metadata`
+///
+/// Only string entries that can be sent back as headers are kept, so
+/// metadata written by other APIs never breaks a GET or HEAD.
+pub fn user_metadata_from_json(value: Option<&serde_json::Value>) -> UserMetadata {
+    value
+        .and_then(serde_json::Value::as_object)
+        .map(|object| {
+            object
+                .iter()
+                .filter_map(|(name, value)| Some((name.clone(), value.as_str()?.to_string())))
+                .filter(|(name, value)| is_valid_user_metadata_entry(name, value))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delete_objects_request_from_xml() {
+        let body = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Delete xmlns="http://s3.amazonaws.com/doc/2006-03-01/">
+  <Quiet>true</Quiet>
+  <Object><Key>logs/a.txt</Key></Object>
+  <Object><Key>b&amp;c.txt</Key><VersionId>1</VersionId></Object>
+</Delete>"#;
+        let request = DeleteObjectsRequest::from_xml(body).unwrap();
+        assert!(request.quiet);
+        assert_eq!(request.keys, vec!["logs/a.txt", "b&c.txt"]);
+
+        let loud = DeleteObjectsRequest::from_xml("<Delete><Object><Key>x</Key></Object></Delete>")
+            .unwrap();
+        assert!(!loud.quiet);
+
+        assert!(DeleteObjectsRequest::from_xml("<Delete></Delete>").is_err());
+        assert!(DeleteObjectsRequest::from_xml("<Delete><Object></Object></Delete>").is_err());
+        assert!(DeleteObjectsRequest::from_xml("<Object><Key>x</Key></Object>").is_err());
+
+        let too_many = format!(
+            "<Delete>{}</Delete>",
+            "<Object><Key>k</Key></Object>".repeat(MAX_DELETE_KEYS + 1)
+        );
+        assert!(DeleteObjectsRequest::from_xml(&too_many).is_err());
+    }
+
+    #[test]
+    fn test_delete_objects_result_xml() {
+        let result = DeleteObjectsResult {
+            quiet: false,
+            deleted: vec!["a.txt".to_string()],
+            errors: vec![DeleteError {
+                key: "../x".to_string(),
+                code: AwsErrorCode::InvalidArgument,
+                message: "Key cannot contain '..'".to_string(),
+            }],
+        };
+        let xml = result.to_xml();
+        assert!(xml.contains("<Deleted>\n    <Key>a.txt</Key>"));
+        assert!(xml.contains("<Key>../x</Key>"));
+        assert!(xml.contains("<Code>InvalidArgument</Code>"));
+        assert!(xml.contains("<Message>Key cannot contain &apos;..&apos;</Message>"));
+
+        // Quiet mode only lists errors
+        let quiet = DeleteObjectsResult {
+            quiet: true,
+            ..result
+        };
+        assert!(!quiet.to_xml().contains("<Deleted>"));
+    }
+
+    #[test]
+    fn test_parse_range_header() {
+        // Full range
+        assert_eq!(parse_range_header("bytes=0-499", 1000), Some((0, 499)));
+
+        // Open-ended range
+        assert_eq!(parse_range_header("bytes=500-", 1000), Some((500, 999)));
+
+        // Suffix range
+        assert_eq!(parse_range_header("bytes=-500", 1000), Some((500, 999)));
+
+        // Invalid range
+        assert_eq!(parse_range_header("bytes=500-100", 1000), None);
+
+        // Out of bounds
+        assert_eq!(parse_range_header("bytes=1500-", 1000), None);
+    }
+
+    #[test]
+    fn test_parse_expiration() {
+        let now = chrono::DateTime::parse_from_rfc3339("2025-01-01T00:00:00Z")
+            .unwrap()
+            .with_timezone(&chrono::Utc);
+        let mut headers = HeaderMap::new();
+        assert_eq!(parse_expiration(&headers, now).unwrap(), None);
+
+        headers.insert(TTL_HEADER, "3600".parse().unwrap());
+        assert_eq!(
+            parse_expiration(&headers, now).unwrap(),
+            Some(now + chrono::Duration::hours(1))
+        );
+
+        // The absolute header wins over the TTL
+        headers.insert(
+            EXPIRATION_HEADER,
+            "Thu, 02 Jan 2025 00:00:00 GMT".parse().unwrap(),
+        );
+        let expires_at = parse_expiration(&headers, now).unwrap().unwrap();
+        assert_eq!(expires_at, now + chrono::Duration::days(1));
+        assert_eq!(
+            expiration_header_value(expires_at),
+            "expiry-date=\"Thu, 02 Jan 2025 00:00:00 GMT\""
+        );
+
+        headers.insert(EXPIRATION_HEADER, "2025-01-01T12:00:00Z".parse().unwrap());
+        assert!(parse_expiration(&headers, now).unwrap().is_some());
+
+        // Past, zero and malformed values are rejected
+        headers.insert(EXPIRATION_HEADER, "2024-12-31T00:00:00Z".parse().unwrap());
+        assert!(parse_expiration(&headers, now).is_err());
+        headers.insert(EXPIRATION_HEADER, "tomorrow".parse().unwrap());
+        assert!(parse_expiration(&headers, now).is_err());
+        headers.remove(EXPIRATION_HEADER);
+        headers.insert(TTL_HEADER, "0".parse().unwrap());
+        assert!(parse_expiration(&headers, now).is_err());
+    }
+
+    #[test]
+    fn test_parse_idempotency_key() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(parse_idempotency_key(&headers).unwrap(), None);
+
+        headers.insert(IDEMPOTENCY_KEY_HEADER, " upload-42 ".parse().unwrap());
+        assert_eq!(parse_idempotency_key(&headers).unwrap(), Some("upload-42"));
+
+        // Empty, overlong and non-printable keys are rejected
+        for bad in ["", "two words", &"k".repeat(MAX_IDEMPOTENCY_KEY_LEN + 1)] {
+            headers.insert(IDEMPOTENCY_KEY_HEADER, bad.parse().unwrap());
+            assert!(parse_idempotency_key(&headers).is_err(), "{:?}", bad);
+        }
+    }
+
+    #[test]
+    fn test_parse_sse() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(parse_sse(&headers).unwrap(), None);
+
+        headers.insert(SSE_HEADER, "aws:kms".parse().unwrap());
+        assert_eq!(parse_sse(&headers).unwrap(), Some(SseAlgorithm::AwsKms));
+        headers.insert(SSE_HEADER, "aes256".parse().unwrap());
+        assert!(parse_sse(&headers).is_err());
+
+        // Customer keys and named KMS keys are refused, not ignored
+        headers.insert(SSE_HEADER, "AES256".parse().unwrap());
+        headers.insert(SSE_KMS_KEY_ID_HEADER, "arn:key".parse().unwrap());
+        assert!(parse_sse(&headers).is_err());
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "x-amz-server-side-encryption-customer-algorithm",
+            "AES256".parse().unwrap(),
+        );
+        assert!(parse_sse(&headers).is_err());
+    }
+
+    #[test]
+    fn test_parse_user_metadata() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-amz-meta-project", "apollo".parse().unwrap());
+        headers.append("x-amz-meta-tags", "a".parse().unwrap());
+        headers.append("x-amz-meta-tags", " b ".parse().unwrap());
+        headers.insert(header::CONTENT_TYPE, "text/plain".parse().unwrap());
+        let metadata = parse_user_metadata(&headers).unwrap();
+        assert_eq!(metadata.len(), 2);
+        assert_eq!(metadata["project"], "apollo");
+        assert_eq!(metadata["tags"], "a,b");
+
+        let big = "x".repeat(MAX_USER_METADATA_SIZE);
+        headers.insert("x-amz-meta-big", big.parse().unwrap());
+        assert!(parse_user_metadata(&headers).is_err());
+
+        let upper = UserMetadata::from([("Project".to_string(), "apollo".to_string())]);
+        assert!(validate_user_metadata(&upper).is_err());
+        let newline = UserMetadata::from([("note".to_string(), "a\nb".to_string())]);
+        assert!(validate_user_metadata(&newline).is_err());
+    }
+
+    #[test]
+    fn test_user_metadata_json() {
+        assert_eq!(user_metadata_to_json(&UserMetadata::new()), None);
+
+        let metadata = UserMetadata::from([("project".to_string(), "apollo".to_string())]);
+        let json = user_metadata_to_json(&metadata).unwrap();
+        assert_eq!(json, serde_json::json!({ "project": "apollo" }));
+        assert_eq!(user_metadata_from_json(Some(&json)), metadata);
+
+        // Metadata written by other APIs is skipped rather than echoed
+        let other = serde_json::json!({ "project": "apollo", "shape": [3, 224], "Bad Name": "x" });
+        assert_eq!(user_metadata_from_json(Some(&other)), metadata);
+        assert!(user_metadata_from_json(None).is_empty());
+    }
+
+    #[test]
+    fn test_parse_copy_source() {
+        let expected = ("src".to_string(), "dir/a b.txt".to_string());
+        assert_eq!(parse_copy_source("/src/dir/a%20b.txt").unwrap(), expected);
+        assert_eq!(parse_copy_source("src/dir/a%20b.txt").unwrap(), expected);
+        assert_eq!(
+            parse_copy_source("src/caf%C3%A9").unwrap().1,
+            "caf\u{e9}".to_string()
+        );
+
+        assert!(parse_copy_source("src").is_err());
+        assert!(parse_copy_source("/src/").is_err());
+        assert!(parse_copy_source("src/a%2").is_err());
+        assert!(parse_copy_source("src/a%zz").is_err());
+        assert!(parse_copy_source("src/a?versionId=1").is_err());
+    }
+
+    #[test]
+    fn test_copy_object_result_xml() {
+        let result = CopyObjectResult {
+            etag: "abc123".to_string(),
+            last_modified: "2024-01-01T00:00:00+00:00".to_string(),
+        };
+        let xml = result.to_xml();
+        assert!(xml.contains("<CopyObjectResult"));
+        assert!(xml.contains("<ETag>&quot;abc123&quot;</ETag>"));
+        assert!(xml.contains("<LastModified>2024-01-01T00:00:00+00:00</LastModified>"));
+    }
+
+    /// Copy `source` to `to` (`bucket/key`) in the default tenant
+    async fn copy(
+        state: &AppState,
+        to: &str,
+        source: &str,
+        headers: &HeaderMap,
+    ) -> S3Result<Response> {
+        let (bucket, key) = to.split_once('/').unwrap();
+        copy_object(state, DEFAULT_TENANT, bucket, key, source, headers).await
+    }
+
+    #[tokio::test]
+    async fn test_copy_object() {
+        let state = AppState::new();
+        state.create_bucket(DEFAULT_TENANT, "src").await.unwrap();
+        state.create_bucket(DEFAULT_TENANT, "dst").await.unwrap();
+
+        let metadata = UserMetadata::from([("project".to_string(), "apollo".to_string())]);
+        state
+            .put_object_if(
+                DEFAULT_TENANT,
+                "src",
+                "a.txt",
+                Bytes::from("hello"),
+                "text/plain",
+                None,
+                &metadata,
+                &ContentHeaders::default(),
+                None,
+                None,
+                |_| Ok(()),
+            )
+            .await
+            .unwrap();
+
+        // COPY keeps the source's content type and metadata
+        let none = HeaderMap::new();
+        let response = copy(&state, "dst/b.txt", "/src/a.txt", &none)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let copied = state
+            .get_object_metadata(DEFAULT_TENANT, "dst", "b.txt")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(copied.content_type, "text/plain");
+        assert_eq!(copied.user_metadata, metadata);
+        let data = state.get_object(DEFAULT_TENANT, "dst", "b.txt").await;
+        assert_eq!(data.unwrap(), Bytes::from("hello"));
+
+        // Copying onto itself needs REPLACE, which takes the request's metadata
+        assert!(copy(&state, "src/a.txt", "src/a.txt", &none).await.is_err());
+        let mut replace = HeaderMap::new();
+        replace.insert(METADATA_DIRECTIVE_HEADER, "REPLACE".parse().unwrap());
+        replace.insert("x-amz-meta-stage", "done".parse().unwrap());
+        copy(&state, "src/a.txt", "src/a.txt", &replace)
+            .await
+            .unwrap();
+        let replaced = state
+            .get_object_metadata(DEFAULT_TENANT, "src", "a.txt")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(replaced.content_type, "application/octet-stream");
+        assert_eq!(replaced.user_metadata["stage"], "done");
+        assert!(!replaced.user_metadata.contains_key("project"));
+
+        let missing = copy(&state, "dst/c.txt", "src/none", &none).await;
+        assert!(matches!(missing, Err(S3Error::NoSuchKey(_))));
+    }
+
+    #[tokio::test]
+    async fn test_content_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::CONTENT_DISPOSITION,
+            "attachment; filename=\"report.csv\"".parse().unwrap(),
+        );
+        headers.insert(
+            header::CONTENT_ENCODING,
+            "aws-chunked, gzip".parse().unwrap(),
+        );
+        headers.insert(header::CACHE_CONTROL, " ".parse().unwrap());
+        let report = ContentHeaders::from_headers(&headers).unwrap();
+        assert_eq!(report.cache_control, None);
+        assert_eq!(report.content_encoding.as_deref(), Some("gzip"));
+        headers.insert(header::CONTENT_ENCODING, "aws-chunked".parse().unwrap());
+        assert_eq!(
+            ContentHeaders::from_headers(&headers)
+                .unwrap()
+                .content_encoding,
+            None
+        );
+
+        let state = AppState::new();
+        state.create_bucket(DEFAULT_TENANT, "site").await.unwrap();
+        let put = |key: &'static str, content_headers: ContentHeaders| {
+            let state = &state;
+            async move {
+                state
+                    .put_object_if(
+                        DEFAULT_TENANT,
+                        "site",
+                        key,
+                        Bytes::from("a,b"),
+                        "text/csv",
+                        None,
+                        &UserMetadata::new(),
+                        &content_headers,
+                        None,
+                        None,
+                        |_| Ok(()),
+                    )
+                    .await
+                    .unwrap();
+                state
+                    .get_object_metadata(DEFAULT_TENANT, "site", key)
+                    .await
+                    .unwrap()
+                    .unwrap()
+            }
+        };
+        let stored = put("report.csv", report.clone()).await;
+        assert_eq!(stored.content_headers, report);
+        let response = head_response(&HeaderMap::new(), &stored).unwrap();
+        assert_eq!(response.headers()[header::CONTENT_ENCODING], "gzip");
+        assert_eq!(
+            response.headers()[header::CONTENT_DISPOSITION],
+            "attachment; filename=\"report.csv\""
+        );
+        assert!(!response.headers().contains_key(header::CACHE_CONTROL));
+
+        // The bucket's default is given to new objects without their own
+        state
+            .set_bucket_cache_policy(DEFAULT_TENANT, "site", Some("max-age=60".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(
+            state
+                .get_bucket_cache_policy(DEFAULT_TENANT, "site")
+                .await
+                .unwrap()
+                .as_deref(),
+            Some("max-age=60")
+        );
+        let stored = put("index.html", ContentHeaders::default()).await;
+        assert_eq!(
+            stored.content_headers.cache_control.as_deref(),
+            Some("max-age=60")
+        );
+        let immutable = ContentHeaders {
+            cache_control: Some("public, max-age=31536000, immutable".to_string()),
+            ..Default::default()
+        };
+        let stored = put("app.js", immutable.clone()).await;
+        assert_eq!(stored.content_headers, immutable);
+        let stored = state
+            .get_object_metadata(DEFAULT_TENANT, "site", "report.csv")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored.content_headers.cache_control, None);
+
+        // A copy keeps the source's content headers
+        copy(
+            &state,
+            "site/copy.csv",
+            "/site/report.csv",
+            &HeaderMap::new(),
+        )
+        .await
+        .unwrap();
+        let copied = state
+            .get_object_metadata(DEFAULT_TENANT, "site", "copy.csv")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            copied.content_headers.content_encoding.as_deref(),
+            Some("gzip")
+        );
+
+        assert!(state
+            .set_bucket_cache_policy(DEFAULT_TENANT, "missing", None)
+            .await
+            .is_err());
+    }
+
+    #[test]
+    fn test_list_objects_response_xml() {
+        let response = ListObjectsV2Response {
+            name: "test-bucket".to_string(),
+            prefix: Some("prefix/".to_string()),
+            max_keys: 1000,
+            is_truncated: false,
+            key_count: 1,
+            contents: vec![ObjectInfo {
+                key: "prefix/file.txt".to_string(),
+                last_modified: "2024-01-01T00:00:00Z".to_string(),
+                etag: "abc123".to_string(),
+                size: 1024,
+                storage_class: "STANDARD".to_string(),
+                user_metadata: UserMetadata::new(),
+            }],
+            common_prefixes: Vec::new(),
+            continuation_token: None,
+            next_continuation_token: None,
+        };
+
+        let xml = response.to_xml();
+        assert!(xml.contains("<Name>test-bucket</Name>"));
+        assert!(xml.contains("<Key>prefix/file.txt</Key>"));
+        assert!(xml.contains("<Size>1024</Size>"));
+    }
+
+    #[test]
+    fn test_error_response_codes() {
+        let cases = [
+            (
+                S3Error::NoSuchBucket("b".to_string()),
+                StatusCode::NOT_FOUND,
+                "NoSuchBucket",
+            ),
+            (
+                S3Error::MalformedXml("Delete: missing Delete".to_string()),
+                StatusCode::BAD_REQUEST,
+                "MalformedXML",
+            ),
+            (
+                S3Error::NotImplemented("SSE-C".to_string()),
+                StatusCode::NOT_IMPLEMENTED,
+                "NotImplemented",
+            ),
+            (
+                S3Error::InvalidRange,
+                StatusCode::RANGE_NOT_SATISFIABLE,
+                "InvalidRange",
+            ),
+            (
+                S3Error::KeyTooLong {
+                    max_len: MAX_KEY_LEN,
+                },
+                StatusCode::BAD_REQUEST,
+                "KeyTooLongError",
+            ),
+            (S3Error::SlowDown, StatusCode::TOO_MANY_REQUESTS, "SlowDown"),
+            (
+                S3Error::service(ErrorCode::IntegrityError, "shard mismatch"),
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "InternalError",
+            ),
+        ];
+        for (err, status, code) in cases {
+            let response = err.into_response();
+            assert_eq!(response.status(), status);
+            assert_eq!(response.extensions().get::<S3ErrorCode>().unwrap().0, code);
+        }
+
+        assert!(matches!(
+            validate_object_key(&"k".repeat(MAX_KEY_LEN + 1)),
+            Err(S3Error::KeyTooLong { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_error_response_body() {
+        let response =
+            S3Error::InvalidRequest("Key cannot contain '..'".to_string()).into_response();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body = std::str::from_utf8(&body).unwrap();
+        assert!(body.contains("<Message>Key cannot contain &apos;..&apos;</Message>"));
+
+        let error: ErrorResponse = s3_xml::from_xml(body).unwrap();
+        assert_eq!(error.code, "InvalidRequest");
+        assert_eq!(error.message, "Key cannot contain '..'");
+        assert_eq!(error.cyxcloud_code.as_deref(), Some("INVALID_ARGUMENT"));
+    }
+
+    #[test]
+    fn test_service_error_response() {
+        let err: S3Error = NodeClientError::NoNodesAvailable.into();
+        assert_eq!(err.error_code(), ErrorCode::NoNodesAvailable);
+
+        let response = err.into_response();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(
+            response.headers().get(ERROR_CODE_HEADER).unwrap(),
+            "NO_NODES_AVAILABLE"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_run_select_streams_matches() {
+        let state = Arc::new(AppState::new());
+        state.create_bucket(DEFAULT_TENANT, "data").await.unwrap();
+        let csv = "id,kind\n1,cat\n2,dog\n3,cat\n";
+        state
+            .put_object(
+                DEFAULT_TENANT,
+                "data",
+                "pets.csv",
+                Bytes::from(csv),
+                "text/csv",
+                None,
+            )
+            .await
+            .unwrap();
 
-/// PUT /:bucket/*key - Upload object
-#[instrument(skip(state, body))]
-async fn put_object(
-    State(state): State<Arc<AppState>>,
-    Path((bucket, key)): Path<(String, String)>,
-    headers: HeaderMap,
-    body: Bytes,
-) -> S3Result<impl IntoResponse> {
-    validate_object_key(&key)?;
-    info!(bucket = %bucket, key = %key, size = body.len(), "Uploading object");
+        let request = SelectRequest::from_xml(
+            "<SelectObjectContentRequest>\
+             <Expression>SELECT id FROM S3Object WHERE kind = 'cat'</Expression>\
+             <InputSerialization><CSV><FileHeaderInfo>USE</FileHeaderInfo></CSV>\
+             </InputSerialization>\
+             <OutputSerialization><CSV/></OutputSerialization>\
+             </SelectObjectContentRequest>",
+        )
+        .unwrap();
+        let (tx, mut rx) = mpsc::channel(4);
+        run_select(
+            state,
+            DEFAULT_TENANT.to_string(),
+            "data".to_string(),
+            "pets.csv".to_string(),
+            csv.len() as u64,
+            SelectProcessor::new(request),
+            tx,
+        )
+        .await;
 
-    // Validate bucket exists
-    if !state.bucket_exists(&bucket).await? {
-        return Err(S3Error::NoSuchBucket(bucket));
+        let mut output = Vec::new();
+        while let Some(piece) = rx.recv().await {
+            output.extend_from_slice(&piece.unwrap());
+        }
+        assert_eq!(output, b"1\n3\n");
     }
 
-    // Get content type from headers
-    let content_type = headers
-        .get(header::CONTENT_TYPE)
-        .and_then(|v| v.to_str().ok())
-        .unwrap_or("application/octet-stream")
-        .to_string();
+    #[tokio::test]
+    async fn test_run_export_writes_archive() {
+        let state = Arc::new(AppState::new());
+        state.create_bucket(DEFAULT_TENANT, "data").await.unwrap();
+        for (key, body) in [
+            ("logs/a.txt", "first"),
+            ("logs/b.txt", ""),
+            ("other.txt", "skipped"),
+        ] {
+            state
+                .put_object(
+                    DEFAULT_TENANT,
+                    "data",
+                    key,
+                    Bytes::from(body),
+                    "text/plain",
+                    None,
+                )
+                .await
+                .unwrap();
+        }
 
-    // Store object
-    let etag = state.put_object(&bucket, &key, body, &content_type).await?;
+        let (tx, mut rx) = mpsc::channel(64);
+        let access = BucketAccess {
+            claims: None,
+            tenant: DEFAULT_TENANT.to_string(),
+            policy: None,
+            source_ip: None,
+        };
+        run_export(state, access, "data".to_string(), "logs/".to_string(), tx).await;
+        let mut archive = Vec::new();
+        while let Some(piece) = rx.recv().await {
+            archive.extend_from_slice(&piece.unwrap());
+        }
+        assert_eq!(archive.len() % export::BLOCK_SIZE, 0);
 
-    Ok((StatusCode::OK, [(header::ETAG, format!("\"{}\"", etag))]))
-}
+        // Walk the entries up to the zero-block trailer
+        let mut entries = Vec::new();
+        let mut pos = 0;
+        loop {
+            let header = &archive[pos..pos + export::BLOCK_SIZE];
+            if header.iter().all(|&b| b == 0) {
+                break;
+            }
+            let name_len = header.iter().position(|&b| b == 0).unwrap();
+            let name = String::from_utf8(header[..name_len].to_vec()).unwrap();
+            let size = std::str::from_utf8(&header[124..135]).unwrap();
+            let size = usize::from_str_radix(size, 8).unwrap();
+            pos += export::BLOCK_SIZE;
+            entries.push((name, archive[pos..pos + size].to_vec()));
+            pos += size + export::padding(size as u64);
+        }
+        assert_eq!(archive.len() - pos, export::BLOCK_SIZE * 2);
 
-/// GET /:bucket/*key - Download object
-#[instrument(skip(state))]
-async fn get_object(
-    State(state): State<Arc<AppState>>,
-    Path((bucket, key)): Path<(String, String)>,
-    headers: HeaderMap,
-) -> S3Result<Response> {
-    validate_object_key(&key)?;
-    debug!(bucket = %bucket, key = %key, "Getting object");
+        let names: Vec<_> = entries.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec!["data/logs/a.txt", "data/logs/b.txt", export::MANIFEST_PATH]
+        );
+        assert_eq!(entries[0].1, b"first");
 
-    // Validate bucket exists
-    if !state.bucket_exists(&bucket).await? {
-        return Err(S3Error::NoSuchBucket(bucket));
+        let manifest: serde_json::Value = serde_json::from_slice(&entries[2].1).unwrap();
+        assert_eq!(manifest["object_count"], 2);
+        assert_eq!(manifest["total_bytes"], 5);
+        assert_eq!(
+            manifest["objects"][0]["blake3"],
+            blake3::hash(b"first").to_hex().to_string()
+        );
     }
 
-    // Get object metadata
-    let metadata = state
-        .get_object_metadata(&bucket, &key)
-        .await?
-        .ok_or_else(|| S3Error::NoSuchKey(key.clone()))?;
+    #[test]
+    fn test_error_codes_for_s3_variants() {
+        assert_eq!(
+            S3Error::NoSuchKey("k".to_string()).error_code(),
+            ErrorCode::NotFound
+        );
+        assert_eq!(
+            S3Error::BucketNotEmpty("b".to_string()).error_code(),
+            ErrorCode::Conflict
+        );
+        assert_eq!(S3Error::SlowDown.error_code(), ErrorCode::RateLimited);
+        assert_eq!(
+            S3Error::ObjectLocked("k".to_string()).error_code(),
+            ErrorCode::PermissionDenied
+        );
+        assert_eq!(
+            S3Error::MalformedPolicy("p".to_string()).error_code(),
+            ErrorCode::InvalidArgument
+        );
+    }
 
-    // Check for Range header
-    let range = headers
-        .get(header::RANGE)
-        .and_then(|v| v.to_str().ok())
-        .and_then(|s| parse_range_header(s, metadata.size));
+    #[test]
+    fn test_bucket_access_permits() {
+        let policy = BucketPolicy::parse(
+            "site",
+            r#"{"Statement": [
+                {"Effect": "Deny", "Principal": "*", "Action": "s3:PutObject",
+                 "Resource": "arn:aws:s3:::site/*"},
+                {"Effect": "Deny", "Principal": "*", "Action": "s3:GetObject",
+                 "Resource": "arn:aws:s3:::site/internal/*",
+                 "Condition": {"NotIpAddress": {"aws:SourceIp": "10.0.0.0/8"}}}
+            ]}"#,
+        )
+        .unwrap();
+        let mut access = BucketAccess {
+            claims: None,
+            tenant: DEFAULT_TENANT.to_string(),
+            policy: Some(Arc::new(policy)),
+            source_ip: Some("192.0.2.1".parse().unwrap()),
+        };
+        let put = PolicyRequest::object(actions::PUT_OBJECT, "site", "index.html");
+        let get = PolicyRequest::object(actions::GET_OBJECT, "site", "index.html");
+        let internal = PolicyRequest::object(actions::GET_OBJECT, "site", "internal/a");
 
-    // Get object data
-    let (data, status) = if let Some((start, end)) = range {
-        let partial = state.get_object_range(&bucket, &key, start, end).await?;
-        (partial, StatusCode::PARTIAL_CONTENT)
-    } else {
-        let full = state.get_object(&bucket, &key).await?;
-        (full, StatusCode::OK)
-    };
+        assert!(!access.permits(scopes::S3_WRITE, put));
+        assert!(access.permits(scopes::S3_READ, get));
+        assert!(!access.permits(scopes::S3_READ, internal));
 
-    let mut response = Response::builder()
-        .status(status)
-        .header(header::CONTENT_TYPE, &metadata.content_type)
-        .header(header::CONTENT_LENGTH, data.len())
-        .header(header::ETAG, format!("\"{}\"", metadata.etag))
-        .header(header::LAST_MODIFIED, &metadata.last_modified);
+        access.source_ip = Some("10.1.2.3".parse().unwrap());
+        assert!(access.permits(scopes::S3_READ, internal));
 
-    if let Some((start, end)) = range {
-        response = response.header(
-            header::CONTENT_RANGE,
-            format!("bytes {}-{}/{}", start, end, metadata.size),
+        access.policy = None;
+        assert!(access.permits(scopes::S3_WRITE, put));
+    }
+
+    fn conditional(pairs: &[(header::HeaderName, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.insert(name.clone(), value.parse().unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn test_evaluate_preconditions() {
+        let object = ObjectMetadata {
+            key: "k".to_string(),
+            size: 3,
+            content_type: "text/plain".to_string(),
+            etag: "abc".to_string(),
+            last_modified: "2024-01-15T10:30:00Z".to_string(),
+            expires_at: None,
+            user_metadata: UserMetadata::new(),
+            retention: None,
+            encryption: None,
+            public_read: false,
+            content_headers: ContentHeaders::default(),
+            storage_class: StorageClass::Standard,
+            restore: None,
+        };
+        let check = |pairs: &[(header::HeaderName, &str)], read: bool| {
+            evaluate_preconditions(&conditional(pairs), Some(&object), read)
+                .map_err(|e| e.error_code())
+        };
+
+        assert_eq!(check(&[], true), Ok(Precondition::Proceed));
+        assert_eq!(
+            check(&[(header::IF_NONE_MATCH, "\"abc\"")], true),
+            Ok(Precondition::NotModified)
+        );
+        assert_eq!(
+            check(&[(header::IF_NONE_MATCH, "\"xyz\", W/\"abc\"")], true),
+            Ok(Precondition::NotModified)
+        );
+        assert_eq!(
+            check(&[(header::IF_MATCH, "\"xyz\"")], true),
+            Err(ErrorCode::Conflict)
+        );
+        assert_eq!(
+            check(&[(header::IF_MATCH, "W/\"abc\"")], false),
+            Err(ErrorCode::Conflict)
+        );
+        assert_eq!(
+            check(&[(header::IF_MATCH, "\"abc\"")], false),
+            Ok(Precondition::Proceed)
+        );
+
+        // The HTTP date echoed from Last-Modified is not modified since
+        let last_modified = last_modified_header_value(&object);
+        assert_eq!(last_modified, "Mon, 15 Jan 2024 10:30:00 GMT");
+        assert_eq!(
+            check(&[(header::IF_MODIFIED_SINCE, last_modified.as_str())], true),
+            Ok(Precondition::NotModified)
+        );
+        assert_eq!(
+            check(
+                &[(header::IF_MODIFIED_SINCE, "Sun, 14 Jan 2024 10:30:00 GMT")],
+                true
+            ),
+            Ok(Precondition::Proceed)
+        );
+        assert_eq!(
+            check(
+                &[(header::IF_UNMODIFIED_SINCE, "Sun, 14 Jan 2024 10:30:00 GMT")],
+                true
+            ),
+            Err(ErrorCode::Conflict)
+        );
+        // If-None-Match takes precedence over If-Modified-Since
+        assert_eq!(
+            check(
+                &[
+                    (header::IF_NONE_MATCH, "\"xyz\""),
+                    (header::IF_MODIFIED_SINCE, last_modified.as_str())
+                ],
+                true
+            ),
+            Ok(Precondition::Proceed)
+        );
+        // Unparseable dates are ignored
+        assert_eq!(
+            check(&[(header::IF_MODIFIED_SINCE, "yesterday")], true),
+            Ok(Precondition::Proceed)
         );
     }
 
-    response
-        .body(Body::from(data))
-        .map_err(|e| S3Error::Internal(e.to_string()))
-}
+    #[test]
+    fn test_conditional_put() {
+        let create_only = conditional(&[(header::IF_NONE_MATCH, "*")]);
+        assert!(evaluate_preconditions(&create_only, None, false).is_ok());
 
-/// DELETE /:bucket/*key - Delete object
-#[instrument(skip(state))]
-async fn delete_object(
-    State(state): State<Arc<AppState>>,
-    Path((bucket, key)): Path<(String, String)>,
-) -> S3Result<impl IntoResponse> {
-    validate_object_key(&key)?;
-    info!(bucket = %bucket, key = %key, "Deleting object");
+        let existing = ObjectMetadata {
+            key: "k".to_string(),
+            size: 3,
+            content_type: "text/plain".to_string(),
+            etag: "abc".to_string(),
+            last_modified: "2024-01-15T10:30:00Z".to_string(),
+            expires_at: None,
+            user_metadata: UserMetadata::new(),
+            retention: None,
+            encryption: None,
+            public_read: false,
+            content_headers: ContentHeaders::default(),
+            storage_class: StorageClass::Standard,
+            restore: None,
+        };
+        assert!(matches!(
+            evaluate_preconditions(&create_only, Some(&existing), false),
+            Err(S3Error::PreconditionFailed)
+        ));
 
-    // Validate bucket exists
-    if !state.bucket_exists(&bucket).await? {
-        return Err(S3Error::NoSuchBucket(bucket));
+        // Overwrites conditional on an ETag fail once the object is gone
+        let overwrite = conditional(&[(header::IF_MATCH, "\"abc\"")]);
+        assert!(has_preconditions(&overwrite));
+        assert!(matches!(
+            evaluate_preconditions(&overwrite, None, false),
+            Err(S3Error::PreconditionFailed)
+        ));
+
+        let response = S3Error::PreconditionFailed.into_response();
+        assert_eq!(response.status(), StatusCode::PRECONDITION_FAILED);
     }
 
-    // Delete object (idempotent - don't error if not found)
-    state.delete_object(&bucket, &key).await?;
+    #[tokio::test]
+    async fn test_put_object_if() {
+        let state = AppState::new();
+        state.create_bucket(DEFAULT_TENANT, "data").await.unwrap();
+        let create_only = conditional(&[(header::IF_NONE_MATCH, "*")]);
 
-    Ok(StatusCode::NO_CONTENT)
-}
+        let mut results = Vec::new();
+        for body in ["first", "second"] {
+            let result = state
+                .put_object_if(
+                    DEFAULT_TENANT,
+                    "data",
+                    "report.txt",
+                    Bytes::from(body),
+                    "text/plain",
+                    None,
+                    &UserMetadata::new(),
+                    &ContentHeaders::default(),
+                    None,
+                    None,
+                    |current| evaluate_preconditions(&create_only, current, false).map(|_| ()),
+                )
+                .await;
+            results.push(result);
+        }
+        assert!(results[0].is_ok());
+        assert!(matches!(results[1], Err(S3Error::PreconditionFailed)));
 
-/// HEAD /:bucket/*key - Get object metadata
-#[instrument(skip(state))]
-async fn head_object(
-    State(state): State<Arc<AppState>>,
-    Path((bucket, key)): Path<(String, String)>,
-) -> S3Result<Response> {
-    validate_object_key(&key)?;
-    debug!(bucket = %bucket, key = %key, "Head object");
+        let stored = state
+            .get_object_metadata(DEFAULT_TENANT, "data", "report.txt")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored.size, 5);
+    }
 
-    // Validate bucket exists
-    if !state.bucket_exists(&bucket).await? {
-        return Err(S3Error::NoSuchBucket(bucket));
+    #[test]
+    fn test_bucket_logging_status_xml() {
+        let body = r#"<BucketLoggingStatus xmlns="http://s3.amazonaws.com/doc/2006-03-01/">
+  <LoggingEnabled>
+    <TargetBucket>logs</TargetBucket>
+    <TargetPrefix>site&amp;a/</TargetPrefix>
+  </LoggingEnabled>
+</BucketLoggingStatus>"#;
+        let status = BucketLoggingStatus::from_xml(body).unwrap();
+        assert_eq!(
+            status.logging,
+            Some(BucketLogging {
+                target_bucket: "logs".to_string(),
+                target_prefix: "site&a/".to_string(),
+            })
+        );
+        assert_eq!(
+            BucketLoggingStatus::from_xml(&status.to_xml()).unwrap(),
+            status
+        );
+
+        // Empty status disables logging
+        let disabled = BucketLoggingStatus::from_xml(
+            r#"<BucketLoggingStatus xmlns="http://s3.amazonaws.com/doc/2006-03-01/" />"#,
+        )
+        .unwrap();
+        assert_eq!(disabled.logging, None);
+        assert!(!disabled.to_xml().contains("LoggingEnabled"));
+
+        assert!(BucketLoggingStatus::from_xml("").is_err());
+        assert!(BucketLoggingStatus::from_xml(
+            "<BucketLoggingStatus><LoggingEnabled></LoggingEnabled></BucketLoggingStatus>"
+        )
+        .is_err());
+        assert!(BucketLoggingStatus::from_xml(
+            "<BucketLoggingStatus><LoggingEnabled><TargetBucket>logs</TargetBucket>\
+             <TargetPrefix>../x</TargetPrefix></LoggingEnabled></BucketLoggingStatus>"
+        )
+        .is_err());
     }
 
-    // Get object metadata
-    let metadata = state
-        .get_object_metadata(&bucket, &key)
-        .await?
-        .ok_or_else(|| S3Error::NoSuchKey(key.clone()))?;
+    #[test]
+    fn test_object_lock_configuration_xml() {
+        let body = r#"<ObjectLockConfiguration xmlns="http://s3.amazonaws.com/doc/2006-03-01/">
+  <ObjectLockEnabled>Enabled</ObjectLockEnabled>
+  <Rule>
+    <DefaultRetention>
+      <Mode>COMPLIANCE</Mode>
+      <Years>2</Years>
+    </DefaultRetention>
+  </Rule>
+</ObjectLockConfiguration>"#;
+        let configuration = ObjectLockConfiguration::from_xml(body).unwrap();
+        assert_eq!(
+            configuration.config.default_retention,
+            Some(DefaultRetention {
+                mode: RetentionMode::Compliance,
+                days: 730,
+            })
+        );
+        assert_eq!(
+            ObjectLockConfiguration::from_xml(&configuration.to_xml()).unwrap(),
+            configuration
+        );
 
-    Response::builder()
-        .status(StatusCode::OK)
-        .header(header::CONTENT_TYPE, &metadata.content_type)
-        .header(header::CONTENT_LENGTH, metadata.size)
-        .header(header::ETAG, format!("\"{}\"", metadata.etag))
-        .header(header::LAST_MODIFIED, &metadata.last_modified)
-        .body(Body::empty())
-        .map_err(|e| S3Error::Internal(e.to_string()))
-}
+        // Enabling without a rule sets no default retention
+        let enabled = ObjectLockConfiguration::from_xml(
+            "<ObjectLockConfiguration><ObjectLockEnabled>Enabled</ObjectLockEnabled></ObjectLockConfiguration>",
+        )
+        .unwrap();
+        assert_eq!(enabled.config, ObjectLockConfig::default());
+        assert!(!enabled.to_xml().contains("<Rule>"));
 
-// =============================================================================
-// HELPERS
-// =============================================================================
+        assert!(ObjectLockConfiguration::from_xml("<ObjectLockConfiguration/>").is_err());
+        for retention in [
+            "<Mode>GOVERNANCE</Mode>",
+            "<Mode>LEGAL</Mode><Days>1</Days>",
+            "<Mode>GOVERNANCE</Mode><Days>0</Days>",
+            "<Mode>GOVERNANCE</Mode><Days>1</Days><Years>1</Years>",
+        ] {
+            let body = format!(
+                "<ObjectLockConfiguration><ObjectLockEnabled>Enabled</ObjectLockEnabled>\
+                 <Rule><DefaultRetention>{}</DefaultRetention></Rule></ObjectLockConfiguration>",
+                retention
+            );
+            assert!(
+                ObjectLockConfiguration::from_xml(&body).is_err(),
+                "{}",
+                retention
+            );
+        }
+    }
 
-/// Parse Range header (e.g., "bytes=0-999")
-fn parse_range_header(header: &str, total_size: u64) -> Option<(u64, u64)> {
-    let header = header.strip_prefix("bytes=")?;
-    let parts: Vec<&str> = header.split('-').collect();
+    #[test]
+    fn test_retention_xml() {
+        let body = r#"<Retention xmlns="http://s3.amazonaws.com/doc/2006-03-01/">
+  <Mode>GOVERNANCE</Mode>
+  <RetainUntilDate>2027-03-01T12:00:00.000Z</RetainUntilDate>
+</Retention>"#;
+        let request = Retention::from_xml(body).unwrap();
+        assert_eq!(request.retention.mode, RetentionMode::Governance);
+        assert_eq!(
+            retention_date(request.retention.retain_until),
+            "2027-03-01T12:00:00.000Z"
+        );
+        assert_eq!(Retention::from_xml(&request.to_xml()).unwrap(), request);
 
-    if parts.len() != 2 {
-        return None;
+        assert!(Retention::from_xml(
+            "<Retention><Mode>GOVERNANCE</Mode><RetainUntilDate>soon</RetainUntilDate></Retention>"
+        )
+        .is_err());
     }
 
-    let (start, end): (u64, u64) = if parts[0].is_empty() {
-        // Suffix range: -500 means last 500 bytes
-        let suffix: u64 = parts[1].parse().ok()?;
-        (total_size.saturating_sub(suffix), total_size - 1)
-    } else {
-        let start: u64 = parts[0].parse().ok()?;
-        let end: u64 = if parts[1].is_empty() {
-            total_size - 1
-        } else {
-            parts[1].parse().ok()?
+    #[test]
+    fn test_bucket_cache_policy_xml() {
+        let policy = BucketCachePolicy {
+            cache_control: Some("public, max-age=3600".to_string()),
         };
-        (start, end)
-    };
+        assert_eq!(
+            BucketCachePolicy::from_xml(&policy.to_xml()).unwrap(),
+            policy
+        );
 
-    if start > end || start >= total_size {
-        return None;
+        // An empty policy removes the default
+        let empty = BucketCachePolicy::from_xml(
+            "<CachePolicy><CacheControl> </CacheControl></CachePolicy>",
+        )
+        .unwrap();
+        assert_eq!(empty.cache_control, None);
+        assert!(!empty.to_xml().contains("CacheControl"));
+        assert_eq!(
+            BucketCachePolicy::from_xml("<CachePolicy/>")
+                .unwrap()
+                .cache_control,
+            None
+        );
+
+        assert!(BucketCachePolicy::from_xml("").is_err());
+        assert!(BucketCachePolicy::from_xml(
+            "<CachePolicy><CacheControl>max-age=60\u{7}</CacheControl></CachePolicy>"
+        )
+        .is_err());
     }
 
-    Some((start, end.min(total_size - 1)))
-}
+    #[test]
+    fn test_bucket_lifecycle_configuration_xml() {
+        let body = r#"<LifecycleConfiguration xmlns="http://s3.amazonaws.com/doc/2006-03-01/">
+  <Rule>
+    <ID>archive-logs</ID>
+    <Filter><Prefix>logs/</Prefix></Filter>
+    <Status>Enabled</Status>
+    <Transition><Days>30</Days><StorageClass>ARCHIVE</StorageClass></Transition>
+  </Rule>
+  <Rule>
+    <Prefix>tmp&amp;old/</Prefix>
+    <Status>Disabled</Status>
+    <Transition><Days>7</Days><StorageClass>ARCHIVE</StorageClass></Transition>
+  </Rule>
+</LifecycleConfiguration>"#;
+        let configuration = BucketLifecycleConfiguration::from_xml(body).unwrap();
+        assert_eq!(
+            configuration.rules,
+            vec![
+                LifecycleRule {
+                    id: "archive-logs".to_string(),
+                    enabled: true,
+                    prefix: "logs/".to_string(),
+                    days: 30,
+                    storage_class: StorageClass::Archive,
+                },
+                LifecycleRule {
+                    id: "rule-2".to_string(),
+                    enabled: false,
+                    prefix: "tmp&old/".to_string(),
+                    days: 7,
+                    storage_class: StorageClass::Archive,
+                },
+            ]
+        );
+        assert_eq!(
+            BucketLifecycleConfiguration::from_xml(&configuration.to_xml()).unwrap(),
+            configuration
+        );
 
-/// Object metadata returned by storage
-#[derive(Debug, Clone)]
-pub struct ObjectMetadata {
-    pub key: String,
-    pub size: u64,
-    pub content_type: String,
-    pub etag: String,
-    pub last_modified: String,
-}
+        let rule = |inner: &str| {
+            BucketLifecycleConfiguration::from_xml(&format!(
+                "<LifecycleConfiguration><Rule><Status>Enabled</Status>{}</Rule></LifecycleConfiguration>",
+                inner
+            ))
+        };
+        assert!(matches!(
+            rule("<Transition><Days>1</Days><StorageClass>GLACIER</StorageClass></Transition>"),
+            Err(S3Error::InvalidStorageClass(_))
+        ));
+        assert!(matches!(
+            rule("<Expiration><Days>1</Days></Expiration>"),
+            Err(S3Error::NotImplemented(_))
+        ));
+        assert!(matches!(
+            rule("<Transition><StorageClass>ARCHIVE</StorageClass></Transition>"),
+            Err(S3Error::MalformedXml(_))
+        ));
+        assert!(BucketLifecycleConfiguration::from_xml("<LifecycleConfiguration/>").is_err());
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn test_bucket_cors_configuration_xml() {
+        let body = r#"<CORSConfiguration xmlns="http://s3.amazonaws.com/doc/2006-03-01/">
+  <CORSRule>
+    <ID>web-app</ID>
+    <AllowedOrigin>https://app.example.com</AllowedOrigin>
+    <AllowedOrigin>https://*.example.org</AllowedOrigin>
+    <AllowedMethod>GET</AllowedMethod>
+    <AllowedMethod>PUT</AllowedMethod>
+    <AllowedHeader>*</AllowedHeader>
+    <ExposeHeader>ETag</ExposeHeader>
+    <MaxAgeSeconds>3000</MaxAgeSeconds>
+  </CORSRule>
+  <CORSRule>
+    <AllowedOrigin>*</AllowedOrigin>
+    <AllowedMethod>HEAD</AllowedMethod>
+  </CORSRule>
+</CORSConfiguration>"#;
+        let configuration = BucketCorsConfiguration::from_xml(body).unwrap();
+        assert_eq!(
+            configuration.rules,
+            vec![
+                CorsRule {
+                    id: Some("web-app".to_string()),
+                    allowed_origins: vec![
+                        "https://app.example.com".to_string(),
+                        "https://*.example.org".to_string(),
+                    ],
+                    allowed_methods: vec!["GET".to_string(), "PUT".to_string()],
+                    allowed_headers: vec!["*".to_string()],
+                    expose_headers: vec!["ETag".to_string()],
+                    max_age_secs: Some(3000),
+                },
+                CorsRule {
+                    allowed_origins: vec!["*".to_string()],
+                    allowed_methods: vec!["HEAD".to_string()],
+                    ..Default::default()
+                },
+            ]
+        );
+        assert_eq!(
+            BucketCorsConfiguration::from_xml(&configuration.to_xml()).unwrap(),
+            configuration
+        );
+
+        assert!(matches!(
+            BucketCorsConfiguration::from_xml("<CORSConfiguration></CORSConfiguration>"),
+            Err(S3Error::MalformedXml(_))
+        ));
+        assert!(matches!(
+            BucketCorsConfiguration::from_xml(
+                "<CORSConfiguration><CORSRule><AllowedOrigin>*</AllowedOrigin><AllowedMethod>PATCH</AllowedMethod></CORSRule></CORSConfiguration>"
+            ),
+            Err(S3Error::InvalidRequest(_))
+        ));
+        assert!(matches!(
+            BucketCorsConfiguration::from_xml(
+                "<CORSConfiguration><CORSRule><AllowedMethod>GET</AllowedMethod></CORSRule></CORSConfiguration>"
+            ),
+            Err(S3Error::InvalidRequest(_))
+        ));
+    }
 
     #[test]
-    fn test_parse_range_header() {
-        // Full range
-        assert_eq!(parse_range_header("bytes=0-499", 1000), Some((0, 499)));
+    fn test_restore_request_xml() {
+        let request = RestoreRequest::from_xml(
+            "<RestoreRequest><Days>3</Days><GlacierJobParameters><Tier>Standard</Tier></GlacierJobParameters></RestoreRequest>",
+        )
+        .unwrap();
+        assert_eq!(request, RestoreRequest { days: 3 });
 
-        // Open-ended range
-        assert_eq!(parse_range_header("bytes=500-", 1000), Some((500, 999)));
+        assert!(RestoreRequest::from_xml("<RestoreRequest></RestoreRequest>").is_err());
+        assert!(
+            RestoreRequest::from_xml("<RestoreRequest><Days>0</Days></RestoreRequest>").is_err()
+        );
+        assert!(
+            RestoreRequest::from_xml("<RestoreRequest><Days>366</Days></RestoreRequest>").is_err()
+        );
+    }
 
-        // Suffix range
-        assert_eq!(parse_range_header("bytes=-500", 1000), Some((500, 999)));
+    #[test]
+    fn test_access_control_policy_xml() {
+        for acl in [CannedAcl::Private, CannedAcl::PublicRead] {
+            let policy = AccessControlPolicy {
+                owner: "acme".to_string(),
+                acl,
+            };
+            assert_eq!(
+                AccessControlPolicy::acl_from_xml(&policy.to_xml()).unwrap(),
+                acl
+            );
+        }
+        assert!(AccessControlPolicy {
+            owner: "acme".to_string(),
+            acl: CannedAcl::PublicRead,
+        }
+        .to_xml()
+        .contains(ALL_USERS_URI));
 
-        // Invalid range
-        assert_eq!(parse_range_header("bytes=500-100", 1000), None);
+        let grant = |uri: &str, permission: &str| {
+            format!(
+                "<AccessControlPolicy><AccessControlList><Grant><Grantee><URI>{}</URI></Grantee>\
+                 <Permission>{}</Permission></Grant></AccessControlList></AccessControlPolicy>",
+                uri, permission
+            )
+        };
+        assert_eq!(
+            AccessControlPolicy::acl_from_xml(&grant(ALL_USERS_URI, "READ")).unwrap(),
+            CannedAcl::PublicRead
+        );
+        assert!(AccessControlPolicy::acl_from_xml(&grant(ALL_USERS_URI, "WRITE")).is_err());
+        assert!(AccessControlPolicy::acl_from_xml(&grant(
+            "http://acs.amazonaws.com/groups/global/AuthenticatedUsers",
+            "READ"
+        ))
+        .is_err());
+        assert!(AccessControlPolicy::acl_from_xml("<Owner/>").is_err());
 
-        // Out of bounds
-        assert_eq!(parse_range_header("bytes=1500-", 1000), None);
+        let mut headers = HeaderMap::new();
+        assert!(requested_acl(&headers, "").is_err());
+        headers.insert(ACL_HEADER, "public-read".parse().unwrap());
+        // The header wins over the body
+        assert_eq!(
+            requested_acl(&headers, &grant(ALL_USERS_URI, "WRITE")).unwrap(),
+            CannedAcl::PublicRead
+        );
     }
 
     #[test]
-    fn test_list_objects_response_xml() {
-        let response = ListObjectsV2Response {
-            name: "test-bucket".to_string(),
-            prefix: Some("prefix/".to_string()),
+    fn test_list_deleted_objects_xml() {
+        let response = ListDeletedObjectsResponse {
+            name: "bucket".to_string(),
+            prefix: "docs/".to_string(),
             max_keys: 1000,
             is_truncated: false,
-            key_count: 1,
-            contents: vec![ObjectInfo {
-                key: "prefix/file.txt".to_string(),
-                last_modified: "2024-01-01T00:00:00Z".to_string(),
+            objects: vec![DeletedObjectInfo {
+                key: "docs/a&b.txt".to_string(),
+                version_id: "8f0e3a2c-5d41-4e8b-9a57-0c3f1d2b7e64".to_string(),
+                deleted_at: "2024-01-15T12:00:00+00:00".to_string(),
                 etag: "abc123".to_string(),
-                size: 1024,
-                storage_class: "STANDARD".to_string(),
+                size: 42,
             }],
-            common_prefixes: Vec::new(),
-            continuation_token: None,
-            next_continuation_token: None,
         };
-
         let xml = response.to_xml();
-        assert!(xml.contains("<Name>test-bucket</Name>"));
-        assert!(xml.contains("<Key>prefix/file.txt</Key>"));
-        assert!(xml.contains("<Size>1024</Size>"));
+        assert!(xml.contains("<ListDeletedObjectsResult"));
+        assert!(xml.contains("<Key>docs/a&amp;b.txt</Key>"));
+        assert!(xml.contains("<VersionId>8f0e3a2c-5d41-4e8b-9a57-0c3f1d2b7e64</VersionId>"));
+        assert!(xml.contains("<Size>42</Size>"));
+    }
+
+    #[test]
+    fn test_is_write_request() {
+        assert!(is_write_request(&Method::PUT, None));
+        assert!(is_write_request(&Method::DELETE, None));
+        assert!(is_write_request(&Method::POST, Some("delete")));
+        assert!(is_write_request(&Method::PUT, Some("logging")));
+        assert!(is_write_request(
+            &Method::POST,
+            Some("restore&versionId=abc")
+        ));
+        assert!(!is_write_request(
+            &Method::POST,
+            Some("select&select-type=2")
+        ));
+        assert!(!is_write_request(&Method::GET, None));
+        assert!(!is_write_request(&Method::HEAD, Some("logging")));
     }
 }
@@ -0,0 +1,843 @@
+//! S3 XML documents
+//!
+//! Response bodies of the S3 API as serde models, written with quick-xml
+//! instead of by hand so every value is escaped and element names match the
+//! AWS schema. [`AwsErrorCode`] lists the error codes of the S3 API with the
+//! status and message the gateway answers them with.
+//!
+//! The models also deserialize, which the tests use to check them against
+//! responses captured from AWS (`tests/fixtures/s3_xml`). Configuration
+//! documents with request parsers of their own (logging, object lock, ACLs)
+//! are still rendered in [`crate::s3_api`].
+
+use axum::http::StatusCode;
+use quick_xml::se::{QuoteLevel, Serializer};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::s3_api::{S3Error, S3Result};
+
+/// Namespace of S3 documents
+pub const S3_XMLNS: &str = "http://s3.amazonaws.com/doc/2006-03-01/";
+
+const XML_DECLARATION: &str = r#"<?xml version="1.0" encoding="UTF-8"?>"#;
+
+/// Render a document with the XML declaration, indented by two spaces
+pub fn to_xml<T: Serialize>(document: &T) -> String {
+    let mut xml = String::from(XML_DECLARATION);
+    xml.push('\n');
+    let mut serializer = Serializer::new(&mut xml);
+    serializer.indent(' ', 2);
+    // Escape quotes and apostrophes in text as well, like AWS does
+    serializer.set_quote_level(QuoteLevel::Full);
+    document
+        .serialize(serializer)
+        .expect("S3 XML documents always serialize");
+    xml
+}
+
+/// Parse a document
+pub fn from_xml<T: DeserializeOwned>(body: &str) -> S3Result<T> {
+    quick_xml::de::from_str(body).map_err(|e| S3Error::MalformedXml(e.to_string()))
+}
+
+/// `xmlns` attribute of a document element, always [`S3_XMLNS`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct S3Namespace;
+
+impl Serialize for S3Namespace {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(S3_XMLNS)
+    }
+}
+
+impl<'de> Deserialize<'de> for S3Namespace {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(|_| S3Namespace)
+    }
+}
+
+/// `ListBucketResult` of a ListObjectsV2
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename = "ListBucketResult", rename_all = "PascalCase")]
+pub struct ListBucketResult {
+    #[serde(rename = "@xmlns", default)]
+    pub xmlns: S3Namespace,
+    pub name: String,
+    #[serde(default)]
+    pub prefix: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delimiter: Option<String>,
+    pub max_keys: i32,
+    pub is_truncated: bool,
+    #[serde(default)]
+    pub key_count: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub continuation_token: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start_after: Option<String>,
+    #[serde(default)]
+    pub contents: Vec<ListedObject>,
+    #[serde(default)]
+    pub common_prefixes: Vec<CommonPrefix>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_continuation_token: Option<String>,
+}
+
+/// `Contents` entry of a listing
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ListedObject {
+    pub key: String,
+    pub last_modified: String,
+    #[serde(rename = "ETag")]
+    pub etag: String,
+    pub size: u64,
+    #[serde(default)]
+    pub storage_class: String,
+}
+
+/// `CommonPrefixes` entry of a listing
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct CommonPrefix {
+    pub prefix: String,
+}
+
+/// `ListDeletedObjectsResult` of a trash listing (CyxCloud extension)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename = "ListDeletedObjectsResult", rename_all = "PascalCase")]
+pub struct ListDeletedObjectsResult {
+    #[serde(rename = "@xmlns", default)]
+    pub xmlns: S3Namespace,
+    pub name: String,
+    #[serde(default)]
+    pub prefix: String,
+    pub max_keys: i32,
+    pub is_truncated: bool,
+    #[serde(rename = "DeletedObject", default)]
+    pub deleted_objects: Vec<DeletedObject>,
+}
+
+/// `DeletedObject` entry of a trash listing
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct DeletedObject {
+    pub key: String,
+    pub version_id: String,
+    pub deleted_at: String,
+    #[serde(rename = "ETag")]
+    pub etag: String,
+    pub size: u64,
+}
+
+/// `CopyObjectResult` of a CopyObject
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename = "CopyObjectResult", rename_all = "PascalCase")]
+pub struct CopyObjectResult {
+    #[serde(rename = "@xmlns", default)]
+    pub xmlns: S3Namespace,
+    pub last_modified: String,
+    /// Quoted, like the `ETag` header
+    #[serde(rename = "ETag")]
+    pub etag: String,
+}
+
+/// `DeleteResult` of a multi-object delete
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename = "DeleteResult")]
+pub struct DeleteResult {
+    #[serde(rename = "@xmlns", default)]
+    pub xmlns: S3Namespace,
+    #[serde(rename = "Deleted", default)]
+    pub deleted: Vec<DeletedKey>,
+    #[serde(rename = "Error", default)]
+    pub errors: Vec<KeyError>,
+}
+
+/// `Deleted` entry of a multi-object delete
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct DeletedKey {
+    pub key: String,
+}
+
+/// `Error` entry of a multi-object delete
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct KeyError {
+    pub key: String,
+    pub code: String,
+    pub message: String,
+}
+
+/// `InitiateMultipartUploadResult` of a CreateMultipartUpload
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename = "InitiateMultipartUploadResult", rename_all = "PascalCase")]
+pub struct InitiateMultipartUploadResult {
+    #[serde(rename = "@xmlns", default)]
+    pub xmlns: S3Namespace,
+    pub bucket: String,
+    pub key: String,
+    pub upload_id: String,
+}
+
+/// `CompleteMultipartUploadResult` of a CompleteMultipartUpload
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename = "CompleteMultipartUploadResult", rename_all = "PascalCase")]
+pub struct CompleteMultipartUploadResult {
+    #[serde(rename = "@xmlns", default)]
+    pub xmlns: S3Namespace,
+    pub location: String,
+    pub bucket: String,
+    pub key: String,
+    /// Quoted, `"<hash>-<parts>"`
+    #[serde(rename = "ETag")]
+    pub etag: String,
+}
+
+/// `Error` body of a failed request
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename = "Error", rename_all = "PascalCase")]
+pub struct ErrorResponse {
+    pub code: String,
+    pub message: String,
+    /// Bucket or object the request was for
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resource: Option<String>,
+    /// Shared error taxonomy code (also in the `x-cyxcloud-error-code` header)
+    #[serde(
+        rename = "CyxCloudCode",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub cyxcloud_code: Option<String>,
+    #[serde(default)]
+    pub request_id: String,
+}
+
+/// Error code of the S3 API
+///
+/// Covers the codes of the AWS error reference that apply to the operations
+/// CyxCloud implements or rejects. The gateway answers with the AWS status
+/// except for `SlowDown` (429 rather than 503, see [`crate::rate_limit`]) and
+/// `EntityTooLarge` (413 rather than 400); SDKs go by the code for both.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AwsErrorCode {
+    AccessDenied,
+    AccountProblem,
+    AllAccessDisabled,
+    AuthorizationHeaderMalformed,
+    BadDigest,
+    BucketAlreadyExists,
+    BucketAlreadyOwnedByYou,
+    BucketNotEmpty,
+    CredentialsNotSupported,
+    EntityTooSmall,
+    EntityTooLarge,
+    ExpiredToken,
+    IncompleteBody,
+    InternalError,
+    InvalidAccessKeyId,
+    InvalidArgument,
+    InvalidBucketName,
+    InvalidBucketState,
+    InvalidDigest,
+    InvalidEncryptionAlgorithmError,
+    InvalidObjectState,
+    InvalidPart,
+    InvalidPartOrder,
+    InvalidRange,
+    InvalidRequest,
+    InvalidStorageClass,
+    InvalidTargetBucketForLogging,
+    InvalidToken,
+    InvalidUri,
+    KeyTooLongError,
+    MalformedPolicy,
+    MalformedXml,
+    MaxMessageLengthExceeded,
+    MetadataTooLarge,
+    MethodNotAllowed,
+    MissingContentLength,
+    MissingRequestBodyError,
+    MissingSecurityHeader,
+    NoSuchBucket,
+    NoSuchBucketPolicy,
+    NoSuchCorsConfiguration,
+    NoSuchKey,
+    NoSuchLifecycleConfiguration,
+    NoSuchUpload,
+    NoSuchVersion,
+    NotImplemented,
+    ObjectLockConfigurationNotFoundError,
+    OperationAborted,
+    PreconditionFailed,
+    RequestTimeout,
+    RequestTimeTooSkewed,
+    RestoreAlreadyInProgress,
+    ServiceUnavailable,
+    SignatureDoesNotMatch,
+    SlowDown,
+    TooManyBuckets,
+    UnexpectedContent,
+}
+
+impl AwsErrorCode {
+    /// Every code, for lookups by name
+    pub const ALL: &'static [AwsErrorCode] = &[
+        AwsErrorCode::AccessDenied,
+        AwsErrorCode::AccountProblem,
+        AwsErrorCode::AllAccessDisabled,
+        AwsErrorCode::AuthorizationHeaderMalformed,
+        AwsErrorCode::BadDigest,
+        AwsErrorCode::BucketAlreadyExists,
+        AwsErrorCode::BucketAlreadyOwnedByYou,
+        AwsErrorCode::BucketNotEmpty,
+        AwsErrorCode::CredentialsNotSupported,
+        AwsErrorCode::EntityTooSmall,
+        AwsErrorCode::EntityTooLarge,
+        AwsErrorCode::ExpiredToken,
+        AwsErrorCode::IncompleteBody,
+        AwsErrorCode::InternalError,
+        AwsErrorCode::InvalidAccessKeyId,
+        AwsErrorCode::InvalidArgument,
+        AwsErrorCode::InvalidBucketName,
+        AwsErrorCode::InvalidBucketState,
+        AwsErrorCode::InvalidDigest,
+        AwsErrorCode::InvalidEncryptionAlgorithmError,
+        AwsErrorCode::InvalidObjectState,
+        AwsErrorCode::InvalidPart,
+        AwsErrorCode::InvalidPartOrder,
+        AwsErrorCode::InvalidRange,
+        AwsErrorCode::InvalidRequest,
+        AwsErrorCode::InvalidStorageClass,
+        AwsErrorCode::InvalidTargetBucketForLogging,
+        AwsErrorCode::InvalidToken,
+        AwsErrorCode::InvalidUri,
+        AwsErrorCode::KeyTooLongError,
+        AwsErrorCode::MalformedPolicy,
+        AwsErrorCode::MalformedXml,
+        AwsErrorCode::MaxMessageLengthExceeded,
+        AwsErrorCode::MetadataTooLarge,
+        AwsErrorCode::MethodNotAllowed,
+        AwsErrorCode::MissingContentLength,
+        AwsErrorCode::MissingRequestBodyError,
+        AwsErrorCode::MissingSecurityHeader,
+        AwsErrorCode::NoSuchBucket,
+        AwsErrorCode::NoSuchBucketPolicy,
+        AwsErrorCode::NoSuchCorsConfiguration,
+        AwsErrorCode::NoSuchKey,
+        AwsErrorCode::NoSuchLifecycleConfiguration,
+        AwsErrorCode::NoSuchUpload,
+        AwsErrorCode::NoSuchVersion,
+        AwsErrorCode::NotImplemented,
+        AwsErrorCode::ObjectLockConfigurationNotFoundError,
+        AwsErrorCode::OperationAborted,
+        AwsErrorCode::PreconditionFailed,
+        AwsErrorCode::RequestTimeout,
+        AwsErrorCode::RequestTimeTooSkewed,
+        AwsErrorCode::RestoreAlreadyInProgress,
+        AwsErrorCode::ServiceUnavailable,
+        AwsErrorCode::SignatureDoesNotMatch,
+        AwsErrorCode::SlowDown,
+        AwsErrorCode::TooManyBuckets,
+        AwsErrorCode::UnexpectedContent,
+    ];
+
+    /// Code, status and default message
+    fn spec(self) -> (&'static str, StatusCode, &'static str) {
+        use AwsErrorCode::*;
+        match self {
+            AccessDenied => ("AccessDenied", StatusCode::FORBIDDEN, "Access Denied"),
+            AccountProblem => (
+                "AccountProblem",
+                StatusCode::FORBIDDEN,
+                "There is a problem with your account that prevents the operation from completing",
+            ),
+            AllAccessDisabled => (
+                "AllAccessDisabled",
+                StatusCode::FORBIDDEN,
+                "All access to this resource has been disabled",
+            ),
+            AuthorizationHeaderMalformed => (
+                "AuthorizationHeaderMalformed",
+                StatusCode::BAD_REQUEST,
+                "The authorization header you provided is invalid",
+            ),
+            BadDigest => (
+                "BadDigest",
+                StatusCode::BAD_REQUEST,
+                "The Content-MD5 you specified did not match what we received",
+            ),
+            BucketAlreadyExists => (
+                "BucketAlreadyExists",
+                StatusCode::CONFLICT,
+                "The specified bucket already exists",
+            ),
+            BucketAlreadyOwnedByYou => (
+                "BucketAlreadyOwnedByYou",
+                StatusCode::CONFLICT,
+                "The bucket you tried to create already exists, and you own it",
+            ),
+            BucketNotEmpty => (
+                "BucketNotEmpty",
+                StatusCode::CONFLICT,
+                "The bucket is not empty",
+            ),
+            CredentialsNotSupported => (
+                "CredentialsNotSupported",
+                StatusCode::BAD_REQUEST,
+                "This request does not support credentials",
+            ),
+            EntityTooSmall => (
+                "EntityTooSmall",
+                StatusCode::BAD_REQUEST,
+                "Your proposed upload is smaller than the minimum allowed object size",
+            ),
+            EntityTooLarge => (
+                "EntityTooLarge",
+                StatusCode::PAYLOAD_TOO_LARGE,
+                "Your proposed upload exceeds the maximum allowed object size",
+            ),
+            ExpiredToken => (
+                "ExpiredToken",
+                StatusCode::BAD_REQUEST,
+                "The provided token has expired",
+            ),
+            IncompleteBody => (
+                "IncompleteBody",
+                StatusCode::BAD_REQUEST,
+                "You did not provide the number of bytes specified by the Content-Length header",
+            ),
+            InternalError => (
+                "InternalError",
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "An internal error occurred",
+            ),
+            InvalidAccessKeyId => (
+                "InvalidAccessKeyId",
+                StatusCode::FORBIDDEN,
+                "The access key ID you provided does not exist in our records",
+            ),
+            InvalidArgument => ("InvalidArgument", StatusCode::BAD_REQUEST, "Invalid Argument"),
+            InvalidBucketName => (
+                "InvalidBucketName",
+                StatusCode::BAD_REQUEST,
+                "The specified bucket is not valid",
+            ),
+            InvalidBucketState => (
+                "InvalidBucketState",
+                StatusCode::CONFLICT,
+                "The request is not valid with the current state of the bucket",
+            ),
+            InvalidDigest => (
+                "InvalidDigest",
+                StatusCode::BAD_REQUEST,
+                "The Content-MD5 you specified is not valid",
+            ),
+            InvalidEncryptionAlgorithmError => (
+                "InvalidEncryptionAlgorithmError",
+                StatusCode::BAD_REQUEST,
+                "The encryption request you specified is not valid",
+            ),
+            InvalidObjectState => (
+                "InvalidObjectState",
+                StatusCode::FORBIDDEN,
+                "The operation is not valid for the current state of the object",
+            ),
+            InvalidPart => (
+                "InvalidPart",
+                StatusCode::BAD_REQUEST,
+                "One or more of the specified parts could not be found",
+            ),
+            InvalidPartOrder => (
+                "InvalidPartOrder",
+                StatusCode::BAD_REQUEST,
+                "The list of parts was not in ascending order",
+            ),
+            InvalidRange => (
+                "InvalidRange",
+                StatusCode::RANGE_NOT_SATISFIABLE,
+                "The requested range is not satisfiable",
+            ),
+            InvalidRequest => ("InvalidRequest", StatusCode::BAD_REQUEST, "Invalid Request"),
+            InvalidStorageClass => (
+                "InvalidStorageClass",
+                StatusCode::BAD_REQUEST,
+                "The storage class you specified is not valid",
+            ),
+            InvalidTargetBucketForLogging => (
+                "InvalidTargetBucketForLogging",
+                StatusCode::BAD_REQUEST,
+                "The target bucket for logging does not exist or is not owned by you",
+            ),
+            InvalidToken => (
+                "InvalidToken",
+                StatusCode::BAD_REQUEST,
+                "The provided token is malformed or otherwise invalid",
+            ),
+            InvalidUri => (
+                "InvalidURI",
+                StatusCode::BAD_REQUEST,
+                "Couldn't parse the specified URI",
+            ),
+            KeyTooLongError => ("KeyTooLongError", StatusCode::BAD_REQUEST, "Your key is too long"),
+            MalformedPolicy => (
+                "MalformedPolicy",
+                StatusCode::BAD_REQUEST,
+                "The bucket policy is not valid",
+            ),
+            MalformedXml => (
+                "MalformedXML",
+                StatusCode::BAD_REQUEST,
+                "The XML you provided was not well-formed or did not validate against our published schema",
+            ),
+            MaxMessageLengthExceeded => (
+                "MaxMessageLengthExceeded",
+                StatusCode::BAD_REQUEST,
+                "Your request was too big",
+            ),
+            MetadataTooLarge => (
+                "MetadataTooLarge",
+                StatusCode::BAD_REQUEST,
+                "Your metadata headers exceed the maximum allowed metadata size",
+            ),
+            MethodNotAllowed => (
+                "MethodNotAllowed",
+                StatusCode::METHOD_NOT_ALLOWED,
+                "The specified method is not allowed against this resource",
+            ),
+            MissingContentLength => (
+                "MissingContentLength",
+                StatusCode::LENGTH_REQUIRED,
+                "You must provide the Content-Length HTTP header",
+            ),
+            MissingRequestBodyError => (
+                "MissingRequestBodyError",
+                StatusCode::BAD_REQUEST,
+                "Request body is empty",
+            ),
+            MissingSecurityHeader => (
+                "MissingSecurityHeader",
+                StatusCode::BAD_REQUEST,
+                "Your request is missing a required header",
+            ),
+            NoSuchBucket => (
+                "NoSuchBucket",
+                StatusCode::NOT_FOUND,
+                "The specified bucket does not exist",
+            ),
+            NoSuchBucketPolicy => (
+                "NoSuchBucketPolicy",
+                StatusCode::NOT_FOUND,
+                "The bucket policy does not exist",
+            ),
+            NoSuchCorsConfiguration => (
+                "NoSuchCORSConfiguration",
+                StatusCode::NOT_FOUND,
+                "The CORS configuration does not exist",
+            ),
+            NoSuchKey => (
+                "NoSuchKey",
+                StatusCode::NOT_FOUND,
+                "The specified key does not exist",
+            ),
+            NoSuchLifecycleConfiguration => (
+                "NoSuchLifecycleConfiguration",
+                StatusCode::NOT_FOUND,
+                "The lifecycle configuration does not exist",
+            ),
+            NoSuchUpload => (
+                "NoSuchUpload",
+                StatusCode::NOT_FOUND,
+                "The specified multipart upload does not exist",
+            ),
+            NoSuchVersion => (
+                "NoSuchVersion",
+                StatusCode::NOT_FOUND,
+                "The specified version does not exist",
+            ),
+            NotImplemented => (
+                "NotImplemented",
+                StatusCode::NOT_IMPLEMENTED,
+                "A header you provided implies functionality that is not implemented",
+            ),
+            ObjectLockConfigurationNotFoundError => (
+                "ObjectLockConfigurationNotFoundError",
+                StatusCode::NOT_FOUND,
+                "Object Lock configuration does not exist for this bucket",
+            ),
+            OperationAborted => (
+                "OperationAborted",
+                StatusCode::CONFLICT,
+                "A conflicting conditional operation is currently in progress against this resource",
+            ),
+            PreconditionFailed => (
+                "PreconditionFailed",
+                StatusCode::PRECONDITION_FAILED,
+                "At least one of the preconditions you specified did not hold",
+            ),
+            RequestTimeout => (
+                "RequestTimeout",
+                StatusCode::BAD_REQUEST,
+                "Your socket connection to the server was not read from or written to within the timeout period",
+            ),
+            RequestTimeTooSkewed => (
+                "RequestTimeTooSkewed",
+                StatusCode::FORBIDDEN,
+                "The difference between the request time and the server's time is too large",
+            ),
+            RestoreAlreadyInProgress => (
+                "RestoreAlreadyInProgress",
+                StatusCode::CONFLICT,
+                "Object restore is already in progress",
+            ),
+            ServiceUnavailable => (
+                "ServiceUnavailable",
+                StatusCode::SERVICE_UNAVAILABLE,
+                "The service is unavailable, please try again",
+            ),
+            SignatureDoesNotMatch => (
+                "SignatureDoesNotMatch",
+                StatusCode::FORBIDDEN,
+                "The request signature we calculated does not match the signature you provided",
+            ),
+            SlowDown => (
+                "SlowDown",
+                StatusCode::TOO_MANY_REQUESTS,
+                "Please reduce your request rate",
+            ),
+            TooManyBuckets => (
+                "TooManyBuckets",
+                StatusCode::BAD_REQUEST,
+                "You have attempted to create more buckets than allowed",
+            ),
+            UnexpectedContent => (
+                "UnexpectedContent",
+                StatusCode::BAD_REQUEST,
+                "This request does not support content",
+            ),
+        }
+    }
+
+    /// Code as sent in `<Code>`
+    pub fn as_str(self) -> &'static str {
+        self.spec().0
+    }
+
+    /// HTTP status of the error response
+    pub fn status(self) -> StatusCode {
+        self.spec().1
+    }
+
+    /// Message of the error response when there are no details to give
+    pub fn default_message(self) -> &'static str {
+        self.spec().2
+    }
+
+    /// Look up a code by name
+    pub fn parse(code: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|c| c.as_str() == code)
+    }
+}
+
+impl std::fmt::Display for AwsErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    const AWS_LIST_BUCKET_RESULT: &str =
+        include_str!("../tests/fixtures/s3_xml/list_bucket_result.xml");
+    const AWS_COPY_OBJECT_RESULT: &str =
+        include_str!("../tests/fixtures/s3_xml/copy_object_result.xml");
+    const AWS_DELETE_RESULT: &str = include_str!("../tests/fixtures/s3_xml/delete_result.xml");
+    const AWS_ERROR: &str = include_str!("../tests/fixtures/s3_xml/error.xml");
+    const AWS_INITIATE_MULTIPART_UPLOAD_RESULT: &str =
+        include_str!("../tests/fixtures/s3_xml/initiate_multipart_upload_result.xml");
+    const AWS_COMPLETE_MULTIPART_UPLOAD_RESULT: &str =
+        include_str!("../tests/fixtures/s3_xml/complete_multipart_upload_result.xml");
+
+    /// Parse an AWS sample into the model, check it is the expected document
+    /// and that rendering and parsing it again loses nothing
+    fn assert_golden<T>(sample: &str, expected: T)
+    where
+        T: Serialize + DeserializeOwned + PartialEq + std::fmt::Debug,
+    {
+        let parsed: T = from_xml(sample).unwrap();
+        assert_eq!(parsed, expected);
+
+        let xml = to_xml(&parsed);
+        assert!(xml.starts_with(XML_DECLARATION));
+        assert_eq!(from_xml::<T>(&xml).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_list_bucket_result_golden() {
+        let object = |key: &str, last_modified: &str, etag: &str, size| ListedObject {
+            key: key.to_string(),
+            last_modified: last_modified.to_string(),
+            etag: etag.to_string(),
+            size,
+            storage_class: "STANDARD".to_string(),
+        };
+        assert_golden(
+            AWS_LIST_BUCKET_RESULT,
+            ListBucketResult {
+                name: "example-bucket".to_string(),
+                prefix: "photos/".to_string(),
+                delimiter: Some("/".to_string()),
+                max_keys: 1000,
+                is_truncated: true,
+                key_count: 3,
+                contents: vec![
+                    object(
+                        "photos/my-image.jpg",
+                        "2009-10-12T17:50:30.000Z",
+                        "\"fba9dede5f27731c9771645a39863328\"",
+                        434234,
+                    ),
+                    object(
+                        "photos/my-third-image.jpg",
+                        "2009-10-12T17:50:30.000Z",
+                        "\"1b2cf535f27731c974343645a3985328\"",
+                        64994,
+                    ),
+                ],
+                common_prefixes: vec![CommonPrefix {
+                    prefix: "photos/2006/".to_string(),
+                }],
+                next_continuation_token: Some(
+                    "1ueGcxLPRx1Tr/XYExHnhbYLgveDs2J/wm36Hy4vbOwM=".to_string(),
+                ),
+                ..Default::default()
+            },
+        );
+    }
+
+    #[test]
+    fn test_copy_object_result_golden() {
+        assert_golden(
+            AWS_COPY_OBJECT_RESULT,
+            CopyObjectResult {
+                xmlns: S3Namespace,
+                last_modified: "2009-10-28T22:32:00.000Z".to_string(),
+                etag: "\"9b2cf535f27731c974343645a3985328\"".to_string(),
+            },
+        );
+    }
+
+    #[test]
+    fn test_delete_result_golden() {
+        assert_golden(
+            AWS_DELETE_RESULT,
+            DeleteResult {
+                xmlns: S3Namespace,
+                deleted: vec![DeletedKey {
+                    key: "sample1.txt".to_string(),
+                }],
+                errors: vec![KeyError {
+                    key: "sample2.txt".to_string(),
+                    code: "AccessDenied".to_string(),
+                    message: "Access Denied".to_string(),
+                }],
+            },
+        );
+    }
+
+    #[test]
+    fn test_error_golden() {
+        assert_golden(
+            AWS_ERROR,
+            ErrorResponse {
+                code: "NoSuchKey".to_string(),
+                message: "The resource you requested does not exist".to_string(),
+                resource: Some("/mybucket/myfoto.jpg".to_string()),
+                cyxcloud_code: None,
+                request_id: "4442587FB7D0A2F9".to_string(),
+            },
+        );
+    }
+
+    #[test]
+    fn test_multipart_upload_results_golden() {
+        assert_golden(
+            AWS_INITIATE_MULTIPART_UPLOAD_RESULT,
+            InitiateMultipartUploadResult {
+                xmlns: S3Namespace,
+                bucket: "example-bucket".to_string(),
+                key: "example-object".to_string(),
+                upload_id: "VXBsb2FkIElEIGZvciA2aWWpbmcncyBteS1tb3ZpZS5tMnRzIHVwbG9hZA".to_string(),
+            },
+        );
+        assert_golden(
+            AWS_COMPLETE_MULTIPART_UPLOAD_RESULT,
+            CompleteMultipartUploadResult {
+                xmlns: S3Namespace,
+                location: "http://Example-Bucket.s3.region.amazonaws.com/Example-Object"
+                    .to_string(),
+                bucket: "Example-Bucket".to_string(),
+                key: "Example-Object".to_string(),
+                etag: "\"3858f62230ac3c915f300c664312c11f-9\"".to_string(),
+            },
+        );
+    }
+
+    #[test]
+    fn test_to_xml_escapes_values() {
+        let xml = to_xml(&CopyObjectResult {
+            xmlns: S3Namespace,
+            last_modified: "2024-01-01T00:00:00Z".to_string(),
+            etag: "\"a<b>&'c'\"".to_string(),
+        });
+        assert!(
+            xml.contains(r#"<CopyObjectResult xmlns="http://s3.amazonaws.com/doc/2006-03-01/">"#)
+        );
+        assert!(xml.contains("\n  <ETag>&quot;a&lt;b&gt;&amp;&apos;c&apos;&quot;</ETag>"));
+    }
+
+    #[test]
+    fn test_from_xml_rejects_malformed_documents() {
+        assert!(matches!(
+            from_xml::<DeleteResult>("<DeleteResult><Deleted>"),
+            Err(S3Error::MalformedXml(_))
+        ));
+        assert!(from_xml::<CopyObjectResult>("<CopyObjectResult/>").is_err());
+    }
+
+    #[test]
+    fn test_aws_error_codes() {
+        let mut names = HashSet::new();
+        for &code in AwsErrorCode::ALL {
+            assert!(names.insert(code.as_str()), "duplicate {}", code);
+            assert_eq!(AwsErrorCode::parse(code.as_str()), Some(code));
+            assert!(code.status().is_client_error() || code.status().is_server_error());
+            assert!(!code.default_message().is_empty());
+        }
+        assert_eq!(
+            AwsErrorCode::parse("MalformedXML"),
+            Some(AwsErrorCode::MalformedXml)
+        );
+        assert_eq!(AwsErrorCode::parse("NoSuchThing"), None);
+
+        assert_eq!(AwsErrorCode::NoSuchKey.status(), StatusCode::NOT_FOUND);
+        assert_eq!(
+            AwsErrorCode::InvalidRange.status(),
+            StatusCode::RANGE_NOT_SATISFIABLE
+        );
+        assert_eq!(
+            AwsErrorCode::NotImplemented.status(),
+            StatusCode::NOT_IMPLEMENTED
+        );
+        assert_eq!(
+            AwsErrorCode::ServiceUnavailable.status(),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+    }
+}
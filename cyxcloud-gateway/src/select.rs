@@ -0,0 +1,1101 @@
+//! S3 Select
+//!
+//! Restricted server-side filtering for CSV and newline-delimited JSON
+//! objects (`POST /s3/{bucket}/{key}?select`). Supported SQL:
+//!
+//! ```text
+//! SELECT * | col [, col ...] FROM S3Object [[AS] alias]
+//!     [WHERE cond] [LIMIT n]
+//!
+//! cond := cond OR cond | cond AND cond | NOT cond | ( cond )
+//!       | col {= | != | <> | < | <= | > | >=} literal
+//!       | col [NOT] LIKE 'pattern' | col IS [NOT] NULL
+//! ```
+//!
+//! Columns are header names (CSV with `FileHeaderInfo` USE), positional
+//! `_1`, `_2`, ... (CSV) or top-level field names (JSON). Comparisons are
+//! numeric when the literal is a number and the value parses as one.
+//!
+//! Records are returned as plain CSV or JSON lines rather than the AWS
+//! event-stream framing. Input is fed in pieces as chunks are decoded, so
+//! memory use is bounded by the window size, not the object size.
+
+use serde_json::{Map, Value};
+use std::cmp::Ordering;
+use thiserror::Error;
+
+/// S3 Select errors
+#[derive(Error, Debug, PartialEq)]
+pub enum SelectError {
+    #[error("Invalid select request: {0}")]
+    Request(String),
+
+    #[error("Invalid SQL expression: {0}")]
+    Syntax(String),
+
+    #[error("Malformed record {record}: {message}")]
+    Record { record: u64, message: String },
+}
+
+pub type SelectResult<T> = Result<T, SelectError>;
+
+/// How the first line of a CSV object is treated (`FileHeaderInfo`)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CsvHeader {
+    /// No header; every line is a record
+    None,
+    /// Skip the header; columns are addressed by position only
+    Ignore,
+    /// Column names come from the header
+    Use,
+}
+
+/// Format of the object being queried
+#[derive(Debug, Clone, PartialEq)]
+pub enum InputFormat {
+    Csv { header: CsvHeader, delimiter: u8 },
+    JsonLines,
+}
+
+/// Format of the returned records
+#[derive(Debug, Clone, PartialEq)]
+pub enum OutputFormat {
+    Csv { delimiter: u8 },
+    JsonLines,
+}
+
+impl OutputFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            OutputFormat::Csv { .. } => "text/csv",
+            OutputFormat::JsonLines => "application/x-ndjson",
+        }
+    }
+}
+
+/// A parsed `SelectObjectContentRequest`
+#[derive(Debug, Clone)]
+pub struct SelectRequest {
+    pub query: Query,
+    pub input: InputFormat,
+    pub output: OutputFormat,
+}
+
+impl SelectRequest {
+    /// Parse the S3 request body
+    ///
+    /// ```xml
+    /// <SelectObjectContentRequest>
+    ///   <Expression>SELECT s.name FROM S3Object s WHERE s.age > 30</Expression>
+    ///   <ExpressionType>SQL</ExpressionType>
+    ///   <InputSerialization><CSV><FileHeaderInfo>USE</FileHeaderInfo></CSV></InputSerialization>
+    ///   <OutputSerialization><JSON/></OutputSerialization>
+    /// </SelectObjectContentRequest>
+    /// ```
+    pub fn from_xml(body: &str) -> SelectResult<Self> {
+        let expression = xml_value(body, "Expression")
+            .ok_or_else(|| SelectError::Request("missing Expression".to_string()))?;
+        if let Some(kind) = xml_value(body, "ExpressionType") {
+            if !kind.trim().eq_ignore_ascii_case("SQL") {
+                return Err(SelectError::Request(format!(
+                    "unsupported ExpressionType {}",
+                    kind.trim()
+                )));
+            }
+        }
+        let query = Query::parse(&xml_unescape(&expression))?;
+
+        let input_xml = xml_value(body, "InputSerialization")
+            .ok_or_else(|| SelectError::Request("missing InputSerialization".to_string()))?;
+        if xml_value(&input_xml, "CompressionType")
+            .is_some_and(|c| !c.trim().eq_ignore_ascii_case("NONE"))
+        {
+            return Err(SelectError::Request(
+                "compressed input is not supported".to_string(),
+            ));
+        }
+        let input = if let Some(csv) = xml_section(&input_xml, "CSV") {
+            let header = match xml_value(&csv, "FileHeaderInfo") {
+                None => CsvHeader::None,
+                Some(info) => match info.trim().to_ascii_uppercase().as_str() {
+                    "USE" => CsvHeader::Use,
+                    "IGNORE" => CsvHeader::Ignore,
+                    "NONE" => CsvHeader::None,
+                    other => {
+                        return Err(SelectError::Request(format!(
+                            "unsupported FileHeaderInfo {}",
+                            other
+                        )))
+                    }
+                },
+            };
+            InputFormat::Csv {
+                header,
+                delimiter: delimiter(&csv, "FieldDelimiter")?,
+            }
+        } else if let Some(json) = xml_section(&input_xml, "JSON") {
+            if xml_value(&json, "Type").is_some_and(|t| !t.trim().eq_ignore_ascii_case("LINES")) {
+                return Err(SelectError::Request(
+                    "only JSON Type LINES is supported".to_string(),
+                ));
+            }
+            InputFormat::JsonLines
+        } else {
+            return Err(SelectError::Request(
+                "InputSerialization must be CSV or JSON".to_string(),
+            ));
+        };
+
+        Ok(Self {
+            query,
+            input,
+            output: output_format(body)?,
+        })
+    }
+}
+
+fn output_format(body: &str) -> SelectResult<OutputFormat> {
+    let Some(output_xml) = xml_value(body, "OutputSerialization") else {
+        return Ok(OutputFormat::JsonLines);
+    };
+    if let Some(csv) = xml_section(&output_xml, "CSV") {
+        Ok(OutputFormat::Csv {
+            delimiter: delimiter(&csv, "FieldDelimiter")?,
+        })
+    } else {
+        Ok(OutputFormat::JsonLines)
+    }
+}
+
+/// Single-byte delimiter from `tag`, defaulting to a comma
+fn delimiter(xml: &str, tag: &str) -> SelectResult<u8> {
+    match xml_value(xml, tag).map(|d| xml_unescape(&d)) {
+        None => Ok(b','),
+        Some(d) if d.len() == 1 => Ok(d.as_bytes()[0]),
+        Some(d) => Err(SelectError::Request(format!(
+            "{} must be a single character, got {:?}",
+            tag, d
+        ))),
+    }
+}
+
+// =============================================================================
+// QUERY
+// =============================================================================
+
+/// A column reference
+#[derive(Debug, Clone, PartialEq)]
+pub enum Column {
+    Name(String),
+    /// Zero-based position (`_1` is 0)
+    Index(usize),
+}
+
+impl Column {
+    /// Output name of the column
+    fn label(&self) -> String {
+        match self {
+            Column::Name(name) => name.clone(),
+            Column::Index(i) => format!("_{}", i + 1),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CmpOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Literal {
+    Str(String),
+    Num(f64),
+}
+
+/// WHERE clause expression
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Cmp(Column, CmpOp, Literal),
+    Like(Column, String),
+    IsNull(Column),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+}
+
+/// A parsed SELECT statement
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query {
+    /// Selected columns; `None` for `*`
+    pub columns: Option<Vec<Column>>,
+    pub filter: Option<Expr>,
+    pub limit: Option<u64>,
+}
+
+impl Query {
+    pub fn parse(sql: &str) -> SelectResult<Self> {
+        let tokens = tokenize(sql)?;
+        let mut parser = Parser {
+            tokens,
+            pos: 0,
+            alias: None,
+        };
+        parser.statement()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    QuotedIdent(String),
+    Str(String),
+    Num(f64),
+    Op(CmpOp),
+    Star,
+    Comma,
+    LParen,
+    RParen,
+}
+
+fn tokenize(sql: &str) -> SelectResult<Vec<Token>> {
+    let chars: Vec<char> = sql.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            c if c.is_whitespace() => i += 1,
+            '*' => {
+                tokens.push(Token::Star);
+                i += 1;
+            }
+            ',' => {
+                tokens.push(Token::Comma);
+                i += 1;
+            }
+            '(' => {
+                tokens.push(Token::LParen);
+                i += 1;
+            }
+            ')' => {
+                tokens.push(Token::RParen);
+                i += 1;
+            }
+            '\'' | '"' => {
+                // Quotes are escaped by doubling them
+                let mut value = String::new();
+                i += 1;
+                loop {
+                    match chars.get(i) {
+                        None => return Err(SelectError::Syntax("unterminated quote".to_string())),
+                        Some(&q) if q == c && chars.get(i + 1) == Some(&c) => {
+                            value.push(c);
+                            i += 2;
+                        }
+                        Some(&q) if q == c => {
+                            i += 1;
+                            break;
+                        }
+                        Some(&other) => {
+                            value.push(other);
+                            i += 1;
+                        }
+                    }
+                }
+                tokens.push(if c == '\'' {
+                    Token::Str(value)
+                } else {
+                    Token::QuotedIdent(value)
+                });
+            }
+            '=' => {
+                tokens.push(Token::Op(CmpOp::Eq));
+                i += 1;
+            }
+            '!' | '<' | '>' => {
+                let next = chars.get(i + 1).copied();
+                let (op, len) = match (c, next) {
+                    ('!', Some('=')) | ('<', Some('>')) => (CmpOp::Ne, 2),
+                    ('<', Some('=')) => (CmpOp::Le, 2),
+                    ('>', Some('=')) => (CmpOp::Ge, 2),
+                    ('<', _) => (CmpOp::Lt, 1),
+                    ('>', _) => (CmpOp::Gt, 1),
+                    _ => return Err(SelectError::Syntax(format!("unexpected '{}'", c))),
+                };
+                tokens.push(Token::Op(op));
+                i += len;
+            }
+            c if c.is_ascii_digit() || (c == '-' || c == '.') => {
+                let start = i;
+                i += 1;
+                while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                    i += 1;
+                }
+                let text: String = chars[start..i].iter().collect();
+                let num = text
+                    .parse()
+                    .map_err(|_| SelectError::Syntax(format!("invalid number {}", text)))?;
+                tokens.push(Token::Num(num));
+            }
+            c if c.is_alphanumeric() || c == '_' => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_alphanumeric() || "_.".contains(chars[i])) {
+                    i += 1;
+                }
+                // A trailing '.' marks a qualifier for `s.*` or `s."name"`
+                tokens.push(Token::Ident(chars[start..i].iter().collect()));
+            }
+            other => return Err(SelectError::Syntax(format!("unexpected '{}'", other))),
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    alias: Option<String>,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn peek_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Ident(w)) if w.eq_ignore_ascii_case(keyword))
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> SelectResult<()> {
+        if self.peek_keyword(keyword) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(SelectError::Syntax(format!("expected {}", keyword)))
+        }
+    }
+
+    fn statement(&mut self) -> SelectResult<Query> {
+        self.expect_keyword("SELECT")?;
+
+        // Columns are resolved after FROM, since they may use its alias
+        let start = self.pos;
+        while self.pos < self.tokens.len() && !self.peek_keyword("FROM") {
+            self.pos += 1;
+        }
+        let projection_end = self.pos;
+
+        self.expect_keyword("FROM")?;
+        match self.next() {
+            Some(Token::Ident(source)) if source.eq_ignore_ascii_case("S3Object") => {}
+            _ => return Err(SelectError::Syntax("expected FROM S3Object".to_string())),
+        }
+        if self.peek_keyword("AS") {
+            self.pos += 1;
+        }
+        if let Some(Token::Ident(alias)) = self.peek() {
+            if !["WHERE", "LIMIT"]
+                .iter()
+                .any(|k| alias.eq_ignore_ascii_case(k))
+            {
+                self.alias = Some(alias.clone());
+                self.pos += 1;
+            }
+        }
+        let after_from = self.pos;
+
+        self.pos = start;
+        let columns = self.projection(projection_end)?;
+        self.pos = after_from;
+
+        let filter = if self.peek_keyword("WHERE") {
+            self.pos += 1;
+            Some(self.or_expr()?)
+        } else {
+            None
+        };
+
+        let limit = if self.peek_keyword("LIMIT") {
+            self.pos += 1;
+            match self.next() {
+                Some(Token::Num(n)) if n >= 0.0 && n.fract() == 0.0 => Some(n as u64),
+                _ => return Err(SelectError::Syntax("LIMIT needs a count".to_string())),
+            }
+        } else {
+            None
+        };
+
+        if let Some(token) = self.peek() {
+            return Err(SelectError::Syntax(format!(
+                "unexpected {:?} after query",
+                token
+            )));
+        }
+
+        Ok(Query {
+            columns,
+            filter,
+            limit,
+        })
+    }
+
+    fn projection(&mut self, end: usize) -> SelectResult<Option<Vec<Column>>> {
+        if self.pos == end {
+            return Err(SelectError::Syntax("empty projection".to_string()));
+        }
+        if self.peek() == Some(&Token::Star) && self.pos + 1 == end {
+            return Ok(None);
+        }
+        // `alias.*`
+        if let Some(Token::Ident(name)) = self.peek() {
+            if name.ends_with('.') && self.tokens.get(self.pos + 1) == Some(&Token::Star) {
+                return Ok(None);
+            }
+        }
+
+        let mut columns = Vec::new();
+        loop {
+            columns.push(self.column()?);
+            if self.pos >= end {
+                break;
+            }
+            if self.next() != Some(Token::Comma) {
+                return Err(SelectError::Syntax(
+                    "expected ',' between columns".to_string(),
+                ));
+            }
+        }
+        Ok(Some(columns))
+    }
+
+    fn column(&mut self) -> SelectResult<Column> {
+        let name = match self.next() {
+            Some(Token::Ident(name)) => {
+                // Qualifier followed by a quoted name (`s."first name"`)
+                if name.ends_with('.') {
+                    match self.next() {
+                        Some(Token::QuotedIdent(quoted)) => return Ok(Column::Name(quoted)),
+                        _ => return Err(SelectError::Syntax(format!("bad column {}", name))),
+                    }
+                }
+                self.unqualify(&name)
+            }
+            Some(Token::QuotedIdent(name)) => return Ok(Column::Name(name)),
+            other => {
+                return Err(SelectError::Syntax(format!(
+                    "expected column, got {:?}",
+                    other
+                )))
+            }
+        };
+
+        Ok(
+            match name.strip_prefix('_').and_then(|n| n.parse::<usize>().ok()) {
+                Some(n) if n >= 1 => Column::Index(n - 1),
+                _ => Column::Name(name),
+            },
+        )
+    }
+
+    /// Strip the table alias (or `S3Object`) from a column name
+    fn unqualify(&self, name: &str) -> String {
+        if let Some((qualifier, rest)) = name.split_once('.') {
+            let matches_alias = self
+                .alias
+                .as_deref()
+                .is_some_and(|a| a.eq_ignore_ascii_case(qualifier));
+            if matches_alias || qualifier.eq_ignore_ascii_case("S3Object") {
+                return rest.to_string();
+            }
+        }
+        name.to_string()
+    }
+
+    fn or_expr(&mut self) -> SelectResult<Expr> {
+        let mut expr = self.and_expr()?;
+        while self.peek_keyword("OR") {
+            self.pos += 1;
+            expr = Expr::Or(Box::new(expr), Box::new(self.and_expr()?));
+        }
+        Ok(expr)
+    }
+
+    fn and_expr(&mut self) -> SelectResult<Expr> {
+        let mut expr = self.unary_expr()?;
+        while self.peek_keyword("AND") {
+            self.pos += 1;
+            expr = Expr::And(Box::new(expr), Box::new(self.unary_expr()?));
+        }
+        Ok(expr)
+    }
+
+    fn unary_expr(&mut self) -> SelectResult<Expr> {
+        if self.peek_keyword("NOT") {
+            self.pos += 1;
+            return Ok(Expr::Not(Box::new(self.unary_expr()?)));
+        }
+        if self.peek() == Some(&Token::LParen) {
+            self.pos += 1;
+            let expr = self.or_expr()?;
+            if self.next() != Some(Token::RParen) {
+                return Err(SelectError::Syntax("expected ')'".to_string()));
+            }
+            return Ok(expr);
+        }
+
+        let column = self.column()?;
+        if self.peek_keyword("IS") {
+            self.pos += 1;
+            let negated = self.peek_keyword("NOT");
+            if negated {
+                self.pos += 1;
+            }
+            self.expect_keyword("NULL")?;
+            let expr = Expr::IsNull(column);
+            return Ok(if negated {
+                Expr::Not(Box::new(expr))
+            } else {
+                expr
+            });
+        }
+
+        let negated = self.peek_keyword("NOT");
+        if negated {
+            self.pos += 1;
+        }
+        if self.peek_keyword("LIKE") {
+            self.pos += 1;
+            let Some(Token::Str(pattern)) = self.next() else {
+                return Err(SelectError::Syntax(
+                    "LIKE needs a string pattern".to_string(),
+                ));
+            };
+            let expr = Expr::Like(column, pattern);
+            return Ok(if negated {
+                Expr::Not(Box::new(expr))
+            } else {
+                expr
+            });
+        }
+        if negated {
+            return Err(SelectError::Syntax("expected LIKE after NOT".to_string()));
+        }
+
+        let Some(Token::Op(op)) = self.next() else {
+            return Err(SelectError::Syntax(
+                "expected comparison operator".to_string(),
+            ));
+        };
+        let literal = match self.next() {
+            Some(Token::Str(s)) => Literal::Str(s),
+            Some(Token::Num(n)) => Literal::Num(n),
+            other => {
+                return Err(SelectError::Syntax(format!(
+                    "expected literal, got {:?}",
+                    other
+                )))
+            }
+        };
+        Ok(Expr::Cmp(column, op, literal))
+    }
+}
+
+// =============================================================================
+// EVALUATION
+// =============================================================================
+
+/// A record being filtered
+enum Record<'a> {
+    Csv {
+        fields: Vec<String>,
+        header: Option<&'a [String]>,
+    },
+    Json(Map<String, Value>),
+}
+
+impl Record<'_> {
+    fn get(&self, column: &Column) -> Option<Value> {
+        match (self, column) {
+            (Record::Csv { fields, .. }, Column::Index(i)) => {
+                fields.get(*i).cloned().map(Value::String)
+            }
+            (Record::Csv { fields, header }, Column::Name(name)) => {
+                let header = header.as_ref()?;
+                let idx = header
+                    .iter()
+                    .position(|h| h == name)
+                    .or_else(|| header.iter().position(|h| h.eq_ignore_ascii_case(name)))?;
+                fields.get(idx).cloned().map(Value::String)
+            }
+            (Record::Json(map), Column::Name(name)) => map.get(name).cloned(),
+            (Record::Json(_), Column::Index(_)) => None,
+        }
+    }
+
+    fn matches(&self, expr: &Expr) -> bool {
+        match expr {
+            Expr::And(a, b) => self.matches(a) && self.matches(b),
+            Expr::Or(a, b) => self.matches(a) || self.matches(b),
+            Expr::Not(e) => !self.matches(e),
+            Expr::IsNull(column) => matches!(self.get(column), None | Some(Value::Null)),
+            Expr::Like(column, pattern) => match self.get(column) {
+                Some(Value::Null) | None => false,
+                Some(value) => {
+                    let text: Vec<char> = value_text(&value).chars().collect();
+                    let pattern: Vec<char> = pattern.chars().collect();
+                    like(&text, &pattern)
+                }
+            },
+            Expr::Cmp(column, op, literal) => {
+                let Some(value) = self.get(column).filter(|v| !v.is_null()) else {
+                    return false;
+                };
+                let ordering = match literal {
+                    Literal::Num(n) => match value_number(&value) {
+                        Some(v) => v.partial_cmp(n),
+                        None => None,
+                    },
+                    Literal::Str(s) => Some(value_text(&value).as_str().cmp(s.as_str())),
+                };
+                let Some(ordering) = ordering else {
+                    return false;
+                };
+                match op {
+                    CmpOp::Eq => ordering == Ordering::Equal,
+                    CmpOp::Ne => ordering != Ordering::Equal,
+                    CmpOp::Lt => ordering == Ordering::Less,
+                    CmpOp::Le => ordering != Ordering::Greater,
+                    CmpOp::Gt => ordering == Ordering::Greater,
+                    CmpOp::Ge => ordering != Ordering::Less,
+                }
+            }
+        }
+    }
+}
+
+fn value_number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+fn value_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+/// SQL LIKE with `%` (any run) and `_` (any single character)
+fn like(text: &[char], pattern: &[char]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some(('%', rest)) => (0..=text.len()).any(|i| like(&text[i..], rest)),
+        Some(('_', rest)) => !text.is_empty() && like(&text[1..], rest),
+        Some((c, rest)) => text.first() == Some(c) && like(&text[1..], rest),
+    }
+}
+
+// =============================================================================
+// STREAMING PROCESSOR
+// =============================================================================
+
+/// Filters an object fed in arbitrary pieces
+///
+/// Records may span pieces; the incomplete tail of each piece is carried
+/// over to the next.
+pub struct SelectProcessor {
+    request: SelectRequest,
+    /// Header line, once seen (CSV with a header only)
+    header: Option<Vec<String>>,
+    carry: Vec<u8>,
+    records: u64,
+    returned: u64,
+}
+
+impl SelectProcessor {
+    pub fn new(request: SelectRequest) -> Self {
+        Self {
+            request,
+            header: None,
+            carry: Vec::new(),
+            records: 0,
+            returned: 0,
+        }
+    }
+
+    pub fn output_format(&self) -> &OutputFormat {
+        &self.request.output
+    }
+
+    /// Whether the LIMIT has been reached and no more input is needed
+    pub fn is_done(&self) -> bool {
+        self.request
+            .query
+            .limit
+            .is_some_and(|limit| self.returned >= limit)
+    }
+
+    /// Records scanned and returned so far
+    pub fn stats(&self) -> (u64, u64) {
+        (self.records, self.returned)
+    }
+
+    /// Process the next piece of the object, appending matches to `out`
+    pub fn feed(&mut self, data: &[u8], out: &mut Vec<u8>) -> SelectResult<()> {
+        self.carry.extend_from_slice(data);
+        let buffer = std::mem::take(&mut self.carry);
+
+        let mut start = 0;
+        let mut in_quotes = false;
+        let quoted = matches!(self.request.input, InputFormat::Csv { .. });
+        for (i, &b) in buffer.iter().enumerate() {
+            if quoted && b == b'"' {
+                in_quotes = !in_quotes;
+            } else if b == b'\n' && !in_quotes {
+                self.record(&buffer[start..i], out)?;
+                start = i + 1;
+                if self.is_done() {
+                    return Ok(());
+                }
+            }
+        }
+
+        self.carry = buffer[start..].to_vec();
+        Ok(())
+    }
+
+    /// Process any trailing record without a final newline
+    pub fn finish(&mut self, out: &mut Vec<u8>) -> SelectResult<()> {
+        let rest = std::mem::take(&mut self.carry);
+        if !self.is_done() {
+            self.record(&rest, out)?;
+        }
+        Ok(())
+    }
+
+    fn record(&mut self, line: &[u8], out: &mut Vec<u8>) -> SelectResult<()> {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        if line.iter().all(u8::is_ascii_whitespace) {
+            return Ok(());
+        }
+        let text = std::str::from_utf8(line).map_err(|_| SelectError::Record {
+            record: self.records + 1,
+            message: "not valid UTF-8".to_string(),
+        })?;
+
+        let record = match self.request.input {
+            InputFormat::Csv { header, delimiter } => {
+                let fields = split_csv(text, delimiter);
+                if header != CsvHeader::None && self.header.is_none() {
+                    self.header = Some(fields);
+                    return Ok(());
+                }
+                Record::Csv {
+                    fields,
+                    header: self.header.as_deref().filter(|_| header == CsvHeader::Use),
+                }
+            }
+            InputFormat::JsonLines => match serde_json::from_str(text) {
+                Ok(Value::Object(map)) => Record::Json(map),
+                Ok(_) => {
+                    return Err(SelectError::Record {
+                        record: self.records + 1,
+                        message: "not a JSON object".to_string(),
+                    })
+                }
+                Err(e) => {
+                    return Err(SelectError::Record {
+                        record: self.records + 1,
+                        message: e.to_string(),
+                    })
+                }
+            },
+        };
+        self.records += 1;
+
+        let query = &self.request.query;
+        if query.filter.as_ref().is_some_and(|f| !record.matches(f)) {
+            return Ok(());
+        }
+
+        write_record(&record, query.columns.as_deref(), &self.request.output, out);
+        self.returned += 1;
+        Ok(())
+    }
+}
+
+fn write_record(
+    record: &Record<'_>,
+    columns: Option<&[Column]>,
+    format: &OutputFormat,
+    out: &mut Vec<u8>,
+) {
+    // (label, value) pairs in output order
+    let values: Vec<(String, Value)> = match (columns, record) {
+        (Some(columns), _) => columns
+            .iter()
+            .map(|c| (c.label(), record.get(c).unwrap_or(Value::Null)))
+            .collect(),
+        (None, Record::Csv { fields, header }) => fields
+            .iter()
+            .enumerate()
+            .map(|(i, f)| {
+                let label = header
+                    .and_then(|h| h.get(i).cloned())
+                    .unwrap_or_else(|| Column::Index(i).label());
+                (label, Value::String(f.clone()))
+            })
+            .collect(),
+        (None, Record::Json(map)) => map.iter().map(|(k, v)| (k.clone(), v.clone())).collect(),
+    };
+
+    match format {
+        OutputFormat::Csv { delimiter } => {
+            let delimiter = *delimiter as char;
+            let line: Vec<String> = values
+                .iter()
+                .map(|(_, v)| csv_escape(&value_text(v), delimiter))
+                .collect();
+            out.extend_from_slice(line.join(&delimiter.to_string()).as_bytes());
+        }
+        OutputFormat::JsonLines => {
+            let map: Map<String, Value> = values.into_iter().collect();
+            out.extend_from_slice(Value::Object(map).to_string().as_bytes());
+        }
+    }
+    out.push(b'\n');
+}
+
+/// Split a CSV record, honouring double-quoted fields
+fn split_csv(line: &str, delimiter: u8) -> Vec<String> {
+    let delimiter = delimiter as char;
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => in_quotes = !in_quotes,
+            c if c == delimiter && !in_quotes => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+fn csv_escape(value: &str, delimiter: char) -> String {
+    if value.contains(delimiter) || value.contains('"') || value.contains('\n') {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Contents of `<tag>...</tag>`
+fn xml_value(xml: &str, tag: &str) -> Option<String> {
+    let open = format!("<{}>", tag);
+    let close = format!("</{}>", tag);
+    let start = xml.find(&open)? + open.len();
+    let end = xml[start..].find(&close)?;
+    Some(xml[start..start + end].to_string())
+}
+
+/// Contents of `<tag>...</tag>`, or empty for a self-closing `<tag/>`
+fn xml_section(xml: &str, tag: &str) -> Option<String> {
+    xml_value(xml, tag).or_else(|| {
+        [format!("<{}/>", tag), format!("<{} />", tag)]
+            .iter()
+            .any(|t| xml.contains(t.as_str()))
+            .then(String::new)
+    })
+}
+
+pub(crate) fn xml_unescape(s: &str) -> String {
+    s.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(request: SelectRequest, pieces: &[&str]) -> String {
+        let mut processor = SelectProcessor::new(request);
+        let mut out = Vec::new();
+        for piece in pieces {
+            processor.feed(piece.as_bytes(), &mut out).unwrap();
+        }
+        processor.finish(&mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    fn csv_request(sql: &str) -> SelectRequest {
+        SelectRequest {
+            query: Query::parse(sql).unwrap(),
+            input: InputFormat::Csv {
+                header: CsvHeader::Use,
+                delimiter: b',',
+            },
+            output: OutputFormat::Csv { delimiter: b',' },
+        }
+    }
+
+    #[test]
+    fn test_parse_query() {
+        let query = Query::parse(
+            "SELECT s.name, _2 FROM S3Object s WHERE s.age >= 30 AND NOT city = 'Oslo' LIMIT 5",
+        )
+        .unwrap();
+        assert_eq!(
+            query.columns,
+            Some(vec![Column::Name("name".into()), Column::Index(1)])
+        );
+        assert_eq!(query.limit, Some(5));
+        assert_eq!(
+            query.filter,
+            Some(Expr::And(
+                Box::new(Expr::Cmp(
+                    Column::Name("age".into()),
+                    CmpOp::Ge,
+                    Literal::Num(30.0)
+                )),
+                Box::new(Expr::Not(Box::new(Expr::Cmp(
+                    Column::Name("city".into()),
+                    CmpOp::Eq,
+                    Literal::Str("Oslo".into())
+                ))))
+            ))
+        );
+
+        assert_eq!(
+            Query::parse("select * from s3object").unwrap().columns,
+            None
+        );
+        assert!(Query::parse("SELECT * FROM other").is_err());
+        assert!(Query::parse("SELECT * FROM S3Object WHERE a =").is_err());
+        assert!(Query::parse("SELECT * FROM S3Object LIMIT 1 extra").is_err());
+    }
+
+    #[test]
+    fn test_csv_filter_across_pieces() {
+        let request =
+            csv_request("SELECT name, age FROM S3Object WHERE age > 30 OR name LIKE 'b%'");
+        // Records split mid-line and a quoted field containing a newline
+        let out = run(
+            request,
+            &[
+                "name,age,note\nalice,25,x\nbob,2",
+                "8,y\ncarol,41,\"multi\nline\"\ndave,3",
+                "5,z",
+            ],
+        );
+        assert_eq!(out, "bob,28\ncarol,41\ndave,35\n");
+    }
+
+    #[test]
+    fn test_csv_positional_without_header() {
+        let mut request = csv_request("SELECT _2 FROM S3Object WHERE _1 = 'b'");
+        request.input = InputFormat::Csv {
+            header: CsvHeader::None,
+            delimiter: b'|',
+        };
+        assert_eq!(run(request, &["a|1\nb|2\nb|3\n"]), "2\n3\n");
+    }
+
+    #[test]
+    fn test_json_lines_with_limit() {
+        let request = SelectRequest {
+            query: Query::parse("SELECT * FROM S3Object s WHERE s.score < 0.5 LIMIT 1").unwrap(),
+            input: InputFormat::JsonLines,
+            output: OutputFormat::JsonLines,
+        };
+        let mut processor = SelectProcessor::new(request);
+        let mut out = Vec::new();
+        processor
+            .feed(
+                b"{\"id\":1,\"score\":0.9}\n{\"id\":2,\"score\":0.1}\n{\"id\":3,\"score\":0.2}\n",
+                &mut out,
+            )
+            .unwrap();
+
+        assert!(processor.is_done());
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "{\"id\":2,\"score\":0.1}\n"
+        );
+    }
+
+    #[test]
+    fn test_malformed_json_record() {
+        let request = SelectRequest {
+            query: Query::parse("SELECT * FROM S3Object").unwrap(),
+            input: InputFormat::JsonLines,
+            output: OutputFormat::JsonLines,
+        };
+        let mut processor = SelectProcessor::new(request);
+        let err = processor
+            .feed(b"{\"a\":1}\nnot json\n", &mut Vec::new())
+            .unwrap_err();
+        assert!(matches!(err, SelectError::Record { record: 2, .. }));
+    }
+
+    #[test]
+    fn test_request_from_xml() {
+        let body = r#"<?xml version="1.0" encoding="UTF-8"?>
+<SelectObjectContentRequest>
+  <Expression>SELECT s.name FROM S3Object s WHERE s.age &gt; 30</Expression>
+  <ExpressionType>SQL</ExpressionType>
+  <InputSerialization>
+    <CompressionType>NONE</CompressionType>
+    <CSV><FileHeaderInfo>USE</FileHeaderInfo><FieldDelimiter>;</FieldDelimiter></CSV>
+  </InputSerialization>
+  <OutputSerialization><JSON/></OutputSerialization>
+</SelectObjectContentRequest>"#;
+
+        let request = SelectRequest::from_xml(body).unwrap();
+        assert_eq!(
+            request.input,
+            InputFormat::Csv {
+                header: CsvHeader::Use,
+                delimiter: b';'
+            }
+        );
+        assert_eq!(request.output, OutputFormat::JsonLines);
+        assert!(matches!(
+            request.query.filter,
+            Some(Expr::Cmp(_, CmpOp::Gt, Literal::Num(n))) if n == 30.0
+        ));
+
+        let gzip = body.replace(
+            "<CompressionType>NONE</CompressionType>",
+            "<CompressionType>GZIP</CompressionType>",
+        );
+        assert!(SelectRequest::from_xml(&gzip).is_err());
+    }
+}
@@ -8,26 +8,50 @@
 #![allow(unused_imports)]
 #![allow(unused_variables)]
 
-use bytes::Bytes;
+use bytes::{Bytes, BytesMut};
 use cyxcloud_core::{
-    crypto::ContentHash, reassemble_chunks, split_into_chunks, ErasureEncoder, ShardData,
-    DATA_SHARDS, DEFAULT_CHUNK_SIZE, PARITY_SHARDS, TOTAL_SHARDS,
+    crypto::{ContentHash, EncryptionKey},
+    reassemble_chunks, split_bytes_into_chunks, EncodedStripe, ErasureBackend, ErasureConfig,
+    ErasureEncoder, ErrorCode, ShardData, StripeEncoder, DATA_SHARDS, DEFAULT_CHUNK_SIZE,
+    PARITY_SHARDS, SMALL_OBJECT_REPLICAS, SMALL_OBJECT_THRESHOLD, TOTAL_SHARDS,
 };
 use cyxcloud_metadata::{
-    CreateChunk, MetadataConfig, MetadataError, MetadataService, PlacementConfig, PlacementEngine,
-    PlacementNode,
+    CacheConfig, CreateChunk, DbConfig, MetadataConfig, MetadataError, MetadataService, ObjectLock,
+    PlacementConfig, PlacementEngine, PlacementNode, StorageClass, StorageClassJob,
+    StorageClassJobKind, StorageMode,
 };
-use std::collections::HashMap;
-use std::sync::Arc;
-use tokio::sync::RwLock;
+use futures::{Stream, StreamExt};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, OnceLock};
+use tokio::sync::{watch, RwLock};
 use tracing::{debug, error, info, warn};
 use uuid::Uuid;
 
+use crate::access_log::{AccessLogConfig, AccessLogger, BucketLogging};
+use crate::acl::{BucketAcl, CannedAcl};
 use crate::auth::{AuthConfig, AuthService};
+use crate::bandwidth::{BandwidthConfig, BandwidthMeter};
 #[cfg(feature = "blockchain")]
 use crate::blockchain::{BlockchainConfig, CyxCloudBlockchainClient};
+use crate::bucket_cors::{CorsCache, CorsRule};
+use crate::bucket_policy::{BucketPolicy, PolicyCache};
+use crate::health_api::{ReadinessConfig, ReadinessProbe};
+use crate::heat::{HeatConfig, HeatTracker};
+use crate::kms::{
+    plain_len, sealed_len, Kms, KmsConfig, ObjectEncryption, SealedRange, Sealer, SseAlgorithm,
+};
 use crate::node_client::{ChunkMeta, NodeClient, NodeClientConfig};
-use crate::s3_api::{ObjectInfo, ObjectMetadata, S3Error, S3Result};
+use crate::object_lock::{DefaultRetention, ObjectLockConfig, ObjectRetention};
+use crate::oidc::{OidcConfig, OidcProvider};
+use crate::preflight::PreflightGate;
+use crate::rate_limit::{RateLimitConfig, RateLimiter};
+use crate::reload::ConfigReloader;
+use crate::s3_api::{
+    user_metadata_from_json, user_metadata_to_json, ContentHeaders, DeletedObjectInfo, ObjectInfo,
+    ObjectMetadata, S3Error, S3Result, UserMetadata,
+};
+use crate::tiering::{tier_nodes, LifecycleRule, RestoreRequestOutcome, RestoreStatus};
+use crate::upload_limits::UploadLimits;
 use crate::websocket::EventHub;
 
 /// Maximum number of in-memory buckets (development mode)
@@ -36,6 +60,109 @@ const MAX_MEMORY_BUCKETS: usize = 1000;
 /// Maximum total bytes stored in memory (256 MB)
 const MAX_MEMORY_BYTES: usize = 256 * 1024 * 1024;
 
+/// File records fetched per page when re-wrapping data keys
+const REWRAP_BATCH: i64 = 500;
+
+/// What happens when two writers upload the same object key at once
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OverwritePolicy {
+    /// Writers take turns; the one that finishes last wins
+    #[default]
+    LastWriteWins,
+    /// A writer that finds the key locked fails with a conflict
+    Reject,
+}
+
+impl std::str::FromStr for OverwritePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "last-write-wins" | "last_write_wins" | "lww" => Ok(OverwritePolicy::LastWriteWins),
+            "reject" | "conflict" => Ok(OverwritePolicy::Reject),
+            other => Err(format!("unknown overwrite policy: {}", other)),
+        }
+    }
+}
+
+/// Concurrent write handling for object uploads
+#[derive(Debug, Clone)]
+pub struct WriteConfig {
+    /// What to do when another upload of the same key is in progress
+    pub overwrite_policy: OverwritePolicy,
+    /// How long a last-write-wins upload waits for the key's lock
+    pub lock_timeout: std::time::Duration,
+    /// Bytes per stripe of a streamed upload, the most buffered per upload
+    pub stripe_size: usize,
+}
+
+impl Default for WriteConfig {
+    fn default() -> Self {
+        Self {
+            overwrite_policy: OverwritePolicy::default(),
+            lock_timeout: std::time::Duration::from_secs(30),
+            stripe_size: cyxcloud_core::DEFAULT_STRIPE_SIZE,
+        }
+    }
+}
+
+impl WriteConfig {
+    /// Create configuration from environment variables
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            overwrite_policy: std::env::var("GATEWAY_OVERWRITE_POLICY")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.overwrite_policy),
+            lock_timeout: std::env::var("GATEWAY_WRITE_LOCK_TIMEOUT_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .map(std::time::Duration::from_secs)
+                .unwrap_or(defaults.lock_timeout),
+            stripe_size: std::env::var("GATEWAY_STRIPE_SIZE_KB")
+                .ok()
+                .and_then(|v| v.parse::<usize>().ok())
+                .map(|kb| kb * 1024)
+                .unwrap_or(defaults.stripe_size),
+        }
+    }
+}
+
+/// Result of an object upload
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PutObjectOutput {
+    /// ETag of the stored object
+    pub etag: String,
+    /// Size of the stored object in bytes
+    pub size: u64,
+    /// Whether an earlier upload with the same idempotency key was returned
+    /// instead of storing the object again
+    pub replayed: bool,
+    /// Server-side encryption of the stored object
+    pub encryption: Option<SseAlgorithm>,
+}
+
+/// Result of a batch delete
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DeleteObjectsOutput {
+    /// Keys that were deleted (keys that did not exist are left out)
+    pub deleted: Vec<String>,
+    /// Keys kept because they are under retention
+    pub locked: Vec<String>,
+}
+
+/// Result of re-wrapping data keys with the current master key
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct KeyRotationReport {
+    /// Master key the data keys are now wrapped with
+    pub key_id: String,
+    /// Object versions whose data key was re-wrapped
+    pub rewrapped: usize,
+    /// Object versions whose data key could not be re-wrapped
+    pub failed: usize,
+}
+
 /// Gateway configuration
 #[derive(Debug, Clone)]
 pub struct GatewayConfig {
@@ -48,6 +175,42 @@ pub struct GatewayConfig {
     /// Use in-memory storage (for development/testing)
     pub use_memory_storage: bool,
 
+    /// Database pool settings (URL comes from `database_url`)
+    pub db_config: DbConfig,
+
+    /// Cache settings (URL comes from `redis_url`)
+    pub cache_config: CacheConfig,
+
+    /// Shard placement configuration
+    pub placement: PlacementConfig,
+
+    /// S3 API rate limits
+    pub rate_limit: RateLimitConfig,
+
+    /// Storage node client (timeouts, hedged reads)
+    pub node_client: NodeClientConfig,
+
+    /// Concurrent uploads of the same object key
+    pub writes: WriteConfig,
+
+    /// Size, time and throughput limits of S3 upload bodies
+    pub upload_limits: UploadLimits,
+
+    /// S3 server access logging
+    pub access_log: AccessLogConfig,
+
+    /// Per-user bandwidth accounting and egress caps
+    pub bandwidth: BandwidthConfig,
+
+    /// Sampled per-object download counts
+    pub heat: HeatConfig,
+
+    /// Readiness probe (`/readyz`) thresholds
+    pub readiness: ReadinessConfig,
+
+    /// Key management for server-side encryption
+    pub kms: KmsConfig,
+
     /// Enable blockchain integration
     #[cfg(feature = "blockchain")]
     pub enable_blockchain: bool,
@@ -67,6 +230,18 @@ impl Default for GatewayConfig {
             database_url: None,
             redis_url: None,
             use_memory_storage: true, // Default to memory for easy development
+            db_config: DbConfig::default(),
+            cache_config: CacheConfig::default(),
+            placement: PlacementConfig::default().with_env_overrides(),
+            rate_limit: RateLimitConfig::from_env(),
+            node_client: NodeClientConfig::default(),
+            writes: WriteConfig::from_env(),
+            upload_limits: UploadLimits::from_env(),
+            access_log: AccessLogConfig::from_env(),
+            bandwidth: BandwidthConfig::from_env(),
+            heat: HeatConfig::from_env(),
+            readiness: ReadinessConfig::from_env(),
+            kms: KmsConfig::from_env(),
             #[cfg(feature = "blockchain")]
             enable_blockchain: false,
             #[cfg(feature = "blockchain")]
@@ -84,6 +259,18 @@ impl GatewayConfig {
             database_url: Some(database_url.into()),
             redis_url: None,
             use_memory_storage: false,
+            db_config: DbConfig::default(),
+            cache_config: CacheConfig::default(),
+            placement: PlacementConfig::default().with_env_overrides(),
+            rate_limit: RateLimitConfig::from_env(),
+            node_client: NodeClientConfig::default(),
+            writes: WriteConfig::from_env(),
+            upload_limits: UploadLimits::from_env(),
+            access_log: AccessLogConfig::from_env(),
+            bandwidth: BandwidthConfig::from_env(),
+            heat: HeatConfig::from_env(),
+            readiness: ReadinessConfig::from_env(),
+            kms: KmsConfig::from_env(),
             #[cfg(feature = "blockchain")]
             enable_blockchain: false,
             #[cfg(feature = "blockchain")]
@@ -96,6 +283,7 @@ impl GatewayConfig {
     /// Create config from environment variables
     pub fn from_env() -> Self {
         let database_url = std::env::var("DATABASE_URL").ok();
+        let read_url = std::env::var("DATABASE_READ_URL").ok();
         let redis_url = std::env::var("REDIS_URL").ok();
         let use_memory = std::env::var("USE_MEMORY_STORAGE")
             .map(|v| v == "1" || v.to_lowercase() == "true")
@@ -114,6 +302,21 @@ impl GatewayConfig {
             database_url,
             redis_url,
             use_memory_storage: use_memory,
+            db_config: DbConfig {
+                read_url,
+                ..Default::default()
+            },
+            cache_config: CacheConfig::default(),
+            placement: PlacementConfig::default().with_env_overrides(),
+            rate_limit: RateLimitConfig::from_env(),
+            node_client: NodeClientConfig::default(),
+            writes: WriteConfig::from_env(),
+            upload_limits: UploadLimits::from_env(),
+            access_log: AccessLogConfig::from_env(),
+            bandwidth: BandwidthConfig::from_env(),
+            heat: HeatConfig::from_env(),
+            readiness: ReadinessConfig::from_env(),
+            kms: KmsConfig::from_env(),
             #[cfg(feature = "blockchain")]
             enable_blockchain,
             #[cfg(feature = "blockchain")]
@@ -138,11 +341,53 @@ pub struct AppState {
     /// Authentication service
     auth: Arc<AuthService>,
 
+    /// Request rate limiter for the S3 API
+    rate_limiter: Arc<RateLimiter>,
+
+    /// Concurrent write handling for object uploads
+    writes: WriteConfig,
+
+    /// Limits of S3 upload bodies
+    upload_limits: UploadLimits,
+
+    /// Buffered S3 access log lines of logged buckets
+    access_logger: AccessLogger,
+
+    /// Bucket policies looked up for authorization
+    bucket_policies: PolicyCache,
+
+    /// Bucket CORS rules looked up for browser requests
+    bucket_cors: CorsCache,
+
+    /// Per-user bandwidth counters
+    bandwidth_meter: BandwidthMeter,
+
+    /// Per-object download counters
+    heat_tracker: HeatTracker,
+
+    /// Dependency checks behind `/readyz`
+    readiness: ReadinessProbe,
+
+    /// Holds S3 writes until the startup preflight passes
+    preflight: PreflightGate,
+
+    /// Key management for server-side encryption (None = not configured)
+    kms: Option<Arc<Kms>>,
+
+    /// Encryption of uploads that do not ask for any
+    sse_default: Option<SseAlgorithm>,
+
+    /// Shard placement configuration for uploads (swapped on config reload)
+    placement_config: watch::Sender<PlacementConfig>,
+
+    /// Config reloader, installed by the binary at startup
+    config_reloader: OnceLock<Arc<ConfigReloader>>,
+
     /// Blockchain client (optional, for Solana integration)
     #[cfg(feature = "blockchain")]
     blockchain: Option<Arc<CyxCloudBlockchainClient>>,
 
-    /// In-memory bucket storage (for development)
+    /// In-memory bucket storage (for development), keyed by tenant and bucket
     memory_buckets: RwLock<HashMap<String, BucketState>>,
 
     /// Total bytes stored in memory across all buckets
@@ -155,10 +400,110 @@ pub struct AppState {
     use_memory: bool,
 }
 
+/// Nodes and bookkeeping for the shards of one upload
+struct ShardUpload<'a> {
+    meta: &'a MetadataService,
+    nodes: &'a [cyxcloud_metadata::Node],
+    placement_nodes: &'a [PlacementNode],
+    file_id: Uuid,
+    /// Whether the object is stored sealed with its data key
+    encrypted: bool,
+    /// Addresses of nodes that failed a store during this upload
+    failed_nodes: std::sync::Mutex<HashSet<String>>,
+}
+
+impl ShardUpload<'_> {
+    /// Nodes to place the next shards on
+    ///
+    /// Leaves out nodes that failed a store earlier in this upload and nodes
+    /// whose store circuit is open, unless that would leave none at all.
+    fn candidates(&self, node_client: &NodeClient) -> Vec<PlacementNode> {
+        let usable: Vec<PlacementNode> = self
+            .placement_nodes
+            .iter()
+            .filter(|n| self.is_usable(node_client, &n.grpc_address))
+            .cloned()
+            .collect();
+        if usable.is_empty() {
+            self.placement_nodes.to_vec()
+        } else {
+            usable
+        }
+    }
+
+    /// Whether a node has not failed this upload and is accepting writes
+    fn is_usable(&self, node_client: &NodeClient, address: &str) -> bool {
+        !self.failed_nodes().contains(address) && node_client.accepts_writes(address)
+    }
+
+    /// Keep a node out of placement for the rest of the upload
+    fn record_failure(&self, address: &str) {
+        self.failed_nodes().insert(address.to_string());
+    }
+
+    fn failed_nodes(&self) -> std::sync::MutexGuard<'_, HashSet<String>> {
+        self.failed_nodes
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    /// Node ID for a gRPC address
+    fn node_id(&self, address: &str) -> Option<Uuid> {
+        self.nodes
+            .iter()
+            .find(|n| n.grpc_address == address)
+            .map(|n| n.id)
+    }
+
+    /// Record a shard placement in the upload intent (keeping it alive)
+    async fn record_intent(&self, shard_id: &[u8], address: &str) {
+        if let Some(node_id) = self.node_id(address) {
+            if let Err(e) = self
+                .meta
+                .record_upload_intent_shard(
+                    self.file_id,
+                    shard_id,
+                    node_id,
+                    crate::upload_janitor::upload_intent_ttl(),
+                )
+                .await
+            {
+                warn!(error = %e, "Failed to record shard in upload intent");
+            }
+        }
+    }
+
+    /// Register a stored chunk and every node now holding it
+    async fn register_chunk(&self, chunk: CreateChunk, stored_on: &[String]) {
+        let chunk_id = chunk.chunk_id.clone();
+        if let Err(e) = self.meta.register_chunk(chunk).await {
+            warn!(error = %e, "Failed to register chunk in database");
+        }
+
+        for address in stored_on {
+            if let Some(node_id) = self.node_id(address) {
+                if let Err(e) = self.meta.record_chunk_location(&chunk_id, node_id).await {
+                    warn!(error = %e, node = %address, "Failed to record shard location");
+                }
+            }
+        }
+    }
+}
+
 /// Bucket state for in-memory storage
 struct BucketState {
     objects: HashMap<String, StoredObject>,
     created_at: chrono::DateTime<chrono::Utc>,
+    logging: Option<BucketLogging>,
+    /// Object lock configuration, None while object lock is not enabled
+    object_lock: Option<ObjectLockConfig>,
+    policy: Option<Arc<BucketPolicy>>,
+    /// Whether anyone may read the bucket's objects
+    public_read: bool,
+    /// Cache-Control given to new objects uploaded without one
+    cache_control: Option<String>,
+    /// CORS rules, None while the bucket has no CORS configuration
+    cors: Option<Arc<Vec<CorsRule>>>,
 }
 
 /// Stored object for in-memory storage
@@ -167,16 +512,73 @@ struct StoredObject {
     content_type: String,
     etag: String,
     created_at: chrono::DateTime<chrono::Utc>,
+    expires_at: Option<chrono::DateTime<chrono::Utc>>,
+    user_metadata: UserMetadata,
+    retention: Option<ObjectRetention>,
+    /// Data key the object is sealed with, None if stored in plaintext
+    encryption: Option<ObjectEncryption>,
+    /// Whether anyone may read the object
+    public_read: bool,
+    content_headers: ContentHeaders,
+}
+
+impl StoredObject {
+    /// Whether the object has passed its expiry time
+    fn is_expired(&self) -> bool {
+        self.expires_at.is_some_and(|t| t <= chrono::Utc::now())
+    }
+
+    /// Size of the object's plaintext
+    fn size(&self) -> u64 {
+        match self.encryption {
+            Some(_) => plain_len(self.data.len() as u64),
+            None => self.data.len() as u64,
+        }
+    }
+
+    /// S3 metadata of the object stored under `key`
+    fn metadata(&self, key: &str) -> ObjectMetadata {
+        ObjectMetadata {
+            key: key.to_string(),
+            size: self.size(),
+            content_type: self.content_type.clone(),
+            etag: self.etag.clone(),
+            last_modified: self.created_at.to_rfc3339(),
+            expires_at: self.expires_at,
+            user_metadata: self.user_metadata.clone(),
+            retention: self.retention,
+            encryption: self.encryption.as_ref().map(|e| e.algorithm),
+            public_read: self.public_read,
+            content_headers: self.content_headers.clone(),
+            storage_class: StorageClass::Standard,
+            restore: None,
+        }
+    }
 }
 
 impl AppState {
     /// Create a new application state with in-memory storage
     pub fn new() -> Self {
+        let kms_config = KmsConfig::from_env();
         Self {
             event_hub: Arc::new(EventHub::new(1024)),
             metadata: None,
             node_client: Arc::new(NodeClient::new(NodeClientConfig::default())),
             auth: Arc::new(AuthService::from_env()),
+            rate_limiter: Arc::new(RateLimiter::new(RateLimitConfig::from_env())),
+            writes: WriteConfig::from_env(),
+            upload_limits: UploadLimits::from_env(),
+            access_logger: AccessLogger::new(AccessLogConfig::from_env().max_buffered),
+            bucket_policies: PolicyCache::new(),
+            bucket_cors: CorsCache::new(),
+            bandwidth_meter: BandwidthMeter::new(BandwidthConfig::from_env()),
+            heat_tracker: HeatTracker::new(HeatConfig::from_env()),
+            readiness: ReadinessProbe::memory(),
+            preflight: PreflightGate::default(),
+            kms: Self::init_kms(&kms_config),
+            sse_default: kms_config.default_algorithm,
+            placement_config: watch::Sender::new(PlacementConfig::default().with_env_overrides()),
+            config_reloader: OnceLock::new(),
             #[cfg(feature = "blockchain")]
             blockchain: None,
             memory_buckets: RwLock::new(HashMap::new()),
@@ -193,15 +595,19 @@ impl AppState {
                 let meta_config = MetadataConfig {
                     database_url: db_url.clone(),
                     redis_url: config.redis_url.clone(),
+                    db_config: config.db_config.clone(),
+                    cache_config: config.cache_config.clone(),
+                    placement_config: config.placement.clone(),
                     ..Default::default()
                 };
 
                 match MetadataService::new(meta_config).await {
                     Ok(service) => {
                         info!("Connected to metadata database");
-                        // Run migrations
+                        // Run migrations; the startup preflight keeps writes
+                        // refused while the schema does not match
                         if let Err(e) = service.migrate().await {
-                            warn!(error = %e, "Failed to run migrations");
+                            error!(error = %e, "Failed to run migrations");
                         }
                         Some(Arc::new(service))
                     }
@@ -240,29 +646,54 @@ impl AppState {
             None
         };
 
-        // Build auth service, optionally with Redis for persistent token revocation
+        // Build auth service and rate limiter, optionally with Redis for
+        // persistent token revocation and cross-gateway rate limit counters
         let mut auth_service = AuthService::from_env();
+        if let Some(oidc) = OidcConfig::from_env() {
+            auth_service = auth_service.with_provider(Arc::new(OidcProvider::new(oidc)));
+        }
+        let mut rate_limiter = RateLimiter::new(config.rate_limit.clone());
+        let mut redis = None;
         if let Some(ref redis_url) = config.redis_url {
             match redis::Client::open(redis_url.as_str()) {
                 Ok(client) => match client.get_multiplexed_async_connection().await {
                     Ok(conn) => {
-                        auth_service = auth_service.with_redis(conn);
+                        rate_limiter = rate_limiter.with_redis(conn.clone());
+                        auth_service = auth_service.with_redis(conn.clone());
+                        redis = Some(Ok(conn));
                     }
                     Err(e) => {
                         warn!(error = %e, "Failed to connect Redis for token revocation (in-memory only)");
+                        redis = Some(Err(e.to_string()));
                     }
                 },
                 Err(e) => {
                     warn!(error = %e, "Invalid Redis URL for token revocation (in-memory only)");
+                    redis = Some(Err(e.to_string()));
                 }
             }
         }
+        let database_configured = config.database_url.is_some() && !config.use_memory_storage;
 
         Ok(Self {
             event_hub: Arc::new(EventHub::new(1024)),
             metadata,
-            node_client: Arc::new(NodeClient::new(NodeClientConfig::default())),
+            node_client: Arc::new(NodeClient::new(config.node_client.clone())),
             auth: Arc::new(auth_service),
+            rate_limiter: Arc::new(rate_limiter),
+            writes: config.writes.clone(),
+            upload_limits: config.upload_limits.clone(),
+            access_logger: AccessLogger::new(config.access_log.max_buffered),
+            bucket_policies: PolicyCache::new(),
+            bucket_cors: CorsCache::new(),
+            bandwidth_meter: BandwidthMeter::new(config.bandwidth.clone()),
+            heat_tracker: HeatTracker::new(config.heat.clone()),
+            readiness: ReadinessProbe::new(config.readiness.clone(), database_configured, redis),
+            preflight: PreflightGate::default(),
+            kms: Self::init_kms(&config.kms),
+            sse_default: config.kms.default_algorithm,
+            placement_config: watch::Sender::new(config.placement.clone()),
+            config_reloader: OnceLock::new(),
             #[cfg(feature = "blockchain")]
             blockchain,
             memory_buckets: RwLock::new(HashMap::new()),
@@ -272,6 +703,25 @@ impl AppState {
         })
     }
 
+    /// Set up key management, leaving encryption unavailable if the
+    /// configuration is broken
+    fn init_kms(config: &KmsConfig) -> Option<Arc<Kms>> {
+        match Kms::from_config(config) {
+            Ok(Some(kms)) => {
+                info!(
+                    provider = kms.provider_name(),
+                    "Server-side encryption enabled"
+                );
+                Some(Arc::new(kms))
+            }
+            Ok(None) => None,
+            Err(e) => {
+                error!(error = %e, "Failed to set up key management, encryption unavailable");
+                None
+            }
+        }
+    }
+
     /// Initialize blockchain client from config
     #[cfg(feature = "blockchain")]
     fn init_blockchain_client(config: &GatewayConfig) -> anyhow::Result<CyxCloudBlockchainClient> {
@@ -314,6 +764,197 @@ impl AppState {
         self.metadata.clone()
     }
 
+    /// Get the S3 access log buffer
+    pub fn access_logger(&self) -> &AccessLogger {
+        &self.access_logger
+    }
+
+    /// Get the per-user bandwidth counters
+    pub fn bandwidth_meter(&self) -> &BandwidthMeter {
+        &self.bandwidth_meter
+    }
+
+    /// Get the per-object download counters
+    pub fn heat_tracker(&self) -> &HeatTracker {
+        &self.heat_tracker
+    }
+
+    /// Get the S3 upload body limits
+    pub fn upload_limits(&self) -> &UploadLimits {
+        &self.upload_limits
+    }
+
+    /// Get the readiness probe
+    pub fn readiness(&self) -> &ReadinessProbe {
+        &self.readiness
+    }
+
+    /// Get the startup preflight write gate
+    pub fn preflight(&self) -> &PreflightGate {
+        &self.preflight
+    }
+
+    /// Get the key management service, if one is configured
+    pub fn kms(&self) -> Option<&Kms> {
+        self.kms.as_deref()
+    }
+
+    /// Use `kms` for server-side encryption instead of the configured one
+    pub fn with_kms(mut self, kms: Arc<Kms>) -> Self {
+        self.kms = Some(kms);
+        self
+    }
+
+    /// A new data key for an upload that asked for `requested` encryption
+    ///
+    /// Uploads are encrypted with the gateway default when they do not ask
+    /// for anything. An upload that should be encrypted fails rather than
+    /// be stored in plaintext when no key management is configured.
+    async fn new_data_key(
+        &self,
+        requested: Option<SseAlgorithm>,
+    ) -> S3Result<Option<(EncryptionKey, ObjectEncryption)>> {
+        let Some(algorithm) = requested.or(self.sse_default) else {
+            return Ok(None);
+        };
+        let kms = self.kms().ok_or_else(|| {
+            S3Error::service(
+                ErrorCode::ServiceUnavailable,
+                "Server-side encryption is not available",
+            )
+        })?;
+        let (key, data_key) = kms.generate_data_key().await.map_err(S3Error::from)?;
+        Ok(Some((
+            key,
+            ObjectEncryption {
+                algorithm,
+                data_key,
+            },
+        )))
+    }
+
+    /// Unwrapped data key of an encrypted object
+    async fn data_key(&self, encryption: &ObjectEncryption) -> S3Result<EncryptionKey> {
+        let kms = self.kms().ok_or_else(|| {
+            S3Error::service(
+                ErrorCode::ServiceUnavailable,
+                "Object is encrypted but key management is not configured",
+            )
+        })?;
+        kms.unwrap(&encryption.data_key)
+            .await
+            .map_err(S3Error::from)
+    }
+
+    /// Re-wrap the data keys of all encrypted objects with the current
+    /// master key
+    ///
+    /// With `rotate_master` the key provider first makes a new master key
+    /// current. Object data is never rewritten. Data keys that cannot be
+    /// re-wrapped keep their old wrapping and are counted, so the rotation
+    /// can simply be run again; the old master key must be kept until it
+    /// reports no failures.
+    pub async fn rotate_data_keys(&self, rotate_master: bool) -> S3Result<KeyRotationReport> {
+        let kms = self.kms().ok_or_else(|| {
+            S3Error::service(
+                ErrorCode::ServiceUnavailable,
+                "Server-side encryption is not available",
+            )
+        })?;
+        if rotate_master {
+            kms.rotate_master_key().await.map_err(S3Error::from)?;
+        }
+        let key_id = kms.current_key_id().await.map_err(S3Error::from)?;
+        let mut report = KeyRotationReport {
+            key_id: key_id.clone(),
+            rewrapped: 0,
+            failed: 0,
+        };
+
+        if self.use_memory {
+            let mut buckets = self.memory_buckets.write().await;
+            let objects = buckets.values_mut().flat_map(|b| b.objects.values_mut());
+            for encryption in objects.filter_map(|o| o.encryption.as_mut()) {
+                if encryption.data_key.key_id == key_id {
+                    continue;
+                }
+                match kms.rewrap(&encryption.data_key).await {
+                    Ok(data_key) => {
+                        encryption.data_key = data_key;
+                        report.rewrapped += 1;
+                    }
+                    Err(e) => {
+                        warn!(error = %e, "Failed to re-wrap data key");
+                        report.failed += 1;
+                    }
+                }
+            }
+            return Ok(report);
+        }
+
+        let meta = self.metadata.as_deref().ok_or_else(|| {
+            S3Error::service(
+                ErrorCode::ServiceUnavailable,
+                "No storage backend available",
+            )
+        })?;
+        let mut after = None;
+        loop {
+            let files = meta
+                .list_files_to_rewrap(&key_id, after, REWRAP_BATCH)
+                .await
+                .map_err(S3Error::from)?;
+            let Some(last) = files.last() else {
+                break;
+            };
+            after = Some(last.id);
+
+            for file in &files {
+                let Some(encryption) = file.encryption() else {
+                    continue;
+                };
+                let wrapped = crate::kms::WrappedKey {
+                    key_id: encryption.key_id,
+                    ciphertext: encryption.data_key,
+                };
+                let result = match kms.rewrap(&wrapped).await {
+                    Ok(data_key) => meta
+                        .set_file_data_key(file, &data_key.key_id, &data_key.ciphertext)
+                        .await
+                        .map_err(|e| e.to_string()),
+                    Err(e) => Err(e.to_string()),
+                };
+                match result {
+                    Ok(true) => report.rewrapped += 1,
+                    // Re-wrapped by a concurrent rotation
+                    Ok(false) => {}
+                    Err(e) => {
+                        warn!(error = %e, file_id = %file.id, "Failed to re-wrap data key");
+                        report.failed += 1;
+                    }
+                }
+            }
+        }
+
+        info!(
+            key_id = %report.key_id,
+            rewrapped = report.rewrapped,
+            failed = report.failed,
+            "Data keys re-wrapped"
+        );
+        Ok(report)
+    }
+
+    /// Whether writes are refused because the metadata primary is down
+    ///
+    /// Reads keep working from the read replica (if one is configured) and
+    /// the gateway leaves read-only mode once the primary is back.
+    pub fn is_read_only(&self) -> bool {
+        self.metadata
+            .as_ref()
+            .is_some_and(|meta| !meta.database().primary_available())
+    }
+
     /// Get node client reference
     pub fn node_client(&self) -> &NodeClient {
         &self.node_client
@@ -334,6 +975,33 @@ impl AppState {
         self.auth.clone()
     }
 
+    /// Get request rate limiter reference
+    pub fn rate_limiter(&self) -> &RateLimiter {
+        &self.rate_limiter
+    }
+
+    /// Current shard placement configuration
+    pub fn placement_config(&self) -> PlacementConfig {
+        self.placement_config.borrow().clone()
+    }
+
+    /// Replace the shard placement configuration used for new uploads
+    pub fn update_placement_config(&self, config: PlacementConfig) {
+        self.placement_config.send_replace(config);
+    }
+
+    /// Install the config reloader (once, at startup)
+    pub fn set_config_reloader(&self, reloader: Arc<ConfigReloader>) {
+        if self.config_reloader.set(reloader).is_err() {
+            warn!("Config reloader already installed");
+        }
+    }
+
+    /// Get the config reloader, if installed
+    pub fn config_reloader(&self) -> Option<&Arc<ConfigReloader>> {
+        self.config_reloader.get()
+    }
+
     /// Get blockchain client reference
     #[cfg(feature = "blockchain")]
     pub fn blockchain_client(&self) -> Option<&CyxCloudBlockchainClient> {
@@ -520,19 +1188,19 @@ impl AppState {
     // =========================================================================
 
     /// Check if bucket exists
-    pub async fn bucket_exists(&self, name: &str) -> S3Result<bool> {
+    pub async fn bucket_exists(&self, tenant: &str, name: &str) -> S3Result<bool> {
         if self.use_memory {
             let buckets = self.memory_buckets.read().await;
-            return Ok(buckets.contains_key(name));
+            return Ok(buckets.contains_key(&memory_bucket_key(tenant, name)));
         }
 
         // Use metadata service
         if let Some(ref meta) = self.metadata {
-            match meta.get_bucket(name).await {
+            match meta.get_bucket(tenant, name).await {
                 Ok(bucket) => Ok(bucket.is_some()),
                 Err(e) => {
                     warn!(error = %e, bucket = name, "Failed to check bucket existence");
-                    Err(S3Error::Internal(e.to_string()))
+                    Err(e.into())
                 }
             }
         } else {
@@ -541,26 +1209,36 @@ impl AppState {
     }
 
     /// Create a bucket
-    pub async fn create_bucket(&self, name: &str) -> S3Result<()> {
+    pub async fn create_bucket(&self, tenant: &str, name: &str) -> S3Result<()> {
         if self.use_memory {
             let mut buckets = self.memory_buckets.write().await;
 
-            if buckets.contains_key(name) {
+            let bucket_key = memory_bucket_key(tenant, name);
+            if buckets.contains_key(&bucket_key) {
                 return Err(S3Error::BucketAlreadyExists(name.to_string()));
             }
 
             if buckets.len() >= MAX_MEMORY_BUCKETS {
-                return Err(S3Error::Internal(format!(
-                    "Maximum number of in-memory buckets ({}) reached",
-                    MAX_MEMORY_BUCKETS
-                )));
+                return Err(S3Error::service(
+                    ErrorCode::StorageFull,
+                    format!(
+                        "Maximum number of in-memory buckets ({}) reached",
+                        MAX_MEMORY_BUCKETS
+                    ),
+                ));
             }
 
             buckets.insert(
-                name.to_string(),
+                bucket_key,
                 BucketState {
                     objects: HashMap::new(),
                     created_at: chrono::Utc::now(),
+                    logging: None,
+                    object_lock: None,
+                    policy: None,
+                    public_read: false,
+                    cache_control: None,
+                    cors: None,
                 },
             );
 
@@ -571,7 +1249,7 @@ impl AppState {
         // Use metadata service
         if let Some(ref meta) = self.metadata {
             // Check if exists
-            if let Ok(Some(_)) = meta.get_bucket(name).await {
+            if let Ok(Some(_)) = meta.get_bucket(tenant, name).await {
                 return Err(S3Error::BucketAlreadyExists(name.to_string()));
             }
 
@@ -579,27 +1257,30 @@ impl AppState {
             let user = meta
                 .get_or_create_user(&self.user_id.to_string())
                 .await
-                .map_err(|e| S3Error::Internal(e.to_string()))?;
+                .map_err(S3Error::from)?;
 
             // Create bucket
-            meta.create_bucket(name, user.id)
+            meta.create_bucket(tenant, name, user.id)
                 .await
-                .map_err(|e| S3Error::Internal(e.to_string()))?;
+                .map_err(S3Error::from)?;
 
-            info!(bucket = name, "Bucket created (database)");
+            info!(tenant, bucket = name, "Bucket created (database)");
             Ok(())
         } else {
-            Err(S3Error::Internal(
-                "No storage backend available".to_string(),
+            Err(S3Error::service(
+                ErrorCode::ServiceUnavailable,
+                "No storage backend available",
             ))
         }
     }
 
     /// Delete a bucket
-    pub async fn delete_bucket(&self, name: &str) -> S3Result<()> {
+    pub async fn delete_bucket(&self, tenant: &str, name: &str) -> S3Result<()> {
         if self.use_memory {
             let mut buckets = self.memory_buckets.write().await;
-            buckets.remove(name);
+            buckets.remove(&memory_bucket_key(tenant, name));
+            self.bucket_policies.forget(tenant, name);
+            self.bucket_cors.forget(tenant, name);
             info!(bucket = name, "Bucket deleted (memory)");
             return Ok(());
         }
@@ -607,10 +1288,7 @@ impl AppState {
         // Use metadata service
         if let Some(ref meta) = self.metadata {
             // Check if bucket exists
-            let bucket = meta
-                .get_bucket(name)
-                .await
-                .map_err(|e| S3Error::Internal(e.to_string()))?;
+            let bucket = meta.get_bucket(tenant, name).await.map_err(S3Error::from)?;
 
             if bucket.is_none() {
                 return Err(S3Error::NoSuchBucket(name.to_string()));
@@ -618,424 +1296,1699 @@ impl AppState {
 
             // Check if bucket is empty
             let is_empty = meta
-                .bucket_is_empty(name)
+                .bucket_is_empty(tenant, name)
                 .await
-                .map_err(|e| S3Error::Internal(e.to_string()))?;
+                .map_err(S3Error::from)?;
 
             if !is_empty {
                 return Err(S3Error::BucketNotEmpty(name.to_string()));
             }
 
             // Delete the bucket
-            meta.delete_bucket(name)
+            meta.delete_bucket(tenant, name)
                 .await
-                .map_err(|e| S3Error::Internal(e.to_string()))?;
+                .map_err(S3Error::from)?;
+            self.bucket_policies.forget(tenant, name);
+            self.bucket_cors.forget(tenant, name);
 
             info!(bucket = name, "Bucket deleted (database)");
             return Ok(());
         }
 
-        Err(S3Error::Internal(
-            "No storage backend available".to_string(),
+        Err(S3Error::service(
+            ErrorCode::ServiceUnavailable,
+            "No storage backend available",
         ))
     }
 
-    /// Check if bucket is empty
-    pub async fn bucket_is_empty(&self, name: &str) -> S3Result<bool> {
+    /// Access logging target of a bucket (None if logging is disabled)
+    pub async fn get_bucket_logging(
+        &self,
+        tenant: &str,
+        name: &str,
+    ) -> S3Result<Option<BucketLogging>> {
         if self.use_memory {
             let buckets = self.memory_buckets.read().await;
             let bucket = buckets
-                .get(name)
+                .get(&memory_bucket_key(tenant, name))
                 .ok_or_else(|| S3Error::NoSuchBucket(name.to_string()))?;
-            return Ok(bucket.objects.is_empty());
+            return Ok(bucket.logging.clone());
         }
 
         // Use metadata service
         if let Some(ref meta) = self.metadata {
-            let is_empty = meta
-                .bucket_is_empty(name)
+            let bucket = meta
+                .get_bucket(tenant, name)
                 .await
-                .map_err(|e| S3Error::Internal(e.to_string()))?;
-            return Ok(is_empty);
+                .map_err(S3Error::from)?
+                .ok_or_else(|| S3Error::NoSuchBucket(name.to_string()))?;
+            return Ok(bucket
+                .logging_target_bucket
+                .map(|target_bucket| BucketLogging {
+                    target_bucket,
+                    target_prefix: bucket.logging_target_prefix,
+                }));
         }
 
-        // No backend, assume empty
-        Ok(true)
+        Ok(None)
     }
 
-    // =========================================================================
-    // OBJECT OPERATIONS
-    // =========================================================================
-
-    /// Put an object
-    pub async fn put_object(
+    /// Enable (`Some`) or disable access logging of a bucket
+    pub async fn set_bucket_logging(
         &self,
-        bucket: &str,
-        key: &str,
-        data: Bytes,
-        content_type: &str,
-    ) -> S3Result<String> {
+        tenant: &str,
+        name: &str,
+        logging: Option<BucketLogging>,
+    ) -> S3Result<()> {
         if self.use_memory {
-            let new_size = data.len();
-
-            // Check memory limit
-            let current_bytes = self
-                .memory_bytes_used
-                .load(std::sync::atomic::Ordering::Relaxed);
-            if current_bytes + new_size > MAX_MEMORY_BYTES {
-                return Err(S3Error::Internal(format!(
-                    "In-memory storage limit ({} MB) exceeded",
-                    MAX_MEMORY_BYTES / (1024 * 1024)
-                )));
-            }
-
             let mut buckets = self.memory_buckets.write().await;
-            let bucket_state = buckets
-                .get_mut(bucket)
-                .ok_or_else(|| S3Error::NoSuchBucket(bucket.to_string()))?;
-
-            // Calculate ETag (MD5 hash)
-            let etag = format!("{:x}", md5::compute(&data));
-
-            // Track size delta (subtract old object size if overwriting)
-            let old_size = bucket_state
-                .objects
-                .get(key)
-                .map(|o| o.data.len())
-                .unwrap_or(0);
-
-            bucket_state.objects.insert(
-                key.to_string(),
-                StoredObject {
-                    data,
-                    content_type: content_type.to_string(),
-                    etag: etag.clone(),
-                    created_at: chrono::Utc::now(),
-                },
-            );
-
-            // Update tracked memory usage
-            if new_size >= old_size {
-                self.memory_bytes_used
-                    .fetch_add(new_size - old_size, std::sync::atomic::Ordering::Relaxed);
-            } else {
-                self.memory_bytes_used
-                    .fetch_sub(old_size - new_size, std::sync::atomic::Ordering::Relaxed);
+            let bucket = buckets
+                .get_mut(&memory_bucket_key(tenant, name))
+                .ok_or_else(|| S3Error::NoSuchBucket(name.to_string()))?;
+            bucket.logging = logging;
+        } else if let Some(ref meta) = self.metadata {
+            let target = logging
+                .as_ref()
+                .map(|l| (l.target_bucket.as_str(), l.target_prefix.as_str()));
+            let updated = meta
+                .set_bucket_logging(tenant, name, target)
+                .await
+                .map_err(S3Error::from)?;
+            if !updated {
+                return Err(S3Error::NoSuchBucket(name.to_string()));
             }
+        } else {
+            return Err(S3Error::service(
+                ErrorCode::ServiceUnavailable,
+                "No storage backend available",
+            ));
+        }
 
-            // Publish event
-            drop(buckets);
-            self.publish_file_created(bucket, key, 0).await;
+        self.access_logger.forget(tenant, name);
+        Ok(())
+    }
 
-            return Ok(etag);
+    /// Object lock configuration of a bucket (None if object lock is not
+    /// enabled)
+    pub async fn get_bucket_object_lock(
+        &self,
+        tenant: &str,
+        name: &str,
+    ) -> S3Result<Option<ObjectLockConfig>> {
+        if self.use_memory {
+            let buckets = self.memory_buckets.read().await;
+            let bucket = buckets
+                .get(&memory_bucket_key(tenant, name))
+                .ok_or_else(|| S3Error::NoSuchBucket(name.to_string()))?;
+            return Ok(bucket.object_lock);
         }
 
-        // Use metadata service + node storage with erasure coding
+        // Use metadata service
         if let Some(ref meta) = self.metadata {
-            // Get bucket info
-            let bucket_info = meta
-                .get_bucket(bucket)
+            let bucket = meta
+                .get_bucket(tenant, name)
+                .await
+                .map_err(S3Error::from)?
+                .ok_or_else(|| S3Error::NoSuchBucket(name.to_string()))?;
+            if !bucket.object_lock_enabled {
+                return Ok(None);
+            }
+            return Ok(Some(ObjectLockConfig {
+                default_retention: bucket.default_retention().map(|(mode, days)| {
+                    DefaultRetention {
+                        mode,
+                        days: days.max(0) as u32,
+                    }
+                }),
+            }));
+        }
+
+        Ok(None)
+    }
+
+    /// Enable object lock on a bucket with the given default retention
+    ///
+    /// Object lock stays enabled for good; later calls only change the
+    /// default retention, which applies to objects written from then on.
+    pub async fn set_bucket_object_lock(
+        &self,
+        tenant: &str,
+        name: &str,
+        config: ObjectLockConfig,
+    ) -> S3Result<()> {
+        if let Some(default_retention) = config.default_retention {
+            default_retention.validate()?;
+        }
+
+        if self.use_memory {
+            let mut buckets = self.memory_buckets.write().await;
+            let bucket = buckets
+                .get_mut(&memory_bucket_key(tenant, name))
+                .ok_or_else(|| S3Error::NoSuchBucket(name.to_string()))?;
+            bucket.object_lock = Some(config);
+            return Ok(());
+        }
+
+        if let Some(ref meta) = self.metadata {
+            let default_retention = config
+                .default_retention
+                .map(|retention| (retention.mode, retention.days as i32));
+            let updated = meta
+                .set_bucket_object_lock(tenant, name, default_retention)
+                .await
+                .map_err(S3Error::from)?;
+            if !updated {
+                return Err(S3Error::NoSuchBucket(name.to_string()));
+            }
+            return Ok(());
+        }
+
+        Err(S3Error::service(
+            ErrorCode::ServiceUnavailable,
+            "No storage backend available",
+        ))
+    }
+
+    /// Policy of a bucket (None if it has none)
+    pub async fn get_bucket_policy(
+        &self,
+        tenant: &str,
+        name: &str,
+    ) -> S3Result<Option<Arc<BucketPolicy>>> {
+        if self.use_memory {
+            let buckets = self.memory_buckets.read().await;
+            let bucket = buckets
+                .get(&memory_bucket_key(tenant, name))
+                .ok_or_else(|| S3Error::NoSuchBucket(name.to_string()))?;
+            return Ok(bucket.policy.clone());
+        }
+
+        // Use metadata service
+        if let Some(ref meta) = self.metadata {
+            let bucket = meta
+                .get_bucket(tenant, name)
                 .await
-                .map_err(|e| S3Error::Internal(e.to_string()))?
+                .map_err(S3Error::from)?
+                .ok_or_else(|| S3Error::NoSuchBucket(name.to_string()))?;
+            let Some(document) = bucket.policy else {
+                return Ok(None);
+            };
+            let policy = BucketPolicy::from_document(name, document).map_err(|e| {
+                S3Error::Internal(format!(
+                    "Stored policy of bucket {} is invalid: {}",
+                    name, e
+                ))
+            })?;
+            return Ok(Some(Arc::new(policy)));
+        }
+
+        Ok(None)
+    }
+
+    /// Set (`Some`) or remove a bucket's policy
+    pub async fn set_bucket_policy(
+        &self,
+        tenant: &str,
+        name: &str,
+        policy: Option<BucketPolicy>,
+    ) -> S3Result<()> {
+        if self.use_memory {
+            let mut buckets = self.memory_buckets.write().await;
+            let bucket = buckets
+                .get_mut(&memory_bucket_key(tenant, name))
+                .ok_or_else(|| S3Error::NoSuchBucket(name.to_string()))?;
+            bucket.policy = policy.map(Arc::new);
+        } else if let Some(ref meta) = self.metadata {
+            let updated = meta
+                .set_bucket_policy(tenant, name, policy.as_ref().map(BucketPolicy::document))
+                .await
+                .map_err(S3Error::from)?;
+            if !updated {
+                return Err(S3Error::NoSuchBucket(name.to_string()));
+            }
+        } else {
+            return Err(S3Error::service(
+                ErrorCode::ServiceUnavailable,
+                "No storage backend available",
+            ));
+        }
+
+        self.bucket_policies.forget(tenant, name);
+        Ok(())
+    }
+
+    /// Policy a request to a bucket is authorized against
+    ///
+    /// Cached for a short while, as every S3 request needs it. A bucket
+    /// that does not exist has no policy; the handler reports it missing.
+    pub async fn bucket_policy_for_request(
+        &self,
+        tenant: &str,
+        name: &str,
+    ) -> S3Result<Option<Arc<BucketPolicy>>> {
+        if let Some(policy) = self.bucket_policies.get(tenant, name) {
+            return Ok(policy);
+        }
+        let policy = match self.get_bucket_policy(tenant, name).await {
+            Ok(policy) => policy,
+            Err(S3Error::NoSuchBucket(_)) => None,
+            Err(e) => return Err(e),
+        };
+        self.bucket_policies.insert(tenant, name, policy.clone());
+        Ok(policy)
+    }
+
+    /// Whether anyone may read a bucket's objects, and its owner
+    pub async fn get_bucket_acl(&self, tenant: &str, name: &str) -> S3Result<BucketAcl> {
+        if self.use_memory {
+            let buckets = self.memory_buckets.read().await;
+            let bucket = buckets
+                .get(&memory_bucket_key(tenant, name))
+                .ok_or_else(|| S3Error::NoSuchBucket(name.to_string()))?;
+            return Ok(BucketAcl {
+                public_read: bucket.public_read,
+                owner: None,
+            });
+        }
+
+        // Use metadata service
+        if let Some(ref meta) = self.metadata {
+            let bucket = meta
+                .get_bucket(tenant, name)
+                .await
+                .map_err(S3Error::from)?
+                .ok_or_else(|| S3Error::NoSuchBucket(name.to_string()))?;
+            return Ok(BucketAcl {
+                public_read: bucket.public_read,
+                owner: Some(bucket.owner_id.to_string()),
+            });
+        }
+
+        Err(S3Error::NoSuchBucket(name.to_string()))
+    }
+
+    /// Set the canned ACL of a bucket
+    pub async fn set_bucket_acl(&self, tenant: &str, name: &str, acl: CannedAcl) -> S3Result<()> {
+        if self.use_memory {
+            let mut buckets = self.memory_buckets.write().await;
+            let bucket = buckets
+                .get_mut(&memory_bucket_key(tenant, name))
+                .ok_or_else(|| S3Error::NoSuchBucket(name.to_string()))?;
+            bucket.public_read = acl.is_public_read();
+            return Ok(());
+        }
+
+        if let Some(ref meta) = self.metadata {
+            let updated = meta
+                .set_bucket_public_read(tenant, name, acl.is_public_read())
+                .await
+                .map_err(S3Error::from)?;
+            if !updated {
+                return Err(S3Error::NoSuchBucket(name.to_string()));
+            }
+            return Ok(());
+        }
+
+        Err(S3Error::service(
+            ErrorCode::ServiceUnavailable,
+            "No storage backend available",
+        ))
+    }
+
+    /// Cache-Control given to a bucket's new objects (None if there is none)
+    pub async fn get_bucket_cache_policy(
+        &self,
+        tenant: &str,
+        name: &str,
+    ) -> S3Result<Option<String>> {
+        if self.use_memory {
+            let buckets = self.memory_buckets.read().await;
+            let bucket = buckets
+                .get(&memory_bucket_key(tenant, name))
+                .ok_or_else(|| S3Error::NoSuchBucket(name.to_string()))?;
+            return Ok(bucket.cache_control.clone());
+        }
+
+        // Use metadata service
+        if let Some(ref meta) = self.metadata {
+            let bucket = meta
+                .get_bucket(tenant, name)
+                .await
+                .map_err(S3Error::from)?
+                .ok_or_else(|| S3Error::NoSuchBucket(name.to_string()))?;
+            return Ok(bucket.default_cache_control);
+        }
+
+        Ok(None)
+    }
+
+    /// Set or clear (`cache_control` = None) the Cache-Control given to a
+    /// bucket's new objects
+    ///
+    /// Objects uploaded with a Cache-Control of their own keep it, and
+    /// objects already stored are not changed.
+    pub async fn set_bucket_cache_policy(
+        &self,
+        tenant: &str,
+        name: &str,
+        cache_control: Option<String>,
+    ) -> S3Result<()> {
+        if self.use_memory {
+            let mut buckets = self.memory_buckets.write().await;
+            let bucket = buckets
+                .get_mut(&memory_bucket_key(tenant, name))
+                .ok_or_else(|| S3Error::NoSuchBucket(name.to_string()))?;
+            bucket.cache_control = cache_control;
+            return Ok(());
+        }
+
+        if let Some(ref meta) = self.metadata {
+            let updated = meta
+                .set_bucket_cache_control(tenant, name, cache_control.as_deref())
+                .await
+                .map_err(S3Error::from)?;
+            if !updated {
+                return Err(S3Error::NoSuchBucket(name.to_string()));
+            }
+            return Ok(());
+        }
+
+        Err(S3Error::service(
+            ErrorCode::ServiceUnavailable,
+            "No storage backend available",
+        ))
+    }
+
+    /// Lifecycle rules of a bucket
+    pub async fn get_bucket_lifecycle(
+        &self,
+        tenant: &str,
+        name: &str,
+    ) -> S3Result<Vec<LifecycleRule>> {
+        let meta = self.tiering_metadata()?;
+        let bucket = meta
+            .get_bucket(tenant, name)
+            .await
+            .map_err(S3Error::from)?
+            .ok_or_else(|| S3Error::NoSuchBucket(name.to_string()))?;
+        match bucket.lifecycle_rules {
+            Some(ref rules) => Ok(crate::tiering::bucket_lifecycle_rules(Some(rules))),
+            None => Err(S3Error::NoSuchLifecycleConfiguration(name.to_string())),
+        }
+    }
+
+    /// Set or remove (`rules` = None) the lifecycle rules of a bucket
+    ///
+    /// The rules are applied by the tiering daemon of each gateway.
+    pub async fn set_bucket_lifecycle(
+        &self,
+        tenant: &str,
+        name: &str,
+        rules: Option<Vec<LifecycleRule>>,
+    ) -> S3Result<()> {
+        let meta = self.tiering_metadata()?;
+        let rules = rules
+            .map(serde_json::to_value)
+            .transpose()
+            .map_err(|e| S3Error::Internal(format!("Failed to encode lifecycle rules: {}", e)))?;
+        let updated = meta
+            .set_bucket_lifecycle(tenant, name, rules.as_ref())
+            .await
+            .map_err(S3Error::from)?;
+        if !updated {
+            return Err(S3Error::NoSuchBucket(name.to_string()));
+        }
+        Ok(())
+    }
+
+    /// CORS rules of a bucket
+    pub async fn get_bucket_cors(&self, tenant: &str, name: &str) -> S3Result<Vec<CorsRule>> {
+        let rules = if self.use_memory {
+            let buckets = self.memory_buckets.read().await;
+            let bucket = buckets
+                .get(&memory_bucket_key(tenant, name))
+                .ok_or_else(|| S3Error::NoSuchBucket(name.to_string()))?;
+            bucket.cors.as_deref().cloned()
+        } else if let Some(ref meta) = self.metadata {
+            let bucket = meta
+                .get_bucket(tenant, name)
+                .await
+                .map_err(S3Error::from)?
+                .ok_or_else(|| S3Error::NoSuchBucket(name.to_string()))?;
+            bucket
+                .cors_rules
+                .map(serde_json::from_value)
+                .transpose()
+                .map_err(|e| {
+                    S3Error::Internal(format!(
+                        "Stored CORS rules of bucket {} are invalid: {}",
+                        name, e
+                    ))
+                })?
+        } else {
+            None
+        };
+        rules.ok_or_else(|| S3Error::NoSuchCorsConfiguration(name.to_string()))
+    }
+
+    /// Set or remove (`rules` = None) the CORS rules of a bucket
+    pub async fn set_bucket_cors(
+        &self,
+        tenant: &str,
+        name: &str,
+        rules: Option<Vec<CorsRule>>,
+    ) -> S3Result<()> {
+        if self.use_memory {
+            let mut buckets = self.memory_buckets.write().await;
+            let bucket = buckets
+                .get_mut(&memory_bucket_key(tenant, name))
+                .ok_or_else(|| S3Error::NoSuchBucket(name.to_string()))?;
+            bucket.cors = rules.map(Arc::new);
+        } else if let Some(ref meta) = self.metadata {
+            let rules = rules
+                .map(serde_json::to_value)
+                .transpose()
+                .map_err(|e| S3Error::Internal(format!("Failed to encode CORS rules: {}", e)))?;
+            let updated = meta
+                .set_bucket_cors(tenant, name, rules.as_ref())
+                .await
+                .map_err(S3Error::from)?;
+            if !updated {
+                return Err(S3Error::NoSuchBucket(name.to_string()));
+            }
+        } else {
+            return Err(S3Error::service(
+                ErrorCode::ServiceUnavailable,
+                "No storage backend available",
+            ));
+        }
+
+        self.bucket_cors.forget(tenant, name);
+        Ok(())
+    }
+
+    /// CORS rules a browser request to a bucket is answered with
+    ///
+    /// Cached for a short while, as every request with an `Origin` needs
+    /// them. A bucket that does not exist has no rules.
+    pub async fn bucket_cors_for_request(
+        &self,
+        tenant: &str,
+        name: &str,
+    ) -> S3Result<Option<Arc<Vec<CorsRule>>>> {
+        if let Some(rules) = self.bucket_cors.get(tenant, name) {
+            return Ok(rules);
+        }
+        let rules = match self.get_bucket_cors(tenant, name).await {
+            Ok(rules) => Some(Arc::new(rules)),
+            Err(S3Error::NoSuchBucket(_) | S3Error::NoSuchCorsConfiguration(_)) => None,
+            Err(e) => return Err(e),
+        };
+        self.bucket_cors.insert(tenant, name, rules.clone());
+        Ok(rules)
+    }
+
+    /// Check if bucket is empty
+    pub async fn bucket_is_empty(&self, tenant: &str, name: &str) -> S3Result<bool> {
+        if self.use_memory {
+            let buckets = self.memory_buckets.read().await;
+            let bucket = buckets
+                .get(&memory_bucket_key(tenant, name))
+                .ok_or_else(|| S3Error::NoSuchBucket(name.to_string()))?;
+            return Ok(bucket.objects.is_empty());
+        }
+
+        // Use metadata service
+        if let Some(ref meta) = self.metadata {
+            let is_empty = meta
+                .bucket_is_empty(tenant, name)
+                .await
+                .map_err(S3Error::from)?;
+            return Ok(is_empty);
+        }
+
+        // No backend, assume empty
+        Ok(true)
+    }
+
+    // =========================================================================
+    // OBJECT OPERATIONS
+    // =========================================================================
+
+    /// Put an object
+    ///
+    /// An object with `expires_at` is hidden once that time passes and later
+    /// garbage collected by the upload janitor.
+    pub async fn put_object(
+        &self,
+        tenant: &str,
+        bucket: &str,
+        key: &str,
+        data: Bytes,
+        content_type: &str,
+        expires_at: Option<chrono::DateTime<chrono::Utc>>,
+    ) -> S3Result<String> {
+        self.put_object_if(
+            tenant,
+            bucket,
+            key,
+            data,
+            content_type,
+            expires_at,
+            &UserMetadata::new(),
+            &ContentHeaders::default(),
+            None,
+            None,
+            |_| Ok(()),
+        )
+        .await
+        .map(|output| output.etag)
+    }
+
+    /// Put an object with user metadata if `check` accepts the version
+    /// currently stored
+    ///
+    /// Uploads of the same key are serialized: the check and the write run
+    /// under the key's write lock, and the new version replaces the old one
+    /// in a single metadata transaction. When another upload holds the lock,
+    /// this waits or fails with a conflict depending on the overwrite policy.
+    ///
+    /// A retry carrying the `idempotency_key` of an upload that was already
+    /// published returns that upload's result without storing the data again.
+    /// Idempotency keys need the metadata service; in memory mode they are
+    /// ignored.
+    ///
+    /// With `sse` (or a gateway default) the object is sealed with a new data
+    /// key before it leaves the gateway.
+    pub async fn put_object_if<F>(
+        &self,
+        tenant: &str,
+        bucket: &str,
+        key: &str,
+        data: Bytes,
+        content_type: &str,
+        expires_at: Option<chrono::DateTime<chrono::Utc>>,
+        user_metadata: &UserMetadata,
+        content_headers: &ContentHeaders,
+        sse: Option<SseAlgorithm>,
+        idempotency_key: Option<&str>,
+        check: F,
+    ) -> S3Result<PutObjectOutput>
+    where
+        F: FnOnce(Option<&ObjectMetadata>) -> S3Result<()> + Send,
+    {
+        if self.use_memory {
+            // Seal the object before taking the bucket lock; the ETag is
+            // that of the plaintext
+            let size = data.len() as u64;
+            let etag = format!("{:x}", md5::compute(&data));
+            let (data, encryption) = match self.new_data_key(sse).await? {
+                Some((data_key, encryption)) => (
+                    crate::kms::seal(&data_key, &data).map_err(S3Error::from)?,
+                    Some(encryption),
+                ),
+                None => (data, None),
+            };
+            let new_size = data.len();
+
+            // Check memory limit
+            let current_bytes = self
+                .memory_bytes_used
+                .load(std::sync::atomic::Ordering::Relaxed);
+            if current_bytes + new_size > MAX_MEMORY_BYTES {
+                return Err(S3Error::service(
+                    ErrorCode::StorageFull,
+                    format!(
+                        "In-memory storage limit ({} MB) exceeded",
+                        MAX_MEMORY_BYTES / (1024 * 1024)
+                    ),
+                ));
+            }
+
+            let mut buckets = self.memory_buckets.write().await;
+            let bucket_state = buckets
+                .get_mut(&memory_bucket_key(tenant, bucket))
                 .ok_or_else(|| S3Error::NoSuchBucket(bucket.to_string()))?;
 
-            // Get available nodes
-            let nodes = meta
-                .get_online_nodes()
+            // The bucket write guard serializes uploads of the key. A locked
+            // object cannot be overwritten even once it has expired.
+            let now = chrono::Utc::now();
+            if let Some(retention) = bucket_state.objects.get(key).and_then(|o| o.retention) {
+                retention.check_overwrite(key, now)?;
+            }
+            let current = bucket_state
+                .objects
+                .get(key)
+                .filter(|o| !o.is_expired())
+                .map(|o| o.metadata(key));
+            check(current.as_ref())?;
+            let retention = bucket_state
+                .object_lock
+                .and_then(|lock| lock.default_retention)
+                .map(|default| default.retention_from(now));
+            let content_headers = ContentHeaders {
+                cache_control: content_headers
+                    .cache_control
+                    .clone()
+                    .or_else(|| bucket_state.cache_control.clone()),
+                ..content_headers.clone()
+            };
+
+            // Track size delta (subtract old object size if overwriting)
+            let old_size = bucket_state
+                .objects
+                .get(key)
+                .map(|o| o.data.len())
+                .unwrap_or(0);
+
+            bucket_state.objects.insert(
+                key.to_string(),
+                StoredObject {
+                    data,
+                    content_type: content_type.to_string(),
+                    etag: etag.clone(),
+                    created_at: now,
+                    expires_at,
+                    user_metadata: user_metadata.clone(),
+                    retention,
+                    encryption: encryption.clone(),
+                    public_read: false,
+                    content_headers,
+                },
+            );
+
+            // Update tracked memory usage
+            if new_size >= old_size {
+                self.memory_bytes_used
+                    .fetch_add(new_size - old_size, std::sync::atomic::Ordering::Relaxed);
+            } else {
+                self.memory_bytes_used
+                    .fetch_sub(old_size - new_size, std::sync::atomic::Ordering::Relaxed);
+            }
+
+            // Publish event
+            drop(buckets);
+            self.publish_file_created(bucket, key, 0).await;
+
+            return Ok(PutObjectOutput {
+                etag,
+                size,
+                replayed: false,
+                encryption: encryption.map(|e| e.algorithm),
+            });
+        }
+
+        // Use metadata service + node storage with erasure coding
+        if let Some(ref meta) = self.metadata {
+            let lock = self.lock_object(meta, tenant, bucket, key).await?;
+
+            let result = async {
+                if let Some(idempotency_key) = idempotency_key {
+                    if let Some(output) = self
+                        .replay_upload(meta, tenant, bucket, key, &data, idempotency_key)
+                        .await?
+                    {
+                        return Ok(output);
+                    }
+                }
+
+                let current = self.get_object_metadata(tenant, bucket, key).await?;
+                check_not_locked(key, current.as_ref())?;
+                check(current.as_ref())?;
+                let size = data.len() as u64;
+                let encryption = self.new_data_key(sse).await?;
+                let algorithm = encryption.as_ref().map(|(_, e)| e.algorithm);
+                let etag = self
+                    .store_object(
+                        meta,
+                        tenant,
+                        bucket,
+                        key,
+                        data,
+                        content_type,
+                        expires_at,
+                        user_metadata,
+                        content_headers,
+                        encryption,
+                        idempotency_key,
+                    )
+                    .await?;
+                Ok(PutObjectOutput {
+                    etag,
+                    size,
+                    replayed: false,
+                    encryption: algorithm,
+                })
+            }
+            .await;
+
+            if let Err(e) = lock.release().await {
+                warn!(error = %e, bucket = bucket, key = key, "Failed to release object lock");
+            }
+            return result;
+        }
+
+        Err(S3Error::service(
+            ErrorCode::ServiceUnavailable,
+            "No storage backend available",
+        ))
+    }
+
+    /// Put an object whose body arrives as a stream
+    ///
+    /// The body is erasure coded one stripe at a time as it arrives, so the
+    /// upload holds at most a stripe of it however large the object is.
+    /// Bodies that end within the first stripe, and every upload in memory
+    /// mode, are stored whole as by [`AppState::put_object_if`].
+    pub async fn put_object_stream<S>(
+        &self,
+        tenant: &str,
+        bucket: &str,
+        key: &str,
+        mut body: S,
+        content_type: &str,
+        expires_at: Option<chrono::DateTime<chrono::Utc>>,
+        user_metadata: &UserMetadata,
+        content_headers: &ContentHeaders,
+        sse: Option<SseAlgorithm>,
+    ) -> S3Result<PutObjectOutput>
+    where
+        S: Stream<Item = S3Result<Bytes>> + Unpin + Send,
+    {
+        // Buffer up to the first stripe to tell small bodies from streams
+        let mut head = BytesMut::new();
+        let mut overflow = None;
+        while let Some(piece) = body.next().await {
+            let piece = piece?;
+            if !self.use_memory && head.len() + piece.len() > self.writes.stripe_size {
+                overflow = Some(piece);
+                break;
+            }
+            head.extend_from_slice(&piece);
+        }
+
+        let (Some(overflow), Some(meta)) = (overflow, &self.metadata) else {
+            return self
+                .put_object_if(
+                    tenant,
+                    bucket,
+                    key,
+                    head.freeze(),
+                    content_type,
+                    expires_at,
+                    user_metadata,
+                    content_headers,
+                    sse,
+                    None,
+                    |_| Ok(()),
+                )
+                .await;
+        };
+
+        let lock = self.lock_object(meta, tenant, bucket, key).await?;
+        let body = futures::stream::iter([Ok(head.freeze()), Ok(overflow)]).chain(body);
+        let result = async {
+            let current = self.get_object_metadata(tenant, bucket, key).await?;
+            check_not_locked(key, current.as_ref())?;
+            let encryption = self.new_data_key(sse).await?;
+            self.store_object_stream(
+                meta,
+                tenant,
+                bucket,
+                key,
+                body,
+                content_type,
+                expires_at,
+                user_metadata,
+                content_headers,
+                encryption,
+            )
+            .await
+        }
+        .await;
+
+        if let Err(e) = lock.release().await {
+            warn!(error = %e, bucket = bucket, key = key, "Failed to release object lock");
+        }
+        result
+    }
+
+    /// Result of an earlier upload with the same idempotency key, if any
+    ///
+    /// A key reused for a different object or different content is rejected
+    /// rather than silently returning the earlier result.
+    async fn replay_upload(
+        &self,
+        meta: &MetadataService,
+        tenant: &str,
+        bucket: &str,
+        key: &str,
+        data: &[u8],
+        idempotency_key: &str,
+    ) -> S3Result<Option<PutObjectOutput>> {
+        let Some(earlier) = meta
+            .get_idempotent_upload(tenant, idempotency_key)
+            .await
+            .map_err(S3Error::from)?
+        else {
+            return Ok(None);
+        };
+
+        let content_hash = cyxcloud_core::ContentHash::compute(data);
+        if earlier.path != format!("{}/{}", bucket, key)
+            || earlier.content_hash != content_hash.as_bytes()
+        {
+            return Err(S3Error::InvalidRequest(format!(
+                "Idempotency key {} was used for a different upload",
+                idempotency_key
+            )));
+        }
+
+        info!(
+            bucket = bucket,
+            key = key,
+            file_id = %earlier.file_id,
+            "Replaying upload for idempotency key"
+        );
+        let encryption = meta
+            .get_file(tenant, earlier.file_id)
+            .await
+            .map_err(S3Error::from)?
+            .and_then(|file| file_encryption(&file));
+        Ok(Some(PutObjectOutput {
+            etag: hex::encode(&earlier.content_hash),
+            size: earlier.size_bytes as u64,
+            replayed: true,
+            encryption,
+        }))
+    }
+
+    /// Take the write lock of an object key, as the overwrite policy says
+    async fn lock_object(
+        &self,
+        meta: &MetadataService,
+        tenant: &str,
+        bucket: &str,
+        key: &str,
+    ) -> S3Result<ObjectLock> {
+        let path = format!("{}/{}", bucket, key);
+        let wait = match self.writes.overwrite_policy {
+            OverwritePolicy::LastWriteWins => Some(self.writes.lock_timeout),
+            OverwritePolicy::Reject => None,
+        };
+
+        let lock = meta
+            .lock_object(tenant, &path, wait)
+            .await
+            .map_err(S3Error::from)?;
+        lock.ok_or_else(|| match self.writes.overwrite_policy {
+            OverwritePolicy::LastWriteWins => S3Error::service(
+                ErrorCode::Timeout,
+                format!("Timed out waiting for another upload of {}", key),
+            ),
+            OverwritePolicy::Reject => S3Error::service(
+                ErrorCode::Conflict,
+                format!("Another upload of {} is in progress", key),
+            ),
+        })
+    }
+
+    /// Store an object on the storage nodes and publish it in metadata
+    ///
+    /// With a data key the object is sealed first, and its chunks and shards
+    /// are cut from the sealed bytes.
+    async fn store_object(
+        &self,
+        meta: &MetadataService,
+        tenant: &str,
+        bucket: &str,
+        key: &str,
+        data: Bytes,
+        content_type: &str,
+        expires_at: Option<chrono::DateTime<chrono::Utc>>,
+        user_metadata: &UserMetadata,
+        content_headers: &ContentHeaders,
+        encryption: Option<(EncryptionKey, ObjectEncryption)>,
+        idempotency_key: Option<&str>,
+    ) -> S3Result<String> {
+        let (replicas, nodes) = self
+            .upload_targets(meta, tenant, bucket, StorageClass::Standard)
+            .await?;
+
+        // Create placement engine for smart node selection
+        let placement_engine = PlacementEngine::new(self.placement_config());
+
+        // Convert nodes to PlacementNodes for the engine
+        let placement_nodes: Vec<PlacementNode> =
+            nodes.iter().map(PlacementNode::from_node).collect();
+
+        // Create file record
+        let file_id = Uuid::new_v4();
+        let content_hash = cyxcloud_core::ContentHash::compute(&data);
+        let size = data.len() as u64;
+        let (data, encryption) = match encryption {
+            Some((data_key, encryption)) => (
+                crate::kms::seal(&data_key, &data).map_err(S3Error::from)?,
+                Some(encryption),
+            ),
+            None => (data, None),
+        };
+
+        // Create erasure encoder (10 data + 4 parity = 14 shards)
+        let erasure_encoder = ErasureEncoder::new()
+            .map_err(|e| S3Error::Internal(format!("Failed to create erasure encoder: {}", e)))?;
+
+        // Small objects are copied whole instead of being erasure coded,
+        // where padding would dominate their footprint
+        let storage_mode = if !data.is_empty() && data.len() < SMALL_OBJECT_THRESHOLD {
+            StorageMode::Replicated
+        } else {
+            StorageMode::Erasure
+        };
+
+        let (chunks, chunk_count, total_shards) = match storage_mode {
+            StorageMode::Replicated => (Vec::new(), 1, 1),
+            StorageMode::Erasure => {
+                let chunks = split_bytes_into_chunks(&data, DEFAULT_CHUNK_SIZE, Some(file_id))
+                    .map_err(S3Error::from)?;
+                let chunk_count = chunks.len();
+                (chunks, chunk_count, chunk_count * TOTAL_SHARDS)
+            }
+        };
+
+        info!(
+            bucket = bucket,
+            key = key,
+            size = data.len(),
+            chunks = chunk_count,
+            total_shards = total_shards,
+            mode = storage_mode.as_str(),
+            "Storing object with {} chunks ({} total shards)",
+            chunk_count,
+            total_shards
+        );
+
+        let (data_shards, parity_shards, chunk_size) = match storage_mode {
+            StorageMode::Replicated => (1, 0, data.len()),
+            StorageMode::Erasure => (DATA_SHARDS, PARITY_SHARDS, DEFAULT_CHUNK_SIZE),
+        };
+
+        // Create file record FIRST so chunks can reference it (foreign key)
+        let create_file = cyxcloud_metadata::CreateFile {
+            id: Some(file_id),
+            name: key.split('/').last().unwrap_or(key).to_string(),
+            path: format!("{}/{}", bucket, key),
+            content_hash: content_hash.as_bytes().to_vec(),
+            size_bytes: size as i64,
+            chunk_count: chunk_count as i32,
+            data_shards: data_shards as i32,
+            parity_shards: parity_shards as i32,
+            chunk_size: chunk_size as i32,
+            erasure_backend: erasure_encoder.backend().to_string(),
+            storage_mode,
+            owner_id: Some(self.user_id),
+            bucket: Some(bucket.to_string()),
+            tenant_id: tenant.to_string(),
+            content_type: Some(content_type.to_string()),
+            metadata: user_metadata_to_json(user_metadata),
+            expires_at,
+            encryption: encryption.as_ref().map(ObjectEncryption::to_file),
+            cache_control: content_headers.cache_control.clone(),
+            content_disposition: content_headers.content_disposition.clone(),
+            content_encoding: content_headers.content_encoding.clone(),
+        };
+        let file = meta
+            .register_file(create_file)
+            .await
+            .map_err(S3Error::from)?;
+
+        // Write-ahead intent so a crash mid-upload leaves cleanable state
+        meta.create_upload_intent(
+            file.id,
+            chunk_count as i32,
+            total_shards as i32,
+            crate::upload_janitor::upload_intent_ttl(),
+            idempotency_key,
+        )
+        .await
+        .map_err(S3Error::from)?;
+
+        debug!(file_id = %file.id, "File record created, now storing shards");
+
+        let upload = ShardUpload {
+            meta,
+            nodes: &nodes,
+            placement_nodes: &placement_nodes,
+            file_id,
+            encrypted: encryption.is_some(),
+            failed_nodes: Default::default(),
+        };
+
+        // Track total shards stored for verification
+        let mut shards_stored = 0;
+        let mut failed_shards = 0;
+
+        if storage_mode == StorageMode::Replicated {
+            let copies = replicas.max(SMALL_OBJECT_REPLICAS);
+            if self
+                .store_replicated_blob(&upload, &placement_engine, &data, copies)
                 .await
-                .map_err(|e| S3Error::Internal(e.to_string()))?;
+            {
+                shards_stored += 1;
+            } else {
+                failed_shards += 1;
+            }
+        }
+
+        // Process each chunk with erasure coding
+        for chunk in &chunks {
+            let chunk_id = chunk.metadata.id.as_bytes();
+
+            // Encode chunk into shards using erasure coding (data shards
+            // are slices of the uploaded body)
+            let shards = erasure_encoder
+                .encode_bytes(&chunk.data)
+                .map_err(|e| S3Error::Internal(format!("Erasure encoding failed: {}", e)))?;
+
+            debug!(
+                chunk_index = chunk.metadata.index,
+                chunk_size = chunk.data.len(),
+                shard_count = shards.len(),
+                "Encoded chunk into {} shards",
+                shards.len()
+            );
+
+            let (stored, failed) = self
+                .store_chunk_shards(
+                    &upload,
+                    &placement_engine,
+                    replicas,
+                    ChunkMeta {
+                        encrypted: upload.encrypted,
+                        ..ChunkMeta::from(&chunk.metadata)
+                    },
+                    &shards,
+                )
+                .await;
+            shards_stored += stored;
+            failed_shards += failed;
+        }
+
+        // Check if we stored enough shards (need at least DATA_SHARDS per
+        // chunk, or one copy of a replicated object)
+        let min_shards_needed = chunk_count * data_shards;
+        if shards_stored < min_shards_needed {
+            error!(
+                shards_stored = shards_stored,
+                min_needed = min_shards_needed,
+                failed = failed_shards,
+                "Insufficient shards stored, data may not be recoverable"
+            );
+            return Err(S3Error::service(
+                ErrorCode::InsufficientShards,
+                format!(
+                    "Failed to store sufficient shards: {} stored, {} needed",
+                    shards_stored, min_shards_needed
+                ),
+            ));
+        }
+
+        // Publish the new version, replacing the previous one (this also
+        // clears the upload intent)
+        meta.publish_file_version(tenant, file.id)
+            .await
+            .map_err(S3Error::from)?;
+
+        // Calculate ETag
+        let etag = hex::encode(content_hash.as_bytes());
+
+        info!(
+            bucket = bucket,
+            key = key,
+            file_id = %file.id,
+            etag = %etag,
+            shards_stored = shards_stored,
+            failed_shards = failed_shards,
+            mode = storage_mode.as_str(),
+            "Object stored successfully"
+        );
+
+        // Publish event
+        self.publish_file_created(bucket, key, size).await;
+
+        Ok(etag)
+    }
+
+    /// Replicas per shard of a bucket, and the online nodes to store objects
+    /// of a storage class on
+    async fn upload_targets(
+        &self,
+        meta: &MetadataService,
+        tenant: &str,
+        bucket: &str,
+        class: StorageClass,
+    ) -> S3Result<(usize, Vec<cyxcloud_metadata::Node>)> {
+        // Get bucket info
+        let bucket_info = meta
+            .get_bucket(tenant, bucket)
+            .await
+            .map_err(S3Error::from)?
+            .ok_or_else(|| S3Error::NoSuchBucket(bucket.to_string()))?;
+
+        // Get available nodes
+        let nodes = meta.get_online_nodes().await.map_err(S3Error::from)?;
+
+        // Need at least TOTAL_SHARDS (14) nodes for optimal distribution
+        // but can work with fewer using replication
+        if nodes.is_empty() {
+            return Err(S3Error::service(
+                ErrorCode::NoNodesAvailable,
+                "No storage nodes available",
+            ));
+        }
 
-            // Need at least TOTAL_SHARDS (14) nodes for optimal distribution
-            // but can work with fewer using replication
-            if nodes.is_empty() {
-                return Err(S3Error::Internal("No storage nodes available".to_string()));
-            }
+        // Nodes each shard is written to (1 = erasure coding only)
+        let replicas = bucket_info.durability_mode().shard_replicas();
+        Ok((replicas, tier_nodes(nodes, class)))
+    }
 
-            // Create placement engine for smart node selection
-            let placement_config = PlacementConfig::default();
-            let placement_engine = PlacementEngine::new(placement_config);
+    /// Store a streamed object stripe by stripe and publish it in metadata
+    ///
+    /// Every stripe becomes one erasure coded chunk of the file, so the
+    /// object reads back and is repaired like any other. The file record is
+    /// created before the first stripe and given its hash and size once the
+    /// stream has ended. With a data key the body is sealed segment by
+    /// segment on its way into the stripes.
+    async fn store_object_stream<S>(
+        &self,
+        meta: &MetadataService,
+        tenant: &str,
+        bucket: &str,
+        key: &str,
+        mut body: S,
+        content_type: &str,
+        expires_at: Option<chrono::DateTime<chrono::Utc>>,
+        user_metadata: &UserMetadata,
+        content_headers: &ContentHeaders,
+        encryption: Option<(EncryptionKey, ObjectEncryption)>,
+    ) -> S3Result<PutObjectOutput>
+    where
+        S: Stream<Item = S3Result<Bytes>> + Unpin + Send,
+    {
+        let (replicas, nodes) = self
+            .upload_targets(meta, tenant, bucket, StorageClass::Standard)
+            .await?;
+        let placement_engine = PlacementEngine::new(self.placement_config());
+        let placement_nodes: Vec<PlacementNode> =
+            nodes.iter().map(PlacementNode::from_node).collect();
+
+        let erasure_encoder = ErasureEncoder::new()
+            .map_err(|e| S3Error::Internal(format!("Failed to create erasure encoder: {}", e)))?;
+        let erasure_backend = erasure_encoder.backend().to_string();
+        let mut stripes =
+            StripeEncoder::new(erasure_encoder, self.writes.stripe_size).map_err(S3Error::from)?;
+
+        // Hash, size and chunk count are filled in when the stream ends
+        let file_id = Uuid::new_v4();
+        let create_file = cyxcloud_metadata::CreateFile {
+            id: Some(file_id),
+            name: key.split('/').last().unwrap_or(key).to_string(),
+            path: format!("{}/{}", bucket, key),
+            content_hash: Vec::new(),
+            size_bytes: 0,
+            chunk_count: 0,
+            data_shards: DATA_SHARDS as i32,
+            parity_shards: PARITY_SHARDS as i32,
+            chunk_size: self.writes.stripe_size as i32,
+            erasure_backend,
+            storage_mode: StorageMode::Erasure,
+            owner_id: Some(self.user_id),
+            bucket: Some(bucket.to_string()),
+            tenant_id: tenant.to_string(),
+            content_type: Some(content_type.to_string()),
+            metadata: user_metadata_to_json(user_metadata),
+            expires_at,
+            encryption: encryption.as_ref().map(|(_, e)| e.to_file()),
+            cache_control: content_headers.cache_control.clone(),
+            content_disposition: content_headers.content_disposition.clone(),
+            content_encoding: content_headers.content_encoding.clone(),
+        };
+        let file = meta
+            .register_file(create_file)
+            .await
+            .map_err(S3Error::from)?;
+
+        // Write-ahead intent; the shard count is not known in advance
+        meta.create_upload_intent(
+            file.id,
+            0,
+            0,
+            crate::upload_janitor::upload_intent_ttl(),
+            None,
+        )
+        .await
+        .map_err(S3Error::from)?;
+
+        info!(
+            bucket = bucket,
+            key = key,
+            file_id = %file.id,
+            stripe_size = stripes.stripe_size(),
+            "Storing streamed object"
+        );
 
-            // Convert nodes to PlacementNodes for the engine
-            let placement_nodes: Vec<PlacementNode> =
-                nodes.iter().map(PlacementNode::from_node).collect();
+        let upload = ShardUpload {
+            meta,
+            nodes: &nodes,
+            placement_nodes: &placement_nodes,
+            file_id,
+            encrypted: encryption.is_some(),
+            failed_nodes: Default::default(),
+        };
+        let algorithm = encryption.as_ref().map(|(_, e)| e.algorithm);
+        let mut sealer = encryption.map(|(data_key, _)| Sealer::new(data_key));
+
+        let mut hasher = blake3::Hasher::new();
+        let mut size = 0;
+        let mut shards_stored = 0;
+        let mut failed_shards = 0;
+        let mut chunk_count = 0;
+
+        while let Some(piece) = body.next().await {
+            let piece = piece?;
+            hasher.update(&piece);
+            size += piece.len() as u64;
+            let piece = match sealer.as_mut() {
+                Some(sealer) => sealer.push(&piece).map_err(S3Error::from)?,
+                None => piece,
+            };
+            for stripe in stripes.push(piece).map_err(S3Error::from)? {
+                let (stored, failed) = self
+                    .store_stripe(&upload, &placement_engine, replicas, &stripe)
+                    .await?;
+                shards_stored += stored;
+                failed_shards += failed;
+                chunk_count += 1;
+            }
+        }
 
-            // Create file record
-            let file_id = Uuid::new_v4();
-            let content_hash = cyxcloud_core::ContentHash::compute(&data);
+        if let Some(sealer) = sealer {
+            let last = sealer.finish().map_err(S3Error::from)?;
+            for stripe in stripes.push(last).map_err(S3Error::from)? {
+                let (stored, failed) = self
+                    .store_stripe(&upload, &placement_engine, replicas, &stripe)
+                    .await?;
+                shards_stored += stored;
+                failed_shards += failed;
+                chunk_count += 1;
+            }
+        }
+        if let Some(stripe) = stripes.finish().map_err(S3Error::from)? {
+            let (stored, failed) = self
+                .store_stripe(&upload, &placement_engine, replicas, &stripe)
+                .await?;
+            shards_stored += stored;
+            failed_shards += failed;
+            chunk_count += 1;
+        }
 
-            // Create erasure encoder (10 data + 4 parity = 14 shards)
-            let erasure_encoder = ErasureEncoder::new().map_err(|e| {
-                S3Error::Internal(format!("Failed to create erasure encoder: {}", e))
-            })?;
+        let content_hash = hasher.finalize();
+        meta.set_file_content(file.id, content_hash.as_bytes(), size as i64, chunk_count)
+            .await
+            .map_err(S3Error::from)?;
 
-            // Split data into chunks
-            let chunks = split_into_chunks(&data, DEFAULT_CHUNK_SIZE, Some(file_id))
-                .map_err(|e| S3Error::Internal(e.to_string()))?;
+        // Publish the new version, replacing the previous one (this also
+        // clears the upload intent)
+        meta.publish_file_version(tenant, file.id)
+            .await
+            .map_err(S3Error::from)?;
 
-            let chunk_count = chunks.len();
-            let total_shards = chunk_count * TOTAL_SHARDS;
+        let etag = hex::encode(content_hash.as_bytes());
 
-            info!(
-                bucket = bucket,
-                key = key,
-                size = data.len(),
-                chunks = chunk_count,
-                total_shards = total_shards,
-                "Storing object with {} chunks ({} total shards using {}/{} erasure coding)",
-                chunk_count,
-                total_shards,
-                DATA_SHARDS,
-                PARITY_SHARDS
-            );
+        info!(
+            bucket = bucket,
+            key = key,
+            file_id = %file.id,
+            etag = %etag,
+            size = size,
+            chunks = chunk_count,
+            shards_stored = shards_stored,
+            failed_shards = failed_shards,
+            "Streamed object stored successfully"
+        );
 
-            // Create file record FIRST so chunks can reference it (foreign key)
-            let create_file = cyxcloud_metadata::CreateFile {
-                id: Some(file_id),
-                name: key.split('/').last().unwrap_or(key).to_string(),
-                path: format!("{}/{}", bucket, key),
-                content_hash: content_hash.as_bytes().to_vec(),
-                size_bytes: data.len() as i64,
-                chunk_count: chunk_count as i32,
-                data_shards: DATA_SHARDS as i32,
-                parity_shards: PARITY_SHARDS as i32,
-                chunk_size: DEFAULT_CHUNK_SIZE as i32,
-                owner_id: Some(self.user_id),
-                bucket: Some(bucket.to_string()),
-                content_type: Some(content_type.to_string()),
-                metadata: None,
-            };
-            let file = meta
-                .register_file(create_file)
-                .await
-                .map_err(|e| S3Error::Internal(e.to_string()))?;
+        self.publish_file_created(bucket, key, size).await;
 
-            debug!(file_id = %file.id, "File record created, now storing shards");
+        Ok(PutObjectOutput {
+            etag,
+            size,
+            replayed: false,
+            encryption: algorithm,
+        })
+    }
 
-            // Track total shards stored for verification
-            let mut shards_stored = 0;
-            let mut failed_shards = 0;
+    /// Store one stripe of a streamed object as a chunk of the file
+    ///
+    /// Fails once the stripe has fewer than DATA_SHARDS shards stored, since
+    /// the object could not be read back; the pending file is then left to
+    /// the upload janitor.
+    async fn store_stripe(
+        &self,
+        upload: &ShardUpload<'_>,
+        placement_engine: &PlacementEngine,
+        replicas: usize,
+        stripe: &EncodedStripe,
+    ) -> S3Result<(usize, usize)> {
+        let chunk_meta = ChunkMeta {
+            size: stripe.size as u64,
+            index: stripe.index,
+            // Unknown until the stream ends
+            total_chunks: 0,
+            parent_id: Some(upload.file_id),
+            created_at: chrono::Utc::now().timestamp(),
+            encrypted: upload.encrypted,
+            shard_index: None,
+            is_parity: false,
+            original_size: stripe.size as u64,
+        };
 
-            // Process each chunk with erasure coding
-            for chunk in &chunks {
-                let chunk_id = chunk.metadata.id.as_bytes();
+        let (stored, failed) = self
+            .store_chunk_shards(
+                upload,
+                placement_engine,
+                replicas,
+                chunk_meta,
+                &stripe.shards,
+            )
+            .await;
+        if stored < DATA_SHARDS {
+            error!(
+                file_id = %upload.file_id,
+                chunk_index = stripe.index,
+                shards_stored = stored,
+                min_needed = DATA_SHARDS,
+                "Insufficient shards stored for stripe, aborting upload"
+            );
+            return Err(S3Error::service(
+                ErrorCode::InsufficientShards,
+                format!(
+                    "Failed to store sufficient shards: {} stored, {} needed",
+                    stored, DATA_SHARDS
+                ),
+            ));
+        }
+        Ok((stored, failed))
+    }
 
-                // Encode chunk into shards using erasure coding
-                // For large chunks (> 1MB), use parallel encoding
-                let shards = if chunk.data.len() > 1024 * 1024 {
-                    erasure_encoder.encode_parallel(&chunk.data)
-                } else {
-                    erasure_encoder.encode(&chunk.data)
-                }
-                .map_err(|e| S3Error::Internal(format!("Erasure encoding failed: {}", e)))?;
+    /// Store the erasure coded shards of one chunk on placement-chosen nodes
+    ///
+    /// Returns how many shards were stored and how many could not be.
+    async fn store_chunk_shards(
+        &self,
+        upload: &ShardUpload<'_>,
+        placement_engine: &PlacementEngine,
+        replicas: usize,
+        chunk_meta: ChunkMeta,
+        shards: &[ShardData],
+    ) -> (usize, usize) {
+        let mut shards_stored = 0;
+        let mut failed_shards = 0;
+
+        // Use PlacementEngine to select nodes for shard distribution.
+        // EC-only buckets place each shard once; replicated buckets
+        // get `replicas` distinct nodes per shard.
+        let placement_decisions = placement_engine.select_nodes(
+            &upload.candidates(&self.node_client),
+            shards.len(), // Number of shards to place
+            replicas,
+            None, // No origin preference
+        );
 
-                debug!(
-                    chunk_index = chunk.metadata.index,
-                    chunk_size = chunk.data.len(),
-                    shard_count = shards.len(),
-                    "Encoded chunk into {} shards",
-                    shards.len()
+        // Distribute shards to selected nodes
+        for (shard, decision) in shards.iter().zip(placement_decisions.iter()) {
+            if decision.nodes.is_empty() {
+                warn!(
+                    shard_index = shard.index,
+                    "No nodes available for shard, skipping"
                 );
+                failed_shards += 1;
+                continue;
+            }
 
-                // Use PlacementEngine to select nodes for shard distribution
-                // Each shard needs 1 replica (erasure coding provides redundancy)
-                let placement_decisions = placement_engine.select_nodes(
-                    &placement_nodes,
-                    shards.len(), // Number of shards to place
-                    1,            // 1 replica per shard (erasure coding handles redundancy)
-                    None,         // No origin preference
-                );
+            // Create shard-specific chunk ID by hashing the shard data
+            // This satisfies content-addressing: shard_id = hash(shard_data)
+            // which the storage node validates before storing
+            let shard_id = ContentHash::compute(&shard.data).as_bytes().to_vec();
+
+            // Create metadata for this shard
+            let shard_meta = ChunkMeta {
+                size: shard.data.len() as u64,
+                shard_index: Some(shard.index as u32),
+                is_parity: shard.is_parity,
+                original_size: chunk_meta.size,
+                ..chunk_meta
+            };
 
-                // Distribute shards to selected nodes
-                for (shard, decision) in shards.iter().zip(placement_decisions.iter()) {
-                    if decision.nodes.is_empty() {
-                        warn!(
-                            shard_index = shard.index,
-                            "No nodes available for shard, skipping"
-                        );
-                        failed_shards += 1;
-                        continue;
-                    }
+            let stored_on = self
+                .store_shard_replicas(
+                    upload,
+                    &decision.nodes,
+                    replicas,
+                    &shard_id,
+                    &shard.data,
+                    shard_meta,
+                )
+                .await;
 
-                    // Create shard-specific chunk ID by hashing the shard data
-                    // This satisfies content-addressing: shard_id = hash(shard_data)
-                    // which the storage node validates before storing
-                    let shard_id = ContentHash::compute(&shard.data).as_bytes().to_vec();
-
-                    // Create metadata for this shard
-                    let shard_meta = ChunkMeta {
-                        size: shard.data.len() as u64,
-                        index: chunk.metadata.index,
-                        total_chunks: chunk.metadata.total_chunks,
-                        parent_id: chunk.metadata.parent_id,
-                        created_at: chunk.metadata.created_at,
-                        encrypted: chunk.metadata.encrypted,
-                        shard_index: Some(shard.index as u32),
-                    };
-
-                    // Get target node address
-                    let target_node = &decision.nodes[0];
-
-                    // Store shard on the selected node
-                    match self
-                        .node_client
-                        .store_chunk(
-                            &target_node.grpc_address,
-                            &shard_id,
-                            shard.data.clone(),
-                            Some(shard_meta.clone()),
-                        )
-                        .await
-                    {
-                        Ok(()) => {
-                            debug!(
-                                chunk_index = chunk.metadata.index,
-                                shard_index = shard.index,
-                                node = %target_node.grpc_address,
-                                is_parity = shard.is_parity,
-                                "Shard stored successfully"
-                            );
-
-                            // Register chunk in chunks table
-                            let create_chunk = CreateChunk {
-                                chunk_id: shard_id.clone(),
-                                file_id,
-                                chunk_index: chunk.metadata.index as i32,
-                                shard_index: shard.index as i32,
-                                is_parity: shard.is_parity,
-                                size_bytes: shard.data.len() as i32,
-                                replication_factor: 3, // Target replicas for rebalancer
-                            };
-                            if let Err(e) = meta.register_chunk(create_chunk).await {
-                                warn!(error = %e, "Failed to register chunk in database");
-                            }
-
-                            // Record shard location in metadata
-                            if let Some(node) = nodes
-                                .iter()
-                                .find(|n| n.grpc_address == target_node.grpc_address)
-                            {
-                                if let Err(e) = meta.record_chunk_location(&shard_id, node.id).await
-                                {
-                                    warn!(error = %e, "Failed to record shard location");
-                                }
-                            }
-                            shards_stored += 1;
-                        }
-                        Err(e) => {
-                            warn!(
-                                error = %e,
-                                chunk_index = chunk.metadata.index,
-                                shard_index = shard.index,
-                                "Failed to store shard on primary node, trying backup"
-                            );
-
-                            // Try to store on any other available node
-                            let mut stored = false;
-                            for backup_node in placement_nodes.iter() {
-                                if backup_node.grpc_address == target_node.grpc_address {
-                                    continue;
-                                }
-
-                                if let Ok(()) = self
-                                    .node_client
-                                    .store_chunk(
-                                        &backup_node.grpc_address,
-                                        &shard_id,
-                                        shard.data.clone(),
-                                        Some(shard_meta.clone()),
-                                    )
-                                    .await
-                                {
-                                    // Register chunk in chunks table
-                                    let create_chunk = CreateChunk {
-                                        chunk_id: shard_id.clone(),
-                                        file_id,
-                                        chunk_index: chunk.metadata.index as i32,
-                                        shard_index: shard.index as i32,
-                                        is_parity: shard.is_parity,
-                                        size_bytes: shard.data.len() as i32,
-                                        replication_factor: 3, // Target replicas for rebalancer
-                                    };
-                                    if let Err(e) = meta.register_chunk(create_chunk).await {
-                                        warn!(error = %e, "Failed to register chunk in database (backup node)");
-                                    }
-
-                                    if let Some(node) = nodes
-                                        .iter()
-                                        .find(|n| n.grpc_address == backup_node.grpc_address)
-                                    {
-                                        if let Err(e) = meta.record_chunk_location(&shard_id, node.id).await {
-                                            warn!(error = %e, "Failed to record shard location (backup node)");
-                                        }
-                                    }
-                                    shards_stored += 1;
-                                    stored = true;
-                                    break;
-                                }
-                            }
-
-                            if !stored {
-                                failed_shards += 1;
-                            }
-                        }
-                    }
-                }
+            if stored_on.is_empty() {
+                failed_shards += 1;
+                continue;
             }
 
-            // Check if we stored enough shards (need at least DATA_SHARDS per chunk)
-            let min_shards_needed = chunk_count * DATA_SHARDS;
-            if shards_stored < min_shards_needed {
-                error!(
-                    shards_stored = shards_stored,
-                    min_needed = min_shards_needed,
-                    failed = failed_shards,
-                    "Insufficient shards stored, data may not be recoverable"
+            debug!(
+                chunk_index = chunk_meta.index,
+                shard_index = shard.index,
+                nodes = ?stored_on,
+                is_parity = shard.is_parity,
+                "Shard stored successfully"
+            );
+            if stored_on.len() < replicas {
+                warn!(
+                    chunk_index = chunk_meta.index,
+                    shard_index = shard.index,
+                    stored = stored_on.len(),
+                    wanted = replicas,
+                    "Shard under-replicated, leaving the rest to the rebalancer"
                 );
-                return Err(S3Error::Internal(format!(
-                    "Failed to store sufficient shards: {} stored, {} needed",
-                    shards_stored, min_shards_needed
-                )));
             }
 
-            // Calculate ETag
-            let etag = hex::encode(content_hash.as_bytes());
+            // Register chunk and every shard location in metadata
+            let create_chunk = CreateChunk {
+                chunk_id: shard_id,
+                file_id: upload.file_id,
+                chunk_index: chunk_meta.index as i32,
+                shard_index: shard.index as i32,
+                is_parity: shard.is_parity,
+                size_bytes: shard.data.len() as i32,
+                replication_factor: replicas as i32,
+            };
+            upload.register_chunk(create_chunk, &stored_on).await;
+            shards_stored += 1;
+        }
+
+        (shards_stored, failed_shards)
+    }
 
-            info!(
-                bucket = bucket,
-                key = key,
-                file_id = %file.id,
-                etag = %etag,
-                shards_stored = shards_stored,
-                failed_shards = failed_shards,
-                "Object stored successfully with erasure coding"
+    /// Store a small object as a single unsharded chunk on `copies` nodes
+    ///
+    /// Returns whether at least one copy was stored; missing copies are left
+    /// to the rebalancer, which repairs towards the chunk's replication factor.
+    async fn store_replicated_blob(
+        &self,
+        upload: &ShardUpload<'_>,
+        placement_engine: &PlacementEngine,
+        data: &Bytes,
+        copies: usize,
+    ) -> bool {
+        let chunk_id = ContentHash::compute(data).as_bytes().to_vec();
+        let chunk_meta = ChunkMeta {
+            size: data.len() as u64,
+            index: 0,
+            total_chunks: 1,
+            parent_id: Some(upload.file_id),
+            created_at: chrono::Utc::now().timestamp(),
+            encrypted: upload.encrypted,
+            shard_index: None,
+            is_parity: false,
+            original_size: data.len() as u64,
+        };
+
+        let targets = placement_engine
+            .select_nodes(&upload.candidates(&self.node_client), 1, copies, None)
+            .into_iter()
+            .next()
+            .map(|decision| decision.nodes)
+            .unwrap_or_default();
+
+        let stored_on = self
+            .store_shard_replicas(upload, &targets, copies, &chunk_id, data, chunk_meta)
+            .await;
+        if stored_on.is_empty() {
+            return false;
+        }
+        if stored_on.len() < copies {
+            warn!(
+                file_id = %upload.file_id,
+                stored = stored_on.len(),
+                wanted = copies,
+                "Small object under-replicated, leaving the rest to the rebalancer"
             );
+        }
 
-            // Publish event
-            self.publish_file_created(bucket, key, data.len() as u64)
-                .await;
+        let create_chunk = CreateChunk {
+            chunk_id,
+            file_id: upload.file_id,
+            chunk_index: 0,
+            shard_index: 0,
+            is_parity: false,
+            size_bytes: data.len() as i32,
+            replication_factor: copies as i32,
+        };
+        upload.register_chunk(create_chunk, &stored_on).await;
+        true
+    }
 
-            return Ok(etag);
+    /// Store one shard on up to `replicas` nodes
+    ///
+    /// Writes go to the placement `targets` in parallel; replicas that fail
+    /// are retried on other online nodes one at a time, skipping nodes that
+    /// already failed this upload. Failed nodes are left out of placement for
+    /// the rest of the upload. Returns the addresses of the nodes now holding
+    /// the shard.
+    async fn store_shard_replicas(
+        &self,
+        upload: &ShardUpload<'_>,
+        targets: &[PlacementNode],
+        replicas: usize,
+        shard_id: &[u8],
+        data: &Bytes,
+        shard_meta: ChunkMeta,
+    ) -> Vec<String> {
+        let mut tried: HashSet<&str> = HashSet::new();
+        let mut stored = Vec::with_capacity(replicas);
+
+        // Log the placements in the upload intent before dispatch
+        for target in targets {
+            tried.insert(&target.grpc_address);
+            upload.record_intent(shard_id, &target.grpc_address).await;
         }
 
-        Err(S3Error::Internal(
-            "No storage backend available".to_string(),
-        ))
+        let results = futures::future::join_all(targets.iter().map(|target| {
+            self.node_client.store_chunk(
+                &target.grpc_address,
+                shard_id,
+                data.clone(),
+                Some(shard_meta),
+            )
+        }))
+        .await;
+        for (target, result) in targets.iter().zip(results) {
+            match result {
+                Ok(()) => stored.push(target.grpc_address.clone()),
+                Err(e) => {
+                    warn!(
+                        error = %e,
+                        node = %target.grpc_address,
+                        shard_index = ?shard_meta.shard_index,
+                        "Failed to store shard on node, trying backup"
+                    );
+                    upload.record_failure(&target.grpc_address);
+                }
+            }
+        }
+
+        // Replace failed replicas with any other available node
+        for backup in upload.placement_nodes {
+            if stored.len() >= replicas {
+                break;
+            }
+            if !tried.insert(&backup.grpc_address)
+                || !upload.is_usable(&self.node_client, &backup.grpc_address)
+            {
+                continue;
+            }
+
+            upload.record_intent(shard_id, &backup.grpc_address).await;
+            match self
+                .node_client
+                .store_chunk(
+                    &backup.grpc_address,
+                    shard_id,
+                    data.clone(),
+                    Some(shard_meta),
+                )
+                .await
+            {
+                Ok(()) => stored.push(backup.grpc_address.clone()),
+                Err(_) => upload.record_failure(&backup.grpc_address),
+            }
+        }
+
+        stored
     }
 
     /// Get an object
-    pub async fn get_object(&self, bucket: &str, key: &str) -> S3Result<Bytes> {
+    pub async fn get_object(&self, tenant: &str, bucket: &str, key: &str) -> S3Result<Bytes> {
+        self.read_object(tenant, bucket, key, None).await
+    }
+
+    /// Read an object, or only the inclusive byte range `range` of it
+    ///
+    /// For a range, only the chunks overlapping it are fetched and decoded.
+    async fn read_object(
+        &self,
+        tenant: &str,
+        bucket: &str,
+        key: &str,
+        range: Option<(u64, u64)>,
+    ) -> S3Result<Bytes> {
         if self.use_memory {
             let buckets = self.memory_buckets.read().await;
             let bucket_state = buckets
-                .get(bucket)
+                .get(&memory_bucket_key(tenant, bucket))
                 .ok_or_else(|| S3Error::NoSuchBucket(bucket.to_string()))?;
 
             let obj = bucket_state
                 .objects
                 .get(key)
+                .filter(|o| !o.is_expired())
                 .ok_or_else(|| S3Error::NoSuchKey(key.to_string()))?;
 
-            return Ok(obj.data.clone());
+            let Some(encryption) = obj.encryption.clone() else {
+                return match range {
+                    Some(range) => {
+                        let (start, end) = clamp_range(range, obj.data.len() as u64)?;
+                        Ok(obj.data.slice(start as usize..end as usize))
+                    }
+                    None => Ok(obj.data.clone()),
+                };
+            };
+
+            // Unwrap the data key without holding the bucket lock
+            let (data, size) = (obj.data.clone(), obj.size());
+            drop(buckets);
+            let range = match range {
+                Some(range) => clamp_range(range, size)?,
+                None => (0, size),
+            };
+            let sealed = SealedRange::new(size, range);
+            let data_key = self.data_key(&encryption).await?;
+            return sealed
+                .open(
+                    &data_key,
+                    &data[sealed.stored.0 as usize..sealed.stored.1 as usize],
+                )
+                .map_err(S3Error::from);
         }
 
         // Use metadata service + node retrieval with erasure decoding
@@ -1043,206 +2996,354 @@ impl AppState {
             // Get file info from database
             let file_path = format!("{}/{}", bucket, key);
             let file = meta
-                .get_file_by_path(&file_path)
+                .get_file_by_path(tenant, &file_path)
                 .await
-                .map_err(|e| S3Error::Internal(e.to_string()))?
+                .map_err(S3Error::from)?
                 .ok_or_else(|| S3Error::NoSuchKey(key.to_string()))?;
+            let file = self.readable_file(meta, file, key).await?;
 
-            // Get all shard records for this file
-            let shard_records = meta
-                .get_file_chunks(file.id)
-                .await
-                .map_err(|e| S3Error::Internal(e.to_string()))?;
+            let size = file.size_bytes as u64;
+            let (start, end) = match range {
+                Some(range) => clamp_range(range, size)?,
+                None => (0, size),
+            };
 
-            if shard_records.is_empty() {
-                return Err(S3Error::Internal("No shards found for file".to_string()));
-            }
+            // An encrypted object is read as the sealed segments covering the
+            // requested bytes, which are opened once fetched
+            let sealed = match file.encryption() {
+                Some(encryption) => {
+                    let encryption =
+                        ObjectEncryption::from_file(&encryption).map_err(S3Error::from)?;
+                    let data_key = self.data_key(&encryption).await?;
+                    Some((data_key, SealedRange::new(size, (start, end))))
+                }
+                None => None,
+            };
+            let (stored_size, stored_range) = match &sealed {
+                Some((_, sealed)) => (sealed_len(size), sealed.stored),
+                None => (size, (start, end)),
+            };
 
-            let num_chunks = file.chunk_count as usize;
+            let data = self
+                .read_stored_range(meta, &file, stored_size, stored_range)
+                .await?;
             info!(
                 bucket = bucket,
                 key = key,
-                file_id = %file.id,
-                shards = shard_records.len(),
-                chunks = num_chunks,
-                "Retrieving object with erasure decoding"
+                size = data.len(),
+                "Object retrieved and decoded successfully"
             );
+            return match &sealed {
+                Some((data_key, sealed)) => sealed.open(data_key, &data).map_err(S3Error::from),
+                None => Ok(data),
+            };
+        }
 
-            // Group shard records by chunk_index
-            let mut chunk_shards: HashMap<i32, Vec<&cyxcloud_metadata::Chunk>> = HashMap::new();
-            for shard in &shard_records {
-                chunk_shards
-                    .entry(shard.chunk_index)
-                    .or_default()
-                    .push(shard);
-            }
+        Err(S3Error::NoSuchKey(key.to_string()))
+    }
+
+    /// File to read an object's data from
+    ///
+    /// An archived object is read from its restored copy; without one it
+    /// cannot be read (403 InvalidObjectState).
+    async fn readable_file(
+        &self,
+        meta: &MetadataService,
+        file: cyxcloud_metadata::File,
+        key: &str,
+    ) -> S3Result<cyxcloud_metadata::File> {
+        if !file.is_archived() {
+            return Ok(file);
+        }
+        let not_restored = || {
+            S3Error::InvalidObjectState(format!(
+                "{} is archived; restore it with POST ?restore before reading it",
+                key
+            ))
+        };
+        let copy_id = file.restored_copy().ok_or_else(not_restored)?;
+        meta.database()
+            .get_file(copy_id)
+            .await
+            .map_err(S3Error::from)?
+            .ok_or_else(not_restored)
+    }
+
+    /// Read the stored bytes `[start, end)` of a file of `stored_size` bytes
+    ///
+    /// Stored bytes are the sealed ones of an encrypted file. Only the chunks
+    /// overlapping the range are fetched and decoded, with the erasure
+    /// profile the file was encoded with.
+    async fn read_stored_range(
+        &self,
+        meta: &MetadataService,
+        file: &cyxcloud_metadata::File,
+        stored_size: u64,
+        (start, end): (u64, u64),
+    ) -> S3Result<Bytes> {
+        // Get all shard records for this file
+        let shard_records = meta.get_file_chunks(file.id).await.map_err(S3Error::from)?;
+
+        if shard_records.is_empty() {
+            return Err(S3Error::service(
+                ErrorCode::InsufficientShards,
+                "No shards found for file",
+            ));
+        }
+
+        // A transition may have re-encoded the file since its record was read
+        let reread;
+        let file = if chunks_fit_layout(file, &shard_records) {
+            file
+        } else {
+            reread = meta
+                .database()
+                .get_file(file.id)
+                .await
+                .map_err(S3Error::from)?
+                .ok_or_else(|| S3Error::NoSuchKey(file.path.clone()))?;
+            &reread
+        };
 
-            // Batch-fetch all chunk locations for this file (avoids N+1 queries)
-            let all_locations = meta
+        // Small objects are a single chunk copied whole to several nodes
+        if file.is_replicated() {
+            let chunk_id = &shard_records[0].chunk_id;
+            let addresses = meta
                 .get_file_chunk_locations(file.id)
                 .await
-                .map_err(|e| S3Error::Internal(e.to_string()))?;
+                .map_err(S3Error::from)?
+                .remove(chunk_id)
+                .unwrap_or_default();
+            if addresses.is_empty() {
+                return Err(S3Error::service(
+                    ErrorCode::InsufficientShards,
+                    "No replicas found for object",
+                ));
+            }
 
-            // Create erasure decoder
-            let erasure_decoder = ErasureEncoder::new().map_err(|e| {
-                S3Error::Internal(format!("Failed to create erasure decoder: {}", e))
-            })?;
+            let data = self
+                .node_client
+                .get_chunk_from_any(&addresses, chunk_id)
+                .await
+                .map_err(|e| {
+                    S3Error::service(
+                        ErrorCode::InsufficientShards,
+                        format!("Failed to read object replica: {}", e),
+                    )
+                })?;
 
-            // Decode each chunk using erasure coding
-            let mut decoded_chunks: Vec<(i32, Bytes)> = Vec::with_capacity(num_chunks);
+            debug!(
+                file_id = %file.id,
+                replicas = addresses.len(),
+                "Replicated object retrieved"
+            );
+            let end = (end as usize).min(data.len());
+            return Ok(data.slice((start as usize).min(end)..end));
+        }
 
-            for chunk_idx in 0..num_chunks as i32 {
-                let shards = chunk_shards.get(&chunk_idx).ok_or_else(|| {
-                    S3Error::Internal(format!("No shards found for chunk {}", chunk_idx))
-                })?;
+        let num_chunks = file.chunk_count as usize;
+        let file_chunk_size = file.chunk_size.max(1) as u64;
 
-                // Retrieve shards from storage nodes
-                // We need at least DATA_SHARDS (10) out of TOTAL_SHARDS (14)
-                let mut shard_opts: Vec<Option<ShardData>> = vec![None; TOTAL_SHARDS];
-                let mut retrieved_count = 0;
+        // Chunks covering the requested bytes, and the bytes within them
+        let (range_start, range_end) = (start, end);
+        let first_chunk = (range_start / file_chunk_size) as i32;
+        let last_chunk = if range_end == 0 {
+            num_chunks as i32 - 1
+        } else {
+            ((range_end - 1) / file_chunk_size) as i32
+        };
 
-                for shard_record in shards {
-                    if retrieved_count >= DATA_SHARDS {
-                        // We have enough shards, no need to retrieve more
-                        break;
-                    }
+        // Erasure profile the file was encoded with
+        let erasure_config = ErasureConfig::new(
+            file.data_shards.max(1) as usize,
+            file.parity_shards.max(1) as usize,
+        )
+        .map_err(|e| S3Error::Internal(format!("Invalid erasure profile: {}", e)))?;
+        let (data_shards, total_shards) =
+            (erasure_config.data_shards, erasure_config.total_shards());
+
+        info!(
+            file_id = %file.id,
+            shards = shard_records.len(),
+            chunks = num_chunks,
+            data_shards,
+            total_shards,
+            first_chunk,
+            last_chunk,
+            "Retrieving object with erasure decoding"
+        );
 
-                    let shard_idx = shard_record.shard_index as usize;
-                    if shard_idx >= TOTAL_SHARDS {
-                        warn!(shard_index = shard_idx, "Invalid shard index, skipping");
-                        continue;
-                    }
+        // Group shard records by chunk_index
+        let mut chunk_shards: HashMap<i32, Vec<&cyxcloud_metadata::Chunk>> = HashMap::new();
+        for shard in &shard_records {
+            chunk_shards
+                .entry(shard.chunk_index)
+                .or_default()
+                .push(shard);
+        }
 
-                    // Look up node addresses from batch-fetched map
-                    let addresses = all_locations
-                        .get(&shard_record.chunk_id)
-                        .cloned()
-                        .unwrap_or_default();
+        // Batch-fetch all chunk locations for this file (avoids N+1 queries)
+        let all_locations = meta
+            .get_file_chunk_locations(file.id)
+            .await
+            .map_err(S3Error::from)?;
+
+        // Create erasure decoder for the backend that encoded the file
+        let erasure_decoder = file
+            .erasure_backend
+            .parse::<ErasureBackend>()
+            .and_then(|backend| ErasureEncoder::with_backend(erasure_config, backend))
+            .map_err(|e| S3Error::Internal(format!("Failed to create erasure decoder: {}", e)))?;
+
+        // Decode each chunk using erasure coding
+        let mut decoded_chunks: Vec<(i32, Bytes)> = Vec::with_capacity(num_chunks);
+
+        for chunk_idx in first_chunk..=last_chunk {
+            let shards = chunk_shards.get(&chunk_idx).ok_or_else(|| {
+                S3Error::service(
+                    ErrorCode::InsufficientShards,
+                    format!("No shards found for chunk {}", chunk_idx),
+                )
+            })?;
 
-                    if addresses.is_empty() {
-                        debug!(
-                            chunk_index = chunk_idx,
-                            shard_index = shard_idx,
-                            "No nodes have this shard, will try to reconstruct"
-                        );
-                        continue;
-                    }
+            // Retrieve shards from storage nodes
+            // We need at least data_shards out of total_shards
+            let mut shard_opts: Vec<Option<ShardData>> = vec![None; total_shards];
+            let mut retrieved_count = 0;
 
-                    // Retrieve shard from any available node
-                    match self
-                        .node_client
-                        .get_chunk_from_any(&addresses, &shard_record.chunk_id)
-                        .await
-                    {
-                        Ok(data) => {
-                            debug!(
-                                chunk_index = chunk_idx,
-                                shard_index = shard_idx,
-                                size = data.len(),
-                                "Shard retrieved"
-                            );
-                            shard_opts[shard_idx] = Some(ShardData::new(
-                                shard_idx as u8,
-                                data,
-                                shard_record.is_parity,
-                            ));
-                            retrieved_count += 1;
-                        }
-                        Err(e) => {
-                            debug!(
-                                error = %e,
-                                chunk_index = chunk_idx,
-                                shard_index = shard_idx,
-                                "Failed to retrieve shard, will try to reconstruct"
-                            );
-                        }
-                    }
+            for shard_record in shards {
+                if retrieved_count >= data_shards {
+                    // We have enough shards, no need to retrieve more
+                    break;
+                }
+
+                let shard_idx = shard_record.shard_index as usize;
+                if shard_idx >= total_shards {
+                    warn!(shard_index = shard_idx, "Invalid shard index, skipping");
+                    continue;
                 }
 
-                // Check if we have enough shards to decode
-                if retrieved_count < DATA_SHARDS {
-                    error!(
+                // Look up node addresses from batch-fetched map
+                let addresses = all_locations
+                    .get(&shard_record.chunk_id)
+                    .cloned()
+                    .unwrap_or_default();
+
+                if addresses.is_empty() {
+                    debug!(
                         chunk_index = chunk_idx,
-                        retrieved = retrieved_count,
-                        required = DATA_SHARDS,
-                        "Insufficient shards for erasure decoding"
+                        shard_index = shard_idx,
+                        "No nodes have this shard, will try to reconstruct"
                     );
-                    return Err(S3Error::Internal(format!(
-                        "Insufficient shards for chunk {}: have {}, need {}",
-                        chunk_idx, retrieved_count, DATA_SHARDS
-                    )));
+                    continue;
                 }
 
-                // Calculate the original chunk size for this chunk
-                // For the last chunk, it may be smaller
-                let chunk_size = if chunk_idx == (num_chunks as i32 - 1) {
-                    // Last chunk: remaining bytes
-                    let full_chunks_size =
-                        (num_chunks - 1) * file.chunk_size as usize;
-                    file.size_bytes as usize - full_chunks_size
-                } else {
-                    file.chunk_size as usize
-                };
-
-                // Decode shards back to original chunk data
-                let decoded = erasure_decoder
-                    .decode(&shard_opts, chunk_size)
-                    .map_err(|e| {
-                        S3Error::Internal(format!(
-                            "Erasure decoding failed for chunk {}: {}",
-                            chunk_idx, e
-                        ))
-                    })?;
+                // Retrieve shard from any available node
+                match self
+                    .node_client
+                    .get_chunk_from_any(&addresses, &shard_record.chunk_id)
+                    .await
+                {
+                    Ok(data) => {
+                        debug!(
+                            chunk_index = chunk_idx,
+                            shard_index = shard_idx,
+                            size = data.len(),
+                            "Shard retrieved"
+                        );
+                        shard_opts[shard_idx] = Some(ShardData::new(
+                            shard_idx as u8,
+                            data,
+                            shard_record.is_parity,
+                        ));
+                        retrieved_count += 1;
+                    }
+                    Err(e) => {
+                        debug!(
+                            error = %e,
+                            chunk_index = chunk_idx,
+                            shard_index = shard_idx,
+                            "Failed to retrieve shard, will try to reconstruct"
+                        );
+                    }
+                }
+            }
 
-                debug!(
+            // Check if we have enough shards to decode
+            if retrieved_count < data_shards {
+                error!(
                     chunk_index = chunk_idx,
-                    decoded_size = decoded.len(),
-                    "Chunk decoded successfully"
+                    retrieved = retrieved_count,
+                    required = data_shards,
+                    "Insufficient shards for erasure decoding"
                 );
-                decoded_chunks.push((chunk_idx, decoded));
+                return Err(S3Error::service(
+                    ErrorCode::InsufficientShards,
+                    format!(
+                        "Insufficient shards for chunk {}: have {}, need {}",
+                        chunk_idx, retrieved_count, data_shards
+                    ),
+                ));
             }
 
-            // Sort by chunk index and concatenate
-            decoded_chunks.sort_by_key(|(idx, _)| *idx);
-
-            let mut result = Vec::with_capacity(file.size_bytes as usize);
-            for (_, chunk_data) in decoded_chunks {
-                result.extend_from_slice(&chunk_data);
-            }
+            // Calculate the original chunk size for this chunk
+            // For the last chunk, it may be smaller
+            let chunk_size = if chunk_idx == (num_chunks as i32 - 1) {
+                // Last chunk: remaining bytes
+                let full_chunks_size = (num_chunks - 1) * file.chunk_size as usize;
+                stored_size as usize - full_chunks_size
+            } else {
+                file.chunk_size as usize
+            };
 
-            // Trim to exact original size (should already be correct, but be safe)
-            result.truncate(file.size_bytes as usize);
+            // Decode shards back to original chunk data
+            let decoded = erasure_decoder
+                .decode(&shard_opts, chunk_size)
+                .map_err(|e| {
+                    S3Error::Internal(format!(
+                        "Erasure decoding failed for chunk {}: {}",
+                        chunk_idx, e
+                    ))
+                })?;
 
-            info!(
-                bucket = bucket,
-                key = key,
-                size = result.len(),
-                "Object retrieved and decoded successfully"
+            debug!(
+                chunk_index = chunk_idx,
+                decoded_size = decoded.len(),
+                "Chunk decoded successfully"
             );
+            decoded_chunks.push((chunk_idx, decoded));
+        }
+
+        // Sort by chunk index and concatenate
+        decoded_chunks.sort_by_key(|(idx, _)| *idx);
 
-            return Ok(Bytes::from(result));
+        let decoded_len: usize = decoded_chunks.iter().map(|(_, c)| c.len()).sum();
+        let mut result = Vec::with_capacity(decoded_len);
+        for (_, chunk_data) in decoded_chunks {
+            result.extend_from_slice(&chunk_data);
         }
 
-        Err(S3Error::NoSuchKey(key.to_string()))
+        // Trim to the requested bytes (the whole file without a range)
+        let offset = first_chunk.max(0) as u64 * file_chunk_size;
+        let start = ((range_start - offset) as usize).min(result.len());
+        let end = ((range_end - offset) as usize).min(result.len());
+
+        Ok(Bytes::from(result).slice(start..end))
     }
 
-    /// Get object range
+    /// Get an inclusive byte range of an object
+    ///
+    /// Only the chunks covering the range are fetched from storage nodes.
     pub async fn get_object_range(
         &self,
+        tenant: &str,
         bucket: &str,
         key: &str,
         start: u64,
         end: u64,
     ) -> S3Result<Bytes> {
-        let data = self.get_object(bucket, key).await?;
-        let start = start as usize;
-        let end = (end as usize + 1).min(data.len());
-
-        if start >= data.len() {
-            return Err(S3Error::InvalidRequest("Range out of bounds".to_string()));
-        }
-
-        Ok(data.slice(start..end))
+        self.read_object(tenant, bucket, key, Some((start, end)))
+            .await
     }
 
     /// Get object with content hash verification
@@ -1251,11 +3352,12 @@ impl AppState {
     /// Used by DataStream service to ensure data integrity during training.
     pub async fn get_object_verified(
         &self,
+        tenant: &str,
         bucket: &str,
         key: &str,
         expected_hash: &[u8],
     ) -> S3Result<Bytes> {
-        let data = self.get_object(bucket, key).await?;
+        let data = self.get_object(tenant, bucket, key).await?;
 
         // Compute actual hash
         let actual_hash = ContentHash::compute(&data);
@@ -1269,13 +3371,16 @@ impl AppState {
                 actual = actual_hash.to_hex(),
                 "Content hash verification failed"
             );
-            return Err(S3Error::Internal(format!(
-                "Content hash mismatch for {}/{}: expected {}, got {}",
-                bucket,
-                key,
-                hex::encode(expected_hash),
-                actual_hash.to_hex()
-            )));
+            return Err(S3Error::service(
+                ErrorCode::IntegrityError,
+                format!(
+                    "Content hash mismatch for {}/{}: expected {}, got {}",
+                    bucket,
+                    key,
+                    hex::encode(expected_hash),
+                    actual_hash.to_hex()
+                ),
+            ));
         }
 
         debug!(
@@ -1289,79 +3394,127 @@ impl AppState {
     }
 
     /// Delete an object
-    pub async fn delete_object(&self, bucket: &str, key: &str) -> S3Result<()> {
+    ///
+    /// Fails if the object is under retention, unless it is in governance
+    /// mode and `bypass_governance` is set.
+    pub async fn delete_object(
+        &self,
+        tenant: &str,
+        bucket: &str,
+        key: &str,
+        bypass_governance: bool,
+    ) -> S3Result<()> {
+        let output = self
+            .delete_objects(tenant, bucket, &[key.to_string()], bypass_governance)
+            .await?;
+        if !output.locked.is_empty() {
+            return Err(S3Error::ObjectLocked(format!("{} is under retention", key)));
+        }
+        Ok(())
+    }
+
+    /// Delete several objects in one batch
+    ///
+    /// With the metadata service the files are soft-deleted in a single
+    /// statement and their shards queued for garbage collection. Keys that
+    /// do not exist are not an error. Keys under retention are kept and
+    /// reported as locked, unless they are in governance mode and
+    /// `bypass_governance` is set.
+    pub async fn delete_objects(
+        &self,
+        tenant: &str,
+        bucket: &str,
+        keys: &[String],
+        bypass_governance: bool,
+    ) -> S3Result<DeleteObjectsOutput> {
         if self.use_memory {
             let mut buckets = self.memory_buckets.write().await;
             let bucket_state = buckets
-                .get_mut(bucket)
+                .get_mut(&memory_bucket_key(tenant, bucket))
                 .ok_or_else(|| S3Error::NoSuchBucket(bucket.to_string()))?;
 
-            if let Some(removed) = bucket_state.objects.remove(key) {
-                self.memory_bytes_used
-                    .fetch_sub(removed.data.len(), std::sync::atomic::Ordering::Relaxed);
+            let now = chrono::Utc::now();
+            let mut output = DeleteObjectsOutput::default();
+            for key in keys {
+                let locked = bucket_state.objects.get(key).is_some_and(|o| {
+                    o.retention
+                        .is_some_and(|r| r.blocks_delete(now, bypass_governance))
+                });
+                if locked {
+                    output.locked.push(key.clone());
+                } else if let Some(removed) = bucket_state.objects.remove(key) {
+                    self.memory_bytes_used
+                        .fetch_sub(removed.data.len(), std::sync::atomic::Ordering::Relaxed);
+                    output.deleted.push(key.clone());
+                }
             }
 
-            // Publish event
+            // Publish events
             drop(buckets);
-            self.publish_file_deleted(bucket, key).await;
+            for key in &output.deleted {
+                self.publish_file_deleted(bucket, key).await;
+            }
 
-            return Ok(());
+            return Ok(output);
         }
 
         // Use metadata service
         if let Some(ref meta) = self.metadata {
-            // Get file info from database
-            let file_path = format!("{}/{}", bucket, key);
-            let file = meta
-                .get_file_by_path(&file_path)
-                .await
-                .map_err(|e| S3Error::Internal(e.to_string()))?;
-
-            if let Some(file) = file {
-                // Delete the file (soft delete)
-                meta.delete_file(file.id)
-                    .await
-                    .map_err(|e| S3Error::Internal(e.to_string()))?;
+            let paths: Vec<String> = keys
+                .iter()
+                .map(|key| format!("{}/{}", bucket, key))
+                .collect();
+            let files = meta
+                .delete_files(tenant, &paths, bypass_governance)
+                .await
+                .map_err(S3Error::from)?;
 
-                info!(bucket = bucket, key = key, file_id = %file.id, "Object deleted (database)");
+            let bucket_prefix = format!("{}/", bucket);
+            let strip = |path: &str| {
+                path.strip_prefix(&bucket_prefix)
+                    .unwrap_or(path)
+                    .to_string()
+            };
+            let mut output = DeleteObjectsOutput {
+                deleted: Vec::with_capacity(files.deleted.len()),
+                locked: files.locked.iter().map(|path| strip(path)).collect(),
+            };
+            for file in files.deleted {
+                let key = strip(&file.path);
+                info!(bucket = bucket, key = %key, file_id = %file.id, "Object deleted (database)");
 
                 // Publish event
-                self.publish_file_deleted(bucket, key).await;
+                self.publish_file_deleted(bucket, &key).await;
+                output.deleted.push(key);
             }
-            // If file doesn't exist, that's okay for DELETE
 
-            return Ok(());
+            return Ok(output);
         }
 
-        Err(S3Error::Internal(
-            "No storage backend available".to_string(),
+        Err(S3Error::service(
+            ErrorCode::ServiceUnavailable,
+            "No storage backend available",
         ))
     }
 
     /// Get object metadata
     pub async fn get_object_metadata(
         &self,
+        tenant: &str,
         bucket: &str,
         key: &str,
     ) -> S3Result<Option<ObjectMetadata>> {
         if self.use_memory {
             let buckets = self.memory_buckets.read().await;
             let bucket_state = buckets
-                .get(bucket)
+                .get(&memory_bucket_key(tenant, bucket))
                 .ok_or_else(|| S3Error::NoSuchBucket(bucket.to_string()))?;
 
-            let obj = match bucket_state.objects.get(key) {
-                Some(o) => o,
-                None => return Ok(None),
-            };
-
-            return Ok(Some(ObjectMetadata {
-                key: key.to_string(),
-                size: obj.data.len() as u64,
-                content_type: obj.content_type.clone(),
-                etag: obj.etag.clone(),
-                last_modified: obj.created_at.to_rfc3339(),
-            }));
+            return Ok(bucket_state
+                .objects
+                .get(key)
+                .filter(|o| !o.is_expired())
+                .map(|o| o.metadata(key)));
         }
 
         // Use metadata service
@@ -1369,11 +3522,13 @@ impl AppState {
             // Get file info from database
             let file_path = format!("{}/{}", bucket, key);
             let file = meta
-                .get_file_by_path(&file_path)
+                .get_file_by_path(tenant, &file_path)
                 .await
-                .map_err(|e| S3Error::Internal(e.to_string()))?;
+                .map_err(S3Error::from)?;
 
             if let Some(file) = file {
+                let retention = file_retention(&file);
+                let restore = self.file_restore_status(meta, &file).await?;
                 return Ok(Some(ObjectMetadata {
                     key: key.to_string(),
                     size: file.size_bytes as u64,
@@ -1382,6 +3537,14 @@ impl AppState {
                         .unwrap_or_else(|| "application/octet-stream".to_string()),
                     etag: hex::encode(&file.content_hash),
                     last_modified: file.updated_at.to_rfc3339(),
+                    expires_at: file.expires_at,
+                    user_metadata: user_metadata_from_json(file.metadata.as_ref()),
+                    retention,
+                    encryption: file_encryption(&file),
+                    public_read: file.public_read,
+                    content_headers: file_content_headers(&file),
+                    storage_class: file_storage_class(&file),
+                    restore,
                 }));
             }
 
@@ -1391,66 +3554,803 @@ impl AppState {
         Ok(None)
     }
 
+    /// Set the retention of an object
+    ///
+    /// The bucket must have object lock enabled. An active retention can
+    /// only be extended (see [`ObjectRetention::check_update`]).
+    pub async fn set_object_retention(
+        &self,
+        tenant: &str,
+        bucket: &str,
+        key: &str,
+        retention: ObjectRetention,
+    ) -> S3Result<()> {
+        if self.get_bucket_object_lock(tenant, bucket).await?.is_none() {
+            return Err(S3Error::InvalidRequest(
+                "Bucket is missing Object Lock Configuration".to_string(),
+            ));
+        }
+        let now = chrono::Utc::now();
+
+        if self.use_memory {
+            let mut buckets = self.memory_buckets.write().await;
+            let object = buckets
+                .get_mut(&memory_bucket_key(tenant, bucket))
+                .ok_or_else(|| S3Error::NoSuchBucket(bucket.to_string()))?
+                .objects
+                .get_mut(key)
+                .filter(|o| !o.is_expired())
+                .ok_or_else(|| S3Error::NoSuchKey(key.to_string()))?;
+            ObjectRetention::check_update(object.retention.as_ref(), &retention, now)?;
+            object.retention = Some(retention);
+            return Ok(());
+        }
+
+        if let Some(ref meta) = self.metadata {
+            let file = meta
+                .get_file_by_path(tenant, &format!("{}/{}", bucket, key))
+                .await
+                .map_err(S3Error::from)?
+                .ok_or_else(|| S3Error::NoSuchKey(key.to_string()))?;
+            ObjectRetention::check_update(file_retention(&file).as_ref(), &retention, now)?;
+
+            // The update re-checks the rule, in case another request
+            // extended the retention meanwhile
+            let updated = meta
+                .set_file_retention(tenant, file.id, retention.mode, retention.retain_until)
+                .await
+                .map_err(S3Error::from)?;
+            if !updated {
+                return Err(S3Error::ObjectLocked(format!(
+                    "Retention of {} was changed concurrently",
+                    key
+                )));
+            }
+            info!(
+                bucket = bucket,
+                key = key,
+                file_id = %file.id,
+                mode = retention.mode.as_str(),
+                retain_until = %retention.retain_until,
+                "Object retention set"
+            );
+            return Ok(());
+        }
+
+        Err(S3Error::service(
+            ErrorCode::ServiceUnavailable,
+            "No storage backend available",
+        ))
+    }
+
+    /// Set the canned ACL of an object
+    ///
+    /// Applies to the current version only; a new version starts out
+    /// private.
+    pub async fn set_object_acl(
+        &self,
+        tenant: &str,
+        bucket: &str,
+        key: &str,
+        acl: CannedAcl,
+    ) -> S3Result<()> {
+        if self.use_memory {
+            let mut buckets = self.memory_buckets.write().await;
+            let object = buckets
+                .get_mut(&memory_bucket_key(tenant, bucket))
+                .ok_or_else(|| S3Error::NoSuchBucket(bucket.to_string()))?
+                .objects
+                .get_mut(key)
+                .filter(|o| !o.is_expired())
+                .ok_or_else(|| S3Error::NoSuchKey(key.to_string()))?;
+            object.public_read = acl.is_public_read();
+            return Ok(());
+        }
+
+        if let Some(ref meta) = self.metadata {
+            let file = meta
+                .get_file_by_path(tenant, &format!("{}/{}", bucket, key))
+                .await
+                .map_err(S3Error::from)?
+                .ok_or_else(|| S3Error::NoSuchKey(key.to_string()))?;
+            let updated = meta
+                .set_file_public_read(tenant, file.id, acl.is_public_read())
+                .await
+                .map_err(S3Error::from)?;
+            if !updated {
+                return Err(S3Error::NoSuchKey(key.to_string()));
+            }
+            return Ok(());
+        }
+
+        Err(S3Error::service(
+            ErrorCode::ServiceUnavailable,
+            "No storage backend available",
+        ))
+    }
+
     /// List objects in bucket
+    ///
+    /// Keys are returned in bytewise order, at most `max_keys` per page. A page
+    /// continues after the key encoded in `continuation_token`, or else after
+    /// `start_after`; truncated pages return the token for the next one.
     pub async fn list_objects(
         &self,
+        tenant: &str,
+        bucket: &str,
+        prefix: &str,
+        delimiter: Option<&str>,
+        max_keys: i32,
+        continuation_token: Option<&str>,
+        start_after: Option<&str>,
+    ) -> S3Result<(Vec<ObjectInfo>, bool, Option<String>)> {
+        self.list_objects_matching(
+            tenant,
+            bucket,
+            prefix,
+            delimiter,
+            max_keys,
+            continuation_token,
+            start_after,
+            &UserMetadata::new(),
+        )
+        .await
+    }
+
+    /// List objects in bucket whose user metadata contains every entry of
+    /// `metadata_filter`
+    ///
+    /// Pages like [`AppState::list_objects`]; an empty filter lists all
+    /// objects.
+    pub async fn list_objects_matching(
+        &self,
+        tenant: &str,
         bucket: &str,
         prefix: &str,
         _delimiter: Option<&str>,
         max_keys: i32,
-        _continuation_token: Option<&str>,
+        continuation_token: Option<&str>,
+        start_after: Option<&str>,
+        metadata_filter: &UserMetadata,
     ) -> S3Result<(Vec<ObjectInfo>, bool, Option<String>)> {
-        if self.use_memory {
+        let cursor = match continuation_token {
+            Some(token) => Some(decode_list_token(token)?),
+            None => start_after.map(str::to_string),
+        };
+        let max_keys = max_keys.max(0) as usize;
+
+        let mut objects = if self.use_memory {
             let buckets = self.memory_buckets.read().await;
             let bucket_state = buckets
-                .get(bucket)
+                .get(&memory_bucket_key(tenant, bucket))
                 .ok_or_else(|| S3Error::NoSuchBucket(bucket.to_string()))?;
 
             let mut objects: Vec<_> = bucket_state
                 .objects
                 .iter()
-                .filter(|(k, _)| k.starts_with(prefix))
+                .filter(|(k, v)| {
+                    k.starts_with(prefix)
+                        && cursor.as_deref().map_or(true, |c| k.as_str() > c)
+                        && !v.is_expired()
+                        && metadata_filter
+                            .iter()
+                            .all(|(name, value)| v.user_metadata.get(name) == Some(value))
+                })
                 .map(|(k, v)| ObjectInfo {
                     key: k.clone(),
                     last_modified: v.created_at.to_rfc3339(),
                     etag: v.etag.clone(),
-                    size: v.data.len() as u64,
+                    size: v.size(),
                     storage_class: "STANDARD".to_string(),
+                    user_metadata: v.user_metadata.clone(),
                 })
                 .collect();
 
             objects.sort_by(|a, b| a.key.cmp(&b.key));
-
-            let is_truncated = objects.len() > max_keys as usize;
-            let objects: Vec<_> = objects.into_iter().take(max_keys as usize).collect();
-
-            return Ok((objects, is_truncated, None));
-        }
-
-        // Use metadata service for file listing
-        if let Some(ref meta) = self.metadata {
-            let db = meta.database();
-            let files = db
-                .list_files_in_bucket(bucket, Some(prefix), max_keys as i64, 0)
+            objects.truncate(max_keys + 1);
+            objects
+        } else if let Some(ref meta) = self.metadata {
+            // One extra row tells whether another page follows
+            let files = meta
+                .database()
+                .list_files_in_bucket(
+                    tenant,
+                    bucket,
+                    Some(prefix),
+                    cursor.as_deref(),
+                    max_keys as i64 + 1,
+                    false,
+                    user_metadata_to_json(metadata_filter).as_ref(),
+                )
                 .await
-                .map_err(|e| S3Error::Internal(e.to_string()))?;
+                .map_err(S3Error::from)?;
 
-            let objects: Vec<ObjectInfo> = files
+            let bucket_prefix = format!("{}/", bucket);
+            files
                 .into_iter()
                 .map(|f| ObjectInfo {
-                    key: f.path.clone(),
+                    key: f
+                        .path
+                        .strip_prefix(&bucket_prefix)
+                        .unwrap_or(&f.path)
+                        .to_string(),
                     last_modified: f.created_at.to_rfc3339(),
                     etag: hex::encode(&f.content_hash),
                     size: f.size_bytes as u64,
-                    storage_class: "STANDARD".to_string(),
+                    storage_class: f.storage_class.clone(),
+                    user_metadata: user_metadata_from_json(f.metadata.as_ref()),
                 })
-                .collect();
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        let is_truncated = objects.len() > max_keys;
+        objects.truncate(max_keys);
+        let next_token = if is_truncated {
+            objects.last().map(|o| encode_list_token(&o.key))
+        } else {
+            None
+        };
+
+        Ok((objects, is_truncated, next_token))
+    }
+
+    /// List a bucket's restorable deleted objects
+    ///
+    /// Objects deleted within the trash retention window are returned by
+    /// key, newest deletion first, at most `max_keys` of them. Returns the
+    /// objects and whether more matched. Only the metadata service keeps a
+    /// trash; the in-memory backend deletes objects outright.
+    pub async fn list_deleted_objects(
+        &self,
+        tenant: &str,
+        bucket: &str,
+        prefix: &str,
+        max_keys: i32,
+    ) -> S3Result<(Vec<DeletedObjectInfo>, bool)> {
+        let meta = match self.metadata {
+            Some(ref meta) if !self.use_memory => meta,
+            _ => return Ok((Vec::new(), false)),
+        };
+
+        let max_keys = max_keys.max(0) as usize;
+        let files = meta
+            .list_deleted_files(
+                tenant,
+                bucket,
+                Some(prefix),
+                crate::upload_janitor::trash_retention(),
+                max_keys as i64 + 1,
+            )
+            .await
+            .map_err(S3Error::from)?;
+
+        let is_truncated = files.len() > max_keys;
+        let bucket_prefix = format!("{}/", bucket);
+        let objects = files
+            .into_iter()
+            .take(max_keys)
+            .map(|f| DeletedObjectInfo {
+                key: f
+                    .path
+                    .strip_prefix(&bucket_prefix)
+                    .unwrap_or(&f.path)
+                    .to_string(),
+                version_id: f.id.to_string(),
+                deleted_at: f.deleted_at.unwrap_or(f.updated_at).to_rfc3339(),
+                etag: hex::encode(&f.content_hash),
+                size: f.size_bytes as u64,
+            })
+            .collect();
+
+        Ok((objects, is_truncated))
+    }
+
+    /// Restore a deleted object from the trash
+    ///
+    /// Restores `version_id`, or else the most recently deleted version of
+    /// the key, as long as it was deleted within the retention window.
+    pub async fn restore_object(
+        &self,
+        tenant: &str,
+        bucket: &str,
+        key: &str,
+        version_id: Option<Uuid>,
+    ) -> S3Result<ObjectMetadata> {
+        let meta = match self.metadata {
+            Some(ref meta) if !self.use_memory => meta,
+            _ => return Err(S3Error::NoSuchKey(key.to_string())),
+        };
+
+        let file = meta
+            .restore_file(
+                tenant,
+                &format!("{}/{}", bucket, key),
+                version_id,
+                crate::upload_janitor::trash_retention(),
+            )
+            .await
+            .map_err(S3Error::from)?
+            .ok_or_else(|| S3Error::NoSuchKey(key.to_string()))?;
+
+        self.publish_file_created(bucket, key, file.size_bytes as u64)
+            .await;
+
+        let retention = file_retention(&file);
+        Ok(ObjectMetadata {
+            key: key.to_string(),
+            size: file.size_bytes as u64,
+            content_type: file
+                .content_type
+                .unwrap_or_else(|| "application/octet-stream".to_string()),
+            etag: hex::encode(&file.content_hash),
+            last_modified: file.updated_at.to_rfc3339(),
+            expires_at: file.expires_at,
+            user_metadata: user_metadata_from_json(file.metadata.as_ref()),
+            retention,
+            encryption: file_encryption(&file),
+            public_read: file.public_read,
+            content_headers: file_content_headers(&file),
+            storage_class: file_storage_class(&file),
+            restore: None,
+        })
+    }
+
+    // =========================================================================
+    // STORAGE CLASS OPERATIONS
+    // =========================================================================
+
+    /// Metadata service holding storage classes
+    fn tiering_metadata(&self) -> S3Result<&MetadataService> {
+        match self.metadata {
+            Some(ref meta) if !self.use_memory => Ok(meta),
+            _ => Err(S3Error::NotImplemented(
+                "Storage classes need a metadata service".to_string(),
+            )),
+        }
+    }
+
+    /// Queue a transition of an object to another storage class
+    ///
+    /// The object is re-encoded by the tiering daemon; an object with a job
+    /// in progress cannot be queued again.
+    pub async fn transition_object(
+        &self,
+        tenant: &str,
+        bucket: &str,
+        key: &str,
+        storage_class: StorageClass,
+    ) -> S3Result<StorageClassJob> {
+        let meta = self.tiering_metadata()?;
+        let file = meta
+            .get_file_by_path(tenant, &format!("{}/{}", bucket, key))
+            .await
+            .map_err(S3Error::from)?
+            .ok_or_else(|| S3Error::NoSuchKey(key.to_string()))?;
+        if file_storage_class(&file) == storage_class {
+            return Err(S3Error::InvalidRequest(format!(
+                "{} is already in the {} storage class",
+                key,
+                storage_class.as_str()
+            )));
+        }
+
+        let job = meta
+            .database()
+            .queue_storage_class_job(
+                file.id,
+                StorageClassJobKind::Transition,
+                storage_class,
+                None,
+            )
+            .await
+            .map_err(S3Error::from)?
+            .ok_or_else(|| {
+                S3Error::service(
+                    ErrorCode::Conflict,
+                    format!("A storage class job of {} is in progress", key),
+                )
+            })?;
+        info!(
+            bucket = bucket,
+            key = key,
+            job_id = job.id,
+            storage_class = storage_class.as_str(),
+            "Storage class transition queued"
+        );
+        Ok(job)
+    }
+
+    /// Request a restored copy of an archived object, kept for `days`
+    ///
+    /// An object already restored keeps its copy at least `days` from now.
+    pub async fn restore_archived_object(
+        &self,
+        tenant: &str,
+        bucket: &str,
+        key: &str,
+        days: u32,
+    ) -> S3Result<RestoreRequestOutcome> {
+        let meta = self.tiering_metadata()?;
+        let file = meta
+            .get_file_by_path(tenant, &format!("{}/{}", bucket, key))
+            .await
+            .map_err(S3Error::from)?
+            .ok_or_else(|| S3Error::NoSuchKey(key.to_string()))?;
+        if !file.is_archived() {
+            return Err(S3Error::InvalidObjectState(format!(
+                "{} is not archived",
+                key
+            )));
+        }
+
+        let until = chrono::Utc::now() + chrono::Duration::days(days as i64);
+        if file.restored_copy().is_some()
+            && meta
+                .extend_restored_copy(&file, until)
+                .await
+                .map_err(S3Error::from)?
+        {
+            return Ok(RestoreRequestOutcome::AlreadyRestored);
+        }
+
+        let db = meta.database();
+        let queued = db
+            .queue_storage_class_job(
+                file.id,
+                StorageClassJobKind::Restore,
+                StorageClass::Standard,
+                Some(days as i32),
+            )
+            .await
+            .map_err(S3Error::from)?;
+        if queued.is_some() {
+            info!(bucket = bucket, key = key, days, "Archive restore queued");
+            return Ok(RestoreRequestOutcome::Queued);
+        }
+
+        let open = db
+            .get_open_storage_class_job(file.id)
+            .await
+            .map_err(S3Error::from)?;
+        match open {
+            Some(job) if job.kind != StorageClassJobKind::Restore.as_str() => {
+                Err(S3Error::service(
+                    ErrorCode::Conflict,
+                    format!("A storage class transition of {} is in progress", key),
+                ))
+            }
+            _ => Err(S3Error::RestoreAlreadyInProgress(key.to_string())),
+        }
+    }
+
+    /// Most recent storage class jobs of a bucket
+    pub async fn list_storage_class_jobs(
+        &self,
+        tenant: &str,
+        bucket: &str,
+        limit: i64,
+    ) -> S3Result<Vec<StorageClassJob>> {
+        let meta = self.tiering_metadata()?;
+        if meta
+            .get_bucket(tenant, bucket)
+            .await
+            .map_err(S3Error::from)?
+            .is_none()
+        {
+            return Err(S3Error::NoSuchBucket(bucket.to_string()));
+        }
+        meta.database()
+            .list_storage_class_jobs(tenant, bucket, limit)
+            .await
+            .map_err(S3Error::from)
+    }
+
+    /// Run a storage class job claimed by the tiering daemon
+    ///
+    /// A job whose object was deleted or overwritten, or is already in the
+    /// target class, has nothing left to do.
+    pub async fn run_storage_class_job(
+        &self,
+        job: &StorageClassJob,
+        archive_profile: ErasureConfig,
+    ) -> S3Result<()> {
+        let meta = self.tiering_metadata()?;
+        let file = match meta
+            .database()
+            .get_file(job.file_id)
+            .await
+            .map_err(S3Error::from)?
+        {
+            Some(file) if file.status == "complete" => file,
+            _ => return Ok(()),
+        };
+
+        if job.kind == StorageClassJobKind::Restore.as_str() {
+            let days = job.restore_days.unwrap_or(1).max(1);
+            return self.restore_file(meta, &file, days, archive_profile).await;
+        }
+        let storage_class = StorageClass::from_str(&job.storage_class).unwrap_or_default();
+        self.transition_file(meta, &file, storage_class, archive_profile)
+            .await
+    }
+
+    /// Re-encode a file into a storage class
+    async fn transition_file(
+        &self,
+        meta: &MetadataService,
+        file: &cyxcloud_metadata::File,
+        storage_class: StorageClass,
+        archive_profile: ErasureConfig,
+    ) -> S3Result<()> {
+        if file_storage_class(file) == storage_class {
+            return Ok(());
+        }
+
+        // An archived object going back to STANDARD takes over the chunks of
+        // its restored copy
+        let staged_id = match file.restored_copy() {
+            Some(copy_id) if storage_class == StorageClass::Standard => copy_id,
+            _ => {
+                self.stage_encoding(meta, file, storage_class, archive_profile)
+                    .await?
+            }
+        };
+        if !meta
+            .swap_file_encoding(file, staged_id, storage_class)
+            .await
+            .map_err(S3Error::from)?
+        {
+            return Err(S3Error::service(
+                ErrorCode::Conflict,
+                format!("{} changed while it was re-encoded", file.path),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Write a restored copy of an archived file, kept for `days`
+    async fn restore_file(
+        &self,
+        meta: &MetadataService,
+        file: &cyxcloud_metadata::File,
+        days: i32,
+        archive_profile: ErasureConfig,
+    ) -> S3Result<()> {
+        if !file.is_archived() {
+            return Ok(());
+        }
+
+        // A copy that expired but was not removed yet is attached again
+        let previous = match file.restored_copy_id {
+            Some(copy_id) => meta
+                .database()
+                .get_file(copy_id)
+                .await
+                .map_err(S3Error::from)?
+                .map(|copy| copy.id),
+            None => None,
+        };
+        let copy_id = match previous {
+            Some(copy_id) => copy_id,
+            None => {
+                self.stage_encoding(meta, file, StorageClass::Standard, archive_profile)
+                    .await?
+            }
+        };
+
+        let until = chrono::Utc::now() + chrono::Duration::days(days as i64);
+        if !meta
+            .attach_restored_copy(file, copy_id, until)
+            .await
+            .map_err(S3Error::from)?
+        {
+            return Err(S3Error::service(
+                ErrorCode::Conflict,
+                format!("{} changed while it was restored", file.path),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Write a file's data again, encoded for a storage class, as a staged
+    /// file of its own
+    ///
+    /// The staged file is a pending upload with the object's path, content
+    /// and encryption; it holds the new chunks until they replace the file's
+    /// own or become its restored copy. ARCHIVE data is erasure coded with
+    /// `archive_profile`, STANDARD data as a new upload would be, on the
+    /// nodes of the class. Every shard must be stored; a staged upload that
+    /// fails is left to the upload janitor.
+    async fn stage_encoding(
+        &self,
+        meta: &MetadataService,
+        file: &cyxcloud_metadata::File,
+        storage_class: StorageClass,
+        archive_profile: ErasureConfig,
+    ) -> S3Result<Uuid> {
+        let bucket = file
+            .bucket
+            .as_deref()
+            .ok_or_else(|| S3Error::Internal(format!("File {} has no bucket", file.id)))?;
+        let (replicas, nodes) = self
+            .upload_targets(meta, &file.tenant_id, bucket, storage_class)
+            .await?;
+        let placement_engine = PlacementEngine::new(self.placement_config());
+        let placement_nodes: Vec<PlacementNode> =
+            nodes.iter().map(PlacementNode::from_node).collect();
+
+        // The stored bytes of an encrypted object are sealed, and copied
+        // without opening them
+        let size = file.size_bytes as u64;
+        let encryption = file.encryption();
+        let stored_size = match encryption {
+            Some(_) => sealed_len(size),
+            None => size,
+        };
+        let data = self
+            .read_stored_range(meta, file, stored_size, (0, stored_size))
+            .await?;
+        if encryption.is_none() && file.content_hash != ContentHash::compute(&data).as_bytes() {
+            return Err(S3Error::service(
+                ErrorCode::IntegrityError,
+                format!("Data of {} does not match its hash", file.path),
+            ));
+        }
+
+        let (storage_mode, erasure_config) = match storage_class {
+            StorageClass::Archive => (StorageMode::Erasure, archive_profile),
+            StorageClass::Standard if !data.is_empty() && data.len() < SMALL_OBJECT_THRESHOLD => {
+                (StorageMode::Replicated, ErasureConfig::default())
+            }
+            StorageClass::Standard => (StorageMode::Erasure, ErasureConfig::default()),
+        };
+        let erasure_encoder = ErasureEncoder::with_config(erasure_config)
+            .map_err(|e| S3Error::Internal(format!("Failed to create erasure encoder: {}", e)))?;
+
+        let staged_id = Uuid::new_v4();
+        let (chunks, chunk_count, total_shards) = match storage_mode {
+            StorageMode::Replicated => (Vec::new(), 1, 1),
+            StorageMode::Erasure => {
+                let chunks = split_bytes_into_chunks(&data, DEFAULT_CHUNK_SIZE, Some(staged_id))
+                    .map_err(S3Error::from)?;
+                let chunk_count = chunks.len();
+                (
+                    chunks,
+                    chunk_count,
+                    chunk_count * erasure_config.total_shards(),
+                )
+            }
+        };
+        let (data_shards, parity_shards, chunk_size) = match storage_mode {
+            StorageMode::Replicated => (1, 0, data.len()),
+            StorageMode::Erasure => (
+                erasure_config.data_shards,
+                erasure_config.parity_shards,
+                DEFAULT_CHUNK_SIZE,
+            ),
+        };
+
+        let create_file = cyxcloud_metadata::CreateFile {
+            id: Some(staged_id),
+            name: file.name.clone(),
+            path: file.path.clone(),
+            content_hash: file.content_hash.clone(),
+            size_bytes: file.size_bytes,
+            chunk_count: chunk_count as i32,
+            data_shards: data_shards as i32,
+            parity_shards: parity_shards as i32,
+            chunk_size: chunk_size as i32,
+            erasure_backend: erasure_encoder.backend().to_string(),
+            storage_mode,
+            owner_id: file.owner_id,
+            bucket: file.bucket.clone(),
+            tenant_id: file.tenant_id.clone(),
+            content_type: file.content_type.clone(),
+            metadata: file.metadata.clone(),
+            expires_at: file.expires_at,
+            encryption: encryption.clone(),
+            cache_control: file.cache_control.clone(),
+            content_disposition: file.content_disposition.clone(),
+            content_encoding: file.content_encoding.clone(),
+        };
+        meta.register_file(create_file)
+            .await
+            .map_err(S3Error::from)?;
+        meta.create_upload_intent(
+            staged_id,
+            chunk_count as i32,
+            total_shards as i32,
+            crate::upload_janitor::upload_intent_ttl(),
+            None,
+        )
+        .await
+        .map_err(S3Error::from)?;
+
+        info!(
+            file_id = %file.id,
+            staged_id = %staged_id,
+            storage_class = storage_class.as_str(),
+            mode = storage_mode.as_str(),
+            chunks = chunk_count,
+            total_shards,
+            "Re-encoding object"
+        );
+
+        let upload = ShardUpload {
+            meta,
+            nodes: &nodes,
+            placement_nodes: &placement_nodes,
+            file_id: staged_id,
+            encrypted: encryption.is_some(),
+            failed_nodes: Default::default(),
+        };
+
+        let mut shards_stored = 0;
+        if storage_mode == StorageMode::Replicated {
+            let copies = replicas.max(SMALL_OBJECT_REPLICAS);
+            if self
+                .store_replicated_blob(&upload, &placement_engine, &data, copies)
+                .await
+            {
+                shards_stored += 1;
+            }
+        }
+        for chunk in &chunks {
+            let shards = erasure_encoder
+                .encode_bytes(&chunk.data)
+                .map_err(|e| S3Error::Internal(format!("Erasure encoding failed: {}", e)))?;
+            let (stored, _) = self
+                .store_chunk_shards(
+                    &upload,
+                    &placement_engine,
+                    replicas,
+                    ChunkMeta {
+                        encrypted: upload.encrypted,
+                        ..ChunkMeta::from(&chunk.metadata)
+                    },
+                    &shards,
+                )
+                .await;
+            shards_stored += stored;
+        }
 
-            let is_truncated = objects.len() >= max_keys as usize;
-            return Ok((objects, is_truncated, None));
+        // The old encoding is removed once this one replaces it, so it must
+        // be complete
+        if shards_stored < total_shards {
+            return Err(S3Error::service(
+                ErrorCode::InsufficientShards,
+                format!(
+                    "Failed to store the re-encoded object: {} of {} shards stored",
+                    shards_stored, total_shards
+                ),
+            ));
         }
+        Ok(staged_id)
+    }
 
-        Ok((Vec::new(), false, None))
+    /// Restore state of a file, None unless it is archived
+    async fn file_restore_status(
+        &self,
+        meta: &MetadataService,
+        file: &cyxcloud_metadata::File,
+    ) -> S3Result<Option<RestoreStatus>> {
+        if !file.is_archived() {
+            return Ok(None);
+        }
+        let open = meta
+            .database()
+            .get_open_storage_class_job(file.id)
+            .await
+            .map_err(S3Error::from)?;
+        if open.is_some_and(|job| job.kind == StorageClassJobKind::Restore.as_str()) {
+            return Ok(Some(RestoreStatus::Ongoing));
+        }
+        Ok(file
+            .restored_copy()
+            .and(file.restored_until)
+            .map(|expiry| RestoreStatus::Restored { expiry }))
     }
 
     // =========================================================================
@@ -1460,6 +4360,7 @@ impl AppState {
     /// Get dataset info
     pub async fn get_dataset(
         &self,
+        tenant: &str,
         dataset_id: &str,
     ) -> Result<
         Option<crate::grpc_api::data_service::DatasetInfo>,
@@ -1468,7 +4369,7 @@ impl AppState {
         if let Some(ref meta) = self.metadata {
             // Try to parse as UUID
             if let Ok(uuid) = Uuid::parse_str(dataset_id) {
-                if let Ok(Some(file)) = meta.get_file(uuid).await {
+                if let Ok(Some(file)) = meta.get_file(tenant, uuid).await {
                     return Ok(Some(crate::grpc_api::data_service::DatasetInfo {
                         id: file.id.to_string(),
                         name: file.name.clone(),
@@ -1566,3 +4467,77 @@ impl Default for AppState {
         Self::new()
     }
 }
+
+/// Encode the last key of a listing page as an opaque continuation token
+fn encode_list_token(key: &str) -> String {
+    base64::Engine::encode(
+        &base64::engine::general_purpose::URL_SAFE_NO_PAD,
+        key.as_bytes(),
+    )
+}
+
+/// Decode a continuation token back into the key to list after
+fn decode_list_token(token: &str) -> S3Result<String> {
+    base64::Engine::decode(&base64::engine::general_purpose::URL_SAFE_NO_PAD, token)
+        .ok()
+        .and_then(|bytes| String::from_utf8(bytes).ok())
+        .ok_or_else(|| S3Error::InvalidRequest("Invalid continuation token".to_string()))
+}
+
+/// Retention of a stored file, if it was ever locked
+fn file_retention(file: &cyxcloud_metadata::File) -> Option<ObjectRetention> {
+    file.retention()
+        .map(|(mode, retain_until)| ObjectRetention { mode, retain_until })
+}
+
+/// Server-side encryption of a stored file, None if it is in plaintext
+fn file_encryption(file: &cyxcloud_metadata::File) -> Option<SseAlgorithm> {
+    file.sse_algorithm.as_deref().and_then(|a| a.parse().ok())
+}
+
+/// Content headers of a stored file
+fn file_content_headers(file: &cyxcloud_metadata::File) -> ContentHeaders {
+    ContentHeaders {
+        cache_control: file.cache_control.clone(),
+        content_disposition: file.content_disposition.clone(),
+        content_encoding: file.content_encoding.clone(),
+    }
+}
+
+fn file_storage_class(file: &cyxcloud_metadata::File) -> StorageClass {
+    StorageClass::from_str(&file.storage_class).unwrap_or_default()
+}
+
+/// Whether a file's chunks are those of the erasure layout it records
+///
+/// They are not when a transition re-encoded the file between reading its
+/// record and its chunks.
+fn chunks_fit_layout(file: &cyxcloud_metadata::File, chunks: &[cyxcloud_metadata::Chunk]) -> bool {
+    let data = file.data_shards.max(1);
+    let total = data + file.parity_shards.max(0);
+    chunks.len() >= (file.chunk_count.max(1) * data) as usize
+        && chunks
+            .iter()
+            .all(|c| c.shard_index < total && c.is_parity == (c.shard_index >= data))
+}
+
+/// Refuse overwriting the current version of a key while it is locked
+fn check_not_locked(key: &str, current: Option<&ObjectMetadata>) -> S3Result<()> {
+    match current.and_then(|c| c.retention) {
+        Some(retention) => retention.check_overwrite(key, chrono::Utc::now()),
+        None => Ok(()),
+    }
+}
+
+/// Key of a tenant's bucket in the in-memory store
+fn memory_bucket_key(tenant: &str, bucket: &str) -> String {
+    format!("{}/{}", tenant, bucket)
+}
+
+/// Convert an inclusive byte range to a half-open one within `len`
+fn clamp_range((start, end): (u64, u64), len: u64) -> S3Result<(u64, u64)> {
+    if start >= len || end < start {
+        return Err(S3Error::InvalidRange);
+    }
+    Ok((start, end.saturating_add(1).min(len)))
+}
@@ -0,0 +1,252 @@
+//! Storage statistics REST API
+//!
+//! Object counts and bytes stored, for all of the caller's buckets or for
+//! one bucket (`cyxcloud status`). Figures come from counters kept on the
+//! bucket rows, so reading them costs the same however large the bucket is.
+//!
+//! A bucket's most downloaded objects, ranked by heat (see [`crate::heat`]),
+//! are listed under `/{bucket}/hot`.
+
+use crate::admin_api::require_metadata;
+use crate::auth_api::{extract_and_validate_token, ApiError};
+use crate::AppState;
+use axum::{
+    extract::{Json, Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    routing::get,
+    Router,
+};
+use chrono::{DateTime, Utc};
+use cyxcloud_metadata::{Bucket, DbError, ObjectHeat};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::error;
+
+/// Statistics of one bucket
+#[derive(Debug, Serialize)]
+pub struct BucketStats {
+    pub bucket: String,
+    pub object_count: i64,
+    pub total_bytes: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<&Bucket> for BucketStats {
+    fn from(bucket: &Bucket) -> Self {
+        Self {
+            bucket: bucket.name.clone(),
+            object_count: bucket.object_count,
+            total_bytes: bucket.total_bytes,
+            created_at: bucket.created_at,
+        }
+    }
+}
+
+/// Objects listed by default, and at most, by the hot objects endpoint
+const DEFAULT_HOT_LIMIT: i64 = 20;
+const MAX_HOT_LIMIT: i64 = 1000;
+
+/// Query of the hot objects endpoint
+#[derive(Debug, Deserialize)]
+pub struct HotQuery {
+    /// Objects to list (default 20, at most 1000)
+    pub limit: Option<i64>,
+}
+
+/// Download figures of one object
+#[derive(Debug, Serialize)]
+pub struct HotObject {
+    pub key: String,
+    /// Downloads, estimated from sampled ones
+    pub access_count: i64,
+    /// Downloads decayed by half every half-life, as of now
+    pub heat: f64,
+    pub last_accessed_at: DateTime<Utc>,
+}
+
+impl From<ObjectHeat> for HotObject {
+    fn from(heat: ObjectHeat) -> Self {
+        Self {
+            key: heat.object_key,
+            access_count: heat.access_count,
+            heat: heat.heat,
+            last_accessed_at: heat.last_accessed_at,
+        }
+    }
+}
+
+/// Hottest objects of one bucket
+#[derive(Debug, Serialize)]
+pub struct HotObjects {
+    pub bucket: String,
+    pub half_life_secs: u64,
+    pub objects: Vec<HotObject>,
+}
+
+/// Statistics of all of a tenant's buckets
+#[derive(Debug, Default, Serialize)]
+pub struct StorageStats {
+    pub bucket_count: usize,
+    pub object_count: i64,
+    pub total_bytes: i64,
+    pub buckets: Vec<BucketStats>,
+}
+
+impl StorageStats {
+    fn from_buckets(buckets: &[Bucket]) -> Self {
+        Self {
+            bucket_count: buckets.len(),
+            object_count: buckets.iter().map(|b| b.object_count).sum(),
+            total_bytes: buckets.iter().map(|b| b.total_bytes).sum(),
+            buckets: buckets.iter().map(BucketStats::from).collect(),
+        }
+    }
+}
+
+/// Create stats routes
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/", get(get_storage_stats))
+        .route("/:bucket", get(get_bucket_stats))
+        .route("/:bucket/hot", get(get_hot_objects))
+}
+
+/// 404 for a bucket the caller doesn't have
+fn no_such_bucket(bucket: &str) -> (StatusCode, Json<ApiError>) {
+    (
+        StatusCode::NOT_FOUND,
+        Json(ApiError::new(
+            format!("Bucket not found: {}", bucket),
+            "NO_SUCH_BUCKET",
+        )),
+    )
+}
+
+/// Map a database error to a 500
+fn stats_db_error(e: DbError) -> (StatusCode, Json<ApiError>) {
+    error!(error = %e, "Storage stats query failed");
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(ApiError::new(
+            "Failed to load storage stats",
+            "INTERNAL_ERROR",
+        )),
+    )
+}
+
+/// Totals across the caller's buckets, and per-bucket figures
+async fn get_storage_stats(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<StorageStats>, (StatusCode, Json<ApiError>)> {
+    let claims = extract_and_validate_token(&headers, state.auth_service()).await?;
+    let buckets = require_metadata(&state)?
+        .database()
+        .list_tenant_buckets(claims.tenant())
+        .await
+        .map_err(stats_db_error)?;
+
+    Ok(Json(StorageStats::from_buckets(&buckets)))
+}
+
+/// Figures for one of the caller's buckets
+async fn get_bucket_stats(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(bucket): Path<String>,
+) -> Result<Json<BucketStats>, (StatusCode, Json<ApiError>)> {
+    let claims = extract_and_validate_token(&headers, state.auth_service()).await?;
+    let found = require_metadata(&state)?
+        .database()
+        .get_bucket(claims.tenant(), &bucket)
+        .await
+        .map_err(stats_db_error)?;
+
+    match found {
+        Some(b) => Ok(Json(BucketStats::from(&b))),
+        None => Err(no_such_bucket(&bucket)),
+    }
+}
+
+/// The most downloaded objects of one of the caller's buckets, hottest first
+///
+/// Counts flushed by every gateway are included; this gateway's unflushed
+/// counts are not.
+async fn get_hot_objects(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(bucket): Path<String>,
+    Query(query): Query<HotQuery>,
+) -> Result<Json<HotObjects>, (StatusCode, Json<ApiError>)> {
+    let claims = extract_and_validate_token(&headers, state.auth_service()).await?;
+    let db = require_metadata(&state)?.database();
+    if db
+        .get_bucket(claims.tenant(), &bucket)
+        .await
+        .map_err(stats_db_error)?
+        .is_none()
+    {
+        return Err(no_such_bucket(&bucket));
+    }
+
+    let half_life = state.heat_tracker().config().half_life;
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_HOT_LIMIT)
+        .clamp(1, MAX_HOT_LIMIT);
+    let objects = db
+        .get_hottest_objects(claims.tenant(), &bucket, limit, half_life.as_secs_f64())
+        .await
+        .map_err(stats_db_error)?;
+
+    Ok(Json(HotObjects {
+        bucket,
+        half_life_secs: half_life.as_secs(),
+        objects: objects.into_iter().map(HotObject::from).collect(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn bucket(name: &str, object_count: i64, total_bytes: i64) -> Bucket {
+        Bucket {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            owner_id: Uuid::new_v4(),
+            tenant_id: "default".to_string(),
+            versioning_enabled: false,
+            public_read: false,
+            max_size_bytes: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            shard_replicas: 1,
+            logging_target_bucket: None,
+            logging_target_prefix: String::new(),
+            object_count,
+            total_bytes,
+            object_lock_enabled: false,
+            object_lock_mode: None,
+            object_lock_days: None,
+            policy: None,
+            default_cache_control: None,
+            lifecycle_rules: None,
+            cors_rules: None,
+        }
+    }
+
+    #[test]
+    fn test_storage_stats_totals() {
+        let stats = StorageStats::from_buckets(&[bucket("a", 3, 1024), bucket("b", 0, 0)]);
+        assert_eq!(stats.bucket_count, 2);
+        assert_eq!(stats.object_count, 3);
+        assert_eq!(stats.total_bytes, 1024);
+        assert_eq!(stats.buckets[0].bucket, "a");
+
+        let empty = StorageStats::from_buckets(&[]);
+        assert_eq!(empty.bucket_count, 0);
+        assert_eq!(empty.total_bytes, 0);
+    }
+}
@@ -0,0 +1,106 @@
+//! Storage class REST API
+//!
+//! Queues transitions of the caller's objects between the STANDARD and
+//! ARCHIVE storage classes and lists a bucket's recent transition and
+//! restore jobs (see [`crate::tiering`]). Jobs are run by the tiering
+//! daemon; their status is polled from the jobs listing.
+
+use crate::auth_api::{extract_and_validate_token, ApiError};
+use crate::s3_api::S3Error;
+use crate::AppState;
+use axum::{
+    extract::{Json, Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    routing::{get, post},
+    Router,
+};
+use cyxcloud_core::error::HasErrorCode;
+use cyxcloud_metadata::{StorageClass, StorageClassJob};
+use serde::Deserialize;
+use std::sync::Arc;
+use tracing::info;
+
+/// Jobs listed by default, and at most
+const DEFAULT_JOBS_LIMIT: i64 = 100;
+const MAX_JOBS_LIMIT: i64 = 1000;
+
+/// Transition request
+#[derive(Debug, Deserialize)]
+pub struct TransitionRequest {
+    /// Object key within the bucket
+    pub key: String,
+    /// Class to move the object to
+    pub storage_class: StorageClass,
+}
+
+/// Query of the jobs listing
+#[derive(Debug, Deserialize)]
+pub struct JobsQuery {
+    /// Jobs to list, most recent first (default 100, at most 1000)
+    pub limit: Option<i64>,
+}
+
+/// Create storage class routes
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/:bucket/transitions", post(transition_object))
+        .route("/:bucket/jobs", get(list_jobs))
+}
+
+/// Map a gateway error to its status and code
+fn storage_class_error(e: S3Error) -> (StatusCode, Json<ApiError>) {
+    let code = e.error_code();
+    (
+        StatusCode::from_u16(code.http_status()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
+        Json(ApiError::new(e.to_string(), code.as_str())),
+    )
+}
+
+/// Queue a transition of one of the caller's objects
+///
+/// Answers 202 with the queued job.
+async fn transition_object(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(bucket): Path<String>,
+    Json(request): Json<TransitionRequest>,
+) -> Result<(StatusCode, Json<StorageClassJob>), (StatusCode, Json<ApiError>)> {
+    let claims = extract_and_validate_token(&headers, state.auth_service()).await?;
+    let job = state
+        .transition_object(
+            claims.tenant(),
+            &bucket,
+            &request.key,
+            request.storage_class,
+        )
+        .await
+        .map_err(storage_class_error)?;
+
+    info!(
+        user = %claims.sub,
+        bucket = %bucket,
+        key = %request.key,
+        storage_class = request.storage_class.as_str(),
+        "Storage class transition requested"
+    );
+    Ok((StatusCode::ACCEPTED, Json(job)))
+}
+
+/// Recent storage class jobs of one of the caller's buckets
+async fn list_jobs(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(bucket): Path<String>,
+    Query(query): Query<JobsQuery>,
+) -> Result<Json<Vec<StorageClassJob>>, (StatusCode, Json<ApiError>)> {
+    let claims = extract_and_validate_token(&headers, state.auth_service()).await?;
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_JOBS_LIMIT)
+        .clamp(1, MAX_JOBS_LIMIT);
+    let jobs = state
+        .list_storage_class_jobs(claims.tenant(), &bucket, limit)
+        .await
+        .map_err(storage_class_error)?;
+    Ok(Json(jobs))
+}
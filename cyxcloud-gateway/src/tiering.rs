@@ -0,0 +1,491 @@
+//! Storage classes and tiering
+//!
+//! Objects are stored in the STANDARD class, with the default 10+4 erasure
+//! profile (whole copies for small objects), or in the ARCHIVE class, erasure
+//! coded with a wider profile (16+8 by default) that survives twice as many
+//! lost shards. Archive shards go to nodes flagged as archival, typically
+//! cheaper HDD nodes (`PUT /api/v1/admin/nodes/{id}/archival`), and standard
+//! uploads stay off those nodes, as long as each tier has nodes.
+//!
+//! Objects change class through transition jobs, queued by
+//! `POST /api/v1/storage-class/{bucket}/transitions`, by `x-amz-storage-class:
+//! ARCHIVE` on upload, or by a bucket's lifecycle rules (`PUT /{bucket}
+//! ?lifecycle`), which the tiering daemon applies every `lifecycle_interval`.
+//! A transition re-encodes the object in place and garbage collects the old
+//! shards.
+//!
+//! Archived objects are not read directly: GET answers 403
+//! InvalidObjectState until `POST /{bucket}/{key}?restore` has queued a
+//! restore job and the daemon has written a temporary STANDARD copy, which is
+//! served for the requested number of days. Restores are run before
+//! transitions. HEAD reports the storage class and the `x-amz-restore` state,
+//! listings the storage class.
+//!
+//! Jobs run on the tiering daemon of every gateway, a few per cycle; the job
+//! of a gateway that died is claimed again after [`STALE_JOB_AFTER`].
+//! Storage classes need a metadata service.
+
+use crate::state::AppState;
+use chrono::{DateTime, Utc};
+use cyxcloud_core::ErasureConfig;
+use cyxcloud_metadata::{Node, StorageClass, StorageClassJob};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+use tokio::time::interval;
+use tracing::{debug, info, warn};
+
+/// Running jobs not finished after this long are claimed again
+pub const STALE_JOB_AFTER: Duration = Duration::from_secs(60 * 60);
+
+/// Longest a restored copy can be kept
+pub const MAX_RESTORE_DAYS: u32 = 365;
+
+/// Lifecycle rules a bucket can have
+pub const MAX_LIFECYCLE_RULES: usize = 1000;
+
+/// Restored copies removed per cycle
+const EXPIRED_COPIES_PER_CYCLE: i64 = 100;
+
+/// Transitions one lifecycle rule queues per run
+const LIFECYCLE_BATCH: i64 = 10_000;
+
+/// Largest data or parity shard count of the archive profile
+const MAX_ARCHIVE_SHARDS: usize = 64;
+
+/// Tiering configuration
+#[derive(Debug, Clone, PartialEq)]
+pub struct TieringConfig {
+    /// Run storage class jobs and lifecycle rules on this gateway
+    pub enabled: bool,
+    /// How often jobs are claimed
+    pub interval: Duration,
+    /// Jobs claimed per cycle (run one after another)
+    pub jobs_per_cycle: i64,
+    /// Attempts before a job is given up
+    pub max_attempts: i32,
+    /// How often lifecycle rules are applied
+    pub lifecycle_interval: Duration,
+    /// Data shards per chunk of archive objects
+    pub archive_data_shards: usize,
+    /// Parity shards per chunk of archive objects
+    pub archive_parity_shards: usize,
+}
+
+impl Default for TieringConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval: Duration::from_secs(30),
+            jobs_per_cycle: 4,
+            max_attempts: 5,
+            lifecycle_interval: Duration::from_secs(60 * 60),
+            archive_data_shards: 16,
+            archive_parity_shards: 8,
+        }
+    }
+}
+
+impl TieringConfig {
+    /// Create configuration from environment variables
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let secs = |name: &str, default: Duration| {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|&v: &u64| v > 0)
+                .map(Duration::from_secs)
+                .unwrap_or(default)
+        };
+        let shards = |name: &str, default: usize| {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|v: &usize| (1..=MAX_ARCHIVE_SHARDS).contains(v))
+                .unwrap_or(default)
+        };
+
+        Self {
+            enabled: std::env::var("TIERING_ENABLED")
+                .map(|v| v == "true" || v == "1")
+                .unwrap_or(defaults.enabled),
+            interval: secs("TIERING_INTERVAL_SECS", defaults.interval),
+            jobs_per_cycle: std::env::var("TIERING_JOBS_PER_CYCLE")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|&v: &i64| v > 0)
+                .unwrap_or(defaults.jobs_per_cycle),
+            max_attempts: std::env::var("TIERING_MAX_ATTEMPTS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|&v: &i32| v > 0)
+                .unwrap_or(defaults.max_attempts),
+            lifecycle_interval: secs("LIFECYCLE_INTERVAL_SECS", defaults.lifecycle_interval),
+            archive_data_shards: shards("ARCHIVE_DATA_SHARDS", defaults.archive_data_shards),
+            archive_parity_shards: shards("ARCHIVE_PARITY_SHARDS", defaults.archive_parity_shards),
+        }
+    }
+
+    /// Erasure profile of archive objects
+    pub fn archive_profile(&self) -> ErasureConfig {
+        ErasureConfig {
+            data_shards: self.archive_data_shards,
+            parity_shards: self.archive_parity_shards,
+        }
+    }
+}
+
+/// Lifecycle rule moving a bucket's objects to another storage class
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LifecycleRule {
+    /// Rule name (`ID`)
+    pub id: String,
+    /// Whether the rule is applied (`Status` Enabled)
+    pub enabled: bool,
+    /// Key prefix of the objects the rule applies to (empty = all)
+    pub prefix: String,
+    /// Days after an object's creation it moves
+    pub days: u32,
+    /// Class the objects move to
+    pub storage_class: StorageClass,
+}
+
+/// Lifecycle rules of a bucket row, skipping ones that cannot be read
+pub fn bucket_lifecycle_rules(rules: Option<&serde_json::Value>) -> Vec<LifecycleRule> {
+    rules
+        .and_then(|rules| match serde_json::from_value(rules.clone()) {
+            Ok(rules) => Some(rules),
+            Err(e) => {
+                warn!(error = %e, "Unreadable bucket lifecycle rules");
+                None
+            }
+        })
+        .unwrap_or_default()
+}
+
+/// Nodes to store objects of a storage class on
+///
+/// Archive objects go to archival nodes and standard objects to the
+/// others; a class without nodes of its own uses all of them.
+pub fn tier_nodes(nodes: Vec<Node>, class: StorageClass) -> Vec<Node> {
+    let archival = class == StorageClass::Archive;
+    if nodes.iter().any(|n| n.archival == archival) {
+        nodes
+            .into_iter()
+            .filter(|n| n.archival == archival)
+            .collect()
+    } else {
+        nodes
+    }
+}
+
+/// Restore state of an archived object
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RestoreStatus {
+    /// A restore job is queued or running
+    Ongoing,
+    /// A restored copy is served until `expiry`
+    Restored { expiry: DateTime<Utc> },
+}
+
+impl RestoreStatus {
+    /// Value of the `x-amz-restore` header
+    pub fn header_value(&self) -> String {
+        match self {
+            Self::Ongoing => r#"ongoing-request="true""#.to_string(),
+            Self::Restored { expiry } => format!(
+                r#"ongoing-request="false", expiry-date="{}""#,
+                expiry.format("%a, %d %b %Y %H:%M:%S GMT")
+            ),
+        }
+    }
+}
+
+/// Outcome of a restore request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RestoreRequestOutcome {
+    /// A restore job was queued (202)
+    Queued,
+    /// A restored copy already exists; its expiry was extended (200)
+    AlreadyRestored,
+}
+
+/// Tiering daemon running storage class jobs and lifecycle rules
+pub struct TieringDaemon {
+    config: TieringConfig,
+}
+
+impl TieringDaemon {
+    /// Create a new tiering daemon
+    pub fn new(config: TieringConfig) -> Self {
+        Self { config }
+    }
+
+    /// Start the tiering loop (background task)
+    ///
+    /// Returns None without a metadata service.
+    pub fn start(self: Arc<Self>, state: Arc<AppState>) -> Option<JoinHandle<()>> {
+        state.metadata_service()?;
+        let daemon = self;
+
+        Some(tokio::spawn(async move {
+            let mut timer = interval(daemon.config.interval);
+            timer.tick().await;
+
+            info!(
+                interval_secs = daemon.config.interval.as_secs(),
+                jobs_per_cycle = daemon.config.jobs_per_cycle,
+                archive_data_shards = daemon.config.archive_data_shards,
+                archive_parity_shards = daemon.config.archive_parity_shards,
+                "Tiering daemon started"
+            );
+
+            let mut lifecycle_applied: Option<Instant> = None;
+            loop {
+                timer.tick().await;
+                daemon.expire_restored_copies(&state).await;
+                if lifecycle_applied
+                    .map_or(true, |t| t.elapsed() >= daemon.config.lifecycle_interval)
+                {
+                    daemon.apply_lifecycle_rules(&state).await;
+                    lifecycle_applied = Some(Instant::now());
+                }
+                daemon.run_jobs(&state).await;
+            }
+        }))
+    }
+
+    /// Remove restored copies that expired or lost their object
+    async fn expire_restored_copies(&self, state: &AppState) {
+        let Some(meta) = state.metadata_service() else {
+            return;
+        };
+        match meta
+            .database()
+            .expire_restored_copies(EXPIRED_COPIES_PER_CYCLE)
+            .await
+        {
+            Ok(0) => {}
+            Ok(copies) => info!(copies, "Restored copies expired"),
+            Err(e) => warn!(error = %e, "Failed to expire restored copies"),
+        }
+    }
+
+    /// Queue transitions for every enabled lifecycle rule
+    async fn apply_lifecycle_rules(&self, state: &AppState) {
+        let Some(meta) = state.metadata_service() else {
+            return;
+        };
+        let db = meta.database();
+        let buckets = match db.list_buckets_with_lifecycle().await {
+            Ok(buckets) => buckets,
+            Err(e) => {
+                warn!(error = %e, "Failed to load bucket lifecycle rules");
+                return;
+            }
+        };
+
+        for bucket in buckets {
+            for rule in bucket_lifecycle_rules(bucket.lifecycle_rules.as_ref()) {
+                if !rule.enabled {
+                    continue;
+                }
+                match db
+                    .queue_lifecycle_transitions(
+                        &bucket.tenant_id,
+                        &bucket.name,
+                        &rule.prefix,
+                        rule.days.min(i32::MAX as u32) as i32,
+                        rule.storage_class,
+                        LIFECYCLE_BATCH,
+                    )
+                    .await
+                {
+                    Ok(0) => {}
+                    Ok(queued) => info!(
+                        tenant = %bucket.tenant_id,
+                        bucket = %bucket.name,
+                        rule = %rule.id,
+                        queued,
+                        "Lifecycle transitions queued"
+                    ),
+                    Err(e) => warn!(
+                        error = %e,
+                        bucket = %bucket.name,
+                        rule = %rule.id,
+                        "Failed to apply lifecycle rule"
+                    ),
+                }
+            }
+        }
+    }
+
+    /// Claim and run jobs, one after another
+    async fn run_jobs(&self, state: &AppState) {
+        let Some(meta) = state.metadata_service() else {
+            return;
+        };
+        let jobs = match meta
+            .database()
+            .claim_storage_class_jobs(self.config.jobs_per_cycle, STALE_JOB_AFTER)
+            .await
+        {
+            Ok(jobs) => jobs,
+            Err(e) => {
+                warn!(error = %e, "Failed to claim storage class jobs");
+                return;
+            }
+        };
+
+        for job in jobs {
+            self.run_job(state, &job).await;
+        }
+    }
+
+    async fn run_job(&self, state: &AppState, job: &StorageClassJob) {
+        let Some(meta) = state.metadata_service() else {
+            return;
+        };
+        debug!(
+            job_id = job.id,
+            kind = %job.kind,
+            bucket = %job.bucket,
+            key = %job.object_key,
+            "Running storage class job"
+        );
+
+        let result = state
+            .run_storage_class_job(job, self.config.archive_profile())
+            .await;
+        let error = result.as_ref().err().map(|e| e.to_string());
+        if let Some(ref error) = error {
+            warn!(
+                job_id = job.id,
+                kind = %job.kind,
+                bucket = %job.bucket,
+                key = %job.object_key,
+                attempt = job.attempts,
+                error = %error,
+                "Storage class job failed"
+            );
+        }
+
+        match meta
+            .database()
+            .finish_storage_class_job(job.id, error.as_deref(), self.config.max_attempts)
+            .await
+        {
+            Ok(status) => {
+                let outcome = match status.as_str() {
+                    "pending" => "retry",
+                    status => status,
+                };
+                crate::metrics::record_storage_class_job(&job.kind, outcome);
+            }
+            Err(e) => warn!(error = %e, job_id = job.id, "Failed to record storage class job"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(archival: bool) -> Node {
+        let now = Utc::now();
+        Node {
+            id: uuid::Uuid::new_v4(),
+            peer_id: "peer".to_string(),
+            grpc_address: "127.0.0.1:50051".to_string(),
+            storage_total: 0,
+            storage_reserved: 0,
+            storage_used: 0,
+            bandwidth_mbps: 100,
+            measured_bandwidth_mbps: None,
+            bandwidth_measured_at: None,
+            max_connections: 100,
+            disk_available: None,
+            datacenter: None,
+            rack: None,
+            region: None,
+            latitude: None,
+            longitude: None,
+            status: "online".to_string(),
+            last_heartbeat: Some(now),
+            failure_count: 0,
+            first_offline_at: None,
+            status_changed_at: None,
+            warmup_started_at: None,
+            reputation: cyxcloud_metadata::NEUTRAL_REPUTATION,
+            archival,
+            version: None,
+            created_at: now,
+            updated_at: now,
+            wallet_address: None,
+            public_key: None,
+        }
+    }
+
+    #[test]
+    fn test_tier_nodes() {
+        let nodes = vec![node(false), node(true), node(false)];
+        assert_eq!(tier_nodes(nodes.clone(), StorageClass::Standard).len(), 2);
+        let archive = tier_nodes(nodes, StorageClass::Archive);
+        assert_eq!(archive.len(), 1);
+        assert!(archive[0].archival);
+
+        // A tier without nodes of its own falls back to all of them
+        let standard_only = vec![node(false), node(false)];
+        assert_eq!(tier_nodes(standard_only, StorageClass::Archive).len(), 2);
+        let archival_only = vec![node(true)];
+        assert_eq!(tier_nodes(archival_only, StorageClass::Standard).len(), 1);
+    }
+
+    #[test]
+    fn test_restore_header() {
+        let expiry = DateTime::parse_from_rfc3339("2026-10-20T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        assert_eq!(
+            RestoreStatus::Ongoing.header_value(),
+            r#"ongoing-request="true""#
+        );
+        assert_eq!(
+            RestoreStatus::Restored { expiry }.header_value(),
+            r#"ongoing-request="false", expiry-date="Tue, 20 Oct 2026 00:00:00 GMT""#
+        );
+    }
+
+    #[test]
+    fn test_bucket_lifecycle_rules() {
+        let rules = serde_json::json!([{
+            "id": "archive-logs",
+            "enabled": true,
+            "prefix": "logs/",
+            "days": 30,
+            "storage_class": "ARCHIVE",
+        }]);
+        assert_eq!(
+            bucket_lifecycle_rules(Some(&rules)),
+            vec![LifecycleRule {
+                id: "archive-logs".to_string(),
+                enabled: true,
+                prefix: "logs/".to_string(),
+                days: 30,
+                storage_class: StorageClass::Archive,
+            }]
+        );
+        assert!(bucket_lifecycle_rules(None).is_empty());
+        assert!(bucket_lifecycle_rules(Some(&serde_json::json!({"bad": 1}))).is_empty());
+    }
+
+    #[test]
+    fn test_config_default() {
+        let config = TieringConfig::default();
+        assert!(config.enabled);
+        assert_eq!(config.archive_profile().total_shards(), 24);
+        assert_eq!(config.lifecycle_interval, Duration::from_secs(3600));
+    }
+}
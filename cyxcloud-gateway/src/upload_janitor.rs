@@ -0,0 +1,477 @@
+//! Upload Janitor
+//!
+//! Background task that cleans up partial uploads. Every upload writes an
+//! intent record before dispatching shards; publishing the finished file
+//! removes it. Every shard the upload records pushes the expiry out again, so
+//! an intent that outlives it belongs to an upload that crashed, failed or
+//! lost to a newer version, and the janitor deletes its shards from the nodes
+//! and purges the file row. Intents whose object path is still write-locked
+//! are left alone: their upload is alive, however slow.
+//!
+//! The janitor also garbage collects expiring objects: files whose
+//! `expires_at` has passed (already hidden from GET/LIST) get the same
+//! treatment. Shards of deleted objects, queued in bulk when the files are
+//! soft-deleted, are removed from their nodes in batches as well, once the
+//! trash retention window has passed and the objects can no longer be
+//! restored.
+//!
+//! Idempotency keys of published uploads are dropped once they expire, after
+//! which a retry with the same key stores the object again.
+//!
+//! Less often (hourly by default), the janitor recounts every bucket's
+//! object and byte counters from its files, correcting any drift.
+
+use crate::node_client::NodeClient;
+use crate::state::AppState;
+use cyxcloud_metadata::{Database, MetadataService};
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+use tokio::time::interval;
+use tracing::{debug, error, info, warn};
+use uuid::Uuid;
+
+/// Default lifetime of an upload intent before it is considered abandoned
+const DEFAULT_INTENT_TTL_SECS: u64 = 60 * 60; // 1 hour
+
+/// Default time deleted objects stay restorable
+const DEFAULT_TRASH_RETENTION_SECS: u64 = 7 * 24 * 60 * 60; // 7 days
+
+/// Failed deletions after which a queued shard is reported as stuck
+const SHARD_GC_MAX_ATTEMPTS: i32 = 5;
+
+/// How often a stuck shard is retried
+const SHARD_GC_STUCK_RETRY: Duration = Duration::from_secs(6 * 60 * 60);
+
+/// Lifetime of upload intents (`UPLOAD_INTENT_TTL_SECS`)
+pub fn upload_intent_ttl() -> Duration {
+    static TTL: OnceLock<Duration> = OnceLock::new();
+    *TTL.get_or_init(|| {
+        Duration::from_secs(
+            std::env::var("UPLOAD_INTENT_TTL_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(DEFAULT_INTENT_TTL_SECS),
+        )
+    })
+}
+
+/// How long deleted objects can be restored (`TRASH_RETENTION_SECS`)
+///
+/// Shards of deleted objects are only garbage collected after this.
+pub fn trash_retention() -> Duration {
+    static RETENTION: OnceLock<Duration> = OnceLock::new();
+    *RETENTION.get_or_init(|| {
+        Duration::from_secs(
+            std::env::var("TRASH_RETENTION_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(DEFAULT_TRASH_RETENTION_SECS),
+        )
+    })
+}
+
+/// Upload janitor configuration
+#[derive(Debug, Clone)]
+pub struct UploadJanitorConfig {
+    /// How often to look for expired intents and files
+    pub scan_interval: Duration,
+    /// Maximum intents (and expired files) to clean up per cycle
+    pub batch_size: i64,
+    /// How often to reconcile bucket object counters
+    pub stats_reconcile_interval: Duration,
+}
+
+impl Default for UploadJanitorConfig {
+    fn default() -> Self {
+        Self {
+            scan_interval: Duration::from_secs(5 * 60),
+            batch_size: 100,
+            stats_reconcile_interval: Duration::from_secs(60 * 60),
+        }
+    }
+}
+
+impl UploadJanitorConfig {
+    /// Create configuration from environment variables
+    pub fn from_env() -> Self {
+        Self {
+            scan_interval: Duration::from_secs(
+                std::env::var("UPLOAD_JANITOR_INTERVAL_SECS")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(5 * 60),
+            ),
+            batch_size: std::env::var("UPLOAD_JANITOR_BATCH_SIZE")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(100),
+            stats_reconcile_interval: Duration::from_secs(
+                std::env::var("BUCKET_STATS_RECONCILE_INTERVAL_SECS")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(60 * 60),
+            ),
+        }
+    }
+}
+
+/// Janitor for abandoned uploads
+pub struct UploadJanitor {
+    config: UploadJanitorConfig,
+}
+
+impl UploadJanitor {
+    /// Create a new upload janitor
+    pub fn new(config: UploadJanitorConfig) -> Self {
+        Self { config }
+    }
+
+    /// Start the janitor as a background task
+    pub fn start(self: Arc<Self>, state: Arc<AppState>) -> JoinHandle<()> {
+        let janitor = self;
+
+        tokio::spawn(async move {
+            let mut timer = interval(janitor.config.scan_interval);
+            let mut last_reconcile: Option<Instant> = None;
+
+            info!(
+                interval_secs = janitor.config.scan_interval.as_secs(),
+                intent_ttl_secs = upload_intent_ttl().as_secs(),
+                "Upload janitor started"
+            );
+
+            loop {
+                timer.tick().await;
+
+                if let Some(metadata) = state.metadata_service() {
+                    if let Err(e) = janitor
+                        .run_cleanup_cycle(metadata, state.node_client())
+                        .await
+                    {
+                        error!(error = %e, "Upload janitor cycle failed");
+                    }
+                    if let Err(e) = janitor
+                        .run_expiry_cycle(metadata, state.node_client())
+                        .await
+                    {
+                        error!(error = %e, "Expired object cleanup failed");
+                    }
+                    if let Err(e) = janitor
+                        .run_shard_gc_cycle(metadata, state.node_client())
+                        .await
+                    {
+                        error!(error = %e, "Deleted shard cleanup failed");
+                    }
+                    match metadata.database().delete_expired_idempotency_keys().await {
+                        Ok(0) => {}
+                        Ok(removed) => info!(keys = removed, "Expired idempotency keys removed"),
+                        Err(e) => error!(error = %e, "Idempotency key cleanup failed"),
+                    }
+                    if last_reconcile.map_or(true, |at| {
+                        at.elapsed() >= janitor.config.stats_reconcile_interval
+                    }) {
+                        if let Err(e) = metadata.reconcile_bucket_stats().await {
+                            error!(error = %e, "Bucket counter reconciliation failed");
+                        }
+                        last_reconcile = Some(Instant::now());
+                    }
+                } else {
+                    debug!("Metadata service not available, skipping upload janitor cycle");
+                }
+            }
+        })
+    }
+
+    /// Clean up all expired intents (one batch)
+    async fn run_cleanup_cycle(
+        &self,
+        metadata: &MetadataService,
+        node_client: &NodeClient,
+    ) -> anyhow::Result<()> {
+        let db = metadata.database();
+        let expired = db
+            .get_expired_upload_intents(self.config.batch_size)
+            .await?;
+
+        if expired.is_empty() {
+            debug!("No expired upload intents");
+            return Ok(());
+        }
+
+        let mut node_addresses: HashMap<Uuid, Option<String>> = HashMap::new();
+        let mut files_purged = 0;
+        let mut shards_deleted = 0;
+        let mut skipped = 0;
+
+        for intent in &expired {
+            match upload_in_progress(db, intent.file_id).await {
+                Ok(false) => {}
+                Ok(true) => {
+                    debug!(file_id = %intent.file_id, "Upload still holds its object lock, skipping");
+                    skipped += 1;
+                    continue;
+                }
+                Err(e) => {
+                    warn!(error = %e, file_id = %intent.file_id, "Failed to check object lock");
+                    continue;
+                }
+            }
+
+            let shards = match db.get_upload_intent_shards(intent.file_id).await {
+                Ok(shards) => shards,
+                Err(e) => {
+                    warn!(error = %e, file_id = %intent.file_id, "Failed to load intent shards");
+                    continue;
+                }
+            };
+
+            for shard in &shards {
+                if delete_shard(
+                    db,
+                    node_client,
+                    &mut node_addresses,
+                    intent.file_id,
+                    &shard.chunk_id,
+                    shard.node_id,
+                )
+                .await
+                {
+                    shards_deleted += 1;
+                }
+            }
+
+            match db.purge_file(intent.file_id).await {
+                Ok(()) => {
+                    files_purged += 1;
+                    info!(
+                        file_id = %intent.file_id,
+                        shards = shards.len(),
+                        expected_shards = intent.expected_shards,
+                        "Cleaned up abandoned upload"
+                    );
+                }
+                Err(e) => {
+                    error!(error = %e, file_id = %intent.file_id, "Failed to purge abandoned upload");
+                }
+            }
+        }
+
+        info!(
+            intents = expired.len(),
+            files_purged = files_purged,
+            shards_deleted = shards_deleted,
+            skipped = skipped,
+            "Upload janitor cycle complete"
+        );
+
+        Ok(())
+    }
+
+    /// Delete expired files and their shards (one batch)
+    async fn run_expiry_cycle(
+        &self,
+        metadata: &MetadataService,
+        node_client: &NodeClient,
+    ) -> anyhow::Result<()> {
+        let db = metadata.database();
+        let expired = db.get_expired_files(self.config.batch_size).await?;
+
+        if expired.is_empty() {
+            debug!("No expired files");
+            return Ok(());
+        }
+
+        let mut node_addresses: HashMap<Uuid, Option<String>> = HashMap::new();
+        let mut files_purged = 0;
+        let mut shards_deleted = 0;
+
+        for file in &expired {
+            let locations = match db.get_file_shard_locations(file.id).await {
+                Ok(locations) => locations,
+                Err(e) => {
+                    warn!(error = %e, file_id = %file.id, "Failed to load shard locations");
+                    continue;
+                }
+            };
+
+            for (chunk_id, node_id) in &locations {
+                if delete_shard(
+                    db,
+                    node_client,
+                    &mut node_addresses,
+                    file.id,
+                    chunk_id,
+                    *node_id,
+                )
+                .await
+                {
+                    shards_deleted += 1;
+                }
+            }
+
+            match db.purge_file(file.id).await {
+                Ok(()) => {
+                    files_purged += 1;
+                    info!(
+                        file_id = %file.id,
+                        path = %file.path,
+                        expires_at = ?file.expires_at,
+                        shards = locations.len(),
+                        "Deleted expired object"
+                    );
+                }
+                Err(e) => {
+                    error!(error = %e, file_id = %file.id, "Failed to purge expired file");
+                }
+            }
+        }
+
+        info!(
+            files = expired.len(),
+            files_purged = files_purged,
+            shards_deleted = shards_deleted,
+            "Expired object cleanup complete"
+        );
+
+        Ok(())
+    }
+
+    /// Remove queued shards of deleted files from their nodes (one batch)
+    async fn run_shard_gc_cycle(
+        &self,
+        metadata: &MetadataService,
+        node_client: &NodeClient,
+    ) -> anyhow::Result<()> {
+        let db = metadata.database();
+        let batch = db
+            .get_shard_gc_batch(self.config.batch_size, trash_retention())
+            .await?;
+
+        if batch.is_empty() {
+            debug!("No queued shards to delete");
+            return Ok(());
+        }
+
+        let mut node_addresses: HashMap<Uuid, Option<String>> = HashMap::new();
+        let mut removed = Vec::new();
+        let mut failed = Vec::new();
+
+        for entry in &batch {
+            if delete_shard(
+                db,
+                node_client,
+                &mut node_addresses,
+                entry.file_id,
+                &entry.chunk_id,
+                entry.node_id,
+            )
+            .await
+            {
+                removed.push(entry.id);
+            } else {
+                failed.push(entry.id);
+            }
+        }
+
+        let exhausted = db
+            .finish_shard_gc(
+                &removed,
+                &failed,
+                SHARD_GC_MAX_ATTEMPTS,
+                SHARD_GC_STUCK_RETRY,
+            )
+            .await?;
+        if exhausted > 0 {
+            warn!(
+                shards = exhausted,
+                max_attempts = SHARD_GC_MAX_ATTEMPTS,
+                retry_secs = SHARD_GC_STUCK_RETRY.as_secs(),
+                "Shards of deleted files keep failing to delete (check the nodes' cluster token)"
+            );
+        }
+        match db.count_stuck_shard_gc(SHARD_GC_MAX_ATTEMPTS).await {
+            Ok(stuck) => crate::metrics::set_shard_gc_stuck(stuck as u64),
+            Err(e) => warn!(error = %e, "Failed to count stuck shard deletions"),
+        }
+
+        info!(
+            queued = batch.len(),
+            shards_deleted = removed.len(),
+            failed = failed.len(),
+            "Deleted shard cleanup complete"
+        );
+
+        Ok(())
+    }
+}
+
+/// Whether the upload of `file_id` still holds the write lock of its path
+async fn upload_in_progress(db: &Database, file_id: Uuid) -> anyhow::Result<bool> {
+    match db.get_file(file_id).await? {
+        Some(file) => Ok(db.object_lock_held(&file.tenant_id, &file.path).await?),
+        None => Ok(false),
+    }
+}
+
+/// Delete one shard of `file_id` from its node, unless another file also
+/// references it. Returns whether the node deleted the shard.
+async fn delete_shard(
+    db: &Database,
+    node_client: &NodeClient,
+    node_addresses: &mut HashMap<Uuid, Option<String>>,
+    file_id: Uuid,
+    chunk_id: &[u8],
+    node_id: Uuid,
+) -> bool {
+    // Never remove data that another file also references
+    match db.chunk_owned_by_other_file(chunk_id, file_id).await {
+        Ok(false) => {}
+        Ok(true) => return false,
+        Err(e) => {
+            warn!(error = %e, "Failed to check shard ownership, keeping shard");
+            return false;
+        }
+    }
+
+    if !node_addresses.contains_key(&node_id) {
+        let address = db
+            .get_node(node_id)
+            .await
+            .ok()
+            .flatten()
+            .map(|n| n.grpc_address);
+        node_addresses.insert(node_id, address);
+    }
+
+    let Some(Some(address)) = node_addresses.get(&node_id) else {
+        return false;
+    };
+
+    match node_client.delete_chunk(address, chunk_id).await {
+        Ok(deleted) => deleted,
+        Err(e) => {
+            debug!(
+                error = %e,
+                node = %address,
+                chunk_id = %hex::encode(chunk_id),
+                "Failed to delete orphan shard"
+            );
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_upload_janitor_config_default() {
+        let config = UploadJanitorConfig::default();
+        assert_eq!(config.scan_interval.as_secs(), 300);
+        assert_eq!(config.batch_size, 100);
+        assert_eq!(config.stats_reconcile_interval.as_secs(), 3600);
+    }
+}
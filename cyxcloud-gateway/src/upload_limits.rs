@@ -0,0 +1,255 @@
+//! Upload body limits
+//!
+//! S3 PUT bodies are read as a stream instead of being buffered by an
+//! extractor, and [`UploadLimits::limit`] guards that stream:
+//!
+//! - a `Content-Length` above the maximum object size is rejected before
+//!   any of the body is read, and a body without one is cut off as soon as
+//!   it grows past the limit
+//! - the whole body must arrive within the upload timeout
+//! - after a grace period the client must keep up a minimum throughput, and
+//!   a client that sends nothing for the grace period is dropped
+//!
+//! Throughput only counts the time spent waiting for the client, not the
+//! time the gateway spends writing stripes to storage nodes, so backpressure
+//! from slow nodes is never blamed on the client. A rejected upload releases
+//! its key lock and stripe buffers right away.
+
+use crate::s3_api::{S3Error, S3Result};
+use axum::http::{header, HeaderMap};
+use bytes::Bytes;
+use futures::{Stream, StreamExt};
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Upload body limits
+#[derive(Debug, Clone, PartialEq)]
+pub struct UploadLimits {
+    /// Largest object accepted by a single PUT, in bytes
+    pub max_object_size: u64,
+    /// Time allowed for receiving the whole body
+    pub timeout: Duration,
+    /// Slowest accepted client in bytes per second (0 disables the check)
+    pub min_throughput: u64,
+    /// Time before the throughput check starts, and longest a client may
+    /// send nothing
+    pub grace_period: Duration,
+}
+
+impl Default for UploadLimits {
+    fn default() -> Self {
+        Self {
+            max_object_size: 5 * 1024 * 1024 * 1024,
+            timeout: Duration::from_secs(3600),
+            min_throughput: 16 * 1024,
+            grace_period: Duration::from_secs(30),
+        }
+    }
+}
+
+impl UploadLimits {
+    /// Create configuration from environment variables
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let parse = |name: &str| std::env::var(name).ok().and_then(|v| v.parse::<u64>().ok());
+
+        Self {
+            max_object_size: parse("GATEWAY_MAX_OBJECT_MB")
+                .map(|mb| mb * 1024 * 1024)
+                .unwrap_or(defaults.max_object_size),
+            timeout: parse("GATEWAY_UPLOAD_TIMEOUT_SECS")
+                .map(Duration::from_secs)
+                .unwrap_or(defaults.timeout),
+            min_throughput: parse("GATEWAY_MIN_UPLOAD_KBPS")
+                .map(|kb| kb * 1024)
+                .unwrap_or(defaults.min_throughput),
+            grace_period: parse("GATEWAY_SLOW_UPLOAD_GRACE_SECS")
+                .map(Duration::from_secs)
+                .unwrap_or(defaults.grace_period),
+        }
+    }
+
+    /// Reject a request whose declared `Content-Length` is too large
+    pub fn check_content_length(&self, headers: &HeaderMap) -> S3Result<()> {
+        let declared = headers
+            .get(header::CONTENT_LENGTH)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<u64>().ok());
+        match declared {
+            Some(length) if length > self.max_object_size => Err(S3Error::EntityTooLarge {
+                max_size: self.max_object_size,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Wrap a request body, failing it once it breaks one of the limits
+    pub fn limit<S, E>(&self, body: S) -> impl Stream<Item = S3Result<Bytes>> + Unpin + Send
+    where
+        S: Stream<Item = Result<Bytes, E>> + Unpin + Send + 'static,
+        E: std::fmt::Display + Send,
+    {
+        let limits = self.clone();
+        let started = Instant::now();
+        let deadline = started + limits.timeout;
+
+        Box::pin(futures::stream::try_unfold(
+            (body, BodyProgress::default()),
+            move |(mut body, mut progress)| {
+                let limits = limits.clone();
+                async move {
+                    // Wait for the client at most until the upload deadline,
+                    // and no longer than the grace period at a time
+                    let waiting_since = Instant::now();
+                    let wake = deadline.min(waiting_since + limits.grace_period);
+                    let next = tokio::time::timeout_at(wake, body.next()).await;
+                    progress.waited += waiting_since.elapsed();
+
+                    let chunk = match next {
+                        Err(_) if wake == deadline => {
+                            return Err(S3Error::RequestTimeout(format!(
+                                "upload not completed within {} seconds",
+                                limits.timeout.as_secs()
+                            )))
+                        }
+                        Err(_) => {
+                            return Err(S3Error::RequestTimeout(format!(
+                                "no data received for {} seconds",
+                                limits.grace_period.as_secs()
+                            )))
+                        }
+                        Ok(None) => return Ok(None),
+                        Ok(Some(Err(e))) => {
+                            return Err(S3Error::InvalidRequest(format!(
+                                "Failed to read request body: {}",
+                                e
+                            )))
+                        }
+                        Ok(Some(Ok(chunk))) => chunk,
+                    };
+
+                    progress.received += chunk.len() as u64;
+                    if progress.received > limits.max_object_size {
+                        return Err(S3Error::EntityTooLarge {
+                            max_size: limits.max_object_size,
+                        });
+                    }
+                    if limits.too_slow(&progress) {
+                        return Err(S3Error::RequestTimeout(format!(
+                            "client sent {} bytes in {} seconds, below the minimum of {} bytes per second",
+                            progress.received,
+                            progress.waited.as_secs(),
+                            limits.min_throughput
+                        )));
+                    }
+                    Ok(Some((chunk, (body, progress))))
+                }
+            },
+        ))
+    }
+
+    /// Whether a client is below the minimum throughput after the grace period
+    fn too_slow(&self, progress: &BodyProgress) -> bool {
+        self.min_throughput > 0
+            && progress.waited > self.grace_period
+            && (progress.received as f64)
+                < self.min_throughput as f64 * progress.waited.as_secs_f64()
+    }
+}
+
+/// Bytes of a body received so far and the time spent waiting for them
+#[derive(Debug, Default, Clone, Copy)]
+struct BodyProgress {
+    received: u64,
+    waited: Duration,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::TryStreamExt;
+    use std::convert::Infallible;
+
+    fn limits() -> UploadLimits {
+        UploadLimits {
+            max_object_size: 10,
+            timeout: Duration::from_secs(60),
+            min_throughput: 1024,
+            grace_period: Duration::from_secs(5),
+        }
+    }
+
+    fn body(chunks: &[&'static str]) -> impl Stream<Item = Result<Bytes, Infallible>> + Unpin {
+        futures::stream::iter(
+            chunks
+                .iter()
+                .map(|c| Ok(Bytes::from_static(c.as_bytes())))
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    #[test]
+    fn test_check_content_length() {
+        let mut headers = HeaderMap::new();
+        assert!(limits().check_content_length(&headers).is_ok());
+
+        headers.insert(header::CONTENT_LENGTH, "10".parse().unwrap());
+        assert!(limits().check_content_length(&headers).is_ok());
+
+        headers.insert(header::CONTENT_LENGTH, "11".parse().unwrap());
+        assert!(matches!(
+            limits().check_content_length(&headers),
+            Err(S3Error::EntityTooLarge { max_size: 10 })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_limit_passes_small_body() {
+        let data: Vec<Bytes> = limits()
+            .limit(body(&["hello", " ", "you"]))
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(data.concat(), b"hello you");
+    }
+
+    #[tokio::test]
+    async fn test_limit_rejects_oversized_body() {
+        let result: S3Result<Vec<Bytes>> = limits()
+            .limit(body(&["hello", " there", "!"]))
+            .try_collect()
+            .await;
+        assert!(matches!(result, Err(S3Error::EntityTooLarge { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_limit_drops_stalled_client() {
+        let limits = UploadLimits {
+            grace_period: Duration::from_millis(20),
+            ..limits()
+        };
+        let stalled = futures::stream::pending::<Result<Bytes, Infallible>>();
+        let result: S3Result<Vec<Bytes>> = limits.limit(stalled).try_collect().await;
+        assert!(matches!(result, Err(S3Error::RequestTimeout(_))));
+    }
+
+    #[test]
+    fn test_too_slow() {
+        let limits = limits();
+        let progress = |received, secs| BodyProgress {
+            received,
+            waited: Duration::from_secs(secs),
+        };
+
+        // Within the grace period nobody is too slow
+        assert!(!limits.too_slow(&progress(0, 5)));
+        assert!(limits.too_slow(&progress(1024, 10)));
+        assert!(!limits.too_slow(&progress(20 * 1024, 10)));
+
+        let unlimited = UploadLimits {
+            min_throughput: 0,
+            ..limits
+        };
+        assert!(!unlimited.too_slow(&progress(0, 100)));
+    }
+}
@@ -0,0 +1,189 @@
+//! Usage REST API
+//!
+//! Bandwidth the caller transferred through the S3 API, per hour and tenant,
+//! together with the egress cap of their plan and how much of it this month
+//! has used.
+
+use crate::auth_api::{extract_and_validate_token, ApiError};
+use crate::bandwidth::month_start;
+use crate::AppState;
+use axum::{
+    extract::{Json, Query, State},
+    http::{HeaderMap, StatusCode},
+    routing::get,
+    Router,
+};
+use chrono::{DateTime, Duration, Utc};
+use cyxcloud_metadata::{BandwidthUsage, DbError};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::error;
+
+/// Longest period one request may cover
+const MAX_RANGE_DAYS: i64 = 366;
+
+/// Period of a bandwidth usage request
+#[derive(Debug, Deserialize)]
+pub struct BandwidthQuery {
+    /// Start (RFC 3339), default the start of the current UTC month
+    pub from: Option<DateTime<Utc>>,
+    /// End, exclusive (RFC 3339), default now
+    pub to: Option<DateTime<Utc>>,
+}
+
+/// Bandwidth of one hour in one tenant
+#[derive(Debug, Serialize)]
+pub struct HourlyBandwidth {
+    pub hour: DateTime<Utc>,
+    pub tenant: String,
+    pub bytes_in: i64,
+    pub bytes_out: i64,
+    pub requests: i64,
+}
+
+impl From<BandwidthUsage> for HourlyBandwidth {
+    fn from(usage: BandwidthUsage) -> Self {
+        Self {
+            hour: usage.hour,
+            tenant: usage.tenant_id,
+            bytes_in: usage.bytes_in,
+            bytes_out: usage.bytes_out,
+            requests: usage.requests,
+        }
+    }
+}
+
+/// Bandwidth of the caller in a period
+#[derive(Debug, Serialize)]
+pub struct BandwidthReport {
+    pub user_id: String,
+    pub plan: String,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub bytes_in: i64,
+    pub bytes_out: i64,
+    pub requests: i64,
+    /// Monthly egress cap of the plan, None if unlimited
+    pub egress_cap_bytes: Option<u64>,
+    /// Bytes downloaded since the start of the current UTC month
+    pub month_egress_bytes: i64,
+    pub hours: Vec<HourlyBandwidth>,
+}
+
+/// Create usage routes
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new().route("/bandwidth", get(get_bandwidth_usage))
+}
+
+/// Map a database error to a 500
+fn usage_db_error(e: DbError) -> (StatusCode, Json<ApiError>) {
+    error!(error = %e, "Bandwidth usage query failed");
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(ApiError::new(
+            "Failed to load bandwidth usage",
+            "INTERNAL_ERROR",
+        )),
+    )
+}
+
+/// Resolve the requested period, rejecting reversed or overlong ones
+fn usage_period(
+    query: &BandwidthQuery,
+    now: DateTime<Utc>,
+) -> Result<(DateTime<Utc>, DateTime<Utc>), String> {
+    let from = query.from.unwrap_or_else(|| month_start(now));
+    let to = query.to.unwrap_or(now);
+    if to <= from {
+        return Err("'to' must be after 'from'".to_string());
+    }
+    if to - from > Duration::days(MAX_RANGE_DAYS) {
+        return Err(format!("period cannot exceed {} days", MAX_RANGE_DAYS));
+    }
+    Ok((from, to))
+}
+
+/// Hourly bandwidth of the caller, with their plan's egress cap
+async fn get_bandwidth_usage(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(query): Query<BandwidthQuery>,
+) -> Result<Json<BandwidthReport>, (StatusCode, Json<ApiError>)> {
+    let claims = extract_and_validate_token(&headers, state.auth_service()).await?;
+    let now = Utc::now();
+    let (from, to) = usage_period(&query, now).map_err(|message| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ApiError::new(message, "INVALID_ARGUMENT")),
+        )
+    })?;
+
+    let meter = state.bandwidth_meter();
+    let rows = meter
+        .user_usage(&state, &claims.sub, from, to)
+        .await
+        .map_err(usage_db_error)?;
+    let month_egress_bytes = meter
+        .user_usage(
+            &state,
+            &claims.sub,
+            month_start(now),
+            now + Duration::hours(1),
+        )
+        .await
+        .map_err(usage_db_error)?
+        .iter()
+        .map(|row| row.bytes_out)
+        .sum();
+    let plan = meter
+        .user_plan(&state, &claims.sub)
+        .await
+        .map_err(usage_db_error)?;
+
+    Ok(Json(BandwidthReport {
+        user_id: claims.sub,
+        egress_cap_bytes: meter.config().egress_cap(&plan),
+        plan,
+        from,
+        to,
+        bytes_in: rows.iter().map(|row| row.bytes_in).sum(),
+        bytes_out: rows.iter().map(|row| row.bytes_out).sum(),
+        requests: rows.iter().map(|row| row.requests).sum(),
+        month_egress_bytes,
+        hours: rows.into_iter().map(HourlyBandwidth::from).collect(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_usage_period() {
+        let now = time("2026-10-15T13:47:12Z");
+        let query = BandwidthQuery {
+            from: None,
+            to: None,
+        };
+        assert_eq!(
+            usage_period(&query, now).unwrap(),
+            (time("2026-10-01T00:00:00Z"), now)
+        );
+
+        let reversed = BandwidthQuery {
+            from: Some(now),
+            to: Some(time("2026-10-01T00:00:00Z")),
+        };
+        assert!(usage_period(&reversed, now).is_err());
+
+        let too_long = BandwidthQuery {
+            from: Some(time("2024-01-01T00:00:00Z")),
+            to: None,
+        };
+        assert!(usage_period(&too_long, now).is_err());
+    }
+}
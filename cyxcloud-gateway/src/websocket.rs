@@ -16,11 +16,12 @@ use axum::{
     routing::get,
     Router,
 };
+use chrono::{DateTime, Utc};
 use futures::{SinkExt, StreamExt};
 use serde::{Deserialize, Serialize};
 use std::{
-    collections::HashMap,
-    sync::{Arc, Weak},
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex, Weak},
     time::Duration,
 };
 use tokio::sync::{broadcast, mpsc, RwLock};
@@ -141,6 +142,17 @@ impl Event {
 // EVENT HUB
 // =============================================================================
 
+/// Cluster and replication events kept for the cluster overview
+const RECENT_EVENTS: usize = 100;
+
+/// A published event with its publish time
+#[derive(Debug, Clone, Serialize)]
+pub struct RecentEvent {
+    pub at: DateTime<Utc>,
+    #[serde(flatten)]
+    pub event: Event,
+}
+
 /// Central event hub for broadcasting events
 pub struct EventHub {
     /// Broadcast channel for all events
@@ -151,6 +163,9 @@ pub struct EventHub {
 
     /// Connected clients count
     client_count: RwLock<usize>,
+
+    /// Latest cluster and replication events, oldest first
+    recent: Mutex<VecDeque<RecentEvent>>,
 }
 
 impl EventHub {
@@ -162,6 +177,7 @@ impl EventHub {
             broadcast_tx,
             topic_subscribers: RwLock::new(HashMap::new()),
             client_count: RwLock::new(0),
+            recent: Mutex::new(VecDeque::with_capacity(RECENT_EVENTS)),
         }
     }
 
@@ -187,6 +203,17 @@ impl EventHub {
 
     /// Publish an event to all subscribers
     pub async fn publish(&self, event: Event) {
+        if matches!(event.category(), "cluster" | "replication") {
+            let mut recent = self.recent.lock().unwrap();
+            if recent.len() == RECENT_EVENTS {
+                recent.pop_front();
+            }
+            recent.push_back(RecentEvent {
+                at: Utc::now(),
+                event: event.clone(),
+            });
+        }
+
         // Broadcast to all subscribers
         let _ = self.broadcast_tx.send(event.clone());
 
@@ -203,6 +230,12 @@ impl EventHub {
         }
     }
 
+    /// Latest cluster and replication events, newest first
+    pub fn recent_events(&self, limit: usize) -> Vec<RecentEvent> {
+        let recent = self.recent.lock().unwrap();
+        recent.iter().rev().take(limit).cloned().collect()
+    }
+
     /// Get current client count
     pub async fn client_count(&self) -> usize {
         *self.client_count.read().await
@@ -506,6 +539,36 @@ mod tests {
         assert_eq!(event.category(), "cluster");
     }
 
+    #[tokio::test]
+    async fn test_recent_events() {
+        let hub = EventHub::new(16);
+        hub.publish(Event::FileCreated {
+            bucket: "test".to_string(),
+            key: "file.txt".to_string(),
+            size: 1,
+        })
+        .await;
+        for i in 0..RECENT_EVENTS + 5 {
+            hub.publish(Event::NodeLeft {
+                node_id: format!("n{}", i),
+                reason: "timeout".to_string(),
+            })
+            .await;
+        }
+
+        // File events are not kept, and only the newest cluster events are
+        assert_eq!(hub.recent_events(usize::MAX).len(), RECENT_EVENTS);
+        let latest = hub.recent_events(1);
+        assert!(matches!(
+            &latest[0].event,
+            Event::NodeLeft { node_id, .. } if *node_id == format!("n{}", RECENT_EVENTS + 4)
+        ));
+
+        let json = serde_json::to_value(&latest[0]).unwrap();
+        assert_eq!(json["type"], "NodeLeft");
+        assert!(json["at"].is_string());
+    }
+
     #[test]
     fn test_event_serialization() {
         let event = Event::UploadProgress {
@@ -4,10 +4,19 @@
 //! Run with: cargo test --test integration_tests -p cyxcloud-gateway
 
 use bytes::Bytes;
+use std::collections::HashMap;
 use std::sync::Arc;
 
+use cyxcloud_core::crypto::EncryptionKey;
+use cyxcloud_gateway::acl::CannedAcl;
 use cyxcloud_gateway::auth::TokenType;
+use cyxcloud_gateway::bucket_policy::{actions, BucketPolicy, Decision, PolicyRequest};
+use cyxcloud_gateway::kms::{Kms, LocalKeyring, SseAlgorithm};
+use cyxcloud_gateway::object_lock::{
+    DefaultRetention, ObjectLockConfig, ObjectRetention, RetentionMode,
+};
 use cyxcloud_gateway::{AppState, AuthService};
+use cyxcloud_metadata::DEFAULT_TENANT;
 
 // ============================================================================
 // Auth Service Tests
@@ -79,11 +88,11 @@ async fn test_auth_permissions() {
 async fn test_auth_challenge_generation() {
     let auth = AuthService::from_env();
 
-    let (challenge, nonce) = auth.generate_challenge();
+    let (challenge, nonce) = auth.generate_challenge("wallet");
     assert!(!challenge.is_empty());
     assert!(!nonce.is_empty());
 
-    let (_, nonce2) = auth.generate_challenge();
+    let (_, nonce2) = auth.generate_challenge("wallet");
     assert_ne!(nonce, nonce2);
 }
 
@@ -146,37 +155,62 @@ async fn test_auth_different_token_types() {
 async fn test_bucket_create_and_exists() {
     let state = Arc::new(AppState::new());
 
-    assert!(!state.bucket_exists("test-bucket").await.unwrap());
-    state.create_bucket("test-bucket").await.unwrap();
-    assert!(state.bucket_exists("test-bucket").await.unwrap());
+    assert!(!state
+        .bucket_exists(DEFAULT_TENANT, "test-bucket")
+        .await
+        .unwrap());
+    state
+        .create_bucket(DEFAULT_TENANT, "test-bucket")
+        .await
+        .unwrap();
+    assert!(state
+        .bucket_exists(DEFAULT_TENANT, "test-bucket")
+        .await
+        .unwrap());
 }
 
 #[tokio::test]
 async fn test_bucket_duplicate_create() {
     let state = Arc::new(AppState::new());
 
-    state.create_bucket("dup-bucket").await.unwrap();
-    let result = state.create_bucket("dup-bucket").await;
+    state
+        .create_bucket(DEFAULT_TENANT, "dup-bucket")
+        .await
+        .unwrap();
+    let result = state.create_bucket(DEFAULT_TENANT, "dup-bucket").await;
     assert!(result.is_err());
 }
 
 #[tokio::test]
 async fn test_object_put_get_delete() {
     let state = Arc::new(AppState::new());
-    state.create_bucket("mybucket").await.unwrap();
+    state
+        .create_bucket(DEFAULT_TENANT, "mybucket")
+        .await
+        .unwrap();
 
     let data = Bytes::from("hello world");
     let etag = state
-        .put_object("mybucket", "test.txt", data.clone(), "text/plain")
+        .put_object(
+            DEFAULT_TENANT,
+            "mybucket",
+            "test.txt",
+            data.clone(),
+            "text/plain",
+            None,
+        )
         .await
         .unwrap();
     assert!(!etag.is_empty());
 
-    let retrieved = state.get_object("mybucket", "test.txt").await.unwrap();
+    let retrieved = state
+        .get_object(DEFAULT_TENANT, "mybucket", "test.txt")
+        .await
+        .unwrap();
     assert_eq!(retrieved, data);
 
     let meta = state
-        .get_object_metadata("mybucket", "test.txt")
+        .get_object_metadata(DEFAULT_TENANT, "mybucket", "test.txt")
         .await
         .unwrap();
     assert!(meta.is_some());
@@ -184,10 +218,13 @@ async fn test_object_put_get_delete() {
     assert_eq!(meta.size, 11);
     assert_eq!(meta.content_type, "text/plain");
 
-    state.delete_object("mybucket", "test.txt").await.unwrap();
+    state
+        .delete_object(DEFAULT_TENANT, "mybucket", "test.txt", false)
+        .await
+        .unwrap();
 
     let meta = state
-        .get_object_metadata("mybucket", "test.txt")
+        .get_object_metadata(DEFAULT_TENANT, "mybucket", "test.txt")
         .await
         .unwrap();
     assert!(meta.is_none());
@@ -196,18 +233,35 @@ async fn test_object_put_get_delete() {
 #[tokio::test]
 async fn test_object_overwrite() {
     let state = Arc::new(AppState::new());
-    state.create_bucket("bucket").await.unwrap();
+    state.create_bucket(DEFAULT_TENANT, "bucket").await.unwrap();
 
     state
-        .put_object("bucket", "key", Bytes::from("v1"), "text/plain")
+        .put_object(
+            DEFAULT_TENANT,
+            "bucket",
+            "key",
+            Bytes::from("v1"),
+            "text/plain",
+            None,
+        )
         .await
         .unwrap();
     state
-        .put_object("bucket", "key", Bytes::from("version2"), "text/plain")
+        .put_object(
+            DEFAULT_TENANT,
+            "bucket",
+            "key",
+            Bytes::from("version2"),
+            "text/plain",
+            None,
+        )
         .await
         .unwrap();
 
-    let data = state.get_object("bucket", "key").await.unwrap();
+    let data = state
+        .get_object(DEFAULT_TENANT, "bucket", "key")
+        .await
+        .unwrap();
     assert_eq!(data, Bytes::from("version2"));
 }
 
@@ -216,7 +270,14 @@ async fn test_object_in_nonexistent_bucket() {
     let state = Arc::new(AppState::new());
 
     let result = state
-        .put_object("no-such-bucket", "key", Bytes::from("data"), "text/plain")
+        .put_object(
+            DEFAULT_TENANT,
+            "no-such-bucket",
+            "key",
+            Bytes::from("data"),
+            "text/plain",
+            None,
+        )
         .await;
     assert!(result.is_err());
 }
@@ -224,19 +285,21 @@ async fn test_object_in_nonexistent_bucket() {
 #[tokio::test]
 async fn test_get_nonexistent_object() {
     let state = Arc::new(AppState::new());
-    state.create_bucket("bucket").await.unwrap();
+    state.create_bucket(DEFAULT_TENANT, "bucket").await.unwrap();
 
-    let result = state.get_object("bucket", "missing-key").await;
+    let result = state
+        .get_object(DEFAULT_TENANT, "bucket", "missing-key")
+        .await;
     assert!(result.is_err());
 }
 
 #[tokio::test]
 async fn test_list_objects_empty_bucket() {
     let state = Arc::new(AppState::new());
-    state.create_bucket("empty").await.unwrap();
+    state.create_bucket(DEFAULT_TENANT, "empty").await.unwrap();
 
     let (objects, is_truncated, next_token) = state
-        .list_objects("empty", "", None, 1000, None)
+        .list_objects(DEFAULT_TENANT, "empty", "", None, 1000, None, None)
         .await
         .unwrap();
 
@@ -248,23 +311,44 @@ async fn test_list_objects_empty_bucket() {
 #[tokio::test]
 async fn test_list_objects_with_prefix() {
     let state = Arc::new(AppState::new());
-    state.create_bucket("bucket").await.unwrap();
+    state.create_bucket(DEFAULT_TENANT, "bucket").await.unwrap();
 
     state
-        .put_object("bucket", "docs/readme.md", Bytes::from("readme"), "text/plain")
+        .put_object(
+            DEFAULT_TENANT,
+            "bucket",
+            "docs/readme.md",
+            Bytes::from("readme"),
+            "text/plain",
+            None,
+        )
         .await
         .unwrap();
     state
-        .put_object("bucket", "docs/guide.md", Bytes::from("guide"), "text/plain")
+        .put_object(
+            DEFAULT_TENANT,
+            "bucket",
+            "docs/guide.md",
+            Bytes::from("guide"),
+            "text/plain",
+            None,
+        )
         .await
         .unwrap();
     state
-        .put_object("bucket", "images/logo.png", Bytes::from("img"), "image/png")
+        .put_object(
+            DEFAULT_TENANT,
+            "bucket",
+            "images/logo.png",
+            Bytes::from("img"),
+            "image/png",
+            None,
+        )
         .await
         .unwrap();
 
     let (objects, _, _) = state
-        .list_objects("bucket", "docs/", None, 1000, None)
+        .list_objects(DEFAULT_TENANT, "bucket", "docs/", None, 1000, None, None)
         .await
         .unwrap();
 
@@ -274,26 +358,447 @@ async fn test_list_objects_with_prefix() {
     }
 }
 
+#[tokio::test]
+async fn test_list_objects_pagination() {
+    let state = Arc::new(AppState::new());
+    state.create_bucket(DEFAULT_TENANT, "bucket").await.unwrap();
+
+    for key in ["c.txt", "a.txt", "e.txt", "b.txt", "d.txt"] {
+        state
+            .put_object(
+                DEFAULT_TENANT,
+                "bucket",
+                key,
+                Bytes::from("data"),
+                "text/plain",
+                None,
+            )
+            .await
+            .unwrap();
+    }
+
+    // Pages of two, following continuation tokens
+    let mut keys = Vec::new();
+    let mut token: Option<String> = None;
+    loop {
+        let (objects, is_truncated, next_token) = state
+            .list_objects(
+                DEFAULT_TENANT,
+                "bucket",
+                "",
+                None,
+                2,
+                token.as_deref(),
+                None,
+            )
+            .await
+            .unwrap();
+        assert!(objects.len() <= 2);
+        keys.extend(objects.into_iter().map(|o| o.key));
+        assert_eq!(is_truncated, next_token.is_some());
+        match next_token {
+            Some(next) => token = Some(next),
+            None => break,
+        }
+    }
+    assert_eq!(keys, ["a.txt", "b.txt", "c.txt", "d.txt", "e.txt"]);
+
+    // start-after skips to the first key past it
+    let (objects, _, _) = state
+        .list_objects(
+            DEFAULT_TENANT,
+            "bucket",
+            "",
+            None,
+            1000,
+            None,
+            Some("c.txt"),
+        )
+        .await
+        .unwrap();
+    let keys: Vec<_> = objects.iter().map(|o| o.key.as_str()).collect();
+    assert_eq!(keys, ["d.txt", "e.txt"]);
+
+    // A garbled token is rejected
+    assert!(state
+        .list_objects(DEFAULT_TENANT, "bucket", "", None, 2, Some("%%%"), None)
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn test_expired_object_hidden() {
+    let state = Arc::new(AppState::new());
+    state.create_bucket(DEFAULT_TENANT, "bucket").await.unwrap();
+
+    let expired = chrono::Utc::now() - chrono::Duration::seconds(1);
+    let later = chrono::Utc::now() + chrono::Duration::hours(1);
+    state
+        .put_object(
+            DEFAULT_TENANT,
+            "bucket",
+            "old.txt",
+            Bytes::from("old"),
+            "text/plain",
+            Some(expired),
+        )
+        .await
+        .unwrap();
+    state
+        .put_object(
+            DEFAULT_TENANT,
+            "bucket",
+            "new.txt",
+            Bytes::from("new"),
+            "text/plain",
+            Some(later),
+        )
+        .await
+        .unwrap();
+
+    assert!(state
+        .get_object(DEFAULT_TENANT, "bucket", "old.txt")
+        .await
+        .is_err());
+    assert!(state
+        .get_object_metadata(DEFAULT_TENANT, "bucket", "old.txt")
+        .await
+        .unwrap()
+        .is_none());
+
+    let meta = state
+        .get_object_metadata(DEFAULT_TENANT, "bucket", "new.txt")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(meta.expires_at, Some(later));
+
+    let (objects, _, _) = state
+        .list_objects(DEFAULT_TENANT, "bucket", "", None, 1000, None, None)
+        .await
+        .unwrap();
+    assert_eq!(objects.len(), 1);
+    assert_eq!(objects[0].key, "new.txt");
+}
+
+#[tokio::test]
+async fn test_delete_objects_batch() {
+    let state = Arc::new(AppState::new());
+    state.create_bucket(DEFAULT_TENANT, "bucket").await.unwrap();
+
+    for key in ["a.txt", "b.txt", "c.txt"] {
+        state
+            .put_object(
+                DEFAULT_TENANT,
+                "bucket",
+                key,
+                Bytes::from("data"),
+                "text/plain",
+                None,
+            )
+            .await
+            .unwrap();
+    }
+
+    // Missing keys are skipped, not an error
+    let keys = vec![
+        "a.txt".to_string(),
+        "c.txt".to_string(),
+        "missing.txt".to_string(),
+    ];
+    let output = state
+        .delete_objects(DEFAULT_TENANT, "bucket", &keys, false)
+        .await
+        .unwrap();
+    assert_eq!(output.deleted, vec!["a.txt", "c.txt"]);
+    assert!(output.locked.is_empty());
+
+    let (objects, _, _) = state
+        .list_objects(DEFAULT_TENANT, "bucket", "", None, 1000, None, None)
+        .await
+        .unwrap();
+    assert_eq!(objects.len(), 1);
+    assert_eq!(objects[0].key, "b.txt");
+}
+
+#[tokio::test]
+async fn test_object_lock_retention() {
+    let state = Arc::new(AppState::new());
+    state.create_bucket(DEFAULT_TENANT, "vault").await.unwrap();
+    let lock = ObjectLockConfig {
+        default_retention: Some(DefaultRetention {
+            mode: RetentionMode::Governance,
+            days: 1,
+        }),
+    };
+    state
+        .set_bucket_object_lock(DEFAULT_TENANT, "vault", lock)
+        .await
+        .unwrap();
+
+    for key in ["a.txt", "b.txt"] {
+        state
+            .put_object(
+                DEFAULT_TENANT,
+                "vault",
+                key,
+                Bytes::from("v1"),
+                "text/plain",
+                None,
+            )
+            .await
+            .unwrap();
+    }
+    let meta = state
+        .get_object_metadata(DEFAULT_TENANT, "vault", "a.txt")
+        .await
+        .unwrap()
+        .unwrap();
+    let retention = meta.retention.unwrap();
+    assert_eq!(retention.mode, RetentionMode::Governance);
+    assert!(retention.retain_until > chrono::Utc::now());
+
+    // Locked objects can be neither overwritten nor deleted
+    assert!(state
+        .put_object(
+            DEFAULT_TENANT,
+            "vault",
+            "a.txt",
+            Bytes::from("v2"),
+            "text/plain",
+            None,
+        )
+        .await
+        .is_err());
+    assert!(state
+        .delete_object(DEFAULT_TENANT, "vault", "a.txt", false)
+        .await
+        .is_err());
+
+    // Compliance retention can be extended but not shortened or bypassed
+    let compliance = ObjectRetention {
+        mode: RetentionMode::Compliance,
+        retain_until: retention.retain_until + chrono::Duration::days(1),
+    };
+    state
+        .set_object_retention(DEFAULT_TENANT, "vault", "b.txt", compliance)
+        .await
+        .unwrap();
+    let shorter = ObjectRetention {
+        retain_until: retention.retain_until,
+        ..compliance
+    };
+    assert!(state
+        .set_object_retention(DEFAULT_TENANT, "vault", "b.txt", shorter)
+        .await
+        .is_err());
+
+    let keys = vec!["a.txt".to_string(), "b.txt".to_string()];
+    let output = state
+        .delete_objects(DEFAULT_TENANT, "vault", &keys, true)
+        .await
+        .unwrap();
+    assert_eq!(output.deleted, vec!["a.txt"]);
+    assert_eq!(output.locked, vec!["b.txt"]);
+    assert!(state
+        .get_object(DEFAULT_TENANT, "vault", "b.txt")
+        .await
+        .is_ok());
+}
+
+#[tokio::test]
+async fn test_bucket_policy() {
+    let state = Arc::new(AppState::new());
+    state.create_bucket(DEFAULT_TENANT, "site").await.unwrap();
+    assert!(state
+        .get_bucket_policy(DEFAULT_TENANT, "site")
+        .await
+        .unwrap()
+        .is_none());
+
+    let policy = BucketPolicy::parse(
+        "site",
+        r#"{
+            "Version": "2012-10-17",
+            "Statement": [{
+                "Sid": "PublicRead",
+                "Effect": "Allow",
+                "Principal": "*",
+                "Action": "s3:GetObject",
+                "Resource": "arn:aws:s3:::site/public/*"
+            }]
+        }"#,
+    )
+    .unwrap();
+    state
+        .set_bucket_policy(DEFAULT_TENANT, "site", Some(policy))
+        .await
+        .unwrap();
+
+    let stored = state
+        .bucket_policy_for_request(DEFAULT_TENANT, "site")
+        .await
+        .unwrap()
+        .expect("policy should be set");
+    assert_eq!(stored.document()["Statement"][0]["Sid"], "PublicRead");
+    let read = PolicyRequest::object(actions::GET_OBJECT, "site", "public/index.html");
+    assert_eq!(stored.evaluate(&read), Decision::Allow);
+
+    // Removing the policy takes effect at once on this gateway
+    state
+        .set_bucket_policy(DEFAULT_TENANT, "site", None)
+        .await
+        .unwrap();
+    assert!(state
+        .bucket_policy_for_request(DEFAULT_TENANT, "site")
+        .await
+        .unwrap()
+        .is_none());
+
+    // Buckets that do not exist have no policy to authorize against
+    assert!(state
+        .bucket_policy_for_request(DEFAULT_TENANT, "missing")
+        .await
+        .unwrap()
+        .is_none());
+    assert!(state
+        .set_bucket_policy(DEFAULT_TENANT, "missing", None)
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn test_public_read_acl() {
+    let state = Arc::new(AppState::new());
+    state.create_bucket(DEFAULT_TENANT, "share").await.unwrap();
+    let put = |key: &'static str| {
+        let state = state.clone();
+        async move {
+            state
+                .put_object(
+                    DEFAULT_TENANT,
+                    "share",
+                    key,
+                    Bytes::from("data"),
+                    "text/plain",
+                    None,
+                )
+                .await
+                .unwrap();
+        }
+    };
+    let object_public = |key: &'static str| {
+        let state = state.clone();
+        async move {
+            state
+                .get_object_metadata(DEFAULT_TENANT, "share", key)
+                .await
+                .unwrap()
+                .expect("object should exist")
+                .public_read
+        }
+    };
+    put("report.pdf").await;
+    assert!(!object_public("report.pdf").await);
+
+    state
+        .set_object_acl(DEFAULT_TENANT, "share", "report.pdf", CannedAcl::PublicRead)
+        .await
+        .unwrap();
+    assert!(object_public("report.pdf").await);
+
+    // A new version starts out private
+    put("report.pdf").await;
+    assert!(!object_public("report.pdf").await);
+    assert!(state
+        .set_object_acl(DEFAULT_TENANT, "share", "missing", CannedAcl::PublicRead)
+        .await
+        .is_err());
+
+    let acl = state.get_bucket_acl(DEFAULT_TENANT, "share").await.unwrap();
+    assert!(!acl.public_read);
+    state
+        .set_bucket_acl(DEFAULT_TENANT, "share", CannedAcl::PublicRead)
+        .await
+        .unwrap();
+    let acl = state.get_bucket_acl(DEFAULT_TENANT, "share").await.unwrap();
+    assert!(acl.public_read);
+    // In-memory buckets have no owner to bill public downloads to
+    assert_eq!(acl.owner, None);
+    assert!(state.get_bucket_acl(DEFAULT_TENANT, "missing").await.is_err());
+}
+
 #[tokio::test]
 async fn test_delete_bucket_non_empty() {
     let state = Arc::new(AppState::new());
-    state.create_bucket("bucket").await.unwrap();
+    state.create_bucket(DEFAULT_TENANT, "bucket").await.unwrap();
     state
-        .put_object("bucket", "file.txt", Bytes::from("data"), "text/plain")
+        .put_object(
+            DEFAULT_TENANT,
+            "bucket",
+            "file.txt",
+            Bytes::from("data"),
+            "text/plain",
+            None,
+        )
         .await
         .unwrap();
 
-    assert!(!state.bucket_is_empty("bucket").await.unwrap());
+    assert!(!state
+        .bucket_is_empty(DEFAULT_TENANT, "bucket")
+        .await
+        .unwrap());
 }
 
 #[tokio::test]
 async fn test_delete_bucket_empty() {
     let state = Arc::new(AppState::new());
-    state.create_bucket("bucket").await.unwrap();
+    state.create_bucket(DEFAULT_TENANT, "bucket").await.unwrap();
 
-    assert!(state.bucket_is_empty("bucket").await.unwrap());
-    state.delete_bucket("bucket").await.unwrap();
-    assert!(!state.bucket_exists("bucket").await.unwrap());
+    assert!(state
+        .bucket_is_empty(DEFAULT_TENANT, "bucket")
+        .await
+        .unwrap());
+    state.delete_bucket(DEFAULT_TENANT, "bucket").await.unwrap();
+    assert!(!state.bucket_exists(DEFAULT_TENANT, "bucket").await.unwrap());
+}
+
+#[tokio::test]
+async fn test_tenants_have_separate_namespaces() {
+    let state = Arc::new(AppState::new());
+    state.create_bucket("acme", "shared").await.unwrap();
+    state.create_bucket("globex", "shared").await.unwrap();
+
+    state
+        .put_object(
+            "acme",
+            "shared",
+            "plan.txt",
+            Bytes::from("acme"),
+            "text/plain",
+            None,
+        )
+        .await
+        .unwrap();
+
+    assert!(state
+        .get_object_metadata("globex", "shared", "plan.txt")
+        .await
+        .unwrap()
+        .is_none());
+    assert!(state
+        .get_object("globex", "shared", "plan.txt")
+        .await
+        .is_err());
+    assert!(state.bucket_is_empty("globex", "shared").await.unwrap());
+    assert!(!state.bucket_exists(DEFAULT_TENANT, "shared").await.unwrap());
+
+    state.delete_bucket("globex", "shared").await.unwrap();
+    let data = state
+        .get_object("acme", "shared", "plan.txt")
+        .await
+        .unwrap();
+    assert_eq!(data, Bytes::from("acme"));
 }
 
 // ============================================================================
@@ -303,7 +808,10 @@ async fn test_delete_bucket_empty() {
 #[tokio::test]
 async fn test_concurrent_uploads() {
     let state = Arc::new(AppState::new());
-    state.create_bucket("concurrent").await.unwrap();
+    state
+        .create_bucket(DEFAULT_TENANT, "concurrent")
+        .await
+        .unwrap();
 
     let mut handles = Vec::new();
     for i in 0..50 {
@@ -311,7 +819,7 @@ async fn test_concurrent_uploads() {
         handles.push(tokio::spawn(async move {
             let key = format!("file-{}.txt", i);
             let data = Bytes::from(format!("content-{}", i));
-            s.put_object("concurrent", &key, data, "text/plain")
+            s.put_object(DEFAULT_TENANT, "concurrent", &key, data, "text/plain", None)
                 .await
                 .unwrap();
         }));
@@ -322,7 +830,7 @@ async fn test_concurrent_uploads() {
     }
 
     let (objects, _, _) = state
-        .list_objects("concurrent", "", None, 1000, None)
+        .list_objects(DEFAULT_TENANT, "concurrent", "", None, 1000, None, None)
         .await
         .unwrap();
     assert_eq!(objects.len(), 50);
@@ -331,9 +839,16 @@ async fn test_concurrent_uploads() {
 #[tokio::test]
 async fn test_concurrent_read_write() {
     let state = Arc::new(AppState::new());
-    state.create_bucket("rw").await.unwrap();
+    state.create_bucket(DEFAULT_TENANT, "rw").await.unwrap();
     state
-        .put_object("rw", "shared.txt", Bytes::from("initial"), "text/plain")
+        .put_object(
+            DEFAULT_TENANT,
+            "rw",
+            "shared.txt",
+            Bytes::from("initial"),
+            "text/plain",
+            None,
+        )
         .await
         .unwrap();
 
@@ -342,7 +857,10 @@ async fn test_concurrent_read_write() {
     for _ in 0..20 {
         let s = state.clone();
         handles.push(tokio::spawn(async move {
-            let data = s.get_object("rw", "shared.txt").await.unwrap();
+            let data = s
+                .get_object(DEFAULT_TENANT, "rw", "shared.txt")
+                .await
+                .unwrap();
             assert!(!data.is_empty());
         }));
     }
@@ -351,7 +869,7 @@ async fn test_concurrent_read_write() {
         let s = state.clone();
         handles.push(tokio::spawn(async move {
             let data = Bytes::from(format!("update-{}", i));
-            s.put_object("rw", "shared.txt", data, "text/plain")
+            s.put_object(DEFAULT_TENANT, "rw", "shared.txt", data, "text/plain", None)
                 .await
                 .unwrap();
         }));
@@ -361,7 +879,10 @@ async fn test_concurrent_read_write() {
         h.await.unwrap();
     }
 
-    let data = state.get_object("rw", "shared.txt").await.unwrap();
+    let data = state
+        .get_object(DEFAULT_TENANT, "rw", "shared.txt")
+        .await
+        .unwrap();
     assert!(!data.is_empty());
 }
 
@@ -372,15 +893,25 @@ async fn test_concurrent_read_write() {
 #[tokio::test]
 async fn test_large_object() {
     let state = Arc::new(AppState::new());
-    state.create_bucket("large").await.unwrap();
+    state.create_bucket(DEFAULT_TENANT, "large").await.unwrap();
 
     let data = Bytes::from(vec![42u8; 1024 * 1024]);
     state
-        .put_object("large", "big.bin", data.clone(), "application/octet-stream")
+        .put_object(
+            DEFAULT_TENANT,
+            "large",
+            "big.bin",
+            data.clone(),
+            "application/octet-stream",
+            None,
+        )
         .await
         .unwrap();
 
-    let retrieved = state.get_object("large", "big.bin").await.unwrap();
+    let retrieved = state
+        .get_object(DEFAULT_TENANT, "large", "big.bin")
+        .await
+        .unwrap();
     assert_eq!(retrieved.len(), 1024 * 1024);
     assert_eq!(retrieved, data);
 }
@@ -392,19 +923,153 @@ async fn test_large_object() {
 #[tokio::test]
 async fn test_range_retrieval() {
     let state = Arc::new(AppState::new());
-    state.create_bucket("range").await.unwrap();
+    state.create_bucket(DEFAULT_TENANT, "range").await.unwrap();
 
     let data = Bytes::from("0123456789ABCDEF");
     state
-        .put_object("range", "data.txt", data, "text/plain")
+        .put_object(
+            DEFAULT_TENANT,
+            "range",
+            "data.txt",
+            data,
+            "text/plain",
+            None,
+        )
         .await
         .unwrap();
 
     let partial = state
-        .get_object_range("range", "data.txt", 0, 4)
+        .get_object_range(DEFAULT_TENANT, "range", "data.txt", 0, 4)
         .await
         .unwrap();
     assert_eq!(partial, Bytes::from("01234"));
+
+    // Ranges past the end are truncated; ranges starting past it fail
+    let tail = state
+        .get_object_range(DEFAULT_TENANT, "range", "data.txt", 12, 100)
+        .await
+        .unwrap();
+    assert_eq!(tail, Bytes::from("CDEF"));
+    assert!(state
+        .get_object_range(DEFAULT_TENANT, "range", "data.txt", 16, 20)
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn test_streamed_object() {
+    let state = Arc::new(AppState::new());
+    state.create_bucket(DEFAULT_TENANT, "stream").await.unwrap();
+
+    let data = Bytes::from((0..300_000u32).map(|i| i as u8).collect::<Vec<u8>>());
+    let pieces: Vec<_> = data
+        .chunks(64 * 1024)
+        .map(Bytes::copy_from_slice)
+        .map(Ok)
+        .collect();
+    let output = state
+        .put_object_stream(
+            DEFAULT_TENANT,
+            "stream",
+            "streamed.bin",
+            futures::stream::iter(pieces),
+            "application/octet-stream",
+            None,
+            &Default::default(),
+            &Default::default(),
+            None,
+        )
+        .await
+        .unwrap();
+    assert_eq!(output.size, data.len() as u64);
+
+    let retrieved = state
+        .get_object(DEFAULT_TENANT, "stream", "streamed.bin")
+        .await
+        .unwrap();
+    assert_eq!(retrieved, data);
+}
+
+#[tokio::test]
+async fn test_encrypted_object() {
+    let keyring = LocalKeyring::new(
+        "k1",
+        HashMap::from([("k1".to_string(), EncryptionKey::generate())]),
+    )
+    .unwrap();
+    let kms = Arc::new(Kms::new(Arc::new(keyring)));
+    let state = Arc::new(AppState::new().with_kms(kms));
+    state.create_bucket(DEFAULT_TENANT, "secret").await.unwrap();
+
+    let data = Bytes::from(
+        (0..200_000u32)
+            .map(|i| (i % 251) as u8)
+            .collect::<Vec<u8>>(),
+    );
+    let output = state
+        .put_object_if(
+            DEFAULT_TENANT,
+            "secret",
+            "a.bin",
+            data.clone(),
+            "application/octet-stream",
+            None,
+            &Default::default(),
+            &Default::default(),
+            Some(SseAlgorithm::Aes256),
+            None,
+            |_| Ok(()),
+        )
+        .await
+        .unwrap();
+    assert_eq!(output.encryption, Some(SseAlgorithm::Aes256));
+    assert_eq!(output.size, data.len() as u64);
+
+    let meta = state
+        .get_object_metadata(DEFAULT_TENANT, "secret", "a.bin")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(meta.size, data.len() as u64);
+    assert_eq!(meta.encryption, Some(SseAlgorithm::Aes256));
+
+    // Reads are decrypted, ranges across segment boundaries included
+    let retrieved = state
+        .get_object(DEFAULT_TENANT, "secret", "a.bin")
+        .await
+        .unwrap();
+    assert_eq!(retrieved, data);
+    let range = state
+        .get_object_range(DEFAULT_TENANT, "secret", "a.bin", 60_000, 140_000)
+        .await
+        .unwrap();
+    assert_eq!(range, data.slice(60_000..=140_000));
+
+    // Every data key is already wrapped with the current master key
+    let report = state.rotate_data_keys(false).await.unwrap();
+    assert_eq!(report.key_id, "k1");
+    assert_eq!(report.rewrapped, 0);
+    assert!(state.rotate_data_keys(true).await.is_err());
+
+    // Without key management an encrypted upload is refused
+    let plain = AppState::new();
+    plain.create_bucket(DEFAULT_TENANT, "secret").await.unwrap();
+    assert!(plain
+        .put_object_if(
+            DEFAULT_TENANT,
+            "secret",
+            "a.bin",
+            data,
+            "application/octet-stream",
+            None,
+            &Default::default(),
+            &Default::default(),
+            Some(SseAlgorithm::AwsKms),
+            None,
+            |_| Ok(()),
+        )
+        .await
+        .is_err());
 }
 
 // ============================================================================
@@ -0,0 +1,18 @@
+-- ============================================================================
+-- MIGRATION 009: Node warm-up tracking
+-- ============================================================================
+-- Newly joined nodes start in a warm-up phase during which their placement
+-- weight ramps up gradually while the gateway runs verification probes.
+-- A NULL warmup_started_at means the node has completed warm-up.
+-- ============================================================================
+
+-- Added without a default so existing nodes are treated as fully warmed up
+ALTER TABLE nodes ADD COLUMN IF NOT EXISTS warmup_started_at TIMESTAMP WITH TIME ZONE;
+
+-- New registrations enter warm-up immediately
+ALTER TABLE nodes ALTER COLUMN warmup_started_at SET DEFAULT NOW();
+
+CREATE INDEX IF NOT EXISTS idx_nodes_warmup_started_at ON nodes(warmup_started_at)
+    WHERE warmup_started_at IS NOT NULL;
+
+COMMENT ON COLUMN nodes.warmup_started_at IS 'Start of placement warm-up ramp (NULL once warm-up is complete)';
@@ -0,0 +1,36 @@
+-- ============================================================================
+-- MIGRATION 010: Upload intent log
+-- ============================================================================
+-- Write-ahead record of in-flight uploads. An intent is created after the
+-- file row and before any shard is dispatched; each shard placement is
+-- appended before the store RPC is issued. complete_file removes the intent.
+-- Intents that outlive their expiry belong to crashed or failed uploads and
+-- are cleaned up (shards deleted from nodes, file row purged) by the janitor.
+-- ============================================================================
+
+CREATE TABLE IF NOT EXISTS upload_intents (
+    file_id UUID PRIMARY KEY REFERENCES files(id) ON DELETE CASCADE,
+
+    -- Expected layout
+    expected_chunks INTEGER NOT NULL,
+    expected_shards INTEGER NOT NULL,
+
+    -- Lifecycle
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_upload_intents_expires_at ON upload_intents(expires_at);
+
+-- Shard placements dispatched under an intent
+CREATE TABLE IF NOT EXISTS upload_intent_shards (
+    file_id UUID NOT NULL REFERENCES upload_intents(file_id) ON DELETE CASCADE,
+    chunk_id BYTEA NOT NULL,
+    node_id UUID NOT NULL REFERENCES nodes(id) ON DELETE CASCADE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+
+    PRIMARY KEY (file_id, chunk_id, node_id)
+);
+
+COMMENT ON TABLE upload_intents IS 'Write-ahead log of in-flight uploads for partial upload cleanup';
+COMMENT ON TABLE upload_intent_shards IS 'Shard placements dispatched for an in-flight upload';
@@ -0,0 +1,15 @@
+-- ============================================================================
+-- MIGRATION 011: Expiring objects
+-- ============================================================================
+-- Objects can be uploaded with an expiry time. Expired files are hidden from
+-- reads and listings immediately; the upload janitor later deletes their
+-- shards from the nodes and purges the file row.
+-- A NULL expires_at means the file never expires.
+-- ============================================================================
+
+ALTER TABLE files ADD COLUMN IF NOT EXISTS expires_at TIMESTAMP WITH TIME ZONE;
+
+CREATE INDEX IF NOT EXISTS idx_files_expires_at ON files(expires_at)
+    WHERE expires_at IS NOT NULL;
+
+COMMENT ON COLUMN files.expires_at IS 'Time after which the file is expired and garbage collected (NULL = never)';
@@ -0,0 +1,12 @@
+-- ============================================================================
+-- MIGRATION 012: Erasure coding backend per file
+-- ============================================================================
+-- Parity shards produced by different erasure coding backends are not
+-- interchangeable, so each file records the backend that encoded it and is
+-- reconstructed with the same one. Existing files were all encoded with the
+-- reed-solomon-erasure (galois8) backend.
+-- ============================================================================
+
+ALTER TABLE files ADD COLUMN IF NOT EXISTS erasure_backend VARCHAR(32) NOT NULL DEFAULT 'galois8';
+
+COMMENT ON COLUMN files.erasure_backend IS 'Erasure coding backend used to encode the shards (galois8, leopard)';
@@ -0,0 +1,67 @@
+-- ============================================================================
+-- MIGRATION 013: Bucket replication
+-- ============================================================================
+-- Per-bucket rules that copy new and changed objects to a second CyxCloud
+-- cluster or an external S3 endpoint. The gateway's replication daemon tails
+-- files by (updated_at, id) from each rule's cursor and records the outcome
+-- per object so failures can be retried and reported.
+-- ============================================================================
+
+CREATE TABLE IF NOT EXISTS replication_rules (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    bucket VARCHAR(63) NOT NULL,
+    -- Only keys starting with this prefix are replicated ('' = all)
+    prefix TEXT NOT NULL DEFAULT '',
+
+    -- Target: 'cyxcloud' (another gateway) or 's3' (generic S3, SigV4)
+    target_type VARCHAR(16) NOT NULL,
+    endpoint TEXT NOT NULL,
+    target_bucket VARCHAR(63) NOT NULL,
+    region VARCHAR(64) NOT NULL DEFAULT 'us-east-1',
+    -- Bearer token (cyxcloud) or access key pair (s3)
+    access_token TEXT,
+    access_key_id TEXT,
+    secret_access_key TEXT,
+
+    replicate_deletes BOOLEAN NOT NULL DEFAULT FALSE,
+    enabled BOOLEAN NOT NULL DEFAULT TRUE,
+
+    -- Tail position: last file change processed
+    cursor_updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT 'epoch',
+    cursor_file_id UUID NOT NULL DEFAULT '00000000-0000-0000-0000-000000000000',
+    last_run_at TIMESTAMP WITH TIME ZONE,
+
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_replication_rules_bucket ON replication_rules(bucket);
+
+CREATE TRIGGER update_replication_rules_updated_at
+    BEFORE UPDATE ON replication_rules
+    FOR EACH ROW EXECUTE FUNCTION update_updated_at();
+
+-- Latest replication outcome per object and rule
+CREATE TABLE IF NOT EXISTS replication_objects (
+    rule_id UUID NOT NULL REFERENCES replication_rules(id) ON DELETE CASCADE,
+    path TEXT NOT NULL,
+    file_id UUID NOT NULL,
+    -- 'put' or 'delete'
+    operation VARCHAR(16) NOT NULL,
+    -- 'completed' or 'failed'
+    status VARCHAR(16) NOT NULL,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+
+    PRIMARY KEY (rule_id, path)
+);
+
+CREATE INDEX IF NOT EXISTS idx_replication_objects_failed ON replication_objects(rule_id, updated_at)
+    WHERE status = 'failed';
+
+-- Tailing scans files of one bucket in change order
+CREATE INDEX IF NOT EXISTS idx_files_bucket_updated ON files(bucket, updated_at, id);
+
+COMMENT ON TABLE replication_rules IS 'Per-bucket replication to another cluster or S3 endpoint';
+COMMENT ON TABLE replication_objects IS 'Per-object replication outcome for status and retries';
@@ -0,0 +1,22 @@
+-- ============================================================================
+-- MIGRATION 014: Node load metrics
+-- ============================================================================
+-- Latest load reported by each node with its heartbeat. The rebalancer reads
+-- it to prefer idle, fast nodes as repair sources. Transfer counters are the
+-- node's own cumulative totals; throughput_bps is derived from the difference
+-- between the last two reports (0 after a node restart resets its counters).
+-- ============================================================================
+
+CREATE TABLE IF NOT EXISTS node_load (
+    node_id UUID PRIMARY KEY REFERENCES nodes(id) ON DELETE CASCADE,
+    cpu_usage DOUBLE PRECISION NOT NULL DEFAULT 0,       -- Percent (0-100)
+    memory_usage DOUBLE PRECISION NOT NULL DEFAULT 0,    -- Percent (0-100)
+    active_connections BIGINT NOT NULL DEFAULT 0,
+    bytes_uploaded BIGINT NOT NULL DEFAULT 0,            -- Counter at the last report
+    bytes_downloaded BIGINT NOT NULL DEFAULT 0,          -- Counter at the last report
+    throughput_bps BIGINT NOT NULL DEFAULT 0,            -- Bytes/second between the last two reports
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_repair_jobs_open_source ON repair_jobs(source_node_id)
+    WHERE status IN ('pending', 'in_progress');
@@ -0,0 +1,21 @@
+-- ============================================================================
+-- MIGRATION 015: Tenant namespaces
+-- ============================================================================
+-- Buckets and files belong to a tenant (the organization from the caller's
+-- token). Bucket names are unique per tenant rather than globally, and every
+-- bucket and path lookup is scoped by tenant. Existing rows move to the
+-- 'default' tenant, which is also used for callers without an organization.
+-- ============================================================================
+
+ALTER TABLE buckets ADD COLUMN IF NOT EXISTS tenant_id VARCHAR(128) NOT NULL DEFAULT 'default';
+ALTER TABLE files ADD COLUMN IF NOT EXISTS tenant_id VARCHAR(128) NOT NULL DEFAULT 'default';
+ALTER TABLE replication_rules ADD COLUMN IF NOT EXISTS tenant_id VARCHAR(128) NOT NULL DEFAULT 'default';
+
+ALTER TABLE buckets DROP CONSTRAINT IF EXISTS buckets_name_key;
+CREATE UNIQUE INDEX IF NOT EXISTS idx_buckets_tenant_name ON buckets(tenant_id, name);
+
+CREATE INDEX IF NOT EXISTS idx_files_tenant_path ON files(tenant_id, path);
+CREATE INDEX IF NOT EXISTS idx_files_tenant_bucket_path ON files(tenant_id, bucket, path);
+
+COMMENT ON COLUMN buckets.tenant_id IS 'Tenant namespace the bucket name is unique in';
+COMMENT ON COLUMN files.tenant_id IS 'Tenant namespace of the file''s bucket and path';
@@ -0,0 +1,25 @@
+-- ============================================================================
+-- MIGRATION 016: External user identities
+-- ============================================================================
+-- Users who sign in through an OpenID Connect identity provider are linked
+-- to their CyxCloud user by the token's issuer and subject. The user is
+-- created on first login; later logins with the same identity reuse it.
+-- ============================================================================
+
+CREATE TABLE IF NOT EXISTS user_identities (
+    issuer VARCHAR(512) NOT NULL,
+    subject VARCHAR(256) NOT NULL,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+
+    -- Email reported by the provider at first login (informational only)
+    email VARCHAR(256),
+
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_login_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    PRIMARY KEY (issuer, subject)
+);
+
+CREATE INDEX IF NOT EXISTS idx_user_identities_user ON user_identities(user_id);
+
+COMMENT ON TABLE user_identities IS 'Identity provider accounts linked to CyxCloud users';
@@ -0,0 +1,38 @@
+-- ============================================================================
+-- MIGRATION 017: Epoch payout reports
+-- ============================================================================
+-- When the payment daemon distributes an epoch it stores one report with the
+-- pool split, every node's weight inputs and reward, and the transactions it
+-- sent. Reports are written once and served read-only to operators.
+-- ============================================================================
+
+CREATE TABLE IF NOT EXISTS epoch_payout_reports (
+    epoch BIGINT PRIMARY KEY REFERENCES payment_epochs(epoch) ON DELETE CASCADE,
+
+    -- Pool split (token smallest units)
+    total_pool_amount BIGINT NOT NULL,
+    nodes_share BIGINT NOT NULL,
+    platform_share BIGINT NOT NULL,
+    community_share BIGINT NOT NULL,
+
+    -- Distribution totals
+    total_weight BIGINT NOT NULL,
+    nodes_paid INTEGER NOT NULL,
+    total_paid BIGINT NOT NULL,
+
+    -- Per-node breakdown: node, wallet, stored bytes, uptime, reputation,
+    -- weight, reward, transaction signature and status
+    payouts JSONB NOT NULL DEFAULT '[]',
+
+    -- TRUE when no on-chain transactions were sent
+    dry_run BOOLEAN NOT NULL,
+
+    finalize_tx_signature VARCHAR(128),
+    platform_claim_tx_signature VARCHAR(128),
+    community_claim_tx_signature VARCHAR(128),
+    next_epoch_tx_signature VARCHAR(128),
+
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+COMMENT ON TABLE epoch_payout_reports IS 'Reward distribution result of each finalized payment epoch';
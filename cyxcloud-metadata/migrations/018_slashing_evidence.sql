@@ -0,0 +1,44 @@
+-- ============================================================================
+-- MIGRATION 018: Slashing evidence
+-- ============================================================================
+-- The gateway's proof-of-storage auditor challenges nodes for chunks they are
+-- recorded as storing. Consecutive failures are counted on the chunk location;
+-- when a location reaches the evidence threshold the auditor stores what it
+-- asked for, what it expected and what the node returned. Evidence is always
+-- written, whether or not a slash transaction was submitted for it.
+-- ============================================================================
+
+-- Start of the current failure streak (cleared by a passed audit)
+ALTER TABLE chunk_locations ADD COLUMN IF NOT EXISTS first_failed_at TIMESTAMP WITH TIME ZONE;
+
+CREATE TABLE IF NOT EXISTS slashing_evidence (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    node_id UUID NOT NULL REFERENCES nodes(id) ON DELETE CASCADE,
+    chunk_id BYTEA NOT NULL,
+    epoch BIGINT NOT NULL,
+    reason VARCHAR(64) NOT NULL,  -- data_loss, corrupted_data, failed_proofs
+
+    -- Challenge that crossed the threshold
+    challenge BYTEA NOT NULL,           -- Random 32-byte audit nonce
+    expected_proof BYTEA NOT NULL,      -- Content hash the chunk must match
+    returned_proof BYTEA,               -- Hash of the returned data (NULL if none)
+    error TEXT,                         -- Node or transport error, if any
+    failure_count INTEGER NOT NULL,
+
+    -- Timestamps
+    first_failed_at TIMESTAMP WITH TIME ZONE,
+    last_verified TIMESTAMP WITH TIME ZONE,
+    challenged_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    responded_at TIMESTAMP WITH TIME ZONE,
+
+    -- Set when the evidence led to a slash
+    slashing_event_id UUID REFERENCES node_slashing_events(id) ON DELETE SET NULL,
+    tx_signature VARCHAR(128),
+
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_slashing_evidence_node ON slashing_evidence(node_id, created_at DESC);
+CREATE INDEX IF NOT EXISTS idx_slashing_evidence_chunk ON slashing_evidence(chunk_id);
+
+COMMENT ON TABLE slashing_evidence IS 'Failed proof-of-storage challenges kept for slashing audits';
@@ -0,0 +1,21 @@
+-- ============================================================================
+-- MIGRATION 019: Node reputation
+-- ============================================================================
+-- Reputation signals per node, decayed over time so old behaviour fades:
+-- proof-of-storage challenges passed and failed, completed repair jobs, and
+-- offline transitions, plus a moving average of challenge read latency.
+-- The gateway periodically folds them into nodes.reputation (0-10000), which
+-- is listed with the node and used as a placement weight.
+-- ============================================================================
+
+ALTER TABLE nodes ADD COLUMN IF NOT EXISTS reputation INTEGER NOT NULL DEFAULT 5000;
+
+CREATE TABLE IF NOT EXISTS node_reputation (
+    node_id UUID PRIMARY KEY REFERENCES nodes(id) ON DELETE CASCADE,
+    verifications_passed DOUBLE PRECISION NOT NULL DEFAULT 0,  -- Decayed counters
+    verifications_failed DOUBLE PRECISION NOT NULL DEFAULT 0,
+    repairs_completed DOUBLE PRECISION NOT NULL DEFAULT 0,
+    offline_events DOUBLE PRECISION NOT NULL DEFAULT 0,
+    read_latency_ms DOUBLE PRECISION,                          -- Moving average (NULL until measured)
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()  -- Last decay
+);
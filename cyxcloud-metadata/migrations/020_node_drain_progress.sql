@@ -0,0 +1,22 @@
+-- ============================================================================
+-- MIGRATION 020: Node drain progress
+-- ============================================================================
+-- When a node starts draining, the gateway snapshots how many chunks (and
+-- bytes) have to be copied off it. The rebalancer bumps the evacuated
+-- counters as it completes evacuation repair jobs, so operators and the
+-- draining node itself can follow progress and an ETA.
+-- ============================================================================
+
+CREATE TABLE IF NOT EXISTS node_drains (
+    node_id UUID PRIMARY KEY REFERENCES nodes(id) ON DELETE CASCADE,
+    reason TEXT,
+    total_chunks BIGINT NOT NULL DEFAULT 0,
+    total_bytes BIGINT NOT NULL DEFAULT 0,
+    evacuated_chunks BIGINT NOT NULL DEFAULT 0,
+    evacuated_bytes BIGINT NOT NULL DEFAULT 0,
+    started_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),  -- Last evacuated chunk
+    completed_at TIMESTAMP WITH TIME ZONE                        -- Set once every chunk moved
+);
+
+COMMENT ON TABLE node_drains IS 'Evacuation progress of draining nodes';
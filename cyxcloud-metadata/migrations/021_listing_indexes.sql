@@ -0,0 +1,12 @@
+-- ============================================================================
+-- MIGRATION 021: Object listing indexes
+-- ============================================================================
+-- Bucket listings page with a keyset cursor (path > last key) instead of
+-- OFFSET, and turn key prefixes into path ranges. Both compare paths
+-- bytewise (COLLATE "C", the S3 key order), so the index uses the same
+-- collation and only covers live files, matching the listing filter.
+-- ============================================================================
+
+CREATE INDEX IF NOT EXISTS idx_files_listing
+    ON files(tenant_id, bucket, path COLLATE "C")
+    WHERE deleted_at IS NULL;
@@ -0,0 +1,22 @@
+-- ============================================================================
+-- MIGRATION 022: Shard garbage collection queue
+-- ============================================================================
+-- Deleting objects only soft-deletes their file rows. The shards they leave
+-- on storage nodes are queued here in the same transaction and removed from
+-- the nodes in batches by the upload janitor. Entries are dropped once the
+-- node deleted the shard, or after too many failed attempts.
+-- ============================================================================
+
+CREATE TABLE IF NOT EXISTS shard_gc_queue (
+    id BIGSERIAL PRIMARY KEY,
+    file_id UUID NOT NULL,      -- Deleted file the shard belonged to
+    chunk_id BYTEA NOT NULL,
+    node_id UUID NOT NULL REFERENCES nodes(id) ON DELETE CASCADE,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    enqueued_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    UNIQUE (chunk_id, node_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_shard_gc_queue_enqueued ON shard_gc_queue(enqueued_at);
+
+COMMENT ON TABLE shard_gc_queue IS 'Shards of deleted files waiting to be removed from storage nodes';
@@ -0,0 +1,15 @@
+-- ============================================================================
+-- MIGRATION 023: Per-bucket durability mode
+-- ============================================================================
+-- Objects are always erasure coded. Buckets can additionally ask for every
+-- shard to be written to several nodes at upload time instead of waiting for
+-- the rebalancer. shard_replicas = 1 is erasure coding only.
+-- ============================================================================
+
+ALTER TABLE buckets ADD COLUMN IF NOT EXISTS shard_replicas SMALLINT NOT NULL DEFAULT 1;
+
+ALTER TABLE buckets DROP CONSTRAINT IF EXISTS buckets_shard_replicas_check;
+ALTER TABLE buckets ADD CONSTRAINT buckets_shard_replicas_check
+    CHECK (shard_replicas BETWEEN 1 AND 5);
+
+COMMENT ON COLUMN buckets.shard_replicas IS 'Nodes each shard is written to at upload (1 = erasure coding only)';
@@ -0,0 +1,17 @@
+-- ============================================================================
+-- MIGRATION 024: Storage mode per file
+-- ============================================================================
+-- Objects smaller than the minimum chunk size lose most of their space to
+-- erasure coding padding, so they are stored as whole replicated blobs
+-- instead. A replicated file has a single chunk row (shard 0) whose
+-- replication_factor is the number of copies. Existing files are all
+-- erasure coded.
+-- ============================================================================
+
+ALTER TABLE files ADD COLUMN IF NOT EXISTS storage_mode VARCHAR(16) NOT NULL DEFAULT 'erasure';
+
+ALTER TABLE files DROP CONSTRAINT IF EXISTS files_storage_mode_check;
+ALTER TABLE files ADD CONSTRAINT files_storage_mode_check
+    CHECK (storage_mode IN ('erasure', 'replicated'));
+
+COMMENT ON COLUMN files.storage_mode IS 'How the data is stored on nodes (erasure = shards, replicated = whole-object copies)';
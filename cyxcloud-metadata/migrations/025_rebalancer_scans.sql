@@ -0,0 +1,27 @@
+-- ============================================================================
+-- MIGRATION 025: Rebalancer scan history
+-- ============================================================================
+-- One summary row per rebalancer scan, so operators can see whether
+-- under-replication is shrinking over time. The gateway trims the table to a
+-- bounded number of rows after every insert.
+-- ============================================================================
+
+CREATE TABLE IF NOT EXISTS rebalancer_scans (
+    id BIGSERIAL PRIMARY KEY,
+    scanned_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    duration_ms BIGINT NOT NULL,
+    chunks_scanned BIGINT NOT NULL,
+    under_replicated BIGINT NOT NULL,
+    critical BIGINT NOT NULL,
+    over_replicated BIGINT NOT NULL,
+    orphaned BIGINT NOT NULL,
+    corrupt BIGINT NOT NULL,
+    deferred BIGINT NOT NULL,
+    incidents BIGINT NOT NULL,
+    bytes_at_risk BIGINT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_rebalancer_scans_scanned_at ON rebalancer_scans(scanned_at);
+
+COMMENT ON COLUMN rebalancer_scans.critical IS 'Under-replicated chunks with no healthy replica left';
+COMMENT ON COLUMN rebalancer_scans.bytes_at_risk IS 'Size of the under-replicated chunks';
@@ -0,0 +1,13 @@
+-- ============================================================================
+-- MIGRATION 026: Per-bucket access logging
+-- ============================================================================
+-- Buckets with a logging target get S3 server access log objects written to
+-- that bucket (same tenant) under the target prefix. A NULL target bucket
+-- means logging is disabled.
+-- ============================================================================
+
+ALTER TABLE buckets ADD COLUMN IF NOT EXISTS logging_target_bucket VARCHAR(255);
+ALTER TABLE buckets ADD COLUMN IF NOT EXISTS logging_target_prefix TEXT NOT NULL DEFAULT '';
+
+COMMENT ON COLUMN buckets.logging_target_bucket IS 'Bucket receiving access logs (NULL = logging disabled)';
+COMMENT ON COLUMN buckets.logging_target_prefix IS 'Key prefix of access log objects in the target bucket';
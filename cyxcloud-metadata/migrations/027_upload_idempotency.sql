@@ -0,0 +1,32 @@
+-- ============================================================================
+-- MIGRATION 027: Upload idempotency keys
+-- ============================================================================
+-- Clients may send an idempotency key with an upload. The key is recorded
+-- on the upload intent and, when the file is published, moved into
+-- upload_idempotency_keys in the same transaction. A retried upload with the
+-- same key then returns the recorded result instead of storing the object
+-- again. Keys expire after 24 hours and are removed by the upload janitor.
+-- ============================================================================
+
+ALTER TABLE upload_intents ADD COLUMN IF NOT EXISTS idempotency_key VARCHAR(255);
+
+CREATE TABLE IF NOT EXISTS upload_idempotency_keys (
+    tenant_id VARCHAR(128) NOT NULL,
+    idempotency_key VARCHAR(255) NOT NULL,
+
+    -- Result of the original upload (the file may since have been replaced)
+    file_id UUID NOT NULL,
+    path TEXT NOT NULL,
+    content_hash BYTEA NOT NULL,
+    size_bytes BIGINT NOT NULL,
+
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+
+    PRIMARY KEY (tenant_id, idempotency_key)
+);
+
+CREATE INDEX IF NOT EXISTS idx_upload_idempotency_keys_expires_at
+    ON upload_idempotency_keys(expires_at);
+
+COMMENT ON TABLE upload_idempotency_keys IS 'Results of uploads sent with an idempotency key, for replaying retries';
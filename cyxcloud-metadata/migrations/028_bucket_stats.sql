@@ -0,0 +1,68 @@
+-- ============================================================================
+-- MIGRATION 028: Bucket statistics counters
+-- ============================================================================
+-- Buckets keep a running count of their live objects (complete, not deleted)
+-- and of the bytes those objects hold. A trigger on files adjusts the
+-- counters in the same transaction as the insert, publish, delete or purge
+-- that changes them, so reading bucket stats never scans files. The gateway
+-- reconciles the counters against files periodically to correct any drift.
+-- ============================================================================
+
+ALTER TABLE buckets ADD COLUMN IF NOT EXISTS object_count BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE buckets ADD COLUMN IF NOT EXISTS total_bytes BIGINT NOT NULL DEFAULT 0;
+
+COMMENT ON COLUMN buckets.object_count IS 'Live objects in the bucket (maintained by trigger)';
+COMMENT ON COLUMN buckets.total_bytes IS 'Bytes of live objects in the bucket (maintained by trigger)';
+
+-- Adjust the counters of the bucket a file leaves or joins
+CREATE OR REPLACE FUNCTION update_bucket_stats()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_OP IN ('UPDATE', 'DELETE') THEN
+        IF OLD.bucket IS NOT NULL AND OLD.status = 'complete' AND OLD.deleted_at IS NULL THEN
+            UPDATE buckets
+            SET object_count = object_count - 1, total_bytes = total_bytes - OLD.size_bytes
+            WHERE tenant_id = OLD.tenant_id AND name = OLD.bucket;
+        END IF;
+    END IF;
+
+    IF TG_OP IN ('INSERT', 'UPDATE') THEN
+        IF NEW.bucket IS NOT NULL AND NEW.status = 'complete' AND NEW.deleted_at IS NULL THEN
+            UPDATE buckets
+            SET object_count = object_count + 1, total_bytes = total_bytes + NEW.size_bytes
+            WHERE tenant_id = NEW.tenant_id AND name = NEW.bucket;
+        END IF;
+    END IF;
+
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS update_bucket_stats_on_write ON files;
+CREATE TRIGGER update_bucket_stats_on_write
+    AFTER INSERT OR DELETE ON files
+    FOR EACH ROW EXECUTE FUNCTION update_bucket_stats();
+
+DROP TRIGGER IF EXISTS update_bucket_stats_on_update ON files;
+CREATE TRIGGER update_bucket_stats_on_update
+    AFTER UPDATE OF tenant_id, bucket, status, size_bytes, deleted_at ON files
+    FOR EACH ROW EXECUTE FUNCTION update_bucket_stats();
+
+-- Counter changes are not configuration changes: leave updated_at alone
+DROP TRIGGER IF EXISTS update_buckets_updated_at ON buckets;
+CREATE TRIGGER update_buckets_updated_at
+    BEFORE UPDATE ON buckets
+    FOR EACH ROW
+    WHEN (OLD.object_count = NEW.object_count AND OLD.total_bytes = NEW.total_bytes)
+    EXECUTE FUNCTION update_updated_at();
+
+-- Backfill from existing files
+UPDATE buckets b
+SET object_count = s.object_count, total_bytes = s.total_bytes
+FROM (
+    SELECT tenant_id, bucket, COUNT(*) AS object_count, COALESCE(SUM(size_bytes), 0) AS total_bytes
+    FROM files
+    WHERE bucket IS NOT NULL AND status = 'complete' AND deleted_at IS NULL
+    GROUP BY tenant_id, bucket
+) s
+WHERE b.tenant_id = s.tenant_id AND b.name = s.bucket;
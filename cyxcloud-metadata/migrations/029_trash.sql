@@ -0,0 +1,14 @@
+-- ============================================================================
+-- MIGRATION 029: Trash
+-- ============================================================================
+-- Deleted objects stay restorable for a retention window: the shard GC queue
+-- only releases shards queued longer ago than the window, and restoring a
+-- file clears its deleted_at and drops its queued shards. The indexes below
+-- serve listing a bucket's trash and unqueueing a restored file's shards.
+-- ============================================================================
+
+CREATE INDEX IF NOT EXISTS idx_files_trash
+    ON files(tenant_id, bucket, path COLLATE "C", deleted_at DESC)
+    WHERE status = 'deleted';
+
+CREATE INDEX IF NOT EXISTS idx_shard_gc_queue_file_id ON shard_gc_queue(file_id);
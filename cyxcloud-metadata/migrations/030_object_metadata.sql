@@ -0,0 +1,12 @@
+-- ============================================================================
+-- MIGRATION 030: Object user metadata
+-- ============================================================================
+-- S3 objects keep their user metadata (x-amz-meta-* headers) in
+-- files.metadata as a flat JSON object of lowercase names to string values.
+-- Listings can filter on it with containment (metadata @> '{"k": "v"}'),
+-- which this index serves.
+-- ============================================================================
+
+CREATE INDEX IF NOT EXISTS idx_files_metadata
+    ON files USING GIN (metadata jsonb_path_ops)
+    WHERE metadata IS NOT NULL;
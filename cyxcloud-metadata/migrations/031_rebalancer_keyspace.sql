@@ -0,0 +1,35 @@
+-- ============================================================================
+-- MIGRATION 031: Rebalancer keyspace leases
+-- ============================================================================
+-- Large clusters run several rebalancer instances, each responsible for a
+-- range of the chunk keyspace. The keyspace is the first two bytes of the
+-- chunk ID read as a number (0-65535); chunk IDs are content hashes, so a
+-- range holds its proportional share of the chunks.
+--
+-- An instance holds its range through a lease it renews every scan. Claims
+-- are checked against the unexpired leases of other instances under a table
+-- lock, so two instances never own overlapping ranges. A lease that is not
+-- renewed expires and its range can be claimed by another instance.
+-- ============================================================================
+
+CREATE TABLE IF NOT EXISTS rebalancer_keyspace_leases (
+    owner TEXT PRIMARY KEY,
+    range_start INTEGER NOT NULL,
+    range_end INTEGER NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK (range_start >= 0 AND range_start < range_end AND range_end <= 65536)
+);
+
+COMMENT ON TABLE rebalancer_keyspace_leases IS 'Chunk keyspace ranges owned by rebalancer instances';
+COMMENT ON COLUMN rebalancer_keyspace_leases.range_start IS 'First keyspace slot owned (inclusive)';
+COMMENT ON COLUMN rebalancer_keyspace_leases.range_end IS 'End of the owned slots (exclusive)';
+
+-- Keyspace slot of a chunk ID (IDs shorter than two bytes fall in slot 0)
+CREATE OR REPLACE FUNCTION chunk_keyspace_slot(chunk_id BYTEA)
+RETURNS INTEGER AS $$
+    SELECT CASE
+        WHEN length(chunk_id) >= 2 THEN get_byte(chunk_id, 0) * 256 + get_byte(chunk_id, 1)
+        ELSE 0
+    END
+$$ LANGUAGE SQL IMMUTABLE STRICT;
@@ -0,0 +1,34 @@
+-- ============================================================================
+-- MIGRATION 032: Per-user bandwidth accounting
+-- ============================================================================
+-- Gateways count the bytes each authenticated user sends (uploads) and
+-- receives (downloads) through the S3 API and add them to one row per user,
+-- tenant and UTC hour. Rows are only ever incremented, so several gateways
+-- can flush into the same hour. The table is the source of the usage API and
+-- of billing exports.
+--
+-- Users get a plan name; the gateway maps plans to monthly egress caps.
+-- ============================================================================
+
+ALTER TABLE users ADD COLUMN IF NOT EXISTS plan VARCHAR(32) NOT NULL DEFAULT 'free';
+
+COMMENT ON COLUMN users.plan IS 'Subscription plan, selects the egress cap configured in the gateway';
+
+CREATE TABLE IF NOT EXISTS bandwidth_usage (
+    -- Token subject of the user (not necessarily a users.id)
+    user_id VARCHAR(256) NOT NULL,
+    tenant_id VARCHAR(128) NOT NULL,
+    -- Start of the UTC hour the bytes were transferred in
+    hour TIMESTAMP WITH TIME ZONE NOT NULL,
+
+    bytes_in BIGINT NOT NULL DEFAULT 0,
+    bytes_out BIGINT NOT NULL DEFAULT 0,
+    requests BIGINT NOT NULL DEFAULT 0,
+
+    PRIMARY KEY (user_id, hour, tenant_id)
+);
+
+-- Billing exports read every user's usage of a period
+CREATE INDEX IF NOT EXISTS idx_bandwidth_usage_hour ON bandwidth_usage(hour);
+
+COMMENT ON TABLE bandwidth_usage IS 'Bytes transferred through the S3 API per user, tenant and hour';
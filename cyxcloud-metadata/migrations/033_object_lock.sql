@@ -0,0 +1,31 @@
+-- ============================================================================
+-- MIGRATION 033: Object lock (WORM retention)
+-- ============================================================================
+-- A bucket with object lock enabled can give every new object a default
+-- retention. A version under retention (retain_until in the future) cannot
+-- be overwritten or deleted; in GOVERNANCE mode a delete may bypass the
+-- lock, in COMPLIANCE mode nobody can. Retention can be extended but never
+-- shortened. Expiry and the shard GC queue leave the data of a version alone
+-- until its retention has passed, whatever removed it from view.
+-- Object lock cannot be disabled on a bucket once enabled.
+-- ============================================================================
+
+ALTER TABLE buckets ADD COLUMN IF NOT EXISTS object_lock_enabled BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE buckets ADD COLUMN IF NOT EXISTS object_lock_mode VARCHAR(16)
+    CHECK (object_lock_mode IN ('GOVERNANCE', 'COMPLIANCE'));
+ALTER TABLE buckets ADD COLUMN IF NOT EXISTS object_lock_days INTEGER
+    CHECK (object_lock_days > 0);
+
+COMMENT ON COLUMN buckets.object_lock_enabled IS 'Whether objects of the bucket can be locked (cannot be disabled)';
+COMMENT ON COLUMN buckets.object_lock_mode IS 'Retention mode given to new objects (NULL = no default retention)';
+COMMENT ON COLUMN buckets.object_lock_days IS 'Days of retention given to new objects';
+
+ALTER TABLE files ADD COLUMN IF NOT EXISTS lock_mode VARCHAR(16)
+    CHECK (lock_mode IN ('GOVERNANCE', 'COMPLIANCE'));
+ALTER TABLE files ADD COLUMN IF NOT EXISTS retain_until TIMESTAMPTZ;
+
+COMMENT ON COLUMN files.lock_mode IS 'Retention mode of the version (NULL = not locked)';
+COMMENT ON COLUMN files.retain_until IS 'Time until which the version cannot be overwritten or deleted';
+
+CREATE INDEX IF NOT EXISTS idx_files_retain_until ON files(retain_until)
+    WHERE retain_until IS NOT NULL;
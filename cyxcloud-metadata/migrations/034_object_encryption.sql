@@ -0,0 +1,36 @@
+-- ============================================================================
+-- MIGRATION 034: Server-side encryption at rest
+-- ============================================================================
+-- An encrypted version is sealed by the gateway with a data key of its own
+-- before it is erasure coded, so storage nodes only hold ciphertext. The data
+-- key is stored wrapped by a master key of the key management service; master
+-- key rotation re-wraps data keys without touching the data.
+-- ============================================================================
+
+ALTER TABLE files ADD COLUMN IF NOT EXISTS sse_algorithm VARCHAR(16)
+    CHECK (sse_algorithm IN ('AES256', 'aws:kms'));
+ALTER TABLE files ADD COLUMN IF NOT EXISTS sse_key_id TEXT;
+ALTER TABLE files ADD COLUMN IF NOT EXISTS sse_data_key BYTEA;
+
+ALTER TABLE files DROP CONSTRAINT IF EXISTS files_sse_complete;
+ALTER TABLE files ADD CONSTRAINT files_sse_complete CHECK (
+    (sse_algorithm IS NULL AND sse_key_id IS NULL AND sse_data_key IS NULL)
+    OR (sse_algorithm IS NOT NULL AND sse_key_id IS NOT NULL AND sse_data_key IS NOT NULL)
+);
+
+COMMENT ON COLUMN files.sse_algorithm IS 'S3 server-side encryption algorithm (NULL = plaintext)';
+COMMENT ON COLUMN files.sse_key_id IS 'Master key that wrapped the data key';
+COMMENT ON COLUMN files.sse_data_key IS 'Data key of the version, wrapped by the master key';
+
+-- Finds the data keys left to re-wrap after a master key rotation
+CREATE INDEX IF NOT EXISTS idx_files_sse_key_id ON files(sse_key_id)
+    WHERE sse_key_id IS NOT NULL;
+
+-- Re-wrapping a data key does not modify the object: leave updated_at alone
+DROP TRIGGER IF EXISTS update_files_updated_at ON files;
+CREATE TRIGGER update_files_updated_at
+    BEFORE UPDATE ON files
+    FOR EACH ROW
+    WHEN (OLD.sse_key_id IS NOT DISTINCT FROM NEW.sse_key_id
+          AND OLD.sse_data_key IS NOT DISTINCT FROM NEW.sse_data_key)
+    EXECUTE FUNCTION update_updated_at();
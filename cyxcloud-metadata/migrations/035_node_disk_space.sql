@@ -0,0 +1,40 @@
+-- ============================================================================
+-- MIGRATION 035: Free disk space reported by nodes
+-- ============================================================================
+-- Nodes send the free space of their data directory's filesystem with every
+-- heartbeat, next to the capacity they offer. A node whose disk fills up
+-- (other data on the disk, or a capacity set larger than the disk) then
+-- counts as full even while its offered capacity is not used up.
+-- ============================================================================
+
+ALTER TABLE nodes ADD COLUMN IF NOT EXISTS disk_available BIGINT;
+
+COMMENT ON COLUMN nodes.disk_available IS 'Free bytes on the node''s data filesystem as of its last heartbeat (NULL if never reported)';
+
+-- Available space is capped by the free disk space (LEAST ignores NULL)
+DROP VIEW IF EXISTS node_storage_summary;
+
+CREATE VIEW node_storage_summary AS
+SELECT
+    n.id,
+    n.peer_id,
+    n.grpc_address,
+    n.storage_total,
+    n.storage_reserved,
+    n.storage_used,
+    LEAST(
+        GREATEST(0, n.storage_total - n.storage_reserved - n.storage_used),
+        n.disk_available
+    ) AS storage_available,
+    GREATEST(0, n.storage_total - n.storage_reserved) AS storage_allocatable,
+    CASE
+        WHEN (n.storage_total - n.storage_reserved) > 0
+        THEN ROUND((n.storage_used::numeric / (n.storage_total - n.storage_reserved)::numeric) * 100, 2)
+        ELSE 0
+    END AS utilization_percent,
+    COUNT(cl.id) AS chunk_count,
+    n.status,
+    n.last_heartbeat
+FROM nodes n
+LEFT JOIN chunk_locations cl ON cl.node_id = n.id AND cl.status = 'stored'
+GROUP BY n.id;
@@ -0,0 +1,14 @@
+-- ============================================================================
+-- MIGRATION 036: Node rack and coordinates from registration
+-- ============================================================================
+-- Nodes now report their rack and coordinates when they register and with
+-- every heartbeat. Racks are numbered from 1; NULL means not configured.
+-- The old default of 0 put every node in the same rack, so placement and
+-- outage correlation would treat the whole datacenter as a single rack.
+-- ============================================================================
+
+ALTER TABLE nodes ALTER COLUMN rack DROP DEFAULT;
+
+UPDATE nodes SET rack = NULL WHERE rack = 0;
+
+COMMENT ON COLUMN nodes.rack IS 'Rack within the datacenter, numbered from 1 (NULL if not configured)';
@@ -0,0 +1,14 @@
+-- ============================================================================
+-- MIGRATION 037: Repair job kinds
+-- ============================================================================
+-- Evacuations copy a chunk off a node that is going away; once the copy is
+-- stored the source replica is dropped. Repairs copy a chunk from a healthy
+-- replica to restore a lost one, and the source has to keep its copy.
+-- Existing jobs were all created as evacuations.
+-- ============================================================================
+
+ALTER TABLE repair_jobs
+    ADD COLUMN kind VARCHAR(16) NOT NULL DEFAULT 'evacuation'
+    CHECK (kind IN ('evacuation', 'repair'));
+
+COMMENT ON COLUMN repair_jobs.kind IS 'evacuation (drop the source replica when done) or repair (keep it)';
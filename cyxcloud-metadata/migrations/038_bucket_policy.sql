@@ -0,0 +1,12 @@
+-- ============================================================================
+-- MIGRATION 038: Bucket policies
+-- ============================================================================
+-- A bucket can carry a JSON policy document (a subset of the AWS policy
+-- language) granting or denying S3 actions to principals on keys of the
+-- bucket. The gateway validates the document before storing it and
+-- evaluates it before the token's scopes. NULL means no policy.
+-- ============================================================================
+
+ALTER TABLE buckets ADD COLUMN IF NOT EXISTS policy JSONB;
+
+COMMENT ON COLUMN buckets.policy IS 'Bucket policy document (NULL = no policy)';
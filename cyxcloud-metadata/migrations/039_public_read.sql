@@ -0,0 +1,15 @@
+-- ============================================================================
+-- MIGRATION 039: Public-read objects
+-- ============================================================================
+-- buckets.public_read (from the initial schema) lets anyone read every object
+-- of a bucket; files.public_read does the same for a single version. Both are
+-- set through the S3 `x-amz-acl: public-read` canned ACL. The gateway serves
+-- public objects without authentication under /public/{tenant}/{bucket}/{key}
+-- and bills their downloads to the bucket owner. A new version of a key
+-- starts out private.
+-- ============================================================================
+
+ALTER TABLE files ADD COLUMN IF NOT EXISTS public_read BOOLEAN NOT NULL DEFAULT FALSE;
+
+COMMENT ON COLUMN buckets.public_read IS 'Whether anyone may read the objects of the bucket';
+COMMENT ON COLUMN files.public_read IS 'Whether anyone may read the version';
@@ -0,0 +1,19 @@
+-- ============================================================================
+-- MIGRATION 040: Content headers for CDN caching
+-- ============================================================================
+-- Cache-Control, Content-Disposition and Content-Encoding given on an upload
+-- are stored with the version and returned on GET and HEAD, so a CDN in front
+-- of the gateway can cache objects. buckets.default_cache_control is given to
+-- new versions uploaded without a Cache-Control of their own.
+-- ============================================================================
+
+ALTER TABLE files ADD COLUMN IF NOT EXISTS cache_control TEXT;
+ALTER TABLE files ADD COLUMN IF NOT EXISTS content_disposition TEXT;
+ALTER TABLE files ADD COLUMN IF NOT EXISTS content_encoding TEXT;
+
+ALTER TABLE buckets ADD COLUMN IF NOT EXISTS default_cache_control TEXT;
+
+COMMENT ON COLUMN files.cache_control IS 'Cache-Control header returned with the version';
+COMMENT ON COLUMN files.content_disposition IS 'Content-Disposition header returned with the version';
+COMMENT ON COLUMN files.content_encoding IS 'Content-Encoding header returned with the version';
+COMMENT ON COLUMN buckets.default_cache_control IS 'Cache-Control given to new versions uploaded without one (NULL = none)';
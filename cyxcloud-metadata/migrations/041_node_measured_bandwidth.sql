@@ -0,0 +1,20 @@
+-- ============================================================================
+-- MIGRATION 041: Measured node bandwidth
+-- ============================================================================
+-- Nodes report their own bandwidth_mbps when they register. Gateways also
+-- measure it, by timing a stream of random data from the node
+-- (ChunkService.ProbeBandwidth), and store the result next to the reported
+-- value. Placement and repair planning use the lower of the two, so a node
+-- cannot attract more traffic than its link carries by over-reporting.
+-- ============================================================================
+
+ALTER TABLE nodes ADD COLUMN IF NOT EXISTS measured_bandwidth_mbps INTEGER
+    CHECK (measured_bandwidth_mbps >= 0);
+ALTER TABLE nodes ADD COLUMN IF NOT EXISTS bandwidth_measured_at TIMESTAMPTZ;
+
+COMMENT ON COLUMN nodes.measured_bandwidth_mbps IS 'Throughput measured by the last bandwidth probe (NULL = never probed)';
+COMMENT ON COLUMN nodes.bandwidth_measured_at IS 'Time of the last bandwidth probe';
+
+-- Finds the nodes whose measurement is due
+CREATE INDEX IF NOT EXISTS idx_nodes_bandwidth_measured_at
+    ON nodes(bandwidth_measured_at NULLS FIRST) WHERE status = 'online';
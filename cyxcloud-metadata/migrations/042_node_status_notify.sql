@@ -0,0 +1,35 @@
+-- ============================================================================
+-- MIGRATION 042: Node status notifications
+-- ============================================================================
+-- Gateways keep the list of online nodes that placement picks from in memory
+-- for up to a minute. A node marked offline by one gateway's node monitor
+-- (or brought back by its heartbeats) would otherwise keep receiving, or
+-- missing out on, new shards from the other gateways until their copy
+-- expires. Every status or address change of a node is announced on the
+-- cyxcloud_node_status channel, and gateways listening there drop their
+-- copy as soon as the online set changes.
+-- ============================================================================
+
+CREATE OR REPLACE FUNCTION notify_node_status()
+RETURNS TRIGGER AS $$
+BEGIN
+    PERFORM pg_notify('cyxcloud_node_status', json_build_object(
+        'node_id', CASE WHEN TG_OP = 'DELETE' THEN OLD.id ELSE NEW.id END,
+        'old_status', CASE WHEN TG_OP = 'INSERT' THEN NULL ELSE OLD.status END,
+        'status', CASE WHEN TG_OP = 'DELETE' THEN NULL ELSE NEW.status END
+    )::text);
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS notify_node_status_on_write ON nodes;
+CREATE TRIGGER notify_node_status_on_write
+    AFTER INSERT OR DELETE ON nodes
+    FOR EACH ROW EXECUTE FUNCTION notify_node_status();
+
+DROP TRIGGER IF EXISTS notify_node_status_on_update ON nodes;
+CREATE TRIGGER notify_node_status_on_update
+    AFTER UPDATE OF status, grpc_address ON nodes
+    FOR EACH ROW
+    WHEN (OLD.status IS DISTINCT FROM NEW.status OR OLD.grpc_address IS DISTINCT FROM NEW.grpc_address)
+    EXECUTE FUNCTION notify_node_status();
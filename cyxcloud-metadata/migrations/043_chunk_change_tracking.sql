@@ -0,0 +1,37 @@
+-- ============================================================================
+-- MIGRATION 043: Chunk change tracking
+-- ============================================================================
+-- The rebalancer used to read every under-replicated chunk of its keyspace
+-- range each scan, although on a stable cluster almost none of them change
+-- between scans. Chunks now record when their replication state (replica
+-- count, replication factor or status) last changed, so a scan can read
+-- only the chunks changed since the previous one. A rolling sweep over
+-- keyspace slices, served by the slot index, catches anything a scan missed.
+-- ============================================================================
+
+ALTER TABLE chunks ADD COLUMN IF NOT EXISTS changed_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
+
+COMMENT ON COLUMN chunks.changed_at IS 'Last change of current_replicas, replication_factor or status (maintained by trigger)';
+
+CREATE OR REPLACE FUNCTION touch_chunk_changed_at()
+RETURNS TRIGGER AS $$
+BEGIN
+    NEW.changed_at = NOW();
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS touch_chunks_changed_at ON chunks;
+CREATE TRIGGER touch_chunks_changed_at
+    BEFORE UPDATE OF current_replicas, replication_factor, status ON chunks
+    FOR EACH ROW
+    WHEN (OLD.current_replicas IS DISTINCT FROM NEW.current_replicas
+        OR OLD.replication_factor IS DISTINCT FROM NEW.replication_factor
+        OR OLD.status IS DISTINCT FROM NEW.status)
+    EXECUTE FUNCTION touch_chunk_changed_at();
+
+-- Finds the chunks changed since a scan
+CREATE INDEX IF NOT EXISTS idx_chunks_changed_at ON chunks(changed_at);
+
+-- Finds the chunks of a keyspace slice (see migration 031)
+CREATE INDEX IF NOT EXISTS idx_chunks_keyspace_slot ON chunks(chunk_keyspace_slot(chunk_id));
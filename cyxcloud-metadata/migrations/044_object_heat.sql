@@ -0,0 +1,32 @@
+-- ============================================================================
+-- MIGRATION 044: Object access heat
+-- ============================================================================
+-- Gateways sample object downloads and add them up per object, flushing the
+-- counts here in batches. Besides the total access count and the time of the
+-- last access, each object keeps a heat score: the accesses decayed by half
+-- every half-life (configured in the gateway), as of last_accessed_at. Heat
+-- ranks objects for tiering, cache warming and archiving cold buckets.
+--
+-- Rows are keyed by object path rather than file, so heat survives an object
+-- being overwritten. Rows of deleted objects are skipped when read.
+-- ============================================================================
+
+CREATE TABLE IF NOT EXISTS object_heat (
+    tenant_id VARCHAR(128) NOT NULL,
+    bucket VARCHAR(256) NOT NULL,
+    object_key VARCHAR(4096) NOT NULL,
+
+    -- Estimated from sampled downloads
+    access_count BIGINT NOT NULL DEFAULT 0,
+    -- Decayed access count as of last_accessed_at
+    heat DOUBLE PRECISION NOT NULL DEFAULT 0,
+    last_accessed_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+
+    PRIMARY KEY (tenant_id, bucket, object_key)
+);
+
+-- Finds a bucket's cold objects
+CREATE INDEX IF NOT EXISTS idx_object_heat_last_accessed
+    ON object_heat(tenant_id, bucket, last_accessed_at);
+
+COMMENT ON TABLE object_heat IS 'Sampled download counts and decayed heat per object';
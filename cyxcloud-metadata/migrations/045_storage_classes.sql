@@ -0,0 +1,84 @@
+-- ============================================================================
+-- MIGRATION 045: Storage classes
+-- ============================================================================
+-- Objects are stored in the STANDARD class (the 10+4 erasure profile, or
+-- whole copies for small objects) or in the ARCHIVE class, re-encoded with a
+-- wider erasure profile and placed on nodes flagged as archival where there
+-- are any. A transition job re-encodes an object in place: its new chunks
+-- replace the old ones in one transaction and the old shards are queued for
+-- garbage collection.
+--
+-- Archived objects are not read directly. A restore job writes a STANDARD
+-- copy of the object as a file row of its own (status 'restored'), referenced
+-- by restored_copy_id and served until restored_until.
+--
+-- Transitions are requested through the API or by a bucket's lifecycle
+-- rules; restores by S3 RestoreObject. A file has at most one open job.
+-- ============================================================================
+
+ALTER TABLE files ADD COLUMN IF NOT EXISTS storage_class VARCHAR(16) NOT NULL DEFAULT 'STANDARD'
+    CHECK (storage_class IN ('STANDARD', 'ARCHIVE'));
+ALTER TABLE files ADD COLUMN IF NOT EXISTS restored_until TIMESTAMP WITH TIME ZONE;
+ALTER TABLE files ADD COLUMN IF NOT EXISTS restored_copy_id UUID;
+
+COMMENT ON COLUMN files.storage_class IS 'STANDARD or ARCHIVE (wider erasure profile on archival nodes)';
+COMMENT ON COLUMN files.restored_until IS 'Expiry of the restored copy of an archived object';
+COMMENT ON COLUMN files.restored_copy_id IS 'File row holding the restored STANDARD copy';
+
+-- Finds the object a restored copy belongs to
+CREATE INDEX IF NOT EXISTS idx_files_restored_copy_id ON files(restored_copy_id)
+    WHERE restored_copy_id IS NOT NULL;
+
+ALTER TABLE nodes ADD COLUMN IF NOT EXISTS archival BOOLEAN NOT NULL DEFAULT FALSE;
+
+COMMENT ON COLUMN nodes.archival IS 'Node holds archive objects (cheaper, slower storage)';
+
+ALTER TABLE buckets ADD COLUMN IF NOT EXISTS lifecycle_rules JSONB;
+
+COMMENT ON COLUMN buckets.lifecycle_rules IS 'Lifecycle transition rules (NULL = no lifecycle configuration)';
+
+CREATE TABLE IF NOT EXISTS storage_class_jobs (
+    id BIGSERIAL PRIMARY KEY,
+    file_id UUID NOT NULL REFERENCES files(id) ON DELETE CASCADE,
+    tenant_id VARCHAR(128) NOT NULL,
+    bucket VARCHAR(256) NOT NULL,
+    object_key VARCHAR(4096) NOT NULL,
+
+    kind VARCHAR(16) NOT NULL CHECK (kind IN ('transition', 'restore')),
+    -- Target class of a transition
+    storage_class VARCHAR(16) NOT NULL CHECK (storage_class IN ('STANDARD', 'ARCHIVE')),
+    -- Days a restored copy is kept
+    restore_days INTEGER,
+
+    status VARCHAR(16) NOT NULL DEFAULT 'pending'
+        CHECK (status IN ('pending', 'running', 'done', 'failed')),
+    attempts INTEGER NOT NULL DEFAULT 0,
+    error TEXT,
+
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    started_at TIMESTAMP WITH TIME ZONE,
+    finished_at TIMESTAMP WITH TIME ZONE
+);
+
+-- One open job per file
+CREATE UNIQUE INDEX IF NOT EXISTS idx_storage_class_jobs_open
+    ON storage_class_jobs(file_id) WHERE status IN ('pending', 'running');
+
+CREATE INDEX IF NOT EXISTS idx_storage_class_jobs_bucket
+    ON storage_class_jobs(tenant_id, bucket, created_at);
+
+COMMENT ON TABLE storage_class_jobs IS 'Queued storage class transitions and archive restores';
+
+-- Re-encoding or restoring an object does not modify it: leave updated_at alone
+DROP TRIGGER IF EXISTS update_files_updated_at ON files;
+CREATE TRIGGER update_files_updated_at
+    BEFORE UPDATE ON files
+    FOR EACH ROW
+    WHEN (OLD.sse_key_id IS NOT DISTINCT FROM NEW.sse_key_id
+          AND OLD.sse_data_key IS NOT DISTINCT FROM NEW.sse_data_key
+          AND OLD.storage_class IS NOT DISTINCT FROM NEW.storage_class
+          AND OLD.data_shards IS NOT DISTINCT FROM NEW.data_shards
+          AND OLD.parity_shards IS NOT DISTINCT FROM NEW.parity_shards
+          AND OLD.restored_until IS NOT DISTINCT FROM NEW.restored_until
+          AND OLD.restored_copy_id IS NOT DISTINCT FROM NEW.restored_copy_id)
+    EXECUTE FUNCTION update_updated_at();
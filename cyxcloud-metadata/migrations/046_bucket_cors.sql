@@ -0,0 +1,12 @@
+-- ============================================================================
+-- MIGRATION 046: Bucket CORS configuration
+-- ============================================================================
+-- CORS rules of a bucket (allowed origins, methods and headers, exposed
+-- headers and preflight max age), set with PUT /{bucket}?cors. The gateway
+-- answers browser preflight requests to the bucket with them instead of its
+-- gateway-wide CORS settings.
+-- ============================================================================
+
+ALTER TABLE buckets ADD COLUMN IF NOT EXISTS cors_rules JSONB;
+
+COMMENT ON COLUMN buckets.cors_rules IS 'CORS rules (NULL = no CORS configuration)';
@@ -0,0 +1,50 @@
+-- ============================================================================
+-- MIGRATION 047: Repair risk of under-replicated chunks
+-- ============================================================================
+-- The rebalancer read under-replicated chunks ordered by missing replicas
+-- only, so when a scan hit its batch size it could leave out chunks a single
+-- failure away from permanent loss. chunk_repair_risk extends
+-- chunk_replication_status with what makes a loss more or less recoverable:
+--
+-- - safe_replicas: copies on online nodes that never failed verification
+--   (copies on recovering, draining or maintenance nodes may go soon)
+-- - stripe_spare: readable shards of an erasure-coded chunk's stripe beyond
+--   the data shards needed to rebuild it; NULL for whole chunks (small
+--   objects stored as plain copies), which nothing can rebuild
+-- - storage_class and restored_copy of the chunk's file
+--
+-- The rebalancer reads under-replicated chunks from this view, fewest safe
+-- copies and least spare first.
+-- ============================================================================
+
+CREATE OR REPLACE VIEW chunk_repair_risk AS
+SELECT
+    s.*,
+    COALESCE(copies.safe, 0)::INTEGER AS safe_replicas,
+    CASE
+        WHEN f.parity_shards > 0 THEN (COALESCE(stripe.shards, 0) - f.data_shards)::INTEGER
+    END AS stripe_spare,
+    f.storage_class,
+    f.status = 'restored' AS restored_copy
+FROM chunk_replication_status s
+JOIN chunks c ON c.chunk_id = s.chunk_id
+JOIN files f ON f.id = c.file_id
+LEFT JOIN LATERAL (
+    SELECT COUNT(*) AS safe
+    FROM chunk_locations cl
+    JOIN nodes n ON n.id = cl.node_id
+    WHERE cl.chunk_id = c.chunk_id
+    AND cl.status = 'stored'
+    AND cl.verification_failures = 0
+    AND n.status = 'online'
+) copies ON TRUE
+LEFT JOIN LATERAL (
+    SELECT COUNT(DISTINCT sib.shard_index) AS shards
+    FROM chunks sib
+    JOIN chunk_locations cl ON cl.chunk_id = sib.chunk_id AND cl.status = 'stored'
+    JOIN nodes n ON n.id = cl.node_id AND n.status IN ('online', 'recovering')
+    WHERE sib.file_id = c.file_id
+    AND sib.chunk_index = c.chunk_index
+) stripe ON f.parity_shards > 0;
+
+COMMENT ON VIEW chunk_repair_risk IS 'Chunk replication status with the risk of permanent loss (safe copies, spare stripe shards, file class)';
@@ -0,0 +1,17 @@
+-- ============================================================================
+-- MIGRATION 048: Keep shard GC entries that keep failing
+-- ============================================================================
+-- Entries of the shard GC queue used to be dropped after too many failed
+-- deletions. Since chunk deletes need the cluster token, a node whose token
+-- is missing or different rejects every delete, and dropping its entries
+-- left the shards on disk with no trace. Entries are now kept: once they
+-- have failed too often they are only retried after a delay, and the
+-- gateway reports them as stuck.
+-- ============================================================================
+
+ALTER TABLE shard_gc_queue
+    ADD COLUMN IF NOT EXISTS next_attempt_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW();
+
+CREATE INDEX IF NOT EXISTS idx_shard_gc_queue_next_attempt ON shard_gc_queue(next_attempt_at);
+
+COMMENT ON COLUMN shard_gc_queue.next_attempt_at IS 'Entry is not retried before this';
@@ -1,11 +1,15 @@
 //! Redis caching layer for CyxCloud metadata
 //!
 //! Provides caching for hot paths like chunk locations and node lookups.
+//! [`LocalSnapshot`] keeps per-process copies that are invalidated by
+//! database notifications instead (see the online node list).
 
+use cyxcloud_core::error::{ErrorCode, HasErrorCode};
 use redis::aio::MultiplexedConnection;
 use redis::{AsyncCommands, Client};
 use serde::{de::DeserializeOwned, Serialize};
-use std::time::Duration;
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
 use thiserror::Error;
 use tracing::{debug, info, warn};
 
@@ -22,6 +26,16 @@ pub enum CacheError {
     Miss,
 }
 
+impl HasErrorCode for CacheError {
+    fn error_code(&self) -> ErrorCode {
+        match self {
+            CacheError::Redis(_) => ErrorCode::MetadataUnavailable,
+            CacheError::Serialization(_) => ErrorCode::Internal,
+            CacheError::Miss => ErrorCode::NotFound,
+        }
+    }
+}
+
 pub type Result<T> = std::result::Result<T, CacheError>;
 
 /// Cache configuration
@@ -111,6 +125,33 @@ impl Cache {
         Ok(())
     }
 
+    /// Delete every key starting with `prefix`, returning how many were removed
+    pub async fn delete_prefix(&self, prefix: &str) -> Result<u64> {
+        let mut conn = self.conn.clone();
+        let pattern = format!("{}*", escape_pattern(prefix));
+        let mut cursor: u64 = 0;
+        let mut removed = 0;
+        loop {
+            let (next, keys): (u64, Vec<String>) = redis::cmd("SCAN")
+                .arg(cursor)
+                .arg("MATCH")
+                .arg(&pattern)
+                .arg("COUNT")
+                .arg(500)
+                .query_async(&mut conn)
+                .await?;
+            if !keys.is_empty() {
+                removed += conn.del::<_, u64>(&keys).await?;
+            }
+            if next == 0 {
+                break;
+            }
+            cursor = next;
+        }
+        debug!(prefix = %prefix, removed, "Cache prefix delete");
+        Ok(removed)
+    }
+
     /// Check if a key exists
     pub async fn exists(&self, key: &str) -> Result<bool> {
         let mut conn = self.conn.clone();
@@ -417,6 +458,20 @@ impl OptionalCache {
         }
     }
 
+    /// Try to delete all keys with a prefix, returning how many were removed
+    pub async fn try_delete_prefix(&self, prefix: &str) -> u64 {
+        match &self.cache {
+            Some(cache) => match cache.delete_prefix(prefix).await {
+                Ok(removed) => removed,
+                Err(e) => {
+                    warn!(prefix = %prefix, error = %e, "Cache prefix delete failed");
+                    0
+                }
+            },
+            None => 0,
+        }
+    }
+
     /// Check if cache is available
     pub fn is_available(&self) -> bool {
         self.cache.is_some()
@@ -440,6 +495,92 @@ impl OptionalCache {
     }
 }
 
+/// In-process copy of a value loaded from the database
+///
+/// Used until it is older than its TTL or invalidated. A load that started
+/// before an invalidation is not stored, so a slow query cannot bring back
+/// what the invalidation dropped.
+pub struct LocalSnapshot<T> {
+    ttl: Duration,
+    state: RwLock<SnapshotState<T>>,
+}
+
+struct SnapshotState<T> {
+    value: Option<(T, Instant)>,
+    generation: u64,
+}
+
+impl<T: Clone> LocalSnapshot<T> {
+    /// Create an empty snapshot
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            state: RwLock::new(SnapshotState {
+                value: None,
+                generation: 0,
+            }),
+        }
+    }
+
+    /// The value, unless missing or expired
+    pub fn get(&self) -> Option<T> {
+        let state = self.state.read().unwrap_or_else(|e| e.into_inner());
+        match &state.value {
+            Some((value, loaded_at)) if loaded_at.elapsed() < self.ttl => Some(value.clone()),
+            _ => None,
+        }
+    }
+
+    /// Generation to pass to [`store`](Self::store), taken before loading
+    pub fn generation(&self) -> u64 {
+        self.state
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .generation
+    }
+
+    /// Store a value loaded since `generation` was taken
+    ///
+    /// Returns false (and drops the value) if the snapshot was invalidated
+    /// in the meantime.
+    pub fn store(&self, generation: u64, value: T) -> bool {
+        let mut state = self.state.write().unwrap_or_else(|e| e.into_inner());
+        if state.generation != generation {
+            return false;
+        }
+        state.value = Some((value, Instant::now()));
+        true
+    }
+
+    /// Drop the value, and any load still in flight
+    pub fn invalidate(&self) {
+        let mut state = self.state.write().unwrap_or_else(|e| e.into_inner());
+        state.value = None;
+        state.generation += 1;
+    }
+
+    /// Time since the value was loaded, None if there is none
+    pub fn age(&self) -> Option<Duration> {
+        let state = self.state.read().unwrap_or_else(|e| e.into_inner());
+        state
+            .value
+            .as_ref()
+            .map(|(_, loaded_at)| loaded_at.elapsed())
+    }
+}
+
+/// Escape glob metacharacters so `pattern` matches literally in SCAN MATCH
+fn escape_pattern(pattern: &str) -> String {
+    let mut escaped = String::with_capacity(pattern.len());
+    for c in pattern.chars() {
+        if matches!(c, '*' | '?' | '[' | ']' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -466,4 +607,45 @@ mod tests {
         let stats = CacheStats::default();
         assert_eq!(stats.hit_ratio(), 0.0);
     }
+
+    #[test]
+    fn test_local_snapshot() {
+        let snapshot = LocalSnapshot::new(Duration::from_secs(60));
+        assert_eq!(snapshot.get(), None::<u32>);
+        assert_eq!(snapshot.age(), None);
+
+        assert!(snapshot.store(snapshot.generation(), 1));
+        assert_eq!(snapshot.get(), Some(1));
+        assert!(snapshot.age().is_some());
+
+        snapshot.invalidate();
+        assert_eq!(snapshot.get(), None);
+        assert_eq!(snapshot.age(), None);
+    }
+
+    #[test]
+    fn test_local_snapshot_drops_load_from_before_invalidation() {
+        let snapshot = LocalSnapshot::new(Duration::from_secs(60));
+        let generation = snapshot.generation();
+        snapshot.invalidate();
+
+        assert!(!snapshot.store(generation, "stale"));
+        assert_eq!(snapshot.get(), None);
+        assert!(snapshot.store(snapshot.generation(), "fresh"));
+        assert_eq!(snapshot.get(), Some("fresh"));
+    }
+
+    #[test]
+    fn test_local_snapshot_expires() {
+        let snapshot = LocalSnapshot::new(Duration::ZERO);
+        assert!(snapshot.store(snapshot.generation(), 1));
+        assert_eq!(snapshot.get(), None);
+        assert!(snapshot.age().is_some());
+    }
+
+    #[test]
+    fn test_escape_pattern() {
+        assert_eq!(escape_pattern("tenant:acme:"), "tenant:acme:");
+        assert_eq!(escape_pattern("a*b?[c]"), "a\\*b\\?\\[c\\]");
+    }
 }
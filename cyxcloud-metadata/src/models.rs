@@ -7,6 +7,11 @@ use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use uuid::Uuid;
 
+/// Tenant of buckets and files created without an organization
+///
+/// Rows that predate multi-tenancy belong to this tenant.
+pub const DEFAULT_TENANT: &str = "default";
+
 /// Node status enumeration
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
 #[sqlx(type_name = "varchar", rename_all = "lowercase")]
@@ -96,8 +101,14 @@ pub struct Node {
     pub storage_total: i64,
     pub storage_reserved: i64, // Gateway-reserved storage (2GB default)
     pub storage_used: i64,
+    /// Bandwidth the node reported when it registered
     pub bandwidth_mbps: i32,
+    /// Bandwidth measured by the last probe (None = never probed)
+    pub measured_bandwidth_mbps: Option<i32>,
+    pub bandwidth_measured_at: Option<DateTime<Utc>>,
     pub max_connections: i32,
+    /// Free bytes on the node's data filesystem, from its last heartbeat
+    pub disk_available: Option<i64>,
 
     // Location
     pub datacenter: Option<String>,
@@ -115,6 +126,15 @@ pub struct Node {
     pub first_offline_at: Option<DateTime<Utc>>,
     pub status_changed_at: Option<DateTime<Utc>>,
 
+    // Warm-up tracking (None once the node has completed its ramp)
+    pub warmup_started_at: Option<DateTime<Utc>>,
+
+    // Reputation score (0-10000, see NodeReputation)
+    pub reputation: i32,
+
+    /// Node holds archive objects (cheaper, slower storage)
+    pub archival: bool,
+
     // Metadata
     pub version: Option<String>,
     pub created_at: DateTime<Utc>,
@@ -126,15 +146,66 @@ pub struct Node {
 }
 
 impl Node {
-    /// Returns the available storage capacity (total - reserved - used)
+    /// Returns the available storage capacity (total - reserved - used),
+    /// capped by the free disk space the node reported
     pub fn storage_available(&self) -> i64 {
-        (self.storage_total - self.storage_reserved - self.storage_used).max(0)
+        let available = (self.storage_total - self.storage_reserved - self.storage_used).max(0);
+        match self.disk_available {
+            Some(disk) => available.min(disk.max(0)),
+            None => available,
+        }
     }
 
     /// Returns the allocatable storage (total - reserved, for user data)
     pub fn storage_allocatable(&self) -> i64 {
         (self.storage_total - self.storage_reserved).max(0)
     }
+
+    /// Bandwidth placement and repair planning count on
+    ///
+    /// The lower of the reported and the measured bandwidth, as a node may
+    /// over-report and a probe only sees one link. A node that reported no
+    /// bandwidth gets the measured one.
+    pub fn effective_bandwidth_mbps(&self) -> i32 {
+        match self.measured_bandwidth_mbps {
+            Some(measured) if self.bandwidth_mbps > 0 => self.bandwidth_mbps.min(measured),
+            Some(measured) => measured,
+            None => self.bandwidth_mbps,
+        }
+    }
+}
+
+/// Status change of a node, as notified on the node status channel
+///
+/// `old_status` is None for a newly registered node, `status` None for a
+/// removed one.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NodeStatusEvent {
+    pub node_id: Uuid,
+    pub old_status: Option<String>,
+    pub status: Option<String>,
+}
+
+impl NodeStatusEvent {
+    /// Whether the node joined or left the online set placement picks from
+    ///
+    /// Address changes of an online node count too, as placement hands out
+    /// the address.
+    pub fn changes_placement(&self) -> bool {
+        let online = |status: &Option<String>| status.as_deref() == Some("online");
+        online(&self.old_status) || online(&self.status)
+    }
+}
+
+/// Where a node sits in the failure-domain hierarchy
+/// (region > datacenter > rack), plus its coordinates
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct NodeLocation {
+    pub datacenter: Option<String>,
+    pub rack: Option<i32>,
+    pub region: Option<String>,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
 }
 
 /// Parameters for creating a new node
@@ -146,7 +217,10 @@ pub struct CreateNode {
     pub storage_reserved: i64, // Gateway-reserved storage
     pub bandwidth_mbps: i32,
     pub datacenter: Option<String>,
+    pub rack: Option<i32>,
     pub region: Option<String>,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
     pub version: Option<String>,
     // Identity/Payment
     pub wallet_address: Option<String>,
@@ -169,10 +243,14 @@ pub struct File {
     pub data_shards: i32,
     pub parity_shards: i32,
     pub chunk_size: i32,
+    pub erasure_backend: String,
+    /// How the data is stored ("erasure" or "replicated")
+    pub storage_mode: String,
 
     // Ownership
     pub owner_id: Option<Uuid>,
     pub bucket: Option<String>,
+    pub tenant_id: String,
 
     // Status
     pub status: String,
@@ -183,6 +261,165 @@ pub struct File {
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub deleted_at: Option<DateTime<Utc>>,
+
+    // Expiry (None = never expires)
+    pub expires_at: Option<DateTime<Utc>>,
+
+    // Object lock (None = not locked)
+    pub lock_mode: Option<String>,
+    pub retain_until: Option<DateTime<Utc>>,
+
+    // Server-side encryption (None = stored in plaintext)
+    pub sse_algorithm: Option<String>,
+    /// Master key that wrapped the data key
+    pub sse_key_id: Option<String>,
+    /// Data key of the version, wrapped by the master key
+    pub sse_data_key: Option<Vec<u8>>,
+
+    /// Whether anyone may read the version
+    pub public_read: bool,
+
+    // Content headers returned on GET and HEAD (None = not sent)
+    pub cache_control: Option<String>,
+    pub content_disposition: Option<String>,
+    pub content_encoding: Option<String>,
+
+    /// Storage class ("STANDARD" or "ARCHIVE")
+    pub storage_class: String,
+    /// Expiry of the restored copy of an archived object
+    pub restored_until: Option<DateTime<Utc>>,
+    /// File row holding the restored copy
+    pub restored_copy_id: Option<Uuid>,
+}
+
+impl File {
+    /// Whether the file has passed its expiry time
+    pub fn is_expired(&self) -> bool {
+        self.expires_at.is_some_and(|t| t <= Utc::now())
+    }
+
+    /// Retention mode and time of the version, if it was ever locked
+    pub fn retention(&self) -> Option<(RetentionMode, DateTime<Utc>)> {
+        let mode = RetentionMode::from_str(self.lock_mode.as_deref()?)?;
+        Some((mode, self.retain_until?))
+    }
+
+    /// Encryption of the version, if it is encrypted at rest
+    pub fn encryption(&self) -> Option<FileEncryption> {
+        Some(FileEncryption {
+            algorithm: self.sse_algorithm.clone()?,
+            key_id: self.sse_key_id.clone()?,
+            data_key: self.sse_data_key.clone()?,
+        })
+    }
+
+    /// Whether the file is stored as whole-object replicas instead of shards
+    pub fn is_replicated(&self) -> bool {
+        self.storage_mode == StorageMode::Replicated.as_str()
+    }
+
+    /// Whether the file is in the archive class
+    pub fn is_archived(&self) -> bool {
+        self.storage_class == StorageClass::Archive.as_str()
+    }
+
+    /// Restored copy of an archived file, while it has not expired
+    pub fn restored_copy(&self) -> Option<Uuid> {
+        self.restored_until
+            .filter(|t| *t > Utc::now())
+            .and(self.restored_copy_id)
+    }
+}
+
+/// How a file's data is laid out on storage nodes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageMode {
+    /// Chunks erasure coded into data and parity shards
+    #[default]
+    Erasure,
+    /// The whole object in a single chunk, copied to several nodes
+    Replicated,
+}
+
+impl StorageMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Erasure => "erasure",
+            Self::Replicated => "replicated",
+        }
+    }
+}
+
+/// Storage class of an object
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum StorageClass {
+    /// Default erasure profile on any node, read directly
+    #[default]
+    Standard,
+    /// Wider erasure profile on archival nodes, read after a restore
+    Archive,
+}
+
+impl StorageClass {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Standard => "STANDARD",
+            Self::Archive => "ARCHIVE",
+        }
+    }
+
+    /// Convert from string
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "STANDARD" => Some(Self::Standard),
+            "ARCHIVE" => Some(Self::Archive),
+            _ => None,
+        }
+    }
+}
+
+/// Kind of storage class job
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageClassJobKind {
+    /// Re-encode the object into its target class
+    Transition,
+    /// Write a temporary standard copy of an archived object
+    Restore,
+}
+
+impl StorageClassJobKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Transition => "transition",
+            Self::Restore => "restore",
+        }
+    }
+}
+
+/// Queued storage class transition or archive restore
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct StorageClassJob {
+    pub id: i64,
+    pub file_id: Uuid,
+    pub tenant_id: String,
+    pub bucket: String,
+    pub object_key: String,
+    /// "transition" or "restore"
+    pub kind: String,
+    /// Target class of a transition
+    pub storage_class: String,
+    /// Days a restored copy is kept
+    pub restore_days: Option<i32>,
+    /// pending, running, done or failed
+    pub status: String,
+    pub attempts: i32,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
 }
 
 /// Parameters for creating a new file
@@ -198,10 +435,37 @@ pub struct CreateFile {
     pub data_shards: i32,
     pub parity_shards: i32,
     pub chunk_size: i32,
+    /// Erasure coding backend that encodes the shards (e.g. "galois8")
+    pub erasure_backend: String,
+    pub storage_mode: StorageMode,
     pub owner_id: Option<Uuid>,
     pub bucket: Option<String>,
+    /// Tenant whose namespace holds the file's bucket and path
+    pub tenant_id: String,
     pub content_type: Option<String>,
     pub metadata: Option<serde_json::Value>,
+    /// Expiry time, after which the file is hidden and garbage collected
+    pub expires_at: Option<DateTime<Utc>>,
+    /// Encryption at rest (None = stored in plaintext)
+    pub encryption: Option<FileEncryption>,
+    /// Cache-Control header, None for the bucket's default cache policy
+    pub cache_control: Option<String>,
+    pub content_disposition: Option<String>,
+    pub content_encoding: Option<String>,
+}
+
+/// Envelope encryption of a file version
+///
+/// The data is sealed with a data key of its own, which is stored wrapped
+/// by a master key of the gateway's key management service.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileEncryption {
+    /// S3 server-side encryption algorithm (`AES256` or `aws:kms`)
+    pub algorithm: String,
+    /// Master key that wrapped the data key
+    pub key_id: String,
+    /// Wrapped data key
+    pub data_key: Vec<u8>,
 }
 
 /// Chunk metadata
@@ -253,6 +517,59 @@ pub struct ChunkLocation {
     pub last_verified: Option<DateTime<Utc>>,
     pub verification_failures: i32,
     pub created_at: DateTime<Utc>,
+    /// Start of the current verification failure streak
+    pub first_failed_at: Option<DateTime<Utc>>,
+}
+
+/// Chunk location selected for a proof-of-storage audit
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct ChunkAuditTarget {
+    pub chunk_id: Vec<u8>,
+    pub node_id: Uuid,
+    pub peer_id: String,
+    pub grpc_address: String,
+    pub wallet_address: Option<String>,
+}
+
+/// Stored copy on a node selected for a sampled integrity check
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct ChunkIntegrityCandidate {
+    pub chunk_id: Vec<u8>,
+    pub file_id: Uuid,
+    pub size_bytes: i32,
+    pub replication_factor: i32,
+    /// Seconds since the copy was last verified, or stored if never verified
+    pub unverified_secs: f64,
+}
+
+/// What restoring one salvaged chunk location did
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SalvageOutcome {
+    /// The location was missing and is recorded again
+    Restored,
+    /// The location was already recorded
+    Known,
+    /// The chunk's file no longer exists; nothing was recorded
+    Orphaned,
+}
+
+/// Chunk locations restored from a node's salvage report
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SalvageSummary {
+    pub restored: u64,
+    pub known: u64,
+    pub orphaned: u64,
+}
+
+impl SalvageSummary {
+    /// Count one restored location
+    pub fn record(&mut self, outcome: SalvageOutcome) {
+        match outcome {
+            SalvageOutcome::Restored => self.restored += 1,
+            SalvageOutcome::Known => self.known += 1,
+            SalvageOutcome::Orphaned => self.orphaned += 1,
+        }
+    }
 }
 
 /// User account
@@ -267,6 +584,36 @@ pub struct User {
     pub status: String,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    /// Subscription plan (selects the gateway's egress cap)
+    pub plan: String,
+}
+
+/// Download counts and heat of one object
+#[derive(Debug, Clone, PartialEq, FromRow, Serialize, Deserialize)]
+pub struct ObjectHeat {
+    pub tenant_id: String,
+    pub bucket: String,
+    pub object_key: String,
+    /// Downloads, estimated from sampled ones
+    pub access_count: i64,
+    /// Downloads decayed by half every half-life, as of `last_accessed_at`
+    pub heat: f64,
+    pub last_accessed_at: DateTime<Utc>,
+}
+
+/// Bytes one user transferred through the S3 API in one tenant and hour
+#[derive(Debug, Clone, PartialEq, Eq, FromRow, Serialize, Deserialize)]
+pub struct BandwidthUsage {
+    /// Token subject of the user
+    pub user_id: String,
+    pub tenant_id: String,
+    /// Start of the UTC hour
+    pub hour: DateTime<Utc>,
+    /// Request body bytes (uploads)
+    pub bytes_in: i64,
+    /// Response body bytes (downloads)
+    pub bytes_out: i64,
+    pub requests: i64,
 }
 
 /// Bucket (S3-compatible)
@@ -275,11 +622,166 @@ pub struct Bucket {
     pub id: Uuid,
     pub name: String,
     pub owner_id: Uuid,
+    pub tenant_id: String,
     pub versioning_enabled: bool,
     pub public_read: bool,
     pub max_size_bytes: Option<i64>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    /// Nodes each shard is written to at upload (1 = erasure coding only)
+    pub shard_replicas: i16,
+    /// Bucket receiving access logs (None = logging disabled)
+    pub logging_target_bucket: Option<String>,
+    /// Key prefix of access log objects in the target bucket
+    pub logging_target_prefix: String,
+    /// Live objects in the bucket (kept up to date by a trigger on files)
+    pub object_count: i64,
+    /// Bytes held by the bucket's live objects
+    pub total_bytes: i64,
+    /// Whether objects of the bucket can be locked
+    pub object_lock_enabled: bool,
+    /// Retention mode given to new objects (None = no default retention)
+    pub object_lock_mode: Option<String>,
+    /// Days of retention given to new objects
+    pub object_lock_days: Option<i32>,
+    /// Bucket policy document (None = no policy)
+    pub policy: Option<serde_json::Value>,
+    /// Cache-Control given to new objects uploaded without one
+    pub default_cache_control: Option<String>,
+    /// Lifecycle transition rules (None = no lifecycle configuration)
+    pub lifecycle_rules: Option<serde_json::Value>,
+    /// CORS rules (None = no CORS configuration)
+    pub cors_rules: Option<serde_json::Value>,
+}
+
+impl Bucket {
+    /// How the bucket's objects are protected
+    pub fn durability_mode(&self) -> DurabilityMode {
+        DurabilityMode::from_shard_replicas(self.shard_replicas)
+    }
+
+    /// Retention mode and days given to new objects, if any
+    pub fn default_retention(&self) -> Option<(RetentionMode, i32)> {
+        let mode = RetentionMode::from_str(self.object_lock_mode.as_deref()?)?;
+        Some((mode, self.object_lock_days?))
+    }
+}
+
+/// Object lock retention mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum RetentionMode {
+    /// Deletes that explicitly bypass governance retention are allowed
+    Governance,
+    /// Nobody can delete the version or shorten its retention
+    Compliance,
+}
+
+impl RetentionMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Governance => "GOVERNANCE",
+            Self::Compliance => "COMPLIANCE",
+        }
+    }
+
+    /// Convert from string
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "GOVERNANCE" => Some(Self::Governance),
+            "COMPLIANCE" => Some(Self::Compliance),
+            _ => None,
+        }
+    }
+}
+
+/// Outcome of deleting files by path
+#[derive(Debug, Clone, Default)]
+pub struct DeletedFiles {
+    /// Files that were deleted
+    pub deleted: Vec<File>,
+    /// Paths whose current version is under retention and was kept
+    pub locked: Vec<String>,
+}
+
+/// Most nodes a bucket may ask each shard to be written to
+pub const MAX_SHARD_REPLICAS: u8 = 5;
+
+/// Per-bucket durability mode
+///
+/// Objects are always erasure coded. `Replicated` also writes every shard to
+/// several nodes at upload time, trading storage for durability and read
+/// availability right after the upload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(tag = "mode")]
+pub enum DurabilityMode {
+    /// Each shard is stored once; erasure coding provides the redundancy
+    #[default]
+    #[serde(rename = "ec")]
+    ErasureCoded,
+    /// Each shard is stored on `replicas` nodes
+    #[serde(rename = "ec_replicated")]
+    Replicated { replicas: u8 },
+}
+
+impl DurabilityMode {
+    /// Mode for a stored `shard_replicas` value
+    pub fn from_shard_replicas(replicas: i16) -> Self {
+        if replicas > 1 {
+            Self::Replicated {
+                replicas: replicas.min(MAX_SHARD_REPLICAS as i16) as u8,
+            }
+        } else {
+            Self::ErasureCoded
+        }
+    }
+
+    /// Nodes each shard is written to
+    pub fn shard_replicas(&self) -> usize {
+        match self {
+            Self::ErasureCoded => 1,
+            Self::Replicated { replicas } => *replicas as usize,
+        }
+    }
+
+    /// Check the replica count is in range
+    pub fn validate(&self) -> Result<(), String> {
+        if let Self::Replicated { replicas } = self {
+            if !(2..=MAX_SHARD_REPLICAS).contains(replicas) {
+                return Err(format!(
+                    "replicas must be between 2 and {}",
+                    MAX_SHARD_REPLICAS
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Latest load reported by a node
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct NodeLoad {
+    pub node_id: Uuid,
+    /// CPU usage in percent (0-100)
+    pub cpu_usage: f64,
+    /// Memory usage in percent (0-100)
+    pub memory_usage: f64,
+    pub active_connections: i64,
+    pub bytes_uploaded: i64,
+    pub bytes_downloaded: i64,
+    /// Transfer rate between the last two reports (bytes/second)
+    pub throughput_bps: i64,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Load metrics carried by a node heartbeat
+#[derive(Debug, Clone, Default)]
+pub struct NodeLoadReport {
+    pub cpu_usage: f64,
+    pub memory_usage: f64,
+    pub active_connections: i64,
+    pub bytes_uploaded: i64,
+    pub bytes_downloaded: i64,
 }
 
 /// Repair job for chunk replication
@@ -296,9 +798,95 @@ pub struct RepairJob {
     pub error_message: Option<String>,
     pub retry_count: i32,
     pub created_at: DateTime<Utc>,
+    /// `evacuation` drops the source replica once the target holds the
+    /// chunk, `repair` restores a lost replica and keeps the source
+    pub kind: String,
+}
+
+impl RepairJob {
+    /// Whether the job moves the chunk off its source rather than copying it
+    pub fn is_evacuation(&self) -> bool {
+        self.kind == "evacuation"
+    }
+}
+
+/// Outcome of dropping a chunk location its node turned out not to hold
+#[derive(Debug, Clone)]
+pub struct StaleLocationRepair {
+    /// Node the location was recorded on
+    pub node_id: Uuid,
+    /// Nodes still recorded as holding the chunk
+    pub remaining_replicas: usize,
+    /// Job copying the chunk to another node (None without a source or target)
+    pub repair_job: Option<RepairJob>,
+}
+
+/// Evacuation progress of a draining node
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct NodeDrain {
+    pub node_id: Uuid,
+    pub reason: Option<String>,
+    /// Chunks stored on the node when the drain started
+    pub total_chunks: i64,
+    pub total_bytes: i64,
+    /// Chunks copied to other nodes so far
+    pub evacuated_chunks: i64,
+    pub evacuated_bytes: i64,
+    pub started_at: DateTime<Utc>,
+    /// When the last chunk was evacuated
+    pub updated_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+impl NodeDrain {
+    /// Chunks still to be copied off the node
+    pub fn remaining_chunks(&self) -> i64 {
+        (self.total_chunks - self.evacuated_chunks).max(0)
+    }
+
+    /// Bytes still to be copied off the node
+    pub fn bytes_remaining(&self) -> i64 {
+        (self.total_bytes - self.evacuated_bytes).max(0)
+    }
+
+    /// Whether every chunk has been evacuated
+    pub fn is_complete(&self) -> bool {
+        self.completed_at.is_some() || self.remaining_chunks() == 0
+    }
+
+    /// Evacuation throughput so far (bytes/second)
+    pub fn throughput_bps(&self) -> f64 {
+        let elapsed = (self.updated_at - self.started_at).num_milliseconds() as f64 / 1000.0;
+        if elapsed <= 0.0 {
+            return 0.0;
+        }
+        self.evacuated_bytes as f64 / elapsed
+    }
+
+    /// Estimated seconds until the drain completes at the current throughput
+    ///
+    /// `None` until the first chunk has been evacuated. Falls back to the
+    /// chunk rate when chunk sizes are unknown.
+    pub fn eta_secs(&self) -> Option<u64> {
+        if self.is_complete() {
+            return Some(0);
+        }
+
+        let elapsed = (self.updated_at - self.started_at).num_milliseconds() as f64 / 1000.0;
+        if elapsed <= 0.0 || self.evacuated_chunks == 0 {
+            return None;
+        }
+
+        let secs = if self.evacuated_bytes > 0 && self.total_bytes > 0 {
+            self.bytes_remaining() as f64 / self.throughput_bps()
+        } else {
+            self.remaining_chunks() as f64 * elapsed / self.evacuated_chunks as f64
+        };
+        Some(secs.ceil() as u64)
+    }
 }
 
-/// Chunk replication status view
+/// Chunk replication status with its repair risk (chunk_repair_risk view)
 #[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
 pub struct ChunkReplicationStatus {
     pub chunk_id: Vec<u8>,
@@ -307,6 +895,15 @@ pub struct ChunkReplicationStatus {
     pub current_replicas: i32,
     pub replicas_needed: i32,
     pub health_status: String,
+    /// Copies on online nodes that never failed verification
+    pub safe_replicas: i32,
+    /// Readable shards of the chunk's stripe beyond the data shards needed
+    /// to rebuild it (None for whole chunks, which cannot be rebuilt)
+    pub stripe_spare: Option<i32>,
+    /// Storage class of the chunk's file
+    pub storage_class: String,
+    /// The chunk belongs to a restored copy of an archived object
+    pub restored_copy: bool,
 }
 
 /// Node storage summary view
@@ -326,6 +923,179 @@ pub struct NodeStorageSummary {
     pub last_heartbeat: Option<DateTime<Utc>>,
 }
 
+/// Summary of one rebalancer scan
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct ScanRecord {
+    pub id: i64,
+    pub scanned_at: DateTime<Utc>,
+    pub duration_ms: i64,
+    pub chunks_scanned: i64,
+    pub under_replicated: i64,
+    /// Under-replicated chunks with no healthy replica left
+    pub critical: i64,
+    pub over_replicated: i64,
+    pub orphaned: i64,
+    pub corrupt: i64,
+    /// Repairs held back while an outage stabilizes
+    pub deferred: i64,
+    pub incidents: i64,
+    /// Size of the under-replicated chunks
+    pub bytes_at_risk: i64,
+}
+
+/// Parameters for recording a rebalancer scan
+#[derive(Debug, Clone, Default)]
+pub struct CreateScanRecord {
+    pub duration_ms: i64,
+    pub chunks_scanned: i64,
+    pub under_replicated: i64,
+    pub critical: i64,
+    pub over_replicated: i64,
+    pub orphaned: i64,
+    pub corrupt: i64,
+    pub deferred: i64,
+    pub incidents: i64,
+    pub bytes_at_risk: i64,
+}
+
+/// Storage capacity summed over the cluster's nodes
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct ClusterCapacity {
+    pub storage_total: i64,
+    pub storage_reserved: i64,
+    pub storage_used: i64,
+    /// Capacity of online nodes only
+    pub online_storage_total: i64,
+    /// Space still writable on online nodes
+    pub online_storage_available: i64,
+}
+
+impl ClusterCapacity {
+    /// Used share of the allocatable (non-reserved) capacity, in percent
+    pub fn utilization_percent(&self) -> f64 {
+        let allocatable = self.storage_total - self.storage_reserved;
+        if allocatable <= 0 {
+            return 0.0;
+        }
+        self.storage_used as f64 / allocatable as f64 * 100.0
+    }
+}
+
+/// Write-ahead record of an in-flight upload
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct UploadIntent {
+    pub file_id: Uuid,
+    pub expected_chunks: i32,
+    pub expected_shards: i32,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    /// Client-supplied key identifying retries of this upload
+    pub idempotency_key: Option<String>,
+}
+
+/// Result of a published upload that was sent with an idempotency key
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct IdempotentUpload {
+    pub tenant_id: String,
+    pub idempotency_key: String,
+    pub file_id: Uuid,
+    /// `bucket/key` the upload was stored under
+    pub path: String,
+    pub content_hash: Vec<u8>,
+    pub size_bytes: i64,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Shard placement dispatched under an upload intent
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct UploadIntentShard {
+    pub file_id: Uuid,
+    pub chunk_id: Vec<u8>,
+    pub node_id: Uuid,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Shard of a deleted file waiting to be removed from its node
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct ShardGcEntry {
+    pub id: i64,
+    pub file_id: Uuid,
+    pub chunk_id: Vec<u8>,
+    pub node_id: Uuid,
+    pub attempts: i32,
+    pub enqueued_at: DateTime<Utc>,
+    /// Not retried before this
+    pub next_attempt_at: DateTime<Utc>,
+}
+
+/// Bucket replication rule
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct ReplicationRule {
+    pub id: Uuid,
+    pub tenant_id: String,
+    pub bucket: String,
+    pub prefix: String,
+    /// "cyxcloud" or "s3"
+    pub target_type: String,
+    pub endpoint: String,
+    pub target_bucket: String,
+    pub region: String,
+    #[serde(skip_serializing)]
+    pub access_token: Option<String>,
+    pub access_key_id: Option<String>,
+    #[serde(skip_serializing)]
+    pub secret_access_key: Option<String>,
+    pub replicate_deletes: bool,
+    pub enabled: bool,
+    pub cursor_updated_at: DateTime<Utc>,
+    pub cursor_file_id: Uuid,
+    pub last_run_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Parameters for creating a replication rule
+#[derive(Debug, Clone)]
+pub struct CreateReplicationRule {
+    /// Tenant owning the source bucket
+    pub tenant_id: String,
+    pub bucket: String,
+    pub prefix: String,
+    pub target_type: String,
+    pub endpoint: String,
+    pub target_bucket: String,
+    pub region: String,
+    pub access_token: Option<String>,
+    pub access_key_id: Option<String>,
+    pub secret_access_key: Option<String>,
+    pub replicate_deletes: bool,
+}
+
+/// Latest replication outcome for one object under a rule
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct ReplicationObject {
+    pub rule_id: Uuid,
+    pub path: String,
+    pub file_id: Uuid,
+    /// "put" or "delete"
+    pub operation: String,
+    /// "completed" or "failed"
+    pub status: String,
+    pub attempts: i32,
+    pub last_error: Option<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Aggregate replication progress for a rule
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReplicationStats {
+    pub completed: i64,
+    pub failed: i64,
+    /// File changes after the rule's cursor, not yet processed
+    pub pending: i64,
+}
+
 // =============================================================================
 // PAYMENT SYSTEM MODELS
 // =============================================================================
@@ -364,6 +1134,59 @@ impl NodeEpochUptime {
     }
 }
 
+/// Reputation assumed for nodes without history
+pub const NEUTRAL_REPUTATION: i32 = 5000;
+
+/// Decayed reputation signals of a node
+///
+/// A node without history scores [`NEUTRAL_REPUTATION`]. Score components (max 10000):
+/// - Verification pass rate: up to 4000 (pass rate with one prior pass and
+///   one prior failure, so new nodes start at 2000)
+/// - Heartbeat stability: up to 2500, halved with every offline transition
+/// - Latency: up to 2000, full at 50 ms challenge reads, none at 1 s and above
+///   (500 until measured)
+/// - Repair contribution: up to 1500, half reached at 10 completed repairs
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct NodeReputation {
+    pub node_id: Uuid,
+    pub verifications_passed: f64,
+    pub verifications_failed: f64,
+    pub repairs_completed: f64,
+    pub offline_events: f64,
+    pub read_latency_ms: Option<f64>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl NodeReputation {
+    /// Latency at or below which the latency component is full
+    const FAST_READ_MS: f64 = 50.0;
+    /// Latency at or above which the latency component is zero
+    const SLOW_READ_MS: f64 = 1000.0;
+
+    /// Reputation score (0-10000)
+    pub fn score(&self) -> i32 {
+        let checks = self.verifications_passed + self.verifications_failed;
+        let pass_rate = (self.verifications_passed + 1.0) / (checks + 2.0);
+        let verification = pass_rate * 4000.0;
+
+        let stability = 2500.0 / 2f64.powf(self.offline_events);
+
+        let latency = match self.read_latency_ms {
+            Some(ms) => {
+                let slowness =
+                    (ms - Self::FAST_READ_MS) / (Self::SLOW_READ_MS - Self::FAST_READ_MS);
+                (1.0 - slowness.clamp(0.0, 1.0)) * 2000.0
+            }
+            None => 500.0,
+        };
+
+        let repairs = self.repairs_completed.max(0.0);
+        let repair = 1500.0 * repairs / (repairs + 10.0);
+
+        ((verification + stability + latency + repair).round() as i32).clamp(0, 10000)
+    }
+}
+
 /// Slashing reason enumeration
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum SlashReason {
@@ -498,10 +1321,244 @@ impl NodeWeight {
     }
 }
 
+/// One node's line in an epoch payout report
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NodePayout {
+    pub node_id: Uuid,
+    pub peer_id: String,
+    pub wallet_address: Option<String>,
+    pub storage_bytes: i64,
+    pub uptime_seconds: i64,
+    pub uptime_factor: f64,
+    pub reputation: u16,
+    pub weight: u64,
+    pub reward: u64,
+    pub tx_signature: Option<String>,
+    /// `allocated`, `dry_run`, `no_wallet`, `failed` or `zero_reward`
+    pub status: String,
+}
+
+/// Evidence of a failed proof-of-storage challenge
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct SlashingEvidence {
+    pub id: Uuid,
+    pub node_id: Uuid,
+    pub chunk_id: Vec<u8>,
+    pub epoch: i64,
+    pub reason: String,
+    pub challenge: Vec<u8>,
+    pub expected_proof: Vec<u8>,
+    pub returned_proof: Option<Vec<u8>>,
+    pub error: Option<String>,
+    pub failure_count: i32,
+    pub first_failed_at: Option<DateTime<Utc>>,
+    pub last_verified: Option<DateTime<Utc>>,
+    pub challenged_at: DateTime<Utc>,
+    pub responded_at: Option<DateTime<Utc>>,
+    pub slashing_event_id: Option<Uuid>,
+    pub tx_signature: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Parameters for recording slashing evidence
+#[derive(Debug, Clone)]
+pub struct CreateSlashingEvidence {
+    pub node_id: Uuid,
+    pub chunk_id: Vec<u8>,
+    pub epoch: i64,
+    pub reason: SlashReason,
+    pub challenge: Vec<u8>,
+    pub expected_proof: Vec<u8>,
+    pub returned_proof: Option<Vec<u8>>,
+    pub error: Option<String>,
+    pub failure_count: i32,
+    pub first_failed_at: Option<DateTime<Utc>>,
+    pub last_verified: Option<DateTime<Utc>>,
+    pub challenged_at: DateTime<Utc>,
+    pub responded_at: Option<DateTime<Utc>>,
+    pub slashing_event_id: Option<Uuid>,
+    pub tx_signature: Option<String>,
+}
+
+/// Result of distributing one payment epoch
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct EpochPayoutReport {
+    pub epoch: i64,
+    pub total_pool_amount: i64,
+    pub nodes_share: i64,
+    pub platform_share: i64,
+    pub community_share: i64,
+    pub total_weight: i64,
+    pub nodes_paid: i32,
+    pub total_paid: i64,
+    /// Serialized `Vec<NodePayout>`
+    pub payouts: serde_json::Value,
+    pub dry_run: bool,
+    pub finalize_tx_signature: Option<String>,
+    pub platform_claim_tx_signature: Option<String>,
+    pub community_claim_tx_signature: Option<String>,
+    pub next_epoch_tx_signature: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn drain(total: i64, evacuated: i64, elapsed_secs: i64) -> NodeDrain {
+        let started_at = Utc::now() - chrono::Duration::seconds(600);
+        NodeDrain {
+            node_id: Uuid::new_v4(),
+            reason: None,
+            total_chunks: total,
+            total_bytes: total * 1000,
+            evacuated_chunks: evacuated,
+            evacuated_bytes: evacuated * 1000,
+            started_at,
+            updated_at: started_at + chrono::Duration::seconds(elapsed_secs),
+            completed_at: None,
+        }
+    }
+
+    #[test]
+    fn test_durability_mode() {
+        assert_eq!(
+            DurabilityMode::from_shard_replicas(1),
+            DurabilityMode::ErasureCoded
+        );
+        let replicated = DurabilityMode::from_shard_replicas(3);
+        assert_eq!(replicated, DurabilityMode::Replicated { replicas: 3 });
+        assert_eq!(replicated.shard_replicas(), 3);
+        assert!(replicated.validate().is_ok());
+        let invalid = |replicas| DurabilityMode::Replicated { replicas }.validate().is_err();
+        assert!(invalid(1));
+        assert!(invalid(MAX_SHARD_REPLICAS + 1));
+
+        let json = serde_json::to_value(replicated).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({"mode": "ec_replicated", "replicas": 3})
+        );
+        let parsed: DurabilityMode = serde_json::from_str(r#"{"mode": "ec"}"#).unwrap();
+        assert_eq!(parsed, DurabilityMode::ErasureCoded);
+    }
+
+    #[test]
+    fn test_cluster_capacity_utilization() {
+        let capacity = ClusterCapacity {
+            storage_total: 1_000,
+            storage_reserved: 200,
+            storage_used: 200,
+            online_storage_total: 1_000,
+            online_storage_available: 600,
+        };
+        assert_eq!(capacity.utilization_percent(), 25.0);
+
+        let empty = ClusterCapacity {
+            storage_total: 0,
+            storage_reserved: 0,
+            storage_used: 0,
+            online_storage_total: 0,
+            online_storage_available: 0,
+        };
+        assert_eq!(empty.utilization_percent(), 0.0);
+    }
+
+    #[test]
+    fn test_storage_mode() {
+        assert_eq!(StorageMode::default(), StorageMode::Erasure);
+        for mode in [StorageMode::Erasure, StorageMode::Replicated] {
+            let json = serde_json::to_value(mode).unwrap();
+            assert_eq!(json, serde_json::json!(mode.as_str()));
+        }
+    }
+
+    #[test]
+    fn test_retention_mode() {
+        for mode in [RetentionMode::Governance, RetentionMode::Compliance] {
+            assert_eq!(RetentionMode::from_str(mode.as_str()), Some(mode));
+            let json = serde_json::to_value(mode).unwrap();
+            assert_eq!(json, serde_json::json!(mode.as_str()));
+        }
+        assert_eq!(RetentionMode::from_str("governance"), None);
+    }
+
+    #[test]
+    fn test_storage_class() {
+        assert_eq!(StorageClass::default(), StorageClass::Standard);
+        for class in [StorageClass::Standard, StorageClass::Archive] {
+            assert_eq!(StorageClass::from_str(class.as_str()), Some(class));
+            let json = serde_json::to_value(class).unwrap();
+            assert_eq!(json, serde_json::json!(class.as_str()));
+        }
+        assert_eq!(StorageClass::from_str("GLACIER"), None);
+    }
+
+    #[test]
+    fn test_node_drain_eta() {
+        // Nothing evacuated yet: no throughput to extrapolate from
+        assert_eq!(drain(100, 0, 0).eta_secs(), None);
+
+        // 25 chunks (25 KB) in 50s -> 500 B/s, 75 KB left -> 150s
+        let d = drain(100, 25, 50);
+        assert_eq!(d.remaining_chunks(), 75);
+        assert_eq!(d.bytes_remaining(), 75_000);
+        assert_eq!(d.throughput_bps(), 500.0);
+        assert_eq!(d.eta_secs(), Some(150));
+
+        // Unknown chunk sizes use the chunk rate instead
+        let mut d = drain(100, 25, 50);
+        d.total_bytes = 0;
+        d.evacuated_bytes = 0;
+        assert_eq!(d.eta_secs(), Some(150));
+
+        assert!(drain(100, 100, 200).is_complete());
+        assert_eq!(drain(100, 100, 200).eta_secs(), Some(0));
+    }
+
+    #[test]
+    fn test_node_effective_bandwidth() {
+        let now = Utc::now();
+        let node = |reported: i32, measured: Option<i32>| Node {
+            id: Uuid::new_v4(),
+            peer_id: "node-1".to_string(),
+            grpc_address: "10.0.0.1:50051".to_string(),
+            storage_total: 1000,
+            storage_reserved: 0,
+            storage_used: 0,
+            bandwidth_mbps: reported,
+            measured_bandwidth_mbps: measured,
+            bandwidth_measured_at: measured.map(|_| now),
+            max_connections: 100,
+            disk_available: None,
+            datacenter: None,
+            rack: None,
+            region: None,
+            latitude: None,
+            longitude: None,
+            status: "online".to_string(),
+            last_heartbeat: Some(now),
+            failure_count: 0,
+            first_offline_at: None,
+            status_changed_at: None,
+            warmup_started_at: None,
+            reputation: 5000,
+            archival: false,
+            version: None,
+            created_at: now,
+            updated_at: now,
+            wallet_address: None,
+            public_key: None,
+        };
+
+        assert_eq!(node(1000, None).effective_bandwidth_mbps(), 1000);
+        // An over-reporting node gets what was measured
+        assert_eq!(node(1000, Some(80)).effective_bandwidth_mbps(), 80);
+        // A probe limited by the gateway's link does not raise a node
+        assert_eq!(node(100, Some(900)).effective_bandwidth_mbps(), 100);
+        assert_eq!(node(0, Some(250)).effective_bandwidth_mbps(), 250);
+    }
+
     const EPOCH_DURATION: i64 = 7 * 24 * 60 * 60; // 7 days in seconds
     const TB: i64 = 1_000_000_000_000; // 1 TB in bytes
 
@@ -702,6 +1759,85 @@ mod tests {
         );
         assert_eq!(SlashReason::from_str("invalid"), None);
     }
+
+    fn reputation() -> NodeReputation {
+        NodeReputation {
+            node_id: Uuid::new_v4(),
+            verifications_passed: 0.0,
+            verifications_failed: 0.0,
+            repairs_completed: 0.0,
+            offline_events: 0.0,
+            read_latency_ms: None,
+            updated_at: chrono::Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_reputation_new_node_is_neutral() {
+        assert_eq!(reputation().score(), NEUTRAL_REPUTATION);
+    }
+
+    #[test]
+    fn test_reputation_rewards_reliable_nodes() {
+        let reliable = NodeReputation {
+            verifications_passed: 200.0,
+            repairs_completed: 30.0,
+            read_latency_ms: Some(20.0),
+            ..reputation()
+        };
+        let flaky = NodeReputation {
+            verifications_passed: 100.0,
+            verifications_failed: 100.0,
+            offline_events: 3.0,
+            read_latency_ms: Some(1500.0),
+            ..reputation()
+        };
+
+        assert!(reliable.score() > 9000);
+        assert!(flaky.score() < 3000);
+        assert!(reliable.score() <= 10000);
+        assert!(flaky.score() >= 0);
+    }
+
+    #[test]
+    fn test_reputation_offline_events_halve_stability() {
+        let stable = reputation().score();
+        let once = NodeReputation {
+            offline_events: 1.0,
+            ..reputation()
+        };
+        assert_eq!(stable - once.score(), 1250);
+    }
+
+    #[test]
+    fn test_node_status_event_changes_placement() {
+        let event = |payload: &str| serde_json::from_str::<NodeStatusEvent>(payload).unwrap();
+
+        let offline = event(&format!(
+            r#"{{"node_id":"{}","old_status":"online","status":"offline"}}"#,
+            Uuid::new_v4()
+        ));
+        assert_eq!(offline.status.as_deref(), Some("offline"));
+        assert!(offline.changes_placement());
+
+        let registered = event(&format!(
+            r#"{{"node_id":"{}","old_status":null,"status":"online"}}"#,
+            Uuid::new_v4()
+        ));
+        assert!(registered.changes_placement());
+
+        let quarantined = event(&format!(
+            r#"{{"node_id":"{}","old_status":"offline","status":"recovering"}}"#,
+            Uuid::new_v4()
+        ));
+        assert!(!quarantined.changes_placement());
+
+        let removed = event(&format!(
+            r#"{{"node_id":"{}","old_status":"draining","status":null}}"#,
+            Uuid::new_v4()
+        ));
+        assert!(!removed.changes_placement());
+    }
 }
 
 // =============================================================================
@@ -3,11 +3,16 @@
 //! Provides CRUD operations and queries using SQLx.
 
 use crate::models::*;
-use sqlx::postgres::{PgPool, PgPoolOptions};
-use std::collections::HashMap;
+use cyxcloud_core::error::{ErrorCode, HasErrorCode};
+use futures::stream::{self, Stream, TryStreamExt};
+use sqlx::pool::PoolConnection;
+use sqlx::postgres::{PgConnection, PgListener, PgPool, PgPoolOptions, Postgres};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use std::time::Duration;
 use thiserror::Error;
-use tracing::{debug, info, instrument};
+use tracing::{debug, info, instrument, warn};
 use uuid::Uuid;
 
 /// Database error types
@@ -25,20 +30,78 @@ pub enum DbError {
     #[error("Invalid data: {0}")]
     Invalid(String),
 
+    #[error("Object is under retention: {0}")]
+    Locked(String),
+
+    #[error("Database busy: {0}")]
+    Busy(String),
+
     #[error("Migration error: {0}")]
     Migration(#[from] sqlx::migrate::MigrateError),
 }
 
+impl HasErrorCode for DbError {
+    fn error_code(&self) -> ErrorCode {
+        match self {
+            DbError::Sqlx(sqlx::Error::RowNotFound) | DbError::NotFound(_) => ErrorCode::NotFound,
+            e if e.is_connection_error() => ErrorCode::MetadataUnavailable,
+            DbError::Sqlx(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+                ErrorCode::AlreadyExists
+            }
+            DbError::Duplicate(_) => ErrorCode::AlreadyExists,
+            DbError::Invalid(_) => ErrorCode::InvalidArgument,
+            DbError::Locked(_) => ErrorCode::PermissionDenied,
+            DbError::Busy(_) => ErrorCode::ServiceUnavailable,
+            DbError::Sqlx(_) | DbError::Migration(_) => ErrorCode::Internal,
+        }
+    }
+}
+
+impl DbError {
+    /// Whether the error means the database could not be reached (rather
+    /// than rejecting the query), so that retrying later may succeed
+    ///
+    /// Besides pool and I/O failures this covers the errors a server that is
+    /// shutting down or restarting sends to its open sessions.
+    pub fn is_connection_error(&self) -> bool {
+        match self {
+            DbError::Sqlx(
+                sqlx::Error::PoolTimedOut
+                | sqlx::Error::PoolClosed
+                | sqlx::Error::Io(_)
+                | sqlx::Error::Tls(_)
+                | sqlx::Error::Protocol(_)
+                | sqlx::Error::WorkerCrashed,
+            ) => true,
+            DbError::Sqlx(sqlx::Error::Database(e)) => e.code().is_some_and(|code| {
+                // Class 08 (connection exception), admin/crash shutdown,
+                // cannot connect now (starting up or in recovery)
+                code.starts_with("08") || matches!(&*code, "57P01" | "57P02" | "57P03")
+            }),
+            _ => false,
+        }
+    }
+}
+
 pub type Result<T> = std::result::Result<T, DbError>;
 
 /// Database configuration
 #[derive(Debug, Clone)]
 pub struct DbConfig {
     pub url: String,
+    /// Read replica for lag-tolerant queries (dashboards, node lists)
+    pub read_url: Option<String>,
     pub max_connections: u32,
     pub min_connections: u32,
+    /// Connections for object write locks, which hold theirs for a whole
+    /// upload (a pool of its own)
+    pub max_lock_connections: u32,
     pub connect_timeout: Duration,
     pub idle_timeout: Duration,
+    /// Further attempts to reach the primary at startup, with backoff
+    pub connect_retries: u32,
+    /// How often the health monitor checks reachable pools
+    pub health_check_interval: Duration,
 }
 
 /// Fault tolerance threshold configuration
@@ -101,47 +164,397 @@ impl Default for DbConfig {
     fn default() -> Self {
         Self {
             url: "postgres://localhost/cyxcloud".to_string(),
+            read_url: None,
             max_connections: 10,
             min_connections: 2,
+            max_lock_connections: 20,
             connect_timeout: Duration::from_secs(5),
             idle_timeout: Duration::from_secs(300),
+            connect_retries: 3,
+            health_check_interval: Duration::from_secs(5),
+        }
+    }
+}
+
+/// Advisory lock serializing writes to one object path
+///
+/// The lock belongs to the session of a connection from the object lock
+/// pool, kept apart from the main pool so that long uploads cannot starve it.
+/// [`ObjectLock::release`] unlocks it and returns the connection; a lock
+/// dropped without release closes its connection instead, which ends the
+/// session and with it the lock.
+pub struct ObjectLock {
+    conn: Option<PoolConnection<Postgres>>,
+    key: i64,
+}
+
+impl ObjectLock {
+    /// Release the lock
+    pub async fn release(mut self) -> Result<()> {
+        let Some(mut conn) = self.conn.take() else {
+            return Ok(());
+        };
+        if let Err(e) = sqlx::query("SELECT pg_advisory_unlock($1)")
+            .bind(self.key)
+            .execute(&mut *conn)
+            .await
+        {
+            // Never hand a session that may still hold the lock back
+            drop(conn.detach());
+            return Err(e.into());
+        }
+        Ok(())
+    }
+}
+
+impl Drop for ObjectLock {
+    fn drop(&mut self) {
+        if let Some(conn) = self.conn.take() {
+            drop(conn.detach());
+        }
+    }
+}
+
+/// Advisory lock key of an object path within a tenant
+fn object_lock_key(tenant: &str, path: &str) -> i64 {
+    let mut input = Vec::with_capacity(tenant.len() + path.len() + 1);
+    input.extend_from_slice(tenant.as_bytes());
+    input.push(0);
+    input.extend_from_slice(path.as_bytes());
+    let hash = cyxcloud_core::ContentHash::compute(&input);
+    let mut key = [0u8; 8];
+    key.copy_from_slice(&hash.as_bytes()[..8]);
+    i64::from_le_bytes(key)
+}
+
+/// How often a waiting writer retries a held object lock
+const OBJECT_LOCK_RETRY: Duration = Duration::from_millis(50);
+
+/// How long the result of an upload sent with an idempotency key is kept
+pub const IDEMPOTENCY_KEY_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Channel a trigger on `nodes` notifies status and address changes on
+pub const NODE_STATUS_CHANNEL: &str = "cyxcloud_node_status";
+
+/// Migrations this binary ships, applied by [`Database::migrate`]
+static MIGRATOR: sqlx::migrate::Migrator = sqlx::migrate!("./migrations");
+
+/// How the migrations applied to the database compare to the ones this
+/// binary ships
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SchemaStatus {
+    /// Latest migration this binary ships
+    pub expected_version: i64,
+    /// Latest migration applied to the database
+    pub applied_version: Option<i64>,
+    /// Shipped migrations not applied yet
+    pub pending: Vec<i64>,
+    /// Applied migrations this binary does not know (a newer gateway
+    /// migrated the database)
+    pub unknown: Vec<i64>,
+    /// Migrations that failed part way
+    pub failed: Vec<i64>,
+    /// Applied migrations whose SQL differs from the shipped file
+    pub modified: Vec<i64>,
+}
+
+impl SchemaStatus {
+    /// Compare shipped `(version, checksum)` migrations with applied
+    /// `(version, success, checksum)` rows
+    pub fn compare(expected: &[(i64, Vec<u8>)], applied: &[(i64, bool, Vec<u8>)]) -> Self {
+        let shipped: HashMap<i64, &[u8]> = expected
+            .iter()
+            .map(|(version, checksum)| (*version, checksum.as_slice()))
+            .collect();
+        let applied_versions: HashSet<i64> =
+            applied.iter().map(|(version, _, _)| *version).collect();
+
+        let mut status = Self {
+            expected_version: expected.iter().map(|(v, _)| *v).max().unwrap_or(0),
+            applied_version: applied.iter().map(|(v, _, _)| *v).max(),
+            pending: expected
+                .iter()
+                .map(|(v, _)| *v)
+                .filter(|v| !applied_versions.contains(v))
+                .collect(),
+            ..Default::default()
+        };
+        for (version, success, checksum) in applied {
+            match shipped.get(version) {
+                None => status.unknown.push(*version),
+                Some(_) if !success => status.failed.push(*version),
+                Some(shipped) if *shipped != checksum.as_slice() => status.modified.push(*version),
+                Some(_) => {}
+            }
+        }
+        status.pending.sort_unstable();
+        status
+    }
+
+    /// Whether the database has exactly the shipped migrations
+    pub fn is_current(&self) -> bool {
+        self.pending.is_empty()
+            && self.unknown.is_empty()
+            && self.failed.is_empty()
+            && self.modified.is_empty()
+    }
+}
+
+impl std::fmt::Display for SchemaStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let applied = self
+            .applied_version
+            .map_or_else(|| "none".to_string(), |v| v.to_string());
+        write!(
+            f,
+            "schema version {}, binary expects {}",
+            applied, self.expected_version
+        )?;
+        for (what, versions) in [
+            ("pending", &self.pending),
+            ("unknown to this binary", &self.unknown),
+            ("failed", &self.failed),
+            ("modified", &self.modified),
+        ] {
+            if !versions.is_empty() {
+                write!(f, "; {}: {:?}", what, versions)?;
+            }
         }
+        Ok(())
     }
 }
 
+/// First and longest wait between reconnect attempts
+const RECONNECT_BACKOFF_MIN: Duration = Duration::from_millis(500);
+const RECONNECT_BACKOFF_MAX: Duration = Duration::from_secs(30);
+
+/// Wait before reconnect attempt `attempt` (0-based), doubling each time
+fn reconnect_backoff(attempt: u32) -> Duration {
+    RECONNECT_BACKOFF_MIN
+        .saturating_mul(2u32.saturating_pow(attempt))
+        .min(RECONNECT_BACKOFF_MAX)
+}
+
+/// Reachability of the primary and the read replica
+///
+/// Updated by the health monitor and used to route reads: lag-tolerant
+/// queries prefer the replica, and every read falls back to the replica
+/// while the primary is down.
+#[derive(Debug)]
+struct PoolHealth {
+    primary: AtomicBool,
+    replica: AtomicBool,
+}
+
 /// PostgreSQL database client
 #[derive(Clone)]
 pub struct Database {
     pool: PgPool,
+    replica: Option<PgPool>,
+    /// Connections holding object write locks
+    locks: PgPool,
+    health: Arc<PoolHealth>,
+    health_check_interval: Duration,
 }
 
 impl Database {
     /// Create a new database connection pool
+    ///
+    /// The primary is retried `connect_retries` times with exponential
+    /// backoff, so a gateway starting next to a restarting database waits
+    /// for it instead of giving up. The replica pool connects lazily and
+    /// only serves reads once the health monitor has reached it.
     pub async fn new(config: DbConfig) -> Result<Self> {
-        let pool = PgPoolOptions::new()
+        let options = PgPoolOptions::new()
             .max_connections(config.max_connections)
             .min_connections(config.min_connections)
             .acquire_timeout(config.connect_timeout)
             .idle_timeout(config.idle_timeout)
-            .connect(&config.url)
-            .await?;
-
+            .test_before_acquire(true);
+
+        let mut attempt = 0;
+        let pool = loop {
+            match options.clone().connect(&config.url).await {
+                Ok(pool) => break pool,
+                Err(e) => {
+                    let e = DbError::from(e);
+                    if attempt >= config.connect_retries || !e.is_connection_error() {
+                        return Err(e);
+                    }
+                    let wait = reconnect_backoff(attempt);
+                    warn!(
+                        error = %e,
+                        attempt = attempt + 1,
+                        retry_in = ?wait,
+                        "PostgreSQL not reachable, retrying"
+                    );
+                    tokio::time::sleep(wait).await;
+                    attempt += 1;
+                }
+            }
+        };
         info!("Connected to PostgreSQL database");
-        Ok(Self { pool })
+
+        // Object locks keep their connection for a whole upload, so they
+        // get a bounded pool of their own
+        let locks = PgPoolOptions::new()
+            .max_connections(config.max_lock_connections.max(1))
+            .min_connections(0)
+            .acquire_timeout(config.connect_timeout)
+            .idle_timeout(config.idle_timeout)
+            .connect_lazy_with((*pool.connect_options()).clone());
+
+        let replica = match config.read_url {
+            Some(ref url) => {
+                let replica = options.connect_lazy(url)?;
+                info!("PostgreSQL read replica configured");
+                Some(replica)
+            }
+            None => None,
+        };
+
+        Ok(Self {
+            pool,
+            replica,
+            locks,
+            health: Arc::new(PoolHealth {
+                primary: AtomicBool::new(true),
+                replica: AtomicBool::new(false),
+            }),
+            health_check_interval: config.health_check_interval,
+        })
     }
 
     /// Run migrations
     pub async fn migrate(&self) -> Result<()> {
-        sqlx::migrate!("./migrations").run(&self.pool).await?;
+        MIGRATOR.run(&self.pool).await?;
         info!("Database migrations complete");
         Ok(())
     }
 
+    /// Compare the applied migrations with the ones this binary ships
+    pub async fn schema_status(&self) -> Result<SchemaStatus> {
+        let expected: Vec<(i64, Vec<u8>)> = MIGRATOR
+            .iter()
+            .filter(|m| m.migration_type.is_up_migration())
+            .map(|m| (m.version, m.checksum.to_vec()))
+            .collect();
+
+        let table_exists: bool =
+            sqlx::query_scalar("SELECT to_regclass('_sqlx_migrations') IS NOT NULL")
+                .fetch_one(&self.pool)
+                .await?;
+        let applied = if table_exists {
+            sqlx::query_as::<_, (i64, bool, Vec<u8>)>(
+                "SELECT version, success, checksum FROM _sqlx_migrations ORDER BY version",
+            )
+            .fetch_all(&self.pool)
+            .await?
+        } else {
+            Vec::new()
+        };
+
+        Ok(SchemaStatus::compare(&expected, &applied))
+    }
+
     /// Get the connection pool
     pub fn pool(&self) -> &PgPool {
         &self.pool
     }
 
+    /// Check that a pooled connection can run a query
+    pub async fn ping(&self) -> Result<()> {
+        sqlx::query("SELECT 1").execute(&self.pool).await?;
+        Ok(())
+    }
+
+    /// Check the read replica, None when none is configured
+    pub async fn ping_replica(&self) -> Option<Result<()>> {
+        let replica = self.replica.as_ref()?;
+        Some(
+            sqlx::query("SELECT 1")
+                .execute(replica)
+                .await
+                .map(|_| ())
+                .map_err(DbError::from),
+        )
+    }
+
+    /// Whether the primary answered the last health check
+    pub fn primary_available(&self) -> bool {
+        self.health.primary.load(Ordering::Relaxed)
+    }
+
+    /// Whether a read replica is configured
+    pub fn has_replica(&self) -> bool {
+        self.replica.is_some()
+    }
+
+    /// Whether a read replica is configured and answered the last check
+    pub fn replica_available(&self) -> bool {
+        self.replica.is_some() && self.health.replica.load(Ordering::Relaxed)
+    }
+
+    /// Pool for reads that tolerate replication lag
+    fn read_pool(&self) -> &PgPool {
+        match self.replica {
+            Some(ref replica) if self.health.replica.load(Ordering::Relaxed) => replica,
+            _ => &self.pool,
+        }
+    }
+
+    /// Pool for reads that must see the latest writes, which only go to
+    /// the replica while the primary is down
+    fn consistent_read_pool(&self) -> &PgPool {
+        match self.replica {
+            Some(ref replica) if !self.primary_available() && self.replica_available() => replica,
+            _ => &self.pool,
+        }
+    }
+
+    /// Start checking the primary and the replica in the background
+    ///
+    /// Reachable pools are checked every `health_check_interval`. While one
+    /// is down it is retried with exponential backoff; the pool itself
+    /// reconnects on the next successful acquire.
+    pub fn start_health_monitor(&self) -> tokio::task::JoinHandle<()> {
+        let db = self.clone();
+        tokio::spawn(async move {
+            let mut failures = 0u32;
+            loop {
+                let primary = db.ping().await;
+                let replica = db.ping_replica().await;
+
+                let primary_up = primary.is_ok();
+                if primary_up != db.health.primary.swap(primary_up, Ordering::Relaxed) {
+                    match primary {
+                        Ok(()) => info!("PostgreSQL primary reachable again"),
+                        Err(ref e) => warn!(error = %e, "PostgreSQL primary unreachable"),
+                    }
+                }
+                if let Some(replica) = replica {
+                    let replica_up = replica.is_ok();
+                    if replica_up != db.health.replica.swap(replica_up, Ordering::Relaxed) {
+                        match replica {
+                            Ok(()) => info!("PostgreSQL read replica reachable"),
+                            Err(ref e) => warn!(error = %e, "PostgreSQL read replica unreachable"),
+                        }
+                    }
+                }
+
+                let all_up = primary_up && (!db.has_replica() || db.replica_available());
+                let wait = if all_up {
+                    failures = 0;
+                    db.health_check_interval
+                } else {
+                    failures = failures.saturating_add(1);
+                    reconnect_backoff(failures - 1)
+                };
+                tokio::time::sleep(wait).await;
+            }
+        })
+    }
+
     // =========================================================================
     // NODE OPERATIONS
     // =========================================================================
@@ -151,15 +564,18 @@ impl Database {
     pub async fn create_node(&self, node: CreateNode) -> Result<Node> {
         let result = sqlx::query_as::<_, Node>(
             r#"
-            INSERT INTO nodes (peer_id, grpc_address, storage_total, storage_reserved, bandwidth_mbps, datacenter, region, version, wallet_address, public_key, status)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, 'online')
+            INSERT INTO nodes (peer_id, grpc_address, storage_total, storage_reserved, bandwidth_mbps, datacenter, rack, region, latitude, longitude, version, wallet_address, public_key, status)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, 'online')
             ON CONFLICT (peer_id) DO UPDATE SET
                 grpc_address = EXCLUDED.grpc_address,
                 storage_total = EXCLUDED.storage_total,
                 storage_reserved = EXCLUDED.storage_reserved,
                 bandwidth_mbps = EXCLUDED.bandwidth_mbps,
                 datacenter = EXCLUDED.datacenter,
+                rack = EXCLUDED.rack,
                 region = EXCLUDED.region,
+                latitude = EXCLUDED.latitude,
+                longitude = EXCLUDED.longitude,
                 version = EXCLUDED.version,
                 wallet_address = COALESCE(EXCLUDED.wallet_address, nodes.wallet_address),
                 public_key = COALESCE(EXCLUDED.public_key, nodes.public_key),
@@ -175,7 +591,10 @@ impl Database {
         .bind(node.storage_reserved)
         .bind(node.bandwidth_mbps)
         .bind(&node.datacenter)
+        .bind(node.rack)
         .bind(&node.region)
+        .bind(node.latitude)
+        .bind(node.longitude)
         .bind(&node.version)
         .bind(&node.wallet_address)
         .bind(&node.public_key)
@@ -190,7 +609,7 @@ impl Database {
     pub async fn get_node(&self, id: Uuid) -> Result<Option<Node>> {
         let result = sqlx::query_as::<_, Node>("SELECT * FROM nodes WHERE id = $1")
             .bind(id)
-            .fetch_optional(&self.pool)
+            .fetch_optional(self.consistent_read_pool())
             .await?;
         Ok(result)
     }
@@ -204,23 +623,46 @@ impl Database {
         Ok(result)
     }
 
+    /// Get a node by the gRPC address it serves chunks on
+    pub async fn get_node_by_grpc_address(&self, grpc_address: &str) -> Result<Option<Node>> {
+        let result = sqlx::query_as::<_, Node>(
+            "SELECT * FROM nodes WHERE grpc_address = $1 ORDER BY last_heartbeat DESC NULLS LAST LIMIT 1",
+        )
+        .bind(grpc_address)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(result)
+    }
+
     /// Get all online nodes
+    ///
+    /// Read from the primary, so a list reloaded after a status
+    /// notification sees the change.
     pub async fn get_online_nodes(&self) -> Result<Vec<Node>> {
         let result = sqlx::query_as::<_, Node>(
             "SELECT * FROM nodes WHERE status = 'online' ORDER BY storage_used ASC",
         )
-        .fetch_all(&self.pool)
+        .fetch_all(self.consistent_read_pool())
         .await?;
         Ok(result)
     }
 
+    /// Listen for node status changes on [`NODE_STATUS_CHANNEL`]
+    ///
+    /// Each notification's payload is a JSON [`NodeStatusEvent`].
+    pub async fn listen_node_status(&self) -> Result<PgListener> {
+        let mut listener = PgListener::connect_with(&self.pool).await?;
+        listener.listen(NODE_STATUS_CHANNEL).await?;
+        Ok(listener)
+    }
+
     /// Get nodes by region
     pub async fn get_nodes_by_region(&self, region: &str) -> Result<Vec<Node>> {
         let result = sqlx::query_as::<_, Node>(
             "SELECT * FROM nodes WHERE region = $1 AND status = 'online'",
         )
         .bind(region)
-        .fetch_all(&self.pool)
+        .fetch_all(self.read_pool())
         .await?;
         Ok(result)
     }
@@ -261,6 +703,66 @@ impl Database {
         Ok(())
     }
 
+    /// Store the capacity a node reported with its heartbeat
+    ///
+    /// `storage_total` is kept when 0 (not reported), `disk_available` is
+    /// cleared when None. Returns `false` if no node has this peer ID.
+    pub async fn update_node_capacity(
+        &self,
+        peer_id: &str,
+        storage_total: i64,
+        storage_used: i64,
+        disk_available: Option<i64>,
+    ) -> Result<bool> {
+        let result = sqlx::query(
+            r#"
+            UPDATE nodes
+            SET storage_total = CASE WHEN $2 > 0 THEN $2 ELSE storage_total END,
+                storage_used = $3,
+                disk_available = $4
+            WHERE peer_id = $1
+            "#,
+        )
+        .bind(peer_id)
+        .bind(storage_total)
+        .bind(storage_used)
+        .bind(disk_available)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Store a node's location if it differs from the stored one
+    ///
+    /// Returns whether anything changed.
+    pub async fn update_node_location(
+        &self,
+        peer_id: &str,
+        location: &NodeLocation,
+    ) -> Result<bool> {
+        let result = sqlx::query(
+            r#"
+            UPDATE nodes
+            SET datacenter = $2, rack = $3, region = $4, latitude = $5, longitude = $6
+            WHERE peer_id = $1
+              AND (datacenter IS DISTINCT FROM $2
+                OR rack IS DISTINCT FROM $3
+                OR region IS DISTINCT FROM $4
+                OR latitude IS DISTINCT FROM $5
+                OR longitude IS DISTINCT FROM $6)
+            "#,
+        )
+        .bind(peer_id)
+        .bind(&location.datacenter)
+        .bind(location.rack)
+        .bind(&location.region)
+        .bind(location.latitude)
+        .bind(location.longitude)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
     /// Increment node failure count
     pub async fn increment_node_failures(&self, node_id: Uuid) -> Result<i32> {
         let result = sqlx::query_scalar::<_, i32>(
@@ -280,11 +782,110 @@ impl Database {
     /// Get node storage summary
     pub async fn get_node_storage_summary(&self) -> Result<Vec<NodeStorageSummary>> {
         let result = sqlx::query_as::<_, NodeStorageSummary>("SELECT * FROM node_storage_summary")
-            .fetch_all(&self.pool)
+            .fetch_all(self.read_pool())
             .await?;
         Ok(result)
     }
 
+    /// Number of nodes in each status
+    pub async fn count_nodes_by_status(&self) -> Result<HashMap<String, i64>> {
+        let rows = sqlx::query_as::<_, (String, i64)>(
+            "SELECT status, COUNT(*) FROM nodes GROUP BY status",
+        )
+        .fetch_all(self.read_pool())
+        .await?;
+        Ok(rows.into_iter().collect())
+    }
+
+    /// Storage capacity summed over all nodes and over online nodes
+    pub async fn get_cluster_capacity(&self) -> Result<ClusterCapacity> {
+        let result = sqlx::query_as::<_, ClusterCapacity>(
+            r#"
+            SELECT
+                COALESCE(SUM(storage_total), 0)::BIGINT AS storage_total,
+                COALESCE(SUM(storage_reserved), 0)::BIGINT AS storage_reserved,
+                COALESCE(SUM(storage_used), 0)::BIGINT AS storage_used,
+                COALESCE(SUM(storage_total) FILTER (WHERE status = 'online'), 0)::BIGINT
+                    AS online_storage_total,
+                COALESCE(
+                    SUM(LEAST(
+                        GREATEST(0, storage_total - storage_reserved - storage_used),
+                        disk_available
+                    )) FILTER (WHERE status = 'online'),
+                    0
+                )::BIGINT AS online_storage_available
+            FROM nodes
+            "#,
+        )
+        .fetch_one(self.read_pool())
+        .await?;
+        Ok(result)
+    }
+
+    /// Number of chunks in each replication health state (healthy,
+    /// under_replicated, missing)
+    pub async fn count_chunks_by_health(&self) -> Result<HashMap<String, i64>> {
+        let rows = sqlx::query_as::<_, (String, i64)>(
+            "SELECT health_status, COUNT(*) FROM chunk_replication_status GROUP BY health_status",
+        )
+        .fetch_all(self.read_pool())
+        .await?;
+        Ok(rows.into_iter().collect())
+    }
+
+    /// Store the load a node reported with its heartbeat
+    ///
+    /// Returns `false` if no node has this peer ID.
+    pub async fn record_node_load(&self, peer_id: &str, report: &NodeLoadReport) -> Result<bool> {
+        let result = sqlx::query(
+            r#"
+            INSERT INTO node_load (
+                node_id, cpu_usage, memory_usage, active_connections,
+                bytes_uploaded, bytes_downloaded
+            )
+            SELECT id, $2, $3, $4, $5, $6 FROM nodes WHERE peer_id = $1
+            ON CONFLICT (node_id) DO UPDATE SET
+                cpu_usage = EXCLUDED.cpu_usage,
+                memory_usage = EXCLUDED.memory_usage,
+                active_connections = EXCLUDED.active_connections,
+                throughput_bps = CASE
+                    WHEN EXCLUDED.bytes_uploaded >= node_load.bytes_uploaded
+                     AND EXCLUDED.bytes_downloaded >= node_load.bytes_downloaded
+                    THEN ((EXCLUDED.bytes_uploaded - node_load.bytes_uploaded
+                           + EXCLUDED.bytes_downloaded - node_load.bytes_downloaded)
+                          / GREATEST(EXTRACT(EPOCH FROM NOW() - node_load.updated_at), 1))::BIGINT
+                    ELSE 0
+                END,
+                bytes_uploaded = EXCLUDED.bytes_uploaded,
+                bytes_downloaded = EXCLUDED.bytes_downloaded,
+                updated_at = NOW()
+            "#,
+        )
+        .bind(peer_id)
+        .bind(report.cpu_usage)
+        .bind(report.memory_usage)
+        .bind(report.active_connections)
+        .bind(report.bytes_uploaded)
+        .bind(report.bytes_downloaded)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Get node loads reported within `max_age`
+    pub async fn get_node_loads(&self, max_age: Duration) -> Result<Vec<NodeLoad>> {
+        let result = sqlx::query_as::<_, NodeLoad>(
+            r#"
+            SELECT * FROM node_load
+            WHERE updated_at > NOW() - make_interval(secs => $1)
+            "#,
+        )
+        .bind(max_age.as_secs() as i64)
+        .fetch_all(self.read_pool())
+        .await?;
+        Ok(result)
+    }
+
     // =========================================================================
     // FAULT TOLERANCE OPERATIONS
     // =========================================================================
@@ -439,6 +1040,24 @@ impl Database {
         Ok(())
     }
 
+    /// Mark a node as in maintenance (planned downtime, excluded from placement)
+    #[instrument(skip(self))]
+    pub async fn mark_node_maintenance(&self, node_id: Uuid) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE nodes
+            SET status = 'maintenance',
+                status_changed_at = NOW()
+            WHERE id = $1
+            "#,
+        )
+        .bind(node_id)
+        .execute(&self.pool)
+        .await?;
+        debug!(node_id = %node_id, "Node marked as maintenance");
+        Ok(())
+    }
+
     /// Update node heartbeat with recovery-aware logic
     /// Returns the new status after the update
     #[instrument(skip(self))]
@@ -593,561 +1212,3715 @@ impl Database {
     /// Get all nodes (for health monitoring)
     pub async fn get_all_nodes(&self) -> Result<Vec<Node>> {
         let result = sqlx::query_as::<_, Node>("SELECT * FROM nodes ORDER BY created_at")
-            .fetch_all(&self.pool)
+            .fetch_all(self.read_pool())
             .await?;
         Ok(result)
     }
 
+    /// Count stored shards per node (for topology and distribution reporting)
+    pub async fn get_node_shard_counts(&self) -> Result<HashMap<Uuid, i64>> {
+        let rows = sqlx::query_as::<_, (Uuid, i64)>(
+            r#"
+            SELECT node_id, COUNT(*) FROM chunk_locations
+            WHERE status = 'stored'
+            GROUP BY node_id
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().collect())
+    }
+
     // =========================================================================
-    // FILE OPERATIONS
+    // NODE WARM-UP OPERATIONS
     // =========================================================================
 
-    /// Create a new file record
-    #[instrument(skip(self, file))]
-    pub async fn create_file(&self, file: CreateFile) -> Result<File> {
-        // Use provided ID or generate a new one
-        let file_id = file.id.unwrap_or_else(Uuid::new_v4);
-
-        let result = sqlx::query_as::<_, File>(
+    /// Get online nodes that are still in their warm-up phase
+    pub async fn get_warming_nodes(&self) -> Result<Vec<Node>> {
+        let result = sqlx::query_as::<_, Node>(
             r#"
-            INSERT INTO files (id, name, path, content_hash, size_bytes, chunk_count,
-                              data_shards, parity_shards, chunk_size, owner_id, bucket,
-                              content_type, metadata, status)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, 'pending')
-            RETURNING *
+            SELECT * FROM nodes
+            WHERE status = 'online' AND warmup_started_at IS NOT NULL
+            ORDER BY warmup_started_at
             "#,
         )
-        .bind(file_id)
-        .bind(&file.name)
-        .bind(&file.path)
-        .bind(&file.content_hash)
-        .bind(file.size_bytes)
-        .bind(file.chunk_count)
-        .bind(file.data_shards)
-        .bind(file.parity_shards)
-        .bind(file.chunk_size)
-        .bind(file.owner_id)
-        .bind(&file.bucket)
-        .bind(&file.content_type)
-        .bind(&file.metadata)
-        .fetch_one(&self.pool)
+        .fetch_all(&self.pool)
         .await?;
-
-        debug!(file_id = %result.id, path = %file.path, "File created");
         Ok(result)
     }
 
-    /// Get a file by ID
-    pub async fn get_file(&self, id: Uuid) -> Result<Option<File>> {
-        let result =
-            sqlx::query_as::<_, File>("SELECT * FROM files WHERE id = $1 AND deleted_at IS NULL")
-                .bind(id)
-                .fetch_optional(&self.pool)
-                .await?;
-        Ok(result)
+    /// Restart the warm-up ramp for a node (e.g. after a failed probe)
+    #[instrument(skip(self))]
+    pub async fn restart_node_warmup(&self, node_id: Uuid) -> Result<()> {
+        sqlx::query("UPDATE nodes SET warmup_started_at = NOW() WHERE id = $1")
+            .bind(node_id)
+            .execute(&self.pool)
+            .await?;
+        debug!(node_id = %node_id, "Node warm-up restarted");
+        Ok(())
     }
 
-    /// Get a file by path
-    pub async fn get_file_by_path(&self, path: &str) -> Result<Option<File>> {
-        let result =
-            sqlx::query_as::<_, File>("SELECT * FROM files WHERE path = $1 AND deleted_at IS NULL")
-                .bind(path)
-                .fetch_optional(&self.pool)
-                .await?;
-        Ok(result)
+    /// Mark a node as having completed warm-up (full placement weight)
+    #[instrument(skip(self))]
+    pub async fn complete_node_warmup(&self, node_id: Uuid) -> Result<()> {
+        sqlx::query("UPDATE nodes SET warmup_started_at = NULL WHERE id = $1")
+            .bind(node_id)
+            .execute(&self.pool)
+            .await?;
+        debug!(node_id = %node_id, "Node warm-up complete");
+        Ok(())
     }
 
-    /// List files in a bucket
-    pub async fn list_files_in_bucket(
+    // =========================================================================
+    // NODE BANDWIDTH OPERATIONS
+    // =========================================================================
+
+    /// Get online nodes whose bandwidth was never measured or not within
+    /// `max_age`, least recently measured first
+    pub async fn get_nodes_due_for_bandwidth_probe(
         &self,
-        bucket: &str,
-        prefix: Option<&str>,
+        max_age: Duration,
         limit: i64,
-        offset: i64,
-    ) -> Result<Vec<File>> {
-        let result = if let Some(prefix) = prefix {
-            sqlx::query_as::<_, File>(
-                r#"
-                SELECT * FROM files
-                WHERE bucket = $1 AND path LIKE $2 AND deleted_at IS NULL
-                ORDER BY path
-                LIMIT $3 OFFSET $4
-                "#,
-            )
-            .bind(bucket)
-            .bind(format!("{}%", prefix))
-            .bind(limit)
-            .bind(offset)
-            .fetch_all(&self.pool)
-            .await?
-        } else {
-            sqlx::query_as::<_, File>(
-                r#"
-                SELECT * FROM files
-                WHERE bucket = $1 AND deleted_at IS NULL
-                ORDER BY path
-                LIMIT $2 OFFSET $3
-                "#,
-            )
+    ) -> Result<Vec<Node>> {
+        let max_age_secs = max_age.as_secs() as i64;
+        let result = sqlx::query_as::<_, Node>(
+            r#"
+            SELECT * FROM nodes
+            WHERE status = 'online'
+              AND (bandwidth_measured_at IS NULL
+                   OR bandwidth_measured_at < NOW() - make_interval(secs => $1))
+            ORDER BY bandwidth_measured_at NULLS FIRST
+            LIMIT $2
+            "#,
+        )
+        .bind(max_age_secs)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(result)
+    }
+
+    /// Store the bandwidth a probe measured for a node
+    #[instrument(skip(self))]
+    pub async fn record_node_bandwidth(&self, node_id: Uuid, measured_mbps: i32) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE nodes SET measured_bandwidth_mbps = $2, bandwidth_measured_at = NOW()
+            WHERE id = $1
+            "#,
+        )
+        .bind(node_id)
+        .bind(measured_mbps.max(0))
+        .execute(&self.pool)
+        .await?;
+        debug!(node_id = %node_id, measured_mbps, "Node bandwidth measured");
+        Ok(())
+    }
+
+    /// Flag a node as archival (holding archive objects) or not
+    ///
+    /// Returns false if the node does not exist.
+    #[instrument(skip(self))]
+    pub async fn set_node_archival(&self, node_id: Uuid, archival: bool) -> Result<bool> {
+        let result =
+            sqlx::query("UPDATE nodes SET archival = $2, updated_at = NOW() WHERE id = $1")
+                .bind(node_id)
+                .bind(archival)
+                .execute(&self.pool)
+                .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    // =========================================================================
+    // REPUTATION OPERATIONS
+    // =========================================================================
+
+    /// Record a proof-of-storage challenge result for a node's reputation
+    ///
+    /// `latency_ms` is the time the node took to return the chunk; it feeds a
+    /// moving average (weight 0.2 for the new sample).
+    pub async fn record_node_verification(
+        &self,
+        node_id: Uuid,
+        passed: bool,
+        latency_ms: Option<f64>,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO node_reputation (node_id, verifications_passed, verifications_failed, read_latency_ms)
+            VALUES ($1, CASE WHEN $2 THEN 1 ELSE 0 END, CASE WHEN $2 THEN 0 ELSE 1 END, $3)
+            ON CONFLICT (node_id) DO UPDATE SET
+                verifications_passed = node_reputation.verifications_passed + EXCLUDED.verifications_passed,
+                verifications_failed = node_reputation.verifications_failed + EXCLUDED.verifications_failed,
+                read_latency_ms = CASE
+                    WHEN $3 IS NULL THEN node_reputation.read_latency_ms
+                    WHEN node_reputation.read_latency_ms IS NULL THEN $3
+                    ELSE node_reputation.read_latency_ms * 0.8 + $3 * 0.2
+                END
+            "#,
+        )
+        .bind(node_id)
+        .bind(passed)
+        .bind(latency_ms)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Count an offline transition against a node's reputation
+    pub async fn record_node_offline_event(&self, node_id: Uuid) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO node_reputation (node_id, offline_events)
+            VALUES ($1, 1)
+            ON CONFLICT (node_id) DO UPDATE SET
+                offline_events = node_reputation.offline_events + 1
+            "#,
+        )
+        .bind(node_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Decay reputation signals and store every node's reputation score
+    ///
+    /// Counters are halved every `half_life`, and repair jobs completed since
+    /// the last refresh (as source or target) are added. Returns the updated
+    /// signals.
+    #[instrument(skip(self))]
+    pub async fn refresh_node_reputations(
+        &self,
+        half_life: Duration,
+    ) -> Result<Vec<NodeReputation>> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO node_reputation (node_id)
+            SELECT id FROM nodes
+            ON CONFLICT (node_id) DO NOTHING
+            "#,
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        let reputations = sqlx::query_as::<_, NodeReputation>(
+            r#"
+            WITH decay AS (
+                SELECT r.node_id,
+                       POWER(0.5::float8, (EXTRACT(EPOCH FROM NOW() - r.updated_at) / $1)::float8) AS factor,
+                       (
+                           SELECT COUNT(*) FROM repair_jobs j
+                           WHERE j.status = 'completed' AND j.completed_at > r.updated_at
+                           AND (j.source_node_id = r.node_id OR j.target_node_id = r.node_id)
+                       )::float8 AS new_repairs
+                FROM node_reputation r
+            )
+            UPDATE node_reputation r
+            SET verifications_passed = r.verifications_passed * d.factor,
+                verifications_failed = r.verifications_failed * d.factor,
+                repairs_completed = r.repairs_completed * d.factor + d.new_repairs,
+                offline_events = r.offline_events * d.factor,
+                updated_at = NOW()
+            FROM decay d
+            WHERE r.node_id = d.node_id
+            RETURNING r.*
+            "#,
+        )
+        .bind(half_life.as_secs_f64().max(1.0))
+        .fetch_all(&mut *tx)
+        .await?;
+
+        for reputation in &reputations {
+            sqlx::query("UPDATE nodes SET reputation = $2 WHERE id = $1")
+                .bind(reputation.node_id)
+                .bind(reputation.score())
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await?;
+        debug!(nodes = reputations.len(), "Node reputations refreshed");
+        Ok(reputations)
+    }
+
+    // =========================================================================
+    // FILE OPERATIONS
+    // =========================================================================
+
+    /// Create a new file record
+    ///
+    /// A file without a Cache-Control gets its bucket's default one.
+    #[instrument(skip(self, file))]
+    pub async fn create_file(&self, file: CreateFile) -> Result<File> {
+        // Use provided ID or generate a new one
+        let file_id = file.id.unwrap_or_else(Uuid::new_v4);
+        let encryption = file.encryption.as_ref();
+
+        let result = sqlx::query_as::<_, File>(
+            r#"
+            INSERT INTO files (id, name, path, content_hash, size_bytes, chunk_count,
+                              data_shards, parity_shards, chunk_size, erasure_backend,
+                              storage_mode, owner_id, bucket, tenant_id, content_type, metadata,
+                              expires_at, sse_algorithm, sse_key_id, sse_data_key, cache_control,
+                              content_disposition, content_encoding, status)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17,
+                    $18, $19, $20,
+                    COALESCE($21, (SELECT default_cache_control FROM buckets
+                                   WHERE tenant_id = $14 AND name = $13)),
+                    $22, $23, 'pending')
+            RETURNING *
+            "#,
+        )
+        .bind(file_id)
+        .bind(&file.name)
+        .bind(&file.path)
+        .bind(&file.content_hash)
+        .bind(file.size_bytes)
+        .bind(file.chunk_count)
+        .bind(file.data_shards)
+        .bind(file.parity_shards)
+        .bind(file.chunk_size)
+        .bind(&file.erasure_backend)
+        .bind(file.storage_mode.as_str())
+        .bind(file.owner_id)
+        .bind(&file.bucket)
+        .bind(&file.tenant_id)
+        .bind(&file.content_type)
+        .bind(&file.metadata)
+        .bind(file.expires_at)
+        .bind(encryption.map(|e| e.algorithm.as_str()))
+        .bind(encryption.map(|e| e.key_id.as_str()))
+        .bind(encryption.map(|e| e.data_key.as_slice()))
+        .bind(&file.cache_control)
+        .bind(&file.content_disposition)
+        .bind(&file.content_encoding)
+        .fetch_one(&self.pool)
+        .await?;
+
+        debug!(file_id = %result.id, path = %file.path, "File created");
+        Ok(result)
+    }
+
+    /// Get a file by ID (deleted and expired files are excluded)
+    pub async fn get_file(&self, id: Uuid) -> Result<Option<File>> {
+        let result = sqlx::query_as::<_, File>(
+            r#"
+            SELECT * FROM files
+            WHERE id = $1 AND deleted_at IS NULL
+              AND (expires_at IS NULL OR expires_at > NOW())
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(self.consistent_read_pool())
+        .await?;
+        Ok(result)
+    }
+
+    /// Get the current version of a file by path within a tenant
+    ///
+    /// Uploads still in progress, deleted and expired files are excluded.
+    pub async fn get_file_by_path(&self, tenant: &str, path: &str) -> Result<Option<File>> {
+        let result = sqlx::query_as::<_, File>(
+            r#"
+            SELECT * FROM files
+            WHERE tenant_id = $1 AND path = $2 AND deleted_at IS NULL
+              AND status = 'complete'
+              AND (expires_at IS NULL OR expires_at > NOW())
+            ORDER BY created_at DESC
+            LIMIT 1
+            "#,
+        )
+        .bind(tenant)
+        .bind(path)
+        .fetch_optional(self.consistent_read_pool())
+        .await?;
+        Ok(result)
+    }
+
+    /// List files in a tenant's bucket, one page at a time
+    ///
+    /// Keys (paths relative to the bucket) are ordered bytewise, like S3, and
+    /// paged with a keyset cursor: pass the last key of the previous page as
+    /// `start_after`. With `reverse` the keys come in descending order and the
+    /// page continues below `start_after`. With `metadata_filter` only files
+    /// whose metadata contains that JSON object are listed.
+    pub async fn list_files_in_bucket(
+        &self,
+        tenant: &str,
+        bucket: &str,
+        prefix: Option<&str>,
+        start_after: Option<&str>,
+        limit: i64,
+        reverse: bool,
+        metadata_filter: Option<&serde_json::Value>,
+    ) -> Result<Vec<File>> {
+        // Files are stored as "{bucket}/{key}", so a key prefix is a path range
+        let lower = format!("{}/{}", bucket, prefix.unwrap_or_default());
+        let upper = prefix_upper_bound(&lower);
+        let (cursor_op, order) = if reverse { ("<", "DESC") } else { (">", "ASC") };
+
+        let mut sql = String::from(
+            r#"
+            SELECT * FROM files
+            WHERE tenant_id = $1 AND bucket = $2 AND deleted_at IS NULL
+              AND status = 'complete'
+              AND (expires_at IS NULL OR expires_at > NOW())
+              AND path COLLATE "C" >= $3
+            "#,
+        );
+        let mut next_param = 4;
+        if upper.is_some() {
+            sql.push_str(&format!("  AND path COLLATE \"C\" < ${}\n", next_param));
+            next_param += 1;
+        }
+        if start_after.is_some() {
+            sql.push_str(&format!(
+                "  AND path COLLATE \"C\" {} ${}\n",
+                cursor_op, next_param
+            ));
+            next_param += 1;
+        }
+        if metadata_filter.is_some() {
+            sql.push_str(&format!("  AND metadata @> ${}\n", next_param));
+            next_param += 1;
+        }
+        sql.push_str(&format!(
+            "ORDER BY path COLLATE \"C\" {} LIMIT ${}",
+            order, next_param
+        ));
+
+        let mut query = sqlx::query_as::<_, File>(&sql)
+            .bind(tenant)
             .bind(bucket)
+            .bind(&lower);
+        if let Some(upper) = upper {
+            query = query.bind(upper);
+        }
+        if let Some(cursor) = start_after {
+            query = query.bind(format!("{}/{}", bucket, cursor));
+        }
+        if let Some(filter) = metadata_filter {
+            query = query.bind(filter);
+        }
+        let result = query
             .bind(limit)
-            .bind(offset)
-            .fetch_all(&self.pool)
-            .await?
+            .fetch_all(self.consistent_read_pool())
+            .await?;
+        Ok(result)
+    }
+
+    /// List deleted files of a tenant's bucket that can still be restored
+    ///
+    /// Files deleted within the last `retention` are returned by key, the
+    /// most recently deleted version of each key first. Files that expired
+    /// rather than being deleted are not included.
+    pub async fn list_deleted_files(
+        &self,
+        tenant: &str,
+        bucket: &str,
+        prefix: Option<&str>,
+        retention: std::time::Duration,
+        limit: i64,
+    ) -> Result<Vec<File>> {
+        let lower = format!("{}/{}", bucket, prefix.unwrap_or_default());
+        let upper = prefix_upper_bound(&lower);
+
+        let result = sqlx::query_as::<_, File>(
+            r#"
+            SELECT * FROM files
+            WHERE tenant_id = $1 AND bucket = $2 AND status = 'deleted'
+              AND deleted_at > NOW() - make_interval(secs => $5)
+              AND path COLLATE "C" >= $3
+              AND ($4::TEXT IS NULL OR path COLLATE "C" < $4)
+            ORDER BY path COLLATE "C", deleted_at DESC
+            LIMIT $6
+            "#,
+        )
+        .bind(tenant)
+        .bind(bucket)
+        .bind(&lower)
+        .bind(upper)
+        .bind(retention.as_secs_f64())
+        .bind(limit)
+        .fetch_all(self.consistent_read_pool())
+        .await?;
+        Ok(result)
+    }
+
+    /// Restore a deleted file as the current version of its path
+    ///
+    /// Restores `file_id`, or else the most recently deleted version of the
+    /// path, if it was deleted within the last `retention`. Its shards are
+    /// taken off the GC queue in the same transaction. Fails with
+    /// [`DbError::Duplicate`] if the path has a current version. Returns
+    /// None if there is no restorable version.
+    #[instrument(skip(self))]
+    pub async fn restore_file(
+        &self,
+        tenant: &str,
+        path: &str,
+        file_id: Option<Uuid>,
+        retention: std::time::Duration,
+    ) -> Result<Option<File>> {
+        let mut tx = self.pool.begin().await?;
+
+        let Some(candidate) = sqlx::query_as::<_, File>(
+            r#"
+            SELECT * FROM files
+            WHERE tenant_id = $1 AND path = $2 AND status = 'deleted'
+              AND deleted_at > NOW() - make_interval(secs => $4)
+              AND ($3::UUID IS NULL OR id = $3)
+            ORDER BY deleted_at DESC
+            LIMIT 1
+            FOR UPDATE
+            "#,
+        )
+        .bind(tenant)
+        .bind(path)
+        .bind(file_id)
+        .bind(retention.as_secs_f64())
+        .fetch_optional(&mut *tx)
+        .await?
+        else {
+            return Ok(None);
         };
+
+        let current = sqlx::query_scalar::<_, bool>(
+            r#"
+            SELECT EXISTS(
+                SELECT 1 FROM files
+                WHERE tenant_id = $1 AND path = $2 AND deleted_at IS NULL
+                  AND status = 'complete'
+                  AND (expires_at IS NULL OR expires_at > NOW())
+            )
+            "#,
+        )
+        .bind(tenant)
+        .bind(path)
+        .fetch_one(&mut *tx)
+        .await?;
+        if current {
+            return Err(DbError::Duplicate(format!(
+                "{} has a current version",
+                path
+            )));
+        }
+
+        let restored = sqlx::query_as::<_, File>(
+            r#"
+            UPDATE files SET deleted_at = NULL, status = 'complete'
+            WHERE id = $1
+            RETURNING *
+            "#,
+        )
+        .bind(candidate.id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let unqueued = sqlx::query("DELETE FROM shard_gc_queue WHERE file_id = $1")
+            .bind(candidate.id)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+
+        tx.commit().await?;
+        debug!(file_id = %restored.id, shards = unqueued, "File restored");
+        Ok(Some(restored))
+    }
+
+    /// Stream every file in a tenant's bucket in key order
+    ///
+    /// For internal consumers that walk whole buckets (garbage collection,
+    /// replication backfill): files are fetched in keyset pages of
+    /// `page_size`, so the listing is never held in memory at once and no
+    /// connection stays checked out between pages.
+    pub fn stream_files_in_bucket<'a>(
+        &'a self,
+        tenant: &'a str,
+        bucket: &'a str,
+        prefix: Option<&'a str>,
+        page_size: i64,
+    ) -> impl Stream<Item = Result<File>> + Send + 'a {
+        let page_size = page_size.max(1);
+        let bucket_prefix = format!("{}/", bucket);
+
+        // State: the cursor to resume after, or None once the last page was read
+        stream::try_unfold(Some(None::<String>), move |cursor| {
+            let bucket_prefix = bucket_prefix.clone();
+            async move {
+                let Some(start_after) = cursor else {
+                    return Ok::<_, DbError>(None);
+                };
+
+                let page = self
+                    .list_files_in_bucket(
+                        tenant,
+                        bucket,
+                        prefix,
+                        start_after.as_deref(),
+                        page_size,
+                        false,
+                        None,
+                    )
+                    .await?;
+
+                let next = if (page.len() as i64) < page_size {
+                    None
+                } else {
+                    page.last().map(|f| {
+                        Some(
+                            f.path
+                                .strip_prefix(&bucket_prefix)
+                                .unwrap_or(&f.path)
+                                .to_string(),
+                        )
+                    })
+                };
+                Ok(Some((stream::iter(page.into_iter().map(Ok)), next)))
+            }
+        })
+        .try_flatten()
+    }
+
+    /// Update file status
+    pub async fn update_file_status(&self, file_id: Uuid, status: &str) -> Result<()> {
+        sqlx::query("UPDATE files SET status = $1 WHERE id = $2")
+            .bind(status)
+            .bind(file_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Take the write lock of an object path within a tenant
+    ///
+    /// Without `wait` this returns None right away if another writer holds
+    /// the lock; otherwise it retries until `wait` has passed. Fails with
+    /// [`DbError::Busy`] when every object lock connection stays in use.
+    pub async fn lock_object(
+        &self,
+        tenant: &str,
+        path: &str,
+        wait: Option<Duration>,
+    ) -> Result<Option<ObjectLock>> {
+        let key = object_lock_key(tenant, path);
+        let deadline = wait.map(|wait| tokio::time::Instant::now() + wait);
+        let mut conn = self.locks.acquire().await.map_err(|e| match e {
+            sqlx::Error::PoolTimedOut => {
+                DbError::Busy("no connection free for object locks".to_string())
+            }
+            e => DbError::from(e),
+        })?;
+
+        loop {
+            let locked = sqlx::query_scalar::<_, bool>("SELECT pg_try_advisory_lock($1)")
+                .bind(key)
+                .fetch_one(&mut *conn)
+                .await?;
+            if locked {
+                return Ok(Some(ObjectLock {
+                    conn: Some(conn),
+                    key,
+                }));
+            }
+            match deadline {
+                Some(deadline) if tokio::time::Instant::now() < deadline => {
+                    tokio::time::sleep(OBJECT_LOCK_RETRY).await;
+                }
+                _ => return Ok(None),
+            }
+        }
+    }
+
+    /// Whether a writer currently holds the write lock of an object path
+    pub async fn object_lock_held(&self, tenant: &str, path: &str) -> Result<bool> {
+        // A bigint advisory key is split into classid (high half) and objid
+        let key = object_lock_key(tenant, path) as u64;
+        let held = sqlx::query_scalar::<_, bool>(
+            r#"
+            SELECT EXISTS (
+                SELECT 1 FROM pg_locks
+                WHERE locktype = 'advisory'
+                AND database = (SELECT oid FROM pg_database WHERE datname = current_database())
+                AND granted
+                AND classid = $1::BIGINT::OID
+                AND objid = $2::BIGINT::OID
+                AND objsubid = 1
+            )
+            "#,
+        )
+        .bind((key >> 32) as i64)
+        .bind((key & 0xffff_ffff) as i64)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(held)
+    }
+
+    /// Record the content of a file that was uploaded as a stream
+    ///
+    /// A streamed upload only knows its hash, size and chunk count once the
+    /// last byte arrived, after its file record was created. Only pending
+    /// files can be updated.
+    pub async fn set_file_content(
+        &self,
+        file_id: Uuid,
+        content_hash: &[u8],
+        size_bytes: i64,
+        chunk_count: i32,
+    ) -> Result<()> {
+        let result = sqlx::query(
+            r#"
+            UPDATE files SET content_hash = $2, size_bytes = $3, chunk_count = $4
+            WHERE id = $1 AND status = 'pending' AND deleted_at IS NULL
+            "#,
+        )
+        .bind(file_id)
+        .bind(content_hash)
+        .bind(size_bytes)
+        .bind(chunk_count)
+        .execute(&self.pool)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(DbError::NotFound(format!("pending file {}", file_id)));
+        }
+        Ok(())
+    }
+
+    /// Publish an uploaded file as the current version of its path
+    ///
+    /// In one transaction the file is marked complete, its upload intent is
+    /// cleared (recording its idempotency key, if any), and the older
+    /// versions of the path are soft-deleted with their shards queued for
+    /// removal. The file gets its bucket's default retention, if any. Fails
+    /// with [`DbError::Duplicate`] and changes nothing if a newer version was
+    /// published in the meantime, and with [`DbError::Locked`] if the current
+    /// version is under retention. Returns the versions that were replaced.
+    pub async fn publish_file_version(&self, file_id: Uuid) -> Result<Vec<File>> {
+        let mut tx = self.pool.begin().await?;
+
+        let file = sqlx::query_as::<_, File>(
+            r#"
+            UPDATE files SET status = 'complete'
+            WHERE id = $1 AND deleted_at IS NULL
+            RETURNING *
+            "#,
+        )
+        .bind(file_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| DbError::NotFound(format!("file {}", file_id)))?;
+
+        let locked = sqlx::query_scalar::<_, bool>(
+            r#"
+            SELECT EXISTS(
+                SELECT 1 FROM files
+                WHERE tenant_id = $1 AND path = $2 AND id <> $3
+                  AND deleted_at IS NULL AND status = 'complete' AND retain_until > NOW()
+            )
+            "#,
+        )
+        .bind(&file.tenant_id)
+        .bind(&file.path)
+        .bind(file.id)
+        .fetch_one(&mut *tx)
+        .await?;
+        if locked {
+            return Err(DbError::Locked(file.path));
+        }
+
+        sqlx::query(
+            r#"
+            UPDATE files f
+            SET lock_mode = b.object_lock_mode,
+                retain_until = NOW() + make_interval(days => b.object_lock_days)
+            FROM buckets b
+            WHERE f.id = $1 AND b.tenant_id = f.tenant_id AND b.name = f.bucket
+              AND b.object_lock_enabled AND b.object_lock_mode IS NOT NULL
+              AND b.object_lock_days IS NOT NULL
+            "#,
+        )
+        .bind(file.id)
+        .execute(&mut *tx)
+        .await?;
+
+        let newer = sqlx::query_scalar::<_, bool>(
+            r#"
+            SELECT EXISTS(
+                SELECT 1 FROM files
+                WHERE tenant_id = $1 AND path = $2 AND id <> $3
+                  AND deleted_at IS NULL AND status = 'complete' AND created_at > $4
+            )
+            "#,
+        )
+        .bind(&file.tenant_id)
+        .bind(&file.path)
+        .bind(file.id)
+        .bind(file.created_at)
+        .fetch_one(&mut *tx)
+        .await?;
+        if newer {
+            return Err(DbError::Duplicate(format!(
+                "a newer version of {} was written",
+                file.path
+            )));
+        }
+
+        let replaced = sqlx::query_as::<_, File>(
+            r#"
+            UPDATE files
+            SET deleted_at = NOW(), status = 'deleted'
+            WHERE tenant_id = $1 AND path = $2 AND id <> $3
+              AND deleted_at IS NULL AND status = 'complete'
+            RETURNING *
+            "#,
+        )
+        .bind(&file.tenant_id)
+        .bind(&file.path)
+        .bind(file.id)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        if !replaced.is_empty() {
+            let file_ids: Vec<Uuid> = replaced.iter().map(|f| f.id).collect();
+            sqlx::query(
+                r#"
+                INSERT INTO shard_gc_queue (file_id, chunk_id, node_id)
+                SELECT c.file_id, cl.chunk_id, cl.node_id
+                FROM chunks c
+                JOIN chunk_locations cl ON cl.chunk_id = c.chunk_id
+                WHERE c.file_id = ANY($1)
+                ON CONFLICT (chunk_id, node_id) DO NOTHING
+                "#,
+            )
+            .bind(&file_ids)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        sqlx::query(
+            r#"
+            INSERT INTO upload_idempotency_keys
+                (tenant_id, idempotency_key, file_id, path, content_hash, size_bytes, expires_at)
+            SELECT f.tenant_id, ui.idempotency_key, f.id, f.path, f.content_hash, f.size_bytes,
+                   NOW() + make_interval(secs => $2)
+            FROM upload_intents ui
+            JOIN files f ON f.id = ui.file_id
+            WHERE ui.file_id = $1 AND ui.idempotency_key IS NOT NULL
+            ON CONFLICT (tenant_id, idempotency_key) DO UPDATE SET
+                file_id = EXCLUDED.file_id,
+                path = EXCLUDED.path,
+                content_hash = EXCLUDED.content_hash,
+                size_bytes = EXCLUDED.size_bytes,
+                created_at = NOW(),
+                expires_at = EXCLUDED.expires_at
+            "#,
+        )
+        .bind(file_id)
+        .bind(IDEMPOTENCY_KEY_TTL.as_secs_f64())
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query("DELETE FROM upload_intents WHERE file_id = $1")
+            .bind(file_id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        debug!(file_id = %file_id, replaced = replaced.len(), "File version published");
+        Ok(replaced)
+    }
+
+    /// Set the retention of a file, extending but never shortening it
+    ///
+    /// A file whose retention has passed can get any new one. While it is
+    /// under retention the new time must not be earlier and a compliance
+    /// lock cannot become a governance lock. Returns false if the file is
+    /// not a current version or the change would weaken its retention.
+    pub async fn set_file_retention(
+        &self,
+        file_id: Uuid,
+        mode: RetentionMode,
+        retain_until: chrono::DateTime<chrono::Utc>,
+    ) -> Result<bool> {
+        let result = sqlx::query(
+            r#"
+            UPDATE files SET lock_mode = $2, retain_until = $3
+            WHERE id = $1 AND deleted_at IS NULL AND status = 'complete'
+              AND (retain_until IS NULL OR retain_until <= NOW()
+                   OR ($3 >= retain_until
+                       AND NOT (lock_mode = 'COMPLIANCE' AND $2 = 'GOVERNANCE')))
+            "#,
+        )
+        .bind(file_id)
+        .bind(mode.as_str())
+        .bind(retain_until)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Set whether anyone may read a file version
+    ///
+    /// Returns false if the file is not a current version.
+    pub async fn set_file_public_read(&self, file_id: Uuid, public_read: bool) -> Result<bool> {
+        let result = sqlx::query(
+            r#"
+            UPDATE files SET public_read = $2
+            WHERE id = $1 AND deleted_at IS NULL AND status = 'complete'
+            "#,
+        )
+        .bind(file_id)
+        .bind(public_read)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Encrypted files whose data key is wrapped by another master key
+    /// than `key_id`, in ID order after `after`
+    ///
+    /// Deleted versions are included, since they can still be restored.
+    pub async fn list_files_to_rewrap(
+        &self,
+        key_id: &str,
+        after: Option<Uuid>,
+        limit: i64,
+    ) -> Result<Vec<File>> {
+        let result = sqlx::query_as::<_, File>(
+            r#"
+            SELECT * FROM files
+            WHERE sse_data_key IS NOT NULL AND sse_key_id <> $1
+              AND ($2::uuid IS NULL OR id > $2)
+            ORDER BY id
+            LIMIT $3
+            "#,
+        )
+        .bind(key_id)
+        .bind(after)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(result)
+    }
+
+    /// Replace the wrapped data key of a file, if it is still wrapped by
+    /// `old_key_id`
+    ///
+    /// The data key itself is unchanged, so the object's data and its
+    /// modification time stay as they are.
+    pub async fn set_file_data_key(
+        &self,
+        file_id: Uuid,
+        old_key_id: &str,
+        key_id: &str,
+        data_key: &[u8],
+    ) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE files SET sse_key_id = $3, sse_data_key = $4 WHERE id = $1 AND sse_key_id = $2",
+        )
+        .bind(file_id)
+        .bind(old_key_id)
+        .bind(key_id)
+        .bind(data_key)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Soft delete a file
+    pub async fn delete_file(&self, file_id: Uuid) -> Result<()> {
+        sqlx::query("UPDATE files SET deleted_at = NOW(), status = 'deleted' WHERE id = $1")
+            .bind(file_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Soft-delete files by path in one statement and queue their shards
+    ///
+    /// The shards of every deleted file are added to the shard GC queue in
+    /// the same transaction. Paths that do not exist (or are already deleted)
+    /// are skipped. Versions under retention are kept, unless they are in
+    /// governance mode and `bypass_governance` is set, and their paths are
+    /// returned as locked.
+    #[instrument(skip(self, paths), fields(paths = paths.len()))]
+    pub async fn delete_files_by_path(
+        &self,
+        tenant: &str,
+        paths: &[String],
+        bypass_governance: bool,
+    ) -> Result<DeletedFiles> {
+        let mut tx = self.pool.begin().await?;
+
+        let deleted = sqlx::query_as::<_, File>(
+            r#"
+            UPDATE files
+            SET deleted_at = NOW(), status = 'deleted'
+            WHERE tenant_id = $1 AND path = ANY($2) AND deleted_at IS NULL
+              AND NOT COALESCE(retain_until > NOW() AND NOT ($3 AND lock_mode = 'GOVERNANCE'), FALSE)
+            RETURNING *
+            "#,
+        )
+        .bind(tenant)
+        .bind(paths)
+        .bind(bypass_governance)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let locked = sqlx::query_scalar::<_, String>(
+            r#"
+            SELECT DISTINCT path FROM files
+            WHERE tenant_id = $1 AND path = ANY($2) AND deleted_at IS NULL
+              AND retain_until > NOW()
+            "#,
+        )
+        .bind(tenant)
+        .bind(paths)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        if !deleted.is_empty() {
+            let file_ids: Vec<Uuid> = deleted.iter().map(|f| f.id).collect();
+            let queued = sqlx::query(
+                r#"
+                INSERT INTO shard_gc_queue (file_id, chunk_id, node_id)
+                SELECT c.file_id, cl.chunk_id, cl.node_id
+                FROM chunks c
+                JOIN chunk_locations cl ON cl.chunk_id = c.chunk_id
+                WHERE c.file_id = ANY($1)
+                ON CONFLICT (chunk_id, node_id) DO NOTHING
+                "#,
+            )
+            .bind(&file_ids)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+            debug!(
+                files = deleted.len(),
+                shards = queued,
+                "Queued shards of deleted files"
+            );
+        }
+
+        tx.commit().await?;
+        Ok(DeletedFiles { deleted, locked })
+    }
+
+    /// Oldest entries of the shard GC queue
+    ///
+    /// Only entries queued at least `min_age` ago are returned, so deleted
+    /// files can be restored until then. Shards of files under retention
+    /// stay queued until it has passed.
+    pub async fn get_shard_gc_batch(
+        &self,
+        limit: i64,
+        min_age: std::time::Duration,
+    ) -> Result<Vec<ShardGcEntry>> {
+        let result = sqlx::query_as::<_, ShardGcEntry>(
+            r#"
+            SELECT q.* FROM shard_gc_queue q
+            WHERE q.enqueued_at <= NOW() - make_interval(secs => $2)
+              AND q.next_attempt_at <= NOW()
+              AND NOT EXISTS (
+                  SELECT 1 FROM files f
+                  WHERE f.id = q.file_id AND f.retain_until > NOW()
+              )
+            ORDER BY q.enqueued_at, q.id
+            LIMIT $1
+            "#,
+        )
+        .bind(limit)
+        .bind(min_age.as_secs_f64())
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(result)
+    }
+
+    /// Record the outcome of a shard GC batch
+    ///
+    /// Removed shards leave the queue along with their chunk locations.
+    /// Failed entries are retried on the next batch until they have failed
+    /// `max_attempts` times, then only once per `retry_after`; they are never
+    /// dropped, so the shards are not forgotten. Returns how many failed
+    /// entries have failed `max_attempts` times or more.
+    pub async fn finish_shard_gc(
+        &self,
+        removed: &[i64],
+        failed: &[i64],
+        max_attempts: i32,
+        retry_after: std::time::Duration,
+    ) -> Result<u64> {
+        let mut tx = self.pool.begin().await?;
+
+        if !removed.is_empty() {
+            sqlx::query(
+                r#"
+                WITH done AS (
+                    DELETE FROM shard_gc_queue WHERE id = ANY($1)
+                    RETURNING chunk_id, node_id
+                )
+                DELETE FROM chunk_locations cl
+                USING done
+                WHERE cl.chunk_id = done.chunk_id AND cl.node_id = done.node_id
+                "#,
+            )
+            .bind(removed)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        let mut exhausted = 0;
+        if !failed.is_empty() {
+            exhausted = sqlx::query_scalar::<_, i32>(
+                r#"
+                UPDATE shard_gc_queue
+                SET attempts = attempts + 1,
+                    next_attempt_at = CASE
+                        WHEN attempts + 1 >= $2 THEN NOW() + make_interval(secs => $3)
+                        ELSE NOW()
+                    END
+                WHERE id = ANY($1)
+                RETURNING attempts
+                "#,
+            )
+            .bind(failed)
+            .bind(max_attempts)
+            .bind(retry_after.as_secs_f64())
+            .fetch_all(&mut *tx)
+            .await?
+            .iter()
+            .filter(|&&attempts| attempts >= max_attempts)
+            .count() as u64;
+        }
+
+        tx.commit().await?;
+        Ok(exhausted)
+    }
+
+    /// Number of shard GC entries that have failed `max_attempts` times or
+    /// more
+    pub async fn count_stuck_shard_gc(&self, max_attempts: i32) -> Result<i64> {
+        let count = sqlx::query_scalar::<_, i64>(
+            "SELECT COUNT(*) FROM shard_gc_queue WHERE attempts >= $1",
+        )
+        .bind(max_attempts)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(count)
+    }
+
+    /// Get files that have passed their expiry time
+    ///
+    /// Files under retention are left out until it has passed.
+    pub async fn get_expired_files(&self, limit: i64) -> Result<Vec<File>> {
+        let result = sqlx::query_as::<_, File>(
+            r#"
+            SELECT * FROM files
+            WHERE expires_at <= NOW()
+              AND (retain_until IS NULL OR retain_until <= NOW())
+            ORDER BY expires_at
+            LIMIT $1
+            "#,
+        )
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(result)
+    }
+
+    /// Stored bytes (data plus parity) of files created since `since`
+    pub async fn get_stored_bytes_since(
+        &self,
+        since: chrono::DateTime<chrono::Utc>,
+    ) -> Result<i64> {
+        let result = sqlx::query_scalar::<_, i64>(
+            r#"
+            SELECT COALESCE(
+                SUM(size_bytes * (data_shards + parity_shards) / data_shards), 0
+            )::BIGINT
+            FROM files
+            WHERE created_at >= $1 AND deleted_at IS NULL AND data_shards > 0
+            "#,
+        )
+        .bind(since)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(result)
+    }
+
+    // =========================================================================
+    // CHUNK OPERATIONS
+    // =========================================================================
+
+    /// Create a new chunk record
+    #[instrument(skip(self, chunk))]
+    pub async fn create_chunk(&self, chunk: CreateChunk) -> Result<Chunk> {
+        let result = sqlx::query_as::<_, Chunk>(
+            r#"
+            INSERT INTO chunks (chunk_id, file_id, chunk_index, shard_index, is_parity, size_bytes, replication_factor)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            RETURNING *
+            "#,
+        )
+        .bind(&chunk.chunk_id)
+        .bind(chunk.file_id)
+        .bind(chunk.chunk_index)
+        .bind(chunk.shard_index)
+        .bind(chunk.is_parity)
+        .bind(chunk.size_bytes)
+        .bind(chunk.replication_factor)
+        .fetch_one(&self.pool)
+        .await?;
+
+        debug!(chunk_id = ?chunk.chunk_id, file_id = %chunk.file_id, "Chunk created");
+        Ok(result)
+    }
+
+    /// Get a chunk by chunk_id
+    pub async fn get_chunk_by_id(&self, chunk_id: &[u8]) -> Result<Option<Chunk>> {
+        let result = sqlx::query_as::<_, Chunk>("SELECT * FROM chunks WHERE chunk_id = $1")
+            .bind(chunk_id)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(result)
+    }
+
+    /// Get all chunks for a file
+    pub async fn get_file_chunks(&self, file_id: Uuid) -> Result<Vec<Chunk>> {
+        let result = sqlx::query_as::<_, Chunk>(
+            "SELECT * FROM chunks WHERE file_id = $1 ORDER BY chunk_index, shard_index",
+        )
+        .bind(file_id)
+        .fetch_all(self.consistent_read_pool())
+        .await?;
+        Ok(result)
+    }
+
+    /// Update chunk status
+    pub async fn update_chunk_status(&self, chunk_id: &[u8], status: &str) -> Result<()> {
+        sqlx::query("UPDATE chunks SET status = $1 WHERE chunk_id = $2")
+            .bind(status)
+            .bind(chunk_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Update chunk replica count
+    pub async fn update_chunk_replicas(&self, chunk_id: &[u8], count: i32) -> Result<()> {
+        sqlx::query("UPDATE chunks SET current_replicas = $1 WHERE chunk_id = $2")
+            .bind(count)
+            .bind(chunk_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Increment chunk replica count
+    pub async fn increment_chunk_replicas(&self, chunk_id: &[u8]) -> Result<i32> {
+        let result = sqlx::query_scalar::<_, i32>(
+            r#"
+            UPDATE chunks
+            SET current_replicas = current_replicas + 1
+            WHERE chunk_id = $1
+            RETURNING current_replicas
+            "#,
+        )
+        .bind(chunk_id)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(result)
+    }
+
+    /// Get under-replicated chunks, most at risk of permanent loss first
+    ///
+    /// Chunks with the fewest safe copies come first, then those whose
+    /// stripe has the fewest spare shards (whole chunks have none), then
+    /// those missing the most replicas (see migration 047).
+    pub async fn get_under_replicated_chunks(
+        &self,
+        limit: i64,
+    ) -> Result<Vec<ChunkReplicationStatus>> {
+        let result = sqlx::query_as::<_, ChunkReplicationStatus>(
+            r#"
+            SELECT * FROM chunk_repair_risk
+            WHERE replicas_needed > 0
+            ORDER BY safe_replicas, COALESCE(stripe_spare, 0), replicas_needed DESC
+            LIMIT $1
+            "#,
+        )
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(result)
+    }
+
+    /// Get under-replicated chunks whose keyspace slot is in
+    /// `range_start..range_end` (see migration 031), most at risk first
+    pub async fn get_under_replicated_chunks_in_range(
+        &self,
+        limit: i64,
+        range_start: i32,
+        range_end: i32,
+    ) -> Result<Vec<ChunkReplicationStatus>> {
+        let result = sqlx::query_as::<_, ChunkReplicationStatus>(
+            r#"
+            SELECT * FROM chunk_repair_risk
+            WHERE replicas_needed > 0
+            AND chunk_keyspace_slot(chunk_id) >= $2
+            AND chunk_keyspace_slot(chunk_id) < $3
+            ORDER BY safe_replicas, COALESCE(stripe_spare, 0), replicas_needed DESC
+            LIMIT $1
+            "#,
+        )
+        .bind(limit)
+        .bind(range_start)
+        .bind(range_end)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(result)
+    }
+
+    /// Get under-replicated chunks in `range_start..range_end` whose
+    /// replication state changed at or after `since`, plus those whose ID is
+    /// in `recheck` (see migration 043)
+    pub async fn get_changed_under_replicated_chunks_in_range(
+        &self,
+        since: chrono::DateTime<chrono::Utc>,
+        recheck: &[Vec<u8>],
+        limit: i64,
+        range_start: i32,
+        range_end: i32,
+    ) -> Result<Vec<ChunkReplicationStatus>> {
+        let result = sqlx::query_as::<_, ChunkReplicationStatus>(
+            r#"
+            SELECT s.* FROM chunk_repair_risk s
+            JOIN chunks c ON c.chunk_id = s.chunk_id
+            WHERE s.replicas_needed > 0
+            AND (c.changed_at >= $2 OR c.chunk_id = ANY($3))
+            AND chunk_keyspace_slot(c.chunk_id) >= $4
+            AND chunk_keyspace_slot(c.chunk_id) < $5
+            ORDER BY s.safe_replicas, COALESCE(s.stripe_spare, 0), s.replicas_needed DESC
+            LIMIT $1
+            "#,
+        )
+        .bind(limit)
+        .bind(since)
+        .bind(recheck)
+        .bind(range_start)
+        .bind(range_end)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(result)
+    }
+
+    /// Current time of the database clock, which change timestamps use
+    pub async fn now(&self) -> Result<chrono::DateTime<chrono::Utc>> {
+        let now = sqlx::query_scalar("SELECT NOW()")
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(now)
+    }
+
+    /// Claim or renew a rebalancer's lease on a keyspace range
+    ///
+    /// Fails (returns false) while another owner holds an unexpired lease on
+    /// an overlapping range. Claims are serialized by a table lock, so no two
+    /// owners ever hold overlapping ranges.
+    pub async fn claim_keyspace_lease(
+        &self,
+        owner: &str,
+        range_start: i32,
+        range_end: i32,
+        ttl: Duration,
+    ) -> Result<bool> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query("LOCK TABLE rebalancer_keyspace_leases IN SHARE ROW EXCLUSIVE MODE")
+            .execute(&mut *tx)
+            .await?;
+
+        let conflict: bool = sqlx::query_scalar(
+            r#"
+            SELECT EXISTS (
+                SELECT 1 FROM rebalancer_keyspace_leases
+                WHERE owner <> $1 AND expires_at > NOW()
+                AND range_start < $3 AND $2 < range_end
+            )
+            "#,
+        )
+        .bind(owner)
+        .bind(range_start)
+        .bind(range_end)
+        .fetch_one(&mut *tx)
+        .await?;
+        if conflict {
+            return Ok(false);
+        }
+
+        sqlx::query(
+            r#"
+            INSERT INTO rebalancer_keyspace_leases (owner, range_start, range_end, expires_at)
+            VALUES ($1, $2, $3, NOW() + make_interval(secs => $4))
+            ON CONFLICT (owner) DO UPDATE SET
+                range_start = EXCLUDED.range_start,
+                range_end = EXCLUDED.range_end,
+                expires_at = EXCLUDED.expires_at,
+                updated_at = NOW()
+            "#,
+        )
+        .bind(owner)
+        .bind(range_start)
+        .bind(range_end)
+        .bind(ttl.as_secs_f64())
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    /// Give up a rebalancer's keyspace lease so another instance can claim it
+    pub async fn release_keyspace_lease(&self, owner: &str) -> Result<()> {
+        sqlx::query("DELETE FROM rebalancer_keyspace_leases WHERE owner = $1")
+            .bind(owner)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    // =========================================================================
+    // CHUNK LOCATION OPERATIONS
+    // =========================================================================
+
+    /// Record a chunk location
+    pub async fn add_chunk_location(
+        &self,
+        chunk_id: &[u8],
+        node_id: Uuid,
+    ) -> Result<ChunkLocation> {
+        let result = sqlx::query_as::<_, ChunkLocation>(
+            r#"
+            INSERT INTO chunk_locations (chunk_id, node_id, status)
+            VALUES ($1, $2, 'stored')
+            ON CONFLICT (chunk_id, node_id) DO UPDATE SET status = 'stored'
+            RETURNING *
+            "#,
+        )
+        .bind(chunk_id)
+        .bind(node_id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        // Update chunk replica count and activate chunk
+        let new_count = self.increment_chunk_replicas(chunk_id).await?;
+
+        // Mark chunk as active once it has at least one replica stored
+        if new_count == 1 {
+            self.update_chunk_status(chunk_id, "active").await?;
+        }
+
+        Ok(result)
+    }
+
+    /// Restore a chunk location found on a node's disk (`cyxcloud-node salvage`)
+    ///
+    /// The chunk row is recreated from `chunk` if it is missing, taking the
+    /// replication factor of the file's other chunks when there are any. A
+    /// location that was missing counts as a new replica; one still recorded
+    /// is only marked stored. Nothing is written if the file is gone.
+    pub async fn restore_chunk_location(
+        &self,
+        chunk: &CreateChunk,
+        node_id: Uuid,
+    ) -> Result<SalvageOutcome> {
+        let mut tx = self.pool.begin().await?;
+
+        let file_live = sqlx::query_scalar::<_, bool>(
+            r#"
+            SELECT EXISTS (
+                SELECT 1 FROM files
+                WHERE id = $1 AND deleted_at IS NULL AND status <> 'deleted'
+            )
+            "#,
+        )
+        .bind(chunk.file_id)
+        .fetch_one(&mut *tx)
+        .await?;
+        if !file_live {
+            return Ok(SalvageOutcome::Orphaned);
+        }
+
+        sqlx::query(
+            r#"
+            INSERT INTO chunks (chunk_id, file_id, chunk_index, shard_index, is_parity, size_bytes, replication_factor)
+            VALUES ($1, $2, $3, $4, $5, $6, COALESCE(
+                (SELECT MAX(replication_factor) FROM chunks WHERE file_id = $2), $7
+            ))
+            ON CONFLICT (chunk_id) DO NOTHING
+            "#,
+        )
+        .bind(&chunk.chunk_id)
+        .bind(chunk.file_id)
+        .bind(chunk.chunk_index)
+        .bind(chunk.shard_index)
+        .bind(chunk.is_parity)
+        .bind(chunk.size_bytes)
+        .bind(chunk.replication_factor)
+        .execute(&mut *tx)
+        .await?;
+
+        // xmax is 0 only for a freshly inserted row
+        let inserted = sqlx::query_scalar::<_, bool>(
+            r#"
+            INSERT INTO chunk_locations (chunk_id, node_id, status)
+            VALUES ($1, $2, 'stored')
+            ON CONFLICT (chunk_id, node_id) DO UPDATE SET status = 'stored'
+            RETURNING (xmax = 0)
+            "#,
+        )
+        .bind(&chunk.chunk_id)
+        .bind(node_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        if inserted {
+            sqlx::query(
+                r#"
+                UPDATE chunks
+                SET current_replicas = current_replicas + 1,
+                    status = CASE WHEN current_replicas = 0 THEN 'active' ELSE status END
+                WHERE chunk_id = $1
+                "#,
+            )
+            .bind(&chunk.chunk_id)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(if inserted {
+            SalvageOutcome::Restored
+        } else {
+            SalvageOutcome::Known
+        })
+    }
+
+    /// Get all locations for a chunk
+    pub async fn get_chunk_locations(&self, chunk_id: &[u8]) -> Result<Vec<ChunkLocation>> {
+        let result = sqlx::query_as::<_, ChunkLocation>(
+            "SELECT * FROM chunk_locations WHERE chunk_id = $1 AND status = 'stored'",
+        )
+        .bind(chunk_id)
+        .fetch_all(self.consistent_read_pool())
+        .await?;
+        Ok(result)
+    }
+
+    /// Get all chunk locations for a file (batch query to avoid N+1)
+    ///
+    /// Returns a map of chunk_id -> list of node gRPC addresses
+    pub async fn get_file_chunk_locations(
+        &self,
+        file_id: Uuid,
+    ) -> Result<HashMap<Vec<u8>, Vec<String>>> {
+        let rows = sqlx::query_as::<_, (Vec<u8>, String)>(
+            r#"
+            SELECT cl.chunk_id, n.grpc_address
+            FROM chunk_locations cl
+            JOIN chunks c ON cl.chunk_id = c.chunk_id
+            JOIN nodes n ON cl.node_id = n.id
+            WHERE c.file_id = $1 AND cl.status = 'stored' AND n.status = 'online'
+            ORDER BY cl.chunk_id
+            "#,
+        )
+        .bind(file_id)
+        .fetch_all(self.consistent_read_pool())
+        .await?;
+
+        let mut map: HashMap<Vec<u8>, Vec<String>> = HashMap::new();
+        for (chunk_id, address) in rows {
+            map.entry(chunk_id).or_default().push(address);
+        }
+        Ok(map)
+    }
+
+    /// Get every (chunk ID, node ID) location recorded for a file's shards
+    pub async fn get_file_shard_locations(&self, file_id: Uuid) -> Result<Vec<(Vec<u8>, Uuid)>> {
+        let result = sqlx::query_as::<_, (Vec<u8>, Uuid)>(
+            r#"
+            SELECT cl.chunk_id, cl.node_id
+            FROM chunk_locations cl
+            JOIN chunks c ON cl.chunk_id = c.chunk_id
+            WHERE c.file_id = $1
+            "#,
+        )
+        .bind(file_id)
+        .fetch_all(self.consistent_read_pool())
+        .await?;
+        Ok(result)
+    }
+
+    /// Get node addresses storing a chunk
+    pub async fn get_chunk_node_addresses(&self, chunk_id: &[u8]) -> Result<Vec<String>> {
+        let result = sqlx::query_scalar::<_, String>(
+            r#"
+            SELECT n.grpc_address
+            FROM chunk_locations cl
+            JOIN nodes n ON cl.node_id = n.id
+            WHERE cl.chunk_id = $1 AND cl.status = 'stored' AND n.status = 'online'
+            "#,
+        )
+        .bind(chunk_id)
+        .fetch_all(self.consistent_read_pool())
+        .await?;
+        Ok(result)
+    }
+
+    /// Get peer IDs of nodes holding sibling shards of a chunk
+    ///
+    /// Siblings are the other erasure-coded shards of the same file chunk.
+    /// Used by the repair planner to avoid co-locating shards.
+    pub async fn get_sibling_shard_nodes(&self, chunk_id: &[u8]) -> Result<Vec<String>> {
+        let result = sqlx::query_scalar::<_, String>(
+            r#"
+            SELECT DISTINCT n.peer_id
+            FROM chunks c
+            JOIN chunks s ON s.file_id = c.file_id
+                AND s.chunk_index = c.chunk_index
+                AND s.chunk_id <> c.chunk_id
+            JOIN chunk_locations cl ON cl.chunk_id = s.chunk_id AND cl.status = 'stored'
+            JOIN nodes n ON cl.node_id = n.id
+            WHERE c.chunk_id = $1
+            "#,
+        )
+        .bind(chunk_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(result)
+    }
+
+    /// Remove a chunk location (e.g., node went offline)
+    pub async fn remove_chunk_location(&self, chunk_id: &[u8], node_id: Uuid) -> Result<()> {
+        sqlx::query("DELETE FROM chunk_locations WHERE chunk_id = $1 AND node_id = $2")
+            .bind(chunk_id)
+            .bind(node_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Drop locations a node reported as corrupted or unreadable
+    ///
+    /// Each removed location lowers the chunk's replica count so the repair
+    /// scheduler picks it up as under-replicated. Returns the number of
+    /// locations removed.
+    #[instrument(skip(self, chunk_ids), fields(chunks = chunk_ids.len()))]
+    pub async fn remove_damaged_chunk_locations(
+        &self,
+        node_id: Uuid,
+        chunk_ids: &[Vec<u8>],
+    ) -> Result<u64> {
+        let mut tx = self.pool.begin().await?;
+
+        let removed: Vec<Vec<u8>> = sqlx::query_scalar(
+            r#"
+            DELETE FROM chunk_locations
+            WHERE node_id = $1 AND chunk_id = ANY($2)
+            RETURNING chunk_id
+            "#,
+        )
+        .bind(node_id)
+        .bind(chunk_ids)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        if !removed.is_empty() {
+            sqlx::query(
+                r#"
+                UPDATE chunks
+                SET current_replicas = GREATEST(current_replicas - 1, 0)
+                WHERE chunk_id = ANY($1)
+                "#,
+            )
+            .bind(&removed)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(removed.len() as u64)
+    }
+
+    /// Move chunk locations from one node to another (cold migration)
+    ///
+    /// Locations the target already has are dropped from the source instead
+    /// of duplicated. Returns the number of chunks now recorded on the target.
+    #[instrument(skip(self, chunk_ids), fields(chunks = chunk_ids.len()))]
+    pub async fn reassign_chunk_locations(
+        &self,
+        from_node_id: Uuid,
+        to_node_id: Uuid,
+        chunk_ids: &[Vec<u8>],
+    ) -> Result<u64> {
+        let mut tx = self.pool.begin().await?;
+
+        let moved = sqlx::query(
+            r#"
+            UPDATE chunk_locations cl
+            SET node_id = $2, status = 'stored', verification_failures = 0
+            WHERE cl.node_id = $1
+            AND cl.chunk_id = ANY($3)
+            AND NOT EXISTS (
+                SELECT 1 FROM chunk_locations t
+                WHERE t.node_id = $2 AND t.chunk_id = cl.chunk_id
+            )
+            "#,
+        )
+        .bind(from_node_id)
+        .bind(to_node_id)
+        .bind(chunk_ids)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        let duplicates =
+            sqlx::query("DELETE FROM chunk_locations WHERE node_id = $1 AND chunk_id = ANY($2)")
+                .bind(from_node_id)
+                .bind(chunk_ids)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+
+        tx.commit().await?;
+        debug!(
+            from = %from_node_id,
+            to = %to_node_id,
+            moved = moved,
+            duplicates = duplicates,
+            "Chunk locations reassigned"
+        );
+        Ok(moved + duplicates)
+    }
+
+    /// Update chunk location verification
+    ///
+    /// A passed check refreshes `last_verified` and ends any failure streak;
+    /// a failed one extends the streak, marking the location `failed` on the
+    /// third consecutive failure. Returns the updated location, if any.
+    pub async fn update_chunk_verification(
+        &self,
+        chunk_id: &[u8],
+        node_id: Uuid,
+        valid: bool,
+    ) -> Result<Option<ChunkLocation>> {
+        let query = if valid {
+            r#"
+            UPDATE chunk_locations
+            SET last_verified = NOW(), verification_failures = 0, first_failed_at = NULL
+            WHERE chunk_id = $1 AND node_id = $2
+            RETURNING *
+            "#
+        } else {
+            r#"
+            UPDATE chunk_locations
+            SET verification_failures = verification_failures + 1,
+                first_failed_at = COALESCE(first_failed_at, NOW()),
+                status = CASE WHEN verification_failures >= 2 THEN 'failed' ELSE status END
+            WHERE chunk_id = $1 AND node_id = $2
+            RETURNING *
+            "#
+        };
+
+        let result = sqlx::query_as::<_, ChunkLocation>(query)
+            .bind(chunk_id)
+            .bind(node_id)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(result)
+    }
+
+    /// Pick stored chunk locations on online nodes for proof-of-storage audits
+    ///
+    /// Locations never verified come first, then the least recently verified.
+    pub async fn get_chunk_audit_targets(&self, limit: i64) -> Result<Vec<ChunkAuditTarget>> {
+        let result = sqlx::query_as::<_, ChunkAuditTarget>(
+            r#"
+            SELECT cl.chunk_id, cl.node_id, n.peer_id, n.grpc_address, n.wallet_address
+            FROM chunk_locations cl
+            JOIN nodes n ON cl.node_id = n.id
+            WHERE cl.status = 'stored' AND n.status = 'online'
+            ORDER BY cl.last_verified ASC NULLS FIRST, cl.created_at ASC
+            LIMIT $1
+            "#,
+        )
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(result)
+    }
+
+    /// Pick a node's stored copies for sampled integrity checks
+    ///
+    /// Only chunks whose keyspace slot is in `range_start..range_end` are
+    /// considered. Copies never verified come first, then the least recently
+    /// verified.
+    pub async fn get_chunk_integrity_candidates(
+        &self,
+        node_id: Uuid,
+        limit: i64,
+        range_start: i32,
+        range_end: i32,
+    ) -> Result<Vec<ChunkIntegrityCandidate>> {
+        let result = sqlx::query_as::<_, ChunkIntegrityCandidate>(
+            r#"
+            SELECT cl.chunk_id, c.file_id, c.size_bytes, c.replication_factor,
+                   EXTRACT(EPOCH FROM NOW() - COALESCE(cl.last_verified, cl.created_at))::FLOAT8
+                       AS unverified_secs
+            FROM chunk_locations cl
+            JOIN chunks c ON c.chunk_id = cl.chunk_id
+            WHERE cl.node_id = $1 AND cl.status = 'stored'
+            AND chunk_keyspace_slot(cl.chunk_id) >= $3
+            AND chunk_keyspace_slot(cl.chunk_id) < $4
+            ORDER BY cl.last_verified ASC NULLS FIRST, cl.created_at ASC
+            LIMIT $2
+            "#,
+        )
+        .bind(node_id)
+        .bind(limit)
+        .bind(range_start)
+        .bind(range_end)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(result)
+    }
+
+    /// Retire a corrupt copy once a replacement has been stored elsewhere
+    ///
+    /// The location is dropped, lowering the chunk's replica count, and the
+    /// copy is queued for shard GC so the node deletes it. Returns whether a
+    /// location was removed.
+    pub async fn retire_chunk_copy(&self, chunk_id: &[u8], node_id: Uuid) -> Result<bool> {
+        let mut tx = self.pool.begin().await?;
+
+        let removed =
+            sqlx::query("DELETE FROM chunk_locations WHERE chunk_id = $1 AND node_id = $2")
+                .bind(chunk_id)
+                .bind(node_id)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+
+        if removed > 0 {
+            sqlx::query(
+                r#"
+                UPDATE chunks
+                SET current_replicas = GREATEST(current_replicas - 1, 0)
+                WHERE chunk_id = $1
+                "#,
+            )
+            .bind(chunk_id)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        sqlx::query(
+            r#"
+            INSERT INTO shard_gc_queue (file_id, chunk_id, node_id)
+            SELECT file_id, chunk_id, $2 FROM chunks WHERE chunk_id = $1
+            ON CONFLICT (chunk_id, node_id) DO NOTHING
+            "#,
+        )
+        .bind(chunk_id)
+        .bind(node_id)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(removed > 0)
+    }
+
+    // =========================================================================
+    // USER OPERATIONS
+    // =========================================================================
+
+    /// Create a new user
+    pub async fn create_user(
+        &self,
+        wallet_address: Option<&str>,
+        email: Option<&str>,
+        username: Option<&str>,
+    ) -> Result<User> {
+        let result = sqlx::query_as::<_, User>(
+            r#"
+            INSERT INTO users (wallet_address, email, username)
+            VALUES ($1, $2, $3)
+            RETURNING *
+            "#,
+        )
+        .bind(wallet_address)
+        .bind(email)
+        .bind(username)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(result)
+    }
+
+    /// Get user by wallet address
+    pub async fn get_user_by_wallet(&self, wallet_address: &str) -> Result<Option<User>> {
+        let result = sqlx::query_as::<_, User>("SELECT * FROM users WHERE wallet_address = $1")
+            .bind(wallet_address)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(result)
+    }
+
+    /// Get the user linked to an identity provider account
+    pub async fn get_user_by_identity(&self, issuer: &str, subject: &str) -> Result<Option<User>> {
+        let result = sqlx::query_as::<_, User>(
+            r#"
+            UPDATE user_identities SET last_login_at = NOW()
+            FROM users u
+            WHERE issuer = $1 AND subject = $2 AND u.id = user_identities.user_id
+            RETURNING u.*
+            "#,
+        )
+        .bind(issuer)
+        .bind(subject)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(result)
+    }
+
+    /// Create a user linked to an identity provider account
+    ///
+    /// If a concurrent login linked the account first, that user is returned
+    /// and the new one is discarded.
+    pub async fn create_user_with_identity(
+        &self,
+        issuer: &str,
+        subject: &str,
+        email: Option<&str>,
+    ) -> Result<User> {
+        let mut tx = self.pool.begin().await?;
+
+        let user = sqlx::query_as::<_, User>("INSERT INTO users DEFAULT VALUES RETURNING *")
+            .fetch_one(&mut *tx)
+            .await?;
+
+        let linked = sqlx::query(
+            r#"
+            INSERT INTO user_identities (issuer, subject, user_id, email)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (issuer, subject) DO NOTHING
+            "#,
+        )
+        .bind(issuer)
+        .bind(subject)
+        .bind(user.id)
+        .bind(email)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        if linked == 0 {
+            tx.rollback().await?;
+            return self
+                .get_user_by_identity(issuer, subject)
+                .await?
+                .ok_or_else(|| DbError::NotFound(format!("identity {} of {}", subject, issuer)));
+        }
+
+        tx.commit().await?;
+        Ok(user)
+    }
+
+    /// Update user storage usage
+    pub async fn update_user_storage(&self, user_id: Uuid, storage_used: i64) -> Result<()> {
+        sqlx::query("UPDATE users SET storage_used = $1 WHERE id = $2")
+            .bind(storage_used)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Get user by email or wallet address
+    pub async fn get_user_by_email_or_wallet(&self, identifier: &str) -> Result<Option<User>> {
+        let result = sqlx::query_as::<_, User>(
+            "SELECT * FROM users WHERE email = $1 OR wallet_address = $1",
+        )
+        .bind(identifier)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(result)
+    }
+
+    /// Plan of a user, None if the ID is not a known user
+    pub async fn get_user_plan(&self, user_id: &str) -> Result<Option<String>> {
+        let Ok(id) = Uuid::parse_str(user_id) else {
+            return Ok(None);
+        };
+        let result = sqlx::query_scalar::<_, String>("SELECT plan FROM users WHERE id = $1")
+            .bind(id)
+            .fetch_optional(self.read_pool())
+            .await?;
+        Ok(result)
+    }
+
+    // =========================================================================
+    // BANDWIDTH USAGE OPERATIONS
+    // =========================================================================
+
+    /// Add transferred bytes to the hourly usage rows
+    ///
+    /// Counters are added to existing rows, so several gateways can record
+    /// usage for the same user and hour.
+    #[instrument(skip(self, usage), fields(rows = usage.len()))]
+    pub async fn record_bandwidth_usage(&self, usage: &[BandwidthUsage]) -> Result<()> {
+        if usage.is_empty() {
+            return Ok(());
+        }
+
+        let mut user_ids = Vec::with_capacity(usage.len());
+        let mut tenant_ids = Vec::with_capacity(usage.len());
+        let mut hours = Vec::with_capacity(usage.len());
+        let mut bytes_in = Vec::with_capacity(usage.len());
+        let mut bytes_out = Vec::with_capacity(usage.len());
+        let mut requests = Vec::with_capacity(usage.len());
+        for row in usage {
+            user_ids.push(row.user_id.as_str());
+            tenant_ids.push(row.tenant_id.as_str());
+            hours.push(row.hour);
+            bytes_in.push(row.bytes_in);
+            bytes_out.push(row.bytes_out);
+            requests.push(row.requests);
+        }
+
+        sqlx::query(
+            r#"
+            INSERT INTO bandwidth_usage (user_id, tenant_id, hour, bytes_in, bytes_out, requests)
+            SELECT * FROM UNNEST(
+                $1::varchar[], $2::varchar[], $3::timestamptz[],
+                $4::bigint[], $5::bigint[], $6::bigint[]
+            )
+            ON CONFLICT (user_id, hour, tenant_id) DO UPDATE SET
+                bytes_in = bandwidth_usage.bytes_in + EXCLUDED.bytes_in,
+                bytes_out = bandwidth_usage.bytes_out + EXCLUDED.bytes_out,
+                requests = bandwidth_usage.requests + EXCLUDED.requests
+            "#,
+        )
+        .bind(&user_ids)
+        .bind(&tenant_ids)
+        .bind(&hours)
+        .bind(&bytes_in)
+        .bind(&bytes_out)
+        .bind(&requests)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Hourly usage rows of a user in `[from, to)`, oldest first
+    pub async fn get_user_bandwidth_usage(
+        &self,
+        user_id: &str,
+        from: chrono::DateTime<chrono::Utc>,
+        to: chrono::DateTime<chrono::Utc>,
+    ) -> Result<Vec<BandwidthUsage>> {
+        let result = sqlx::query_as::<_, BandwidthUsage>(
+            r#"
+            SELECT * FROM bandwidth_usage
+            WHERE user_id = $1 AND hour >= $2 AND hour < $3
+            ORDER BY hour, tenant_id
+            "#,
+        )
+        .bind(user_id)
+        .bind(from)
+        .bind(to)
+        .fetch_all(self.read_pool())
+        .await?;
+        Ok(result)
+    }
+
+    /// Bytes downloaded by a user since `since`, across all tenants
+    pub async fn get_user_egress_since(
+        &self,
+        user_id: &str,
+        since: chrono::DateTime<chrono::Utc>,
+    ) -> Result<i64> {
+        let result = sqlx::query_scalar::<_, i64>(
+            r#"
+            SELECT COALESCE(SUM(bytes_out), 0)::bigint FROM bandwidth_usage
+            WHERE user_id = $1 AND hour >= $2
+            "#,
+        )
+        .bind(user_id)
+        .bind(since)
+        .fetch_one(self.read_pool())
+        .await?;
+        Ok(result)
+    }
+
+    /// Add sampled downloads to the objects' access counts and heat
+    ///
+    /// Each row's heat is decayed from its previous access to the new
+    /// `last_accessed_at` before the row's heat is added.
+    pub async fn record_object_heat(&self, rows: &[ObjectHeat], half_life_secs: f64) -> Result<()> {
+        if rows.is_empty() {
+            return Ok(());
+        }
+
+        let mut tenant_ids = Vec::with_capacity(rows.len());
+        let mut buckets = Vec::with_capacity(rows.len());
+        let mut keys = Vec::with_capacity(rows.len());
+        let mut counts = Vec::with_capacity(rows.len());
+        let mut heats = Vec::with_capacity(rows.len());
+        let mut accessed = Vec::with_capacity(rows.len());
+        for row in rows {
+            tenant_ids.push(row.tenant_id.as_str());
+            buckets.push(row.bucket.as_str());
+            keys.push(row.object_key.as_str());
+            counts.push(row.access_count);
+            heats.push(row.heat);
+            accessed.push(row.last_accessed_at);
+        }
+
+        sqlx::query(
+            r#"
+            INSERT INTO object_heat (tenant_id, bucket, object_key, access_count, heat, last_accessed_at)
+            SELECT * FROM UNNEST(
+                $1::varchar[], $2::varchar[], $3::varchar[],
+                $4::bigint[], $5::float8[], $6::timestamptz[]
+            )
+            ON CONFLICT (tenant_id, bucket, object_key) DO UPDATE SET
+                access_count = object_heat.access_count + EXCLUDED.access_count,
+                heat = object_heat.heat * POWER(0.5::float8, (GREATEST(EXTRACT(EPOCH FROM
+                    EXCLUDED.last_accessed_at - object_heat.last_accessed_at), 0) / $7)::float8)
+                    + EXCLUDED.heat,
+                last_accessed_at = GREATEST(object_heat.last_accessed_at, EXCLUDED.last_accessed_at)
+            "#,
+        )
+        .bind(&tenant_ids)
+        .bind(&buckets)
+        .bind(&keys)
+        .bind(&counts)
+        .bind(&heats)
+        .bind(&accessed)
+        .bind(half_life_secs)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// A bucket's hottest objects, heat decayed to now, hottest first
+    pub async fn get_hottest_objects(
+        &self,
+        tenant: &str,
+        bucket: &str,
+        limit: i64,
+        half_life_secs: f64,
+    ) -> Result<Vec<ObjectHeat>> {
+        let result = sqlx::query_as::<_, ObjectHeat>(
+            r#"
+            SELECT h.tenant_id, h.bucket, h.object_key, h.access_count,
+                   h.heat * POWER(0.5::float8,
+                       (GREATEST(EXTRACT(EPOCH FROM NOW() - h.last_accessed_at), 0) / $4)::float8) AS heat,
+                   h.last_accessed_at
+            FROM object_heat h
+            WHERE h.tenant_id = $1 AND h.bucket = $2
+              AND EXISTS (
+                  SELECT 1 FROM files f
+                  WHERE f.tenant_id = h.tenant_id AND f.path = h.bucket || '/' || h.object_key
+                    AND f.deleted_at IS NULL AND f.status = 'complete'
+              )
+            ORDER BY heat DESC
+            LIMIT $3
+            "#,
+        )
+        .bind(tenant)
+        .bind(bucket)
+        .bind(limit)
+        .bind(half_life_secs)
+        .fetch_all(self.read_pool())
+        .await?;
+        Ok(result)
+    }
+
+    /// Every user's hourly usage rows in `[from, to)` (billing export)
+    pub async fn list_bandwidth_usage(
+        &self,
+        from: chrono::DateTime<chrono::Utc>,
+        to: chrono::DateTime<chrono::Utc>,
+    ) -> Result<Vec<BandwidthUsage>> {
+        let result = sqlx::query_as::<_, BandwidthUsage>(
+            r#"
+            SELECT * FROM bandwidth_usage
+            WHERE hour >= $1 AND hour < $2
+            ORDER BY user_id, hour, tenant_id
+            "#,
+        )
+        .bind(from)
+        .bind(to)
+        .fetch_all(self.read_pool())
+        .await?;
+        Ok(result)
+    }
+
+    // =========================================================================
+    // BUCKET OPERATIONS
+    // =========================================================================
+
+    /// Create a new bucket in a tenant's namespace
+    pub async fn create_bucket(&self, tenant: &str, name: &str, owner_id: Uuid) -> Result<Bucket> {
+        let result = sqlx::query_as::<_, Bucket>(
+            r#"
+            INSERT INTO buckets (tenant_id, name, owner_id)
+            VALUES ($1, $2, $3)
+            RETURNING *
+            "#,
+        )
+        .bind(tenant)
+        .bind(name)
+        .bind(owner_id)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(result)
+    }
+
+    /// Get a tenant's bucket by name
+    pub async fn get_bucket(&self, tenant: &str, name: &str) -> Result<Option<Bucket>> {
+        let result =
+            sqlx::query_as::<_, Bucket>("SELECT * FROM buckets WHERE tenant_id = $1 AND name = $2")
+                .bind(tenant)
+                .bind(name)
+                .fetch_optional(self.consistent_read_pool())
+                .await?;
+        Ok(result)
+    }
+
+    /// List buckets for a user
+    pub async fn list_user_buckets(&self, owner_id: Uuid) -> Result<Vec<Bucket>> {
+        let result =
+            sqlx::query_as::<_, Bucket>("SELECT * FROM buckets WHERE owner_id = $1 ORDER BY name")
+                .bind(owner_id)
+                .fetch_all(self.consistent_read_pool())
+                .await?;
+        Ok(result)
+    }
+
+    /// Delete a tenant's bucket by name
+    ///
+    /// Note: This performs a hard delete. Make sure the bucket is empty first.
+    pub async fn delete_bucket(&self, tenant: &str, name: &str) -> Result<()> {
+        sqlx::query("DELETE FROM buckets WHERE tenant_id = $1 AND name = $2")
+            .bind(tenant)
+            .bind(name)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Set how many nodes each shard of a tenant's bucket is written to
+    ///
+    /// Returns false if the bucket does not exist.
+    pub async fn set_bucket_shard_replicas(
+        &self,
+        tenant: &str,
+        name: &str,
+        replicas: i16,
+    ) -> Result<bool> {
+        let result = sqlx::query(
+            r#"
+            UPDATE buckets SET shard_replicas = $3, updated_at = NOW()
+            WHERE tenant_id = $1 AND name = $2
+            "#,
+        )
+        .bind(tenant)
+        .bind(name)
+        .bind(replicas)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Set or clear (`target` = None) a bucket's access logging target
+    ///
+    /// `target` is the target bucket and key prefix. Returns false if the
+    /// bucket does not exist.
+    pub async fn set_bucket_logging(
+        &self,
+        tenant: &str,
+        name: &str,
+        target: Option<(&str, &str)>,
+    ) -> Result<bool> {
+        let (target_bucket, target_prefix) = match target {
+            Some((bucket, prefix)) => (Some(bucket), prefix),
+            None => (None, ""),
+        };
+        let result = sqlx::query(
+            r#"
+            UPDATE buckets
+            SET logging_target_bucket = $3, logging_target_prefix = $4, updated_at = NOW()
+            WHERE tenant_id = $1 AND name = $2
+            "#,
+        )
+        .bind(tenant)
+        .bind(name)
+        .bind(target_bucket)
+        .bind(target_prefix)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Enable object lock on a bucket and set (or clear, with None) the
+    /// default retention of its new objects
+    ///
+    /// Object lock cannot be disabled again. Returns false if the bucket
+    /// does not exist.
+    pub async fn set_bucket_object_lock(
+        &self,
+        tenant: &str,
+        name: &str,
+        default_retention: Option<(RetentionMode, i32)>,
+    ) -> Result<bool> {
+        let (mode, days) = match default_retention {
+            Some((mode, days)) => (Some(mode.as_str()), Some(days)),
+            None => (None, None),
+        };
+        let result = sqlx::query(
+            r#"
+            UPDATE buckets
+            SET object_lock_enabled = TRUE, object_lock_mode = $3, object_lock_days = $4,
+                updated_at = NOW()
+            WHERE tenant_id = $1 AND name = $2
+            "#,
+        )
+        .bind(tenant)
+        .bind(name)
+        .bind(mode)
+        .bind(days)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Set or clear (`policy` = None) a bucket's policy document
+    ///
+    /// Returns false if the bucket does not exist.
+    pub async fn set_bucket_policy(
+        &self,
+        tenant: &str,
+        name: &str,
+        policy: Option<&serde_json::Value>,
+    ) -> Result<bool> {
+        let result = sqlx::query(
+            r#"
+            UPDATE buckets SET policy = $3, updated_at = NOW()
+            WHERE tenant_id = $1 AND name = $2
+            "#,
+        )
+        .bind(tenant)
+        .bind(name)
+        .bind(policy)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Set or clear (`cache_control` = None) the Cache-Control given to a
+    /// bucket's new objects
+    ///
+    /// Returns false if the bucket does not exist.
+    pub async fn set_bucket_cache_control(
+        &self,
+        tenant: &str,
+        name: &str,
+        cache_control: Option<&str>,
+    ) -> Result<bool> {
+        let result = sqlx::query(
+            r#"
+            UPDATE buckets SET default_cache_control = $3, updated_at = NOW()
+            WHERE tenant_id = $1 AND name = $2
+            "#,
+        )
+        .bind(tenant)
+        .bind(name)
+        .bind(cache_control)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Set whether anyone may read the objects of a bucket
+    ///
+    /// Returns false if the bucket does not exist.
+    pub async fn set_bucket_public_read(
+        &self,
+        tenant: &str,
+        name: &str,
+        public_read: bool,
+    ) -> Result<bool> {
+        let result = sqlx::query(
+            r#"
+            UPDATE buckets SET public_read = $3, updated_at = NOW()
+            WHERE tenant_id = $1 AND name = $2
+            "#,
+        )
+        .bind(tenant)
+        .bind(name)
+        .bind(public_read)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Set or clear (`rules` = None) the lifecycle rules of a bucket
+    ///
+    /// Returns false if the bucket does not exist.
+    pub async fn set_bucket_lifecycle(
+        &self,
+        tenant: &str,
+        name: &str,
+        rules: Option<&serde_json::Value>,
+    ) -> Result<bool> {
+        let result = sqlx::query(
+            r#"
+            UPDATE buckets SET lifecycle_rules = $3, updated_at = NOW()
+            WHERE tenant_id = $1 AND name = $2
+            "#,
+        )
+        .bind(tenant)
+        .bind(name)
+        .bind(rules)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Set or remove (`rules` = None) the CORS rules of a bucket
+    ///
+    /// Returns false if the bucket does not exist.
+    pub async fn set_bucket_cors(
+        &self,
+        tenant: &str,
+        name: &str,
+        rules: Option<&serde_json::Value>,
+    ) -> Result<bool> {
+        let result = sqlx::query(
+            r#"
+            UPDATE buckets SET cors_rules = $3, updated_at = NOW()
+            WHERE tenant_id = $1 AND name = $2
+            "#,
+        )
+        .bind(tenant)
+        .bind(name)
+        .bind(rules)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Buckets with lifecycle rules, across all tenants
+    pub async fn list_buckets_with_lifecycle(&self) -> Result<Vec<Bucket>> {
+        let result = sqlx::query_as::<_, Bucket>(
+            "SELECT * FROM buckets WHERE lifecycle_rules IS NOT NULL ORDER BY tenant_id, name",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(result)
+    }
+
+    /// Check if a tenant's bucket is empty (has no files)
+    ///
+    /// Uploads still in progress count as files, so a bucket is not empty
+    /// while one is being written to.
+    pub async fn bucket_is_empty(&self, tenant: &str, bucket_name: &str) -> Result<bool> {
+        let exists = sqlx::query_scalar::<_, bool>(
+            r#"
+            SELECT EXISTS(
+                SELECT 1 FROM files
+                WHERE tenant_id = $1 AND bucket = $2 AND deleted_at IS NULL
+            )
+            "#,
+        )
+        .bind(tenant)
+        .bind(bucket_name)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(!exists)
+    }
+
+    /// Count live objects in a tenant's bucket
+    ///
+    /// Reads the bucket's counter; 0 if the bucket does not exist.
+    pub async fn count_files_in_bucket(&self, tenant: &str, bucket_name: &str) -> Result<i64> {
+        let count = sqlx::query_scalar::<_, i64>(
+            "SELECT object_count FROM buckets WHERE tenant_id = $1 AND name = $2",
+        )
+        .bind(tenant)
+        .bind(bucket_name)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(count.unwrap_or(0))
+    }
+
+    /// List a tenant's buckets, with their object counters
+    pub async fn list_tenant_buckets(&self, tenant: &str) -> Result<Vec<Bucket>> {
+        let result =
+            sqlx::query_as::<_, Bucket>("SELECT * FROM buckets WHERE tenant_id = $1 ORDER BY name")
+                .bind(tenant)
+                .fetch_all(self.consistent_read_pool())
+                .await?;
+        Ok(result)
+    }
+
+    /// Recompute every bucket's object counters from its files
+    ///
+    /// Each bucket is locked while it is counted, so uploads and deletes
+    /// committing meanwhile are applied on top of the recomputed values
+    /// rather than lost. Returns the buckets whose counters had drifted.
+    #[instrument(skip(self))]
+    pub async fn reconcile_bucket_stats(&self) -> Result<u64> {
+        let ids: Vec<Uuid> = sqlx::query_scalar("SELECT id FROM buckets ORDER BY id")
+            .fetch_all(&self.pool)
+            .await?;
+
+        let mut corrected = 0;
+        for id in ids {
+            let mut tx = self.pool.begin().await?;
+
+            let Some((tenant, name, object_count, total_bytes)) =
+                sqlx::query_as::<_, (String, String, i64, i64)>(
+                    r#"
+                    SELECT tenant_id, name, object_count, total_bytes
+                    FROM buckets WHERE id = $1
+                    FOR UPDATE
+                    "#,
+                )
+                .bind(id)
+                .fetch_optional(&mut *tx)
+                .await?
+            else {
+                // Deleted since it was listed
+                continue;
+            };
+
+            let (actual_count, actual_bytes): (i64, i64) = sqlx::query_as(
+                r#"
+                SELECT COUNT(*), COALESCE(SUM(size_bytes), 0)::BIGINT
+                FROM files
+                WHERE tenant_id = $1 AND bucket = $2
+                  AND status = 'complete' AND deleted_at IS NULL
+                "#,
+            )
+            .bind(&tenant)
+            .bind(&name)
+            .fetch_one(&mut *tx)
+            .await?;
+
+            if (actual_count, actual_bytes) != (object_count, total_bytes) {
+                sqlx::query("UPDATE buckets SET object_count = $2, total_bytes = $3 WHERE id = $1")
+                    .bind(id)
+                    .bind(actual_count)
+                    .bind(actual_bytes)
+                    .execute(&mut *tx)
+                    .await?;
+                warn!(
+                    tenant = %tenant,
+                    bucket = %name,
+                    object_count,
+                    actual_count,
+                    total_bytes,
+                    actual_bytes,
+                    "Bucket counters drifted, corrected"
+                );
+                corrected += 1;
+            }
+
+            tx.commit().await?;
+        }
+
+        Ok(corrected)
+    }
+
+    // =========================================================================
+    // DRAIN PROGRESS OPERATIONS
+    // =========================================================================
+
+    /// Snapshot the chunks a draining node has to evacuate
+    ///
+    /// Restarts the counters if the node had drained before.
+    #[instrument(skip(self))]
+    pub async fn start_drain_progress(&self, node_id: Uuid, reason: &str) -> Result<NodeDrain> {
+        let result = sqlx::query_as::<_, NodeDrain>(
+            r#"
+            INSERT INTO node_drains (node_id, reason, total_chunks, total_bytes)
+            SELECT $1, $2, COUNT(*), COALESCE(SUM(c.size_bytes), 0)
+            FROM chunk_locations cl
+            LEFT JOIN chunks c ON c.chunk_id = cl.chunk_id
+            WHERE cl.node_id = $1 AND cl.status = 'stored'
+            ON CONFLICT (node_id) DO UPDATE SET
+                reason = EXCLUDED.reason,
+                total_chunks = EXCLUDED.total_chunks,
+                total_bytes = EXCLUDED.total_bytes,
+                evacuated_chunks = 0,
+                evacuated_bytes = 0,
+                started_at = NOW(),
+                updated_at = NOW(),
+                completed_at = CASE WHEN EXCLUDED.total_chunks = 0 THEN NOW() END
+            RETURNING *
+            "#,
+        )
+        .bind(node_id)
+        .bind(reason)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(result)
+    }
+
+    /// Get drain progress for a node (None if it never drained)
+    pub async fn get_drain_progress(&self, node_id: Uuid) -> Result<Option<NodeDrain>> {
+        let result = sqlx::query_as::<_, NodeDrain>("SELECT * FROM node_drains WHERE node_id = $1")
+            .bind(node_id)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(result)
+    }
+
+    /// Complete an evacuation repair job once its chunk is stored on the target
+    ///
+    /// Marks the job completed, drops the source replica (the target holds it
+    /// now) and advances the source node's drain progress. Returns the updated
+    /// progress, or None if the source is not being tracked as draining.
+    /// Repair jobs keep their source and are only marked completed.
+    #[instrument(skip(self, job), fields(job_id = %job.id))]
+    pub async fn complete_evacuation_job(&self, job: &RepairJob) -> Result<Option<NodeDrain>> {
+        let Some(source_node_id) = job.source_node_id.filter(|_| job.is_evacuation()) else {
+            self.update_repair_job_status(job.id, "completed", None)
+                .await?;
+            return Ok(None);
+        };
+
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(
+            r#"
+            UPDATE repair_jobs
+            SET status = 'completed', error_message = NULL, completed_at = NOW()
+            WHERE id = $1
+            "#,
+        )
+        .bind(job.id)
+        .execute(&mut *tx)
+        .await?;
+
+        let removed =
+            sqlx::query("DELETE FROM chunk_locations WHERE chunk_id = $1 AND node_id = $2")
+                .bind(&job.chunk_id)
+                .bind(source_node_id)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+
+        if removed > 0 {
+            sqlx::query(
+                r#"
+                UPDATE chunks
+                SET current_replicas = GREATEST(current_replicas - 1, 0)
+                WHERE chunk_id = $1
+                "#,
+            )
+            .bind(&job.chunk_id)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        let progress = sqlx::query_as::<_, NodeDrain>(
+            r#"
+            UPDATE node_drains d
+            SET evacuated_chunks = d.evacuated_chunks + 1,
+                evacuated_bytes = d.evacuated_bytes + COALESCE(
+                    (SELECT size_bytes FROM chunks WHERE chunk_id = $2), 0
+                ),
+                updated_at = NOW(),
+                completed_at = CASE
+                    WHEN d.evacuated_chunks + 1 >= d.total_chunks THEN NOW()
+                END
+            WHERE d.node_id = $1 AND d.completed_at IS NULL
+            RETURNING d.*
+            "#,
+        )
+        .bind(source_node_id)
+        .bind(&job.chunk_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(progress)
+    }
+
+    // =========================================================================
+    // REPAIR JOB OPERATIONS
+    // =========================================================================
+
+    /// Create a repair job
+    pub async fn create_repair_job(
+        &self,
+        chunk_id: &[u8],
+        source_node_id: Option<Uuid>,
+        target_node_id: Uuid,
+        priority: i32,
+    ) -> Result<RepairJob> {
+        let result = sqlx::query_as::<_, RepairJob>(
+            r#"
+            INSERT INTO repair_jobs (chunk_id, source_node_id, target_node_id, priority)
+            VALUES ($1, $2, $3, $4)
+            RETURNING *
+            "#,
+        )
+        .bind(chunk_id)
+        .bind(source_node_id)
+        .bind(target_node_id)
+        .bind(priority)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(result)
+    }
+
+    /// Create a job restoring a lost replica by copying from a healthy one
+    ///
+    /// Unlike evacuations, the source keeps its copy when the job completes.
+    pub async fn create_replica_repair_job(
+        &self,
+        chunk_id: &[u8],
+        source_node_id: Uuid,
+        target_node_id: Uuid,
+        priority: i32,
+    ) -> Result<RepairJob> {
+        let result = sqlx::query_as::<_, RepairJob>(
+            r#"
+            INSERT INTO repair_jobs (chunk_id, source_node_id, target_node_id, priority, kind)
+            VALUES ($1, $2, $3, $4, 'repair')
+            RETURNING *
+            "#,
+        )
+        .bind(chunk_id)
+        .bind(source_node_id)
+        .bind(target_node_id)
+        .bind(priority)
+        .fetch_one(&self.pool)
+        .await?;
         Ok(result)
     }
 
-    /// Update file status
-    pub async fn update_file_status(&self, file_id: Uuid, status: &str) -> Result<()> {
-        sqlx::query("UPDATE files SET status = $1 WHERE id = $2")
-            .bind(status)
-            .bind(file_id)
-            .execute(&self.pool)
-            .await?;
+    /// Get pending repair jobs
+    pub async fn get_pending_repair_jobs(&self, limit: i64) -> Result<Vec<RepairJob>> {
+        let result = sqlx::query_as::<_, RepairJob>(
+            r#"
+            SELECT * FROM repair_jobs
+            WHERE status = 'pending'
+            ORDER BY priority DESC, created_at ASC
+            LIMIT $1
+            "#,
+        )
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(result)
+    }
+
+    /// Count evacuations still moving shards off a node (pending or in progress)
+    pub async fn count_open_repair_jobs_from_node(&self, node_id: Uuid) -> Result<i64> {
+        let count = sqlx::query_scalar::<_, i64>(
+            r#"
+            SELECT COUNT(*) FROM repair_jobs
+            WHERE source_node_id = $1
+            AND kind = 'evacuation'
+            AND status IN ('pending', 'in_progress')
+            "#,
+        )
+        .bind(node_id)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(count)
+    }
+
+    /// Count open repair jobs (pending or in progress) per source node
+    pub async fn count_open_repair_jobs_by_source(&self) -> Result<HashMap<Uuid, i64>> {
+        let rows = sqlx::query_as::<_, (Uuid, i64)>(
+            r#"
+            SELECT source_node_id, COUNT(*) FROM repair_jobs
+            WHERE source_node_id IS NOT NULL
+            AND status IN ('pending', 'in_progress')
+            GROUP BY source_node_id
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().collect())
+    }
+
+    /// Number of repair jobs in each status
+    pub async fn count_repair_jobs_by_status(&self) -> Result<HashMap<String, i64>> {
+        let rows = sqlx::query_as::<_, (String, i64)>(
+            "SELECT status, COUNT(*) FROM repair_jobs GROUP BY status",
+        )
+        .fetch_all(self.read_pool())
+        .await?;
+        Ok(rows.into_iter().collect())
+    }
+
+    /// Update repair job status
+    pub async fn update_repair_job_status(
+        &self,
+        job_id: Uuid,
+        status: &str,
+        error: Option<&str>,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE repair_jobs
+            SET status = $1,
+                error_message = $2,
+                started_at = CASE WHEN $1 = 'in_progress' THEN NOW() ELSE started_at END,
+                completed_at = CASE WHEN $1 IN ('completed', 'failed') THEN NOW() ELSE completed_at END
+            WHERE id = $3
+            "#,
+        )
+        .bind(status)
+        .bind(error)
+        .bind(job_id)
+        .execute(&self.pool)
+        .await?;
         Ok(())
     }
 
-    /// Soft delete a file
-    pub async fn delete_file(&self, file_id: Uuid) -> Result<()> {
-        sqlx::query("UPDATE files SET deleted_at = NOW(), status = 'deleted' WHERE id = $1")
-            .bind(file_id)
+    // =========================================================================
+    // REBALANCER SCAN HISTORY
+    // =========================================================================
+
+    /// Record a rebalancer scan, keeping only the latest `keep` scans
+    pub async fn record_scan(&self, scan: &CreateScanRecord, keep: i64) -> Result<ScanRecord> {
+        let result = sqlx::query_as::<_, ScanRecord>(
+            r#"
+            INSERT INTO rebalancer_scans (
+                duration_ms, chunks_scanned, under_replicated, critical, over_replicated,
+                orphaned, corrupt, deferred, incidents, bytes_at_risk
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            RETURNING *
+            "#,
+        )
+        .bind(scan.duration_ms)
+        .bind(scan.chunks_scanned)
+        .bind(scan.under_replicated)
+        .bind(scan.critical)
+        .bind(scan.over_replicated)
+        .bind(scan.orphaned)
+        .bind(scan.corrupt)
+        .bind(scan.deferred)
+        .bind(scan.incidents)
+        .bind(scan.bytes_at_risk)
+        .fetch_one(&self.pool)
+        .await?;
+
+        sqlx::query("DELETE FROM rebalancer_scans WHERE id <= $1 - $2")
+            .bind(result.id)
+            .bind(keep.max(1))
             .execute(&self.pool)
             .await?;
-        Ok(())
+
+        Ok(result)
+    }
+
+    /// Scans recorded since `since`, oldest first, at most `limit` (the latest)
+    pub async fn get_scan_history(
+        &self,
+        since: chrono::DateTime<chrono::Utc>,
+        limit: i64,
+    ) -> Result<Vec<ScanRecord>> {
+        let result = sqlx::query_as::<_, ScanRecord>(
+            r#"
+            SELECT * FROM (
+                SELECT * FROM rebalancer_scans
+                WHERE scanned_at >= $1
+                ORDER BY scanned_at DESC
+                LIMIT $2
+            ) latest
+            ORDER BY scanned_at ASC
+            "#,
+        )
+        .bind(since)
+        .bind(limit)
+        .fetch_all(self.read_pool())
+        .await?;
+        Ok(result)
     }
 
     // =========================================================================
-    // CHUNK OPERATIONS
+    // UPLOAD INTENT OPERATIONS
     // =========================================================================
 
-    /// Create a new chunk record
-    #[instrument(skip(self, chunk))]
-    pub async fn create_chunk(&self, chunk: CreateChunk) -> Result<Chunk> {
-        let result = sqlx::query_as::<_, Chunk>(
+    /// Record an upload intent before any shards are dispatched
+    #[instrument(skip(self))]
+    pub async fn create_upload_intent(
+        &self,
+        file_id: Uuid,
+        expected_chunks: i32,
+        expected_shards: i32,
+        ttl: Duration,
+        idempotency_key: Option<&str>,
+    ) -> Result<UploadIntent> {
+        let result = sqlx::query_as::<_, UploadIntent>(
             r#"
-            INSERT INTO chunks (chunk_id, file_id, chunk_index, shard_index, is_parity, size_bytes, replication_factor)
-            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            INSERT INTO upload_intents
+                (file_id, expected_chunks, expected_shards, expires_at, idempotency_key)
+            VALUES ($1, $2, $3, NOW() + make_interval(secs => $4), $5)
             RETURNING *
             "#,
         )
-        .bind(&chunk.chunk_id)
-        .bind(chunk.file_id)
-        .bind(chunk.chunk_index)
-        .bind(chunk.shard_index)
-        .bind(chunk.is_parity)
-        .bind(chunk.size_bytes)
-        .bind(chunk.replication_factor)
+        .bind(file_id)
+        .bind(expected_chunks)
+        .bind(expected_shards)
+        .bind(ttl.as_secs_f64())
+        .bind(idempotency_key)
         .fetch_one(&self.pool)
         .await?;
+        Ok(result)
+    }
 
-        debug!(chunk_id = ?chunk.chunk_id, file_id = %chunk.file_id, "Chunk created");
+    /// Get the unexpired result recorded for a tenant's idempotency key
+    pub async fn get_idempotent_upload(
+        &self,
+        tenant: &str,
+        idempotency_key: &str,
+    ) -> Result<Option<IdempotentUpload>> {
+        let result = sqlx::query_as::<_, IdempotentUpload>(
+            r#"
+            SELECT * FROM upload_idempotency_keys
+            WHERE tenant_id = $1 AND idempotency_key = $2 AND expires_at > NOW()
+            "#,
+        )
+        .bind(tenant)
+        .bind(idempotency_key)
+        .fetch_optional(&self.pool)
+        .await?;
         Ok(result)
     }
 
-    /// Get a chunk by chunk_id
-    pub async fn get_chunk_by_id(&self, chunk_id: &[u8]) -> Result<Option<Chunk>> {
-        let result = sqlx::query_as::<_, Chunk>("SELECT * FROM chunks WHERE chunk_id = $1")
-            .bind(chunk_id)
-            .fetch_optional(&self.pool)
+    /// Delete expired idempotency keys, returning how many were removed
+    pub async fn delete_expired_idempotency_keys(&self) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM upload_idempotency_keys WHERE expires_at <= NOW()")
+            .execute(&self.pool)
             .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Append a shard placement to an upload intent (before the store RPC)
+    ///
+    /// Also pushes the intent's expiry out to `ttl` from now, so an upload
+    /// that is still making progress is never taken for abandoned.
+    pub async fn add_upload_intent_shard(
+        &self,
+        file_id: Uuid,
+        chunk_id: &[u8],
+        node_id: Uuid,
+        ttl: Duration,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            WITH extended AS (
+                UPDATE upload_intents
+                SET expires_at = GREATEST(expires_at, NOW() + make_interval(secs => $4))
+                WHERE file_id = $1
+            )
+            INSERT INTO upload_intent_shards (file_id, chunk_id, node_id)
+            VALUES ($1, $2, $3)
+            ON CONFLICT DO NOTHING
+            "#,
+        )
+        .bind(file_id)
+        .bind(chunk_id)
+        .bind(node_id)
+        .bind(ttl.as_secs_f64())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Get upload intents that have passed their expiry
+    pub async fn get_expired_upload_intents(&self, limit: i64) -> Result<Vec<UploadIntent>> {
+        let result = sqlx::query_as::<_, UploadIntent>(
+            r#"
+            SELECT * FROM upload_intents
+            WHERE expires_at < NOW()
+            ORDER BY expires_at
+            LIMIT $1
+            "#,
+        )
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
         Ok(result)
     }
 
-    /// Get all chunks for a file
-    pub async fn get_file_chunks(&self, file_id: Uuid) -> Result<Vec<Chunk>> {
-        let result = sqlx::query_as::<_, Chunk>(
-            "SELECT * FROM chunks WHERE file_id = $1 ORDER BY chunk_index, shard_index",
+    /// Get the shard placements recorded for an upload intent
+    pub async fn get_upload_intent_shards(&self, file_id: Uuid) -> Result<Vec<UploadIntentShard>> {
+        let result = sqlx::query_as::<_, UploadIntentShard>(
+            "SELECT * FROM upload_intent_shards WHERE file_id = $1",
+        )
+        .bind(file_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(result)
+    }
+
+    /// Remove an upload intent (upload finished or cleaned up)
+    pub async fn delete_upload_intent(&self, file_id: Uuid) -> Result<()> {
+        sqlx::query("DELETE FROM upload_intents WHERE file_id = $1")
+            .bind(file_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Check whether a chunk is registered to a file other than the given one
+    pub async fn chunk_owned_by_other_file(&self, chunk_id: &[u8], file_id: Uuid) -> Result<bool> {
+        let result = sqlx::query_scalar::<_, bool>(
+            "SELECT EXISTS(SELECT 1 FROM chunks WHERE chunk_id = $1 AND file_id <> $2)",
+        )
+        .bind(chunk_id)
+        .bind(file_id)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(result)
+    }
+
+    /// Permanently remove a file, its chunks and their locations
+    #[instrument(skip(self))]
+    pub async fn purge_file(&self, file_id: Uuid) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(
+            r#"
+            DELETE FROM chunk_locations
+            WHERE chunk_id IN (SELECT chunk_id FROM chunks WHERE file_id = $1)
+            "#,
+        )
+        .bind(file_id)
+        .execute(&mut *tx)
+        .await?;
+
+        // chunks and upload_intents cascade from files
+        sqlx::query("DELETE FROM files WHERE id = $1")
+            .bind(file_id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        debug!(file_id = %file_id, "File purged");
+        Ok(())
+    }
+
+    // =========================================================================
+    // STORAGE CLASS OPERATIONS
+    // =========================================================================
+
+    /// Queue a transition or restore job for a current file version
+    ///
+    /// Returns None if the file is not a current version or already has an
+    /// open job.
+    #[instrument(skip(self))]
+    pub async fn queue_storage_class_job(
+        &self,
+        file_id: Uuid,
+        kind: StorageClassJobKind,
+        storage_class: StorageClass,
+        restore_days: Option<i32>,
+    ) -> Result<Option<StorageClassJob>> {
+        let result = sqlx::query_as::<_, StorageClassJob>(
+            r#"
+            INSERT INTO storage_class_jobs
+                (file_id, tenant_id, bucket, object_key, kind, storage_class, restore_days)
+            SELECT f.id, f.tenant_id, f.bucket, substr(f.path, length(f.bucket) + 2), $2, $3, $4
+            FROM files f
+            WHERE f.id = $1 AND f.deleted_at IS NULL AND f.status = 'complete'
+              AND f.bucket IS NOT NULL
+            ON CONFLICT (file_id) WHERE status IN ('pending', 'running') DO NOTHING
+            RETURNING *
+            "#,
+        )
+        .bind(file_id)
+        .bind(kind.as_str())
+        .bind(storage_class.as_str())
+        .bind(restore_days)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(result)
+    }
+
+    /// Queue transitions of a bucket's objects that match a lifecycle rule
+    ///
+    /// Current versions under `prefix` created at least `min_age_days` ago
+    /// and not yet in `storage_class` are queued, up to `limit` of them.
+    /// Objects with an open job are skipped. Returns the number queued.
+    #[instrument(skip(self))]
+    pub async fn queue_lifecycle_transitions(
+        &self,
+        tenant: &str,
+        bucket: &str,
+        prefix: &str,
+        min_age_days: i32,
+        storage_class: StorageClass,
+        limit: i64,
+    ) -> Result<u64> {
+        let lower = format!("{}/{}", bucket, prefix);
+        let upper = prefix_upper_bound(&lower);
+        let result = sqlx::query(
+            r#"
+            INSERT INTO storage_class_jobs
+                (file_id, tenant_id, bucket, object_key, kind, storage_class)
+            SELECT f.id, f.tenant_id, f.bucket, substr(f.path, length(f.bucket) + 2),
+                   'transition', $5
+            FROM files f
+            WHERE f.tenant_id = $1 AND f.bucket = $2 AND f.deleted_at IS NULL
+              AND f.status = 'complete'
+              AND (f.expires_at IS NULL OR f.expires_at > NOW())
+              AND f.path COLLATE "C" >= $3
+              AND ($4::text IS NULL OR f.path COLLATE "C" < $4)
+              AND f.storage_class <> $5
+              AND f.created_at <= NOW() - make_interval(days => $6)
+              AND NOT EXISTS (
+                  SELECT 1 FROM storage_class_jobs j
+                  WHERE j.file_id = f.id AND j.status IN ('pending', 'running')
+              )
+            ORDER BY f.created_at
+            LIMIT $7
+            ON CONFLICT (file_id) WHERE status IN ('pending', 'running') DO NOTHING
+            "#,
+        )
+        .bind(tenant)
+        .bind(bucket)
+        .bind(&lower)
+        .bind(upper)
+        .bind(storage_class.as_str())
+        .bind(min_age_days)
+        .bind(limit)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Claim up to `limit` storage class jobs to run, restores first
+    ///
+    /// Jobs left running for longer than `stale_after` (their gateway died)
+    /// are claimed again.
+    pub async fn claim_storage_class_jobs(
+        &self,
+        limit: i64,
+        stale_after: Duration,
+    ) -> Result<Vec<StorageClassJob>> {
+        let result = sqlx::query_as::<_, StorageClassJob>(
+            r#"
+            UPDATE storage_class_jobs
+            SET status = 'running', started_at = NOW(), attempts = attempts + 1
+            WHERE id IN (
+                SELECT id FROM storage_class_jobs
+                WHERE status = 'pending'
+                   OR (status = 'running' AND started_at < NOW() - make_interval(secs => $2))
+                ORDER BY (kind = 'restore') DESC, created_at, id
+                LIMIT $1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING *
+            "#,
         )
-        .bind(file_id)
+        .bind(limit)
+        .bind(stale_after.as_secs_f64())
         .fetch_all(&self.pool)
         .await?;
         Ok(result)
     }
 
-    /// Update chunk status
-    pub async fn update_chunk_status(&self, chunk_id: &[u8], status: &str) -> Result<()> {
-        sqlx::query("UPDATE chunks SET status = $1 WHERE chunk_id = $2")
-            .bind(status)
-            .bind(chunk_id)
-            .execute(&self.pool)
-            .await?;
-        Ok(())
-    }
-
-    /// Update chunk replica count
-    pub async fn update_chunk_replicas(&self, chunk_id: &[u8], count: i32) -> Result<()> {
-        sqlx::query("UPDATE chunks SET current_replicas = $1 WHERE chunk_id = $2")
-            .bind(count)
-            .bind(chunk_id)
-            .execute(&self.pool)
-            .await?;
-        Ok(())
+    /// Record the outcome of a storage class job
+    ///
+    /// A failed job is retried until it has been attempted `max_attempts`
+    /// times. Returns the job's new status.
+    pub async fn finish_storage_class_job(
+        &self,
+        id: i64,
+        error: Option<&str>,
+        max_attempts: i32,
+    ) -> Result<String> {
+        let status = sqlx::query_scalar::<_, String>(
+            r#"
+            UPDATE storage_class_jobs
+            SET status = CASE
+                    WHEN $2::text IS NULL THEN 'done'
+                    WHEN attempts >= $3 THEN 'failed'
+                    ELSE 'pending'
+                END,
+                error = $2,
+                finished_at = NOW()
+            WHERE id = $1
+            RETURNING status
+            "#,
+        )
+        .bind(id)
+        .bind(error)
+        .bind(max_attempts)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or_else(|| DbError::NotFound(format!("storage class job {}", id)))?;
+        Ok(status)
     }
 
-    /// Increment chunk replica count
-    pub async fn increment_chunk_replicas(&self, chunk_id: &[u8]) -> Result<i32> {
-        let result = sqlx::query_scalar::<_, i32>(
+    /// The pending or running job of a file, if any
+    pub async fn get_open_storage_class_job(
+        &self,
+        file_id: Uuid,
+    ) -> Result<Option<StorageClassJob>> {
+        let result = sqlx::query_as::<_, StorageClassJob>(
             r#"
-            UPDATE chunks
-            SET current_replicas = current_replicas + 1
-            WHERE chunk_id = $1
-            RETURNING current_replicas
+            SELECT * FROM storage_class_jobs
+            WHERE file_id = $1 AND status IN ('pending', 'running')
             "#,
         )
-        .bind(chunk_id)
-        .fetch_one(&self.pool)
+        .bind(file_id)
+        .fetch_optional(&self.pool)
         .await?;
         Ok(result)
     }
 
-    /// Get under-replicated chunks
-    pub async fn get_under_replicated_chunks(
+    /// A bucket's most recent storage class jobs, newest first
+    pub async fn list_storage_class_jobs(
         &self,
+        tenant: &str,
+        bucket: &str,
         limit: i64,
-    ) -> Result<Vec<ChunkReplicationStatus>> {
-        let result = sqlx::query_as::<_, ChunkReplicationStatus>(
+    ) -> Result<Vec<StorageClassJob>> {
+        let result = sqlx::query_as::<_, StorageClassJob>(
             r#"
-            SELECT * FROM chunk_replication_status
-            WHERE replicas_needed > 0
-            ORDER BY replicas_needed DESC
-            LIMIT $1
+            SELECT * FROM storage_class_jobs
+            WHERE tenant_id = $1 AND bucket = $2
+            ORDER BY created_at DESC, id DESC
+            LIMIT $3
             "#,
         )
+        .bind(tenant)
+        .bind(bucket)
         .bind(limit)
-        .fetch_all(&self.pool)
+        .fetch_all(self.read_pool())
         .await?;
         Ok(result)
     }
 
-    // =========================================================================
-    // CHUNK LOCATION OPERATIONS
-    // =========================================================================
-
-    /// Record a chunk location
-    pub async fn add_chunk_location(
+    /// Replace a file's chunks with the re-encoded ones of a staged file
+    ///
+    /// In one transaction the shards of the file's current chunks are queued
+    /// for garbage collection, the staged file's chunks and erasure layout
+    /// move to the file, which takes `storage_class`, and the staged row is
+    /// deleted. A file moving back to STANDARD drops its restored copy, which
+    /// may itself be the staged file. Returns false and changes nothing if
+    /// the file is no longer the current version with `content_hash` or the
+    /// staged file is gone; a staged upload is then left to the janitor.
+    #[instrument(skip(self, content_hash))]
+    pub async fn swap_file_encoding(
         &self,
-        chunk_id: &[u8],
-        node_id: Uuid,
-    ) -> Result<ChunkLocation> {
-        let result = sqlx::query_as::<_, ChunkLocation>(
+        file_id: Uuid,
+        staged_id: Uuid,
+        content_hash: &[u8],
+        storage_class: StorageClass,
+    ) -> Result<bool> {
+        let mut tx = self.pool.begin().await?;
+
+        let current = sqlx::query_scalar::<_, Uuid>(
             r#"
-            INSERT INTO chunk_locations (chunk_id, node_id, status)
-            VALUES ($1, $2, 'stored')
-            ON CONFLICT (chunk_id, node_id) DO UPDATE SET status = 'stored'
-            RETURNING *
+            SELECT id FROM files
+            WHERE id = $1 AND deleted_at IS NULL AND status = 'complete' AND content_hash = $2
+            FOR UPDATE
             "#,
         )
-        .bind(chunk_id)
-        .bind(node_id)
-        .fetch_one(&self.pool)
+        .bind(file_id)
+        .bind(content_hash)
+        .fetch_optional(&mut *tx)
         .await?;
+        if current.is_none() || !lock_file_row(&mut tx, staged_id).await? {
+            return Ok(false);
+        }
 
-        // Update chunk replica count and activate chunk
-        let new_count = self.increment_chunk_replicas(chunk_id).await?;
+        // Old shards are queued under the staged file, which is gone once
+        // this commits, so a file under retention does not hold them back
+        let queued = sqlx::query(
+            r#"
+            INSERT INTO shard_gc_queue (file_id, chunk_id, node_id)
+            SELECT $2, cl.chunk_id, cl.node_id
+            FROM chunks c
+            JOIN chunk_locations cl ON cl.chunk_id = c.chunk_id
+            WHERE c.file_id = $1
+            ON CONFLICT (chunk_id, node_id) DO NOTHING
+            "#,
+        )
+        .bind(file_id)
+        .bind(staged_id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
 
-        // Mark chunk as active once it has at least one replica stored
-        if new_count == 1 {
-            self.update_chunk_status(chunk_id, "active").await?;
-        }
+        // Content addressing may give a new shard the ID of one already
+        // queued for removal
+        sqlx::query(
+            r#"
+            DELETE FROM shard_gc_queue
+            WHERE chunk_id IN (SELECT chunk_id FROM chunks WHERE file_id = $1)
+            "#,
+        )
+        .bind(staged_id)
+        .execute(&mut *tx)
+        .await?;
 
-        Ok(result)
-    }
+        sqlx::query("DELETE FROM chunks WHERE file_id = $1")
+            .bind(file_id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("UPDATE chunks SET file_id = $1 WHERE file_id = $2")
+            .bind(file_id)
+            .bind(staged_id)
+            .execute(&mut *tx)
+            .await?;
 
-    /// Get all locations for a chunk
-    pub async fn get_chunk_locations(&self, chunk_id: &[u8]) -> Result<Vec<ChunkLocation>> {
-        let result = sqlx::query_as::<_, ChunkLocation>(
-            "SELECT * FROM chunk_locations WHERE chunk_id = $1 AND status = 'stored'",
+        sqlx::query(
+            r#"
+            UPDATE files f
+            SET chunk_count = s.chunk_count,
+                data_shards = s.data_shards,
+                parity_shards = s.parity_shards,
+                chunk_size = s.chunk_size,
+                erasure_backend = s.erasure_backend,
+                storage_mode = s.storage_mode,
+                storage_class = $3,
+                restored_until = CASE WHEN $3 = 'ARCHIVE' THEN f.restored_until END,
+                restored_copy_id = CASE WHEN $3 = 'ARCHIVE' THEN f.restored_copy_id END
+            FROM files s
+            WHERE f.id = $1 AND s.id = $2
+            "#,
         )
-        .bind(chunk_id)
-        .fetch_all(&self.pool)
+        .bind(file_id)
+        .bind(staged_id)
+        .bind(storage_class.as_str())
+        .execute(&mut *tx)
         .await?;
-        Ok(result)
+
+        // The upload intent cascades
+        sqlx::query("DELETE FROM files WHERE id = $1")
+            .bind(staged_id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        debug!(
+            file_id = %file_id,
+            storage_class = storage_class.as_str(),
+            old_shards = queued,
+            "File re-encoded"
+        );
+        Ok(true)
     }
 
-    /// Get all chunk locations for a file (batch query to avoid N+1)
+    /// Make a staged upload the restored copy of an archived file
     ///
-    /// Returns a map of chunk_id -> list of node gRPC addresses
-    pub async fn get_file_chunk_locations(
+    /// The copy is kept until `until`; a previous copy is retired with its
+    /// shards queued for removal. A copy that expired but was not removed yet
+    /// can be attached again. Returns false and changes nothing if the file
+    /// is no longer a current archived version or the copy is gone; a staged
+    /// upload is then left to the janitor.
+    #[instrument(skip(self))]
+    pub async fn attach_restored_copy(
         &self,
         file_id: Uuid,
-    ) -> Result<HashMap<Vec<u8>, Vec<String>>> {
-        let rows = sqlx::query_as::<_, (Vec<u8>, String)>(
+        copy_id: Uuid,
+        until: chrono::DateTime<chrono::Utc>,
+    ) -> Result<bool> {
+        let mut tx = self.pool.begin().await?;
+
+        let previous = sqlx::query_scalar::<_, Option<Uuid>>(
             r#"
-            SELECT cl.chunk_id, n.grpc_address
-            FROM chunk_locations cl
-            JOIN chunks c ON cl.chunk_id = c.chunk_id
-            JOIN nodes n ON cl.node_id = n.id
-            WHERE c.file_id = $1 AND cl.status = 'stored' AND n.status = 'online'
-            ORDER BY cl.chunk_id
+            SELECT restored_copy_id FROM files
+            WHERE id = $1 AND deleted_at IS NULL AND status = 'complete'
+              AND storage_class = 'ARCHIVE'
+            FOR UPDATE
             "#,
         )
         .bind(file_id)
-        .fetch_all(&self.pool)
+        .fetch_optional(&mut *tx)
         .await?;
-
-        let mut map: HashMap<Vec<u8>, Vec<String>> = HashMap::new();
-        for (chunk_id, address) in rows {
-            map.entry(chunk_id).or_default().push(address);
+        let Some(previous) = previous else {
+            return Ok(false);
+        };
+        if !lock_file_row(&mut tx, copy_id).await? {
+            return Ok(false);
         }
-        Ok(map)
-    }
 
-    /// Get node addresses storing a chunk
-    pub async fn get_chunk_node_addresses(&self, chunk_id: &[u8]) -> Result<Vec<String>> {
-        let result = sqlx::query_scalar::<_, String>(
+        sqlx::query(
             r#"
-            SELECT n.grpc_address
-            FROM chunk_locations cl
-            JOIN nodes n ON cl.node_id = n.id
-            WHERE cl.chunk_id = $1 AND cl.status = 'stored' AND n.status = 'online'
+            DELETE FROM shard_gc_queue
+            WHERE chunk_id IN (SELECT chunk_id FROM chunks WHERE file_id = $1)
             "#,
         )
-        .bind(chunk_id)
-        .fetch_all(&self.pool)
+        .bind(copy_id)
+        .execute(&mut *tx)
         .await?;
-        Ok(result)
-    }
 
-    /// Remove a chunk location (e.g., node went offline)
-    pub async fn remove_chunk_location(&self, chunk_id: &[u8], node_id: Uuid) -> Result<()> {
-        sqlx::query("DELETE FROM chunk_locations WHERE chunk_id = $1 AND node_id = $2")
-            .bind(chunk_id)
-            .bind(node_id)
-            .execute(&self.pool)
+        if let Some(previous) = previous.filter(|id| *id != copy_id) {
+            retire_files(&mut tx, &[previous]).await?;
+        }
+
+        sqlx::query("UPDATE files SET status = 'restored' WHERE id = $1")
+            .bind(copy_id)
+            .execute(&mut *tx)
             .await?;
-        Ok(())
+        sqlx::query("DELETE FROM upload_intents WHERE file_id = $1")
+            .bind(copy_id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("UPDATE files SET restored_until = $2, restored_copy_id = $3 WHERE id = $1")
+            .bind(file_id)
+            .bind(until)
+            .bind(copy_id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        debug!(file_id = %file_id, copy_id = %copy_id, %until, "Restored copy attached");
+        Ok(true)
     }
 
-    /// Update chunk location verification
-    pub async fn update_chunk_verification(
+    /// Keep the restored copy of an archived file until at least `until`
+    ///
+    /// Returns false if the file has no restored copy that is still valid.
+    pub async fn extend_restored_copy(
         &self,
-        chunk_id: &[u8],
-        node_id: Uuid,
-        valid: bool,
-    ) -> Result<()> {
-        if valid {
-            sqlx::query(
-                r#"
-                UPDATE chunk_locations
-                SET last_verified = NOW(), status = 'verified', verification_failures = 0
-                WHERE chunk_id = $1 AND node_id = $2
-                "#,
-            )
-            .bind(chunk_id)
-            .bind(node_id)
-            .execute(&self.pool)
-            .await?;
-        } else {
-            sqlx::query(
-                r#"
-                UPDATE chunk_locations
-                SET verification_failures = verification_failures + 1,
-                    status = CASE WHEN verification_failures >= 2 THEN 'failed' ELSE status END
-                WHERE chunk_id = $1 AND node_id = $2
-                "#,
-            )
-            .bind(chunk_id)
-            .bind(node_id)
-            .execute(&self.pool)
-            .await?;
+        file_id: Uuid,
+        until: chrono::DateTime<chrono::Utc>,
+    ) -> Result<bool> {
+        let result = sqlx::query(
+            r#"
+            UPDATE files SET restored_until = GREATEST(restored_until, $2)
+            WHERE id = $1 AND restored_copy_id IS NOT NULL AND restored_until > NOW()
+            "#,
+        )
+        .bind(file_id)
+        .bind(until)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Remove up to `limit` restored copies that are no longer served
+    ///
+    /// Copies that expired, and copies whose archived file was deleted or
+    /// transitioned, are deleted with their shards queued for removal.
+    /// Returns the number of copies removed.
+    #[instrument(skip(self))]
+    pub async fn expire_restored_copies(&self, limit: i64) -> Result<u64> {
+        let mut tx = self.pool.begin().await?;
+
+        let expired = sqlx::query_scalar::<_, Uuid>(
+            r#"
+            SELECT c.id FROM files c
+            WHERE c.status = 'restored'
+              AND NOT EXISTS (
+                  SELECT 1 FROM files f
+                  WHERE f.restored_copy_id = c.id AND f.restored_until > NOW()
+                    AND f.deleted_at IS NULL AND f.status = 'complete'
+              )
+            LIMIT $1
+            FOR UPDATE SKIP LOCKED
+            "#,
+        )
+        .bind(limit)
+        .fetch_all(&mut *tx)
+        .await?;
+        if expired.is_empty() {
+            return Ok(0);
         }
-        Ok(())
+
+        sqlx::query(
+            r#"
+            UPDATE files SET restored_until = NULL, restored_copy_id = NULL
+            WHERE restored_copy_id = ANY($1)
+            "#,
+        )
+        .bind(&expired)
+        .execute(&mut *tx)
+        .await?;
+        retire_files(&mut tx, &expired).await?;
+
+        tx.commit().await?;
+        debug!(copies = expired.len(), "Restored copies expired");
+        Ok(expired.len() as u64)
     }
 
     // =========================================================================
-    // USER OPERATIONS
+    // REPLICATION OPERATIONS
     // =========================================================================
 
-    /// Create a new user
-    pub async fn create_user(
+    /// Create a bucket replication rule
+    #[instrument(skip(self, rule), fields(bucket = %rule.bucket))]
+    pub async fn create_replication_rule(
         &self,
-        wallet_address: Option<&str>,
-        email: Option<&str>,
-        username: Option<&str>,
-    ) -> Result<User> {
-        let result = sqlx::query_as::<_, User>(
+        rule: CreateReplicationRule,
+    ) -> Result<ReplicationRule> {
+        let result = sqlx::query_as::<_, ReplicationRule>(
             r#"
-            INSERT INTO users (wallet_address, email, username)
-            VALUES ($1, $2, $3)
+            INSERT INTO replication_rules (tenant_id, bucket, prefix, target_type, endpoint,
+                                           target_bucket, region, access_token, access_key_id,
+                                           secret_access_key, replicate_deletes)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
             RETURNING *
             "#,
         )
-        .bind(wallet_address)
-        .bind(email)
-        .bind(username)
+        .bind(&rule.tenant_id)
+        .bind(&rule.bucket)
+        .bind(&rule.prefix)
+        .bind(&rule.target_type)
+        .bind(&rule.endpoint)
+        .bind(&rule.target_bucket)
+        .bind(&rule.region)
+        .bind(&rule.access_token)
+        .bind(&rule.access_key_id)
+        .bind(&rule.secret_access_key)
+        .bind(rule.replicate_deletes)
         .fetch_one(&self.pool)
         .await?;
         Ok(result)
     }
 
-    /// Get user by wallet address
-    pub async fn get_user_by_wallet(&self, wallet_address: &str) -> Result<Option<User>> {
-        let result = sqlx::query_as::<_, User>("SELECT * FROM users WHERE wallet_address = $1")
-            .bind(wallet_address)
-            .fetch_optional(&self.pool)
-            .await?;
-        Ok(result)
-    }
-
-    /// Update user storage usage
-    pub async fn update_user_storage(&self, user_id: Uuid, storage_used: i64) -> Result<()> {
-        sqlx::query("UPDATE users SET storage_used = $1 WHERE id = $2")
-            .bind(storage_used)
-            .bind(user_id)
-            .execute(&self.pool)
-            .await?;
-        Ok(())
-    }
-
-    /// Get user by email or wallet address
-    pub async fn get_user_by_email_or_wallet(&self, identifier: &str) -> Result<Option<User>> {
-        let result = sqlx::query_as::<_, User>(
-            "SELECT * FROM users WHERE email = $1 OR wallet_address = $1",
-        )
-        .bind(identifier)
-        .fetch_optional(&self.pool)
-        .await?;
+    /// Get a replication rule by ID
+    pub async fn get_replication_rule(&self, id: Uuid) -> Result<Option<ReplicationRule>> {
+        let result =
+            sqlx::query_as::<_, ReplicationRule>("SELECT * FROM replication_rules WHERE id = $1")
+                .bind(id)
+                .fetch_optional(&self.pool)
+                .await?;
         Ok(result)
     }
 
-    // =========================================================================
-    // BUCKET OPERATIONS
-    // =========================================================================
-
-    /// Create a new bucket
-    pub async fn create_bucket(&self, name: &str, owner_id: Uuid) -> Result<Bucket> {
-        let result = sqlx::query_as::<_, Bucket>(
+    /// List replication rules, optionally only the enabled ones
+    pub async fn list_replication_rules(&self, enabled_only: bool) -> Result<Vec<ReplicationRule>> {
+        let result = sqlx::query_as::<_, ReplicationRule>(
             r#"
-            INSERT INTO buckets (name, owner_id)
-            VALUES ($1, $2)
-            RETURNING *
+            SELECT * FROM replication_rules
+            WHERE enabled OR NOT $1
+            ORDER BY bucket, created_at
             "#,
         )
-        .bind(name)
-        .bind(owner_id)
-        .fetch_one(&self.pool)
+        .bind(enabled_only)
+        .fetch_all(&self.pool)
         .await?;
         Ok(result)
     }
 
-    /// Get bucket by name
-    pub async fn get_bucket(&self, name: &str) -> Result<Option<Bucket>> {
-        let result = sqlx::query_as::<_, Bucket>("SELECT * FROM buckets WHERE name = $1")
-            .bind(name)
-            .fetch_optional(&self.pool)
+    /// Enable or disable a replication rule
+    pub async fn set_replication_rule_enabled(&self, id: Uuid, enabled: bool) -> Result<bool> {
+        let result = sqlx::query("UPDATE replication_rules SET enabled = $1 WHERE id = $2")
+            .bind(enabled)
+            .bind(id)
+            .execute(&self.pool)
             .await?;
-        Ok(result)
-    }
-
-    /// List buckets for a user
-    pub async fn list_user_buckets(&self, owner_id: Uuid) -> Result<Vec<Bucket>> {
-        let result =
-            sqlx::query_as::<_, Bucket>("SELECT * FROM buckets WHERE owner_id = $1 ORDER BY name")
-                .bind(owner_id)
-                .fetch_all(&self.pool)
-                .await?;
-        Ok(result)
+        Ok(result.rows_affected() > 0)
     }
 
-    /// Delete a bucket by name
-    ///
-    /// Note: This performs a hard delete. Make sure the bucket is empty first.
-    pub async fn delete_bucket(&self, name: &str) -> Result<()> {
-        sqlx::query("DELETE FROM buckets WHERE name = $1")
-            .bind(name)
+    /// Delete a replication rule and its object status
+    pub async fn delete_replication_rule(&self, id: Uuid) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM replication_rules WHERE id = $1")
+            .bind(id)
             .execute(&self.pool)
             .await?;
-        Ok(())
+        Ok(result.rows_affected() > 0)
     }
 
-    /// Check if a bucket is empty (has no files)
-    pub async fn bucket_is_empty(&self, bucket_name: &str) -> Result<bool> {
-        let count: (i64,) =
-            sqlx::query_as("SELECT COUNT(*) FROM files WHERE bucket = $1 AND deleted_at IS NULL")
-                .bind(bucket_name)
-                .fetch_one(&self.pool)
-                .await?;
-        Ok(count.0 == 0)
+    /// File changes (uploads and deletes) after a rule's cursor, in change order
+    ///
+    /// Changes newer than `settle` are left for the next scan: a transaction
+    /// that started earlier may still commit a row with an older `updated_at`.
+    pub async fn get_replication_changes(
+        &self,
+        rule: &ReplicationRule,
+        settle: Duration,
+        limit: i64,
+    ) -> Result<Vec<File>> {
+        let result = sqlx::query_as::<_, File>(
+            r#"
+            SELECT * FROM files
+            WHERE tenant_id = $1 AND bucket = $2 AND path LIKE $3
+              AND status IN ('complete', 'deleted')
+              AND (updated_at, id) > ($4, $5)
+              AND updated_at < NOW() - make_interval(secs => $6)
+            ORDER BY updated_at, id
+            LIMIT $7
+            "#,
+        )
+        .bind(&rule.tenant_id)
+        .bind(&rule.bucket)
+        .bind(format!("{}/{}%", rule.bucket, rule.prefix))
+        .bind(rule.cursor_updated_at)
+        .bind(rule.cursor_file_id)
+        .bind(settle.as_secs_f64())
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(result)
     }
 
-    /// Count files in a bucket
-    pub async fn count_files_in_bucket(&self, bucket_name: &str) -> Result<i64> {
-        let count: (i64,) =
-            sqlx::query_as("SELECT COUNT(*) FROM files WHERE bucket = $1 AND deleted_at IS NULL")
-                .bind(bucket_name)
-                .fetch_one(&self.pool)
-                .await?;
-        Ok(count.0)
+    /// Move a rule's cursor past a processed change
+    pub async fn advance_replication_cursor(
+        &self,
+        rule_id: Uuid,
+        updated_at: chrono::DateTime<chrono::Utc>,
+        file_id: Uuid,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE replication_rules
+            SET cursor_updated_at = $2, cursor_file_id = $3, last_run_at = NOW()
+            WHERE id = $1
+            "#,
+        )
+        .bind(rule_id)
+        .bind(updated_at)
+        .bind(file_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
     }
 
-    // =========================================================================
-    // REPAIR JOB OPERATIONS
-    // =========================================================================
+    /// Record the outcome of replicating one object
+    pub async fn record_replication_result(
+        &self,
+        rule_id: Uuid,
+        path: &str,
+        file_id: Uuid,
+        operation: &str,
+        error: Option<&str>,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO replication_objects (rule_id, path, file_id, operation, status,
+                                             attempts, last_error)
+            VALUES ($1, $2, $3, $4, CASE WHEN $5::TEXT IS NULL THEN 'completed' ELSE 'failed' END,
+                    1, $5)
+            ON CONFLICT (rule_id, path) DO UPDATE SET
+                file_id = EXCLUDED.file_id,
+                operation = EXCLUDED.operation,
+                status = EXCLUDED.status,
+                -- A new version of the object starts counting afresh
+                attempts = CASE
+                    WHEN replication_objects.file_id = EXCLUDED.file_id
+                        AND replication_objects.operation = EXCLUDED.operation
+                    THEN replication_objects.attempts + 1
+                    ELSE 1
+                END,
+                last_error = EXCLUDED.last_error,
+                updated_at = NOW()
+            "#,
+        )
+        .bind(rule_id)
+        .bind(path)
+        .bind(file_id)
+        .bind(operation)
+        .bind(error)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
 
-    /// Create a repair job
-    pub async fn create_repair_job(
+    /// Failed objects of a rule that have attempts left
+    pub async fn get_failed_replication_objects(
         &self,
-        chunk_id: &[u8],
-        source_node_id: Option<Uuid>,
-        target_node_id: Uuid,
-        priority: i32,
-    ) -> Result<RepairJob> {
-        let result = sqlx::query_as::<_, RepairJob>(
+        rule_id: Uuid,
+        max_attempts: i32,
+        limit: i64,
+    ) -> Result<Vec<ReplicationObject>> {
+        let result = sqlx::query_as::<_, ReplicationObject>(
             r#"
-            INSERT INTO repair_jobs (chunk_id, source_node_id, target_node_id, priority)
-            VALUES ($1, $2, $3, $4)
-            RETURNING *
+            SELECT * FROM replication_objects
+            WHERE rule_id = $1 AND status = 'failed' AND attempts < $2
+            ORDER BY updated_at
+            LIMIT $3
             "#,
         )
-        .bind(chunk_id)
-        .bind(source_node_id)
-        .bind(target_node_id)
-        .bind(priority)
-        .fetch_one(&self.pool)
+        .bind(rule_id)
+        .bind(max_attempts)
+        .bind(limit)
+        .fetch_all(&self.pool)
         .await?;
         Ok(result)
     }
 
-    /// Get pending repair jobs
-    pub async fn get_pending_repair_jobs(&self, limit: i64) -> Result<Vec<RepairJob>> {
-        let result = sqlx::query_as::<_, RepairJob>(
+    /// Most recent failures of a rule
+    pub async fn get_recent_replication_failures(
+        &self,
+        rule_id: Uuid,
+        limit: i64,
+    ) -> Result<Vec<ReplicationObject>> {
+        let result = sqlx::query_as::<_, ReplicationObject>(
             r#"
-            SELECT * FROM repair_jobs
-            WHERE status = 'pending'
-            ORDER BY priority DESC, created_at ASC
-            LIMIT $1
+            SELECT * FROM replication_objects
+            WHERE rule_id = $1 AND status = 'failed'
+            ORDER BY updated_at DESC
+            LIMIT $2
             "#,
         )
+        .bind(rule_id)
         .bind(limit)
         .fetch_all(&self.pool)
         .await?;
         Ok(result)
     }
 
-    /// Update repair job status
-    pub async fn update_repair_job_status(
-        &self,
-        job_id: Uuid,
-        status: &str,
-        error: Option<&str>,
-    ) -> Result<()> {
-        sqlx::query(
+    /// Completed, failed and pending counts for a rule
+    pub async fn get_replication_stats(&self, rule: &ReplicationRule) -> Result<ReplicationStats> {
+        let (completed, failed): (i64, i64) = sqlx::query_as(
             r#"
-            UPDATE repair_jobs
-            SET status = $1,
-                error_message = $2,
-                started_at = CASE WHEN $1 = 'in_progress' THEN NOW() ELSE started_at END,
-                completed_at = CASE WHEN $1 IN ('completed', 'failed') THEN NOW() ELSE completed_at END
-            WHERE id = $3
+            SELECT COUNT(*) FILTER (WHERE status = 'completed'),
+                   COUNT(*) FILTER (WHERE status = 'failed')
+            FROM replication_objects
+            WHERE rule_id = $1
             "#,
         )
-        .bind(status)
-        .bind(error)
-        .bind(job_id)
-        .execute(&self.pool)
+        .bind(rule.id)
+        .fetch_one(&self.pool)
         .await?;
-        Ok(())
+
+        let pending = sqlx::query_scalar::<_, i64>(
+            r#"
+            SELECT COUNT(*) FROM files
+            WHERE tenant_id = $1 AND bucket = $2 AND path LIKE $3
+              AND status IN ('complete', 'deleted')
+              AND (updated_at, id) > ($4, $5)
+            "#,
+        )
+        .bind(&rule.tenant_id)
+        .bind(&rule.bucket)
+        .bind(format!("{}/{}%", rule.bucket, rule.prefix))
+        .bind(rule.cursor_updated_at)
+        .bind(rule.cursor_file_id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(ReplicationStats {
+            completed,
+            failed,
+            pending,
+        })
     }
 
     // =========================================================================
@@ -1380,6 +5153,94 @@ impl Database {
         Ok(result)
     }
 
+    /// Get a node's slashing event for a reason in an epoch, if any
+    pub async fn find_slashing_event(
+        &self,
+        node_id: Uuid,
+        epoch: i64,
+        reason: &str,
+    ) -> Result<Option<SlashingEvent>> {
+        let result = sqlx::query_as::<_, SlashingEvent>(
+            r#"
+            SELECT * FROM node_slashing_events
+            WHERE node_id = $1 AND epoch = $2 AND reason = $3
+            ORDER BY created_at
+            LIMIT 1
+            "#,
+        )
+        .bind(node_id)
+        .bind(epoch)
+        .bind(reason)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(result)
+    }
+
+    /// Record evidence of a failed proof-of-storage challenge
+    #[instrument(skip(self, evidence), fields(node_id = %evidence.node_id))]
+    pub async fn record_slashing_evidence(
+        &self,
+        evidence: &CreateSlashingEvidence,
+    ) -> Result<SlashingEvidence> {
+        let result = sqlx::query_as::<_, SlashingEvidence>(
+            r#"
+            INSERT INTO slashing_evidence (
+                node_id, chunk_id, epoch, reason, challenge, expected_proof,
+                returned_proof, error, failure_count, first_failed_at, last_verified,
+                challenged_at, responded_at, slashing_event_id, tx_signature
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15)
+            RETURNING *
+            "#,
+        )
+        .bind(evidence.node_id)
+        .bind(&evidence.chunk_id)
+        .bind(evidence.epoch)
+        .bind(evidence.reason.to_string())
+        .bind(&evidence.challenge)
+        .bind(&evidence.expected_proof)
+        .bind(&evidence.returned_proof)
+        .bind(&evidence.error)
+        .bind(evidence.failure_count)
+        .bind(evidence.first_failed_at)
+        .bind(evidence.last_verified)
+        .bind(evidence.challenged_at)
+        .bind(evidence.responded_at)
+        .bind(evidence.slashing_event_id)
+        .bind(&evidence.tx_signature)
+        .fetch_one(&self.pool)
+        .await?;
+
+        debug!(
+            node_id = %evidence.node_id,
+            chunk_id = %hex::encode(&evidence.chunk_id),
+            reason = %evidence.reason,
+            "Slashing evidence recorded"
+        );
+        Ok(result)
+    }
+
+    /// List slashing evidence, newest first, optionally for one node
+    pub async fn list_slashing_evidence(
+        &self,
+        node_id: Option<Uuid>,
+        limit: i64,
+    ) -> Result<Vec<SlashingEvidence>> {
+        let result = sqlx::query_as::<_, SlashingEvidence>(
+            r#"
+            SELECT * FROM slashing_evidence
+            WHERE $1::uuid IS NULL OR node_id = $1
+            ORDER BY created_at DESC
+            LIMIT $2
+            "#,
+        )
+        .bind(node_id)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(result)
+    }
+
     // =========================================================================
     // PAYMENT EPOCH OPERATIONS
     // =========================================================================
@@ -1484,6 +5345,66 @@ impl Database {
         Ok(())
     }
 
+    /// Store the payout report of a distributed epoch
+    ///
+    /// A report is written once; saving an epoch again keeps the first one.
+    #[instrument(skip(self, report))]
+    pub async fn save_epoch_payout_report(&self, report: &EpochPayoutReport) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO epoch_payout_reports (
+                epoch, total_pool_amount, nodes_share, platform_share, community_share,
+                total_weight, nodes_paid, total_paid, payouts, dry_run,
+                finalize_tx_signature, platform_claim_tx_signature,
+                community_claim_tx_signature, next_epoch_tx_signature
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)
+            ON CONFLICT (epoch) DO NOTHING
+            "#,
+        )
+        .bind(report.epoch)
+        .bind(report.total_pool_amount)
+        .bind(report.nodes_share)
+        .bind(report.platform_share)
+        .bind(report.community_share)
+        .bind(report.total_weight)
+        .bind(report.nodes_paid)
+        .bind(report.total_paid)
+        .bind(&report.payouts)
+        .bind(report.dry_run)
+        .bind(&report.finalize_tx_signature)
+        .bind(&report.platform_claim_tx_signature)
+        .bind(&report.community_claim_tx_signature)
+        .bind(&report.next_epoch_tx_signature)
+        .execute(&self.pool)
+        .await?;
+
+        debug!(epoch = report.epoch, "Epoch payout report saved");
+        Ok(())
+    }
+
+    /// Get the payout report of an epoch
+    pub async fn get_epoch_payout_report(&self, epoch: i64) -> Result<Option<EpochPayoutReport>> {
+        let result = sqlx::query_as::<_, EpochPayoutReport>(
+            "SELECT * FROM epoch_payout_reports WHERE epoch = $1",
+        )
+        .bind(epoch)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(result)
+    }
+
+    /// List payout reports, newest epoch first
+    pub async fn list_epoch_payout_reports(&self, limit: i64) -> Result<Vec<EpochPayoutReport>> {
+        let result = sqlx::query_as::<_, EpochPayoutReport>(
+            "SELECT * FROM epoch_payout_reports ORDER BY epoch DESC LIMIT $1",
+        )
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(result)
+    }
+
     /// Get nodes with extended downtime (>4 hours offline in an epoch)
     /// These nodes should be slashed for extended downtime
     pub async fn get_nodes_with_extended_downtime(
@@ -1763,7 +5684,7 @@ impl Database {
     pub async fn get_all_public_datasets(&self) -> Result<Vec<PublicDataset>> {
         let result =
             sqlx::query_as::<_, PublicDataset>("SELECT * FROM public_datasets ORDER BY name")
-                .fetch_all(&self.pool)
+                .fetch_all(self.read_pool())
                 .await?;
         Ok(result)
     }
@@ -1793,11 +5714,11 @@ impl Database {
                 "SELECT * FROM public_datasets WHERE name ILIKE $1 ORDER BY name",
             )
             .bind(pattern)
-            .fetch_all(&self.pool)
+            .fetch_all(self.read_pool())
             .await?
         } else {
             sqlx::query_as::<_, PublicDataset>("SELECT * FROM public_datasets ORDER BY name")
-                .fetch_all(&self.pool)
+                .fetch_all(self.read_pool())
                 .await?
         };
         Ok(result)
@@ -2013,6 +5934,47 @@ impl Database {
         Ok(())
     }
 
+    /// Dataset through which a user may stream a file
+    ///
+    /// Returns a dataset holding the file that the user owns or has an
+    /// unexpired share with `stream` permission on.
+    pub async fn find_file_stream_grant(
+        &self,
+        file_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<Option<Uuid>> {
+        let result = sqlx::query_scalar::<_, Uuid>(
+            r#"
+            SELECT df.dataset_id FROM dataset_files df
+            JOIN datasets d ON d.id = df.dataset_id
+            LEFT JOIN dataset_shares ds ON ds.dataset_id = d.id
+                AND ds.shared_with_user_id = $2
+                AND (ds.expires_at IS NULL OR ds.expires_at > NOW())
+            WHERE df.file_id = $1
+            AND (d.owner_id = $2 OR ds.permissions && ARRAY['stream', '*'])
+            ORDER BY (d.owner_id = $2) DESC
+            LIMIT 1
+            "#,
+        )
+        .bind(file_id)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(result)
+    }
+
+    /// Check whether a dataset holds a file
+    pub async fn dataset_contains_file(&self, dataset_id: Uuid, file_id: Uuid) -> Result<bool> {
+        let result = sqlx::query_scalar::<_, bool>(
+            "SELECT EXISTS(SELECT 1 FROM dataset_files WHERE dataset_id = $1 AND file_id = $2)",
+        )
+        .bind(dataset_id)
+        .bind(file_id)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(result)
+    }
+
     /// Get dataset summary with trust info
     pub async fn get_dataset_summary(&self, dataset_id: Uuid) -> Result<Option<DatasetSummary>> {
         let result = sqlx::query_as::<_, DatasetSummary>(
@@ -2083,14 +6045,16 @@ impl Database {
         Ok(result)
     }
 
-    /// List files in a bucket with optional prefix filter
+    /// List files in a tenant's bucket with optional prefix filter
     pub async fn list_files_by_bucket_prefix(
         &self,
+        tenant: &str,
         bucket: &str,
         prefix: Option<&str>,
         limit: i32,
     ) -> Result<Vec<File>> {
-        self.list_files_in_bucket(bucket, prefix, limit as i64, 0).await
+        self.list_files_in_bucket(tenant, bucket, prefix, None, limit as i64, false, None)
+            .await
     }
 
     /// Share a dataset with another user (convenience method)
@@ -2116,15 +6080,160 @@ impl Database {
     }
 }
 
+/// Smallest string above every string starting with `prefix` (bytewise)
+///
+/// Increments the last character that can be incremented, so the result
+/// bounds a `COLLATE "C"` range scan. None if no such string exists.
+/// Lock a file row for the rest of the transaction
+///
+/// Returns false if the row does not exist.
+async fn lock_file_row(tx: &mut PgConnection, file_id: Uuid) -> Result<bool> {
+    let row = sqlx::query_scalar::<_, Uuid>("SELECT id FROM files WHERE id = $1 FOR UPDATE")
+        .bind(file_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+    Ok(row.is_some())
+}
+
+/// Delete file rows, queueing their shards for removal first
+///
+/// Their chunks cascade; the queued shards' locations go once the shards
+/// are removed from the nodes.
+async fn retire_files(tx: &mut PgConnection, file_ids: &[Uuid]) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO shard_gc_queue (file_id, chunk_id, node_id)
+        SELECT c.file_id, cl.chunk_id, cl.node_id
+        FROM chunks c
+        JOIN chunk_locations cl ON cl.chunk_id = c.chunk_id
+        WHERE c.file_id = ANY($1)
+        ON CONFLICT (chunk_id, node_id) DO NOTHING
+        "#,
+    )
+    .bind(file_ids)
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query("DELETE FROM files WHERE id = ANY($1)")
+        .bind(file_ids)
+        .execute(&mut *tx)
+        .await?;
+    Ok(())
+}
+
+fn prefix_upper_bound(prefix: &str) -> Option<String> {
+    let mut chars: Vec<char> = prefix.chars().collect();
+    while let Some(last) = chars.pop() {
+        let next = (last as u32 + 1..=char::MAX as u32).find_map(char::from_u32);
+        if let Some(next) = next {
+            chars.push(next);
+            return Some(chars.into_iter().collect());
+        }
+    }
+    None
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_prefix_upper_bound() {
+        assert_eq!(
+            prefix_upper_bound("photos/2024"),
+            Some("photos/2025".to_string())
+        );
+        assert_eq!(prefix_upper_bound("b/"), Some("b0".to_string()));
+        // Surrogates are skipped, maximal chars roll over to the previous one
+        assert_eq!(
+            prefix_upper_bound("a\u{D7FF}"),
+            Some("a\u{E000}".to_string())
+        );
+        assert_eq!(prefix_upper_bound("a\u{10FFFF}"), Some("b".to_string()));
+        assert_eq!(prefix_upper_bound("\u{10FFFF}"), None);
+        assert_eq!(prefix_upper_bound(""), None);
+    }
+
+    #[test]
+    fn test_object_lock_key() {
+        let key = object_lock_key("acme", "photos/cat.jpg");
+        assert_eq!(key, object_lock_key("acme", "photos/cat.jpg"));
+        assert_ne!(key, object_lock_key("globex", "photos/cat.jpg"));
+        assert_ne!(key, object_lock_key("acme", "photos/dog.jpg"));
+        // The separator keeps tenant and path from running together
+        assert_ne!(object_lock_key("ab", "c"), object_lock_key("a", "bc"));
+    }
+
+    #[test]
+    fn test_schema_status() {
+        let expected = vec![(1, vec![1]), (2, vec![2]), (3, vec![3])];
+
+        let current = SchemaStatus::compare(
+            &expected,
+            &[(1, true, vec![1]), (2, true, vec![2]), (3, true, vec![3])],
+        );
+        assert!(current.is_current());
+        assert_eq!(current.applied_version, Some(3));
+        assert_eq!(current.expected_version, 3);
+
+        let empty = SchemaStatus::compare(&expected, &[]);
+        assert_eq!(empty.pending, vec![1, 2, 3]);
+        assert_eq!(empty.applied_version, None);
+        assert!(!empty.is_current());
+
+        // Migration 3 failed, 2 was edited after it ran, 4 is from a newer binary
+        let broken = SchemaStatus::compare(
+            &expected,
+            &[
+                (1, true, vec![1]),
+                (2, true, vec![9]),
+                (3, false, vec![3]),
+                (4, true, vec![4]),
+            ],
+        );
+        assert!(broken.pending.is_empty());
+        assert_eq!(broken.failed, vec![3]);
+        assert_eq!(broken.modified, vec![2]);
+        assert_eq!(broken.unknown, vec![4]);
+        assert_eq!(
+            broken.to_string(),
+            "schema version 4, binary expects 3; unknown to this binary: [4]; failed: [3]; modified: [2]"
+        );
+    }
+
     #[test]
     fn test_db_config_default() {
         let config = DbConfig::default();
         assert_eq!(config.max_connections, 10);
         assert_eq!(config.min_connections, 2);
+        assert_eq!(config.max_lock_connections, 20);
+        assert!(config.read_url.is_none());
+    }
+
+    #[test]
+    fn test_reconnect_backoff() {
+        assert_eq!(reconnect_backoff(0), Duration::from_millis(500));
+        assert_eq!(reconnect_backoff(1), Duration::from_secs(1));
+        assert_eq!(reconnect_backoff(3), Duration::from_secs(4));
+        assert_eq!(reconnect_backoff(10), RECONNECT_BACKOFF_MAX);
+        assert_eq!(reconnect_backoff(u32::MAX), RECONNECT_BACKOFF_MAX);
+    }
+
+    #[test]
+    fn test_connection_errors_are_unavailable() {
+        for e in [
+            sqlx::Error::PoolTimedOut,
+            sqlx::Error::WorkerCrashed,
+            sqlx::Error::Protocol("unexpected EOF".to_string()),
+        ] {
+            let e = DbError::from(e);
+            assert!(e.is_connection_error());
+            assert_eq!(e.error_code(), ErrorCode::MetadataUnavailable);
+        }
+
+        let e = DbError::from(sqlx::Error::RowNotFound);
+        assert!(!e.is_connection_error());
+        assert_eq!(e.error_code(), ErrorCode::NotFound);
+        assert!(!DbError::Invalid("bad".to_string()).is_connection_error());
     }
 
     #[test]
@@ -2,6 +2,7 @@
 //!
 //! Implements configurable read/write quorum for distributed storage.
 
+use cyxcloud_core::error::{ErrorCode, HasErrorCode};
 use futures::future::join_all;
 use std::time::Duration;
 use thiserror::Error;
@@ -30,6 +31,20 @@ pub enum QuorumError {
     Network(String),
 }
 
+impl HasErrorCode for QuorumError {
+    fn error_code(&self) -> ErrorCode {
+        match self {
+            QuorumError::QuorumNotAchieved { .. } | QuorumError::AllFailed => {
+                ErrorCode::InsufficientShards
+            }
+            QuorumError::NoNodesAvailable => ErrorCode::NoNodesAvailable,
+            QuorumError::Timeout => ErrorCode::Timeout,
+            QuorumError::Database(_) => ErrorCode::MetadataUnavailable,
+            QuorumError::Network(_) => ErrorCode::NodeUnreachable,
+        }
+    }
+}
+
 pub type Result<T> = std::result::Result<T, QuorumError>;
 
 /// Quorum configuration
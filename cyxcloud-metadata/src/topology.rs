@@ -2,14 +2,62 @@
 //!
 //! Implements placement strategies that consider:
 //! - Datacenter distribution (no more than N shards per DC)
-//! - Rack awareness (spread across racks)
+//! - Rack awareness (spread across racks within a datacenter)
 //! - Geographic proximity (for latency optimization)
 //! - Node capacity and utilization
+//! - Warm-up ramp for newly joined nodes
+//! - Node reputation (verification, repair, stability and latency record)
+//! - Anti-affinity between shards of the same chunk (node and rack)
 
-use crate::models::Node;
+use crate::models::{Node, NEUTRAL_REPUTATION};
+use chrono::{DateTime, Utc};
 use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use std::time::Duration;
 use tracing::{debug, warn};
 
+/// How strictly shards of the same chunk are kept apart
+///
+/// Applies to both nodes and racks: two shards of a chunk should never share
+/// a node, and should not share a rack when the cluster has enough racks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AntiAffinity {
+    /// Never co-locate shards; leave a shard under-placed instead
+    Strict,
+    /// Avoid co-location, but fall back to it when no other node fits
+    #[default]
+    BestEffort,
+    /// Ignore existing shard locations
+    Off,
+}
+
+impl AntiAffinity {
+    /// Effective level for a cluster of `eligible_nodes` nodes
+    ///
+    /// Strict placement cannot be satisfied on small clusters, so it is
+    /// relaxed to best-effort below `min_nodes`.
+    pub fn effective(self, eligible_nodes: usize, min_nodes: usize) -> Self {
+        if self == AntiAffinity::Strict && eligible_nodes < min_nodes {
+            AntiAffinity::BestEffort
+        } else {
+            self
+        }
+    }
+}
+
+impl FromStr for AntiAffinity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "strict" => Ok(AntiAffinity::Strict),
+            "best-effort" | "best_effort" | "besteffort" => Ok(AntiAffinity::BestEffort),
+            "off" | "disabled" | "none" => Ok(AntiAffinity::Off),
+            other => Err(format!("unknown anti-affinity level: {}", other)),
+        }
+    }
+}
+
 /// Placement strategy configuration
 #[derive(Debug, Clone)]
 pub struct PlacementConfig {
@@ -25,6 +73,16 @@ pub struct PlacementConfig {
     pub proximity_weight: f64,
     /// Minimum available storage per node (bytes)
     pub min_available_storage: u64,
+    /// Time for a newly joined node to ramp up to full placement weight
+    pub warmup_period: Duration,
+    /// Placement weight applied to a node at the start of its warm-up (0.0 - 1.0)
+    pub warmup_min_weight: f64,
+    /// How strongly reputation scales a node's score (0.0 - 1.0)
+    pub reputation_weight: f64,
+    /// Anti-affinity between shards of the same chunk
+    pub anti_affinity: AntiAffinity,
+    /// Minimum eligible nodes for `Strict` anti-affinity to be enforced
+    pub anti_affinity_min_nodes: usize,
 }
 
 impl Default for PlacementConfig {
@@ -36,7 +94,49 @@ impl Default for PlacementConfig {
             utilization_weight: 0.5,
             proximity_weight: 0.3,
             min_available_storage: 1024 * 1024 * 1024, // 1 GB
+            warmup_period: Duration::from_secs(6 * 60 * 60), // 6 hours
+            warmup_min_weight: 0.1,
+            reputation_weight: 1.0,
+            anti_affinity: AntiAffinity::default(),
+            anti_affinity_min_nodes: 14, // 10+4 erasure coding
+        }
+    }
+}
+
+impl PlacementConfig {
+    /// Override warm-up and anti-affinity settings from environment variables
+    pub fn with_env_overrides(mut self) -> Self {
+        if let Some(secs) = std::env::var("NODE_WARMUP_PERIOD_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+        {
+            self.warmup_period = Duration::from_secs(secs);
+        }
+        if let Some(weight) = std::env::var("NODE_WARMUP_MIN_WEIGHT")
+            .ok()
+            .and_then(|v| v.parse::<f64>().ok())
+        {
+            self.warmup_min_weight = weight.clamp(0.0, 1.0);
+        }
+        if let Some(weight) = std::env::var("PLACEMENT_REPUTATION_WEIGHT")
+            .ok()
+            .and_then(|v| v.parse::<f64>().ok())
+        {
+            self.reputation_weight = weight.clamp(0.0, 1.0);
         }
+        if let Some(level) = std::env::var("PLACEMENT_ANTI_AFFINITY")
+            .ok()
+            .and_then(|v| v.parse().ok())
+        {
+            self.anti_affinity = level;
+        }
+        if let Some(min_nodes) = std::env::var("PLACEMENT_ANTI_AFFINITY_MIN_NODES")
+            .ok()
+            .and_then(|v| v.parse().ok())
+        {
+            self.anti_affinity_min_nodes = min_nodes;
+        }
+        self
     }
 }
 
@@ -52,7 +152,13 @@ pub struct PlacementNode {
     pub longitude: Option<f64>,
     pub storage_total: u64,
     pub storage_used: u64,
+    /// Free bytes on the node's disk, if reported
+    pub disk_available: Option<u64>,
     pub bandwidth_mbps: u32,
+    /// Start of the warm-up ramp (None if the node is fully warmed up)
+    pub warmup_started_at: Option<DateTime<Utc>>,
+    /// Reputation score (0-10000)
+    pub reputation: i32,
 }
 
 impl PlacementNode {
@@ -68,13 +174,27 @@ impl PlacementNode {
             longitude: node.longitude,
             storage_total: node.storage_total as u64,
             storage_used: node.storage_used as u64,
-            bandwidth_mbps: node.bandwidth_mbps as u32,
+            disk_available: node.disk_available.map(|d| d.max(0) as u64),
+            bandwidth_mbps: node.effective_bandwidth_mbps().max(0) as u32,
+            warmup_started_at: node.warmup_started_at,
+            reputation: node.reputation,
         }
     }
 
-    /// Get available storage
+    /// Rack this node is in, as a failure domain
+    ///
+    /// Racks sit between nodes and datacenters: rack numbers are only unique
+    /// within a datacenter. Nodes without a datacenter share one namespace.
+    pub fn rack_key(&self) -> Option<(String, i32)> {
+        let rack = self.rack?;
+        Some((self.datacenter.clone().unwrap_or_default(), rack))
+    }
+
+    /// Get available storage, capped by the free disk space
     pub fn available_storage(&self) -> u64 {
-        self.storage_total.saturating_sub(self.storage_used)
+        let available = self.storage_total.saturating_sub(self.storage_used);
+        self.disk_available
+            .map_or(available, |disk| available.min(disk))
     }
 
     /// Get utilization percentage
@@ -123,6 +243,14 @@ pub struct PlacementDecision {
     pub score: f64,
 }
 
+/// Nodes and racks already holding a shard of the chunk being placed
+#[derive(Debug, Default)]
+struct SiblingShards {
+    nodes: HashSet<String>,
+    racks: HashSet<(String, i32)>,
+    level: AntiAffinity,
+}
+
 /// Topology-aware placement engine
 pub struct PlacementEngine {
     config: PlacementConfig,
@@ -160,9 +288,18 @@ impl PlacementEngine {
             return Vec::new();
         }
 
+        let anti_affinity = self
+            .config
+            .anti_affinity
+            .effective(eligible_nodes.len(), self.config.anti_affinity_min_nodes);
+
         let mut decisions = Vec::with_capacity(num_shards);
         let mut dc_usage: HashMap<String, usize> = HashMap::new();
         let mut rack_usage: HashMap<(String, i32), usize> = HashMap::new();
+        let mut siblings = SiblingShards {
+            level: anti_affinity,
+            ..Default::default()
+        };
 
         for shard_index in 0..num_shards {
             let selected = self.select_for_shard(
@@ -171,16 +308,28 @@ impl PlacementEngine {
                 origin,
                 &dc_usage,
                 &rack_usage,
+                &siblings,
             );
 
+            if selected.len() < replicas_per_shard {
+                warn!(
+                    shard_index = shard_index,
+                    selected = selected.len(),
+                    wanted = replicas_per_shard,
+                    "Shard under-placed due to placement constraints"
+                );
+            }
+
             // Update usage tracking
             for node in &selected {
                 if let Some(dc) = &node.datacenter {
                     *dc_usage.entry(dc.clone()).or_default() += 1;
                 }
-                if let (Some(dc), Some(rack)) = (&node.datacenter, node.rack) {
-                    *rack_usage.entry((dc.clone(), rack)).or_default() += 1;
+                if let Some(rack) = node.rack_key() {
+                    *rack_usage.entry(rack.clone()).or_default() += 1;
+                    siblings.racks.insert(rack);
                 }
+                siblings.nodes.insert(node.id.clone());
             }
 
             let score = self.calculate_placement_score(&selected, origin);
@@ -209,8 +358,9 @@ impl PlacementEngine {
         origin: Option<&PlacementNode>,
         dc_usage: &HashMap<String, usize>,
         rack_usage: &HashMap<(String, i32), usize>,
+        siblings: &SiblingShards,
     ) -> Vec<PlacementNode> {
-        if count >= nodes.len() {
+        if siblings.level == AntiAffinity::Off && count >= nodes.len() {
             return nodes.to_vec();
         }
 
@@ -226,44 +376,69 @@ impl PlacementEngine {
         // Sort by score descending
         scored_nodes.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
 
-        // Select top N nodes with diversity constraints
-        let mut selected = Vec::with_capacity(count);
+        // Select top N nodes with diversity constraints. The first pass also
+        // skips nodes and racks that already hold a shard of this chunk; the
+        // second pass (best-effort only) fills any remaining slots without it.
+        let mut selected: Vec<PlacementNode> = Vec::with_capacity(count);
         let mut selected_dcs: HashMap<String, usize> = HashMap::new();
         let mut selected_racks: HashMap<(String, i32), usize> = HashMap::new();
 
-        for (_, node) in scored_nodes {
-            if selected.len() >= count {
-                break;
-            }
+        let passes: &[bool] = match siblings.level {
+            AntiAffinity::Strict => &[true],
+            AntiAffinity::BestEffort => &[true, false],
+            AntiAffinity::Off => &[false],
+        };
 
-            // Check datacenter constraint
-            if let Some(dc) = &node.datacenter {
-                let dc_count = selected_dcs.get(dc).copied().unwrap_or(0);
-                if dc_count >= self.config.max_shards_per_dc {
-                    continue;
+        for &enforce_affinity in passes {
+            for (_, node) in &scored_nodes {
+                if selected.len() >= count {
+                    break;
                 }
-            }
 
-            // Check rack constraint
-            if let (Some(dc), Some(rack)) = (&node.datacenter, node.rack) {
-                let rack_count = selected_racks
-                    .get(&(dc.clone(), rack))
-                    .copied()
-                    .unwrap_or(0);
-                if rack_count >= self.config.max_shards_per_rack {
+                if selected.iter().any(|n| n.id == node.id) {
                     continue;
                 }
-            }
 
-            // Add node
-            if let Some(dc) = &node.datacenter {
-                *selected_dcs.entry(dc.clone()).or_default() += 1;
-            }
-            if let (Some(dc), Some(rack)) = (&node.datacenter, node.rack) {
-                *selected_racks.entry((dc.clone(), rack)).or_default() += 1;
-            }
+                // Check anti-affinity with sibling shards
+                if enforce_affinity {
+                    if siblings.nodes.contains(&node.id) {
+                        continue;
+                    }
+                    if node
+                        .rack_key()
+                        .is_some_and(|rack| siblings.racks.contains(&rack))
+                    {
+                        continue;
+                    }
+                }
+
+                // Check datacenter constraint
+                if let Some(dc) = &node.datacenter {
+                    let dc_count = selected_dcs.get(dc).copied().unwrap_or(0);
+                    if dc_count >= self.config.max_shards_per_dc {
+                        continue;
+                    }
+                }
 
-            selected.push(node.clone());
+                // Check rack constraint
+                let rack = node.rack_key();
+                if let Some(rack) = &rack {
+                    let rack_count = selected_racks.get(rack).copied().unwrap_or(0);
+                    if rack_count >= self.config.max_shards_per_rack {
+                        continue;
+                    }
+                }
+
+                // Add node
+                if let Some(dc) = &node.datacenter {
+                    *selected_dcs.entry(dc.clone()).or_default() += 1;
+                }
+                if let Some(rack) = rack {
+                    *selected_racks.entry(rack).or_default() += 1;
+                }
+
+                selected.push((*node).clone());
+            }
         }
 
         selected
@@ -305,8 +480,8 @@ impl PlacementEngine {
         }
 
         // Rack diversity bonus
-        if let (Some(dc), Some(rack)) = (&node.datacenter, node.rack) {
-            let rack_count = rack_usage.get(&(dc.clone(), rack)).copied().unwrap_or(0);
+        if let Some(rack) = node.rack_key() {
+            let rack_count = rack_usage.get(&rack).copied().unwrap_or(0);
             if rack_count == 0 {
                 score += 25.0; // Bonus for new rack
             } else {
@@ -317,7 +492,41 @@ impl PlacementEngine {
         // Bandwidth bonus
         score += (node.bandwidth_mbps as f64) / 100.0;
 
-        score
+        // Scale down nodes that are still warming up, then by reputation.
+        // Diversity penalties can push the score below zero, where scaling
+        // down would rank the node higher, so clamp it first.
+        score.max(0.0) * self.warmup_weight(node, Utc::now()) * self.reputation_weight(node)
+    }
+
+    /// Placement weight for a node based on its reputation
+    ///
+    /// With full `reputation_weight` this ranges from 0.5 (reputation 0) over
+    /// 1.0 (neutral) to 1.5 (reputation 10000), like the payment weight.
+    pub fn reputation_weight(&self, node: &PlacementNode) -> f64 {
+        let influence = self.config.reputation_weight.clamp(0.0, 1.0);
+        let reputation = node.reputation.clamp(0, 10000) as f64 / 10000.0;
+        1.0 + influence * (reputation - 0.5)
+    }
+
+    /// Placement weight for a node based on its warm-up progress
+    ///
+    /// Ramps linearly from `warmup_min_weight` to 1.0 over `warmup_period`.
+    pub fn warmup_weight(&self, node: &PlacementNode, now: DateTime<Utc>) -> f64 {
+        let started_at = match node.warmup_started_at {
+            Some(t) => t,
+            None => return 1.0,
+        };
+
+        let period = self.config.warmup_period.as_secs_f64();
+        if period <= 0.0 {
+            return 1.0;
+        }
+
+        let elapsed = (now - started_at).num_milliseconds().max(0) as f64 / 1000.0;
+        let progress = (elapsed / period).min(1.0);
+        let min_weight = self.config.warmup_min_weight.clamp(0.0, 1.0);
+
+        min_weight + (1.0 - min_weight) * progress
     }
 
     /// Calculate overall placement score
@@ -444,7 +653,10 @@ impl RegionGrouper {
             longitude: Some(lon),
             storage_total: 0,
             storage_used: 0,
+            disk_available: None,
             bandwidth_mbps: 0,
+            warmup_started_at: None,
+            reputation: NEUTRAL_REPUTATION,
         };
 
         let mut with_distance: Vec<_> = nodes
@@ -478,7 +690,10 @@ mod tests {
             longitude: Some(-74.0),
             storage_total: total,
             storage_used: (total as f64 * util) as u64,
+            disk_available: None,
             bandwidth_mbps: 1000,
+            warmup_started_at: None,
+            reputation: NEUTRAL_REPUTATION,
         }
     }
 
@@ -496,6 +711,28 @@ mod tests {
         assert_eq!(node.available_storage(), 5_000_000_000); // 50% of 10 GB
     }
 
+    #[test]
+    fn test_placement_skips_full_disks() {
+        let engine = PlacementEngine::new(PlacementConfig::default());
+
+        // n1 offers plenty of capacity but its disk is full
+        let mut full = make_test_node("n1", "dc1", 1, 0.1);
+        full.disk_available = Some(0);
+        assert_eq!(full.available_storage(), 0);
+        let nodes = vec![
+            full,
+            make_test_node("n2", "dc1", 2, 0.5),
+            make_test_node("n3", "dc2", 1, 0.5),
+        ];
+
+        let decisions = engine.select_nodes(&nodes, 2, 1, None);
+        assert_eq!(decisions.len(), 2);
+        assert!(decisions
+            .iter()
+            .flat_map(|d| &d.nodes)
+            .all(|n| n.id != "n1"));
+    }
+
     #[test]
     fn test_placement_engine_selects_diverse_nodes() {
         let engine = PlacementEngine::new(PlacementConfig::default());
@@ -523,6 +760,190 @@ mod tests {
         assert!(decisions.is_empty());
     }
 
+    #[test]
+    fn test_warmup_weight_ramps_linearly() {
+        let engine = PlacementEngine::new(PlacementConfig::default());
+        let now = Utc::now();
+
+        let mut node = make_test_node("n1", "dc1", 1, 0.1);
+        assert_eq!(engine.warmup_weight(&node, now), 1.0);
+
+        node.warmup_started_at = Some(now);
+        assert!((engine.warmup_weight(&node, now) - 0.1).abs() < 1e-6);
+
+        node.warmup_started_at = Some(now - chrono::Duration::hours(3));
+        assert!((engine.warmup_weight(&node, now) - 0.55).abs() < 1e-6);
+
+        node.warmup_started_at = Some(now - chrono::Duration::hours(12));
+        assert_eq!(engine.warmup_weight(&node, now), 1.0);
+    }
+
+    #[test]
+    fn test_placement_prefers_warmed_up_nodes() {
+        let engine = PlacementEngine::new(PlacementConfig::default());
+
+        let mut fresh = make_test_node("fresh", "dc1", 1, 0.0);
+        fresh.warmup_started_at = Some(Utc::now());
+        let nodes = vec![
+            fresh,
+            make_test_node("n2", "dc2", 1, 0.5),
+            make_test_node("n3", "dc3", 1, 0.5),
+        ];
+
+        let decisions = engine.select_nodes(&nodes, 1, 2, None);
+        assert_eq!(decisions.len(), 1);
+        assert!(decisions[0].nodes.iter().all(|n| n.id != "fresh"));
+    }
+
+    #[test]
+    fn test_warmup_never_raises_negative_scores() {
+        let engine = PlacementEngine::new(PlacementConfig::default());
+
+        let warm = make_test_node("warm", "dc1", 1, 0.5);
+        let mut fresh = make_test_node("fresh", "dc1", 1, 0.5);
+        fresh.warmup_started_at = Some(Utc::now());
+
+        // dc1 and its rack already hold enough shards to go below zero
+        let dc_usage = HashMap::from([("dc1".to_string(), 50)]);
+        let rack_usage = HashMap::from([(("dc1".to_string(), 1), 50)]);
+
+        let warm_score = engine.score_node(&warm, None, &dc_usage, &rack_usage);
+        let fresh_score = engine.score_node(&fresh, None, &dc_usage, &rack_usage);
+        assert!(fresh_score >= 0.0);
+        assert!(warm_score >= fresh_score);
+    }
+
+    #[test]
+    fn test_reputation_weight() {
+        let engine = PlacementEngine::new(PlacementConfig::default());
+        let mut node = make_test_node("n1", "dc1", 1, 0.1);
+        assert_eq!(engine.reputation_weight(&node), 1.0);
+
+        node.reputation = 0;
+        assert_eq!(engine.reputation_weight(&node), 0.5);
+        node.reputation = 10000;
+        assert_eq!(engine.reputation_weight(&node), 1.5);
+
+        let engine = PlacementEngine::new(PlacementConfig {
+            reputation_weight: 0.0,
+            ..Default::default()
+        });
+        assert_eq!(engine.reputation_weight(&node), 1.0);
+    }
+
+    #[test]
+    fn test_placement_prefers_reputable_nodes() {
+        let engine = PlacementEngine::new(PlacementConfig::default());
+
+        let mut flaky = make_test_node("flaky", "dc1", 1, 0.0);
+        flaky.reputation = 1000;
+        let nodes = vec![
+            flaky,
+            make_test_node("n2", "dc2", 1, 0.3),
+            make_test_node("n3", "dc3", 1, 0.3),
+        ];
+
+        let decisions = engine.select_nodes(&nodes, 1, 2, None);
+        assert_eq!(decisions.len(), 1);
+        assert!(decisions[0].nodes.iter().all(|n| n.id != "flaky"));
+    }
+
+    #[test]
+    fn test_anti_affinity_parse_and_effective() {
+        assert_eq!("strict".parse::<AntiAffinity>(), Ok(AntiAffinity::Strict));
+        assert_eq!(
+            "best-effort".parse::<AntiAffinity>(),
+            Ok(AntiAffinity::BestEffort)
+        );
+        assert_eq!("OFF".parse::<AntiAffinity>(), Ok(AntiAffinity::Off));
+        assert!("sometimes".parse::<AntiAffinity>().is_err());
+
+        assert_eq!(AntiAffinity::Strict.effective(20, 14), AntiAffinity::Strict);
+        assert_eq!(
+            AntiAffinity::Strict.effective(5, 14),
+            AntiAffinity::BestEffort
+        );
+        assert_eq!(AntiAffinity::Off.effective(5, 14), AntiAffinity::Off);
+    }
+
+    #[test]
+    fn test_placement_spreads_shards_across_nodes_and_racks() {
+        let engine = PlacementEngine::new(PlacementConfig::default());
+
+        let nodes = vec![
+            make_test_node("n1", "dc1", 1, 0.1),
+            make_test_node("n2", "dc1", 2, 0.2),
+            make_test_node("n3", "dc2", 1, 0.3),
+            make_test_node("n4", "dc2", 2, 0.4),
+        ];
+
+        let decisions = engine.select_nodes(&nodes, 4, 1, None);
+
+        let ids: HashSet<_> = decisions.iter().map(|d| d.nodes[0].id.clone()).collect();
+        assert_eq!(ids.len(), 4);
+    }
+
+    #[test]
+    fn test_placement_racks_without_datacenter() {
+        let engine = PlacementEngine::new(PlacementConfig {
+            anti_affinity: AntiAffinity::Strict,
+            anti_affinity_min_nodes: 0,
+            ..Default::default()
+        });
+
+        // Racks count as failure domains even when no datacenter is set
+        let mut nodes = vec![
+            make_test_node("n1", "dc1", 1, 0.1),
+            make_test_node("n2", "dc1", 1, 0.2),
+            make_test_node("n3", "dc1", 2, 0.3),
+        ];
+        for node in &mut nodes {
+            node.datacenter = None;
+        }
+        assert_eq!(nodes[0].rack_key(), nodes[1].rack_key());
+
+        // n1 and n2 share a rack, so the strict pass places only two shards
+        let decisions = engine.select_nodes(&nodes, 3, 1, None);
+        let placed: Vec<_> = decisions
+            .iter()
+            .filter_map(|d| d.nodes.first())
+            .map(|n| n.rack)
+            .collect();
+        assert_eq!(placed.len(), 2);
+        assert_ne!(placed[0], placed[1]);
+
+        // The same rack number in another datacenter is a different rack
+        let other = make_test_node("n4", "dc2", 1, 0.1);
+        assert_ne!(
+            other.rack_key(),
+            make_test_node("n5", "dc1", 1, 0.1).rack_key()
+        );
+    }
+
+    #[test]
+    fn test_placement_anti_affinity_small_cluster() {
+        let nodes = vec![
+            make_test_node("n1", "dc1", 1, 0.1),
+            make_test_node("n2", "dc2", 1, 0.2),
+        ];
+
+        // Best-effort falls back to reusing nodes
+        let engine = PlacementEngine::new(PlacementConfig::default());
+        let decisions = engine.select_nodes(&nodes, 3, 1, None);
+        assert!(decisions.iter().all(|d| d.nodes.len() == 1));
+
+        // Strict leaves the third shard unplaced
+        let engine = PlacementEngine::new(PlacementConfig {
+            anti_affinity: AntiAffinity::Strict,
+            anti_affinity_min_nodes: 0,
+            ..Default::default()
+        });
+        let decisions = engine.select_nodes(&nodes, 3, 1, None);
+        assert_eq!(decisions[0].nodes.len(), 1);
+        assert_eq!(decisions[1].nodes.len(), 1);
+        assert!(decisions[2].nodes.is_empty());
+    }
+
     #[test]
     fn test_rebalance_suggestions() {
         let engine = PlacementEngine::new(PlacementConfig::default());
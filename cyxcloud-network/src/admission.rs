@@ -0,0 +1,206 @@
+//! Admission control for the ChunkService
+//!
+//! Each class of RPC (reads, writes) gets its own [`AdmissionGate`]: a fixed
+//! number of requests run concurrently, a bounded number wait for a slot, and
+//! everything beyond that is shed with `RESOURCE_EXHAUSTED`. Shed responses
+//! carry a `grpc-retry-pushback-ms` hint so callers back off instead of
+//! hammering a node that is already stalling on RocksDB writes.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tonic::metadata::MetadataValue;
+use tonic::Status;
+use tracing::warn;
+
+/// Metadata key carrying the retry hint on shed requests (milliseconds)
+pub const RETRY_PUSHBACK_HEADER: &str = "grpc-retry-pushback-ms";
+
+/// Limits for one class of RPCs
+#[derive(Debug, Clone)]
+pub struct AdmissionConfig {
+    /// Requests allowed to run at the same time
+    pub max_concurrent: usize,
+    /// Requests allowed to wait for a slot before new ones are shed
+    pub max_queued: usize,
+    /// How long a queued request waits for a slot before it is shed
+    pub queue_timeout: Duration,
+    /// Back-off suggested to shed callers
+    pub retry_after: Duration,
+}
+
+impl AdmissionConfig {
+    /// Defaults for read RPCs (GetChunk, ReadChunk, StreamChunks, VerifyChunk)
+    pub fn reads() -> Self {
+        Self {
+            max_concurrent: 128,
+            max_queued: 256,
+            queue_timeout: Duration::from_secs(5),
+            retry_after: Duration::from_millis(500),
+        }
+    }
+
+    /// Defaults for write RPCs (StoreChunk, WriteChunk, DeleteChunk)
+    ///
+    /// Lower than reads: each write lands in the RocksDB memtable and too many
+    /// at once trigger write stalls for every caller.
+    pub fn writes() -> Self {
+        Self {
+            max_concurrent: 32,
+            max_queued: 64,
+            queue_timeout: Duration::from_secs(5),
+            retry_after: Duration::from_secs(1),
+        }
+    }
+}
+
+/// Bounded admission queue in front of one class of RPCs
+#[derive(Debug)]
+pub struct AdmissionGate {
+    name: &'static str,
+    config: AdmissionConfig,
+    slots: Arc<Semaphore>,
+    queued: AtomicUsize,
+}
+
+impl AdmissionGate {
+    /// Create a gate; `name` is used in logs and error messages
+    pub fn new(name: &'static str, config: AdmissionConfig) -> Self {
+        Self {
+            name,
+            slots: Arc::new(Semaphore::new(config.max_concurrent.max(1))),
+            queued: AtomicUsize::new(0),
+            config,
+        }
+    }
+
+    /// Wait for a slot, or shed the request if the queue is full or the wait
+    /// exceeds the queue timeout
+    ///
+    /// The slot is released when the returned permit is dropped.
+    pub async fn admit(&self, rpc: &str) -> Result<OwnedSemaphorePermit, Status> {
+        if let Ok(permit) = self.slots.clone().try_acquire_owned() {
+            return Ok(permit);
+        }
+
+        // Reserve a place in the queue
+        let reserved = self
+            .queued
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |queued| {
+                (queued < self.config.max_queued).then_some(queued + 1)
+            });
+        if reserved.is_err() {
+            return Err(self.shed(rpc, "admission queue full"));
+        }
+
+        let waited = tokio::time::timeout(
+            self.config.queue_timeout,
+            self.slots.clone().acquire_owned(),
+        )
+        .await;
+        self.queued.fetch_sub(1, Ordering::AcqRel);
+
+        match waited {
+            Ok(Ok(permit)) => Ok(permit),
+            Ok(Err(_)) => Err(Status::unavailable("Admission gate closed")),
+            Err(_) => Err(self.shed(rpc, "timed out waiting in admission queue")),
+        }
+    }
+
+    /// Requests currently running
+    pub fn in_flight(&self) -> usize {
+        self.config.max_concurrent.max(1) - self.slots.available_permits()
+    }
+
+    /// Requests currently waiting for a slot
+    pub fn queued(&self) -> usize {
+        self.queued.load(Ordering::Acquire)
+    }
+
+    /// Build the RESOURCE_EXHAUSTED status for a shed request
+    fn shed(&self, rpc: &str, reason: &str) -> Status {
+        let retry_ms = self.config.retry_after.as_millis() as u64;
+        warn!(
+            class = self.name,
+            rpc,
+            reason,
+            in_flight = self.in_flight(),
+            queued = self.queued(),
+            retry_after_ms = retry_ms,
+            "Shedding request"
+        );
+
+        let mut status = Status::resource_exhausted(format!(
+            "Node overloaded ({} {}): {}, retry after {} ms",
+            self.name, rpc, reason, retry_ms
+        ));
+        if let Ok(value) = MetadataValue::try_from(retry_ms.to_string()) {
+            status.metadata_mut().insert(RETRY_PUSHBACK_HEADER, value);
+        }
+        status
+    }
+}
+
+/// Retry hint carried by a shed response, if any
+pub fn retry_after_hint(status: &Status) -> Option<Duration> {
+    status
+        .metadata()
+        .get(RETRY_PUSHBACK_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok())
+        .map(Duration::from_millis)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(max_concurrent: usize, max_queued: usize) -> AdmissionConfig {
+        AdmissionConfig {
+            max_concurrent,
+            max_queued,
+            queue_timeout: Duration::from_millis(50),
+            retry_after: Duration::from_millis(750),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sheds_when_queue_full() {
+        let gate = Arc::new(AdmissionGate::new("writes", config(1, 0)));
+
+        let _held = gate.admit("StoreChunk").await.unwrap();
+        assert_eq!(gate.in_flight(), 1);
+
+        let status = gate.admit("StoreChunk").await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::ResourceExhausted);
+        assert_eq!(retry_after_hint(&status), Some(Duration::from_millis(750)));
+    }
+
+    #[tokio::test]
+    async fn test_queued_request_times_out() {
+        let gate = AdmissionGate::new("reads", config(1, 1));
+
+        let _held = gate.admit("GetChunk").await.unwrap();
+        let status = gate.admit("GetChunk").await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::ResourceExhausted);
+        assert_eq!(gate.queued(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_queued_request_admitted_when_slot_frees() {
+        let gate = Arc::new(AdmissionGate::new("reads", config(1, 1)));
+
+        let held = gate.admit("GetChunk").await.unwrap();
+        let waiter = {
+            let gate = gate.clone();
+            tokio::spawn(async move { gate.admit("GetChunk").await.map(|_| ()) })
+        };
+
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        drop(held);
+
+        assert!(waiter.await.unwrap().is_ok());
+        assert_eq!(gate.in_flight(), 0);
+    }
+}
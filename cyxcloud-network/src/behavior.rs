@@ -4,9 +4,11 @@
 //! - Kademlia for peer discovery
 //! - Identify for peer info exchange
 //! - Ping for liveness checking
+//! - Gossipsub for node status propagation
 
 use libp2p::{
-    identify, kad, kad::store::MemoryStore, ping, swarm::NetworkBehaviour, Multiaddr, PeerId,
+    gossipsub, identify, kad, kad::store::MemoryStore, ping, swarm::NetworkBehaviour, Multiaddr,
+    PeerId,
 };
 use std::time::Duration;
 use tracing::{debug, info};
@@ -30,6 +32,8 @@ pub struct CyxCloudBehaviour {
     pub identify: identify::Behaviour,
     /// Ping protocol for liveness checking
     pub ping: ping::Behaviour,
+    /// Gossipsub for status broadcasts
+    pub gossipsub: gossipsub::Behaviour,
 }
 
 /// Events emitted by the CyxCloud behaviour
@@ -53,6 +57,15 @@ pub enum CyxCloudEvent {
     },
     /// Kademlia event
     Kademlia(kad::Event),
+    /// Gossip message received on a subscribed topic
+    GossipMessage {
+        /// Peer that published the message (signed)
+        source: Option<PeerId>,
+        topic: gossipsub::TopicHash,
+        data: Vec<u8>,
+    },
+    /// Other gossipsub event (subscription changes)
+    Gossipsub(gossipsub::Event),
 }
 
 impl From<kad::Event> for CyxCloudEvent {
@@ -105,6 +118,31 @@ impl From<identify::Event> for CyxCloudEvent {
     }
 }
 
+impl From<gossipsub::Event> for CyxCloudEvent {
+    fn from(event: gossipsub::Event) -> Self {
+        match event {
+            gossipsub::Event::Message {
+                propagation_source,
+                message,
+                ..
+            } => {
+                debug!(
+                    from = %propagation_source,
+                    topic = %message.topic,
+                    bytes = message.data.len(),
+                    "Gossip message received"
+                );
+                CyxCloudEvent::GossipMessage {
+                    source: message.source,
+                    topic: message.topic,
+                    data: message.data,
+                }
+            }
+            other => CyxCloudEvent::Gossipsub(other),
+        }
+    }
+}
+
 impl From<ping::Event> for CyxCloudEvent {
     fn from(event: ping::Event) -> Self {
         match event.result {
@@ -132,6 +170,8 @@ pub struct BehaviourConfig {
     pub local_peer_id: PeerId,
     /// Local public key (for identify protocol)
     pub local_public_key: libp2p::identity::PublicKey,
+    /// Local keypair (signs gossip messages)
+    pub local_keypair: libp2p::identity::Keypair,
     /// Ping interval
     pub ping_interval: Duration,
     /// Ping timeout
@@ -140,6 +180,8 @@ pub struct BehaviourConfig {
     pub kademlia_query_timeout: Duration,
     /// Kademlia record replication interval
     pub kademlia_replication_interval: Duration,
+    /// Gossipsub heartbeat (mesh maintenance) interval
+    pub gossip_heartbeat_interval: Duration,
 }
 
 impl BehaviourConfig {
@@ -148,10 +190,12 @@ impl BehaviourConfig {
         Self {
             local_peer_id: keypair.public().to_peer_id(),
             local_public_key: keypair.public(),
+            local_keypair: keypair.clone(),
             ping_interval: Duration::from_secs(30),
             ping_timeout: Duration::from_secs(10),
             kademlia_query_timeout: Duration::from_secs(60),
             kademlia_replication_interval: Duration::from_secs(3600), // 1 hour
+            gossip_heartbeat_interval: Duration::from_secs(1),
         }
     }
 
@@ -197,6 +241,18 @@ impl CyxCloudBehaviour {
             .with_timeout(config.ping_timeout);
         let ping = ping::Behaviour::new(ping_config);
 
+        // Create Gossipsub behaviour (messages are signed by their publisher)
+        let gossipsub_config = gossipsub::ConfigBuilder::default()
+            .heartbeat_interval(config.gossip_heartbeat_interval)
+            .validation_mode(gossipsub::ValidationMode::Strict)
+            .build()
+            .expect("valid gossipsub config");
+        let gossipsub = gossipsub::Behaviour::new(
+            gossipsub::MessageAuthenticity::Signed(config.local_keypair.clone()),
+            gossipsub_config,
+        )
+        .expect("valid gossipsub behaviour");
+
         info!(
             peer_id = %config.local_peer_id,
             ping_interval = ?config.ping_interval,
@@ -207,6 +263,7 @@ impl CyxCloudBehaviour {
             kademlia,
             identify,
             ping,
+            gossipsub,
         }
     }
 
@@ -243,6 +300,21 @@ impl CyxCloudBehaviour {
         self.kademlia.get_record(key)
     }
 
+    /// Subscribe to a gossip topic
+    pub fn subscribe(&mut self, topic: &str) -> Result<bool, gossipsub::SubscriptionError> {
+        self.gossipsub.subscribe(&gossipsub::IdentTopic::new(topic))
+    }
+
+    /// Publish a message on a gossip topic
+    pub fn publish(
+        &mut self,
+        topic: &str,
+        data: Vec<u8>,
+    ) -> Result<gossipsub::MessageId, gossipsub::PublishError> {
+        self.gossipsub
+            .publish(gossipsub::IdentTopic::new(topic), data)
+    }
+
     /// Get the closest peers to a key
     pub fn get_closest_peers(&mut self, key: Vec<u8>) -> kad::QueryId {
         let key = kad::RecordKey::new(&key);
@@ -277,6 +349,21 @@ mod tests {
         assert_eq!(config.ping_timeout, Duration::from_secs(20));
     }
 
+    #[test]
+    fn test_subscribe_node_status() {
+        let keypair = Keypair::generate_ed25519();
+        let mut behaviour = CyxCloudBehaviour::new(BehaviourConfig::from_keypair(&keypair));
+
+        assert!(behaviour
+            .subscribe(crate::protocol::NODE_STATUS_TOPIC)
+            .unwrap());
+        // Already subscribed
+        assert!(!behaviour
+            .subscribe(crate::protocol::NODE_STATUS_TOPIC)
+            .unwrap());
+        assert_eq!(behaviour.gossipsub.topics().count(), 1);
+    }
+
     #[test]
     fn test_add_address() {
         let keypair = Keypair::generate_ed25519();
@@ -1,10 +1,18 @@
 //! Peer discovery using libp2p with Kademlia DHT
 //!
 //! Manages the libp2p swarm and provides peer discovery functionality.
+//! Peers are remembered in a [`PeerRegistry`] across restarts and redialed
+//! with backoff. Node status changes travel over gossipsub and are collected
+//! in a [`ClusterView`].
 
 use crate::behavior::{BehaviourConfig, CyxCloudBehaviour, CyxCloudEvent};
+use crate::peer_registry::{PeerRegistry, PeerRegistryConfig, PeerRegistryStats};
+use crate::protocol::{NodeAnnouncement, NodeStatus, NODE_STATUS_TOPIC};
 use futures::StreamExt;
-use libp2p::{identity::Keypair, noise, tcp, yamux, Multiaddr, PeerId, Swarm};
+use libp2p::gossipsub::{IdentTopic, PublishError};
+use libp2p::swarm::dial_opts::DialOpts;
+use libp2p::swarm::SwarmEvent;
+use libp2p::{identity::Keypair, multiaddr::Protocol, noise, tcp, yamux, Multiaddr, PeerId, Swarm};
 use parking_lot::RwLock;
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -77,6 +85,25 @@ fn extract_ip_from_multiaddr(addr: &str) -> Option<String> {
     None
 }
 
+/// Open the peer store, keeping peers in memory if it cannot be opened
+fn open_registry(config: &PeerRegistryConfig) -> Arc<PeerRegistry> {
+    let registry = PeerRegistry::open(config.clone()).or_else(|e| {
+        warn!(error = %e, "Failed to open peer store, peers will not persist");
+        PeerRegistry::temporary(config.clone())
+    });
+    Arc::new(registry.expect("in-memory peer store"))
+}
+
+/// Wait for the next announcement to publish (forever if there is no channel)
+async fn next_announcement(
+    rx: &mut Option<mpsc::Receiver<NodeAnnouncement>>,
+) -> Option<NodeAnnouncement> {
+    match rx {
+        Some(rx) => rx.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
 /// Configuration for the discovery service
 #[derive(Debug, Clone)]
 pub struct DiscoveryConfig {
@@ -88,8 +115,10 @@ pub struct DiscoveryConfig {
     pub grpc_port: u16,
     /// Peer timeout (remove if not seen for this long)
     pub peer_timeout: Duration,
-    /// How often to refresh the peer list
+    /// How often to refresh the peer list and redial known peers
     pub refresh_interval: Duration,
+    /// Peer store and redial backoff
+    pub registry: PeerRegistryConfig,
 }
 
 impl Default for DiscoveryConfig {
@@ -103,6 +132,7 @@ impl Default for DiscoveryConfig {
             grpc_port: 50051,
             peer_timeout: Duration::from_secs(300), // 5 minutes
             refresh_interval: Duration::from_secs(60),
+            registry: PeerRegistryConfig::default(),
         }
     }
 }
@@ -122,11 +152,30 @@ impl DiscoveryConfig {
         self
     }
 
+    /// Add a bootstrap peer from a multiaddr ending in `/p2p/<peer id>`
+    ///
+    /// Returns false (and adds nothing) if the address has no peer ID.
+    pub fn add_bootstrap_addr(&mut self, addr: Multiaddr) -> bool {
+        match addr.iter().last() {
+            Some(Protocol::P2p(peer_id)) => {
+                self.bootstrap_peers.push((peer_id, addr));
+                true
+            }
+            _ => false,
+        }
+    }
+
     /// Set the gRPC port
     pub fn with_grpc_port(mut self, port: u16) -> Self {
         self.grpc_port = port;
         self
     }
+
+    /// Keep the peer store in the given directory
+    pub fn with_peer_store(mut self, path: impl Into<std::path::PathBuf>) -> Self {
+        self.registry.path = Some(path.into());
+        self
+    }
 }
 
 /// Events from the discovery service
@@ -138,6 +187,73 @@ pub enum DiscoveryEvent {
     PeerRemoved(PeerId),
     /// Peer latency updated
     PeerLatencyUpdated { peer_id: PeerId, latency_ms: u64 },
+    /// A node announced a new status, address or capacity
+    NodeStatus {
+        /// Peer that published the announcement
+        source: Option<PeerId>,
+        announcement: NodeAnnouncement,
+    },
+}
+
+/// Latest status announcement of every node heard through gossip
+#[derive(Debug, Clone, Default)]
+pub struct ClusterView {
+    nodes: Arc<RwLock<HashMap<String, NodeAnnouncement>>>,
+}
+
+impl ClusterView {
+    /// Create an empty view
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record an announcement
+    ///
+    /// Returns true if the node is new or the announcement changes its
+    /// address, status or capacity. Announcements older than the one held
+    /// are ignored.
+    pub fn update(&self, announcement: NodeAnnouncement) -> bool {
+        let mut nodes = self.nodes.write();
+        match nodes.get(&announcement.node_id) {
+            Some(current) if !announcement.supersedes(current) => false,
+            current => {
+                let changed = current.map_or(true, |c| announcement.changes(c));
+                nodes.insert(announcement.node_id.clone(), announcement);
+                changed
+            }
+        }
+    }
+
+    /// Get the latest announcement of a node
+    pub fn get(&self, node_id: &str) -> Option<NodeAnnouncement> {
+        self.nodes.read().get(node_id).cloned()
+    }
+
+    /// Get the latest announcement of every node
+    pub fn nodes(&self) -> Vec<NodeAnnouncement> {
+        self.nodes.read().values().cloned().collect()
+    }
+
+    /// Get nodes whose latest announcement is online and at most
+    /// `max_age_secs` old
+    pub fn online_nodes(&self, max_age_secs: u64) -> Vec<NodeAnnouncement> {
+        self.nodes
+            .read()
+            .values()
+            .filter(|a| a.status == NodeStatus::Online && !a.is_stale(max_age_secs))
+            .cloned()
+            .collect()
+    }
+
+    /// Get the number of nodes in the view
+    pub fn len(&self) -> usize {
+        self.nodes.read().len()
+    }
+
+    /// Check whether no node has been heard from
+    pub fn is_empty(&self) -> bool {
+        self.nodes.read().is_empty()
+    }
 }
 
 /// Discovery service that manages libp2p swarm and peer discovery
@@ -148,10 +264,16 @@ pub struct DiscoveryService {
     local_peer_id: PeerId,
     /// Known peers
     peers: Arc<RwLock<HashMap<PeerId, PeerInfo>>>,
+    /// Persisted peers and redial state
+    registry: Arc<PeerRegistry>,
     /// Configuration
     config: DiscoveryConfig,
     /// Event sender (for notifying about peer changes)
     event_tx: Option<mpsc::Sender<DiscoveryEvent>>,
+    /// Node status gossip heard so far
+    cluster: ClusterView,
+    /// Announcements to gossip on behalf of this node
+    publish_rx: Option<mpsc::Receiver<NodeAnnouncement>>,
 }
 
 impl DiscoveryService {
@@ -166,8 +288,11 @@ impl DiscoveryService {
             keypair,
             local_peer_id,
             peers: Arc::new(RwLock::new(HashMap::new())),
+            registry: open_registry(&config.registry),
             config,
             event_tx: None,
+            cluster: ClusterView::new(),
+            publish_rx: None,
         }
     }
 
@@ -181,8 +306,11 @@ impl DiscoveryService {
             keypair,
             local_peer_id,
             peers: Arc::new(RwLock::new(HashMap::new())),
+            registry: open_registry(&config.registry),
             config,
             event_tx: None,
+            cluster: ClusterView::new(),
+            publish_rx: None,
         }
     }
 
@@ -196,6 +324,16 @@ impl DiscoveryService {
         self.event_tx = Some(tx);
     }
 
+    /// Set the channel of announcements to gossip for this node
+    pub fn set_publish_channel(&mut self, rx: mpsc::Receiver<NodeAnnouncement>) {
+        self.publish_rx = Some(rx);
+    }
+
+    /// Get the node status view (stays usable after [`Self::run`] takes the service)
+    pub fn cluster_view(&self) -> ClusterView {
+        self.cluster.clone()
+    }
+
     /// Get a list of all known peers
     pub fn get_peers(&self) -> Vec<PeerInfo> {
         let peers = self.peers.read();
@@ -223,6 +361,16 @@ impl DiscoveryService {
         self.peers.read().len()
     }
 
+    /// Get the peer registry (stays usable after [`Self::run`] takes the service)
+    pub fn registry(&self) -> Arc<PeerRegistry> {
+        self.registry.clone()
+    }
+
+    /// Get known, connected and banned peer counts
+    pub fn registry_stats(&self) -> PeerRegistryStats {
+        self.registry.stats()
+    }
+
     /// Build and return the libp2p swarm
     fn build_swarm(
         &self,
@@ -246,9 +394,13 @@ impl DiscoveryService {
     }
 
     /// Start the discovery service
-    pub async fn run(self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    pub async fn run(mut self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         let mut swarm = self.build_swarm()?;
 
+        if let Err(e) = swarm.behaviour_mut().subscribe(NODE_STATUS_TOPIC) {
+            warn!(error = ?e, "Failed to subscribe to node status gossip");
+        }
+
         // Listen on configured addresses
         for addr in &self.config.listen_addrs {
             match swarm.listen_on(addr.clone()) {
@@ -263,8 +415,25 @@ impl DiscoveryService {
             info!(peer = %peer_id, addr = %addr, "Added bootstrap peer");
         }
 
+        // Peers remembered from earlier runs are bootstrap candidates too
+        let mut stored = 0;
+        for peer in self.registry.known_peers() {
+            if peer.peer_id == self.local_peer_id || self.registry.is_banned(&peer.peer_id) {
+                continue;
+            }
+            for addr in &peer.addresses {
+                swarm
+                    .behaviour_mut()
+                    .add_address(&peer.peer_id, addr.clone());
+            }
+            stored += 1;
+        }
+        if stored > 0 {
+            info!(count = stored, "Added stored peers");
+        }
+
         // Start bootstrap if we have peers
-        if !self.config.bootstrap_peers.is_empty() {
+        if !self.config.bootstrap_peers.is_empty() || stored > 0 {
             match swarm.behaviour_mut().bootstrap() {
                 Ok(_) => info!("Started Kademlia bootstrap"),
                 Err(e) => warn!(error = ?e, "Bootstrap failed - no known peers"),
@@ -279,11 +448,30 @@ impl DiscoveryService {
         // Spawn cleanup task
         let peers_cleanup = peers.clone();
         let event_tx_cleanup = event_tx.clone();
+        let registry_cleanup = self.registry.clone();
         tokio::spawn(async move {
             let mut interval = tokio::time::interval(refresh_interval);
             loop {
                 interval.tick().await;
 
+                // Persist peers we still hear from, forget long-gone ones
+                {
+                    let peers = peers_cleanup.read();
+                    for peer in peers.values().filter(|p| !p.is_stale(peer_timeout)) {
+                        if let Err(e) = registry_cleanup.record_seen(peer) {
+                            warn!(peer = %peer.peer_id, error = %e, "Failed to store peer");
+                        }
+                    }
+                }
+                match registry_cleanup.prune() {
+                    Ok(0) => {}
+                    Ok(count) => debug!(count, "Forgot peers past retention"),
+                    Err(e) => warn!(error = %e, "Failed to prune peer store"),
+                }
+                if let Err(e) = registry_cleanup.flush() {
+                    warn!(error = %e, "Failed to flush peer store");
+                }
+
                 // Remove stale peers
                 let stale: Vec<PeerId> = {
                     let peers = peers_cleanup.read();
@@ -309,21 +497,81 @@ impl DiscoveryService {
         });
 
         // Main event loop
+        let mut redial = tokio::time::interval(refresh_interval);
+        let mut publish_rx = self.publish_rx.take();
         loop {
-            match swarm.select_next_some().await {
-                libp2p::swarm::SwarmEvent::Behaviour(event) => {
-                    self.handle_behaviour_event(event, &peers, &event_tx).await;
-                }
-                libp2p::swarm::SwarmEvent::NewListenAddr { address, .. } => {
-                    info!(addr = %address, "New listen address");
-                }
-                libp2p::swarm::SwarmEvent::ConnectionEstablished { peer_id, .. } => {
-                    debug!(peer = %peer_id, "Connection established");
-                }
-                libp2p::swarm::SwarmEvent::ConnectionClosed { peer_id, .. } => {
-                    debug!(peer = %peer_id, "Connection closed");
+            tokio::select! {
+                event = swarm.select_next_some() => match event {
+                    SwarmEvent::Behaviour(event) => {
+                        self.handle_behaviour_event(event, &peers, &event_tx).await;
+                    }
+                    SwarmEvent::NewListenAddr { address, .. } => {
+                        info!(addr = %address, "New listen address");
+                    }
+                    SwarmEvent::ConnectionEstablished { peer_id, .. } => {
+                        debug!(peer = %peer_id, "Connection established");
+                        self.registry.record_connected(peer_id);
+                    }
+                    SwarmEvent::ConnectionClosed {
+                        peer_id,
+                        num_established,
+                        ..
+                    } => {
+                        debug!(peer = %peer_id, "Connection closed");
+                        if num_established == 0 {
+                            self.registry.record_disconnected(peer_id);
+                        }
+                    }
+                    SwarmEvent::OutgoingConnectionError {
+                        peer_id: Some(peer_id),
+                        error,
+                        ..
+                    } => {
+                        debug!(peer = %peer_id, error = %error, "Outgoing connection failed");
+                        self.registry.record_dial_failure(peer_id);
+                    }
+                    _ => {}
+                },
+                _ = redial.tick() => {
+                    self.redial_known_peers(&mut swarm);
                 }
-                _ => {}
+                announcement = next_announcement(&mut publish_rx) => match announcement {
+                    Some(announcement) => self.publish_status(&mut swarm, announcement),
+                    None => publish_rx = None,
+                },
+            }
+        }
+    }
+
+    /// Gossip one of this node's status announcements
+    fn publish_status(&self, swarm: &mut Swarm<CyxCloudBehaviour>, announcement: NodeAnnouncement) {
+        let data = match announcement.to_bytes() {
+            Ok(data) => data,
+            Err(e) => {
+                warn!(error = %e, "Failed to encode node status");
+                return;
+            }
+        };
+        match swarm.behaviour_mut().publish(NODE_STATUS_TOPIC, data) {
+            Ok(_) => debug!(status = %announcement.status, "Published node status"),
+            Err(PublishError::InsufficientPeers) => {
+                debug!("No gossip peers yet, node status not published")
+            }
+            Err(e) => warn!(error = ?e, "Failed to publish node status"),
+        }
+        self.cluster.update(announcement);
+    }
+
+    /// Dial stored peers that are disconnected and past their backoff
+    fn redial_known_peers(&self, swarm: &mut Swarm<CyxCloudBehaviour>) {
+        for (peer_id, addresses) in self.registry.dial_candidates() {
+            if peer_id == self.local_peer_id || swarm.is_connected(&peer_id) {
+                continue;
+            }
+            let opts = DialOpts::peer_id(peer_id).addresses(addresses).build();
+            if let Err(e) = swarm.dial(opts) {
+                debug!(peer = %peer_id, error = %e, "Dial not started");
+                self.registry.record_dial_failure(peer_id);
             }
         }
     }
@@ -340,22 +588,28 @@ impl DiscoveryService {
                     return; // Skip self
                 }
 
-                let mut peers = peers.write();
-                let peer = peers
-                    .entry(peer_id)
-                    .or_insert_with(|| PeerInfo::new(peer_id));
-                peer.addresses = addresses.clone();
-                peer.touch();
+                let peer = {
+                    let mut peers = peers.write();
+                    let peer = peers
+                        .entry(peer_id)
+                        .or_insert_with(|| PeerInfo::new(peer_id));
+                    peer.addresses = addresses.clone();
+                    peer.touch();
+                    peer.clone()
+                };
 
                 info!(peer = %peer_id, addresses = ?addresses, "Peer discovered");
+                if let Err(e) = self.registry.record_seen(&peer) {
+                    warn!(peer = %peer_id, error = %e, "Failed to store peer");
+                }
 
                 if let Some(ref tx) = event_tx {
-                    let _ = tx.send(DiscoveryEvent::PeerDiscovered(peer.clone())).await;
+                    let _ = tx.send(DiscoveryEvent::PeerDiscovered(peer)).await;
                 }
             }
             CyxCloudEvent::PeerExpired { peer_id } => {
-                let mut peers = peers.write();
-                if peers.remove(&peer_id).is_some() {
+                let removed = peers.write().remove(&peer_id).is_some();
+                if removed {
                     debug!(peer = %peer_id, "Peer expired");
 
                     if let Some(ref tx) = event_tx {
@@ -364,11 +618,15 @@ impl DiscoveryService {
                 }
             }
             CyxCloudEvent::PingResult { peer_id, rtt } => {
-                let mut peers = peers.write();
-                if let Some(peer) = peers.get_mut(&peer_id) {
-                    peer.latency_ms = Some(rtt.as_millis() as u64);
-                    peer.touch();
-
+                let known = match peers.write().get_mut(&peer_id) {
+                    Some(peer) => {
+                        peer.latency_ms = Some(rtt.as_millis() as u64);
+                        peer.touch();
+                        true
+                    }
+                    None => false,
+                };
+                if known {
                     if let Some(ref tx) = event_tx {
                         let _ = tx
                             .send(DiscoveryEvent::PeerLatencyUpdated {
@@ -383,18 +641,57 @@ impl DiscoveryService {
                 debug!(peer = %peer_id, "Ping failed");
             }
             CyxCloudEvent::IdentifyReceived { peer_id, info } => {
-                let mut peers = peers.write();
-                let peer = peers
-                    .entry(peer_id)
-                    .or_insert_with(|| PeerInfo::new(peer_id));
-                peer.addresses = info.listen_addrs.clone();
-                peer.agent_version = Some(info.agent_version);
-                peer.touch();
+                let peer = {
+                    let mut peers = peers.write();
+                    let peer = peers
+                        .entry(peer_id)
+                        .or_insert_with(|| PeerInfo::new(peer_id));
+                    peer.addresses = info.listen_addrs.clone();
+                    peer.agent_version = Some(info.agent_version);
+                    peer.touch();
+                    peer.clone()
+                };
+                if let Err(e) = self.registry.record_seen(&peer) {
+                    warn!(peer = %peer_id, error = %e, "Failed to store peer");
+                }
 
                 debug!(peer = %peer_id, "Updated peer info from identify");
             }
-            CyxCloudEvent::Kademlia(_) => {
-                // Kademlia events are handled internally
+            CyxCloudEvent::GossipMessage {
+                source,
+                topic,
+                data,
+            } => {
+                if topic != IdentTopic::new(NODE_STATUS_TOPIC).hash() {
+                    return;
+                }
+                let announcement = match NodeAnnouncement::from_bytes(&data) {
+                    Ok(announcement) => announcement,
+                    Err(e) => {
+                        debug!(source = ?source, error = %e, "Ignoring malformed node status");
+                        return;
+                    }
+                };
+                if !self.cluster.update(announcement.clone()) {
+                    return;
+                }
+
+                info!(
+                    node_id = %announcement.node_id,
+                    status = %announcement.status,
+                    "Node status changed"
+                );
+                if let Some(ref tx) = event_tx {
+                    let _ = tx
+                        .send(DiscoveryEvent::NodeStatus {
+                            source,
+                            announcement,
+                        })
+                        .await;
+                }
+            }
+            CyxCloudEvent::Kademlia(_) | CyxCloudEvent::Gossipsub(_) => {
+                // Kademlia and subscription events are handled internally
             }
         }
     }
@@ -438,6 +735,44 @@ mod tests {
         assert!(!config.listen_addrs.is_empty());
     }
 
+    #[test]
+    fn test_bootstrap_addr_needs_peer_id() {
+        let peer_id = Keypair::generate_ed25519().public().to_peer_id();
+        let mut config = DiscoveryConfig::default();
+
+        assert!(!config.add_bootstrap_addr("/ip4/10.0.0.1/tcp/4001".parse().unwrap()));
+        let addr: Multiaddr = format!("/ip4/10.0.0.1/tcp/4001/p2p/{}", peer_id)
+            .parse()
+            .unwrap();
+        assert!(config.add_bootstrap_addr(addr));
+        assert_eq!(config.bootstrap_peers.len(), 1);
+        assert_eq!(config.bootstrap_peers[0].0, peer_id);
+    }
+
+    #[test]
+    fn test_cluster_view_keeps_latest() {
+        let view = ClusterView::new();
+        let mut online = NodeAnnouncement::new("node-1", "10.0.0.1:50051");
+        online.timestamp = 1_000;
+
+        assert!(view.update(online.clone()));
+        // Periodic re-announcement with nothing new
+        let mut repeat = online.clone();
+        repeat.timestamp = 1_060;
+        assert!(!view.update(repeat));
+
+        let mut draining = online.clone();
+        draining.timestamp = 1_100;
+        draining.status = NodeStatus::Draining;
+        assert!(view.update(draining));
+
+        // Out-of-order delivery of an older announcement is ignored
+        assert!(!view.update(online));
+        assert_eq!(view.get("node-1").unwrap().status, NodeStatus::Draining);
+        assert_eq!(view.len(), 1);
+        assert!(view.online_nodes(u64::MAX).is_empty());
+    }
+
     #[test]
     fn test_discovery_service_creation() {
         let config = DiscoveryConfig::default();
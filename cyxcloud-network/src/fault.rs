@@ -0,0 +1,425 @@
+//! Fault injection for chaos testing
+//!
+//! Only built with the `fault-injection` feature. A [`FaultInjector`] sits in
+//! front of the RPCs of the ChunkService and of the clients that call it, and
+//! can, per call:
+//!
+//! - add latency
+//! - fail the call with `UNAVAILABLE`
+//! - reset the connection it runs on
+//! - corrupt the chunk data it returns (selected chunks, or at random)
+//!
+//! Corrupted data is altered before the wire checksum is computed, so it
+//! looks like bit rot on the replica rather than a transport error: readers
+//! must catch it by the content hash and fall back to another replica or to
+//! erasure reconstruction.
+//!
+//! Faults are read from `FAULT_*` environment variables at startup and can
+//! be replaced at runtime (the gateway exposes `/api/v1/admin/faults` in
+//! builds with the feature).
+
+use bytes::{Bytes, BytesMut};
+use cyxcloud_core::chunk::ChunkId;
+use cyxcloud_core::error::Result;
+use cyxcloud_storage::backend::{StorageBackendSync, StorageStats};
+use cyxcloud_storage::shard_header::ShardHeader;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+use tonic::Status;
+use tracing::{debug, warn};
+
+/// Faults to inject; all rates are probabilities from 0.0 to 1.0
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FaultConfig {
+    /// Calls failed with `UNAVAILABLE`
+    pub error_rate: f64,
+    /// Calls whose connection is reset
+    pub reset_rate: f64,
+    /// Calls delayed by `latency_ms`
+    pub latency_rate: f64,
+    /// Delay added to delayed calls
+    pub latency_ms: u64,
+    /// Chunk reads returned corrupted
+    pub corrupt_rate: f64,
+    /// Chunks (base58 or hex IDs) whose reads are always corrupted
+    pub corrupt_chunks: Vec<String>,
+}
+
+impl FaultConfig {
+    /// Load faults from environment variables
+    ///
+    /// `FAULT_ERROR_RATE`, `FAULT_RESET_RATE`, `FAULT_LATENCY_RATE`,
+    /// `FAULT_LATENCY_MS`, `FAULT_CORRUPT_RATE` and `FAULT_CORRUPT_CHUNKS`
+    /// (comma-separated chunk IDs).
+    pub fn from_env() -> Self {
+        let rate = |name: &str| {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.parse::<f64>().ok())
+                .unwrap_or(0.0)
+        };
+
+        Self {
+            error_rate: rate("FAULT_ERROR_RATE"),
+            reset_rate: rate("FAULT_RESET_RATE"),
+            latency_rate: rate("FAULT_LATENCY_RATE"),
+            latency_ms: std::env::var("FAULT_LATENCY_MS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(0),
+            corrupt_rate: rate("FAULT_CORRUPT_RATE"),
+            corrupt_chunks: std::env::var("FAULT_CORRUPT_CHUNKS")
+                .map(|v| {
+                    v.split(',')
+                        .map(str::trim)
+                        .filter(|id| !id.is_empty())
+                        .map(String::from)
+                        .collect()
+                })
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Check that every rate is a probability
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        let rates = [
+            ("error_rate", self.error_rate),
+            ("reset_rate", self.reset_rate),
+            ("latency_rate", self.latency_rate),
+            ("corrupt_rate", self.corrupt_rate),
+        ];
+        for (name, rate) in rates {
+            if !(0.0..=1.0).contains(&rate) {
+                return Err(format!(
+                    "{} must be between 0.0 and 1.0, got {}",
+                    name, rate
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether any fault is configured
+    pub fn is_active(&self) -> bool {
+        self.error_rate > 0.0
+            || self.reset_rate > 0.0
+            || (self.latency_rate > 0.0 && self.latency_ms > 0)
+            || self.corrupt_rate > 0.0
+            || !self.corrupt_chunks.is_empty()
+    }
+
+    /// Whether reads of `chunk_id` are always corrupted
+    fn targets(&self, chunk_id: ChunkId) -> bool {
+        if self.corrupt_chunks.is_empty() {
+            return false;
+        }
+        let hex: String = chunk_id
+            .as_bytes()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        let base58 = chunk_id.to_string();
+        self.corrupt_chunks
+            .iter()
+            .any(|id| id.eq_ignore_ascii_case(&hex) || *id == base58)
+    }
+}
+
+/// A fault injected into one call
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    /// The call fails as if the peer were unavailable
+    Unavailable,
+    /// The connection the call runs on is dropped
+    ConnectionReset,
+}
+
+impl Fault {
+    /// The status a server answers the faulted call with
+    pub fn to_status(self, rpc: &str) -> Status {
+        match self {
+            Fault::Unavailable => Status::unavailable(format!("{}: injected failure", rpc)),
+            Fault::ConnectionReset => {
+                Status::unavailable(format!("{}: connection reset (injected)", rpc))
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for Fault {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Fault::Unavailable => write!(f, "injected failure"),
+            Fault::ConnectionReset => write!(f, "connection reset (injected)"),
+        }
+    }
+}
+
+/// Decides which calls fail, stall or return corrupted data
+#[derive(Debug, Default)]
+pub struct FaultInjector {
+    config: RwLock<FaultConfig>,
+}
+
+impl FaultInjector {
+    /// Create an injector with the given faults
+    pub fn new(config: FaultConfig) -> Self {
+        Self {
+            config: RwLock::new(config),
+        }
+    }
+
+    /// Create an injector with the faults from the environment
+    pub fn from_env() -> Self {
+        let config = FaultConfig::from_env();
+        if config.is_active() {
+            warn!(?config, "Fault injection active");
+        }
+        Self::new(config)
+    }
+
+    /// The faults currently injected
+    pub fn config(&self) -> FaultConfig {
+        self.config.read().clone()
+    }
+
+    /// Replace the faults
+    pub fn set(&self, config: FaultConfig) {
+        *self.config.write() = config;
+    }
+
+    /// Stop injecting faults
+    pub fn clear(&self) {
+        self.set(FaultConfig::default());
+    }
+
+    /// Apply the latency fault to a call, then pick the fault it fails with
+    pub async fn before_call(&self, rpc: &str) -> Option<Fault> {
+        let (latency, reset_rate, error_rate) = {
+            let config = self.config.read();
+            let latency = (config.latency_ms > 0 && roll(config.latency_rate))
+                .then(|| Duration::from_millis(config.latency_ms));
+            (latency, config.reset_rate, config.error_rate)
+        };
+
+        if let Some(latency) = latency {
+            debug!(
+                rpc,
+                latency_ms = latency.as_millis() as u64,
+                "Injecting latency"
+            );
+            tokio::time::sleep(latency).await;
+        }
+
+        let fault = if roll(reset_rate) {
+            Some(Fault::ConnectionReset)
+        } else if roll(error_rate) {
+            Some(Fault::Unavailable)
+        } else {
+            None
+        };
+        if let Some(fault) = fault {
+            debug!(rpc, %fault, "Injecting fault");
+        }
+        fault
+    }
+
+    /// Return `data` with one byte flipped if reads of this chunk are
+    /// corrupted, unchanged otherwise
+    pub fn corrupt(&self, chunk_id: ChunkId, data: Bytes) -> Bytes {
+        let corrupt = {
+            let config = self.config.read();
+            config.targets(chunk_id) || roll(config.corrupt_rate)
+        };
+        if !corrupt || data.is_empty() {
+            return data;
+        }
+
+        debug!(chunk_id = %chunk_id, "Injecting chunk corruption");
+        let mut corrupted = BytesMut::from(&data[..]);
+        let middle = corrupted.len() / 2;
+        corrupted[middle] ^= 0xff;
+        corrupted.freeze()
+    }
+}
+
+/// Storage backend whose reads go through a [`FaultInjector`]
+///
+/// Corruption happens here, below the RPC layer, so every read path
+/// (GetChunk, ReadChunk, StreamChunks, ReplicateChunk, VerifyChunk) sees the
+/// same rotten data.
+pub struct FaultyBackend {
+    inner: Arc<dyn StorageBackendSync>,
+    faults: Arc<FaultInjector>,
+}
+
+impl FaultyBackend {
+    /// Wrap `inner`, corrupting reads as `faults` dictates
+    pub fn new(inner: Arc<dyn StorageBackendSync>, faults: Arc<FaultInjector>) -> Self {
+        Self { inner, faults }
+    }
+}
+
+impl StorageBackendSync for FaultyBackend {
+    fn put(&self, id: ChunkId, data: Bytes) -> Result<()> {
+        self.inner.put(id, data)
+    }
+
+    fn get(&self, id: ChunkId) -> Result<Option<Bytes>> {
+        Ok(self
+            .inner
+            .get(id)?
+            .map(|data| self.faults.corrupt(id, data)))
+    }
+
+    fn delete(&self, id: ChunkId) -> Result<bool> {
+        self.inner.delete(id)
+    }
+
+    fn exists(&self, id: ChunkId) -> Result<bool> {
+        self.inner.exists(id)
+    }
+
+    fn stats(&self) -> Result<StorageStats> {
+        self.inner.stats()
+    }
+
+    fn list_chunks(&self) -> Result<Vec<ChunkId>> {
+        self.inner.list_chunks()
+    }
+
+    fn flush(&self) -> Result<()> {
+        self.inner.flush()
+    }
+
+    fn put_shard(&self, id: ChunkId, header: &ShardHeader, data: Bytes) -> Result<()> {
+        self.inner.put_shard(id, header, data)
+    }
+
+    fn get_shard(&self, id: ChunkId) -> Result<Option<(Option<ShardHeader>, Bytes)>> {
+        Ok(self
+            .inner
+            .get_shard(id)?
+            .map(|(header, data)| (header, self.faults.corrupt(id, data))))
+    }
+}
+
+/// True with probability `rate`
+fn roll(rate: f64) -> bool {
+    rate > 0.0 && rand::random::<f64>() < rate
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_inactive_injector_passes_calls() {
+        let faults = FaultInjector::default();
+        assert!(!faults.config().is_active());
+        assert_eq!(faults.before_call("GetChunk").await, None);
+
+        let data = Bytes::from_static(b"chunk data");
+        assert_eq!(
+            faults.corrupt(ChunkId::from_data(&data), data.clone()),
+            data
+        );
+    }
+
+    #[tokio::test]
+    async fn test_certain_faults() {
+        let faults = FaultInjector::new(FaultConfig {
+            error_rate: 1.0,
+            ..Default::default()
+        });
+        assert_eq!(
+            faults.before_call("GetChunk").await,
+            Some(Fault::Unavailable)
+        );
+
+        // Resets win over plain failures
+        faults.set(FaultConfig {
+            error_rate: 1.0,
+            reset_rate: 1.0,
+            ..Default::default()
+        });
+        assert_eq!(
+            faults.before_call("GetChunk").await,
+            Some(Fault::ConnectionReset)
+        );
+
+        faults.clear();
+        assert_eq!(faults.before_call("GetChunk").await, None);
+    }
+
+    #[test]
+    fn test_corrupt_targeted_chunks() {
+        let target = ChunkId::from_data(b"target");
+        let other = ChunkId::from_data(b"other");
+        let faults = FaultInjector::new(FaultConfig {
+            corrupt_chunks: vec![target.to_string()],
+            ..Default::default()
+        });
+
+        let data = Bytes::from_static(b"chunk data");
+        let corrupted = faults.corrupt(target, data.clone());
+        assert_ne!(corrupted, data);
+        assert_eq!(corrupted.len(), data.len());
+        assert_eq!(faults.corrupt(other, data.clone()), data);
+
+        // Hex IDs match too
+        let hex: String = other
+            .as_bytes()
+            .iter()
+            .map(|b| format!("{:02X}", b))
+            .collect();
+        faults.set(FaultConfig {
+            corrupt_chunks: vec![hex],
+            ..Default::default()
+        });
+        assert_ne!(faults.corrupt(other, data.clone()), data);
+    }
+
+    #[test]
+    fn test_faulty_backend_corrupts_reads() {
+        use cyxcloud_storage::MemoryBackend;
+
+        let faults = Arc::new(FaultInjector::default());
+        let backend = FaultyBackend::new(Arc::new(MemoryBackend::new()), faults.clone());
+        let data = Bytes::from_static(b"stored chunk");
+        let id = ChunkId::from_data(&data);
+        backend.put(id, data.clone()).unwrap();
+        assert_eq!(backend.get(id).unwrap().unwrap(), data);
+
+        faults.set(FaultConfig {
+            corrupt_rate: 1.0,
+            ..Default::default()
+        });
+        let read = backend.get(id).unwrap().unwrap();
+        assert_ne!(ChunkId::from_data(&read), id);
+        assert!(backend
+            .get(ChunkId::from_data(b"missing"))
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_config_json() {
+        let config: FaultConfig =
+            serde_json::from_str(r#"{"error_rate": 0.5, "latency_ms": 200}"#).unwrap();
+        assert_eq!(config.error_rate, 0.5);
+        assert_eq!(config.latency_ms, 200);
+        assert_eq!(config.latency_rate, 0.0);
+        assert!(config.is_active());
+        assert!(config.validate().is_ok());
+
+        let config = FaultConfig {
+            error_rate: 1.5,
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+    }
+}
@@ -1,23 +1,51 @@
 //! gRPC client for communicating with other CyxCloud nodes
 //!
 //! Provides connection pooling, retry logic, and high-level chunk operations.
+//!
+//! Storing a chunk on several nodes is pipelined: the chunk is cut into
+//! frames once and each frame is handed to every target's `WriteChunk`
+//! stream as soon as it is available. A target that fails drops out without
+//! holding up the others, and the [`FanOutReport`] says which targets got the
+//! chunk. [`replicate_chunk`] feeds the same pipeline straight from another
+//! node's `ReadChunk` stream, so a repair reads the chunk once and writes it
+//! while it is still downloading.
+//!
+//! Chunk data carries a wire checksum both ways. Data that fails it is a
+//! [`CyxCloudError::TransportCorruption`]: single-node calls retry it like
+//! any other failure and [`get_from_any_node`] moves on to the next replica.
+//!
+//! With `encrypt_transfers` set, fan-outs encrypt every target's frames to
+//! that target's transfer key (see [`crate::transfer_crypto`]). A target
+//! that cannot hand out a key fails rather than getting the chunk in clear.
 
+#[cfg(feature = "fault-injection")]
+use crate::fault::{Fault, FaultInjector};
+use crate::grpc_server::{CLUSTER_TOKEN_METADATA, DEFAULT_FRAME_SIZE};
+use crate::transfer_crypto::TransferCipher;
 use bytes::Bytes;
-use cyxcloud_core::chunk::ChunkId;
-use cyxcloud_core::error::{CyxCloudError, Result};
+use cyxcloud_core::chunk::{verify_wire_checksum, wire_checksum, ChunkId};
+use cyxcloud_core::error::{CyxCloudError, ErrorCode, Result};
 use cyxcloud_core::tls::{create_tonic_client_tls, TlsClientConfig};
 use cyxcloud_protocol::chunk::{
-    chunk_service_client::ChunkServiceClient, DeleteChunkRequest, GetChunkRequest,
-    StoreChunkRequest, StreamChunksRequest, VerifyChunkRequest,
+    chunk_service_client::ChunkServiceClient, ChunkFrame, ChunkMetadata, DeleteChunkRequest,
+    GetChunkRequest, GetTransferKeyRequest, ReadChunkRequest, ReplicateChunkRequest,
+    StoreChunkRequest, StreamChunksRequest, VerifyChunkRequest, WriteChunkFrame,
 };
+use futures::future::Either;
+use futures::{Stream, StreamExt};
 use parking_lot::RwLock;
 use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
 use tonic::transport::Channel;
 use tracing::{debug, info, instrument, warn};
 
+/// Frames queued per fan-out target before the source waits for it
+const FAN_OUT_QUEUE_FRAMES: usize = 4;
+
 /// Configuration for the gRPC client
 #[derive(Debug, Clone)]
 pub struct ChunkClientConfig {
@@ -41,6 +69,11 @@ pub struct ChunkClientConfig {
     pub tls_client_cert: Option<PathBuf>,
     /// Client key path for mTLS
     pub tls_client_key: Option<PathBuf>,
+    /// Shared token presented on node-to-node RPCs (WriteChunk, ReplicateChunk)
+    pub cluster_token: Option<String>,
+    /// Encrypt chunks fanned out to nodes, and ask nodes pushing chunks to
+    /// encrypt them
+    pub encrypt_transfers: bool,
 }
 
 impl Default for ChunkClientConfig {
@@ -56,6 +89,8 @@ impl Default for ChunkClientConfig {
             tls_ca_cert: None,
             tls_client_cert: None,
             tls_client_key: None,
+            cluster_token: None,
+            encrypt_transfers: false,
         }
     }
 }
@@ -66,6 +101,9 @@ pub struct ChunkClient {
     clients: Arc<RwLock<HashMap<String, ChunkServiceClient<Channel>>>>,
     /// Configuration
     config: ChunkClientConfig,
+    /// Faults injected into outgoing calls
+    #[cfg(feature = "fault-injection")]
+    faults: Arc<FaultInjector>,
 }
 
 impl ChunkClient {
@@ -79,11 +117,28 @@ impl ChunkClient {
         Self {
             clients: Arc::new(RwLock::new(HashMap::new())),
             config,
+            #[cfg(feature = "fault-injection")]
+            faults: Arc::new(FaultInjector::from_env()),
         }
     }
 
+    /// Faults injected into this client's calls
+    #[cfg(feature = "fault-injection")]
+    pub fn faults(&self) -> Arc<FaultInjector> {
+        self.faults.clone()
+    }
+
     /// Get or create a client for the given address
     async fn get_client(&self, addr: &str) -> Result<ChunkServiceClient<Channel>> {
+        // An injected reset drops the cached connection like a real one would
+        #[cfg(feature = "fault-injection")]
+        if let Some(fault) = self.faults.before_call(addr).await {
+            if fault == Fault::ConnectionReset {
+                self.remove_client(addr);
+            }
+            return Err(CyxCloudError::Network(format!("{}: {}", addr, fault)));
+        }
+
         // Check if we have an existing connection
         {
             let clients = self.clients.read();
@@ -157,6 +212,19 @@ impl ChunkClient {
         debug!(addr = %addr, "Removed gRPC connection from cache");
     }
 
+    /// Attach the cluster token, if one is configured, to a request
+    fn authorize<T>(&self, mut request: tonic::Request<T>) -> tonic::Request<T> {
+        if let Some(token) = self
+            .config
+            .cluster_token
+            .as_deref()
+            .and_then(|t| t.parse().ok())
+        {
+            request.metadata_mut().insert(CLUSTER_TOKEN_METADATA, token);
+        }
+        request
+    }
+
     /// Execute an operation with retry logic
     async fn with_retry<F, Fut, T>(&self, addr: &str, operation: F) -> Result<T>
     where
@@ -205,6 +273,7 @@ impl ChunkClient {
     #[instrument(skip(self, data), fields(addr = %addr, chunk_id = %chunk_id))]
     pub async fn store_chunk(&self, addr: &str, chunk_id: ChunkId, data: Bytes) -> Result<()> {
         debug!(size = data.len(), "Storing chunk on remote node");
+        let checksum = wire_checksum(&data);
 
         self.with_retry(addr, |mut client| {
             let chunk_id = chunk_id;
@@ -212,14 +281,15 @@ impl ChunkClient {
             async move {
                 let request = tonic::Request::new(StoreChunkRequest {
                     chunk_id: chunk_id.as_bytes().to_vec(),
-                    data: data.to_vec(),
+                    data,
                     metadata: None,
+                    checksum,
                 });
 
                 let response = client
                     .store_chunk(request)
                     .await
-                    .map_err(|e| CyxCloudError::Network(format!("StoreChunk RPC failed: {}", e)))?;
+                    .map_err(|e| rpc_error("StoreChunk", e))?;
 
                 let inner = response.into_inner();
                 if inner.success {
@@ -235,6 +305,116 @@ impl ChunkClient {
         .await
     }
 
+    /// Store a chunk on a remote node from a stream of frames
+    ///
+    /// The first frame must carry the chunk ID. Not retried: the frames are
+    /// consumed as they are sent.
+    #[instrument(skip(self, frames), fields(addr = %addr))]
+    pub async fn write_chunk<S>(&self, addr: &str, frames: S) -> Result<()>
+    where
+        S: Stream<Item = WriteChunkFrame> + Send + 'static,
+    {
+        let mut client = self.get_client(addr).await?;
+
+        let response = client
+            .write_chunk(self.authorize(tonic::Request::new(frames)))
+            .await
+            .map_err(|e| rpc_error("WriteChunk", e))?;
+
+        let inner = response.into_inner();
+        if inner.success {
+            Ok(())
+        } else {
+            Err(CyxCloudError::Network(format!(
+                "WriteChunk failed: {}",
+                inner.error
+            )))
+        }
+    }
+
+    /// Ask the `source` node to push one of its chunks to `targets`
+    ///
+    /// The data goes from the source straight to the targets without passing
+    /// through this client. Returns `None` if the source predates
+    /// ReplicateChunk, so the caller can relay the chunk itself.
+    #[instrument(skip(self, targets), fields(source = %source, chunk_id = %chunk_id))]
+    pub async fn push_chunk(
+        &self,
+        source: &str,
+        chunk_id: ChunkId,
+        targets: &[String],
+    ) -> Result<Option<FanOutReport>> {
+        let mut client = self.get_client(source).await?;
+        let request = self.authorize(tonic::Request::new(ReplicateChunkRequest {
+            chunk_id: chunk_id.as_bytes().to_vec(),
+            target_addrs: targets.to_vec(),
+            encrypt: self.config.encrypt_transfers,
+        }));
+
+        let response = match client.replicate_chunk(request).await {
+            Ok(response) => response.into_inner(),
+            Err(status) if status.code() == tonic::Code::Unimplemented => return Ok(None),
+            Err(status) if status.code() == tonic::Code::NotFound => {
+                return Err(CyxCloudError::ChunkNotFound(chunk_id.to_string()))
+            }
+            Err(e) => {
+                return Err(CyxCloudError::Network(format!(
+                    "ReplicateChunk RPC failed: {}",
+                    e
+                )))
+            }
+        };
+        if self.config.encrypt_transfers && !response.encrypted {
+            // Sources predating transfer encryption ignore the flag
+            warn!("Source node pushed the chunk unencrypted");
+        }
+
+        let targets = response
+            .results
+            .into_iter()
+            .map(|result| TargetOutcome {
+                addr: result.target_addr,
+                bytes_sent: result.bytes_sent,
+                error: (!result.success).then_some(result.error),
+            })
+            .collect();
+        Ok(Some(FanOutReport { targets }))
+    }
+
+    /// Fetch the public key a node receives encrypted transfers with
+    #[instrument(skip(self), fields(addr = %addr))]
+    pub async fn get_transfer_key(&self, addr: &str) -> Result<Vec<u8>> {
+        self.with_retry(addr, |mut client| async move {
+            match client
+                .get_transfer_key(tonic::Request::new(GetTransferKeyRequest {}))
+                .await
+            {
+                Ok(response) => Ok(response.into_inner().public_key),
+                Err(status)
+                    if matches!(
+                        status.code(),
+                        tonic::Code::Unimplemented | tonic::Code::FailedPrecondition
+                    ) =>
+                {
+                    Err(CyxCloudError::Network(format!(
+                        "{} does not accept encrypted transfers",
+                        addr
+                    )))
+                }
+                Err(e) => Err(CyxCloudError::Network(format!(
+                    "GetTransferKey RPC failed: {}",
+                    e
+                ))),
+            }
+        })
+        .await
+    }
+
+    /// Start an encrypted transfer to a node
+    pub async fn transfer_cipher(&self, addr: &str) -> Result<TransferCipher> {
+        TransferCipher::seal_to(&self.get_transfer_key(addr).await?)
+    }
+
     /// Get a chunk from a remote node
     #[instrument(skip(self), fields(addr = %addr, chunk_id = %chunk_id))]
     pub async fn get_chunk(&self, addr: &str, chunk_id: ChunkId) -> Result<Option<Bytes>> {
@@ -254,7 +434,8 @@ impl ChunkClient {
 
                 let inner = response.into_inner();
                 if inner.found {
-                    Ok(Some(Bytes::from(inner.data)))
+                    verify_wire_checksum(&inner.data, inner.checksum)?;
+                    Ok(Some(inner.data))
                 } else {
                     Ok(None)
                 }
@@ -271,9 +452,9 @@ impl ChunkClient {
         self.with_retry(addr, |mut client| {
             let chunk_id = chunk_id;
             async move {
-                let request = tonic::Request::new(DeleteChunkRequest {
+                let request = self.authorize(tonic::Request::new(DeleteChunkRequest {
                     chunk_id: chunk_id.as_bytes().to_vec(),
-                });
+                }));
 
                 let response = client.delete_chunk(request).await.map_err(|e| {
                     CyxCloudError::Network(format!("DeleteChunk RPC failed: {}", e))
@@ -308,6 +489,47 @@ impl ChunkClient {
         .await
     }
 
+    /// Hash of a chunk's data as stored on a remote node
+    ///
+    /// Returns None if the node doesn't hold the chunk. Nodes that predate
+    /// reporting the hash only say whether the data matches the chunk ID, so
+    /// a match is returned as the ID itself and a mismatch as
+    /// [`CyxCloudError::ChunkCorrupted`].
+    #[instrument(skip(self), fields(addr = %addr, chunk_id = %chunk_id))]
+    pub async fn stored_chunk_hash(
+        &self,
+        addr: &str,
+        chunk_id: ChunkId,
+    ) -> Result<Option<ChunkId>> {
+        debug!("Requesting stored chunk hash from remote node");
+
+        let inner = self
+            .with_retry(addr, |mut client| {
+                let chunk_id = chunk_id;
+                async move {
+                    let request = tonic::Request::new(VerifyChunkRequest {
+                        chunk_id: chunk_id.as_bytes().to_vec(),
+                    });
+
+                    let response = client.verify_chunk(request).await.map_err(|e| {
+                        CyxCloudError::Network(format!("VerifyChunk RPC failed: {}", e))
+                    })?;
+
+                    Ok(response.into_inner())
+                }
+            })
+            .await?;
+
+        if let Ok(hash) = <[u8; 32]>::try_from(inner.content_hash.as_slice()) {
+            return Ok(Some(ChunkId::from_bytes(hash)));
+        }
+        match (inner.valid, inner.size) {
+            (true, _) => Ok(Some(chunk_id)),
+            (false, 0) => Ok(None),
+            (false, _) => Err(CyxCloudError::ChunkCorrupted),
+        }
+    }
+
     /// Stream multiple chunks from a remote node
     #[instrument(skip(self, chunk_ids), fields(addr = %addr, count = chunk_ids.len()))]
     pub async fn stream_chunks(
@@ -338,10 +560,11 @@ impl ChunkClient {
                     .map_err(|e| CyxCloudError::Network(format!("Stream error: {}", e)))?
                 {
                     if chunk_data.chunk_id.len() == 32 {
+                        verify_wire_checksum(&chunk_data.data, chunk_data.checksum)?;
                         let mut arr = [0u8; 32];
                         arr.copy_from_slice(&chunk_data.chunk_id);
                         let id = ChunkId::from_bytes(arr);
-                        results.push((id, Bytes::from(chunk_data.data)));
+                        results.push((id, chunk_data.data));
                     }
                 }
 
@@ -351,6 +574,65 @@ impl ChunkClient {
         .await
     }
 
+    /// Read a single chunk from a remote node as a stream of frames
+    ///
+    /// Preferred over `get_chunk` for large chunks: the node never has to
+    /// build one message holding the whole chunk. `frame_size` of 0 uses the
+    /// server default.
+    #[instrument(skip(self), fields(addr = %addr, chunk_id = %chunk_id))]
+    pub async fn read_chunk(
+        &self,
+        addr: &str,
+        chunk_id: ChunkId,
+        frame_size: u32,
+    ) -> Result<Option<Bytes>> {
+        debug!("Reading chunk frames from remote node");
+
+        self.with_retry(addr, |mut client| {
+            let chunk_id = chunk_id;
+            async move {
+                let request = tonic::Request::new(ReadChunkRequest {
+                    chunk_id: chunk_id.as_bytes().to_vec(),
+                    frame_size,
+                });
+
+                let mut stream = match client.read_chunk(request).await {
+                    Ok(response) => response.into_inner(),
+                    Err(status) if status.code() == tonic::Code::NotFound => return Ok(None),
+                    Err(e) => {
+                        return Err(CyxCloudError::Network(format!(
+                            "ReadChunk RPC failed: {}",
+                            e
+                        )))
+                    }
+                };
+
+                let mut data = Vec::new();
+                while let Some(frame) = stream
+                    .message()
+                    .await
+                    .map_err(|e| CyxCloudError::Network(format!("Stream error: {}", e)))?
+                {
+                    if frame.offset != data.len() as u64 {
+                        return Err(CyxCloudError::Network(format!(
+                            "ReadChunk frame out of order: offset {}, expected {}",
+                            frame.offset,
+                            data.len()
+                        )));
+                    }
+                    verify_wire_checksum(&frame.data, frame.checksum)?;
+                    if data.is_empty() {
+                        data.reserve_exact(frame.total_size as usize);
+                    }
+                    data.extend_from_slice(&frame.data);
+                }
+
+                Ok(Some(Bytes::from(data)))
+            }
+        })
+        .await
+    }
+
     /// Check if a node is reachable by attempting to connect
     pub async fn is_reachable(&self, addr: &str) -> bool {
         match self.get_client(addr).await {
@@ -382,6 +664,47 @@ impl Default for ChunkClient {
     }
 }
 
+/// How one target of a fan-out fared
+#[derive(Debug, Clone)]
+pub struct TargetOutcome {
+    /// Target node address
+    pub addr: String,
+    /// Bytes handed to the target before it finished or dropped out
+    pub bytes_sent: u64,
+    /// Why the target did not store the chunk, if it didn't
+    pub error: Option<String>,
+}
+
+impl TargetOutcome {
+    /// Whether the target stored the chunk
+    pub fn succeeded(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// Result of storing one chunk on several nodes
+#[derive(Debug, Clone, Default)]
+pub struct FanOutReport {
+    /// One outcome per target, in the order the targets were given
+    pub targets: Vec<TargetOutcome>,
+}
+
+impl FanOutReport {
+    /// Addresses of the targets that stored the chunk
+    pub fn succeeded(&self) -> Vec<String> {
+        self.targets
+            .iter()
+            .filter(|t| t.succeeded())
+            .map(|t| t.addr.clone())
+            .collect()
+    }
+
+    /// Targets that did not store the chunk
+    pub fn failed(&self) -> impl Iterator<Item = &TargetOutcome> {
+        self.targets.iter().filter(|t| !t.succeeded())
+    }
+}
+
 /// Store a chunk to multiple nodes, returning list of successful nodes
 pub async fn store_to_multiple_nodes(
     client: &ChunkClient,
@@ -389,48 +712,289 @@ pub async fn store_to_multiple_nodes(
     data: Bytes,
     nodes: &[String],
 ) -> Result<Vec<String>> {
-    let mut successful = Vec::new();
-    let mut errors = Vec::new();
+    let report = fan_out_chunk(client, chunk_id, data, nodes).await?;
+    let successful = report.succeeded();
 
-    // Store to all nodes concurrently
-    let futures: Vec<_> = nodes
-        .iter()
-        .map(|addr| {
-            let client = client;
-            let addr = addr.clone();
-            let data = data.clone();
-            async move {
-                (
-                    addr.clone(),
-                    client.store_chunk(&addr, chunk_id, data).await,
-                )
+    if successful.is_empty() && report.failed().next().is_some() {
+        return Err(CyxCloudError::Network(format!(
+            "Failed to store chunk to any node: {:?}",
+            report
+                .failed()
+                .map(|t| format!("{}: {}", t.addr, t.error.as_deref().unwrap_or_default()))
+                .collect::<Vec<_>>()
+        )));
+    }
+
+    Ok(successful)
+}
+
+/// Store a chunk on all `nodes` at once, reporting each target's outcome
+pub async fn fan_out_chunk(
+    client: &ChunkClient,
+    chunk_id: ChunkId,
+    data: Bytes,
+    nodes: &[String],
+) -> Result<FanOutReport> {
+    let encrypt = client.config.encrypt_transfers;
+    fan_out_chunk_with(client, chunk_id, data, None, nodes, encrypt).await
+}
+
+/// Like [`fan_out_chunk`], choosing whether the frames are encrypted
+///
+/// `metadata` travels with the first frame so the targets store the shard
+/// header along with the data.
+pub async fn fan_out_chunk_with(
+    client: &ChunkClient,
+    chunk_id: ChunkId,
+    data: Bytes,
+    metadata: Option<ChunkMetadata>,
+    nodes: &[String],
+    encrypt: bool,
+) -> Result<FanOutReport> {
+    let mut frames = split_frames(&data, DEFAULT_FRAME_SIZE);
+    if let Some(first) = frames.first_mut() {
+        first.metadata = metadata;
+    }
+    let frames = frames.into_iter().map(Ok);
+    tee_frames(
+        client,
+        chunk_id,
+        futures::stream::iter(frames),
+        nodes,
+        encrypt,
+    )
+    .await
+}
+
+/// Copy a chunk from the `source` node to all `targets`
+///
+/// The chunk is read once as a `ReadChunk` stream and every frame goes out
+/// to the targets as it arrives, instead of downloading the whole chunk
+/// before uploading it.
+#[instrument(skip(client, targets), fields(source = %source, chunk_id = %chunk_id))]
+pub async fn replicate_chunk(
+    client: &ChunkClient,
+    source: &str,
+    chunk_id: ChunkId,
+    targets: &[String],
+) -> Result<FanOutReport> {
+    let mut source_client = client.get_client(source).await?;
+    let request = tonic::Request::new(ReadChunkRequest {
+        chunk_id: chunk_id.as_bytes().to_vec(),
+        frame_size: 0,
+    });
+
+    let frames = match source_client.read_chunk(request).await {
+        Ok(response) => response.into_inner(),
+        Err(status) if status.code() == tonic::Code::NotFound => {
+            return Err(CyxCloudError::ChunkNotFound(chunk_id.to_string()))
+        }
+        Err(e) => {
+            return Err(CyxCloudError::Network(format!(
+                "ReadChunk RPC failed: {}",
+                e
+            )))
+        }
+    };
+
+    let frames = frames
+        .map(|frame| frame.map_err(|e| CyxCloudError::Network(format!("Stream error: {}", e))));
+    let encrypt = client.config.encrypt_transfers;
+    tee_frames(client, chunk_id, frames, targets, encrypt).await
+}
+
+/// Cut a chunk into frames of at most `frame_size` bytes, without copying
+fn split_frames(data: &Bytes, frame_size: usize) -> Vec<ChunkFrame> {
+    let total_size = data.len() as u64;
+    (0..data.len())
+        .step_by(frame_size)
+        .map(|offset| {
+            let data = data.slice(offset..(offset + frame_size).min(data.len()));
+            ChunkFrame {
+                offset: offset as u64,
+                checksum: wire_checksum(&data),
+                data,
+                total_size,
+                metadata: None,
             }
         })
-        .collect();
+        .collect()
+}
 
-    let results = futures::future::join_all(futures).await;
+/// Turn a failed chunk RPC into an error, keeping transport corruption typed
+fn rpc_error(rpc: &str, status: tonic::Status) -> CyxCloudError {
+    if ErrorCode::from_status(&status) == ErrorCode::TransportCorruption {
+        CyxCloudError::TransportCorruption(format!("{}: {}", rpc, status.message()))
+    } else {
+        CyxCloudError::Network(format!("{} RPC failed: {}", rpc, status))
+    }
+}
+
+/// Send every frame of `source` to all `targets` concurrently
+///
+/// Each target gets its own `WriteChunk` stream with a small queue, so the
+/// pipeline runs at the pace of the slowest live target. A target whose
+/// stream fails is dropped from the fan-out and the rest carry on. An error
+/// from the source ends every stream early, which the targets reject as
+/// incomplete.
+///
+/// With `encrypt`, every target's frames are sealed to that target's transfer
+/// key. A target that does not hand out a key fails without being sent
+/// anything.
+///
+/// Shard metadata on the source's first frame is passed on unchanged.
+async fn tee_frames<S>(
+    client: &ChunkClient,
+    chunk_id: ChunkId,
+    mut source: S,
+    targets: &[String],
+    encrypt: bool,
+) -> Result<FanOutReport>
+where
+    S: Stream<Item = Result<ChunkFrame>> + Unpin,
+{
+    let ciphers: Vec<Option<Result<TransferCipher>>> = if encrypt {
+        futures::future::join_all(targets.iter().map(|addr| client.transfer_cipher(addr)))
+            .await
+            .into_iter()
+            .map(Some)
+            .collect()
+    } else {
+        targets.iter().map(|_| None).collect()
+    };
 
-    for (addr, result) in results {
-        match result {
-            Ok(()) => successful.push(addr),
+    let mut senders = Vec::with_capacity(targets.len());
+    let mut writes = Vec::with_capacity(targets.len());
+    for (addr, cipher) in targets.iter().zip(ciphers) {
+        let cipher = match cipher.transpose() {
+            Ok(cipher) => cipher,
             Err(e) => {
-                warn!(addr = %addr, error = %e, "Failed to store chunk");
-                errors.push((addr, e));
+                senders.push(None);
+                writes.push(Either::Left(futures::future::ready(Err(e))));
+                continue;
             }
-        }
+        };
+        let (tx, rx) = mpsc::channel(FAN_OUT_QUEUE_FRAMES);
+        senders.push(Some((tx, cipher)));
+        writes.push(Either::Right(
+            client.write_chunk(addr, ReceiverStream::new(rx)),
+        ));
     }
 
-    if successful.is_empty() && !errors.is_empty() {
-        return Err(CyxCloudError::Network(format!(
-            "Failed to store chunk to any node: {:?}",
-            errors
-                .iter()
-                .map(|(a, e)| format!("{}: {}", a, e))
-                .collect::<Vec<_>>()
-        )));
-    }
+    let pump = async move {
+        let mut bytes_sent = vec![0u64; senders.len()];
+        let mut offset = 0u64;
 
-    Ok(successful)
+        while let Some(frame) = source.next().await {
+            let frame = frame?;
+            if frame.offset != offset {
+                return Err(CyxCloudError::Network(format!(
+                    "Source frame out of order: offset {}, expected {}",
+                    frame.offset, offset
+                )));
+            }
+            // Damage on the way in must not be passed on as a good frame
+            verify_wire_checksum(&frame.data, frame.checksum)?;
+
+            let frame = WriteChunkFrame {
+                chunk_id: if offset == 0 {
+                    chunk_id.as_bytes().to_vec()
+                } else {
+                    Vec::new()
+                },
+                offset,
+                checksum: match frame.checksum {
+                    0 => wire_checksum(&frame.data),
+                    checksum => checksum,
+                },
+                data: frame.data,
+                total_size: frame.total_size,
+                sender_key: Vec::new(),
+                metadata: frame.metadata,
+            };
+            let len = frame.data.len() as u64;
+
+            let sent = futures::future::join_all(senders.iter().map(|target| {
+                let frame = match target {
+                    Some((_, Some(cipher))) => seal_frame(cipher, chunk_id, &frame),
+                    _ => Ok(frame.clone()),
+                };
+                async move {
+                    match (target, frame) {
+                        (Some((tx, _)), Ok(frame)) => tx.send(frame).await.is_ok(),
+                        _ => false,
+                    }
+                }
+            }))
+            .await;
+
+            // A closed queue means the target's RPC already ended in error
+            for ((tx, ok), sent_bytes) in senders.iter_mut().zip(sent).zip(&mut bytes_sent) {
+                if ok {
+                    *sent_bytes += len;
+                } else {
+                    *tx = None;
+                }
+            }
+            if senders.iter().all(Option::is_none) {
+                break;
+            }
+            offset += len;
+        }
+
+        // Dropping the senders ends every target's stream
+        Ok(bytes_sent)
+    };
+
+    let (pumped, results) = tokio::join!(pump, futures::future::join_all(writes));
+    let bytes_sent = pumped?;
+
+    let targets: Vec<TargetOutcome> = targets
+        .iter()
+        .zip(results)
+        .zip(bytes_sent)
+        .map(|((addr, result), bytes_sent)| {
+            if let Err(ref e) = result {
+                warn!(addr = %addr, bytes_sent, error = %e, "Failed to store chunk");
+            }
+            TargetOutcome {
+                addr: addr.clone(),
+                bytes_sent,
+                error: result.err().map(|e| e.to_string()),
+            }
+        })
+        .collect();
+
+    debug!(
+        chunk_id = %chunk_id,
+        targets = targets.len(),
+        failed = targets.iter().filter(|t| !t.succeeded()).count(),
+        "Chunk fan-out complete"
+    );
+
+    Ok(FanOutReport { targets })
+}
+
+/// Encrypt a frame for one target, adding the sender key to the first frame
+fn seal_frame(
+    cipher: &TransferCipher,
+    chunk_id: ChunkId,
+    frame: &WriteChunkFrame,
+) -> Result<WriteChunkFrame> {
+    let data = cipher.encrypt(chunk_id, frame.offset, &frame.data)?;
+    Ok(WriteChunkFrame {
+        chunk_id: frame.chunk_id.clone(),
+        offset: frame.offset,
+        checksum: wire_checksum(&data),
+        data,
+        total_size: frame.total_size,
+        sender_key: if frame.offset == 0 {
+            cipher.sender_key().to_vec()
+        } else {
+            Vec::new()
+        },
+        metadata: frame.metadata.clone(),
+    })
 }
 
 /// Get a chunk from any of the provided nodes (tries until success)
@@ -472,6 +1036,83 @@ mod tests {
         assert_eq!(client.connection_count(), 0);
     }
 
+    #[test]
+    fn test_split_frames() {
+        let data: Bytes = (0..10u8).collect();
+        let frames = split_frames(&data, 4);
+        assert_eq!(frames.len(), 3);
+        assert_eq!(
+            frames.iter().map(|f| f.offset).collect::<Vec<_>>(),
+            vec![0, 4, 8]
+        );
+        assert_eq!(frames[2].data.as_ref(), &[8, 9]);
+        assert!(frames.iter().all(|f| f.total_size == 10));
+        assert!(frames
+            .iter()
+            .all(|f| verify_wire_checksum(&f.data, f.checksum).is_ok() && f.checksum != 0));
+
+        assert!(split_frames(&Bytes::new(), 4).is_empty());
+    }
+
+    #[test]
+    fn test_fan_out_report() {
+        let report = FanOutReport {
+            targets: vec![
+                TargetOutcome {
+                    addr: "a:1".to_string(),
+                    bytes_sent: 10,
+                    error: None,
+                },
+                TargetOutcome {
+                    addr: "b:1".to_string(),
+                    bytes_sent: 4,
+                    error: Some("connection reset".to_string()),
+                },
+            ],
+        };
+        assert_eq!(report.succeeded(), vec!["a:1".to_string()]);
+        let failed: Vec<_> = report.failed().map(|t| t.addr.as_str()).collect();
+        assert_eq!(failed, vec!["b:1"]);
+    }
+
+    #[test]
+    fn test_seal_frame() {
+        use crate::transfer_crypto::TransferKey;
+
+        let chunk_id = ChunkId::from_data(b"0123456789");
+        let data: Bytes = Bytes::from_static(b"0123456789");
+        let frames: Vec<WriteChunkFrame> = split_frames(&data, 6)
+            .into_iter()
+            .map(|frame| WriteChunkFrame {
+                chunk_id: chunk_id.as_bytes().to_vec(),
+                offset: frame.offset,
+                data: frame.data,
+                total_size: frame.total_size,
+                checksum: frame.checksum,
+                sender_key: Vec::new(),
+                metadata: None,
+            })
+            .collect();
+
+        let target = TransferKey::generate();
+        let cipher = TransferCipher::seal_to(&target.public_key()).unwrap();
+        let sealed: Vec<WriteChunkFrame> = frames
+            .iter()
+            .map(|frame| seal_frame(&cipher, chunk_id, frame).unwrap())
+            .collect();
+        assert_eq!(sealed[0].sender_key, cipher.sender_key().to_vec());
+        assert!(sealed[1].sender_key.is_empty());
+
+        let opened = target.open(&sealed[0].sender_key).unwrap();
+        let mut received = Vec::new();
+        for frame in &sealed {
+            assert!(verify_wire_checksum(&frame.data, frame.checksum).is_ok());
+            received
+                .extend_from_slice(&opened.decrypt(chunk_id, frame.offset, &frame.data).unwrap());
+        }
+        assert_eq!(received, data.as_ref());
+    }
+
     #[test]
     fn test_clear_connections() {
         let client = ChunkClient::new();
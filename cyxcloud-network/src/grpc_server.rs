@@ -4,23 +4,54 @@
 //! This is the server-side implementation that handles incoming requests
 //! from other nodes in the CyxCloud network.
 
-use bytes::Bytes;
-use cyxcloud_core::chunk::ChunkId;
+use crate::admission::{AdmissionConfig, AdmissionGate};
+#[cfg(feature = "fault-injection")]
+use crate::fault::{FaultInjector, FaultyBackend};
+use crate::grpc_client::{fan_out_chunk_with, ChunkClient, ChunkClientConfig};
+use crate::transfer_crypto::TransferKey;
+use bytes::{Bytes, BytesMut};
+use cyxcloud_core::chunk::{verify_wire_checksum, wire_checksum, ChunkId};
+use cyxcloud_core::error::ErrorCode;
 use cyxcloud_core::tls::{create_tonic_server_tls, TlsServerConfig};
+use cyxcloud_core::MAX_CHUNK_SIZE;
 use cyxcloud_protocol::chunk::{
-    chunk_service_server::ChunkService, ChunkData, DeleteChunkRequest, DeleteChunkResponse,
-    GetChunkRequest, GetChunkResponse, StoreChunkRequest, StoreChunkResponse, StreamChunksRequest,
-    VerifyChunkRequest, VerifyChunkResponse,
+    chunk_service_server::ChunkService, ChunkData, ChunkFrame, ChunkMetadata, DeleteChunkRequest,
+    DeleteChunkResponse, GetChunkRequest, GetChunkResponse, GetTransferKeyRequest,
+    GetTransferKeyResponse, ProbeBandwidthFrame, ProbeBandwidthRequest, ReadChunkRequest,
+    ReplicaResult, ReplicateChunkRequest, ReplicateChunkResponse, StoreChunkRequest,
+    StoreChunkResponse, StreamChunksRequest, VerifyChunkRequest, VerifyChunkResponse,
+    WriteChunkFrame,
 };
 use cyxcloud_storage::backend::StorageBackendSync;
-use cyxcloud_storage::RocksDbBackend;
+use cyxcloud_storage::{RocksDbBackend, ShardHeader};
+use rand::RngCore;
 use std::net::SocketAddr;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use tokio::sync::mpsc;
 use tokio_stream::wrappers::ReceiverStream;
-use tonic::{Request, Response, Status};
+use tonic::{Request, Response, Status, Streaming};
 use tracing::{debug, error, info, instrument, warn};
+use uuid::Uuid;
+
+/// Default ReadChunk frame size
+pub const DEFAULT_FRAME_SIZE: usize = 1024 * 1024; // 1 MB
+
+/// Largest ReadChunk frame a client may ask for
+const MAX_FRAME_SIZE: usize = 4 * 1024 * 1024; // 4 MB
+
+/// Bytes a ProbeBandwidth call sends when the caller does not say
+pub const DEFAULT_PROBE_SIZE: u64 = 8 * 1024 * 1024; // 8 MB
+
+/// Most bytes a single ProbeBandwidth call may ask for
+const MAX_PROBE_SIZE: u64 = 64 * 1024 * 1024; // 64 MB
+
+/// Most targets a single ReplicateChunk call may push to
+const MAX_REPLICATE_TARGETS: usize = 16;
+
+/// Request metadata carrying the shared cluster token between nodes
+pub const CLUSTER_TOKEN_METADATA: &str = "x-cyxcloud-cluster-token";
 
 /// Configuration for the gRPC server
 #[derive(Debug, Clone)]
@@ -39,6 +70,17 @@ pub struct GrpcServerConfig {
     pub tls_ca_cert: Option<PathBuf>,
     /// Require client certificates (mTLS)
     pub tls_require_client_cert: bool,
+    /// Admission limits for GetChunk, ReadChunk, StreamChunks, VerifyChunk
+    /// and ProbeBandwidth
+    pub read_limits: AdmissionConfig,
+    /// Admission limits for StoreChunk, WriteChunk and DeleteChunk
+    pub write_limits: AdmissionConfig,
+    /// Shared secret other nodes must present on WriteChunk and ReplicateChunk
+    pub cluster_token: Option<String>,
+    /// Key other nodes encrypt WriteChunk frames to (plaintext only when unset)
+    pub transfer_key: Option<Arc<TransferKey>>,
+    /// Encrypt every chunk pushed by ReplicateChunk, even if not asked to
+    pub encrypt_transfers: bool,
 }
 
 impl Default for GrpcServerConfig {
@@ -51,6 +93,11 @@ impl Default for GrpcServerConfig {
             tls_key: None,
             tls_ca_cert: None,
             tls_require_client_cert: false,
+            read_limits: AdmissionConfig::reads(),
+            write_limits: AdmissionConfig::writes(),
+            cluster_token: None,
+            transfer_key: None,
+            encrypt_transfers: false,
         }
     }
 }
@@ -91,20 +138,170 @@ impl GrpcServerConfig {
         self.tls_require_client_cert = require_client_cert;
         self
     }
+
+    /// Set the admission limits for read and write RPCs
+    pub fn with_limits(mut self, reads: AdmissionConfig, writes: AdmissionConfig) -> Self {
+        self.read_limits = reads;
+        self.write_limits = writes;
+        self
+    }
+
+    /// Require other nodes to present this token on node-to-node RPCs
+    pub fn with_cluster_token(mut self, token: impl Into<String>) -> Self {
+        self.cluster_token = Some(token.into());
+        self
+    }
+
+    /// Accept encrypted transfers sealed to this key
+    pub fn with_transfer_key(mut self, key: TransferKey) -> Self {
+        self.transfer_key = Some(Arc::new(key));
+        self
+    }
+
+    /// Encrypt every chunk this node pushes to its peers
+    pub fn with_transfer_encryption(mut self, encrypt: bool) -> Self {
+        self.encrypt_transfers = encrypt;
+        self
+    }
 }
 
-/// ChunkService implementation using RocksDB storage
+/// ChunkService implementation over a local storage backend
+///
+/// Nodes serve from RocksDB; tests can use the in-memory backend.
 pub struct ChunkServiceImpl {
     /// Local chunk storage
-    storage: Arc<RocksDbBackend>,
+    storage: Arc<dyn StorageBackendSync>,
     /// Node ID for logging
     node_id: String,
+    /// Whether new chunks are accepted (cleared during shutdown)
+    accepting_writes: Arc<AtomicBool>,
+    /// Admission gate for read RPCs
+    reads: Arc<AdmissionGate>,
+    /// Admission gate for write RPCs
+    writes: Arc<AdmissionGate>,
+    /// Token node-to-node RPCs must carry (unchecked when unset)
+    cluster_token: Option<String>,
+    /// Client for pushing chunks to other nodes
+    peers: Arc<ChunkClient>,
+    /// Key for opening encrypted WriteChunk streams
+    transfer_key: Option<Arc<TransferKey>>,
+    /// Encrypt pushed chunks whether or not the caller asked to
+    encrypt_transfers: bool,
+    /// Faults injected into RPCs and storage reads
+    #[cfg(feature = "fault-injection")]
+    faults: Arc<FaultInjector>,
 }
 
 impl ChunkServiceImpl {
     /// Create a new ChunkService with the given storage backend
-    pub fn new(storage: Arc<RocksDbBackend>, node_id: String) -> Self {
-        Self { storage, node_id }
+    pub fn new(storage: Arc<dyn StorageBackendSync>, node_id: String) -> Self {
+        #[cfg(feature = "fault-injection")]
+        let faults = Arc::new(FaultInjector::from_env());
+        #[cfg(feature = "fault-injection")]
+        let storage: Arc<dyn StorageBackendSync> =
+            Arc::new(FaultyBackend::new(storage, faults.clone()));
+
+        Self {
+            storage,
+            node_id,
+            accepting_writes: Arc::new(AtomicBool::new(true)),
+            reads: Arc::new(AdmissionGate::new("reads", AdmissionConfig::reads())),
+            writes: Arc::new(AdmissionGate::new("writes", AdmissionConfig::writes())),
+            cluster_token: None,
+            peers: Arc::new(ChunkClient::new()),
+            transfer_key: None,
+            encrypt_transfers: false,
+            #[cfg(feature = "fault-injection")]
+            faults,
+        }
+    }
+
+    /// Replace the default admission limits
+    ///
+    /// Reads and writes are gated separately so a repair storm of StoreChunk
+    /// calls cannot starve reads, and vice versa.
+    pub fn with_limits(mut self, reads: AdmissionConfig, writes: AdmissionConfig) -> Self {
+        self.reads = Arc::new(AdmissionGate::new("reads", reads));
+        self.writes = Arc::new(AdmissionGate::new("writes", writes));
+        self
+    }
+
+    /// Share a write gate with the caller
+    ///
+    /// Storing the gate to `false` makes the service reject new chunks while
+    /// reads and deletes keep working, e.g. while the node shuts down.
+    pub fn with_write_gate(mut self, gate: Arc<AtomicBool>) -> Self {
+        self.accepting_writes = gate;
+        self
+    }
+
+    /// Require and present a shared token on node-to-node RPCs
+    ///
+    /// WriteChunk, ReplicateChunk and DeleteChunk calls must carry the token,
+    /// and chunks this node pushes to its peers are sent with it.
+    pub fn with_cluster_token(mut self, token: Option<String>) -> Self {
+        self.peers = Arc::new(ChunkClient::with_config(ChunkClientConfig {
+            cluster_token: token.clone(),
+            ..Default::default()
+        }));
+        self.cluster_token = token;
+        self
+    }
+
+    /// Accept encrypted WriteChunk streams sealed to `key`
+    ///
+    /// Without a key GetTransferKey fails and peers only send in clear.
+    pub fn with_transfer_key(mut self, key: Option<Arc<TransferKey>>) -> Self {
+        self.transfer_key = key;
+        self
+    }
+
+    /// Encrypt chunks pushed by ReplicateChunk even when the caller did not
+    /// ask to
+    pub fn with_transfer_encryption(mut self, encrypt: bool) -> Self {
+        self.encrypt_transfers = encrypt;
+        self
+    }
+
+    /// Faults injected into this service, shared with its storage reads
+    #[cfg(feature = "fault-injection")]
+    pub fn faults(&self) -> Arc<FaultInjector> {
+        self.faults.clone()
+    }
+
+    /// Apply injected latency and failures to an RPC
+    #[cfg(feature = "fault-injection")]
+    async fn inject_faults(&self, rpc: &str) -> Result<(), Status> {
+        match self.faults.before_call(rpc).await {
+            Some(fault) => Err(fault.to_status(rpc)),
+            None => Ok(()),
+        }
+    }
+
+    #[cfg(not(feature = "fault-injection"))]
+    async fn inject_faults(&self, _rpc: &str) -> Result<(), Status> {
+        Ok(())
+    }
+
+    /// Check the cluster token of a node-to-node RPC
+    fn authorize_peer<T>(&self, request: &Request<T>) -> Result<(), Status> {
+        let Some(expected) = self.cluster_token.as_deref() else {
+            return Ok(());
+        };
+        let presented = request
+            .metadata()
+            .get(CLUSTER_TOKEN_METADATA)
+            .and_then(|v| v.to_str().ok());
+        match presented {
+            Some(token) if constant_time_eq(token.as_bytes(), expected.as_bytes()) => Ok(()),
+            _ => {
+                warn!(
+                    remote = ?request.remote_addr(),
+                    "Rejected peer RPC without a valid cluster token"
+                );
+                Err(Status::unauthenticated("Missing or invalid cluster token"))
+            }
+        }
     }
 
     /// Convert bytes to ChunkId
@@ -124,6 +321,93 @@ impl ChunkServiceImpl {
     fn chunk_id_to_bytes(id: ChunkId) -> Vec<u8> {
         id.as_bytes().to_vec()
     }
+
+    /// Verify content hash matches chunk ID (content-addressing)
+    fn verify_chunk_id(chunk_id: ChunkId, data: &[u8]) -> Result<(), Status> {
+        let computed_id = ChunkId::from_data(data);
+        if computed_id != chunk_id {
+            warn!(
+                expected = %chunk_id,
+                computed = %computed_id,
+                "Chunk ID mismatch - data doesn't match claimed ID"
+            );
+            return Err(Status::invalid_argument("Chunk ID doesn't match data hash"));
+        }
+        Ok(())
+    }
+
+    /// Check data against the wire checksum it was sent with
+    fn verify_wire(chunk_id: ChunkId, data: &[u8], checksum: u64) -> Result<(), Status> {
+        verify_wire_checksum(data, checksum).map_err(|e| {
+            warn!(chunk_id = %chunk_id, error = %e, "Chunk data corrupted in transit");
+            ErrorCode::TransportCorruption.to_status(e.to_string(), None)
+        })
+    }
+
+    /// Shard header for a chunk sent with `metadata`
+    ///
+    /// Only metadata naming the file the chunk belongs to yields a header;
+    /// chunks without it are stored bare.
+    fn shard_header(chunk_id: ChunkId, metadata: Option<&ChunkMetadata>) -> Option<ShardHeader> {
+        let metadata = metadata?;
+        Some(ShardHeader {
+            file_id: Uuid::from_slice(&metadata.parent_id).ok()?,
+            chunk_index: metadata.index,
+            shard_index: metadata.shard_index,
+            is_parity: metadata.is_parity,
+            original_size: metadata.original_size,
+            hash: *chunk_id.as_bytes(),
+        })
+    }
+
+    /// Metadata describing a stored shard header
+    fn header_metadata(chunk_id: ChunkId, header: &ShardHeader, size: u64) -> ChunkMetadata {
+        ChunkMetadata {
+            chunk_id: Self::chunk_id_to_bytes(chunk_id),
+            size,
+            index: header.chunk_index,
+            parent_id: header.file_id.as_bytes().to_vec(),
+            shard_index: header.shard_index,
+            is_parity: header.is_parity,
+            original_size: header.original_size,
+            ..Default::default()
+        }
+    }
+
+    /// Store a verified chunk, reporting storage failures in the response
+    fn put_chunk(
+        &self,
+        chunk_id: ChunkId,
+        header: Option<ShardHeader>,
+        data: Bytes,
+    ) -> Response<StoreChunkResponse> {
+        let data_len = data.len();
+        let stored = match header {
+            Some(ref header) => self.storage.put_shard(chunk_id, header, data),
+            None => self.storage.put(chunk_id, data),
+        };
+        match stored {
+            Ok(()) => {
+                info!(
+                    chunk_id = %chunk_id,
+                    size = data_len,
+                    shard_header = header.is_some(),
+                    "Chunk stored successfully"
+                );
+                Response::new(StoreChunkResponse {
+                    success: true,
+                    error: String::new(),
+                })
+            }
+            Err(e) => {
+                error!(chunk_id = %chunk_id, error = %e, "Failed to store chunk");
+                Response::new(StoreChunkResponse {
+                    success: false,
+                    error: e.to_string(),
+                })
+            }
+        }
+    }
 }
 
 #[tonic::async_trait]
@@ -134,45 +418,213 @@ impl ChunkService for ChunkServiceImpl {
         &self,
         request: Request<StoreChunkRequest>,
     ) -> Result<Response<StoreChunkResponse>, Status> {
+        self.inject_faults("StoreChunk").await?;
+
         let req = request.into_inner();
         let chunk_id = Self::bytes_to_chunk_id(&req.chunk_id)?;
         let data_len = req.data.len();
 
         debug!(chunk_id = %chunk_id, size = data_len, "Storing chunk");
 
+        if !self.accepting_writes.load(Ordering::Acquire) {
+            return Err(Status::unavailable(
+                "Node is shutting down, not accepting chunks",
+            ));
+        }
+
         // Validate chunk data
         if req.data.is_empty() {
             return Err(Status::invalid_argument("Chunk data cannot be empty"));
         }
 
-        // Verify content hash matches chunk ID (content-addressing)
-        let computed_id = ChunkId::from_data(&req.data);
-        if computed_id != chunk_id {
-            warn!(
-                expected = %chunk_id,
-                computed = %computed_id,
-                "Chunk ID mismatch - data doesn't match claimed ID"
-            );
-            return Err(Status::invalid_argument("Chunk ID doesn't match data hash"));
+        Self::verify_wire(chunk_id, &req.data, req.checksum)?;
+        Self::verify_chunk_id(chunk_id, &req.data)?;
+
+        let _permit = self.writes.admit("StoreChunk").await?;
+
+        let header = Self::shard_header(chunk_id, req.metadata.as_ref());
+        Ok(self.put_chunk(chunk_id, header, req.data))
+    }
+
+    /// Store a chunk received as a stream of frames
+    ///
+    /// Frames must arrive in order. The chunk is buffered until its last
+    /// byte is in, then checked against its ID and stored like `store_chunk`.
+    #[instrument(skip(self, request), fields(node_id = %self.node_id))]
+    async fn write_chunk(
+        &self,
+        request: Request<Streaming<WriteChunkFrame>>,
+    ) -> Result<Response<StoreChunkResponse>, Status> {
+        self.authorize_peer(&request)?;
+        self.inject_faults("WriteChunk").await?;
+
+        if !self.accepting_writes.load(Ordering::Acquire) {
+            return Err(Status::unavailable(
+                "Node is shutting down, not accepting chunks",
+            ));
         }
 
-        // Store the chunk
-        match self.storage.put(chunk_id, Bytes::from(req.data)) {
-            Ok(()) => {
-                info!(chunk_id = %chunk_id, size = data_len, "Chunk stored successfully");
-                Ok(Response::new(StoreChunkResponse {
-                    success: true,
-                    error: String::new(),
-                }))
+        let mut stream = request.into_inner();
+        let first = stream
+            .message()
+            .await?
+            .ok_or_else(|| Status::invalid_argument("WriteChunk stream is empty"))?;
+        let chunk_id = Self::bytes_to_chunk_id(&first.chunk_id)?;
+        let total_size = first.total_size as usize;
+        let header = Self::shard_header(chunk_id, first.metadata.as_ref());
+
+        if total_size == 0 {
+            return Err(Status::invalid_argument("Chunk data cannot be empty"));
+        }
+        if total_size > MAX_CHUNK_SIZE {
+            return Err(Status::invalid_argument(format!(
+                "Chunk of {} bytes exceeds the {} byte limit",
+                total_size, MAX_CHUNK_SIZE
+            )));
+        }
+
+        let cipher = if first.sender_key.is_empty() {
+            None
+        } else {
+            let key = self.transfer_key.as_ref().ok_or_else(|| {
+                Status::failed_precondition("Node does not accept encrypted transfers")
+            })?;
+            Some(
+                key.open(&first.sender_key)
+                    .map_err(|e| Status::invalid_argument(e.to_string()))?,
+            )
+        };
+
+        // Held while the frames come in, so slow senders count against it
+        let _permit = self.writes.admit("WriteChunk").await?;
+
+        debug!(
+            chunk_id = %chunk_id,
+            size = total_size,
+            encrypted = cipher.is_some(),
+            "Receiving chunk frames"
+        );
+
+        let mut data = BytesMut::with_capacity(total_size);
+        let mut frame = Some(first);
+        while let Some(current) = frame {
+            if current.offset != data.len() as u64 {
+                return Err(Status::invalid_argument(format!(
+                    "WriteChunk frame out of order: offset {}, expected {}",
+                    current.offset,
+                    data.len()
+                )));
             }
-            Err(e) => {
-                error!(chunk_id = %chunk_id, error = %e, "Failed to store chunk");
-                Ok(Response::new(StoreChunkResponse {
-                    success: false,
-                    error: e.to_string(),
-                }))
+            Self::verify_wire(chunk_id, &current.data, current.checksum)?;
+            let payload = match cipher {
+                Some(ref cipher) => cipher
+                    .decrypt(chunk_id, current.offset, &current.data)
+                    .map_err(|e| {
+                        warn!(chunk_id = %chunk_id, error = %e, "Encrypted frame rejected");
+                        Status::invalid_argument(e.to_string())
+                    })?,
+                None => current.data,
+            };
+            if data.len() + payload.len() > total_size {
+                return Err(Status::invalid_argument(format!(
+                    "WriteChunk frames exceed the announced {} bytes",
+                    total_size
+                )));
             }
+            data.extend_from_slice(&payload);
+            frame = stream.message().await?;
+        }
+
+        if data.len() != total_size {
+            return Err(Status::invalid_argument(format!(
+                "WriteChunk stream ended at {} of {} bytes",
+                data.len(),
+                total_size
+            )));
+        }
+
+        let data = data.freeze();
+        Self::verify_chunk_id(chunk_id, &data)?;
+        Ok(self.put_chunk(chunk_id, header, data))
+    }
+
+    /// Push a local chunk to other nodes
+    ///
+    /// Lets the rebalancer repair a chunk without relaying its data: this
+    /// node streams the chunk to every target at once and reports how far
+    /// each one got. When the caller asks for it (or the node is configured
+    /// to), the chunk is encrypted to each target's transfer key.
+    #[instrument(skip(self, request), fields(node_id = %self.node_id))]
+    async fn replicate_chunk(
+        &self,
+        request: Request<ReplicateChunkRequest>,
+    ) -> Result<Response<ReplicateChunkResponse>, Status> {
+        self.authorize_peer(&request)?;
+        self.inject_faults("ReplicateChunk").await?;
+
+        let req = request.into_inner();
+        let chunk_id = Self::bytes_to_chunk_id(&req.chunk_id)?;
+        if req.target_addrs.is_empty() {
+            return Err(Status::invalid_argument("No target nodes given"));
+        }
+        if req.target_addrs.len() > MAX_REPLICATE_TARGETS {
+            return Err(Status::invalid_argument(format!(
+                "At most {} target nodes per call",
+                MAX_REPLICATE_TARGETS
+            )));
         }
+
+        // Counted as a read here; each target gates its own write
+        let _permit = self.reads.admit("ReplicateChunk").await?;
+
+        let (header, data) = match self.storage.get_shard(chunk_id) {
+            Ok(Some(shard)) => shard,
+            Ok(None) => {
+                debug!(chunk_id = %chunk_id, "Chunk not found");
+                return Err(Status::not_found(format!("Chunk {} not found", chunk_id)));
+            }
+            Err(e) => {
+                error!(chunk_id = %chunk_id, error = %e, "Failed to read chunk");
+                return Err(Status::internal(format!("Storage error: {}", e)));
+            }
+        };
+        let metadata = header.map(|h| Self::header_metadata(chunk_id, &h, data.len() as u64));
+
+        let encrypt = req.encrypt || self.encrypt_transfers;
+        info!(
+            chunk_id = %chunk_id,
+            size = data.len(),
+            targets = req.target_addrs.len(),
+            encrypt,
+            "Replicating chunk to peers"
+        );
+
+        let report = fan_out_chunk_with(
+            &self.peers,
+            chunk_id,
+            data,
+            metadata,
+            &req.target_addrs,
+            encrypt,
+        )
+        .await
+        .map_err(|e| Status::internal(e.to_string()))?;
+
+        let results = report
+            .targets
+            .into_iter()
+            .map(|target| ReplicaResult {
+                success: target.succeeded(),
+                target_addr: target.addr,
+                error: target.error.unwrap_or_default(),
+                bytes_sent: target.bytes_sent,
+            })
+            .collect();
+
+        Ok(Response::new(ReplicateChunkResponse {
+            results,
+            encrypted: encrypt,
+        }))
     }
 
     /// Retrieve a chunk
@@ -181,26 +633,33 @@ impl ChunkService for ChunkServiceImpl {
         &self,
         request: Request<GetChunkRequest>,
     ) -> Result<Response<GetChunkResponse>, Status> {
+        self.inject_faults("GetChunk").await?;
+
         let req = request.into_inner();
         let chunk_id = Self::bytes_to_chunk_id(&req.chunk_id)?;
 
         debug!(chunk_id = %chunk_id, "Retrieving chunk");
 
-        match self.storage.get(chunk_id) {
-            Ok(Some(data)) => {
+        let _permit = self.reads.admit("GetChunk").await?;
+
+        match self.storage.get_shard(chunk_id) {
+            Ok(Some((header, data))) => {
                 debug!(chunk_id = %chunk_id, size = data.len(), "Chunk found");
                 Ok(Response::new(GetChunkResponse {
-                    data: data.to_vec(),
-                    metadata: None, // TODO: Store and retrieve metadata
+                    checksum: wire_checksum(&data),
+                    metadata: header
+                        .map(|h| Self::header_metadata(chunk_id, &h, data.len() as u64)),
+                    data,
                     found: true,
                 }))
             }
             Ok(None) => {
                 debug!(chunk_id = %chunk_id, "Chunk not found");
                 Ok(Response::new(GetChunkResponse {
-                    data: Vec::new(),
+                    data: Bytes::new(),
                     metadata: None,
                     found: false,
+                    checksum: 0,
                 }))
             }
             Err(e) => {
@@ -216,11 +675,17 @@ impl ChunkService for ChunkServiceImpl {
         &self,
         request: Request<DeleteChunkRequest>,
     ) -> Result<Response<DeleteChunkResponse>, Status> {
+        // Only the gateway and rebalancer remove chunks
+        self.authorize_peer(&request)?;
+        self.inject_faults("DeleteChunk").await?;
+
         let req = request.into_inner();
         let chunk_id = Self::bytes_to_chunk_id(&req.chunk_id)?;
 
         debug!(chunk_id = %chunk_id, "Deleting chunk");
 
+        let _permit = self.writes.admit("DeleteChunk").await?;
+
         match self.storage.delete(chunk_id) {
             Ok(deleted) => {
                 if deleted {
@@ -245,6 +710,8 @@ impl ChunkService for ChunkServiceImpl {
         &self,
         request: Request<StreamChunksRequest>,
     ) -> Result<Response<Self::StreamChunksStream>, Status> {
+        self.inject_faults("StreamChunks").await?;
+
         let req = request.into_inner();
         let chunk_ids: Vec<ChunkId> = req
             .chunk_ids
@@ -254,16 +721,21 @@ impl ChunkService for ChunkServiceImpl {
 
         info!(count = chunk_ids.len(), "Streaming chunks");
 
+        // Held by the streaming task so the slot stays taken until it finishes
+        let permit = self.reads.admit("StreamChunks").await?;
+
         let (tx, rx) = mpsc::channel(32);
         let storage = self.storage.clone();
 
         // Spawn task to stream chunks
         tokio::spawn(async move {
+            let _permit = permit;
             for (index, chunk_id) in chunk_ids.into_iter().enumerate() {
                 let result = match storage.get(chunk_id) {
                     Ok(Some(data)) => Ok(ChunkData {
                         chunk_id: Self::chunk_id_to_bytes(chunk_id),
-                        data: data.to_vec(),
+                        checksum: wire_checksum(&data),
+                        data,
                         index: index as u32,
                     }),
                     Ok(None) => {
@@ -288,17 +760,88 @@ impl ChunkService for ChunkServiceImpl {
         Ok(Response::new(ReceiverStream::new(rx)))
     }
 
+    type ReadChunkStream = ReceiverStream<Result<ChunkFrame, Status>>;
+
+    /// Read one chunk as a stream of frames
+    ///
+    /// Frames are slices of the stored buffer, so a chunk served from a
+    /// memory-mapped file goes to the wire without being copied onto the heap
+    /// first, and only a few frames are queued at a time.
+    #[instrument(skip(self, request), fields(node_id = %self.node_id))]
+    async fn read_chunk(
+        &self,
+        request: Request<ReadChunkRequest>,
+    ) -> Result<Response<Self::ReadChunkStream>, Status> {
+        self.inject_faults("ReadChunk").await?;
+
+        let req = request.into_inner();
+        let chunk_id = Self::bytes_to_chunk_id(&req.chunk_id)?;
+        let frame_size = match req.frame_size as usize {
+            0 => DEFAULT_FRAME_SIZE,
+            size => size.min(MAX_FRAME_SIZE),
+        };
+
+        // Held by the streaming task so the slot stays taken until it finishes
+        let permit = self.reads.admit("ReadChunk").await?;
+
+        let (header, data) = match self.storage.get_shard(chunk_id) {
+            Ok(Some(shard)) => shard,
+            Ok(None) => {
+                debug!(chunk_id = %chunk_id, "Chunk not found");
+                return Err(Status::not_found(format!("Chunk {} not found", chunk_id)));
+            }
+            Err(e) => {
+                error!(chunk_id = %chunk_id, error = %e, "Failed to read chunk");
+                return Err(Status::internal(format!("Storage error: {}", e)));
+            }
+        };
+        let mut metadata = header.map(|h| Self::header_metadata(chunk_id, &h, data.len() as u64));
+
+        debug!(chunk_id = %chunk_id, size = data.len(), frame_size, "Streaming chunk frames");
+
+        let (tx, rx) = mpsc::channel(4);
+        tokio::spawn(async move {
+            let _permit = permit;
+            let total_size = data.len() as u64;
+            let mut offset = 0;
+            loop {
+                let end = (offset + frame_size).min(data.len());
+                let frame = ChunkFrame {
+                    offset: offset as u64,
+                    checksum: wire_checksum(&data[offset..end]),
+                    data: data.slice(offset..end),
+                    total_size,
+                    metadata: metadata.take(),
+                };
+                if tx.send(Ok(frame)).await.is_err() {
+                    debug!("Client disconnected during chunk read");
+                    break;
+                }
+                offset = end;
+                if offset >= data.len() {
+                    break;
+                }
+            }
+        });
+
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+
     /// Verify chunk integrity
     #[instrument(skip(self, request), fields(node_id = %self.node_id))]
     async fn verify_chunk(
         &self,
         request: Request<VerifyChunkRequest>,
     ) -> Result<Response<VerifyChunkResponse>, Status> {
+        self.inject_faults("VerifyChunk").await?;
+
         let req = request.into_inner();
         let chunk_id = Self::bytes_to_chunk_id(&req.chunk_id)?;
 
         debug!(chunk_id = %chunk_id, "Verifying chunk");
 
+        let _permit = self.reads.admit("VerifyChunk").await?;
+
         match self.storage.get(chunk_id) {
             Ok(Some(data)) => {
                 // Verify content hash
@@ -316,11 +859,13 @@ impl ChunkService for ChunkServiceImpl {
                 Ok(Response::new(VerifyChunkResponse {
                     valid,
                     size: data.len() as u64,
+                    content_hash: computed_id.as_bytes().to_vec(),
                 }))
             }
             Ok(None) => Ok(Response::new(VerifyChunkResponse {
                 valid: false,
                 size: 0,
+                content_hash: Vec::new(),
             })),
             Err(e) => {
                 error!(chunk_id = %chunk_id, error = %e, "Failed to verify chunk");
@@ -328,6 +873,79 @@ impl ChunkService for ChunkServiceImpl {
             }
         }
     }
+
+    type ProbeBandwidthStream = ReceiverStream<Result<ProbeBandwidthFrame, Status>>;
+
+    /// Stream random data for a bandwidth measurement
+    ///
+    /// The caller times the stream; the node only sends. One random frame is
+    /// sent over and over, so a probe costs no disk reads and little CPU.
+    /// Probes take a read slot, as they compete with reads for the uplink.
+    #[instrument(skip(self, request), fields(node_id = %self.node_id))]
+    async fn probe_bandwidth(
+        &self,
+        request: Request<ProbeBandwidthRequest>,
+    ) -> Result<Response<Self::ProbeBandwidthStream>, Status> {
+        self.inject_faults("ProbeBandwidth").await?;
+
+        let req = request.into_inner();
+        let total_size = match req.size {
+            0 => DEFAULT_PROBE_SIZE,
+            size => size.min(MAX_PROBE_SIZE),
+        };
+        let frame_size = match req.frame_size as usize {
+            0 => DEFAULT_FRAME_SIZE,
+            size => size.min(MAX_FRAME_SIZE),
+        };
+
+        let permit = self.reads.admit("ProbeBandwidth").await?;
+
+        let mut frame = vec![0u8; frame_size.min(total_size as usize)];
+        rand::thread_rng().fill_bytes(&mut frame);
+        let frame = Bytes::from(frame);
+
+        debug!(size = total_size, frame_size, "Streaming bandwidth probe");
+
+        let (tx, rx) = mpsc::channel(4);
+        tokio::spawn(async move {
+            let _permit = permit;
+            let mut remaining = total_size;
+            while remaining > 0 {
+                let len = remaining.min(frame.len() as u64) as usize;
+                let probe = ProbeBandwidthFrame {
+                    data: frame.slice(..len),
+                    total_size,
+                };
+                if tx.send(Ok(probe)).await.is_err() {
+                    debug!("Client disconnected during bandwidth probe");
+                    break;
+                }
+                remaining -= len as u64;
+            }
+        });
+
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+
+    /// Hand out the public key encrypted transfers are sealed to
+    async fn get_transfer_key(
+        &self,
+        _request: Request<GetTransferKeyRequest>,
+    ) -> Result<Response<GetTransferKeyResponse>, Status> {
+        match self.transfer_key {
+            Some(ref key) => Ok(Response::new(GetTransferKeyResponse {
+                public_key: key.public_key().to_vec(),
+            })),
+            None => Err(Status::failed_precondition(
+                "Node does not accept encrypted transfers",
+            )),
+        }
+    }
+}
+
+/// Compare two secrets without leaking where they differ
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
 }
 
 /// Start the gRPC server
@@ -338,7 +956,11 @@ pub async fn start_server(
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     use cyxcloud_protocol::chunk::chunk_service_server::ChunkServiceServer;
 
-    let service = ChunkServiceImpl::new(storage, node_id.clone());
+    let service = ChunkServiceImpl::new(storage, node_id.clone())
+        .with_limits(config.read_limits.clone(), config.write_limits.clone())
+        .with_cluster_token(config.cluster_token.clone())
+        .with_transfer_key(config.transfer_key.clone())
+        .with_transfer_encryption(config.encrypt_transfers);
     let server = ChunkServiceServer::new(service)
         .max_decoding_message_size(config.max_message_size)
         .max_encoding_message_size(config.max_message_size);
@@ -408,8 +1030,9 @@ mod tests {
         // Store chunk
         let store_request = Request::new(StoreChunkRequest {
             chunk_id: chunk_id.as_bytes().to_vec(),
-            data: data.to_vec(),
+            data: Bytes::from_static(data),
             metadata: None,
+            checksum: wire_checksum(data),
         });
 
         let store_response = service.store_chunk(store_request).await.unwrap();
@@ -423,7 +1046,136 @@ mod tests {
         let get_response = service.get_chunk(get_request).await.unwrap();
         let inner = get_response.into_inner();
         assert!(inner.found);
-        assert_eq!(inner.data, data.to_vec());
+        assert_eq!(inner.data, &data[..]);
+        assert_eq!(inner.checksum, wire_checksum(data));
+    }
+
+    #[tokio::test]
+    async fn test_store_keeps_shard_header() {
+        let (storage, _dir) = create_test_storage();
+        let service = ChunkServiceImpl::new(storage.clone(), "test-node".to_string());
+
+        let data = b"parity shard";
+        let chunk_id = ChunkId::from_data(data);
+        let file_id = Uuid::new_v4();
+        let metadata = ChunkMetadata {
+            chunk_id: chunk_id.as_bytes().to_vec(),
+            size: data.len() as u64,
+            index: 3,
+            parent_id: file_id.as_bytes().to_vec(),
+            shard_index: 13,
+            is_parity: true,
+            original_size: 4096,
+            ..Default::default()
+        };
+        let store_request = Request::new(StoreChunkRequest {
+            chunk_id: chunk_id.as_bytes().to_vec(),
+            data: Bytes::from_static(data),
+            metadata: Some(metadata.clone()),
+            checksum: wire_checksum(data),
+        });
+        assert!(
+            service
+                .store_chunk(store_request)
+                .await
+                .unwrap()
+                .into_inner()
+                .success
+        );
+
+        let (header, stored) = storage.get_shard(chunk_id).unwrap().unwrap();
+        assert_eq!(stored, &data[..]);
+        let header = header.unwrap();
+        assert_eq!(header.file_id, file_id);
+        assert_eq!((header.chunk_index, header.shard_index), (3, 13));
+        assert!(header.is_parity);
+        assert_eq!(header.original_size, 4096);
+
+        let get_request = Request::new(GetChunkRequest {
+            chunk_id: chunk_id.as_bytes().to_vec(),
+        });
+        let inner = service.get_chunk(get_request).await.unwrap().into_inner();
+        assert_eq!(inner.data, &data[..]);
+        assert_eq!(inner.metadata, Some(metadata));
+    }
+
+    #[tokio::test]
+    async fn test_store_rejects_transport_corruption() {
+        let (storage, _dir) = create_test_storage();
+        let service = ChunkServiceImpl::new(storage, "test-node".to_string());
+
+        let data = b"sent intact";
+        let chunk_id = ChunkId::from_data(data);
+        let request = Request::new(StoreChunkRequest {
+            chunk_id: chunk_id.as_bytes().to_vec(),
+            data: Bytes::from_static(b"sent intacT"),
+            metadata: None,
+            checksum: wire_checksum(data),
+        });
+
+        let status = service.store_chunk(request).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::DataLoss);
+        assert_eq!(
+            ErrorCode::from_status(&status),
+            ErrorCode::TransportCorruption
+        );
+        assert!(!service.storage.exists(chunk_id).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_store_rejected_when_gate_closed() {
+        let (storage, _dir) = create_test_storage();
+        let gate = Arc::new(AtomicBool::new(true));
+        let service =
+            ChunkServiceImpl::new(storage, "test-node".to_string()).with_write_gate(gate.clone());
+
+        gate.store(false, Ordering::Release);
+
+        let data = b"late write";
+        let request = Request::new(StoreChunkRequest {
+            chunk_id: ChunkId::from_data(data).as_bytes().to_vec(),
+            data: Bytes::from_static(data),
+            metadata: None,
+            checksum: wire_checksum(data),
+        });
+
+        let status = service.store_chunk(request).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::Unavailable);
+    }
+
+    #[tokio::test]
+    async fn test_store_shed_when_writes_saturated() {
+        let (storage, _dir) = create_test_storage();
+        let writes = AdmissionConfig {
+            max_concurrent: 1,
+            max_queued: 0,
+            ..AdmissionConfig::writes()
+        };
+        let service = ChunkServiceImpl::new(storage, "test-node".to_string())
+            .with_limits(AdmissionConfig::reads(), writes);
+
+        // Occupy the only write slot
+        let _held = service.writes.admit("StoreChunk").await.unwrap();
+
+        let data = b"repair storm";
+        let chunk_id = ChunkId::from_data(data);
+        let request = Request::new(StoreChunkRequest {
+            chunk_id: chunk_id.as_bytes().to_vec(),
+            data: Bytes::from_static(data),
+            metadata: None,
+            checksum: wire_checksum(data),
+        });
+
+        let status = service.store_chunk(request).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::ResourceExhausted);
+        assert!(crate::admission::retry_after_hint(&status).is_some());
+
+        // Reads have their own gate and are unaffected
+        let get_request = Request::new(GetChunkRequest {
+            chunk_id: chunk_id.as_bytes().to_vec(),
+        });
+        let get_response = service.get_chunk(get_request).await.unwrap();
+        assert!(!get_response.into_inner().found);
     }
 
     #[tokio::test]
@@ -437,8 +1189,9 @@ mod tests {
 
         let store_request = Request::new(StoreChunkRequest {
             chunk_id: wrong_id.as_bytes().to_vec(),
-            data: data.to_vec(),
+            data: Bytes::from_static(data),
             metadata: None,
+            checksum: wire_checksum(data),
         });
 
         let result = service.store_chunk(store_request).await;
@@ -460,8 +1213,9 @@ mod tests {
         // Store chunk first
         let store_request = Request::new(StoreChunkRequest {
             chunk_id: chunk_id.as_bytes().to_vec(),
-            data: data.to_vec(),
+            data: Bytes::from_static(data),
             metadata: None,
+            checksum: wire_checksum(data),
         });
         service.store_chunk(store_request).await.unwrap();
 
@@ -474,6 +1228,175 @@ mod tests {
         let inner = verify_response.into_inner();
         assert!(inner.valid);
         assert_eq!(inner.size, data.len() as u64);
+        assert_eq!(inner.content_hash, chunk_id.as_bytes().to_vec());
+    }
+
+    #[tokio::test]
+    async fn test_read_chunk_frames() {
+        use tokio_stream::StreamExt;
+
+        let temp_dir = TempDir::new().unwrap();
+        let config = StorageConfig::new(temp_dir.path()).with_file_chunk_threshold(1024 * 1024);
+        let storage = Arc::new(RocksDbBackend::open(config).unwrap());
+        let service = ChunkServiceImpl::new(storage.clone(), "test-node".to_string());
+
+        let data: Bytes = (0..2 * 1024 * 1024 + 17).map(|i| i as u8).collect();
+        let chunk_id = ChunkId::from_data(&data);
+        storage.put(chunk_id, data.clone()).unwrap();
+
+        let request = Request::new(ReadChunkRequest {
+            chunk_id: chunk_id.as_bytes().to_vec(),
+            frame_size: 512 * 1024,
+        });
+        let mut stream = service.read_chunk(request).await.unwrap().into_inner();
+
+        let mut assembled = Vec::new();
+        let mut frames = 0;
+        while let Some(frame) = stream.next().await {
+            let frame = frame.unwrap();
+            assert_eq!(frame.offset, assembled.len() as u64);
+            assert_eq!(frame.total_size, data.len() as u64);
+            assert!(verify_wire_checksum(&frame.data, frame.checksum).is_ok());
+            assembled.extend_from_slice(&frame.data);
+            frames += 1;
+        }
+        assert_eq!(frames, 5);
+        assert_eq!(assembled, data);
+
+        let missing = Request::new(ReadChunkRequest {
+            chunk_id: ChunkId::from_data(b"missing").as_bytes().to_vec(),
+            frame_size: 0,
+        });
+        let status = service.read_chunk(missing).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_probe_bandwidth() {
+        use tokio_stream::StreamExt;
+
+        let (storage, _temp) = create_test_storage();
+        let service = ChunkServiceImpl::new(storage, "test-node".to_string());
+
+        let request = Request::new(ProbeBandwidthRequest {
+            size: 1024 * 1024 + 100,
+            frame_size: 256 * 1024,
+        });
+        let mut stream = service.probe_bandwidth(request).await.unwrap().into_inner();
+        let mut received = 0;
+        let mut frames = 0;
+        while let Some(frame) = stream.next().await {
+            let frame = frame.unwrap();
+            assert_eq!(frame.total_size, 1024 * 1024 + 100);
+            received += frame.data.len();
+            frames += 1;
+        }
+        assert_eq!(received, 1024 * 1024 + 100);
+        assert_eq!(frames, 5);
+
+        // Oversized probes are capped
+        let request = Request::new(ProbeBandwidthRequest {
+            size: u64::MAX,
+            frame_size: 0,
+        });
+        let mut stream = service.probe_bandwidth(request).await.unwrap().into_inner();
+        let first = stream.next().await.unwrap().unwrap();
+        assert_eq!(first.total_size, MAX_PROBE_SIZE);
+        assert_eq!(first.data.len(), DEFAULT_FRAME_SIZE);
+    }
+
+    #[tokio::test]
+    async fn test_get_transfer_key() {
+        let (storage, _dir) = create_test_storage();
+        let service = ChunkServiceImpl::new(storage.clone(), "test-node".to_string());
+        let status = service
+            .get_transfer_key(Request::new(GetTransferKeyRequest {}))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::FailedPrecondition);
+
+        let key = Arc::new(TransferKey::generate());
+        let service = ChunkServiceImpl::new(storage, "test-node".to_string())
+            .with_transfer_key(Some(key.clone()));
+        let response = service
+            .get_transfer_key(Request::new(GetTransferKeyRequest {}))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(response.public_key, key.public_key().to_vec());
+    }
+
+    #[tokio::test]
+    async fn test_replicate_chunk_requires_cluster_token() {
+        let (storage, _dir) = create_test_storage();
+        let service = ChunkServiceImpl::new(storage, "test-node".to_string())
+            .with_cluster_token(Some("s3cret".to_string()));
+
+        let replicate = |token: Option<&str>| {
+            let mut request = Request::new(ReplicateChunkRequest {
+                chunk_id: ChunkId::from_data(b"missing").as_bytes().to_vec(),
+                target_addrs: vec!["127.0.0.1:1".to_string()],
+                encrypt: false,
+            });
+            if let Some(token) = token {
+                request
+                    .metadata_mut()
+                    .insert(CLUSTER_TOKEN_METADATA, token.parse().unwrap());
+            }
+            request
+        };
+
+        for token in [None, Some("wrong")] {
+            let status = service.replicate_chunk(replicate(token)).await.unwrap_err();
+            assert_eq!(status.code(), tonic::Code::Unauthenticated);
+        }
+
+        // With the right token the request gets as far as the missing chunk
+        let status = service
+            .replicate_chunk(replicate(Some("s3cret")))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_delete_chunk_requires_cluster_token() {
+        let (storage, _dir) = create_test_storage();
+        let data = Bytes::from("chunk to delete");
+        let chunk_id = ChunkId::from_data(&data);
+        storage.put(chunk_id, data).unwrap();
+        let service = ChunkServiceImpl::new(storage.clone(), "test-node".to_string())
+            .with_cluster_token(Some("s3cret".to_string()));
+
+        let delete = |token: Option<&str>| {
+            let mut request = Request::new(DeleteChunkRequest {
+                chunk_id: chunk_id.as_bytes().to_vec(),
+            });
+            if let Some(token) = token {
+                request
+                    .metadata_mut()
+                    .insert(CLUSTER_TOKEN_METADATA, token.parse().unwrap());
+            }
+            request
+        };
+
+        for token in [None, Some("wrong")] {
+            let status = service.delete_chunk(delete(token)).await.unwrap_err();
+            assert_eq!(status.code(), tonic::Code::Unauthenticated);
+        }
+        assert!(storage.exists(chunk_id).unwrap());
+
+        let response = service.delete_chunk(delete(Some("s3cret"))).await.unwrap();
+        assert!(response.into_inner().deleted);
+        assert!(!storage.exists(chunk_id).unwrap());
+    }
+
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq(b"token", b"token"));
+        assert!(!constant_time_eq(b"token", b"tokem"));
+        assert!(!constant_time_eq(b"token", b"token2"));
+        assert!(constant_time_eq(b"", b""));
     }
 
     #[tokio::test]
@@ -487,8 +1410,9 @@ mod tests {
         // Store chunk
         let store_request = Request::new(StoreChunkRequest {
             chunk_id: chunk_id.as_bytes().to_vec(),
-            data: data.to_vec(),
+            data: Bytes::from_static(data),
             metadata: None,
+            checksum: wire_checksum(data),
         });
         service.store_chunk(store_request).await.unwrap();
 
@@ -2,7 +2,7 @@
 //!
 //! Provides hybrid networking for CyxCloud storage nodes:
 //! - **gRPC (tonic)**: Direct node-to-node data transfer for chunks
-//! - **libp2p**: Peer discovery via Kademlia DHT
+//! - **libp2p**: Peer discovery via Kademlia DHT, node status via gossipsub
 //!
 //! # Architecture
 //!
@@ -54,21 +54,32 @@
 #![allow(clippy::derivable_impls)]
 #![allow(clippy::should_implement_trait)]
 
+pub mod admission;
 pub mod behavior;
 pub mod discovery;
+#[cfg(feature = "fault-injection")]
+pub mod fault;
 pub mod grpc_client;
 pub mod grpc_server;
+pub mod peer_registry;
 pub mod protocol;
+pub mod transfer_crypto;
 
 // Re-exports
+pub use admission::{AdmissionConfig, AdmissionGate};
 pub use behavior::{BehaviourConfig, CyxCloudBehaviour, CyxCloudEvent};
-pub use discovery::{DiscoveryConfig, DiscoveryEvent, DiscoveryService, PeerInfo};
-pub use grpc_client::{ChunkClient, ChunkClientConfig};
+pub use discovery::{ClusterView, DiscoveryConfig, DiscoveryEvent, DiscoveryService, PeerInfo};
+#[cfg(feature = "fault-injection")]
+pub use fault::{FaultConfig, FaultInjector};
+pub use grpc_client::{ChunkClient, ChunkClientConfig, FanOutReport, TargetOutcome};
 pub use grpc_server::{ChunkServiceImpl, GrpcServerConfig};
+pub use libp2p::{Multiaddr, PeerId};
+pub use peer_registry::{PeerRegistry, PeerRegistryConfig, PeerRegistryStats};
 pub use protocol::{
     ChunkLocationAnnouncement, NodeAnnouncement, NodeCapacity, NodeLocation, NodeStatus,
-    PROTOCOL_VERSION,
+    NODE_STATUS_TOPIC, PROTOCOL_VERSION,
 };
+pub use transfer_crypto::{TransferCipher, TransferKey};
 
 use bytes::Bytes;
 use cyxcloud_core::chunk::ChunkId;
@@ -139,7 +150,10 @@ pub struct NetworkManager {
 impl NetworkManager {
     /// Create a new NetworkManager
     pub fn new(config: NetworkConfig, storage: Arc<RocksDbBackend>) -> Self {
-        let grpc_client = Arc::new(ChunkClient::with_config(ChunkClientConfig::default()));
+        let grpc_client = Arc::new(ChunkClient::with_config(ChunkClientConfig {
+            cluster_token: config.grpc.cluster_token.clone(),
+            ..Default::default()
+        }));
 
         info!(
             node_id = %config.node_id,
@@ -220,6 +234,30 @@ impl NetworkManager {
         grpc_client::store_to_multiple_nodes(&self.grpc_client, chunk_id, data, target_nodes).await
     }
 
+    /// Ask a remote node to push one of its chunks straight to others
+    ///
+    /// Returns `None` if the source node does not support direct pushes.
+    pub async fn push_chunk(
+        &self,
+        chunk_id: ChunkId,
+        source_node: &str,
+        target_nodes: &[String],
+    ) -> Result<Option<FanOutReport>> {
+        self.grpc_client
+            .push_chunk(source_node, chunk_id, target_nodes)
+            .await
+    }
+
+    /// Copy a chunk from one remote node to others, streaming it through
+    pub async fn replicate_chunk(
+        &self,
+        chunk_id: ChunkId,
+        source_node: &str,
+        target_nodes: &[String],
+    ) -> Result<FanOutReport> {
+        grpc_client::replicate_chunk(&self.grpc_client, source_node, chunk_id, target_nodes).await
+    }
+
     /// Get a chunk from any of the provided nodes
     pub async fn get_chunk_from_any(
         &self,
@@ -0,0 +1,458 @@
+//! Persistent peer registry
+//!
+//! Remembers every peer the discovery service has heard of in a sled store,
+//! so a restarted node can redial its old neighbours instead of depending on
+//! the configured bootstrap peers alone. Dials to peers that keep failing are
+//! spaced out with exponential backoff and jitter, and a peer that fails too
+//! many times in a row is banned for a while.
+
+use crate::discovery::PeerInfo;
+use cyxcloud_core::error::{CyxCloudError, Result};
+use libp2p::{Multiaddr, PeerId};
+use parking_lot::RwLock;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tracing::{debug, info, warn};
+
+/// Configuration for the peer registry
+#[derive(Debug, Clone)]
+pub struct PeerRegistryConfig {
+    /// Directory of the on-disk peer store (`None` keeps peers in memory only)
+    pub path: Option<PathBuf>,
+    /// Delay before the first redial of a failing peer
+    pub backoff_base: Duration,
+    /// Longest delay between redials
+    pub backoff_max: Duration,
+    /// Consecutive dial failures before a peer is banned
+    pub max_dial_failures: u32,
+    /// How long a banned peer is left alone
+    pub ban_duration: Duration,
+    /// Stored peers not seen for this long are forgotten
+    pub retention: Duration,
+}
+
+impl Default for PeerRegistryConfig {
+    fn default() -> Self {
+        Self {
+            path: None,
+            backoff_base: Duration::from_secs(2),
+            backoff_max: Duration::from_secs(600),
+            max_dial_failures: 10,
+            ban_duration: Duration::from_secs(3600),
+            retention: Duration::from_secs(7 * 24 * 3600), // 7 days
+        }
+    }
+}
+
+impl PeerRegistryConfig {
+    /// Keep the peer store in the given directory
+    pub fn with_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+}
+
+/// Peer counts reported as node metrics
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PeerRegistryStats {
+    /// Peers in the store
+    pub known: usize,
+    /// Peers with an open connection
+    pub connected: usize,
+    /// Peers currently banned
+    pub banned: usize,
+}
+
+/// Peer as kept in the store
+#[derive(Debug, Serialize, Deserialize)]
+struct StoredPeer {
+    addresses: Vec<String>,
+    grpc_port: u16,
+    /// Unix time the peer was last heard from
+    last_seen: u64,
+    agent_version: Option<String>,
+}
+
+impl StoredPeer {
+    fn from_info(info: &PeerInfo) -> Self {
+        let age = info.last_seen.elapsed().as_secs();
+        Self {
+            addresses: info.addresses.iter().map(|a| a.to_string()).collect(),
+            grpc_port: info.grpc_port,
+            last_seen: unix_now().saturating_sub(age),
+            agent_version: info.agent_version.clone(),
+        }
+    }
+
+    fn into_info(self, peer_id: PeerId) -> PeerInfo {
+        let age = Duration::from_secs(unix_now().saturating_sub(self.last_seen));
+        let mut info = PeerInfo::new(peer_id);
+        info.addresses = self
+            .addresses
+            .iter()
+            .filter_map(|a| a.parse().ok())
+            .collect();
+        info.grpc_port = self.grpc_port;
+        info.last_seen = Instant::now().checked_sub(age).unwrap_or(info.last_seen);
+        info.agent_version = self.agent_version;
+        info
+    }
+}
+
+/// Redial state of one peer
+#[derive(Debug, Default)]
+struct DialState {
+    connected: bool,
+    /// Consecutive failed dials
+    failures: u32,
+    /// Earliest time the peer may be dialed again
+    retry_at: Option<Instant>,
+    banned_until: Option<Instant>,
+}
+
+impl DialState {
+    fn is_banned(&self, now: Instant) -> bool {
+        self.banned_until.is_some_and(|until| until > now)
+    }
+}
+
+/// Known peers with their redial state
+pub struct PeerRegistry {
+    db: sled::Db,
+    dial: RwLock<HashMap<PeerId, DialState>>,
+    config: PeerRegistryConfig,
+}
+
+impl PeerRegistry {
+    /// Open the registry, creating the store if needed
+    pub fn open(config: PeerRegistryConfig) -> Result<Self> {
+        let db = match config.path {
+            Some(ref path) => {
+                info!(path = ?path, "Opening peer store");
+                sled::open(path)
+            }
+            None => sled::Config::new().temporary(true).open(),
+        }
+        .map_err(|e| CyxCloudError::Storage(format!("Failed to open peer store: {}", e)))?;
+
+        let registry = Self {
+            db,
+            dial: RwLock::new(HashMap::new()),
+            config,
+        };
+        let forgotten = registry.prune()?;
+        if forgotten > 0 {
+            debug!(count = forgotten, "Forgot peers past retention");
+        }
+        Ok(registry)
+    }
+
+    /// Open an in-memory registry
+    pub fn temporary(config: PeerRegistryConfig) -> Result<Self> {
+        Self::open(PeerRegistryConfig {
+            path: None,
+            ..config
+        })
+    }
+
+    /// Record a peer that was discovered or identified
+    pub fn record_seen(&self, info: &PeerInfo) -> Result<()> {
+        let encoded = bincode::serialize(&StoredPeer::from_info(info))?;
+        self.db
+            .insert(info.peer_id.to_bytes(), encoded)
+            .map_err(|e| CyxCloudError::Storage(e.to_string()))?;
+        Ok(())
+    }
+
+    /// All stored peers, most recently seen first
+    pub fn known_peers(&self) -> Vec<PeerInfo> {
+        let mut peers: Vec<PeerInfo> = self
+            .db
+            .iter()
+            .filter_map(|entry| {
+                let (key, value) = entry.ok()?;
+                let peer_id = PeerId::from_bytes(&key).ok()?;
+                let stored: StoredPeer = bincode::deserialize(&value).ok()?;
+                Some(stored.into_info(peer_id))
+            })
+            .collect();
+        peers.sort_by_key(|p| p.last_seen.elapsed());
+        peers
+    }
+
+    /// Stored peers that are due for a dial, with their addresses
+    ///
+    /// Each returned peer is held back for one backoff period, so a dial that
+    /// never reports back is retried later rather than on every call.
+    pub fn dial_candidates(&self) -> Vec<(PeerId, Vec<Multiaddr>)> {
+        let now = Instant::now();
+        let known = self.known_peers();
+        let mut dial = self.dial.write();
+
+        let mut due = Vec::new();
+        for peer in known {
+            if peer.addresses.is_empty() {
+                continue;
+            }
+            let state = dial.entry(peer.peer_id).or_default();
+            if state.connected || state.is_banned(now) || state.retry_at.is_some_and(|at| at > now)
+            {
+                continue;
+            }
+            state.retry_at = Some(now + self.backoff(state.failures));
+            due.push((peer.peer_id, peer.addresses));
+        }
+        due
+    }
+
+    /// Record an established connection, clearing the peer's failures
+    pub fn record_connected(&self, peer_id: PeerId) {
+        let mut dial = self.dial.write();
+        let state = dial.entry(peer_id).or_default();
+        state.connected = true;
+        state.failures = 0;
+        state.retry_at = None;
+        state.banned_until = None;
+    }
+
+    /// Record that the last connection to a peer closed
+    pub fn record_disconnected(&self, peer_id: PeerId) {
+        if let Some(state) = self.dial.write().get_mut(&peer_id) {
+            state.connected = false;
+        }
+    }
+
+    /// Record a failed dial and return how long to wait before the next one
+    ///
+    /// Bans the peer once it has failed `max_dial_failures` times in a row.
+    pub fn record_dial_failure(&self, peer_id: PeerId) -> Duration {
+        let now = Instant::now();
+        let mut dial = self.dial.write();
+        let state = dial.entry(peer_id).or_default();
+        state.failures += 1;
+
+        if state.failures >= self.config.max_dial_failures {
+            warn!(
+                peer = %peer_id,
+                failures = state.failures,
+                "Banning peer after repeated dial failures"
+            );
+            state.failures = 0;
+            state.banned_until = Some(now + self.config.ban_duration);
+            state.retry_at = state.banned_until;
+            return self.config.ban_duration;
+        }
+
+        let delay = self.backoff(state.failures);
+        state.retry_at = Some(now + delay);
+        debug!(peer = %peer_id, failures = state.failures, delay = ?delay, "Dial failed");
+        delay
+    }
+
+    /// Ban a peer for the given duration
+    pub fn ban(&self, peer_id: PeerId, duration: Duration) {
+        let until = Instant::now() + duration;
+        let mut dial = self.dial.write();
+        let state = dial.entry(peer_id).or_default();
+        state.banned_until = Some(until);
+        state.retry_at = Some(until);
+    }
+
+    /// Check whether a peer is banned
+    pub fn is_banned(&self, peer_id: &PeerId) -> bool {
+        let now = Instant::now();
+        self.dial
+            .read()
+            .get(peer_id)
+            .is_some_and(|state| state.is_banned(now))
+    }
+
+    /// Forget stored peers not seen within the retention period
+    pub fn prune(&self) -> Result<usize> {
+        let cutoff = unix_now().saturating_sub(self.config.retention.as_secs());
+        let dial = self.dial.read();
+        let mut removed = 0;
+
+        for entry in self.db.iter() {
+            let (key, value) = entry.map_err(|e| CyxCloudError::Storage(e.to_string()))?;
+            let connected = PeerId::from_bytes(&key)
+                .ok()
+                .and_then(|id| dial.get(&id))
+                .is_some_and(|state| state.connected);
+            let expired = match bincode::deserialize::<StoredPeer>(&value) {
+                Ok(stored) => stored.last_seen < cutoff,
+                Err(_) => true, // unreadable entries are dropped
+            };
+            if expired && !connected {
+                self.db
+                    .remove(&key)
+                    .map_err(|e| CyxCloudError::Storage(e.to_string()))?;
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+
+    /// Write pending changes to disk
+    pub fn flush(&self) -> Result<()> {
+        self.db
+            .flush()
+            .map_err(|e| CyxCloudError::Storage(e.to_string()))?;
+        Ok(())
+    }
+
+    /// Current peer counts
+    pub fn stats(&self) -> PeerRegistryStats {
+        let now = Instant::now();
+        let dial = self.dial.read();
+        PeerRegistryStats {
+            known: self.db.len(),
+            connected: dial.values().filter(|s| s.connected).count(),
+            banned: dial.values().filter(|s| s.is_banned(now)).count(),
+        }
+    }
+
+    /// Backoff after `failures` consecutive failures, with jitter
+    ///
+    /// Doubles from `backoff_base` up to `backoff_max`, then picks a delay
+    /// between half and all of that so peers that failed together do not
+    /// retry together.
+    fn backoff(&self, failures: u32) -> Duration {
+        let exp = failures.saturating_sub(1).min(20);
+        let delay = self
+            .config
+            .backoff_base
+            .saturating_mul(1 << exp)
+            .min(self.config.backoff_max);
+        let half = delay / 2;
+        half + half.mul_f64(rand::thread_rng().gen::<f64>())
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libp2p::identity::Keypair;
+    use tempfile::TempDir;
+
+    fn random_peer() -> PeerId {
+        Keypair::generate_ed25519().public().to_peer_id()
+    }
+
+    fn peer_info(peer_id: PeerId) -> PeerInfo {
+        let mut info = PeerInfo::new(peer_id);
+        info.addresses = vec!["/ip4/10.0.0.7/tcp/4001".parse().unwrap()];
+        info.grpc_port = 50061;
+        info
+    }
+
+    #[test]
+    fn test_peers_survive_restart() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = PeerRegistryConfig::default().with_path(temp_dir.path().join("peers"));
+        let peer_id = random_peer();
+
+        {
+            let registry = PeerRegistry::open(config.clone()).unwrap();
+            registry.record_seen(&peer_info(peer_id)).unwrap();
+            registry.flush().unwrap();
+        }
+
+        let registry = PeerRegistry::open(config).unwrap();
+        let peers = registry.known_peers();
+        assert_eq!(peers.len(), 1);
+        assert_eq!(peers[0].peer_id, peer_id);
+        assert_eq!(peers[0].grpc_port, 50061);
+        assert_eq!(peers[0].grpc_address().unwrap(), "10.0.0.7:50061");
+    }
+
+    #[test]
+    fn test_backoff_grows_with_jitter() {
+        let registry = PeerRegistry::temporary(PeerRegistryConfig::default()).unwrap();
+
+        for failures in 1..=12 {
+            let full = Duration::from_secs(2)
+                .saturating_mul(1 << (failures - 1))
+                .min(Duration::from_secs(600));
+            let delay = registry.backoff(failures);
+            assert!(
+                delay >= full / 2 && delay <= full,
+                "{:?} vs {:?}",
+                delay,
+                full
+            );
+        }
+    }
+
+    #[test]
+    fn test_failing_peer_backs_off_then_banned() {
+        let config = PeerRegistryConfig {
+            max_dial_failures: 3,
+            ..Default::default()
+        };
+        let registry = PeerRegistry::temporary(config).unwrap();
+        let peer_id = random_peer();
+        registry.record_seen(&peer_info(peer_id)).unwrap();
+
+        // Dialed once, then held back until the backoff expires
+        assert_eq!(registry.dial_candidates().len(), 1);
+        assert!(registry.dial_candidates().is_empty());
+
+        registry.record_dial_failure(peer_id);
+        registry.record_dial_failure(peer_id);
+        assert!(!registry.is_banned(&peer_id));
+        registry.record_dial_failure(peer_id);
+        assert!(registry.is_banned(&peer_id));
+        assert_eq!(
+            registry.stats(),
+            PeerRegistryStats {
+                known: 1,
+                connected: 0,
+                banned: 1
+            }
+        );
+
+        // A successful connection lifts the ban
+        registry.record_connected(peer_id);
+        assert!(!registry.is_banned(&peer_id));
+        assert_eq!(registry.stats().connected, 1);
+        assert!(registry.dial_candidates().is_empty());
+
+        registry.record_disconnected(peer_id);
+        assert_eq!(registry.dial_candidates().len(), 1);
+    }
+
+    #[test]
+    fn test_prune_forgets_old_peers() {
+        let registry = PeerRegistry::temporary(PeerRegistryConfig {
+            retention: Duration::from_secs(3600),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let fresh = random_peer();
+        registry.record_seen(&peer_info(fresh)).unwrap();
+
+        let old = random_peer();
+        let mut info = peer_info(old);
+        if let Some(last_seen) = Instant::now().checked_sub(Duration::from_secs(7200)) {
+            info.last_seen = last_seen;
+            registry.record_seen(&info).unwrap();
+            assert_eq!(registry.prune().unwrap(), 1);
+        }
+
+        let peers = registry.known_peers();
+        assert_eq!(peers.len(), 1);
+        assert_eq!(peers[0].peer_id, fresh);
+    }
+}
@@ -9,6 +9,10 @@ use std::time::SystemTime;
 /// Protocol version string
 pub const PROTOCOL_VERSION: &str = "/cyxcloud/1.0.0";
 
+/// Gossipsub topic carrying [`NodeAnnouncement`]s when a node's status or
+/// capacity changes
+pub const NODE_STATUS_TOPIC: &str = "/cyxcloud/node-status/1.0.0";
+
 /// Node announcement message
 ///
 /// This is stored in the Kademlia DHT to advertise a node's presence
@@ -89,6 +93,20 @@ impl NodeAnnouncement {
     pub fn dht_key(&self) -> Vec<u8> {
         format!("node:{}", self.node_id).into_bytes()
     }
+
+    /// Check whether this announcement replaces `other` (same or later time)
+    pub fn supersedes(&self, other: &NodeAnnouncement) -> bool {
+        self.timestamp >= other.timestamp
+    }
+
+    /// Check whether this announcement differs from `other` in anything
+    /// other nodes act on (address, status or capacity)
+    pub fn changes(&self, other: &NodeAnnouncement) -> bool {
+        self.grpc_address != other.grpc_address
+            || self.status != other.status
+            || self.capacity.storage_total != other.capacity.storage_total
+            || self.capacity.storage_used != other.capacity.storage_used
+    }
 }
 
 /// Node storage capacity
@@ -321,6 +339,25 @@ mod tests {
         assert!(!announcement.is_stale(300));
     }
 
+    #[test]
+    fn test_announcement_changes() {
+        let mut first = NodeAnnouncement::new("node", "10.0.0.1:50051");
+        first.timestamp = 100;
+        let mut second = first.clone();
+        second.timestamp = 160;
+
+        assert!(second.supersedes(&first));
+        assert!(!first.supersedes(&second));
+        assert!(!second.changes(&first));
+
+        second.status = NodeStatus::Draining;
+        assert!(second.changes(&first));
+
+        second.status = NodeStatus::Online;
+        second.capacity.storage_used = 1024;
+        assert!(second.changes(&first));
+    }
+
     #[test]
     fn test_chunk_location_announcement() {
         let chunk_id = vec![0u8; 32];
@@ -0,0 +1,277 @@
+//! Encryption of chunk transfers between nodes
+//!
+//! Chunks pushed node to node (ReplicateChunk, repair fan-outs) may cross
+//! networks nobody controls. Without mTLS their frames can be encrypted
+//! instead:
+//!
+//! 1. Every node holds a long-lived X25519 [`TransferKey`] and hands out its
+//!    public half through `GetTransferKey`.
+//! 2. The sender generates an ephemeral key per transfer and target, and
+//!    derives an AES-256-GCM key from the Diffie-Hellman secret of the two.
+//! 3. Frames are sealed with the chunk ID as associated data and a nonce made
+//!    of the frame's offset, so frames cannot be moved within the chunk or
+//!    into another one. The ephemeral public key travels in the first
+//!    `WriteChunkFrame`.
+//!
+//! The target's public key is taken as served, so encryption keeps shard
+//! contents from passive observers; stopping an active man in the middle
+//! still takes mTLS. Either way the target checks the decrypted chunk
+//! against its content-addressed ID.
+
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use bytes::Bytes;
+use cyxcloud_core::chunk::ChunkId;
+use cyxcloud_core::error::{CyxCloudError, Result};
+use rand::rngs::OsRng;
+use std::fmt;
+use std::path::Path;
+use x25519_dalek::{EphemeralSecret, PublicKey, StaticSecret};
+
+/// Size of an X25519 public key
+pub const PUBLIC_KEY_SIZE: usize = 32;
+
+/// Bytes a sealed frame carries on top of its data (the GCM tag)
+pub const SEAL_OVERHEAD: usize = 16;
+
+/// Context string separating transfer keys from any other use of the secret
+const KDF_CONTEXT: &str = "cyxcloud 2026 chunk transfer v1";
+
+/// A node's long-lived key for receiving encrypted transfers
+pub struct TransferKey {
+    secret: StaticSecret,
+    public: PublicKey,
+}
+
+impl TransferKey {
+    /// Generate a new random key
+    pub fn generate() -> Self {
+        Self::from_secret(StaticSecret::random_from_rng(OsRng))
+    }
+
+    /// Load the key stored at `path`, creating it if the file is missing
+    pub fn load_or_generate(path: &Path) -> std::io::Result<Self> {
+        match std::fs::read(path) {
+            Ok(bytes) => {
+                let bytes: [u8; 32] = bytes.try_into().map_err(|_| {
+                    std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        format!("{} is not a 32-byte transfer key", path.display()),
+                    )
+                })?;
+                Ok(Self::from_secret(StaticSecret::from(bytes)))
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                let key = Self::generate();
+                if let Some(parent) = path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                std::fs::write(path, key.secret.to_bytes())?;
+
+                // Readable by the node's user only
+                #[cfg(unix)]
+                {
+                    use std::os::unix::fs::PermissionsExt;
+                    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+                }
+
+                Ok(key)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    fn from_secret(secret: StaticSecret) -> Self {
+        let public = PublicKey::from(&secret);
+        Self { secret, public }
+    }
+
+    /// Public key senders encrypt to
+    pub fn public_key(&self) -> [u8; PUBLIC_KEY_SIZE] {
+        self.public.to_bytes()
+    }
+
+    /// Cipher for a transfer a sender started with `sender_key`
+    pub fn open(&self, sender_key: &[u8]) -> Result<TransferCipher> {
+        let sender = parse_public_key(sender_key)?;
+        let shared = self.secret.diffie_hellman(&sender);
+        if !shared.was_contributory() {
+            return Err(CyxCloudError::Decryption(
+                "Sender key is a low-order point".to_string(),
+            ));
+        }
+        Ok(TransferCipher::derive(
+            shared.as_bytes(),
+            sender.to_bytes(),
+            self.public.to_bytes(),
+        ))
+    }
+}
+
+impl fmt::Debug for TransferKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "TransferKey({})", hex_prefix(&self.public_key()))
+    }
+}
+
+/// AES-256-GCM cipher of one transfer to one target
+pub struct TransferCipher {
+    cipher: Aes256Gcm,
+    /// Public half of the sender's ephemeral key
+    sender_key: [u8; PUBLIC_KEY_SIZE],
+}
+
+impl TransferCipher {
+    /// Start a transfer to the node whose transfer key is `recipient_key`
+    pub fn seal_to(recipient_key: &[u8]) -> Result<Self> {
+        let recipient = parse_public_key(recipient_key)?;
+        let ephemeral = EphemeralSecret::random_from_rng(OsRng);
+        let sender = PublicKey::from(&ephemeral);
+        let shared = ephemeral.diffie_hellman(&recipient);
+        if !shared.was_contributory() {
+            return Err(CyxCloudError::Encryption(
+                "Recipient key is a low-order point".to_string(),
+            ));
+        }
+        Ok(Self::derive(
+            shared.as_bytes(),
+            sender.to_bytes(),
+            recipient.to_bytes(),
+        ))
+    }
+
+    /// Derive the transfer key from the shared secret and both public keys
+    fn derive(
+        shared: &[u8; 32],
+        sender_key: [u8; PUBLIC_KEY_SIZE],
+        recipient_key: [u8; PUBLIC_KEY_SIZE],
+    ) -> Self {
+        let mut material = [0u8; 32 + 2 * PUBLIC_KEY_SIZE];
+        material[..32].copy_from_slice(shared);
+        material[32..32 + PUBLIC_KEY_SIZE].copy_from_slice(&sender_key);
+        material[32 + PUBLIC_KEY_SIZE..].copy_from_slice(&recipient_key);
+        let mut key = blake3::derive_key(KDF_CONTEXT, &material);
+        material.iter_mut().for_each(|b| *b = 0);
+
+        let cipher = Aes256Gcm::new(&key.into());
+        key.iter_mut().for_each(|b| *b = 0);
+        Self { cipher, sender_key }
+    }
+
+    /// Public key the target needs to open the transfer
+    pub fn sender_key(&self) -> [u8; PUBLIC_KEY_SIZE] {
+        self.sender_key
+    }
+
+    /// Seal the frame of `chunk_id` starting at `offset`
+    pub fn encrypt(&self, chunk_id: ChunkId, offset: u64, data: &[u8]) -> Result<Bytes> {
+        let payload = Payload {
+            msg: data,
+            aad: chunk_id.as_bytes(),
+        };
+        self.cipher
+            .encrypt(Nonce::from_slice(&frame_nonce(offset)), payload)
+            .map(Bytes::from)
+            .map_err(|e| CyxCloudError::Encryption(e.to_string()))
+    }
+
+    /// Open the frame of `chunk_id` starting at `offset`
+    pub fn decrypt(&self, chunk_id: ChunkId, offset: u64, data: &[u8]) -> Result<Bytes> {
+        let payload = Payload {
+            msg: data,
+            aad: chunk_id.as_bytes(),
+        };
+        self.cipher
+            .decrypt(Nonce::from_slice(&frame_nonce(offset)), payload)
+            .map(Bytes::from)
+            .map_err(|_| {
+                CyxCloudError::Decryption(format!(
+                    "Frame at offset {} failed authentication",
+                    offset
+                ))
+            })
+    }
+}
+
+impl fmt::Debug for TransferCipher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "TransferCipher({})", hex_prefix(&self.sender_key))
+    }
+}
+
+/// Nonce of the frame at `offset`
+///
+/// Offsets are unique within a chunk and every transfer has its own key, so
+/// a nonce is never reused under one key.
+fn frame_nonce(offset: u64) -> [u8; 12] {
+    let mut nonce = [0u8; 12];
+    nonce[..8].copy_from_slice(&offset.to_be_bytes());
+    nonce
+}
+
+fn parse_public_key(bytes: &[u8]) -> Result<PublicKey> {
+    let bytes: [u8; PUBLIC_KEY_SIZE] =
+        bytes
+            .try_into()
+            .map_err(|_| CyxCloudError::InvalidKeyLength {
+                expected: PUBLIC_KEY_SIZE,
+                actual: bytes.len(),
+            })?;
+    Ok(PublicKey::from(bytes))
+}
+
+/// First bytes of a key in hex, for logs
+fn hex_prefix(key: &[u8]) -> String {
+    key[..4].iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_transfer_roundtrip() {
+        let target = TransferKey::generate();
+        let sender = TransferCipher::seal_to(&target.public_key()).unwrap();
+        let receiver = target.open(&sender.sender_key()).unwrap();
+
+        let chunk_id = ChunkId::from_data(b"shard");
+        let sealed = sender.encrypt(chunk_id, 4096, b"shard").unwrap();
+        assert_eq!(sealed.len(), 5 + SEAL_OVERHEAD);
+        assert_ne!(&sealed[..5], b"shard");
+        assert_eq!(
+            receiver.decrypt(chunk_id, 4096, &sealed).unwrap().as_ref(),
+            b"shard"
+        );
+
+        // Bound to the offset, the chunk and the recipient
+        assert!(receiver.decrypt(chunk_id, 0, &sealed).is_err());
+        assert!(receiver
+            .decrypt(ChunkId::from_data(b"other"), 4096, &sealed)
+            .is_err());
+        let outsider = TransferKey::generate().open(&sender.sender_key()).unwrap();
+        assert!(outsider.decrypt(chunk_id, 4096, &sealed).is_err());
+    }
+
+    #[test]
+    fn test_transfer_key_rejects_bad_keys() {
+        assert!(TransferCipher::seal_to(&[1u8; 31]).is_err());
+        // The identity point would make the shared secret all zeros
+        assert!(TransferCipher::seal_to(&[0u8; 32]).is_err());
+        assert!(TransferKey::generate().open(&[0u8; 32]).is_err());
+    }
+
+    #[test]
+    fn test_transfer_key_persisted() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("keys").join("transfer.key");
+
+        let key = TransferKey::load_or_generate(&path).unwrap();
+        let reloaded = TransferKey::load_or_generate(&path).unwrap();
+        assert_eq!(key.public_key(), reloaded.public_key());
+
+        std::fs::write(&path, b"short").unwrap();
+        assert!(TransferKey::load_or_generate(&path).is_err());
+    }
+}
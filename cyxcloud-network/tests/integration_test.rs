@@ -5,9 +5,12 @@
 use bytes::Bytes;
 use cyxcloud_core::chunk::ChunkId;
 use cyxcloud_network::{
-    grpc_client::{get_from_any_node, store_to_multiple_nodes, ChunkClient},
+    grpc_client::{
+        fan_out_chunk, get_from_any_node, replicate_chunk, store_to_multiple_nodes, ChunkClient,
+        ChunkClientConfig,
+    },
     grpc_server::{start_server, GrpcServerConfig},
-    NetworkConfig, NetworkManager,
+    NetworkConfig, NetworkManager, TransferKey,
 };
 use cyxcloud_storage::{RocksDbBackend, StorageConfig};
 use std::net::SocketAddr;
@@ -24,11 +27,18 @@ struct TestNode {
 
 impl TestNode {
     async fn start(port: u16) -> Self {
+        Self::start_with(port, |config| config).await
+    }
+
+    async fn start_with(
+        port: u16,
+        configure: impl FnOnce(GrpcServerConfig) -> GrpcServerConfig,
+    ) -> Self {
         let temp_dir = TempDir::new().unwrap();
         let config = StorageConfig::new(temp_dir.path());
         let storage = Arc::new(RocksDbBackend::open(config).unwrap());
         let addr: SocketAddr = format!("127.0.0.1:{}", port).parse().unwrap();
-        let grpc_config = GrpcServerConfig::new(addr);
+        let grpc_config = configure(GrpcServerConfig::new(addr));
         let node_id = format!("node-{}", port);
 
         let handle = tokio::spawn(async move {
@@ -183,6 +193,184 @@ async fn test_partial_replication_failure() {
     }
 }
 
+#[tokio::test]
+async fn test_pipelined_fan_out() {
+    let nodes = vec![TestNode::start(50270).await, TestNode::start(50271).await];
+
+    let client = ChunkClient::new();
+    let target_addrs = vec![
+        nodes[0].addr.clone(),
+        "127.0.0.1:59998".to_string(), // This node doesn't exist
+        nodes[1].addr.clone(),
+    ];
+
+    // Several frames, so the chunk is streamed rather than sent whole
+    let data: Bytes = (0..3 * 1024 * 1024 + 5).map(|i| (i % 251) as u8).collect();
+    let chunk_id = ChunkId::from_data(&data);
+
+    let report = fan_out_chunk(&client, chunk_id, data.clone(), &target_addrs)
+        .await
+        .unwrap();
+
+    // The dead target fails without holding up the others
+    assert_eq!(
+        report.succeeded(),
+        vec![nodes[0].addr.clone(), nodes[1].addr.clone()]
+    );
+    let failed: Vec<_> = report.failed().collect();
+    assert_eq!(failed.len(), 1);
+    assert_eq!(failed[0].addr, "127.0.0.1:59998");
+    for target in report.targets.iter().filter(|t| t.succeeded()) {
+        assert_eq!(target.bytes_sent, data.len() as u64);
+    }
+
+    for node in &nodes {
+        let retrieved = client.get_chunk(&node.addr, chunk_id).await.unwrap();
+        assert_eq!(retrieved.unwrap(), data);
+    }
+
+    for node in &nodes {
+        node.stop();
+    }
+}
+
+#[tokio::test]
+async fn test_replicate_chunk_from_source() {
+    let nodes = vec![
+        TestNode::start(50280).await,
+        TestNode::start(50281).await,
+        TestNode::start(50282).await,
+    ];
+
+    let client = ChunkClient::new();
+    let data: Bytes = (0..2 * 1024 * 1024 + 17).map(|i| (i % 253) as u8).collect();
+    let chunk_id = ChunkId::from_data(&data);
+
+    // Only the source has the chunk
+    client
+        .store_chunk(&nodes[0].addr, chunk_id, data.clone())
+        .await
+        .unwrap();
+
+    let targets = vec![nodes[1].addr.clone(), nodes[2].addr.clone()];
+    let report = replicate_chunk(&client, &nodes[0].addr, chunk_id, &targets)
+        .await
+        .unwrap();
+    assert_eq!(report.succeeded(), targets);
+
+    for addr in &targets {
+        let retrieved = client.get_chunk(addr, chunk_id).await.unwrap();
+        assert_eq!(retrieved.unwrap(), data);
+    }
+
+    // A chunk the source doesn't have is reported, not half-copied
+    let missing = ChunkId::from_data(b"not stored anywhere");
+    assert!(replicate_chunk(&client, &nodes[0].addr, missing, &targets)
+        .await
+        .is_err());
+
+    for node in &nodes {
+        node.stop();
+    }
+}
+
+#[tokio::test]
+async fn test_push_chunk_between_nodes() {
+    let token = "cluster-secret";
+    let mut nodes = Vec::new();
+    for port in [50290, 50291, 50292] {
+        nodes.push(TestNode::start_with(port, |c| c.with_cluster_token(token)).await);
+    }
+
+    let data: Bytes = (0..1024 * 1024 + 3).map(|i| (i % 241) as u8).collect();
+    let chunk_id = ChunkId::from_data(&data);
+    let targets = vec![nodes[1].addr.clone(), nodes[2].addr.clone()];
+
+    // The source only pushes for callers holding the cluster token
+    let outsider = ChunkClient::new();
+    outsider
+        .store_chunk(&nodes[0].addr, chunk_id, data.clone())
+        .await
+        .unwrap();
+    assert!(outsider
+        .push_chunk(&nodes[0].addr, chunk_id, &targets)
+        .await
+        .is_err());
+
+    let client = ChunkClient::with_config(ChunkClientConfig {
+        cluster_token: Some(token.to_string()),
+        ..Default::default()
+    });
+    let report = client
+        .push_chunk(&nodes[0].addr, chunk_id, &targets)
+        .await
+        .unwrap()
+        .expect("source supports ReplicateChunk");
+    assert_eq!(report.succeeded(), targets);
+    for target in &report.targets {
+        assert_eq!(target.bytes_sent, data.len() as u64);
+    }
+
+    for addr in &targets {
+        let retrieved = client.get_chunk(addr, chunk_id).await.unwrap();
+        assert_eq!(retrieved.unwrap(), data);
+    }
+
+    for node in &nodes {
+        node.stop();
+    }
+}
+
+#[tokio::test]
+async fn test_push_chunk_encrypted() {
+    let mut nodes = Vec::new();
+    for port in [50300, 50301, 50302] {
+        nodes.push(
+            TestNode::start_with(port, |c| c.with_transfer_key(TransferKey::generate())).await,
+        );
+    }
+    // Has no transfer key, so it must not be sent the chunk in clear
+    let plain = TestNode::start(50303).await;
+
+    let data: Bytes = (0..1024 * 1024 + 5).map(|i| (i % 239) as u8).collect();
+    let chunk_id = ChunkId::from_data(&data);
+    let client = ChunkClient::with_config(ChunkClientConfig {
+        encrypt_transfers: true,
+        ..Default::default()
+    });
+    client
+        .store_chunk(&nodes[0].addr, chunk_id, data.clone())
+        .await
+        .unwrap();
+
+    let targets = vec![
+        nodes[1].addr.clone(),
+        nodes[2].addr.clone(),
+        plain.addr.clone(),
+    ];
+    let report = client
+        .push_chunk(&nodes[0].addr, chunk_id, &targets)
+        .await
+        .unwrap()
+        .expect("source supports ReplicateChunk");
+    assert_eq!(report.succeeded(), targets[..2].to_vec());
+    assert_eq!(report.targets[2].bytes_sent, 0);
+
+    for addr in &targets[..2] {
+        let retrieved = client.get_chunk(addr, chunk_id).await.unwrap();
+        assert_eq!(retrieved.unwrap(), data);
+    }
+    assert!(client
+        .get_chunk(&plain.addr, chunk_id)
+        .await
+        .unwrap()
+        .is_none());
+
+    for node in nodes.iter().chain([&plain]) {
+        node.stop();
+    }
+}
+
 #[tokio::test]
 async fn test_network_manager_creation() {
     let temp_dir = TempDir::new().unwrap();
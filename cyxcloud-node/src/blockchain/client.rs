@@ -16,7 +16,7 @@ use tracing::{info, warn};
 
 use super::heartbeat::HeartbeatOps;
 use super::registration::RegistrationOps;
-use super::rewards::RewardsOps;
+use super::rewards::{ClaimOutcome, RewardsOps};
 use super::types::{
     EpochClaimInfo, NodeBlockchainConfig, ProofChallenge, ProofOfStorage, StorageNodeInfo,
     StorageSpec,
@@ -253,6 +253,18 @@ impl StorageNodeBlockchainClient {
             .await
     }
 
+    /// Claim rewards for an epoch, retrying RPC failures
+    pub async fn claim_rewards_with_retry(
+        &self,
+        node_id: &str,
+        epoch: u64,
+        max_attempts: u32,
+    ) -> Result<Option<Signature>> {
+        self.rewards
+            .claim_rewards_with_retry(&self.owner, node_id, epoch, max_attempts)
+            .await
+    }
+
     /// Get all pending rewards
     pub async fn get_pending_rewards(&self, node_id: &str) -> Result<Vec<EpochClaimInfo>> {
         self.rewards
@@ -260,9 +272,26 @@ impl StorageNodeBlockchainClient {
             .await
     }
 
+    /// Get reward allocations for the last `lookback` epochs
+    pub async fn get_reward_history(
+        &self,
+        node_id: &str,
+        lookback: u64,
+    ) -> Result<Vec<EpochClaimInfo>> {
+        self.rewards
+            .get_reward_history(&self.owner.pubkey(), node_id, lookback)
+            .await
+    }
+
     /// Claim all pending rewards
-    pub async fn claim_all_rewards(&self, node_id: &str) -> Result<Vec<Signature>> {
-        self.rewards.claim_all_rewards(&self.owner, node_id).await
+    pub async fn claim_all_rewards(
+        &self,
+        node_id: &str,
+        max_attempts: u32,
+    ) -> Result<Vec<ClaimOutcome>> {
+        self.rewards
+            .claim_all_rewards(&self.owner, node_id, max_attempts)
+            .await
     }
 
     // =========================================================================
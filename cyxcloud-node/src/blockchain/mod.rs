@@ -42,13 +42,14 @@ pub use client::StorageNodeBlockchainClient;
 
 // Re-export types
 pub use types::{
-    DiskType, NodeBlockchainConfig, ProofChallenge, ProofOfStorage, StorageNodeStatus, StorageSpec,
+    DiskType, EpochClaimInfo, NodeBlockchainConfig, ProofChallenge, ProofOfStorage,
+    StorageNodeStatus, StorageSpec,
 };
 
 // Re-export operations for advanced use
 pub use heartbeat::HeartbeatOps;
 pub use registration::RegistrationOps;
-pub use rewards::RewardsOps;
+pub use rewards::{ClaimOutcome, RewardsClaimService, RewardsOps, DEFAULT_REWARD_LOOKBACK_EPOCHS};
 
 // Re-export constants
 pub use types::constants;
@@ -1,9 +1,11 @@
 //! Reward Claiming Operations
 //!
-//! Handles claiming epoch rewards from the payment pool.
+//! Handles claiming epoch rewards from the payment pool, with retries on
+//! RPC failures and an optional background auto-claim service.
 
 use anyhow::Result;
 use sha2::{Digest, Sha256};
+use solana_client::client_error::ClientError;
 use solana_client::rpc_client::RpcClient;
 use solana_sdk::{
     instruction::{AccountMeta, Instruction},
@@ -13,10 +15,21 @@ use solana_sdk::{
     transaction::Transaction,
 };
 use std::sync::Arc;
-use tracing::{info, warn};
+use std::time::Duration;
+use tracing::{debug, info, warn};
 
+use super::client::StorageNodeBlockchainClient;
 use super::types::EpochClaimInfo;
 
+/// Number of past epochs checked for rewards by default
+pub const DEFAULT_REWARD_LOOKBACK_EPOCHS: u64 = 10;
+
+/// Delay before the first claim retry; doubled after every failure
+const CLAIM_RETRY_BASE_DELAY: Duration = Duration::from_secs(2);
+
+/// Upper bound for the delay between claim retries
+const CLAIM_RETRY_MAX_DELAY: Duration = Duration::from_secs(30);
+
 /// SPL Token program ID
 const SPL_TOKEN_PROGRAM_ID: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
 
@@ -42,6 +55,27 @@ fn get_associated_token_address(wallet: &Pubkey, mint: &Pubkey) -> Pubkey {
     address
 }
 
+/// Whether a failed claim is worth retrying
+///
+/// RPC failures (timeouts, dropped connections, expired blockhashes) are
+/// retried; transactions the program rejected and local errors are not.
+pub fn is_retryable(error: &anyhow::Error) -> bool {
+    match error.downcast_ref::<ClientError>() {
+        Some(e) => e.get_transaction_error().is_none(),
+        None => false,
+    }
+}
+
+/// Result of claiming one epoch's reward
+#[derive(Debug)]
+pub struct ClaimOutcome {
+    pub epoch: u64,
+    pub reward_amount: u64,
+    /// Transaction signature, `None` if the reward turned out to be claimed
+    /// already (e.g. by an earlier attempt that timed out)
+    pub result: Result<Option<Signature>>,
+}
+
 /// Reward claiming operations for storage nodes
 pub struct RewardsOps {
     rpc_client: Arc<RpcClient>,
@@ -136,9 +170,14 @@ impl RewardsOps {
     ) -> Result<Option<EpochClaimInfo>> {
         let (claim_pda, _) = self.derive_claim_pda(epoch, owner, node_id);
 
-        let account = match self.rpc_client.get_account(&claim_pda) {
-            Ok(acc) => acc,
-            Err(_) => return Ok(None),
+        // A missing account means nothing was allocated; RPC errors propagate
+        let account = match self
+            .rpc_client
+            .get_account_with_commitment(&claim_pda, self.rpc_client.commitment())?
+            .value
+        {
+            Some(acc) => acc,
+            None => return Ok(None),
         };
 
         let data = &account.data;
@@ -248,47 +287,112 @@ impl RewardsOps {
         Ok(signature)
     }
 
-    /// Get pending rewards across multiple epochs
-    pub async fn get_pending_rewards(
+    /// Claim rewards for an epoch, retrying RPC failures
+    ///
+    /// Makes up to `max_attempts` attempts with exponential backoff. Before
+    /// each retry the claim account is re-read, since a transaction that
+    /// timed out may still have landed; `Ok(None)` means it had.
+    pub async fn claim_rewards_with_retry(
+        &self,
+        owner: &Keypair,
+        node_id: &str,
+        epoch: u64,
+        max_attempts: u32,
+    ) -> Result<Option<Signature>> {
+        let mut attempt = 1;
+        let mut delay = CLAIM_RETRY_BASE_DELAY;
+        loop {
+            let error = match self.claim_rewards(owner, node_id, epoch).await {
+                Ok(sig) => return Ok(Some(sig)),
+                Err(e) => e,
+            };
+            if attempt >= max_attempts || !is_retryable(&error) {
+                return Err(error);
+            }
+
+            warn!(
+                epoch = epoch,
+                attempt = attempt,
+                retry_in_secs = delay.as_secs(),
+                error = %error,
+                "Reward claim failed, retrying"
+            );
+            tokio::time::sleep(delay).await;
+            delay = (delay * 2).min(CLAIM_RETRY_MAX_DELAY);
+            attempt += 1;
+
+            if let Ok(Some(claim)) = self.get_epoch_claim(epoch, &owner.pubkey(), node_id).await {
+                if claim.claimed {
+                    return Ok(None);
+                }
+            }
+        }
+    }
+
+    /// Get reward allocations for the last `lookback` epochs, oldest first
+    ///
+    /// Includes claimed rewards; epochs without an allocation are skipped.
+    pub async fn get_reward_history(
         &self,
         owner: &Pubkey,
         node_id: &str,
+        lookback: u64,
     ) -> Result<Vec<EpochClaimInfo>> {
         let current_epoch = self.get_current_epoch().await?;
-        let mut pending = Vec::new();
+        let mut history = Vec::new();
 
-        // Check last 10 epochs for unclaimed rewards
-        let start_epoch = current_epoch.saturating_sub(10);
+        let start_epoch = current_epoch.saturating_sub(lookback);
         for epoch in start_epoch..current_epoch {
             if let Some(claim) = self.get_epoch_claim(epoch, owner, node_id).await? {
-                if !claim.claimed && claim.reward_amount > 0 {
-                    pending.push(claim);
-                }
+                history.push(claim);
             }
         }
 
-        Ok(pending)
+        Ok(history)
+    }
+
+    /// Get pending rewards across multiple epochs
+    pub async fn get_pending_rewards(
+        &self,
+        owner: &Pubkey,
+        node_id: &str,
+    ) -> Result<Vec<EpochClaimInfo>> {
+        let history = self
+            .get_reward_history(owner, node_id, DEFAULT_REWARD_LOOKBACK_EPOCHS)
+            .await?;
+        Ok(history
+            .into_iter()
+            .filter(|claim| !claim.claimed && claim.reward_amount > 0)
+            .collect())
     }
 
     /// Claim all pending rewards
+    ///
+    /// A failed epoch does not stop the others; each outcome is returned.
     pub async fn claim_all_rewards(
         &self,
         owner: &Keypair,
         node_id: &str,
-    ) -> Result<Vec<Signature>> {
+        max_attempts: u32,
+    ) -> Result<Vec<ClaimOutcome>> {
         let pending = self.get_pending_rewards(&owner.pubkey(), node_id).await?;
-        let mut signatures = Vec::new();
+        let mut outcomes = Vec::with_capacity(pending.len());
 
         for claim in pending {
-            match self.claim_rewards(owner, node_id, claim.epoch).await {
-                Ok(sig) => {
+            let result = self
+                .claim_rewards_with_retry(owner, node_id, claim.epoch, max_attempts)
+                .await;
+            match &result {
+                Ok(Some(sig)) => {
                     info!(
                         epoch = claim.epoch,
                         amount_cyxwiz = claim.reward_amount / 1_000_000_000,
                         signature = %sig,
                         "Claimed epoch reward"
                     );
-                    signatures.push(sig);
+                }
+                Ok(None) => {
+                    info!(epoch = claim.epoch, "Epoch reward was already claimed");
                 }
                 Err(e) => {
                     warn!(
@@ -298,8 +402,75 @@ impl RewardsOps {
                     );
                 }
             }
+            outcomes.push(ClaimOutcome {
+                epoch: claim.epoch,
+                reward_amount: claim.reward_amount,
+                result,
+            });
+        }
+
+        Ok(outcomes)
+    }
+}
+
+/// Background service that claims pending rewards periodically
+pub struct RewardsClaimService {
+    client: Arc<StorageNodeBlockchainClient>,
+    node_id: String,
+    interval_secs: u64,
+    max_attempts: u32,
+}
+
+impl RewardsClaimService {
+    /// Create a new auto-claim service
+    pub fn new(
+        client: Arc<StorageNodeBlockchainClient>,
+        node_id: String,
+        interval_secs: u64,
+        max_attempts: u32,
+    ) -> Self {
+        Self {
+            client,
+            node_id,
+            interval_secs,
+            max_attempts,
         }
+    }
 
-        Ok(signatures)
+    /// Run the auto-claim service (blocking)
+    pub async fn run(&self) {
+        info!(
+            node_id = %self.node_id,
+            interval_secs = self.interval_secs,
+            "Starting reward auto-claim service"
+        );
+
+        let mut interval =
+            tokio::time::interval(std::time::Duration::from_secs(self.interval_secs));
+
+        loop {
+            interval.tick().await;
+
+            match self
+                .client
+                .claim_all_rewards(&self.node_id, self.max_attempts)
+                .await
+            {
+                Ok(outcomes) if outcomes.is_empty() => {
+                    debug!("No pending rewards to claim");
+                }
+                Ok(outcomes) => {
+                    let failed = outcomes.iter().filter(|o| o.result.is_err()).count();
+                    info!(
+                        epochs = outcomes.len(),
+                        failed = failed,
+                        "Reward auto-claim finished"
+                    );
+                }
+                Err(e) => {
+                    warn!(error = %e, "Failed to check pending rewards");
+                }
+            }
+        }
     }
 }
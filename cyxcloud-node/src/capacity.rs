@@ -0,0 +1,225 @@
+//! Storage capacity detection for CyxCloud storage node
+//!
+//! Without `max_capacity_gb` the node offers `auto_capacity_percent` of the
+//! space it could use on the data directory's filesystem: the free space
+//! plus what it already stores. Counting its own data keeps the offer steady
+//! as the node fills up, while anything else written to the disk shrinks it.
+//! The capacity is re-evaluated every `capacity_check_secs`.
+//!
+//! Heartbeats report the offered capacity and the free disk space side by
+//! side, so the gateway stops placing chunks on a node whose disk is full
+//! even while its configured capacity is not.
+
+use crate::config::StorageSettings;
+use cyxcloud_storage::RocksDbBackend;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+const GB: f64 = 1024.0 * 1024.0 * 1024.0;
+
+/// Space on the filesystem holding a directory
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DiskSpace {
+    /// Bytes available to the node (excludes blocks reserved for root)
+    pub available_bytes: u64,
+    /// Size of the filesystem
+    pub total_bytes: u64,
+}
+
+/// Space on the filesystem holding `path`
+#[cfg(unix)]
+#[allow(clippy::unnecessary_cast)] // statvfs field types differ between platforms
+pub fn disk_space(path: &Path) -> io::Result<DiskSpace> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = CString::new(path.as_os_str().as_bytes())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    // SAFETY: statvfs is plain old data, fully written on success
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    // SAFETY: `path` is NUL-terminated and `stat` is a valid out pointer
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let fragment = stat.f_frsize as u64;
+    Ok(DiskSpace {
+        available_bytes: stat.f_bavail as u64 * fragment,
+        total_bytes: stat.f_blocks as u64 * fragment,
+    })
+}
+
+/// Space on the filesystem holding `path`
+#[cfg(not(unix))]
+pub fn disk_space(path: &Path) -> io::Result<DiskSpace> {
+    let path = path.canonicalize()?;
+    let disks = sysinfo::Disks::new_with_refreshed_list();
+
+    // The disk whose mount point is the longest prefix of the path
+    disks
+        .iter()
+        .filter(|d| path.starts_with(d.mount_point()))
+        .max_by_key(|d| d.mount_point().as_os_str().len())
+        .map(|d| DiskSpace {
+            available_bytes: d.available_space(),
+            total_bytes: d.total_space(),
+        })
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no disk holds the path"))
+}
+
+/// Capacity offered for `percent` of the free space plus the node's own data
+pub fn auto_capacity(used_bytes: u64, disk: DiskSpace, percent: f64) -> u64 {
+    (used_bytes.saturating_add(disk.available_bytes) as f64 * percent / 100.0) as u64
+}
+
+/// Keeps the storage backend's capacity in line with the disk
+pub struct CapacityMonitor {
+    data_dir: PathBuf,
+    /// Configured capacity in bytes (0 = detect)
+    configured: u64,
+    percent: f64,
+    interval: Duration,
+    storage: Arc<RocksDbBackend>,
+}
+
+impl CapacityMonitor {
+    /// Create a monitor for the storage settings' data directory
+    pub fn new(settings: &StorageSettings, storage: Arc<RocksDbBackend>) -> Self {
+        Self {
+            data_dir: settings.data_dir.clone(),
+            configured: settings.max_capacity_gb * 1024 * 1024 * 1024,
+            percent: settings.auto_capacity_percent,
+            interval: Duration::from_secs(settings.capacity_check_secs.max(1)),
+            storage,
+        }
+    }
+
+    /// Whether the capacity is detected rather than configured
+    pub fn is_auto(&self) -> bool {
+        self.configured == 0
+    }
+
+    /// Re-evaluate the capacity
+    ///
+    /// Returns the disk space it was based on, or None if the filesystem
+    /// could not be inspected; the capacity is left unchanged then.
+    pub fn refresh(&self) -> Option<DiskSpace> {
+        let disk = match disk_space(&self.data_dir) {
+            Ok(disk) => disk,
+            Err(e) => {
+                warn!(error = %e, data_dir = ?self.data_dir, "Failed to read free disk space");
+                return None;
+            }
+        };
+
+        // The same measure of usage the backend enforces the capacity with
+        let used = self.storage.approximate_size();
+
+        if !self.is_auto() {
+            if self.configured > used.saturating_add(disk.available_bytes) {
+                debug!(
+                    capacity_gb = format!("{:.1}", self.configured as f64 / GB),
+                    disk_available_gb = format!("{:.1}", disk.available_bytes as f64 / GB),
+                    "Configured capacity exceeds the space left on disk"
+                );
+            }
+            return Some(disk);
+        }
+
+        let capacity = auto_capacity(used, disk, self.percent);
+        let previous = self.storage.max_capacity();
+        if capacity != previous {
+            self.storage.set_max_capacity(capacity);
+
+            // Log first detection and moves of more than 1%
+            if previous == 0 || previous.abs_diff(capacity).saturating_mul(100) > previous {
+                info!(
+                    capacity_gb = format!("{:.1}", capacity as f64 / GB),
+                    used_gb = format!("{:.1}", used as f64 / GB),
+                    disk_available_gb = format!("{:.1}", disk.available_bytes as f64 / GB),
+                    percent = self.percent,
+                    "Storage capacity detected"
+                );
+            }
+        }
+
+        Some(disk)
+    }
+
+    /// Re-evaluate the capacity periodically (runs forever)
+    pub async fn run(&self) {
+        let mut interval = tokio::time::interval(self.interval);
+        // The first tick fires immediately; startup already refreshed
+        interval.tick().await;
+
+        loop {
+            interval.tick().await;
+            self.refresh();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cyxcloud_storage::backend::StorageBackendSync;
+    use tempfile::TempDir;
+
+    const GIB: u64 = 1024 * 1024 * 1024;
+
+    #[test]
+    fn test_auto_capacity() {
+        let disk = DiskSpace {
+            available_bytes: 80 * GIB,
+            total_bytes: 100 * GIB,
+        };
+        assert_eq!(auto_capacity(0, disk, 50.0), 40 * GIB);
+
+        // Data the node stores counts, so the offer does not grow as it fills
+        let filled = DiskSpace {
+            available_bytes: 60 * GIB,
+            ..disk
+        };
+        assert_eq!(auto_capacity(20 * GIB, filled, 50.0), 40 * GIB);
+        assert_eq!(auto_capacity(0, disk, 100.0), 80 * GIB);
+    }
+
+    #[test]
+    fn test_disk_space() {
+        let dir = TempDir::new().unwrap();
+        let disk = disk_space(dir.path()).unwrap();
+        assert!(disk.total_bytes > 0);
+        assert!(disk.available_bytes <= disk.total_bytes);
+
+        assert!(disk_space(&dir.path().join("missing")).is_err());
+    }
+
+    #[test]
+    fn test_monitor_sets_capacity() {
+        let dir = TempDir::new().unwrap();
+        let mut settings = StorageSettings::default();
+        settings.data_dir = dir.path().to_path_buf();
+        let storage = Arc::new(RocksDbBackend::open(settings.to_storage_config()).unwrap());
+
+        // Detected
+        let monitor = CapacityMonitor::new(&settings, storage.clone());
+        assert!(monitor.is_auto());
+        let disk = monitor.refresh().unwrap();
+        let capacity = storage.stats().unwrap().bytes_capacity;
+        assert!(capacity > 0);
+        assert!(capacity <= disk.available_bytes);
+
+        // Configured capacity is left alone
+        let dir = TempDir::new().unwrap();
+        settings.data_dir = dir.path().to_path_buf();
+        settings.max_capacity_gb = 1;
+        let storage = Arc::new(RocksDbBackend::open(settings.to_storage_config()).unwrap());
+        let monitor = CapacityMonitor::new(&settings, storage.clone());
+        assert!(!monitor.is_auto());
+        assert!(monitor.refresh().is_some());
+        assert_eq!(storage.max_capacity(), GIB);
+    }
+}
@@ -0,0 +1,367 @@
+//! Offline chunk store export/import for cold migration
+//!
+//! `cyxcloud-node export` writes every chunk of the local store into a tar
+//! archive (`chunks/<hex id>` entries plus a `manifest.json`), and
+//! `cyxcloud-node import` loads such an archive into another node's store.
+//! Chunks are content-addressed, so each one is verified against its ID on
+//! both sides; corrupted chunks are reported and skipped. Shards keep their
+//! shard header: the entry holds the header followed by the data.
+//!
+//! After an import the gateway still lists the chunks on the old node. The
+//! import writes an adoption request (`<archive>.adopt.json`) that can be
+//! posted to `POST /api/v1/admin/nodes/{node_id}/chunks/adopt` to move the
+//! chunk locations to the new node.
+
+use bytes::Bytes;
+use cyxcloud_core::chunk::ChunkId;
+use cyxcloud_core::error::CyxCloudError;
+use cyxcloud_storage::backend::StorageBackendSync;
+use cyxcloud_storage::ShardHeader;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::io::{Read, Write};
+use thiserror::Error;
+use tracing::{debug, warn};
+
+/// Archive format version
+pub const ARCHIVE_VERSION: u32 = 1;
+
+/// Name of the manifest entry
+pub const MANIFEST_NAME: &str = "manifest.json";
+
+/// Directory holding chunk entries inside the archive
+const CHUNK_DIR: &str = "chunks/";
+
+/// Archive errors
+#[derive(Error, Debug)]
+pub enum ArchiveError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Storage error: {0}")]
+    Storage(#[from] CyxCloudError),
+
+    #[error("Invalid manifest: {0}")]
+    Manifest(String),
+}
+
+/// One chunk listed in the manifest
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    /// Hex-encoded chunk ID
+    pub id: String,
+    /// Chunk size in bytes
+    pub size: u64,
+}
+
+/// Archive manifest, written as the last entry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchiveManifest {
+    pub version: u32,
+    /// Node the chunks were exported from
+    pub node_id: String,
+    /// Export time (RFC 3339)
+    pub created_at: String,
+    pub chunks: Vec<ManifestEntry>,
+}
+
+/// Result of an export
+#[derive(Debug, Default)]
+pub struct ExportSummary {
+    pub exported: u64,
+    pub bytes: u64,
+    /// Chunks whose data no longer matches their ID (not exported)
+    pub corrupted: Vec<String>,
+}
+
+/// Result of an import
+#[derive(Debug, Default)]
+pub struct ImportSummary {
+    /// Node the archive was exported from (from the manifest)
+    pub source_node_id: Option<String>,
+    pub imported: u64,
+    pub bytes: u64,
+    /// Chunks that were already in the store
+    pub already_present: u64,
+    /// Chunks whose data did not match their ID (not imported)
+    pub corrupted: Vec<String>,
+    /// Chunks listed in the manifest but missing from the archive
+    pub missing: Vec<String>,
+    /// Hex IDs of all chunks now in the store (imported or already present)
+    pub chunk_ids: Vec<String>,
+}
+
+/// Body for the gateway's chunk adoption endpoint
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AdoptionRequest {
+    pub from_node_id: String,
+    pub chunk_ids: Vec<String>,
+}
+
+impl ImportSummary {
+    /// Adoption request moving the imported chunks from the source node
+    pub fn adoption_request(&self) -> Option<AdoptionRequest> {
+        Some(AdoptionRequest {
+            from_node_id: self.source_node_id.clone()?,
+            chunk_ids: self.chunk_ids.clone(),
+        })
+    }
+}
+
+/// Write every chunk in `storage` to a tar archive
+pub fn export_chunks<S, W>(
+    storage: &S,
+    node_id: &str,
+    writer: W,
+) -> Result<ExportSummary, ArchiveError>
+where
+    S: StorageBackendSync + ?Sized,
+    W: Write,
+{
+    let mut archive = tar::Builder::new(writer);
+    let mut summary = ExportSummary::default();
+    let mut entries = Vec::new();
+
+    for id in storage.list_chunks()? {
+        let hex_id = hex::encode(id.as_bytes());
+        let Some((header, data)) = storage.get_shard(id)? else {
+            // Deleted since listing
+            continue;
+        };
+
+        if ChunkId::from_data(&data) != id {
+            warn!(chunk_id = %hex_id, "Chunk data does not match its ID, skipping");
+            summary.corrupted.push(hex_id);
+            continue;
+        }
+
+        let entry = match header {
+            Some(header) => header.wrap(&data),
+            None => data.clone(),
+        };
+        append_file(&mut archive, &format!("{}{}", CHUNK_DIR, hex_id), &entry)?;
+        debug!(chunk_id = %hex_id, size = data.len(), "Exported chunk");
+
+        summary.exported += 1;
+        summary.bytes += data.len() as u64;
+        entries.push(ManifestEntry {
+            id: hex_id,
+            size: data.len() as u64,
+        });
+    }
+
+    let manifest = ArchiveManifest {
+        version: ARCHIVE_VERSION,
+        node_id: node_id.to_string(),
+        created_at: chrono::Utc::now().to_rfc3339(),
+        chunks: entries,
+    };
+    let manifest_json =
+        serde_json::to_vec_pretty(&manifest).map_err(|e| ArchiveError::Manifest(e.to_string()))?;
+    append_file(&mut archive, MANIFEST_NAME, &manifest_json)?;
+
+    archive.into_inner()?.flush()?;
+    Ok(summary)
+}
+
+/// Load chunks from a tar archive into `storage`
+pub fn import_chunks<S, R>(storage: &S, reader: R) -> Result<ImportSummary, ArchiveError>
+where
+    S: StorageBackendSync + ?Sized,
+    R: Read,
+{
+    let mut archive = tar::Archive::new(reader);
+    let mut summary = ImportSummary::default();
+    let mut manifest: Option<ArchiveManifest> = None;
+    let mut seen = HashSet::new();
+
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.to_string_lossy().into_owned();
+
+        let mut data = Vec::with_capacity(entry.size() as usize);
+        entry.read_to_end(&mut data)?;
+
+        if path == MANIFEST_NAME {
+            let parsed: ArchiveManifest =
+                serde_json::from_slice(&data).map_err(|e| ArchiveError::Manifest(e.to_string()))?;
+            if parsed.version != ARCHIVE_VERSION {
+                return Err(ArchiveError::Manifest(format!(
+                    "unsupported archive version {}",
+                    parsed.version
+                )));
+            }
+            manifest = Some(parsed);
+            continue;
+        }
+
+        let Some(hex_id) = path.strip_prefix(CHUNK_DIR) else {
+            debug!(path = %path, "Skipping unknown archive entry");
+            continue;
+        };
+
+        let shard =
+            parse_chunk_id(hex_id).map(|id| (id, ShardHeader::strip(id, Bytes::from(data))));
+        let (id, header, data) = match shard {
+            Some((id, (header, data))) if ChunkId::from_data(&data) == id => (id, header, data),
+            _ => {
+                warn!(chunk_id = %hex_id, "Chunk failed integrity check, skipping");
+                summary.corrupted.push(hex_id.to_string());
+                continue;
+            }
+        };
+
+        if storage.exists(id)? {
+            summary.already_present += 1;
+        } else {
+            summary.bytes += data.len() as u64;
+            match header {
+                Some(header) => storage.put_shard(id, &header, data)?,
+                None => storage.put(id, data)?,
+            }
+            summary.imported += 1;
+        }
+        seen.insert(hex_id.to_string());
+        summary.chunk_ids.push(hex_id.to_string());
+    }
+
+    storage.flush()?;
+
+    if let Some(manifest) = manifest {
+        summary.missing = manifest
+            .chunks
+            .into_iter()
+            .map(|entry| entry.id)
+            .filter(|id| !seen.contains(id))
+            .collect();
+        summary.source_node_id = Some(manifest.node_id);
+    }
+
+    Ok(summary)
+}
+
+/// Append an in-memory file to the archive
+fn append_file<W: Write>(
+    archive: &mut tar::Builder<W>,
+    path: &str,
+    data: &[u8],
+) -> std::io::Result<()> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(data.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(chrono::Utc::now().timestamp().max(0) as u64);
+    header.set_cksum();
+    archive.append_data(&mut header, path, data)
+}
+
+/// Parse a hex-encoded chunk ID
+fn parse_chunk_id(hex_id: &str) -> Option<ChunkId> {
+    let bytes: [u8; 32] = hex::decode(hex_id).ok()?.try_into().ok()?;
+    Some(ChunkId::from_bytes(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cyxcloud_storage::MemoryBackend;
+
+    fn store(storage: &MemoryBackend, data: &[u8]) -> ChunkId {
+        let id = ChunkId::from_data(data);
+        storage.put(id, Bytes::copy_from_slice(data)).unwrap();
+        id
+    }
+
+    #[test]
+    fn test_export_import_roundtrip() {
+        let source = MemoryBackend::new();
+        let a = store(&source, b"chunk a");
+        let b = store(&source, b"chunk b");
+
+        let mut archive = Vec::new();
+        let exported = export_chunks(&source, "old-node", &mut archive).unwrap();
+        assert_eq!(exported.exported, 2);
+        assert!(exported.corrupted.is_empty());
+
+        let target = MemoryBackend::new();
+        store(&target, b"chunk a");
+        let imported = import_chunks(&target, archive.as_slice()).unwrap();
+
+        assert_eq!(imported.imported, 1);
+        assert_eq!(imported.already_present, 1);
+        assert!(imported.missing.is_empty());
+        assert_eq!(imported.source_node_id.as_deref(), Some("old-node"));
+        assert!(target.exists(a).unwrap());
+        assert!(target.exists(b).unwrap());
+
+        let adoption = imported.adoption_request().unwrap();
+        assert_eq!(adoption.from_node_id, "old-node");
+        assert_eq!(adoption.chunk_ids.len(), 2);
+    }
+
+    #[test]
+    fn test_roundtrip_keeps_shard_headers() {
+        use cyxcloud_storage::{RocksDbBackend, StorageConfig};
+
+        let dirs = [
+            tempfile::TempDir::new().unwrap(),
+            tempfile::TempDir::new().unwrap(),
+        ];
+        let open =
+            |dir: &tempfile::TempDir| RocksDbBackend::open(StorageConfig::new(dir.path())).unwrap();
+        let source = open(&dirs[0]);
+
+        let data = Bytes::from_static(b"data shard");
+        let id = ChunkId::from_data(&data);
+        let header = ShardHeader {
+            file_id: uuid::Uuid::new_v4(),
+            chunk_index: 1,
+            shard_index: 4,
+            is_parity: false,
+            original_size: 40,
+            hash: *id.as_bytes(),
+        };
+        source.put_shard(id, &header, data.clone()).unwrap();
+
+        let mut archive = Vec::new();
+        export_chunks(&source, "old-node", &mut archive).unwrap();
+
+        let target = open(&dirs[1]);
+        let imported = import_chunks(&target, archive.as_slice()).unwrap();
+        assert_eq!(imported.imported, 1);
+        assert!(imported.corrupted.is_empty());
+        assert_eq!(target.get_shard(id).unwrap(), Some((Some(header), data)));
+    }
+
+    #[test]
+    fn test_export_skips_corrupted_chunks() {
+        let source = MemoryBackend::new();
+        store(&source, b"good chunk");
+        let bad_id = ChunkId::from_data(b"original");
+        source.put(bad_id, Bytes::from_static(b"bit rot")).unwrap();
+
+        let mut archive = Vec::new();
+        let summary = export_chunks(&source, "node", &mut archive).unwrap();
+        assert_eq!(summary.exported, 1);
+        assert_eq!(summary.corrupted, vec![hex::encode(bad_id.as_bytes())]);
+    }
+
+    #[test]
+    fn test_import_rejects_tampered_chunk() {
+        let id = ChunkId::from_data(b"original");
+        let mut builder = tar::Builder::new(Vec::new());
+        append_file(
+            &mut builder,
+            &format!("{}{}", CHUNK_DIR, hex::encode(id.as_bytes())),
+            b"tampered",
+        )
+        .unwrap();
+        let archive = builder.into_inner().unwrap();
+
+        let target = MemoryBackend::new();
+        let summary = import_chunks(&target, archive.as_slice()).unwrap();
+        assert_eq!(summary.imported, 0);
+        assert_eq!(summary.corrupted.len(), 1);
+        assert!(!target.exists(id).unwrap());
+        assert!(summary.adoption_request().is_none());
+    }
+}
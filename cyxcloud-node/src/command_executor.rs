@@ -10,7 +10,6 @@ use cyxcloud_protocol::node::{
     DeleteChunkCommand, NodeCommand, RepairChunkCommand, TransferChunkCommand,
 };
 use cyxcloud_storage::backend::StorageBackendSync;
-use cyxcloud_storage::RocksDbBackend;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
@@ -38,7 +37,7 @@ pub enum CommandType {
 /// Executor for processing node commands
 pub struct CommandExecutor {
     node_id: String,
-    storage: Arc<RocksDbBackend>,
+    storage: Arc<dyn StorageBackendSync>,
     chunk_client: Arc<ChunkClient>,
     metrics: NodeMetrics,
     /// Channel for sending command results (for monitoring)
@@ -47,7 +46,11 @@ pub struct CommandExecutor {
 
 impl CommandExecutor {
     /// Create a new command executor
-    pub fn new(node_id: String, storage: Arc<RocksDbBackend>, metrics: NodeMetrics) -> Self {
+    pub fn new(
+        node_id: String,
+        storage: Arc<dyn StorageBackendSync>,
+        metrics: NodeMetrics,
+    ) -> Self {
         Self {
             node_id,
             storage,
@@ -60,7 +63,7 @@ impl CommandExecutor {
     /// Create with custom chunk client (for testing or custom configuration)
     pub fn with_chunk_client(
         node_id: String,
-        storage: Arc<RocksDbBackend>,
+        storage: Arc<dyn StorageBackendSync>,
         chunk_client: Arc<ChunkClient>,
         metrics: NodeMetrics,
     ) -> Self {
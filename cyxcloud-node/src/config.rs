@@ -2,10 +2,14 @@
 //!
 //! Supports loading from TOML files and environment variables.
 
+use cyxcloud_network::{AdmissionConfig, DiscoveryConfig, Multiaddr};
+use cyxcloud_storage::{RocksTuning, StorageProfile};
 use serde::{Deserialize, Serialize};
 use serde_json;
-use std::net::SocketAddr;
+use std::fmt;
+use std::net::{IpAddr, SocketAddr};
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 use thiserror::Error;
 
 /// Configuration errors
@@ -18,7 +22,95 @@ pub enum ConfigError {
     ParseError(#[from] toml::de::Error),
 
     #[error("Invalid configuration: {0}")]
-    ValidationError(String),
+    Invalid(ValidationReport),
+}
+
+/// How serious a configuration issue is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// The node refuses to start
+    Error,
+    /// The node starts, but the setting is likely a mistake
+    Warning,
+}
+
+/// A problem with one configuration setting
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigIssue {
+    pub severity: Severity,
+    /// Path of the setting, e.g. `network.grpc_port`
+    pub field: String,
+    pub message: String,
+}
+
+impl fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(f, "{}: {}: {}", severity, self.field, self.message)
+    }
+}
+
+/// Issues found while checking a configuration
+#[derive(Debug, Clone, Default)]
+pub struct ValidationReport {
+    pub issues: Vec<ConfigIssue>,
+}
+
+impl ValidationReport {
+    fn error(&mut self, field: impl Into<String>, message: impl Into<String>) {
+        self.push(Severity::Error, field.into(), message.into());
+    }
+
+    fn warning(&mut self, field: impl Into<String>, message: impl Into<String>) {
+        self.push(Severity::Warning, field.into(), message.into());
+    }
+
+    fn push(&mut self, severity: Severity, field: String, message: String) {
+        self.issues.push(ConfigIssue {
+            severity,
+            field,
+            message,
+        });
+    }
+
+    /// Append the issues of another report
+    pub fn merge(&mut self, other: ValidationReport) {
+        self.issues.extend(other.issues);
+    }
+
+    /// Issues that prevent startup
+    pub fn errors(&self) -> impl Iterator<Item = &ConfigIssue> {
+        self.issues.iter().filter(|i| i.severity == Severity::Error)
+    }
+
+    /// Issues that are reported but allow startup
+    pub fn warnings(&self) -> impl Iterator<Item = &ConfigIssue> {
+        self.issues
+            .iter()
+            .filter(|i| i.severity == Severity::Warning)
+    }
+
+    /// Check whether any issue prevents startup
+    pub fn has_errors(&self) -> bool {
+        self.errors().next().is_some()
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let errors: Vec<String> = self
+            .errors()
+            .map(|i| format!("{}: {}", i.field, i.message))
+            .collect();
+        if errors.is_empty() {
+            write!(f, "no errors")
+        } else {
+            write!(f, "{}", errors.join("; "))
+        }
+    }
 }
 
 /// Complete node configuration
@@ -44,10 +136,22 @@ pub struct NodeConfig {
     #[serde(default)]
     pub central: CentralServerSettings,
 
+    /// Disk health monitoring and self-drain thresholds
+    #[serde(default)]
+    pub disk_health: DiskHealthSettings,
+
+    /// Local self-scrub and compaction scheduling
+    #[serde(default)]
+    pub maintenance: MaintenanceSettings,
+
     /// CyxWiz API connection (for auth, machines, wallets)
     #[serde(default)]
     pub cyxwiz_api: CyxWizApiSettings,
 
+    /// Self-update from signed releases
+    #[serde(default)]
+    pub update: UpdateSettings,
+
     /// Blockchain (Solana) integration for staking and rewards
     #[serde(default)]
     pub blockchain: BlockchainSettings,
@@ -61,7 +165,10 @@ impl Default for NodeConfig {
             network: NetworkSettings::default(),
             metrics: MetricsSettings::default(),
             central: CentralServerSettings::default(),
+            disk_health: DiskHealthSettings::default(),
+            maintenance: MaintenanceSettings::default(),
             cyxwiz_api: CyxWizApiSettings::default(),
+            update: UpdateSettings::default(),
             blockchain: BlockchainSettings::default(),
         }
     }
@@ -69,13 +176,23 @@ impl Default for NodeConfig {
 
 impl NodeConfig {
     /// Load configuration from a TOML file
+    ///
+    /// Fails if the file sets invalid values (see [`Self::lint`]).
     pub fn from_file(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
-        let content = std::fs::read_to_string(path)?;
-        let config: NodeConfig = toml::from_str(&content)?;
-        config.validate()?;
+        let config = Self::read_file(path)?;
+        let report = config.lint();
+        if report.has_errors() {
+            return Err(ConfigError::Invalid(report));
+        }
         Ok(config)
     }
 
+    /// Parse a TOML configuration file without checking its values
+    pub fn read_file(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let content = std::fs::read_to_string(path)?;
+        Ok(toml::from_str(&content)?)
+    }
+
     /// Load configuration with fallback to defaults
     pub fn load_or_default(path: impl AsRef<Path>) -> Self {
         match Self::from_file(path) {
@@ -107,25 +224,288 @@ impl NodeConfig {
     }
 
     /// Validate the configuration
+    ///
+    /// Runs [`Self::lint`] and [`Self::check_environment`]. Warnings are
+    /// logged; any error fails validation with the full report.
     pub fn validate(&self) -> Result<(), ConfigError> {
-        // Validate storage path is writable
-        if !self.storage.data_dir.exists() {
-            std::fs::create_dir_all(&self.storage.data_dir).map_err(|e| {
-                ConfigError::ValidationError(format!(
-                    "Cannot create data directory {:?}: {}",
-                    self.storage.data_dir, e
-                ))
-            })?;
+        let mut report = self.lint();
+        report.merge(self.check_environment());
+
+        for issue in report.warnings() {
+            tracing::warn!(field = %issue.field, "{}", issue.message);
+        }
+        if report.has_errors() {
+            return Err(ConfigError::Invalid(report));
         }
+        Ok(())
+    }
+
+    /// Check setting values without touching the system
+    pub fn lint(&self) -> ValidationReport {
+        let mut report = ValidationReport::default();
+        let network = &self.network;
 
-        // Validate ports are sensible
-        if self.network.grpc_port == 0 {
-            return Err(ConfigError::ValidationError(
-                "gRPC port cannot be 0".to_string(),
-            ));
+        if self.node.rack == Some(0) {
+            report.error("node.rack", "racks are numbered from 1");
+        }
+        match (self.node.latitude, self.node.longitude) {
+            (Some(lat), Some(lon)) => {
+                if !(-90.0..=90.0).contains(&lat) {
+                    report.error(
+                        "node.latitude",
+                        format!("must be between -90 and 90, got {}", lat),
+                    );
+                }
+                if !(-180.0..=180.0).contains(&lon) {
+                    report.error(
+                        "node.longitude",
+                        format!("must be between -180 and 180, got {}", lon),
+                    );
+                }
+            }
+            (Some(_), None) => report.error("node.longitude", "required with node.latitude"),
+            (None, Some(_)) => report.error("node.latitude", "required with node.longitude"),
+            (None, None) => {}
         }
 
-        Ok(())
+        if network.bind_address.parse::<IpAddr>().is_err() {
+            report.error(
+                "network.bind_address",
+                format!("'{}' is not an IP address", network.bind_address),
+            );
+        }
+        if network.grpc_port == 0 {
+            report.error("network.grpc_port", "cannot be 0");
+        }
+        if network.p2p_port == 0 {
+            report.error("network.p2p_port", "cannot be 0");
+        } else if network.p2p_port == network.grpc_port {
+            report.error(
+                "network.p2p_port",
+                format!("conflicts with network.grpc_port ({})", network.grpc_port),
+            );
+        }
+        if self.metrics.enabled {
+            if self.metrics.port == 0 {
+                report.error("metrics.port", "cannot be 0");
+            } else if self.metrics.port == network.grpc_port {
+                report.error(
+                    "metrics.port",
+                    format!("conflicts with network.grpc_port ({})", network.grpc_port),
+                );
+            } else if self.metrics.port == network.p2p_port {
+                report.error(
+                    "metrics.port",
+                    format!("conflicts with network.p2p_port ({})", network.p2p_port),
+                );
+            }
+        }
+
+        for (i, peer) in network.bootstrap_peers.iter().enumerate() {
+            let field = format!("network.bootstrap_peers[{}]", i);
+            match peer.parse::<Multiaddr>() {
+                Ok(addr) if !addr.to_string().contains("/p2p/") => {
+                    report.warning(field, format!("'{}' has no /p2p/<peer id>, ignored", peer))
+                }
+                Ok(_) => {}
+                Err(e) => report.error(field, format!("'{}' is not a multiaddr: {}", peer, e)),
+            }
+        }
+
+        if network.enable_tls {
+            if network.tls_cert.is_none() {
+                report.error("network.tls_cert", "required when enable_tls is set");
+            }
+            if network.tls_key.is_none() {
+                report.error("network.tls_key", "required when enable_tls is set");
+            }
+        }
+        if network.tls_client_cert.is_some() != network.tls_client_key.is_some() {
+            report.error(
+                "network.tls_client_key",
+                "tls_client_cert and tls_client_key must be set together",
+            );
+        }
+        if network.cluster_token.is_none() {
+            report.warning(
+                "network.cluster_token",
+                "not set; node-to-node transfers and chunk deletes are not authenticated",
+            );
+        }
+        if network.max_message_size_mb == 0 {
+            report.error("network.max_message_size_mb", "cannot be 0");
+        }
+        if network.max_concurrent_reads == 0 {
+            report.error("network.max_concurrent_reads", "cannot be 0");
+        }
+        if network.max_concurrent_writes == 0 {
+            report.error("network.max_concurrent_writes", "cannot be 0");
+        }
+
+        if self.storage.max_capacity_gb == 0
+            && !(self.storage.auto_capacity_percent > 0.0
+                && self.storage.auto_capacity_percent <= 100.0)
+        {
+            report.error(
+                "storage.auto_capacity_percent",
+                format!(
+                    "must be above 0 and at most 100, got {}",
+                    self.storage.auto_capacity_percent
+                ),
+            );
+        }
+        if self.storage.capacity_check_secs == 0 {
+            report.error("storage.capacity_check_secs", "cannot be 0");
+        }
+        if let Err(e) = self.storage.profile.parse::<StorageProfile>() {
+            report.error("storage.profile", e.to_string());
+        }
+
+        if !matches!(
+            self.central.shutdown_status.as_str(),
+            "maintenance" | "offline"
+        ) {
+            report.error(
+                "central.shutdown_status",
+                format!(
+                    "must be 'maintenance' or 'offline', got '{}'",
+                    self.central.shutdown_status
+                ),
+            );
+        }
+        if self.central.heartbeat_interval_secs == 0 {
+            report.error("central.heartbeat_interval_secs", "cannot be 0");
+        }
+        if self.central.drain_poll_secs == 0 {
+            report.error("central.drain_poll_secs", "cannot be 0");
+        }
+
+        if self.disk_health.check_interval_secs == 0 {
+            report.error("disk_health.check_interval_secs", "cannot be 0");
+        }
+        if !(0.0..=100.0).contains(&self.disk_health.min_free_percent) {
+            report.error(
+                "disk_health.min_free_percent",
+                format!(
+                    "must be between 0 and 100, got {}",
+                    self.disk_health.min_free_percent
+                ),
+            );
+        }
+
+        if self.maintenance.scrub_chunks_per_sec == 0 {
+            report.error("maintenance.scrub_chunks_per_sec", "cannot be 0");
+        }
+        if self.maintenance.compaction_window_start_hour > 23 {
+            report.error(
+                "maintenance.compaction_window_start_hour",
+                "must be between 0 and 23",
+            );
+        }
+        if self.maintenance.compaction_window_end_hour > 23 {
+            report.error(
+                "maintenance.compaction_window_end_hour",
+                "must be between 0 and 23",
+            );
+        }
+        if self.maintenance.purge_batch_size == 0 {
+            report.error("maintenance.purge_batch_size", "cannot be 0");
+        }
+        if self.storage.deferred_delete && !self.maintenance.enabled {
+            report.warning(
+                "storage.deferred_delete",
+                "deleted chunks are never purged while maintenance is disabled",
+            );
+        }
+
+        if !matches!(self.update.channel.as_str(), "stable" | "beta") {
+            report.error(
+                "update.channel",
+                format!("must be 'stable' or 'beta', got '{}'", self.update.channel),
+            );
+        }
+        if !matches!(self.update.restart.as_str(), "supervisor" | "exec") {
+            report.error(
+                "update.restart",
+                format!(
+                    "must be 'supervisor' or 'exec', got '{}'",
+                    self.update.restart
+                ),
+            );
+        }
+        if self.update.check_interval_secs < 60 {
+            report.error("update.check_interval_secs", "must be at least 60");
+        }
+        if self.update.enabled {
+            match self.update.public_key.as_deref().map(hex::decode) {
+                None => report.error("update.public_key", "required when updates are enabled"),
+                Some(Ok(key)) if key.len() == 32 => {}
+                Some(_) => report.error(
+                    "update.public_key",
+                    "must be a hex-encoded 32-byte Ed25519 public key",
+                ),
+            }
+        }
+
+        if self.blockchain.auto_claim_interval_secs == 0 {
+            report.error("blockchain.auto_claim_interval_secs", "cannot be 0");
+        }
+        if self.blockchain.claim_max_attempts == 0 {
+            report.error("blockchain.claim_max_attempts", "cannot be 0");
+        }
+
+        report
+    }
+
+    /// Check the settings against the system: the data directory is created
+    /// if needed and must be writable and fit within the capacity, and
+    /// configured TLS files must exist
+    pub fn check_environment(&self) -> ValidationReport {
+        let mut report = ValidationReport::default();
+        let data_dir = &self.storage.data_dir;
+
+        if let Err(e) = std::fs::create_dir_all(data_dir) {
+            report.error(
+                "storage.data_dir",
+                format!("cannot create {}: {}", data_dir.display(), e),
+            );
+        } else if let Err(e) = write_test(data_dir) {
+            report.error(
+                "storage.data_dir",
+                format!("{} is not writable: {}", data_dir.display(), e),
+            );
+        } else if self.storage.max_capacity_gb > 0 {
+            let capacity = self.storage.max_capacity_gb * 1024 * 1024 * 1024;
+            let used = dir_size(data_dir);
+            if used > capacity {
+                report.error(
+                    "storage.max_capacity_gb",
+                    format!(
+                        "{} GB is less than the {:.1} GB already stored in {}",
+                        self.storage.max_capacity_gb,
+                        used as f64 / (1024.0 * 1024.0 * 1024.0),
+                        data_dir.display()
+                    ),
+                );
+            }
+        }
+
+        let tls_files = [
+            ("network.tls_cert", &self.network.tls_cert),
+            ("network.tls_key", &self.network.tls_key),
+            ("network.tls_ca_cert", &self.network.tls_ca_cert),
+            ("network.tls_client_cert", &self.network.tls_client_cert),
+            ("network.tls_client_key", &self.network.tls_client_key),
+        ];
+        for (field, path) in tls_files {
+            if let Some(path) = path {
+                if !path.is_file() {
+                    report.error(field, format!("{} does not exist", path.display()));
+                }
+            }
+        }
+
+        report
     }
 
     /// Override config with CLI arguments
@@ -149,6 +529,25 @@ impl NodeConfig {
             self.central.address = addr;
         }
 
+        // Additional gateway addresses for failover (comma-separated)
+        if let Ok(addrs) = std::env::var("CENTRAL_SERVER_ADDRS") {
+            self.central.fallback_addresses = addrs
+                .split(',')
+                .map(|a| a.trim().to_string())
+                .filter(|a| !a.is_empty())
+                .collect();
+        }
+
+        // Gateway DNS discovery name
+        if let Ok(name) = std::env::var("CENTRAL_DISCOVERY_DNS") {
+            self.central.discovery_dns = Some(name);
+        }
+
+        // Status reported on graceful shutdown
+        if let Ok(status) = std::env::var("NODE_SHUTDOWN_STATUS") {
+            self.central.shutdown_status = status;
+        }
+
         // Storage capacity override (in GB)
         if let Ok(capacity) = std::env::var("STORAGE_CAPACITY_GB") {
             if let Ok(gb) = capacity.parse::<u64>() {
@@ -156,6 +555,18 @@ impl NodeConfig {
             }
         }
 
+        // Share of the free disk space offered when no capacity is set
+        if let Ok(percent) = std::env::var("STORAGE_AUTO_CAPACITY_PERCENT") {
+            if let Ok(percent) = percent.parse::<f64>() {
+                self.storage.auto_capacity_percent = percent;
+            }
+        }
+
+        // RocksDB tuning preset override
+        if let Ok(profile) = std::env::var("STORAGE_PROFILE") {
+            self.storage.profile = profile;
+        }
+
         // Node ID override
         if let Ok(id) = std::env::var("NODE_ID") {
             self.node.id = id;
@@ -166,10 +577,16 @@ impl NodeConfig {
             self.node.name = name;
         }
 
-        // Node region override
+        // Node location overrides
         if let Ok(region) = std::env::var("NODE_REGION") {
             self.node.region = Some(region);
         }
+        if let Ok(zone) = std::env::var("NODE_ZONE").or_else(|_| std::env::var("NODE_DATACENTER")) {
+            self.node.zone = Some(zone);
+        }
+        if let Some(rack) = std::env::var("NODE_RACK").ok().and_then(|v| v.parse().ok()) {
+            self.node.rack = Some(rack);
+        }
 
         // Public address override (for Docker/cloud networking)
         if let Ok(addr) = std::env::var("PUBLIC_ADDRESS") {
@@ -196,8 +613,92 @@ impl NodeConfig {
             self.network.tls_client_key = Some(PathBuf::from(key));
         }
 
+        // Shared token for node-to-node transfers
+        if let Ok(token) = std::env::var("CYXCLOUD_CLUSTER_TOKEN") {
+            self.network.cluster_token = Some(token).filter(|t| !t.is_empty());
+        }
+        if let Ok(encrypt) = std::env::var("CYXCLOUD_ENCRYPT_TRANSFERS") {
+            self.network.encrypt_transfers = encrypt.to_lowercase() == "true" || encrypt == "1";
+        }
+
+        // P2P discovery
+        if let Ok(enabled) = std::env::var("P2P_ENABLED") {
+            self.network.enable_p2p = enabled.to_lowercase() == "true" || enabled == "1";
+        }
+
+        // Self-update (CYXCLOUD_AUTO_UPDATE=false is the fleet-wide kill switch)
+        if let Ok(enabled) = std::env::var("CYXCLOUD_AUTO_UPDATE") {
+            self.update.enabled = enabled.to_lowercase() == "true" || enabled == "1";
+        }
+        if let Ok(channel) = std::env::var("CYXCLOUD_UPDATE_CHANNEL") {
+            self.update.channel = channel;
+        }
+
         self
     }
+
+    /// Discovery settings: listen on the P2P port, bootstrap from the
+    /// configured peers, and keep the peer store in the data directory
+    pub fn discovery_config(&self) -> DiscoveryConfig {
+        let bind = &self.network.bind_address;
+        let port = self.network.p2p_port;
+        let protocol = if bind.parse::<IpAddr>().is_ok_and(|ip| ip.is_ipv6()) {
+            "ip6"
+        } else {
+            "ip4"
+        };
+
+        let mut discovery = DiscoveryConfig {
+            listen_addrs: [
+                format!("/{}/{}/udp/{}/quic-v1", protocol, bind, port),
+                format!("/{}/{}/tcp/{}", protocol, bind, port),
+            ]
+            .iter()
+            .filter_map(|addr| addr.parse().ok())
+            .collect(),
+            ..DiscoveryConfig::default()
+        }
+        .with_grpc_port(self.network.grpc_port)
+        .with_peer_store(self.storage.data_dir.join("peers"));
+
+        for peer in &self.network.bootstrap_peers {
+            if let Ok(addr) = peer.parse::<Multiaddr>() {
+                discovery.add_bootstrap_addr(addr);
+            }
+        }
+        discovery
+    }
+}
+
+/// Write, read back and remove a probe file in a directory
+fn write_test(dir: &Path) -> std::io::Result<()> {
+    let probe = dir.join(".cyxcloud-write-test");
+    let data = b"cyxcloud write test";
+    std::fs::write(&probe, data)?;
+    let read = std::fs::read(&probe);
+    std::fs::remove_file(&probe)?;
+    if read? != data {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "probe file read back differently",
+        ));
+    }
+    Ok(())
+}
+
+/// Total size of the files under a directory (unreadable entries count as 0)
+fn dir_size(dir: &Path) -> u64 {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .map(|entry| match entry.metadata() {
+            Ok(meta) if meta.is_dir() => dir_size(&entry.path()),
+            Ok(meta) => meta.len(),
+            Err(_) => 0,
+        })
+        .sum()
 }
 
 /// Node identity configuration
@@ -215,10 +716,23 @@ pub struct NodeIdentity {
     #[serde(default)]
     pub region: Option<String>,
 
-    /// Node zone within region
-    #[serde(default)]
+    /// Zone (datacenter) within the region
+    #[serde(default, alias = "datacenter")]
     pub zone: Option<String>,
 
+    /// Rack within the zone, numbered from 1. Placement keeps shards of a
+    /// chunk on different racks.
+    #[serde(default)]
+    pub rack: Option<u32>,
+
+    /// Latitude in degrees, for proximity-aware placement
+    #[serde(default)]
+    pub latitude: Option<f64>,
+
+    /// Longitude in degrees, for proximity-aware placement
+    #[serde(default)]
+    pub longitude: Option<f64>,
+
     /// Operator wallet address (Solana) for payments
     #[serde(default)]
     pub wallet_address: Option<String>,
@@ -231,6 +745,9 @@ impl Default for NodeIdentity {
             name: default_node_name(),
             region: None,
             zone: None,
+            rack: None,
+            latitude: None,
+            longitude: None,
             wallet_address: None,
         }
     }
@@ -274,10 +791,19 @@ pub struct StorageSettings {
     #[serde(default = "default_data_dir")]
     pub data_dir: PathBuf,
 
-    /// Maximum storage capacity in GB (0 = unlimited)
+    /// Maximum storage capacity in GB (0 = detect from the free disk space)
     #[serde(default)]
     pub max_capacity_gb: u64,
 
+    /// Capacity offered when `max_capacity_gb` is 0, in percent of the
+    /// data directory's free space plus the node's own data
+    #[serde(default = "default_auto_capacity_percent")]
+    pub auto_capacity_percent: f64,
+
+    /// Interval for re-evaluating the offered capacity, in seconds
+    #[serde(default = "default_capacity_check_secs")]
+    pub capacity_check_secs: u64,
+
     /// Enable LZ4 compression for stored chunks
     #[serde(default = "default_true")]
     pub compression: bool,
@@ -289,6 +815,40 @@ pub struct StorageSettings {
     /// Number of background compaction threads
     #[serde(default = "default_compaction_threads")]
     pub compaction_threads: usize,
+
+    /// RocksDB tuning preset: "balanced", "repair-heavy" or "read-heavy"
+    #[serde(default = "default_storage_profile")]
+    pub profile: String,
+
+    /// Memtable size per chunk column family in MB (overrides the profile)
+    #[serde(default)]
+    pub write_buffer_size_mb: Option<usize>,
+
+    /// Target SST file size in MB (overrides the profile)
+    #[serde(default)]
+    pub target_file_size_mb: Option<u64>,
+
+    /// Bloom filter bits per key, 0 disables (overrides the profile)
+    #[serde(default)]
+    pub bloom_filter_bits: Option<f64>,
+
+    /// Flush/compaction write limit in MB/s, 0 = unlimited (overrides the profile)
+    #[serde(default)]
+    pub rate_limit_mb_per_sec: Option<i64>,
+
+    /// Chunks up to this size in KB use the small-chunk column family
+    #[serde(default)]
+    pub small_chunk_threshold_kb: Option<usize>,
+
+    /// Chunks at least this size in MB are stored as files and served
+    /// memory-mapped instead of from RocksDB (0 = disabled)
+    #[serde(default)]
+    pub file_chunk_threshold_mb: usize,
+
+    /// Deletes only tombstone chunks; the maintenance scheduler purges the
+    /// data in batches while the node is idle
+    #[serde(default = "default_true")]
+    pub deferred_delete: bool,
 }
 
 impl Default for StorageSettings {
@@ -296,15 +856,27 @@ impl Default for StorageSettings {
         Self {
             data_dir: default_data_dir(),
             max_capacity_gb: 0,
+            auto_capacity_percent: default_auto_capacity_percent(),
+            capacity_check_secs: default_capacity_check_secs(),
             compression: true,
             cache_size_mb: 512,
             compaction_threads: 4,
+            profile: default_storage_profile(),
+            write_buffer_size_mb: None,
+            target_file_size_mb: None,
+            bloom_filter_bits: None,
+            rate_limit_mb_per_sec: None,
+            small_chunk_threshold_kb: None,
+            file_chunk_threshold_mb: 0,
+            deferred_delete: true,
         }
     }
 }
 
 impl StorageSettings {
     /// Convert to cyxcloud_storage::StorageConfig
+    ///
+    /// An unknown profile falls back to balanced; `validate` rejects it first.
     pub fn to_storage_config(&self) -> cyxcloud_storage::StorageConfig {
         cyxcloud_storage::StorageConfig {
             path: self.data_dir.clone(),
@@ -312,14 +884,55 @@ impl StorageSettings {
             compression: self.compression,
             cache_size: self.cache_size_mb * 1024 * 1024,
             compaction_threads: self.compaction_threads,
+            tuning: self.tuning(),
+            file_chunk_threshold: self.file_chunk_threshold_mb * 1024 * 1024,
+            deferred_delete: self.deferred_delete,
         }
     }
+
+    /// Profile preset with any explicit overrides applied
+    fn tuning(&self) -> RocksTuning {
+        let mut tuning = self
+            .profile
+            .parse::<StorageProfile>()
+            .unwrap_or_default()
+            .tuning();
+
+        if let Some(mb) = self.write_buffer_size_mb {
+            tuning.write_buffer_size = mb * 1024 * 1024;
+        }
+        if let Some(mb) = self.target_file_size_mb {
+            tuning.target_file_size = mb * 1024 * 1024;
+        }
+        if let Some(bits) = self.bloom_filter_bits {
+            tuning.bloom_filter_bits = bits;
+        }
+        if let Some(mb) = self.rate_limit_mb_per_sec {
+            tuning.rate_limit_bytes_per_sec = mb * 1024 * 1024;
+        }
+        if let Some(kb) = self.small_chunk_threshold_kb {
+            tuning.small_chunk_threshold = kb * 1024;
+        }
+        tuning
+    }
+}
+
+fn default_storage_profile() -> String {
+    StorageProfile::default().to_string()
 }
 
 fn default_data_dir() -> PathBuf {
     PathBuf::from("./data")
 }
 
+fn default_auto_capacity_percent() -> f64 {
+    80.0
+}
+
+fn default_capacity_check_secs() -> u64 {
+    300
+}
+
 fn default_cache_size() -> usize {
     512
 }
@@ -379,9 +992,45 @@ pub struct NetworkSettings {
     #[serde(default)]
     pub tls_client_key: Option<PathBuf>,
 
+    /// Run libp2p peer discovery on the P2P port
+    #[serde(default)]
+    pub enable_p2p: bool,
+
     /// Bootstrap peers for P2P discovery
     #[serde(default)]
     pub bootstrap_peers: Vec<String>,
+
+    /// Shared secret the gateway, nodes and the rebalancer present on
+    /// cluster-internal RPCs (WriteChunk, ReplicateChunk, DeleteChunk);
+    /// unchecked when unset
+    #[serde(default)]
+    pub cluster_token: Option<String>,
+
+    /// Encrypt every chunk this node pushes to other nodes, even when the
+    /// rebalancer does not ask to. Receiving encrypted chunks needs no
+    /// setting: every node has a transfer key.
+    #[serde(default)]
+    pub encrypt_transfers: bool,
+
+    /// Read RPCs (GetChunk, StreamChunks, VerifyChunk) served concurrently
+    #[serde(default = "default_max_concurrent_reads")]
+    pub max_concurrent_reads: usize,
+
+    /// Write RPCs (StoreChunk, WriteChunk, DeleteChunk) served concurrently
+    #[serde(default = "default_max_concurrent_writes")]
+    pub max_concurrent_writes: usize,
+
+    /// Read RPCs allowed to wait for a slot before new ones are rejected
+    #[serde(default = "default_max_queued_reads")]
+    pub max_queued_reads: usize,
+
+    /// Write RPCs allowed to wait for a slot before new ones are rejected
+    #[serde(default = "default_max_queued_writes")]
+    pub max_queued_writes: usize,
+
+    /// How long a queued RPC waits for a slot before it is rejected (ms)
+    #[serde(default = "default_admission_timeout_ms")]
+    pub admission_timeout_ms: u64,
 }
 
 impl Default for NetworkSettings {
@@ -398,7 +1047,15 @@ impl Default for NetworkSettings {
             tls_ca_cert: None,
             tls_client_cert: None,
             tls_client_key: None,
+            enable_p2p: false,
             bootstrap_peers: Vec::new(),
+            cluster_token: None,
+            encrypt_transfers: false,
+            max_concurrent_reads: default_max_concurrent_reads(),
+            max_concurrent_writes: default_max_concurrent_writes(),
+            max_queued_reads: default_max_queued_reads(),
+            max_queued_writes: default_max_queued_writes(),
+            admission_timeout_ms: default_admission_timeout_ms(),
         }
     }
 }
@@ -417,6 +1074,26 @@ impl NetworkSettings {
             .parse()
             .unwrap_or_else(|_| "0.0.0.0:4001".parse().unwrap())
     }
+
+    /// Admission limits for read RPCs on the gRPC server
+    pub fn read_limits(&self) -> AdmissionConfig {
+        AdmissionConfig {
+            max_concurrent: self.max_concurrent_reads,
+            max_queued: self.max_queued_reads,
+            queue_timeout: Duration::from_millis(self.admission_timeout_ms),
+            ..AdmissionConfig::reads()
+        }
+    }
+
+    /// Admission limits for write RPCs on the gRPC server
+    pub fn write_limits(&self) -> AdmissionConfig {
+        AdmissionConfig {
+            max_concurrent: self.max_concurrent_writes,
+            max_queued: self.max_queued_writes,
+            queue_timeout: Duration::from_millis(self.admission_timeout_ms),
+            ..AdmissionConfig::writes()
+        }
+    }
 }
 
 fn default_bind_addr() -> String {
@@ -435,6 +1112,26 @@ fn default_max_message_size() -> usize {
     64
 }
 
+fn default_max_concurrent_reads() -> usize {
+    128
+}
+
+fn default_max_concurrent_writes() -> usize {
+    32
+}
+
+fn default_max_queued_reads() -> usize {
+    256
+}
+
+fn default_max_queued_writes() -> usize {
+    64
+}
+
+fn default_admission_timeout_ms() -> u64 {
+    5000
+}
+
 /// Metrics and monitoring configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MetricsSettings {
@@ -500,6 +1197,38 @@ pub struct CentralServerSettings {
     /// Authentication token (if required)
     #[serde(default)]
     pub auth_token: Option<String>,
+
+    /// Additional gateway gRPC addresses used for failover
+    #[serde(default)]
+    pub fallback_addresses: Vec<String>,
+
+    /// DNS name (`host:port`) resolving to all gateways
+    ///
+    /// Every A/AAAA record becomes a gateway endpoint, using the scheme of
+    /// `address`. Re-resolved every `discovery_refresh_secs`.
+    #[serde(default)]
+    pub discovery_dns: Option<String>,
+
+    /// Gateway DNS discovery refresh interval in seconds
+    #[serde(default = "default_discovery_refresh")]
+    pub discovery_refresh_secs: u64,
+
+    /// Status reported to the gateway on graceful shutdown
+    /// (`maintenance` for planned restarts, `offline` to start repair sooner)
+    #[serde(default = "default_shutdown_status")]
+    pub shutdown_status: String,
+
+    /// Time allowed for in-flight requests and the final heartbeat on shutdown
+    #[serde(default = "default_shutdown_timeout")]
+    pub shutdown_timeout_secs: u64,
+
+    /// Longest time to wait for shard evacuation with `--drain-on-shutdown`
+    #[serde(default = "default_drain_timeout")]
+    pub drain_timeout_secs: u64,
+
+    /// How often to poll the gateway for evacuation progress while draining
+    #[serde(default = "default_drain_poll")]
+    pub drain_poll_secs: u64,
 }
 
 impl Default for CentralServerSettings {
@@ -510,7 +1239,27 @@ impl Default for CentralServerSettings {
             heartbeat_interval_secs: 30,
             connect_timeout_secs: 10,
             auth_token: None,
+            fallback_addresses: Vec::new(),
+            discovery_dns: None,
+            discovery_refresh_secs: 300,
+            shutdown_status: default_shutdown_status(),
+            shutdown_timeout_secs: 10,
+            drain_timeout_secs: 3600,
+            drain_poll_secs: 10,
+        }
+    }
+}
+
+impl CentralServerSettings {
+    /// All statically configured gateway addresses, primary first
+    pub fn gateway_addresses(&self) -> Vec<String> {
+        let mut addresses = vec![self.address.clone()];
+        for addr in &self.fallback_addresses {
+            if !addresses.contains(addr) {
+                addresses.push(addr.clone());
+            }
         }
+        addresses
     }
 }
 
@@ -526,6 +1275,210 @@ fn default_connect_timeout() -> u64 {
     10
 }
 
+fn default_discovery_refresh() -> u64 {
+    300
+}
+
+fn default_shutdown_status() -> String {
+    "maintenance".to_string()
+}
+
+fn default_shutdown_timeout() -> u64 {
+    10
+}
+
+fn default_drain_timeout() -> u64 {
+    3600
+}
+
+fn default_drain_poll() -> u64 {
+    10
+}
+
+/// Disk health monitoring configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiskHealthSettings {
+    /// Enable disk health sampling
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+
+    /// Sampling interval in seconds
+    #[serde(default = "default_disk_check_interval")]
+    pub check_interval_secs: u64,
+
+    /// Read SMART data with `smartctl` (skipped if it is not installed)
+    #[serde(default = "default_true")]
+    pub smart_enabled: bool,
+
+    /// Path to the `smartctl` binary
+    #[serde(default = "default_smartctl_path")]
+    pub smartctl_path: String,
+
+    /// Minimum free space on the data disk, in percent
+    #[serde(default = "default_min_free_percent")]
+    pub min_free_percent: f64,
+
+    /// Maximum kernel I/O errors on the data disk
+    #[serde(default = "default_max_io_errors")]
+    pub max_io_errors: u64,
+
+    /// Maximum SMART reallocated sectors
+    #[serde(default = "default_max_reallocated_sectors")]
+    pub max_reallocated_sectors: u64,
+
+    /// Maximum SMART pending plus uncorrectable sectors
+    #[serde(default = "default_max_pending_sectors")]
+    pub max_pending_sectors: u64,
+
+    /// Maximum NVMe media errors
+    #[serde(default = "default_max_media_errors")]
+    pub max_media_errors: u64,
+
+    /// Ask the gateway to drain this node when a threshold is crossed
+    #[serde(default = "default_true")]
+    pub self_drain: bool,
+}
+
+impl Default for DiskHealthSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            check_interval_secs: default_disk_check_interval(),
+            smart_enabled: true,
+            smartctl_path: default_smartctl_path(),
+            min_free_percent: default_min_free_percent(),
+            max_io_errors: default_max_io_errors(),
+            max_reallocated_sectors: default_max_reallocated_sectors(),
+            max_pending_sectors: default_max_pending_sectors(),
+            max_media_errors: default_max_media_errors(),
+            self_drain: true,
+        }
+    }
+}
+
+/// Node-local storage maintenance
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MaintenanceSettings {
+    /// Enable the maintenance scheduler
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+
+    /// Hours between the starts of two full self-scrub passes
+    #[serde(default = "default_scrub_interval_hours")]
+    pub scrub_interval_hours: u64,
+
+    /// Chunks verified per second while scrubbing
+    #[serde(default = "default_scrub_chunks_per_sec")]
+    pub scrub_chunks_per_sec: u32,
+
+    /// Delete local copies that fail the scrub (they are repaired elsewhere)
+    #[serde(default = "default_true")]
+    pub delete_damaged: bool,
+
+    /// Start of the low-traffic compaction window (UTC hour, 0-23)
+    #[serde(default = "default_compaction_window_start")]
+    pub compaction_window_start_hour: u32,
+
+    /// End of the low-traffic compaction window (UTC hour, exclusive).
+    /// May be lower than the start for windows that span midnight.
+    #[serde(default = "default_compaction_window_end")]
+    pub compaction_window_end_hour: u32,
+
+    /// Minimum hours between manual compactions
+    #[serde(default = "default_compaction_interval_hours")]
+    pub compaction_interval_hours: u64,
+
+    /// Skip compaction while client traffic is above this many ops/sec
+    #[serde(default = "default_compaction_max_ops_per_sec")]
+    pub compaction_max_ops_per_sec: u64,
+
+    /// Deleted chunks physically removed per purge batch
+    #[serde(default = "default_purge_batch_size")]
+    pub purge_batch_size: usize,
+
+    /// Purge deleted chunks older than this many hours even while the node
+    /// is busy
+    #[serde(default = "default_purge_max_delay_hours")]
+    pub purge_max_delay_hours: u64,
+}
+
+impl Default for MaintenanceSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            scrub_interval_hours: default_scrub_interval_hours(),
+            scrub_chunks_per_sec: default_scrub_chunks_per_sec(),
+            delete_damaged: true,
+            compaction_window_start_hour: default_compaction_window_start(),
+            compaction_window_end_hour: default_compaction_window_end(),
+            compaction_interval_hours: default_compaction_interval_hours(),
+            compaction_max_ops_per_sec: default_compaction_max_ops_per_sec(),
+            purge_batch_size: default_purge_batch_size(),
+            purge_max_delay_hours: default_purge_max_delay_hours(),
+        }
+    }
+}
+
+fn default_scrub_interval_hours() -> u64 {
+    24
+}
+
+fn default_scrub_chunks_per_sec() -> u32 {
+    50
+}
+
+fn default_compaction_window_start() -> u32 {
+    2
+}
+
+fn default_compaction_window_end() -> u32 {
+    5
+}
+
+fn default_compaction_interval_hours() -> u64 {
+    24
+}
+
+fn default_compaction_max_ops_per_sec() -> u64 {
+    50
+}
+
+fn default_purge_batch_size() -> usize {
+    256
+}
+
+fn default_purge_max_delay_hours() -> u64 {
+    24
+}
+
+fn default_disk_check_interval() -> u64 {
+    300
+}
+
+fn default_smartctl_path() -> String {
+    "smartctl".to_string()
+}
+
+fn default_min_free_percent() -> f64 {
+    2.0
+}
+
+fn default_max_io_errors() -> u64 {
+    10
+}
+
+fn default_max_reallocated_sectors() -> u64 {
+    100
+}
+
+fn default_max_pending_sectors() -> u64 {
+    10
+}
+
+fn default_max_media_errors() -> u64 {
+    0
+}
+
 /// CyxWiz API connection configuration (for auth, machines, wallets)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CyxWizApiSettings {
@@ -602,6 +1555,76 @@ fn default_cyxwiz_api_url() -> String {
     "http://localhost:3002".to_string()
 }
 
+/// Self-update configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateSettings {
+    /// Check for and install new releases. Off by default; turning it off is
+    /// the kill switch that stops updates on this node
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Release channel: "stable" or "beta"
+    #[serde(default = "default_update_channel")]
+    pub channel: String,
+
+    /// Release manifest URL, `{channel}` is replaced by the channel
+    /// (default: the CyxWiz API's release endpoint)
+    #[serde(default)]
+    pub manifest_url: Option<String>,
+
+    /// Hex Ed25519 public key releases must be signed with
+    #[serde(default)]
+    pub public_key: Option<String>,
+
+    /// Seconds between manifest checks
+    #[serde(default = "default_update_check_interval")]
+    pub check_interval_secs: u64,
+
+    /// How the new binary is started after installing it: "supervisor" exits
+    /// with status 75 for systemd (or another supervisor) to restart the
+    /// node, "exec" replaces the running process
+    #[serde(default = "default_update_restart")]
+    pub restart: String,
+}
+
+impl Default for UpdateSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            channel: default_update_channel(),
+            manifest_url: None,
+            public_key: None,
+            check_interval_secs: default_update_check_interval(),
+            restart: default_update_restart(),
+        }
+    }
+}
+
+impl UpdateSettings {
+    /// Manifest URL of the configured channel
+    pub fn manifest_url(&self, cyxwiz_api: &CyxWizApiSettings) -> String {
+        let template = self.manifest_url.clone().unwrap_or_else(|| {
+            format!(
+                "{}/api/releases/cyxcloud-node/{{channel}}",
+                cyxwiz_api.base_url.trim_end_matches('/')
+            )
+        });
+        template.replace("{channel}", &self.channel)
+    }
+}
+
+fn default_update_channel() -> String {
+    "stable".to_string()
+}
+
+fn default_update_check_interval() -> u64 {
+    6 * 3600
+}
+
+fn default_update_restart() -> String {
+    "supervisor".to_string()
+}
+
 /// Get the standard CyxCloud config directory (~/.cyxcloud/)
 pub fn cyxcloud_config_dir() -> std::path::PathBuf {
     dirs::home_dir()
@@ -726,6 +1749,14 @@ pub struct BlockchainSettings {
     /// Enable automatic reward claiming
     #[serde(default)]
     pub auto_claim_rewards: bool,
+
+    /// How often to check for claimable rewards in seconds
+    #[serde(default = "default_auto_claim_interval")]
+    pub auto_claim_interval_secs: u64,
+
+    /// Attempts per reward claim before giving up (RPC failures are retried)
+    #[serde(default = "default_claim_max_attempts")]
+    pub claim_max_attempts: u32,
 }
 
 impl Default for BlockchainSettings {
@@ -737,6 +1768,8 @@ impl Default for BlockchainSettings {
             heartbeat_enabled: false,
             heartbeat_interval_secs: 60,
             auto_claim_rewards: false,
+            auto_claim_interval_secs: default_auto_claim_interval(),
+            claim_max_attempts: default_claim_max_attempts(),
         }
     }
 }
@@ -749,6 +1782,14 @@ fn default_blockchain_heartbeat_interval() -> u64 {
     60
 }
 
+fn default_auto_claim_interval() -> u64 {
+    3600
+}
+
+fn default_claim_max_attempts() -> u32 {
+    5
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -768,6 +1809,8 @@ mod tests {
             [node]
             name = "test-node"
             region = "us-west"
+            datacenter = "us-west-2a"
+            rack = 4
 
             [storage]
             max_capacity_gb = 100
@@ -785,6 +1828,8 @@ mod tests {
         let config: NodeConfig = toml::from_str(toml).unwrap();
         assert_eq!(config.node.name, "test-node");
         assert_eq!(config.node.region, Some("us-west".to_string()));
+        assert_eq!(config.node.zone, Some("us-west-2a".to_string()));
+        assert_eq!(config.node.rack, Some(4));
         assert_eq!(config.storage.max_capacity_gb, 100);
         assert_eq!(config.network.grpc_port, 9000);
     }
@@ -796,6 +1841,166 @@ mod tests {
         config.storage.data_dir = temp_dir.path().to_path_buf();
 
         assert!(config.validate().is_ok());
+
+        config.central.shutdown_status = "gone".to_string();
+        assert!(config.validate().is_err());
+
+        config.central.shutdown_status = "offline".to_string();
+        config.central.drain_poll_secs = 0;
+        assert!(config.validate().is_err());
+
+        config.central.drain_poll_secs = 10;
+        config.network.max_concurrent_writes = 0;
+        assert!(config.validate().is_err());
+
+        config.network.max_concurrent_writes = 32;
+        config.node.rack = Some(0);
+        assert!(config.validate().is_err());
+
+        config.node.rack = Some(3);
+        config.node.latitude = Some(52.5);
+        assert!(config.validate().is_err());
+
+        config.node.longitude = Some(13.4);
+        assert!(config.validate().is_ok());
+
+        config.node.latitude = Some(152.5);
+        assert!(config.validate().is_err());
+
+        config.node.latitude = Some(52.5);
+        config.maintenance.compaction_window_end_hour = 24;
+        assert!(config.validate().is_err());
+
+        config.maintenance.compaction_window_end_hour = 5;
+        config.maintenance.purge_batch_size = 0;
+        assert!(config.validate().is_err());
+
+        config.maintenance.purge_batch_size = 256;
+        config.storage.profile = "fastest".to_string();
+        assert!(config.validate().is_err());
+
+        config.storage.profile = "read-heavy".to_string();
+        config.disk_health.min_free_percent = 150.0;
+        assert!(config.validate().is_err());
+
+        config.disk_health.min_free_percent = 10.0;
+        config.blockchain.claim_max_attempts = 0;
+        assert!(config.validate().is_err());
+
+        config.blockchain.claim_max_attempts = 3;
+        config.update.enabled = true;
+        assert!(config.validate().is_err());
+
+        config.update.public_key = Some("ab".repeat(32));
+        assert!(config.validate().is_ok());
+
+        config.update.channel = "nightly".to_string();
+        assert!(config.validate().is_err());
+
+        config.update.channel = "beta".to_string();
+        config.storage.auto_capacity_percent = 0.0;
+        assert!(config.validate().is_err());
+
+        // Only used when no capacity is configured
+        config.storage.max_capacity_gb = 100;
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_validation_report_fields() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = NodeConfig::default();
+        config.storage.data_dir = temp_dir.path().to_path_buf();
+        config.network.p2p_port = config.network.grpc_port;
+        config.network.bootstrap_peers = vec![
+            "/ip4/10.0.0.1/tcp/4001".to_string(),
+            "not-an-address".to_string(),
+        ];
+
+        let report = config.lint();
+        let errors: Vec<&str> = report.errors().map(|i| i.field.as_str()).collect();
+        assert_eq!(
+            errors,
+            vec!["network.p2p_port", "network.bootstrap_peers[1]"]
+        );
+        assert!(report
+            .warnings()
+            .any(|i| i.field == "network.bootstrap_peers[0]"));
+
+        match config.validate() {
+            Err(ConfigError::Invalid(report)) => assert_eq!(report.errors().count(), 2),
+            other => panic!("expected invalid config, got {:?}", other),
+        }
+
+        // Warnings alone do not fail validation
+        config.network.p2p_port = config.network.grpc_port + 1;
+        config.network.bootstrap_peers.truncate(1);
+        let report = config.lint();
+        assert!(!report.has_errors());
+        assert!(report.warnings().count() > 0);
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_environment_checks() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = NodeConfig::default();
+        config.storage.data_dir = temp_dir.path().join("data");
+
+        // The data directory is created and passes the write test
+        assert!(!config.check_environment().has_errors());
+        assert!(config.storage.data_dir.is_dir());
+        let data_dir = config.storage.data_dir.clone();
+        assert!(!data_dir.join(".cyxcloud-write-test").exists());
+
+        std::fs::write(data_dir.join("blob"), vec![0u8; 4096]).unwrap();
+        assert_eq!(dir_size(&data_dir), 4096);
+
+        config.network.tls_cert = Some(temp_dir.path().join("missing.pem"));
+        let report = config.check_environment();
+        let errors: Vec<&str> = report.errors().map(|i| i.field.as_str()).collect();
+        assert_eq!(errors, vec!["network.tls_cert"]);
+    }
+
+    #[test]
+    fn test_discovery_config() {
+        let mut config = NodeConfig::default();
+        config.network.p2p_port = 4100;
+        config.network.bootstrap_peers = vec![
+            "/ip4/10.0.0.1/tcp/4001".to_string(),
+            "/ip4/10.0.0.2/tcp/4001/p2p/QmNnooDu7bfjPFoTZYxMNLWUQJyrVwtbZg5gBMjTezGAJN".to_string(),
+        ];
+
+        let discovery = config.discovery_config();
+        assert_eq!(discovery.listen_addrs.len(), 2);
+        assert_eq!(
+            discovery.listen_addrs[1].to_string(),
+            "/ip4/0.0.0.0/tcp/4100"
+        );
+        assert_eq!(discovery.bootstrap_peers.len(), 1);
+        assert_eq!(
+            discovery.registry.path,
+            Some(config.storage.data_dir.join("peers"))
+        );
+    }
+
+    #[test]
+    fn test_storage_profile_overrides() {
+        let toml = r#"
+            [storage]
+            profile = "repair-heavy"
+            bloom_filter_bits = 0.0
+            small_chunk_threshold_kb = 128
+        "#;
+
+        let config: NodeConfig = toml::from_str(toml).unwrap();
+        let tuning = config.storage.to_storage_config().tuning;
+        let preset = StorageProfile::RepairHeavy.tuning();
+
+        assert_eq!(tuning.write_buffer_size, preset.write_buffer_size);
+        assert_eq!(tuning.bloom_filter_bits, 0.0);
+        assert_eq!(tuning.small_chunk_threshold, 128 * 1024);
+        assert_eq!(config.storage.to_storage_config().file_chunk_threshold, 0);
     }
 
     #[test]
@@ -820,4 +2025,40 @@ mod tests {
         let no_creds = CyxWizApiSettings::default();
         assert!(!no_creds.has_credentials());
     }
+
+    #[test]
+    fn test_update_manifest_url() {
+        let api = CyxWizApiSettings {
+            base_url: "https://api.cyxwiz.com/".to_string(),
+            ..Default::default()
+        };
+        let mut update = UpdateSettings::default();
+        assert_eq!(
+            update.manifest_url(&api),
+            "https://api.cyxwiz.com/api/releases/cyxcloud-node/stable"
+        );
+
+        update.channel = "beta".to_string();
+        update.manifest_url = Some("https://gw.example.com/releases/{channel}.json".to_string());
+        assert_eq!(
+            update.manifest_url(&api),
+            "https://gw.example.com/releases/beta.json"
+        );
+    }
+
+    #[test]
+    fn test_gateway_addresses() {
+        let toml = r#"
+            [central]
+            address = "http://gw1:50052"
+            fallback_addresses = ["http://gw2:50052", "http://gw1:50052"]
+        "#;
+
+        let config: NodeConfig = toml::from_str(toml).unwrap();
+        assert_eq!(
+            config.central.gateway_addresses(),
+            vec!["http://gw1:50052", "http://gw2:50052"]
+        );
+        assert_eq!(config.central.discovery_refresh_secs, 300);
+    }
 }
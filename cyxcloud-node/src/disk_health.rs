@@ -0,0 +1,337 @@
+//! Disk health sampling for CyxCloud storage node
+//!
+//! Samples the disk holding the data directory:
+//! - free space (all platforms, via sysinfo)
+//! - kernel I/O error counter (Linux, `/sys/block/<dev>/device/ioerr_cnt`)
+//! - SMART status and attributes (when `smartctl` is installed)
+//!
+//! Samples are checked against the `[disk_health]` thresholds; a failing disk
+//! makes the node request a drain so its shards are moved before data is lost.
+
+use crate::config::DiskHealthSettings;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use sysinfo::Disks;
+use tracing::debug;
+
+/// SMART attribute: reallocated sectors count
+const SMART_REALLOCATED_SECTORS: u32 = 5;
+
+/// SMART attribute: current pending sectors
+const SMART_PENDING_SECTORS: u32 = 197;
+
+/// SMART attribute: offline uncorrectable sectors
+const SMART_UNCORRECTABLE_SECTORS: u32 = 198;
+
+/// SMART data read from the disk
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SmartData {
+    /// Overall self-assessment passed
+    pub passed: bool,
+    /// Reallocated sectors (ATA attribute 5)
+    pub reallocated_sectors: u64,
+    /// Pending plus offline uncorrectable sectors (ATA attributes 197/198)
+    pub pending_sectors: u64,
+    /// Media and data integrity errors (NVMe)
+    pub media_errors: u64,
+}
+
+/// One disk health sample
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DiskHealth {
+    /// Block device backing the data directory (e.g. `/dev/sda`), if known
+    pub device: Option<String>,
+    /// Free bytes on the filesystem
+    pub free_bytes: u64,
+    /// Total bytes on the filesystem
+    pub total_bytes: u64,
+    /// I/O errors reported by the kernel for the device
+    pub io_errors: Option<u64>,
+    /// SMART data, if `smartctl` is available and supports the device
+    pub smart: Option<SmartData>,
+}
+
+impl DiskHealth {
+    /// Free space as a percentage of the total
+    pub fn free_percent(&self) -> f64 {
+        if self.total_bytes == 0 {
+            return 100.0;
+        }
+        self.free_bytes as f64 / self.total_bytes as f64 * 100.0
+    }
+
+    /// Thresholds crossed by this sample, as human readable problems
+    pub fn problems(&self, settings: &DiskHealthSettings) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        if self.total_bytes > 0 && self.free_percent() < settings.min_free_percent {
+            problems.push(format!(
+                "free space {:.1}% below {:.1}%",
+                self.free_percent(),
+                settings.min_free_percent
+            ));
+        }
+        if let Some(errors) = self.io_errors {
+            if errors > settings.max_io_errors {
+                problems.push(format!(
+                    "{} I/O errors (max {})",
+                    errors, settings.max_io_errors
+                ));
+            }
+        }
+        if let Some(ref smart) = self.smart {
+            if !smart.passed {
+                problems.push("SMART self-assessment failed".to_string());
+            }
+            if smart.reallocated_sectors > settings.max_reallocated_sectors {
+                problems.push(format!(
+                    "{} reallocated sectors (max {})",
+                    smart.reallocated_sectors, settings.max_reallocated_sectors
+                ));
+            }
+            if smart.pending_sectors > settings.max_pending_sectors {
+                problems.push(format!(
+                    "{} pending sectors (max {})",
+                    smart.pending_sectors, settings.max_pending_sectors
+                ));
+            }
+            if smart.media_errors > settings.max_media_errors {
+                problems.push(format!(
+                    "{} media errors (max {})",
+                    smart.media_errors, settings.max_media_errors
+                ));
+            }
+        }
+
+        problems
+    }
+}
+
+/// Samples the health of the disk holding the data directory
+pub struct DiskHealthSampler {
+    data_dir: PathBuf,
+    settings: DiskHealthSettings,
+    disks: Disks,
+}
+
+impl DiskHealthSampler {
+    /// Create a sampler for the disk holding `data_dir`
+    pub fn new(data_dir: PathBuf, settings: DiskHealthSettings) -> Self {
+        Self {
+            data_dir,
+            settings,
+            disks: Disks::new_with_refreshed_list(),
+        }
+    }
+
+    /// Thresholds this sampler is configured with
+    pub fn settings(&self) -> &DiskHealthSettings {
+        &self.settings
+    }
+
+    /// Take a sample
+    pub async fn sample(&mut self) -> DiskHealth {
+        self.disks.refresh_list();
+
+        let data_dir = self
+            .data_dir
+            .canonicalize()
+            .unwrap_or_else(|_| self.data_dir.clone());
+
+        let mut health = DiskHealth::default();
+
+        // The disk whose mount point is the longest prefix of the data dir
+        if let Some(disk) = self
+            .disks
+            .iter()
+            .filter(|d| data_dir.starts_with(d.mount_point()))
+            .max_by_key(|d| d.mount_point().as_os_str().len())
+        {
+            health.free_bytes = disk.available_space();
+            health.total_bytes = disk.total_space();
+        }
+
+        if let Some(device) = block_device(&data_dir) {
+            health.io_errors = read_io_errors(&device);
+            health.device = Some(format!("/dev/{}", device));
+        }
+
+        if self.settings.smart_enabled {
+            if let Some(ref device) = health.device {
+                health.smart = read_smart(&self.settings.smartctl_path, device).await;
+            }
+        }
+
+        health
+    }
+}
+
+/// Name of the whole block device (e.g. `sda`, `nvme0n1`) holding `path`
+#[cfg(target_os = "linux")]
+fn block_device(path: &Path) -> Option<String> {
+    use std::os::unix::fs::MetadataExt;
+
+    let dev = std::fs::metadata(path).ok()?.dev();
+    let major = ((dev >> 8) & 0xfff) | ((dev >> 32) & !0xfff);
+    let minor = (dev & 0xff) | ((dev >> 12) & !0xff);
+
+    // /sys/dev/block/MAJ:MIN links to the partition or the whole device
+    let sys_path = std::fs::canonicalize(format!("/sys/dev/block/{}:{}", major, minor)).ok()?;
+    let sys_path = if sys_path.join("partition").exists() {
+        sys_path.parent()?.to_path_buf()
+    } else {
+        sys_path
+    };
+
+    sys_path.file_name()?.to_str().map(str::to_string)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn block_device(_path: &Path) -> Option<String> {
+    None
+}
+
+/// Kernel I/O error counter for a SCSI/SATA device (hex encoded in sysfs)
+fn read_io_errors(device: &str) -> Option<u64> {
+    let raw = std::fs::read_to_string(format!("/sys/block/{}/device/ioerr_cnt", device)).ok()?;
+    let raw = raw.trim();
+    u64::from_str_radix(raw.trim_start_matches("0x"), 16).ok()
+}
+
+/// Read SMART data with `smartctl --json`
+async fn read_smart(smartctl: &str, device: &str) -> Option<SmartData> {
+    let output = tokio::process::Command::new(smartctl)
+        .args(["--json", "-H", "-A", device])
+        .output()
+        .await;
+
+    match output {
+        // smartctl uses exit status bits for disk problems, so parse regardless
+        Ok(output) => parse_smartctl_json(&output.stdout),
+        Err(e) => {
+            debug!(error = %e, smartctl = %smartctl, "smartctl not available");
+            None
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct SmartctlOutput {
+    smart_status: Option<SmartctlStatus>,
+    ata_smart_attributes: Option<SmartctlAttributes>,
+    nvme_smart_health_information_log: Option<SmartctlNvmeLog>,
+}
+
+#[derive(Deserialize)]
+struct SmartctlStatus {
+    passed: bool,
+}
+
+#[derive(Deserialize)]
+struct SmartctlAttributes {
+    table: Vec<SmartctlAttribute>,
+}
+
+#[derive(Deserialize)]
+struct SmartctlAttribute {
+    id: u32,
+    raw: SmartctlRaw,
+}
+
+#[derive(Deserialize)]
+struct SmartctlRaw {
+    value: u64,
+}
+
+#[derive(Deserialize)]
+struct SmartctlNvmeLog {
+    #[serde(default)]
+    media_errors: u64,
+}
+
+/// Parse `smartctl --json -H -A` output
+fn parse_smartctl_json(json: &[u8]) -> Option<SmartData> {
+    let output: SmartctlOutput = serde_json::from_slice(json).ok()?;
+    let status = output.smart_status?;
+
+    let mut smart = SmartData {
+        passed: status.passed,
+        ..Default::default()
+    };
+
+    if let Some(attributes) = output.ata_smart_attributes {
+        for attribute in attributes.table {
+            match attribute.id {
+                SMART_REALLOCATED_SECTORS => smart.reallocated_sectors = attribute.raw.value,
+                SMART_PENDING_SECTORS | SMART_UNCORRECTABLE_SECTORS => {
+                    smart.pending_sectors += attribute.raw.value
+                }
+                _ => {}
+            }
+        }
+    }
+    if let Some(log) = output.nvme_smart_health_information_log {
+        smart.media_errors = log.media_errors;
+    }
+
+    Some(smart)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_smartctl_ata() {
+        let json = br#"{
+            "smart_status": {"passed": true},
+            "ata_smart_attributes": {"table": [
+                {"id": 5, "name": "Reallocated_Sector_Ct", "raw": {"value": 12}},
+                {"id": 9, "name": "Power_On_Hours", "raw": {"value": 40000}},
+                {"id": 197, "name": "Current_Pending_Sector", "raw": {"value": 3}},
+                {"id": 198, "name": "Offline_Uncorrectable", "raw": {"value": 1}}
+            ]}
+        }"#;
+
+        let smart = parse_smartctl_json(json).unwrap();
+        assert!(smart.passed);
+        assert_eq!(smart.reallocated_sectors, 12);
+        assert_eq!(smart.pending_sectors, 4);
+        assert_eq!(smart.media_errors, 0);
+    }
+
+    #[test]
+    fn test_parse_smartctl_nvme_and_garbage() {
+        let json = br#"{
+            "smart_status": {"passed": false},
+            "nvme_smart_health_information_log": {"media_errors": 7}
+        }"#;
+        let smart = parse_smartctl_json(json).unwrap();
+        assert!(!smart.passed);
+        assert_eq!(smart.media_errors, 7);
+
+        assert!(parse_smartctl_json(b"not json").is_none());
+        assert!(parse_smartctl_json(br#"{"smartctl": {}}"#).is_none());
+    }
+
+    #[test]
+    fn test_disk_health_problems() {
+        let settings = DiskHealthSettings::default();
+        let mut health = DiskHealth {
+            free_bytes: 50,
+            total_bytes: 100,
+            io_errors: Some(0),
+            smart: Some(SmartData {
+                passed: true,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert!(health.problems(&settings).is_empty());
+
+        health.free_bytes = 1;
+        health.io_errors = Some(settings.max_io_errors + 1);
+        health.smart.as_mut().unwrap().passed = false;
+        assert_eq!(health.problems(&settings).len(), 3);
+    }
+}
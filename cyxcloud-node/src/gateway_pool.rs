@@ -0,0 +1,289 @@
+//! Gateway endpoint pool for control-plane failover
+//!
+//! Nodes can be configured with several gateway gRPC endpoints, plus a DNS
+//! name that resolves to all gateways. The pool sticks to one healthy
+//! gateway and fails over to the next one when registration or heartbeats
+//! fail. Failed gateways are retried after an exponential backoff.
+
+use crate::config::CentralServerSettings;
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+
+/// Base backoff after a gateway failure
+const BASE_BACKOFF: Duration = Duration::from_secs(5);
+
+/// Maximum backoff for a repeatedly failing gateway
+const MAX_BACKOFF: Duration = Duration::from_secs(300);
+
+/// A single gateway endpoint and its health
+#[derive(Debug, Clone)]
+pub struct GatewayEndpoint {
+    /// gRPC URL (e.g. `http://gateway-1:50052`)
+    pub address: String,
+    /// Consecutive failures since the last success
+    pub consecutive_failures: u32,
+    /// Endpoint is skipped until this instant
+    pub backoff_until: Option<Instant>,
+    /// Endpoint was found through DNS discovery (replaced on refresh)
+    pub discovered: bool,
+}
+
+impl GatewayEndpoint {
+    fn new(address: String, discovered: bool) -> Self {
+        Self {
+            address,
+            consecutive_failures: 0,
+            backoff_until: None,
+            discovered,
+        }
+    }
+
+    /// Whether the endpoint can be used at `now`
+    pub fn is_available(&self, now: Instant) -> bool {
+        self.backoff_until.map_or(true, |until| now >= until)
+    }
+}
+
+/// Pool of gateway endpoints with sticky selection and failover
+#[derive(Debug)]
+pub struct GatewayPool {
+    endpoints: Vec<GatewayEndpoint>,
+    current: usize,
+    discovery_dns: Option<String>,
+    scheme: &'static str,
+}
+
+impl GatewayPool {
+    /// Build the pool from the central server settings
+    pub fn from_settings(settings: &CentralServerSettings) -> Self {
+        let endpoints: Vec<_> = settings
+            .gateway_addresses()
+            .into_iter()
+            .map(|addr| GatewayEndpoint::new(addr, false))
+            .collect();
+
+        let scheme = if settings.address.starts_with("https://") {
+            "https"
+        } else {
+            "http"
+        };
+
+        Self {
+            endpoints,
+            current: 0,
+            discovery_dns: settings.discovery_dns.clone(),
+            scheme,
+        }
+    }
+
+    /// Number of known endpoints
+    pub fn len(&self) -> usize {
+        self.endpoints.len()
+    }
+
+    /// Whether the pool has no endpoints
+    pub fn is_empty(&self) -> bool {
+        self.endpoints.is_empty()
+    }
+
+    /// All endpoints with their health
+    pub fn endpoints(&self) -> &[GatewayEndpoint] {
+        &self.endpoints
+    }
+
+    /// Address of the gateway to use now
+    ///
+    /// Prefers the current gateway, then the next available one. If every
+    /// gateway is backing off, the one whose backoff ends first is returned.
+    pub fn current_address(&mut self) -> Option<String> {
+        self.select(Instant::now())
+    }
+
+    fn select(&mut self, now: Instant) -> Option<String> {
+        if self.endpoints.is_empty() {
+            return None;
+        }
+
+        let n = self.endpoints.len();
+        let index = (0..n)
+            .map(|offset| (self.current + offset) % n)
+            .find(|&i| self.endpoints[i].is_available(now))
+            .unwrap_or_else(|| {
+                (0..n)
+                    .min_by_key(|&i| self.endpoints[i].backoff_until)
+                    .unwrap_or(0)
+            });
+
+        self.current = index;
+        Some(self.endpoints[index].address.clone())
+    }
+
+    /// Record a successful call to `address`
+    pub fn mark_success(&mut self, address: &str) {
+        if let Some(endpoint) = self.endpoints.iter_mut().find(|e| e.address == address) {
+            if endpoint.consecutive_failures > 0 {
+                info!(gateway = %address, "Gateway recovered");
+            }
+            endpoint.consecutive_failures = 0;
+            endpoint.backoff_until = None;
+        }
+    }
+
+    /// Record a failed call to `address` and move to the next gateway
+    pub fn mark_failure(&mut self, address: &str) {
+        self.mark_failure_at(address, Instant::now());
+    }
+
+    fn mark_failure_at(&mut self, address: &str, now: Instant) {
+        let Some(index) = self.endpoints.iter().position(|e| e.address == address) else {
+            return;
+        };
+
+        let endpoint = &mut self.endpoints[index];
+        endpoint.consecutive_failures += 1;
+        let backoff = backoff_for(endpoint.consecutive_failures);
+        endpoint.backoff_until = Some(now + backoff);
+
+        warn!(
+            gateway = %address,
+            failures = endpoint.consecutive_failures,
+            backoff_secs = backoff.as_secs(),
+            "Gateway marked unhealthy"
+        );
+
+        if self.endpoints.len() > 1 && index == self.current {
+            self.current = (index + 1) % self.endpoints.len();
+        }
+    }
+
+    /// Re-resolve the discovery DNS name and update discovered endpoints
+    ///
+    /// Every address the name resolves to (A/AAAA records) becomes an
+    /// endpoint. Health of endpoints that are still present is kept.
+    pub async fn refresh_discovery(&mut self) {
+        let Some(ref name) = self.discovery_dns else {
+            return;
+        };
+
+        let resolved = match tokio::net::lookup_host(name.as_str()).await {
+            Ok(addrs) => addrs
+                .map(|addr| format!("{}://{}", self.scheme, addr))
+                .collect::<Vec<_>>(),
+            Err(e) => {
+                warn!(name = %name, error = %e, "Gateway DNS discovery failed");
+                return;
+            }
+        };
+
+        if resolved.is_empty() {
+            debug!(name = %name, "Gateway DNS discovery returned no addresses");
+            return;
+        }
+
+        self.merge_discovered(resolved);
+    }
+
+    fn merge_discovered(&mut self, resolved: Vec<String>) {
+        let current_address = self.endpoints.get(self.current).map(|e| e.address.clone());
+
+        self.endpoints
+            .retain(|e| !e.discovered || resolved.contains(&e.address));
+        for address in resolved {
+            if !self.endpoints.iter().any(|e| e.address == address) {
+                info!(gateway = %address, "Discovered gateway");
+                self.endpoints.push(GatewayEndpoint::new(address, true));
+            }
+        }
+
+        self.current = current_address
+            .and_then(|addr| self.endpoints.iter().position(|e| e.address == addr))
+            .unwrap_or(0);
+    }
+}
+
+/// Exponential backoff for the n-th consecutive failure
+fn backoff_for(failures: u32) -> Duration {
+    let factor = 2u32.saturating_pow(failures.saturating_sub(1).min(16));
+    (BASE_BACKOFF * factor).min(MAX_BACKOFF)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_pool(addrs: &[&str]) -> GatewayPool {
+        let settings = CentralServerSettings {
+            address: addrs[0].to_string(),
+            fallback_addresses: addrs[1..].iter().map(|s| s.to_string()).collect(),
+            ..Default::default()
+        };
+        GatewayPool::from_settings(&settings)
+    }
+
+    #[test]
+    fn test_sticky_selection_and_failover() {
+        let mut pool = make_pool(&["http://gw1:50052", "http://gw2:50052", "http://gw3:50052"]);
+        let now = Instant::now();
+
+        assert_eq!(pool.select(now).unwrap(), "http://gw1:50052");
+        assert_eq!(pool.select(now).unwrap(), "http://gw1:50052");
+
+        pool.mark_failure_at("http://gw1:50052", now);
+        assert_eq!(pool.select(now).unwrap(), "http://gw2:50052");
+
+        pool.mark_failure_at("http://gw2:50052", now);
+        assert_eq!(pool.select(now).unwrap(), "http://gw3:50052");
+
+        // After its backoff expires gw1 is eligible again, but we stay on gw3
+        let later = now + Duration::from_secs(10);
+        assert_eq!(pool.select(later).unwrap(), "http://gw3:50052");
+    }
+
+    #[test]
+    fn test_all_failed_picks_earliest_backoff() {
+        let mut pool = make_pool(&["http://gw1:50052", "http://gw2:50052"]);
+        let now = Instant::now();
+
+        pool.mark_failure_at("http://gw1:50052", now);
+        pool.mark_failure_at("http://gw1:50052", now);
+        pool.mark_failure_at("http://gw2:50052", now);
+
+        assert_eq!(pool.select(now).unwrap(), "http://gw2:50052");
+    }
+
+    #[test]
+    fn test_success_resets_backoff() {
+        let mut pool = make_pool(&["http://gw1:50052", "http://gw2:50052"]);
+        let now = Instant::now();
+
+        pool.mark_failure_at("http://gw1:50052", now);
+        pool.mark_success("http://gw1:50052");
+        assert!(pool.endpoints()[0].is_available(now));
+        assert_eq!(pool.endpoints()[0].consecutive_failures, 0);
+    }
+
+    #[test]
+    fn test_merge_discovered_keeps_static_and_current() {
+        let mut pool = make_pool(&["http://gw1:50052"]);
+        pool.merge_discovered(vec![
+            "http://10.0.0.1:50052".to_string(),
+            "http://10.0.0.2:50052".to_string(),
+        ]);
+        assert_eq!(pool.len(), 3);
+
+        pool.current = 2;
+        pool.merge_discovered(vec!["http://10.0.0.2:50052".to_string()]);
+        assert_eq!(pool.len(), 2);
+        assert_eq!(
+            pool.select(Instant::now()).unwrap(),
+            "http://10.0.0.2:50052"
+        );
+    }
+
+    #[test]
+    fn test_backoff_growth() {
+        assert_eq!(backoff_for(1), Duration::from_secs(5));
+        assert_eq!(backoff_for(2), Duration::from_secs(10));
+        assert_eq!(backoff_for(20), MAX_BACKOFF);
+    }
+}
@@ -2,20 +2,31 @@
 //!
 //! Periodically checks local health and reports to central server via gRPC.
 
+use crate::capacity::{self, DiskSpace};
 use crate::command_executor::{CommandBatchSummary, CommandExecutor};
-use crate::config::NodeConfig;
+use crate::config::{NodeConfig, NodeIdentity};
+use crate::disk_health::{DiskHealth, DiskHealthSampler};
+use crate::gateway_pool::GatewayPool;
+use crate::maintenance::DamageReport;
 use crate::metrics::{HealthState, NodeMetrics};
 use cyxcloud_core::tls::{create_tonic_client_tls, TlsClientConfig};
+use cyxcloud_network::protocol::{
+    NodeAnnouncement as GossipAnnouncement, NodeCapacity as GossipCapacity,
+    NodeLocation as GossipLocation, NodeStatus as GossipStatus,
+};
 use cyxcloud_protocol::node::{
-    node_service_client::NodeServiceClient, HeartbeatRequest, NodeCapacity, NodeCommand, NodeInfo,
-    NodeLocation, NodeMetrics as ProtoNodeMetrics, NodeStatus, RegisterNodeRequest,
+    node_service_client::NodeServiceClient, DiskHealth as ProtoDiskHealth,
+    DiskSpace as ProtoDiskSpace, DrainNodeRequest, HeartbeatRequest, NodeCapacity, NodeCommand,
+    NodeInfo, NodeLocation, NodeMetrics as ProtoNodeMetrics, NodeStatus, RegisterNodeRequest,
+    ReportChunkDamageRequest,
 };
 use cyxcloud_storage::backend::StorageBackendSync;
 use cyxcloud_storage::RocksDbBackend;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 use sysinfo::{CpuRefreshKind, MemoryRefreshKind, RefreshKind, System};
-use tokio::sync::RwLock;
+use tokio::sync::{mpsc, Mutex, RwLock};
 use tonic::transport::Channel;
 use tracing::{debug, error, info, warn};
 
@@ -26,6 +37,10 @@ pub struct HealthChecker {
     metrics: NodeMetrics,
     state: Arc<RwLock<HealthState>>,
     check_interval: Duration,
+    /// Disk health sampler (None disables disk monitoring)
+    disk_sampler: Option<Mutex<DiskHealthSampler>>,
+    /// Used to report disk health and request a self-drain
+    heartbeat: Option<Arc<HeartbeatService>>,
 }
 
 impl HealthChecker {
@@ -42,18 +57,42 @@ impl HealthChecker {
             metrics,
             state,
             check_interval: Duration::from_secs(10),
+            disk_sampler: None,
+            heartbeat: None,
         }
     }
 
+    /// Monitor the disk holding the data directory
+    pub fn with_disk_health(mut self, sampler: DiskHealthSampler) -> Self {
+        self.disk_sampler = Some(Mutex::new(sampler));
+        self
+    }
+
+    /// Report disk health to the Gateway through the heartbeat service
+    pub fn with_heartbeat_service(mut self, heartbeat: Arc<HeartbeatService>) -> Self {
+        self.heartbeat = Some(heartbeat);
+        self
+    }
+
     /// Start the health check loop
     pub async fn run(&self) {
         info!(node_id = %self.node_id, "Starting health checker");
 
         let mut interval = tokio::time::interval(self.check_interval);
+        let mut last_disk_check: Option<tokio::time::Instant> = None;
 
         loop {
             interval.tick().await;
 
+            if let Some(ref sampler) = self.disk_sampler {
+                let mut sampler = sampler.lock().await;
+                let due = Duration::from_secs(sampler.settings().check_interval_secs);
+                if last_disk_check.map_or(true, |at| at.elapsed() >= due) {
+                    self.check_disk(&mut sampler).await;
+                    last_disk_check = Some(tokio::time::Instant::now());
+                }
+            }
+
             let storage_ok = self.check_storage().await;
             let network_ok = self.check_network().await;
 
@@ -81,6 +120,43 @@ impl HealthChecker {
         }
     }
 
+    /// Sample disk health, publish it, and request a drain if the disk is failing
+    async fn check_disk(&self, sampler: &mut DiskHealthSampler) {
+        let health = sampler.sample().await;
+        let problems = health.problems(sampler.settings());
+        let disk_ok = problems.is_empty();
+
+        self.metrics.update_disk_health(&health, disk_ok);
+        self.state.write().await.update_disk(disk_ok);
+
+        debug!(
+            node_id = %self.node_id,
+            device = ?health.device,
+            free_percent = format!("{:.1}", health.free_percent()),
+            io_errors = ?health.io_errors,
+            smart = ?health.smart,
+            "Disk health sampled"
+        );
+
+        let Some(ref heartbeat) = self.heartbeat else {
+            return;
+        };
+        heartbeat.set_disk_health(health, problems.clone()).await;
+
+        if disk_ok {
+            return;
+        }
+        warn!(node_id = %self.node_id, problems = ?problems, "Disk health thresholds crossed");
+
+        if sampler.settings().self_drain && !heartbeat.is_draining() {
+            let reason = format!("disk health: {}", problems.join(", "));
+            match heartbeat.request_drain(&reason).await {
+                Ok(()) => warn!(node_id = %self.node_id, "Requested self-drain due to disk health"),
+                Err(e) => error!(error = %e, "Failed to request self-drain"),
+            }
+        }
+    }
+
     /// Check storage backend health
     async fn check_storage(&self) -> bool {
         // Try to get stats - if this works, storage is accessible
@@ -174,7 +250,10 @@ pub struct HeartbeatService {
     metrics: NodeMetrics,
     storage: Arc<RocksDbBackend>,
     grpc_address: String,
-    client: RwLock<Option<NodeServiceClient<Channel>>>,
+    /// Cached client and the gateway address it is connected to
+    client: RwLock<Option<(String, NodeServiceClient<Channel>)>>,
+    /// Known gateways with health tracking for failover
+    gateways: RwLock<GatewayPool>,
     auth_token: RwLock<Option<String>>,
     /// JWT token from CyxWiz API for Gateway authentication
     jwt_token: RwLock<Option<String>>,
@@ -182,6 +261,11 @@ pub struct HeartbeatService {
     credentials_wallet: RwLock<Option<String>>,
     system: RwLock<System>,
     command_executor: CommandExecutor,
+    /// Latest disk health sample and the thresholds it crossed
+    disk_health: RwLock<Option<(DiskHealth, Vec<String>)>>,
+    /// Drain was requested; heartbeats report DRAINING so the node stays out
+    /// of placement
+    draining: AtomicBool,
 }
 
 impl HeartbeatService {
@@ -209,6 +293,8 @@ impl HeartbeatService {
         let command_executor =
             CommandExecutor::new(node_id.clone(), storage.clone(), metrics.clone());
 
+        let gateways = GatewayPool::from_settings(&config.central);
+
         Self {
             node_id,
             config,
@@ -216,11 +302,14 @@ impl HeartbeatService {
             storage,
             grpc_address,
             client: RwLock::new(None),
+            gateways: RwLock::new(gateways),
             auth_token: RwLock::new(None),
             jwt_token: RwLock::new(None),
             credentials_wallet: RwLock::new(None),
             system: RwLock::new(system),
             command_executor,
+            disk_health: RwLock::new(None),
+            draining: AtomicBool::new(false),
         }
     }
 
@@ -245,6 +334,21 @@ impl HeartbeatService {
         info!(wallet = %wallet, "Wallet address set from credentials");
     }
 
+    /// Store the latest disk health sample, sent with the next heartbeat
+    pub async fn set_disk_health(&self, health: DiskHealth, problems: Vec<String>) {
+        *self.disk_health.write().await = Some((health, problems));
+    }
+
+    /// Whether a drain has been requested for this node
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::Acquire)
+    }
+
+    /// gRPC address this node registers with the Gateway
+    pub fn grpc_address(&self) -> &str {
+        &self.grpc_address
+    }
+
     /// Get the effective wallet address for registration
     /// Priority: credentials wallet > config wallet
     async fn get_wallet_address(&self) -> String {
@@ -263,9 +367,15 @@ impl HeartbeatService {
             return;
         }
 
+        // Resolve discovered gateways before the first registration
+        self.gateways.write().await.refresh_discovery().await;
+        let discovery_refresh = Duration::from_secs(self.config.central.discovery_refresh_secs);
+        let mut last_discovery = tokio::time::Instant::now();
+
         info!(
             node_id = %self.node_id,
             central_addr = %self.config.central.address,
+            gateways = self.gateways.read().await.len(),
             interval_secs = self.config.central.heartbeat_interval_secs,
             "Starting heartbeat service"
         );
@@ -275,7 +385,7 @@ impl HeartbeatService {
         ));
 
         // Initial registration
-        match self.register().await {
+        match self.register_with_failover().await {
             Ok(()) => info!(node_id = %self.node_id, "Initial registration successful"),
             Err(e) => warn!(error = %e, "Failed initial registration with central server"),
         }
@@ -283,6 +393,13 @@ impl HeartbeatService {
         loop {
             interval.tick().await;
 
+            if self.config.central.discovery_dns.is_some()
+                && last_discovery.elapsed() >= discovery_refresh
+            {
+                self.gateways.write().await.refresh_discovery().await;
+                last_discovery = tokio::time::Instant::now();
+            }
+
             match self.send_heartbeat().await {
                 Ok(()) => {
                     self.metrics.record_heartbeat(true);
@@ -295,8 +412,8 @@ impl HeartbeatService {
                         error = %e,
                         "Failed to send heartbeat, attempting re-registration"
                     );
-                    // Try to re-register on heartbeat failure
-                    if let Err(reg_err) = self.register().await {
+                    // Try to re-register on heartbeat failure (next gateway if it was down)
+                    if let Err(reg_err) = self.register_with_failover().await {
                         warn!(error = %reg_err, "Re-registration also failed");
                     }
                 }
@@ -304,28 +421,84 @@ impl HeartbeatService {
         }
     }
 
-    /// Connect to the central server
+    /// Register, trying each known gateway once until one accepts
+    async fn register_with_failover(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let attempts = self.gateways.read().await.len().max(1);
+        let mut last_error = None;
+
+        for _ in 0..attempts {
+            let before = self.gateways.write().await.current_address();
+            match self.register().await {
+                Ok(()) => return Ok(()),
+                Err(e) => last_error = Some(e),
+            }
+
+            // Only retry when the failure moved us to another gateway
+            if self.gateways.write().await.current_address() == before {
+                break;
+            }
+        }
+
+        Err(last_error.unwrap_or_else(|| "No gateways configured".into()))
+    }
+
+    /// Drop the cached connection and move on to the next gateway
+    async fn fail_over(&self, gateway: &str) {
+        self.gateways.write().await.mark_failure(gateway);
+
+        let mut client = self.client.write().await;
+        if client.as_ref().is_some_and(|(addr, _)| addr == gateway) {
+            *client = None;
+        }
+    }
+
+    /// Connect to the current gateway
+    ///
+    /// Returns the gateway address together with the client so callers can
+    /// report failures against the right endpoint.
     async fn connect(
         &self,
-    ) -> Result<NodeServiceClient<Channel>, Box<dyn std::error::Error + Send + Sync>> {
-        // Check if we already have a connection
+    ) -> Result<(String, NodeServiceClient<Channel>), Box<dyn std::error::Error + Send + Sync>>
+    {
+        let gateway = self
+            .gateways
+            .write()
+            .await
+            .current_address()
+            .unwrap_or_else(|| self.config.central.address.clone());
+
+        // Reuse the connection if it points at the current gateway
         {
             let client = self.client.read().await;
-            if client.is_some() {
-                return Ok(client.clone().unwrap());
+            if let Some((addr, client)) = client.as_ref() {
+                if *addr == gateway {
+                    return Ok((gateway, client.clone()));
+                }
+            }
+        }
+
+        match self.connect_to(&gateway).await {
+            Ok(client) => Ok((gateway, client)),
+            Err(e) => {
+                self.fail_over(&gateway).await;
+                Err(e)
             }
         }
+    }
 
+    /// Open a new connection to a gateway
+    async fn connect_to(
+        &self,
+        gateway: &str,
+    ) -> Result<NodeServiceClient<Channel>, Box<dyn std::error::Error + Send + Sync>> {
         // Determine if TLS should be used (based on URL scheme or explicit config)
-        let use_tls =
-            self.config.central.address.starts_with("https://") || self.config.network.enable_tls;
+        let use_tls = gateway.starts_with("https://") || self.config.network.enable_tls;
 
         // Create new connection
-        let mut endpoint =
-            tonic::transport::Endpoint::from_shared(self.config.central.address.clone())?
-                .connect_timeout(Duration::from_secs(
-                    self.config.central.connect_timeout_secs,
-                ));
+        let mut endpoint = tonic::transport::Endpoint::from_shared(gateway.to_string())?
+            .connect_timeout(Duration::from_secs(
+                self.config.central.connect_timeout_secs,
+            ));
 
         // Configure TLS if enabled
         if use_tls {
@@ -340,7 +513,7 @@ impl HeartbeatService {
                     Ok(tls) => {
                         endpoint = endpoint.tls_config(tls)?;
                         info!(
-                            central_addr = %self.config.central.address,
+                            central_addr = %gateway,
                             mtls = self.config.network.tls_client_cert.is_some(),
                             "TLS configured for gateway connection"
                         );
@@ -350,7 +523,7 @@ impl HeartbeatService {
                         return Err(format!("Failed to load TLS config: {}", e).into());
                     }
                 }
-            } else if self.config.central.address.starts_with("https://") {
+            } else if gateway.starts_with("https://") {
                 // HTTPS URL but no CA cert - try system roots
                 warn!("Using HTTPS without explicit CA cert - using system roots");
             }
@@ -362,11 +535,11 @@ impl HeartbeatService {
         // Store the connection
         {
             let mut stored_client = self.client.write().await;
-            *stored_client = Some(client.clone());
+            *stored_client = Some((gateway.to_string(), client.clone()));
         }
 
         info!(
-            central_addr = %self.config.central.address,
+            central_addr = %gateway,
             tls = use_tls,
             "Connected to central server"
         );
@@ -392,15 +565,15 @@ impl HeartbeatService {
             return Err("JWT token not set - login to CyxWiz API first".into());
         }
 
+        let (gateway, mut client) = self.connect().await?;
+
         info!(
             node_id = %self.node_id,
-            central_addr = %self.config.central.address,
+            central_addr = %gateway,
             grpc_addr = %self.grpc_address,
             "Registering with Gateway (using JWT auth)"
         );
 
-        let mut client = self.connect().await?;
-
         // Get storage stats for capacity info
         let stats = self.storage.stats().unwrap_or_default();
 
@@ -415,13 +588,7 @@ impl HeartbeatService {
                 public_key: String::new(), // TODO: Add key support
                 wallet_address: wallet_address.clone(),
                 listen_addrs: vec![self.grpc_address.clone()],
-                location: Some(NodeLocation {
-                    datacenter: String::new(),
-                    rack: 0,
-                    region: self.config.node.region.clone().unwrap_or_default(),
-                    latitude: 0.0,
-                    longitude: 0.0,
-                }),
+                location: Some(location_to_proto(&self.config.node)),
                 capacity: Some(NodeCapacity {
                     storage_total: stats.bytes_capacity,
                     storage_used: stats.bytes_used,
@@ -430,6 +597,7 @@ impl HeartbeatService {
                 }),
                 status: NodeStatus::Online.into(),
                 registered_at: chrono::Utc::now().timestamp(),
+                reputation: 0, // Assigned by the gateway
             }),
         };
 
@@ -440,8 +608,15 @@ impl HeartbeatService {
         // Create request with JWT auth header
         let request = self.create_auth_request(register_req, jwt_token.as_deref());
 
-        let response = client.register_node(request).await?;
+        let response = match client.register_node(request).await {
+            Ok(response) => response,
+            Err(e) => {
+                self.fail_over(&gateway).await;
+                return Err(e.into());
+            }
+        };
         let result = response.into_inner();
+        self.gateways.write().await.mark_success(&gateway);
 
         if result.success {
             // Calculate storage info for display
@@ -467,7 +642,7 @@ impl HeartbeatService {
                 info!("  Available:       {:.1} GB", available_gb);
                 info!("========================================");
             } else {
-                warn!("No storage capacity configured - set max_capacity_gb in config.toml");
+                warn!("Storage capacity unknown - set max_capacity_gb in config.toml");
             }
 
             // Store the gateway auth token for future requests (if any)
@@ -482,17 +657,205 @@ impl HeartbeatService {
     }
 
     /// Send heartbeat to central server
+    ///
+    /// Once a drain was requested the heartbeat keeps reporting DRAINING, so
+    /// the Gateway does not treat the node as recovered.
     async fn send_heartbeat(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let status = if self.is_draining() {
+            NodeStatus::Draining
+        } else {
+            NodeStatus::Online
+        };
+        self.send_heartbeat_with_status(status, "")
+            .await
+            .map(|_| ())
+    }
+
+    /// Ask the gateway to drain this node before it shuts down
+    ///
+    /// The node is marked draining (no new shards) and evacuation jobs are
+    /// created for the shards it stores. Tries each known gateway once.
+    pub async fn request_drain(
+        &self,
+        reason: &str,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let jwt_token = self.jwt_token.read().await.clone();
+        if jwt_token.is_none() {
+            return Err("JWT token not set - login to CyxWiz API first".into());
+        }
+
+        let attempts = self.gateways.read().await.len().max(1);
+        let mut last_error: Option<Box<dyn std::error::Error + Send + Sync>> = None;
+
+        for _ in 0..attempts {
+            let (gateway, mut client) = match self.connect().await {
+                Ok(connection) => connection,
+                Err(e) => {
+                    last_error = Some(e);
+                    continue;
+                }
+            };
+
+            let drain_req = DrainNodeRequest {
+                node_id: self.node_id.clone(),
+                reason: reason.to_string(),
+            };
+            let request = self.create_auth_request(drain_req, jwt_token.as_deref());
+
+            match client.drain_node(request).await {
+                Ok(response) => {
+                    self.gateways.write().await.mark_success(&gateway);
+                    let response = response.into_inner();
+                    if response.accepted {
+                        self.draining.store(true, Ordering::Release);
+                        let chunks = response.progress.map_or(0, |p| p.total_chunks);
+                        info!(
+                            node_id = %self.node_id,
+                            reason = %reason,
+                            chunks = chunks,
+                            estimated_secs = response.estimated_duration_secs,
+                            "Drain requested"
+                        );
+                        return Ok(());
+                    }
+                    return Err("Drain request not accepted".into());
+                }
+                Err(e) => {
+                    self.fail_over(&gateway).await;
+                    last_error = Some(e.into());
+                }
+            }
+        }
+
+        Err(last_error.unwrap_or_else(|| "No gateways configured".into()))
+    }
+
+    /// Report draining status and get the number of shards still being
+    /// evacuated from this node
+    pub async fn drain_progress(
+        &self,
+        reason: &str,
+    ) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
+        self.send_heartbeat_with_status(NodeStatus::Draining, reason)
+            .await
+    }
+
+    /// Report chunks that failed the local scrub
+    ///
+    /// The gateway drops this node's locations for them and queues repair.
+    /// Returns the number of locations removed. Tries each known gateway once.
+    pub async fn report_chunk_damage(
+        &self,
+        report: &DamageReport,
+    ) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
+        let jwt_token = self.jwt_token.read().await.clone();
+        if jwt_token.is_none() {
+            return Err("JWT token not set - login to CyxWiz API first".into());
+        }
+
+        let attempts = self.gateways.read().await.len().max(1);
+        let mut last_error: Option<Box<dyn std::error::Error + Send + Sync>> = None;
+
+        for _ in 0..attempts {
+            let (gateway, mut client) = match self.connect().await {
+                Ok(connection) => connection,
+                Err(e) => {
+                    last_error = Some(e);
+                    continue;
+                }
+            };
+
+            let damage_req = ReportChunkDamageRequest {
+                node_id: self.node_id.clone(),
+                corrupted_chunks: report
+                    .corrupted
+                    .iter()
+                    .map(|id| id.as_bytes().to_vec())
+                    .collect(),
+                missing_chunks: report
+                    .missing
+                    .iter()
+                    .map(|id| id.as_bytes().to_vec())
+                    .collect(),
+            };
+            let request = self.create_auth_request(damage_req, jwt_token.as_deref());
+
+            match client.report_chunk_damage(request).await {
+                Ok(response) => {
+                    self.gateways.write().await.mark_success(&gateway);
+                    return Ok(response.into_inner().locations_removed);
+                }
+                Err(e) => {
+                    self.fail_over(&gateway).await;
+                    last_error = Some(e.into());
+                }
+            }
+        }
+
+        Err(last_error.unwrap_or_else(|| "No gateways configured".into()))
+    }
+
+    /// Send a final heartbeat announcing that the node is going away
+    ///
+    /// Reports `central.shutdown_status` (maintenance or offline) so the
+    /// gateway stops placing shards here right away. Tries each known gateway
+    /// once.
+    pub async fn send_shutdown_heartbeat(
+        &self,
+        reason: &str,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let status = match self.config.central.shutdown_status.as_str() {
+            "offline" => NodeStatus::Offline,
+            _ => NodeStatus::Maintenance,
+        };
+
+        let attempts = self.gateways.read().await.len().max(1);
+        let mut last_error = None;
+
+        for _ in 0..attempts {
+            match self.send_heartbeat_with_status(status, reason).await {
+                Ok(_) => {
+                    info!(
+                        node_id = %self.node_id,
+                        status = ?status,
+                        reason = %reason,
+                        "Shutdown reported to Gateway"
+                    );
+                    return Ok(());
+                }
+                Err(e) => last_error = Some(e),
+            }
+        }
+
+        Err(last_error.unwrap_or_else(|| "No gateways configured".into()))
+    }
+
+    /// Send heartbeat reporting the given status
+    ///
+    /// Returns the number of shards the gateway is still evacuating from
+    /// this node (only reported while draining).
+    async fn send_heartbeat_with_status(
+        &self,
+        status: NodeStatus,
+        reason: &str,
+    ) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
         // Get JWT token for authentication
         let jwt_token = self.jwt_token.read().await.clone();
         if jwt_token.is_none() {
             return Err("JWT token not set - login to CyxWiz API first".into());
         }
 
-        let mut client = self.connect().await?;
+        let (gateway, mut client) = self.connect().await?;
 
         // Get current stats
         let stats = self.storage.stats()?;
+        let disk_space = match capacity::disk_space(&self.config.storage.data_dir) {
+            Ok(disk) => Some(disk),
+            Err(e) => {
+                debug!(error = %e, "Failed to read free disk space");
+                None
+            }
+        };
 
         // Collect system metrics (CPU and memory)
         let (cpu_usage, memory_usage) = {
@@ -533,14 +896,32 @@ impl HeartbeatService {
                 memory_usage,
                 active_connections: 0,
                 last_updated: chrono::Utc::now().timestamp(),
+                disk_health: self
+                    .disk_health
+                    .read()
+                    .await
+                    .as_ref()
+                    .map(|(health, problems)| disk_health_to_proto(health, problems)),
+                storage_total: stats.bytes_capacity,
+                disk_space: disk_space.map(disk_space_to_proto),
             }),
+            status: status.into(),
+            status_reason: reason.to_string(),
+            location: Some(location_to_proto(&self.config.node)),
         };
 
         // Create request with JWT auth header
         let request = self.create_auth_request(heartbeat_req, jwt_token.as_deref());
 
-        let response = client.heartbeat(request).await?;
+        let response = match client.heartbeat(request).await {
+            Ok(response) => response,
+            Err(e) => {
+                self.fail_over(&gateway).await;
+                return Err(e.into());
+            }
+        };
         let result = response.into_inner();
+        self.gateways.write().await.mark_success(&gateway);
 
         if result.acknowledged {
             debug!(
@@ -554,8 +935,8 @@ impl HeartbeatService {
                 "Heartbeat acknowledged"
             );
 
-            // Process any commands from the server
-            if !result.commands.is_empty() {
+            // Process any commands from the server (not while shutting down)
+            if !result.commands.is_empty() && status == NodeStatus::Online {
                 info!(
                     node_id = %self.node_id,
                     command_count = result.commands.len(),
@@ -566,7 +947,7 @@ impl HeartbeatService {
                 self.execute_server_commands(result.commands).await;
             }
 
-            Ok(())
+            Ok(result.pending_evacuations)
         } else {
             Err("Heartbeat not acknowledged".into())
         }
@@ -629,11 +1010,55 @@ impl HeartbeatService {
     }
 }
 
+/// Convert a disk health sample to its proto form
+fn disk_health_to_proto(health: &DiskHealth, problems: &[String]) -> ProtoDiskHealth {
+    let smart = health.smart.clone().unwrap_or_default();
+    ProtoDiskHealth {
+        device: health.device.clone().unwrap_or_default(),
+        free_bytes: health.free_bytes,
+        total_bytes: health.total_bytes,
+        io_errors: health.io_errors.unwrap_or(0),
+        smart_available: health.smart.is_some(),
+        smart_passed: smart.passed,
+        reallocated_sectors: smart.reallocated_sectors,
+        pending_sectors: smart.pending_sectors,
+        media_errors: smart.media_errors,
+        problems: problems.to_vec(),
+    }
+}
+
+/// Convert free disk space to its proto form
+fn disk_space_to_proto(disk: DiskSpace) -> ProtoDiskSpace {
+    ProtoDiskSpace {
+        available_bytes: disk.available_bytes,
+        total_bytes: disk.total_bytes,
+    }
+}
+
+/// Convert the configured location to its proto form (unset = empty/0)
+fn location_to_proto(node: &NodeIdentity) -> NodeLocation {
+    NodeLocation {
+        datacenter: node.zone.clone().unwrap_or_default(),
+        rack: node.rack.unwrap_or(0),
+        region: node.region.clone().unwrap_or_default(),
+        latitude: node.latitude.unwrap_or(0.0),
+        longitude: node.longitude.unwrap_or(0.0),
+    }
+}
+
+/// How often the announcer checks for a status or capacity change
+const ANNOUNCE_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Unchanged status is republished at this interval so late joiners catch up
+const ANNOUNCE_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
 /// Node announcer for P2P network
 pub struct NodeAnnouncer {
     node_id: String,
     grpc_addr: String,
     storage: Arc<RocksDbBackend>,
+    region: Option<String>,
+    heartbeat: Option<Arc<HeartbeatService>>,
 }
 
 impl NodeAnnouncer {
@@ -643,9 +1068,23 @@ impl NodeAnnouncer {
             node_id,
             grpc_addr,
             storage,
+            region: None,
+            heartbeat: None,
         }
     }
 
+    /// Set the region included in announcements
+    pub fn with_region(mut self, region: Option<String>) -> Self {
+        self.region = region;
+        self
+    }
+
+    /// Report DRAINING while the heartbeat service is draining
+    pub fn with_heartbeat(mut self, heartbeat: Arc<HeartbeatService>) -> Self {
+        self.heartbeat = Some(heartbeat);
+        self
+    }
+
     /// Get node capacity info for announcements
     pub fn get_capacity(&self) -> NodeCapacity2 {
         let stats = self.storage.stats().unwrap_or_default();
@@ -658,6 +1097,14 @@ impl NodeAnnouncer {
         }
     }
 
+    /// Current status as seen by the rest of the cluster
+    pub fn current_status(&self) -> NodeStatus2 {
+        match &self.heartbeat {
+            Some(heartbeat) if heartbeat.is_draining() => NodeStatus2::Draining,
+            _ => NodeStatus2::Online,
+        }
+    }
+
     /// Get node announcement message
     pub fn get_announcement(&self) -> NodeAnnouncement {
         let capacity = self.get_capacity();
@@ -666,11 +1113,82 @@ impl NodeAnnouncer {
             node_id: self.node_id.clone(),
             grpc_addr: self.grpc_addr.clone(),
             capacity,
-            status: NodeStatus2::Online,
+            status: self.current_status(),
+        }
+    }
+
+    /// Build the gossip message for the given status
+    pub fn gossip_announcement(&self, status: NodeStatus2) -> GossipAnnouncement {
+        let capacity = self.get_capacity();
+        let mut location = GossipLocation::default();
+        if let Some(region) = &self.region {
+            location.region = region.clone();
+        }
+
+        GossipAnnouncement::new(self.node_id.clone(), self.grpc_addr.clone())
+            .with_capacity(GossipCapacity {
+                storage_total: capacity.total_bytes,
+                storage_used: capacity.used_bytes,
+                ..Default::default()
+            })
+            .with_location(location)
+            .with_status(status.into())
+    }
+
+    /// Publish a single announcement with the given status (e.g. on shutdown)
+    pub async fn announce(&self, tx: &mpsc::Sender<GossipAnnouncement>, status: NodeStatus2) {
+        if tx.send(self.gossip_announcement(status)).await.is_err() {
+            debug!("P2P discovery stopped, status announcement dropped");
+        }
+    }
+
+    /// Publish status changes until the discovery service goes away
+    ///
+    /// Checks every few seconds and publishes when the status or address
+    /// changes or usage moves by more than 1% of capacity; otherwise the
+    /// last status is republished every minute.
+    pub async fn run(&self, tx: mpsc::Sender<GossipAnnouncement>) {
+        let mut interval = tokio::time::interval(ANNOUNCE_CHECK_INTERVAL);
+        let mut last: Option<(GossipAnnouncement, std::time::Instant)> = None;
+
+        loop {
+            interval.tick().await;
+
+            let next = self.gossip_announcement(self.current_status());
+            let due = match &last {
+                Some((prev, at)) => {
+                    significant_change(prev, &next) || at.elapsed() >= ANNOUNCE_REFRESH_INTERVAL
+                }
+                None => true,
+            };
+            if !due {
+                continue;
+            }
+
+            if tx.send(next.clone()).await.is_err() {
+                debug!("P2P discovery stopped, ending status announcements");
+                return;
+            }
+            last = Some((next, std::time::Instant::now()));
         }
     }
 }
 
+/// Whether `next` is worth gossiping before the periodic refresh
+fn significant_change(prev: &GossipAnnouncement, next: &GossipAnnouncement) -> bool {
+    if prev.status != next.status
+        || prev.grpc_address != next.grpc_address
+        || prev.capacity.storage_total != next.capacity.storage_total
+    {
+        return true;
+    }
+    let moved = prev
+        .capacity
+        .storage_used
+        .abs_diff(next.capacity.storage_used);
+    moved.saturating_mul(100) > next.capacity.storage_total
+}
+
 /// Node capacity information (local struct, not proto)
 #[derive(Debug, Clone, Default)]
 pub struct NodeCapacity2 {
@@ -689,6 +1207,17 @@ pub enum NodeStatus2 {
     Offline,
 }
 
+impl From<NodeStatus2> for GossipStatus {
+    fn from(status: NodeStatus2) -> Self {
+        match status {
+            NodeStatus2::Online => GossipStatus::Online,
+            NodeStatus2::Maintenance => GossipStatus::Maintenance,
+            NodeStatus2::Draining => GossipStatus::Draining,
+            NodeStatus2::Offline => GossipStatus::Offline,
+        }
+    }
+}
+
 /// Node announcement message
 #[derive(Debug, Clone)]
 pub struct NodeAnnouncement {
@@ -726,6 +1255,24 @@ mod tests {
         assert_eq!(announcement.status, NodeStatus2::Online);
     }
 
+    #[test]
+    fn test_significant_change() {
+        let mut prev = GossipAnnouncement::new("node", "localhost:50051");
+        prev.capacity.storage_total = 1000;
+        prev.capacity.storage_used = 300;
+
+        let mut next = prev.clone();
+        next.capacity.storage_used = 305;
+        assert!(!significant_change(&prev, &next));
+
+        next.capacity.storage_used = 320;
+        assert!(significant_change(&prev, &next));
+
+        let mut next = prev.clone();
+        next.status = GossipStatus::Draining;
+        assert!(significant_change(&prev, &next));
+    }
+
     #[test]
     fn test_node_capacity() {
         let capacity = NodeCapacity2 {
@@ -2,34 +2,49 @@
 //!
 //! Provides components for running a distributed storage node:
 //! - Configuration management
-//! - Prometheus metrics and health checking
+//! - Storage capacity detection from the free disk space
+//! - Prometheus metrics and health checking (including disk/SMART health)
 //! - Heartbeat service for central server registration
 //! - Command execution (repair, delete, transfer chunks)
+//! - Offline chunk store export/import for cold migration
+//! - Offline salvage of chunk locations from shard headers
+//! - Local self-scrub and low-traffic compaction scheduling
 //! - P2P network announcements
+//! - Simulated in-memory nodes for synthetic test clusters
+//! - Signed self-update from the release channel
 //! - CyxWiz API integration for machine management
 //! - Blockchain integration for Solana (optional)
 
 #![allow(clippy::field_reassign_with_default)]
 #![allow(clippy::derivable_impls)]
 
+pub mod capacity;
+pub mod chunk_archive;
 pub mod command_executor;
 pub mod config;
 pub mod cyxwiz_api_client;
 pub mod data_loader;
 pub mod datastream_client;
+pub mod disk_health;
+pub mod gateway_pool;
 pub mod health;
 pub mod machine_service;
+pub mod maintenance;
 pub mod metrics;
+pub mod salvage;
+pub mod simulation;
 pub mod symbols;
 pub mod training_executor;
+pub mod updater;
 pub mod verification;
 
 #[cfg(feature = "blockchain")]
 pub mod blockchain;
 
 pub use config::{
-    BlockchainSettings, CentralServerSettings, ConfigError, CyxWizApiSettings, MetricsSettings,
-    NetworkSettings, NodeConfig, NodeIdentity, StorageSettings,
+    BlockchainSettings, CentralServerSettings, ConfigError, ConfigIssue, CyxWizApiSettings,
+    DiskHealthSettings, MaintenanceSettings, MetricsSettings, NetworkSettings, NodeConfig,
+    NodeIdentity, Severity, StorageSettings, UpdateSettings, ValidationReport,
 };
 
 #[cfg(feature = "blockchain")]
@@ -37,6 +52,11 @@ pub use blockchain::{
     constants as blockchain_constants, DiskType, NodeBlockchainConfig, ProofChallenge,
     ProofOfStorage, StorageNodeBlockchainClient, StorageNodeStatus, StorageSpec,
 };
+pub use capacity::{CapacityMonitor, DiskSpace};
+pub use chunk_archive::{
+    export_chunks, import_chunks, AdoptionRequest, ArchiveError, ArchiveManifest, ExportSummary,
+    ImportSummary,
+};
 pub use command_executor::{CommandBatchSummary, CommandExecutor, CommandResult, CommandType};
 pub use cyxwiz_api_client::{
     CpuInfo, CyxWizApiClient, DetectedHardware, GpuInfo, LoginResponse, SavedCredentials, UserInfo,
@@ -46,7 +66,12 @@ pub use health::{
     NodeCapacity2 as NodeCapacity, NodeStatus2 as NodeStatus,
 };
 pub use machine_service::MachineService;
+pub use maintenance::{DamageReport, MaintenanceScheduler};
 pub use metrics::{init_metrics, HealthState, MetricsServer, NodeMetrics};
+pub use salvage::{salvage_chunks, SalvageReport, SalvagedShard};
+pub use simulation::{
+    run_simulation, SimulatedBackend, SimulatedNode, SimulationConfig, SimulationProfile,
+};
 pub use data_loader::{
     DataLoader, DataLoaderBuilder, DataLoaderConfig, LoaderState, LoaderStats, TrainingBatch,
 };
@@ -54,6 +79,8 @@ pub use datastream_client::{
     BatchIterator, DataStreamClient, DataStreamClientBuilder, DataStreamConfig, DataStreamError,
     DataStreamResult, VerifiedBatch,
 };
+pub use disk_health::{DiskHealth, DiskHealthSampler, SmartData};
+pub use gateway_pool::{GatewayEndpoint, GatewayPool};
 pub use training_executor::{
     TrainingError, TrainingExecutor, TrainingExecutorBuilder, TrainingJobConfig, TrainingState,
     TrainingStatus,
@@ -5,26 +5,49 @@
 //! - Participates in P2P network for peer discovery
 //! - Serves chunk requests via gRPC
 //! - Reports health metrics via Prometheus endpoint
-
-use clap::Parser;
+//!
+//! `cyxcloud-node export` / `import` move the chunk store between machines
+//! while the daemon is stopped, and `cyxcloud-node salvage` recovers the
+//! store's chunk locations from its shard headers. `cyxcloud-node rewards`
+//! shows and claims the node's epoch rewards. `cyxcloud-node simulate` runs
+//! a synthetic cluster of in-memory nodes against a gateway instead of the
+//! daemon.
+
+use clap::{Parser, Subcommand};
+use cyxcloud_network::DiscoveryService;
+use cyxcloud_node::updater::{self, Updater};
 use cyxcloud_node::{
-    init_metrics, HealthChecker, HealthState, HeartbeatService, MachineService, MetricsServer,
-    NodeConfig, NodeMetrics,
+    export_chunks, import_chunks, init_metrics, run_simulation, salvage_chunks, CapacityMonitor,
+    DiskHealthSampler, HealthChecker, HealthState, HeartbeatService, MachineService,
+    MaintenanceScheduler, MetricsServer, NodeAnnouncer, NodeConfig, NodeMetrics, NodeStatus,
+    SimulationConfig, SimulationProfile,
 };
+use cyxcloud_storage::backend::StorageBackendSync;
 use cyxcloud_storage::RocksDbBackend;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot, watch, RwLock};
+use tokio::task::JoinHandle;
 use tracing::{error, info, warn};
 
 #[cfg(feature = "blockchain")]
 use cyxcloud_node::{
-    blockchain::{heartbeat::BlockchainHeartbeatService, NodeBlockchainConfig},
+    blockchain::{
+        heartbeat::BlockchainHeartbeatService, NodeBlockchainConfig, RewardsClaimService,
+    },
     DiskType, StorageNodeBlockchainClient, StorageSpec,
 };
 
 #[cfg(feature = "blockchain")]
-use solana_sdk::signer::Signer;
+use solana_sdk::{signature::Keypair, signer::Signer};
+
+/// How often P2P peer counts are copied into the metrics
+const PEER_METRICS_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Status announcements waiting to be gossiped
+const STATUS_GOSSIP_QUEUE: usize = 16;
 
 #[derive(Parser)]
 #[command(name = "cyxcloud-node")]
@@ -54,6 +77,103 @@ struct Cli {
     /// Logout and clear saved credentials
     #[arg(long)]
     logout: bool,
+
+    /// On shutdown, ask the gateway to evacuate this node's shards and wait
+    /// for it to finish before exiting
+    #[arg(long)]
+    drain_on_shutdown: bool,
+
+    /// Check the configuration (including a storage write test) and exit
+    #[arg(long)]
+    check_config: bool,
+
+    #[command(subcommand)]
+    command: Option<Commands>,
+}
+
+/// Maintenance commands (export/import need the node daemon stopped)
+#[derive(Subcommand)]
+enum Commands {
+    /// Export the chunk store to a tar archive (for hardware migration)
+    Export {
+        /// Archive to write
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+    /// Import chunks from an archive created by `export`
+    Import {
+        /// Archive to read
+        #[arg(short, long)]
+        input: PathBuf,
+    },
+    /// Rebuild the chunk locations of this store from its shard headers
+    Salvage {
+        /// Salvage report to write (body for the gateway's salvage endpoint)
+        #[arg(short, long)]
+        output: PathBuf,
+        /// Chunk store to read (default: the configured storage path)
+        #[arg(long)]
+        data_dir: Option<PathBuf>,
+    },
+    /// Show and claim epoch rewards (needs the node owner keypair)
+    Rewards {
+        #[command(subcommand)]
+        action: RewardsCommand,
+    },
+    /// Run a cluster of simulated in-memory nodes (capacity planning, CI)
+    Simulate {
+        /// Number of nodes
+        #[arg(long, env = "SIM_NODES", default_value_t = 10)]
+        nodes: usize,
+        /// Port of the first node; the others listen on the next ports
+        #[arg(long, env = "SIM_BASE_PORT", default_value_t = 60000)]
+        base_port: u16,
+        /// Node IDs are `<prefix>-0000`, `<prefix>-0001`, ...
+        #[arg(long, env = "SIM_ID_PREFIX", default_value = "sim")]
+        id_prefix: String,
+        /// Storage offered by each node, in GB
+        #[arg(long, env = "SIM_CAPACITY_GB", default_value_t = 100)]
+        capacity_gb: u64,
+        /// Regions the nodes are spread over (comma-separated)
+        #[arg(
+            long,
+            env = "SIM_REGIONS",
+            value_delimiter = ',',
+            default_value = "sim-region"
+        )]
+        regions: Vec<String>,
+        /// Racks per region the nodes are spread over
+        #[arg(long, env = "SIM_RACKS_PER_REGION", default_value_t = 4)]
+        racks_per_region: u32,
+        /// Delay added to every chunk read and write
+        #[arg(long, env = "SIM_LATENCY_MS", default_value_t = 0)]
+        latency_ms: u64,
+        /// Chunk transfer rate in Mbit/s (0 = unlimited)
+        #[arg(long, env = "SIM_BANDWIDTH_MBPS", default_value_t = 0)]
+        bandwidth_mbps: u64,
+        /// Chunk reads and writes that fail, from 0.0 to 1.0
+        #[arg(long, env = "SIM_FAILURE_RATE", default_value_t = 0.0)]
+        failure_rate: f64,
+    },
+}
+
+/// Epoch reward operations
+#[derive(Subcommand)]
+enum RewardsCommand {
+    /// Show the current epoch and claimable rewards
+    Status,
+    /// Claim pending rewards
+    Claim {
+        /// Only claim this epoch (default: every epoch with pending rewards)
+        #[arg(long)]
+        epoch: Option<u64>,
+    },
+    /// Show reward allocations of recent epochs
+    History {
+        /// Number of past epochs to show
+        #[arg(long, default_value_t = 10)]
+        epochs: u64,
+    },
 }
 
 #[tokio::main]
@@ -97,78 +217,29 @@ async fn main() -> anyhow::Result<()> {
 
     // Load configuration
     // Priority: CLI args > node config.toml > shared config (~/.cyxcloud/config.toml) > defaults
-    let mut config = NodeConfig::load_or_default(&cli.config)
+    let file_config = if cli.config.exists() {
+        NodeConfig::read_file(&cli.config)
+            .map_err(|e| anyhow::anyhow!("Failed to load {}: {}", cli.config.display(), e))?
+    } else {
+        info!(path = ?cli.config, "No config file, using defaults");
+        NodeConfig::default()
+    };
+    let config = file_config
         .with_shared_config() // Apply shared config from ~/.cyxcloud/config.toml
         .with_overrides(cli.data_dir, cli.port)
         .with_env_overrides();
 
-    // Check if storage capacity is configured
-    if config.storage.max_capacity_gb == 0 {
-        // Check if running in interactive mode (TTY)
-        if atty::is(atty::Stream::Stdin) {
-            use cyxcloud_node::symbols;
-            use std::io::{self, Write};
-
-            println!();
-            println!("{}", symbols::BOX_TOP);
-            println!(
-                "{}  Storage Capacity Not Configured                        {}",
-                symbols::BOX_SIDE,
-                symbols::BOX_SIDE
-            );
-            println!("{}", symbols::BOX_BOTTOM);
-            println!();
-            println!("Your storage capacity (max_capacity_gb) is not set in config.toml.");
-            println!("This determines how much disk space you're offering to the network.");
-            println!();
-            println!("Please enter the storage capacity you want to allocate:");
-            println!();
+    if cli.check_config {
+        return check_config(&config);
+    }
 
-            loop {
-                print!("Storage capacity in GB (e.g., 100): ");
-                io::stdout().flush()?;
-
-                let mut input = String::new();
-                io::stdin().read_line(&mut input)?;
-                let input = input.trim();
-
-                match input.parse::<u64>() {
-                    Ok(gb) if gb >= 1 => {
-                        config.storage.max_capacity_gb = gb;
-                        println!();
-                        println!("{} Storage capacity set to {} GB", symbols::CHECK, gb);
-
-                        // Calculate reserved and available
-                        let reserved_gb = 2;
-                        let available_gb = gb.saturating_sub(reserved_gb);
-                        println!("  System reserved: {} GB", reserved_gb);
-                        println!("  Available for storage: {} GB", available_gb);
-                        println!();
-
-                        // Suggest saving to config
-                        println!("TIP: Add this to your config.toml to skip this prompt:");
-                        println!();
-                        println!("  [storage]");
-                        println!("  max_capacity_gb = {}", gb);
-                        println!();
-                        break;
-                    }
-                    Ok(_) => {
-                        println!("{} Please enter at least 1 GB", symbols::CROSS);
-                    }
-                    Err(_) => {
-                        println!(
-                            "{} Invalid input. Please enter a number (e.g., 100)",
-                            symbols::CROSS
-                        );
-                    }
-                }
-            }
-        } else {
-            // Non-interactive mode, warn but continue with 0 (unlimited)
-            warn!("Storage capacity not configured (max_capacity_gb = 0)");
-            warn!("Set max_capacity_gb in config.toml or run interactively to configure");
-        }
+    // Maintenance commands run instead of the daemon
+    if let Some(command) = cli.command {
+        return match command {
+            Commands::Rewards { action } => run_rewards_command(action, &config).await,
+            command @ Commands::Simulate { .. } => run_simulation_command(command, &config).await,
+            command => run_archive_command(command, &config),
+        };
     }
 
     info!(
@@ -201,10 +272,24 @@ async fn main() -> anyhow::Result<()> {
     };
     info!("Storage backend initialized");
 
+    // Offer a share of the free disk space unless a capacity is configured
+    let capacity_monitor = Arc::new(CapacityMonitor::new(&config.storage, storage.clone()));
+    if capacity_monitor.refresh().is_none() && capacity_monitor.is_auto() {
+        warn!("Storage capacity could not be detected; set max_capacity_gb in config.toml");
+    }
+
     // Create shared state
     let health_state = Arc::new(RwLock::new(HealthState::default()));
     let node_metrics = NodeMetrics::new(&config.node.id);
 
+    // Background tasks cancelled on shutdown
+    let mut background: Vec<JoinHandle<()>> = Vec::new();
+
+    // Re-evaluate the capacity as the disk fills up
+    background.push(tokio::spawn(async move {
+        capacity_monitor.run().await;
+    }));
+
     // Start metrics HTTP server
     let metrics_port = cli.metrics_port.unwrap_or(config.metrics.port);
     if config.metrics.enabled {
@@ -214,31 +299,18 @@ async fn main() -> anyhow::Result<()> {
         let metrics_path = config.metrics.metrics_path.clone();
         let health_state_clone = health_state.clone();
 
-        tokio::spawn(async move {
+        background.push(tokio::spawn(async move {
             if let Err(e) = metrics_server
                 .start(health_path, metrics_path, health_state_clone)
                 .await
             {
                 error!(error = %e, "Metrics server failed");
             }
-        });
+        }));
 
         info!(port = metrics_port, "Metrics server started");
     }
 
-    // Start health checker
-    let health_checker = HealthChecker::new(
-        config.node.id.clone(),
-        storage.clone(),
-        node_metrics.clone(),
-        health_state.clone(),
-    );
-
-    tokio::spawn(async move {
-        health_checker.run().await;
-    });
-    info!("Health checker started");
-
     // ========================================
     // Authentication Flow:
     // 1. Login to CyxWiz API (port 3002) to get JWT token
@@ -295,24 +367,125 @@ async fn main() -> anyhow::Result<()> {
         heartbeat_service.set_credentials_wallet(wallet).await;
     }
 
+    // Start health checker (disk problems are reported through the heartbeat)
+    let mut health_checker = HealthChecker::new(
+        config.node.id.clone(),
+        storage.clone(),
+        node_metrics.clone(),
+        health_state.clone(),
+    );
+    if config.disk_health.enabled {
+        health_checker = health_checker.with_disk_health(DiskHealthSampler::new(
+            config.storage.data_dir.clone(),
+            config.disk_health.clone(),
+        ));
+    }
+    if config.central.register {
+        health_checker = health_checker.with_heartbeat_service(heartbeat_service.clone());
+    }
+
+    background.push(tokio::spawn(async move {
+        health_checker.run().await;
+    }));
+    info!(
+        disk_health = config.disk_health.enabled,
+        "Health checker started"
+    );
+
+    // Start self-scrub and compaction scheduling (damage is reported to the gateway)
+    if config.maintenance.enabled {
+        let mut maintenance = MaintenanceScheduler::new(
+            storage.clone(),
+            config.maintenance.clone(),
+            node_metrics.clone(),
+        );
+        if config.central.register {
+            maintenance = maintenance.with_reporter(heartbeat_service.clone());
+        }
+        background.push(tokio::spawn(async move {
+            maintenance.run().await;
+        }));
+    }
+
+    // Start P2P discovery (peer store counts are exported as metrics)
+    let mut status_gossip = None;
+    if config.network.enable_p2p {
+        let mut discovery = DiscoveryService::new(config.discovery_config());
+        let registry = discovery.registry();
+
+        // Status and capacity changes are gossiped to the rest of the cluster
+        let (gossip_tx, gossip_rx) = mpsc::channel(STATUS_GOSSIP_QUEUE);
+        discovery.set_publish_channel(gossip_rx);
+        let announcer = Arc::new(
+            NodeAnnouncer::new(
+                config.node.id.clone(),
+                heartbeat_service.grpc_address().to_string(),
+                storage.clone(),
+            )
+            .with_region(config.node.region.clone())
+            .with_heartbeat(heartbeat_service.clone()),
+        );
+        let announcer_clone = announcer.clone();
+        let tx = gossip_tx.clone();
+        background.push(tokio::spawn(async move {
+            announcer_clone.run(tx).await;
+        }));
+        status_gossip = Some((announcer, gossip_tx));
+
+        background.push(tokio::spawn(async move {
+            if let Err(e) = discovery.run().await {
+                error!(error = %e, "P2P discovery failed");
+            }
+        }));
+
+        let metrics = node_metrics.clone();
+        background.push(tokio::spawn(async move {
+            let mut interval = tokio::time::interval(PEER_METRICS_INTERVAL);
+            loop {
+                interval.tick().await;
+                metrics.update_peers(&registry.stats());
+            }
+        }));
+        info!(
+            p2p_port = config.network.p2p_port,
+            bootstrap_peers = config.network.bootstrap_peers.len(),
+            "P2P discovery started"
+        );
+    }
+
     // Start Gateway heartbeat service
+    let mut heartbeat_handle = None;
     if config.central.register {
         let heartbeat_clone = heartbeat_service.clone();
-        tokio::spawn(async move {
+        heartbeat_handle = Some(tokio::spawn(async move {
             heartbeat_clone.run().await;
-        });
+        }));
         info!(
             central_addr = %config.central.address,
+            fallback_gateways = config.central.fallback_addresses.len(),
             "Gateway heartbeat service started (with JWT auth)"
         );
     }
 
+    // Start the self-updater; it reports an installed release on update_rx
+    let (update_tx, mut update_rx) = oneshot::channel();
+    let mut update_binary = None;
+    if config.update.enabled {
+        match Updater::new(&config.update, &config.cyxwiz_api, &config.node.id) {
+            Ok(updater) => {
+                update_binary = Some(updater.binary().to_path_buf());
+                background.push(tokio::spawn(updater.run(update_tx)));
+            }
+            Err(e) => error!(error = %e, "Self-update disabled"),
+        }
+    }
+
     // Start CyxWiz API machine service (for heartbeats to CyxWiz API)
     if config.cyxwiz_api.register {
         let machine_service_clone = machine_service.clone();
-        tokio::spawn(async move {
+        background.push(tokio::spawn(async move {
             machine_service_clone.run(already_registered).await;
-        });
+        }));
         info!(
             api_url = %config.cyxwiz_api.base_url,
             "CyxWiz API machine service started"
@@ -323,16 +496,17 @@ async fn main() -> anyhow::Result<()> {
     #[cfg(feature = "blockchain")]
     {
         if config.blockchain.enabled {
-            match initialize_blockchain_service(&config).await {
-                Ok(Some((client, heartbeat_handle))) => {
+            match initialize_blockchain_service(&config, storage.max_capacity()).await {
+                Ok(Some((client, handles))) => {
                     info!(
                         rpc_url = %config.blockchain.rpc_url,
                         heartbeat = config.blockchain.heartbeat_enabled,
+                        auto_claim = config.blockchain.auto_claim_rewards,
                         "Blockchain service started"
                     );
-                    // Store client and handle for later use
-                    // The heartbeat service runs in the background
-                    let _ = (client, heartbeat_handle);
+                    // Heartbeat and auto-claim run in the background
+                    let _ = client;
+                    background.extend(handles);
                 }
                 Ok(None) => {
                     warn!("Blockchain service enabled but no keypair configured");
@@ -348,7 +522,32 @@ async fn main() -> anyhow::Result<()> {
     let grpc_addr = config.network.grpc_addr();
     info!(addr = %grpc_addr, "Starting gRPC server...");
 
-    let grpc_server = start_grpc_server(grpc_addr, storage.clone(), config.node.id.clone());
+    // Key other nodes encrypt repair transfers to; kept with the data
+    let transfer_key_path = config.storage.data_dir.join("transfer.key");
+    let transfer_key = cyxcloud_network::TransferKey::load_or_generate(&transfer_key_path)
+        .map_err(|e| {
+            anyhow::anyhow!(
+                "Failed to load transfer key {}: {}",
+                transfer_key_path.display(),
+                e
+            )
+        })?;
+    info!(key = ?transfer_key, "Transfer key loaded");
+
+    let accepting_writes = Arc::new(AtomicBool::new(true));
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    let mut grpc_server = tokio::spawn(start_grpc_server(
+        grpc_addr,
+        config.network.read_limits(),
+        config.network.write_limits(),
+        config.network.cluster_token.clone(),
+        transfer_key,
+        config.network.encrypt_transfers,
+        storage.clone(),
+        config.node.id.clone(),
+        accepting_writes.clone(),
+        shutdown_rx,
+    ));
 
     // Print startup summary
     info!("========================================");
@@ -383,59 +582,542 @@ async fn main() -> anyhow::Result<()> {
     info!("Press Ctrl+C to shut down");
 
     // Wait for shutdown signal
-    tokio::select! {
-        result = grpc_server => {
-            if let Err(e) = result {
-                error!(error = %e, "gRPC server error");
+    let mut grpc_finished = false;
+    let reason = tokio::select! {
+        result = &mut grpc_server => {
+            grpc_finished = true;
+            match result {
+                Ok(Err(e)) => error!(error = %e, "gRPC server error"),
+                Err(e) => error!(error = %e, "gRPC server task failed"),
+                Ok(Ok(())) => {}
             }
+            "grpc server stopped"
         }
-        _ = tokio::signal::ctrl_c() => {
-            info!("Received shutdown signal");
+        signal = shutdown_signal() => {
+            info!(signal = signal, "Received shutdown signal");
+            signal
         }
-    }
+        Ok(version) = &mut update_rx => {
+            info!(version = %version, "Restarting on the updated binary");
+            "update installed"
+        }
+    };
 
     // Graceful shutdown
     info!("Shutting down...");
+    let shutdown_timeout = Duration::from_secs(config.central.shutdown_timeout_secs);
+
+    // 1. Stop accepting new chunks; reads keep working while we drain
+    accepting_writes.store(false, Ordering::Release);
+
+    // 2. Tell the Gateway (and peers) we are leaving so they stop placing shards here
+    if let Some((announcer, tx)) = &status_gossip {
+        let status = match config.central.shutdown_status.as_str() {
+            "offline" => NodeStatus::Offline,
+            _ => NodeStatus::Maintenance,
+        };
+        announcer.announce(tx, status).await;
+    }
+    if let Some(handle) = heartbeat_handle.take() {
+        handle.abort();
+        let reason = format!("node shutdown ({})", reason);
+
+        // Optionally wait for our shards to be copied elsewhere first
+        if cli.drain_on_shutdown {
+            drain_before_shutdown(&heartbeat_service, &config, &reason).await;
+        }
+
+        match tokio::time::timeout(
+            shutdown_timeout,
+            heartbeat_service.send_shutdown_heartbeat(&reason),
+        )
+        .await
+        {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => warn!(error = %e, "Failed to report shutdown to Gateway"),
+            Err(_) => warn!("Timed out reporting shutdown to Gateway"),
+        }
+    }
+
+    // 3. Let in-flight gRPC requests finish
+    let _ = shutdown_tx.send(true);
+    if !grpc_finished
+        && tokio::time::timeout(shutdown_timeout, &mut grpc_server)
+            .await
+            .is_err()
+    {
+        warn!("gRPC server did not stop in time, aborting");
+        grpc_server.abort();
+    }
+
+    // 4. Cancel background services
+    for handle in background {
+        handle.abort();
+    }
 
-    // Give services time to finish
-    tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+    // 5. Flush storage to disk
+    match storage.flush() {
+        Ok(()) => info!("Storage flushed"),
+        Err(e) => error!(error = %e, "Failed to flush storage"),
+    }
 
     info!("CyxCloud node stopped");
+    if reason == "update installed" {
+        if let Some(binary) = update_binary {
+            updater::restart(&config.update, &binary);
+        }
+    }
+    Ok(())
+}
+
+/// Run every configuration check, print the findings, and fail on errors
+fn check_config(config: &NodeConfig) -> anyhow::Result<()> {
+    use cyxcloud_node::symbols;
+
+    let mut report = config.lint();
+    report.merge(config.check_environment());
+
+    for issue in &report.issues {
+        println!("{}", issue);
+    }
+
+    let errors = report.errors().count();
+    if errors > 0 {
+        anyhow::bail!("configuration has {} error(s)", errors);
+    }
+    println!("{} Configuration OK", symbols::CHECK);
     Ok(())
 }
 
+/// Run an export, import or salvage against the local chunk store
+fn run_archive_command(command: Commands, config: &NodeConfig) -> anyhow::Result<()> {
+    let mut storage_config = config.storage.to_storage_config();
+    if let Commands::Salvage {
+        data_dir: Some(dir),
+        ..
+    } = &command
+    {
+        storage_config.path = dir.clone();
+    }
+
+    // Opening the store fails if the daemon is still running (RocksDB lock)
+    let storage = RocksDbBackend::open(storage_config)
+        .map_err(|e| anyhow::anyhow!("Failed to open chunk store (is the node running?): {}", e))?;
+
+    match command {
+        Commands::Export { output } => {
+            let file = std::io::BufWriter::new(std::fs::File::create(&output)?);
+            let summary = export_chunks(&storage, &config.node.id, file)?;
+
+            println!(
+                "Exported {} chunks ({} bytes) to {}",
+                summary.exported,
+                summary.bytes,
+                output.display()
+            );
+            if !summary.corrupted.is_empty() {
+                println!(
+                    "Skipped {} corrupted chunks (data does not match ID):",
+                    summary.corrupted.len()
+                );
+                for id in &summary.corrupted {
+                    println!("  {}", id);
+                }
+            }
+        }
+        Commands::Import { input } => {
+            let file = std::io::BufReader::new(std::fs::File::open(&input)?);
+            let summary = import_chunks(&storage, file)?;
+
+            println!(
+                "Imported {} chunks ({} bytes), {} already present",
+                summary.imported, summary.bytes, summary.already_present
+            );
+            if !summary.corrupted.is_empty() {
+                println!(
+                    "Rejected {} chunks failing integrity checks",
+                    summary.corrupted.len()
+                );
+            }
+            if !summary.missing.is_empty() {
+                println!(
+                    "{} chunks listed in the manifest were missing from the archive",
+                    summary.missing.len()
+                );
+            }
+
+            match summary.adoption_request() {
+                Some(request) => {
+                    let path = PathBuf::from(format!("{}.adopt.json", input.display()));
+                    std::fs::write(&path, serde_json::to_vec_pretty(&request)?)?;
+                    println!();
+                    println!(
+                        "Chunks are still registered to node {}.",
+                        request.from_node_id
+                    );
+                    println!("Once this node is registered, re-associate them with:");
+                    println!("  curl -X POST -H 'Authorization: Bearer <admin token>' \\");
+                    println!(
+                        "    -H 'Content-Type: application/json' -d @{} \\",
+                        path.display()
+                    );
+                    println!(
+                        "    <gateway>/api/v1/admin/nodes/{}/chunks/adopt",
+                        config.node.id
+                    );
+                }
+                None => println!("Archive has no manifest; chunk locations were not recorded"),
+            }
+        }
+        Commands::Salvage { output, .. } => {
+            let report = salvage_chunks(&storage, &config.node.id)?;
+            std::fs::write(&output, serde_json::to_vec_pretty(&report)?)?;
+
+            println!(
+                "Recovered {} shards ({} bytes) to {}",
+                report.shards.len(),
+                report.bytes(),
+                output.display()
+            );
+            if !report.unlabelled.is_empty() {
+                println!(
+                    "{} chunks have no shard header and cannot be placed",
+                    report.unlabelled.len()
+                );
+            }
+            if !report.corrupted.is_empty() {
+                println!(
+                    "Skipped {} corrupted chunks (data does not match ID)",
+                    report.corrupted.len()
+                );
+            }
+
+            println!();
+            println!("Restore the chunk locations with:");
+            println!("  curl -X POST -H 'Authorization: Bearer <admin token>' \\");
+            println!(
+                "    -H 'Content-Type: application/json' -d @{} \\",
+                output.display()
+            );
+            println!(
+                "    <gateway>/api/v1/admin/nodes/{}/chunks/salvage",
+                config.node.id
+            );
+        }
+        Commands::Rewards { .. } => unreachable!("handled by run_rewards_command"),
+        Commands::Simulate { .. } => unreachable!("handled by run_simulation_command"),
+    }
+
+    Ok(())
+}
+
+/// Run a simulated cluster against the configured gateway until interrupted
+async fn run_simulation_command(command: Commands, config: &NodeConfig) -> anyhow::Result<()> {
+    let Commands::Simulate {
+        nodes,
+        base_port,
+        id_prefix,
+        capacity_gb,
+        regions,
+        racks_per_region,
+        latency_ms,
+        bandwidth_mbps,
+        failure_rate,
+    } = command
+    else {
+        unreachable!("only simulate is run here");
+    };
+
+    let simulation = SimulationConfig {
+        nodes,
+        id_prefix,
+        gateway: config.central.address.clone(),
+        bind_address: config.network.bind_address.clone(),
+        advertise_host: config
+            .network
+            .public_address
+            .clone()
+            .unwrap_or_else(|| config.network.bind_address.clone()),
+        base_port,
+        capacity_bytes: capacity_gb * 1024 * 1024 * 1024,
+        regions,
+        racks_per_region,
+        heartbeat_interval_secs: config.central.heartbeat_interval_secs,
+        cluster_token: config.network.cluster_token.clone(),
+        profile: SimulationProfile {
+            latency_ms,
+            bandwidth_mbps,
+            failure_rate,
+        },
+    };
+
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    let cluster = tokio::spawn(run_simulation(simulation, shutdown_rx));
+
+    let signal = shutdown_signal().await;
+    info!(signal = signal, "Received shutdown signal");
+    let _ = shutdown_tx.send(true);
+    cluster.await?
+}
+
+/// Run a `rewards` subcommand against the payment pool
+#[cfg(feature = "blockchain")]
+async fn run_rewards_command(action: RewardsCommand, config: &NodeConfig) -> anyhow::Result<()> {
+    use cyxcloud_node::symbols;
+
+    let (keypair_path, owner) = load_owner_keypair(config)?.ok_or_else(|| {
+        anyhow::anyhow!("No node owner keypair found (set blockchain.keypair_path)")
+    })?;
+    let client = StorageNodeBlockchainClient::new(
+        NodeBlockchainConfig {
+            rpc_url: config.blockchain.rpc_url.clone(),
+            keypair_path: Some(keypair_path),
+            ..NodeBlockchainConfig::default()
+        },
+        owner,
+    )?;
+    let node_id = &config.node.id;
+    let max_attempts = config.blockchain.claim_max_attempts;
+
+    match action {
+        RewardsCommand::Status => {
+            let current_epoch = client.get_current_epoch().await?;
+            let pending = client.get_pending_rewards(node_id).await?;
+
+            println!("Node:          {}", node_id);
+            println!("Owner:         {}", client.owner_pubkey());
+            println!("Current epoch: {}", current_epoch);
+            println!();
+            if pending.is_empty() {
+                println!("No claimable rewards");
+            } else {
+                println!("Claimable rewards:");
+                for claim in &pending {
+                    println!(
+                        "  epoch {:>6}  {} CYXWIZ",
+                        claim.epoch,
+                        format_cyxwiz(claim.reward_amount)
+                    );
+                }
+                let total: u64 = pending.iter().map(|c| c.reward_amount).sum();
+                println!("  total         {} CYXWIZ", format_cyxwiz(total));
+                println!();
+                println!("Claim them with: cyxcloud-node rewards claim");
+            }
+        }
+        RewardsCommand::Claim { epoch: Some(epoch) } => {
+            let claim = client
+                .get_epoch_claim(node_id, epoch)
+                .await?
+                .ok_or_else(|| anyhow::anyhow!("No reward allocated for epoch {}", epoch))?;
+            if claim.claimed {
+                println!("Epoch {} was already claimed", epoch);
+                return Ok(());
+            }
+            match client
+                .claim_rewards_with_retry(node_id, epoch, max_attempts)
+                .await?
+            {
+                Some(sig) => println!(
+                    "{} Claimed {} CYXWIZ for epoch {} ({})",
+                    symbols::CHECK,
+                    format_cyxwiz(claim.reward_amount),
+                    epoch,
+                    sig
+                ),
+                None => println!("Epoch {} was already claimed", epoch),
+            }
+        }
+        RewardsCommand::Claim { epoch: None } => {
+            let outcomes = client.claim_all_rewards(node_id, max_attempts).await?;
+            if outcomes.is_empty() {
+                println!("No claimable rewards");
+                return Ok(());
+            }
+
+            let mut failed = 0;
+            for outcome in &outcomes {
+                let amount = format_cyxwiz(outcome.reward_amount);
+                match &outcome.result {
+                    Ok(Some(sig)) => println!(
+                        "{} epoch {:>6}  {} CYXWIZ  {}",
+                        symbols::CHECK,
+                        outcome.epoch,
+                        amount,
+                        sig
+                    ),
+                    Ok(None) => println!(
+                        "{} epoch {:>6}  {} CYXWIZ  already claimed",
+                        symbols::CHECK,
+                        outcome.epoch,
+                        amount
+                    ),
+                    Err(e) => {
+                        failed += 1;
+                        println!(
+                            "{} epoch {:>6}  {} CYXWIZ  failed: {}",
+                            symbols::CROSS,
+                            outcome.epoch,
+                            amount,
+                            e
+                        );
+                    }
+                }
+            }
+            if failed > 0 {
+                anyhow::bail!("{} of {} claims failed", failed, outcomes.len());
+            }
+        }
+        RewardsCommand::History { epochs } => {
+            let history = client.get_reward_history(node_id, epochs).await?;
+            if history.is_empty() {
+                println!("No rewards in the last {} epochs", epochs);
+                return Ok(());
+            }
+
+            println!("{:>8}  {:>20}  STATUS", "EPOCH", "REWARD (CYXWIZ)");
+            for claim in history.iter().rev() {
+                println!(
+                    "{:>8}  {:>20}  {}",
+                    claim.epoch,
+                    format_cyxwiz(claim.reward_amount),
+                    if claim.claimed {
+                        "claimed"
+                    } else {
+                        "unclaimed"
+                    }
+                );
+            }
+            let total: u64 = history.iter().map(|c| c.reward_amount).sum();
+            println!("{:>8}  {:>20}", "total", format_cyxwiz(total));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(not(feature = "blockchain"))]
+async fn run_rewards_command(_action: RewardsCommand, _config: &NodeConfig) -> anyhow::Result<()> {
+    anyhow::bail!("Rewards need a cyxcloud-node built with the `blockchain` feature")
+}
+
+/// Format a token amount (9 decimals) as CYXWIZ
+#[cfg(feature = "blockchain")]
+fn format_cyxwiz(amount: u64) -> String {
+    format!("{}.{:09}", amount / 1_000_000_000, amount % 1_000_000_000)
+}
+
+/// Ask the Gateway to drain this node and wait until its shards are evacuated
+///
+/// Gives up after `central.drain_timeout_secs` or on a second shutdown signal;
+/// the node then exits anyway and the Gateway repairs what is left.
+async fn drain_before_shutdown(
+    heartbeat_service: &HeartbeatService,
+    config: &NodeConfig,
+    reason: &str,
+) {
+    if let Err(e) = heartbeat_service.request_drain(reason).await {
+        warn!(error = %e, "Failed to request drain, shutting down without evacuation");
+        return;
+    }
+
+    let poll_interval = Duration::from_secs(config.central.drain_poll_secs);
+    let wait = async {
+        loop {
+            match heartbeat_service.drain_progress(reason).await {
+                Ok(0) => break,
+                Ok(pending) => info!(pending_shards = pending, "Waiting for shard evacuation"),
+                Err(e) => warn!(error = %e, "Failed to check drain progress"),
+            }
+            tokio::time::sleep(poll_interval).await;
+        }
+    };
+
+    info!(
+        timeout_secs = config.central.drain_timeout_secs,
+        "Draining node before shutdown (send the signal again to skip)"
+    );
+    let drain_timeout = Duration::from_secs(config.central.drain_timeout_secs);
+    tokio::select! {
+        result = tokio::time::timeout(drain_timeout, wait) => {
+            match result {
+                Ok(()) => info!("All shards evacuated"),
+                Err(_) => warn!("Timed out waiting for shard evacuation"),
+            }
+        }
+        signal = shutdown_signal() => {
+            warn!(signal = signal, "Drain interrupted, shutting down");
+        }
+    }
+}
+
+/// Wait for Ctrl+C or SIGTERM, returning the signal name
+async fn shutdown_signal() -> &'static str {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        match signal(SignalKind::terminate()) {
+            Ok(mut sigterm) => tokio::select! {
+                _ = tokio::signal::ctrl_c() => "SIGINT",
+                _ = sigterm.recv() => "SIGTERM",
+            },
+            Err(e) => {
+                warn!(error = %e, "Failed to install SIGTERM handler");
+                let _ = tokio::signal::ctrl_c().await;
+                "SIGINT"
+            }
+        }
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+        "SIGINT"
+    }
+}
+
 /// Start the gRPC server for chunk operations
+#[allow(clippy::too_many_arguments)]
 async fn start_grpc_server(
     addr: std::net::SocketAddr,
+    read_limits: cyxcloud_network::AdmissionConfig,
+    write_limits: cyxcloud_network::AdmissionConfig,
+    cluster_token: Option<String>,
+    transfer_key: cyxcloud_network::TransferKey,
+    encrypt_transfers: bool,
     storage: Arc<RocksDbBackend>,
     node_id: String,
+    accepting_writes: Arc<AtomicBool>,
+    mut shutdown: watch::Receiver<bool>,
 ) -> anyhow::Result<()> {
     use cyxcloud_network::grpc_server::ChunkServiceImpl;
     use cyxcloud_protocol::ChunkServiceServer;
     use tonic::transport::Server;
 
-    let chunk_service = ChunkServiceImpl::new(storage, node_id);
+    let chunk_service = ChunkServiceImpl::new(storage, node_id)
+        .with_write_gate(accepting_writes)
+        .with_limits(read_limits, write_limits)
+        .with_cluster_token(cluster_token)
+        .with_transfer_key(Some(Arc::new(transfer_key)))
+        .with_transfer_encryption(encrypt_transfers);
 
     Server::builder()
         .add_service(ChunkServiceServer::new(chunk_service))
-        .serve(addr)
+        .serve_with_shutdown(addr, async move {
+            let _ = shutdown.changed().await;
+        })
         .await?;
 
     Ok(())
 }
 
-/// Initialize blockchain service for Solana integration
+/// Load the node owner keypair from the configured or default path
+///
+/// Returns `None` if no path is configured and the default keypair does not
+/// exist.
 #[cfg(feature = "blockchain")]
-async fn initialize_blockchain_service(
-    config: &NodeConfig,
-) -> anyhow::Result<
-    Option<(
-        Arc<StorageNodeBlockchainClient>,
-        tokio::task::JoinHandle<()>,
-    )>,
-> {
-    use cyxcloud_node::blockchain::heartbeat::HeartbeatOps;
-    use solana_sdk::signature::Keypair;
+fn load_owner_keypair(config: &NodeConfig) -> anyhow::Result<Option<(String, Keypair)>> {
     use std::path::Path;
 
     // Check if keypair is configured
@@ -468,6 +1150,26 @@ async fn initialize_blockchain_service(
         "Loaded blockchain keypair"
     );
 
+    Ok(Some((keypair_path, owner)))
+}
+
+/// Initialize blockchain service for Solana integration
+///
+/// `capacity` is the offered storage in bytes, configured or detected.
+/// Returns the client and the handles of the heartbeat and auto-claim tasks
+/// that were enabled.
+#[cfg(feature = "blockchain")]
+async fn initialize_blockchain_service(
+    config: &NodeConfig,
+    capacity: u64,
+) -> anyhow::Result<Option<(Arc<StorageNodeBlockchainClient>, Vec<JoinHandle<()>>)>> {
+    use cyxcloud_node::blockchain::heartbeat::HeartbeatOps;
+
+    let (keypair_path, owner) = match load_owner_keypair(config)? {
+        Some(loaded) => loaded,
+        None => return Ok(None),
+    };
+
     // Create blockchain config
     let blockchain_config = NodeBlockchainConfig {
         rpc_url: config.blockchain.rpc_url.clone(),
@@ -489,7 +1191,7 @@ async fn initialize_blockchain_service(
 
         // Create storage spec from config
         let spec = StorageSpec::new(
-            config.storage.max_capacity_gb * 1024 * 1024 * 1024,
+            capacity,
             DiskType::SSD, // Default to SSD, could be configurable
             config
                 .node
@@ -512,8 +1214,10 @@ async fn initialize_blockchain_service(
         info!(node_id = %config.node.id, "Node already registered on blockchain");
     }
 
+    let mut handles = Vec::new();
+
     // Start blockchain heartbeat service if enabled
-    let heartbeat_handle = if config.blockchain.heartbeat_enabled {
+    if config.blockchain.heartbeat_enabled {
         let node_registry_program_id = client.config().node_registry_program_id;
         let rpc_client = Arc::new(solana_client::rpc_client::RpcClient::new(
             config.blockchain.rpc_url.clone(),
@@ -535,20 +1239,34 @@ async fn initialize_blockchain_service(
             config.blockchain.heartbeat_interval_secs,
         );
 
-        let handle = tokio::spawn(async move {
+        handles.push(tokio::spawn(async move {
             heartbeat_service.run().await;
-        });
+        }));
 
         info!(
             interval_secs = config.blockchain.heartbeat_interval_secs,
             "Blockchain heartbeat service started"
         );
+    }
 
-        handle
-    } else {
-        // Return a dummy handle that completes immediately
-        tokio::spawn(async {})
-    };
+    // Start reward auto-claim service if enabled
+    if config.blockchain.auto_claim_rewards {
+        let claim_service = RewardsClaimService::new(
+            client.clone(),
+            config.node.id.clone(),
+            config.blockchain.auto_claim_interval_secs,
+            config.blockchain.claim_max_attempts,
+        );
+
+        handles.push(tokio::spawn(async move {
+            claim_service.run().await;
+        }));
+
+        info!(
+            interval_secs = config.blockchain.auto_claim_interval_secs,
+            "Reward auto-claim service started"
+        );
+    }
 
-    Ok(Some((client, heartbeat_handle)))
+    Ok(Some((client, handles)))
 }
@@ -0,0 +1,554 @@
+//! Local storage maintenance for CyxCloud storage node
+//!
+//! Three loops run side by side:
+//! - **Self-scrub**: walks every stored chunk at a bounded rate and checks
+//!   that its data still hashes to its key. Damaged chunks are dropped
+//!   locally and reported to the gateway, which removes the location and
+//!   repairs the chunk from other replicas without waiting for an audit.
+//! - **Compaction**: runs a manual RocksDB compaction inside the configured
+//!   low-traffic window, at most once per interval and only while the node is
+//!   quiet.
+//! - **Purge**: physically removes chunks that deletes only tombstoned, in
+//!   batches and only while the node is quiet. Deletes older than
+//!   `purge_max_delay_hours` are purged regardless of traffic so a busy node
+//!   still gets its space back.
+
+use crate::config::MaintenanceSettings;
+use crate::health::HeartbeatService;
+use crate::metrics::NodeMetrics;
+use chrono::{Timelike, Utc};
+use cyxcloud_core::chunk::ChunkId;
+use cyxcloud_storage::backend::StorageBackendSync;
+use cyxcloud_storage::{PurgeSummary, RocksDbBackend};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::Mutex;
+use tracing::{debug, error, info, warn};
+
+/// Chunks verified per blocking batch
+const SCRUB_BATCH: usize = 64;
+
+/// Damaged chunks collected before a report is sent mid-pass
+const REPORT_BATCH: usize = 256;
+
+/// Damaged chunks kept for retry while the gateway is unreachable
+const MAX_PENDING_REPORT: usize = 10_000;
+
+/// How often the compaction window and traffic are checked
+const COMPACTION_CHECK_INTERVAL: Duration = Duration::from_secs(300);
+
+/// How often pending deletes and traffic are checked for a purge sweep
+const PURGE_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Pause between purge batches, leaving room for foreground I/O
+const PURGE_BATCH_PAUSE: Duration = Duration::from_millis(100);
+
+/// Chunks found damaged by the scrub, awaiting report to the gateway
+#[derive(Debug, Clone, Default)]
+pub struct DamageReport {
+    /// Data no longer hashes to the chunk ID
+    pub corrupted: Vec<ChunkId>,
+    /// Listed in the store but could not be read
+    pub missing: Vec<ChunkId>,
+}
+
+impl DamageReport {
+    /// Number of damaged chunks
+    pub fn len(&self) -> usize {
+        self.corrupted.len() + self.missing.len()
+    }
+
+    /// Whether nothing is waiting to be reported
+    pub fn is_empty(&self) -> bool {
+        self.corrupted.is_empty() && self.missing.is_empty()
+    }
+}
+
+/// Result of checking one stored chunk
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ChunkCheck {
+    Valid,
+    Corrupted,
+    Unreadable,
+    /// Deleted between listing and checking
+    Gone,
+}
+
+/// Outcome of a full scrub pass
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ScrubSummary {
+    pub verified: u64,
+    pub corrupted: u64,
+    pub missing: u64,
+}
+
+/// Schedules self-scrub and compaction for the local chunk store
+pub struct MaintenanceScheduler {
+    storage: Arc<RocksDbBackend>,
+    settings: MaintenanceSettings,
+    metrics: NodeMetrics,
+    /// Reports damage to the gateway (none when not registered)
+    reporter: Option<Arc<HeartbeatService>>,
+    /// Damage not yet accepted by the gateway
+    pending: Mutex<DamageReport>,
+    /// Storage reads issued by the scrub, excluded from the traffic estimate
+    scrub_reads: AtomicU64,
+}
+
+impl MaintenanceScheduler {
+    /// Create a scheduler for the given store
+    pub fn new(
+        storage: Arc<RocksDbBackend>,
+        settings: MaintenanceSettings,
+        metrics: NodeMetrics,
+    ) -> Self {
+        Self {
+            storage,
+            settings,
+            metrics,
+            reporter: None,
+            pending: Mutex::new(DamageReport::default()),
+            scrub_reads: AtomicU64::new(0),
+        }
+    }
+
+    /// Report damaged chunks to the gateway through the heartbeat service
+    pub fn with_reporter(mut self, heartbeat: Arc<HeartbeatService>) -> Self {
+        self.reporter = Some(heartbeat);
+        self
+    }
+
+    /// Run the scrub and compaction loops until the task is cancelled
+    pub async fn run(&self) {
+        info!(
+            scrub_interval_hours = self.settings.scrub_interval_hours,
+            scrub_rate = self.settings.scrub_chunks_per_sec,
+            window_start = self.settings.compaction_window_start_hour,
+            window_end = self.settings.compaction_window_end_hour,
+            "Maintenance scheduler started"
+        );
+        tokio::join!(self.scrub_loop(), self.compaction_loop(), self.purge_loop());
+    }
+
+    async fn scrub_loop(&self) {
+        let interval = Duration::from_secs(self.settings.scrub_interval_hours.max(1) * 3600);
+        loop {
+            let started = Instant::now();
+            let summary = self.scrub_pass().await;
+            info!(
+                verified = summary.verified,
+                corrupted = summary.corrupted,
+                missing = summary.missing,
+                elapsed_secs = started.elapsed().as_secs(),
+                "Self-scrub pass complete"
+            );
+            tokio::time::sleep(interval.saturating_sub(started.elapsed())).await;
+        }
+    }
+
+    /// Verify every stored chunk once
+    pub async fn scrub_pass(&self) -> ScrubSummary {
+        let mut summary = ScrubSummary::default();
+
+        let storage = self.storage.clone();
+        let chunk_ids = match tokio::task::spawn_blocking(move || storage.list_chunks()).await {
+            Ok(Ok(ids)) => ids,
+            Ok(Err(e)) => {
+                error!(error = %e, "Failed to list chunks for scrub");
+                return summary;
+            }
+            Err(e) => {
+                error!(error = %e, "Scrub listing task failed");
+                return summary;
+            }
+        };
+        debug!(chunks = chunk_ids.len(), "Starting self-scrub pass");
+
+        // Pace batches so the scrub stays at the configured rate
+        let rate = self.settings.scrub_chunks_per_sec.max(1) as f64;
+        let batch_period = Duration::from_secs_f64(SCRUB_BATCH as f64 / rate);
+
+        for batch in chunk_ids.chunks(SCRUB_BATCH) {
+            let batch_started = Instant::now();
+            let storage = self.storage.clone();
+            let batch = batch.to_vec();
+            let delete_damaged = self.settings.delete_damaged;
+
+            let results = match tokio::task::spawn_blocking(move || {
+                batch
+                    .into_iter()
+                    .map(|id| {
+                        let check = check_chunk(&storage, id);
+                        if delete_damaged && matches!(check, ChunkCheck::Corrupted) {
+                            if let Err(e) = storage.delete(id) {
+                                warn!(chunk_id = %id, error = %e, "Failed to delete corrupted chunk");
+                            }
+                        }
+                        (id, check)
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .await
+            {
+                Ok(results) => results,
+                Err(e) => {
+                    error!(error = %e, "Scrub batch task failed");
+                    continue;
+                }
+            };
+            self.scrub_reads
+                .fetch_add(results.len() as u64, Ordering::Relaxed);
+
+            let (mut verified, mut corrupted, mut missing) = (0, 0, 0);
+            {
+                let mut pending = self.pending.lock().await;
+                for (id, check) in results {
+                    match check {
+                        ChunkCheck::Valid => verified += 1,
+                        ChunkCheck::Corrupted => {
+                            warn!(chunk_id = %id, "Scrub found corrupted chunk");
+                            corrupted += 1;
+                            if pending.len() < MAX_PENDING_REPORT {
+                                pending.corrupted.push(id);
+                            }
+                        }
+                        ChunkCheck::Unreadable => {
+                            warn!(chunk_id = %id, "Scrub could not read chunk");
+                            missing += 1;
+                            if pending.len() < MAX_PENDING_REPORT {
+                                pending.missing.push(id);
+                            }
+                        }
+                        ChunkCheck::Gone => {}
+                    }
+                }
+            }
+            self.metrics.record_scrub(verified, corrupted, missing);
+            summary.verified += verified;
+            summary.corrupted += corrupted;
+            summary.missing += missing;
+
+            if self.pending.lock().await.len() >= REPORT_BATCH {
+                self.flush_damage().await;
+            }
+
+            tokio::time::sleep(batch_period.saturating_sub(batch_started.elapsed())).await;
+        }
+
+        self.flush_damage().await;
+        summary
+    }
+
+    /// Send pending damage to the gateway; kept for the next try on failure
+    async fn flush_damage(&self) {
+        let Some(reporter) = &self.reporter else {
+            return;
+        };
+
+        let report = {
+            let pending = self.pending.lock().await;
+            if pending.is_empty() {
+                return;
+            }
+            pending.clone()
+        };
+
+        match reporter.report_chunk_damage(&report).await {
+            Ok(removed) => {
+                info!(
+                    corrupted = report.corrupted.len(),
+                    missing = report.missing.len(),
+                    locations_removed = removed,
+                    "Reported damaged chunks to gateway"
+                );
+                // Drop what was sent; the scrub may have added more meanwhile
+                let mut pending = self.pending.lock().await;
+                pending.corrupted.drain(..report.corrupted.len());
+                pending.missing.drain(..report.missing.len());
+            }
+            Err(e) => {
+                warn!(error = %e, pending = report.len(), "Failed to report damaged chunks");
+            }
+        }
+    }
+
+    async fn compaction_loop(&self) {
+        let mut ticker = tokio::time::interval(COMPACTION_CHECK_INTERVAL);
+        let mut last_ops = self.client_ops();
+        let mut last_check = Instant::now();
+        let mut last_compaction: Option<Instant> = None;
+        let min_gap = Duration::from_secs(self.settings.compaction_interval_hours * 3600);
+
+        // The first tick completes immediately and has no traffic sample yet
+        ticker.tick().await;
+
+        loop {
+            ticker.tick().await;
+
+            let ops = self.client_ops();
+            let elapsed = last_check.elapsed().as_secs().max(1);
+            let ops_per_sec = ops.saturating_sub(last_ops) / elapsed;
+            last_ops = ops;
+            last_check = Instant::now();
+
+            if !in_window(
+                Utc::now().hour(),
+                self.settings.compaction_window_start_hour,
+                self.settings.compaction_window_end_hour,
+            ) {
+                continue;
+            }
+            if last_compaction.is_some_and(|at| at.elapsed() < min_gap) {
+                continue;
+            }
+            if ops_per_sec > self.settings.compaction_max_ops_per_sec {
+                debug!(
+                    ops_per_sec = ops_per_sec,
+                    "Skipping compaction while the node is busy"
+                );
+                continue;
+            }
+
+            let started = Instant::now();
+            let storage = self.storage.clone();
+            if let Err(e) = tokio::task::spawn_blocking(move || storage.compact()).await {
+                error!(error = %e, "Compaction task failed");
+                continue;
+            }
+            self.metrics.record_compaction(started.elapsed());
+            last_compaction = Some(Instant::now());
+            info!(
+                elapsed_secs = started.elapsed().as_secs(),
+                "Scheduled compaction complete"
+            );
+        }
+    }
+
+    async fn purge_loop(&self) {
+        let mut ticker = tokio::time::interval(PURGE_CHECK_INTERVAL);
+        let mut last_ops = self.client_ops();
+        let mut last_check = Instant::now();
+        let max_delay = self.settings.purge_max_delay_hours * 3600;
+
+        // The first tick completes immediately and has no traffic sample yet
+        ticker.tick().await;
+
+        loop {
+            ticker.tick().await;
+
+            let ops = self.client_ops();
+            let elapsed = last_check.elapsed().as_secs().max(1);
+            let ops_per_sec = ops.saturating_sub(last_ops) / elapsed;
+
+            let deletions = self.storage.deletion_stats();
+            self.metrics.update_deletions(&deletions);
+            if deletions.pending_chunks == 0 {
+                last_ops = ops;
+                last_check = Instant::now();
+                continue;
+            }
+
+            let overdue = deletions
+                .oldest_pending
+                .is_some_and(|at| unix_now().saturating_sub(at) >= max_delay);
+            if !overdue && ops_per_sec > self.settings.compaction_max_ops_per_sec {
+                debug!(
+                    ops_per_sec = ops_per_sec,
+                    pending = deletions.pending_chunks,
+                    "Deferring purge while the node is busy"
+                );
+                last_ops = ops;
+                last_check = Instant::now();
+                continue;
+            }
+
+            let started = Instant::now();
+            let summary = self.purge_sweep(overdue).await;
+            self.metrics
+                .update_deletions(&self.storage.deletion_stats());
+            if summary.chunks > 0 {
+                info!(
+                    chunks = summary.chunks,
+                    bytes = summary.bytes,
+                    overdue = overdue,
+                    elapsed_ms = started.elapsed().as_millis() as u64,
+                    "Purged deleted chunks"
+                );
+            }
+
+            last_ops = self.client_ops();
+            last_check = Instant::now();
+        }
+    }
+
+    /// Purge tombstoned chunks batch by batch until none are left
+    ///
+    /// Unless `overdue`, the sweep stops early once client traffic picks up;
+    /// the rest waits for the next quiet check.
+    pub async fn purge_sweep(&self, overdue: bool) -> PurgeSummary {
+        let batch_size = self.settings.purge_batch_size.max(1);
+        let mut total = PurgeSummary::default();
+
+        loop {
+            let batch_started = Instant::now();
+            let ops_before = self.client_ops();
+            let storage = self.storage.clone();
+            let summary =
+                match tokio::task::spawn_blocking(move || storage.purge_tombstones(batch_size))
+                    .await
+                {
+                    Ok(Ok(summary)) => summary,
+                    Ok(Err(e)) => {
+                        error!(error = %e, "Failed to purge deleted chunks");
+                        break;
+                    }
+                    Err(e) => {
+                        error!(error = %e, "Purge task failed");
+                        break;
+                    }
+                };
+            total.chunks += summary.chunks;
+            total.bytes += summary.bytes;
+            if (summary.chunks as usize) < batch_size {
+                break;
+            }
+
+            tokio::time::sleep(PURGE_BATCH_PAUSE).await;
+
+            let busy = self.client_ops().saturating_sub(ops_before) as f64
+                / batch_started.elapsed().as_secs_f64()
+                > self.settings.compaction_max_ops_per_sec as f64;
+            if busy && !overdue {
+                debug!(
+                    purged = total.chunks,
+                    "Pausing purge, client traffic resumed"
+                );
+                break;
+            }
+        }
+
+        total
+    }
+
+    /// Storage operations issued by clients (excluding the scrub's own reads)
+    fn client_ops(&self) -> u64 {
+        self.storage
+            .operation_count()
+            .saturating_sub(self.scrub_reads.load(Ordering::Relaxed))
+    }
+}
+
+/// Check that a stored chunk still hashes to its ID
+fn check_chunk(storage: &RocksDbBackend, id: ChunkId) -> ChunkCheck {
+    match storage.get(id) {
+        Ok(Some(data)) if ChunkId::from_data(&data) == id => ChunkCheck::Valid,
+        Ok(Some(_)) => ChunkCheck::Corrupted,
+        Ok(None) => ChunkCheck::Gone,
+        Err(e) => {
+            debug!(chunk_id = %id, error = %e, "Chunk read failed during scrub");
+            ChunkCheck::Unreadable
+        }
+    }
+}
+
+/// Current Unix time in seconds
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// Whether `hour` falls in the window `[start, end)`, which may wrap past
+/// midnight. Equal bounds mean the window covers the whole day.
+fn in_window(hour: u32, start: u32, end: u32) -> bool {
+    if start == end {
+        true
+    } else if start < end {
+        hour >= start && hour < end
+    } else {
+        hour >= start || hour < end
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+    use cyxcloud_storage::StorageConfig;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_in_window() {
+        assert!(in_window(3, 2, 5));
+        assert!(!in_window(5, 2, 5));
+        assert!(!in_window(1, 2, 5));
+
+        // Window spanning midnight
+        assert!(in_window(23, 22, 4));
+        assert!(in_window(0, 22, 4));
+        assert!(!in_window(12, 22, 4));
+
+        assert!(in_window(12, 0, 0));
+    }
+
+    #[tokio::test]
+    async fn test_scrub_finds_corrupted_chunk() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = Arc::new(RocksDbBackend::open(StorageConfig::new(temp_dir.path())).unwrap());
+
+        let good = ChunkId::from_data(b"good");
+        storage.put(good, Bytes::from_static(b"good")).unwrap();
+
+        // Stored under an ID its data does not hash to
+        let bad = ChunkId::from_data(b"original");
+        storage.put(bad, Bytes::from_static(b"bit rot")).unwrap();
+
+        let settings = MaintenanceSettings {
+            scrub_chunks_per_sec: 10_000,
+            ..Default::default()
+        };
+        let scheduler =
+            MaintenanceScheduler::new(storage.clone(), settings, NodeMetrics::new("n1"));
+
+        let summary = scheduler.scrub_pass().await;
+        assert_eq!(summary.verified, 1);
+        assert_eq!(summary.corrupted, 1);
+
+        // Corrupted copy is dropped; with no reporter the damage stays pending
+        assert!(!storage.exists(bad).unwrap());
+        assert!(storage.exists(good).unwrap());
+        assert_eq!(scheduler.pending.lock().await.corrupted, vec![bad]);
+    }
+
+    #[tokio::test]
+    async fn test_purge_sweep() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = StorageConfig::new(temp_dir.path()).with_deferred_delete(true);
+        let storage = Arc::new(RocksDbBackend::open(config).unwrap());
+
+        let ids: Vec<ChunkId> = (0..10u8).map(|i| ChunkId::from_data(&[i])).collect();
+        for id in &ids {
+            storage.put(*id, Bytes::from_static(b"data")).unwrap();
+            assert!(storage.delete(*id).unwrap());
+        }
+        assert_eq!(storage.deletion_stats().pending_chunks, 10);
+
+        let settings = MaintenanceSettings {
+            purge_batch_size: 3,
+            ..Default::default()
+        };
+        let scheduler =
+            MaintenanceScheduler::new(storage.clone(), settings, NodeMetrics::new("n1"));
+
+        // Batches continue until every tombstone is purged
+        let summary = scheduler.purge_sweep(true).await;
+        assert_eq!(summary.chunks, 10);
+        assert_eq!(summary.bytes, 40);
+
+        let deletions = storage.deletion_stats();
+        assert_eq!(deletions.pending_chunks, 0);
+        assert_eq!(deletions.deleted_bytes, deletions.purged_bytes);
+    }
+}
@@ -2,6 +2,9 @@
 //!
 //! Exposes node health, performance, and storage metrics.
 
+use crate::disk_health::DiskHealth;
+use cyxcloud_network::PeerRegistryStats;
+use cyxcloud_storage::DeletionStats;
 use metrics::{counter, describe_counter, describe_gauge, describe_histogram, gauge, histogram};
 use metrics_exporter_prometheus::{PrometheusBuilder, PrometheusHandle};
 use std::net::SocketAddr;
@@ -16,6 +19,16 @@ pub mod names {
     pub const STORAGE_BYTES_AVAILABLE: &str = "cyxcloud_storage_bytes_available";
     pub const STORAGE_CHUNKS_TOTAL: &str = "cyxcloud_storage_chunks_total";
 
+    // Disk health metrics
+    pub const DISK_FREE_BYTES: &str = "cyxcloud_disk_free_bytes";
+    pub const DISK_TOTAL_BYTES: &str = "cyxcloud_disk_total_bytes";
+    pub const DISK_IO_ERRORS: &str = "cyxcloud_disk_io_errors";
+    pub const DISK_SMART_PASSED: &str = "cyxcloud_disk_smart_passed";
+    pub const DISK_REALLOCATED_SECTORS: &str = "cyxcloud_disk_reallocated_sectors";
+    pub const DISK_PENDING_SECTORS: &str = "cyxcloud_disk_pending_sectors";
+    pub const DISK_MEDIA_ERRORS: &str = "cyxcloud_disk_media_errors";
+    pub const DISK_HEALTHY: &str = "cyxcloud_disk_healthy";
+
     // Request metrics
     pub const REQUESTS_TOTAL: &str = "cyxcloud_requests_total";
     pub const REQUESTS_DURATION: &str = "cyxcloud_request_duration_seconds";
@@ -25,12 +38,25 @@ pub mod names {
     pub const CONNECTIONS_ACTIVE: &str = "cyxcloud_connections_active";
     pub const BANDWIDTH_IN: &str = "cyxcloud_bandwidth_in_bytes";
     pub const BANDWIDTH_OUT: &str = "cyxcloud_bandwidth_out_bytes";
+    pub const PEERS_KNOWN: &str = "cyxcloud_peers_known";
+    pub const PEERS_CONNECTED: &str = "cyxcloud_peers_connected";
+    pub const PEERS_BANNED: &str = "cyxcloud_peers_banned";
 
     // Health metrics
     pub const NODE_UP: &str = "cyxcloud_node_up";
     pub const NODE_START_TIME: &str = "cyxcloud_node_start_time_seconds";
     pub const HEARTBEAT_SUCCESS: &str = "cyxcloud_heartbeat_success_total";
     pub const HEARTBEAT_FAILURE: &str = "cyxcloud_heartbeat_failure_total";
+
+    // Maintenance metrics
+    pub const SCRUB_CHUNKS_VERIFIED: &str = "cyxcloud_scrub_chunks_verified_total";
+    pub const SCRUB_CHUNKS_DAMAGED: &str = "cyxcloud_scrub_chunks_damaged_total";
+    pub const COMPACTIONS_TOTAL: &str = "cyxcloud_compactions_total";
+    pub const COMPACTION_DURATION: &str = "cyxcloud_compaction_duration_seconds";
+    pub const TOMBSTONED_CHUNKS: &str = "cyxcloud_storage_tombstoned_chunks";
+    pub const TOMBSTONED_BYTES: &str = "cyxcloud_storage_tombstoned_bytes";
+    pub const DELETED_BYTES: &str = "cyxcloud_storage_deleted_bytes_total";
+    pub const PURGED_BYTES: &str = "cyxcloud_storage_purged_bytes_total";
 }
 
 /// Initialize metric descriptions
@@ -46,6 +72,31 @@ pub fn init_metrics() {
     );
     describe_gauge!(names::STORAGE_CHUNKS_TOTAL, "Total number of chunks stored");
 
+    // Disk health metrics
+    describe_gauge!(names::DISK_FREE_BYTES, "Free bytes on the data disk");
+    describe_gauge!(names::DISK_TOTAL_BYTES, "Total bytes on the data disk");
+    describe_gauge!(
+        names::DISK_IO_ERRORS,
+        "I/O errors reported by the kernel for the data disk"
+    );
+    describe_gauge!(
+        names::DISK_SMART_PASSED,
+        "Whether the SMART self-assessment passed (1) or failed (0)"
+    );
+    describe_gauge!(
+        names::DISK_REALLOCATED_SECTORS,
+        "SMART reallocated sector count"
+    );
+    describe_gauge!(
+        names::DISK_PENDING_SECTORS,
+        "SMART pending and uncorrectable sector count"
+    );
+    describe_gauge!(names::DISK_MEDIA_ERRORS, "NVMe media error count");
+    describe_gauge!(
+        names::DISK_HEALTHY,
+        "Whether the data disk is within all health thresholds (1) or not (0)"
+    );
+
     // Request metrics
     describe_counter!(names::REQUESTS_TOTAL, "Total number of requests processed");
     describe_histogram!(
@@ -61,6 +112,12 @@ pub fn init_metrics() {
     );
     describe_counter!(names::BANDWIDTH_IN, "Total incoming bandwidth in bytes");
     describe_counter!(names::BANDWIDTH_OUT, "Total outgoing bandwidth in bytes");
+    describe_gauge!(names::PEERS_KNOWN, "Peers in the P2P peer store");
+    describe_gauge!(names::PEERS_CONNECTED, "P2P peers with an open connection");
+    describe_gauge!(
+        names::PEERS_BANNED,
+        "P2P peers banned after repeated dial failures"
+    );
 
     // Health metrics
     describe_gauge!(names::NODE_UP, "Whether the node is up (1) or down (0)");
@@ -70,6 +127,37 @@ pub fn init_metrics() {
     );
     describe_counter!(names::HEARTBEAT_SUCCESS, "Number of successful heartbeats");
     describe_counter!(names::HEARTBEAT_FAILURE, "Number of failed heartbeats");
+
+    // Maintenance metrics
+    describe_counter!(
+        names::SCRUB_CHUNKS_VERIFIED,
+        "Chunks whose data matched their hash during self-scrub"
+    );
+    describe_counter!(
+        names::SCRUB_CHUNKS_DAMAGED,
+        "Chunks found corrupted or unreadable during self-scrub"
+    );
+    describe_counter!(names::COMPACTIONS_TOTAL, "Manual compactions run");
+    describe_histogram!(
+        names::COMPACTION_DURATION,
+        "Manual compaction duration in seconds"
+    );
+    describe_gauge!(
+        names::TOMBSTONED_CHUNKS,
+        "Deleted chunks whose data has not been purged yet"
+    );
+    describe_gauge!(
+        names::TOMBSTONED_BYTES,
+        "Bytes held by deleted chunks awaiting purge"
+    );
+    describe_counter!(
+        names::DELETED_BYTES,
+        "Bytes of chunks deleted (logically) since the node started"
+    );
+    describe_counter!(
+        names::PURGED_BYTES,
+        "Bytes of deleted chunks physically removed since the node started"
+    );
 }
 
 /// Metrics recorder for tracking node statistics
@@ -165,11 +253,47 @@ impl NodeMetrics {
             .set(chunk_count as f64);
     }
 
+    /// Update disk health gauges
+    pub fn update_disk_health(&self, health: &DiskHealth, healthy: bool) {
+        let node_id = self.node_id.clone();
+        gauge!(names::DISK_FREE_BYTES, "node_id" => node_id.clone()).set(health.free_bytes as f64);
+        gauge!(names::DISK_TOTAL_BYTES, "node_id" => node_id.clone())
+            .set(health.total_bytes as f64);
+        gauge!(names::DISK_HEALTHY, "node_id" => node_id.clone()).set(if healthy {
+            1.0
+        } else {
+            0.0
+        });
+        if let Some(errors) = health.io_errors {
+            gauge!(names::DISK_IO_ERRORS, "node_id" => node_id.clone()).set(errors as f64);
+        }
+        if let Some(ref smart) = health.smart {
+            gauge!(names::DISK_SMART_PASSED, "node_id" => node_id.clone()).set(if smart.passed {
+                1.0
+            } else {
+                0.0
+            });
+            gauge!(names::DISK_REALLOCATED_SECTORS, "node_id" => node_id.clone())
+                .set(smart.reallocated_sectors as f64);
+            gauge!(names::DISK_PENDING_SECTORS, "node_id" => node_id.clone())
+                .set(smart.pending_sectors as f64);
+            gauge!(names::DISK_MEDIA_ERRORS, "node_id" => node_id).set(smart.media_errors as f64);
+        }
+    }
+
     /// Update connection count
     pub fn update_connections(&self, count: usize) {
         gauge!(names::CONNECTIONS_ACTIVE, "node_id" => self.node_id.clone()).set(count as f64);
     }
 
+    /// Update P2P peer registry gauges
+    pub fn update_peers(&self, stats: &PeerRegistryStats) {
+        let node_id = self.node_id.clone();
+        gauge!(names::PEERS_KNOWN, "node_id" => node_id.clone()).set(stats.known as f64);
+        gauge!(names::PEERS_CONNECTED, "node_id" => node_id.clone()).set(stats.connected as f64);
+        gauge!(names::PEERS_BANNED, "node_id" => node_id).set(stats.banned as f64);
+    }
+
     /// Record heartbeat result
     pub fn record_heartbeat(&self, success: bool) {
         if success {
@@ -179,6 +303,34 @@ impl NodeMetrics {
         }
     }
 
+    /// Record the outcome of a self-scrub batch
+    pub fn record_scrub(&self, verified: u64, corrupted: u64, missing: u64) {
+        let node_id = self.node_id.clone();
+        counter!(names::SCRUB_CHUNKS_VERIFIED, "node_id" => node_id.clone()).increment(verified);
+        counter!(names::SCRUB_CHUNKS_DAMAGED, "node_id" => node_id.clone(), "kind" => "corrupted")
+            .increment(corrupted);
+        counter!(names::SCRUB_CHUNKS_DAMAGED, "node_id" => node_id, "kind" => "missing")
+            .increment(missing);
+    }
+
+    /// Record a manual compaction
+    pub fn record_compaction(&self, duration: std::time::Duration) {
+        counter!(names::COMPACTIONS_TOTAL, "node_id" => self.node_id.clone()).increment(1);
+        histogram!(names::COMPACTION_DURATION, "node_id" => self.node_id.clone())
+            .record(duration.as_secs_f64());
+    }
+
+    /// Update deletion metrics from the store's tombstone bookkeeping
+    pub fn update_deletions(&self, stats: &DeletionStats) {
+        let node_id = self.node_id.clone();
+        gauge!(names::TOMBSTONED_CHUNKS, "node_id" => node_id.clone())
+            .set(stats.pending_chunks as f64);
+        gauge!(names::TOMBSTONED_BYTES, "node_id" => node_id.clone())
+            .set(stats.pending_bytes as f64);
+        counter!(names::DELETED_BYTES, "node_id" => node_id.clone()).absolute(stats.deleted_bytes);
+        counter!(names::PURGED_BYTES, "node_id" => node_id).absolute(stats.purged_bytes);
+    }
+
     /// Mark node as down
     pub fn mark_down(&self) {
         gauge!(names::NODE_UP, "node_id" => self.node_id.clone()).set(0.0);
@@ -259,6 +411,7 @@ pub struct HealthState {
     pub is_healthy: bool,
     pub storage_ok: bool,
     pub network_ok: bool,
+    pub disk_ok: bool,
     pub last_check: std::time::Instant,
 }
 
@@ -268,6 +421,7 @@ impl Default for HealthState {
             is_healthy: true,
             storage_ok: true,
             network_ok: true,
+            disk_ok: true,
             last_check: std::time::Instant::now(),
         }
     }
@@ -278,9 +432,15 @@ impl HealthState {
     pub fn update(&mut self, storage_ok: bool, network_ok: bool) {
         self.storage_ok = storage_ok;
         self.network_ok = network_ok;
-        self.is_healthy = storage_ok && network_ok;
+        self.is_healthy = storage_ok && network_ok && self.disk_ok;
         self.last_check = std::time::Instant::now();
     }
+
+    /// Update disk health (from the latest disk sample)
+    pub fn update_disk(&mut self, disk_ok: bool) {
+        self.disk_ok = disk_ok;
+        self.is_healthy = self.storage_ok && self.network_ok && disk_ok;
+    }
 }
 
 #[cfg(test)]
@@ -303,5 +463,10 @@ mod tests {
 
         state.update(true, true);
         assert!(state.is_healthy);
+
+        state.update_disk(false);
+        assert!(!state.is_healthy);
+        state.update(true, true);
+        assert!(!state.is_healthy);
     }
 }
@@ -0,0 +1,146 @@
+//! Offline recovery of chunk locations from a node's disk
+//!
+//! Every shard the gateway uploads is stored with a shard header naming the
+//! file, chunk and shard it belongs to. `cyxcloud-node salvage` reads the
+//! local store while the daemon is stopped, checks each shard against its
+//! header and writes the result as a salvage report (`<output>`). Posting
+//! the report to `POST /api/v1/admin/nodes/{node_id}/chunks/salvage` puts the
+//! `chunks` / `chunk_locations` rows back, e.g. after the metadata database
+//! was restored from an old backup.
+//!
+//! Chunks without a header (stored before headers existed) cannot be placed
+//! and are only counted, as are chunks whose data no longer matches.
+
+use cyxcloud_core::chunk::ChunkId;
+use cyxcloud_core::error::CyxCloudError;
+use cyxcloud_storage::backend::StorageBackendSync;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+/// One shard recovered from the store
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SalvagedShard {
+    /// Hex-encoded chunk ID
+    pub chunk_id: String,
+    pub file_id: Uuid,
+    pub chunk_index: u32,
+    pub shard_index: u32,
+    pub is_parity: bool,
+    /// Size of the chunk before erasure coding
+    pub original_size: u64,
+    /// Shard size in bytes
+    pub size: u64,
+}
+
+/// Result of a salvage scan, also the body of the gateway's salvage endpoint
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SalvageReport {
+    /// Node the store belongs to
+    pub node_id: String,
+    pub shards: Vec<SalvagedShard>,
+    /// Hex IDs of intact chunks stored without a shard header
+    pub unlabelled: Vec<String>,
+    /// Hex IDs of chunks whose data does not match their ID
+    pub corrupted: Vec<String>,
+}
+
+impl SalvageReport {
+    /// Bytes held by the recovered shards
+    pub fn bytes(&self) -> u64 {
+        self.shards.iter().map(|shard| shard.size).sum()
+    }
+}
+
+/// Read every chunk in `storage` and collect the shards it can place
+pub fn salvage_chunks<S>(storage: &S, node_id: &str) -> Result<SalvageReport, CyxCloudError>
+where
+    S: StorageBackendSync + ?Sized,
+{
+    let mut report = SalvageReport {
+        node_id: node_id.to_string(),
+        ..Default::default()
+    };
+
+    for id in storage.list_chunks()? {
+        let hex_id = hex::encode(id.as_bytes());
+        let Some((header, data)) = storage.get_shard(id)? else {
+            continue;
+        };
+
+        match header {
+            Some(header) if header.verify(&data) => {
+                debug!(chunk_id = %hex_id, file_id = %header.file_id, "Salvaged shard");
+                report.shards.push(SalvagedShard {
+                    chunk_id: hex_id,
+                    file_id: header.file_id,
+                    chunk_index: header.chunk_index,
+                    shard_index: header.shard_index,
+                    is_parity: header.is_parity,
+                    original_size: header.original_size,
+                    size: data.len() as u64,
+                });
+            }
+            None if ChunkId::from_data(&data) == id => {
+                report.unlabelled.push(hex_id);
+            }
+            _ => {
+                warn!(chunk_id = %hex_id, "Chunk data does not match its ID, skipping");
+                report.corrupted.push(hex_id);
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+    use cyxcloud_storage::{RocksDbBackend, ShardHeader, StorageConfig};
+    use tempfile::TempDir;
+
+    fn put_shard(storage: &RocksDbBackend, data: &'static [u8], shard_index: u32) -> ShardHeader {
+        let id = ChunkId::from_data(data);
+        let header = ShardHeader {
+            file_id: Uuid::new_v4(),
+            chunk_index: 0,
+            shard_index,
+            is_parity: shard_index >= 10,
+            original_size: 1024,
+            hash: *id.as_bytes(),
+        };
+        storage
+            .put_shard(id, &header, Bytes::from_static(data))
+            .unwrap();
+        header
+    }
+
+    #[test]
+    fn test_salvage_chunks() {
+        let dir = TempDir::new().unwrap();
+        let storage = RocksDbBackend::open(StorageConfig::new(dir.path())).unwrap();
+
+        let data = put_shard(&storage, b"data shard", 2);
+        let parity = put_shard(&storage, b"parity shard", 11);
+        let legacy = ChunkId::from_data(b"legacy");
+        storage.put(legacy, Bytes::from_static(b"legacy")).unwrap();
+        let rotten = ChunkId::from_data(b"original");
+        storage.put(rotten, Bytes::from_static(b"bit rot")).unwrap();
+
+        let mut report = salvage_chunks(&storage, "node-1").unwrap();
+        report.shards.sort_by_key(|shard| shard.shard_index);
+
+        assert_eq!(report.node_id, "node-1");
+        assert_eq!(report.shards.len(), 2);
+        assert_eq!(report.shards[0].file_id, data.file_id);
+        assert!(!report.shards[0].is_parity);
+        assert_eq!(report.shards[1].file_id, parity.file_id);
+        assert!(report.shards[1].is_parity);
+        assert_eq!(report.shards[1].size, b"parity shard".len() as u64);
+        assert_eq!(report.bytes(), 22);
+        assert_eq!(report.unlabelled, vec![hex::encode(legacy.as_bytes())]);
+        assert_eq!(report.corrupted, vec![hex::encode(rotten.as_bytes())]);
+    }
+}
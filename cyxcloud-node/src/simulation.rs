@@ -0,0 +1,599 @@
+//! Simulated storage nodes for capacity planning and CI
+//!
+//! `cyxcloud-node simulate` runs many nodes in one process. Each one serves
+//! the ChunkService on its own port, registers with the gateway, heartbeats
+//! and executes the repair/delete/transfer commands it is sent, like a real
+//! node, but keeps its chunks in a [`MemoryBackend`] of the configured
+//! capacity. A [`SimulationProfile`] adds latency, a bandwidth limit and
+//! random failures to every chunk read and write, so placement and the
+//! rebalancer can be exercised on large synthetic clusters without disks.
+//!
+//! Simulated nodes do not log in to the CyxWiz API: registration needs no
+//! token, and heartbeats carry the token the gateway returns with it.
+
+use crate::command_executor::{CommandBatchSummary, CommandExecutor};
+use crate::metrics::NodeMetrics;
+use bytes::Bytes;
+use cyxcloud_core::chunk::ChunkId;
+use cyxcloud_core::error::{CyxCloudError, Result};
+use cyxcloud_network::grpc_server::ChunkServiceImpl;
+use cyxcloud_protocol::node::{
+    node_service_client::NodeServiceClient, HeartbeatRequest, NodeCapacity, NodeInfo, NodeLocation,
+    NodeMetrics as ProtoNodeMetrics, NodeStatus, RegisterNodeRequest,
+};
+use cyxcloud_protocol::ChunkServiceServer;
+use cyxcloud_storage::backend::{StorageBackendSync, StorageStats};
+use cyxcloud_storage::MemoryBackend;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+use tonic::transport::{Channel, ClientTlsConfig, Endpoint, Server};
+use tracing::{debug, info, warn};
+
+/// Artificial behaviour of a simulated node's storage
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SimulationProfile {
+    /// Delay added to every chunk read and write
+    pub latency_ms: u64,
+    /// Rate chunk data is read and written at, in megabits per second
+    /// (0 = unlimited)
+    pub bandwidth_mbps: u64,
+    /// Chunk reads and writes that fail, from 0.0 to 1.0
+    pub failure_rate: f64,
+}
+
+impl SimulationProfile {
+    /// Check that the failure rate is a probability
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        if !(0.0..=1.0).contains(&self.failure_rate) {
+            return Err(format!(
+                "failure_rate must be between 0.0 and 1.0, got {}",
+                self.failure_rate
+            ));
+        }
+        Ok(())
+    }
+
+    /// Time a read or write of `bytes` takes
+    pub fn delay(&self, bytes: usize) -> Duration {
+        let mut delay = Duration::from_millis(self.latency_ms);
+        if self.bandwidth_mbps > 0 {
+            delay += Duration::from_secs_f64(
+                bytes as f64 * 8.0 / (self.bandwidth_mbps as f64 * 1_000_000.0),
+            );
+        }
+        delay
+    }
+}
+
+/// In-memory chunk store that is slowed down and fails as its
+/// [`SimulationProfile`] dictates
+pub struct SimulatedBackend {
+    inner: MemoryBackend,
+    profile: SimulationProfile,
+}
+
+impl SimulatedBackend {
+    /// Create an empty store of `capacity_bytes` (0 = unlimited)
+    pub fn new(capacity_bytes: u64, profile: SimulationProfile) -> Self {
+        Self {
+            inner: MemoryBackend::with_capacity(capacity_bytes),
+            profile,
+        }
+    }
+
+    /// Wait out the transfer of `bytes`, then fail it at the profile's rate
+    fn transfer(&self, op: &str, id: ChunkId, bytes: usize) -> Result<()> {
+        stall(self.profile.delay(bytes));
+        if roll(self.profile.failure_rate) {
+            debug!(chunk_id = %id, op, "Injecting simulated storage failure");
+            return Err(CyxCloudError::Storage(format!("{}: simulated failure", op)));
+        }
+        Ok(())
+    }
+}
+
+impl StorageBackendSync for SimulatedBackend {
+    fn put(&self, id: ChunkId, data: Bytes) -> Result<()> {
+        self.transfer("put", id, data.len())?;
+        self.inner.put(id, data)
+    }
+
+    fn get(&self, id: ChunkId) -> Result<Option<Bytes>> {
+        let data = self.inner.get(id)?;
+        self.transfer("get", id, data.as_ref().map_or(0, Bytes::len))?;
+        Ok(data)
+    }
+
+    fn delete(&self, id: ChunkId) -> Result<bool> {
+        self.inner.delete(id)
+    }
+
+    fn exists(&self, id: ChunkId) -> Result<bool> {
+        self.inner.exists(id)
+    }
+
+    fn stats(&self) -> Result<StorageStats> {
+        self.inner.stats()
+    }
+
+    fn list_chunks(&self) -> Result<Vec<ChunkId>> {
+        self.inner.list_chunks()
+    }
+
+    fn flush(&self) -> Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Block the calling thread for `duration`
+///
+/// Storage calls are synchronous; on a multi-threaded runtime the worker is
+/// handed over to other tasks while it sleeps.
+fn stall(duration: Duration) {
+    if duration.is_zero() {
+        return;
+    }
+    match tokio::runtime::Handle::try_current() {
+        Ok(handle) if handle.runtime_flavor() == tokio::runtime::RuntimeFlavor::MultiThread => {
+            tokio::task::block_in_place(|| std::thread::sleep(duration))
+        }
+        _ => std::thread::sleep(duration),
+    }
+}
+
+/// True with probability `rate`
+fn roll(rate: f64) -> bool {
+    rate > 0.0 && rand::random::<f64>() < rate
+}
+
+/// A synthetic cluster to run
+#[derive(Debug, Clone)]
+pub struct SimulationConfig {
+    /// Number of nodes
+    pub nodes: usize,
+    /// Node IDs are `<prefix>-0000`, `<prefix>-0001`, ...
+    pub id_prefix: String,
+    /// Gateway gRPC address the nodes register with
+    pub gateway: String,
+    /// Address the nodes' ChunkServices listen on
+    pub bind_address: String,
+    /// Host the nodes advertise to the gateway
+    pub advertise_host: String,
+    /// Port of the first node; node `i` listens on `base_port + i`
+    pub base_port: u16,
+    /// Storage offered by each node
+    pub capacity_bytes: u64,
+    /// Regions the nodes are spread over, round-robin
+    pub regions: Vec<String>,
+    /// Racks per region the nodes are spread over
+    pub racks_per_region: u32,
+    /// Seconds between heartbeats of a node
+    pub heartbeat_interval_secs: u64,
+    /// Shared secret required on chunk RPCs, as on real nodes
+    pub cluster_token: Option<String>,
+    /// Behaviour of every node's storage
+    pub profile: SimulationProfile,
+}
+
+impl Default for SimulationConfig {
+    fn default() -> Self {
+        Self {
+            nodes: 10,
+            id_prefix: "sim".to_string(),
+            gateway: "http://127.0.0.1:50052".to_string(),
+            bind_address: "127.0.0.1".to_string(),
+            advertise_host: "127.0.0.1".to_string(),
+            base_port: 60000,
+            capacity_bytes: 100 * 1024 * 1024 * 1024,
+            regions: vec!["sim-region".to_string()],
+            racks_per_region: 4,
+            heartbeat_interval_secs: 30,
+            cluster_token: None,
+            profile: SimulationProfile::default(),
+        }
+    }
+}
+
+impl SimulationConfig {
+    /// Check that the cluster fits the port range and the profile is valid
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        if self.nodes == 0 {
+            return Err("at least one node must be simulated".to_string());
+        }
+        if self.base_port as usize + self.nodes - 1 > u16::MAX as usize {
+            return Err(format!(
+                "{} nodes do not fit in the ports from {}",
+                self.nodes, self.base_port
+            ));
+        }
+        if self.regions.is_empty() {
+            return Err("at least one region is required".to_string());
+        }
+        if self.racks_per_region == 0 {
+            return Err("racks_per_region must be at least 1".to_string());
+        }
+        self.profile.validate()
+    }
+}
+
+/// One node of a simulated cluster
+pub struct SimulatedNode {
+    index: usize,
+    node_id: String,
+    listen_addr: SocketAddr,
+    grpc_address: String,
+    location: NodeLocation,
+    storage: Arc<SimulatedBackend>,
+    heartbeat_interval: Duration,
+    metrics: NodeMetrics,
+    command_executor: CommandExecutor,
+}
+
+impl SimulatedNode {
+    /// Create node `index` of the cluster
+    pub fn new(config: &SimulationConfig, index: usize) -> anyhow::Result<Self> {
+        let node_id = format!("{}-{:04}", config.id_prefix, index);
+        let port = config.base_port as usize + index;
+        let listen_addr = format!("{}:{}", config.bind_address, port).parse()?;
+        let grpc_address = format!("{}:{}", config.advertise_host, port);
+
+        let regions = config.regions.len();
+        let region = config.regions[index % regions].clone();
+        let rack = (index / regions) as u32 % config.racks_per_region + 1;
+        let location = NodeLocation {
+            datacenter: format!("{}-dc", region),
+            rack,
+            region,
+            latitude: 0.0,
+            longitude: 0.0,
+        };
+
+        let storage = Arc::new(SimulatedBackend::new(
+            config.capacity_bytes,
+            config.profile.clone(),
+        ));
+        let metrics = NodeMetrics::new(&node_id);
+        let command_executor =
+            CommandExecutor::new(node_id.clone(), storage.clone(), metrics.clone());
+
+        Ok(Self {
+            index,
+            node_id,
+            listen_addr,
+            grpc_address,
+            location,
+            storage,
+            heartbeat_interval: Duration::from_secs(config.heartbeat_interval_secs.max(1)),
+            metrics,
+            command_executor,
+        })
+    }
+
+    /// ID the node registers with
+    pub fn node_id(&self) -> &str {
+        &self.node_id
+    }
+
+    /// Address the node advertises to the gateway
+    pub fn grpc_address(&self) -> &str {
+        &self.grpc_address
+    }
+
+    /// The node's chunk store
+    pub fn storage(&self) -> &Arc<SimulatedBackend> {
+        &self.storage
+    }
+
+    /// Serve the ChunkService until shutdown
+    async fn serve(
+        &self,
+        cluster_token: Option<String>,
+        mut shutdown: watch::Receiver<bool>,
+    ) -> anyhow::Result<()> {
+        let chunk_service = ChunkServiceImpl::new(self.storage.clone(), self.node_id.clone())
+            .with_cluster_token(cluster_token);
+
+        Server::builder()
+            .add_service(ChunkServiceServer::new(chunk_service))
+            .serve_with_shutdown(self.listen_addr, async move {
+                let _ = shutdown.changed().await;
+            })
+            .await?;
+        Ok(())
+    }
+
+    /// Register, then heartbeat forever, re-registering when a heartbeat
+    /// fails
+    ///
+    /// Nodes start spread over the first interval so a large cluster does
+    /// not register all at once.
+    async fn run_heartbeats(&self, client: NodeServiceClient<Channel>, nodes: usize) {
+        tokio::time::sleep(self.heartbeat_interval * self.index as u32 / nodes.max(1) as u32).await;
+
+        let mut interval = tokio::time::interval(self.heartbeat_interval);
+        let mut token: Option<String> = None;
+        loop {
+            interval.tick().await;
+
+            if token.is_none() {
+                match self.register(client.clone()).await {
+                    Ok(auth_token) => {
+                        debug!(node_id = %self.node_id, "Simulated node registered");
+                        token = Some(auth_token);
+                    }
+                    Err(e) => {
+                        warn!(node_id = %self.node_id, error = %e, "Simulated node registration failed");
+                        continue;
+                    }
+                }
+            }
+
+            if let Err(e) = self.heartbeat(client.clone(), token.as_deref()).await {
+                self.metrics.record_heartbeat(false);
+                warn!(node_id = %self.node_id, error = %e, "Simulated heartbeat failed, re-registering");
+                token = None;
+            } else {
+                self.metrics.record_heartbeat(true);
+            }
+        }
+    }
+
+    /// Register with the gateway and get the token to heartbeat with
+    async fn register(&self, mut client: NodeServiceClient<Channel>) -> anyhow::Result<String> {
+        let stats = self.storage.stats()?;
+        let request = RegisterNodeRequest {
+            node_id: self.node_id.clone(),
+            info: Some(NodeInfo {
+                node_id: self.node_id.clone(),
+                public_key: String::new(),
+                wallet_address: String::new(),
+                listen_addrs: vec![self.grpc_address.clone()],
+                location: Some(self.location.clone()),
+                capacity: Some(NodeCapacity {
+                    storage_total: stats.bytes_capacity,
+                    storage_used: stats.bytes_used,
+                    bandwidth_mbps: match self.storage.profile.bandwidth_mbps {
+                        0 => 1000,
+                        mbps => mbps.min(u32::MAX as u64) as u32,
+                    },
+                    max_connections: 100,
+                }),
+                status: NodeStatus::Online.into(),
+                registered_at: chrono::Utc::now().timestamp(),
+                reputation: 0,
+            }),
+        };
+
+        let result = client.register_node(request).await?.into_inner();
+        if !result.success {
+            anyhow::bail!("registration failed: {}", result.error);
+        }
+        Ok(result.auth_token)
+    }
+
+    /// Report the node's usage and run the commands the gateway answers with
+    async fn heartbeat(
+        &self,
+        mut client: NodeServiceClient<Channel>,
+        token: Option<&str>,
+    ) -> anyhow::Result<()> {
+        let stats = self.storage.stats()?;
+        let mut request = tonic::Request::new(HeartbeatRequest {
+            node_id: self.node_id.clone(),
+            metrics: Some(ProtoNodeMetrics {
+                storage_used: stats.bytes_used,
+                storage_available: stats.bytes_capacity.saturating_sub(stats.bytes_used),
+                chunks_stored: stats.chunk_count,
+                bytes_uploaded: self.metrics.get_bytes_uploaded(),
+                bytes_downloaded: self.metrics.get_bytes_downloaded(),
+                cpu_usage: 0.0,
+                memory_usage: 0.0,
+                active_connections: 0,
+                last_updated: chrono::Utc::now().timestamp(),
+                disk_health: None,
+                storage_total: stats.bytes_capacity,
+                disk_space: None,
+            }),
+            status: NodeStatus::Online.into(),
+            status_reason: String::new(),
+            location: Some(self.location.clone()),
+        });
+        if let Some(token) = token.filter(|t| !t.is_empty()) {
+            request
+                .metadata_mut()
+                .insert("authorization", format!("Bearer {}", token).parse()?);
+        }
+
+        let result = client.heartbeat(request).await?.into_inner();
+        if !result.acknowledged {
+            anyhow::bail!("heartbeat not acknowledged");
+        }
+
+        if !result.commands.is_empty() {
+            let results = self
+                .command_executor
+                .execute_commands(result.commands)
+                .await;
+            let summary = CommandBatchSummary::from_results(&results);
+            debug!(
+                node_id = %self.node_id,
+                total = summary.total,
+                failed = summary.failed,
+                "Simulated node executed commands"
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Run a simulated cluster until `shutdown` fires
+///
+/// All nodes share one connection to the gateway.
+pub async fn run_simulation(
+    config: SimulationConfig,
+    shutdown: watch::Receiver<bool>,
+) -> anyhow::Result<()> {
+    config.validate().map_err(|e| anyhow::anyhow!(e))?;
+
+    let mut endpoint = Endpoint::from_shared(config.gateway.clone())?;
+    if config.gateway.starts_with("https://") {
+        endpoint = endpoint.tls_config(ClientTlsConfig::new())?;
+    }
+    let client = NodeServiceClient::new(endpoint.connect_lazy());
+
+    let nodes = (0..config.nodes)
+        .map(|index| SimulatedNode::new(&config, index).map(Arc::new))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    info!(
+        nodes = config.nodes,
+        gateway = %config.gateway,
+        ports = %format!(
+            "{}-{}",
+            config.base_port,
+            config.base_port as usize + config.nodes - 1
+        ),
+        capacity_bytes = config.capacity_bytes,
+        latency_ms = config.profile.latency_ms,
+        bandwidth_mbps = config.profile.bandwidth_mbps,
+        failure_rate = config.profile.failure_rate,
+        "Starting simulated cluster"
+    );
+
+    let mut servers = Vec::with_capacity(nodes.len());
+    let mut heartbeats = Vec::with_capacity(nodes.len());
+    for node in &nodes {
+        let server_node = node.clone();
+        let cluster_token = config.cluster_token.clone();
+        let server_shutdown = shutdown.clone();
+        servers.push(tokio::spawn(async move {
+            if let Err(e) = server_node.serve(cluster_token, server_shutdown).await {
+                warn!(node_id = %server_node.node_id, error = %e, "Simulated node server failed");
+            }
+        }));
+
+        let heartbeat_node = node.clone();
+        let client = client.clone();
+        let count = nodes.len();
+        heartbeats.push(tokio::spawn(async move {
+            heartbeat_node.run_heartbeats(client, count).await;
+        }));
+    }
+
+    let mut shutdown = shutdown;
+    let _ = shutdown.changed().await;
+    info!("Stopping simulated cluster");
+
+    for heartbeat in heartbeats {
+        heartbeat.abort();
+    }
+    for server in servers {
+        let _ = server.await;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_delay() {
+        let profile = SimulationProfile {
+            latency_ms: 5,
+            bandwidth_mbps: 8,
+            failure_rate: 0.0,
+        };
+        // 1 MB at 8 Mbit/s takes a second
+        assert_eq!(profile.delay(1_000_000), Duration::from_millis(1005));
+        assert_eq!(profile.delay(0), Duration::from_millis(5));
+        assert_eq!(
+            SimulationProfile::default().delay(1_000_000),
+            Duration::ZERO
+        );
+
+        assert!(profile.validate().is_ok());
+        let profile = SimulationProfile {
+            failure_rate: 1.5,
+            ..Default::default()
+        };
+        assert!(profile.validate().is_err());
+    }
+
+    #[test]
+    fn test_simulated_backend_failures() {
+        let backend = SimulatedBackend::new(100, SimulationProfile::default());
+        let data = Bytes::from_static(b"simulated chunk");
+        let id = ChunkId::from_data(&data);
+        backend.put(id, data.clone()).unwrap();
+        assert_eq!(backend.get(id).unwrap().unwrap(), data);
+        assert_eq!(backend.stats().unwrap().bytes_capacity, 100);
+
+        let failing = SimulatedBackend::new(
+            0,
+            SimulationProfile {
+                failure_rate: 1.0,
+                ..Default::default()
+            },
+        );
+        assert!(failing.put(id, data).is_err());
+        assert!(!failing.exists(id).unwrap());
+        assert!(failing.get(id).is_err());
+    }
+
+    #[test]
+    fn test_node_layout() {
+        let config = SimulationConfig {
+            nodes: 6,
+            base_port: 61000,
+            regions: vec!["eu".to_string(), "us".to_string()],
+            racks_per_region: 2,
+            ..Default::default()
+        };
+        assert!(config.validate().is_ok());
+
+        let layout: Vec<_> = (0..config.nodes)
+            .map(|i| SimulatedNode::new(&config, i).unwrap())
+            .map(|node| {
+                (
+                    node.node_id().to_string(),
+                    node.grpc_address().to_string(),
+                    node.location.region.clone(),
+                    node.location.rack,
+                )
+            })
+            .collect();
+        assert_eq!(
+            layout[0],
+            (
+                "sim-0000".to_string(),
+                "127.0.0.1:61000".to_string(),
+                "eu".to_string(),
+                1
+            )
+        );
+        assert_eq!(layout[1].2, "us");
+        assert_eq!(layout[1].3, 1);
+        assert_eq!(layout[2].3, 2);
+        assert_eq!(layout[4].3, 1);
+        assert_eq!(layout[5].1, "127.0.0.1:61005");
+    }
+
+    #[test]
+    fn test_config_validation() {
+        let config = SimulationConfig {
+            nodes: 10,
+            base_port: u16::MAX - 5,
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+
+        let config = SimulationConfig {
+            nodes: 0,
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+    }
+}
@@ -0,0 +1,475 @@
+//! Node software self-update
+//!
+//! With `[update] enabled` the node polls the release manifest of its
+//! channel (stable or beta):
+//!
+//! 1. The manifest is a signed envelope, `{"release": "<JSON>", "signature":
+//!    "<hex>"}`. The Ed25519 signature over the `release` string is checked
+//!    against `update.public_key` before anything in it is used.
+//! 2. A release of the node's channel that is newer than the running version
+//!    and not halted by its publisher is downloaded next to the running
+//!    binary and checked against the BLAKE3 hash and size of the artifact for
+//!    this platform.
+//! 3. The new binary replaces the running one (which is kept as
+//!    `<binary>.previous`) and the daemon shuts down gracefully. It then
+//!    exits with [`UPDATE_EXIT_CODE`] for systemd or another supervisor to
+//!    start the new binary, or execs it itself (`update.restart = "exec"`).
+//!
+//! `update.enabled = false` (or `CYXCLOUD_AUTO_UPDATE=false`) is the kill
+//! switch on the node side; publishers stop a rollout by re-signing the
+//! release with `halted` set. Older releases are never installed, so a
+//! replayed manifest cannot downgrade a node.
+
+use crate::config::{CyxWizApiSettings, UpdateSettings};
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use semver::Version;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use thiserror::Error;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::oneshot;
+use tracing::{debug, info, warn};
+
+/// Exit status asking the supervisor to restart the node on the new binary
+pub const UPDATE_EXIT_CODE: i32 = 75;
+
+/// Timeout of a manifest request
+const MANIFEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Timeout of a binary download
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(1800);
+
+/// Self-update errors
+#[derive(Debug, Error)]
+pub enum UpdateError {
+    #[error("HTTP error: {0}")]
+    Http(#[from] reqwest::Error),
+
+    #[error("Invalid manifest: {0}")]
+    Manifest(String),
+
+    #[error("Invalid signature: {0}")]
+    Signature(String),
+
+    #[error("Download failed verification: {0}")]
+    Verification(String),
+
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// Manifest as served: the release JSON and its signature
+#[derive(Debug, Deserialize)]
+struct SignedManifest {
+    release: String,
+    signature: String,
+}
+
+/// A published node release
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Release {
+    /// Channel the release was published to
+    pub channel: String,
+    /// Semantic version
+    pub version: String,
+    /// Set by the publisher to stop the rollout
+    #[serde(default)]
+    pub halted: bool,
+    /// Binaries per platform
+    pub artifacts: Vec<Artifact>,
+}
+
+/// Node binary of a release for one platform
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Artifact {
+    /// `<arch>-<os>`, e.g. `x86_64-linux` (see [`current_platform`])
+    pub platform: String,
+    /// Download URL
+    pub url: String,
+    /// Hex BLAKE3 hash of the binary
+    pub blake3: String,
+    /// Size of the binary in bytes
+    pub size: u64,
+}
+
+impl Release {
+    /// Artifact to install on `platform` if this release updates a node of
+    /// `channel` running `current`
+    pub fn update_for(
+        &self,
+        current: &Version,
+        channel: &str,
+        platform: &str,
+    ) -> Option<&Artifact> {
+        if self.halted || self.channel != channel {
+            return None;
+        }
+        match Version::parse(&self.version) {
+            Ok(version) if version > *current => {}
+            _ => return None,
+        }
+        self.artifacts.iter().find(|a| a.platform == platform)
+    }
+}
+
+/// Platform name artifacts are published under
+pub fn current_platform() -> String {
+    format!("{}-{}", std::env::consts::ARCH, std::env::consts::OS)
+}
+
+/// Check a signed manifest against the publisher's key and parse its release
+pub fn verify_manifest(body: &[u8], public_key: &VerifyingKey) -> Result<Release, UpdateError> {
+    let manifest: SignedManifest =
+        serde_json::from_slice(body).map_err(|e| UpdateError::Manifest(e.to_string()))?;
+    let signature = hex::decode(manifest.signature.trim())
+        .ok()
+        .and_then(|bytes| Signature::from_slice(&bytes).ok())
+        .ok_or_else(|| UpdateError::Signature("not a hex Ed25519 signature".to_string()))?;
+    public_key
+        .verify(manifest.release.as_bytes(), &signature)
+        .map_err(|_| UpdateError::Signature("does not match the release key".to_string()))?;
+
+    serde_json::from_str(&manifest.release).map_err(|e| UpdateError::Manifest(e.to_string()))
+}
+
+/// Parse a hex Ed25519 public key
+pub fn parse_public_key(hex_key: &str) -> Result<VerifyingKey, UpdateError> {
+    let bytes: [u8; 32] = hex::decode(hex_key.trim())
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| UpdateError::Signature("public key must be 32 hex bytes".to_string()))?;
+    VerifyingKey::from_bytes(&bytes).map_err(|e| UpdateError::Signature(e.to_string()))
+}
+
+/// Move the staged binary into place, keeping the current one as
+/// `<binary>.previous`
+///
+/// Both are renames within one directory, so the binary is never half
+/// written; the running process keeps its (now renamed) file open.
+pub fn swap_binary(staged: &Path, binary: &Path) -> std::io::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(staged, std::fs::Permissions::from_mode(0o755))?;
+    }
+
+    let previous = sibling(binary, "previous");
+    if binary.exists() {
+        std::fs::rename(binary, &previous)?;
+    }
+    if let Err(e) = std::fs::rename(staged, binary) {
+        // Put the old binary back so the node can still start
+        let _ = std::fs::rename(&previous, binary);
+        return Err(e);
+    }
+    Ok(())
+}
+
+/// `<binary>.<suffix>` in the binary's directory
+fn sibling(binary: &Path, suffix: &str) -> PathBuf {
+    let mut name = binary.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(suffix);
+    binary.with_file_name(name)
+}
+
+/// Polls the release manifest and installs new releases
+pub struct Updater {
+    settings: UpdateSettings,
+    manifest_url: String,
+    public_key: VerifyingKey,
+    /// Binary replaced by updates, resolved before any update
+    binary: PathBuf,
+    current: Version,
+    /// Delay of the first check, so a fleet does not download at once
+    stagger: Duration,
+    client: reqwest::Client,
+}
+
+impl Updater {
+    /// Create an updater for the running binary
+    pub fn new(
+        settings: &UpdateSettings,
+        cyxwiz_api: &CyxWizApiSettings,
+        node_id: &str,
+    ) -> Result<Self, UpdateError> {
+        let public_key = parse_public_key(settings.public_key.as_deref().unwrap_or_default())?;
+        let binary = std::env::current_exe()?;
+        let current =
+            Version::parse(env!("CARGO_PKG_VERSION")).expect("package version is valid semver");
+
+        // Same offset for a node across restarts, spread over the interval
+        let hash = blake3::hash(node_id.as_bytes());
+        let offset = u64::from_le_bytes(hash.as_bytes()[..8].try_into().unwrap());
+        let stagger = Duration::from_secs(offset % settings.check_interval_secs.max(1));
+
+        Ok(Self {
+            settings: settings.clone(),
+            manifest_url: settings.manifest_url(cyxwiz_api),
+            public_key,
+            binary,
+            current,
+            stagger,
+            client: reqwest::Client::new(),
+        })
+    }
+
+    /// Binary updates are installed to
+    pub fn binary(&self) -> &Path {
+        &self.binary
+    }
+
+    /// Fetch the manifest and return the release to install, if any
+    pub async fn check(&self) -> Result<Option<(Release, Artifact)>, UpdateError> {
+        let body = self
+            .client
+            .get(&self.manifest_url)
+            .timeout(MANIFEST_TIMEOUT)
+            .send()
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?;
+        let release = verify_manifest(&body, &self.public_key)?;
+        if release.halted {
+            info!(version = %release.version, "Release rollout halted by the publisher");
+        }
+
+        let artifact = release
+            .update_for(&self.current, &self.settings.channel, &current_platform())
+            .cloned();
+        Ok(artifact.map(|artifact| (release, artifact)))
+    }
+
+    /// Download, verify and install the artifact of a release
+    pub async fn install(&self, artifact: &Artifact) -> Result<(), UpdateError> {
+        let staged = sibling(&self.binary, "update");
+        let result = self.download(artifact, &staged).await;
+        if let Err(e) = result {
+            let _ = tokio::fs::remove_file(&staged).await;
+            return Err(e);
+        }
+        swap_binary(&staged, &self.binary)?;
+        Ok(())
+    }
+
+    /// Stream the artifact to `path`, checking its size and hash
+    async fn download(&self, artifact: &Artifact, path: &Path) -> Result<(), UpdateError> {
+        let mut response = self
+            .client
+            .get(&artifact.url)
+            .timeout(DOWNLOAD_TIMEOUT)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        let mut file = tokio::fs::File::create(path).await?;
+        let mut hasher = blake3::Hasher::new();
+        let mut size = 0u64;
+        while let Some(chunk) = response.chunk().await? {
+            size += chunk.len() as u64;
+            if size > artifact.size {
+                return Err(UpdateError::Verification(format!(
+                    "larger than the published {} bytes",
+                    artifact.size
+                )));
+            }
+            hasher.update(&chunk);
+            file.write_all(&chunk).await?;
+        }
+        file.sync_all().await?;
+
+        if size != artifact.size {
+            return Err(UpdateError::Verification(format!(
+                "{} bytes instead of {}",
+                size, artifact.size
+            )));
+        }
+        let hash = hasher.finalize().to_hex().to_string();
+        if !hash.eq_ignore_ascii_case(artifact.blake3.trim()) {
+            return Err(UpdateError::Verification(format!(
+                "BLAKE3 {} does not match the release",
+                hash
+            )));
+        }
+        Ok(())
+    }
+
+    /// Check periodically until a release is installed, then report its
+    /// version so the daemon can restart
+    pub async fn run(self, installed: oneshot::Sender<String>) {
+        info!(
+            channel = %self.settings.channel,
+            manifest = %self.manifest_url,
+            version = %self.current,
+            "Self-update enabled"
+        );
+        tokio::time::sleep(self.stagger).await;
+
+        let mut interval =
+            tokio::time::interval(Duration::from_secs(self.settings.check_interval_secs));
+        loop {
+            interval.tick().await;
+            let (release, artifact) = match self.check().await {
+                Ok(Some(update)) => update,
+                Ok(None) => {
+                    debug!("Node software is up to date");
+                    continue;
+                }
+                Err(e) => {
+                    warn!(error = %e, "Update check failed");
+                    continue;
+                }
+            };
+
+            info!(
+                version = %release.version,
+                channel = %release.channel,
+                url = %artifact.url,
+                "Installing node update"
+            );
+            match self.install(&artifact).await {
+                Ok(()) => {
+                    info!(version = %release.version, binary = ?self.binary, "Node update installed, restarting");
+                    let _ = installed.send(release.version);
+                    return;
+                }
+                Err(e) => warn!(error = %e, version = %release.version, "Node update failed"),
+            }
+        }
+    }
+}
+
+/// Start the installed binary: exec it in place (`update.restart = "exec"`,
+/// Unix only) or exit with [`UPDATE_EXIT_CODE`] for the supervisor
+pub fn restart(settings: &UpdateSettings, binary: &Path) -> ! {
+    #[cfg(unix)]
+    if settings.restart == "exec" {
+        use std::os::unix::process::CommandExt;
+        let err = std::process::Command::new(binary)
+            .args(std::env::args_os().skip(1))
+            .exec();
+        warn!(error = %err, binary = ?binary, "Failed to exec the new binary, exiting instead");
+    }
+    #[cfg(not(unix))]
+    let _ = (settings, binary);
+
+    std::process::exit(UPDATE_EXIT_CODE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signer, SigningKey};
+    use tempfile::TempDir;
+
+    fn release_json(version: &str, halted: bool) -> String {
+        serde_json::json!({
+            "channel": "stable",
+            "version": version,
+            "halted": halted,
+            "artifacts": [{
+                "platform": "x86_64-linux",
+                "url": "https://releases.example.com/cyxcloud-node",
+                "blake3": "00".repeat(32),
+                "size": 1024,
+            }],
+        })
+        .to_string()
+    }
+
+    fn sign(key: &SigningKey, release: &str) -> Vec<u8> {
+        serde_json::json!({
+            "release": release,
+            "signature": hex::encode(key.sign(release.as_bytes()).to_bytes()),
+        })
+        .to_string()
+        .into_bytes()
+    }
+
+    #[test]
+    fn test_verify_manifest() {
+        let key = SigningKey::from_bytes(&[7u8; 32]);
+        let public_key = parse_public_key(&hex::encode(key.verifying_key().to_bytes())).unwrap();
+        let release = release_json("9.0.0", false);
+
+        let parsed = verify_manifest(&sign(&key, &release), &public_key).unwrap();
+        assert_eq!(parsed.version, "9.0.0");
+        assert_eq!(parsed.artifacts[0].size, 1024);
+
+        // Signed by someone else
+        let other = SigningKey::from_bytes(&[8u8; 32]);
+        assert!(matches!(
+            verify_manifest(&sign(&other, &release), &public_key),
+            Err(UpdateError::Signature(_))
+        ));
+
+        // Release changed after signing
+        let mut manifest: serde_json::Value =
+            serde_json::from_slice(&sign(&key, &release)).unwrap();
+        manifest["release"] = release.replace("9.0.0", "9.0.1").into();
+        assert!(verify_manifest(manifest.to_string().as_bytes(), &public_key).is_err());
+
+        assert!(verify_manifest(b"not json", &public_key).is_err());
+        assert!(parse_public_key("abcd").is_err());
+    }
+
+    #[test]
+    fn test_release_update_for() {
+        let current = Version::parse("1.2.0").unwrap();
+        let release: Release = serde_json::from_str(&release_json("1.3.0", false)).unwrap();
+        assert!(release
+            .update_for(&current, "stable", "x86_64-linux")
+            .is_some());
+
+        // Other channel or platform
+        assert!(release
+            .update_for(&current, "beta", "x86_64-linux")
+            .is_none());
+        assert!(release
+            .update_for(&current, "stable", "aarch64-linux")
+            .is_none());
+
+        // Same or older versions are never installed
+        for version in ["1.2.0", "1.1.9", "1.3.0-beta.1", "not-a-version"] {
+            let release: Release = serde_json::from_str(&release_json(version, false)).unwrap();
+            assert!(
+                release
+                    .update_for(&Version::parse("1.3.0").unwrap(), "stable", "x86_64-linux")
+                    .is_none(),
+                "{}",
+                version
+            );
+        }
+
+        let halted: Release = serde_json::from_str(&release_json("1.3.0", true)).unwrap();
+        assert!(halted
+            .update_for(&current, "stable", "x86_64-linux")
+            .is_none());
+    }
+
+    #[test]
+    fn test_swap_binary() {
+        let dir = TempDir::new().unwrap();
+        let binary = dir.path().join("cyxcloud-node");
+        let staged = dir.path().join("cyxcloud-node.update");
+        std::fs::write(&binary, b"old").unwrap();
+        std::fs::write(&staged, b"new").unwrap();
+
+        swap_binary(&staged, &binary).unwrap();
+        assert_eq!(std::fs::read(&binary).unwrap(), b"new");
+        assert_eq!(
+            std::fs::read(dir.path().join("cyxcloud-node.previous")).unwrap(),
+            b"old"
+        );
+        assert!(!staged.exists());
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&binary).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o755);
+        }
+    }
+}
@@ -2,9 +2,21 @@
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Compile proto files
+    //
+    // Chunk and object payloads are generated as `bytes::Bytes` so they move
+    // between storage, gRPC and the erasure coder without copies.
     tonic_build::configure()
         .build_server(true)
         .build_client(true)
+        .bytes([
+            ".cyxcloud.chunk.StoreChunkRequest.data",
+            ".cyxcloud.chunk.GetChunkResponse.data",
+            ".cyxcloud.chunk.ChunkData.data",
+            ".cyxcloud.chunk.ChunkFrame.data",
+            ".cyxcloud.chunk.WriteChunkFrame.data",
+            ".cyxcloud.object.PutObjectRequest.data",
+            ".cyxcloud.object.GetObjectResponse.data",
+        ])
         .compile(
             &[
                 "proto/chunk.proto",
@@ -12,6 +24,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 "proto/node.proto",
                 "proto/data.proto",
                 "proto/datastream.proto",
+                "proto/object.proto",
             ],
             &["proto"],
         )?;
@@ -8,6 +8,7 @@
 //! - `NodeService` - Node registration and health
 //! - `DataService` - Stream ML training data (basic)
 //! - `DataStreamService` - Zero-copy ML training data with verification
+//! - `ObjectService` - Bucket and object management
 
 /// Chunk service messages and client/server
 pub mod chunk {
@@ -34,6 +35,11 @@ pub mod datastream {
     tonic::include_proto!("cyxcloud.datastream");
 }
 
+/// Object service messages and client/server
+pub mod object {
+    tonic::include_proto!("cyxcloud.object");
+}
+
 // Re-export commonly used types
 pub use chunk::chunk_service_client::ChunkServiceClient;
 pub use chunk::chunk_service_server::{ChunkService, ChunkServiceServer};
@@ -45,3 +51,5 @@ pub use metadata::metadata_service_client::MetadataServiceClient;
 pub use metadata::metadata_service_server::{MetadataService, MetadataServiceServer};
 pub use node::node_service_client::NodeServiceClient;
 pub use node::node_service_server::{NodeService, NodeServiceServer};
+pub use object::object_service_client::ObjectServiceClient;
+pub use object::object_service_server::{ObjectService, ObjectServiceServer};
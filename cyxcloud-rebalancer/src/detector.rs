@@ -5,11 +5,33 @@
 //! - Over-replicated chunks (above target replication factor)
 //! - Orphaned chunks (no longer referenced by any file)
 //! - Corrupt chunks (failed integrity check)
+//!
+//! Integrity is checked on a sample of each node's copies per scan, weighted
+//! toward the copies that went longest without verification.
+//!
+//! Under-replicated chunks are prioritized by their risk of permanent loss
+//! (see [`RepairRisk`]): copies on fragile nodes count as missing, shards whose
+//! stripe can still be rebuilt rank below whole chunks and stripes without
+//! spare shards, and archive objects rank above restored copies.
+//!
+//! Issues caused by unavailable nodes are correlated into incidents (see
+//! [`crate::incident`]), and repairs for large outages are held back until
+//! the outage has stabilized.
+//!
+//! In incremental mode, once a full scan has completed, a scan reads only the
+//! chunks whose replication changed since the previous scan, the chunks the
+//! previous scan reported, and one slice of a rolling sweep that covers the
+//! keyspace every `full_sweep_interval`. The sweep catches changes whose
+//! transaction committed after a later scan had already moved past them.
 
+use crate::incident::{correlate, Incident, NodeDomains, OutageTracker};
+use crate::keyspace::KeyRange;
+use chrono::{DateTime, Utc};
+use rand::Rng;
 use std::collections::{HashMap, HashSet};
 use std::time::{Duration, Instant};
 use thiserror::Error;
-use tracing::{debug, info, instrument};
+use tracing::{debug, info, instrument, warn};
 
 /// Detector errors
 #[derive(Error, Debug)]
@@ -43,6 +65,34 @@ pub enum ChunkHealth {
     Corrupt { node_ids: Vec<String> },
 }
 
+/// Issues at or above this priority are never held back during an outage
+/// (at most one safe copy left and few spare shards to rebuild it from)
+const AT_RISK_PRIORITY: u32 = 800;
+
+/// Priority added to chunks nothing could rebuild after one more loss (whole
+/// chunks, and shards of stripes without spare shards)
+const NO_SPARE_BONUS: u32 = 50;
+
+/// Priority taken off per spare shard of a chunk's stripe, at most
+/// `MAX_SPARE_CREDIT`
+const SPARE_SHARD_CREDIT: u32 = 50;
+const MAX_SPARE_CREDIT: u32 = 150;
+
+/// Priority added to chunks of archive objects, which are rarely read, so
+/// their losses go unnoticed the longest
+const ARCHIVE_BONUS: u32 = 20;
+
+/// Priority taken off chunks of restored copies, which can be restored again
+/// from the archived object
+const RESTORED_COPY_CREDIT: u32 = 100;
+
+/// Candidates fetched per sampled copy, so the sample has room to vary
+const INTEGRITY_CANDIDATE_FACTOR: usize = 4;
+
+/// Incremental scans start this long before the previous scan, so changes
+/// stamped by transactions still open at that scan are not skipped
+const CHANGE_OVERLAP: chrono::Duration = chrono::Duration::minutes(5);
+
 /// Information about a chunk that needs attention
 #[derive(Debug, Clone)]
 pub struct ChunkIssue {
@@ -52,8 +102,12 @@ pub struct ChunkIssue {
     pub health: ChunkHealth,
     /// Nodes currently holding this chunk
     pub current_nodes: Vec<String>,
+    /// Nodes holding other shards of the same chunk (anti-affinity)
+    pub sibling_nodes: Vec<String>,
     /// File ID this chunk belongs to (if any)
     pub file_id: Option<String>,
+    /// Position of the chunk's stripe within the file (erasure coded shards)
+    pub chunk_index: Option<u32>,
     /// Priority score (higher = more urgent)
     pub priority: u32,
     /// When the issue was detected
@@ -80,6 +134,41 @@ impl ChunkIssue {
             ChunkHealth::Healthy => 0,
         }
     }
+
+    /// Priority of an under-replicated chunk weighted by its risk of
+    /// permanent loss
+    ///
+    /// Only `safe_copies` of the available copies count; the priority is
+    /// then raised or lowered by how rebuildable the chunk is and by the
+    /// class of its file.
+    pub fn risk_priority(health: &ChunkHealth, safe_copies: usize, risk: &RepairRisk) -> u32 {
+        let health = match *health {
+            ChunkHealth::UnderReplicated { target, .. } => ChunkHealth::UnderReplicated {
+                current: safe_copies,
+                target,
+            },
+            ref other => other.clone(),
+        };
+
+        let mut priority = Self::calculate_priority(&health);
+        match risk.spare_shards() {
+            0 => priority += NO_SPARE_BONUS,
+            spare => {
+                priority = priority.saturating_sub(
+                    (spare as u32)
+                        .saturating_mul(SPARE_SHARD_CREDIT)
+                        .min(MAX_SPARE_CREDIT),
+                )
+            }
+        }
+        if risk.archive {
+            priority += ARCHIVE_BONUS;
+        }
+        if risk.restored_copy {
+            priority = priority.saturating_sub(RESTORED_COPY_CREDIT);
+        }
+        priority
+    }
 }
 
 /// Detector configuration
@@ -91,10 +180,25 @@ pub struct DetectorConfig {
     pub batch_size: usize,
     /// Minimum time between full scans
     pub scan_interval: Duration,
-    /// Enable integrity checking
+    /// Enable sampled integrity checking
     pub verify_integrity: bool,
+    /// Copies verified per node each scan when integrity checking is enabled
+    pub integrity_samples_per_node: usize,
     /// Timeout for node health checks
     pub health_check_timeout: Duration,
+    /// Fraction of a failure domain's nodes that must be down to treat it as
+    /// a domain outage
+    pub outage_domain_ratio: f64,
+    /// Affected chunks from which a single-node incident counts as large
+    pub large_outage_chunks: usize,
+    /// How long repairs for a large outage are held after its most recent
+    /// node failure (zero disables holding)
+    pub outage_stabilization_delay: Duration,
+    /// Only read chunks changed since the previous scan, when the metadata
+    /// client tracks changes
+    pub incremental: bool,
+    /// Time the rolling sweep of incremental scans takes to cover the keyspace
+    pub full_sweep_interval: Duration,
 }
 
 impl Default for DetectorConfig {
@@ -104,8 +208,25 @@ impl Default for DetectorConfig {
             batch_size: 1000,
             scan_interval: Duration::from_secs(60),
             verify_integrity: false, // Expensive, enable in production
+            integrity_samples_per_node: 10,
             health_check_timeout: Duration::from_secs(5),
+            outage_domain_ratio: 0.5,
+            large_outage_chunks: 1000,
+            outage_stabilization_delay: Duration::from_secs(300),
+            incremental: true,
+            full_sweep_interval: Duration::from_secs(60 * 60),
+        }
+    }
+}
+
+impl DetectorConfig {
+    /// Slices the rolling sweep splits the keyspace into, one per scan
+    fn sweep_slices(&self) -> usize {
+        if self.scan_interval.is_zero() {
+            return 1;
         }
+        let slices = self.full_sweep_interval.as_secs_f64() / self.scan_interval.as_secs_f64();
+        (slices.ceil() as usize).clamp(1, crate::keyspace::KEYSPACE_SLOTS as usize)
     }
 }
 
@@ -126,6 +247,19 @@ pub struct ScanResult {
     pub duration: Duration,
     /// Any errors encountered
     pub errors: Vec<String>,
+    /// Correlated node failures behind the issues
+    pub incidents: Vec<Incident>,
+    /// Under-replicated chunks whose repair is held while an outage stabilizes
+    pub deferred: Vec<ChunkIssue>,
+    /// Total size of the under-replicated chunks (including deferred ones)
+    pub bytes_at_risk: u64,
+    /// Under-replicated chunks one more lost copy away from permanent loss,
+    /// or already lost (including deferred ones)
+    pub chunks_at_permanent_risk: usize,
+    /// Total size of the chunks at risk of permanent loss
+    pub bytes_at_permanent_risk: u64,
+    /// Only changed chunks and a sweep slice were read (incremental scan)
+    pub incremental: bool,
 }
 
 impl ScanResult {
@@ -143,6 +277,15 @@ impl ScanResult {
         issues
     }
 
+    /// Under-replicated chunks with no healthy replica left
+    pub fn critical_count(&self) -> usize {
+        self.under_replicated
+            .iter()
+            .chain(self.deferred.iter())
+            .filter(|i| matches!(i.health, ChunkHealth::Critical))
+            .count()
+    }
+
     /// Check if there are any critical issues
     pub fn has_critical_issues(&self) -> bool {
         self.under_replicated
@@ -153,15 +296,34 @@ impl ScanResult {
 
     /// Get summary statistics
     pub fn summary(&self) -> String {
-        format!(
-            "Scanned {} chunks in {:?}: {} under-replicated, {} over-replicated, {} orphaned, {} corrupt",
+        let mut summary = format!(
+            "Scanned {} chunks{} in {:?}: {} under-replicated, {} over-replicated, {} orphaned, {} corrupt",
             self.total_scanned,
+            if self.incremental { " (incremental)" } else { "" },
             self.duration,
             self.under_replicated.len(),
             self.over_replicated.len(),
             self.orphaned.len(),
             self.corrupt.len()
-        )
+        );
+        if self.chunks_at_permanent_risk > 0 {
+            summary.push_str(&format!(
+                "; {} chunk(s) ({} bytes) at risk of permanent loss",
+                self.chunks_at_permanent_risk, self.bytes_at_permanent_risk
+            ));
+        }
+        if !self.incidents.is_empty() {
+            summary.push_str(&format!(
+                "; {} incident(s), {} domain outage(s), {} repair(s) deferred",
+                self.incidents.len(),
+                self.incidents
+                    .iter()
+                    .filter(|i| i.is_domain_outage())
+                    .count(),
+                self.deferred.len()
+            ));
+        }
+        summary
     }
 }
 
@@ -174,6 +336,15 @@ pub struct Detector {
     node_health: HashMap<String, bool>,
     /// Cached node availability status
     node_availability: HashMap<String, NodeAvailability>,
+    /// When currently unavailable nodes went down
+    outages: OutageTracker,
+    /// Where the next incremental scan starts reading changes, None until a
+    /// full scan has completed
+    change_watermark: Option<ChangeClock>,
+    /// Next slice of the rolling sweep
+    sweep_slice: usize,
+    /// Under-replicated chunks of the previous scan, read again until repaired
+    recheck: Vec<Vec<u8>>,
 }
 
 impl Detector {
@@ -184,6 +355,10 @@ impl Detector {
             last_scan: None,
             node_health: HashMap::new(),
             node_availability: HashMap::new(),
+            outages: OutageTracker::default(),
+            change_watermark: None,
+            sweep_slice: 0,
+            recheck: Vec::new(),
         }
     }
 
@@ -211,11 +386,19 @@ impl Detector {
 
         debug!(healthy_nodes = healthy_nodes.len(), "Got healthy nodes");
 
+        self.outages.update(
+            self.node_availability
+                .iter()
+                .filter(|(_, a)| **a == NodeAvailability::Unavailable)
+                .map(|(id, _)| id),
+            Instant::now(),
+        );
+        let mut failed_by_issue = Vec::new();
+
         // Step 2: Get under-replicated chunks from metadata
-        let under_rep_chunks = metadata_client
-            .get_under_replicated_chunks(self.config.batch_size)
-            .await
-            .map_err(|e| DetectorError::Metadata(e.to_string()))?;
+        let under_rep_chunks = self
+            .fetch_under_replicated(metadata_client, &mut result)
+            .await?;
 
         for chunk in under_rep_chunks {
             // Filter out nodes that are unhealthy
@@ -231,17 +414,39 @@ impl Detector {
             } else {
                 ChunkHealth::UnderReplicated {
                     current: available_nodes.len(),
-                    target: self.config.replication_factor,
+                    target: chunk
+                        .replication_factor
+                        .unwrap_or(self.config.replication_factor),
                 }
             };
 
-            let priority = ChunkIssue::calculate_priority(&health);
+            // Copies on fragile nodes are readable but may go at any moment
+            let safe_copies = available_nodes
+                .iter()
+                .filter(|n| !chunk.risk.fragile_nodes.contains(*n))
+                .count();
+            let priority = ChunkIssue::risk_priority(&health, safe_copies, &chunk.risk);
+            result.bytes_at_risk += chunk.size;
+            if chunk.risk.permanent_loss_risk(safe_copies) {
+                result.chunks_at_permanent_risk += 1;
+                result.bytes_at_permanent_risk += chunk.size;
+            }
 
+            failed_by_issue.push(
+                chunk
+                    .node_ids
+                    .iter()
+                    .filter(|n| !healthy_node_ids.contains(*n))
+                    .cloned()
+                    .collect::<Vec<_>>(),
+            );
             result.under_replicated.push(ChunkIssue {
                 chunk_id: chunk.chunk_id,
                 health,
                 current_nodes: available_nodes,
+                sibling_nodes: chunk.sibling_nodes,
                 file_id: chunk.file_id,
+                chunk_index: chunk.chunk_index,
                 priority,
                 detected_at: Instant::now(),
             });
@@ -249,15 +454,40 @@ impl Detector {
 
         result.total_scanned += result.under_replicated.len();
 
-        // Step 3: Check for over-replicated chunks (optional)
+        if !self.outages.failed_nodes().is_empty() {
+            let domains = network_client.get_node_domains().await.unwrap_or_else(|e| {
+                warn!(error = %e, "Failed to load failure domains");
+                HashMap::new()
+            });
+            self.correlate_outages(&mut result, &failed_by_issue, &domains);
+        }
+
+        // Step 3: Spot-check a sample of the copies on each healthy node
+        if self.config.verify_integrity {
+            self.verify_samples(metadata_client, network_client, &healthy_nodes, &mut result)
+                .await;
+        }
+
+        // Step 4: Check for over-replicated chunks (optional)
         // This is less critical and can be done less frequently
 
-        // Step 4: Update stats
+        // Step 5: Update stats
         result.duration = start.elapsed();
         self.last_scan = Some(Instant::now());
+        self.recheck = result
+            .under_replicated
+            .iter()
+            .chain(result.deferred.iter())
+            .map(|issue| issue.chunk_id.clone())
+            .collect();
 
         info!(
+            incremental = result.incremental,
             under_replicated = result.under_replicated.len(),
+            at_permanent_risk = result.chunks_at_permanent_risk,
+            corrupt = result.corrupt.len(),
+            deferred = result.deferred.len(),
+            incidents = result.incidents.len(),
             duration = ?result.duration,
             "Scan complete"
         );
@@ -265,6 +495,238 @@ impl Detector {
         Ok(result)
     }
 
+    /// Under-replicated chunks to examine this scan
+    ///
+    /// Reads every under-replicated chunk unless an incremental scan is
+    /// possible. A result that hit the batch size may have left changes out,
+    /// so the watermark only moves on when it did not.
+    async fn fetch_under_replicated<M: MetadataClient>(
+        &mut self,
+        metadata_client: &M,
+        result: &mut ScanResult,
+    ) -> Result<Vec<ChunkInfo>> {
+        let limit = self.config.batch_size;
+        let clock = if self.config.incremental {
+            metadata_client
+                .change_clock()
+                .await
+                .map_err(|e| DetectorError::Metadata(e.to_string()))?
+        } else {
+            None
+        };
+        let Some(clock) = clock else {
+            return metadata_client
+                .get_under_replicated_chunks(limit)
+                .await
+                .map_err(|e| DetectorError::Metadata(e.to_string()));
+        };
+
+        match self.change_watermark.clone() {
+            // The keyspace range changed: its new slots were never scanned
+            Some(since) if since.scope == clock.scope => {
+                let changed = metadata_client
+                    .get_changed_under_replicated_chunks(since.now, &self.recheck, limit)
+                    .await
+                    .map_err(|e| DetectorError::Metadata(e.to_string()))?;
+                let slices = self.config.sweep_slices();
+                let slice = self.sweep_slice % slices;
+                let sweep = metadata_client
+                    .get_under_replicated_chunks_in_slice(slice, slices, limit)
+                    .await
+                    .map_err(|e| DetectorError::Metadata(e.to_string()))?;
+                self.sweep_slice = (slice + 1) % slices;
+
+                debug!(
+                    changed = changed.len(),
+                    sweep_slice = slice,
+                    swept = sweep.len(),
+                    "Incremental scan"
+                );
+                if changed.len() < limit {
+                    self.change_watermark = Some(clock.rewound());
+                }
+                result.incremental = true;
+                Ok(merge_chunks(changed, sweep))
+            }
+            _ => {
+                let chunks = metadata_client
+                    .get_under_replicated_chunks(limit)
+                    .await
+                    .map_err(|e| DetectorError::Metadata(e.to_string()))?;
+                self.change_watermark = (chunks.len() < limit).then(|| clock.rewound());
+                Ok(chunks)
+            }
+        }
+    }
+
+    /// Verify a random sample of the copies stored on each healthy node
+    ///
+    /// Copies that failed a check are reported as `Corrupt` issues, one per
+    /// chunk. Checks that could not be carried out are recorded as errors and
+    /// leave the copy alone.
+    async fn verify_samples<M, N>(
+        &self,
+        metadata_client: &M,
+        network_client: &N,
+        healthy_nodes: &[String],
+        result: &mut ScanResult,
+    ) where
+        M: MetadataClient,
+        N: NetworkClient,
+    {
+        let samples = self.config.integrity_samples_per_node;
+        let mut corrupt: HashMap<Vec<u8>, (ChunkInfo, Vec<String>)> = HashMap::new();
+
+        for node_id in healthy_nodes {
+            let candidates = match metadata_client
+                .get_integrity_candidates(node_id, samples * INTEGRITY_CANDIDATE_FACTOR)
+                .await
+            {
+                Ok(candidates) => candidates,
+                Err(e) => {
+                    result
+                        .errors
+                        .push(format!("Integrity candidates for {}: {}", node_id, e));
+                    continue;
+                }
+            };
+            let sample = weighted_sample(candidates, samples, &mut rand::thread_rng());
+
+            for candidate in sample {
+                let chunk_id = candidate.chunk.chunk_id.clone();
+                let valid = match network_client
+                    .verify_chunk_integrity(node_id, &chunk_id)
+                    .await
+                {
+                    Ok(valid) => valid,
+                    Err(e) => {
+                        result.errors.push(format!(
+                            "Integrity check of {} on {}: {}",
+                            hex::encode(&chunk_id),
+                            node_id,
+                            e
+                        ));
+                        continue;
+                    }
+                };
+                result.total_scanned += 1;
+
+                if let Err(e) = metadata_client
+                    .record_integrity_check(&chunk_id, node_id, valid)
+                    .await
+                {
+                    warn!(node = %node_id, error = %e, "Failed to record integrity check");
+                }
+                if !valid {
+                    warn!(
+                        node = %node_id,
+                        chunk = %hex::encode(&chunk_id),
+                        "Sampled integrity check failed"
+                    );
+                    corrupt
+                        .entry(chunk_id)
+                        .or_insert_with(|| (candidate.chunk, Vec::new()))
+                        .1
+                        .push(node_id.clone());
+                }
+            }
+        }
+
+        for (chunk_id, (chunk, bad_nodes)) in corrupt {
+            let current_nodes = chunk
+                .node_ids
+                .into_iter()
+                .filter(|n| healthy_nodes.contains(n) && !bad_nodes.contains(n))
+                .collect();
+            let health = ChunkHealth::Corrupt {
+                node_ids: bad_nodes,
+            };
+            result.corrupt.push(ChunkIssue {
+                chunk_id,
+                priority: ChunkIssue::calculate_priority(&health),
+                health,
+                current_nodes,
+                sibling_nodes: chunk.sibling_nodes,
+                file_id: chunk.file_id,
+                chunk_index: chunk.chunk_index,
+                detected_at: Instant::now(),
+            });
+        }
+    }
+
+    /// Group under-replicated issues into incidents and defer the repairs of
+    /// large outages that are still within their stabilization delay
+    ///
+    /// `failed_by_issue[i]` holds the unavailable nodes of
+    /// `result.under_replicated[i]`.
+    fn correlate_outages(
+        &self,
+        result: &mut ScanResult,
+        failed_by_issue: &[Vec<String>],
+        domains: &NodeDomains,
+    ) {
+        let mut incidents = correlate(
+            self.outages.failed_nodes(),
+            domains,
+            self.config.outage_domain_ratio,
+        );
+        let incident_of: HashMap<String, usize> = incidents
+            .iter()
+            .enumerate()
+            .flat_map(|(i, incident)| incident.failed_nodes.iter().map(move |n| (n.clone(), i)))
+            .collect();
+
+        for (issue, failed) in result.under_replicated.iter().zip(failed_by_issue) {
+            let touched: HashSet<usize> = failed
+                .iter()
+                .filter_map(|n| incident_of.get(n.as_str()).copied())
+                .collect();
+            for i in touched {
+                incidents[i].affected_chunks += 1;
+                if issue.priority >= AT_RISK_PRIORITY {
+                    incidents[i].at_risk_chunks += 1;
+                }
+            }
+        }
+
+        let delay = self.config.outage_stabilization_delay;
+        for incident in &mut incidents {
+            let large = incident.is_domain_outage()
+                || incident.affected_chunks >= self.config.large_outage_chunks;
+            let age = incident.last_failure.elapsed();
+            if large && age < delay {
+                incident.repair_hold = Some(delay - age);
+            }
+        }
+
+        // Hold an issue only when every failed node behind it is held and it
+        // still has more than one healthy copy
+        let issues = std::mem::take(&mut result.under_replicated);
+        for (issue, failed) in issues.into_iter().zip(failed_by_issue) {
+            let held = !failed.is_empty()
+                && issue.priority < AT_RISK_PRIORITY
+                && failed.iter().all(|n| {
+                    incident_of
+                        .get(n.as_str())
+                        .is_some_and(|&i| incidents[i].repair_hold.is_some())
+                });
+            if held {
+                result.deferred.push(issue);
+            } else {
+                result.under_replicated.push(issue);
+            }
+        }
+
+        for incident in &incidents {
+            if incident.is_domain_outage() || incident.repair_hold.is_some() {
+                warn!(incident = %incident.summary(), "Correlated outage detected");
+            } else {
+                debug!(incident = %incident.summary(), "Node outage");
+            }
+        }
+        result.incidents = incidents;
+    }
+
     /// Get list of healthy nodes (can read from these nodes)
     /// This includes both 'online' and 'recovering' nodes since they can serve existing chunks.
     async fn get_healthy_nodes<N: NetworkClient>(&mut self, client: &N) -> Result<Vec<String>> {
@@ -352,6 +814,83 @@ pub trait MetadataClient: Send + Sync {
         &self,
         limit: usize,
     ) -> std::result::Result<Vec<ChunkInfo>, Box<dyn std::error::Error + Send + Sync>>;
+
+    /// Copies stored on a node that may be picked for an integrity check,
+    /// least recently verified first
+    async fn get_integrity_candidates(
+        &self,
+        _node_id: &str,
+        _limit: usize,
+    ) -> std::result::Result<Vec<IntegrityCandidate>, Box<dyn std::error::Error + Send + Sync>>
+    {
+        // Default implementation: nothing to check
+        Ok(Vec::new())
+    }
+
+    /// Record the outcome of an integrity check of a node's copy
+    async fn record_integrity_check(
+        &self,
+        _chunk_id: &[u8],
+        _node_id: &str,
+        _valid: bool,
+    ) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Ok(())
+    }
+
+    /// Current time of the clock chunk changes are recorded with, None if
+    /// changes are not tracked (every scan is then a full scan)
+    async fn change_clock(
+        &self,
+    ) -> std::result::Result<Option<ChangeClock>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(None)
+    }
+
+    /// Under-replicated chunks whose replication changed at or after
+    /// `since`, plus those whose ID is in `recheck`
+    async fn get_changed_under_replicated_chunks(
+        &self,
+        _since: DateTime<Utc>,
+        _recheck: &[Vec<u8>],
+        limit: usize,
+    ) -> std::result::Result<Vec<ChunkInfo>, Box<dyn std::error::Error + Send + Sync>> {
+        // Default implementation: everything may have changed
+        self.get_under_replicated_chunks(limit).await
+    }
+
+    /// Under-replicated chunks in part `slice` of the keyspace split into
+    /// `slices` equal parts
+    async fn get_under_replicated_chunks_in_slice(
+        &self,
+        slice: usize,
+        _slices: usize,
+        limit: usize,
+    ) -> std::result::Result<Vec<ChunkInfo>, Box<dyn std::error::Error + Send + Sync>> {
+        // Default implementation: the whole keyspace is the first slice
+        if slice == 0 {
+            self.get_under_replicated_chunks(limit).await
+        } else {
+            Ok(Vec::new())
+        }
+    }
+}
+
+/// Reading of a metadata client's change clock
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChangeClock {
+    /// Current time of the clock changes are recorded with
+    pub now: DateTime<Utc>,
+    /// Keyspace range the client's queries cover
+    pub scope: KeyRange,
+}
+
+impl ChangeClock {
+    /// Watermark to read the next scan's changes from
+    fn rewound(self) -> Self {
+        Self {
+            now: self.now - CHANGE_OVERLAP,
+            ..self
+        }
+    }
 }
 
 /// Node availability status for rebalancing
@@ -409,6 +948,14 @@ pub trait NetworkClient: Send + Sync {
         node_id: &str,
         chunk_id: &[u8],
     ) -> std::result::Result<bool, Box<dyn std::error::Error + Send + Sync>>;
+
+    /// Failure domain of every node, keyed by node ID
+    async fn get_node_domains(
+        &self,
+    ) -> std::result::Result<NodeDomains, Box<dyn std::error::Error + Send + Sync>> {
+        // Default implementation: no topology information
+        Ok(HashMap::new())
+    }
 }
 
 /// Chunk information from metadata
@@ -416,13 +963,89 @@ pub trait NetworkClient: Send + Sync {
 pub struct ChunkInfo {
     pub chunk_id: Vec<u8>,
     pub node_ids: Vec<String>,
+    /// Nodes holding sibling shards of the same chunk
+    pub sibling_nodes: Vec<String>,
     pub file_id: Option<String>,
+    /// Stripe the shard belongs to within its file
+    pub chunk_index: Option<u32>,
     pub size: u64,
+    /// Copies the chunk should have, when it records its own (erasure coded
+    /// shards and replicated small objects can differ from the default)
+    pub replication_factor: Option<usize>,
+    /// What makes losing the chunk more or less recoverable
+    pub risk: RepairRisk,
+}
+
+/// What, besides its missing copies, decides how close a chunk is to
+/// permanent loss
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RepairRisk {
+    /// Nodes whose copy is readable but fragile: the node is recovering,
+    /// draining or in maintenance, or the copy failed verification
+    pub fragile_nodes: Vec<String>,
+    /// Readable shards of the chunk's erasure-coded stripe beyond the data
+    /// shards needed to rebuild it; None for whole chunks, which nothing can
+    /// rebuild
+    pub stripe_spare: Option<i32>,
+    /// The chunk belongs to an archive object
+    pub archive: bool,
+    /// The chunk belongs to a restored copy of an archived object
+    pub restored_copy: bool,
+}
+
+impl RepairRisk {
+    /// Further losses the chunk's data survives once its own copies are gone
+    pub fn spare_shards(&self) -> usize {
+        self.stripe_spare.unwrap_or(0).max(0) as usize
+    }
+
+    /// Whether losing one more copy (or the losses so far) destroys data for
+    /// good
+    pub fn permanent_loss_risk(&self, safe_copies: usize) -> bool {
+        safe_copies <= 1 && self.spare_shards() == 0
+    }
+}
+
+/// A node's copy of a chunk that may be picked for an integrity check
+#[derive(Debug, Clone)]
+pub struct IntegrityCandidate {
+    /// The chunk, with every node holding it
+    pub chunk: ChunkInfo,
+    /// Time since the copy was last verified (or stored, if never verified)
+    pub unverified_for: Duration,
+}
+
+/// Chunks of both lists, each chunk once
+fn merge_chunks(mut chunks: Vec<ChunkInfo>, more: Vec<ChunkInfo>) -> Vec<ChunkInfo> {
+    let mut seen: HashSet<Vec<u8>> = chunks.iter().map(|c| c.chunk_id.clone()).collect();
+    chunks.extend(more.into_iter().filter(|c| seen.insert(c.chunk_id.clone())));
+    chunks
+}
+
+/// Pick up to `count` candidates at random without replacement, weighted by
+/// how long each copy has gone unverified
+fn weighted_sample<R: Rng>(
+    candidates: Vec<IntegrityCandidate>,
+    count: usize,
+    rng: &mut R,
+) -> Vec<IntegrityCandidate> {
+    // Efraimidis-Spirakis: keep the largest u^(1/w), compared as ln(u)/w
+    let mut keyed: Vec<(f64, IntegrityCandidate)> = candidates
+        .into_iter()
+        .map(|c| {
+            let weight = c.unverified_for.as_secs_f64() + 1.0;
+            (rng.gen::<f64>().ln() / weight, c)
+        })
+        .collect();
+    keyed.sort_by(|a, b| b.0.total_cmp(&a.0));
+    keyed.truncate(count);
+    keyed.into_iter().map(|(_, c)| c).collect()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::incident::FailureDomain;
 
     #[test]
     fn test_priority_calculation() {
@@ -471,7 +1094,9 @@ mod tests {
                 target: 3,
             },
             current_nodes: vec!["n1".to_string()],
+            sibling_nodes: vec![],
             file_id: None,
+            chunk_index: None,
             priority: 800,
             detected_at: Instant::now(),
         });
@@ -479,6 +1104,7 @@ mod tests {
         let summary = result.summary();
         assert!(summary.contains("100 chunks"));
         assert!(summary.contains("1 under-replicated"));
+        assert_eq!(result.critical_count(), 0);
     }
 
     #[test]
@@ -492,7 +1118,9 @@ mod tests {
                 target: 3,
             },
             current_nodes: vec![],
+            sibling_nodes: vec![],
             file_id: None,
+            chunk_index: None,
             priority: 600,
             detected_at: Instant::now(),
         });
@@ -503,7 +1131,9 @@ mod tests {
                 node_ids: vec!["n1".to_string()],
             },
             current_nodes: vec![],
+            sibling_nodes: vec![],
             file_id: None,
+            chunk_index: None,
             priority: 700,
             detected_at: Instant::now(),
         });
@@ -513,4 +1143,416 @@ mod tests {
         assert_eq!(issues[0].priority, 700); // Corrupt first (higher priority)
         assert_eq!(issues[1].priority, 600);
     }
+
+    fn issue(current: usize, priority: u32) -> ChunkIssue {
+        ChunkIssue {
+            chunk_id: vec![current as u8],
+            health: ChunkHealth::UnderReplicated { current, target: 3 },
+            current_nodes: vec![],
+            sibling_nodes: vec![],
+            file_id: None,
+            chunk_index: None,
+            priority,
+            detected_at: Instant::now(),
+        }
+    }
+
+    #[test]
+    fn test_domain_outage_defers_repairs_until_stable() {
+        let mut detector = Detector::new(DetectorConfig::default());
+        let mut domains = HashMap::new();
+        for (node, rack) in [("a", 1), ("b", 2), ("c", 1), ("d", 2), ("e", 1)] {
+            let dc = if node == "e" { "dc2" } else { "dc1" };
+            domains.insert(
+                node.to_string(),
+                FailureDomain {
+                    region: None,
+                    datacenter: Some(dc.to_string()),
+                    rack: Some(rack),
+                },
+            );
+        }
+        let down = ["a".to_string(), "b".to_string(), "c".to_string()];
+        detector.outages.update(down.iter(), Instant::now());
+
+        let mut result = ScanResult::default();
+        result.under_replicated.push(issue(2, 600)); // lost a shard in dc1
+        result.under_replicated.push(issue(1, 800)); // only one copy left
+        result.under_replicated.push(issue(2, 600)); // no failed node behind it
+        let failed = vec![vec!["a".to_string()], vec!["b".to_string()], vec![]];
+
+        detector.correlate_outages(&mut result, &failed, &domains);
+
+        assert_eq!(result.incidents.len(), 1);
+        let incident = &result.incidents[0];
+        assert!(incident.is_domain_outage());
+        assert_eq!(incident.affected_chunks, 2);
+        assert_eq!(incident.at_risk_chunks, 1);
+        assert!(incident.repair_hold.is_some());
+
+        // At-risk and unrelated issues are still repaired right away
+        assert_eq!(result.deferred.len(), 1);
+        assert_eq!(result.under_replicated.len(), 2);
+        assert!(result.summary().contains("1 repair(s) deferred"));
+
+        // Without a stabilization delay nothing is held
+        detector.config.outage_stabilization_delay = Duration::ZERO;
+        let mut result = ScanResult::default();
+        result.under_replicated.push(issue(2, 600));
+        detector.correlate_outages(&mut result, &failed[..1], &domains);
+        assert!(result.deferred.is_empty());
+        assert_eq!(result.under_replicated.len(), 1);
+    }
+
+    #[test]
+    fn test_risk_priority_orders_by_loss_risk() {
+        let health = ChunkHealth::UnderReplicated {
+            current: 2,
+            target: 3,
+        };
+        let whole = RepairRisk::default();
+        let spare_shards = RepairRisk {
+            stripe_spare: Some(2),
+            ..Default::default()
+        };
+        let archive = RepairRisk {
+            archive: true,
+            ..Default::default()
+        };
+        let restored = RepairRisk {
+            restored_copy: true,
+            ..Default::default()
+        };
+
+        // A fragile copy counts as missing
+        let fragile = ChunkIssue::risk_priority(&health, 1, &whole);
+        let plain = ChunkIssue::risk_priority(&health, 2, &whole);
+        assert!(fragile >= AT_RISK_PRIORITY);
+        assert!(fragile > plain);
+
+        // Shards that can be rebuilt from their stripe wait for whole chunks
+        assert!(ChunkIssue::risk_priority(&health, 2, &spare_shards) < plain);
+        assert!(ChunkIssue::risk_priority(&health, 1, &spare_shards) < AT_RISK_PRIORITY);
+
+        assert!(ChunkIssue::risk_priority(&health, 2, &archive) > plain);
+        assert!(ChunkIssue::risk_priority(&health, 2, &restored) < plain);
+    }
+
+    #[test]
+    fn test_permanent_loss_risk() {
+        let whole = RepairRisk::default();
+        assert!(whole.permanent_loss_risk(0));
+        assert!(whole.permanent_loss_risk(1));
+        assert!(!whole.permanent_loss_risk(2));
+
+        let exhausted_stripe = RepairRisk {
+            stripe_spare: Some(-1),
+            ..Default::default()
+        };
+        assert_eq!(exhausted_stripe.spare_shards(), 0);
+        assert!(exhausted_stripe.permanent_loss_risk(1));
+
+        let spare_shards = RepairRisk {
+            stripe_spare: Some(1),
+            ..Default::default()
+        };
+        assert!(!spare_shards.permanent_loss_risk(0));
+    }
+
+    fn candidate(chunk: u8, nodes: &[&str], unverified_secs: u64) -> IntegrityCandidate {
+        IntegrityCandidate {
+            chunk: ChunkInfo {
+                chunk_id: vec![chunk],
+                node_ids: nodes.iter().map(|n| n.to_string()).collect(),
+                sibling_nodes: vec![],
+                file_id: None,
+                chunk_index: None,
+                size: 1024,
+                replication_factor: None,
+                risk: RepairRisk::default(),
+            },
+            unverified_for: Duration::from_secs(unverified_secs),
+        }
+    }
+
+    #[test]
+    fn test_weighted_sample_favours_stale_copies() {
+        use rand::SeedableRng;
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(7);
+        let mut stale_picked = 0;
+        for _ in 0..200 {
+            let candidates = vec![
+                candidate(1, &["n1"], 0),
+                candidate(2, &["n1"], 0),
+                candidate(3, &["n1"], 86_400),
+            ];
+            let sample = weighted_sample(candidates, 1, &mut rng);
+            assert_eq!(sample.len(), 1);
+            if sample[0].chunk.chunk_id == [3] {
+                stale_picked += 1;
+            }
+        }
+        assert!(stale_picked > 190);
+
+        let sample = weighted_sample(vec![candidate(1, &["n1"], 5)], 3, &mut rng);
+        assert_eq!(sample.len(), 1);
+    }
+
+    struct SampleMetadata {
+        candidates: HashMap<String, Vec<IntegrityCandidate>>,
+        recorded: std::sync::Mutex<Vec<(Vec<u8>, String, bool)>>,
+    }
+
+    #[async_trait::async_trait]
+    impl MetadataClient for SampleMetadata {
+        async fn get_under_replicated_chunks(
+            &self,
+            _limit: usize,
+        ) -> std::result::Result<Vec<ChunkInfo>, Box<dyn std::error::Error + Send + Sync>> {
+            Ok(vec![])
+        }
+
+        async fn get_orphaned_chunks(
+            &self,
+            _limit: usize,
+        ) -> std::result::Result<Vec<ChunkInfo>, Box<dyn std::error::Error + Send + Sync>> {
+            Ok(vec![])
+        }
+
+        async fn get_integrity_candidates(
+            &self,
+            node_id: &str,
+            limit: usize,
+        ) -> std::result::Result<Vec<IntegrityCandidate>, Box<dyn std::error::Error + Send + Sync>>
+        {
+            let mut candidates = self.candidates.get(node_id).cloned().unwrap_or_default();
+            candidates.truncate(limit);
+            Ok(candidates)
+        }
+
+        async fn record_integrity_check(
+            &self,
+            chunk_id: &[u8],
+            node_id: &str,
+            valid: bool,
+        ) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync>> {
+            self.recorded
+                .lock()
+                .unwrap()
+                .push((chunk_id.to_vec(), node_id.to_string(), valid));
+            Ok(())
+        }
+    }
+
+    /// Nodes n1..n3 are online; n1's copy of chunk 1 is corrupt and n3
+    /// cannot be asked about chunk 2
+    struct SampleNetwork;
+
+    #[async_trait::async_trait]
+    impl NetworkClient for SampleNetwork {
+        async fn get_all_nodes(
+            &self,
+        ) -> std::result::Result<Vec<String>, Box<dyn std::error::Error + Send + Sync>> {
+            Ok(vec!["n1".into(), "n2".into(), "n3".into()])
+        }
+
+        async fn check_node_health(
+            &self,
+            _node_id: &str,
+            _timeout: Duration,
+        ) -> std::result::Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+            Ok(true)
+        }
+
+        async fn verify_chunk_integrity(
+            &self,
+            node_id: &str,
+            chunk_id: &[u8],
+        ) -> std::result::Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+            match (node_id, chunk_id) {
+                ("n1", [1]) => Ok(false),
+                ("n3", [2]) => Err("unreachable".into()),
+                _ => Ok(true),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_scan_samples_integrity_and_reports_corrupt_copies() {
+        let metadata = SampleMetadata {
+            candidates: HashMap::from([
+                (
+                    "n1".to_string(),
+                    vec![candidate(1, &["n1", "n2"], 60), candidate(3, &["n1"], 0)],
+                ),
+                ("n2".to_string(), vec![candidate(1, &["n1", "n2"], 30)]),
+                ("n3".to_string(), vec![candidate(2, &["n3"], 10)]),
+            ]),
+            recorded: std::sync::Mutex::new(Vec::new()),
+        };
+
+        // Disabled by default
+        let mut detector = Detector::new(DetectorConfig::default());
+        let result = detector.scan(&metadata, &SampleNetwork).await.unwrap();
+        assert!(result.corrupt.is_empty());
+        assert!(metadata.recorded.lock().unwrap().is_empty());
+
+        let mut detector = Detector::new(DetectorConfig {
+            verify_integrity: true,
+            ..Default::default()
+        });
+        let result = detector.scan(&metadata, &SampleNetwork).await.unwrap();
+
+        assert_eq!(result.total_scanned, 3);
+        assert_eq!(result.errors.len(), 1);
+        assert_eq!(result.corrupt.len(), 1);
+        let issue = &result.corrupt[0];
+        assert_eq!(issue.chunk_id, vec![1]);
+        assert_eq!(
+            issue.health,
+            ChunkHealth::Corrupt {
+                node_ids: vec!["n1".to_string()]
+            }
+        );
+        assert_eq!(issue.current_nodes, vec!["n2".to_string()]);
+        assert_eq!(issue.priority, 700);
+
+        let recorded = metadata.recorded.lock().unwrap();
+        assert_eq!(recorded.len(), 3);
+        assert!(recorded.contains(&(vec![1], "n1".to_string(), false)));
+        assert!(recorded.contains(&(vec![1], "n2".to_string(), true)));
+    }
+
+    /// Chunk 9 has one of three copies; records which queries scans make
+    struct ChangingMetadata {
+        scope: std::sync::Mutex<KeyRange>,
+        calls: std::sync::Mutex<Vec<String>>,
+    }
+
+    impl ChangingMetadata {
+        fn chunk() -> ChunkInfo {
+            ChunkInfo {
+                chunk_id: vec![9],
+                node_ids: vec!["n1".to_string()],
+                sibling_nodes: vec![],
+                file_id: None,
+                chunk_index: None,
+                size: 10,
+                replication_factor: Some(3),
+                risk: RepairRisk::default(),
+            }
+        }
+
+        fn take_calls(&self) -> Vec<String> {
+            std::mem::take(&mut *self.calls.lock().unwrap())
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl MetadataClient for ChangingMetadata {
+        async fn get_under_replicated_chunks(
+            &self,
+            _limit: usize,
+        ) -> std::result::Result<Vec<ChunkInfo>, Box<dyn std::error::Error + Send + Sync>> {
+            self.calls.lock().unwrap().push("full".to_string());
+            Ok(vec![Self::chunk()])
+        }
+
+        async fn get_orphaned_chunks(
+            &self,
+            _limit: usize,
+        ) -> std::result::Result<Vec<ChunkInfo>, Box<dyn std::error::Error + Send + Sync>> {
+            Ok(vec![])
+        }
+
+        async fn change_clock(
+            &self,
+        ) -> std::result::Result<Option<ChangeClock>, Box<dyn std::error::Error + Send + Sync>>
+        {
+            Ok(Some(ChangeClock {
+                now: Utc::now(),
+                scope: *self.scope.lock().unwrap(),
+            }))
+        }
+
+        async fn get_changed_under_replicated_chunks(
+            &self,
+            _since: DateTime<Utc>,
+            recheck: &[Vec<u8>],
+            _limit: usize,
+        ) -> std::result::Result<Vec<ChunkInfo>, Box<dyn std::error::Error + Send + Sync>> {
+            self.calls
+                .lock()
+                .unwrap()
+                .push(format!("changed {:?}", recheck));
+            Ok(recheck.iter().map(|_| Self::chunk()).collect())
+        }
+
+        async fn get_under_replicated_chunks_in_slice(
+            &self,
+            slice: usize,
+            slices: usize,
+            _limit: usize,
+        ) -> std::result::Result<Vec<ChunkInfo>, Box<dyn std::error::Error + Send + Sync>> {
+            self.calls
+                .lock()
+                .unwrap()
+                .push(format!("slice {}/{}", slice, slices));
+            Ok(vec![Self::chunk()])
+        }
+    }
+
+    #[tokio::test]
+    async fn test_incremental_scan_after_full_scan() {
+        let metadata = ChangingMetadata {
+            scope: std::sync::Mutex::new(KeyRange::FULL),
+            calls: std::sync::Mutex::new(Vec::new()),
+        };
+        let mut detector = Detector::new(DetectorConfig {
+            full_sweep_interval: Duration::from_secs(150),
+            ..Default::default()
+        });
+
+        let result = detector.scan(&metadata, &SampleNetwork).await.unwrap();
+        assert!(!result.incremental);
+        assert_eq!(metadata.take_calls(), vec!["full"]);
+
+        // Changed chunks (the previous result is rechecked) plus a sweep slice
+        for slice in ["slice 0/3", "slice 1/3", "slice 2/3", "slice 0/3"] {
+            let result = detector.scan(&metadata, &SampleNetwork).await.unwrap();
+            assert!(result.incremental);
+            assert_eq!(result.under_replicated.len(), 1);
+            assert_eq!(metadata.take_calls(), vec!["changed [[9]]", slice]);
+        }
+
+        // Slots that just joined the scope were never scanned
+        *metadata.scope.lock().unwrap() = KeyRange::FULL.slice(0, 2);
+        let result = detector.scan(&metadata, &SampleNetwork).await.unwrap();
+        assert!(!result.incremental);
+        assert_eq!(metadata.take_calls(), vec!["full"]);
+
+        // Disabled
+        let mut detector = Detector::new(DetectorConfig {
+            incremental: false,
+            ..Default::default()
+        });
+        for _ in 0..2 {
+            detector.scan(&metadata, &SampleNetwork).await.unwrap();
+        }
+        assert_eq!(metadata.take_calls(), vec!["full", "full"]);
+    }
+
+    #[test]
+    fn test_sweep_slices() {
+        let config = DetectorConfig::default();
+        assert_eq!(config.sweep_slices(), 60);
+
+        let config = DetectorConfig {
+            scan_interval: Duration::from_secs(60),
+            full_sweep_interval: Duration::from_secs(30),
+            ..Default::default()
+        };
+        assert_eq!(config.sweep_slices(), 1);
+    }
 }
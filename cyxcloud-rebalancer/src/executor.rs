@@ -3,19 +3,23 @@
 //! Executes repair plans with:
 //! - Parallel execution across nodes
 //! - Rate limiting per node
+//! - Moving tasks off sources whose queue grows too long
 //! - Progress tracking
 //! - Error handling and retries
+//! - Rebuilding batched shards from their siblings, one batch per chunk
+//! - Failing tasks whose copies don't match the chunk's checksum
 
+use cyxcloud_core::error::{ErrorCode, HasErrorCode};
 use std::collections::HashMap;
 use std::sync::atomic::AtomicU64;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 use thiserror::Error;
 use tokio::sync::{mpsc, RwLock, Semaphore};
 use tokio::time::timeout;
-use tracing::{error, info, instrument};
+use tracing::{debug, error, info, instrument, warn};
 
-use crate::planner::{RepairPlan, RepairTask};
+use crate::planner::{RepairBatch, RepairPlan, RepairTask};
 
 /// Executor errors
 #[derive(Error, Debug, Clone)]
@@ -35,22 +39,63 @@ pub enum ExecutorError {
     #[error("Rate limit exceeded")]
     RateLimitExceeded,
 
+    #[error("Copy failed verification: {0}")]
+    VerificationFailed(String),
+
     #[error("Executor shutdown")]
     Shutdown,
 }
 
+impl HasErrorCode for ExecutorError {
+    fn error_code(&self) -> ErrorCode {
+        match self {
+            ExecutorError::TransferFailed(_)
+            | ExecutorError::SourceUnavailable(_)
+            | ExecutorError::TargetUnavailable(_) => ErrorCode::NodeUnreachable,
+            ExecutorError::Timeout => ErrorCode::Timeout,
+            ExecutorError::RateLimitExceeded => ErrorCode::RateLimited,
+            ExecutorError::VerificationFailed(_) => ErrorCode::IntegrityError,
+            ExecutorError::Shutdown => ErrorCode::ServiceUnavailable,
+        }
+    }
+}
+
 pub type Result<T> = std::result::Result<T, ExecutorError>;
 
+/// Outcome of one transfer attempt
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TransferOutcome {
+    /// Targets whose copy was verified and recorded
+    pub stored: Vec<String>,
+    /// Targets whose copy did not match the chunk's checksum
+    pub rejected: Vec<String>,
+}
+
+impl TransferOutcome {
+    /// Every given target stored a verified copy
+    pub fn all_stored(targets: Vec<String>) -> Self {
+        Self {
+            stored: targets,
+            rejected: Vec::new(),
+        }
+    }
+}
+
 /// Result of executing a single repair task
 #[derive(Debug, Clone)]
 pub struct TaskResult {
     pub task_id: String,
+    pub chunk_id: Vec<u8>,
     pub success: bool,
     pub error: Option<ExecutorError>,
     pub bytes_transferred: u64,
     pub duration: Duration,
     pub targets_succeeded: Vec<String>,
     pub targets_failed: Vec<String>,
+    /// Failed targets whose copy did not match the chunk's checksum
+    pub targets_rejected: Vec<String>,
+    /// Corrupt copies the new ones replace (see [`RepairTask::replaces`])
+    pub replaces: Vec<String>,
 }
 
 /// Overall execution result
@@ -79,16 +124,26 @@ impl ExecutionResult {
         }
     }
 
+    /// Copies rejected by checksum verification
+    pub fn rejected_copies(&self) -> usize {
+        self.failed.iter().map(|t| t.targets_rejected.len()).sum()
+    }
+
     /// Summary string
     pub fn summary(&self) -> String {
-        format!(
+        let mut summary = format!(
             "{} succeeded, {} failed, {} bytes in {:?} ({:.1}% success rate)",
             self.succeeded.len(),
             self.failed.len(),
             self.total_bytes,
             self.duration,
             self.success_rate()
-        )
+        );
+        let rejected = self.rejected_copies();
+        if rejected > 0 {
+            summary.push_str(&format!(", {} copies rejected", rejected));
+        }
+        summary
     }
 }
 
@@ -101,6 +156,8 @@ pub struct ExecutorConfig {
     pub max_per_source: usize,
     /// Maximum concurrent repairs per target node
     pub max_per_target: usize,
+    /// Tasks queued on a source before new tasks move to another replica
+    pub source_queue_threshold: usize,
     /// Transfer timeout per task
     pub transfer_timeout: Duration,
     /// Number of retries for failed transfers
@@ -119,6 +176,7 @@ impl Default for ExecutorConfig {
             max_concurrent: 10,
             max_per_source: 3,
             max_per_target: 3,
+            source_queue_threshold: 6,
             transfer_timeout: Duration::from_secs(300), // 5 minutes
             max_retries: 3,
             retry_delay: Duration::from_secs(5),
@@ -156,6 +214,8 @@ pub struct Executor {
     node_semaphores: Arc<RwLock<HashMap<String, Arc<Semaphore>>>>,
     /// Bytes transferred per node
     node_bytes: Arc<RwLock<HashMap<String, AtomicU64>>>,
+    /// Tasks waiting for or reading from each source node
+    source_queues: Arc<Mutex<HashMap<String, usize>>>,
     /// Progress channel
     progress_tx: Option<mpsc::Sender<ProgressUpdate>>,
     /// Shutdown flag
@@ -172,6 +232,7 @@ impl Executor {
             global_semaphore,
             node_semaphores: Arc::new(RwLock::new(HashMap::new())),
             node_bytes: Arc::new(RwLock::new(HashMap::new())),
+            source_queues: Arc::new(Mutex::new(HashMap::new())),
             progress_tx: None,
             shutdown: Arc::new(RwLock::new(false)),
         }
@@ -190,11 +251,17 @@ impl Executor {
     pub async fn execute<F, Fut>(&self, plan: RepairPlan, transfer_fn: F) -> ExecutionResult
     where
         F: Fn(String, String, Vec<u8>, Vec<String>) -> Fut + Clone + Send + Sync + 'static,
-        Fut: std::future::Future<Output = std::result::Result<Vec<String>, String>> + Send,
+        Fut: std::future::Future<Output = std::result::Result<TransferOutcome, String>> + Send,
     {
         let start = Instant::now();
         let mut result = ExecutionResult::default();
 
+        if !plan.batches.is_empty() {
+            let shards: usize = plan.batches.iter().map(|b| b.shards.len()).sum();
+            warn!(shards, "No rebuild function given, skipping shard rebuilds");
+            result.skipped += shards;
+        }
+
         if plan.tasks.is_empty() {
             info!("No tasks to execute");
             return result;
@@ -245,11 +312,110 @@ impl Executor {
         result
     }
 
+    /// Execute a repair plan, rebuilding its batched shards with `rebuild_fn`
+    ///
+    /// `rebuild_fn` is called once per batch (and again on retry, with only
+    /// the shards and targets still missing). It returns, for each shard of
+    /// the batch in order, the targets that stored the rebuilt shard.
+    #[instrument(skip(self, plan, transfer_fn, rebuild_fn))]
+    pub async fn execute_with_rebuild<F, Fut, R, RFut>(
+        &self,
+        mut plan: RepairPlan,
+        transfer_fn: F,
+        rebuild_fn: R,
+    ) -> ExecutionResult
+    where
+        F: Fn(String, String, Vec<u8>, Vec<String>) -> Fut + Clone + Send + Sync + 'static,
+        Fut: std::future::Future<Output = std::result::Result<TransferOutcome, String>> + Send,
+        R: Fn(RepairBatch) -> RFut + Clone + Send + Sync + 'static,
+        RFut: std::future::Future<Output = std::result::Result<Vec<Vec<String>>, String>> + Send,
+    {
+        let start = Instant::now();
+        let batches = std::mem::take(&mut plan.batches);
+
+        let mut handles = Vec::new();
+        let mut skipped = 0;
+        for batch in batches {
+            if *self.shutdown.read().await {
+                skipped += batch.shards.len();
+                continue;
+            }
+
+            let executor = self.clone_for_task();
+            let rebuild = rebuild_fn.clone();
+            handles.push(tokio::spawn(async move {
+                executor.execute_batch(batch, rebuild).await
+            }));
+        }
+
+        let mut result = self.execute(plan, transfer_fn).await;
+        result.skipped += skipped;
+
+        for handle in handles {
+            match handle.await {
+                Ok(shard_results) => {
+                    for shard_result in shard_results {
+                        result.total_bytes += shard_result.bytes_transferred;
+                        if shard_result.success {
+                            result.succeeded.push(shard_result);
+                        } else {
+                            result.failed.push(shard_result);
+                        }
+                    }
+                }
+                Err(e) => {
+                    error!(error = %e, "Rebuild batch panicked");
+                }
+            }
+        }
+
+        result.duration = start.elapsed();
+        result
+    }
+
+    /// Tasks currently queued on or reading from each source node
+    pub fn source_queue_depths(&self) -> HashMap<String, usize> {
+        self.source_queues.lock().unwrap().clone()
+    }
+
+    /// Queue a task on its source, moving it to a less busy replica if the
+    /// planned source's queue has reached the threshold
+    fn enqueue_source(&self, task: &mut RepairTask) -> SourceSlot {
+        let mut queues = self.source_queues.lock().unwrap();
+        let depth = |node: &str| queues.get(node).copied().unwrap_or(0);
+
+        if depth(&task.source_node) >= self.config.source_queue_threshold {
+            let alternative = task
+                .issue
+                .current_nodes
+                .iter()
+                .filter(|n| **n != task.source_node && !task.target_nodes.contains(*n))
+                .min_by_key(|n| depth(n))
+                .filter(|n| depth(n) < self.config.source_queue_threshold);
+            if let Some(alternative) = alternative {
+                debug!(
+                    task_id = %task.task_id,
+                    from = %task.source_node,
+                    to = %alternative,
+                    queued = depth(&task.source_node),
+                    "Moving repair to a less busy source"
+                );
+                task.source_node = alternative.clone();
+            }
+        }
+
+        *queues.entry(task.source_node.clone()).or_default() += 1;
+        SourceSlot {
+            queues: self.source_queues.clone(),
+            node: task.source_node.clone(),
+        }
+    }
+
     /// Execute a single repair task
-    async fn execute_task<F, Fut>(&self, task: RepairTask, transfer_fn: F) -> TaskResult
+    async fn execute_task<F, Fut>(&self, mut task: RepairTask, transfer_fn: F) -> TaskResult
     where
         F: Fn(String, String, Vec<u8>, Vec<String>) -> Fut + Clone,
-        Fut: std::future::Future<Output = std::result::Result<Vec<String>, String>> + Send,
+        Fut: std::future::Future<Output = std::result::Result<TransferOutcome, String>> + Send,
     {
         let start = Instant::now();
         let task_id = task.task_id.clone();
@@ -270,29 +436,36 @@ impl Executor {
             Err(_) => {
                 return TaskResult {
                     task_id,
+                    chunk_id: task.chunk_id.clone(),
                     success: false,
                     error: Some(ExecutorError::Shutdown),
                     bytes_transferred: 0,
                     duration: start.elapsed(),
                     targets_succeeded: Vec::new(),
                     targets_failed: task.target_nodes.clone(),
+                    targets_rejected: Vec::new(),
+                    replaces: task.replaces.clone(),
                 };
             }
         };
 
         // Acquire node semaphores
+        let _source_slot = self.enqueue_source(&mut task);
         let source_sem = self.get_node_semaphore(&task.source_node).await;
         let _source_permit = match source_sem.acquire().await {
             Ok(p) => p,
             Err(_) => {
                 return TaskResult {
                     task_id,
+                    chunk_id: task.chunk_id.clone(),
                     success: false,
                     error: Some(ExecutorError::SourceUnavailable(task.source_node.clone())),
                     bytes_transferred: 0,
                     duration: start.elapsed(),
                     targets_succeeded: Vec::new(),
                     targets_failed: task.target_nodes.clone(),
+                    targets_rejected: Vec::new(),
+                    replaces: task.replaces.clone(),
                 };
             }
         };
@@ -311,8 +484,20 @@ impl Executor {
         let mut last_error = None;
         let mut targets_succeeded = Vec::new();
         let mut targets_failed = task.target_nodes.clone();
+        let mut targets_rejected: Vec<String> = Vec::new();
 
         for attempt in 0..=self.config.max_retries {
+            // A target that stored a bad copy isn't tried again; the chunk is
+            // planned again on the next scan
+            let pending: Vec<String> = targets_failed
+                .iter()
+                .filter(|t| !targets_rejected.contains(t))
+                .cloned()
+                .collect();
+            if pending.is_empty() {
+                break;
+            }
+
             if attempt > 0 {
                 // Report retry
                 self.report_progress(ProgressUpdate {
@@ -334,24 +519,32 @@ impl Executor {
                     task.source_node.clone(),
                     task_id.clone(),
                     task.chunk_id.clone(),
-                    targets_failed.clone(),
+                    pending,
                 ),
             )
             .await
             {
-                Ok(Ok(succeeded)) => {
+                Ok(Ok(outcome)) => {
                     // Some or all targets succeeded
-                    for s in &succeeded {
+                    for s in &outcome.stored {
                         if !targets_succeeded.contains(s) {
                             targets_succeeded.push(s.clone());
                         }
                     }
-                    targets_failed.retain(|t| !succeeded.contains(t));
+                    targets_failed.retain(|t| !outcome.stored.contains(t));
 
                     if targets_failed.is_empty() {
                         // All targets succeeded
                         break;
                     }
+                    if !outcome.rejected.is_empty() {
+                        warn!(
+                            task_id = %task_id,
+                            rejected = ?outcome.rejected,
+                            "Copies failed checksum verification"
+                        );
+                        targets_rejected.extend(outcome.rejected);
+                    }
                     last_error = Some(ExecutorError::TransferFailed(format!(
                         "Partial success: {} of {} targets",
                         targets_succeeded.len(),
@@ -369,6 +562,12 @@ impl Executor {
 
         let success = targets_failed.is_empty();
         let bytes_transferred = if success { task.chunk_size } else { 0 };
+        if !targets_rejected.is_empty() {
+            last_error = Some(ExecutorError::VerificationFailed(format!(
+                "checksum mismatch on {}",
+                targets_rejected.join(", ")
+            )));
+        }
 
         // Report completion
         self.report_progress(ProgressUpdate {
@@ -391,15 +590,152 @@ impl Executor {
 
         TaskResult {
             task_id,
+            chunk_id: task.chunk_id,
             success,
             error: if success { None } else { last_error },
             bytes_transferred,
             duration: start.elapsed(),
             targets_succeeded,
             targets_failed,
+            targets_rejected,
+            replaces: task.replaces,
         }
     }
 
+    /// Rebuild the shards of one batch, retrying shards and targets that failed
+    async fn execute_batch<R, RFut>(&self, batch: RepairBatch, rebuild_fn: R) -> Vec<TaskResult>
+    where
+        R: Fn(RepairBatch) -> RFut,
+        RFut: std::future::Future<Output = std::result::Result<Vec<Vec<String>>, String>> + Send,
+    {
+        let start = Instant::now();
+        let batch_id = batch.batch_id.clone();
+        let total_bytes = batch.estimated_bandwidth();
+
+        let mut targets_succeeded: Vec<Vec<String>> = vec![Vec::new(); batch.shards.len()];
+        let mut targets_failed: Vec<Vec<String>> = batch
+            .shards
+            .iter()
+            .map(|s| s.target_nodes.clone())
+            .collect();
+        let mut last_error = None;
+
+        let global_permit = match self.global_semaphore.acquire().await {
+            Ok(p) => Some(p),
+            Err(_) => {
+                last_error = Some(ExecutorError::Shutdown);
+                None
+            }
+        };
+
+        if global_permit.is_some() {
+            self.report_progress(ProgressUpdate {
+                task_id: batch_id.clone(),
+                bytes_transferred: 0,
+                total_bytes,
+                percent: 0.0,
+                status: ProgressStatus::Running,
+            })
+            .await;
+
+            for attempt in 0..=self.config.max_retries {
+                if attempt > 0 {
+                    self.report_progress(ProgressUpdate {
+                        task_id: batch_id.clone(),
+                        bytes_transferred: 0,
+                        total_bytes,
+                        percent: 0.0,
+                        status: ProgressStatus::Retrying(attempt),
+                    })
+                    .await;
+
+                    tokio::time::sleep(self.config.retry_delay).await;
+                }
+
+                // Only shards with targets still missing are rebuilt again
+                let pending: Vec<usize> = (0..batch.shards.len())
+                    .filter(|&i| !targets_failed[i].is_empty())
+                    .collect();
+                let mut retry = batch.clone();
+                retry.shards = pending
+                    .iter()
+                    .map(|&i| {
+                        let mut shard = batch.shards[i].clone();
+                        shard.target_nodes = targets_failed[i].clone();
+                        shard
+                    })
+                    .collect();
+
+                match timeout(self.config.transfer_timeout, rebuild_fn(retry)).await {
+                    Ok(Ok(stored)) => {
+                        for (&i, succeeded) in pending.iter().zip(stored) {
+                            targets_failed[i].retain(|t| !succeeded.contains(t));
+                            targets_succeeded[i].extend(succeeded);
+                        }
+                        if targets_failed.iter().all(|t| t.is_empty()) {
+                            break;
+                        }
+                        last_error = Some(ExecutorError::TransferFailed(
+                            "Partial success rebuilding shards".to_string(),
+                        ));
+                    }
+                    Ok(Err(e)) => {
+                        last_error = Some(ExecutorError::TransferFailed(e));
+                    }
+                    Err(_) => {
+                        last_error = Some(ExecutorError::Timeout);
+                    }
+                }
+            }
+        }
+
+        let succeeded = targets_failed.iter().filter(|t| t.is_empty()).count();
+        debug!(
+            batch_id = %batch_id,
+            shards = batch.shards.len(),
+            succeeded,
+            "Rebuild batch finished"
+        );
+        self.report_progress(ProgressUpdate {
+            task_id: batch_id.clone(),
+            bytes_transferred: 0,
+            total_bytes,
+            percent: 100.0 * succeeded as f32 / batch.shards.len().max(1) as f32,
+            status: if succeeded == batch.shards.len() {
+                ProgressStatus::Completed
+            } else {
+                ProgressStatus::Failed(
+                    last_error
+                        .as_ref()
+                        .map(|e| e.to_string())
+                        .unwrap_or_default(),
+                )
+            },
+        })
+        .await;
+
+        batch
+            .shards
+            .into_iter()
+            .zip(targets_succeeded.into_iter().zip(targets_failed))
+            .map(|(shard, (succeeded, failed))| {
+                let success = failed.is_empty();
+                TaskResult {
+                    task_id: shard.task_id,
+                    chunk_id: shard.chunk_id,
+                    success,
+                    error: if success { None } else { last_error.clone() },
+                    bytes_transferred: if success { shard.chunk_size } else { 0 },
+                    duration: start.elapsed(),
+                    targets_succeeded: succeeded,
+                    targets_failed: failed,
+                    targets_rejected: Vec::new(),
+                    replaces: Vec::new(),
+                }
+            })
+            .collect()
+    }
+
     /// Get or create node semaphore
     async fn get_node_semaphore(&self, node_id: &str) -> Arc<Semaphore> {
         let semaphores = self.node_semaphores.read().await;
@@ -429,6 +765,7 @@ impl Executor {
             global_semaphore: self.global_semaphore.clone(),
             node_semaphores: self.node_semaphores.clone(),
             node_bytes: self.node_bytes.clone(),
+            source_queues: self.source_queues.clone(),
             progress_tx: self.progress_tx.clone(),
             shutdown: self.shutdown.clone(),
         }
@@ -442,6 +779,24 @@ impl Executor {
     }
 }
 
+/// A task's place in its source's queue, released on drop
+struct SourceSlot {
+    queues: Arc<Mutex<HashMap<String, usize>>>,
+    node: String,
+}
+
+impl Drop for SourceSlot {
+    fn drop(&mut self) {
+        let mut queues = self.queues.lock().unwrap();
+        if let Some(depth) = queues.get_mut(&self.node) {
+            *depth -= 1;
+            if *depth == 0 {
+                queues.remove(&self.node);
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -464,10 +819,13 @@ mod tests {
                     target: 3,
                 },
                 current_nodes: vec![source.to_string()],
+                sibling_nodes: vec![],
                 file_id: None,
+                chunk_index: None,
                 priority: 100,
                 detected_at: StdInstant::now(),
             },
+            replaces: vec![],
         }
     }
 
@@ -486,22 +844,28 @@ mod tests {
 
         result.succeeded.push(TaskResult {
             task_id: "1".to_string(),
+            chunk_id: vec![1],
             success: true,
             error: None,
             bytes_transferred: 100,
             duration: Duration::from_secs(1),
             targets_succeeded: vec!["n1".to_string()],
             targets_failed: vec![],
+            targets_rejected: vec![],
+            replaces: vec![],
         });
 
         result.failed.push(TaskResult {
             task_id: "2".to_string(),
+            chunk_id: vec![1],
             success: false,
             error: Some(ExecutorError::Timeout),
             bytes_transferred: 0,
             duration: Duration::from_secs(1),
             targets_succeeded: vec![],
             targets_failed: vec!["n2".to_string()],
+            targets_rejected: vec![],
+            replaces: vec![],
         });
 
         assert_eq!(result.success_rate(), 50.0);
@@ -513,7 +877,7 @@ mod tests {
         let plan = RepairPlan::default();
 
         let result = executor
-            .execute(plan, |_, _, _, _| async { Ok(vec![]) })
+            .execute(plan, |_, _, _, _| async { Ok(TransferOutcome::default()) })
             .await;
 
         assert_eq!(result.succeeded.len(), 0);
@@ -530,7 +894,7 @@ mod tests {
         let result = executor
             .execute(plan, |_, _, _, targets| async move {
                 // Simulate successful transfer to all targets
-                Ok(targets)
+                Ok(TransferOutcome::all_stored(targets))
             })
             .await;
 
@@ -559,6 +923,136 @@ mod tests {
         assert_eq!(result.failed.len(), 1);
     }
 
+    #[tokio::test]
+    async fn test_rejected_copy_fails_task_without_retrying_target() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let executor = Executor::new(ExecutorConfig {
+            retry_delay: Duration::ZERO,
+            ..Default::default()
+        });
+
+        let mut plan = RepairPlan::default();
+        plan.add_task(make_task("task1", "n1", vec!["n2", "n3", "n4"]));
+
+        // n3 stores a bad copy; n4 is unreachable the first time
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let result = executor
+            .execute(plan, move |_, _, _, targets| {
+                let first = counter.fetch_add(1, Ordering::SeqCst) == 0;
+                async move {
+                    let mut outcome = TransferOutcome::default();
+                    for target in targets {
+                        match target.as_str() {
+                            "n3" => outcome.rejected.push(target),
+                            "n4" if first => {}
+                            _ => outcome.stored.push(target),
+                        }
+                    }
+                    Ok(outcome)
+                }
+            })
+            .await;
+
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert_eq!(result.failed.len(), 1);
+        let task = &result.failed[0];
+        assert_eq!(task.targets_succeeded, vec!["n2", "n4"]);
+        assert_eq!(task.targets_failed, vec!["n3"]);
+        assert_eq!(task.targets_rejected, vec!["n3"]);
+        assert!(matches!(
+            task.error,
+            Some(ExecutorError::VerificationFailed(_))
+        ));
+        assert_eq!(task.bytes_transferred, 0);
+        assert_eq!(result.rejected_copies(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_executor_rebuilds_batch_once() {
+        use crate::planner::{RepairBatch, ShardRepair, StripeKey};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let executor = Executor::new(ExecutorConfig {
+            retry_delay: Duration::ZERO,
+            ..Default::default()
+        });
+
+        let shard = |id: &str, chunk: u8, targets: Vec<&str>| ShardRepair {
+            task_id: id.to_string(),
+            chunk_id: vec![chunk],
+            target_nodes: targets.iter().map(|s| s.to_string()).collect(),
+            chunk_size: 1024,
+            issue: make_task(id, "n1", vec![]).issue,
+        };
+        let mut plan = RepairPlan::default();
+        plan.add_batch(RepairBatch {
+            batch_id: "batch-1".to_string(),
+            stripe: StripeKey {
+                file_id: "file-1".to_string(),
+                chunk_index: 0,
+            },
+            shards: vec![shard("s1", 1, vec!["n2"]), shard("s2", 2, vec!["n3", "n4"])],
+            priority: 1000,
+        });
+
+        // n4 fails the first time, so only that target is retried
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let result = executor
+            .execute_with_rebuild(
+                plan,
+                |_, _, _, targets| async move { Ok(TransferOutcome::all_stored(targets)) },
+                move |batch: RepairBatch| {
+                    let first = counter.fetch_add(1, Ordering::SeqCst) == 0;
+                    async move {
+                        Ok(batch
+                            .shards
+                            .into_iter()
+                            .map(|s| {
+                                s.target_nodes
+                                    .into_iter()
+                                    .filter(|t| !(first && t == "n4"))
+                                    .collect()
+                            })
+                            .collect())
+                    }
+                },
+            )
+            .await;
+
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert_eq!(result.succeeded.len(), 2);
+        assert!(result.failed.is_empty());
+        let s2 = result.succeeded.iter().find(|r| r.task_id == "s2").unwrap();
+        assert_eq!(s2.targets_succeeded, vec!["n3", "n4"]);
+    }
+
+    #[test]
+    fn test_busy_source_moves_to_other_replica() {
+        let executor = Executor::new(ExecutorConfig {
+            source_queue_threshold: 1,
+            ..Default::default()
+        });
+
+        let mut first = make_task("task1", "n1", vec!["n3"]);
+        let _slot = executor.enqueue_source(&mut first);
+        assert_eq!(first.source_node, "n1");
+
+        let mut second = make_task("task2", "n1", vec!["n3"]);
+        second.issue.current_nodes = vec!["n1".to_string(), "n2".to_string()];
+        let second_slot = executor.enqueue_source(&mut second);
+        assert_eq!(second.source_node, "n2");
+
+        let depths = executor.source_queue_depths();
+        assert_eq!(depths.get("n1"), Some(&1));
+        assert_eq!(depths.get("n2"), Some(&1));
+
+        drop(second_slot);
+        assert!(!executor.source_queue_depths().contains_key("n2"));
+    }
+
     #[test]
     fn test_progress_status_display() {
         let update = ProgressUpdate {
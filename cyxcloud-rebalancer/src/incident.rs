@@ -0,0 +1,350 @@
+//! Outage Correlation
+//!
+//! When a rack or datacenter drops, every chunk with a shard in it shows up
+//! as an independent issue. This module groups the unavailable nodes behind
+//! those issues into incidents: a single failed node, or an outage of a whole
+//! failure domain (region, datacenter or rack). The detector uses incidents
+//! to hold back repairs while a large outage is still unfolding, since the
+//! domain often comes back before moving its data would finish.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// Location of a node in the failure-domain hierarchy
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FailureDomain {
+    pub region: Option<String>,
+    pub datacenter: Option<String>,
+    pub rack: Option<i32>,
+}
+
+impl FailureDomain {
+    /// Name of the domain at `level`, if the node has one
+    fn key(&self, level: DomainLevel) -> Option<String> {
+        match level {
+            DomainLevel::Region => self.region.clone(),
+            DomainLevel::Datacenter => self.datacenter.clone(),
+            DomainLevel::Rack => {
+                let rack = self.rack?;
+                match &self.datacenter {
+                    Some(dc) => Some(format!("{}/rack-{}", dc, rack)),
+                    None => Some(format!("rack-{}", rack)),
+                }
+            }
+        }
+    }
+}
+
+/// Failure domain of every node, keyed by node ID
+pub type NodeDomains = HashMap<String, FailureDomain>;
+
+/// Level of a failure domain, broadest first
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DomainLevel {
+    Region,
+    Datacenter,
+    Rack,
+}
+
+impl DomainLevel {
+    const ALL: [DomainLevel; 3] = [
+        DomainLevel::Region,
+        DomainLevel::Datacenter,
+        DomainLevel::Rack,
+    ];
+
+    /// Next narrower level
+    fn child(self) -> Option<DomainLevel> {
+        match self {
+            DomainLevel::Region => Some(DomainLevel::Datacenter),
+            DomainLevel::Datacenter => Some(DomainLevel::Rack),
+            DomainLevel::Rack => None,
+        }
+    }
+}
+
+impl fmt::Display for DomainLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DomainLevel::Region => write!(f, "region"),
+            DomainLevel::Datacenter => write!(f, "datacenter"),
+            DomainLevel::Rack => write!(f, "rack"),
+        }
+    }
+}
+
+/// Classification of an incident
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IncidentKind {
+    /// One node failed on its own
+    SingleNode,
+    /// Several nodes of the same failure domain failed together
+    DomainOutage { level: DomainLevel, domain: String },
+}
+
+/// A group of correlated node failures and the chunk issues they cause
+#[derive(Debug, Clone)]
+pub struct Incident {
+    pub kind: IncidentKind,
+    /// Unavailable nodes belonging to this incident
+    pub failed_nodes: Vec<String>,
+    /// Under-replicated chunks with a shard on one of the failed nodes
+    pub affected_chunks: usize,
+    /// Affected chunks with at most one healthy copy left
+    pub at_risk_chunks: usize,
+    /// When the first node of the incident became unavailable
+    pub first_failure: Instant,
+    /// When the most recent node of the incident became unavailable
+    pub last_failure: Instant,
+    /// Time left before repairs for this incident start (`None` = not held)
+    pub repair_hold: Option<Duration>,
+}
+
+impl Incident {
+    /// Whether this incident is a failure-domain outage
+    pub fn is_domain_outage(&self) -> bool {
+        matches!(self.kind, IncidentKind::DomainOutage { .. })
+    }
+
+    /// One-line description for logs
+    pub fn summary(&self) -> String {
+        let what = match &self.kind {
+            IncidentKind::SingleNode => format!("node {}", self.failed_nodes.join(",")),
+            IncidentKind::DomainOutage { level, domain } => format!(
+                "{} {} outage ({} nodes)",
+                level,
+                domain,
+                self.failed_nodes.len()
+            ),
+        };
+        let hold = match self.repair_hold {
+            Some(remaining) => format!(", repairs held for {}s", remaining.as_secs()),
+            None => String::new(),
+        };
+        format!(
+            "{}: {} chunks affected, {} at risk, down for {}s{}",
+            what,
+            self.affected_chunks,
+            self.at_risk_chunks,
+            self.first_failure.elapsed().as_secs(),
+            hold
+        )
+    }
+}
+
+/// Remembers since when each node has been unavailable
+#[derive(Debug, Default)]
+pub struct OutageTracker {
+    unavailable_since: HashMap<String, Instant>,
+}
+
+impl OutageTracker {
+    /// Record the nodes that are currently unavailable
+    ///
+    /// Nodes not in `unavailable` are considered back and forgotten.
+    pub fn update<'a>(&mut self, unavailable: impl IntoIterator<Item = &'a String>, now: Instant) {
+        let current: HashSet<&String> = unavailable.into_iter().collect();
+        self.unavailable_since
+            .retain(|node, _| current.contains(node));
+        for node in current {
+            self.unavailable_since.entry(node.clone()).or_insert(now);
+        }
+    }
+
+    /// Unavailable nodes and when they failed
+    pub fn failed_nodes(&self) -> &HashMap<String, Instant> {
+        &self.unavailable_since
+    }
+}
+
+/// Group failed nodes into incidents
+///
+/// A failure domain counts as out when at least two of its nodes and at
+/// least `min_domain_ratio` of them are unavailable. Broader domains are
+/// checked first, so a datacenter outage is not reported as one outage per
+/// rack; a domain whose failed nodes all sit in the same child domain is left
+/// to that child. Failed nodes outside any such domain become single-node
+/// incidents.
+pub fn correlate(
+    failed: &HashMap<String, Instant>,
+    domains: &HashMap<String, FailureDomain>,
+    min_domain_ratio: f64,
+) -> Vec<Incident> {
+    let mut incidents = Vec::new();
+    let mut assigned: HashSet<&String> = HashSet::new();
+
+    for level in DomainLevel::ALL {
+        // domain -> (total nodes, failed nodes)
+        let mut groups: HashMap<String, (usize, Vec<&String>)> = HashMap::new();
+        for (node, domain) in domains {
+            let Some(key) = domain.key(level) else {
+                continue;
+            };
+            let group = groups.entry(key).or_default();
+            group.0 += 1;
+            if failed.contains_key(node) {
+                group.1.push(node);
+            }
+        }
+
+        let mut keys: Vec<_> = groups.keys().cloned().collect();
+        keys.sort();
+        for key in keys {
+            let (total, down) = &groups[&key];
+            if down.len() < 2 || (down.len() as f64) < *total as f64 * min_domain_ratio {
+                continue;
+            }
+            let mut nodes: Vec<&String> = down
+                .iter()
+                .copied()
+                .filter(|n| !assigned.contains(n))
+                .collect();
+            if nodes.len() < 2 || within_one_child(&nodes, domains, level) {
+                continue;
+            }
+            nodes.sort();
+            assigned.extend(nodes.iter().copied());
+            incidents.push(new_incident(
+                IncidentKind::DomainOutage { level, domain: key },
+                &nodes,
+                failed,
+            ));
+        }
+    }
+
+    let mut singles: Vec<&String> = failed.keys().filter(|n| !assigned.contains(n)).collect();
+    singles.sort();
+    for node in singles {
+        incidents.push(new_incident(IncidentKind::SingleNode, &[node], failed));
+    }
+
+    incidents
+}
+
+/// Whether all `nodes` belong to the same domain one level below `level`
+fn within_one_child(
+    nodes: &[&String],
+    domains: &HashMap<String, FailureDomain>,
+    level: DomainLevel,
+) -> bool {
+    let Some(child) = level.child() else {
+        return false;
+    };
+    let keys: Option<HashSet<String>> = nodes.iter().map(|n| domains[*n].key(child)).collect();
+    matches!(keys, Some(keys) if keys.len() == 1)
+}
+
+fn new_incident(
+    kind: IncidentKind,
+    nodes: &[&String],
+    failed: &HashMap<String, Instant>,
+) -> Incident {
+    let times: Vec<Instant> = nodes.iter().map(|n| failed[*n]).collect();
+    let now = Instant::now();
+    Incident {
+        kind,
+        failed_nodes: nodes.iter().map(|n| (*n).clone()).collect(),
+        affected_chunks: 0,
+        at_risk_chunks: 0,
+        first_failure: times.iter().copied().min().unwrap_or(now),
+        last_failure: times.iter().copied().max().unwrap_or(now),
+        repair_hold: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn domain(region: &str, dc: &str, rack: i32) -> FailureDomain {
+        FailureDomain {
+            region: Some(region.to_string()),
+            datacenter: Some(dc.to_string()),
+            rack: Some(rack),
+        }
+    }
+
+    fn cluster() -> HashMap<String, FailureDomain> {
+        let mut domains = HashMap::new();
+        for i in 0..4 {
+            domains.insert(format!("fra-{}", i), domain("eu", "fra1", i % 2));
+            domains.insert(format!("ams-{}", i), domain("eu", "ams1", i % 2));
+            domains.insert(format!("nyc-{}", i), domain("us", "nyc1", i % 2));
+        }
+        domains
+    }
+
+    fn failed(nodes: &[&str]) -> HashMap<String, Instant> {
+        let now = Instant::now();
+        nodes.iter().map(|n| (n.to_string(), now)).collect()
+    }
+
+    #[test]
+    fn test_datacenter_outage_is_one_incident() {
+        let incidents = correlate(
+            &failed(&["fra-0", "fra-1", "fra-2", "fra-3"]),
+            &cluster(),
+            0.5,
+        );
+
+        assert_eq!(incidents.len(), 1);
+        assert_eq!(
+            incidents[0].kind,
+            IncidentKind::DomainOutage {
+                level: DomainLevel::Datacenter,
+                domain: "fra1".to_string()
+            }
+        );
+        assert_eq!(incidents[0].failed_nodes.len(), 4);
+    }
+
+    #[test]
+    fn test_rack_outage_and_unrelated_single_node() {
+        // fra-0 and fra-2 share rack 0 of fra1; nyc-1 fails on its own
+        let incidents = correlate(&failed(&["fra-0", "fra-2", "nyc-1"]), &cluster(), 0.5);
+
+        assert_eq!(incidents.len(), 2);
+        assert_eq!(
+            incidents[0].kind,
+            IncidentKind::DomainOutage {
+                level: DomainLevel::Rack,
+                domain: "fra1/rack-0".to_string()
+            }
+        );
+        assert_eq!(incidents[1].kind, IncidentKind::SingleNode);
+        assert_eq!(incidents[1].failed_nodes, vec!["nyc-1".to_string()]);
+    }
+
+    #[test]
+    fn test_scattered_failures_are_single_nodes() {
+        let incidents = correlate(&failed(&["fra-0", "ams-1", "nyc-2"]), &cluster(), 0.5);
+
+        assert_eq!(incidents.len(), 3);
+        assert!(incidents.iter().all(|i| !i.is_domain_outage()));
+    }
+
+    #[test]
+    fn test_nodes_without_domains_are_single_nodes() {
+        let incidents = correlate(&failed(&["a", "b"]), &HashMap::new(), 0.5);
+        assert_eq!(incidents.len(), 2);
+        assert!(incidents.iter().all(|i| i.kind == IncidentKind::SingleNode));
+    }
+
+    #[test]
+    fn test_tracker_keeps_first_failure_time() {
+        let mut tracker = OutageTracker::default();
+        let t0 = Instant::now();
+        let t1 = t0 + Duration::from_secs(30);
+        let a = "a".to_string();
+        let b = "b".to_string();
+
+        tracker.update([&a], t0);
+        tracker.update([&a, &b], t1);
+        assert_eq!(tracker.failed_nodes()[&a], t0);
+        assert_eq!(tracker.failed_nodes()[&b], t1);
+
+        tracker.update([&b], t1);
+        assert!(!tracker.failed_nodes().contains_key(&a));
+    }
+}
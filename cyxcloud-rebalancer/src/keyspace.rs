@@ -0,0 +1,295 @@
+//! Chunk keyspace sharding
+//!
+//! A single rebalancer cannot keep up with a very large cluster, so several
+//! instances can split the chunk keyspace between them. The keyspace is the
+//! first two bytes of the chunk ID (65536 slots); since chunk IDs are content
+//! hashes, an equal share of the slots holds an equal share of the chunks.
+//!
+//! Each instance owns one of `shard_count` equal ranges, either configured or
+//! claimed from the ranges still free. Ownership is a lease in Postgres that
+//! the instance renews every scan; the database refuses overlapping leases,
+//! and a lease that is not renewed expires so another instance can take over.
+
+use cyxcloud_metadata::postgres::Database;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
+use tracing::{info, warn};
+
+/// Number of slots in the chunk keyspace
+pub const KEYSPACE_SLOTS: u32 = 1 << 16;
+
+/// Shortest lease, long enough to outlast a repair run between renewals
+pub const MIN_LEASE_TTL: Duration = Duration::from_secs(300);
+
+/// Keyspace errors
+#[derive(Error, Debug)]
+pub enum KeyspaceError {
+    #[error("Invalid shard configuration: {0}")]
+    InvalidConfig(String),
+
+    #[error("Database error: {0}")]
+    Database(String),
+}
+
+pub type Result<T> = std::result::Result<T, KeyspaceError>;
+
+/// A range of keyspace slots, `start..end`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyRange {
+    pub start: u32,
+    pub end: u32,
+}
+
+impl KeyRange {
+    /// The whole keyspace
+    pub const FULL: KeyRange = KeyRange {
+        start: 0,
+        end: KEYSPACE_SLOTS,
+    };
+
+    /// Range of shard `index` when the keyspace is split into `count` shards
+    pub fn for_shard(index: usize, count: usize) -> Self {
+        Self::FULL.slice(index, count)
+    }
+
+    /// Part `index` of this range when it is split into `count` equal parts
+    pub fn slice(&self, index: usize, count: usize) -> Self {
+        let count = count.max(1) as u64;
+        let index = (index as u64).min(count - 1);
+        let len = (self.end - self.start) as u64;
+        Self {
+            start: self.start + (index * len / count) as u32,
+            end: self.start + ((index + 1) * len / count) as u32,
+        }
+    }
+
+    /// Keyspace slot of a chunk ID
+    pub fn slot(chunk_id: &[u8]) -> u32 {
+        match chunk_id {
+            [a, b, ..] => u16::from_be_bytes([*a, *b]) as u32,
+            _ => 0,
+        }
+    }
+
+    /// Check whether a chunk falls in this range
+    pub fn contains(&self, chunk_id: &[u8]) -> bool {
+        let slot = Self::slot(chunk_id);
+        self.start <= slot && slot < self.end
+    }
+
+    /// Check whether this range covers the whole keyspace
+    pub fn is_full(&self) -> bool {
+        *self == Self::FULL
+    }
+}
+
+impl Default for KeyRange {
+    fn default() -> Self {
+        Self::FULL
+    }
+}
+
+impl fmt::Display for KeyRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04x}..{:04x}", self.start, self.end)
+    }
+}
+
+/// Keyspace sharding configuration
+#[derive(Debug, Clone)]
+pub struct ShardConfig {
+    /// Number of equal ranges the keyspace is split into
+    pub shard_count: usize,
+    /// Range this instance owns (`None` claims the first free one)
+    pub shard_index: Option<usize>,
+    /// Name of this instance, recorded as the lease owner
+    pub instance_id: String,
+    /// How long a lease lasts without renewal
+    pub lease_ttl: Duration,
+}
+
+impl ShardConfig {
+    /// Lease duration for an instance that scans every `scan_interval`
+    pub fn lease_ttl_for(scan_interval: Duration) -> Duration {
+        (scan_interval * 3).max(MIN_LEASE_TTL)
+    }
+
+    /// Validate the configuration
+    pub fn validate(&self) -> Result<()> {
+        if self.shard_count == 0 || self.shard_count > KEYSPACE_SLOTS as usize {
+            return Err(KeyspaceError::InvalidConfig(format!(
+                "shard count must be between 1 and {}",
+                KEYSPACE_SLOTS
+            )));
+        }
+        if let Some(index) = self.shard_index {
+            if index >= self.shard_count {
+                return Err(KeyspaceError::InvalidConfig(format!(
+                    "shard index {} is out of range for {} shards",
+                    index, self.shard_count
+                )));
+            }
+        }
+        if self.instance_id.is_empty() {
+            return Err(KeyspaceError::InvalidConfig(
+                "instance ID must not be empty".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// This instance's lease on a keyspace range
+pub struct KeyspaceLease {
+    db: Arc<Database>,
+    config: ShardConfig,
+    /// Shard currently held
+    held: Option<usize>,
+}
+
+impl KeyspaceLease {
+    /// Create a lease manager (nothing is claimed until [`Self::renew`])
+    pub fn new(db: Arc<Database>, config: ShardConfig) -> Result<Self> {
+        config.validate()?;
+        Ok(Self {
+            db,
+            config,
+            held: None,
+        })
+    }
+
+    /// Range currently held, if any
+    pub fn range(&self) -> Option<KeyRange> {
+        self.held
+            .map(|index| KeyRange::for_shard(index, self.config.shard_count))
+    }
+
+    /// Claim or renew the lease
+    ///
+    /// Keeps the range already held when possible. Returns the range this
+    /// instance owns, or `None` when every range it may take is leased to
+    /// another instance.
+    pub async fn renew(&mut self) -> Result<Option<KeyRange>> {
+        let count = self.config.shard_count;
+        let candidates: Vec<usize> = match (self.config.shard_index, self.held) {
+            (Some(index), _) => vec![index],
+            (None, Some(held)) => std::iter::once(held)
+                .chain((0..count).filter(|&i| i != held))
+                .collect(),
+            (None, None) => (0..count).collect(),
+        };
+
+        for index in candidates {
+            let range = KeyRange::for_shard(index, count);
+            let claimed = self
+                .db
+                .claim_keyspace_lease(
+                    &self.config.instance_id,
+                    range.start as i32,
+                    range.end as i32,
+                    self.config.lease_ttl,
+                )
+                .await
+                .map_err(|e| KeyspaceError::Database(e.to_string()))?;
+            if claimed {
+                if self.held != Some(index) {
+                    info!(
+                        instance = %self.config.instance_id,
+                        shard = index,
+                        shards = count,
+                        range = %range,
+                        "Claimed keyspace range"
+                    );
+                }
+                self.held = Some(index);
+                return Ok(Some(range));
+            }
+        }
+
+        if let Some(index) = self.held.take() {
+            warn!(
+                instance = %self.config.instance_id,
+                shard = index,
+                "Keyspace range is now leased to another instance"
+            );
+        }
+        Ok(None)
+    }
+
+    /// Give up the lease so another instance can take the range over
+    pub async fn release(&mut self) -> Result<()> {
+        if self.held.take().is_some() {
+            self.db
+                .release_keyspace_lease(&self.config.instance_id)
+                .await
+                .map_err(|e| KeyspaceError::Database(e.to_string()))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shards_cover_keyspace() {
+        for count in [1, 3, 7, 16] {
+            let mut next = 0;
+            for index in 0..count {
+                let range = KeyRange::for_shard(index, count);
+                assert_eq!(range.start, next);
+                assert!(range.start < range.end);
+                next = range.end;
+            }
+            assert_eq!(next, KEYSPACE_SLOTS);
+        }
+        assert!(KeyRange::for_shard(0, 1).is_full());
+    }
+
+    #[test]
+    fn test_slices_cover_range() {
+        let range = KeyRange::for_shard(1, 3);
+        let mut next = range.start;
+        for index in 0..60 {
+            let slice = range.slice(index, 60);
+            assert_eq!(slice.start, next);
+            assert!(slice.start < slice.end);
+            next = slice.end;
+        }
+        assert_eq!(next, range.end);
+        assert_eq!(range.slice(0, 1), range);
+        assert_eq!(range.slice(99, 2), range.slice(1, 2));
+    }
+
+    #[test]
+    fn test_range_contains_chunk() {
+        let range = KeyRange::for_shard(1, 4); // 0x4000..0x8000
+        assert_eq!(range.to_string(), "4000..8000");
+        assert!(range.contains(&[0x40, 0x00, 0xff]));
+        assert!(range.contains(&[0x7f, 0xff]));
+        assert!(!range.contains(&[0x80, 0x00]));
+        assert!(!range.contains(&[0x3f, 0xff]));
+        assert_eq!(KeyRange::slot(&[0x12]), 0);
+    }
+
+    #[test]
+    fn test_shard_config_validation() {
+        let mut config = ShardConfig {
+            shard_count: 4,
+            shard_index: Some(3),
+            instance_id: "rebalancer-1".to_string(),
+            lease_ttl: Duration::from_secs(180),
+        };
+        assert!(config.validate().is_ok());
+
+        config.shard_index = Some(4);
+        assert!(config.validate().is_err());
+
+        config.shard_index = None;
+        config.shard_count = 0;
+        assert!(config.validate().is_err());
+    }
+}
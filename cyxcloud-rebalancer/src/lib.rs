@@ -4,7 +4,8 @@
 //!
 //! The rebalancer monitors cluster health and performs:
 //! - Failure detection (find under-replicated chunks)
-//! - Data repair (replicate chunks to restore target replication factor)
+//! - Data repair (replicate chunks to restore target replication factor, or
+//!   rebuild lost erasure coded shards from their siblings)
 //! - Rebalancing (distribute data evenly across nodes)
 //! - Hot-swap support (drain nodes before shutdown)
 
@@ -13,21 +14,32 @@
 pub mod config;
 pub mod detector;
 pub mod executor;
+pub mod incident;
+pub mod keyspace;
 pub mod metadata_client;
 pub mod network_client;
 pub mod planner;
+pub mod simulation;
 pub mod transfer;
 
 // Re-export main types
 pub use config::RebalancerConfig;
 pub use detector::{
-    ChunkHealth, ChunkInfo, ChunkIssue, Detector, DetectorConfig, MetadataClient, NetworkClient,
-    NodeAvailability, ScanResult,
+    ChangeClock, ChunkHealth, ChunkInfo, ChunkIssue, Detector, DetectorConfig, IntegrityCandidate,
+    MetadataClient, NetworkClient, NodeAvailability, RepairRisk, ScanResult,
 };
 pub use executor::{
     Executor, ExecutorConfig, ExecutorError, ProgressStatus, ProgressUpdate, TaskResult,
+    TransferOutcome,
 };
+pub use incident::{DomainLevel, FailureDomain, Incident, IncidentKind, NodeDomains};
+pub use keyspace::{KeyRange, KeyspaceError, KeyspaceLease, ShardConfig};
 pub use metadata_client::PostgresMetadataClient;
 pub use network_client::GrpcNetworkClient;
-pub use planner::{NodeInfo, Planner, PlannerConfig, RepairPlan, RepairTask};
+pub use planner::{
+    NodeInfo, Planner, PlannerConfig, RepairBatch, RepairPlan, RepairTask, ShardRepair, StripeKey,
+};
+pub use simulation::{
+    simulate, NodeAddition, Scenario, SimulationConfig, SimulationError, SimulationReport,
+};
 pub use transfer::{ChunkTransferService, TransferError};
@@ -11,14 +11,18 @@
 mod config;
 mod detector;
 mod executor;
+mod incident;
+mod keyspace;
 mod metadata_client;
 mod network_client;
 mod planner;
 mod transfer;
 
 use clap::Parser;
+use cyxcloud_metadata::AntiAffinity;
 use detector::{Detector, DetectorConfig};
-use executor::{Executor, ExecutorConfig, ProgressUpdate};
+use executor::{Executor, ExecutorConfig, ProgressUpdate, TransferOutcome};
+use keyspace::{KeyspaceLease, ShardConfig};
 use metadata_client::PostgresMetadataClient;
 use network_client::GrpcNetworkClient;
 use planner::{NodeInfo, Planner, PlannerConfig};
@@ -27,7 +31,7 @@ use std::time::Duration;
 use tokio::signal;
 use tokio::sync::mpsc;
 use tracing::{error, info, warn, Level};
-use transfer::create_transfer_fn;
+use transfer::{create_rebuild_fn, create_transfer_fn, retire_replaced_copies};
 
 #[derive(Parser)]
 #[command(name = "cyxcloud-rebalancer")]
@@ -56,6 +60,55 @@ struct Cli {
     /// Dry run mode (don't actually repair)
     #[arg(long, default_value = "false")]
     dry_run: bool,
+
+    /// Shard anti-affinity for repair targets (strict, best-effort, off)
+    #[arg(long, env = "REBALANCER_ANTI_AFFINITY", default_value = "best-effort")]
+    anti_affinity: AntiAffinity,
+
+    /// Seconds to hold repairs after the latest node failure of a large outage
+    #[arg(long, env = "REBALANCER_OUTAGE_STABILIZATION_SECS", default_value = "300")]
+    outage_stabilization_secs: u64,
+
+    /// Spot-check a sample of each node's copies every scan
+    #[arg(long, env = "REBALANCER_VERIFY_INTEGRITY", default_value = "false")]
+    verify_integrity: bool,
+
+    /// Copies verified per node each scan when integrity checks are enabled
+    #[arg(long, env = "REBALANCER_INTEGRITY_SAMPLES", default_value = "10")]
+    integrity_samples: usize,
+
+    /// Scan only chunks changed since the previous scan plus a rolling sweep
+    #[arg(
+        long,
+        env = "REBALANCER_INCREMENTAL_SCAN",
+        default_value = "true",
+        action = clap::ArgAction::Set
+    )]
+    incremental_scan: bool,
+
+    /// Seconds the rolling sweep of incremental scans takes to cover the keyspace
+    #[arg(long, env = "REBALANCER_FULL_SWEEP_SECS", default_value = "3600")]
+    full_sweep_secs: u64,
+
+    /// Number of keyspace shards split between rebalancer instances
+    #[arg(long, env = "REBALANCER_SHARD_COUNT", default_value = "1")]
+    shard_count: usize,
+
+    /// Keyspace shard this instance owns (claims a free one if unset)
+    #[arg(long, env = "REBALANCER_SHARD_INDEX")]
+    shard_index: Option<usize>,
+
+    /// Name of this instance in keyspace leases (random if unset)
+    #[arg(long, env = "REBALANCER_INSTANCE_ID")]
+    instance_id: Option<String>,
+
+    /// Shared token presented to nodes when asking them to push chunks
+    #[arg(long, env = "CYXCLOUD_CLUSTER_TOKEN", hide_env_values = true)]
+    cluster_token: Option<String>,
+
+    /// Encrypt repaired chunks to each target node's transfer key
+    #[arg(long, env = "CYXCLOUD_ENCRYPT_TRANSFERS")]
+    encrypt_transfers: bool,
 }
 
 /// Client mode for the rebalancer
@@ -77,6 +130,10 @@ struct RebalancerService {
     client_mode: ClientMode,
     dry_run: bool,
     scan_interval: Duration,
+    cluster_token: Option<String>,
+    encrypt_transfers: bool,
+    /// Lease on this instance's keyspace range, when sharded
+    keyspace: Option<KeyspaceLease>,
 }
 
 impl RebalancerService {
@@ -85,8 +142,13 @@ impl RebalancerService {
             replication_factor: cli.replication_factor,
             batch_size: 1000,
             scan_interval: Duration::from_secs(cli.scan_interval),
-            verify_integrity: false,
+            verify_integrity: cli.verify_integrity,
+            integrity_samples_per_node: cli.integrity_samples,
             health_check_timeout: Duration::from_secs(5),
+            outage_stabilization_delay: Duration::from_secs(cli.outage_stabilization_secs),
+            incremental: cli.incremental_scan,
+            full_sweep_interval: Duration::from_secs(cli.full_sweep_secs),
+            ..Default::default()
         };
 
         let planner_config = PlannerConfig {
@@ -96,12 +158,16 @@ impl RebalancerService {
             prefer_local: true,
             max_node_load: 0.8,
             node_rate_limit: 100 * 1024 * 1024,
+            anti_affinity: cli.anti_affinity,
+            anti_affinity_min_nodes: 14,
+            max_source_repairs: 3,
         };
 
         let executor_config = ExecutorConfig {
             max_concurrent: cli.parallelism,
             max_per_source: 3,
             max_per_target: 3,
+            source_queue_threshold: 6,
             transfer_timeout: Duration::from_secs(300),
             max_retries: 3,
             retry_delay: Duration::from_secs(5),
@@ -112,6 +178,7 @@ impl RebalancerService {
         let (executor, progress_rx) = Executor::with_progress(executor_config);
 
         // Determine client mode based on database URL
+        let mut keyspace = None;
         let client_mode = if let Some(ref db_url) = cli.database_url {
             info!("Production mode: connecting to PostgreSQL");
 
@@ -131,6 +198,19 @@ impl RebalancerService {
 
             info!("Connected to PostgreSQL database");
 
+            if cli.shard_count > 1 {
+                let shard_config = ShardConfig {
+                    shard_count: cli.shard_count,
+                    shard_index: cli.shard_index,
+                    instance_id: cli
+                        .instance_id
+                        .clone()
+                        .unwrap_or_else(|| format!("rebalancer-{}", uuid::Uuid::new_v4())),
+                    lease_ttl: ShardConfig::lease_ttl_for(Duration::from_secs(cli.scan_interval)),
+                };
+                keyspace = Some(KeyspaceLease::new(db.clone(), shard_config)?);
+            }
+
             ClientMode::Production {
                 db,
                 metadata_client,
@@ -148,6 +228,9 @@ impl RebalancerService {
             client_mode,
             dry_run: cli.dry_run,
             scan_interval: Duration::from_secs(cli.scan_interval),
+            cluster_token: cli.cluster_token.clone(),
+            encrypt_transfers: cli.encrypt_transfers,
+            keyspace,
         };
 
         Ok((service, progress_rx))
@@ -180,6 +263,11 @@ impl RebalancerService {
 
         // Graceful shutdown
         self.executor.shutdown().await;
+        if let Some(lease) = self.keyspace.as_mut() {
+            if let Err(e) = lease.release().await {
+                warn!(error = %e, "Failed to release keyspace lease");
+            }
+        }
         info!("Rebalancer service stopped");
 
         Ok(())
@@ -211,6 +299,17 @@ impl RebalancerService {
         metadata_client: Arc<PostgresMetadataClient>,
         network_client: Arc<GrpcNetworkClient>,
     ) -> anyhow::Result<()> {
+        // Only scan the keyspace range this instance holds
+        if let Some(lease) = self.keyspace.as_mut() {
+            match lease.renew().await? {
+                Some(range) => metadata_client.set_keyspace(range),
+                None => {
+                    warn!("Every keyspace range is leased to another instance, skipping scan");
+                    return Ok(());
+                }
+            }
+        }
+
         // Step 1: Detect issues
         let scan_result = self
             .detector
@@ -251,11 +350,28 @@ impl RebalancerService {
         }
 
         // Step 3: Execute repairs with real transfer function
-        let transfer_fn = create_transfer_fn(db);
-        let result = self.executor.execute(plan, transfer_fn).await;
+        let transfer_fn = create_transfer_fn(
+            db.clone(),
+            self.cluster_token.clone(),
+            self.encrypt_transfers,
+        );
+        let rebuild_fn = create_rebuild_fn(
+            db.clone(),
+            self.cluster_token.clone(),
+            self.encrypt_transfers,
+        );
+        let result = self
+            .executor
+            .execute_with_rebuild(plan, transfer_fn, rebuild_fn)
+            .await;
 
         info!(summary = %result.summary(), "Repair execution complete");
 
+        let retired = retire_replaced_copies(&db, &result).await;
+        if retired > 0 {
+            info!(retired = retired, "Corrupt chunk copies retired");
+        }
+
         Ok(())
     }
 
@@ -314,7 +430,7 @@ impl RebalancerService {
                     "Would transfer chunk (mock mode)"
                 );
                 // Simulate success
-                Ok(targets)
+                Ok(TransferOutcome::all_stored(targets))
             })
             .await;
 
@@ -372,7 +488,11 @@ impl MockNetworkClient {
                 available_storage: 100 * 1024 * 1024 * 1024,
                 load: 0.3,
                 datacenter: Some("dc1".to_string()),
+                rack: None,
                 is_healthy: true,
+                bandwidth_bps: 0,
+                throughput_bps: 0,
+                active_repairs: 0,
             },
             NodeInfo {
                 id: "node2".to_string(),
@@ -380,7 +500,11 @@ impl MockNetworkClient {
                 available_storage: 100 * 1024 * 1024 * 1024,
                 load: 0.4,
                 datacenter: Some("dc1".to_string()),
+                rack: None,
                 is_healthy: true,
+                bandwidth_bps: 0,
+                throughput_bps: 0,
+                active_repairs: 0,
             },
             NodeInfo {
                 id: "node3".to_string(),
@@ -388,7 +512,11 @@ impl MockNetworkClient {
                 available_storage: 100 * 1024 * 1024 * 1024,
                 load: 0.5,
                 datacenter: Some("dc2".to_string()),
+                rack: None,
                 is_healthy: true,
+                bandwidth_bps: 0,
+                throughput_bps: 0,
+                active_repairs: 0,
             },
         ])
     }
@@ -1,45 +1,55 @@
 //! PostgreSQL-backed metadata client for rebalancer
 //!
 //! Implements the MetadataClient trait using cyxcloud-metadata's Database.
+//! Queries only return chunks in the client's keyspace range (see
+//! [`crate::keyspace`]).
 
-use crate::detector::{ChunkInfo, MetadataClient};
+use crate::detector::{ChangeClock, ChunkInfo, IntegrityCandidate, MetadataClient, RepairRisk};
+use crate::keyspace::KeyRange;
+use chrono::{DateTime, Utc};
+use cyxcloud_metadata::models::{ChunkReplicationStatus, StorageClass};
 use cyxcloud_metadata::postgres::Database;
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
 use tracing::{debug, instrument};
 
 /// PostgreSQL metadata client
 pub struct PostgresMetadataClient {
     db: Arc<Database>,
+    /// Chunks this rebalancer instance is responsible for
+    keyspace: RwLock<KeyRange>,
 }
 
 impl PostgresMetadataClient {
-    /// Create a new PostgreSQL metadata client
+    /// Create a new PostgreSQL metadata client covering the whole keyspace
     pub fn new(db: Arc<Database>) -> Self {
-        Self { db }
+        Self {
+            db,
+            keyspace: RwLock::new(KeyRange::FULL),
+        }
+    }
+
+    /// Restrict queries to a keyspace range
+    pub fn set_keyspace(&self, range: KeyRange) {
+        *self.keyspace.write().unwrap() = range;
+    }
+
+    /// Keyspace range queries are restricted to
+    pub fn keyspace(&self) -> KeyRange {
+        *self.keyspace.read().unwrap()
     }
 
     /// Get the underlying database reference
     pub fn database(&self) -> &Database {
         &self.db
     }
-}
 
-#[async_trait::async_trait]
-impl MetadataClient for PostgresMetadataClient {
-    #[instrument(skip(self))]
-    async fn get_under_replicated_chunks(
+    /// Look up the locations, size, siblings and repair risk of
+    /// under-replicated chunks
+    async fn chunk_infos(
         &self,
-        limit: usize,
+        chunks: Vec<ChunkReplicationStatus>,
     ) -> Result<Vec<ChunkInfo>, Box<dyn std::error::Error + Send + Sync>> {
-        // Query under-replicated chunks from the database
-        let chunks = self
-            .db
-            .get_under_replicated_chunks(limit as i64)
-            .await
-            .map_err(|e| Box::new(e) as Box<dyn std::error::Error + Send + Sync>)?;
-
-        debug!(count = chunks.len(), "Found under-replicated chunks");
-
         // Convert to ChunkInfo with node locations
         let mut result = Vec::with_capacity(chunks.len());
 
@@ -53,8 +63,12 @@ impl MetadataClient for PostgresMetadataClient {
 
             // Get the peer_id for each node (the detector uses peer_id as node identifier)
             let mut node_ids = Vec::with_capacity(locations.len());
+            let mut fragile_nodes = Vec::new();
             for loc in &locations {
                 if let Ok(Some(node)) = self.db.get_node(loc.node_id).await {
+                    if node.status != "online" || loc.verification_failures > 0 {
+                        fragile_nodes.push(node.peer_id.clone());
+                    }
                     node_ids.push(node.peer_id);
                 }
             }
@@ -66,18 +80,63 @@ impl MetadataClient for PostgresMetadataClient {
                 .await
                 .map_err(|e| Box::new(e) as Box<dyn std::error::Error + Send + Sync>)?;
 
-            let size = chunk_record.map(|c| c.size_bytes as u64).unwrap_or(0);
+            let size = chunk_record
+                .as_ref()
+                .map(|c| c.size_bytes as u64)
+                .unwrap_or(0);
+            let chunk_index = chunk_record.map(|c| c.chunk_index.max(0) as u32);
+
+            // Nodes holding the other shards of this chunk (for anti-affinity)
+            let sibling_nodes = self
+                .db
+                .get_sibling_shard_nodes(&chunk.chunk_id)
+                .await
+                .map_err(|e| Box::new(e) as Box<dyn std::error::Error + Send + Sync>)?;
 
             result.push(ChunkInfo {
                 chunk_id: chunk.chunk_id,
                 node_ids,
+                sibling_nodes,
                 file_id: Some(chunk.file_id.to_string()),
+                chunk_index,
                 size,
+                replication_factor: Some(chunk.replication_factor.max(1) as usize),
+                risk: RepairRisk {
+                    fragile_nodes,
+                    stripe_spare: chunk.stripe_spare,
+                    archive: chunk.storage_class == StorageClass::Archive.as_str(),
+                    restored_copy: chunk.restored_copy,
+                },
             });
         }
 
         Ok(result)
     }
+}
+
+#[async_trait::async_trait]
+impl MetadataClient for PostgresMetadataClient {
+    #[instrument(skip(self))]
+    async fn get_under_replicated_chunks(
+        &self,
+        limit: usize,
+    ) -> Result<Vec<ChunkInfo>, Box<dyn std::error::Error + Send + Sync>> {
+        // Query under-replicated chunks from the database
+        let range = self.keyspace();
+        let chunks = self
+            .db
+            .get_under_replicated_chunks_in_range(
+                limit as i64,
+                range.start as i32,
+                range.end as i32,
+            )
+            .await
+            .map_err(|e| Box::new(e) as Box<dyn std::error::Error + Send + Sync>)?;
+
+        debug!(count = chunks.len(), "Found under-replicated chunks");
+
+        self.chunk_infos(chunks).await
+    }
 
     #[instrument(skip(self))]
     async fn get_orphaned_chunks(
@@ -90,4 +149,128 @@ impl MetadataClient for PostgresMetadataClient {
         debug!("Orphaned chunk detection not yet implemented");
         Ok(Vec::new())
     }
+
+    #[instrument(skip(self))]
+    async fn get_integrity_candidates(
+        &self,
+        node_id: &str,
+        limit: usize,
+    ) -> Result<Vec<IntegrityCandidate>, Box<dyn std::error::Error + Send + Sync>> {
+        let node = match self.db.get_node_by_peer_id(node_id).await? {
+            Some(node) => node,
+            None => return Ok(Vec::new()),
+        };
+        let range = self.keyspace();
+        let candidates = self
+            .db
+            .get_chunk_integrity_candidates(
+                node.id,
+                limit as i64,
+                range.start as i32,
+                range.end as i32,
+            )
+            .await?;
+
+        let mut result = Vec::with_capacity(candidates.len());
+        for candidate in candidates {
+            let locations = self.db.get_chunk_locations(&candidate.chunk_id).await?;
+            let mut node_ids = Vec::with_capacity(locations.len());
+            for loc in &locations {
+                if let Ok(Some(node)) = self.db.get_node(loc.node_id).await {
+                    node_ids.push(node.peer_id);
+                }
+            }
+            let sibling_nodes = self.db.get_sibling_shard_nodes(&candidate.chunk_id).await?;
+
+            result.push(IntegrityCandidate {
+                chunk: ChunkInfo {
+                    chunk_id: candidate.chunk_id,
+                    node_ids,
+                    sibling_nodes,
+                    file_id: Some(candidate.file_id.to_string()),
+                    chunk_index: None,
+                    size: candidate.size_bytes.max(0) as u64,
+                    replication_factor: Some(candidate.replication_factor.max(1) as usize),
+                    risk: RepairRisk::default(),
+                },
+                unverified_for: Duration::from_secs_f64(candidate.unverified_secs.max(0.0)),
+            });
+        }
+
+        Ok(result)
+    }
+
+    #[instrument(skip(self))]
+    async fn record_integrity_check(
+        &self,
+        chunk_id: &[u8],
+        node_id: &str,
+        valid: bool,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let node = self
+            .db
+            .get_node_by_peer_id(node_id)
+            .await?
+            .ok_or_else(|| format!("unknown node {}", node_id))?;
+        self.db
+            .update_chunk_verification(chunk_id, node.id, valid)
+            .await?;
+        Ok(())
+    }
+
+    async fn change_clock(
+        &self,
+    ) -> Result<Option<ChangeClock>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(Some(ChangeClock {
+            now: self.db.now().await?,
+            scope: self.keyspace(),
+        }))
+    }
+
+    #[instrument(skip(self, recheck), fields(recheck = recheck.len()))]
+    async fn get_changed_under_replicated_chunks(
+        &self,
+        since: DateTime<Utc>,
+        recheck: &[Vec<u8>],
+        limit: usize,
+    ) -> Result<Vec<ChunkInfo>, Box<dyn std::error::Error + Send + Sync>> {
+        let range = self.keyspace();
+        let chunks = self
+            .db
+            .get_changed_under_replicated_chunks_in_range(
+                since,
+                recheck,
+                limit as i64,
+                range.start as i32,
+                range.end as i32,
+            )
+            .await?;
+
+        debug!(
+            count = chunks.len(),
+            "Found changed under-replicated chunks"
+        );
+
+        self.chunk_infos(chunks).await
+    }
+
+    #[instrument(skip(self))]
+    async fn get_under_replicated_chunks_in_slice(
+        &self,
+        slice: usize,
+        slices: usize,
+        limit: usize,
+    ) -> Result<Vec<ChunkInfo>, Box<dyn std::error::Error + Send + Sync>> {
+        let range = self.keyspace().slice(slice, slices);
+        let chunks = self
+            .db
+            .get_under_replicated_chunks_in_range(
+                limit as i64,
+                range.start as i32,
+                range.end as i32,
+            )
+            .await?;
+
+        self.chunk_infos(chunks).await
+    }
 }
@@ -4,12 +4,16 @@
 //! and gRPC for reachability checks.
 
 use crate::detector::{NetworkClient, NodeAvailability};
+use crate::incident::{FailureDomain, NodeDomains};
 use crate::planner::NodeInfo;
 use cyxcloud_core::chunk::ChunkId;
 use cyxcloud_metadata::postgres::Database;
 use cyxcloud_network::grpc_client::ChunkClient;
 use std::sync::Arc;
 use std::time::Duration;
+
+/// Load reports older than this are ignored when picking repair sources
+const LOAD_MAX_AGE: Duration = Duration::from_secs(300);
 use tracing::{debug, instrument, warn};
 
 /// Network client that uses database for status and gRPC for health checks
@@ -68,6 +72,9 @@ impl GrpcNetworkClient {
     }
 
     /// Get node info for the planner
+    ///
+    /// Includes the load each node last reported and the repair jobs already
+    /// reading from it, so the planner can pick idle sources.
     pub async fn get_node_info(
         &self,
     ) -> Result<Vec<NodeInfo>, Box<dyn std::error::Error + Send + Sync>> {
@@ -77,18 +84,41 @@ impl GrpcNetworkClient {
             .await
             .map_err(|e| Box::new(e) as Box<dyn std::error::Error + Send + Sync>)?;
 
+        // Load data only refines source selection; plan without it on errors
+        let loads: std::collections::HashMap<_, _> =
+            match self.db.get_node_loads(LOAD_MAX_AGE).await {
+                Ok(loads) => loads.into_iter().map(|l| (l.node_id, l)).collect(),
+                Err(e) => {
+                    warn!(error = %e, "Failed to load node metrics");
+                    Default::default()
+                }
+            };
+        let repairs = self
+            .db
+            .count_open_repair_jobs_by_source()
+            .await
+            .unwrap_or_else(|e| {
+                warn!(error = %e, "Failed to count open repair jobs");
+                Default::default()
+            });
+
         let result: Vec<NodeInfo> = nodes
             .into_iter()
             .map(|n| {
                 let is_healthy = n.status == "online" || n.status == "recovering";
                 let available = n.storage_available() as u64;
+                let load = loads.get(&n.id);
                 NodeInfo {
                     id: n.peer_id,
                     address: n.grpc_address,
                     available_storage: available,
-                    load: 0.0, // Could calculate from usage/capacity
+                    load: load.map_or(0.0, |l| (l.cpu_usage / 100.0).clamp(0.0, 1.0)),
                     datacenter: n.datacenter,
+                    rack: n.rack,
                     is_healthy,
+                    bandwidth_bps: n.effective_bandwidth_mbps().max(0) as u64 * 1_000_000 / 8,
+                    throughput_bps: load.map_or(0, |l| l.throughput_bps.max(0) as u64),
+                    active_repairs: repairs.get(&n.id).copied().unwrap_or(0).max(0) as usize,
                 }
             })
             .collect();
@@ -210,25 +240,17 @@ impl NetworkClient for GrpcNetworkClient {
         node_id: &str,
         chunk_id: &[u8],
     ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
-        let address = match self.get_node_address(node_id).await {
-            Some(addr) => addr,
-            None => {
-                debug!(node_id = node_id, "Node address not found");
-                return Ok(false);
-            }
-        };
+        // Only a completed check may report a copy as corrupt, so lookups
+        // and RPCs that fail are errors rather than `false`
+        let address = self
+            .get_node_address(node_id)
+            .await
+            .ok_or_else(|| format!("no address for node {}", node_id))?;
 
         // Convert bytes to ChunkId
-        if chunk_id.len() != 32 {
-            debug!(
-                node_id = node_id,
-                len = chunk_id.len(),
-                "Invalid chunk ID length"
-            );
-            return Ok(false);
-        }
-        let mut arr = [0u8; 32];
-        arr.copy_from_slice(chunk_id);
+        let arr: [u8; 32] = chunk_id
+            .try_into()
+            .map_err(|_| format!("invalid chunk ID length {}", chunk_id.len()))?;
         let chunk_id_obj = ChunkId::from_bytes(arr);
 
         // Use the ChunkClient to verify the chunk
@@ -241,8 +263,31 @@ impl NetworkClient for GrpcNetworkClient {
                     error = %e,
                     "Chunk verification failed"
                 );
-                Ok(false)
+                Err(e.to_string().into())
             }
         }
     }
+
+    #[instrument(skip(self))]
+    async fn get_node_domains(
+        &self,
+    ) -> Result<NodeDomains, Box<dyn std::error::Error + Send + Sync>> {
+        let nodes = self
+            .db
+            .get_all_nodes()
+            .await
+            .map_err(|e| Box::new(e) as Box<dyn std::error::Error + Send + Sync>)?;
+
+        Ok(nodes
+            .into_iter()
+            .map(|n| {
+                let domain = FailureDomain {
+                    region: n.region,
+                    datacenter: n.datacenter,
+                    rack: n.rack,
+                };
+                (n.peer_id, domain)
+            })
+            .collect())
+    }
 }
@@ -3,9 +3,16 @@
 //! Creates repair plans from detected chunk issues.
 //! Optimizes for:
 //! - Network efficiency (prefer nearby nodes)
-//! - Load balancing (spread repairs across nodes)
+//! - Load balancing (spread repairs across nodes, read from idle, fast sources)
 //! - Priority (critical issues first)
+//! - Fault tolerance (no two shards of a chunk on the same node or rack)
+//!
+//! Shards with no healthy copy left can't be copied; they are rebuilt from
+//! their siblings instead. All shards of one erasure coded chunk that need
+//! repair are grouped into a single [`RepairBatch`], so the sibling shards are
+//! fetched and decoded once for the whole chunk rather than once per shard.
 
+use cyxcloud_metadata::AntiAffinity;
 use std::collections::{HashMap, HashSet};
 use std::time::Duration;
 use thiserror::Error;
@@ -48,6 +55,8 @@ pub struct RepairTask {
     pub priority: u32,
     /// Original issue that triggered this repair
     pub issue: ChunkIssue,
+    /// Corrupt copies to retire once the new copies are stored
+    pub replaces: Vec<String>,
 }
 
 impl RepairTask {
@@ -57,11 +66,70 @@ impl RepairTask {
     }
 }
 
+/// The erasure coded chunk (stripe) a shard belongs to
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct StripeKey {
+    /// File the chunk belongs to
+    pub file_id: String,
+    /// Position of the chunk within the file
+    pub chunk_index: u32,
+}
+
+impl StripeKey {
+    /// Stripe of the issue's shard, if it is part of a file
+    pub fn of(issue: &ChunkIssue) -> Option<Self> {
+        Some(Self {
+            file_id: issue.file_id.clone()?,
+            chunk_index: issue.chunk_index?,
+        })
+    }
+}
+
+/// One shard rebuilt as part of a [`RepairBatch`]
+#[derive(Debug, Clone)]
+pub struct ShardRepair {
+    /// Unique task ID
+    pub task_id: String,
+    /// Shard to rebuild
+    pub chunk_id: Vec<u8>,
+    /// Nodes to write the rebuilt shard to
+    pub target_nodes: Vec<String>,
+    /// Shard size in bytes
+    pub chunk_size: u64,
+    /// Original issue that triggered this repair
+    pub issue: ChunkIssue,
+}
+
+/// Shards of one erasure coded chunk rebuilt together from their siblings
+#[derive(Debug, Clone)]
+pub struct RepairBatch {
+    /// Unique batch ID
+    pub batch_id: String,
+    /// Chunk whose shards are rebuilt
+    pub stripe: StripeKey,
+    /// Shards to rebuild
+    pub shards: Vec<ShardRepair>,
+    /// Priority of the most urgent shard
+    pub priority: u32,
+}
+
+impl RepairBatch {
+    /// Estimate bandwidth written in bytes (sibling reads not included)
+    pub fn estimated_bandwidth(&self) -> u64 {
+        self.shards
+            .iter()
+            .map(|s| s.chunk_size * s.target_nodes.len() as u64)
+            .sum()
+    }
+}
+
 /// Repair plan containing multiple tasks
 #[derive(Debug, Default)]
 pub struct RepairPlan {
     /// Ordered list of repair tasks
     pub tasks: Vec<RepairTask>,
+    /// Shards rebuilt from their siblings, grouped by chunk
+    pub batches: Vec<RepairBatch>,
     /// Total bytes to transfer
     pub total_bytes: u64,
     /// Estimated duration
@@ -85,6 +153,24 @@ impl RepairPlan {
         self.tasks.push(task);
     }
 
+    /// Add a batch of shard rebuilds to the plan
+    pub fn add_batch(&mut self, batch: RepairBatch) {
+        self.total_bytes += batch.estimated_bandwidth();
+
+        for shard in &batch.shards {
+            for target in &shard.target_nodes {
+                self.target_nodes.insert(target.clone());
+            }
+        }
+
+        self.batches.push(batch);
+    }
+
+    /// Number of shards repaired by the plan (copies and rebuilds)
+    pub fn repair_count(&self) -> usize {
+        self.tasks.len() + self.batches.iter().map(|b| b.shards.len()).sum::<usize>()
+    }
+
     /// Get tasks for a specific source node
     pub fn tasks_for_source(&self, node_id: &str) -> Vec<&RepairTask> {
         self.tasks
@@ -104,8 +190,9 @@ impl RepairPlan {
     /// Summary of the plan
     pub fn summary(&self) -> String {
         format!(
-            "{} tasks, {} bytes to transfer, {} source nodes, {} target nodes",
+            "{} tasks, {} rebuild batches, {} bytes to transfer, {} source nodes, {} target nodes",
             self.tasks.len(),
+            self.batches.len(),
             self.total_bytes,
             self.source_nodes.len(),
             self.target_nodes.len()
@@ -124,8 +211,27 @@ pub struct NodeInfo {
     pub load: f64,
     /// Datacenter/region for locality
     pub datacenter: Option<String>,
+    /// Rack within the datacenter
+    pub rack: Option<i32>,
     /// Is node healthy?
     pub is_healthy: bool,
+    /// Link capacity in bytes/second (0 = unknown)
+    pub bandwidth_bps: u64,
+    /// Recently observed transfer rate in bytes/second
+    pub throughput_bps: u64,
+    /// Repairs already reading from this node
+    pub active_repairs: usize,
+}
+
+impl NodeInfo {
+    /// Fraction of the link in use (0.0 when the capacity is unknown)
+    pub fn bandwidth_utilization(&self) -> f64 {
+        if self.bandwidth_bps == 0 {
+            0.0
+        } else {
+            (self.throughput_bps as f64 / self.bandwidth_bps as f64).min(1.0)
+        }
+    }
 }
 
 /// Planner configuration
@@ -143,6 +249,12 @@ pub struct PlannerConfig {
     pub max_node_load: f64,
     /// Rate limit per node (bytes/second)
     pub node_rate_limit: u64,
+    /// Anti-affinity between a repaired replica and sibling shards
+    pub anti_affinity: AntiAffinity,
+    /// Minimum healthy nodes for `Strict` anti-affinity to be enforced
+    pub anti_affinity_min_nodes: usize,
+    /// Repairs reading from one node before it is only used as a last resort
+    pub max_source_repairs: usize,
 }
 
 impl Default for PlannerConfig {
@@ -154,6 +266,9 @@ impl Default for PlannerConfig {
             prefer_local: true,
             max_node_load: 0.8,
             node_rate_limit: 100 * 1024 * 1024, // 100 MB/s
+            anti_affinity: AntiAffinity::default(),
+            anti_affinity_min_nodes: 14,
+            max_source_repairs: 3,
         }
     }
 }
@@ -163,6 +278,8 @@ pub struct Planner {
     config: PlannerConfig,
     /// Track pending load per node
     pending_load: HashMap<String, u64>,
+    /// Repairs assigned per source node in the current plan
+    planned_reads: HashMap<String, usize>,
     /// Task ID counter
     task_counter: u64,
 }
@@ -173,6 +290,7 @@ impl Planner {
         Self {
             config,
             pending_load: HashMap::new(),
+            planned_reads: HashMap::new(),
             task_counter: 0,
         }
     }
@@ -201,10 +319,21 @@ impl Planner {
 
         // Reset pending load tracking
         self.pending_load.clear();
+        self.planned_reads.clear();
+
+        // Chunks with a shard that has no copy left are rebuilt from their
+        // siblings, together with every other shard of theirs needing repair
+        let lost_stripes: HashSet<StripeKey> = sorted_issues
+            .iter()
+            .filter(|issue| Self::is_lost(issue))
+            .filter_map(|issue| StripeKey::of(issue))
+            .collect();
+        let mut rebuilds: Vec<(StripeKey, Vec<&ChunkIssue>)> = Vec::new();
+        let mut rebuild_count = 0;
 
         for issue in sorted_issues {
             // Check plan limits
-            if plan.tasks.len() >= self.config.max_tasks {
+            if plan.tasks.len() + rebuild_count >= self.config.max_tasks {
                 debug!("Reached max tasks limit");
                 break;
             }
@@ -213,6 +342,17 @@ impl Planner {
                 break;
             }
 
+            if let Some(stripe) = StripeKey::of(issue).filter(|k| lost_stripes.contains(k)) {
+                if !matches!(issue.health, ChunkHealth::Corrupt { .. }) {
+                    match rebuilds.iter_mut().find(|(key, _)| *key == stripe) {
+                        Some((_, shards)) => shards.push(issue),
+                        None => rebuilds.push((stripe, vec![issue])),
+                    }
+                    rebuild_count += 1;
+                    continue;
+                }
+            }
+
             // Try to create a repair task for this issue
             match self.plan_repair(issue, &healthy_nodes) {
                 Ok(task) => {
@@ -221,6 +361,10 @@ impl Planner {
                         .pending_load
                         .entry(task.source_node.clone())
                         .or_default() += task.chunk_size;
+                    *self
+                        .planned_reads
+                        .entry(task.source_node.clone())
+                        .or_default() += 1;
                     for target in &task.target_nodes {
                         *self.pending_load.entry(target.clone()).or_default() += task.chunk_size;
                     }
@@ -237,6 +381,28 @@ impl Planner {
             }
         }
 
+        for (stripe, shards) in rebuilds {
+            match self.plan_rebuild(stripe.clone(), &shards, &healthy_nodes) {
+                Ok(batch) => {
+                    for shard in &batch.shards {
+                        for target in &shard.target_nodes {
+                            *self.pending_load.entry(target.clone()).or_default() +=
+                                shard.chunk_size;
+                        }
+                    }
+                    plan.add_batch(batch);
+                }
+                Err(e) => {
+                    warn!(
+                        file_id = %stripe.file_id,
+                        chunk_index = stripe.chunk_index,
+                        error = %e,
+                        "Could not plan shard rebuild for chunk"
+                    );
+                }
+            }
+        }
+
         // Estimate duration based on rate limits
         plan.estimated_duration = self.estimate_duration(&plan);
 
@@ -258,6 +424,7 @@ impl Planner {
                 }
                 self.plan_under_replicated(issue, nodes, 0, self.config.replication_factor)
             }
+            ChunkHealth::Corrupt { node_ids } => self.plan_replacement(issue, nodes, node_ids),
             ChunkHealth::OverReplicated {
                 current: _,
                 target: _,
@@ -307,6 +474,92 @@ impl Planner {
             chunk_size: 1024 * 1024, // Default 1MB, should come from metadata
             priority: issue.priority,
             issue: issue.clone(),
+            replaces: Vec::new(),
+        })
+    }
+
+    /// Plan replacing corrupt copies with fresh ones on other nodes
+    ///
+    /// The new copies are read from a holder whose copy is intact; the corrupt
+    /// ones are retired after the transfer succeeds.
+    fn plan_replacement(
+        &mut self,
+        issue: &ChunkIssue,
+        nodes: &[&NodeInfo],
+        corrupt: &[String],
+    ) -> Result<RepairTask> {
+        let source = self.select_source_node(issue, nodes)?;
+        let targets = self.select_target_nodes(issue, nodes, &source, corrupt.len())?;
+
+        self.task_counter += 1;
+        let task_id = format!("replace-{}", self.task_counter);
+
+        Ok(RepairTask {
+            task_id,
+            chunk_id: issue.chunk_id.clone(),
+            source_node: source,
+            target_nodes: targets,
+            chunk_size: 1024 * 1024, // Default 1MB, should come from metadata
+            priority: issue.priority,
+            issue: issue.clone(),
+            replaces: corrupt.to_vec(),
+        })
+    }
+
+    /// Whether an issue's shard has no healthy copy left to copy from
+    fn is_lost(issue: &ChunkIssue) -> bool {
+        matches!(issue.health, ChunkHealth::Critical) && issue.current_nodes.is_empty()
+    }
+
+    /// Plan rebuilding the shards of one chunk from its siblings
+    ///
+    /// A lost shard is rebuilt onto one node; further copies its replication
+    /// factor asks for are made by later scans, copying from that node. The
+    /// shards of the batch never share a target, nor land next to a sibling.
+    fn plan_rebuild(
+        &mut self,
+        stripe: StripeKey,
+        issues: &[&ChunkIssue],
+        nodes: &[&NodeInfo],
+    ) -> Result<RepairBatch> {
+        let mut placed: Vec<String> = Vec::new();
+        let mut shards = Vec::with_capacity(issues.len());
+
+        for issue in issues {
+            let count = match issue.health {
+                ChunkHealth::UnderReplicated { current, target } => target.saturating_sub(current),
+                _ => 1,
+            };
+            if count == 0 {
+                continue;
+            }
+
+            // Nodes given an earlier shard of the batch are taken
+            let mut taken = (*issue).clone();
+            taken.current_nodes.extend(placed.iter().cloned());
+            let targets = self.select_target_nodes(&taken, nodes, "", count)?;
+            placed.extend(targets.iter().cloned());
+
+            self.task_counter += 1;
+            shards.push(ShardRepair {
+                task_id: format!("rebuild-{}", self.task_counter),
+                chunk_id: issue.chunk_id.clone(),
+                target_nodes: targets,
+                chunk_size: 1024 * 1024, // Default 1MB, should come from metadata
+                issue: (*issue).clone(),
+            });
+        }
+
+        if shards.is_empty() {
+            return Err(PlannerError::Internal("No shards to rebuild".to_string()));
+        }
+
+        self.task_counter += 1;
+        Ok(RepairBatch {
+            batch_id: format!("batch-{}", self.task_counter),
+            stripe,
+            priority: issues.iter().map(|i| i.priority).max().unwrap_or(0),
+            shards,
         })
     }
 
@@ -321,19 +574,36 @@ impl Planner {
             return Err(PlannerError::NoSourceNodes);
         }
 
-        // Prefer node with lowest load
+        // Prefer sources with spare repair slots, then the cheapest to read from
         let best = healthy_sources
             .iter()
             .min_by(|a, b| {
-                let load_a = self.get_node_load(&a.id, a.load);
-                let load_b = self.get_node_load(&b.id, b.load);
-                load_a.partial_cmp(&load_b).unwrap()
+                let busy_a = self.source_repairs(a) >= self.config.max_source_repairs;
+                let busy_b = self.source_repairs(b) >= self.config.max_source_repairs;
+                busy_a
+                    .cmp(&busy_b)
+                    .then_with(|| self.source_cost(a).total_cmp(&self.source_cost(b)))
             })
             .unwrap();
 
         Ok(best.id.clone())
     }
 
+    /// Repairs reading from a node: already running plus planned so far
+    fn source_repairs(&self, node: &NodeInfo) -> usize {
+        node.active_repairs + self.planned_reads.get(&node.id).copied().unwrap_or(0)
+    }
+
+    /// Cost of reading from a source (lower is better)
+    ///
+    /// Combines the node's load, how much of its link is in use and how many
+    /// repairs already read from it.
+    fn source_cost(&self, node: &NodeInfo) -> f64 {
+        let repairs =
+            self.source_repairs(node) as f64 / self.config.max_source_repairs.max(1) as f64;
+        self.get_node_load(&node.id, node.load) + node.bandwidth_utilization() + repairs.min(1.0)
+    }
+
     /// Select target nodes for writing
     fn select_target_nodes(
         &self,
@@ -342,7 +612,11 @@ impl Planner {
         source: &str,
         count: usize,
     ) -> Result<Vec<String>> {
-        let current_set: HashSet<_> = issue.current_nodes.iter().cloned().collect();
+        let mut current_set: HashSet<_> = issue.current_nodes.iter().cloned().collect();
+        // A node with a corrupt copy can't take the copy that replaces it
+        if let ChunkHealth::Corrupt { node_ids } = &issue.health {
+            current_set.extend(node_ids.iter().cloned());
+        }
 
         // Get source datacenter for locality preference
         let source_dc = nodes
@@ -361,13 +635,6 @@ impl Planner {
             })
             .collect();
 
-        if candidates.len() < count {
-            return Err(PlannerError::InsufficientNodes {
-                have: candidates.len(),
-                need: count,
-            });
-        }
-
         // Score candidates
         candidates.sort_by(|a, b| {
             let score_a = self.score_target(a, &source_dc);
@@ -375,14 +642,87 @@ impl Planner {
             score_b.partial_cmp(&score_a).unwrap() // Higher score is better
         });
 
-        // Take top N
-        let targets: Vec<String> = candidates
+        let anti_affinity = self
+            .config
+            .anti_affinity
+            .effective(nodes.len(), self.config.anti_affinity_min_nodes);
+
+        let targets = if anti_affinity == AntiAffinity::Off {
+            candidates
+                .iter()
+                .take(count)
+                .map(|n| n.id.clone())
+                .collect()
+        } else {
+            self.pick_with_anti_affinity(issue, nodes, &candidates, count, anti_affinity)
+        };
+
+        if targets.len() < count {
+            return Err(PlannerError::InsufficientNodes {
+                have: targets.len(),
+                need: count,
+            });
+        }
+
+        Ok(targets)
+    }
+
+    /// Pick targets that avoid nodes and racks holding shards of the chunk
+    ///
+    /// Candidates must already be sorted best-first. With `BestEffort`, any
+    /// remaining slots are filled from the conflicting candidates.
+    fn pick_with_anti_affinity(
+        &self,
+        issue: &ChunkIssue,
+        nodes: &[&NodeInfo],
+        candidates: &[&&NodeInfo],
+        count: usize,
+        anti_affinity: AntiAffinity,
+    ) -> Vec<String> {
+        let siblings: HashSet<&String> = issue.sibling_nodes.iter().collect();
+
+        // Racks already occupied by a replica or sibling shard
+        let mut occupied_racks: HashSet<(String, i32)> = nodes
             .iter()
-            .take(count)
-            .map(|n| n.id.clone())
+            .filter(|n| issue.current_nodes.contains(&n.id) || siblings.contains(&n.id))
+            .filter_map(|n| Some((n.datacenter.clone()?, n.rack?)))
             .collect();
 
-        Ok(targets)
+        let mut targets: Vec<String> = Vec::with_capacity(count);
+
+        for node in candidates {
+            if targets.len() >= count {
+                break;
+            }
+            if siblings.contains(&node.id) {
+                continue;
+            }
+            if let (Some(dc), Some(rack)) = (&node.datacenter, node.rack) {
+                if !occupied_racks.insert((dc.clone(), rack)) {
+                    continue;
+                }
+            }
+            targets.push(node.id.clone());
+        }
+
+        if targets.len() < count && anti_affinity == AntiAffinity::BestEffort {
+            debug!(
+                chunk_id = hex::encode(&issue.chunk_id),
+                placed = targets.len(),
+                needed = count,
+                "Relaxing anti-affinity for repair targets"
+            );
+            for node in candidates {
+                if targets.len() >= count {
+                    break;
+                }
+                if !targets.contains(&node.id) {
+                    targets.push(node.id.clone());
+                }
+            }
+        }
+
+        targets
     }
 
     /// Score a target node (higher is better)
@@ -454,7 +794,9 @@ mod tests {
                 target: 3,
             },
             current_nodes: nodes.iter().map(|s| s.to_string()).collect(),
+            sibling_nodes: vec![],
             file_id: None,
+            chunk_index: None,
             priority,
             detected_at: Instant::now(),
         }
@@ -467,7 +809,11 @@ mod tests {
             available_storage: 100 * 1024 * 1024 * 1024, // 100 GB
             load,
             datacenter: Some(dc.to_string()),
+            rack: None,
             is_healthy: true,
+            bandwidth_bps: 0,
+            throughput_bps: 0,
+            active_repairs: 0,
         }
     }
 
@@ -523,10 +869,185 @@ mod tests {
         assert_eq!(plan.tasks[1].priority, 500);
     }
 
+    #[test]
+    fn test_targets_avoid_sibling_nodes_and_racks() {
+        let mut planner = Planner::new(PlannerConfig::default());
+
+        let mut issue = make_issue(1, vec!["n1"], 800);
+        issue.sibling_nodes = vec!["n2".to_string(), "n3".to_string()];
+
+        let mut nodes = vec![
+            make_node("n1", "dc1", 0.1),
+            make_node("n2", "dc1", 0.1),
+            make_node("n3", "dc1", 0.1),
+            make_node("n4", "dc1", 0.5),
+            make_node("n5", "dc1", 0.6),
+            make_node("n6", "dc1", 0.7),
+        ];
+        for (i, node) in nodes.iter_mut().enumerate() {
+            node.rack = Some(i as i32);
+        }
+        // n5 shares a rack with sibling n2
+        nodes[4].rack = Some(1);
+
+        let plan = planner.create_plan(&[issue], &nodes).unwrap();
+
+        assert_eq!(plan.tasks.len(), 1);
+        assert_eq!(plan.tasks[0].target_nodes, vec!["n4", "n6"]);
+    }
+
+    #[test]
+    fn test_anti_affinity_strictness() {
+        let mut issue = make_issue(1, vec!["n1"], 800);
+        issue.sibling_nodes = vec!["n2".to_string()];
+
+        let nodes = vec![
+            make_node("n1", "dc1", 0.1),
+            make_node("n2", "dc1", 0.1),
+            make_node("n3", "dc2", 0.1),
+        ];
+
+        // Best-effort falls back to the sibling node
+        let mut planner = Planner::new(PlannerConfig::default());
+        let plan = planner.create_plan(&[issue.clone()], &nodes).unwrap();
+        assert_eq!(plan.tasks[0].target_nodes.len(), 2);
+
+        // Strict refuses to co-locate shards
+        let mut planner = Planner::new(PlannerConfig {
+            anti_affinity: AntiAffinity::Strict,
+            anti_affinity_min_nodes: 0,
+            ..Default::default()
+        });
+        let plan = planner.create_plan(&[issue.clone()], &nodes).unwrap();
+        assert!(plan.tasks.is_empty());
+
+        // Strict degrades to best-effort on small clusters
+        let mut planner = Planner::new(PlannerConfig {
+            anti_affinity: AntiAffinity::Strict,
+            ..Default::default()
+        });
+        let plan = planner.create_plan(&[issue], &nodes).unwrap();
+        assert_eq!(plan.tasks.len(), 1);
+    }
+
+    #[test]
+    fn test_source_selection_avoids_busy_and_saturated_nodes() {
+        let mut planner = Planner::new(PlannerConfig::default());
+        let mut issue = make_issue(1, vec!["n1", "n2", "n3"], 500);
+        issue.health = ChunkHealth::UnderReplicated {
+            current: 1,
+            target: 3,
+        };
+
+        let mut nodes = vec![
+            make_node("n1", "dc1", 0.1),
+            make_node("n2", "dc1", 0.2),
+            make_node("n3", "dc1", 0.3),
+            make_node("n4", "dc1", 0.1),
+            make_node("n5", "dc1", 0.1),
+        ];
+        // Least loaded, but its repair slots are taken
+        nodes[0].active_repairs = 3;
+        // Link is saturated
+        nodes[1].bandwidth_bps = 100 * 1024 * 1024;
+        nodes[1].throughput_bps = 100 * 1024 * 1024;
+
+        let plan = planner.create_plan(&[issue], &nodes).unwrap();
+        assert_eq!(plan.tasks[0].source_node, "n3");
+    }
+
+    #[test]
+    fn test_sources_spread_within_plan() {
+        let mut planner = Planner::new(PlannerConfig {
+            max_source_repairs: 1,
+            ..Default::default()
+        });
+        let issues = vec![
+            make_issue(1, vec!["n1", "n2"], 500),
+            make_issue(2, vec!["n1", "n2"], 500),
+        ];
+        let nodes = vec![
+            make_node("n1", "dc1", 0.0),
+            make_node("n2", "dc1", 0.5),
+            make_node("n3", "dc1", 0.1),
+            make_node("n4", "dc1", 0.1),
+        ];
+
+        let plan = planner.create_plan(&issues, &nodes).unwrap();
+        let sources: HashSet<_> = plan.tasks.iter().map(|t| t.source_node.as_str()).collect();
+        assert_eq!(sources.len(), 2);
+    }
+
+    #[test]
+    fn test_corrupt_copy_replaced_elsewhere() {
+        let mut planner = Planner::new(PlannerConfig::default());
+        let mut issue = make_issue(1, vec!["n2"], 700);
+        issue.health = ChunkHealth::Corrupt {
+            node_ids: vec!["n1".to_string()],
+        };
+
+        let nodes = vec![
+            make_node("n1", "dc1", 0.0),
+            make_node("n2", "dc1", 0.5),
+            make_node("n3", "dc1", 0.1),
+        ];
+
+        let plan = planner.create_plan(&[issue], &nodes).unwrap();
+        assert_eq!(plan.tasks.len(), 1);
+        let task = &plan.tasks[0];
+        assert_eq!(task.source_node, "n2");
+        assert_eq!(task.target_nodes, vec!["n3"]);
+        assert_eq!(task.replaces, vec!["n1"]);
+    }
+
+    #[test]
+    fn test_lost_shards_rebuilt_together() {
+        let mut planner = Planner::new(PlannerConfig::default());
+        let shard = |chunk_id: u8, nodes: Vec<&str>, chunk_index: u32| {
+            let mut issue = make_issue(chunk_id, nodes, 800);
+            issue.file_id = Some("file-1".to_string());
+            issue.chunk_index = Some(chunk_index);
+            issue
+        };
+
+        let mut lost = shard(1, vec![], 0);
+        lost.health = ChunkHealth::Critical;
+        let issues = vec![
+            lost,
+            shard(2, vec!["n1"], 0),
+            // Another chunk of the file still has a copy to read from
+            shard(3, vec!["n1"], 1),
+        ];
+        let nodes: Vec<_> = (1..=6)
+            .map(|i| make_node(&format!("n{}", i), "dc1", 0.1))
+            .collect();
+
+        let plan = planner.create_plan(&issues, &nodes).unwrap();
+
+        assert_eq!(plan.tasks.len(), 1);
+        assert_eq!(plan.tasks[0].chunk_id, vec![3]);
+        assert_eq!(plan.batches.len(), 1);
+
+        let batch = &plan.batches[0];
+        assert_eq!(batch.stripe.chunk_index, 0);
+        assert_eq!(batch.shards.len(), 2);
+        assert_eq!(batch.shards[0].target_nodes.len(), 1);
+        assert_eq!(batch.shards[1].target_nodes.len(), 2);
+
+        let targets: HashSet<_> = batch
+            .shards
+            .iter()
+            .flat_map(|s| s.target_nodes.iter())
+            .collect();
+        assert_eq!(targets.len(), 3);
+        assert!(!targets.contains(&"n1".to_string()));
+    }
+
     #[test]
     fn test_repair_plan_summary() {
         let plan = RepairPlan {
             tasks: vec![],
+            batches: vec![],
             total_bytes: 1024 * 1024,
             estimated_duration: Duration::from_secs(10),
             source_nodes: ["n1".to_string()].into_iter().collect(),
@@ -0,0 +1,597 @@
+//! Planner Simulation
+//!
+//! What-if analysis for cluster changes. Given the current nodes and a
+//! hypothetical change (remove some nodes, add new ones), the simulation
+//! estimates how much data the rebalancer would have to move, how full every
+//! node would end up, how long the repair takes at the configured rate
+//! limits, and how long the cluster lasts at the current ingest rate.
+//!
+//! Data on removed (and draining) nodes is re-created on the remaining
+//! nodes, filling the emptiest nodes first like the planner's preference for
+//! free space. Nothing is written; the result is a [`SimulationReport`].
+
+use cyxcloud_metadata::Node;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::time::Duration;
+use thiserror::Error;
+
+/// Simulation errors
+#[derive(Error, Debug)]
+pub enum SimulationError {
+    #[error("Unknown node: {0}")]
+    UnknownNode(String),
+
+    #[error("Invalid scenario: {0}")]
+    InvalidScenario(String),
+}
+
+pub type Result<T> = std::result::Result<T, SimulationError>;
+
+/// Hypothetical change to the cluster
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Scenario {
+    /// Nodes to remove (UUID or peer ID)
+    #[serde(default)]
+    pub remove_nodes: Vec<String>,
+    /// Nodes to add
+    #[serde(default)]
+    pub add_nodes: Vec<NodeAddition>,
+}
+
+/// A group of identical nodes to add
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NodeAddition {
+    /// Number of nodes
+    #[serde(default = "default_count")]
+    pub count: usize,
+    pub region: Option<String>,
+    pub datacenter: Option<String>,
+    /// Allocatable bytes per node (default: average of the current nodes)
+    pub capacity_bytes: Option<u64>,
+}
+
+fn default_count() -> usize {
+    1
+}
+
+/// Limits the simulation estimates against
+#[derive(Debug, Clone)]
+pub struct SimulationConfig {
+    /// Cluster-wide repair rate limit (bytes/hour)
+    pub bytes_per_hour: u64,
+    /// Per-node transfer rate limit (bytes/second)
+    pub node_rate_limit: u64,
+    /// Nodes needed to place every shard of a chunk on a different node
+    pub min_nodes: usize,
+    /// Fill ratio at which the cluster counts as full
+    pub full_threshold: f64,
+}
+
+impl Default for SimulationConfig {
+    fn default() -> Self {
+        Self {
+            bytes_per_hour: 10 * 1024 * 1024 * 1024, // 10 GB/hour
+            node_rate_limit: 100 * 1024 * 1024,      // 100 MB/s
+            min_nodes: 14,
+            full_threshold: 0.9,
+        }
+    }
+}
+
+/// Result of a simulation
+#[derive(Debug, Clone, Serialize)]
+pub struct SimulationReport {
+    pub scenario: Scenario,
+    /// Whether the remaining capacity can hold the data of the removed nodes
+    pub feasible: bool,
+    pub warnings: Vec<String>,
+    /// Bytes that must be re-created because their nodes are removed
+    pub bytes_to_move: u64,
+    /// Further bytes that would move to even out fill ratios
+    pub rebalance_bytes: u64,
+    /// Estimated time to re-create the removed data
+    pub estimated_repair_secs: u64,
+    /// Estimated time to also even out fill ratios
+    pub estimated_rebalance_secs: u64,
+    pub before: CapacitySummary,
+    pub after: CapacitySummary,
+    pub forecast: CapacityForecast,
+    pub nodes: Vec<NodeProjection>,
+}
+
+/// Cluster capacity at one point in the simulation
+#[derive(Debug, Clone, Serialize)]
+pub struct CapacitySummary {
+    pub nodes: usize,
+    pub capacity_bytes: u64,
+    pub used_bytes: u64,
+    pub fill_ratio: f64,
+}
+
+/// How long the cluster lasts at the current ingest rate
+#[derive(Debug, Clone, Serialize)]
+pub struct CapacityForecast {
+    /// Stored bytes (including parity) added per day recently
+    pub ingest_bytes_per_day: u64,
+    pub full_threshold: f64,
+    /// Days until `full_threshold` is reached today (`None` = not growing)
+    pub days_until_full_before: Option<f64>,
+    /// Days until `full_threshold` is reached after the change
+    pub days_until_full_after: Option<f64>,
+}
+
+/// Projected state of one node
+#[derive(Debug, Clone, Serialize)]
+pub struct NodeProjection {
+    /// Node ID, or `new-<n>` for hypothetical nodes
+    pub id: String,
+    pub peer_id: Option<String>,
+    pub region: Option<String>,
+    pub datacenter: Option<String>,
+    pub status: String,
+    pub capacity_bytes: u64,
+    pub used_before: u64,
+    pub used_after: u64,
+    pub fill_before: f64,
+    pub fill_after: f64,
+}
+
+/// Node as seen by the simulation
+struct SimNode {
+    projection: NodeProjection,
+    /// Node takes part in the cluster before the change
+    active_before: bool,
+    /// Node takes part in the cluster after the change
+    active_after: bool,
+}
+
+/// Run a what-if simulation
+///
+/// `ingest_bytes_per_day` is the recent growth of stored data and drives
+/// the capacity forecast.
+pub fn simulate(
+    nodes: &[Node],
+    scenario: &Scenario,
+    config: &SimulationConfig,
+    ingest_bytes_per_day: u64,
+) -> Result<SimulationReport> {
+    let mut warnings = Vec::new();
+
+    let mut removed: HashSet<&str> = HashSet::new();
+    for wanted in &scenario.remove_nodes {
+        let node = nodes
+            .iter()
+            .find(|n| n.id.to_string() == *wanted || n.peer_id == *wanted)
+            .ok_or_else(|| SimulationError::UnknownNode(wanted.clone()))?;
+        removed.insert(&node.peer_id);
+    }
+    if scenario.add_nodes.iter().any(|a| a.count == 0) {
+        return Err(SimulationError::InvalidScenario(
+            "add_nodes.count must be at least 1".to_string(),
+        ));
+    }
+
+    let offline = nodes.iter().filter(|n| n.status == "offline").count();
+    if offline > 0 {
+        warnings.push(format!(
+            "{} offline node(s) excluded; their data is already being repaired",
+            offline
+        ));
+    }
+
+    let mut sim: Vec<SimNode> = nodes
+        .iter()
+        .filter(|n| n.status != "offline")
+        .map(|n| {
+            let capacity = n.storage_allocatable().max(0) as u64;
+            let used = n.storage_used.max(0) as u64;
+            SimNode {
+                projection: NodeProjection {
+                    id: n.id.to_string(),
+                    peer_id: Some(n.peer_id.clone()),
+                    region: n.region.clone(),
+                    datacenter: n.datacenter.clone(),
+                    status: if removed.contains(n.peer_id.as_str()) {
+                        "removed".to_string()
+                    } else {
+                        n.status.clone()
+                    },
+                    capacity_bytes: capacity,
+                    used_before: used,
+                    used_after: used,
+                    fill_before: ratio(used, capacity),
+                    fill_after: 0.0,
+                },
+                active_before: true,
+                // Draining nodes are on their way out as well
+                active_after: !removed.contains(n.peer_id.as_str()) && n.status != "draining",
+            }
+        })
+        .collect();
+
+    let average_capacity = {
+        let active: Vec<u64> = sim.iter().map(|n| n.projection.capacity_bytes).collect();
+        if active.is_empty() {
+            0
+        } else {
+            active.iter().sum::<u64>() / active.len() as u64
+        }
+    };
+
+    let mut added = 0;
+    for addition in &scenario.add_nodes {
+        let capacity = addition.capacity_bytes.unwrap_or(average_capacity);
+        if capacity == 0 {
+            return Err(SimulationError::InvalidScenario(
+                "capacity_bytes is required when the cluster has no nodes".to_string(),
+            ));
+        }
+        for _ in 0..addition.count {
+            added += 1;
+            sim.push(SimNode {
+                projection: NodeProjection {
+                    id: format!("new-{}", added),
+                    peer_id: None,
+                    region: addition.region.clone(),
+                    datacenter: addition.datacenter.clone(),
+                    status: "planned".to_string(),
+                    capacity_bytes: capacity,
+                    used_before: 0,
+                    used_after: 0,
+                    fill_before: 0.0,
+                    fill_after: 0.0,
+                },
+                active_before: false,
+                active_after: true,
+            });
+        }
+    }
+
+    // Data on departing nodes has to be re-created elsewhere
+    let bytes_to_move: u64 = sim
+        .iter()
+        .filter(|n| n.active_before && !n.active_after)
+        .map(|n| n.projection.used_before)
+        .sum();
+    for node in sim.iter_mut().filter(|n| !n.active_after) {
+        node.projection.used_after = 0;
+    }
+
+    let targets: Vec<usize> = (0..sim.len()).filter(|&i| sim[i].active_after).collect();
+    let free: u64 = targets
+        .iter()
+        .map(|&i| {
+            let p = &sim[i].projection;
+            p.capacity_bytes.saturating_sub(p.used_before)
+        })
+        .sum();
+    let feasible = free >= bytes_to_move;
+    if !feasible {
+        warnings.push(format!(
+            "Remaining free space ({} bytes) cannot hold the {} bytes to move",
+            free, bytes_to_move
+        ));
+    }
+
+    let received = water_fill(&mut sim, &targets, bytes_to_move);
+
+    if targets.len() < config.min_nodes {
+        warnings.push(format!(
+            "{} node(s) remain but {} are needed to keep every shard of a chunk on a separate node",
+            targets.len(),
+            config.min_nodes
+        ));
+    }
+
+    // Moves needed afterwards to bring every node to the average fill ratio
+    let capacity_after: u64 = targets
+        .iter()
+        .map(|&i| sim[i].projection.capacity_bytes)
+        .sum();
+    let used_after: u64 = targets.iter().map(|&i| sim[i].projection.used_after).sum();
+    let average_fill = ratio(used_after, capacity_after);
+    let rebalance_bytes: u64 = targets
+        .iter()
+        .map(|&i| {
+            let p = &sim[i].projection;
+            p.used_after
+                .saturating_sub((average_fill * p.capacity_bytes as f64) as u64)
+        })
+        .sum();
+
+    for node in &mut sim {
+        node.projection.fill_after = if node.active_after {
+            ratio(node.projection.used_after, node.projection.capacity_bytes)
+        } else {
+            0.0
+        };
+    }
+
+    let max_received = received.iter().copied().max().unwrap_or(0);
+    let estimated_repair = transfer_time(bytes_to_move, max_received, config);
+    let estimated_rebalance = transfer_time(
+        bytes_to_move + rebalance_bytes,
+        max_received + rebalance_bytes / targets.len().max(1) as u64,
+        config,
+    );
+
+    let before = summary(sim.iter().filter(|n| n.active_before), |p| p.used_before);
+    let after = summary(sim.iter().filter(|n| n.active_after), |p| p.used_after);
+    let forecast = CapacityForecast {
+        ingest_bytes_per_day,
+        full_threshold: config.full_threshold,
+        days_until_full_before: days_until_full(&before, config, ingest_bytes_per_day),
+        days_until_full_after: days_until_full(&after, config, ingest_bytes_per_day),
+    };
+    if after.fill_ratio >= config.full_threshold {
+        warnings.push(format!(
+            "Cluster would be {:.0}% full after the change",
+            after.fill_ratio * 100.0
+        ));
+    }
+
+    Ok(SimulationReport {
+        scenario: scenario.clone(),
+        feasible,
+        warnings,
+        bytes_to_move,
+        rebalance_bytes,
+        estimated_repair_secs: estimated_repair.as_secs(),
+        estimated_rebalance_secs: estimated_rebalance.as_secs(),
+        before,
+        after,
+        forecast,
+        nodes: sim.into_iter().map(|n| n.projection).collect(),
+    })
+}
+
+/// Spread `bytes` over `targets`, raising the emptiest nodes first
+///
+/// Finds the fill level every target is brought up to (nodes already above
+/// it get nothing). Returns the bytes each target receives.
+fn water_fill(sim: &mut [SimNode], targets: &[usize], bytes: u64) -> Vec<u64> {
+    let mut received = vec![0u64; targets.len()];
+    if bytes == 0 || targets.is_empty() {
+        return received;
+    }
+
+    let take = |level: f64, p: &NodeProjection| -> u64 {
+        let goal = (level * p.capacity_bytes as f64) as u64;
+        goal.min(p.capacity_bytes).saturating_sub(p.used_before)
+    };
+    let total_at = |level: f64| -> u64 {
+        targets
+            .iter()
+            .map(|&i| take(level, &sim[i].projection))
+            .sum()
+    };
+
+    let (mut low, mut high) = (0.0f64, 1.0f64);
+    for _ in 0..64 {
+        let mid = (low + high) / 2.0;
+        if total_at(mid) >= bytes {
+            high = mid;
+        } else {
+            low = mid;
+        }
+    }
+
+    let mut remaining = bytes;
+    for (slot, &i) in targets.iter().enumerate() {
+        let amount = take(high, &sim[i].projection).min(remaining);
+        received[slot] = amount;
+        remaining -= amount;
+        sim[i].projection.used_after = sim[i].projection.used_before + amount;
+    }
+    received
+}
+
+/// Time to move `total` bytes when the busiest node receives `per_node`
+fn transfer_time(total: u64, per_node: u64, config: &SimulationConfig) -> Duration {
+    let cluster_secs = if config.bytes_per_hour > 0 {
+        total as f64 * 3600.0 / config.bytes_per_hour as f64
+    } else {
+        0.0
+    };
+    let node_secs = if config.node_rate_limit > 0 {
+        per_node as f64 / config.node_rate_limit as f64
+    } else {
+        0.0
+    };
+    Duration::from_secs_f64(cluster_secs.max(node_secs).ceil())
+}
+
+fn summary<'a>(
+    nodes: impl Iterator<Item = &'a SimNode>,
+    used: impl Fn(&NodeProjection) -> u64,
+) -> CapacitySummary {
+    let (mut count, mut capacity, mut used_bytes) = (0, 0u64, 0u64);
+    for node in nodes {
+        count += 1;
+        capacity += node.projection.capacity_bytes;
+        used_bytes += used(&node.projection);
+    }
+    CapacitySummary {
+        nodes: count,
+        capacity_bytes: capacity,
+        used_bytes,
+        fill_ratio: ratio(used_bytes, capacity),
+    }
+}
+
+fn days_until_full(
+    summary: &CapacitySummary,
+    config: &SimulationConfig,
+    ingest_bytes_per_day: u64,
+) -> Option<f64> {
+    if ingest_bytes_per_day == 0 {
+        return None;
+    }
+    let limit = summary.capacity_bytes as f64 * config.full_threshold;
+    let headroom = (limit - summary.used_bytes as f64).max(0.0);
+    Some(headroom / ingest_bytes_per_day as f64)
+}
+
+fn ratio(used: u64, capacity: u64) -> f64 {
+    if capacity == 0 {
+        0.0
+    } else {
+        used as f64 / capacity as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    const GB: u64 = 1024 * 1024 * 1024;
+
+    fn make_node(peer_id: &str, capacity_gb: u64, used_gb: u64, status: &str) -> Node {
+        let now = chrono::Utc::now();
+        Node {
+            id: Uuid::new_v4(),
+            peer_id: peer_id.to_string(),
+            grpc_address: "127.0.0.1:50051".to_string(),
+            storage_total: (capacity_gb * GB) as i64,
+            storage_reserved: 0,
+            storage_used: (used_gb * GB) as i64,
+            bandwidth_mbps: 100,
+            measured_bandwidth_mbps: None,
+            bandwidth_measured_at: None,
+            max_connections: 100,
+            disk_available: None,
+            datacenter: Some("dc1".to_string()),
+            rack: None,
+            region: Some("eu".to_string()),
+            latitude: None,
+            longitude: None,
+            status: status.to_string(),
+            last_heartbeat: Some(now),
+            failure_count: 0,
+            first_offline_at: None,
+            status_changed_at: None,
+            warmup_started_at: None,
+            reputation: cyxcloud_metadata::NEUTRAL_REPUTATION,
+            archival: false,
+            version: None,
+            created_at: now,
+            updated_at: now,
+            wallet_address: None,
+            public_key: None,
+        }
+    }
+
+    fn config() -> SimulationConfig {
+        SimulationConfig {
+            bytes_per_hour: 36 * GB,
+            node_rate_limit: 100 * GB,
+            min_nodes: 3,
+            full_threshold: 0.9,
+        }
+    }
+
+    #[test]
+    fn test_remove_node_moves_its_data_to_emptiest_nodes() {
+        let nodes = vec![
+            make_node("a", 100, 60, "online"),
+            make_node("b", 100, 20, "online"),
+            make_node("c", 100, 40, "online"),
+            make_node("d", 100, 40, "online"),
+        ];
+        let scenario = Scenario {
+            remove_nodes: vec!["a".to_string()],
+            add_nodes: vec![],
+        };
+
+        let report = simulate(&nodes, &scenario, &config(), 0).unwrap();
+
+        assert!(report.feasible);
+        assert_eq!(report.bytes_to_move, 60 * GB);
+        // 60 GB spread so b, c and d end at equal fill (20+40+40+60 = 160 / 300)
+        let fills: Vec<f64> = report.nodes[1..].iter().map(|n| n.fill_after).collect();
+        for fill in &fills {
+            assert!((fill - 160.0 / 300.0).abs() < 0.01, "fill {}", fill);
+        }
+        assert_eq!(report.nodes[0].status, "removed");
+        assert_eq!(report.nodes[0].used_after, 0);
+        assert_eq!(report.after.nodes, 3);
+        assert_eq!(report.after.used_bytes, report.before.used_bytes);
+        // 60 GB at 36 GB/hour
+        assert_eq!(report.estimated_repair_secs, 6000);
+    }
+
+    #[test]
+    fn test_added_nodes_absorb_data_and_extend_forecast() {
+        let nodes = vec![
+            make_node("a", 100, 80, "online"),
+            make_node("b", 100, 80, "online"),
+            make_node("c", 100, 80, "online"),
+        ];
+        let scenario = Scenario {
+            remove_nodes: vec!["a".to_string()],
+            add_nodes: vec![NodeAddition {
+                count: 2,
+                region: Some("us".to_string()),
+                datacenter: None,
+                capacity_bytes: None,
+            }],
+        };
+
+        let report = simulate(&nodes, &scenario, &config(), 10 * GB).unwrap();
+
+        assert_eq!(report.nodes.len(), 5);
+        assert_eq!(report.nodes[3].id, "new-1");
+        assert_eq!(report.nodes[3].capacity_bytes, 100 * GB);
+        // All 80 GB of node a lands on the empty new nodes
+        assert_eq!(
+            report.nodes[3].used_after + report.nodes[4].used_after,
+            80 * GB
+        );
+        assert!(report.rebalance_bytes > 0);
+
+        // 30 GB below the 90% mark today, 120 GB after the change
+        let before = report.forecast.days_until_full_before.unwrap();
+        let after = report.forecast.days_until_full_after.unwrap();
+        assert!((before - 3.0).abs() < 0.01, "before {}", before);
+        assert!((after - 12.0).abs() < 0.01, "after {}", after);
+    }
+
+    #[test]
+    fn test_infeasible_removal_and_warnings() {
+        let nodes = vec![
+            make_node("a", 100, 90, "online"),
+            make_node("b", 100, 90, "online"),
+            make_node("c", 100, 10, "draining"),
+            make_node("d", 100, 50, "offline"),
+        ];
+        let scenario = Scenario {
+            remove_nodes: vec!["a".to_string()],
+            add_nodes: vec![],
+        };
+
+        let report = simulate(&nodes, &scenario, &config(), 0).unwrap();
+
+        // Draining node c moves too; offline node d is ignored
+        assert_eq!(report.bytes_to_move, 100 * GB);
+        assert!(!report.feasible);
+        assert_eq!(report.nodes.len(), 3);
+        assert!(report.warnings.iter().any(|w| w.contains("offline")));
+        assert!(report.warnings.iter().any(|w| w.contains("separate node")));
+        assert!(report.forecast.days_until_full_after.is_none());
+    }
+
+    #[test]
+    fn test_unknown_node_is_rejected() {
+        let nodes = vec![make_node("a", 100, 10, "online")];
+        let scenario = Scenario {
+            remove_nodes: vec!["zzz".to_string()],
+            add_nodes: vec![],
+        };
+        assert!(matches!(
+            simulate(&nodes, &scenario, &config(), 0),
+            Err(SimulationError::UnknownNode(_))
+        ));
+    }
+}
@@ -1,15 +1,38 @@
 //! Chunk transfer functionality for rebalancer
 //!
 //! Handles the actual transfer of chunks between storage nodes using gRPC.
+//!
+//! The rebalancer only coordinates: it asks the source node to push the
+//! chunk straight to the targets (`ReplicateChunk`), then verifies each copy
+//! and records it. Only a source too old to push has its chunks relayed
+//! through the rebalancer.
+//!
+//! A copy is verified by asking its target for the hash of the data it
+//! stored and comparing that with the chunk ID, which is the hash the data
+//! must have. Only a matching copy is added to `chunk_locations` (raising the
+//! chunk's replica count); a mismatching one is queued for shard GC and its
+//! target is reported as rejected, so the chunk is planned again.
+//!
+//! Shards with no copy left are rebuilt instead: the rebalancer reads just
+//! enough sibling shards of the chunk to decode it, re-encodes it with the
+//! file's erasure profile and stores the missing shards, all shards of a chunk
+//! from a single read of its siblings.
 
 #![allow(clippy::type_complexity)]
 
+use crate::executor::{ExecutionResult, TransferOutcome};
+use crate::planner::RepairBatch;
+use bytes::Bytes;
 use cyxcloud_core::chunk::ChunkId;
+use cyxcloud_core::error::{CyxCloudError, ErrorCode, HasErrorCode};
+use cyxcloud_core::{ErasureBackend, ErasureConfig, ErasureEncoder, ShardData};
 use cyxcloud_metadata::postgres::Database;
-use cyxcloud_network::grpc_client::ChunkClient;
+use cyxcloud_metadata::{Chunk, Node, NodeDrain, RepairJob};
+use cyxcloud_network::grpc_client::{ChunkClient, ChunkClientConfig};
 use std::sync::Arc;
 use thiserror::Error;
 use tracing::{debug, error, info, instrument, warn};
+use uuid::Uuid;
 
 /// Transfer errors
 #[derive(Error, Debug)]
@@ -29,6 +52,9 @@ pub enum TransferError {
     #[error("Verification failed after transfer")]
     VerificationFailed,
 
+    #[error("Checksum mismatch: expected {expected}, got {actual}")]
+    ChecksumMismatch { expected: String, actual: String },
+
     #[error("Database error: {0}")]
     Database(String),
 
@@ -36,12 +62,30 @@ pub enum TransferError {
     Network(String),
 }
 
+impl HasErrorCode for TransferError {
+    fn error_code(&self) -> ErrorCode {
+        match self {
+            TransferError::SourceNotFound(_)
+            | TransferError::TargetNotFound(_)
+            | TransferError::ChunkNotFound(_) => ErrorCode::NotFound,
+            TransferError::TransferFailed(_) | TransferError::Network(_) => {
+                ErrorCode::NodeUnreachable
+            }
+            TransferError::VerificationFailed | TransferError::ChecksumMismatch { .. } => {
+                ErrorCode::IntegrityError
+            }
+            TransferError::Database(_) => ErrorCode::MetadataUnavailable,
+        }
+    }
+}
+
 /// Result type for transfer operations
 pub type Result<T> = std::result::Result<T, TransferError>;
 
 /// Chunk transfer service
 pub struct ChunkTransferService {
     db: Arc<Database>,
+    client_config: ChunkClientConfig,
     chunk_client: ChunkClient,
 }
 
@@ -50,16 +94,33 @@ impl ChunkTransferService {
     pub fn new(db: Arc<Database>) -> Self {
         Self {
             db,
+            client_config: ChunkClientConfig::default(),
             chunk_client: ChunkClient::new(),
         }
     }
 
+    /// Present a shared cluster token to nodes asked to push chunks
+    pub fn with_cluster_token(mut self, token: Option<String>) -> Self {
+        self.client_config.cluster_token = token;
+        self.chunk_client = ChunkClient::with_config(self.client_config.clone());
+        self
+    }
+
+    /// Have chunks encrypted to each target node on their way there
+    ///
+    /// Applies to chunks pushed by source nodes and to chunks relayed or
+    /// rebuilt through the rebalancer alike.
+    pub fn with_transfer_encryption(mut self, encrypt: bool) -> Self {
+        self.client_config.encrypt_transfers = encrypt;
+        self.chunk_client = ChunkClient::with_config(self.client_config.clone());
+        self
+    }
+
     /// Transfer a chunk from source to target node
     ///
-    /// 1. Get chunk data from source node
-    /// 2. Store chunk on target node
-    /// 3. Verify chunk on target node
-    /// 4. Update metadata database
+    /// 1. Have the source node push the chunk to the target
+    /// 2. Verify chunk on target node
+    /// 3. Update metadata database
     #[instrument(skip(self), fields(chunk_id = hex::encode(chunk_id)))]
     pub async fn transfer_chunk(
         &self,
@@ -67,6 +128,81 @@ impl ChunkTransferService {
         source_peer_id: &str,
         target_peer_id: &str,
     ) -> Result<()> {
+        self.copy_chunk(chunk_id, source_peer_id, &[target_peer_id.to_string()])
+            .await?
+            .pop()
+            .unwrap_or_else(|| Err(TransferError::TargetNotFound(target_peer_id.to_string())))
+    }
+
+    /// Transfer a chunk to multiple targets
+    ///
+    /// The source pushes to all targets at once, so the transfer takes about
+    /// as long as the slowest target rather than the sum of all of them.
+    /// Targets whose copy failed its checksum are reported as rejected.
+    #[instrument(skip(self), fields(chunk_id = hex::encode(chunk_id), target_count = target_peer_ids.len()))]
+    pub async fn transfer_to_multiple(
+        &self,
+        chunk_id: &[u8],
+        source_peer_id: &str,
+        target_peer_ids: Vec<String>,
+    ) -> TransferOutcome {
+        let results = match self
+            .copy_chunk(chunk_id, source_peer_id, &target_peer_ids)
+            .await
+        {
+            Ok(results) => results,
+            Err(e) => {
+                warn!(
+                    chunk_id = hex::encode(chunk_id),
+                    source = %source_peer_id,
+                    error = %e,
+                    "Transfer from source failed"
+                );
+                return TransferOutcome::default();
+            }
+        };
+
+        let mut outcome = TransferOutcome::default();
+        for (target, result) in target_peer_ids.iter().zip(results) {
+            match result {
+                Ok(()) => {
+                    outcome.stored.push(target.clone());
+                }
+                Err(e @ TransferError::ChecksumMismatch { .. }) => {
+                    warn!(
+                        chunk_id = hex::encode(chunk_id),
+                        target = %target,
+                        error = %e,
+                        "Copy rejected by checksum verification"
+                    );
+                    outcome.rejected.push(target.clone());
+                }
+                Err(e) => {
+                    warn!(
+                        chunk_id = hex::encode(chunk_id),
+                        target = %target,
+                        error = %e,
+                        "Transfer to target failed"
+                    );
+                }
+            }
+        }
+
+        outcome
+    }
+
+    /// Copy a chunk from the source node to each target node
+    ///
+    /// Fails as a whole only when the source can't provide the chunk;
+    /// otherwise returns one result per target, in order.
+    async fn copy_chunk(
+        &self,
+        chunk_id: &[u8],
+        source_peer_id: &str,
+        target_peer_ids: &[String],
+    ) -> Result<Vec<Result<()>>> {
+        let chunk_id_obj = self.bytes_to_chunk_id(chunk_id)?;
+
         // Get source node info
         let source_node = self
             .db
@@ -76,105 +212,408 @@ impl ChunkTransferService {
             .ok_or_else(|| TransferError::SourceNotFound(source_peer_id.to_string()))?;
 
         // Get target node info
-        let target_node = self
-            .db
-            .get_node_by_peer_id(target_peer_id)
-            .await
-            .map_err(|e| TransferError::Database(e.to_string()))?
-            .ok_or_else(|| TransferError::TargetNotFound(target_peer_id.to_string()))?;
+        let mut targets = Vec::with_capacity(target_peer_ids.len());
+        for peer_id in target_peer_ids {
+            let target = self
+                .db
+                .get_node_by_peer_id(peer_id)
+                .await
+                .map_err(|e| TransferError::Database(e.to_string()))
+                .and_then(|node| {
+                    node.ok_or_else(|| TransferError::TargetNotFound(peer_id.clone()))
+                });
+            targets.push(target);
+        }
+        let addrs: Vec<String> = targets
+            .iter()
+            .flatten()
+            .map(|node| node.grpc_address.clone())
+            .collect();
 
         info!(
             source = %source_peer_id,
-            target = %target_peer_id,
             source_addr = %source_node.grpc_address,
-            target_addr = %target_node.grpc_address,
+            targets = ?addrs,
             "Transferring chunk"
         );
 
-        // Step 1: Get chunk from source
-        let chunk_id_obj = self.bytes_to_chunk_id(chunk_id)?;
+        // Step 1: Have the source push the chunk straight to the targets
+        let delivered = if addrs.is_empty() {
+            Vec::new()
+        } else {
+            match self
+                .chunk_client
+                .push_chunk(&source_node.grpc_address, chunk_id_obj, &addrs)
+                .await
+            {
+                Ok(Some(report)) => report
+                    .targets
+                    .into_iter()
+                    .map(|target| match target.error {
+                        None => Ok(()),
+                        Some(e) => Err(TransferError::TransferFailed(e)),
+                    })
+                    .collect(),
+                Ok(None) => {
+                    debug!("Source node can't push chunks, relaying through the rebalancer");
+                    self.relay_chunk(&source_node.grpc_address, chunk_id_obj, &addrs)
+                        .await?
+                }
+                Err(CyxCloudError::ChunkNotFound(_)) => {
+                    return Err(TransferError::ChunkNotFound(hex::encode(chunk_id)))
+                }
+                Err(e) => return Err(TransferError::Network(e.to_string())),
+            }
+        };
+
+        // Steps 2 and 3: Verify and record each copy
+        let mut delivered = delivered.into_iter();
+        let mut results = Vec::with_capacity(targets.len());
+        for target in targets {
+            let result = match (target, delivered.next()) {
+                (Ok(node), Some(Ok(()))) => self.confirm_copy(chunk_id, chunk_id_obj, &node).await,
+                (Ok(_), Some(Err(e))) => Err(e),
+                (Ok(node), None) => Err(TransferError::TransferFailed(format!(
+                    "Source node reported nothing for {}",
+                    node.grpc_address
+                ))),
+                (Err(e), _) => Err(e),
+            };
+            results.push(result);
+        }
 
+        Ok(results)
+    }
+
+    /// Copy a chunk by downloading it from the source and uploading it to
+    /// each target, for source nodes without ReplicateChunk
+    async fn relay_chunk(
+        &self,
+        source_addr: &str,
+        chunk_id: ChunkId,
+        target_addrs: &[String],
+    ) -> Result<Vec<Result<()>>> {
         let chunk_data = self
             .chunk_client
-            .get_chunk(&source_node.grpc_address, chunk_id_obj)
+            .get_chunk(source_addr, chunk_id)
             .await
             .map_err(|e| TransferError::Network(e.to_string()))?
-            .ok_or_else(|| TransferError::ChunkNotFound(hex::encode(chunk_id)))?;
+            .ok_or_else(|| TransferError::ChunkNotFound(chunk_id.to_string()))?;
+
+        // Don't spread a corrupt source copy
+        let computed = ChunkId::from_data(&chunk_data);
+        if computed != chunk_id {
+            return Err(TransferError::ChecksumMismatch {
+                expected: chunk_id.to_string(),
+                actual: computed.to_string(),
+            });
+        }
 
         debug!(size = chunk_data.len(), "Retrieved chunk from source node");
 
-        // Step 2: Store chunk on target
-        self.chunk_client
-            .store_chunk(&target_node.grpc_address, chunk_id_obj, chunk_data)
-            .await
-            .map_err(|e| TransferError::Network(e.to_string()))?;
-
-        debug!("Stored chunk on target node");
+        let mut results = Vec::with_capacity(target_addrs.len());
+        for addr in target_addrs {
+            let result = self
+                .chunk_client
+                .store_chunk(addr, chunk_id, chunk_data.clone())
+                .await
+                .map_err(|e| TransferError::Network(e.to_string()));
+            results.push(result);
+        }
+        Ok(results)
+    }
 
-        // Step 3: Verify chunk on target
-        let (valid, _size) = self
+    /// Verify a copied chunk on its target and record the new location
+    ///
+    /// The target's hash of the data it stored must match the chunk ID. A
+    /// copy that doesn't is queued for shard GC rather than recorded.
+    async fn confirm_copy(
+        &self,
+        chunk_id: &[u8],
+        chunk_id_obj: ChunkId,
+        target: &Node,
+    ) -> Result<()> {
+        let mismatch = match self
             .chunk_client
-            .verify_chunk(&target_node.grpc_address, chunk_id_obj)
+            .stored_chunk_hash(&target.grpc_address, chunk_id_obj)
             .await
-            .map_err(|e| TransferError::Network(e.to_string()))?;
+        {
+            Ok(Some(hash)) if hash == chunk_id_obj => None,
+            Ok(Some(hash)) => Some(hash.to_string()),
+            // Older targets only report that the hash differs
+            Err(CyxCloudError::ChunkCorrupted) => Some("unknown".to_string()),
+            Ok(None) => {
+                error!(
+                    chunk_id = hex::encode(chunk_id),
+                    target = %target.peer_id,
+                    "Chunk missing on target after transfer"
+                );
+                return Err(TransferError::VerificationFailed);
+            }
+            Err(e) => return Err(TransferError::Network(e.to_string())),
+        };
 
-        if !valid {
+        if let Some(actual) = mismatch {
             error!(
                 chunk_id = hex::encode(chunk_id),
-                target = %target_peer_id,
-                "Chunk verification failed after transfer"
+                target = %target.peer_id,
+                stored = %actual,
+                "Chunk checksum mismatch after transfer"
             );
-            return Err(TransferError::VerificationFailed);
+            if let Err(e) = self.db.retire_chunk_copy(chunk_id, target.id).await {
+                warn!(
+                    target = %target.peer_id,
+                    error = %e,
+                    "Failed to queue rejected copy for shard GC"
+                );
+            }
+            return Err(TransferError::ChecksumMismatch {
+                expected: chunk_id_obj.to_string(),
+                actual,
+            });
         }
 
-        debug!("Chunk verified on target node");
+        debug!(target = %target.peer_id, "Chunk checksum verified on target node");
 
-        // Step 4: Update metadata database
         self.db
-            .add_chunk_location(chunk_id, target_node.id)
+            .add_chunk_location(chunk_id, target.id)
             .await
             .map_err(|e| TransferError::Database(e.to_string()))?;
 
         info!(
             chunk_id = hex::encode(chunk_id),
-            source = %source_peer_id,
-            target = %target_peer_id,
+            target = %target.peer_id,
             "Chunk transfer completed successfully"
         );
 
         Ok(())
     }
 
-    /// Transfer a chunk to multiple targets
-    #[instrument(skip(self), fields(chunk_id = hex::encode(chunk_id), target_count = target_peer_ids.len()))]
-    pub async fn transfer_to_multiple(
+    /// Run a queued repair job with a source node
+    ///
+    /// Marks the job in progress, copies the chunk to the job's target and
+    /// completes it. Evacuations (copying a chunk off a draining node) then
+    /// drop the source replica and advance its drain progress. A failed copy
+    /// marks the job failed. Returns the updated drain progress, if tracked.
+    #[instrument(skip(self, job), fields(job_id = %job.id, chunk_id = hex::encode(&job.chunk_id)))]
+    pub async fn execute_repair_job(&self, job: &RepairJob) -> Result<Option<NodeDrain>> {
+        let source_id = job
+            .source_node_id
+            .ok_or_else(|| TransferError::SourceNotFound("repair job has no source".to_string()))?;
+
+        self.db
+            .update_repair_job_status(job.id, "in_progress", None)
+            .await
+            .map_err(|e| TransferError::Database(e.to_string()))?;
+
+        let outcome = self.copy_for_job(job, source_id).await;
+        if let Err(e) = &outcome {
+            if let Err(db_err) = self
+                .db
+                .update_repair_job_status(job.id, "failed", Some(&e.to_string()))
+                .await
+            {
+                warn!(error = %db_err, "Failed to mark repair job failed");
+            }
+        }
+        outcome?;
+
+        self.db
+            .complete_evacuation_job(job)
+            .await
+            .map_err(|e| TransferError::Database(e.to_string()))
+    }
+
+    /// Resolve a job's nodes and copy its chunk
+    async fn copy_for_job(&self, job: &RepairJob, source_id: uuid::Uuid) -> Result<()> {
+        let source = self
+            .db
+            .get_node(source_id)
+            .await
+            .map_err(|e| TransferError::Database(e.to_string()))?
+            .ok_or_else(|| TransferError::SourceNotFound(source_id.to_string()))?;
+        let target = self
+            .db
+            .get_node(job.target_node_id)
+            .await
+            .map_err(|e| TransferError::Database(e.to_string()))?
+            .ok_or_else(|| TransferError::TargetNotFound(job.target_node_id.to_string()))?;
+
+        self.transfer_chunk(&job.chunk_id, &source.peer_id, &target.peer_id)
+            .await
+    }
+
+    /// Convert byte slice to ChunkId
+    /// Rebuild shards of one erasure coded chunk from its siblings
+    ///
+    /// Reads `data_shards` intact siblings once, decodes the chunk with the
+    /// file's erasure profile and re-encodes it, then stores each requested
+    /// shard on its targets. Returns the targets that stored each shard, in
+    /// order; fails as a whole only when the chunk can't be decoded.
+    #[instrument(skip(self, shards), fields(file_id = %file_id, shards = shards.len()))]
+    pub async fn rebuild_shards(
         &self,
-        chunk_id: &[u8],
-        source_peer_id: &str,
-        target_peer_ids: Vec<String>,
-    ) -> Vec<String> {
-        let mut successful = Vec::new();
+        file_id: Uuid,
+        chunk_index: u32,
+        shards: &[(Vec<u8>, Vec<String>)],
+    ) -> Result<Vec<Vec<String>>> {
+        let file = self
+            .db
+            .get_file(file_id)
+            .await
+            .map_err(|e| TransferError::Database(e.to_string()))?
+            .ok_or_else(|| TransferError::ChunkNotFound(format!("file {}", file_id)))?;
+        let config = ErasureConfig::new(
+            file.data_shards.max(0) as usize,
+            file.parity_shards.max(0) as usize,
+        )
+        .map_err(|e| TransferError::TransferFailed(e.to_string()))?;
+        let backend: ErasureBackend = file
+            .erasure_backend
+            .parse()
+            .map_err(|e: CyxCloudError| TransferError::TransferFailed(e.to_string()))?;
+        let encoder = ErasureEncoder::with_backend(config, backend)
+            .map_err(|e| TransferError::TransferFailed(e.to_string()))?;
+
+        let stripe: Vec<Chunk> = self
+            .db
+            .get_file_chunks(file_id)
+            .await
+            .map_err(|e| TransferError::Database(e.to_string()))?
+            .into_iter()
+            .filter(|c| c.chunk_index == chunk_index as i32)
+            .collect();
+        let locations = self
+            .db
+            .get_file_chunk_locations(file_id)
+            .await
+            .map_err(|e| TransferError::Database(e.to_string()))?;
 
-        for target in &target_peer_ids {
-            match self.transfer_chunk(chunk_id, source_peer_id, target).await {
-                Ok(()) => {
-                    successful.push(target.clone());
-                }
-                Err(e) => {
+        // Read just enough intact siblings to decode (chunks come sorted by shard)
+        let mut slots: Vec<Option<ShardData>> = vec![None; config.total_shards()];
+        let mut fetched = 0;
+        for sibling in &stripe {
+            if fetched == config.data_shards {
+                break;
+            }
+            let index = sibling.shard_index as usize;
+            if index >= slots.len()
+                || slots[index].is_some()
+                || shards.iter().any(|(id, _)| *id == sibling.chunk_id)
+            {
+                continue;
+            }
+            let addrs = locations.get(&sibling.chunk_id).map_or(&[][..], |a| a);
+            if let Some(data) = self.fetch_shard(&sibling.chunk_id, addrs).await {
+                slots[index] = Some(ShardData::new(index as u8, data, sibling.is_parity));
+                fetched += 1;
+            }
+        }
+        if fetched < config.data_shards {
+            return Err(TransferError::TransferFailed(format!(
+                "only {} of {} sibling shards readable",
+                fetched, config.data_shards
+            )));
+        }
+
+        let shard_size = slots.iter().flatten().map(|s| s.size()).next().unwrap_or(0);
+        let rebuilt = encoder
+            .decode(&slots, shard_size * config.data_shards)
+            .and_then(|data| encoder.encode_bytes(&data))
+            .map_err(|e| TransferError::TransferFailed(e.to_string()))?;
+        debug!(
+            chunk_index,
+            siblings_read = fetched,
+            "Decoded chunk for shard rebuild"
+        );
+
+        let mut results = Vec::with_capacity(shards.len());
+        for (chunk_id, targets) in shards {
+            let data = stripe
+                .iter()
+                .find(|c| c.chunk_id == *chunk_id)
+                .and_then(|c| rebuilt.get(c.shard_index as usize))
+                .map(|shard| shard.data.clone());
+            let stored = match data {
+                Some(data) => self.store_rebuilt_shard(chunk_id, data, targets).await,
+                None => {
                     warn!(
                         chunk_id = hex::encode(chunk_id),
-                        target = %target,
-                        error = %e,
-                        "Transfer to target failed"
+                        "Shard is not part of the chunk, not rebuilt"
                     );
+                    Vec::new()
                 }
+            };
+            results.push(stored);
+        }
+
+        Ok(results)
+    }
+
+    /// Read a shard from the first node that returns an intact copy
+    async fn fetch_shard(&self, chunk_id: &[u8], addrs: &[String]) -> Option<Bytes> {
+        let chunk_id_obj = self.bytes_to_chunk_id(chunk_id).ok()?;
+        for addr in addrs {
+            match self.chunk_client.get_chunk(addr, chunk_id_obj).await {
+                Ok(Some(data)) if ChunkId::from_data(&data) == chunk_id_obj => return Some(data),
+                Ok(Some(_)) => warn!(
+                    chunk_id = hex::encode(chunk_id),
+                    node = %addr,
+                    "Sibling shard failed its hash check"
+                ),
+                Ok(None) => {}
+                Err(e) => debug!(node = %addr, error = %e, "Failed to read sibling shard"),
             }
         }
+        None
+    }
+
+    /// Store a rebuilt shard on each target, verifying and recording every copy
+    async fn store_rebuilt_shard(
+        &self,
+        chunk_id: &[u8],
+        data: Bytes,
+        targets: &[String],
+    ) -> Vec<String> {
+        let Ok(chunk_id_obj) = self.bytes_to_chunk_id(chunk_id) else {
+            return Vec::new();
+        };
+        // A wrong profile or backend decodes to different bytes
+        if ChunkId::from_data(&data) != chunk_id_obj {
+            error!(
+                chunk_id = hex::encode(chunk_id),
+                "Rebuilt shard does not match its ID"
+            );
+            return Vec::new();
+        }
 
-        successful
+        let mut stored = Vec::with_capacity(targets.len());
+        for peer_id in targets {
+            let result = match self.db.get_node_by_peer_id(peer_id).await {
+                Ok(Some(node)) => match self
+                    .chunk_client
+                    .store_chunk(&node.grpc_address, chunk_id_obj, data.clone())
+                    .await
+                {
+                    Ok(()) => self.confirm_copy(chunk_id, chunk_id_obj, &node).await,
+                    Err(e) => Err(TransferError::Network(e.to_string())),
+                },
+                Ok(None) => Err(TransferError::TargetNotFound(peer_id.clone())),
+                Err(e) => Err(TransferError::Database(e.to_string())),
+            };
+            match result {
+                Ok(()) => stored.push(peer_id.clone()),
+                Err(e) => warn!(
+                    chunk_id = hex::encode(chunk_id),
+                    target = %peer_id,
+                    error = %e,
+                    "Failed to store rebuilt shard"
+                ),
+            }
+        }
+        stored
     }
 
-    /// Convert byte slice to ChunkId
     fn bytes_to_chunk_id(&self, bytes: &[u8]) -> Result<ChunkId> {
         if bytes.len() != 32 {
             return Err(TransferError::TransferFailed(format!(
@@ -193,40 +632,141 @@ impl ChunkTransferService {
 /// This returns a closure that can be used with Executor::execute()
 pub fn create_transfer_fn(
     db: Arc<Database>,
+    cluster_token: Option<String>,
+    encrypt_transfers: bool,
 ) -> impl Fn(
     String,
     String,
     Vec<u8>,
     Vec<String>,
 ) -> std::pin::Pin<
-    Box<dyn std::future::Future<Output = std::result::Result<Vec<String>, String>> + Send>,
+    Box<dyn std::future::Future<Output = std::result::Result<TransferOutcome, String>> + Send>,
 > + Clone
        + Send
        + Sync
        + 'static {
-    let service = Arc::new(ChunkTransferService::new(db));
+    let service = Arc::new(
+        ChunkTransferService::new(db)
+            .with_cluster_token(cluster_token)
+            .with_transfer_encryption(encrypt_transfers),
+    );
 
     move |source_node: String, _task_id: String, chunk_id: Vec<u8>, target_nodes: Vec<String>| {
         let service = service.clone();
 
         Box::pin(async move {
-            let successful = service
+            let outcome = service
                 .transfer_to_multiple(&chunk_id, &source_node, target_nodes)
                 .await;
 
-            if successful.is_empty() {
+            if outcome.stored.is_empty() && outcome.rejected.is_empty() {
                 Err("All transfers failed".to_string())
             } else {
-                Ok(successful)
+                Ok(outcome)
             }
         })
     }
 }
 
+/// Create a shard rebuild function for use with the executor
+///
+/// This returns a closure that can be used with Executor::execute_with_rebuild()
+pub fn create_rebuild_fn(
+    db: Arc<Database>,
+    cluster_token: Option<String>,
+    encrypt_transfers: bool,
+) -> impl Fn(
+    RepairBatch,
+) -> std::pin::Pin<
+    Box<dyn std::future::Future<Output = std::result::Result<Vec<Vec<String>>, String>> + Send>,
+> + Clone
+       + Send
+       + Sync
+       + 'static {
+    let service = Arc::new(
+        ChunkTransferService::new(db)
+            .with_cluster_token(cluster_token)
+            .with_transfer_encryption(encrypt_transfers),
+    );
+
+    move |batch: RepairBatch| {
+        let service = service.clone();
+
+        Box::pin(async move {
+            let file_id = Uuid::parse_str(&batch.stripe.file_id).map_err(|e| e.to_string())?;
+            let shards: Vec<(Vec<u8>, Vec<String>)> = batch
+                .shards
+                .into_iter()
+                .map(|s| (s.chunk_id, s.target_nodes))
+                .collect();
+
+            service
+                .rebuild_shards(file_id, batch.stripe.chunk_index, &shards)
+                .await
+                .map_err(|e| e.to_string())
+        })
+    }
+}
+
+/// Retire the corrupt copies replaced by successful repair tasks
+///
+/// Each copy's location is dropped and the copy is queued for shard GC, which
+/// deletes it from its node. Returns the number of copies retired.
+pub async fn retire_replaced_copies(db: &Database, result: &ExecutionResult) -> usize {
+    let mut retired = 0;
+    for task in &result.succeeded {
+        for peer_id in &task.replaces {
+            let outcome = match db.get_node_by_peer_id(peer_id).await {
+                Ok(Some(node)) => db.retire_chunk_copy(&task.chunk_id, node.id).await,
+                Ok(None) => Ok(false),
+                Err(e) => Err(e),
+            };
+            match outcome {
+                Ok(true) => {
+                    retired += 1;
+                    info!(
+                        chunk_id = hex::encode(&task.chunk_id),
+                        node = %peer_id,
+                        "Retired corrupt chunk copy"
+                    );
+                }
+                Ok(false) => {}
+                Err(e) => warn!(
+                    chunk_id = hex::encode(&task.chunk_id),
+                    node = %peer_id,
+                    error = %e,
+                    "Failed to retire corrupt chunk copy"
+                ),
+            }
+        }
+    }
+    retired
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_transfer_error_codes() {
+        assert_eq!(
+            TransferError::VerificationFailed.error_code(),
+            ErrorCode::IntegrityError
+        );
+        assert_eq!(
+            TransferError::ChecksumMismatch {
+                expected: "aa".to_string(),
+                actual: "bb".to_string(),
+            }
+            .error_code(),
+            ErrorCode::IntegrityError
+        );
+        assert_eq!(
+            TransferError::Network("refused".to_string()).error_code(),
+            ErrorCode::NodeUnreachable
+        );
+    }
+
     #[test]
     fn test_chunk_id_hex_conversion() {
         // Test hex encoding/decoding for chunk IDs
@@ -2,6 +2,7 @@
 //!
 //! Defines the interface that all storage implementations must follow.
 
+use crate::shard_header::ShardHeader;
 use bytes::Bytes;
 use cyxcloud_core::chunk::ChunkId;
 use cyxcloud_core::error::Result;
@@ -119,6 +120,22 @@ pub trait StorageBackendSync: Send + Sync {
 
     /// Flush any pending writes
     fn flush(&self) -> Result<()>;
+
+    /// Store an erasure-coded shard together with its header
+    ///
+    /// `get` still returns only `data`. Backends that cannot keep the header
+    /// store the data alone.
+    fn put_shard(&self, id: ChunkId, _header: &ShardHeader, data: Bytes) -> Result<()> {
+        self.put(id, data)
+    }
+
+    /// Retrieve a chunk with the header it was stored with by `put_shard`
+    ///
+    /// The header is None for chunks stored by `put` (or written before
+    /// shard headers existed).
+    fn get_shard(&self, id: ChunkId) -> Result<Option<(Option<ShardHeader>, Bytes)>> {
+        Ok(self.get(id)?.map(|data| (None, data)))
+    }
 }
 
 /// Wrapper to convert sync backend to async
@@ -0,0 +1,389 @@
+//! Filesystem storage backend
+//!
+//! Stores each chunk as its own file under `<root>/<xx>/<hex id>`, where `xx`
+//! is the first byte of the ID. Files are written to `<root>/tmp` and renamed
+//! into place, so a chunk file is never modified after it becomes visible.
+//! That makes it safe to memory-map: large chunks are returned as `Bytes`
+//! backed by the mapping, and slicing them for the wire copies nothing.
+
+use crate::backend::{StorageBackendSync, StorageStats};
+use bytes::Bytes;
+use cyxcloud_core::chunk::ChunkId;
+use cyxcloud_core::error::{CyxCloudError, Result};
+use memmap2::Mmap;
+use std::fs::{self, File};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+use tracing::{debug, info, warn};
+
+/// Directory for in-flight writes
+const TMP_DIR: &str = "tmp";
+
+/// Files at least this large are mapped instead of read by default
+pub const DEFAULT_MMAP_THRESHOLD: usize = 1024 * 1024; // 1 MB
+
+/// One-file-per-chunk storage backend
+pub struct FsBackend {
+    /// Root directory
+    root: PathBuf,
+
+    /// Files at least this large are memory-mapped on read
+    mmap_threshold: usize,
+
+    /// Maximum storage capacity (0 = unlimited)
+    max_capacity: u64,
+
+    /// Current totals (scanned on open, maintained on put/delete)
+    chunk_count: AtomicU64,
+    bytes_used: AtomicU64,
+
+    /// Operation counters
+    reads: AtomicU64,
+    writes: AtomicU64,
+    deletes: AtomicU64,
+
+    /// Latency tracking (cumulative microseconds)
+    read_latency_total_us: AtomicU64,
+    write_latency_total_us: AtomicU64,
+}
+
+impl FsBackend {
+    /// Open or create a chunk directory
+    ///
+    /// Leftover temporary files from an interrupted write are removed.
+    pub fn open(root: impl Into<PathBuf>) -> Result<Self> {
+        let root = root.into();
+        info!(path = ?root, "Opening filesystem chunk storage");
+
+        let tmp = root.join(TMP_DIR);
+        if tmp.exists() {
+            fs::remove_dir_all(&tmp).map_err(|e| storage_error("clear temp directory", e))?;
+        }
+        fs::create_dir_all(&tmp).map_err(|e| storage_error("create storage directory", e))?;
+
+        let backend = Self {
+            root,
+            mmap_threshold: DEFAULT_MMAP_THRESHOLD,
+            max_capacity: 0,
+            chunk_count: AtomicU64::new(0),
+            bytes_used: AtomicU64::new(0),
+            reads: AtomicU64::new(0),
+            writes: AtomicU64::new(0),
+            deletes: AtomicU64::new(0),
+            read_latency_total_us: AtomicU64::new(0),
+            write_latency_total_us: AtomicU64::new(0),
+        };
+
+        let mut count = 0u64;
+        let mut bytes = 0u64;
+        backend.for_each_file(|_, len| {
+            count += 1;
+            bytes += len;
+        })?;
+        backend.chunk_count.store(count, Ordering::Relaxed);
+        backend.bytes_used.store(bytes, Ordering::Relaxed);
+
+        Ok(backend)
+    }
+
+    /// Map files at least `bytes` long instead of reading them
+    pub fn with_mmap_threshold(mut self, bytes: usize) -> Self {
+        self.mmap_threshold = bytes;
+        self
+    }
+
+    /// Set maximum capacity
+    pub fn with_max_capacity(mut self, bytes: u64) -> Self {
+        self.max_capacity = bytes;
+        self
+    }
+
+    /// Bytes stored in chunk files
+    pub fn bytes_used(&self) -> u64 {
+        self.bytes_used.load(Ordering::Relaxed)
+    }
+
+    /// Size of a stored chunk, None if it is not stored
+    pub fn chunk_size(&self, id: ChunkId) -> Result<Option<u64>> {
+        match fs::metadata(self.chunk_path(id)) {
+            Ok(meta) => Ok(Some(meta.len())),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(storage_error("stat chunk", e)),
+        }
+    }
+
+    /// Path of a chunk's file
+    fn chunk_path(&self, id: ChunkId) -> PathBuf {
+        let name = hex_name(id);
+        self.root.join(&name[..2]).join(name)
+    }
+
+    /// Call `f` with the ID and size of every stored chunk
+    fn for_each_file(&self, mut f: impl FnMut(ChunkId, u64)) -> Result<()> {
+        let shards = fs::read_dir(&self.root).map_err(|e| storage_error("list chunks", e))?;
+        for shard in shards.flatten() {
+            let path = shard.path();
+            if !path.is_dir() || shard.file_name() == TMP_DIR {
+                continue;
+            }
+            let files = fs::read_dir(&path).map_err(|e| storage_error("list chunks", e))?;
+            for file in files.flatten() {
+                let Some(id) = file.file_name().to_str().and_then(parse_hex_name) else {
+                    continue;
+                };
+                if let Ok(meta) = file.metadata() {
+                    f(id, meta.len());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Read a chunk file, mapping it if it is large enough
+    fn read_file(&self, path: &Path) -> std::io::Result<Bytes> {
+        let file = File::open(path)?;
+        let len = file.metadata()?.len() as usize;
+        if len == 0 || len < self.mmap_threshold {
+            return fs::read(path).map(Bytes::from);
+        }
+
+        // SAFETY: chunk files are written to a temp path and renamed into
+        // place, never modified afterwards. Deleting the file while mapped
+        // only unlinks it; the mapping stays valid until the last `Bytes`
+        // referencing it is dropped.
+        let mmap = unsafe { Mmap::map(&file)? };
+        Ok(Bytes::from_owner(mmap))
+    }
+}
+
+impl StorageBackendSync for FsBackend {
+    fn put(&self, id: ChunkId, data: Bytes) -> Result<()> {
+        let start = Instant::now();
+        let path = self.chunk_path(id);
+
+        // Content-addressed: an existing file already holds this data
+        if path.exists() {
+            return Ok(());
+        }
+
+        if self.max_capacity > 0 {
+            let used = self.bytes_used();
+            if used + data.len() as u64 > self.max_capacity {
+                return Err(CyxCloudError::StorageFull {
+                    used,
+                    capacity: self.max_capacity,
+                });
+            }
+        }
+
+        let tmp = self.root.join(TMP_DIR).join(format!(
+            "{}.{}",
+            hex_name(id),
+            uuid::Uuid::new_v4().simple()
+        ));
+        let write = || -> std::io::Result<()> {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            let mut file = File::create(&tmp)?;
+            file.write_all(&data)?;
+            file.sync_data()?;
+            fs::rename(&tmp, &path)
+        };
+        if let Err(e) = write() {
+            let _ = fs::remove_file(&tmp);
+            return Err(storage_error("write chunk", e));
+        }
+
+        self.chunk_count.fetch_add(1, Ordering::Relaxed);
+        self.bytes_used
+            .fetch_add(data.len() as u64, Ordering::Relaxed);
+
+        let elapsed_us = start.elapsed().as_micros() as u64;
+        self.write_latency_total_us
+            .fetch_add(elapsed_us, Ordering::Relaxed);
+        self.writes.fetch_add(1, Ordering::Relaxed);
+        debug!(chunk_id = %id, size = data.len(), latency_us = elapsed_us, "Stored chunk file");
+
+        Ok(())
+    }
+
+    fn get(&self, id: ChunkId) -> Result<Option<Bytes>> {
+        let start = Instant::now();
+
+        let result = match self.read_file(&self.chunk_path(id)) {
+            Ok(data) => Some(data),
+            Err(e) if e.kind() == ErrorKind::NotFound => None,
+            Err(e) => return Err(storage_error("read chunk", e)),
+        };
+
+        let elapsed_us = start.elapsed().as_micros() as u64;
+        self.read_latency_total_us
+            .fetch_add(elapsed_us, Ordering::Relaxed);
+        self.reads.fetch_add(1, Ordering::Relaxed);
+
+        Ok(result)
+    }
+
+    fn delete(&self, id: ChunkId) -> Result<bool> {
+        let path = self.chunk_path(id);
+        let len = match fs::metadata(&path) {
+            Ok(meta) => meta.len(),
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(false),
+            Err(e) => return Err(storage_error("delete chunk", e)),
+        };
+
+        match fs::remove_file(&path) {
+            Ok(()) => {}
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(false),
+            Err(e) => return Err(storage_error("delete chunk", e)),
+        }
+
+        self.chunk_count.fetch_sub(1, Ordering::Relaxed);
+        self.bytes_used.fetch_sub(len, Ordering::Relaxed);
+        self.deletes.fetch_add(1, Ordering::Relaxed);
+        debug!(chunk_id = %id, "Deleted chunk file");
+
+        Ok(true)
+    }
+
+    fn exists(&self, id: ChunkId) -> Result<bool> {
+        Ok(self.chunk_path(id).is_file())
+    }
+
+    fn stats(&self) -> Result<StorageStats> {
+        let reads = self.reads.load(Ordering::Relaxed);
+        let writes = self.writes.load(Ordering::Relaxed);
+
+        Ok(StorageStats {
+            chunk_count: self.chunk_count.load(Ordering::Relaxed),
+            bytes_used: self.bytes_used(),
+            bytes_capacity: self.max_capacity,
+            reads,
+            writes,
+            deletes: self.deletes.load(Ordering::Relaxed),
+            avg_read_latency_us: self
+                .read_latency_total_us
+                .load(Ordering::Relaxed)
+                .checked_div(reads)
+                .unwrap_or(0),
+            avg_write_latency_us: self
+                .write_latency_total_us
+                .load(Ordering::Relaxed)
+                .checked_div(writes)
+                .unwrap_or(0),
+        })
+    }
+
+    fn list_chunks(&self) -> Result<Vec<ChunkId>> {
+        let mut chunks = Vec::new();
+        self.for_each_file(|id, _| chunks.push(id))?;
+        Ok(chunks)
+    }
+
+    fn flush(&self) -> Result<()> {
+        // Every file is synced before it is renamed into place
+        Ok(())
+    }
+}
+
+/// Lowercase hex file name of a chunk
+fn hex_name(id: ChunkId) -> String {
+    id.as_bytes().iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Parse a chunk file name back into its ID
+fn parse_hex_name(name: &str) -> Option<ChunkId> {
+    if name.len() != 64 || !name.is_ascii() {
+        return None;
+    }
+    let mut arr = [0u8; 32];
+    for (i, byte) in arr.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&name[i * 2..i * 2 + 2], 16).ok()?;
+    }
+    Some(ChunkId::from_bytes(arr))
+}
+
+fn storage_error(action: &str, e: std::io::Error) -> CyxCloudError {
+    warn!(error = %e, "Chunk file {} failed", action);
+    CyxCloudError::Storage(format!("Failed to {}: {}", action, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_put_get_mapped() {
+        let dir = TempDir::new().unwrap();
+        let backend = FsBackend::open(dir.path())
+            .unwrap()
+            .with_mmap_threshold(1024);
+
+        let small = Bytes::from_static(b"small chunk");
+        let large = Bytes::from(vec![7u8; 4096]);
+        let small_id = ChunkId::from_data(&small);
+        let large_id = ChunkId::from_data(&large);
+
+        backend.put(small_id, small.clone()).unwrap();
+        backend.put(large_id, large.clone()).unwrap();
+
+        assert_eq!(backend.get(small_id).unwrap().unwrap(), small);
+        assert_eq!(backend.get(large_id).unwrap().unwrap(), large);
+        assert!(backend
+            .get(ChunkId::from_data(b"absent"))
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_mapping_survives_delete() {
+        let dir = TempDir::new().unwrap();
+        let backend = FsBackend::open(dir.path()).unwrap().with_mmap_threshold(1);
+
+        let data = Bytes::from(vec![3u8; 8192]);
+        let id = ChunkId::from_data(&data);
+        backend.put(id, data.clone()).unwrap();
+
+        let mapped = backend.get(id).unwrap().unwrap();
+        assert!(backend.delete(id).unwrap());
+        assert!(!backend.exists(id).unwrap());
+        assert_eq!(mapped, data);
+    }
+
+    #[test]
+    fn test_reopen_counts_and_lists() {
+        let dir = TempDir::new().unwrap();
+        let ids: Vec<ChunkId> = (0..5u8).map(|i| ChunkId::from_data(&[i])).collect();
+        {
+            let backend = FsBackend::open(dir.path()).unwrap();
+            for id in &ids {
+                backend.put(*id, Bytes::from_static(b"data")).unwrap();
+            }
+            // Stray temp file from an interrupted write
+            fs::write(dir.path().join(TMP_DIR).join("partial"), b"x").unwrap();
+        }
+
+        let backend = FsBackend::open(dir.path()).unwrap();
+        let stats = backend.stats().unwrap();
+        assert_eq!(stats.chunk_count, 5);
+        assert_eq!(stats.bytes_used, 20);
+
+        let mut listed = backend.list_chunks().unwrap();
+        listed.sort_by_key(|id| *id.as_bytes());
+        let mut expected = ids.clone();
+        expected.sort_by_key(|id| *id.as_bytes());
+        assert_eq!(listed, expected);
+        assert!(!dir.path().join(TMP_DIR).join("partial").exists());
+    }
+
+    #[test]
+    fn test_hex_name_roundtrip() {
+        let id = ChunkId::from_data(b"name");
+        assert_eq!(parse_hex_name(&hex_name(id)), Some(id));
+        assert_eq!(parse_hex_name("not-a-chunk"), None);
+    }
+}
@@ -3,18 +3,27 @@
 //! Provides storage abstractions and implementations:
 //! - `StorageBackend` trait for pluggable storage
 //! - `RocksDbBackend` for production chunk storage
+//! - `FsBackend` for one-file-per-chunk storage with memory-mapped reads
 //! - `MemoryBackend` for testing
 //! - `SledBackend` for metadata storage
+//! - `ShardHeader` for the self-describing prefix of stored shards
+//! - `StorageProfile` / `RocksTuning` for RocksDB workload presets
 
 pub mod backend;
+pub mod fs;
 pub mod memory;
 pub mod rocks;
+pub mod shard_header;
 pub mod sled_backend;
+pub mod tuning;
 
 pub use backend::{StorageBackend, StorageStats};
+pub use fs::FsBackend;
 pub use memory::MemoryBackend;
-pub use rocks::RocksDbBackend;
+pub use rocks::{DeletionStats, PurgeSummary, RocksDbBackend};
+pub use shard_header::ShardHeader;
 pub use sled_backend::SledMetadataStore;
+pub use tuning::{RocksTuning, StorageProfile};
 
 /// Storage configuration
 #[derive(Debug, Clone)]
@@ -33,6 +42,17 @@ pub struct StorageConfig {
 
     /// Number of background compaction threads
     pub compaction_threads: usize,
+
+    /// RocksDB column family tuning
+    pub tuning: RocksTuning,
+
+    /// Chunks at least this large are stored as files next to the database
+    /// and served memory-mapped (0 = keep everything in RocksDB)
+    pub file_chunk_threshold: usize,
+
+    /// Deletes only record a tombstone; the data is removed later by
+    /// `RocksDbBackend::purge_tombstones`
+    pub deferred_delete: bool,
 }
 
 impl Default for StorageConfig {
@@ -43,6 +63,9 @@ impl Default for StorageConfig {
             compression: true,
             cache_size: 512 * 1024 * 1024, // 512 MB
             compaction_threads: 4,
+            tuning: RocksTuning::default(),
+            file_chunk_threshold: 0,
+            deferred_delete: false,
         }
     }
 }
@@ -73,4 +96,28 @@ impl StorageConfig {
         self.compression = enabled;
         self
     }
+
+    /// Apply a tuning preset
+    pub fn with_profile(mut self, profile: StorageProfile) -> Self {
+        self.tuning = profile.tuning();
+        self
+    }
+
+    /// Set RocksDB tuning knobs
+    pub fn with_tuning(mut self, tuning: RocksTuning) -> Self {
+        self.tuning = tuning;
+        self
+    }
+
+    /// Store chunks of at least `bytes` as memory-mapped files (0 disables)
+    pub fn with_file_chunk_threshold(mut self, bytes: usize) -> Self {
+        self.file_chunk_threshold = bytes;
+        self
+    }
+
+    /// Defer the removal of deleted chunks to background purges
+    pub fn with_deferred_delete(mut self, enabled: bool) -> Self {
+        self.deferred_delete = enabled;
+        self
+    }
 }
@@ -2,22 +2,104 @@
 //!
 //! Production-grade chunk storage using RocksDB LSM tree.
 //! Optimized for large values (chunks) with high write throughput.
+//!
+//! Chunks are split across two column families by size so small chunks get
+//! small blocks and their own memtables instead of sharing SST files with
+//! multi-megabyte values. Reads consult both; bloom filters keep the miss in
+//! the wrong family cheap.
+//!
+//! With `file_chunk_threshold` set, chunks at or above it bypass RocksDB and
+//! are stored by an [`FsBackend`] under `<path>/chunk_files`. Reads of those
+//! come back memory-mapped, so a 64MB chunk is never copied onto the heap.
+//!
+//! With `deferred_delete` set, deletes are two-phase: `delete` only records a
+//! tombstone (a small write to its own column family), after which the chunk
+//! reads as absent. [`RocksDbBackend::purge_tombstones`] removes the data
+//! later, in batches, so a burst of deletes never competes with foreground
+//! I/O. Tombstones survive restarts.
+//!
+//! Shards stored with `put_shard` keep their [`ShardHeader`] in front of the
+//! data in the same value (or file). `get` strips it, so callers only ever see
+//! the data; `get_shard` returns both.
 
 use crate::backend::{StorageBackendSync, StorageStats};
+use crate::fs::FsBackend;
+use crate::shard_header::ShardHeader;
 use crate::StorageConfig;
 use bytes::Bytes;
 use cyxcloud_core::chunk::ChunkId;
 use cyxcloud_core::error::{CyxCloudError, Result};
 use parking_lot::RwLock;
-use rocksdb::{BlockBasedOptions, Cache, DBCompressionType, Options, WriteOptions, DB};
+use rocksdb::{
+    BlockBasedOptions, BoundColumnFamily, Cache, ColumnFamilyDescriptor, DBCompressionType,
+    Options, WriteBatch, WriteOptions, DB,
+};
+use std::collections::HashMap;
 use std::path::Path;
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::time::Instant;
+use std::sync::Arc;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
 use tracing::{debug, info};
 
 /// Column family names
+///
+/// `chunks` holds chunks above the small-chunk threshold (and every chunk
+/// written before the split existed).
 const CF_CHUNKS: &str = "chunks";
+const CF_SMALL_CHUNKS: &str = "chunks_small";
 const CF_METADATA: &str = "metadata";
+const CF_TOMBSTONES: &str = "tombstones";
+
+/// Directory (under the database path) holding file-backed chunks
+const FILE_CHUNKS_DIR: &str = "chunk_files";
+
+/// A deleted chunk whose data has not been purged yet
+#[derive(Debug, Clone, Copy)]
+struct Tombstone {
+    /// Unix seconds of the delete
+    deleted_at: u64,
+    /// Bytes the chunk occupies
+    size: u64,
+}
+
+impl Tombstone {
+    fn encode(&self) -> [u8; 16] {
+        let mut buf = [0u8; 16];
+        buf[..8].copy_from_slice(&self.deleted_at.to_be_bytes());
+        buf[8..].copy_from_slice(&self.size.to_be_bytes());
+        buf
+    }
+
+    fn decode(value: &[u8]) -> Option<Self> {
+        let value: &[u8; 16] = value.try_into().ok()?;
+        Some(Self {
+            deleted_at: u64::from_be_bytes(value[..8].try_into().unwrap()),
+            size: u64::from_be_bytes(value[8..].try_into().unwrap()),
+        })
+    }
+}
+
+/// Chunks removed by one [`RocksDbBackend::purge_tombstones`] call
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PurgeSummary {
+    pub chunks: u64,
+    pub bytes: u64,
+}
+
+/// Logical vs physical deletion progress
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DeletionStats {
+    /// Tombstoned chunks awaiting purge
+    pub pending_chunks: u64,
+    /// Bytes held by tombstoned chunks
+    pub pending_bytes: u64,
+    /// Unix seconds of the oldest pending delete
+    pub oldest_pending: Option<u64>,
+    /// Bytes deleted since the database was opened
+    pub deleted_bytes: u64,
+    /// Bytes physically removed since the database was opened
+    pub purged_bytes: u64,
+}
 
 /// RocksDB-based storage backend
 pub struct RocksDbBackend {
@@ -27,11 +109,24 @@ pub struct RocksDbBackend {
     /// Configuration
     config: StorageConfig,
 
+    /// File store for large chunks (when `file_chunk_threshold` is set)
+    files: Option<FsBackend>,
+
+    /// Capacity limit in bytes (0 = unlimited), starts at `config.max_capacity`
+    max_capacity: AtomicU64,
+
     /// Operation counters
     reads: AtomicU64,
     writes: AtomicU64,
     deletes: AtomicU64,
 
+    /// Deleted chunks awaiting purge, mirrors the tombstones column family
+    tombstones: RwLock<HashMap<ChunkId, Tombstone>>,
+
+    /// Bytes deleted / physically removed since open
+    deleted_bytes: AtomicU64,
+    purged_bytes: AtomicU64,
+
     /// Latency tracking (cumulative microseconds)
     read_latency_total_us: AtomicU64,
     write_latency_total_us: AtomicU64,
@@ -43,7 +138,8 @@ pub struct RocksDbBackend {
 impl RocksDbBackend {
     /// Open or create a RocksDB storage at the given path
     pub fn open(config: StorageConfig) -> Result<Self> {
-        info!(path = ?config.path, "Opening RocksDB storage");
+        let tuning = &config.tuning;
+        info!(path = ?config.path, ?tuning, "Opening RocksDB storage");
 
         let mut opts = Options::default();
         opts.create_if_missing(true);
@@ -56,29 +152,32 @@ impl RocksDbBackend {
         opts.increase_parallelism(config.compaction_threads as i32);
         opts.set_max_background_jobs(config.compaction_threads as i32);
 
-        // Compression for chunks (LZ4 is fast)
-        if config.compression {
-            opts.set_compression_type(DBCompressionType::Lz4);
+        // Throttle flush/compaction I/O so it does not starve foreground reads
+        if tuning.rate_limit_bytes_per_sec > 0 {
+            opts.set_ratelimiter(tuning.rate_limit_bytes_per_sec, 100_000, 10);
         }
 
-        // Block cache for read performance
+        // Block cache shared by all column families
         let cache = Cache::new_lru_cache(config.cache_size);
-        let mut block_opts = BlockBasedOptions::default();
-        block_opts.set_block_cache(&cache);
-        block_opts.set_block_size(64 * 1024); // 64KB blocks (good for 4MB chunks)
-        block_opts.set_cache_index_and_filter_blocks(true);
-        block_opts.set_pin_l0_filter_and_index_blocks_in_cache(true);
-        opts.set_block_based_table_factory(&block_opts);
 
-        // Optimize for large values (chunks are typically 256KB - 64MB)
-        opts.set_min_write_buffer_number(2);
-        opts.set_max_write_buffer_number(4);
-        opts.set_write_buffer_size(64 * 1024 * 1024); // 64 MB write buffer
-
-        // Column family options
         let cf_descriptors = vec![
-            rocksdb::ColumnFamilyDescriptor::new(CF_CHUNKS, opts.clone()),
-            rocksdb::ColumnFamilyDescriptor::new(CF_METADATA, Options::default()),
+            // 64KB blocks (good for 4MB chunks)
+            ColumnFamilyDescriptor::new(
+                CF_CHUNKS,
+                Self::chunk_cf_options(&config, &cache, 64 * 1024, tuning.write_buffer_size),
+            ),
+            // Small chunks carry a fraction of the bytes; smaller memtables suffice
+            ColumnFamilyDescriptor::new(
+                CF_SMALL_CHUNKS,
+                Self::chunk_cf_options(
+                    &config,
+                    &cache,
+                    16 * 1024,
+                    (tuning.write_buffer_size / 4).max(8 * 1024 * 1024),
+                ),
+            ),
+            ColumnFamilyDescriptor::new(CF_METADATA, Self::metadata_cf_options(&cache)),
+            ColumnFamilyDescriptor::new(CF_TOMBSTONES, Self::metadata_cf_options(&cache)),
         ];
 
         // Create directory if it doesn't exist
@@ -90,56 +189,300 @@ impl RocksDbBackend {
         let db = DB::open_cf_descriptors(&opts, &config.path, cf_descriptors)
             .map_err(|e| CyxCloudError::Storage(format!("Failed to open RocksDB: {}", e)))?;
 
+        let files = if config.file_chunk_threshold > 0 {
+            Some(FsBackend::open(config.path.join(FILE_CHUNKS_DIR))?)
+        } else {
+            None
+        };
+
+        let tombstones = Self::load_tombstones(&db)?;
+        if !tombstones.is_empty() {
+            info!(count = tombstones.len(), "Deleted chunks awaiting purge");
+        }
+
         info!("RocksDB storage opened successfully");
 
         Ok(Self {
             db,
+            max_capacity: AtomicU64::new(config.max_capacity),
             config,
+            files,
             reads: AtomicU64::new(0),
             writes: AtomicU64::new(0),
             deletes: AtomicU64::new(0),
+            tombstones: RwLock::new(tombstones),
+            deleted_bytes: AtomicU64::new(0),
+            purged_bytes: AtomicU64::new(0),
             read_latency_total_us: AtomicU64::new(0),
             write_latency_total_us: AtomicU64::new(0),
             cached_stats: RwLock::new(StorageStats::default()),
         })
     }
 
+    /// Read the tombstones column family
+    fn load_tombstones(db: &DB) -> Result<HashMap<ChunkId, Tombstone>> {
+        let cf = db
+            .cf_handle(CF_TOMBSTONES)
+            .expect("tombstones column family should exist");
+
+        let mut tombstones = HashMap::new();
+        for item in db.iterator_cf(&cf, rocksdb::IteratorMode::Start) {
+            let (key, value) =
+                item.map_err(|e| CyxCloudError::Storage(format!("Tombstone scan failed: {}", e)))?;
+            let (Ok(key), Some(tombstone)) =
+                (<[u8; 32]>::try_from(&*key), Tombstone::decode(&value))
+            else {
+                continue;
+            };
+            tombstones.insert(ChunkId::from_bytes(key), tombstone);
+        }
+        Ok(tombstones)
+    }
+
+    /// Options for a chunk column family
+    fn chunk_cf_options(
+        config: &StorageConfig,
+        cache: &Cache,
+        block_size: usize,
+        write_buffer_size: usize,
+    ) -> Options {
+        let tuning = &config.tuning;
+        let mut opts = Options::default();
+
+        // Compression for chunks (LZ4 is fast)
+        if config.compression {
+            opts.set_compression_type(DBCompressionType::Lz4);
+        }
+
+        let mut block_opts = BlockBasedOptions::default();
+        block_opts.set_block_cache(cache);
+        block_opts.set_block_size(block_size);
+        block_opts.set_cache_index_and_filter_blocks(true);
+        block_opts.set_pin_l0_filter_and_index_blocks_in_cache(true);
+        if tuning.bloom_filter_bits > 0.0 {
+            block_opts.set_bloom_filter(tuning.bloom_filter_bits, false);
+        }
+        opts.set_block_based_table_factory(&block_opts);
+
+        // Optimize for large values (chunks are typically 256KB - 64MB)
+        opts.set_min_write_buffer_number(2);
+        opts.set_max_write_buffer_number(tuning.max_write_buffer_number);
+        opts.set_write_buffer_size(write_buffer_size);
+        opts.set_target_file_size_base(tuning.target_file_size);
+        opts.set_level_zero_slowdown_writes_trigger(tuning.level0_slowdown_writes_trigger);
+        opts.set_level_zero_stop_writes_trigger(tuning.level0_stop_writes_trigger);
+
+        opts
+    }
+
+    /// Options for the metadata column family (small values, point lookups)
+    fn metadata_cf_options(cache: &Cache) -> Options {
+        let mut block_opts = BlockBasedOptions::default();
+        block_opts.set_block_cache(cache);
+        block_opts.set_block_size(4 * 1024);
+        block_opts.set_bloom_filter(10.0, false);
+
+        let mut opts = Options::default();
+        opts.set_block_based_table_factory(&block_opts);
+        opts.set_write_buffer_size(8 * 1024 * 1024); // 8 MB
+        opts
+    }
+
     /// Open with default configuration
     pub fn open_default<P: AsRef<Path>>(path: P) -> Result<Self> {
         Self::open(StorageConfig::new(path.as_ref()))
     }
 
-    /// Get the chunks column family handle
-    fn cf_chunks(&self) -> std::sync::Arc<rocksdb::BoundColumnFamily<'_>> {
+    /// Get a column family handle by name
+    fn cf(&self, name: &str) -> Arc<BoundColumnFamily<'_>> {
         self.db
-            .cf_handle(CF_CHUNKS)
-            .expect("Chunks column family should exist")
+            .cf_handle(name)
+            .unwrap_or_else(|| panic!("{} column family should exist", name))
+    }
+
+    /// Column families holding chunks, small first
+    fn chunk_cfs(&self) -> [Arc<BoundColumnFamily<'_>>; 2] {
+        [self.cf(CF_SMALL_CHUNKS), self.cf(CF_CHUNKS)]
+    }
+
+    /// Column family a chunk of the given size is written to
+    fn cf_for_size(&self, len: usize) -> Arc<BoundColumnFamily<'_>> {
+        if len <= self.config.tuning.small_chunk_threshold {
+            self.cf(CF_SMALL_CHUNKS)
+        } else {
+            self.cf(CF_CHUNKS)
+        }
     }
 
     /// Get the metadata column family handle
     #[allow(dead_code)]
-    fn cf_metadata(&self) -> std::sync::Arc<rocksdb::BoundColumnFamily<'_>> {
-        self.db
-            .cf_handle(CF_METADATA)
-            .expect("Metadata column family should exist")
+    fn cf_metadata(&self) -> Arc<BoundColumnFamily<'_>> {
+        self.cf(CF_METADATA)
     }
 
     /// Compact the database (call periodically for performance)
     pub fn compact(&self) {
         info!("Starting database compaction");
-        self.db
-            .compact_range_cf(&self.cf_chunks(), None::<&[u8]>, None::<&[u8]>);
+        for cf in self.chunk_cfs() {
+            self.db.compact_range_cf(&cf, None::<&[u8]>, None::<&[u8]>);
+        }
         info!("Database compaction complete");
     }
 
+    /// Capacity limit in bytes (0 = unlimited)
+    pub fn max_capacity(&self) -> u64 {
+        self.max_capacity.load(Ordering::Relaxed)
+    }
+
+    /// Change the capacity limit, e.g. when the free disk space changed
+    ///
+    /// Chunks already stored are kept if they exceed the new limit; only new
+    /// writes are refused.
+    pub fn set_max_capacity(&self, bytes: u64) {
+        self.max_capacity.store(bytes, Ordering::Relaxed);
+    }
+
+    /// Reads, writes and deletes served since the database was opened
+    pub fn operation_count(&self) -> u64 {
+        self.reads.load(Ordering::Relaxed)
+            + self.writes.load(Ordering::Relaxed)
+            + self.deletes.load(Ordering::Relaxed)
+    }
+
     /// Get approximate storage size
     pub fn approximate_size(&self) -> u64 {
-        // Get approximate size from RocksDB properties
+        // The property is per column family; sum the chunk families
+        let sst: u64 = self
+            .chunk_cfs()
+            .iter()
+            .filter_map(|cf| {
+                self.db
+                    .property_int_value_cf(cf, "rocksdb.total-sst-files-size")
+                    .ok()
+                    .flatten()
+            })
+            .sum();
+        sst + self.files.as_ref().map_or(0, |f| f.bytes_used())
+    }
+
+    /// Physically remove up to `max_chunks` tombstoned chunks, oldest first
+    pub fn purge_tombstones(&self, max_chunks: usize) -> Result<PurgeSummary> {
+        // Held throughout so a concurrent re-put of a purged id cannot be lost
+        let mut tombstones = self.tombstones.write();
+        if tombstones.is_empty() || max_chunks == 0 {
+            return Ok(PurgeSummary::default());
+        }
+
+        let mut batch: Vec<(ChunkId, Tombstone)> =
+            tombstones.iter().map(|(id, t)| (*id, *t)).collect();
+        batch.sort_unstable_by_key(|(_, t)| t.deleted_at);
+        batch.truncate(max_chunks);
+
+        // Files go first: the tombstone must outlive the data, or a crash
+        // between the two would bring the chunk back
+        if let Some(files) = &self.files {
+            for (id, _) in &batch {
+                files.delete(*id)?;
+            }
+        }
+
+        let chunk_cfs = self.chunk_cfs();
+        let tombstone_cf = self.cf(CF_TOMBSTONES);
+        let mut write = WriteBatch::default();
+        for (id, _) in &batch {
+            for cf in &chunk_cfs {
+                write.delete_cf(cf, id.as_bytes());
+            }
+            write.delete_cf(&tombstone_cf, id.as_bytes());
+        }
+        self.db
+            .write(write)
+            .map_err(|e| CyxCloudError::Storage(format!("Purge failed: {}", e)))?;
+
+        let mut summary = PurgeSummary::default();
+        for (id, tombstone) in &batch {
+            tombstones.remove(id);
+            summary.chunks += 1;
+            summary.bytes += tombstone.size;
+        }
+        self.purged_bytes
+            .fetch_add(summary.bytes, Ordering::Relaxed);
+
+        debug!(
+            chunks = summary.chunks,
+            bytes = summary.bytes,
+            remaining = tombstones.len(),
+            "Purged deleted chunks"
+        );
+        Ok(summary)
+    }
+
+    /// Tombstones awaiting purge and deleted/purged byte totals
+    pub fn deletion_stats(&self) -> DeletionStats {
+        let tombstones = self.tombstones.read();
+        DeletionStats {
+            pending_chunks: tombstones.len() as u64,
+            pending_bytes: tombstones.values().map(|t| t.size).sum(),
+            oldest_pending: tombstones.values().map(|t| t.deleted_at).min(),
+            deleted_bytes: self.deleted_bytes.load(Ordering::Relaxed),
+            purged_bytes: self.purged_bytes.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Whether a chunk is deleted but not yet purged
+    fn is_tombstoned(&self, id: ChunkId) -> bool {
+        self.tombstones.read().contains_key(&id)
+    }
+
+    /// Bytes a chunk occupies, None if it is not stored (tombstones ignored)
+    fn stored_size(&self, id: ChunkId) -> Result<Option<u64>> {
+        let key = id.as_bytes();
+
+        for cf in self.chunk_cfs() {
+            if !self.db.key_may_exist_cf(&cf, key) {
+                continue;
+            }
+            let value = self
+                .db
+                .get_pinned_cf(&cf, key)
+                .map_err(|e| CyxCloudError::Storage(format!("Exists check failed: {}", e)))?;
+            if let Some(value) = value {
+                return Ok(Some(value.len() as u64));
+            }
+        }
+
+        match &self.files {
+            Some(files) => files.chunk_size(id),
+            None => Ok(None),
+        }
+    }
+
+    /// Write a chunk's data to wherever its size belongs
+    fn write_chunk(&self, id: ChunkId, data: Bytes) -> Result<()> {
+        // Large chunks go to their own files
+        if let Some(files) = self.files_for_size(data.len()) {
+            return files.put(id, data);
+        }
+
+        // Configure write options
+        let mut write_opts = WriteOptions::default();
+        write_opts.set_sync(false); // Async writes for performance
+
         self.db
-            .property_int_value("rocksdb.total-sst-files-size")
-            .ok()
-            .flatten()
-            .unwrap_or(0)
+            .put_cf_opt(
+                &self.cf_for_size(data.len()),
+                id.as_bytes(),
+                &data,
+                &write_opts,
+            )
+            .map_err(|e| CyxCloudError::Storage(format!("Write failed: {}", e)))
+    }
+
+    /// File store the chunk of the given size is written to, if any
+    fn files_for_size(&self, len: usize) -> Option<&FsBackend> {
+        self.files
+            .as_ref()
+            .filter(|_| len >= self.config.file_chunk_threshold)
     }
 }
 
@@ -149,86 +492,103 @@ impl StorageBackendSync for RocksDbBackend {
         let key = id.as_bytes();
 
         // Check capacity if set
-        if self.config.max_capacity > 0 {
+        let max_capacity = self.max_capacity();
+        if max_capacity > 0 {
             let current_size = self.approximate_size();
-            if current_size + data.len() as u64 > self.config.max_capacity {
+            if current_size + data.len() as u64 > max_capacity {
                 return Err(CyxCloudError::StorageFull {
                     used: current_size,
-                    capacity: self.config.max_capacity,
+                    capacity: max_capacity,
                 });
             }
         }
 
-        // Configure write options
-        let mut write_opts = WriteOptions::default();
-        write_opts.set_sync(false); // Async writes for performance
-
-        self.db
-            .put_cf_opt(&self.cf_chunks(), key, &data, &write_opts)
-            .map_err(|e| CyxCloudError::Storage(format!("Write failed: {}", e)))?;
+        let size = data.len();
+        if self.is_tombstoned(id) {
+            // Re-stored before the purge: write under the lock so a purge
+            // cannot remove the new data, then drop the tombstone
+            let mut tombstones = self.tombstones.write();
+            self.write_chunk(id, data)?;
+            if tombstones.remove(&id).is_some() {
+                self.db
+                    .delete_cf(&self.cf(CF_TOMBSTONES), key)
+                    .map_err(|e| CyxCloudError::Storage(format!("Write failed: {}", e)))?;
+            }
+        } else {
+            self.write_chunk(id, data)?;
+        }
 
         // Track latency and count
         let elapsed_us = start.elapsed().as_micros() as u64;
         self.write_latency_total_us
             .fetch_add(elapsed_us, Ordering::Relaxed);
         self.writes.fetch_add(1, Ordering::Relaxed);
-        debug!(chunk_id = %id, size = data.len(), latency_us = elapsed_us, "Stored chunk");
+        debug!(chunk_id = %id, size, latency_us = elapsed_us, "Stored chunk");
 
         Ok(())
     }
 
     fn get(&self, id: ChunkId) -> Result<Option<Bytes>> {
-        let start = Instant::now();
-        let key = id.as_bytes();
-
-        let result = self
-            .db
-            .get_cf(&self.cf_chunks(), key)
-            .map_err(|e| CyxCloudError::Storage(format!("Read failed: {}", e)))?;
-
-        // Track latency and count
-        let elapsed_us = start.elapsed().as_micros() as u64;
-        self.read_latency_total_us
-            .fetch_add(elapsed_us, Ordering::Relaxed);
-        self.reads.fetch_add(1, Ordering::Relaxed);
-
-        Ok(result.map(Bytes::from))
+        Ok(self.get_shard(id)?.map(|(_, data)| data))
     }
 
     fn delete(&self, id: ChunkId) -> Result<bool> {
         let key = id.as_bytes();
 
+        if self.is_tombstoned(id) {
+            return Ok(false);
+        }
+
         // Check if exists first
-        let exists = self.exists(id)?;
-        if !exists {
+        let Some(size) = self.stored_size(id)? else {
             return Ok(false);
+        };
+
+        if self.config.deferred_delete {
+            let tombstone = Tombstone {
+                deleted_at: SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs(),
+                size,
+            };
+            let mut tombstones = self.tombstones.write();
+            if tombstones.contains_key(&id) {
+                return Ok(false);
+            }
+            self.db
+                .put_cf(&self.cf(CF_TOMBSTONES), key, tombstone.encode())
+                .map_err(|e| CyxCloudError::Storage(format!("Delete failed: {}", e)))?;
+            tombstones.insert(id, tombstone);
+
+            self.deletes.fetch_add(1, Ordering::Relaxed);
+            self.deleted_bytes.fetch_add(size, Ordering::Relaxed);
+            debug!(chunk_id = %id, size, "Tombstoned chunk");
+            return Ok(true);
         }
 
-        self.db
-            .delete_cf(&self.cf_chunks(), key)
-            .map_err(|e| CyxCloudError::Storage(format!("Delete failed: {}", e)))?;
+        for cf in self.chunk_cfs() {
+            self.db
+                .delete_cf(&cf, key)
+                .map_err(|e| CyxCloudError::Storage(format!("Delete failed: {}", e)))?;
+        }
+        if let Some(files) = &self.files {
+            files.delete(id)?;
+        }
 
         self.deletes.fetch_add(1, Ordering::Relaxed);
-        debug!(chunk_id = %id, "Deleted chunk");
+        self.deleted_bytes.fetch_add(size, Ordering::Relaxed);
+        self.purged_bytes.fetch_add(size, Ordering::Relaxed);
+        debug!(chunk_id = %id, size, "Deleted chunk");
 
         Ok(true)
     }
 
     fn exists(&self, id: ChunkId) -> Result<bool> {
-        let key = id.as_bytes();
-
-        // Use key_may_exist for fast path
-        if !self.db.key_may_exist_cf(&self.cf_chunks(), key) {
+        if self.is_tombstoned(id) {
             return Ok(false);
         }
-
-        // Confirm with actual read (key_may_exist can have false positives)
-        let result = self
-            .db
-            .get_cf(&self.cf_chunks(), key)
-            .map_err(|e| CyxCloudError::Storage(format!("Exists check failed: {}", e)))?;
-
-        Ok(result.is_some())
+        Ok(self.stored_size(id)?.is_some())
     }
 
     fn stats(&self) -> Result<StorageStats> {
@@ -236,14 +596,23 @@ impl StorageBackendSync for RocksDbBackend {
         let mut chunk_count = 0u64;
         let mut bytes_used = 0u64;
 
-        let iter = self
-            .db
-            .iterator_cf(&self.cf_chunks(), rocksdb::IteratorMode::Start);
-
-        for (_, value) in iter.flatten() {
-            chunk_count += 1;
-            bytes_used += value.len() as u64;
+        for cf in self.chunk_cfs() {
+            let iter = self.db.iterator_cf(&cf, rocksdb::IteratorMode::Start);
+            for (_, value) in iter.flatten() {
+                chunk_count += 1;
+                bytes_used += value.len() as u64;
+            }
         }
+        if let Some(files) = &self.files {
+            let file_stats = files.stats()?;
+            chunk_count += file_stats.chunk_count;
+            bytes_used += file_stats.bytes_used;
+        }
+
+        // Deleted chunks are gone as far as callers are concerned
+        let deletions = self.deletion_stats();
+        chunk_count = chunk_count.saturating_sub(deletions.pending_chunks);
+        bytes_used = bytes_used.saturating_sub(deletions.pending_bytes);
 
         // Calculate average latencies
         let reads = self.reads.load(Ordering::Relaxed);
@@ -266,7 +635,7 @@ impl StorageBackendSync for RocksDbBackend {
         let stats = StorageStats {
             chunk_count,
             bytes_used,
-            bytes_capacity: self.config.max_capacity,
+            bytes_capacity: self.max_capacity(),
             reads,
             writes,
             deletes: self.deletes.load(Ordering::Relaxed),
@@ -283,29 +652,78 @@ impl StorageBackendSync for RocksDbBackend {
     fn list_chunks(&self) -> Result<Vec<ChunkId>> {
         let mut chunks = Vec::new();
 
-        let iter = self
-            .db
-            .iterator_cf(&self.cf_chunks(), rocksdb::IteratorMode::Start);
-
-        for (key, _) in iter.flatten() {
-            if key.len() == 32 {
-                let mut arr = [0u8; 32];
-                arr.copy_from_slice(&key);
-                chunks.push(ChunkId::from_bytes(arr));
+        for cf in self.chunk_cfs() {
+            let iter = self.db.iterator_cf(&cf, rocksdb::IteratorMode::Start);
+            for (key, _) in iter.flatten() {
+                if key.len() == 32 {
+                    let mut arr = [0u8; 32];
+                    arr.copy_from_slice(&key);
+                    chunks.push(ChunkId::from_bytes(arr));
+                }
             }
         }
+        if let Some(files) = &self.files {
+            chunks.extend(files.list_chunks()?);
+        }
+
+        let tombstones = self.tombstones.read();
+        if !tombstones.is_empty() {
+            chunks.retain(|id| !tombstones.contains_key(id));
+        }
 
         Ok(chunks)
     }
 
     fn flush(&self) -> Result<()> {
-        self.db
-            .flush()
-            .map_err(|e| CyxCloudError::Storage(format!("Flush failed: {}", e)))?;
+        for cf in self.chunk_cfs() {
+            self.db
+                .flush_cf(&cf)
+                .map_err(|e| CyxCloudError::Storage(format!("Flush failed: {}", e)))?;
+        }
 
         debug!("Flushed storage to disk");
         Ok(())
     }
+
+    fn put_shard(&self, id: ChunkId, header: &ShardHeader, data: Bytes) -> Result<()> {
+        self.put(id, header.wrap(&data))
+    }
+
+    fn get_shard(&self, id: ChunkId) -> Result<Option<(Option<ShardHeader>, Bytes)>> {
+        let start = Instant::now();
+        let key = id.as_bytes();
+
+        if self.is_tombstoned(id) {
+            self.reads.fetch_add(1, Ordering::Relaxed);
+            return Ok(None);
+        }
+
+        let mut result = None;
+        for cf in self.chunk_cfs() {
+            result = self
+                .db
+                .get_cf(&cf, key)
+                .map_err(|e| CyxCloudError::Storage(format!("Read failed: {}", e)))?;
+            if result.is_some() {
+                break;
+            }
+        }
+
+        let mut value = result.map(Bytes::from);
+        if value.is_none() {
+            if let Some(files) = &self.files {
+                value = files.get(id)?;
+            }
+        }
+
+        // Track latency and count
+        let elapsed_us = start.elapsed().as_micros() as u64;
+        self.read_latency_total_us
+            .fetch_add(elapsed_us, Ordering::Relaxed);
+        self.reads.fetch_add(1, Ordering::Relaxed);
+
+        Ok(value.map(|value| ShardHeader::strip(id, value)))
+    }
 }
 
 impl Drop for RocksDbBackend {
@@ -373,6 +791,114 @@ mod tests {
         assert!(!backend.exists(id).unwrap());
     }
 
+    #[test]
+    fn test_deferred_delete() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = StorageConfig::new(temp_dir.path()).with_deferred_delete(true);
+        let backend = RocksDbBackend::open(config).unwrap();
+        let id = ChunkId::from_data(b"tombstone");
+        let kept = ChunkId::from_data(b"kept");
+        backend
+            .put(id, Bytes::from_static(b"deleted later"))
+            .unwrap();
+        backend.put(kept, Bytes::from_static(b"kept")).unwrap();
+
+        // Deleted chunks read as absent before the data is purged
+        assert!(backend.delete(id).unwrap());
+        assert!(!backend.delete(id).unwrap());
+        assert!(!backend.exists(id).unwrap());
+        assert!(backend.get(id).unwrap().is_none());
+        assert_eq!(backend.list_chunks().unwrap(), vec![kept]);
+        assert_eq!(backend.stats().unwrap().chunk_count, 1);
+        assert!(backend.stored_size(id).unwrap().is_some());
+
+        let deletions = backend.deletion_stats();
+        assert_eq!(deletions.pending_chunks, 1);
+        assert_eq!(deletions.pending_bytes, 13);
+        assert_eq!(deletions.deleted_bytes, 13);
+        assert_eq!(deletions.purged_bytes, 0);
+        assert!(deletions.oldest_pending.is_some());
+
+        let summary = backend.purge_tombstones(10).unwrap();
+        assert_eq!(
+            summary,
+            PurgeSummary {
+                chunks: 1,
+                bytes: 13
+            }
+        );
+        assert!(backend.stored_size(id).unwrap().is_none());
+        assert!(backend.exists(kept).unwrap());
+
+        let deletions = backend.deletion_stats();
+        assert_eq!(deletions.pending_chunks, 0);
+        assert_eq!(deletions.purged_bytes, 13);
+        assert_eq!(deletions.oldest_pending, None);
+    }
+
+    #[test]
+    fn test_put_clears_tombstone() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = StorageConfig::new(temp_dir.path())
+            .with_deferred_delete(true)
+            .with_file_chunk_threshold(1024 * 1024);
+        let backend = RocksDbBackend::open(config).unwrap();
+        let id = ChunkId::from_data(b"file");
+        let data = Bytes::from(vec![7u8; 2 * 1024 * 1024]);
+
+        backend.put(id, data.clone()).unwrap();
+        assert!(backend.delete(id).unwrap());
+        backend.put(id, data.clone()).unwrap();
+
+        // Stored again, and the purge leaves it alone
+        assert_eq!(backend.get(id).unwrap().unwrap(), data);
+        assert_eq!(backend.purge_tombstones(10).unwrap().chunks, 0);
+        assert!(backend.exists(id).unwrap());
+    }
+
+    #[test]
+    fn test_tombstones_persist() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = StorageConfig::new(temp_dir.path()).with_deferred_delete(true);
+        let ids: Vec<ChunkId> = (0..5u8).map(|i| ChunkId::from_data(&[i])).collect();
+
+        {
+            let backend = RocksDbBackend::open(config.clone()).unwrap();
+            for id in &ids {
+                backend.put(*id, Bytes::from_static(b"data")).unwrap();
+                backend.delete(*id).unwrap();
+            }
+        }
+
+        let backend = RocksDbBackend::open(config).unwrap();
+        assert!(!backend.exists(ids[0]).unwrap());
+        assert_eq!(backend.deletion_stats().pending_chunks, 5);
+
+        // Batches are bounded
+        assert_eq!(backend.purge_tombstones(2).unwrap().chunks, 2);
+        assert_eq!(backend.deletion_stats().pending_chunks, 3);
+        assert_eq!(backend.purge_tombstones(10).unwrap().chunks, 3);
+        assert!(backend.list_chunks().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_set_max_capacity() {
+        let (backend, _dir) = create_test_backend();
+        let id = ChunkId::from_data(b"capacity");
+        let data = Bytes::from_static(b"does not fit");
+
+        backend.set_max_capacity(4);
+        assert_eq!(backend.stats().unwrap().bytes_capacity, 4);
+        assert!(matches!(
+            backend.put(id, data.clone()),
+            Err(CyxCloudError::StorageFull { capacity: 4, .. })
+        ));
+
+        backend.set_max_capacity(0);
+        backend.put(id, data).unwrap();
+        assert!(backend.exists(id).unwrap());
+    }
+
     #[test]
     fn test_large_chunk() {
         let (backend, _dir) = create_test_backend();
@@ -384,6 +910,116 @@ mod tests {
         assert_eq!(retrieved, data);
     }
 
+    #[test]
+    fn test_chunks_split_by_size() {
+        let (backend, _dir) = create_test_backend();
+        let threshold = backend.config.tuning.small_chunk_threshold;
+
+        let small_id = ChunkId::from_data(b"small");
+        let large_id = ChunkId::from_data(b"large");
+        backend
+            .put(small_id, Bytes::from(vec![1u8; threshold]))
+            .unwrap();
+        backend
+            .put(large_id, Bytes::from(vec![2u8; threshold + 1]))
+            .unwrap();
+
+        let stored_in = |cf: &str, id: ChunkId| {
+            let cf = backend.cf(cf);
+            backend.db.get_cf(&cf, id.as_bytes()).unwrap().is_some()
+        };
+        assert!(stored_in(CF_SMALL_CHUNKS, small_id));
+        assert!(stored_in(CF_CHUNKS, large_id));
+        assert!(!stored_in(CF_CHUNKS, small_id));
+
+        // Reads, listing and deletes span both families
+        assert_eq!(backend.get(small_id).unwrap().unwrap().len(), threshold);
+        assert_eq!(backend.get(large_id).unwrap().unwrap().len(), threshold + 1);
+        assert_eq!(backend.list_chunks().unwrap().len(), 2);
+        assert!(backend.delete(small_id).unwrap());
+        assert!(!backend.exists(small_id).unwrap());
+        assert!(backend.exists(large_id).unwrap());
+    }
+
+    #[test]
+    fn test_open_with_profiles() {
+        for profile in crate::StorageProfile::ALL {
+            let temp_dir = TempDir::new().unwrap();
+            let config = StorageConfig::new(temp_dir.path()).with_profile(profile);
+            let backend = RocksDbBackend::open(config).unwrap();
+
+            let id = ChunkId::from_data(profile.as_str().as_bytes());
+            backend.put(id, Bytes::from_static(b"tuned")).unwrap();
+            assert!(backend.exists(id).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_large_chunks_stored_as_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = StorageConfig::new(temp_dir.path()).with_file_chunk_threshold(1024 * 1024);
+        let backend = RocksDbBackend::open(config).unwrap();
+
+        let small_id = ChunkId::from_data(b"rocks");
+        let large_id = ChunkId::from_data(b"file");
+        let large = Bytes::from(vec![9u8; 2 * 1024 * 1024]);
+        backend.put(small_id, Bytes::from_static(b"small")).unwrap();
+        backend.put(large_id, large.clone()).unwrap();
+
+        let in_rocks = |id: ChunkId| {
+            backend
+                .chunk_cfs()
+                .iter()
+                .any(|cf| backend.db.get_cf(cf, id.as_bytes()).unwrap().is_some())
+        };
+        assert!(in_rocks(small_id));
+        assert!(!in_rocks(large_id));
+
+        assert_eq!(backend.get(large_id).unwrap().unwrap(), large);
+        assert_eq!(backend.stats().unwrap().chunk_count, 2);
+        assert_eq!(backend.list_chunks().unwrap().len(), 2);
+        assert!(backend.approximate_size() >= large.len() as u64);
+
+        assert!(backend.delete(large_id).unwrap());
+        assert!(!backend.exists(large_id).unwrap());
+        assert!(backend.exists(small_id).unwrap());
+    }
+
+    #[test]
+    fn test_shard_header_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = StorageConfig::new(temp_dir.path()).with_file_chunk_threshold(1024 * 1024);
+        let backend = RocksDbBackend::open(config).unwrap();
+
+        for data in [vec![3u8; 4096], vec![4u8; 2 * 1024 * 1024]] {
+            let id = ChunkId::from_data(&data);
+            let header = ShardHeader {
+                file_id: uuid::Uuid::new_v4(),
+                chunk_index: 2,
+                shard_index: 11,
+                is_parity: true,
+                original_size: 8 * 1024 * 1024,
+                hash: *id.as_bytes(),
+            };
+            backend
+                .put_shard(id, &header, Bytes::from(data.clone()))
+                .unwrap();
+
+            assert_eq!(backend.get(id).unwrap().unwrap(), data);
+            let (stored, shard) = backend.get_shard(id).unwrap().unwrap();
+            assert_eq!(stored, Some(header));
+            assert_eq!(shard, data);
+        }
+
+        // Chunks stored without a header read back unchanged
+        let id = ChunkId::from_data(b"plain");
+        backend.put(id, Bytes::from_static(b"plain")).unwrap();
+        assert_eq!(
+            backend.get_shard(id).unwrap(),
+            Some((None, Bytes::from_static(b"plain")))
+        );
+    }
+
     #[test]
     fn test_list_chunks() {
         let (backend, _dir) = create_test_backend();
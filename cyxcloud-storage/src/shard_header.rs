@@ -0,0 +1,210 @@
+//! Self-describing shard header
+//!
+//! Shards stored with [`StorageBackendSync::put_shard`] are prefixed on disk
+//! with a small fixed-size header naming the file, chunk and shard they belong
+//! to. A node's disk is then enough to rebuild its `chunk_locations` rows if
+//! the metadata database is lost, without asking the gateway what the
+//! content-addressed blobs are (see `cyxcloud-node salvage`).
+//!
+//! Layout (big-endian, [`HEADER_LEN`] bytes):
+//!
+//! | offset | size | field                                   |
+//! |--------|------|-----------------------------------------|
+//! | 0      | 4    | magic `CXSH`                            |
+//! | 4      | 1    | version (1)                             |
+//! | 5      | 1    | flags (bit 0: parity shard)             |
+//! | 6      | 2    | header length                           |
+//! | 8      | 16   | file id                                 |
+//! | 24     | 4    | chunk index                             |
+//! | 28     | 4    | shard index                             |
+//! | 32     | 8    | original chunk size                     |
+//! | 40     | 32   | Blake3 hash of the shard data           |
+//! | 72     | 4    | checksum                                |
+//!
+//! The checksum is the first 4 bytes of the Blake3 hash of bytes 0..72.
+//!
+//! The header is only recognised if the magic, version and checksum are valid
+//! and its hash is the chunk id the value is stored under. Anything else is a
+//! chunk written before headers existed (or by a caller that knows nothing
+//! about shards) and is returned unchanged.
+//!
+//! [`StorageBackendSync::put_shard`]: crate::backend::StorageBackendSync::put_shard
+
+use bytes::{BufMut, Bytes, BytesMut};
+use cyxcloud_core::chunk::ChunkId;
+use cyxcloud_core::crypto::ContentHash;
+use uuid::Uuid;
+
+/// Header magic
+pub const MAGIC: [u8; 4] = *b"CXSH";
+
+/// Current header version
+pub const VERSION: u8 = 1;
+
+/// Encoded header size in bytes
+pub const HEADER_LEN: usize = 76;
+
+/// Flag bit of a parity shard
+const FLAG_PARITY: u8 = 0x01;
+
+/// Bytes covered by the checksum
+const CHECKSUM_OFFSET: usize = HEADER_LEN - 4;
+
+/// Where a stored shard belongs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShardHeader {
+    /// File the shard belongs to
+    pub file_id: Uuid,
+    /// Index of the chunk within the file
+    pub chunk_index: u32,
+    /// Index of the shard within the chunk's stripe
+    pub shard_index: u32,
+    /// Parity (true) or data (false) shard
+    pub is_parity: bool,
+    /// Size of the chunk before erasure coding
+    pub original_size: u64,
+    /// Blake3 hash of the shard data (its chunk id)
+    pub hash: [u8; 32],
+}
+
+impl ShardHeader {
+    /// Encode to the on-disk layout
+    pub fn encode(&self) -> [u8; HEADER_LEN] {
+        let mut buf = [0u8; HEADER_LEN];
+        buf[0..4].copy_from_slice(&MAGIC);
+        buf[4] = VERSION;
+        buf[5] = if self.is_parity { FLAG_PARITY } else { 0 };
+        buf[6..8].copy_from_slice(&(HEADER_LEN as u16).to_be_bytes());
+        buf[8..24].copy_from_slice(self.file_id.as_bytes());
+        buf[24..28].copy_from_slice(&self.chunk_index.to_be_bytes());
+        buf[28..32].copy_from_slice(&self.shard_index.to_be_bytes());
+        buf[32..40].copy_from_slice(&self.original_size.to_be_bytes());
+        buf[40..72].copy_from_slice(&self.hash);
+        let checksum = ContentHash::compute(&buf[..CHECKSUM_OFFSET]);
+        buf[CHECKSUM_OFFSET..].copy_from_slice(&checksum.as_bytes()[..4]);
+        buf
+    }
+
+    /// Decode a header from the start of a stored value
+    ///
+    /// None if the value does not start with a valid header.
+    pub fn decode(value: &[u8]) -> Option<Self> {
+        let buf = value.get(..HEADER_LEN)?;
+        if buf[0..4] != MAGIC || buf[4] != VERSION {
+            return None;
+        }
+        if u16::from_be_bytes([buf[6], buf[7]]) as usize != HEADER_LEN {
+            return None;
+        }
+        let checksum = ContentHash::compute(&buf[..CHECKSUM_OFFSET]);
+        if buf[CHECKSUM_OFFSET..] != checksum.as_bytes()[..4] {
+            return None;
+        }
+
+        Some(Self {
+            file_id: Uuid::from_bytes(buf[8..24].try_into().unwrap()),
+            chunk_index: u32::from_be_bytes(buf[24..28].try_into().unwrap()),
+            shard_index: u32::from_be_bytes(buf[28..32].try_into().unwrap()),
+            is_parity: buf[5] & FLAG_PARITY != 0,
+            original_size: u64::from_be_bytes(buf[32..40].try_into().unwrap()),
+            hash: buf[40..72].try_into().unwrap(),
+        })
+    }
+
+    /// Prefix shard data with this header
+    pub fn wrap(&self, data: &[u8]) -> Bytes {
+        let mut buf = BytesMut::with_capacity(HEADER_LEN + data.len());
+        buf.put_slice(&self.encode());
+        buf.put_slice(data);
+        buf.freeze()
+    }
+
+    /// Split a value stored under `id` into its header (if any) and the data
+    ///
+    /// Values without a valid header for `id` come back unchanged.
+    pub fn strip(id: ChunkId, value: Bytes) -> (Option<Self>, Bytes) {
+        match Self::decode(&value) {
+            Some(header) if header.hash == *id.as_bytes() => {
+                let data = value.slice(HEADER_LEN..);
+                (Some(header), data)
+            }
+            _ => (None, value),
+        }
+    }
+
+    /// Whether `data` hashes to the header's hash
+    pub fn verify(&self, data: &[u8]) -> bool {
+        ContentHash::compute(data).as_bytes() == &self.hash
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header_for(data: &[u8]) -> ShardHeader {
+        ShardHeader {
+            file_id: Uuid::new_v4(),
+            chunk_index: 7,
+            shard_index: 12,
+            is_parity: true,
+            original_size: 4 * 1024 * 1024,
+            hash: *ContentHash::compute(data).as_bytes(),
+        }
+    }
+
+    #[test]
+    fn test_encode_decode() {
+        let header = header_for(b"shard");
+        let encoded = header.encode();
+        assert_eq!(&encoded[..4], b"CXSH");
+        assert_eq!(ShardHeader::decode(&encoded), Some(header));
+
+        let data = ShardHeader {
+            is_parity: false,
+            ..header
+        };
+        assert_eq!(ShardHeader::decode(&data.encode()), Some(data));
+    }
+
+    #[test]
+    fn test_decode_rejects_damage() {
+        let header = header_for(b"shard");
+        let encoded = header.encode();
+
+        assert_eq!(ShardHeader::decode(&encoded[..HEADER_LEN - 1]), None);
+        for offset in [0, 4, 6, 20, 40, 73] {
+            let mut damaged = encoded;
+            damaged[offset] ^= 0xff;
+            assert_eq!(ShardHeader::decode(&damaged), None, "offset {}", offset);
+        }
+    }
+
+    #[test]
+    fn test_wrap_strip() {
+        let data = b"erasure coded shard".to_vec();
+        let id = ChunkId::from_data(&data);
+        let header = header_for(&data);
+
+        let stored = header.wrap(&data);
+        assert_eq!(stored.len(), HEADER_LEN + data.len());
+
+        let (parsed, stripped) = ShardHeader::strip(id, stored);
+        assert_eq!(parsed, Some(header));
+        assert_eq!(stripped, data);
+        assert!(header.verify(&stripped));
+        assert!(!header.verify(b"something else"));
+    }
+
+    #[test]
+    fn test_strip_legacy() {
+        let data = Bytes::from_static(b"written before shard headers");
+        let id = ChunkId::from_data(&data);
+        assert_eq!(ShardHeader::strip(id, data.clone()), (None, data));
+
+        // A valid header for another chunk is part of the data
+        let other = header_for(b"other").wrap(b"other");
+        let id = ChunkId::from_data(&other);
+        assert_eq!(ShardHeader::strip(id, other.clone()), (None, other));
+    }
+}
@@ -0,0 +1,152 @@
+//! RocksDB tuning profiles
+//!
+//! Knobs applied to the chunk column families when the database is opened.
+//! A [`StorageProfile`] picks a preset; individual fields can be overridden
+//! afterwards.
+
+use cyxcloud_core::error::{CyxCloudError, Result};
+use std::fmt;
+use std::str::FromStr;
+
+/// Workload preset for RocksDB tuning
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StorageProfile {
+    /// General-purpose settings
+    #[default]
+    Balanced,
+    /// Sustained chunk ingest (repair storms, rebalancing targets)
+    ///
+    /// Larger memtables and L0 triggers absorb write bursts without stalls;
+    /// compaction runs unthrottled so it keeps up.
+    RepairHeavy,
+    /// Mostly serving downloads
+    ///
+    /// Denser bloom filters cut wasted reads and compaction I/O is rate
+    /// limited so it does not compete with foreground reads.
+    ReadHeavy,
+}
+
+impl StorageProfile {
+    /// All profiles
+    pub const ALL: [StorageProfile; 3] = [
+        StorageProfile::Balanced,
+        StorageProfile::RepairHeavy,
+        StorageProfile::ReadHeavy,
+    ];
+
+    /// Config name of the profile
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StorageProfile::Balanced => "balanced",
+            StorageProfile::RepairHeavy => "repair-heavy",
+            StorageProfile::ReadHeavy => "read-heavy",
+        }
+    }
+
+    /// Tuning preset for the profile
+    pub fn tuning(&self) -> RocksTuning {
+        let balanced = RocksTuning::default();
+        match self {
+            StorageProfile::Balanced => balanced,
+            StorageProfile::RepairHeavy => RocksTuning {
+                write_buffer_size: 128 * 1024 * 1024,
+                max_write_buffer_number: 6,
+                target_file_size: 256 * 1024 * 1024,
+                level0_slowdown_writes_trigger: 40,
+                level0_stop_writes_trigger: 64,
+                ..balanced
+            },
+            StorageProfile::ReadHeavy => RocksTuning {
+                write_buffer_size: 32 * 1024 * 1024,
+                max_write_buffer_number: 3,
+                bloom_filter_bits: 16.0,
+                rate_limit_bytes_per_sec: 64 * 1024 * 1024,
+                ..balanced
+            },
+        }
+    }
+}
+
+impl fmt::Display for StorageProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for StorageProfile {
+    type Err = CyxCloudError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let name = s.trim().replace('_', "-");
+        Self::ALL
+            .into_iter()
+            .find(|p| p.as_str().eq_ignore_ascii_case(&name))
+            .ok_or_else(|| CyxCloudError::Configuration(format!("unknown storage profile: {}", s)))
+    }
+}
+
+/// RocksDB knobs for the chunk column families
+#[derive(Debug, Clone, PartialEq)]
+pub struct RocksTuning {
+    /// Memtable size per column family in bytes
+    pub write_buffer_size: usize,
+    /// Memtables kept in memory before writes stall
+    pub max_write_buffer_number: i32,
+    /// Target SST file size at level 1 in bytes
+    pub target_file_size: u64,
+    /// Bloom filter bits per key (0 disables the filter)
+    pub bloom_filter_bits: f64,
+    /// Flush and compaction write rate limit in bytes/sec (0 = unlimited)
+    pub rate_limit_bytes_per_sec: i64,
+    /// Chunks up to this size go to the small-chunk column family
+    pub small_chunk_threshold: usize,
+    /// L0 file count at which writes are slowed down
+    pub level0_slowdown_writes_trigger: i32,
+    /// L0 file count at which writes stop until compaction catches up
+    pub level0_stop_writes_trigger: i32,
+}
+
+impl Default for RocksTuning {
+    fn default() -> Self {
+        Self {
+            write_buffer_size: 64 * 1024 * 1024, // 64 MB
+            max_write_buffer_number: 4,
+            target_file_size: 64 * 1024 * 1024, // 64 MB
+            bloom_filter_bits: 10.0,
+            rate_limit_bytes_per_sec: 0,
+            small_chunk_threshold: 64 * 1024, // 64 KB
+            level0_slowdown_writes_trigger: 20,
+            level0_stop_writes_trigger: 36,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_parse() {
+        for profile in StorageProfile::ALL {
+            assert_eq!(profile.as_str().parse::<StorageProfile>().unwrap(), profile);
+        }
+        assert_eq!(
+            "Repair_Heavy".parse::<StorageProfile>().unwrap(),
+            StorageProfile::RepairHeavy
+        );
+        assert!("fast".parse::<StorageProfile>().is_err());
+    }
+
+    #[test]
+    fn test_profile_presets() {
+        let balanced = StorageProfile::Balanced.tuning();
+        let repair = StorageProfile::RepairHeavy.tuning();
+        let read = StorageProfile::ReadHeavy.tuning();
+
+        assert_eq!(balanced, RocksTuning::default());
+        assert!(repair.write_buffer_size > balanced.write_buffer_size);
+        assert!(repair.level0_stop_writes_trigger > balanced.level0_stop_writes_trigger);
+        assert!(read.bloom_filter_bits > balanced.bloom_filter_bits);
+        assert!(read.rate_limit_bytes_per_sec > 0);
+    }
+}
@@ -0,0 +1,152 @@
+//! In-process cluster of storage nodes and a gateway
+
+use crate::node::TestNode;
+use cyxcloud_core::chunk::ChunkId;
+use cyxcloud_gateway::AppState;
+use cyxcloud_network::grpc_client::{ChunkClient, ChunkClientConfig};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Builder for a [`TestCluster`]
+#[derive(Debug, Clone)]
+pub struct ClusterBuilder {
+    nodes: usize,
+    cluster_token: Option<String>,
+}
+
+impl Default for ClusterBuilder {
+    fn default() -> Self {
+        Self {
+            nodes: 3,
+            cluster_token: None,
+        }
+    }
+}
+
+impl ClusterBuilder {
+    /// Number of storage nodes to start (default: 3)
+    pub fn nodes(mut self, count: usize) -> Self {
+        self.nodes = count;
+        self
+    }
+
+    /// Require a shared token on node-to-node RPCs
+    pub fn cluster_token(mut self, token: impl Into<String>) -> Self {
+        self.cluster_token = Some(token.into());
+        self
+    }
+
+    /// Start the nodes and the gateway
+    pub async fn start(self) -> TestCluster {
+        let mut cluster = TestCluster {
+            nodes: Vec::with_capacity(self.nodes),
+            gateway: Arc::new(AppState::new()),
+            client: ChunkClient::with_config(ChunkClientConfig {
+                connect_timeout: Duration::from_secs(2),
+                request_timeout: Duration::from_secs(10),
+                // A stopped node should fail the call, not stall it
+                max_retries: 0,
+                cluster_token: self.cluster_token.clone(),
+                ..Default::default()
+            }),
+            cluster_token: self.cluster_token,
+        };
+        for _ in 0..self.nodes {
+            cluster.add_node().await;
+        }
+        cluster
+    }
+}
+
+/// An in-process cluster: storage nodes plus a gateway
+///
+/// Nodes are numbered in the order they were started and keep their index
+/// after being stopped.
+pub struct TestCluster {
+    nodes: Vec<TestNode>,
+    gateway: Arc<AppState>,
+    client: ChunkClient,
+    cluster_token: Option<String>,
+}
+
+impl TestCluster {
+    /// Configure a cluster
+    pub fn builder() -> ClusterBuilder {
+        ClusterBuilder::default()
+    }
+
+    /// Start a cluster of `nodes` storage nodes
+    pub async fn start(nodes: usize) -> Self {
+        Self::builder().nodes(nodes).start().await
+    }
+
+    /// Gateway state (in-memory mode)
+    pub fn gateway(&self) -> &Arc<AppState> {
+        &self.gateway
+    }
+
+    /// Chunk client that presents the cluster token
+    pub fn client(&self) -> &ChunkClient {
+        &self.client
+    }
+
+    /// All nodes, stopped ones included
+    pub fn nodes(&self) -> &[TestNode] {
+        &self.nodes
+    }
+
+    /// The node at `index`
+    pub fn node(&self, index: usize) -> &TestNode {
+        &self.nodes[index]
+    }
+
+    /// Addresses of the running nodes
+    pub fn addrs(&self) -> Vec<String> {
+        self.nodes
+            .iter()
+            .filter(|node| node.is_running())
+            .map(TestNode::addr)
+            .collect()
+    }
+
+    /// Addresses of the running nodes that accept new chunks
+    pub fn writable_addrs(&self) -> Vec<String> {
+        self.nodes
+            .iter()
+            .filter(|node| node.is_running() && node.is_accepting_writes())
+            .map(TestNode::addr)
+            .collect()
+    }
+
+    /// Indexes of the running nodes holding `chunk_id`
+    pub fn holders(&self, chunk_id: ChunkId) -> Vec<usize> {
+        self.nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, node)| node.is_running() && node.has_chunk(chunk_id))
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    /// Start one more node and return its index
+    pub async fn add_node(&mut self) -> usize {
+        let index = self.nodes.len();
+        let node = TestNode::start(format!("test-node-{}", index), self.cluster_token.clone())
+            .await
+            .expect("failed to start test node");
+        self.nodes.push(node);
+        index
+    }
+
+    /// Stop the node at `index`, as if it had failed
+    pub async fn stop_node(&mut self, index: usize) {
+        self.nodes[index].stop().await;
+    }
+
+    /// Stop every node
+    pub async fn shutdown(mut self) {
+        for node in &mut self.nodes {
+            node.stop().await;
+        }
+    }
+}
@@ -0,0 +1,42 @@
+//! CyxCloud Test Kit
+//!
+//! Starts a CyxCloud cluster inside the test process, for integration tests
+//! that need real nodes without Docker or fixed ports:
+//!
+//! - **Storage nodes**: ChunkService gRPC servers on ephemeral loopback
+//!   ports, each over its own `MemoryBackend`
+//! - **Gateway**: an [`AppState`](cyxcloud_gateway::AppState) in its
+//!   in-memory mode
+//! - **Client**: a `ChunkClient` that presents the cluster token
+//!
+//! Ports are bound before a node is handed out, so tests never sleep waiting
+//! for a server, and a stopped node closes its connections the way a crashed
+//! one would.
+//!
+//! The metadata service has no in-memory backend, so the gateway keeps
+//! objects uploaded through its S3 layer to itself. Flows that involve nodes
+//! (replicated stores, reads from any replica, repair, drain) use the
+//! gateway's node client or the cluster's `ChunkClient` directly.
+//!
+//! With the `fault-injection` feature, every node and client exposes its
+//! fault injector, so tests can fail, slow down or corrupt individual
+//! replicas.
+//!
+//! # Usage
+//!
+//! ```ignore
+//! use cyxcloud_network::grpc_client::{fan_out_chunk, get_from_any_node};
+//! use cyxcloud_testkit::TestCluster;
+//!
+//! let mut cluster = TestCluster::start(3).await;
+//! fan_out_chunk(cluster.client(), chunk_id, data, &cluster.addrs()).await?;
+//!
+//! cluster.stop_node(0).await;
+//! let data = get_from_any_node(cluster.client(), chunk_id, &cluster.addrs()).await?;
+//! ```
+
+mod cluster;
+mod node;
+
+pub use cluster::{ClusterBuilder, TestCluster};
+pub use node::TestNode;
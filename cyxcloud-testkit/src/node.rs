@@ -0,0 +1,154 @@
+//! In-process storage node
+
+use cyxcloud_core::chunk::ChunkId;
+#[cfg(feature = "fault-injection")]
+use cyxcloud_network::fault::FaultInjector;
+use cyxcloud_network::grpc_server::{ChunkServiceImpl, GrpcServerConfig};
+use cyxcloud_protocol::ChunkServiceServer;
+use cyxcloud_storage::backend::StorageBackendSync;
+use cyxcloud_storage::MemoryBackend;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+use tonic::transport::server::TcpIncoming;
+use tonic::transport::Server;
+use tracing::{debug, warn};
+
+/// A storage node serving ChunkService from memory
+///
+/// The node listens on an ephemeral loopback port that is bound before
+/// [`TestNode::start`] returns, so it accepts connections right away.
+pub struct TestNode {
+    id: String,
+    addr: SocketAddr,
+    storage: Arc<MemoryBackend>,
+    accepting_writes: Arc<AtomicBool>,
+    #[cfg(feature = "fault-injection")]
+    faults: Arc<FaultInjector>,
+    shutdown: Option<oneshot::Sender<()>>,
+    server: Option<JoinHandle<()>>,
+}
+
+impl TestNode {
+    /// Start a node, requiring `cluster_token` on node-to-node RPCs if set
+    pub async fn start(id: impl Into<String>, cluster_token: Option<String>) -> io::Result<Self> {
+        let id = id.into();
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let incoming = TcpIncoming::from_listener(listener, true, None)
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+
+        let storage = Arc::new(MemoryBackend::new());
+        let accepting_writes = Arc::new(AtomicBool::new(true));
+        let service = ChunkServiceImpl::new(storage.clone(), id.clone())
+            .with_write_gate(accepting_writes.clone())
+            .with_cluster_token(cluster_token);
+        #[cfg(feature = "fault-injection")]
+        let faults = service.faults();
+        let max_message_size = GrpcServerConfig::default().max_message_size;
+        let service = ChunkServiceServer::new(service)
+            .max_decoding_message_size(max_message_size)
+            .max_encoding_message_size(max_message_size);
+
+        let (shutdown, signal) = oneshot::channel();
+        let node_id = id.clone();
+        let server = tokio::spawn(async move {
+            let result = Server::builder()
+                .add_service(service)
+                .serve_with_incoming_shutdown(incoming, async {
+                    let _ = signal.await;
+                })
+                .await;
+            if let Err(e) = result {
+                warn!(node_id = %node_id, error = %e, "Test node server failed");
+            }
+        });
+        debug!(node_id = %id, addr = %addr, "Test node started");
+
+        Ok(Self {
+            id,
+            addr,
+            storage,
+            accepting_writes,
+            #[cfg(feature = "fault-injection")]
+            faults,
+            shutdown: Some(shutdown),
+            server: Some(server),
+        })
+    }
+
+    /// Node ID
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// gRPC address (`host:port`) as clients expect it
+    pub fn addr(&self) -> String {
+        self.addr.to_string()
+    }
+
+    /// The node's chunk store
+    pub fn storage(&self) -> &Arc<MemoryBackend> {
+        &self.storage
+    }
+
+    /// Faults injected into the node's RPCs and chunk reads
+    #[cfg(feature = "fault-injection")]
+    pub fn faults(&self) -> &Arc<FaultInjector> {
+        &self.faults
+    }
+
+    /// Whether the node holds `chunk_id`
+    pub fn has_chunk(&self, chunk_id: ChunkId) -> bool {
+        self.storage.exists(chunk_id).unwrap_or(false)
+    }
+
+    /// IDs of the chunks on the node
+    pub fn chunks(&self) -> Vec<ChunkId> {
+        self.storage.list_chunks().unwrap_or_default()
+    }
+
+    /// Stop accepting new chunks, as a draining node does
+    ///
+    /// Reads and ReplicateChunk pushes from this node keep working.
+    pub fn drain(&self) {
+        self.accepting_writes.store(false, Ordering::SeqCst);
+    }
+
+    /// Whether the node accepts new chunks
+    pub fn is_accepting_writes(&self) -> bool {
+        self.accepting_writes.load(Ordering::SeqCst)
+    }
+
+    /// Whether the server is still running
+    pub fn is_running(&self) -> bool {
+        self.server.is_some()
+    }
+
+    /// Shut the server down and wait for it to exit
+    ///
+    /// Open connections are closed as well, so clients holding a cached
+    /// channel see the node as gone. The stored chunks stay readable through
+    /// [`TestNode::storage`].
+    pub async fn stop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+        if let Some(server) = self.server.take() {
+            let _ = server.await;
+        }
+        debug!(node_id = %self.id, "Test node stopped");
+    }
+}
+
+impl Drop for TestNode {
+    fn drop(&mut self) {
+        if let Some(server) = self.server.take() {
+            server.abort();
+        }
+    }
+}
@@ -0,0 +1,207 @@
+//! Upload, download, repair and drain flows on an in-process cluster
+//!
+//! Run with: cargo test -p cyxcloud-testkit
+
+use bytes::Bytes;
+use cyxcloud_core::chunk::{split_bytes_into_chunks, ChunkId};
+use cyxcloud_network::grpc_client::{
+    fan_out_chunk, get_from_any_node, ChunkClient, ChunkClientConfig,
+};
+use cyxcloud_testkit::TestCluster;
+
+const CHUNK_SIZE: usize = 256 * 1024;
+
+/// Deterministic data; different seeds give different chunks
+fn test_data(len: usize, seed: usize) -> Bytes {
+    (0..len)
+        .map(|i| ((i + seed) % 251) as u8)
+        .collect::<Vec<_>>()
+        .into()
+}
+
+#[tokio::test]
+async fn test_upload_download() {
+    let cluster = TestCluster::start(3).await;
+    let data = test_data(4 * CHUNK_SIZE + 1000, 0);
+    let chunks = split_bytes_into_chunks(&data, CHUNK_SIZE, None).unwrap();
+    assert_eq!(chunks.len(), 5);
+
+    for chunk in &chunks {
+        let report = fan_out_chunk(
+            cluster.client(),
+            chunk.id(),
+            chunk.data.clone(),
+            &cluster.addrs(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(report.succeeded(), cluster.addrs());
+        assert_eq!(cluster.holders(chunk.id()), vec![0, 1, 2]);
+    }
+
+    let mut downloaded = Vec::with_capacity(data.len());
+    for chunk in &chunks {
+        let part = get_from_any_node(cluster.client(), chunk.id(), &cluster.addrs())
+            .await
+            .unwrap();
+        downloaded.extend_from_slice(&part);
+    }
+    assert_eq!(downloaded, data);
+
+    cluster.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_gateway_replicated_store() {
+    let cluster = TestCluster::start(3).await;
+    let data = test_data(CHUNK_SIZE, 0);
+    let chunk_id = ChunkId::from_data(&data);
+    let node_client = cluster.gateway().node_client();
+
+    let stored = node_client
+        .store_chunk_replicated(&cluster.addrs(), chunk_id.as_bytes(), data.clone(), None)
+        .await
+        .unwrap();
+    assert_eq!(stored, cluster.addrs());
+    assert_eq!(cluster.holders(chunk_id), vec![0, 1, 2]);
+
+    let read = node_client
+        .get_chunk_from_any(&cluster.addrs(), chunk_id.as_bytes())
+        .await
+        .unwrap();
+    assert_eq!(read, data);
+
+    cluster.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_repair_after_node_failure() {
+    let mut cluster = TestCluster::start(3).await;
+    let data = test_data(CHUNK_SIZE, 0);
+    let chunk_id = ChunkId::from_data(&data);
+    let replicas = vec![cluster.node(0).addr(), cluster.node(1).addr()];
+    fan_out_chunk(cluster.client(), chunk_id, data.clone(), &replicas)
+        .await
+        .unwrap();
+
+    cluster.stop_node(0).await;
+    assert_eq!(cluster.holders(chunk_id), vec![1]);
+
+    // Readers skip the failed replica
+    let read = get_from_any_node(cluster.client(), chunk_id, &replicas)
+        .await
+        .unwrap();
+    assert_eq!(read, data);
+
+    // The surviving replica pushes the chunk to a node without it
+    let target = cluster.node(2).addr();
+    let report = cluster
+        .client()
+        .push_chunk(&replicas[1], chunk_id, &[target.clone()])
+        .await
+        .unwrap()
+        .expect("node supports ReplicateChunk");
+    assert_eq!(report.succeeded(), vec![target.clone()]);
+
+    let (valid, size) = cluster
+        .client()
+        .verify_chunk(&target, chunk_id)
+        .await
+        .unwrap();
+    assert!(valid);
+    assert_eq!(size, data.len() as u64);
+    assert_eq!(cluster.holders(chunk_id), vec![1, 2]);
+
+    cluster.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_drain_moves_chunks_off_node() {
+    let mut cluster = TestCluster::start(3).await;
+    let replicas = vec![cluster.node(0).addr(), cluster.node(1).addr()];
+    let mut stored = Vec::new();
+    for seed in 0..4 {
+        let data = test_data(CHUNK_SIZE, seed);
+        let chunk_id = ChunkId::from_data(&data);
+        fan_out_chunk(cluster.client(), chunk_id, data.clone(), &replicas)
+            .await
+            .unwrap();
+        stored.push((chunk_id, data));
+    }
+
+    // A draining node turns away new chunks
+    let draining = cluster.node(0).addr();
+    cluster.node(0).drain();
+    assert_eq!(
+        cluster.writable_addrs(),
+        vec![cluster.node(1).addr(), cluster.node(2).addr()]
+    );
+    let extra = test_data(CHUNK_SIZE, 100);
+    assert!(cluster
+        .client()
+        .store_chunk(&draining, ChunkId::from_data(&extra), extra)
+        .await
+        .is_err());
+
+    // Its chunks move to a writable node that lacks them
+    let target = cluster.node(2).addr();
+    for chunk_id in cluster.node(0).chunks() {
+        let report = cluster
+            .client()
+            .push_chunk(&draining, chunk_id, &[target.clone()])
+            .await
+            .unwrap()
+            .expect("node supports ReplicateChunk");
+        assert_eq!(report.succeeded(), vec![target.clone()]);
+    }
+
+    cluster.stop_node(0).await;
+    for (chunk_id, data) in &stored {
+        assert_eq!(cluster.holders(*chunk_id), vec![1, 2]);
+        let read = get_from_any_node(cluster.client(), *chunk_id, &cluster.addrs())
+            .await
+            .unwrap();
+        assert_eq!(&read, data);
+    }
+
+    cluster.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_cluster_token_required() {
+    let cluster = TestCluster::builder()
+        .nodes(2)
+        .cluster_token("test-cluster-token")
+        .start()
+        .await;
+    let data = test_data(CHUNK_SIZE, 0);
+    let chunk_id = ChunkId::from_data(&data);
+
+    let outsider = ChunkClient::with_config(ChunkClientConfig {
+        max_retries: 0,
+        ..Default::default()
+    });
+    let stored = fan_out_chunk(&outsider, chunk_id, data.clone(), &cluster.addrs())
+        .await
+        .map(|report| report.succeeded())
+        .unwrap_or_default();
+    assert!(stored.is_empty());
+    assert!(cluster.holders(chunk_id).is_empty());
+
+    // Nodes present the token to each other when pushing chunks
+    let source = cluster.node(0).addr();
+    let target = cluster.node(1).addr();
+    fan_out_chunk(cluster.client(), chunk_id, data, &[source.clone()])
+        .await
+        .unwrap();
+    let report = cluster
+        .client()
+        .push_chunk(&source, chunk_id, &[target.clone()])
+        .await
+        .unwrap()
+        .expect("node supports ReplicateChunk");
+    assert_eq!(report.succeeded(), vec![target]);
+    assert_eq!(cluster.holders(chunk_id), vec![0, 1]);
+
+    cluster.shutdown().await;
+}
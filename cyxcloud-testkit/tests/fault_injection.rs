@@ -0,0 +1,152 @@
+//! Degraded reads, retries and repair under injected faults
+//!
+//! Run with: cargo test -p cyxcloud-testkit --features fault-injection
+
+#![cfg(feature = "fault-injection")]
+
+use bytes::Bytes;
+use cyxcloud_core::chunk::ChunkId;
+use cyxcloud_network::fault::FaultConfig;
+use cyxcloud_network::grpc_client::{fan_out_chunk, get_from_any_node};
+use cyxcloud_testkit::TestCluster;
+
+const CHUNK_SIZE: usize = 256 * 1024;
+
+fn test_data() -> Bytes {
+    (0..CHUNK_SIZE)
+        .map(|i| (i % 251) as u8)
+        .collect::<Vec<_>>()
+        .into()
+}
+
+#[tokio::test]
+async fn test_read_falls_back_when_replica_fails() {
+    let cluster = TestCluster::start(2).await;
+    let data = test_data();
+    let chunk_id = ChunkId::from_data(&data);
+    fan_out_chunk(cluster.client(), chunk_id, data.clone(), &cluster.addrs())
+        .await
+        .unwrap();
+
+    cluster.node(0).faults().set(FaultConfig {
+        error_rate: 1.0,
+        ..Default::default()
+    });
+    assert!(cluster
+        .client()
+        .get_chunk(&cluster.node(0).addr(), chunk_id)
+        .await
+        .is_err());
+    let read = get_from_any_node(cluster.client(), chunk_id, &cluster.addrs())
+        .await
+        .unwrap();
+    assert_eq!(read, data);
+
+    // The gateway's hedged reads get past the failing replica too
+    let read = cluster
+        .gateway()
+        .node_client()
+        .get_chunk_from_any(&cluster.addrs(), chunk_id.as_bytes())
+        .await
+        .unwrap();
+    assert_eq!(read, data);
+
+    cluster.node(0).faults().clear();
+    let read = cluster
+        .client()
+        .get_chunk(&cluster.node(0).addr(), chunk_id)
+        .await
+        .unwrap();
+    assert_eq!(read, Some(data));
+
+    cluster.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_corrupted_replica_is_detected_and_repaired() {
+    let cluster = TestCluster::start(3).await;
+    let data = test_data();
+    let chunk_id = ChunkId::from_data(&data);
+    let replicas = vec![cluster.node(0).addr(), cluster.node(1).addr()];
+    fan_out_chunk(cluster.client(), chunk_id, data.clone(), &replicas)
+        .await
+        .unwrap();
+
+    cluster.node(0).faults().set(FaultConfig {
+        corrupt_chunks: vec![chunk_id.to_string()],
+        ..Default::default()
+    });
+
+    // The rot passes the wire checksum but not the content hash
+    let read = cluster
+        .client()
+        .get_chunk(&replicas[0], chunk_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_ne!(ChunkId::from_data(&read), chunk_id);
+    let (valid, _) = cluster
+        .client()
+        .verify_chunk(&replicas[0], chunk_id)
+        .await
+        .unwrap();
+    assert!(!valid);
+
+    // Pushing from the rotten replica is refused by the target...
+    let target = cluster.node(2).addr();
+    let report = cluster
+        .client()
+        .push_chunk(&replicas[0], chunk_id, &[target.clone()])
+        .await
+        .unwrap()
+        .expect("node supports ReplicateChunk");
+    assert!(report.succeeded().is_empty());
+    assert!(!cluster.node(2).has_chunk(chunk_id));
+
+    // ...and the healthy one repairs it
+    let report = cluster
+        .client()
+        .push_chunk(&replicas[1], chunk_id, &[target.clone()])
+        .await
+        .unwrap()
+        .expect("node supports ReplicateChunk");
+    assert_eq!(report.succeeded(), vec![target.clone()]);
+    let (valid, _) = cluster
+        .client()
+        .verify_chunk(&target, chunk_id)
+        .await
+        .unwrap();
+    assert!(valid);
+
+    cluster.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_client_reset_drops_connection() {
+    let cluster = TestCluster::start(1).await;
+    let addr = cluster.node(0).addr();
+    let data = test_data();
+    let chunk_id = ChunkId::from_data(&data);
+
+    cluster
+        .client()
+        .store_chunk(&addr, chunk_id, data.clone())
+        .await
+        .unwrap();
+    assert_eq!(cluster.client().connection_count(), 1);
+
+    cluster.client().faults().set(FaultConfig {
+        reset_rate: 1.0,
+        ..Default::default()
+    });
+    assert!(cluster.client().get_chunk(&addr, chunk_id).await.is_err());
+    assert_eq!(cluster.client().connection_count(), 0);
+
+    // The next call reconnects
+    cluster.client().faults().clear();
+    let read = cluster.client().get_chunk(&addr, chunk_id).await.unwrap();
+    assert_eq!(read, Some(data));
+    assert_eq!(cluster.client().connection_count(), 1);
+
+    cluster.shutdown().await;
+}